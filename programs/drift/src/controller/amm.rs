@@ -217,12 +217,40 @@ pub fn update_spreads(amm: &mut AMM, reserve_price: u64) -> DriftResult<(u32, u3
             amm.long_intensity_volume,
             amm.short_intensity_volume,
             amm.volume_24h,
+            amm.inventory_skew_spread,
+            amm.volatility_spread_scale,
         )?
     } else {
         let half_base_spread = amm.base_spread.safe_div(2)?;
         (half_base_spread, half_base_spread)
     };
 
+    let (long_spread, short_spread) = if amm.curve_update_intensity > 0 {
+        let reference_price_offset = amm::calculate_reference_price_offset(
+            amm.base_asset_amount_with_amm,
+            amm.base_asset_amount_long,
+            amm.base_asset_amount_short,
+            amm.base_asset_reserve,
+            amm.min_base_asset_reserve,
+            amm.max_base_asset_reserve,
+            amm.max_reference_price_offset,
+        )?;
+
+        let (long_spread, short_spread, reference_price_offset) =
+            amm::apply_reference_price_offset(
+                long_spread,
+                short_spread,
+                amm.base_spread,
+                reference_price_offset,
+            )?;
+        amm.reference_price_offset = reference_price_offset;
+
+        (long_spread, short_spread)
+    } else {
+        amm.reference_price_offset = 0;
+        (long_spread, short_spread)
+    };
+
     amm.long_spread = long_spread;
     amm.short_spread = short_spread;
 
@@ -681,6 +709,67 @@ pub fn update_pnl_pool_and_user_balance(
     Ok(pnl_to_settle_with_user)
 }
 
+/// moves a perp market's pnl pool surplus above surplus_buffer into the quote revenue pool,
+/// returns the amount released
+pub fn release_pnl_pool_surplus(
+    market: &mut PerpMarket,
+    spot_market: &mut SpotMarket,
+    oracle_price: i64,
+    surplus_buffer: u128,
+) -> DriftResult<u128> {
+    let pnl_pool_amount = get_token_amount(
+        market.pnl_pool.scaled_balance,
+        spot_market,
+        market.pnl_pool.balance_type(),
+    )?;
+
+    let surplus = amm::calculate_pnl_pool_surplus(&market.amm, oracle_price, pnl_pool_amount)?;
+
+    let release_amount = surplus
+        .safe_sub(surplus_buffer.cast()?)?
+        .max(0)
+        .unsigned_abs();
+
+    if release_amount == 0 {
+        return Ok(0);
+    }
+
+    transfer_spot_balance_to_revenue_pool(release_amount, spot_market, &mut market.pnl_pool)?;
+
+    Ok(release_amount)
+}
+
+/// tops up a perp market's pnl pool shortfall from the quote revenue pool, up to max_top_up,
+/// returns the amount topped up
+pub fn top_up_pnl_pool_deficit(
+    market: &mut PerpMarket,
+    spot_market: &mut SpotMarket,
+    oracle_price: i64,
+    max_top_up: u128,
+) -> DriftResult<u128> {
+    let pnl_pool_amount = get_token_amount(
+        market.pnl_pool.scaled_balance,
+        spot_market,
+        market.pnl_pool.balance_type(),
+    )?;
+
+    let surplus = amm::calculate_pnl_pool_surplus(&market.amm, oracle_price, pnl_pool_amount)?;
+
+    if surplus >= 0 {
+        return Ok(0);
+    }
+
+    let top_up_amount = surplus.unsigned_abs().min(max_top_up);
+
+    if top_up_amount == 0 {
+        return Ok(0);
+    }
+
+    transfer_revenue_pool_to_spot_balance(top_up_amount, spot_market, &mut market.pnl_pool)?;
+
+    Ok(top_up_amount)
+}
+
 pub fn move_price(
     amm: &mut AMM,
     base_asset_reserve: u128,
@@ -1042,3 +1042,95 @@ fn update_pool_balances_revenue_to_fee_test() {
         market.insurance_claim.max_revenue_withdraw_per_period as i64
     );
 }
+
+#[test]
+fn rebalance_pnl_pools() {
+    let mut spot_market = SpotMarket {
+        deposit_balance: 100 * QUOTE_PRECISION * SPOT_BALANCE_PRECISION,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        revenue_pool: PoolBalance::default(),
+        ..SpotMarket::default()
+    };
+
+    // users are owed 20, pool holds 50
+    let mut surplus_market = PerpMarket {
+        amm: AMM {
+            quote_asset_amount: 20 * QUOTE_PRECISION as i128,
+            ..AMM::default()
+        },
+        pnl_pool: PoolBalance {
+            scaled_balance: 50 * QUOTE_PRECISION * SPOT_BALANCE_PRECISION,
+            market_index: QUOTE_SPOT_MARKET_INDEX,
+            ..PoolBalance::default()
+        },
+        ..PerpMarket::default()
+    };
+
+    // users are owed 80, pool holds 50
+    let mut deficit_market = PerpMarket {
+        amm: AMM {
+            quote_asset_amount: 80 * QUOTE_PRECISION as i128,
+            ..AMM::default()
+        },
+        pnl_pool: PoolBalance {
+            scaled_balance: 50 * QUOTE_PRECISION * SPOT_BALANCE_PRECISION,
+            market_index: QUOTE_SPOT_MARKET_INDEX,
+            ..PoolBalance::default()
+        },
+        market_index: 1,
+        ..PerpMarket::default()
+    };
+
+    let oracle_price = PRICE_PRECISION_I64;
+
+    // nothing to release from a deficit market or top up on a surplus market
+    assert_eq!(
+        release_pnl_pool_surplus(&mut deficit_market, &mut spot_market, oracle_price, 0).unwrap(),
+        0
+    );
+    assert_eq!(
+        top_up_pnl_pool_deficit(
+            &mut surplus_market,
+            &mut spot_market,
+            oracle_price,
+            u128::MAX
+        )
+        .unwrap(),
+        0
+    );
+
+    let released = release_pnl_pool_surplus(
+        &mut surplus_market,
+        &mut spot_market,
+        oracle_price,
+        10 * QUOTE_PRECISION,
+    )
+    .unwrap();
+    assert_eq!(released, 20 * QUOTE_PRECISION);
+    assert_eq!(
+        surplus_market.pnl_pool.scaled_balance,
+        30 * QUOTE_PRECISION * SPOT_BALANCE_PRECISION
+    );
+    assert_eq!(
+        spot_market.revenue_pool.scaled_balance,
+        20 * QUOTE_PRECISION * SPOT_BALANCE_PRECISION
+    );
+
+    let topped_up = top_up_pnl_pool_deficit(
+        &mut deficit_market,
+        &mut spot_market,
+        oracle_price,
+        15 * QUOTE_PRECISION,
+    )
+    .unwrap();
+    assert_eq!(topped_up, 15 * QUOTE_PRECISION);
+    assert_eq!(
+        deficit_market.pnl_pool.scaled_balance,
+        65 * QUOTE_PRECISION * SPOT_BALANCE_PRECISION
+    );
+    assert_eq!(
+        spot_market.revenue_pool.scaled_balance,
+        5 * QUOTE_PRECISION * SPOT_BALANCE_PRECISION
+    );
+}
@@ -0,0 +1,82 @@
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program::{invoke_signed, set_return_data};
+
+use crate::error::ErrorCode;
+use crate::instructions::optional_accounts::FillCallbackAccounts;
+use crate::math::orders::FillReceipt;
+use crate::state::fill_callback::FillCallbackData;
+
+#[cfg(test)]
+mod tests;
+
+/// invokes the user's fill callback if the order filled, then sets the fill receipt as the
+/// return data. the receipt is set last since the callback cpi replaces the return data
+pub fn invoke_fill_callback_and_set_receipt<'info>(
+    fill_callback_accounts: Option<FillCallbackAccounts<'info>>,
+    user: &AccountInfo<'info>,
+    market_index: u16,
+    receipt: FillReceipt,
+) -> Result<()> {
+    if let Some(fill_callback_accounts) = fill_callback_accounts {
+        if receipt.base_asset_amount_filled > 0 {
+            invoke_fill_callback(
+                &fill_callback_accounts.callback_program,
+                &fill_callback_accounts.user_fill_callback,
+                user,
+                fill_callback_accounts.bump,
+                &FillCallbackData {
+                    user: user.key(),
+                    market_index,
+                    receipt,
+                },
+            )?;
+        }
+    }
+
+    let receipt_data = receipt.try_to_vec().map_err(|_| ErrorCode::DefaultError)?;
+    set_return_data(&receipt_data);
+
+    Ok(())
+}
+
+/// invokes the user's fill callback program, signed by the user's UserFillCallback pda so the
+/// program can check the call came from drift. the callback spends the caller's compute budget
+pub fn invoke_fill_callback<'info>(
+    callback_program: &AccountInfo<'info>,
+    user_fill_callback: &AccountInfo<'info>,
+    user: &AccountInfo<'info>,
+    bump: u8,
+    fill_callback_data: &FillCallbackData,
+) -> Result<()> {
+    let instruction = Instruction {
+        program_id: callback_program.key(),
+        accounts: vec![
+            AccountMeta::new_readonly(user_fill_callback.key(), true),
+            AccountMeta::new_readonly(user.key(), false),
+        ],
+        data: fill_callback_data.instruction_data()?,
+    };
+
+    let user_key = user.key();
+    let bump_seed = [bump];
+    let signer_seeds: &[&[u8]] = &[
+        b"user_fill_callback".as_ref(),
+        user_key.as_ref(),
+        bump_seed.as_ref(),
+    ];
+
+    invoke_signed(
+        &instruction,
+        &[
+            user_fill_callback.clone(),
+            user.clone(),
+            callback_program.clone(),
+        ],
+        &[signer_seeds],
+    )
+    .map_err(|e| {
+        msg!("fill callback {} failed: {:?}", callback_program.key(), e);
+        e.into()
+    })
+}
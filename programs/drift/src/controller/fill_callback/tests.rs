@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex};
+
+use anchor_lang::prelude::*;
+use borsh::BorshSerialize;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::Instruction;
+use solana_program::program::get_return_data;
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+
+use crate::controller::fill_callback::invoke_fill_callback_and_set_receipt;
+use crate::instructions::optional_accounts::FillCallbackAccounts;
+use crate::math::constants::{BASE_PRECISION_U64, PRICE_PRECISION_U64, QUOTE_PRECISION_U64};
+use crate::math::orders::FillReceipt;
+use crate::state::user::OrderStatus;
+use crate::test_utils::create_account_info;
+
+/// a callback program that sets its own return data, like any program returning a value would
+struct CallbackStubs {
+    invoked_program: Arc<Mutex<Option<Pubkey>>>,
+    return_data: Mutex<Option<(Pubkey, Vec<u8>)>>,
+}
+
+impl SyscallStubs for CallbackStubs {
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        _account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        *self.invoked_program.lock().unwrap() = Some(instruction.program_id);
+        *self.return_data.lock().unwrap() = Some((instruction.program_id, b"callback".to_vec()));
+        Ok(())
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.return_data.lock().unwrap().clone()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        *self.return_data.lock().unwrap() = Some((crate::id(), data.to_vec()));
+    }
+}
+
+#[test]
+fn receipt_is_return_data_after_callback() {
+    let invoked_program = Arc::new(Mutex::new(None));
+    set_syscall_stubs(Box::new(CallbackStubs {
+        invoked_program: invoked_program.clone(),
+        return_data: Mutex::new(None),
+    }));
+
+    let user_key = Pubkey::new_unique();
+    let callback_program_id = Pubkey::new_unique();
+    let user_fill_callback_key = Pubkey::new_unique();
+    let owner = crate::id();
+
+    let mut user_lamports = 0;
+    let mut user_data = vec![];
+    let user = create_account_info(&user_key, true, &mut user_lamports, &mut user_data, &owner);
+
+    let mut callback_lamports = 0;
+    let mut callback_data = vec![];
+    let mut callback_program = create_account_info(
+        &callback_program_id,
+        false,
+        &mut callback_lamports,
+        &mut callback_data,
+        &owner,
+    );
+    callback_program.executable = true;
+
+    let mut user_fill_callback_lamports = 0;
+    let mut user_fill_callback_data = vec![];
+    let user_fill_callback = create_account_info(
+        &user_fill_callback_key,
+        false,
+        &mut user_fill_callback_lamports,
+        &mut user_fill_callback_data,
+        &owner,
+    );
+
+    let receipt = FillReceipt {
+        order_id: 1,
+        status: OrderStatus::Open,
+        base_asset_amount_filled: BASE_PRECISION_U64,
+        quote_asset_amount_filled: 100 * QUOTE_PRECISION_U64,
+        fill_price: 100 * PRICE_PRECISION_U64,
+        fee_paid: 100_000,
+    };
+
+    invoke_fill_callback_and_set_receipt(
+        Some(FillCallbackAccounts {
+            user_fill_callback,
+            callback_program,
+            bump: 255,
+        }),
+        &user,
+        0,
+        receipt,
+    )
+    .unwrap();
+
+    assert_eq!(*invoked_program.lock().unwrap(), Some(callback_program_id));
+    assert_eq!(
+        get_return_data(),
+        Some((crate::id(), receipt.try_to_vec().unwrap()))
+    );
+}
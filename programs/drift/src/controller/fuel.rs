@@ -0,0 +1,174 @@
+use anchor_lang::prelude::Pubkey;
+use solana_program::msg;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::casting::Cast;
+use crate::math::constants::QUOTE_SPOT_MARKET_INDEX;
+use crate::math::fuel::{
+    calculate_fuel_for_rate, calculate_time_weighted_fuel, calculate_volume_fuel,
+};
+use crate::math::safe_math::SafeMath;
+use crate::math::spot_balance::get_token_value;
+use crate::state::fuel::{FuelBoost, UserFuel};
+use crate::state::fuel_map::FuelMap;
+use crate::state::oracle_map::OracleMap;
+use crate::state::spot_market::SpotBalanceType;
+use crate::state::spot_market_map::SpotMarketMap;
+use crate::state::user::{MarketType, UserStats};
+use crate::state::user_map::UserMap;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+pub fn accrue_fill_fuel(
+    fuel_map: &FuelMap,
+    market_type: &MarketType,
+    market_index: u16,
+    taker_authority: &Pubkey,
+    maker_authority: Option<&Pubkey>,
+    quote_asset_amount: u64,
+    now: i64,
+) -> DriftResult {
+    let fuel_boost = match fuel_map.get_fuel_boost(market_type, market_index) {
+        Some(fuel_boost) if fuel_boost.is_active(now) => *fuel_boost,
+        _ => return Ok(()),
+    };
+
+    // no fuel for trading against yourself
+    if maker_authority == Some(taker_authority) {
+        return Ok(());
+    }
+
+    if let Some(mut taker_fuel) = fuel_map.get_user_fuel_mut(taker_authority)? {
+        taker_fuel.taker = taker_fuel
+            .taker
+            .safe_add(calculate_volume_fuel(quote_asset_amount, fuel_boost.taker)?)?;
+    }
+
+    if let Some(maker_authority) = maker_authority {
+        if let Some(mut maker_fuel) = fuel_map.get_user_fuel_mut(maker_authority)? {
+            maker_fuel.maker = maker_fuel
+                .maker
+                .safe_add(calculate_volume_fuel(quote_asset_amount, fuel_boost.maker)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// accrues insurance and borrow fuel since the last update. Each accrual is capped by the rate
+/// the balances earned at the last update, so only balances held across the whole window earn,
+/// and every boost the balances earn under must be passed so none are skipped
+pub fn update_user_fuel(
+    user_fuel: &mut UserFuel,
+    user_stats: &UserStats,
+    users: &UserMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    fuel_map: &FuelMap,
+    now: i64,
+) -> DriftResult {
+    validate!(
+        users.0.len() == user_stats.number_of_sub_accounts.cast::<usize>()?,
+        ErrorCode::InvalidUserFuel,
+        "all {} sub accounts must be passed",
+        user_stats.number_of_sub_accounts
+    )?;
+
+    let last_update_ts = user_fuel.last_update_ts;
+    let time_since_last_update = now.safe_sub(last_update_ts)?;
+
+    let mut insurance_fuel = 0_u64;
+    let mut insurance_fuel_rate = 0_u64;
+    if user_stats.if_staked_quote_asset_amount > 0 {
+        let fuel_boost = get_required_fuel_boost(fuel_map, QUOTE_SPOT_MARKET_INDEX)?;
+        let duration = fuel_boost.active_duration(last_update_ts, now)?;
+
+        insurance_fuel = calculate_time_weighted_fuel(
+            user_stats.if_staked_quote_asset_amount,
+            duration,
+            fuel_boost.insurance,
+        )?;
+        insurance_fuel_rate = calculate_volume_fuel(
+            user_stats.if_staked_quote_asset_amount,
+            fuel_boost.insurance,
+        )?;
+    }
+
+    let mut borrows_fuel = 0_u64;
+    let mut borrows_fuel_rate = 0_u64;
+    for user_loader in users.0.values() {
+        let user = user_loader
+            .load()
+            .or(Err(ErrorCode::UnableToLoadUserAccount))?;
+
+        validate!(
+            user.authority == user_fuel.authority,
+            ErrorCode::InvalidUserFuel,
+            "user authority {} doesnt match user fuel authority {}",
+            user.authority,
+            user_fuel.authority
+        )?;
+
+        for spot_position in user.spot_positions.iter() {
+            if spot_position.is_available() || spot_position.balance_type != SpotBalanceType::Borrow
+            {
+                continue;
+            }
+
+            let fuel_boost = get_required_fuel_boost(fuel_map, spot_position.market_index)?;
+
+            let spot_market = spot_market_map.get_ref(&spot_position.market_index)?;
+            let oracle_price = oracle_map.get_price_data(&spot_market.oracle)?.price;
+            let borrow_value = get_token_value(
+                spot_position.get_token_amount(&spot_market)?.cast()?,
+                spot_market.decimals,
+                oracle_price,
+            )?
+            .cast::<u64>()?;
+
+            let duration = fuel_boost.active_duration(last_update_ts, now)?;
+            borrows_fuel = borrows_fuel.safe_add(calculate_time_weighted_fuel(
+                borrow_value,
+                duration,
+                fuel_boost.borrows,
+            )?)?;
+            borrows_fuel_rate = borrows_fuel_rate
+                .safe_add(calculate_volume_fuel(borrow_value, fuel_boost.borrows)?)?;
+        }
+    }
+
+    user_fuel.insurance =
+        user_fuel
+            .insurance
+            .safe_add(insurance_fuel.min(calculate_fuel_for_rate(
+                user_fuel.last_insurance_fuel_rate,
+                time_since_last_update,
+            )?))?;
+    user_fuel.borrows = user_fuel
+        .borrows
+        .safe_add(borrows_fuel.min(calculate_fuel_for_rate(
+            user_fuel.last_borrows_fuel_rate,
+            time_since_last_update,
+        )?))?;
+
+    user_fuel.last_insurance_fuel_rate = insurance_fuel_rate;
+    user_fuel.last_borrows_fuel_rate = borrows_fuel_rate;
+    user_fuel.last_update_ts = now;
+
+    Ok(())
+}
+
+fn get_required_fuel_boost(fuel_map: &FuelMap, spot_market_index: u16) -> DriftResult<FuelBoost> {
+    match fuel_map.get_fuel_boost(&MarketType::Spot, spot_market_index) {
+        Some(fuel_boost) => Ok(*fuel_boost),
+        None => {
+            msg!(
+                "fuel boost for spot market {} must be passed",
+                spot_market_index
+            );
+            Err(ErrorCode::InvalidFuelBoost)
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+
+use anchor_lang::prelude::*;
+
+use crate::controller::fuel::{accrue_fill_fuel, update_user_fuel};
+use crate::create_anchor_account_info;
+use crate::error::ErrorCode;
+use crate::math::constants::{QUOTE_PRECISION_U64, TWENTY_FOUR_HOUR};
+use crate::state::fuel::{FuelBoost, UserFuel};
+use crate::state::fuel_map::{load_fuel_map, FuelMap};
+use crate::state::oracle_map::OracleMap;
+use crate::state::spot_market_map::SpotMarketMap;
+use crate::state::user::{MarketType, User, UserStats};
+use crate::state::user_map::UserMap;
+use crate::test_utils::*;
+
+#[test]
+fn accrue_fill_fuel_for_taker_and_maker() {
+    let taker_authority = Pubkey::new_unique();
+    let maker_authority = Pubkey::new_unique();
+
+    let mut taker_fuel = UserFuel {
+        authority: taker_authority,
+        ..UserFuel::default()
+    };
+    create_anchor_account_info!(taker_fuel, UserFuel, taker_fuel_account_info);
+    let mut maker_fuel = UserFuel {
+        authority: maker_authority,
+        ..UserFuel::default()
+    };
+    create_anchor_account_info!(maker_fuel, UserFuel, maker_fuel_account_info);
+    let mut fuel_boost = FuelBoost {
+        start_ts: 0,
+        end_ts: 100,
+        market_index: 0,
+        market_type: MarketType::Perp,
+        taker: 20,
+        maker: 10,
+        ..FuelBoost::default()
+    };
+    create_anchor_account_info!(fuel_boost, FuelBoost, fuel_boost_account_info);
+
+    let account_infos = vec![
+        taker_fuel_account_info,
+        maker_fuel_account_info,
+        fuel_boost_account_info,
+    ];
+    let fuel_map = load_fuel_map(&mut account_infos.iter().peekable()).unwrap();
+
+    let quote_asset_amount = 100 * QUOTE_PRECISION_U64;
+
+    accrue_fill_fuel(
+        &fuel_map,
+        &MarketType::Perp,
+        0,
+        &taker_authority,
+        Some(&maker_authority),
+        quote_asset_amount,
+        50,
+    )
+    .unwrap();
+
+    assert_eq!(
+        fuel_map
+            .get_user_fuel_mut(&taker_authority)
+            .unwrap()
+            .unwrap()
+            .taker,
+        200 * QUOTE_PRECISION_U64
+    );
+    assert_eq!(
+        fuel_map
+            .get_user_fuel_mut(&maker_authority)
+            .unwrap()
+            .unwrap()
+            .maker,
+        100 * QUOTE_PRECISION_U64
+    );
+
+    // no boost for the market
+    accrue_fill_fuel(
+        &fuel_map,
+        &MarketType::Perp,
+        1,
+        &taker_authority,
+        Some(&maker_authority),
+        quote_asset_amount,
+        50,
+    )
+    .unwrap();
+
+    // outside the epoch
+    accrue_fill_fuel(
+        &fuel_map,
+        &MarketType::Perp,
+        0,
+        &taker_authority,
+        Some(&maker_authority),
+        quote_asset_amount,
+        100,
+    )
+    .unwrap();
+
+    // trading against yourself
+    accrue_fill_fuel(
+        &fuel_map,
+        &MarketType::Perp,
+        0,
+        &taker_authority,
+        Some(&taker_authority),
+        quote_asset_amount,
+        50,
+    )
+    .unwrap();
+
+    assert_eq!(
+        fuel_map
+            .get_user_fuel_mut(&taker_authority)
+            .unwrap()
+            .unwrap()
+            .taker,
+        200 * QUOTE_PRECISION_U64
+    );
+    assert_eq!(
+        fuel_map
+            .get_user_fuel_mut(&maker_authority)
+            .unwrap()
+            .unwrap()
+            .maker,
+        100 * QUOTE_PRECISION_U64
+    );
+}
+
+#[test]
+fn update_user_fuel_only_credits_balances_held_across_updates() {
+    let authority = Pubkey::new_unique();
+
+    let mut user = User {
+        authority,
+        ..User::default()
+    };
+    create_anchor_account_info!(user, User, user_account_info);
+    let users = UserMap::load_one(&user_account_info).unwrap();
+
+    let mut fuel_boost = FuelBoost {
+        start_ts: 0,
+        end_ts: 10 * TWENTY_FOUR_HOUR,
+        market_index: 0,
+        market_type: MarketType::Spot,
+        insurance: 10,
+        ..FuelBoost::default()
+    };
+    create_anchor_account_info!(fuel_boost, FuelBoost, fuel_boost_account_info);
+    let account_infos = vec![fuel_boost_account_info];
+    let fuel_map = load_fuel_map(&mut account_infos.iter().peekable()).unwrap();
+
+    let spot_market_map = SpotMarketMap(BTreeMap::new());
+    let mut oracle_map = OracleMap::empty();
+
+    let mut user_fuel = UserFuel {
+        authority,
+        ..UserFuel::default()
+    };
+    let mut user_stats = UserStats {
+        authority,
+        number_of_sub_accounts: 1,
+        if_staked_quote_asset_amount: 1_000 * QUOTE_PRECISION_U64,
+        ..UserStats::default()
+    };
+
+    // nothing earned before the stake has been seen by an update
+    update_user_fuel(
+        &mut user_fuel,
+        &user_stats,
+        &users,
+        &spot_market_map,
+        &mut oracle_map,
+        &fuel_map,
+        TWENTY_FOUR_HOUR,
+    )
+    .unwrap();
+    assert_eq!(user_fuel.insurance, 0);
+    assert_eq!(
+        user_fuel.last_insurance_fuel_rate,
+        1_000 * QUOTE_PRECISION_U64
+    );
+
+    update_user_fuel(
+        &mut user_fuel,
+        &user_stats,
+        &users,
+        &spot_market_map,
+        &mut oracle_map,
+        &fuel_map,
+        2 * TWENTY_FOUR_HOUR,
+    )
+    .unwrap();
+    assert_eq!(user_fuel.insurance, 1_000 * QUOTE_PRECISION_U64);
+
+    // staking more right before an update doesnt earn on the window before it
+    user_stats.if_staked_quote_asset_amount = 10_000 * QUOTE_PRECISION_U64;
+    update_user_fuel(
+        &mut user_fuel,
+        &user_stats,
+        &users,
+        &spot_market_map,
+        &mut oracle_map,
+        &fuel_map,
+        3 * TWENTY_FOUR_HOUR,
+    )
+    .unwrap();
+    assert_eq!(user_fuel.insurance, 2_000 * QUOTE_PRECISION_U64);
+
+    // the boost the stake earns under must be passed
+    let result = update_user_fuel(
+        &mut user_fuel,
+        &user_stats,
+        &users,
+        &spot_market_map,
+        &mut oracle_map,
+        &FuelMap::empty(),
+        4 * TWENTY_FOUR_HOUR,
+    );
+    assert_eq!(result, Err(ErrorCode::InvalidFuelBoost));
+    assert_eq!(user_fuel.last_update_ts, 3 * TWENTY_FOUR_HOUR);
+}
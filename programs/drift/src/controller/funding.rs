@@ -12,7 +12,10 @@ use crate::get_then_update_id;
 use crate::math::amm;
 use crate::math::casting::Cast;
 use crate::math::constants::{FUNDING_RATE_BUFFER, ONE_HOUR_I128, TWENTY_FOUR_HOUR};
-use crate::math::funding::{calculate_funding_payment, calculate_funding_rate_long_short};
+use crate::math::funding::{
+    calculate_funding_payment, calculate_funding_rate_long_short,
+    calculate_max_funding_price_spread,
+};
 use crate::math::helpers::on_the_hour_update;
 use crate::math::safe_math::SafeMath;
 use crate::math::stats::calculate_new_twap;
@@ -217,8 +220,10 @@ pub fn update_funding_rate(
         // low periodicity => quickly updating/settled funding rates => lower funding rate payment per interval
         let price_spread = mid_price_twap.cast::<i64>()?.safe_sub(oracle_price_twap)?;
 
-        // clamp price divergence to 3% for funding rate calculation
-        let max_price_spread = oracle_price_twap.safe_div(33)?; // 3%
+        // clamp price divergence to the market's funding cap, escalated if divergence persists
+        let (max_price_spread, funding_divergence_period_count) =
+            calculate_max_funding_price_spread(&market.amm, oracle_price_twap, price_spread)?;
+        market.amm.funding_divergence_period_count = funding_divergence_period_count;
         let clamped_price_spread = max(-max_price_spread, min(price_spread, max_price_spread));
 
         let funding_rate = clamped_price_spread
@@ -237,6 +242,8 @@ pub fn update_funding_rate(
             formulaic_update_k(market, oracle_price_data, funding_imbalance_cost, now)?;
         }
 
+        market.update_funding_paid_24h(funding_rate_long, funding_rate_short, now)?;
+
         market.amm.cumulative_funding_rate_long = market
             .amm
             .cumulative_funding_rate_long
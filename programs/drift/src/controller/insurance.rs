@@ -11,21 +11,22 @@ use crate::error::ErrorCode;
 use crate::math::amm::calculate_net_user_pnl;
 use crate::math::casting::Cast;
 use crate::math::constants::{
-    MAX_APR_PER_REVENUE_SETTLE_TO_INSURANCE_FUND_VAULT, ONE_YEAR, PERCENTAGE_PRECISION_U64,
+    IF_BOOST_SHARES_PER_WEIGHT_PRECISION, MAX_APR_PER_REVENUE_SETTLE_TO_INSURANCE_FUND_VAULT,
+    ONE_YEAR, PERCENTAGE_PRECISION_U64,
     SHARE_OF_REVENUE_ALLOCATED_TO_INSURANCE_FUND_VAULT_DENOMINATOR,
     SHARE_OF_REVENUE_ALLOCATED_TO_INSURANCE_FUND_VAULT_NUMERATOR,
 };
 use crate::math::helpers::get_proportion_u128;
 use crate::math::helpers::on_the_hour_update;
 use crate::math::insurance::{
-    calculate_if_shares_lost, calculate_rebase_info, if_shares_to_vault_amount,
-    vault_amount_to_if_shares,
+    calculate_if_shares_lost, calculate_insurance_fund_lend_interest, calculate_rebase_info,
+    if_shares_to_vault_amount, vault_amount_to_if_shares,
 };
 use crate::math::safe_math::SafeMath;
 use crate::math::spot_balance::get_token_amount;
 use crate::math::spot_withdraw::validate_spot_market_vault_amount;
 use crate::state::events::{InsuranceFundRecord, InsuranceFundStakeRecord, StakeAction};
-use crate::state::insurance_fund_stake::InsuranceFundStake;
+use crate::state::insurance_fund_stake::{InsuranceFundStake, InsuranceFundStakeLockupTier};
 use crate::state::perp_market::PerpMarket;
 use crate::state::spot_market::{SpotBalanceType, SpotMarket};
 use crate::state::state::State;
@@ -51,6 +52,7 @@ pub fn add_insurance_fund_stake(
 
     apply_rebase_to_insurance_fund(insurance_vault_amount, spot_market)?;
     apply_rebase_to_insurance_fund_stake(insurance_fund_stake, spot_market)?;
+    settle_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
 
     let if_shares_before = insurance_fund_stake.checked_if_shares(spot_market)?;
     let total_if_shares_before = spot_market.insurance_fund.total_shares;
@@ -70,6 +72,7 @@ pub fn add_insurance_fund_stake(
     };
 
     insurance_fund_stake.increase_if_shares(n_shares, spot_market)?;
+    track_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
 
     spot_market.insurance_fund.total_shares =
         spot_market.insurance_fund.total_shares.safe_add(n_shares)?;
@@ -125,6 +128,7 @@ pub fn apply_rebase_to_insurance_fund(
             .insurance_fund
             .user_shares
             .safe_div(rebase_divisor)?;
+        spot_market.if_boost_weight = spot_market.if_boost_weight.safe_div(rebase_divisor)?;
         spot_market.insurance_fund.shares_base = spot_market
             .insurance_fund
             .shares_base
@@ -182,6 +186,74 @@ pub fn apply_rebase_to_insurance_fund_stake(
     Ok(())
 }
 
+/// Credits the stake with the boost shares minted for its lockup since it was last touched and takes
+/// its boost weight out of the spot market, track_insurance_fund_stake_boost adds it back once the
+/// stake's shares are final
+pub fn settle_insurance_fund_stake_boost(
+    insurance_fund_stake: &mut InsuranceFundStake,
+    spot_market: &mut SpotMarket,
+) -> DriftResult {
+    let if_boost_weight = insurance_fund_stake.get_if_boost_weight()?;
+
+    if if_boost_weight == 0 {
+        return Ok(());
+    }
+
+    let boost_shares = if_boost_weight
+        .safe_mul(
+            spot_market
+                .cumulative_if_boost_shares_per_weight
+                .safe_sub(insurance_fund_stake.last_cumulative_if_boost_shares_per_weight)?
+                .cast()?,
+        )?
+        .safe_div(IF_BOOST_SHARES_PER_WEIGHT_PRECISION)?;
+
+    insurance_fund_stake.increase_if_shares(boost_shares, spot_market)?;
+
+    spot_market.if_boost_weight = spot_market.if_boost_weight.safe_sub(if_boost_weight)?;
+
+    Ok(())
+}
+
+pub fn track_insurance_fund_stake_boost(
+    insurance_fund_stake: &mut InsuranceFundStake,
+    spot_market: &mut SpotMarket,
+) -> DriftResult {
+    spot_market.if_boost_weight = spot_market
+        .if_boost_weight
+        .safe_add(insurance_fund_stake.get_if_boost_weight()?)?;
+
+    insurance_fund_stake.last_cumulative_if_boost_shares_per_weight =
+        spot_market.cumulative_if_boost_shares_per_weight;
+
+    Ok(())
+}
+
+pub fn update_insurance_fund_stake_lockup_tier(
+    lockup_tier: InsuranceFundStakeLockupTier,
+    insurance_vault_amount: u64,
+    insurance_fund_stake: &mut InsuranceFundStake,
+    spot_market: &mut SpotMarket,
+) -> DriftResult {
+    validate!(
+        lockup_tier > insurance_fund_stake.lockup_tier,
+        ErrorCode::InvalidIFStakeLockupTier,
+        "lockup tier can only be extended ({:?} -> {:?})",
+        insurance_fund_stake.lockup_tier,
+        lockup_tier
+    )?;
+
+    apply_rebase_to_insurance_fund(insurance_vault_amount, spot_market)?;
+    apply_rebase_to_insurance_fund_stake(insurance_fund_stake, spot_market)?;
+    settle_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
+
+    insurance_fund_stake.lockup_tier = lockup_tier;
+
+    track_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
+
+    Ok(())
+}
+
 pub fn request_remove_insurance_fund_stake(
     n_shares: u128,
     insurance_vault_amount: u64,
@@ -195,6 +267,7 @@ pub fn request_remove_insurance_fund_stake(
 
     apply_rebase_to_insurance_fund(insurance_vault_amount, spot_market)?;
     apply_rebase_to_insurance_fund_stake(insurance_fund_stake, spot_market)?;
+    settle_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
 
     let if_shares_before = insurance_fund_stake.checked_if_shares(spot_market)?;
     let total_if_shares_before = spot_market.insurance_fund.total_shares;
@@ -215,6 +288,8 @@ pub fn request_remove_insurance_fund_stake(
         "if stake base != spot market base"
     )?;
 
+    track_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
+
     insurance_fund_stake.last_withdraw_request_value = if_shares_to_vault_amount(
         insurance_fund_stake.last_withdraw_request_shares,
         spot_market.insurance_fund.total_shares,
@@ -268,6 +343,7 @@ pub fn cancel_request_remove_insurance_fund_stake(
 ) -> DriftResult {
     apply_rebase_to_insurance_fund(insurance_vault_amount, spot_market)?;
     apply_rebase_to_insurance_fund_stake(insurance_fund_stake, spot_market)?;
+    settle_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
 
     let if_shares_before = insurance_fund_stake.checked_if_shares(spot_market)?;
     let total_if_shares_before = spot_market.insurance_fund.total_shares;
@@ -289,6 +365,7 @@ pub fn cancel_request_remove_insurance_fund_stake(
         calculate_if_shares_lost(insurance_fund_stake, spot_market, insurance_vault_amount)?;
 
     insurance_fund_stake.decrease_if_shares(if_shares_lost, spot_market)?;
+    track_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
 
     spot_market.insurance_fund.total_shares = spot_market
         .insurance_fund
@@ -342,13 +419,19 @@ pub fn remove_insurance_fund_stake(
     let time_since_withdraw_request =
         now.safe_sub(insurance_fund_stake.last_withdraw_request_ts)?;
 
+    let unstaking_period = spot_market
+        .insurance_fund
+        .unstaking_period
+        .max(insurance_fund_stake.lockup_tier.unstaking_period());
+
     validate!(
-        time_since_withdraw_request >= spot_market.insurance_fund.unstaking_period,
+        time_since_withdraw_request >= unstaking_period,
         ErrorCode::TryingToRemoveLiquidityTooFast
     )?;
 
     apply_rebase_to_insurance_fund(insurance_vault_amount, spot_market)?;
     apply_rebase_to_insurance_fund_stake(insurance_fund_stake, spot_market)?;
+    settle_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
 
     let if_shares_before = insurance_fund_stake.checked_if_shares(spot_market)?;
     let total_if_shares_before = spot_market.insurance_fund.total_shares;
@@ -380,6 +463,12 @@ pub fn remove_insurance_fund_stake(
 
     insurance_fund_stake.decrease_if_shares(n_shares, spot_market)?;
 
+    // a fully unstaked account starts over without a lockup
+    if insurance_fund_stake.checked_if_shares(spot_market)? == 0 {
+        insurance_fund_stake.lockup_tier = InsuranceFundStakeLockupTier::None;
+    }
+    track_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
+
     insurance_fund_stake.cost_basis = insurance_fund_stake
         .cost_basis
         .safe_sub(withdraw_amount.cast()?)?;
@@ -423,6 +512,195 @@ pub fn remove_insurance_fund_stake(
     Ok(withdraw_amount)
 }
 
+/// Moves shares from a staker into the spot market's wrapped stake and returns the share mint
+/// tokens owed for them. Tokens track the wrapped stake pro rata so they survive rebases
+pub fn wrap_insurance_fund_stake(
+    n_shares: u128,
+    insurance_vault_amount: u64,
+    insurance_fund_stake: &mut InsuranceFundStake,
+    wrapped_insurance_fund_stake: &mut InsuranceFundStake,
+    user_stats: &mut UserStats,
+    spot_market: &mut SpotMarket,
+    share_mint_supply: u64,
+    now: i64,
+) -> DriftResult<u64> {
+    validate!(
+        insurance_fund_stake.lockup_tier == InsuranceFundStakeLockupTier::None,
+        ErrorCode::InvalidIFStakeLockupTier,
+        "shares under a lockup cant be wrapped"
+    )?;
+
+    apply_rebase_to_insurance_fund(insurance_vault_amount, spot_market)?;
+    apply_rebase_to_insurance_fund_stake(insurance_fund_stake, spot_market)?;
+    apply_rebase_to_insurance_fund_stake(wrapped_insurance_fund_stake, spot_market)?;
+
+    let if_shares_before = insurance_fund_stake.checked_if_shares(spot_market)?;
+    let total_if_shares_before = spot_market.insurance_fund.total_shares;
+    let user_if_shares_before = spot_market.insurance_fund.user_shares;
+
+    validate!(
+        n_shares > 0 && n_shares <= if_shares_before,
+        ErrorCode::InsufficientIFShares,
+        "n_shares={} if_shares={}",
+        n_shares,
+        if_shares_before
+    )?;
+
+    let wrapped_if_shares = wrapped_insurance_fund_stake.checked_if_shares(spot_market)?;
+
+    let token_amount = if share_mint_supply == 0 {
+        n_shares.cast::<u64>()?
+    } else {
+        validate!(
+            wrapped_if_shares > 0,
+            ErrorCode::InvalidIFSharesDetected,
+            "share mint supply={} without wrapped shares",
+            share_mint_supply
+        )?;
+
+        get_proportion_u128(n_shares, share_mint_supply.cast()?, wrapped_if_shares)?
+            .cast::<u64>()?
+    };
+
+    validate!(
+        token_amount > 0,
+        ErrorCode::InsufficientIFShares,
+        "n_shares={} too small to wrap",
+        n_shares
+    )?;
+
+    let amount = if_shares_to_vault_amount(
+        n_shares,
+        spot_market.insurance_fund.total_shares,
+        insurance_vault_amount,
+    )?;
+
+    insurance_fund_stake.decrease_if_shares(n_shares, spot_market)?;
+    insurance_fund_stake.cost_basis = insurance_fund_stake.cost_basis.safe_sub(amount.cast()?)?;
+
+    wrapped_insurance_fund_stake.increase_if_shares(n_shares, spot_market)?;
+    wrapped_insurance_fund_stake.cost_basis = wrapped_insurance_fund_stake
+        .cost_basis
+        .safe_add(amount.cast()?)?;
+
+    let if_shares_after = insurance_fund_stake.checked_if_shares(spot_market)?;
+
+    if spot_market.market_index == 0 {
+        user_stats.if_staked_quote_asset_amount = if_shares_to_vault_amount(
+            if_shares_after,
+            spot_market.insurance_fund.total_shares,
+            insurance_vault_amount,
+        )?;
+    }
+
+    emit!(InsuranceFundStakeRecord {
+        ts: now,
+        user_authority: user_stats.authority,
+        action: StakeAction::Wrap,
+        amount,
+        market_index: spot_market.market_index,
+        insurance_vault_amount_before: insurance_vault_amount,
+        if_shares_before,
+        user_if_shares_before,
+        total_if_shares_before,
+        if_shares_after,
+        total_if_shares_after: spot_market.insurance_fund.total_shares,
+        user_if_shares_after: spot_market.insurance_fund.user_shares,
+    });
+
+    Ok(token_amount)
+}
+
+/// Redeems share mint tokens for their pro rata shares of the wrapped stake. Returns the shares
+/// moved into the staker's account
+pub fn unwrap_insurance_fund_stake(
+    token_amount: u64,
+    insurance_vault_amount: u64,
+    insurance_fund_stake: &mut InsuranceFundStake,
+    wrapped_insurance_fund_stake: &mut InsuranceFundStake,
+    user_stats: &mut UserStats,
+    spot_market: &mut SpotMarket,
+    share_mint_supply: u64,
+    now: i64,
+) -> DriftResult<u128> {
+    validate!(
+        token_amount > 0 && token_amount <= share_mint_supply,
+        ErrorCode::InsufficientIFShares,
+        "token_amount={} share_mint_supply={}",
+        token_amount,
+        share_mint_supply
+    )?;
+
+    apply_rebase_to_insurance_fund(insurance_vault_amount, spot_market)?;
+    apply_rebase_to_insurance_fund_stake(insurance_fund_stake, spot_market)?;
+    apply_rebase_to_insurance_fund_stake(wrapped_insurance_fund_stake, spot_market)?;
+    settle_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
+
+    let if_shares_before = insurance_fund_stake.checked_if_shares(spot_market)?;
+    let total_if_shares_before = spot_market.insurance_fund.total_shares;
+    let user_if_shares_before = spot_market.insurance_fund.user_shares;
+
+    let n_shares = get_proportion_u128(
+        token_amount.cast()?,
+        wrapped_insurance_fund_stake.checked_if_shares(spot_market)?,
+        share_mint_supply.cast()?,
+    )?;
+
+    validate!(
+        n_shares > 0,
+        ErrorCode::InsufficientIFShares,
+        "token_amount={} too small to unwrap",
+        token_amount
+    )?;
+
+    let amount = if_shares_to_vault_amount(
+        n_shares,
+        spot_market.insurance_fund.total_shares,
+        insurance_vault_amount,
+    )?;
+
+    wrapped_insurance_fund_stake.decrease_if_shares(n_shares, spot_market)?;
+    wrapped_insurance_fund_stake.cost_basis = wrapped_insurance_fund_stake
+        .cost_basis
+        .safe_sub(amount.cast()?)?;
+
+    // reset cost basis if no shares
+    insurance_fund_stake.cost_basis = if if_shares_before == 0 {
+        amount.cast()?
+    } else {
+        insurance_fund_stake.cost_basis.safe_add(amount.cast()?)?
+    };
+    insurance_fund_stake.increase_if_shares(n_shares, spot_market)?;
+    track_insurance_fund_stake_boost(insurance_fund_stake, spot_market)?;
+
+    let if_shares_after = insurance_fund_stake.checked_if_shares(spot_market)?;
+
+    if spot_market.market_index == 0 {
+        user_stats.if_staked_quote_asset_amount = if_shares_to_vault_amount(
+            if_shares_after,
+            spot_market.insurance_fund.total_shares,
+            insurance_vault_amount,
+        )?;
+    }
+
+    emit!(InsuranceFundStakeRecord {
+        ts: now,
+        user_authority: user_stats.authority,
+        action: StakeAction::Unwrap,
+        amount,
+        market_index: spot_market.market_index,
+        insurance_vault_amount_before: insurance_vault_amount,
+        if_shares_before,
+        user_if_shares_before,
+        total_if_shares_before,
+        if_shares_after,
+        total_if_shares_after: spot_market.insurance_fund.total_shares,
+        user_if_shares_after: spot_market.insurance_fund.user_shares,
+    });
+
+    Ok(n_shares)
+}
+
 pub fn admin_remove_insurance_fund_stake(
     insurance_vault_amount: u64,
     n_shares: u128,
@@ -584,6 +862,10 @@ pub fn settle_revenue_to_insurance_fund(
         token_amount = capped_token_pct_amount.min(capped_apr_amount);
     }
 
+    if spot_market.revenue_settle_max_amount > 0 {
+        token_amount = token_amount.min(spot_market.revenue_settle_max_amount.cast()?);
+    }
+
     let insurance_fund_token_amount = get_proportion_u128(
         token_amount,
         SHARE_OF_REVENUE_ALLOCATED_TO_INSURANCE_FUND_VAULT_NUMERATOR,
@@ -618,6 +900,12 @@ pub fn settle_revenue_to_insurance_fund(
             spot_market.insurance_fund.total_shares.safe_add(n_shares)?;
     }
 
+    settle_revenue_to_insurance_fund_boost(
+        insurance_fund_token_amount,
+        insurance_vault_amount,
+        spot_market,
+    )?;
+
     let total_if_shares_before = spot_market.insurance_fund.total_shares;
 
     update_revenue_pool_balances(
@@ -711,9 +999,7 @@ pub fn resolve_perp_pnl_deficit(
     )?;
 
     let max_insurance_withdraw = market
-        .insurance_claim
-        .quote_max_insurance
-        .safe_sub(market.insurance_claim.quote_settled_insurance)?
+        .get_max_insurance_fund_draw(insurance_vault_amount)?
         .cast::<i128>()?;
 
     validate!(
@@ -786,3 +1072,187 @@ pub fn resolve_perp_pnl_deficit(
 
     insurance_withdraw.cast()
 }
+
+/// Lends idle insurance fund quote to the market's fee pool up to insurance_fund_lend_max_pct of
+/// the insurance fund, or recalls it once users are owed more than half the pnl imbalance that
+/// draws on the insurance fund. Interest is collected from the fee pool first, interest the fee
+/// pool can't cover is forgiven. Returns the amount to send from the insurance vault to the spot
+/// market vault, negative for a recall or interest payment
+pub fn update_insurance_fund_lend_to_amm(
+    vault_amount: u64,
+    insurance_vault_amount: u64,
+    spot_market: &mut SpotMarket,
+    market: &mut PerpMarket,
+    now: i64,
+) -> DriftResult<i64> {
+    update_spot_market_cumulative_interest(spot_market, None, now)?;
+
+    let interest = calculate_insurance_fund_lend_interest(
+        market.insurance_fund_lent,
+        market.insurance_fund_lend_rate,
+        market.insurance_fund_lend_last_ts,
+        now,
+    )?;
+    market.insurance_fund_lend_last_ts = now;
+
+    let interest_paid = interest.min(
+        get_token_amount(
+            market.amm.fee_pool.scaled_balance,
+            spot_market,
+            &SpotBalanceType::Deposit,
+        )?
+        .cast()?,
+    );
+
+    if interest_paid > 0 {
+        update_spot_balances(
+            interest_paid.cast()?,
+            &SpotBalanceType::Borrow,
+            spot_market,
+            &mut market.amm.fee_pool,
+            false,
+        )?;
+
+        market.amm.total_fee_minus_distributions = market
+            .amm
+            .total_fee_minus_distributions
+            .safe_sub(interest_paid.cast()?)?;
+    }
+
+    let net_user_pnl = calculate_net_user_pnl(
+        &market.amm,
+        market.amm.historical_oracle_data.last_oracle_price,
+    )?;
+    let draw_risk_elevated = !market.is_active(now)?
+        || net_user_pnl
+            > market
+                .unrealized_pnl_max_imbalance
+                .cast::<i128>()?
+                .safe_div(2)?;
+
+    let target_lent = if draw_risk_elevated {
+        0
+    } else {
+        // sized against everything the insurance fund holds, including what it has lent out
+        market.get_max_insurance_fund_lend(
+            insurance_vault_amount.safe_add(spot_market.insurance_fund_lent_to_amms)?,
+        )?
+    };
+
+    let total_if_shares_before = spot_market.insurance_fund.total_shares;
+
+    let amount = if market.insurance_fund_lent > target_lent {
+        let fee_pool_token_amount = get_token_amount(
+            market.amm.fee_pool.scaled_balance,
+            spot_market,
+            &SpotBalanceType::Deposit,
+        )?
+        .cast::<u64>()?;
+
+        let recall = market
+            .insurance_fund_lent
+            .safe_sub(target_lent)?
+            .min(fee_pool_token_amount);
+
+        if recall > 0 {
+            update_spot_balances(
+                recall.cast()?,
+                &SpotBalanceType::Borrow,
+                spot_market,
+                &mut market.amm.fee_pool,
+                false,
+            )?;
+
+            market.insurance_fund_lent = market.insurance_fund_lent.safe_sub(recall)?;
+            spot_market.insurance_fund_lent_to_amms =
+                spot_market.insurance_fund_lent_to_amms.safe_sub(recall)?;
+        }
+
+        -recall.cast::<i64>()?.safe_add(interest_paid.cast()?)?
+    } else {
+        let lend = target_lent
+            .safe_sub(market.insurance_fund_lent)?
+            .min(insurance_vault_amount.saturating_sub(1));
+
+        if lend > 0 {
+            update_spot_balances(
+                lend.cast()?,
+                &SpotBalanceType::Deposit,
+                spot_market,
+                &mut market.amm.fee_pool,
+                false,
+            )?;
+
+            market.insurance_fund_lent = market.insurance_fund_lent.safe_add(lend)?;
+            spot_market.insurance_fund_lent_to_amms =
+                spot_market.insurance_fund_lent_to_amms.safe_add(lend)?;
+        }
+
+        lend.cast::<i64>()?.safe_sub(interest_paid.cast()?)?
+    };
+
+    if amount != 0 {
+        emit!(InsuranceFundRecord {
+            ts: now,
+            spot_market_index: spot_market.market_index,
+            perp_market_index: market.market_index,
+            amount: -amount,
+            user_if_factor: spot_market.insurance_fund.user_factor,
+            total_if_factor: spot_market.insurance_fund.total_factor,
+            vault_amount_before: vault_amount,
+            insurance_vault_amount_before: insurance_vault_amount,
+            total_if_shares_before,
+            total_if_shares_after: spot_market.insurance_fund.total_shares,
+        });
+    }
+
+    Ok(amount)
+}
+
+/// Locked up stakers' boost weight counts as extra user shares when splitting the user cut of
+/// revenue. Their extra cut is minted as if shares, claimed as stakes are next touched
+pub fn settle_revenue_to_insurance_fund_boost(
+    insurance_fund_token_amount: u64,
+    insurance_vault_amount: u64,
+    spot_market: &mut SpotMarket,
+) -> DriftResult {
+    if spot_market.if_boost_weight == 0 || spot_market.insurance_fund.user_factor == 0 {
+        return Ok(());
+    }
+
+    let user_if_token_amount = insurance_fund_token_amount
+        .cast::<u128>()?
+        .safe_mul(spot_market.insurance_fund.user_factor.cast()?)?
+        .safe_div(spot_market.insurance_fund.total_factor.cast()?)?;
+
+    let boost_token_amount = get_proportion_u128(
+        user_if_token_amount,
+        spot_market.if_boost_weight,
+        spot_market
+            .insurance_fund
+            .user_shares
+            .safe_add(spot_market.if_boost_weight)?,
+    )?;
+
+    let n_shares = vault_amount_to_if_shares(
+        boost_token_amount.cast()?,
+        spot_market.insurance_fund.total_shares,
+        insurance_vault_amount,
+    )?;
+
+    spot_market.insurance_fund.total_shares =
+        spot_market.insurance_fund.total_shares.safe_add(n_shares)?;
+
+    spot_market.insurance_fund.user_shares =
+        spot_market.insurance_fund.user_shares.safe_add(n_shares)?;
+
+    spot_market.cumulative_if_boost_shares_per_weight =
+        spot_market.cumulative_if_boost_shares_per_weight.safe_add(
+            n_shares
+                .safe_mul(IF_BOOST_SHARES_PER_WEIGHT_PRECISION)?
+                .safe_div(spot_market.if_boost_weight)?
+                .cast()?,
+        )?;
+
+    Ok(())
+}
@@ -1,7 +1,13 @@
 use anchor_lang::prelude::Pubkey;
 
 use crate::controller::insurance::*;
-use crate::math::constants::{QUOTE_PRECISION, SPOT_CUMULATIVE_INTEREST_PRECISION};
+use crate::math::constants::{
+    BASE_PRECISION_I128, PERCENTAGE_PRECISION, PRICE_PRECISION_I64, QUOTE_PRECISION,
+    QUOTE_PRECISION_I128, QUOTE_PRECISION_I64, QUOTE_PRECISION_U64,
+    SPOT_CUMULATIVE_INTEREST_PRECISION, THIRTY_DAY,
+};
+use crate::state::oracle::HistoricalOracleData;
+use crate::state::perp_market::{MarketStatus, AMM};
 use crate::state::spot_market::InsuranceFund;
 use crate::state::user::UserStats;
 
@@ -1356,3 +1362,348 @@ pub fn multiple_if_stakes_and_rebase_and_admin_remove() {
     assert_eq!(spot_market.insurance_fund.user_shares, 0);
     assert_eq!(spot_market.insurance_fund.total_shares, 0);
 }
+
+#[test]
+pub fn lockup_boosted_stake_if_test() {
+    let mut if_balance = 0;
+
+    let mut user_stats = UserStats::default();
+    let mut spot_market = SpotMarket {
+        deposit_balance: 0,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        insurance_fund: InsuranceFund {
+            unstaking_period: 0,
+            user_factor: 100_000,
+            total_factor: 100_000,
+            ..InsuranceFund::default()
+        },
+        ..SpotMarket::default()
+    };
+
+    let amount = 100 * QUOTE_PRECISION as u64; // $100
+
+    let mut if_stake = InsuranceFundStake::new(Pubkey::default(), 0, 0);
+    add_insurance_fund_stake(
+        amount,
+        if_balance,
+        &mut if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        0,
+    )
+    .unwrap();
+    if_balance += amount;
+
+    let mut locked_if_stake = InsuranceFundStake::new(Pubkey::default(), 0, 0);
+    add_insurance_fund_stake(
+        amount,
+        if_balance,
+        &mut locked_if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        0,
+    )
+    .unwrap();
+    if_balance += amount;
+
+    update_insurance_fund_stake_lockup_tier(
+        InsuranceFundStakeLockupTier::NinetyDay,
+        if_balance,
+        &mut locked_if_stake,
+        &mut spot_market,
+    )
+    .unwrap();
+    assert_eq!(spot_market.if_boost_weight, 25 * QUOTE_PRECISION); // 25% of $100 of shares
+
+    // lockup can't be shortened
+    assert!(update_insurance_fund_stake_lockup_tier(
+        InsuranceFundStakeLockupTier::ThirtyDay,
+        if_balance,
+        &mut locked_if_stake,
+        &mut spot_market,
+    )
+    .is_err());
+
+    // $45 of revenue, the boost weight takes 25 / 225 of it
+    let revenue = 45 * QUOTE_PRECISION as u64;
+    settle_revenue_to_insurance_fund_boost(revenue, if_balance, &mut spot_market).unwrap();
+    if_balance += revenue;
+    assert_eq!(
+        spot_market.insurance_fund.total_shares,
+        205 * QUOTE_PRECISION
+    );
+    assert_eq!(
+        spot_market.insurance_fund.user_shares,
+        205 * QUOTE_PRECISION
+    );
+    assert_eq!(
+        spot_market.cumulative_if_boost_shares_per_weight,
+        200_000_000_000
+    );
+
+    // boost shares are claimed when the stake is next touched
+    assert_eq!(if_stake.unchecked_if_shares(), 100 * QUOTE_PRECISION);
+    assert_eq!(locked_if_stake.unchecked_if_shares(), 100 * QUOTE_PRECISION);
+
+    request_remove_insurance_fund_stake(
+        105 * QUOTE_PRECISION,
+        if_balance,
+        &mut locked_if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        0,
+    )
+    .unwrap();
+    assert_eq!(locked_if_stake.unchecked_if_shares(), 105 * QUOTE_PRECISION);
+    assert_eq!(spot_market.if_boost_weight, 26_250_000);
+
+    // still within the 90 day lockup
+    assert!(remove_insurance_fund_stake(
+        if_balance,
+        &mut locked_if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        THIRTY_DAY * 3 - 1,
+    )
+    .is_err());
+
+    let amount_returned = remove_insurance_fund_stake(
+        if_balance,
+        &mut locked_if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        THIRTY_DAY * 3,
+    )
+    .unwrap();
+    assert_eq!(amount_returned, 125_487_804); // vs $119.51 for the unlocked stake
+
+    assert_eq!(locked_if_stake.unchecked_if_shares(), 0);
+    assert_eq!(
+        locked_if_stake.lockup_tier,
+        InsuranceFundStakeLockupTier::None
+    );
+    assert_eq!(spot_market.if_boost_weight, 0);
+}
+
+#[test]
+pub fn wrap_and_unwrap_stake_if_test() {
+    let mut if_balance = 0;
+
+    let mut user_stats = UserStats::default();
+    let mut spot_market = SpotMarket {
+        deposit_balance: 0,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        insurance_fund: InsuranceFund {
+            unstaking_period: 0,
+            ..InsuranceFund::default()
+        },
+        ..SpotMarket::default()
+    };
+
+    let amount = 100 * QUOTE_PRECISION as u64; // $100
+
+    let mut if_stake = InsuranceFundStake::new(Pubkey::default(), 0, 0);
+    add_insurance_fund_stake(
+        amount,
+        if_balance,
+        &mut if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        0,
+    )
+    .unwrap();
+    if_balance += amount;
+
+    let mut wrapped_if_stake = InsuranceFundStake::new(Pubkey::default(), 0, 0);
+    let mut share_mint_supply = 0;
+
+    let token_amount = wrap_insurance_fund_stake(
+        40 * QUOTE_PRECISION,
+        if_balance,
+        &mut if_stake,
+        &mut wrapped_if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        share_mint_supply,
+        0,
+    )
+    .unwrap();
+    share_mint_supply += token_amount;
+    assert_eq!(token_amount, 40 * QUOTE_PRECISION as u64);
+    assert_eq!(if_stake.unchecked_if_shares(), 60 * QUOTE_PRECISION);
+    assert_eq!(wrapped_if_stake.unchecked_if_shares(), 40 * QUOTE_PRECISION);
+    assert_eq!(if_stake.cost_basis, 60 * QUOTE_PRECISION as i64);
+    assert_eq!(
+        spot_market.insurance_fund.user_shares,
+        100 * QUOTE_PRECISION
+    );
+
+    // fund doubles, tokens are now worth 2 shares' value each
+    if_balance *= 2;
+
+    let mut other_if_stake = InsuranceFundStake::new(Pubkey::default(), 0, 0);
+    let n_shares = unwrap_insurance_fund_stake(
+        10 * QUOTE_PRECISION as u64,
+        if_balance,
+        &mut other_if_stake,
+        &mut wrapped_if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        share_mint_supply,
+        0,
+    )
+    .unwrap();
+    share_mint_supply -= 10 * QUOTE_PRECISION as u64;
+    assert_eq!(n_shares, 10 * QUOTE_PRECISION);
+    assert_eq!(other_if_stake.unchecked_if_shares(), 10 * QUOTE_PRECISION);
+    assert_eq!(other_if_stake.cost_basis, 20 * QUOTE_PRECISION as i64);
+    assert_eq!(wrapped_if_stake.unchecked_if_shares(), 30 * QUOTE_PRECISION);
+
+    // new wrappers get tokens at the wrapped stake's share ratio
+    let token_amount = wrap_insurance_fund_stake(
+        30 * QUOTE_PRECISION,
+        if_balance,
+        &mut if_stake,
+        &mut wrapped_if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        share_mint_supply,
+        0,
+    )
+    .unwrap();
+    assert_eq!(token_amount, 30 * QUOTE_PRECISION as u64);
+    assert_eq!(wrapped_if_stake.unchecked_if_shares(), 60 * QUOTE_PRECISION);
+
+    // can't unwrap more than the supply
+    assert!(unwrap_insurance_fund_stake(
+        share_mint_supply + token_amount + 1,
+        if_balance,
+        &mut other_if_stake,
+        &mut wrapped_if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        share_mint_supply + token_amount,
+        0,
+    )
+    .is_err());
+
+    // locked shares can't be wrapped
+    other_if_stake.lockup_tier = InsuranceFundStakeLockupTier::ThirtyDay;
+    assert!(wrap_insurance_fund_stake(
+        QUOTE_PRECISION,
+        if_balance,
+        &mut other_if_stake,
+        &mut wrapped_if_stake,
+        &mut user_stats,
+        &mut spot_market,
+        share_mint_supply,
+        0,
+    )
+    .is_err());
+}
+
+#[test]
+pub fn update_insurance_fund_lend_to_amm_lends_and_recalls() {
+    let mut spot_market = SpotMarket {
+        market_index: 0,
+        decimals: 6,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        ..SpotMarket::default()
+    };
+
+    let mut perp_market = PerpMarket {
+        amm: AMM {
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price: 100 * PRICE_PRECISION_I64,
+                ..HistoricalOracleData::default()
+            },
+            ..AMM::default()
+        },
+        status: MarketStatus::Active,
+        unrealized_pnl_max_imbalance: 100 * QUOTE_PRECISION_U64,
+        insurance_fund_lend_max_pct: 10,
+        insurance_fund_lend_rate: (PERCENTAGE_PRECISION / 10) as u32, // 10%
+        ..PerpMarket::default()
+    };
+
+    // lends 10% of the insurance fund
+    let amount = update_insurance_fund_lend_to_amm(
+        0,
+        1000 * QUOTE_PRECISION_U64,
+        &mut spot_market,
+        &mut perp_market,
+        0,
+    )
+    .unwrap();
+    assert_eq!(amount, 100 * QUOTE_PRECISION_I64);
+    assert_eq!(perp_market.insurance_fund_lent, 100 * QUOTE_PRECISION_U64);
+    assert_eq!(
+        spot_market.insurance_fund_lent_to_amms,
+        100 * QUOTE_PRECISION_U64
+    );
+    // the loan isnt fee pool revenue
+    assert_eq!(perp_market.amm.total_fee_minus_distributions, 0);
+    assert_eq!(
+        get_token_amount(
+            perp_market.amm.fee_pool.scaled_balance,
+            &spot_market,
+            &SpotBalanceType::Deposit
+        )
+        .unwrap(),
+        100 * QUOTE_PRECISION
+    );
+
+    // half a year of interest is paid from the fee pool to the insurance fund
+    let now = (ONE_YEAR / 2) as i64;
+    let amount = update_insurance_fund_lend_to_amm(
+        0,
+        900 * QUOTE_PRECISION_U64,
+        &mut spot_market,
+        &mut perp_market,
+        now,
+    )
+    .unwrap();
+    assert_eq!(amount, -5 * QUOTE_PRECISION_I64);
+    assert_eq!(perp_market.insurance_fund_lent, 100 * QUOTE_PRECISION_U64);
+    assert_eq!(
+        spot_market.insurance_fund_lent_to_amms,
+        100 * QUOTE_PRECISION_U64
+    );
+    assert_eq!(
+        get_token_amount(
+            perp_market.amm.fee_pool.scaled_balance,
+            &spot_market,
+            &SpotBalanceType::Deposit
+        )
+        .unwrap(),
+        95 * QUOTE_PRECISION
+    );
+    assert_eq!(
+        perp_market.amm.total_fee_minus_distributions,
+        -5 * QUOTE_PRECISION_I128
+    );
+
+    // users owed more than half the max imbalance, recalls as much as the fee pool holds
+    perp_market.amm.base_asset_amount_with_amm = BASE_PRECISION_I128;
+    perp_market.amm.quote_asset_amount = -40 * QUOTE_PRECISION_I128;
+    let amount = update_insurance_fund_lend_to_amm(
+        0,
+        905 * QUOTE_PRECISION_U64,
+        &mut spot_market,
+        &mut perp_market,
+        now,
+    )
+    .unwrap();
+    assert_eq!(amount, -95 * QUOTE_PRECISION_I64);
+    assert_eq!(perp_market.insurance_fund_lent, 5 * QUOTE_PRECISION_U64);
+    assert_eq!(
+        spot_market.insurance_fund_lent_to_amms,
+        5 * QUOTE_PRECISION_U64
+    );
+    assert_eq!(perp_market.amm.fee_pool.scaled_balance, 0);
+    assert_eq!(
+        perp_market.amm.total_fee_minus_distributions,
+        -5 * QUOTE_PRECISION_I128
+    );
+}
@@ -26,39 +26,47 @@ use crate::math::constants::{
     QUOTE_PRECISION_I128, QUOTE_PRECISION_U64, QUOTE_SPOT_MARKET_INDEX, SPOT_WEIGHT_PRECISION,
 };
 use crate::math::liquidation::{
-    calculate_asset_transfer_for_liability_transfer,
-    calculate_base_asset_amount_to_cover_margin_shortage,
+    calculate_asset_transfer_for_liability_transfer, calculate_auto_deleverage_price,
+    calculate_auto_deleverage_score, calculate_base_asset_amount_to_cover_margin_shortage,
     calculate_cumulative_deposit_interest_delta_to_resolve_bankruptcy,
-    calculate_funding_rate_deltas_to_resolve_bankruptcy,
+    calculate_dutch_auction_liquidation_fee, calculate_funding_rate_deltas_to_resolve_bankruptcy,
     calculate_liability_transfer_implied_by_asset_amount,
     calculate_liability_transfer_to_cover_margin_shortage, calculate_liquidation_multiplier,
     calculate_margin_shortage, calculate_max_pct_to_liquidate,
-    validate_transfer_satisfies_limit_price, LiquidationMultiplierType,
+    calculate_stale_liquidation_fee_bonus, validate_transfer_satisfies_limit_price,
+    LiquidationMultiplierType,
 };
 use crate::math::margin::{
+    calculate_isolated_perp_position_margin_requirement_and_total_collateral,
     calculate_margin_requirement_and_total_collateral, calculate_user_safest_position_tiers,
-    meets_initial_margin_requirement, MarginRequirementType,
+    meets_initial_margin_requirement, meets_isolated_perp_position_margin_requirement,
+    MarginRequirementType,
 };
 use crate::math::oracle::DriftAction;
 use crate::math::orders::{
     get_position_delta_for_fill, is_multiple_of_step_size, standardize_base_asset_amount,
     standardize_base_asset_amount_ceil,
 };
-use crate::math::position::calculate_base_asset_value_with_oracle_price;
+use crate::math::position::{
+    calculate_base_asset_value_and_pnl_with_oracle_price,
+    calculate_base_asset_value_with_oracle_price,
+};
 use crate::math::safe_math::SafeMath;
 use crate::math::spot_balance::get_token_value;
 use crate::state::events::{
-    LiquidateBorrowForPerpPnlRecord, LiquidatePerpPnlForDepositRecord, LiquidatePerpRecord,
-    LiquidateSpotRecord, LiquidationRecord, LiquidationType, OrderAction, OrderActionExplanation,
-    OrderActionRecord, OrderRecord, PerpBankruptcyRecord, SpotBankruptcyRecord,
+    AutoDeleverageRecord, LiquidateBorrowForPerpPnlRecord, LiquidatePerpPnlForDepositRecord,
+    LiquidatePerpRecord, LiquidateSpotRecord, LiquidationRecord, LiquidationType, OrderAction,
+    OrderActionExplanation, OrderActionRecord, OrderRecord, PerpBankruptcyRecord,
+    SpotBankruptcyRecord,
 };
 use crate::state::oracle_map::OracleMap;
-use crate::state::perp_market::MarketStatus;
+use crate::state::perp_market::{MarketStatus, PerpOperation};
 use crate::state::perp_market_map::PerpMarketMap;
-use crate::state::spot_market::SpotBalanceType;
+use crate::state::spot_market::{SpotBalanceType, SpotOperation};
 use crate::state::spot_market_map::SpotMarketMap;
 use crate::state::state::State;
 use crate::state::user::{MarketType, Order, OrderStatus, OrderType, User, UserStats};
+use crate::state::user_map::UserMap;
 use crate::validate;
 
 #[cfg(test)]
@@ -80,10 +88,36 @@ pub fn liquidate_perp(
     slot: u64,
     now: i64,
     state: &State,
+    backstop_liquidation_fee_bonus: u32,
 ) -> DriftResult {
+    if user
+        .get_perp_position(market_index)
+        .map_or(false, |position| position.is_isolated)
+    {
+        return liquidate_isolated_perp(
+            market_index,
+            liquidator_max_base_asset_amount,
+            limit_price,
+            user,
+            user_key,
+            user_stats,
+            liquidator,
+            liquidator_key,
+            liquidator_stats,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            slot,
+            now,
+            state,
+        );
+    }
+
     let liquidation_margin_buffer_ratio = state.liquidation_margin_buffer_ratio;
     let initial_pct_to_liquidate = state.initial_pct_to_liquidate as u128;
     let liquidation_duration = state.liquidation_duration as u128;
+    let liquidation_fee_ramp_duration = state.liquidation_fee_ramp_duration as u128;
+    let liquidation_target_margin_buffer_ratio = state.liquidation_target_margin_buffer_ratio;
 
     validate!(
         !user.is_bankrupt(),
@@ -188,6 +222,20 @@ pub fn liquidate_perp(
         Some(DriftAction::Liquidate),
     )?;
 
+    validate!(
+        !market.is_operation_paused(PerpOperation::OracleCircuitBreaker)?,
+        ErrorCode::OracleCircuitBreakerTripped,
+        "oracle circuit breaker tripped for perp market {}",
+        market_index
+    )?;
+
+    validate!(
+        !market.is_operation_paused(PerpOperation::Liquidation)?,
+        ErrorCode::MarketActionPaused,
+        "liquidations paused for perp market {}",
+        market_index
+    )?;
+
     let oracle_price = if market.status == MarketStatus::Settlement {
         market.expiry_price
     } else {
@@ -199,23 +247,636 @@ pub fn liquidate_perp(
     // burning lp shares = removing open bids/asks
     let lp_shares = user.perp_positions[position_index].lp_shares;
     if lp_shares > 0 {
+        let share_multiplier = user.perp_lp_tier.share_multiplier();
         burn_lp_shares(
             &mut user.perp_positions[position_index],
             perp_market_map.get_ref_mut(&market_index)?.deref_mut(),
             lp_shares,
             oracle_price,
+            share_multiplier,
         )?;
     }
 
-    // check if user exited liquidation territory
+    // check if user exited liquidation territory
+    let (intermediate_total_collateral, intermediate_margin_requirement_with_buffer) =
+        if !canceled_order_ids.is_empty() || lp_shares > 0 {
+            let (_, intermediate_total_collateral, intermediate_margin_requirement_plus_buffer, _) =
+                calculate_margin_requirement_and_total_collateral(
+                    user,
+                    perp_market_map,
+                    MarginRequirementType::Maintenance,
+                    spot_market_map,
+                    oracle_map,
+                    Some(liquidation_margin_buffer_ratio as u128),
+                )?;
+
+            let initial_margin_shortage =
+                calculate_margin_shortage(margin_requirement_plus_buffer, total_collateral)?;
+            let new_margin_shortage = calculate_margin_shortage(
+                intermediate_margin_requirement_plus_buffer,
+                intermediate_total_collateral,
+            )?;
+
+            margin_freed = initial_margin_shortage
+                .saturating_sub(new_margin_shortage)
+                .cast::<u64>()?;
+            user.increment_margin_freed(margin_freed)?;
+
+            if intermediate_total_collateral
+                >= intermediate_margin_requirement_plus_buffer.cast()?
+            {
+                emit!(LiquidationRecord {
+                    ts: now,
+                    liquidation_id,
+                    liquidation_type: LiquidationType::LiquidatePerp,
+                    user: *user_key,
+                    liquidator: *liquidator_key,
+                    margin_requirement,
+                    total_collateral,
+                    bankrupt: user.is_bankrupt(),
+                    canceled_order_ids,
+                    margin_freed,
+                    liquidate_perp: LiquidatePerpRecord {
+                        market_index,
+                        oracle_price,
+                        lp_shares,
+                        ..LiquidatePerpRecord::default()
+                    },
+                    ..LiquidationRecord::default()
+                });
+
+                user.exit_liquidation();
+                return Ok(());
+            }
+
+            (
+                intermediate_total_collateral,
+                intermediate_margin_requirement_plus_buffer,
+            )
+        } else {
+            (total_collateral, margin_requirement_plus_buffer)
+        };
+
+    if user.perp_positions[position_index].base_asset_amount == 0 {
+        msg!("User has no base asset amount");
+        return Ok(());
+    }
+
+    let liquidator_max_base_asset_amount = standardize_base_asset_amount(
+        liquidator_max_base_asset_amount,
+        perp_market_map.get_ref(&market_index)?.amm.order_step_size,
+    )?;
+
+    validate!(
+        liquidator_max_base_asset_amount != 0,
+        ErrorCode::InvalidBaseAssetAmountForLiquidatePerp,
+        "liquidator_max_base_asset_amount cant be 0"
+    )?;
+
+    let user_base_asset_amount = user.perp_positions[position_index]
+        .base_asset_amount
+        .unsigned_abs();
+
+    let worst_case_base_asset_amount =
+        user.perp_positions[position_index].worst_case_base_asset_amount()?;
+
+    let margin_ratio = perp_market_map.get_ref(&market_index)?.get_margin_ratio(
+        worst_case_base_asset_amount.unsigned_abs(),
+        MarginRequirementType::Maintenance,
+        user.is_high_leverage_mode,
+    )?;
+
+    let margin_ratio_with_buffer = margin_ratio.safe_add(liquidation_margin_buffer_ratio)?;
+
+    let margin_shortage = calculate_margin_shortage(
+        intermediate_margin_requirement_with_buffer,
+        intermediate_total_collateral,
+    )?;
+
+    let market = perp_market_map.get_ref(&market_index)?;
+    let liquidation_fee = calculate_dutch_auction_liquidation_fee(
+        market.liquidator_fee,
+        user,
+        slot,
+        liquidation_fee_ramp_duration,
+    )?;
+    let stale_fee = calculate_stale_liquidation_fee_bonus(
+        market.if_liquidation_fee,
+        user,
+        slot,
+        liquidation_fee_ramp_duration,
+        state.liquidation_stale_fee_escalation_duration.cast()?,
+    )?;
+    // backstop providers and stale account liquidators take part of the insurance fund's cut on top of the liquidator fee
+    let if_fee_to_liquidator = backstop_liquidation_fee_bonus
+        .safe_add(stale_fee)?
+        .min(market.if_liquidation_fee);
+    let liquidation_fee = liquidation_fee.safe_add(if_fee_to_liquidator)?;
+    let if_liquidation_fee = market.if_liquidation_fee.safe_sub(if_fee_to_liquidator)?;
+    let base_asset_amount_to_cover_margin_shortage = standardize_base_asset_amount_ceil(
+        calculate_base_asset_amount_to_cover_margin_shortage(
+            margin_shortage,
+            margin_ratio_with_buffer,
+            liquidation_fee,
+            if_liquidation_fee,
+            oracle_price,
+        )?,
+        market.amm.order_step_size,
+    )?;
+    drop(market);
+
+    // with a target buffer configured, liquidate exactly enough to bring the user back to
+    // maintenance + target buffer rather than a time-based percentage of the shortage
+    let (base_asset_amount, base_asset_amount_to_exit_liquidation) =
+        if liquidation_target_margin_buffer_ratio != 0 {
+            let (_, total_collateral_for_target, margin_requirement_plus_target_buffer, _) =
+                calculate_margin_requirement_and_total_collateral(
+                    user,
+                    perp_market_map,
+                    MarginRequirementType::Maintenance,
+                    spot_market_map,
+                    oracle_map,
+                    Some(liquidation_target_margin_buffer_ratio as u128),
+                )?;
+
+            let target_margin_shortage = calculate_margin_shortage(
+                margin_requirement_plus_target_buffer,
+                total_collateral_for_target,
+            )?;
+
+            let market = perp_market_map.get_ref(&market_index)?;
+            let base_asset_amount_to_reach_target_buffer = standardize_base_asset_amount_ceil(
+                calculate_base_asset_amount_to_cover_margin_shortage(
+                    target_margin_shortage,
+                    margin_ratio.safe_add(liquidation_target_margin_buffer_ratio)?,
+                    liquidation_fee,
+                    if_liquidation_fee,
+                    oracle_price,
+                )?,
+                market.amm.order_step_size,
+            )?;
+            drop(market);
+
+            if base_asset_amount_to_reach_target_buffer == 0 {
+                msg!("base_asset_amount_to_reach_target_buffer == 0");
+                user.exit_liquidation();
+                return Ok(());
+            }
+
+            let base_asset_amount = user_base_asset_amount
+                .min(liquidator_max_base_asset_amount)
+                .min(base_asset_amount_to_reach_target_buffer);
+
+            (base_asset_amount, base_asset_amount_to_reach_target_buffer)
+        } else {
+            let max_pct_allowed = calculate_max_pct_to_liquidate(
+                user,
+                margin_shortage,
+                slot,
+                initial_pct_to_liquidate,
+                liquidation_duration,
+            )?;
+            let max_base_asset_amount_allowed_to_be_transferred =
+                base_asset_amount_to_cover_margin_shortage
+                    .cast::<u128>()?
+                    .saturating_mul(max_pct_allowed)
+                    .safe_div(LIQUIDATION_PCT_PRECISION)?
+                    .cast::<u64>()?;
+
+            if max_base_asset_amount_allowed_to_be_transferred == 0 {
+                msg!("max_base_asset_amount_allowed_to_be_transferred == 0");
+                return Ok(());
+            }
+
+            let base_asset_value = calculate_base_asset_value_with_oracle_price(
+                user_base_asset_amount.cast()?,
+                oracle_price,
+            )?
+            .cast::<u64>()?;
+
+            // if position is less than $10, liquidator can liq all of it
+            let min_base_asset_amount = if base_asset_value > 10 * QUOTE_PRECISION_U64 {
+                0_u64
+            } else {
+                user_base_asset_amount
+            };
+
+            let base_asset_amount = user_base_asset_amount
+                .min(liquidator_max_base_asset_amount)
+                .min(max_base_asset_amount_allowed_to_be_transferred.max(min_base_asset_amount));
+
+            (
+                base_asset_amount,
+                base_asset_amount_to_cover_margin_shortage,
+            )
+        };
+
+    let base_asset_amount = standardize_base_asset_amount_ceil(
+        base_asset_amount,
+        perp_market_map.get_ref(&market_index)?.amm.order_step_size,
+    )?;
+
+    // Make sure liquidator enters at better than limit price
+    if let Some(limit_price) = limit_price {
+        match user.perp_positions[position_index].get_direction() {
+            PositionDirection::Long => validate!(
+                oracle_price <= limit_price.cast()?,
+                ErrorCode::LiquidationDoesntSatisfyLimitPrice,
+                "limit price ({}) > oracle price ({})",
+                limit_price,
+                oracle_price
+            )?,
+            PositionDirection::Short => validate!(
+                oracle_price >= limit_price.cast()?,
+                ErrorCode::LiquidationDoesntSatisfyLimitPrice,
+                "limit price ({}) < oracle price ({})",
+                limit_price,
+                oracle_price
+            )?,
+        }
+    }
+
+    let base_asset_value =
+        calculate_base_asset_value_with_oracle_price(base_asset_amount.cast()?, oracle_price)?
+            .cast::<u64>()?;
+
+    let liquidator_fee = -base_asset_value
+        .cast::<u128>()?
+        .safe_mul(liquidation_fee.cast()?)?
+        .safe_div(LIQUIDATION_FEE_PRECISION_U128)?
+        .cast::<i64>()?;
+
+    let if_fee = -base_asset_value
+        .cast::<u128>()?
+        .safe_mul(if_liquidation_fee.cast()?)?
+        .safe_div(LIQUIDATION_FEE_PRECISION_U128)?
+        .cast::<i64>()?;
+
+    user_stats.update_taker_volume_30d(base_asset_value, now)?;
+    liquidator_stats.update_maker_volume_30d(base_asset_value, now)?;
+
+    let user_position_delta = get_position_delta_for_fill(
+        base_asset_amount,
+        base_asset_value,
+        user.perp_positions[position_index].get_direction_to_close(),
+    )?;
+
+    let liquidator_position_delta = get_position_delta_for_fill(
+        base_asset_amount,
+        base_asset_value,
+        user.perp_positions[position_index].get_direction(),
+    )?;
+
+    let (
+        user_existing_position_direction,
+        user_position_direction_to_close,
+        liquidator_existing_position_direction,
+    ) = {
+        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+
+        let user_position = user.get_perp_position_mut(market_index)?;
+        let user_existing_position_direction = user_position.get_direction();
+        let user_position_direction_to_close = user_position.get_direction_to_close();
+        update_position_and_market(user_position, &mut market, &user_position_delta)?;
+        update_quote_asset_and_break_even_amount(user_position, &mut market, liquidator_fee)?;
+        update_quote_asset_and_break_even_amount(user_position, &mut market, if_fee)?;
+
+        validate!(
+            is_multiple_of_step_size(
+                user_position.base_asset_amount.unsigned_abs(),
+                market.amm.order_step_size
+            )?,
+            ErrorCode::InvalidPerpPosition,
+            "base asset amount {} step size {}",
+            user_position.base_asset_amount,
+            market.amm.order_step_size
+        )?;
+
+        let liquidator_position = liquidator.force_get_perp_position_mut(market_index)?;
+        let liquidator_existing_position_direction = liquidator_position.get_direction();
+        update_position_and_market(liquidator_position, &mut market, &liquidator_position_delta)?;
+        update_quote_asset_and_break_even_amount(
+            liquidator_position,
+            &mut market,
+            -liquidator_fee,
+        )?;
+
+        validate!(
+            is_multiple_of_step_size(
+                liquidator_position.base_asset_amount.unsigned_abs(),
+                market.amm.order_step_size
+            )?,
+            ErrorCode::InvalidPerpPosition,
+            "base asset amount {} step size {}",
+            liquidator_position.base_asset_amount,
+            market.amm.order_step_size
+        )?;
+
+        market.amm.total_liquidation_fee = market
+            .amm
+            .total_liquidation_fee
+            .safe_add(if_fee.unsigned_abs().cast()?)?;
+
+        (
+            user_existing_position_direction,
+            user_position_direction_to_close,
+            liquidator_existing_position_direction,
+        )
+    };
+
+    let margin_freed_for_perp_position = calculate_margin_freed(
+        user,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        liquidation_margin_buffer_ratio,
+        margin_shortage,
+    )?;
+    margin_freed = margin_freed.safe_add(margin_freed_for_perp_position)?;
+    user.increment_margin_freed(margin_freed_for_perp_position)?;
+
+    if base_asset_amount >= base_asset_amount_to_exit_liquidation {
+        user.exit_liquidation();
+    } else if is_user_bankrupt(user) {
+        user.enter_bankruptcy();
+    }
+
+    let liquidator_meets_initial_margin_requirement =
+        meets_initial_margin_requirement(liquidator, perp_market_map, spot_market_map, oracle_map)?
+            && meets_isolated_perp_position_margin_requirement(
+                liquidator,
+                market_index,
+                perp_market_map,
+                MarginRequirementType::Initial,
+                oracle_map,
+                None,
+            )?;
+
+    validate!(
+        liquidator_meets_initial_margin_requirement,
+        ErrorCode::InsufficientCollateral,
+        "Liquidator doesnt have enough collateral to take over perp position"
+    )?;
+
+    // get ids for order fills
+    let user_order_id = get_then_update_id!(user, next_order_id);
+    let liquidator_order_id = get_then_update_id!(liquidator, next_order_id);
+    let fill_record_id = {
+        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+        get_then_update_id!(market, next_fill_record_id)
+    };
+
+    let user_order = Order {
+        slot,
+        base_asset_amount,
+        order_id: user_order_id,
+        market_index,
+        status: OrderStatus::Open,
+        order_type: OrderType::Market,
+        market_type: MarketType::Perp,
+        direction: user_position_direction_to_close,
+        existing_position_direction: user_existing_position_direction,
+        ..Order::default()
+    };
+
+    emit!(OrderRecord {
+        ts: now,
+        user: *user_key,
+        order: user_order
+    });
+
+    let liquidator_order = Order {
+        slot,
+        price: if let Some(price) = limit_price {
+            price
+        } else {
+            0
+        },
+        base_asset_amount,
+        order_id: liquidator_order_id,
+        market_index,
+        status: OrderStatus::Open,
+        order_type: if limit_price.is_some() {
+            OrderType::Limit
+        } else {
+            OrderType::Market
+        },
+        market_type: MarketType::Perp,
+        direction: user_existing_position_direction,
+        existing_position_direction: liquidator_existing_position_direction,
+        ..Order::default()
+    };
+
+    emit!(OrderRecord {
+        ts: now,
+        user: *liquidator_key,
+        order: liquidator_order
+    });
+
+    let fill_record = OrderActionRecord {
+        ts: now,
+        action: OrderAction::Fill,
+        action_explanation: OrderActionExplanation::Liquidation,
+        market_index,
+        market_type: MarketType::Perp,
+        filler: None,
+        filler_reward: None,
+        fill_record_id: Some(fill_record_id),
+        base_asset_amount_filled: Some(base_asset_amount),
+        quote_asset_amount_filled: Some(base_asset_value),
+        taker_fee: Some(
+            liquidator_fee
+                .unsigned_abs()
+                .safe_add(if_fee.unsigned_abs())?,
+        ),
+        maker_fee: Some(liquidator_fee),
+        referrer_reward: None,
+        quote_asset_amount_surplus: None,
+        spot_fulfillment_method_fee: None,
+        taker: Some(*user_key),
+        taker_order_id: Some(user_order_id),
+        taker_order_direction: Some(user_position_direction_to_close),
+        taker_order_base_asset_amount: Some(base_asset_amount),
+        taker_order_cumulative_base_asset_amount_filled: Some(base_asset_amount),
+        taker_order_cumulative_quote_asset_amount_filled: Some(base_asset_value),
+        maker: Some(*liquidator_key),
+        maker_order_id: Some(liquidator_order_id),
+        maker_order_direction: Some(user_existing_position_direction),
+        maker_order_base_asset_amount: Some(base_asset_amount),
+        maker_order_cumulative_base_asset_amount_filled: Some(base_asset_amount),
+        maker_order_cumulative_quote_asset_amount_filled: Some(base_asset_value),
+        oracle_price,
+    };
+    emit!(fill_record);
+
+    emit!(LiquidationRecord {
+        ts: now,
+        liquidation_id,
+        liquidation_type: LiquidationType::LiquidatePerp,
+        user: *user_key,
+        liquidator: *liquidator_key,
+        margin_requirement,
+        total_collateral,
+        bankrupt: user.is_bankrupt(),
+        canceled_order_ids,
+        margin_freed,
+        liquidate_perp: LiquidatePerpRecord {
+            market_index,
+            oracle_price,
+            base_asset_amount: user_position_delta.base_asset_amount,
+            quote_asset_amount: user_position_delta.quote_asset_amount,
+            lp_shares,
+            user_order_id,
+            liquidator_order_id,
+            fill_record_id,
+            liquidator_fee: liquidator_fee.abs().cast()?,
+            if_fee: if_fee.abs().cast()?,
+        },
+        ..LiquidationRecord::default()
+    });
+
+    Ok(())
+}
+
+/// Liquidates a perp position in isolated margin mode. Only the position's dedicated collateral
+/// backs it, so the rest of the user's account is never touched and the user is not put into
+/// liquidation status.
+pub fn liquidate_isolated_perp(
+    market_index: u16,
+    liquidator_max_base_asset_amount: u64,
+    limit_price: Option<u64>,
+    user: &mut User,
+    user_key: &Pubkey,
+    user_stats: &mut UserStats,
+    liquidator: &mut User,
+    liquidator_key: &Pubkey,
+    liquidator_stats: &mut UserStats,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    slot: u64,
+    now: i64,
+    state: &State,
+) -> DriftResult {
+    let liquidation_margin_buffer_ratio = state.liquidation_margin_buffer_ratio;
+
+    validate!(
+        !user.is_bankrupt(),
+        ErrorCode::UserBankrupt,
+        "user bankrupt",
+    )?;
+
+    validate!(
+        !liquidator.is_bankrupt(),
+        ErrorCode::UserBankrupt,
+        "liquidator bankrupt",
+    )?;
+
+    settle_funding_payment(
+        user,
+        user_key,
+        perp_market_map.get_ref_mut(&market_index)?.deref_mut(),
+        now,
+    )?;
+
+    settle_funding_payment(
+        liquidator,
+        liquidator_key,
+        perp_market_map.get_ref_mut(&market_index)?.deref_mut(),
+        now,
+    )?;
+
+    let (margin_requirement, total_collateral, margin_requirement_plus_buffer, _) =
+        calculate_isolated_perp_position_margin_requirement_and_total_collateral(
+            user,
+            market_index,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            oracle_map,
+            Some(liquidation_margin_buffer_ratio as u128),
+        )?;
+
+    if total_collateral >= margin_requirement.cast()? {
+        return Err(ErrorCode::SufficientCollateral);
+    }
+
+    liquidator
+        .force_get_perp_position_mut(market_index)
+        .map_err(|e| {
+            msg!(
+                "Liquidator has no available positions to take on perp position in market {}",
+                market_index
+            );
+            e
+        })?;
+
+    let liquidation_id = get_then_update_id!(user, next_liquidation_id);
+    let mut margin_freed = 0_u64;
+
+    let position_index = get_position_index(&user.perp_positions, market_index)?;
+    validate!(
+        user.perp_positions[position_index].is_open_position()
+            || user.perp_positions[position_index].has_open_order(),
+        ErrorCode::PositionDoesntHaveOpenPositionOrOrders
+    )?;
+
+    let canceled_order_ids = orders::cancel_orders(
+        user,
+        user_key,
+        Some(liquidator_key),
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+        OrderActionExplanation::Liquidation,
+        Some(MarketType::Perp),
+        Some(market_index),
+        None,
+    )?;
+
+    let mut market = perp_market_map.get_ref_mut(&market_index)?;
+    let oracle_price_data = oracle_map.get_price_data(&market.amm.oracle)?;
+
+    update_amm_and_check_validity(
+        &mut market,
+        oracle_price_data,
+        state,
+        now,
+        slot,
+        Some(DriftAction::Liquidate),
+    )?;
+
+    validate!(
+        !market.is_operation_paused(PerpOperation::OracleCircuitBreaker)?,
+        ErrorCode::OracleCircuitBreakerTripped,
+        "oracle circuit breaker tripped for perp market {}",
+        market_index
+    )?;
+
+    validate!(
+        !market.is_operation_paused(PerpOperation::Liquidation)?,
+        ErrorCode::MarketActionPaused,
+        "liquidations paused for perp market {}",
+        market_index
+    )?;
+
+    let oracle_price = if market.status == MarketStatus::Settlement {
+        market.expiry_price
+    } else {
+        oracle_price_data.price
+    };
+
+    drop(market);
+
     let (intermediate_total_collateral, intermediate_margin_requirement_with_buffer) =
-        if !canceled_order_ids.is_empty() || lp_shares > 0 {
+        if !canceled_order_ids.is_empty() {
             let (_, intermediate_total_collateral, intermediate_margin_requirement_plus_buffer, _) =
-                calculate_margin_requirement_and_total_collateral(
+                calculate_isolated_perp_position_margin_requirement_and_total_collateral(
                     user,
+                    market_index,
                     perp_market_map,
                     MarginRequirementType::Maintenance,
-                    spot_market_map,
                     oracle_map,
                     Some(liquidation_margin_buffer_ratio as u128),
                 )?;
@@ -230,7 +891,6 @@ pub fn liquidate_perp(
             margin_freed = initial_margin_shortage
                 .saturating_sub(new_margin_shortage)
                 .cast::<u64>()?;
-            user.increment_margin_freed(margin_freed)?;
 
             if intermediate_total_collateral
                 >= intermediate_margin_requirement_plus_buffer.cast()?
@@ -243,19 +903,17 @@ pub fn liquidate_perp(
                     liquidator: *liquidator_key,
                     margin_requirement,
                     total_collateral,
-                    bankrupt: user.is_bankrupt(),
+                    bankrupt: false,
                     canceled_order_ids,
                     margin_freed,
                     liquidate_perp: LiquidatePerpRecord {
                         market_index,
                         oracle_price,
-                        lp_shares,
                         ..LiquidatePerpRecord::default()
                     },
                     ..LiquidationRecord::default()
                 });
 
-                user.exit_liquidation();
                 return Ok(());
             }
 
@@ -293,6 +951,7 @@ pub fn liquidate_perp(
     let margin_ratio = perp_market_map.get_ref(&market_index)?.get_margin_ratio(
         worst_case_base_asset_amount.unsigned_abs(),
         MarginRequirementType::Maintenance,
+        user.is_high_leverage_mode,
     )?;
 
     let margin_ratio_with_buffer = margin_ratio.safe_add(liquidation_margin_buffer_ratio)?;
@@ -317,25 +976,6 @@ pub fn liquidate_perp(
     )?;
     drop(market);
 
-    let max_pct_allowed = calculate_max_pct_to_liquidate(
-        user,
-        margin_shortage,
-        slot,
-        initial_pct_to_liquidate,
-        liquidation_duration,
-    )?;
-    let max_base_asset_amount_allowed_to_be_transferred =
-        base_asset_amount_to_cover_margin_shortage
-            .cast::<u128>()?
-            .saturating_mul(max_pct_allowed)
-            .safe_div(LIQUIDATION_PCT_PRECISION)?
-            .cast::<u64>()?;
-
-    if max_base_asset_amount_allowed_to_be_transferred == 0 {
-        msg!("max_base_asset_amount_allowed_to_be_transferred == 0");
-        return Ok(());
-    }
-
     let base_asset_value =
         calculate_base_asset_value_with_oracle_price(user_base_asset_amount.cast()?, oracle_price)?
             .cast::<u64>()?;
@@ -349,7 +989,7 @@ pub fn liquidate_perp(
 
     let base_asset_amount = user_base_asset_amount
         .min(liquidator_max_base_asset_amount)
-        .min(max_base_asset_amount_allowed_to_be_transferred.max(min_base_asset_amount));
+        .min(base_asset_amount_to_cover_margin_shortage.max(min_base_asset_amount));
     let base_asset_amount = standardize_base_asset_amount_ceil(
         base_asset_amount,
         perp_market_map.get_ref(&market_index)?.amm.order_step_size,
@@ -431,6 +1071,29 @@ pub fn liquidate_perp(
             market.amm.order_step_size
         )?;
 
+        // once the position is closed, its collateral absorbs the realized loss
+        if user_position.base_asset_amount == 0 && user_position.quote_asset_amount < 0 {
+            let collateral_payment = user_position
+                .isolated_collateral
+                .min(user_position.quote_asset_amount.unsigned_abs());
+
+            if collateral_payment > 0 {
+                let spot_market = &mut spot_market_map.get_ref_mut(&QUOTE_SPOT_MARKET_INDEX)?;
+                update_spot_balances(
+                    collateral_payment.cast()?,
+                    &SpotBalanceType::Deposit,
+                    spot_market,
+                    &mut market.pnl_pool,
+                    false,
+                )?;
+
+                user_position.isolated_collateral = user_position
+                    .isolated_collateral
+                    .safe_sub(collateral_payment)?;
+                update_quote_asset_amount(user_position, &mut market, collateral_payment.cast()?)?;
+            }
+        }
+
         let liquidator_position = liquidator.force_get_perp_position_mut(market_index)?;
         let liquidator_existing_position_direction = liquidator_position.get_direction();
         update_position_and_market(liquidator_position, &mut market, &liquidator_position_delta)?;
@@ -463,25 +1126,33 @@ pub fn liquidate_perp(
         )
     };
 
-    let margin_freed_for_perp_position = calculate_margin_freed(
-        user,
-        perp_market_map,
-        spot_market_map,
-        oracle_map,
-        liquidation_margin_buffer_ratio,
-        margin_shortage,
+    let (_, total_collateral_after, margin_requirement_plus_buffer_after, _) =
+        calculate_isolated_perp_position_margin_requirement_and_total_collateral(
+            user,
+            market_index,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            oracle_map,
+            Some(liquidation_margin_buffer_ratio as u128),
+        )?;
+    let new_margin_shortage =
+        calculate_margin_shortage(margin_requirement_plus_buffer_after, total_collateral_after)?;
+    margin_freed = margin_freed.safe_add(
+        margin_shortage
+            .saturating_sub(new_margin_shortage)
+            .cast::<u64>()?,
     )?;
-    margin_freed = margin_freed.safe_add(margin_freed_for_perp_position)?;
-    user.increment_margin_freed(margin_freed_for_perp_position)?;
-
-    if base_asset_amount >= base_asset_amount_to_cover_margin_shortage {
-        user.exit_liquidation();
-    } else if is_user_bankrupt(user) {
-        user.enter_bankruptcy();
-    }
 
     let liquidator_meets_initial_margin_requirement =
-        meets_initial_margin_requirement(liquidator, perp_market_map, spot_market_map, oracle_map)?;
+        meets_initial_margin_requirement(liquidator, perp_market_map, spot_market_map, oracle_map)?
+            && meets_isolated_perp_position_margin_requirement(
+                liquidator,
+                market_index,
+                perp_market_map,
+                MarginRequirementType::Initial,
+                oracle_map,
+                None,
+            )?;
 
     validate!(
         liquidator_meets_initial_margin_requirement,
@@ -588,7 +1259,7 @@ pub fn liquidate_perp(
         liquidator: *liquidator_key,
         margin_requirement,
         total_collateral,
-        bankrupt: user.is_bankrupt(),
+        bankrupt: user.perp_positions[position_index].is_isolated_bankrupt(),
         canceled_order_ids,
         margin_freed,
         liquidate_perp: LiquidatePerpRecord {
@@ -596,7 +1267,7 @@ pub fn liquidate_perp(
             oracle_price,
             base_asset_amount: user_position_delta.base_asset_amount,
             quote_asset_amount: user_position_delta.quote_asset_amount,
-            lp_shares,
+            lp_shares: 0,
             user_order_id,
             liquidator_order_id,
             fill_record_id,
@@ -626,6 +1297,7 @@ pub fn liquidate_spot(
     liquidation_margin_buffer_ratio: u32,
     initial_pct_to_liquidate: u128,
     liquidation_duration: u128,
+    liquidation_fee_ramp_duration: u128,
 ) -> DriftResult {
     validate!(
         !user.is_bankrupt(),
@@ -684,6 +1356,13 @@ pub fn liquidate_spot(
             Some(DriftAction::Liquidate),
         )?;
 
+        validate!(
+            !asset_market.is_operation_paused(SpotOperation::Liquidation)?,
+            ErrorCode::MarketActionPaused,
+            "liquidations paused for spot market {}",
+            asset_market.market_index
+        )?;
+
         let spot_deposit_position = user.get_spot_position(asset_market_index)?;
 
         validate!(
@@ -708,7 +1387,12 @@ pub fn liquidate_spot(
             asset_market.decimals,
             asset_market.maintenance_asset_weight,
             calculate_liquidation_multiplier(
-                asset_market.liquidator_fee,
+                calculate_dutch_auction_liquidation_fee(
+                    asset_market.liquidator_fee,
+                    user,
+                    slot,
+                    liquidation_fee_ramp_duration,
+                )?,
                 LiquidationMultiplierType::Premium,
             )?,
         )
@@ -734,6 +1418,13 @@ pub fn liquidate_spot(
             Some(DriftAction::Liquidate),
         )?;
 
+        validate!(
+            !liability_market.is_operation_paused(SpotOperation::Liquidation)?,
+            ErrorCode::MarketActionPaused,
+            "liquidations paused for spot market {}",
+            liability_market.market_index
+        )?;
+
         let spot_position = user.get_spot_position(liability_market_index)?;
 
         validate!(
@@ -759,7 +1450,12 @@ pub fn liquidate_spot(
             liability_market.decimals,
             liability_market.maintenance_liability_weight,
             calculate_liquidation_multiplier(
-                liability_market.liquidator_fee,
+                calculate_dutch_auction_liquidation_fee(
+                    liability_market.liquidator_fee,
+                    user,
+                    slot,
+                    liquidation_fee_ramp_duration,
+                )?,
                 LiquidationMultiplierType::Discount,
             )?,
             liability_market.if_liquidation_fee,
@@ -1086,6 +1782,7 @@ pub fn liquidate_borrow_for_perp_pnl(
     liquidation_margin_buffer_ratio: u32,
     initial_pct_to_liquidate: u128,
     liquidation_duration: u128,
+    liquidation_fee_ramp_duration: u128,
 ) -> DriftResult {
     // liquidator takes over a user borrow in exchange for that user's positive perpetual pnl
     // can only be done once a user's perpetual position size is 0
@@ -1111,6 +1808,13 @@ pub fn liquidate_borrow_for_perp_pnl(
         e
     })?;
 
+    validate!(
+        !user.get_perp_position(perp_market_index)?.is_isolated,
+        ErrorCode::InvalidIsolatedPerpPosition,
+        "perp position for market {} is isolated",
+        perp_market_index
+    )?;
+
     user.get_spot_position(liability_market_index)
         .map_err(|_| {
             msg!(
@@ -1178,6 +1882,13 @@ pub fn liquidate_borrow_for_perp_pnl(
 
         let market = perp_market_map.get_ref(&perp_market_index)?;
 
+        validate!(
+            !market.is_operation_paused(PerpOperation::Liquidation)?,
+            ErrorCode::MarketActionPaused,
+            "liquidations paused for perp market {}",
+            perp_market_index
+        )?;
+
         let pnl_asset_weight =
             market.get_unrealized_asset_weight(pnl, MarginRequirementType::Maintenance)?;
 
@@ -1187,7 +1898,12 @@ pub fn liquidate_borrow_for_perp_pnl(
             6_u32,
             pnl_asset_weight,
             calculate_liquidation_multiplier(
-                market.liquidator_fee,
+                calculate_dutch_auction_liquidation_fee(
+                    market.liquidator_fee,
+                    user,
+                    slot,
+                    liquidation_fee_ramp_duration,
+                )?,
                 LiquidationMultiplierType::Premium,
             )?,
         )
@@ -1212,6 +1928,13 @@ pub fn liquidate_borrow_for_perp_pnl(
             Some(DriftAction::Liquidate),
         )?;
 
+        validate!(
+            !liability_market.is_operation_paused(SpotOperation::Liquidation)?,
+            ErrorCode::MarketActionPaused,
+            "liquidations paused for spot market {}",
+            liability_market.market_index
+        )?;
+
         let spot_position = user.get_spot_position(liability_market_index)?;
 
         validate!(
@@ -1235,7 +1958,12 @@ pub fn liquidate_borrow_for_perp_pnl(
             liability_market.decimals,
             liability_market.maintenance_liability_weight,
             calculate_liquidation_multiplier(
-                liability_market.liquidator_fee,
+                calculate_dutch_auction_liquidation_fee(
+                    liability_market.liquidator_fee,
+                    user,
+                    slot,
+                    liquidation_fee_ramp_duration,
+                )?,
                 LiquidationMultiplierType::Discount,
             )?,
         )
@@ -1548,6 +2276,7 @@ pub fn liquidate_perp_pnl_for_deposit(
     liquidation_margin_buffer_ratio: u32,
     initial_pct_to_liquidate: u128,
     liquidation_duration: u128,
+    liquidation_fee_ramp_duration: u128,
 ) -> DriftResult {
     // liquidator takes over remaining negative perpetual pnl in exchange for a user deposit
     // can only be done once the perpetual position's size is 0
@@ -1574,6 +2303,13 @@ pub fn liquidate_perp_pnl_for_deposit(
         e
     })?;
 
+    validate!(
+        !user.get_perp_position(perp_market_index)?.is_isolated,
+        ErrorCode::InvalidIsolatedPerpPosition,
+        "perp position for market {} is isolated",
+        perp_market_index
+    )?;
+
     user.get_spot_position(asset_market_index).map_err(|_| {
         msg!(
             "User does not have a spot balance for asset market {}",
@@ -1630,6 +2366,13 @@ pub fn liquidate_perp_pnl_for_deposit(
             Some(DriftAction::Liquidate),
         )?;
 
+        validate!(
+            !asset_market.is_operation_paused(SpotOperation::Liquidation)?,
+            ErrorCode::MarketActionPaused,
+            "liquidations paused for spot market {}",
+            asset_market.market_index
+        )?;
+
         let token_price = asset_price_data.price;
         let spot_position = user.get_spot_position(asset_market_index)?;
 
@@ -1655,7 +2398,12 @@ pub fn liquidate_perp_pnl_for_deposit(
             asset_market.decimals,
             asset_market.maintenance_asset_weight,
             calculate_liquidation_multiplier(
-                asset_market.liquidator_fee,
+                calculate_dutch_auction_liquidation_fee(
+                    asset_market.liquidator_fee,
+                    user,
+                    slot,
+                    liquidation_fee_ramp_duration,
+                )?,
                 LiquidationMultiplierType::Premium,
             )?,
         )
@@ -1698,6 +2446,13 @@ pub fn liquidate_perp_pnl_for_deposit(
 
         let market = perp_market_map.get_ref(&perp_market_index)?;
 
+        validate!(
+            !market.is_operation_paused(PerpOperation::Liquidation)?,
+            ErrorCode::MarketActionPaused,
+            "liquidations paused for perp market {}",
+            perp_market_index
+        )?;
+
         (
             unsettled_pnl.unsigned_abs(),
             quote_price,
@@ -1705,7 +2460,12 @@ pub fn liquidate_perp_pnl_for_deposit(
             6_u32,
             SPOT_WEIGHT_PRECISION,
             calculate_liquidation_multiplier(
-                market.liquidator_fee,
+                calculate_dutch_auction_liquidation_fee(
+                    market.liquidator_fee,
+                    user,
+                    slot,
+                    liquidation_fee_ramp_duration,
+                )?,
                 LiquidationMultiplierType::Discount,
             )?,
         )
@@ -2030,15 +2790,22 @@ pub fn resolve_perp_bankruptcy(
     now: i64,
     insurance_fund_vault_balance: u64,
 ) -> DriftResult<u64> {
-    if !user.is_bankrupt() && is_user_bankrupt(user) {
-        user.enter_bankruptcy();
-    }
+    // an isolated position can go bankrupt on its own without the rest of the account
+    let is_isolated_bankrupt = user
+        .get_perp_position(market_index)
+        .map_or(false, |position| position.is_isolated_bankrupt());
+
+    if !is_isolated_bankrupt {
+        if !user.is_bankrupt() && is_user_bankrupt(user) {
+            user.enter_bankruptcy();
+        }
 
-    validate!(
-        user.is_bankrupt(),
-        ErrorCode::UserNotBankrupt,
-        "user not bankrupt",
-    )?;
+        validate!(
+            user.is_bankrupt(),
+            ErrorCode::UserNotBankrupt,
+            "user not bankrupt",
+        )?;
+    }
 
     validate!(
         !liquidator.is_being_liquidated(),
@@ -2086,11 +2853,8 @@ pub fn resolve_perp_bankruptcy(
 
     let if_payment = {
         let mut perp_market = perp_market_map.get_ref_mut(&market_index)?;
-        let max_insurance_withdraw = perp_market
-            .insurance_claim
-            .quote_max_insurance
-            .safe_sub(perp_market.insurance_claim.quote_settled_insurance)?
-            .cast::<u128>()?;
+        let max_insurance_withdraw =
+            perp_market.get_max_insurance_fund_draw(insurance_fund_vault_balance)?;
 
         let if_payment = loss
             .unsigned_abs()
@@ -2201,7 +2965,7 @@ pub fn resolve_perp_bankruptcy(
     }
 
     // exit bankruptcy
-    if !is_user_bankrupt(user) {
+    if user.is_bankrupt() && !is_user_bankrupt(user) {
         user.exit_bankruptcy();
     }
 
@@ -2230,6 +2994,329 @@ pub fn resolve_perp_bankruptcy(
     if_payment.cast()
 }
 
+/// Closes an underwater position against the opposing positions in remaining accounts at its
+/// bankruptcy price, highest auto deleverage score first, once the insurance fund and fee pool
+/// cant cover the deficit. Returns the base asset amount deleveraged.
+pub fn auto_deleverage_perp(
+    market_index: u16,
+    user: &mut User,
+    user_key: &Pubkey,
+    keeper_key: &Pubkey,
+    counterparty_map: &UserMap,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    slot: u64,
+    now: i64,
+    state: &State,
+    insurance_fund_vault_balance: u64,
+) -> DriftResult<u64> {
+    validate!(
+        perp_market_map
+            .get_ref(&market_index)?
+            .auto_deleverage_enabled,
+        ErrorCode::AutoDeleverageNotNeeded,
+        "auto deleverage not enabled for perp market {}",
+        market_index
+    )?;
+
+    settle_funding_payment(
+        user,
+        user_key,
+        perp_market_map.get_ref_mut(&market_index)?.deref_mut(),
+        now,
+    )?;
+
+    let position_index = get_position_index(&user.perp_positions, market_index).map_err(|e| {
+        msg!(
+            "User does not have a position for perp market {}",
+            market_index
+        );
+        e
+    })?;
+
+    validate!(
+        user.perp_positions[position_index].base_asset_amount != 0
+            && !user.perp_positions[position_index].is_lp(),
+        ErrorCode::InvalidPerpPositionToAutoDeleverage,
+        "position must have base and no lp shares"
+    )?;
+
+    let oracle_price = {
+        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+        let oracle_price_data = oracle_map.get_price_data(&market.amm.oracle)?;
+
+        update_amm_and_check_validity(
+            &mut market,
+            oracle_price_data,
+            state,
+            now,
+            slot,
+            Some(DriftAction::Liquidate),
+        )?;
+
+        oracle_price_data.price
+    };
+
+    let (margin_requirement, total_collateral) = calculate_auto_deleverage_margin(
+        user,
+        market_index,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+    )?;
+
+    validate!(
+        total_collateral < 0,
+        ErrorCode::AutoDeleverageNotNeeded,
+        "user total collateral {} is not negative",
+        total_collateral
+    )?;
+
+    let deficit = total_collateral.unsigned_abs();
+
+    let max_loss_covered = {
+        let mut perp_market = perp_market_map.get_ref_mut(&market_index)?;
+        let mut quote_spot_market = spot_market_map.get_ref_mut(&QUOTE_SPOT_MARKET_INDEX)?;
+
+        let max_insurance_fund_draw = perp_market
+            .get_max_insurance_fund_draw(insurance_fund_vault_balance)?
+            .min(insurance_fund_vault_balance.saturating_sub(1).cast()?);
+
+        let fee_pool_tokens = get_fee_pool_tokens(&mut perp_market, &mut quote_spot_market)?;
+
+        max_insurance_fund_draw.safe_add(fee_pool_tokens.max(0).unsigned_abs())?
+    };
+
+    validate!(
+        deficit > max_loss_covered,
+        ErrorCode::AutoDeleverageNotNeeded,
+        "deficit {} can be covered by insurance fund and fee pool {}, resolve bankruptcy instead",
+        deficit,
+        max_loss_covered
+    )?;
+
+    let user_base_asset_amount = user.perp_positions[position_index].base_asset_amount;
+    let user_existing_position_direction = user.perp_positions[position_index].get_direction();
+    let user_position_direction_to_close =
+        user.perp_positions[position_index].get_direction_to_close();
+
+    let bankruptcy_price =
+        calculate_auto_deleverage_price(oracle_price, user_base_asset_amount, deficit)?;
+
+    let mut counterparties: Vec<(u128, i128, Pubkey)> =
+        Vec::with_capacity(counterparty_map.0.len());
+    for counterparty_key in counterparty_map.0.keys() {
+        validate!(
+            counterparty_key != user_key,
+            ErrorCode::InvalidPerpPositionToAutoDeleverage,
+            "user cant be its own counterparty"
+        )?;
+
+        let mut counterparty = counterparty_map.get_ref_mut(counterparty_key)?;
+
+        if counterparty.is_being_liquidated() || counterparty.is_bankrupt() {
+            continue;
+        }
+
+        settle_funding_payment(
+            &mut counterparty,
+            counterparty_key,
+            perp_market_map.get_ref_mut(&market_index)?.deref_mut(),
+            now,
+        )?;
+
+        let counterparty_position = match counterparty.get_perp_position(market_index) {
+            Ok(position) => *position,
+            Err(_) => continue,
+        };
+
+        if counterparty_position.is_lp()
+            || counterparty_position.base_asset_amount == 0
+            || counterparty_position.get_direction() != user_position_direction_to_close
+        {
+            continue;
+        }
+
+        let (base_asset_value, unrealized_pnl) =
+            calculate_base_asset_value_and_pnl_with_oracle_price(
+                &counterparty_position,
+                oracle_price,
+            )?;
+
+        if unrealized_pnl <= 0 {
+            continue;
+        }
+
+        let (_, counterparty_total_collateral) = calculate_auto_deleverage_margin(
+            &counterparty,
+            market_index,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+        )?;
+
+        let score = calculate_auto_deleverage_score(
+            unrealized_pnl,
+            counterparty_position.quote_entry_amount,
+            base_asset_value,
+            counterparty_total_collateral,
+        )?;
+
+        counterparties.push((score, unrealized_pnl, *counterparty_key));
+    }
+
+    validate!(
+        !counterparties.is_empty(),
+        ErrorCode::InvalidPerpPositionToAutoDeleverage,
+        "no profitable opposing positions to auto deleverage against"
+    )?;
+
+    counterparties.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let liquidation_id = if user.is_being_liquidated() {
+        user.next_liquidation_id.safe_sub(1)?
+    } else {
+        0
+    };
+
+    let mut base_asset_amount_deleveraged = 0_u64;
+    for (score, counterparty_pnl, counterparty_key) in counterparties {
+        let base_asset_amount_remaining = user.perp_positions[position_index]
+            .base_asset_amount
+            .unsigned_abs();
+
+        if base_asset_amount_remaining == 0 {
+            break;
+        }
+
+        let mut counterparty = counterparty_map.get_ref_mut(&counterparty_key)?;
+
+        let base_asset_amount = counterparty
+            .get_perp_position(market_index)?
+            .base_asset_amount
+            .unsigned_abs()
+            .min(base_asset_amount_remaining);
+
+        let quote_asset_amount = calculate_base_asset_value_with_oracle_price(
+            base_asset_amount.cast()?,
+            bankruptcy_price,
+        )?
+        .cast::<u64>()?;
+
+        let user_position_delta = get_position_delta_for_fill(
+            base_asset_amount,
+            quote_asset_amount,
+            user_position_direction_to_close,
+        )?;
+
+        let counterparty_position_delta = get_position_delta_for_fill(
+            base_asset_amount,
+            quote_asset_amount,
+            user_existing_position_direction,
+        )?;
+
+        {
+            let mut market = perp_market_map.get_ref_mut(&market_index)?;
+
+            let user_position = &mut user.perp_positions[position_index];
+            update_position_and_market(user_position, &mut market, &user_position_delta)?;
+
+            validate!(
+                is_multiple_of_step_size(
+                    user_position.base_asset_amount.unsigned_abs(),
+                    market.amm.order_step_size
+                )?,
+                ErrorCode::InvalidPerpPosition,
+                "base asset amount {} step size {}",
+                user_position.base_asset_amount,
+                market.amm.order_step_size
+            )?;
+
+            let counterparty_position = counterparty.get_perp_position_mut(market_index)?;
+            update_position_and_market(
+                counterparty_position,
+                &mut market,
+                &counterparty_position_delta,
+            )?;
+
+            validate!(
+                is_multiple_of_step_size(
+                    counterparty_position.base_asset_amount.unsigned_abs(),
+                    market.amm.order_step_size
+                )?,
+                ErrorCode::InvalidPerpPosition,
+                "base asset amount {} step size {}",
+                counterparty_position.base_asset_amount,
+                market.amm.order_step_size
+            )?;
+        }
+
+        counterparty.update_last_active_slot(slot);
+
+        base_asset_amount_deleveraged =
+            base_asset_amount_deleveraged.safe_add(base_asset_amount)?;
+
+        emit!(LiquidationRecord {
+            ts: now,
+            liquidation_id,
+            liquidation_type: LiquidationType::AutoDeleverage,
+            user: *user_key,
+            liquidator: *keeper_key,
+            margin_requirement,
+            total_collateral,
+            bankrupt: user.is_bankrupt(),
+            auto_deleverage: AutoDeleverageRecord {
+                market_index,
+                oracle_price,
+                bankruptcy_price,
+                counterparty: counterparty_key,
+                base_asset_amount: user_position_delta.base_asset_amount,
+                quote_asset_amount: user_position_delta.quote_asset_amount,
+                counterparty_pnl: counterparty_pnl.cast()?,
+                score,
+            },
+            ..LiquidationRecord::default()
+        });
+    }
+
+    Ok(base_asset_amount_deleveraged)
+}
+
+fn calculate_auto_deleverage_margin(
+    user: &User,
+    market_index: u16,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+) -> DriftResult<(u128, i128)> {
+    let is_isolated = user
+        .get_perp_position(market_index)
+        .map_or(false, |position| position.is_isolated);
+
+    let (margin_requirement, total_collateral, _, _) = if is_isolated {
+        calculate_isolated_perp_position_margin_requirement_and_total_collateral(
+            user,
+            market_index,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            oracle_map,
+            None,
+        )?
+    } else {
+        calculate_margin_requirement_and_total_collateral(
+            user,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            spot_market_map,
+            oracle_map,
+            None,
+        )?
+    };
+
+    Ok((margin_requirement, total_collateral))
+}
+
 pub fn resolve_spot_bankruptcy(
     market_index: u16,
     user: &mut User,
@@ -155,6 +155,7 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
@@ -300,6 +301,7 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
@@ -447,6 +449,7 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
@@ -578,6 +581,7 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
@@ -728,6 +732,7 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
@@ -775,7 +780,7 @@ pub mod liquidate_perp {
     }
 
     #[test]
-    pub fn successful_liquidation_long_perp_whale_imf_factor() {
+    pub fn successful_liquidation_with_backstop_fee_bonus() {
         let now = 0_i64;
         let slot = 0_u64;
 
@@ -815,7 +820,6 @@ pub mod liquidate_perp {
             margin_ratio_maintenance: 500,
             number_of_users_with_base: 1,
             status: MarketStatus::Initialized,
-            imf_factor: 1000, // SPOT_IMF_PRECISION == 1e6
             liquidator_fee: LIQUIDATION_FEE_PRECISION / 100,
             if_liquidation_fee: LIQUIDATION_FEE_PRECISION / 100,
             ..PerpMarket::default()
@@ -829,6 +833,7 @@ pub mod liquidate_perp {
             cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
             decimals: 6,
             initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
             ..SpotMarket::default()
         };
         create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
@@ -846,10 +851,10 @@ pub mod liquidate_perp {
             }),
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
-                base_asset_amount: BASE_PRECISION_I64 * 10000,
-                quote_asset_amount: -150 * QUOTE_PRECISION_I64 * 10000,
-                quote_entry_amount: -150 * QUOTE_PRECISION_I64 * 10000,
-                quote_break_even_amount: -150 * QUOTE_PRECISION_I64 * 10000,
+                base_asset_amount: 2 * BASE_PRECISION_I64,
+                quote_asset_amount: -200 * QUOTE_PRECISION_I64,
+                quote_entry_amount: -200 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: -200 * QUOTE_PRECISION_I64,
                 open_orders: 1,
                 open_bids: BASE_PRECISION_I64,
                 ..PerpPosition::default()
@@ -857,56 +862,13 @@ pub mod liquidate_perp {
             spot_positions: get_spot_positions(SpotPosition {
                 market_index: 0,
                 balance_type: SpotBalanceType::Deposit,
-                scaled_balance: 150 * 10000 * SPOT_BALANCE_PRECISION_U64,
+                scaled_balance: 5 * SPOT_BALANCE_PRECISION_U64,
                 ..SpotPosition::default()
             }),
 
             ..User::default()
         };
 
-        let (margin_req, _, _, _) = calculate_margin_requirement_and_total_collateral(
-            &user,
-            &perp_market_map,
-            MarginRequirementType::Maintenance,
-            &spot_market_map,
-            &mut oracle_map,
-            None,
-        )
-        .unwrap();
-        assert_eq!(margin_req, 140014010000);
-        assert!(!is_user_being_liquidated(
-            &user,
-            &perp_market_map,
-            &spot_market_map,
-            &mut oracle_map,
-            0
-        )
-        .unwrap());
-
-        {
-            let market_to_edit = &mut perp_market_map.get_ref_mut(&0).unwrap();
-            market_to_edit.imf_factor *= 10;
-        }
-
-        let (margin_req2, _, _, _) = calculate_margin_requirement_and_total_collateral(
-            &user,
-            &perp_market_map,
-            MarginRequirementType::Maintenance,
-            &spot_market_map,
-            &mut oracle_map,
-            None,
-        )
-        .unwrap();
-        assert_eq!(margin_req2, 1040104010000);
-        assert!(is_user_being_liquidated(
-            &user,
-            &perp_market_map,
-            &spot_market_map,
-            &mut oracle_map,
-            0
-        )
-        .unwrap());
-
         let mut liquidator = User {
             spot_positions: get_spot_positions(SpotPosition {
                 market_index: 0,
@@ -923,14 +885,14 @@ pub mod liquidate_perp {
         let mut user_stats = UserStats::default();
         let mut liquidator_stats = UserStats::default();
         let state = State {
-            liquidation_margin_buffer_ratio: 10,
+            liquidation_margin_buffer_ratio: MARGIN_PRECISION as u32 / 50,
             initial_pct_to_liquidate: LIQUIDATION_PCT_PRECISION as u16,
             liquidation_duration: 150,
             ..Default::default()
         };
         liquidate_perp(
             0,
-            BASE_PRECISION_U64,
+            10 * BASE_PRECISION_U64,
             None,
             &mut user,
             &user_key,
@@ -944,29 +906,56 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            LIQUIDATION_FEE_PRECISION / 100,
         )
         .unwrap();
 
-        assert_eq!(user.perp_positions[0].base_asset_amount, 9999000000000);
-        assert_eq!(user.perp_positions[0].quote_asset_amount, -1499902000000);
+        assert_eq!(user.perp_positions[0].base_asset_amount, 200000000);
+        assert_eq!(user.perp_positions[0].quote_asset_amount, -23600000);
+        assert_eq!(user.perp_positions[0].quote_entry_amount, -20000000);
+        assert_eq!(user.perp_positions[0].quote_break_even_amount, -23600000);
         assert_eq!(user.perp_positions[0].open_orders, 0);
         assert_eq!(user.perp_positions[0].open_bids, 0);
 
+        let (_, total_collateral, margin_requirement_plus_buffer, _) =
+            calculate_margin_requirement_and_total_collateral(
+                &user,
+                &perp_market_map,
+                MarginRequirementType::Maintenance,
+                &spot_market_map,
+                &mut oracle_map,
+                Some(state.liquidation_margin_buffer_ratio as u128),
+            )
+            .unwrap();
+
+        // user out of liq territory
         assert_eq!(
-            liquidator.perp_positions[0].base_asset_amount,
-            BASE_PRECISION_I64
-        );
-        assert_eq!(
-            liquidator.perp_positions[0].quote_asset_amount,
-            -99 * QUOTE_PRECISION_I64
+            total_collateral.unsigned_abs(),
+            margin_requirement_plus_buffer
         );
 
+        let oracle_price = oracle_map.get_price_data(&oracle_price_key).unwrap().price;
+
+        let perp_value = calculate_base_asset_value_with_oracle_price(
+            user.perp_positions[0].base_asset_amount as i128,
+            oracle_price,
+        )
+        .unwrap();
+
+        let margin_ratio = total_collateral.unsigned_abs() * MARGIN_PRECISION_U128 / perp_value;
+
+        assert_eq!(margin_ratio, 700);
+
+        // backstop takes the insurance fund's 1% on top of its own 1%
+        assert_eq!(liquidator.perp_positions[0].base_asset_amount, 1800000000);
+        assert_eq!(liquidator.perp_positions[0].quote_asset_amount, -176400000);
+
         let market_after = perp_market_map.get_ref(&0).unwrap();
-        assert_eq!(market_after.amm.total_liquidation_fee, QUOTE_PRECISION);
+        assert_eq!(market_after.amm.total_liquidation_fee, 0)
     }
 
     #[test]
-    pub fn fail_liquidating_long_perp_due_to_limit_price() {
+    pub fn successful_liquidation_to_target_margin_buffer() {
         let now = 0_i64;
         let slot = 0_u64;
 
@@ -1019,6 +1008,7 @@ pub mod liquidate_perp {
             cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
             decimals: 6,
             initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
             ..SpotMarket::default()
         };
         create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
@@ -1036,15 +1026,20 @@ pub mod liquidate_perp {
             }),
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
-                base_asset_amount: BASE_PRECISION_I64,
-                quote_asset_amount: -150 * QUOTE_PRECISION_I64,
-                quote_entry_amount: -150 * QUOTE_PRECISION_I64,
-                quote_break_even_amount: -150 * QUOTE_PRECISION_I64,
+                base_asset_amount: 2 * BASE_PRECISION_I64,
+                quote_asset_amount: -200 * QUOTE_PRECISION_I64,
+                quote_entry_amount: -200 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: -200 * QUOTE_PRECISION_I64,
                 open_orders: 1,
                 open_bids: BASE_PRECISION_I64,
                 ..PerpPosition::default()
             }),
-            spot_positions: [SpotPosition::default(); 8],
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 5 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
 
             ..User::default()
         };
@@ -1065,16 +1060,16 @@ pub mod liquidate_perp {
         let mut user_stats = UserStats::default();
         let mut liquidator_stats = UserStats::default();
         let state = State {
-            liquidation_margin_buffer_ratio: 10,
-            initial_pct_to_liquidate: LIQUIDATION_PCT_PRECISION as u16,
+            liquidation_margin_buffer_ratio: MARGIN_PRECISION as u32 / 50,
+            initial_pct_to_liquidate: (LIQUIDATION_PCT_PRECISION / 10) as u16,
             liquidation_duration: 150,
+            liquidation_target_margin_buffer_ratio: MARGIN_PRECISION as u32 / 100,
             ..Default::default()
         };
-
-        let result = liquidate_perp(
+        liquidate_perp(
             0,
-            BASE_PRECISION_U64,
-            Some(50 * PRICE_PRECISION_U64),
+            10 * BASE_PRECISION_U64,
+            None,
             &mut user,
             &user_key,
             &mut user_stats,
@@ -1087,13 +1082,55 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
+        )
+        .unwrap();
+
+        // 1.75 liquidated in one go despite the 10% initial pct
+        assert_eq!(user.perp_positions[0].base_asset_amount, 250000000);
+        assert_eq!(user.perp_positions[0].quote_asset_amount, -28500000);
+        assert_eq!(user.perp_positions[0].open_orders, 0);
+        assert_eq!(user.perp_positions[0].open_bids, 0);
+        assert!(!user.is_being_liquidated());
+
+        let (_, total_collateral, margin_requirement_plus_target_buffer, _) =
+            calculate_margin_requirement_and_total_collateral(
+                &user,
+                &perp_market_map,
+                MarginRequirementType::Maintenance,
+                &spot_market_map,
+                &mut oracle_map,
+                Some(state.liquidation_target_margin_buffer_ratio as u128),
+            )
+            .unwrap();
+
+        // user restored to maintenance + target buffer, not the full liquidation buffer
+        assert_eq!(
+            total_collateral.unsigned_abs(),
+            margin_requirement_plus_target_buffer
         );
 
-        assert_eq!(result, Err(ErrorCode::LiquidationDoesntSatisfyLimitPrice));
+        let oracle_price = oracle_map.get_price_data(&oracle_price_key).unwrap().price;
+
+        let perp_value = calculate_base_asset_value_with_oracle_price(
+            user.perp_positions[0].base_asset_amount as i128,
+            oracle_price,
+        )
+        .unwrap();
+
+        let margin_ratio = total_collateral.unsigned_abs() * MARGIN_PRECISION_U128 / perp_value;
+
+        assert_eq!(margin_ratio, 600);
+
+        assert_eq!(liquidator.perp_positions[0].base_asset_amount, 1750000000);
+        assert_eq!(liquidator.perp_positions[0].quote_asset_amount, -173250000);
+
+        let market_after = perp_market_map.get_ref(&0).unwrap();
+        assert_eq!(market_after.amm.total_liquidation_fee, 1750000)
     }
 
     #[test]
-    pub fn fail_liquidating_short_perp_due_to_limit_price() {
+    pub fn successful_liquidation_long_perp_whale_imf_factor() {
         let now = 0_i64;
         let slot = 0_u64;
 
@@ -1122,7 +1159,7 @@ pub mod liquidate_perp {
                 max_slippage_ratio: 50,
                 max_fill_reserve_fraction: 100,
                 order_step_size: 10000000,
-                quote_asset_amount: 50 * QUOTE_PRECISION_I128,
+                quote_asset_amount: -150 * QUOTE_PRECISION_I128,
                 base_asset_amount_with_amm: BASE_PRECISION_I128,
                 oracle: oracle_price_key,
                 historical_oracle_data: HistoricalOracleData::default_price(oracle_price.agg.price),
@@ -1133,6 +1170,7 @@ pub mod liquidate_perp {
             margin_ratio_maintenance: 500,
             number_of_users_with_base: 1,
             status: MarketStatus::Initialized,
+            imf_factor: 1000, // SPOT_IMF_PRECISION == 1e6
             liquidator_fee: LIQUIDATION_FEE_PRECISION / 100,
             if_liquidation_fee: LIQUIDATION_FEE_PRECISION / 100,
             ..PerpMarket::default()
@@ -1156,26 +1194,74 @@ pub mod liquidate_perp {
                 market_index: 0,
                 status: OrderStatus::Open,
                 order_type: OrderType::Limit,
-                direction: PositionDirection::Short,
+                direction: PositionDirection::Long,
                 base_asset_amount: BASE_PRECISION_U64,
                 slot: 0,
                 ..Order::default()
             }),
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
-                base_asset_amount: -BASE_PRECISION_I64,
-                quote_asset_amount: 50 * QUOTE_PRECISION_I64,
-                quote_entry_amount: 50 * QUOTE_PRECISION_I64,
-                quote_break_even_amount: 50 * QUOTE_PRECISION_I64,
+                base_asset_amount: BASE_PRECISION_I64 * 10000,
+                quote_asset_amount: -150 * QUOTE_PRECISION_I64 * 10000,
+                quote_entry_amount: -150 * QUOTE_PRECISION_I64 * 10000,
+                quote_break_even_amount: -150 * QUOTE_PRECISION_I64 * 10000,
                 open_orders: 1,
-                open_asks: -BASE_PRECISION_I64,
+                open_bids: BASE_PRECISION_I64,
                 ..PerpPosition::default()
             }),
-            spot_positions: [SpotPosition::default(); 8],
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 150 * 10000 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
 
             ..User::default()
         };
 
+        let (margin_req, _, _, _) = calculate_margin_requirement_and_total_collateral(
+            &user,
+            &perp_market_map,
+            MarginRequirementType::Maintenance,
+            &spot_market_map,
+            &mut oracle_map,
+            None,
+        )
+        .unwrap();
+        assert_eq!(margin_req, 140014010000);
+        assert!(!is_user_being_liquidated(
+            &user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            0
+        )
+        .unwrap());
+
+        {
+            let market_to_edit = &mut perp_market_map.get_ref_mut(&0).unwrap();
+            market_to_edit.imf_factor *= 10;
+        }
+
+        let (margin_req2, _, _, _) = calculate_margin_requirement_and_total_collateral(
+            &user,
+            &perp_market_map,
+            MarginRequirementType::Maintenance,
+            &spot_market_map,
+            &mut oracle_map,
+            None,
+        )
+        .unwrap();
+        assert_eq!(margin_req2, 1040104010000);
+        assert!(is_user_being_liquidated(
+            &user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            0
+        )
+        .unwrap());
+
         let mut liquidator = User {
             spot_positions: get_spot_positions(SpotPosition {
                 market_index: 0,
@@ -1197,11 +1283,10 @@ pub mod liquidate_perp {
             liquidation_duration: 150,
             ..Default::default()
         };
-
-        let result = liquidate_perp(
+        liquidate_perp(
             0,
             BASE_PRECISION_U64,
-            Some(150 * PRICE_PRECISION_U64),
+            None,
             &mut user,
             &user_key,
             &mut user_stats,
@@ -1214,19 +1299,36 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
-        );
-
-        assert_eq!(result, Err(ErrorCode::LiquidationDoesntSatisfyLimitPrice));
-    }
+            0,
+        )
+        .unwrap();
 
-    #[test]
-    pub fn liquidate_user_with_step_size_position() {
-        let now = 0_i64;
-        let slot = 0_u64;
+        assert_eq!(user.perp_positions[0].base_asset_amount, 9999000000000);
+        assert_eq!(user.perp_positions[0].quote_asset_amount, -1499902000000);
+        assert_eq!(user.perp_positions[0].open_orders, 0);
+        assert_eq!(user.perp_positions[0].open_bids, 0);
 
-        let mut oracle_price = get_pyth_price(100, 6);
-        let oracle_price_key =
-            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        assert_eq!(
+            liquidator.perp_positions[0].base_asset_amount,
+            BASE_PRECISION_I64
+        );
+        assert_eq!(
+            liquidator.perp_positions[0].quote_asset_amount,
+            -99 * QUOTE_PRECISION_I64
+        );
+
+        let market_after = perp_market_map.get_ref(&0).unwrap();
+        assert_eq!(market_after.amm.total_liquidation_fee, QUOTE_PRECISION);
+    }
+
+    #[test]
+    pub fn fail_liquidating_long_perp_due_to_limit_price() {
+        let now = 0_i64;
+        let slot = 0_u64;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
         let pyth_program = crate::ids::pyth_program::id();
         create_account_info!(
             oracle_price,
@@ -1279,20 +1381,27 @@ pub mod liquidate_perp {
         let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
 
         let mut user = User {
-            spot_positions: get_spot_positions(SpotPosition {
+            orders: get_orders(Order {
                 market_index: 0,
-                balance_type: SpotBalanceType::Deposit,
-                scaled_balance: 6 * SPOT_BALANCE_PRECISION_U64 / 11,
-                ..SpotPosition::default()
+                status: OrderStatus::Open,
+                order_type: OrderType::Limit,
+                direction: PositionDirection::Long,
+                base_asset_amount: BASE_PRECISION_U64,
+                slot: 0,
+                ..Order::default()
             }),
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
-                base_asset_amount: BASE_PRECISION_I64 / 100,
-                quote_asset_amount: -150 * QUOTE_PRECISION_I64 / 100,
-                quote_entry_amount: -150 * QUOTE_PRECISION_I64 / 100,
-                quote_break_even_amount: -150 * QUOTE_PRECISION_I64 / 100,
+                base_asset_amount: BASE_PRECISION_I64,
+                quote_asset_amount: -150 * QUOTE_PRECISION_I64,
+                quote_entry_amount: -150 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: -150 * QUOTE_PRECISION_I64,
+                open_orders: 1,
+                open_bids: BASE_PRECISION_I64,
                 ..PerpPosition::default()
             }),
+            spot_positions: [SpotPosition::default(); 8],
+
             ..User::default()
         };
 
@@ -1317,10 +1426,11 @@ pub mod liquidate_perp {
             liquidation_duration: 150,
             ..Default::default()
         };
-        liquidate_perp(
+
+        let result = liquidate_perp(
             0,
-            BASE_PRECISION_U64 / 100,
-            None,
+            BASE_PRECISION_U64,
+            Some(50 * PRICE_PRECISION_U64),
             &mut user,
             &user_key,
             &mut user_stats,
@@ -1333,37 +1443,16 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
-        )
-        .unwrap();
-
-        assert_eq!(user.perp_positions[0].base_asset_amount, 0);
-        assert_eq!(
-            user.perp_positions[0].quote_asset_amount,
-            -52 * QUOTE_PRECISION_I64 / 100
-        );
-        assert_eq!(user.perp_positions[0].open_orders, 0);
-        assert_eq!(user.perp_positions[0].open_bids, 0);
-
-        assert_eq!(
-            liquidator.perp_positions[0].base_asset_amount,
-            BASE_PRECISION_I64 / 100
-        );
-        assert_eq!(
-            liquidator.perp_positions[0].quote_asset_amount,
-            -99 * QUOTE_PRECISION_I64 / 100
+            0,
         );
 
-        let market_after = perp_market_map.get_ref(&0).unwrap();
-        assert_eq!(
-            market_after.amm.total_liquidation_fee,
-            QUOTE_PRECISION / 100
-        );
+        assert_eq!(result, Err(ErrorCode::LiquidationDoesntSatisfyLimitPrice));
     }
 
     #[test]
-    pub fn liquidation_over_multiple_slots() {
-        let now = 1_i64;
-        let slot = 1_u64;
+    pub fn fail_liquidating_short_perp_due_to_limit_price() {
+        let now = 0_i64;
+        let slot = 0_u64;
 
         let mut oracle_price = get_pyth_price(100, 6);
         let oracle_price_key =
@@ -1390,7 +1479,7 @@ pub mod liquidate_perp {
                 max_slippage_ratio: 50,
                 max_fill_reserve_fraction: 100,
                 order_step_size: 10000000,
-                quote_asset_amount: -150 * QUOTE_PRECISION_I128,
+                quote_asset_amount: 50 * QUOTE_PRECISION_I128,
                 base_asset_amount_with_amm: BASE_PRECISION_I128,
                 oracle: oracle_price_key,
                 historical_oracle_data: HistoricalOracleData::default_price(oracle_price.agg.price),
@@ -1414,7 +1503,6 @@ pub mod liquidate_perp {
             cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
             decimals: 6,
             initial_asset_weight: SPOT_WEIGHT_PRECISION,
-            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
             ..SpotMarket::default()
         };
         create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
@@ -1425,27 +1513,22 @@ pub mod liquidate_perp {
                 market_index: 0,
                 status: OrderStatus::Open,
                 order_type: OrderType::Limit,
-                direction: PositionDirection::Long,
+                direction: PositionDirection::Short,
                 base_asset_amount: BASE_PRECISION_U64,
                 slot: 0,
                 ..Order::default()
             }),
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
-                base_asset_amount: 2 * BASE_PRECISION_I64,
-                quote_asset_amount: -200 * QUOTE_PRECISION_I64,
-                quote_entry_amount: -200 * QUOTE_PRECISION_I64,
-                quote_break_even_amount: -200 * QUOTE_PRECISION_I64,
+                base_asset_amount: -BASE_PRECISION_I64,
+                quote_asset_amount: 50 * QUOTE_PRECISION_I64,
+                quote_entry_amount: 50 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: 50 * QUOTE_PRECISION_I64,
                 open_orders: 1,
-                open_bids: BASE_PRECISION_I64,
+                open_asks: -BASE_PRECISION_I64,
                 ..PerpPosition::default()
             }),
-            spot_positions: get_spot_positions(SpotPosition {
-                market_index: 0,
-                balance_type: SpotBalanceType::Deposit,
-                scaled_balance: 5 * SPOT_BALANCE_PRECISION_U64,
-                ..SpotPosition::default()
-            }),
+            spot_positions: [SpotPosition::default(); 8],
 
             ..User::default()
         };
@@ -1466,15 +1549,16 @@ pub mod liquidate_perp {
         let mut user_stats = UserStats::default();
         let mut liquidator_stats = UserStats::default();
         let state = State {
-            liquidation_margin_buffer_ratio: MARGIN_PRECISION as u32 / 50,
-            initial_pct_to_liquidate: (LIQUIDATION_PCT_PRECISION / 10) as u16,
+            liquidation_margin_buffer_ratio: 10,
+            initial_pct_to_liquidate: LIQUIDATION_PCT_PRECISION as u16,
             liquidation_duration: 150,
             ..Default::default()
         };
-        liquidate_perp(
+
+        let result = liquidate_perp(
             0,
-            10 * BASE_PRECISION_U64,
-            None,
+            BASE_PRECISION_U64,
+            Some(150 * PRICE_PRECISION_U64),
             &mut user,
             &user_key,
             &mut user_stats,
@@ -1487,18 +1571,113 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
-        )
-        .unwrap();
+            0,
+        );
 
-        assert_eq!(user.last_active_slot, 1);
-        assert_eq!(user.liquidation_margin_freed, 7010000);
-        assert_eq!(user.perp_positions[0].base_asset_amount, 2000000000);
+        assert_eq!(result, Err(ErrorCode::LiquidationDoesntSatisfyLimitPrice));
+    }
 
-        // ~60% of liquidation finished
-        let slot = 76_u64;
+    #[test]
+    pub fn liquidate_user_with_step_size_position() {
+        let now = 0_i64;
+        let slot = 0_u64;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 50,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 10000000,
+                quote_asset_amount: -150 * QUOTE_PRECISION_I128,
+                base_asset_amount_with_amm: BASE_PRECISION_I128,
+                oracle: oracle_price_key,
+                historical_oracle_data: HistoricalOracleData::default_price(oracle_price.agg.price),
+                funding_period: ONE_HOUR,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            number_of_users_with_base: 1,
+            status: MarketStatus::Initialized,
+            liquidator_fee: LIQUIDATION_FEE_PRECISION / 100,
+            if_liquidation_fee: LIQUIDATION_FEE_PRECISION / 100,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        let mut user = User {
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 6 * SPOT_BALANCE_PRECISION_U64 / 11,
+                ..SpotPosition::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: BASE_PRECISION_I64 / 100,
+                quote_asset_amount: -150 * QUOTE_PRECISION_I64 / 100,
+                quote_entry_amount: -150 * QUOTE_PRECISION_I64 / 100,
+                quote_break_even_amount: -150 * QUOTE_PRECISION_I64 / 100,
+                ..PerpPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let mut liquidator = User {
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 50 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let user_key = Pubkey::default();
+        let liquidator_key = Pubkey::default();
+
+        let mut user_stats = UserStats::default();
+        let mut liquidator_stats = UserStats::default();
+        let state = State {
+            liquidation_margin_buffer_ratio: 10,
+            initial_pct_to_liquidate: LIQUIDATION_PCT_PRECISION as u16,
+            liquidation_duration: 150,
+            ..Default::default()
+        };
         liquidate_perp(
             0,
-            10 * BASE_PRECISION_U64,
+            BASE_PRECISION_U64 / 100,
             None,
             &mut user,
             &user_key,
@@ -1512,25 +1691,207 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
-        assert_eq!(user.last_active_slot, 1);
-        assert_eq!(user.liquidation_margin_freed, 9610000);
-        assert_eq!(user.perp_positions[0].base_asset_amount, 1480000000);
+        assert_eq!(user.perp_positions[0].base_asset_amount, 0);
+        assert_eq!(
+            user.perp_positions[0].quote_asset_amount,
+            -52 * QUOTE_PRECISION_I64 / 100
+        );
+        assert_eq!(user.perp_positions[0].open_orders, 0);
+        assert_eq!(user.perp_positions[0].open_bids, 0);
 
-        let (_, total_collateral, margin_requirement_plus_buffer, _) =
-            calculate_margin_requirement_and_total_collateral(
-                &user,
-                &perp_market_map,
-                MarginRequirementType::Maintenance,
-                &spot_market_map,
-                &mut oracle_map,
-                Some(state.liquidation_margin_buffer_ratio as u128),
-            )
-            .unwrap();
+        assert_eq!(
+            liquidator.perp_positions[0].base_asset_amount,
+            BASE_PRECISION_I64 / 100
+        );
+        assert_eq!(
+            liquidator.perp_positions[0].quote_asset_amount,
+            -99 * QUOTE_PRECISION_I64 / 100
+        );
 
-        let margin_shortage =
+        let market_after = perp_market_map.get_ref(&0).unwrap();
+        assert_eq!(
+            market_after.amm.total_liquidation_fee,
+            QUOTE_PRECISION / 100
+        );
+    }
+
+    #[test]
+    pub fn liquidation_over_multiple_slots() {
+        let now = 1_i64;
+        let slot = 1_u64;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 50,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 10000000,
+                quote_asset_amount: -150 * QUOTE_PRECISION_I128,
+                base_asset_amount_with_amm: BASE_PRECISION_I128,
+                oracle: oracle_price_key,
+                historical_oracle_data: HistoricalOracleData::default_price(oracle_price.agg.price),
+                funding_period: ONE_HOUR,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            number_of_users_with_base: 1,
+            status: MarketStatus::Initialized,
+            liquidator_fee: LIQUIDATION_FEE_PRECISION / 100,
+            if_liquidation_fee: LIQUIDATION_FEE_PRECISION / 100,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        let mut user = User {
+            orders: get_orders(Order {
+                market_index: 0,
+                status: OrderStatus::Open,
+                order_type: OrderType::Limit,
+                direction: PositionDirection::Long,
+                base_asset_amount: BASE_PRECISION_U64,
+                slot: 0,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: 2 * BASE_PRECISION_I64,
+                quote_asset_amount: -200 * QUOTE_PRECISION_I64,
+                quote_entry_amount: -200 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: -200 * QUOTE_PRECISION_I64,
+                open_orders: 1,
+                open_bids: BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 5 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+
+            ..User::default()
+        };
+
+        let mut liquidator = User {
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 50 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let user_key = Pubkey::default();
+        let liquidator_key = Pubkey::default();
+
+        let mut user_stats = UserStats::default();
+        let mut liquidator_stats = UserStats::default();
+        let state = State {
+            liquidation_margin_buffer_ratio: MARGIN_PRECISION as u32 / 50,
+            initial_pct_to_liquidate: (LIQUIDATION_PCT_PRECISION / 10) as u16,
+            liquidation_duration: 150,
+            ..Default::default()
+        };
+        liquidate_perp(
+            0,
+            10 * BASE_PRECISION_U64,
+            None,
+            &mut user,
+            &user_key,
+            &mut user_stats,
+            &mut liquidator,
+            &liquidator_key,
+            &mut liquidator_stats,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            slot,
+            now,
+            &state,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(user.last_active_slot, 1);
+        assert_eq!(user.liquidation_margin_freed, 7010000);
+        assert_eq!(user.perp_positions[0].base_asset_amount, 2000000000);
+
+        // ~60% of liquidation finished
+        let slot = 76_u64;
+        liquidate_perp(
+            0,
+            10 * BASE_PRECISION_U64,
+            None,
+            &mut user,
+            &user_key,
+            &mut user_stats,
+            &mut liquidator,
+            &liquidator_key,
+            &mut liquidator_stats,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            slot,
+            now,
+            &state,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(user.last_active_slot, 1);
+        assert_eq!(user.liquidation_margin_freed, 9610000);
+        assert_eq!(user.perp_positions[0].base_asset_amount, 1480000000);
+
+        let (_, total_collateral, margin_requirement_plus_buffer, _) =
+            calculate_margin_requirement_and_total_collateral(
+                &user,
+                &perp_market_map,
+                MarginRequirementType::Maintenance,
+                &spot_market_map,
+                &mut oracle_map,
+                Some(state.liquidation_margin_buffer_ratio as u128),
+            )
+            .unwrap();
+
+        let margin_shortage =
             ((margin_requirement_plus_buffer as i128) - total_collateral).unsigned_abs();
 
         let pct_margin_freed = (user.liquidation_margin_freed as u128) * PRICE_PRECISION
@@ -1555,6 +1916,7 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
@@ -1580,6 +1942,7 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
@@ -1623,6 +1986,7 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
@@ -1747,6 +2111,7 @@ pub mod liquidate_perp {
             slot,
             now,
             &state,
+            0,
         )
         .unwrap();
 
@@ -1769,9 +2134,9 @@ pub mod liquidate_spot {
     use crate::error::ErrorCode;
     use crate::math::constants::{
         LIQUIDATION_FEE_PRECISION, LIQUIDATION_PCT_PRECISION, MARGIN_PRECISION,
-        MARGIN_PRECISION_U128, PERCENTAGE_PRECISION, PRICE_PRECISION, PRICE_PRECISION_U64,
-        SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
-        SPOT_WEIGHT_PRECISION,
+        MARGIN_PRECISION_U128, MAX_USER_ORDERS, PERCENTAGE_PRECISION, PRICE_PRECISION,
+        PRICE_PRECISION_U64, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::math::margin::{
         calculate_margin_requirement_and_total_collateral, MarginRequirementType,
@@ -1860,7 +2225,7 @@ pub mod liquidate_spot {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -1896,6 +2261,7 @@ pub mod liquidate_spot {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -1987,7 +2353,7 @@ pub mod liquidate_spot {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions: spot_market,
             ..User::default()
@@ -2024,6 +2390,7 @@ pub mod liquidate_spot {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .is_err());
 
@@ -2052,6 +2419,7 @@ pub mod liquidate_spot {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -2144,7 +2512,7 @@ pub mod liquidate_spot {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2182,6 +2550,7 @@ pub mod liquidate_spot {
             liquidation_buffer, // 2%
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -2346,7 +2715,7 @@ pub mod liquidate_spot {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2383,6 +2752,7 @@ pub mod liquidate_spot {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         );
 
         assert_eq!(result, Err(ErrorCode::LiquidationDoesntSatisfyLimitPrice));
@@ -2461,7 +2831,7 @@ pub mod liquidate_spot {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2498,6 +2868,7 @@ pub mod liquidate_spot {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         );
 
         assert_eq!(result, Ok(()));
@@ -2577,7 +2948,7 @@ pub mod liquidate_spot {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2615,6 +2986,7 @@ pub mod liquidate_spot {
             liquidation_buffer, // 2%
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -2699,7 +3071,7 @@ pub mod liquidate_spot {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2737,6 +3109,7 @@ pub mod liquidate_spot {
             liquidation_buffer, // 2%
             LIQUIDATION_PCT_PRECISION / 10,
             150,
+            0,
         )
         .unwrap();
 
@@ -2781,6 +3154,7 @@ pub mod liquidate_spot {
             liquidation_buffer, // 2%
             LIQUIDATION_PCT_PRECISION / 10,
             150,
+            0,
         )
         .unwrap();
 
@@ -2825,6 +3199,7 @@ pub mod liquidate_spot {
             liquidation_buffer, // 2%
             LIQUIDATION_PCT_PRECISION / 10,
             150,
+            0,
         )
         .unwrap();
 
@@ -2848,10 +3223,10 @@ pub mod liquidate_borrow_for_perp_pnl {
     use crate::error::ErrorCode;
     use crate::math::constants::{
         AMM_RESERVE_PRECISION, BASE_PRECISION_I128, LIQUIDATION_FEE_PRECISION,
-        LIQUIDATION_PCT_PRECISION, MARGIN_PRECISION, MARGIN_PRECISION_U128, PEG_PRECISION,
-        PERCENTAGE_PRECISION, PRICE_PRECISION, PRICE_PRECISION_U64, QUOTE_PRECISION_I128,
-        QUOTE_PRECISION_I64, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
-        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+        LIQUIDATION_PCT_PRECISION, MARGIN_PRECISION, MARGIN_PRECISION_U128, MAX_USER_ORDERS,
+        PEG_PRECISION, PERCENTAGE_PRECISION, PRICE_PRECISION, PRICE_PRECISION_U64,
+        QUOTE_PRECISION_I128, QUOTE_PRECISION_I64, SPOT_BALANCE_PRECISION,
+        SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::math::margin::{
         calculate_margin_requirement_and_total_collateral, MarginRequirementType,
@@ -2963,7 +3338,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: 100 * QUOTE_PRECISION_I64,
@@ -3003,6 +3378,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -3113,7 +3489,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: 105 * QUOTE_PRECISION_I64,
@@ -3154,6 +3530,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             liquidation_buffer,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -3301,7 +3678,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: 80 * QUOTE_PRECISION_I64,
@@ -3341,6 +3718,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -3450,7 +3828,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: 100 * QUOTE_PRECISION_I64,
@@ -3491,6 +3869,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         );
 
         assert_eq!(result, Err(ErrorCode::LiquidationDoesntSatisfyLimitPrice));
@@ -3591,7 +3970,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: 100 * QUOTE_PRECISION_I64,
@@ -3632,6 +4011,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         );
 
         assert_eq!(result, Ok(()));
@@ -3733,7 +4113,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: 107 * QUOTE_PRECISION_I64 / 50,
@@ -3774,6 +4154,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             liquidation_buffer,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -3884,7 +4265,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: 105 * QUOTE_PRECISION_I64,
@@ -3925,6 +4306,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             liquidation_buffer,
             LIQUIDATION_PCT_PRECISION / 10,
             150,
+            0,
         )
         .unwrap();
 
@@ -3969,6 +4351,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             liquidation_buffer,
             LIQUIDATION_PCT_PRECISION / 10,
             150,
+            0,
         )
         .unwrap();
 
@@ -4013,6 +4396,7 @@ pub mod liquidate_borrow_for_perp_pnl {
             liquidation_buffer,
             LIQUIDATION_PCT_PRECISION / 10,
             150,
+            0,
         )
         .unwrap();
 
@@ -4033,10 +4417,10 @@ pub mod liquidate_perp_pnl_for_deposit {
     use crate::error::ErrorCode;
     use crate::math::constants::{
         AMM_RESERVE_PRECISION, BASE_PRECISION_I128, LIQUIDATION_FEE_PRECISION,
-        LIQUIDATION_PCT_PRECISION, MARGIN_PRECISION, PEG_PRECISION, PERCENTAGE_PRECISION,
-        PRICE_PRECISION, PRICE_PRECISION_U64, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64,
-        SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
-        SPOT_WEIGHT_PRECISION,
+        LIQUIDATION_PCT_PRECISION, MARGIN_PRECISION, MAX_USER_ORDERS, PEG_PRECISION,
+        PERCENTAGE_PRECISION, PRICE_PRECISION, PRICE_PRECISION_U64, QUOTE_PRECISION_I128,
+        QUOTE_PRECISION_I64, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::math::margin::{
         calculate_margin_requirement_and_total_collateral, MarginRequirementType,
@@ -4147,7 +4531,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: -100 * QUOTE_PRECISION_I64,
@@ -4187,6 +4571,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -4297,7 +4682,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: -91 * QUOTE_PRECISION_I64,
@@ -4337,6 +4722,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             MARGIN_PRECISION as u32 / 50,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -4449,7 +4835,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: -150 * QUOTE_PRECISION_I64,
@@ -4489,6 +4875,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -4598,7 +4985,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: -100 * QUOTE_PRECISION_I64,
@@ -4639,6 +5026,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         );
 
         assert_eq!(result, Err(ErrorCode::LiquidationDoesntSatisfyLimitPrice));
@@ -4739,7 +5127,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: -100 * QUOTE_PRECISION_I64,
@@ -4780,6 +5168,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         );
 
         assert_eq!(result, Ok(()));
@@ -4881,7 +5270,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: -91 * QUOTE_PRECISION_I64 / 50,
@@ -4921,6 +5310,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             MARGIN_PRECISION as u32 / 50,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -5031,7 +5421,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: -91 * QUOTE_PRECISION_I64,
@@ -5072,6 +5462,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             liquidation_buffer,
             LIQUIDATION_PCT_PRECISION / 10,
             150,
+            0,
         )
         .unwrap();
 
@@ -5116,6 +5507,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             liquidation_buffer,
             LIQUIDATION_PCT_PRECISION / 10,
             150,
+            0,
         )
         .unwrap();
 
@@ -5160,6 +5552,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             liquidation_buffer,
             LIQUIDATION_PCT_PRECISION / 10,
             150,
+            0,
         )
         .unwrap();
 
@@ -5278,7 +5671,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: -100 * QUOTE_PRECISION_I64,
@@ -5318,6 +5711,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .is_err());
 
@@ -5338,6 +5732,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -5360,6 +5755,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
         assert_eq!(user.perp_positions[0].quote_asset_amount, -50000000);
@@ -5383,6 +5779,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
         assert_eq!(user.spot_positions[0].scaled_balance, 0);
@@ -5531,7 +5928,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 quote_asset_amount: -100 * QUOTE_PRECISION_I64,
@@ -5577,6 +5974,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .is_err());
         assert_eq!(user.perp_positions[0].quote_asset_amount, -100000000);
@@ -5598,6 +5996,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
         assert_eq!(user.perp_positions[0].quote_asset_amount, 0);
@@ -5619,6 +6018,7 @@ pub mod liquidate_perp_pnl_for_deposit {
             10,
             PERCENTAGE_PRECISION,
             150,
+            0,
         )
         .unwrap();
 
@@ -5649,13 +6049,13 @@ pub mod resolve_perp_bankruptcy {
     use crate::math::constants::{
         AMM_RESERVE_PRECISION, BASE_PRECISION_I128, BASE_PRECISION_I64, BASE_PRECISION_U64,
         FUNDING_RATE_PRECISION_I128, FUNDING_RATE_PRECISION_I64, LIQUIDATION_FEE_PRECISION,
-        PEG_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64, QUOTE_SPOT_MARKET_INDEX,
-        SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
-        SPOT_WEIGHT_PRECISION,
+        MAX_USER_ORDERS, PEG_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64,
+        QUOTE_PRECISION_U64, QUOTE_SPOT_MARKET_INDEX, SPOT_BALANCE_PRECISION,
+        SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::state::oracle::OracleSource;
     use crate::state::oracle_map::OracleMap;
-    use crate::state::perp_market::{MarketStatus, PerpMarket, PoolBalance, AMM};
+    use crate::state::perp_market::{InsuranceClaim, MarketStatus, PerpMarket, PoolBalance, AMM};
     use crate::state::perp_market_map::PerpMarketMap;
     use crate::state::spot_market::{SpotBalanceType, SpotMarket};
     use crate::state::spot_market_map::SpotMarketMap;
@@ -5794,7 +6194,7 @@ pub mod resolve_perp_bankruptcy {
         assert_eq!(expected_market, market_map.get_ref(&0).unwrap().clone());
 
         let mut affected_long_user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 base_asset_amount: 5 * BASE_PRECISION_I64,
@@ -5832,7 +6232,7 @@ pub mod resolve_perp_bankruptcy {
         assert_eq!(expected_affected_long_user, affected_long_user);
 
         let mut affected_short_user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 base_asset_amount: -5 * BASE_PRECISION_I64,
@@ -5871,7 +6271,7 @@ pub mod resolve_perp_bankruptcy {
     }
 
     #[test]
-    pub fn successful_resolve_perp_bankruptcy_with_fee_pool() {
+    pub fn resolve_perp_bankruptcy_caps_insurance_draw_to_vault_share() {
         let now = 0_i64;
         let slot = 0_u64;
 
@@ -5907,13 +6307,13 @@ pub mod resolve_perp_bankruptcy {
                 oracle: oracle_price_key,
                 cumulative_funding_rate_long: 1000 * FUNDING_RATE_PRECISION_I128,
                 cumulative_funding_rate_short: -1000 * FUNDING_RATE_PRECISION_I128,
-                fee_pool: PoolBalance {
-                    scaled_balance: 50 * SPOT_BALANCE_PRECISION,
-                    market_index: QUOTE_SPOT_MARKET_INDEX,
-                    ..PoolBalance::default()
-                },
                 ..AMM::default()
             },
+            insurance_claim: InsuranceClaim {
+                quote_max_insurance: 100 * QUOTE_PRECISION_U64,
+                ..InsuranceClaim::default()
+            },
+            insurance_fund_max_draw_pct: 10,
             margin_ratio_initial: 1000,
             margin_ratio_maintenance: 500,
             status: MarketStatus::Initialized,
@@ -5926,7 +6326,6 @@ pub mod resolve_perp_bankruptcy {
 
         let mut spot_market = SpotMarket {
             market_index: 0,
-            deposit_balance: 500 * SPOT_BALANCE_PRECISION,
             oracle_source: OracleSource::QuoteAsset,
             cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
             decimals: 6,
@@ -5937,23 +6336,12 @@ pub mod resolve_perp_bankruptcy {
         let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
 
         let mut user = User {
-            orders: get_orders(Order {
-                market_index: 0,
-                status: OrderStatus::Open,
-                order_type: OrderType::Limit,
-                direction: PositionDirection::Long,
-                base_asset_amount: BASE_PRECISION_U64,
-                slot: 0,
-                ..Order::default()
-            }),
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 base_asset_amount: 0,
                 quote_asset_amount: -100 * QUOTE_PRECISION_I64,
                 quote_entry_amount: -100 * QUOTE_PRECISION_I64,
                 quote_break_even_amount: -100 * QUOTE_PRECISION_I64,
-                open_orders: 1,
-                open_bids: BASE_PRECISION_I64,
                 ..PerpPosition::default()
             }),
             spot_positions: [SpotPosition::default(); 8],
@@ -5975,14 +6363,152 @@ pub mod resolve_perp_bankruptcy {
         let user_key = Pubkey::default();
         let liquidator_key = Pubkey::default();
 
-        let mut expected_user = user;
-        expected_user.status = UserStatus::Active;
-        expected_user.perp_positions[0].quote_asset_amount = 0;
-        expected_user.total_social_loss = 100000000;
-
-        let mut expected_market = market;
-        expected_market.amm.cumulative_funding_rate_long = 1005 * FUNDING_RATE_PRECISION_I128;
-        expected_market.amm.cumulative_funding_rate_short = -1005 * FUNDING_RATE_PRECISION_I128;
+        // market may only take 10% of the $500 vault despite a $100 claim
+        let if_payment = resolve_perp_bankruptcy(
+            0,
+            &mut user,
+            &user_key,
+            &mut liquidator,
+            &liquidator_key,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            500 * QUOTE_PRECISION_U64,
+        )
+        .unwrap();
+
+        assert_eq!(if_payment, 50 * QUOTE_PRECISION_U64);
+        assert_eq!(user.status, UserStatus::Active);
+
+        let market = market_map.get_ref(&0).unwrap();
+        assert_eq!(
+            market.insurance_claim.quote_settled_insurance,
+            50 * QUOTE_PRECISION_U64
+        );
+        assert_eq!(
+            market.amm.total_social_loss,
+            50 * QUOTE_PRECISION_U64 as u128
+        );
+        assert_eq!(
+            market.amm.cumulative_funding_rate_long,
+            1005 * FUNDING_RATE_PRECISION_I128
+        );
+    }
+
+    #[test]
+    pub fn successful_resolve_perp_bankruptcy_with_fee_pool() {
+        let now = 0_i64;
+        let slot = 0_u64;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 50,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 10000000,
+                quote_asset_amount: -150 * QUOTE_PRECISION_I128,
+                base_asset_amount_long: 5 * BASE_PRECISION_I128,
+                base_asset_amount_short: -5 * BASE_PRECISION_I128,
+                base_asset_amount_with_amm: BASE_PRECISION_I128,
+                oracle: oracle_price_key,
+                cumulative_funding_rate_long: 1000 * FUNDING_RATE_PRECISION_I128,
+                cumulative_funding_rate_short: -1000 * FUNDING_RATE_PRECISION_I128,
+                fee_pool: PoolBalance {
+                    scaled_balance: 50 * SPOT_BALANCE_PRECISION,
+                    market_index: QUOTE_SPOT_MARKET_INDEX,
+                    ..PoolBalance::default()
+                },
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            liquidator_fee: LIQUIDATION_FEE_PRECISION / 100,
+            number_of_users: 1,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            deposit_balance: 500 * SPOT_BALANCE_PRECISION,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        let mut user = User {
+            orders: get_orders(Order {
+                market_index: 0,
+                status: OrderStatus::Open,
+                order_type: OrderType::Limit,
+                direction: PositionDirection::Long,
+                base_asset_amount: BASE_PRECISION_U64,
+                slot: 0,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: 0,
+                quote_asset_amount: -100 * QUOTE_PRECISION_I64,
+                quote_entry_amount: -100 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: -100 * QUOTE_PRECISION_I64,
+                open_orders: 1,
+                open_bids: BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: [SpotPosition::default(); 8],
+            status: UserStatus::Bankrupt,
+            next_liquidation_id: 2,
+            ..User::default()
+        };
+
+        let mut liquidator = User {
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 50 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let user_key = Pubkey::default();
+        let liquidator_key = Pubkey::default();
+
+        let mut expected_user = user;
+        expected_user.status = UserStatus::Active;
+        expected_user.perp_positions[0].quote_asset_amount = 0;
+        expected_user.total_social_loss = 100000000;
+
+        let mut expected_market = market;
+        expected_market.amm.cumulative_funding_rate_long = 1005 * FUNDING_RATE_PRECISION_I128;
+        expected_market.amm.cumulative_funding_rate_short = -1005 * FUNDING_RATE_PRECISION_I128;
         expected_market.amm.total_social_loss = 50000000;
         expected_market.amm.quote_asset_amount = -50 * QUOTE_PRECISION_I128;
         expected_market.number_of_users = 0;
@@ -6007,7 +6533,7 @@ pub mod resolve_perp_bankruptcy {
         assert_eq!(expected_market, market_map.get_ref(&0).unwrap().clone());
 
         let mut affected_long_user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 base_asset_amount: 5 * BASE_PRECISION_I64,
@@ -6045,7 +6571,7 @@ pub mod resolve_perp_bankruptcy {
         assert_eq!(expected_affected_long_user, affected_long_user);
 
         let mut affected_short_user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 base_asset_amount: -5 * BASE_PRECISION_I64,
@@ -6084,6 +6610,392 @@ pub mod resolve_perp_bankruptcy {
     }
 }
 
+pub mod auto_deleverage_perp {
+    use std::str::FromStr;
+
+    use anchor_lang::Owner;
+    use solana_program::pubkey::Pubkey;
+
+    use crate::controller::liquidation::auto_deleverage_perp;
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::error::ErrorCode;
+    use crate::math::constants::{
+        AMM_RESERVE_PRECISION, BASE_PRECISION_I128, BASE_PRECISION_I64, BASE_PRECISION_U64,
+        ONE_HOUR, PEG_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64, QUOTE_PRECISION_U64,
+        SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+    };
+    use crate::state::oracle::{HistoricalOracleData, OracleSource};
+    use crate::state::oracle_map::OracleMap;
+    use crate::state::perp_market::{InsuranceClaim, MarketStatus, PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::state::State;
+    use crate::state::user::{PerpPosition, SpotPosition, User};
+    use crate::state::user_map::{load_user_map, UserMap};
+    use crate::test_utils::*;
+    use crate::test_utils::{get_positions, get_pyth_price, get_spot_positions};
+
+    #[test]
+    pub fn successful_auto_deleverage_ranked_counterparties() {
+        let now = 0_i64;
+        let slot = 0_u64;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 50,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 10000000,
+                quote_asset_amount: 500 * QUOTE_PRECISION_I128,
+                base_asset_amount_long: 10 * BASE_PRECISION_I128,
+                base_asset_amount_short: -12 * BASE_PRECISION_I128,
+                base_asset_amount_with_amm: -2 * BASE_PRECISION_I128,
+                oracle: oracle_price_key,
+                historical_oracle_data: HistoricalOracleData::default_price(oracle_price.agg.price),
+                funding_period: ONE_HOUR,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            number_of_users_with_base: 3,
+            number_of_users: 3,
+            status: MarketStatus::Initialized,
+            auto_deleverage_enabled: true,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        // long 10 from $110, $50 of collateral short of the $100 loss
+        let mut user = User {
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: 10 * BASE_PRECISION_I64,
+                quote_asset_amount: -1100 * QUOTE_PRECISION_I64,
+                quote_entry_amount: -1100 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: -1100 * QUOTE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 50 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+
+        // short 4 from $150, 33% pnl at 4x
+        let counterparty_a_key = Pubkey::new_unique();
+        let mut counterparty_a = User {
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: -4 * BASE_PRECISION_I64,
+                quote_asset_amount: 600 * QUOTE_PRECISION_I64,
+                quote_entry_amount: 600 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: 600 * QUOTE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+        create_anchor_account_info!(
+            counterparty_a,
+            &counterparty_a_key,
+            User,
+            counterparty_a_account_info
+        );
+
+        // short 8 from $125, 20% pnl at 8x ranks first
+        let counterparty_b_key = Pubkey::new_unique();
+        let mut counterparty_b = User {
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: -8 * BASE_PRECISION_I64,
+                quote_asset_amount: 1000 * QUOTE_PRECISION_I64,
+                quote_entry_amount: 1000 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: 1000 * QUOTE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+        create_anchor_account_info!(
+            counterparty_b,
+            &counterparty_b_key,
+            User,
+            counterparty_b_account_info
+        );
+
+        let counterparty_account_infos =
+            vec![counterparty_a_account_info, counterparty_b_account_info];
+        let counterparty_map =
+            load_user_map(&mut counterparty_account_infos.iter().peekable()).unwrap();
+
+        let user_key = Pubkey::default();
+        let keeper_key = Pubkey::default();
+        let state = State::default();
+
+        let base_asset_amount_deleveraged = auto_deleverage_perp(
+            0,
+            &mut user,
+            &user_key,
+            &keeper_key,
+            &counterparty_map,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            slot,
+            now,
+            &state,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(base_asset_amount_deleveraged, 10 * BASE_PRECISION_U64);
+
+        // sold at the $105 bankruptcy price, collateral back to zero
+        assert_eq!(user.perp_positions[0].base_asset_amount, 0);
+        assert_eq!(
+            user.perp_positions[0].quote_asset_amount,
+            -50 * QUOTE_PRECISION_I64
+        );
+
+        let counterparty_b = counterparty_map.get_ref(&counterparty_b_key).unwrap();
+        assert_eq!(counterparty_b.perp_positions[0].base_asset_amount, 0);
+        assert_eq!(
+            counterparty_b.perp_positions[0].quote_asset_amount,
+            160 * QUOTE_PRECISION_I64
+        );
+        drop(counterparty_b);
+
+        let counterparty_a = counterparty_map.get_ref(&counterparty_a_key).unwrap();
+        assert_eq!(
+            counterparty_a.perp_positions[0].base_asset_amount,
+            -2 * BASE_PRECISION_I64
+        );
+        assert_eq!(
+            counterparty_a.perp_positions[0].quote_asset_amount,
+            390 * QUOTE_PRECISION_I64
+        );
+        drop(counterparty_a);
+
+        let market_after = perp_market_map.get_ref(&0).unwrap();
+        assert_eq!(market_after.amm.base_asset_amount_long, 0);
+        assert_eq!(
+            market_after.amm.base_asset_amount_short,
+            -2 * BASE_PRECISION_I128
+        );
+        assert_eq!(
+            market_after.amm.base_asset_amount_with_amm,
+            -2 * BASE_PRECISION_I128
+        );
+        assert_eq!(market_after.number_of_users_with_base, 1);
+        drop(market_after);
+
+        // nothing left to deleverage
+        let result = auto_deleverage_perp(
+            0,
+            &mut user,
+            &user_key,
+            &keeper_key,
+            &counterparty_map,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            slot,
+            now,
+            &state,
+            0,
+        );
+        assert_eq!(result, Err(ErrorCode::InvalidPerpPositionToAutoDeleverage));
+    }
+
+    #[test]
+    pub fn fail_auto_deleverage_covered_by_insurance_fund() {
+        let now = 0_i64;
+        let slot = 0_u64;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 50,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 10000000,
+                quote_asset_amount: -500 * QUOTE_PRECISION_I128,
+                base_asset_amount_long: 10 * BASE_PRECISION_I128,
+                base_asset_amount_short: -4 * BASE_PRECISION_I128,
+                base_asset_amount_with_amm: 6 * BASE_PRECISION_I128,
+                oracle: oracle_price_key,
+                historical_oracle_data: HistoricalOracleData::default_price(oracle_price.agg.price),
+                funding_period: ONE_HOUR,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            number_of_users_with_base: 2,
+            number_of_users: 2,
+            status: MarketStatus::Initialized,
+            auto_deleverage_enabled: true,
+            insurance_claim: InsuranceClaim {
+                quote_max_insurance: 100 * QUOTE_PRECISION_U64,
+                ..InsuranceClaim::default()
+            },
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        let mut user = User {
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: 10 * BASE_PRECISION_I64,
+                quote_asset_amount: -1100 * QUOTE_PRECISION_I64,
+                quote_entry_amount: -1100 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: -1100 * QUOTE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 50 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let counterparty_key = Pubkey::new_unique();
+        let mut counterparty = User {
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: -4 * BASE_PRECISION_I64,
+                quote_asset_amount: 600 * QUOTE_PRECISION_I64,
+                quote_entry_amount: 600 * QUOTE_PRECISION_I64,
+                quote_break_even_amount: 600 * QUOTE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+        create_anchor_account_info!(
+            counterparty,
+            &counterparty_key,
+            User,
+            counterparty_account_info
+        );
+        let counterparty_map = UserMap::load_one(&counterparty_account_info).unwrap();
+
+        // the $50 deficit fits in the insurance fund, it goes through resolve_perp_bankruptcy
+        let result = auto_deleverage_perp(
+            0,
+            &mut user,
+            &Pubkey::default(),
+            &Pubkey::default(),
+            &counterparty_map,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            slot,
+            now,
+            &State::default(),
+            1000 * QUOTE_PRECISION_U64,
+        );
+
+        assert_eq!(result, Err(ErrorCode::AutoDeleverageNotNeeded));
+        assert_eq!(
+            user.perp_positions[0].base_asset_amount,
+            10 * BASE_PRECISION_I64
+        );
+        assert_eq!(
+            counterparty_map
+                .get_ref(&counterparty_key)
+                .unwrap()
+                .perp_positions[0]
+                .base_asset_amount,
+            -4 * BASE_PRECISION_I64
+        );
+    }
+}
+
 pub mod resolve_spot_bankruptcy {
     use std::str::FromStr;
 
@@ -0,0 +1,184 @@
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program::invoke_signed;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::ids::address_lookup_table_program;
+use crate::signer::get_signer_seeds;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+const CREATE_LOOKUP_TABLE_INSTRUCTION_TAG: u32 = 0;
+const EXTEND_LOOKUP_TABLE_INSTRUCTION_TAG: u32 = 2;
+
+/// bytes of lookup table metadata before its addresses
+pub const LOOKUP_TABLE_META_SIZE: usize = 56;
+pub const LOOKUP_TABLE_MAX_ADDRESSES: usize = 256;
+
+pub fn get_lookup_table_address(authority: &Pubkey, recent_slot: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[authority.as_ref(), recent_slot.to_le_bytes().as_ref()],
+        &address_lookup_table_program::id(),
+    )
+}
+
+pub fn get_lookup_table_addresses(data: &[u8]) -> DriftResult<Vec<Pubkey>> {
+    validate!(
+        data.len() >= LOOKUP_TABLE_META_SIZE && (data.len() - LOOKUP_TABLE_META_SIZE) % 32 == 0,
+        ErrorCode::InvalidMarketLookupTable,
+        "lookup table data len {} is not a lookup table",
+        data.len()
+    )?;
+
+    Ok(data[LOOKUP_TABLE_META_SIZE..]
+        .chunks_exact(32)
+        .map(Pubkey::new)
+        .collect())
+}
+
+/// the candidates missing from the table, in order and without repeats
+pub fn get_new_lookup_table_addresses(
+    table_addresses: &[Pubkey],
+    candidates: &[Pubkey],
+) -> DriftResult<Vec<Pubkey>> {
+    let mut new_addresses: Vec<Pubkey> = vec![];
+    for candidate in candidates {
+        if !table_addresses.contains(candidate) && !new_addresses.contains(candidate) {
+            new_addresses.push(*candidate);
+        }
+    }
+
+    validate!(
+        table_addresses.len() + new_addresses.len() <= LOOKUP_TABLE_MAX_ADDRESSES,
+        ErrorCode::InvalidMarketLookupTable,
+        "lookup table with {} addresses can't fit {} more",
+        table_addresses.len(),
+        new_addresses.len()
+    )?;
+
+    Ok(new_addresses)
+}
+
+pub fn build_create_lookup_table_instruction(
+    lookup_table: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    recent_slot: u64,
+    bump: u8,
+) -> Instruction {
+    let mut data = CREATE_LOOKUP_TABLE_INSTRUCTION_TAG.to_le_bytes().to_vec();
+    data.extend_from_slice(&recent_slot.to_le_bytes());
+    data.push(bump);
+
+    Instruction {
+        program_id: address_lookup_table_program::id(),
+        data,
+        accounts: vec![
+            AccountMeta::new(*lookup_table, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        ],
+    }
+}
+
+pub fn build_extend_lookup_table_instruction(
+    lookup_table: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    new_addresses: &[Pubkey],
+) -> Instruction {
+    let mut data = EXTEND_LOOKUP_TABLE_INSTRUCTION_TAG.to_le_bytes().to_vec();
+    data.extend_from_slice(&(new_addresses.len() as u64).to_le_bytes());
+    for address in new_addresses {
+        data.extend_from_slice(address.as_ref());
+    }
+
+    Instruction {
+        program_id: address_lookup_table_program::id(),
+        data,
+        accounts: vec![
+            AccountMeta::new(*lookup_table, false),
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        ],
+    }
+}
+
+pub fn create_lookup_table<'info>(
+    lookup_table_program: &AccountInfo<'info>,
+    lookup_table: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    recent_slot: u64,
+    nonce: u8,
+) -> Result<()> {
+    let (lookup_table_address, bump) = get_lookup_table_address(authority.key, recent_slot);
+    validate!(
+        &lookup_table_address == lookup_table.key,
+        ErrorCode::InvalidMarketLookupTable,
+        "lookup table {} != {} for slot {}",
+        lookup_table.key,
+        lookup_table_address,
+        recent_slot
+    )?;
+
+    let instruction = build_create_lookup_table_instruction(
+        lookup_table.key,
+        authority.key,
+        payer.key,
+        recent_slot,
+        bump,
+    );
+
+    let signature_seeds = get_signer_seeds(&nonce);
+    let signers = &[&signature_seeds[..]];
+    invoke_signed(
+        &instruction,
+        &[
+            lookup_table.clone(),
+            authority.clone(),
+            payer.clone(),
+            system_program.clone(),
+            lookup_table_program.clone(),
+        ],
+        signers,
+    )
+    .map_err(Into::into)
+}
+
+pub fn extend_lookup_table<'info>(
+    lookup_table_program: &AccountInfo<'info>,
+    lookup_table: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    new_addresses: &[Pubkey],
+    nonce: u8,
+) -> Result<()> {
+    let instruction = build_extend_lookup_table_instruction(
+        lookup_table.key,
+        authority.key,
+        payer.key,
+        new_addresses,
+    );
+
+    let signature_seeds = get_signer_seeds(&nonce);
+    let signers = &[&signature_seeds[..]];
+    invoke_signed(
+        &instruction,
+        &[
+            lookup_table.clone(),
+            authority.clone(),
+            payer.clone(),
+            system_program.clone(),
+            lookup_table_program.clone(),
+        ],
+        signers,
+    )
+    .map_err(Into::into)
+}
@@ -0,0 +1,71 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::controller::lookup_table::{
+    build_extend_lookup_table_instruction, get_lookup_table_addresses,
+    get_new_lookup_table_addresses, LOOKUP_TABLE_MAX_ADDRESSES, LOOKUP_TABLE_META_SIZE,
+};
+use crate::error::ErrorCode;
+
+#[test]
+fn lookup_table_addresses() {
+    let addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let mut data = vec![0_u8; LOOKUP_TABLE_META_SIZE];
+    assert_eq!(get_lookup_table_addresses(&data).unwrap(), vec![]);
+
+    for address in addresses.iter() {
+        data.extend_from_slice(address.as_ref());
+    }
+    assert_eq!(
+        get_lookup_table_addresses(&data).unwrap(),
+        addresses.to_vec()
+    );
+
+    data.push(0);
+    assert_eq!(
+        get_lookup_table_addresses(&data),
+        Err(ErrorCode::InvalidMarketLookupTable)
+    );
+}
+
+#[test]
+fn new_lookup_table_addresses() {
+    let market = Pubkey::new_unique();
+    let oracle = Pubkey::new_unique();
+    let vault = Pubkey::new_unique();
+
+    // addresses already in the table and repeated candidates are skipped
+    let new_addresses =
+        get_new_lookup_table_addresses(&[market], &[market, oracle, vault, oracle]).unwrap();
+    assert_eq!(new_addresses, vec![oracle, vault]);
+
+    let full_table: Vec<Pubkey> = (0..LOOKUP_TABLE_MAX_ADDRESSES)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    assert_eq!(
+        get_new_lookup_table_addresses(&full_table, &[full_table[0]]).unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        get_new_lookup_table_addresses(&full_table, &[market]),
+        Err(ErrorCode::InvalidMarketLookupTable)
+    );
+}
+
+#[test]
+fn extend_lookup_table_instruction_data() {
+    let lookup_table = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let payer = Pubkey::new_unique();
+    let new_addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let instruction =
+        build_extend_lookup_table_instruction(&lookup_table, &authority, &payer, &new_addresses);
+
+    let mut expected_data = vec![2, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0];
+    expected_data.extend_from_slice(new_addresses[0].as_ref());
+    expected_data.extend_from_slice(new_addresses[1].as_ref());
+    assert_eq!(instruction.data, expected_data);
+    assert!(instruction.accounts[1].is_signer);
+    assert!(instruction.accounts[2].is_signer && instruction.accounts[2].is_writable);
+}
@@ -8,14 +8,16 @@ use crate::emit;
 use crate::error::{DriftResult, ErrorCode};
 use crate::get_struct_values;
 use crate::math::casting::Cast;
+use crate::math::constants::AMM_RESERVE_PRECISION_I128;
 use crate::math::cp_curve::{get_update_k_result, update_k};
-use crate::math::lp::calculate_settle_lp_metrics;
+use crate::math::lp::{calculate_lp_early_removal_penalty, calculate_settle_lp_metrics};
 use crate::math::position::calculate_base_asset_value_with_oracle_price;
 use crate::math::safe_math::SafeMath;
 
 use crate::state::events::{LPAction, LPRecord};
 use crate::state::oracle_map::OracleMap;
-use crate::state::perp_market::PerpMarket;
+use crate::state::perp_lp_range::PerpLpRange;
+use crate::state::perp_market::{MarketStatus, PerpMarket};
 use crate::state::perp_market_map::PerpMarketMap;
 use crate::state::state::State;
 use crate::state::user::PerpPosition;
@@ -26,10 +28,13 @@ use anchor_lang::prelude::Account;
 #[cfg(test)]
 mod tests;
 
+/// Adds n_shares of curve liquidity, crediting the position n_shares * share_multiplier lp shares.
+/// Shares beyond the curve liquidity added are taken over from the protocol owned liquidity
 pub fn mint_lp_shares(
     position: &mut PerpPosition,
     market: &mut PerpMarket,
     n_shares: u64,
+    share_multiplier: u64,
 ) -> DriftResult<()> {
     let amm = market.amm;
 
@@ -42,8 +47,10 @@ pub fn mint_lp_shares(
         position.last_quote_asset_amount_per_lp = amm.quote_asset_amount_per_lp.cast()?;
     }
 
+    let position_shares = n_shares.safe_mul(share_multiplier)?;
+
     // add share balance
-    position.lp_shares = position.lp_shares.safe_add(n_shares)?;
+    position.lp_shares = position.lp_shares.safe_add(position_shares)?;
 
     // update market state
     let new_sqrt_k = sqrt_k.safe_add(n_shares.cast()?)?;
@@ -52,7 +59,10 @@ pub fn mint_lp_shares(
     let update_k_result = get_update_k_result(market, new_sqrt_k_u192, true)?;
     update_k(market, &update_k_result)?;
 
-    market.amm.user_lp_shares = market.amm.user_lp_shares.safe_add(n_shares.cast()?)?;
+    market.amm.user_lp_shares = market
+        .amm
+        .user_lp_shares
+        .safe_add(position_shares.cast()?)?;
 
     crate::validation::perp_market::validate_perp_market(market)?;
     crate::validation::position::validate_perp_position_with_perp_market(position, market)?;
@@ -153,7 +163,16 @@ pub fn burn_lp_shares(
     market: &mut PerpMarket,
     shares_to_burn: u64,
     oracle_price: i64,
+    share_multiplier: u64,
 ) -> DriftResult<(PositionDelta, i64)> {
+    validate!(
+        shares_to_burn % share_multiplier == 0,
+        ErrorCode::UnableToBurnLPTokens,
+        "shares to burn {} not a multiple of the lp tier share multiplier {}",
+        shares_to_burn,
+        share_multiplier
+    )?;
+
     // settle
     let (position_delta, pnl) = settle_lp_position(position, market)?;
 
@@ -212,7 +231,10 @@ pub fn burn_lp_shares(
     market.amm.user_lp_shares = market.amm.user_lp_shares.safe_sub(shares_to_burn.cast()?)?;
 
     // update market state
-    let new_sqrt_k = market.amm.sqrt_k.safe_sub(shares_to_burn.cast()?)?;
+    let new_sqrt_k = market
+        .amm
+        .sqrt_k
+        .safe_sub(shares_to_burn.safe_div(share_multiplier)?.cast()?)?;
     let new_sqrt_k_u192 = U192::from(new_sqrt_k);
 
     let update_k_result = get_update_k_result(market, new_sqrt_k_u192, false)?;
@@ -234,12 +256,14 @@ pub fn remove_perp_lp_shares(
     market_index: u16,
     now: i64,
 ) -> DriftResult<()> {
+    let share_multiplier = user.perp_lp_tier.share_multiplier();
+
     // standardize n shares to burn
     let shares_to_burn: u64 = {
         let market = perp_market_map.get_ref(&market_index)?;
         crate::math::orders::standardize_base_asset_amount(
             shares_to_burn.cast()?,
-            market.amm.order_step_size,
+            market.amm.order_step_size.safe_mul(share_multiplier)?,
         )?
         .cast()?
     };
@@ -267,8 +291,29 @@ pub fn remove_perp_lp_shares(
     )?;
 
     let oracle_price = oracle_map.get_price_data(&market.amm.oracle)?.price;
-    let (position_delta, pnl) =
-        burn_lp_shares(position, &mut market, shares_to_burn, oracle_price)?;
+    let (mut position_delta, pnl) = burn_lp_shares(
+        position,
+        &mut market,
+        shares_to_burn,
+        oracle_price,
+        share_multiplier,
+    )?;
+
+    // winding down markets force lps out, so only voluntary early exits are penalized
+    if !market.is_reduce_only()? {
+        let penalty = calculate_lp_early_removal_penalty(
+            &market,
+            shares_to_burn,
+            oracle_price,
+            time_since_last_add_liquidity,
+        )?;
+
+        let penalty_paid = pay_lp_early_removal_penalty(position, &mut market, penalty)?;
+
+        position_delta.quote_asset_amount = position_delta
+            .quote_asset_amount
+            .safe_sub(penalty_paid.cast()?)?;
+    }
 
     emit!(LPRecord {
         ts: now,
@@ -283,3 +328,147 @@ pub fn remove_perp_lp_shares(
 
     Ok(())
 }
+
+/// Moves an early removal penalty from the removing lp to the remaining user lps, who collect it
+/// like fees when they next settle. Waived if no user lps remain to receive it
+pub fn pay_lp_early_removal_penalty(
+    position: &mut PerpPosition,
+    market: &mut PerpMarket,
+    penalty: u64,
+) -> DriftResult<u64> {
+    let user_lp_shares = market.amm.user_lp_shares;
+
+    if penalty == 0 || user_lp_shares == 0 {
+        return Ok(0);
+    }
+
+    update_quote_asset_amount(position, market, -penalty.cast()?)?;
+
+    // rounds down so lps are never credited more than was paid
+    let per_lp_penalty = penalty
+        .cast::<i128>()?
+        .safe_mul(AMM_RESERVE_PRECISION_I128)?
+        .safe_div(user_lp_shares.cast()?)?;
+
+    market.amm.quote_asset_amount_per_lp = market
+        .amm
+        .quote_asset_amount_per_lp
+        .safe_add(per_lp_penalty)?;
+
+    msg!("lp early removal penalty: {:?}", penalty);
+
+    Ok(penalty)
+}
+
+/// Parks a ranged lp's shares once the amm reserve price leaves its range and mints them back
+/// once the price re-enters it. Returns the action taken, if any
+pub fn update_lp_range_status(
+    user: &mut User,
+    user_key: &Pubkey,
+    perp_lp_range: &mut PerpLpRange,
+    market: &mut PerpMarket,
+    oracle_price: i64,
+    now: i64,
+) -> DriftResult<Option<LPAction>> {
+    let market_index = market.market_index;
+    let reserve_price = market.amm.reserve_price()?;
+    let share_multiplier = user.perp_lp_tier.share_multiplier();
+
+    if !perp_lp_range.is_in_range(reserve_price) {
+        let lp_shares = user
+            .get_perp_position(market_index)
+            .map_or(0, |position| position.lp_shares);
+
+        if lp_shares == 0 {
+            return Ok(None);
+        }
+
+        controller::funding::settle_funding_payment(user, user_key, market, now)?;
+
+        let time_since_last_add_liquidity = now.safe_sub(user.last_add_perp_lp_shares_ts)?;
+
+        let position = user.get_perp_position_mut(market_index)?;
+        let (mut position_delta, pnl) =
+            burn_lp_shares(position, market, lp_shares, oracle_price, share_multiplier)?;
+
+        // a range the price leaves right after adding is an early exit like any other
+        if !market.is_reduce_only()? {
+            let penalty = calculate_lp_early_removal_penalty(
+                market,
+                lp_shares,
+                oracle_price,
+                time_since_last_add_liquidity,
+            )?;
+
+            let penalty_paid = pay_lp_early_removal_penalty(position, market, penalty)?;
+
+            position_delta.quote_asset_amount = position_delta
+                .quote_asset_amount
+                .safe_sub(penalty_paid.cast()?)?;
+        }
+
+        // parked as curve liquidity so the shares are re-minted at the user's current tier
+        perp_lp_range.inactive_shares = perp_lp_range
+            .inactive_shares
+            .safe_add(lp_shares.safe_div(share_multiplier)?)?;
+
+        emit!(LPRecord {
+            ts: now,
+            action: LPAction::RemoveLiquidity,
+            user: *user_key,
+            n_shares: lp_shares,
+            market_index,
+            delta_base_asset_amount: position_delta.base_asset_amount,
+            delta_quote_asset_amount: position_delta.quote_asset_amount,
+            pnl,
+        });
+
+        Ok(Some(LPAction::RemoveLiquidity))
+    } else if perp_lp_range.inactive_shares > 0 {
+        validate!(
+            matches!(
+                market.status,
+                MarketStatus::Active
+                    | MarketStatus::FundingPaused
+                    | MarketStatus::FillPaused
+                    | MarketStatus::WithdrawPaused
+            ),
+            ErrorCode::MarketStatusInvalidForNewLP,
+            "Market Status doesn't allow for new LP liquidity"
+        )?;
+
+        validate!(
+            !user
+                .get_perp_position(market_index)
+                .map_or(false, |position| position.is_isolated),
+            ErrorCode::InvalidIsolatedPerpPosition,
+            "cant provide lp liquidity from an isolated perp position"
+        )?;
+
+        let inactive_shares = perp_lp_range.inactive_shares;
+
+        controller::funding::settle_funding_payment(user, user_key, market, now)?;
+
+        mint_lp_shares(
+            user.force_get_perp_position_mut(market_index)?,
+            market,
+            inactive_shares,
+            share_multiplier,
+        )?;
+
+        perp_lp_range.inactive_shares = 0;
+
+        emit!(LPRecord {
+            ts: now,
+            action: LPAction::AddLiquidity,
+            user: *user_key,
+            n_shares: inactive_shares.safe_mul(share_multiplier)?,
+            market_index,
+            ..LPRecord::default()
+        });
+
+        Ok(Some(LPAction::AddLiquidity))
+    } else {
+        Ok(None)
+    }
+}
@@ -19,7 +19,7 @@ fn test_full_long_settle() {
     };
     let og_market = market;
 
-    mint_lp_shares(&mut position, &mut market, BASE_PRECISION_U64).unwrap();
+    mint_lp_shares(&mut position, &mut market, BASE_PRECISION_U64, 1).unwrap();
 
     market.amm.base_asset_amount_per_lp = 10;
     market.amm.quote_asset_amount_per_lp = -10;
@@ -41,7 +41,7 @@ fn test_full_long_settle() {
 
     // burn
     let lp_shares = position.lp_shares;
-    burn_lp_shares(&mut position, &mut market, lp_shares, 0).unwrap();
+    burn_lp_shares(&mut position, &mut market, lp_shares, 0, 1).unwrap();
     assert_eq!(position.lp_shares, 0);
     assert_eq!(og_market.amm.sqrt_k, market.amm.sqrt_k);
 }
@@ -64,7 +64,7 @@ fn test_full_short_settle() {
         ..PerpMarket::default_test()
     };
 
-    mint_lp_shares(&mut position, &mut market, 100 * BASE_PRECISION_U64).unwrap();
+    mint_lp_shares(&mut position, &mut market, 100 * BASE_PRECISION_U64, 1).unwrap();
 
     market.amm.base_asset_amount_per_lp = -10;
     market.amm.quote_asset_amount_per_lp = 10;
@@ -93,7 +93,7 @@ fn test_partial_short_settle() {
         ..PerpMarket::default_test()
     };
 
-    mint_lp_shares(&mut position, &mut market, BASE_PRECISION_U64).unwrap();
+    mint_lp_shares(&mut position, &mut market, BASE_PRECISION_U64, 1).unwrap();
 
     market.amm.base_asset_amount_per_lp = -10;
     market.amm.quote_asset_amount_per_lp = 10;
@@ -112,7 +112,7 @@ fn test_partial_short_settle() {
     // burn
     let _position = position;
     let lp_shares = position.lp_shares;
-    burn_lp_shares(&mut position, &mut market, lp_shares, 0).unwrap();
+    burn_lp_shares(&mut position, &mut market, lp_shares, 0, 1).unwrap();
     assert_eq!(position.lp_shares, 0);
 }
 
@@ -143,3 +143,233 @@ fn test_partial_long_settle() {
     assert_eq!(position.last_base_asset_amount_per_lp, -10);
     assert_eq!(position.last_quote_asset_amount_per_lp, 10);
 }
+
+#[test]
+fn test_update_lp_range_status() {
+    use crate::math::constants::PRICE_PRECISION_U64;
+    use crate::state::events::LPAction;
+    use crate::state::perp_lp_range::PerpLpRange;
+    use crate::state::perp_market::MarketStatus;
+    use crate::state::user::User;
+    use anchor_lang::prelude::Pubkey;
+
+    let mut market = PerpMarket {
+        amm: AMM {
+            order_step_size: 1,
+            ..AMM::default_test()
+        },
+        status: MarketStatus::Active,
+        ..PerpMarket::default_test()
+    };
+    let og_sqrt_k = market.amm.sqrt_k;
+    let user_key = Pubkey::default();
+    let mut user = User::default();
+
+    mint_lp_shares(
+        user.force_get_perp_position_mut(0).unwrap(),
+        &mut market,
+        BASE_PRECISION_U64,
+        1,
+    )
+    .unwrap();
+    assert_eq!(market.amm.sqrt_k, og_sqrt_k + BASE_PRECISION_U64 as u128);
+
+    // reserve price of 1 is inside the range
+    let mut perp_lp_range = PerpLpRange {
+        lower_price: PRICE_PRECISION_U64 / 2,
+        upper_price: 2 * PRICE_PRECISION_U64,
+        ..PerpLpRange::default()
+    };
+    let action =
+        update_lp_range_status(&mut user, &user_key, &mut perp_lp_range, &mut market, 0, 0)
+            .unwrap();
+    assert!(action.is_none());
+
+    // price leaves the range, shares are parked
+    perp_lp_range
+        .update_range(2 * PRICE_PRECISION_U64, 3 * PRICE_PRECISION_U64)
+        .unwrap();
+    let action =
+        update_lp_range_status(&mut user, &user_key, &mut perp_lp_range, &mut market, 0, 0)
+            .unwrap();
+    assert!(action == Some(LPAction::RemoveLiquidity));
+    assert_eq!(perp_lp_range.inactive_shares, BASE_PRECISION_U64);
+    assert_eq!(user.get_perp_position(0).unwrap().lp_shares, 0);
+    assert_eq!(market.amm.user_lp_shares, 0);
+    assert_eq!(market.amm.sqrt_k, og_sqrt_k);
+
+    // nothing left to park
+    let action =
+        update_lp_range_status(&mut user, &user_key, &mut perp_lp_range, &mut market, 0, 0)
+            .unwrap();
+    assert!(action.is_none());
+
+    // price re-enters the range, shares are minted back
+    perp_lp_range
+        .update_range(PRICE_PRECISION_U64 / 2, 2 * PRICE_PRECISION_U64)
+        .unwrap();
+    let action =
+        update_lp_range_status(&mut user, &user_key, &mut perp_lp_range, &mut market, 0, 0)
+            .unwrap();
+    assert!(action == Some(LPAction::AddLiquidity));
+    assert_eq!(perp_lp_range.inactive_shares, 0);
+    assert_eq!(
+        user.get_perp_position(0).unwrap().lp_shares,
+        BASE_PRECISION_U64
+    );
+    assert_eq!(market.amm.user_lp_shares, BASE_PRECISION_U64 as u128);
+    assert_eq!(market.amm.sqrt_k, og_sqrt_k + BASE_PRECISION_U64 as u128);
+}
+
+#[test]
+fn test_update_lp_range_status_early_removal_penalty() {
+    use crate::math::constants::{PRICE_PRECISION_I64, PRICE_PRECISION_U64, QUOTE_PRECISION_I64};
+    use crate::state::events::LPAction;
+    use crate::state::perp_lp_range::PerpLpRange;
+    use crate::state::perp_market::MarketStatus;
+    use crate::state::user::User;
+    use anchor_lang::prelude::Pubkey;
+
+    let mut market = PerpMarket {
+        amm: AMM {
+            order_step_size: 1,
+            ..AMM::default_test()
+        },
+        status: MarketStatus::Active,
+        lp_min_duration: 3600,
+        lp_early_removal_penalty: 100, // 1%
+        ..PerpMarket::default_test()
+    };
+    let user_key = Pubkey::default();
+    let mut user = User {
+        last_add_perp_lp_shares_ts: 100,
+        ..User::default()
+    };
+    let mut remaining_position = PerpPosition::default();
+
+    mint_lp_shares(
+        user.force_get_perp_position_mut(0).unwrap(),
+        &mut market,
+        BASE_PRECISION_U64,
+        1,
+    )
+    .unwrap();
+    mint_lp_shares(&mut remaining_position, &mut market, BASE_PRECISION_U64, 1).unwrap();
+
+    // price is outside the range straight after adding
+    let mut perp_lp_range = PerpLpRange {
+        lower_price: 2 * PRICE_PRECISION_U64,
+        upper_price: 3 * PRICE_PRECISION_U64,
+        ..PerpLpRange::default()
+    };
+    let action = update_lp_range_status(
+        &mut user,
+        &user_key,
+        &mut perp_lp_range,
+        &mut market,
+        100 * PRICE_PRECISION_I64,
+        200,
+    )
+    .unwrap();
+    assert!(action == Some(LPAction::RemoveLiquidity));
+
+    // 1% of the $100 notional goes to the remaining lp
+    assert_eq!(
+        user.get_perp_position(0).unwrap().quote_asset_amount,
+        -QUOTE_PRECISION_I64
+    );
+    settle_lp_position(&mut remaining_position, &mut market).unwrap();
+    assert_eq!(remaining_position.quote_asset_amount, QUOTE_PRECISION_I64);
+}
+
+#[test]
+fn test_pay_lp_early_removal_penalty() {
+    use crate::math::constants::QUOTE_PRECISION_U64;
+
+    let mut market = PerpMarket {
+        amm: AMM {
+            order_step_size: 1,
+            ..AMM::default_test()
+        },
+        ..PerpMarket::default_test()
+    };
+
+    let mut leaving_position = PerpPosition::default();
+    let mut remaining_position = PerpPosition::default();
+    mint_lp_shares(&mut leaving_position, &mut market, BASE_PRECISION_U64, 1).unwrap();
+    mint_lp_shares(
+        &mut remaining_position,
+        &mut market,
+        4 * BASE_PRECISION_U64,
+        1,
+    )
+    .unwrap();
+
+    let lp_shares = leaving_position.lp_shares;
+    burn_lp_shares(&mut leaving_position, &mut market, lp_shares, 0, 1).unwrap();
+
+    let penalty = 2 * QUOTE_PRECISION_U64;
+    let paid = pay_lp_early_removal_penalty(&mut leaving_position, &mut market, penalty).unwrap();
+    assert_eq!(paid, penalty);
+    assert_eq!(leaving_position.quote_asset_amount, -(penalty as i64));
+
+    // the remaining lp collects the penalty on settle
+    settle_lp_position(&mut remaining_position, &mut market).unwrap();
+    assert_eq!(remaining_position.quote_asset_amount, penalty as i64);
+    assert_eq!(market.amm.quote_asset_amount, 0);
+
+    // waived once no user lps remain
+    let lp_shares = remaining_position.lp_shares;
+    burn_lp_shares(&mut remaining_position, &mut market, lp_shares, 0, 1).unwrap();
+    let paid = pay_lp_early_removal_penalty(&mut remaining_position, &mut market, penalty).unwrap();
+    assert_eq!(paid, 0);
+    assert_eq!(remaining_position.quote_asset_amount, penalty as i64);
+}
+
+#[test]
+fn test_pro_lp_tier_mint_and_burn() {
+    use crate::state::user::PerpLpTier;
+
+    let mut market = PerpMarket {
+        amm: AMM {
+            order_step_size: 1,
+            ..AMM::default_test()
+        },
+        ..PerpMarket::default_test()
+    };
+    let og_sqrt_k = market.amm.sqrt_k;
+    let share_multiplier = PerpLpTier::Pro.share_multiplier();
+    let mut position = PerpPosition::default();
+
+    // pro lps take over protocol owned liquidity on top of the curve liquidity they add
+    mint_lp_shares(
+        &mut position,
+        &mut market,
+        10 * BASE_PRECISION_U64,
+        share_multiplier,
+    )
+    .unwrap();
+    assert_eq!(position.lp_shares, 20 * BASE_PRECISION_U64);
+    assert_eq!(market.amm.user_lp_shares, 20 * AMM_RESERVE_PRECISION);
+    assert_eq!(market.amm.sqrt_k, og_sqrt_k + 10 * AMM_RESERVE_PRECISION);
+
+    // cant take over more than the protocol owns
+    let mut other_position = PerpPosition::default();
+    let mut market_copy = market;
+    assert!(mint_lp_shares(
+        &mut other_position,
+        &mut market_copy,
+        91 * BASE_PRECISION_U64,
+        share_multiplier,
+    )
+    .is_err());
+
+    // burns must map back to whole shares of curve liquidity
+    assert!(burn_lp_shares(&mut position, &mut market, 1, 0, share_multiplier).is_err());
+
+    let lp_shares = position.lp_shares;
+    burn_lp_shares(&mut position, &mut market, lp_shares, 0, share_multiplier).unwrap();
+    assert_eq!(position.lp_shares, 0);
+    assert_eq!(market.amm.user_lp_shares, 0);
+    assert_eq!(market.amm.sqrt_k, og_sqrt_k);
+}
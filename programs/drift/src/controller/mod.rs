@@ -1,14 +1,20 @@
 pub mod amm;
+pub mod fill_callback;
+pub mod fuel;
 pub mod funding;
 pub mod insurance;
 pub mod liquidation;
+pub mod lookup_table;
 pub mod lp;
+pub mod openbook_v2;
 pub mod orders;
 pub mod pda;
+pub mod phoenix;
 pub mod pnl;
 pub mod position;
 pub mod repeg;
 pub mod serum;
 pub mod spot_balance;
 pub mod spot_position;
+pub mod subaccount;
 pub mod token;
@@ -0,0 +1,127 @@
+use crate::error::{DriftResult, ErrorCode};
+use crate::signer::get_signer_seeds;
+use crate::state::token::SpotMarketVault;
+use anchor_lang::prelude::{AccountInfo, Program};
+use anchor_spl::token::Token;
+use borsh::BorshSerialize;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::msg;
+
+pub const PLACE_TAKE_ORDER_DISCRIMINATOR: [u8; 8] = [3, 44, 71, 3, 26, 199, 203, 85];
+
+#[derive(Clone, Copy, BorshSerialize, PartialEq, Debug, Eq)]
+pub enum OpenbookV2Side {
+    Bid,
+    Ask,
+}
+
+#[derive(Clone, Copy, BorshSerialize, PartialEq, Debug, Eq)]
+pub enum OpenbookV2PlaceOrderType {
+    Limit,
+    ImmediateOrCancel,
+    PostOnly,
+    Market,
+    PostOnlySlide,
+    FillOrKill,
+}
+
+#[derive(Clone, Copy, BorshSerialize, PartialEq, Debug, Eq)]
+pub struct OpenbookV2PlaceTakeOrderArgs {
+    pub side: OpenbookV2Side,
+    pub price_lots: i64,
+    pub max_base_lots: i64,
+    pub max_quote_lots_including_fees: i64,
+    pub order_type: OpenbookV2PlaceOrderType,
+    pub limit: u8,
+}
+
+pub struct OpenbookV2FulfillmentParams<'a, 'b> {
+    pub drift_signer: &'a AccountInfo<'b>,
+    pub openbook_v2_program_id: &'a AccountInfo<'b>,
+    pub openbook_v2_market: &'a AccountInfo<'b>,
+    pub openbook_v2_market_authority: &'a AccountInfo<'b>,
+    pub openbook_v2_event_heap: &'a AccountInfo<'b>,
+    pub openbook_v2_bids: &'a AccountInfo<'b>,
+    pub openbook_v2_asks: &'a AccountInfo<'b>,
+    pub openbook_v2_base_vault: &'a AccountInfo<'b>,
+    pub openbook_v2_quote_vault: &'a AccountInfo<'b>,
+    pub token_program: Program<'b, Token>,
+    pub system_program: &'a AccountInfo<'b>,
+    pub base_market_vault: SpotMarketVault<'b>,
+    pub quote_market_vault: SpotMarketVault<'b>,
+    pub signer_nonce: u8,
+}
+
+pub fn invoke_place_take_order<'a>(
+    openbook_v2_program: &AccountInfo<'a>,
+    drift_signer: &AccountInfo<'a>,
+    openbook_v2_market: &AccountInfo<'a>,
+    openbook_v2_market_authority: &AccountInfo<'a>,
+    openbook_v2_bids: &AccountInfo<'a>,
+    openbook_v2_asks: &AccountInfo<'a>,
+    openbook_v2_base_vault: &AccountInfo<'a>,
+    openbook_v2_quote_vault: &AccountInfo<'a>,
+    openbook_v2_event_heap: &AccountInfo<'a>,
+    drift_base_vault: &AccountInfo<'a>,
+    drift_quote_vault: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    args: OpenbookV2PlaceTakeOrderArgs,
+    nonce: u8,
+) -> DriftResult {
+    let mut data = PLACE_TAKE_ORDER_DISCRIMINATOR.to_vec();
+    args.serialize(&mut data).map_err(|e| {
+        msg!("{:?}", e);
+        ErrorCode::FailedOpenbookV2CPI
+    })?;
+
+    // the optional oracle and open orders admin accounts are passed as the program id,
+    // which anchor treats as none. markets with an open orders admin are rejected at init
+    let instruction = Instruction {
+        program_id: *openbook_v2_program.key,
+        data,
+        accounts: vec![
+            AccountMeta::new_readonly(*drift_signer.key, true),
+            AccountMeta::new(*drift_signer.key, true),
+            AccountMeta::new(*openbook_v2_market.key, false),
+            AccountMeta::new_readonly(*openbook_v2_market_authority.key, false),
+            AccountMeta::new(*openbook_v2_bids.key, false),
+            AccountMeta::new(*openbook_v2_asks.key, false),
+            AccountMeta::new(*openbook_v2_base_vault.key, false),
+            AccountMeta::new(*openbook_v2_quote_vault.key, false),
+            AccountMeta::new(*openbook_v2_event_heap.key, false),
+            AccountMeta::new(*drift_base_vault.key, false),
+            AccountMeta::new(*drift_quote_vault.key, false),
+            AccountMeta::new_readonly(*openbook_v2_program.key, false),
+            AccountMeta::new_readonly(*openbook_v2_program.key, false),
+            AccountMeta::new_readonly(*token_program.key, false),
+            AccountMeta::new_readonly(*system_program.key, false),
+            AccountMeta::new_readonly(*openbook_v2_program.key, false),
+        ],
+    };
+
+    let account_infos = [
+        openbook_v2_program.clone(),
+        drift_signer.clone(),
+        openbook_v2_market.clone(),
+        openbook_v2_market_authority.clone(),
+        openbook_v2_bids.clone(),
+        openbook_v2_asks.clone(),
+        openbook_v2_base_vault.clone(),
+        openbook_v2_quote_vault.clone(),
+        openbook_v2_event_heap.clone(),
+        drift_base_vault.clone(),
+        drift_quote_vault.clone(),
+        token_program.clone(),
+        system_program.clone(),
+    ];
+
+    let signer_seeds = get_signer_seeds(&nonce);
+    let signers_seeds = &[&signer_seeds[..]];
+
+    solana_program::program::invoke_signed_unchecked(&instruction, &account_infos, signers_seeds)
+        .map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::FailedOpenbookV2CPI
+        })
+}
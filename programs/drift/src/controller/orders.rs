@@ -10,11 +10,19 @@ use serum_dex::matching::Side;
 use solana_program::msg;
 
 use crate::controller::funding::settle_funding_payment;
+use crate::controller::openbook_v2::{
+    invoke_place_take_order, OpenbookV2FulfillmentParams, OpenbookV2PlaceOrderType,
+    OpenbookV2PlaceTakeOrderArgs, OpenbookV2Side,
+};
+use crate::controller::phoenix::{
+    invoke_swap, PhoenixFulfillmentParams, PhoenixImmediateOrCancelOrderPacket,
+    PhoenixSelfTradeBehavior, PhoenixSide,
+};
 use crate::controller::position;
 use crate::controller::position::{
     add_new_position, decrease_open_bids_and_asks, get_position_index, increase_open_bids_and_asks,
     update_lp_market_position, update_position_and_market, update_quote_asset_amount,
-    PositionDirection,
+    validate_open_interest_for_fill, PositionDirection,
 };
 use crate::controller::serum::{invoke_new_order, invoke_settle_funds, SerumFulfillmentParams};
 use crate::controller::spot_balance::{
@@ -28,18 +36,22 @@ use crate::error::DriftResult;
 use crate::error::ErrorCode;
 use crate::get_struct_values;
 use crate::get_then_update_id;
-use crate::instructions::OrderParams;
+use crate::instructions::{
+    CancelOrdersFilter, ModifyOrderParams, OrderParams, RfqMakerQuote, RfqTakerRequest,
+};
+use crate::load;
 use crate::load_mut;
-use crate::math::auction::calculate_auction_prices;
+use crate::math::auction::{calculate_auction_extension_end_price, calculate_auction_prices};
 use crate::math::casting::Cast;
 use crate::math::constants::{
-    BASE_PRECISION_U64, FEE_POOL_TO_REVENUE_POOL_THRESHOLD, FIVE_MINUTE, ONE_HOUR, PERP_DECIMALS,
-    QUOTE_SPOT_MARKET_INDEX,
+    BASE_PRECISION_U64, FEE_POOL_TO_REVENUE_POOL_THRESHOLD, FIVE_MINUTE,
+    MAX_PREDICTION_MARKET_PRICE_I64, ONE_HOUR, PERP_DECIMALS, QUOTE_SPOT_MARKET_INDEX,
 };
 use crate::math::fees::{FillFees, SerumFillFees};
 use crate::math::fulfillment::{
     determine_perp_fulfillment_methods, determine_spot_fulfillment_methods,
 };
+use crate::math::funding::calculate_predicted_funding_rate;
 use crate::math::liquidation::validate_user_not_being_liquidated;
 use crate::math::matching::{
     are_orders_same_market_but_different_sides, calculate_fill_for_matched_orders,
@@ -47,28 +59,41 @@ use crate::math::matching::{
 };
 use crate::math::oracle;
 use crate::math::oracle::{is_oracle_valid_for_action, DriftAction, OracleValidity};
+use crate::math::phoenix::calculate_phoenix_price_in_ticks;
+use crate::math::position::get_position_update_type;
 use crate::math::safe_math::SafeMath;
 use crate::math::serum::{
     calculate_serum_limit_price, calculate_serum_max_coin_qty,
     calculate_serum_max_native_pc_quantity,
 };
 use crate::math::spot_balance::{get_signed_token_amount, get_token_amount};
+use crate::math::spot_withdraw::{check_max_token_borrows, check_net_withdraw_limit};
 use crate::math::stats::calculate_new_twap;
 use crate::math::{amm, fees, margin::*, orders::*};
+use crate::math_error;
 use crate::{controller, PostOnlyParam};
 
 use crate::math::amm::calculate_amm_available_liquidity;
 use crate::math::safe_unwrap::SafeUnwrap;
 use crate::print_error;
 use crate::state::events::{emit_stack, get_order_action_record, OrderActionRecord, OrderRecord};
-use crate::state::events::{OrderAction, OrderActionExplanation};
+use crate::state::events::{
+    FillRecordBuffer, IcebergReplenishRecord, OrderAction, OrderActionExplanation,
+};
+use crate::state::fuel_map::FuelMap;
 use crate::state::fulfillment::{PerpFulfillmentMethod, SpotFulfillmentMethod};
+use crate::state::market_fee_override::{get_market_fee_structure, MarketFeeOverride};
+use crate::state::market_whitelist::{
+    find_market_whitelist_entry, validate_market_whitelist, MarketWhitelistEntry,
+};
+use crate::state::openbook_v2::load_openbook_v2_market;
 use crate::state::oracle::OraclePriceData;
 use crate::state::oracle_map::OracleMap;
-use crate::state::perp_market::{MarketStatus, PerpMarket};
+use crate::state::perp_market::{MarketStatus, PerpMarket, PerpOperation};
 use crate::state::perp_market_map::PerpMarketMap;
+use crate::state::phoenix::load_phoenix_market;
 use crate::state::serum::{get_best_bid_and_ask, load_open_orders, load_serum_market};
-use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+use crate::state::spot_market::{SpotBalanceType, SpotMarket, SpotOperation};
 use crate::state::spot_market_map::SpotMarketMap;
 use crate::state::state::FeeStructure;
 use crate::state::state::*;
@@ -88,6 +113,107 @@ mod tests;
 #[cfg(test)]
 mod amm_jit_tests;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaceOrderOptions {
+    pub enforce_margin_check: bool,
+    pub risk_increasing: bool,
+    pub existing_order_id: Option<u32>,
+    /// entry for the user's authority, required to place orders in a market with its whitelist enabled
+    pub market_whitelist_entry: Option<MarketWhitelistEntry>,
+    /// the signer can cancel the user's orders, required for replace_existing_user_order_id
+    pub can_cancel_orders: bool,
+}
+
+impl Default for PlaceOrderOptions {
+    fn default() -> Self {
+        Self {
+            enforce_margin_check: true,
+            risk_increasing: false,
+            existing_order_id: None,
+            market_whitelist_entry: None,
+            can_cancel_orders: false,
+        }
+    }
+}
+
+impl PlaceOrderOptions {
+    pub fn update_risk_increasing(&mut self, risk_increasing: bool) {
+        self.risk_increasing = self.risk_increasing || risk_increasing;
+    }
+}
+
+/// a user_order_id already in use rejects the order, unless the params ask to replace the order
+/// holding it so a client retrying a placement can't end up with duplicates. only an order in the
+/// same market can be replaced, and only by a signer that can cancel the user's orders
+fn handle_user_order_id_conflict(
+    user: &mut User,
+    user_key: &Pubkey,
+    params: &OrderParams,
+    can_cancel_orders: bool,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    now: i64,
+    slot: u64,
+) -> DriftResult {
+    if params.user_order_id == 0 {
+        return Ok(());
+    }
+
+    let user_order_id_already_used = user
+        .orders
+        .iter()
+        .position(|order| order.user_order_id == params.user_order_id);
+
+    if let Some(order_index) = user_order_id_already_used {
+        if !params.replace_existing_user_order_id {
+            msg!("user_order_id is already in use {}", params.user_order_id);
+            return Err(ErrorCode::UserOrderIdAlreadyInUse);
+        }
+
+        validate!(
+            can_cancel_orders,
+            ErrorCode::UserOrderIdAlreadyInUse,
+            "user_order_id {} is in use and the signer cant cancel orders to replace it",
+            params.user_order_id
+        )?;
+
+        let existing_order = &user.orders[order_index];
+        validate!(
+            existing_order.market_type == params.market_type
+                && existing_order.market_index == params.market_index,
+            ErrorCode::UserOrderIdAlreadyInUse,
+            "user_order_id {} is in use by an order in {:?} market {}",
+            params.user_order_id,
+            existing_order.market_type,
+            existing_order.market_index
+        )?;
+
+        msg!(
+            "replacing order {} with user_order_id {}",
+            user.orders[order_index].order_id,
+            params.user_order_id
+        );
+
+        cancel_order(
+            order_index,
+            user,
+            user_key,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            now,
+            slot,
+            OrderActionExplanation::UserOrderIdReplaced,
+            None,
+            0,
+            false,
+        )?;
+    }
+
+    Ok(())
+}
+
 pub fn place_perp_order(
     state: &State,
     user: &AccountLoader<User>,
@@ -96,6 +222,7 @@ pub fn place_perp_order(
     oracle_map: &mut OracleMap,
     clock: &Clock,
     params: OrderParams,
+    options: &mut PlaceOrderOptions,
 ) -> DriftResult {
     let now = clock.unix_timestamp;
     let slot = clock.slot;
@@ -135,27 +262,28 @@ pub fn place_perp_order(
         return Ok(());
     }
 
+    handle_user_order_id_conflict(
+        user,
+        &user_key,
+        &params,
+        options.can_cancel_orders,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+    )?;
+
     let new_order_index = user
         .orders
         .iter()
         .position(|order| order.status.eq(&OrderStatus::Init))
         .ok_or(ErrorCode::MaxNumberOfOrders)?;
 
-    if params.user_order_id > 0 {
-        let user_order_id_already_used = user
-            .orders
-            .iter()
-            .position(|order| order.user_order_id == params.user_order_id);
-
-        if user_order_id_already_used.is_some() {
-            msg!("user_order_id is already in use {}", params.user_order_id);
-            return Err(ErrorCode::UserOrderIdAlreadyInUse);
-        }
-    }
-
     let market_index = params.market_index;
     let market = &perp_market_map.get_ref(&market_index)?;
-    let force_reduce_only = market.is_reduce_only()?;
+    let force_reduce_only = market.is_reduce_only()?
+        || market.is_operation_paused(PerpOperation::RiskIncreasingOrder)?;
 
     validate!(
         !matches!(market.status, MarketStatus::Initialized),
@@ -169,6 +297,13 @@ pub fn place_perp_order(
         "Market is in settlement mode",
     )?;
 
+    validate_market_whitelist(
+        market.amm.whitelist_enabled,
+        market_index,
+        &user.authority,
+        options.market_whitelist_entry.as_ref(),
+    )?;
+
     let position_index = get_position_index(&user.perp_positions, market_index)
         .or_else(|_| add_new_position(&mut user.perp_positions, market_index))?;
 
@@ -185,7 +320,8 @@ pub fn place_perp_order(
             market.amm.order_step_size
         )?;
 
-        let base_asset_amount = if params.base_asset_amount == u64::MAX {
+        let base_asset_amount = if params.base_asset_amount == u64::MAX || params.max_margin_sizing
+        {
             calculate_max_perp_order_size(
                 user,
                 position_index,
@@ -209,13 +345,26 @@ pub fn place_perp_order(
     };
 
     let oracle_price_data = oracle_map.get_price_data(&market.amm.oracle)?;
+    let tick_size = market.get_order_tick_size(oracle_price_data.price)?;
     let (auction_start_price, auction_end_price, auction_duration) = get_auction_params(
         &params,
         oracle_price_data,
-        market.amm.order_tick_size,
+        tick_size,
         state.min_perp_auction_duration,
     )?;
 
+    // auctions derived from the oracle can start above 1, oracle auctions are offsets
+    // and are bounded when they're priced
+    let (auction_start_price, auction_end_price) =
+        if market.is_prediction_market() && params.order_type != OrderType::Oracle {
+            (
+                auction_start_price.min(MAX_PREDICTION_MARKET_PRICE_I64),
+                auction_end_price.min(MAX_PREDICTION_MARKET_PRICE_I64),
+            )
+        } else {
+            (auction_start_price, auction_end_price)
+        };
+
     validate!(
         params.market_type == MarketType::Perp,
         ErrorCode::InvalidOrderMarketType,
@@ -227,10 +376,15 @@ pub fn place_perp_order(
         order_type: params.order_type,
         market_type: params.market_type,
         slot,
-        order_id: get_then_update_id!(user, next_order_id),
+        order_id: match options.existing_order_id {
+            Some(order_id) => order_id,
+            None => get_then_update_id!(user, next_order_id),
+        },
+        linked_order_id: 0,
+        twap_interval: params.twap_interval.unwrap_or(0),
         user_order_id: params.user_order_id,
         market_index: params.market_index,
-        price: standardize_price(params.price, market.amm.order_tick_size, params.direction)?,
+        price: standardize_price(params.price, tick_size, params.direction)?,
         existing_position_direction,
         base_asset_amount: order_base_asset_amount,
         base_asset_amount_filled: 0,
@@ -239,7 +393,7 @@ pub fn place_perp_order(
         reduce_only: params.reduce_only || force_reduce_only,
         trigger_price: standardize_price(
             params.trigger_price.unwrap_or(0),
-            market.amm.order_tick_size,
+            tick_size,
             params.direction,
         )?,
         trigger_condition: params.trigger_condition,
@@ -250,6 +404,17 @@ pub fn place_perp_order(
         auction_end_price,
         auction_duration,
         max_ts,
+        trail_amount: params.trail_amount.unwrap_or(0),
+        display_quantity: params.display_quantity.unwrap_or(0),
+        trigger_funding_rate: params.trigger_funding_rate.unwrap_or(0),
+        trail_type: params.trail_type,
+        twap_slice_count: params.twap_slice_count.unwrap_or(0),
+        twap_slices_remaining: params.twap_slice_count.unwrap_or(0),
+        auction_curve: params.auction_curve,
+        auction_step_count: params.auction_step_count.unwrap_or(0),
+        auction_extended: false,
+        builder_fee_share: params.builder_fee_share.unwrap_or(0),
+        max_margin_sizing: params.max_margin_sizing,
         padding: [0; 3],
     };
 
@@ -257,7 +422,10 @@ pub fn place_perp_order(
         oracle_map.get_price_data(&market.amm.oracle)?,
         market,
         &new_order,
-        &state.oracle_guard_rails.validity,
+        &state
+            .oracle_guard_rails
+            .validity
+            .with_override(&market.amm.validity_guard_rails_override),
     )?;
 
     match validate_order(&new_order, market, valid_oracle_price, slot) {
@@ -295,17 +463,39 @@ pub fn place_perp_order(
         <= worst_case_base_asset_amount_before.unsigned_abs()
         && order_risk_reducing;
 
+    options.update_risk_increasing(!risk_decreasing);
+
     // Order fails if it's risk increasing and it brings the user collateral below the margin requirement
-    let meets_initial_margin_requirement = meets_place_order_margin_requirement(
-        user,
-        perp_market_map,
-        spot_market_map,
-        oracle_map,
-        risk_decreasing,
-    )?;
+    // When placing several orders at once, the check only runs for the last one
+    if options.enforce_margin_check {
+        let meets_initial_margin_requirement = meets_place_order_margin_requirement(
+            user,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            !options.risk_increasing,
+        )?;
+
+        if !meets_initial_margin_requirement {
+            return Err(ErrorCode::InvalidOrderForInitialMarginReq);
+        }
 
-    if !meets_initial_margin_requirement {
-        return Err(ErrorCode::InvalidOrderForInitialMarginReq);
+        if options.risk_increasing
+            && !meets_isolated_perp_position_margin_requirement(
+                user,
+                market_index,
+                perp_market_map,
+                MarginRequirementType::Initial,
+                oracle_map,
+                None,
+            )?
+        {
+            msg!(
+                "isolated perp position in market {} below initial margin requirement",
+                market_index
+            );
+            return Err(ErrorCode::InvalidOrderForInitialMarginReq);
+        }
     }
 
     if force_reduce_only && !risk_decreasing {
@@ -443,6 +633,40 @@ pub fn cancel_orders(
     market_type: Option<MarketType>,
     market_index: Option<u16>,
     direction: Option<PositionDirection>,
+) -> DriftResult<Vec<u32>> {
+    cancel_orders_with_filter(
+        user,
+        user_key,
+        filler_key,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+        explanation,
+        market_type,
+        market_index,
+        direction,
+        CancelOrdersFilter::default(),
+    )
+}
+
+/// cancels every open order matching all the filters in one pass. market_type and market_index
+/// only filter when both are given
+pub fn cancel_orders_with_filter(
+    user: &mut User,
+    user_key: &Pubkey,
+    filler_key: Option<&Pubkey>,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    now: i64,
+    slot: u64,
+    explanation: OrderActionExplanation,
+    market_type: Option<MarketType>,
+    market_index: Option<u16>,
+    direction: Option<PositionDirection>,
+    filter: CancelOrdersFilter,
 ) -> DriftResult<Vec<u32>> {
     let mut canceled_order_ids: Vec<u32> = vec![];
     for order_index in 0..user.orders.len() {
@@ -466,6 +690,10 @@ pub fn cancel_orders(
             }
         }
 
+        if !filter.matches(&user.orders[order_index]) {
+            continue;
+        }
+
         canceled_order_ids.push(user.orders[order_index].order_id);
         cancel_order(
             order_index,
@@ -594,6 +822,17 @@ pub fn cancel_order(
 
     validate!(order_status == OrderStatus::Open, ErrorCode::OrderNotOpen)?;
 
+    // canceling a leg that never executed releases its linked order
+    if user.orders[order_index].is_linked()
+        && user.orders[order_index].base_asset_amount_filled == 0
+    {
+        if let Ok(linked_order_index) =
+            user.get_order_index(user.orders[order_index].linked_order_id)
+        {
+            user.orders[linked_order_index].linked_order_id = 0;
+        }
+    }
+
     // When save in the record, we want the status to be canceled
     user.orders[order_index].status = OrderStatus::Canceled;
 
@@ -668,121 +907,494 @@ pub fn cancel_order(
     Ok(())
 }
 
-pub fn fill_perp_order(
+pub fn modify_order(
     order_id: u32,
     state: &State,
-    user: &AccountLoader<User>,
-    user_stats: &AccountLoader<UserStats>,
-    spot_market_map: &SpotMarketMap,
+    user_loader: &AccountLoader<User>,
     perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
     oracle_map: &mut OracleMap,
-    filler: &AccountLoader<User>,
-    filler_stats: &AccountLoader<UserStats>,
-    makers_and_referrer: &UserMap,
-    makers_and_referrer_stats: &UserStatsMap,
-    jit_maker_order_id: Option<u32>,
     clock: &Clock,
-) -> DriftResult<(u64, bool)> {
+    params: ModifyOrderParams,
+) -> DriftResult {
     let now = clock.unix_timestamp;
     let slot = clock.slot;
+    let user_key = user_loader.key();
+    let mut user = load_mut!(user_loader)?;
 
-    let filler_key = filler.key();
-    let user_key = user.key();
-    let user = &mut load_mut!(user)?;
-    let user_stats = &mut load_mut!(user_stats)?;
-
-    let order_index = user
-        .orders
-        .iter()
-        .position(|order| order.order_id == order_id)
-        .ok_or_else(print_error!(ErrorCode::OrderDoesNotExist))?;
+    let order_index = match user.get_order_index(order_id) {
+        Ok(order_index) => order_index,
+        Err(_) => {
+            msg!("could not find order id {}", order_id);
+            return Err(ErrorCode::InvalidOrder);
+        }
+    };
 
-    let (order_status, market_index, order_market_type) =
-        get_struct_values!(user.orders[order_index], status, market_index, market_type);
+    let existing_order = user.orders[order_index];
 
     validate!(
-        order_market_type == MarketType::Perp,
-        ErrorCode::InvalidOrderMarketType,
-        "must be perp order"
+        existing_order.is_limit_order() && !existing_order.triggered(),
+        ErrorCode::InvalidOrder,
+        "only resting limit orders can be modified"
     )?;
 
-    // settle lp position so its tradeable
-    let mut market = perp_market_map.get_ref_mut(&market_index)?;
-    controller::lp::settle_funding_payment_then_lp(user, &user_key, &mut market, now)?;
+    let base_asset_amount = params
+        .base_asset_amount
+        .unwrap_or(existing_order.base_asset_amount);
+    let price = params.price.unwrap_or(existing_order.price);
 
     validate!(
-        matches!(
-            market.status,
-            MarketStatus::Active
-                | MarketStatus::FundingPaused
-                | MarketStatus::ReduceOnly
-                | MarketStatus::WithdrawPaused
-        ),
-        ErrorCode::MarketFillOrderPaused,
-        "Market unavailable for fills"
+        base_asset_amount > existing_order.base_asset_amount_filled,
+        ErrorCode::InvalidOrder,
+        "base asset amount ({}) must be greater than amount already filled ({})",
+        base_asset_amount,
+        existing_order.base_asset_amount_filled
     )?;
 
-    drop(market);
+    // shrinking an order at the same price keeps its order id and slot
+    if price == existing_order.price
+        && base_asset_amount <= existing_order.base_asset_amount
+        && params.post_only.is_none()
+    {
+        let mut modified_order = existing_order;
+        modified_order.base_asset_amount = base_asset_amount;
 
-    validate!(
-        order_status == OrderStatus::Open,
-        ErrorCode::OrderNotOpen,
-        "Order not open"
-    )?;
+        match existing_order.market_type {
+            MarketType::Perp => {
+                let market = perp_market_map.get_ref(&existing_order.market_index)?;
+                let valid_oracle_price = get_valid_oracle_price(
+                    oracle_map.get_price_data(&market.amm.oracle)?,
+                    &market,
+                    &modified_order,
+                    &state
+                        .oracle_guard_rails
+                        .validity
+                        .with_override(&market.amm.validity_guard_rails_override),
+                )?;
 
-    validate!(
-        !user.orders[order_index].must_be_triggered() || user.orders[order_index].triggered(),
-        ErrorCode::OrderMustBeTriggeredFirst,
-        "Order must be triggered first"
-    )?;
+                validate_order(&modified_order, &market, valid_oracle_price, slot)?;
+            }
+            MarketType::Spot => {
+                let spot_market = spot_market_map.get_ref(&existing_order.market_index)?;
+                let valid_oracle_price =
+                    Some(oracle_map.get_price_data(&spot_market.oracle)?.price);
 
-    if user.is_bankrupt() {
-        msg!("user is bankrupt");
-        return Ok((0, false));
-    }
+                validate_spot_order(
+                    &modified_order,
+                    valid_oracle_price,
+                    slot,
+                    spot_market.order_step_size,
+                    spot_market.order_tick_size,
+                    spot_market.get_margin_ratio(&MarginRequirementType::Initial)?,
+                    spot_market.get_margin_ratio(&MarginRequirementType::Maintenance)?,
+                    spot_market.min_order_size,
+                )?;
+            }
+        }
 
-    match validate_user_not_being_liquidated(
-        user,
-        perp_market_map,
-        spot_market_map,
-        oracle_map,
-        state.liquidation_margin_buffer_ratio,
-    ) {
-        Ok(_) => {}
-        Err(_) => {
-            msg!("user is being liquidated");
-            return Ok((0, false));
+        let base_asset_amount_decrease = existing_order
+            .base_asset_amount
+            .safe_sub(base_asset_amount)?;
+
+        if !existing_order.must_be_triggered() {
+            match existing_order.market_type {
+                MarketType::Perp => {
+                    let position_index =
+                        get_position_index(&user.perp_positions, existing_order.market_index)?;
+                    position::decrease_open_bids_and_asks(
+                        &mut user.perp_positions[position_index],
+                        &existing_order.direction,
+                        base_asset_amount_decrease,
+                    )?;
+                }
+                MarketType::Spot => {
+                    let spot_position_index =
+                        user.get_spot_position_index(existing_order.market_index)?;
+                    decrease_spot_open_bids_and_asks(
+                        &mut user.spot_positions[spot_position_index],
+                        &existing_order.direction,
+                        base_asset_amount_decrease,
+                    )?;
+                }
+            }
         }
-    }
 
-    let reserve_price_before: u64;
-    let oracle_reserve_price_spread_pct_before: i64;
-    let is_oracle_valid: bool;
-    let oracle_validity: OracleValidity;
-    let oracle_price: i64;
-    let mut amm_is_available = !state.amm_paused()?;
-    {
-        let market = &mut perp_market_map.get_ref_mut(&market_index)?;
-        amm_is_available &= market.status != MarketStatus::AmmPaused;
-        validation::perp_market::validate_perp_market(market)?;
-        validate!(
-            market.is_active(now)?,
-            ErrorCode::MarketFillOrderPaused,
-            "Market is in settlement mode",
-        )?;
+        user.orders[order_index] = modified_order;
 
-        let oracle_price_data = &oracle_map.get_price_data(&market.amm.oracle)?;
-        oracle_validity = oracle::oracle_validity(
-            market.amm.historical_oracle_data.last_oracle_price_twap,
-            oracle_price_data,
-            &state.oracle_guard_rails.validity,
-        )?;
+        let order_record = OrderRecord {
+            ts: now,
+            user: user_key,
+            order: user.orders[order_index],
+        };
+        emit!(order_record);
 
-        is_oracle_valid =
-            is_oracle_valid_for_action(oracle_validity, Some(DriftAction::FillOrderAmm))?;
+        user.update_last_active_slot(slot);
 
-        reserve_price_before = market.amm.reserve_price()?;
-        oracle_reserve_price_spread_pct_before = amm::calculate_oracle_twap_5min_mark_spread_pct(
+        return Ok(());
+    }
+
+    let post_only = params.post_only.unwrap_or(if existing_order.post_only {
+        PostOnlyParam::MustPostOnly
+    } else {
+        PostOnlyParam::None
+    });
+
+    let order_params = OrderParams {
+        order_type: existing_order.order_type,
+        market_type: existing_order.market_type,
+        direction: existing_order.direction,
+        user_order_id: existing_order.user_order_id,
+        base_asset_amount: base_asset_amount.safe_sub(existing_order.base_asset_amount_filled)?,
+        price,
+        market_index: existing_order.market_index,
+        reduce_only: existing_order.reduce_only,
+        post_only,
+        immediate_or_cancel: existing_order.immediate_or_cancel,
+        max_ts: Some(existing_order.max_ts),
+        trigger_price: Some(existing_order.trigger_price),
+        trigger_condition: existing_order.trigger_condition,
+        oracle_price_offset: Some(existing_order.oracle_price_offset),
+        trail_type: existing_order.trail_type,
+        trail_amount: Some(existing_order.trail_amount),
+        display_quantity: Some(existing_order.display_quantity),
+        trigger_funding_rate: Some(existing_order.trigger_funding_rate),
+        builder_fee_share: Some(existing_order.builder_fee_share),
+        max_margin_sizing: existing_order.max_margin_sizing,
+        ..OrderParams::default()
+    };
+
+    cancel_order(
+        order_index,
+        &mut user,
+        &user_key,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+        OrderActionExplanation::None,
+        None,
+        0,
+        false,
+    )?;
+
+    // cancel and place need to borrow the user separately
+    drop(user);
+
+    let mut options = PlaceOrderOptions {
+        existing_order_id: Some(order_id),
+        ..PlaceOrderOptions::default()
+    };
+
+    match existing_order.market_type {
+        MarketType::Perp => place_perp_order(
+            state,
+            user_loader,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            clock,
+            order_params,
+            &mut options,
+        )?,
+        MarketType::Spot => place_spot_order(
+            state,
+            user_loader,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            clock,
+            order_params,
+            &mut options,
+        )?,
+    }
+
+    // the re-placed order keeps its id, so restore any one-cancels-other link
+    if existing_order.is_linked() {
+        let user = &mut load_mut!(user_loader)?;
+        if let (Ok(order_index), Ok(linked_order_index)) = (
+            user.get_order_index(order_id),
+            user.get_order_index(existing_order.linked_order_id),
+        ) {
+            user.orders[order_index].linked_order_id = existing_order.linked_order_id;
+            user.orders[linked_order_index].linked_order_id = order_id;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn link_orders(user: &mut User, order_id: u32, linked_order_id: u32) -> DriftResult {
+    validate!(
+        order_id != linked_order_id,
+        ErrorCode::InvalidLinkedOrder,
+        "Can not link order to itself"
+    )?;
+
+    let order_index = user.get_order_index(order_id)?;
+    let linked_order_index = user.get_order_index(linked_order_id)?;
+
+    for index in [order_index, linked_order_index] {
+        let order = &user.orders[index];
+
+        validate!(
+            !order.is_linked(),
+            ErrorCode::InvalidLinkedOrder,
+            "Order {} is already linked",
+            order.order_id
+        )?;
+
+        validate!(
+            order.base_asset_amount_filled == 0 && !order.triggered(),
+            ErrorCode::InvalidLinkedOrder,
+            "Order {} has already been filled or triggered",
+            order.order_id
+        )?;
+    }
+
+    validate!(
+        user.orders[order_index].market_type == user.orders[linked_order_index].market_type
+            && user.orders[order_index].market_index
+                == user.orders[linked_order_index].market_index,
+        ErrorCode::InvalidLinkedOrder,
+        "Linked orders must be for the same market"
+    )?;
+
+    user.orders[order_index].linked_order_id = linked_order_id;
+    user.orders[linked_order_index].linked_order_id = order_id;
+
+    Ok(())
+}
+
+/// Cancels open orders whose linked order has been filled (partially or fully) or triggered
+pub fn cancel_linked_orders(
+    user: &mut User,
+    user_key: &Pubkey,
+    filler_key: Option<&Pubkey>,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    now: i64,
+    slot: u64,
+) -> DriftResult {
+    for order_index in 0..user.orders.len() {
+        if user.orders[order_index].status != OrderStatus::Open
+            || !user.orders[order_index].is_linked()
+        {
+            continue;
+        }
+
+        let linked_order_executed =
+            match user.get_order_index(user.orders[order_index].linked_order_id) {
+                Ok(linked_order_index) => {
+                    let linked_order = &user.orders[linked_order_index];
+                    linked_order.base_asset_amount_filled > 0 || linked_order.triggered()
+                }
+                // linked orders canceled before executing unlink, so a missing order was filled
+                Err(_) => true,
+            };
+
+        if linked_order_executed {
+            cancel_order(
+                order_index,
+                user,
+                user_key,
+                perp_market_map,
+                spot_market_map,
+                oracle_map,
+                now,
+                slot,
+                OrderActionExplanation::LinkedOrderExecuted,
+                filler_key,
+                0,
+                false,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn fill_perp_order(
+    order_id: u32,
+    state: &State,
+    user: &AccountLoader<User>,
+    user_stats: &AccountLoader<UserStats>,
+    spot_market_map: &SpotMarketMap,
+    perp_market_map: &PerpMarketMap,
+    oracle_map: &mut OracleMap,
+    filler: &AccountLoader<User>,
+    filler_stats: &AccountLoader<UserStats>,
+    makers_and_referrer: &UserMap,
+    makers_and_referrer_stats: &UserStatsMap,
+    jit_maker_order_id: Option<u32>,
+    clock: &Clock,
+    fuel_map: &FuelMap,
+    market_fee_override: Option<&MarketFeeOverride>,
+    market_whitelist_entries: &[MarketWhitelistEntry],
+) -> DriftResult<(u64, u64, bool)> {
+    let now = clock.unix_timestamp;
+    let slot = clock.slot;
+
+    let filler_key = filler.key();
+    let user_key = user.key();
+    let user = &mut load_mut!(user)?;
+    let user_stats = &mut load_mut!(user_stats)?;
+
+    let order_index = user
+        .orders
+        .iter()
+        .position(|order| order.order_id == order_id)
+        .ok_or_else(print_error!(ErrorCode::OrderDoesNotExist))?;
+
+    let (order_status, market_index, order_market_type) =
+        get_struct_values!(user.orders[order_index], status, market_index, market_type);
+
+    validate!(
+        order_market_type == MarketType::Perp,
+        ErrorCode::InvalidOrderMarketType,
+        "must be perp order"
+    )?;
+
+    // settle lp position so its tradeable
+    let mut market = perp_market_map.get_ref_mut(&market_index)?;
+    controller::lp::settle_funding_payment_then_lp(user, &user_key, &mut market, now)?;
+
+    validate!(
+        matches!(
+            market.status,
+            MarketStatus::Active
+                | MarketStatus::FundingPaused
+                | MarketStatus::ReduceOnly
+                | MarketStatus::WithdrawPaused
+        ),
+        ErrorCode::MarketFillOrderPaused,
+        "Market unavailable for fills"
+    )?;
+
+    validate!(
+        !market.is_operation_paused(PerpOperation::Fill)?,
+        ErrorCode::MarketFillOrderPaused,
+        "Fills paused for market {}",
+        market_index
+    )?;
+
+    // the order waits out a tripped breaker like any other unfillable order, it stays open and the
+    // filler's transaction still succeeds. every update_amm re-evaluates the breaker
+    if market.is_operation_paused(PerpOperation::OracleCircuitBreaker)? {
+        msg!(
+            "oracle circuit breaker tripped for perp market {}",
+            market_index
+        );
+        return Ok((0, 0, false));
+    }
+
+    let auction_extension_duration = market.auction_extension_duration;
+    let auction_extension_price_buffer = market.auction_extension_price_buffer;
+
+    drop(market);
+
+    validate!(
+        order_status == OrderStatus::Open,
+        ErrorCode::OrderNotOpen,
+        "Order not open"
+    )?;
+
+    validate!(
+        !user.orders[order_index].must_be_triggered() || user.orders[order_index].triggered(),
+        ErrorCode::OrderMustBeTriggeredFirst,
+        "Order must be triggered first"
+    )?;
+
+    validate!(
+        user.orders[order_index].order_type != OrderType::Twap,
+        ErrorCode::InvalidTwapOrder,
+        "Twap order must be filled with fill_twap_order"
+    )?;
+
+    if user.is_bankrupt() {
+        msg!("user is bankrupt");
+        return Ok((0, 0, false));
+    }
+
+    match validate_user_not_being_liquidated(
+        user,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        state.liquidation_margin_buffer_ratio,
+    ) {
+        Ok(_) => {}
+        Err(_) => {
+            msg!("user is being liquidated");
+            return Ok((0, 0, false));
+        }
+    }
+
+    // give an unfilled market order one more auction before it falls back to the amm
+    if auction_extension_duration > 0
+        && user.orders[order_index].is_eligible_for_auction_extension(slot)?
+    {
+        extend_order_auction(
+            &mut user.orders[order_index],
+            auction_extension_duration,
+            auction_extension_price_buffer,
+            slot,
+        )?;
+    }
+
+    if user.orders[order_index].max_margin_sizing {
+        update_max_margin_order_size(
+            user,
+            order_index,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+        )?;
+    }
+
+    let reserve_price_before: u64;
+    let oracle_reserve_price_spread_pct_before: i64;
+    let is_oracle_valid: bool;
+    let oracle_validity: OracleValidity;
+    let oracle_price: i64;
+    let fee_structure: FeeStructure;
+    let mut amm_is_available = !state.amm_paused()?;
+    {
+        let market = &mut perp_market_map.get_ref_mut(&market_index)?;
+        amm_is_available &= market.status != MarketStatus::AmmPaused
+            && !market.is_operation_paused(PerpOperation::AmmFill)?;
+        validation::perp_market::validate_perp_market(market)?;
+        validate!(
+            market.is_active(now)?,
+            ErrorCode::MarketFillOrderPaused,
+            "Market is in settlement mode",
+        )?;
+
+        fee_structure = get_market_fee_structure(
+            &state.perp_fee_structure,
+            MarketType::Perp,
+            market_index,
+            market.amm.fee_override_enabled,
+            market_fee_override,
+            now,
+        )?;
+
+        let oracle_price_data = &oracle_map.get_price_data(&market.amm.oracle)?;
+        oracle_validity = oracle::oracle_validity(
+            market.amm.historical_oracle_data.last_oracle_price_twap,
+            oracle_price_data,
+            &state
+                .oracle_guard_rails
+                .validity
+                .with_override(&market.amm.validity_guard_rails_override),
+        )?;
+
+        is_oracle_valid =
+            is_oracle_valid_for_action(oracle_validity, Some(DriftAction::FillOrderAmm))?;
+
+        reserve_price_before = market.amm.reserve_price()?;
+        oracle_reserve_price_spread_pct_before = amm::calculate_oracle_twap_5min_mark_spread_pct(
             &market.amm,
             Some(reserve_price_before),
         )?;
@@ -820,7 +1432,9 @@ pub fn fill_perp_order(
         &filler_key,
         state.perp_fee_structure.flat_filler_fee,
         oracle_price,
+        is_oracle_valid,
         jit_maker_order_id,
+        market_whitelist_entries,
         now,
         slot,
     )?;
@@ -832,10 +1446,19 @@ pub fn fill_perp_order(
         slot,
     )?;
 
+    // the approved cap applies at fill time so revoking it stops fees on resting orders
+    let builder_fee_share = user.orders[order_index]
+        .builder_fee_share
+        .min(user_stats.builder_max_fee_share);
+    let builder_user_key =
+        get_builder_user_key(user_stats, builder_fee_share, makers_and_referrer)?;
+    let total_fee_paid_before = user_stats.fees.total_fee_paid;
+
     let should_expire_order = should_expire_order(user, order_index, now)?;
 
     let position_index =
         get_position_index(&user.perp_positions, user.orders[order_index].market_index)?;
+    resize_reduce_only_order(user, order_index, position_index)?;
     let existing_base_asset_amount = user.perp_positions[position_index].base_asset_amount;
     let should_cancel_reduce_only =
         should_cancel_reduce_only_order(&user.orders[order_index], existing_base_asset_amount)?;
@@ -872,37 +1495,53 @@ pub fn fill_perp_order(
             filler_reward,
             false,
         )?;
-        return Ok((0, true));
+        return Ok((0, 0, true));
     }
 
-    let (base_asset_amount, potentially_risk_increasing, mut updated_user_state) =
-        fulfill_perp_order(
-            user,
-            order_index,
-            &user_key,
-            user_stats,
-            makers_and_referrer,
-            makers_and_referrer_stats,
-            &maker_orders_info,
-            &mut filler.as_deref_mut(),
-            &filler_key,
-            &mut filler_stats.as_deref_mut(),
-            referrer_info,
-            spot_market_map,
-            perp_market_map,
-            oracle_map,
-            &state.perp_fee_structure,
-            reserve_price_before,
-            valid_oracle_price,
-            now,
-            slot,
-            state.min_perp_auction_duration,
-            amm_is_available,
-        )?;
-
-    let base_asset_amount_after = user.perp_positions[position_index].base_asset_amount;
-    let should_cancel_reduce_only =
-        should_cancel_reduce_only_order(&user.orders[order_index], base_asset_amount_after)?;
+    let (
+        base_asset_amount,
+        quote_asset_amount,
+        potentially_risk_increasing,
+        mut updated_user_state,
+    ) = fulfill_perp_order(
+        user,
+        order_index,
+        &user_key,
+        user_stats,
+        makers_and_referrer,
+        makers_and_referrer_stats,
+        &maker_orders_info,
+        &mut filler.as_deref_mut(),
+        &filler_key,
+        &mut filler_stats.as_deref_mut(),
+        referrer_info,
+        spot_market_map,
+        perp_market_map,
+        oracle_map,
+        &fee_structure,
+        reserve_price_before,
+        valid_oracle_price,
+        now,
+        slot,
+        state.min_perp_auction_duration,
+        amm_is_available,
+        fuel_map,
+    )?;
+
+    if let Some(builder_user_key) = builder_user_key {
+        let taker_fee = user_stats
+            .fees
+            .total_fee_paid
+            .safe_sub(total_fee_paid_before)?;
+        let mut builder = makers_and_referrer.get_ref_mut(&builder_user_key)?;
+        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+        pay_builder_reward_for_perps(&mut builder, &mut market, taker_fee, builder_fee_share)?;
+    }
+
+    resize_reduce_only_order(user, order_index, position_index)?;
+    let base_asset_amount_after = user.perp_positions[position_index].base_asset_amount;
+    let should_cancel_reduce_only =
+        should_cancel_reduce_only_order(&user.orders[order_index], base_asset_amount_after)?;
 
     if should_cancel_reduce_only {
         updated_user_state = true;
@@ -936,8 +1575,33 @@ pub fn fill_perp_order(
         )?
     }
 
+    cancel_linked_orders(
+        user,
+        &user_key,
+        Some(&filler_key),
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+    )?;
+
+    for (maker_key, _, _) in maker_orders_info.iter() {
+        let mut maker = makers_and_referrer.get_ref_mut(maker_key)?;
+        cancel_linked_orders(
+            &mut maker,
+            maker_key,
+            Some(&filler_key),
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            now,
+            slot,
+        )?;
+    }
+
     if !updated_user_state {
-        return Ok((base_asset_amount, updated_user_state));
+        return Ok((base_asset_amount, quote_asset_amount, updated_user_state));
     }
 
     {
@@ -948,17 +1612,6 @@ pub fn fill_perp_order(
             potentially_risk_increasing,
             Some(oracle_reserve_price_spread_pct_before),
         )?;
-
-        let open_interest = market.get_open_interest();
-        let max_open_interest = market.amm.max_open_interest;
-
-        validate!(
-            max_open_interest == 0 || max_open_interest > open_interest,
-            ErrorCode::MaxOpenInterest,
-            "open interest ({}) > max open interest ({})",
-            open_interest,
-            max_open_interest
-        )?;
     }
 
     // Try to update the funding rate at the end of every trade
@@ -980,7 +1633,149 @@ pub fn fill_perp_order(
 
     user.update_last_active_slot(slot);
 
-    Ok((base_asset_amount, updated_user_state))
+    Ok((base_asset_amount, quote_asset_amount, updated_user_state))
+}
+
+/// resizes a max margin order to what the user's free collateral supports right now, ignoring the
+/// order's own unfilled amount. the order is left as is if nothing can be added
+pub fn update_max_margin_order_size(
+    user: &mut User,
+    order_index: usize,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+) -> DriftResult {
+    let (market_index, direction) =
+        get_struct_values!(user.orders[order_index], market_index, direction);
+    let position_index = get_position_index(&user.perp_positions, market_index)?;
+
+    let base_asset_amount_unfilled =
+        user.orders[order_index].get_base_asset_amount_unfilled(None)?;
+    decrease_open_bids_and_asks(
+        &mut user.perp_positions[position_index],
+        &direction,
+        base_asset_amount_unfilled,
+    )?;
+
+    let max_base_asset_amount = calculate_max_perp_order_size(
+        user,
+        position_index,
+        market_index,
+        direction,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+    )?;
+
+    let updated_base_asset_amount_unfilled = if max_base_asset_amount == 0 {
+        base_asset_amount_unfilled
+    } else {
+        max_base_asset_amount
+    };
+
+    increase_open_bids_and_asks(
+        &mut user.perp_positions[position_index],
+        &direction,
+        updated_base_asset_amount_unfilled,
+    )?;
+
+    let order = &mut user.orders[order_index];
+    if updated_base_asset_amount_unfilled != base_asset_amount_unfilled {
+        msg!(
+            "resizing max margin order {}: base asset amount {} -> {}",
+            order.order_id,
+            order.base_asset_amount,
+            order
+                .base_asset_amount_filled
+                .safe_add(updated_base_asset_amount_unfilled)?
+        );
+    }
+    order.base_asset_amount = order
+        .base_asset_amount_filled
+        .safe_add(updated_base_asset_amount_unfilled)?;
+
+    Ok(())
+}
+
+/// shrinks an open reduce only order to the part of the position it can still reduce, so a
+/// position closed down elsewhere can't leave it overstating open bids/asks. orders with nothing
+/// left to reduce are left for should_cancel_reduce_only_order
+pub fn resize_reduce_only_order(
+    user: &mut User,
+    order_index: usize,
+    position_index: usize,
+) -> DriftResult<bool> {
+    let existing_base_asset_amount = user.perp_positions[position_index].base_asset_amount;
+
+    let order = &user.orders[order_index];
+    if order.status != OrderStatus::Open || !order.reduce_only {
+        return Ok(false);
+    }
+
+    let base_asset_amount_unfilled = order.get_base_asset_amount_unfilled(None)?;
+    let base_asset_amount_reducible =
+        order.get_base_asset_amount_unfilled(Some(existing_base_asset_amount))?;
+
+    if base_asset_amount_reducible == 0 || base_asset_amount_reducible == base_asset_amount_unfilled
+    {
+        return Ok(false);
+    }
+
+    // untriggered orders aren't counted in open bids/asks yet
+    if !order.must_be_triggered() || order.triggered() {
+        let direction = order.direction;
+        decrease_open_bids_and_asks(
+            &mut user.perp_positions[position_index],
+            &direction,
+            base_asset_amount_unfilled.safe_sub(base_asset_amount_reducible)?,
+        )?;
+    }
+
+    let order = &mut user.orders[order_index];
+    let base_asset_amount = order
+        .base_asset_amount_filled
+        .safe_add(base_asset_amount_reducible)?;
+
+    msg!(
+        "resizing reduce only order {}: base asset amount {} -> {}",
+        order.order_id,
+        order.base_asset_amount,
+        base_asset_amount
+    );
+
+    order.base_asset_amount = base_asset_amount;
+
+    Ok(true)
+}
+
+fn extend_order_auction(
+    order: &mut Order,
+    auction_extension_duration: u8,
+    auction_extension_price_buffer: u32,
+    slot: u64,
+) -> DriftResult<bool> {
+    let extended_end_price =
+        calculate_auction_extension_end_price(order, auction_extension_price_buffer)?;
+
+    // already at the order's limit price, nothing to widen
+    if extended_end_price == order.auction_end_price {
+        return Ok(false);
+    }
+
+    msg!(
+        "extending auction for order {}: end price {} -> {}",
+        order.order_id,
+        order.auction_end_price,
+        extended_end_price
+    );
+
+    order.auction_start_price = order.auction_end_price;
+    order.auction_end_price = extended_end_price;
+    order.auction_duration = auction_extension_duration;
+    order.slot = slot;
+    order.auction_extended = true;
+
+    Ok(true)
 }
 
 pub fn validate_market_within_price_band(
@@ -1055,12 +1850,19 @@ fn get_maker_orders_info(
     filler_key: &Pubkey,
     filler_reward: u64,
     oracle_price: i64,
+    is_oracle_valid: bool,
     jit_maker_order_id: Option<u32>,
+    market_whitelist_entries: &[MarketWhitelistEntry],
     now: i64,
     slot: u64,
 ) -> DriftResult<Vec<(Pubkey, usize, u64)>> {
     let maker_direction = taker_order.direction.opposite();
 
+    let whitelist_enabled = perp_market_map
+        .get_ref(&taker_order.market_index)?
+        .amm
+        .whitelist_enabled;
+
     let mut maker_orders_info = Vec::with_capacity(16);
 
     for (maker_key, user_account_loader) in makers_and_referrer.0.iter() {
@@ -1074,6 +1876,28 @@ fn get_maker_orders_info(
             continue;
         }
 
+        // the whitelist can be revoked after the maker's orders were placed
+        if whitelist_enabled
+            && find_market_whitelist_entry(
+                market_whitelist_entries,
+                taker_order.market_index,
+                &maker.authority,
+            )
+            .is_none()
+        {
+            continue;
+        }
+
+        // fill callbacks are only invoked for the taker
+        if maker.has_fill_callback {
+            continue;
+        }
+
+        // protected makers cant be crossed while the oracle is stale
+        if maker.is_protected_maker && !is_oracle_valid {
+            continue;
+        }
+
         let mut market = perp_market_map.get_ref_mut(&taker_order.market_index)?;
         let maker_order_price_and_indexes = find_maker_orders(
             &maker,
@@ -1083,6 +1907,7 @@ fn get_maker_orders_info(
             Some(oracle_price),
             slot,
             market.amm.order_tick_size,
+            market.is_prediction_market(),
         )?;
 
         if maker_order_price_and_indexes.is_empty() {
@@ -1211,12 +2036,20 @@ fn insert_maker_order_info(
     }
 }
 
+#[derive(Clone, Copy)]
+struct ReferrerInfo {
+    authority: Pubkey,
+    user: Pubkey,
+    /// authority and user of the referrer's own referrer when it takes a share of the reward
+    second_level: Option<(Pubkey, Pubkey)>,
+}
+
 fn get_referrer_info(
     user_stats: &UserStats,
     makers_and_referrer: &UserMap,
     makers_and_referrer_stats: &UserStatsMap,
     slot: u64,
-) -> DriftResult<Option<(Pubkey, Pubkey)>> {
+) -> DriftResult<Option<ReferrerInfo>> {
     if user_stats.referrer.eq(&Pubkey::default()) {
         return Ok(None);
     }
@@ -1251,7 +2084,52 @@ fn get_referrer_info(
         return Err(ErrorCode::ReferrerNotFound);
     }
 
-    Ok(Some((referrer_authority_key, referrer_user_key)))
+    let second_level_authority_key = {
+        let referrer_stats = makers_and_referrer_stats.get_ref(&referrer_authority_key)?;
+        if referrer_stats.second_level_referrer_reward_share == 0
+            || !referrer_stats.has_referrer()
+            || referrer_stats.referrer == user_stats.authority
+        {
+            None
+        } else {
+            Some(referrer_stats.referrer)
+        }
+    };
+
+    let second_level = match second_level_authority_key {
+        Some(second_level_authority_key) => {
+            validate!(
+                makers_and_referrer_stats
+                    .0
+                    .contains_key(&second_level_authority_key),
+                ErrorCode::ReferrerStatsNotFound,
+                "Second level referrer stats not found"
+            )?;
+
+            let mut second_level_user_key = Pubkey::default();
+            for (second_level_key, second_level_referrer) in makers_and_referrer.0.iter() {
+                let second_level_referrer = load!(second_level_referrer)?;
+                if second_level_referrer.authority == second_level_authority_key
+                    && second_level_referrer.sub_account_id == 0
+                {
+                    second_level_user_key = *second_level_key;
+                }
+            }
+
+            if second_level_user_key == Pubkey::default() {
+                return Err(ErrorCode::ReferrerNotFound);
+            }
+
+            Some((second_level_authority_key, second_level_user_key))
+        }
+        None => None,
+    };
+
+    Ok(Some(ReferrerInfo {
+        authority: referrer_authority_key,
+        user: referrer_user_key,
+        second_level,
+    }))
 }
 
 fn fulfill_perp_order(
@@ -1265,7 +2143,7 @@ fn fulfill_perp_order(
     filler: &mut Option<&mut User>,
     filler_key: &Pubkey,
     filler_stats: &mut Option<&mut UserStats>,
-    referrer_info: Option<(Pubkey, Pubkey)>,
+    referrer_info: Option<ReferrerInfo>,
     spot_market_map: &SpotMarketMap,
     perp_market_map: &PerpMarketMap,
     oracle_map: &mut OracleMap,
@@ -1276,7 +2154,8 @@ fn fulfill_perp_order(
     slot: u64,
     min_auction_duration: u8,
     amm_is_available: bool,
-) -> DriftResult<(u64, bool, bool)> {
+    fuel_map: &FuelMap,
+) -> DriftResult<(u64, u64, bool, bool)> {
     let market_index = user.orders[user_order_index].market_index;
 
     let user_position_index = get_position_index(&user.perp_positions, market_index)?;
@@ -1298,16 +2177,18 @@ fn fulfill_perp_order(
             amm_is_available,
             slot,
             min_auction_duration,
+            market.is_prediction_market(),
         )?
     };
 
     if fulfillment_methods.is_empty() {
-        return Ok((0, false, false));
+        return Ok((0, 0, false, false));
     }
 
     let mut base_asset_amount = 0_u64;
     let mut quote_asset_amount = 0_u64;
     let mut makers_filled: BTreeMap<Pubkey, bool> = BTreeMap::new();
+    let mut fill_record_buffer = FillRecordBuffer::default();
     for fulfillment_method in fulfillment_methods.iter() {
         if user.orders[user_order_index].status != OrderStatus::Open {
             break;
@@ -1315,9 +2196,15 @@ fn fulfill_perp_order(
         let mut market = perp_market_map.get_ref_mut(&market_index)?;
         let user_order_direction = user.orders[user_order_index].direction;
 
+        let mut maker_authority = None;
         let (fill_base_asset_amount, fill_quote_asset_amount) = match fulfillment_method {
             PerpFulfillmentMethod::AMM(maker_price) => {
-                let (mut referrer, mut referrer_stats) = get_referrer(
+                let (
+                    mut referrer,
+                    mut referrer_stats,
+                    mut second_level_referrer,
+                    mut second_level_referrer_stats,
+                ) = get_referrer(
                     &referrer_info,
                     makers_and_referrer,
                     makers_and_referrer_stats,
@@ -1341,10 +2228,13 @@ fn fulfill_perp_order(
                         filler_stats,
                         &mut referrer.as_deref_mut(),
                         &mut referrer_stats.as_deref_mut(),
+                        &mut second_level_referrer.as_deref_mut(),
+                        &mut second_level_referrer_stats.as_deref_mut(),
                         fee_structure,
                         None,
                         *maker_price,
                         true,
+                        &mut fill_record_buffer,
                     )?;
 
                 (fill_base_asset_amount, fill_quote_asset_amount)
@@ -1357,7 +2247,12 @@ fn fulfill_perp_order(
                     Some(makers_and_referrer_stats.get_ref_mut(&maker.authority)?)
                 };
 
-                let (mut referrer, mut referrer_stats) = get_referrer(
+                let (
+                    mut referrer,
+                    mut referrer_stats,
+                    mut second_level_referrer,
+                    mut second_level_referrer_stats,
+                ) = get_referrer(
                     &referrer_info,
                     makers_and_referrer,
                     makers_and_referrer_stats,
@@ -1380,29 +2275,49 @@ fn fulfill_perp_order(
                         filler_key,
                         &mut referrer.as_deref_mut(),
                         &mut referrer_stats.as_deref_mut(),
+                        &mut second_level_referrer.as_deref_mut(),
+                        &mut second_level_referrer_stats.as_deref_mut(),
                         reserve_price_before,
                         valid_oracle_price,
                         now,
                         slot,
                         fee_structure,
                         oracle_map,
+                        &mut fill_record_buffer,
                     )?;
 
                 if fill_base_asset_amount != 0 {
                     makers_filled.insert(*maker_key, true);
                 }
 
+                maker_authority = Some(maker.authority);
+
                 (fill_base_asset_amount, fill_quote_asset_amount)
             }
         };
 
+        controller::fuel::accrue_fill_fuel(
+            fuel_map,
+            &MarketType::Perp,
+            market_index,
+            &user.authority,
+            maker_authority.as_ref(),
+            fill_quote_asset_amount,
+            now,
+        )?;
+
         base_asset_amount = base_asset_amount.safe_add(fill_base_asset_amount)?;
         quote_asset_amount = quote_asset_amount.safe_add(fill_quote_asset_amount)?;
-        market
-            .amm
-            .update_volume_24h(fill_quote_asset_amount, user_order_direction, now)?;
+        market.update_fill_stats(
+            fill_base_asset_amount,
+            fill_quote_asset_amount,
+            user_order_direction,
+            now,
+        )?;
     }
 
+    fill_record_buffer.flush()?;
+
     let perp_market = perp_market_map.get_ref(&market_index)?;
     let taker_maintenance_margin_buffer = calculate_maintenance_buffer_ratio(
         perp_market.margin_ratio_initial,
@@ -1416,7 +2331,7 @@ fn fulfill_perp_order(
     )?;
     drop(perp_market);
 
-    let (_, taker_total_collateral, taker_margin_requirement_plus_buffer, _) =
+    let (taker_margin_requirement, taker_total_collateral, taker_margin_requirement_plus_buffer, _) =
         calculate_margin_requirement_and_total_collateral(
             user,
             perp_market_map,
@@ -1434,18 +2349,60 @@ fn fulfill_perp_order(
         return Err(ErrorCode::InsufficientCollateral);
     }
 
+    update_perp_position_liquidation_prices(
+        user,
+        perp_market_map,
+        oracle_map,
+        taker_total_collateral.safe_sub(taker_margin_requirement.cast()?)?,
+    )?;
+
+    if !meets_isolated_perp_position_margin_requirement(
+        user,
+        market_index,
+        perp_market_map,
+        MarginRequirementType::Maintenance,
+        oracle_map,
+        Some(taker_maintenance_margin_buffer.cast()?),
+    )? {
+        msg!(
+            "taker breached isolated maintenance requirements for perp market {}",
+            market_index
+        );
+        return Err(ErrorCode::InsufficientCollateral);
+    }
+
     for (maker_key, _) in makers_filled {
-        let maker = makers_and_referrer.get_ref(&maker_key)?;
+        let mut maker = makers_and_referrer.get_ref_mut(&maker_key)?;
 
-        let (_, maker_total_collateral, maker_margin_requirement_plus_buffer, _) =
-            calculate_margin_requirement_and_total_collateral(
-                &maker,
-                perp_market_map,
-                MarginRequirementType::Maintenance,
-                spot_market_map,
-                oracle_map,
-                Some(maker_maintenance_margin_buffer.cast()?),
-            )?;
+        if !meets_isolated_perp_position_margin_requirement(
+            &maker,
+            market_index,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            oracle_map,
+            Some(maker_maintenance_margin_buffer.cast()?),
+        )? {
+            msg!(
+                "maker ({}) breached isolated maintenance requirements for perp market {}",
+                maker_key,
+                market_index
+            );
+            return Err(ErrorCode::InsufficientCollateral);
+        }
+
+        let (
+            maker_margin_requirement,
+            maker_total_collateral,
+            maker_margin_requirement_plus_buffer,
+            _,
+        ) = calculate_margin_requirement_and_total_collateral(
+            &maker,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            spot_market_map,
+            oracle_map,
+            Some(maker_maintenance_margin_buffer.cast()?),
+        )?;
 
         if maker_total_collateral < maker_margin_requirement_plus_buffer.cast()? {
             msg!(
@@ -1456,6 +2413,13 @@ fn fulfill_perp_order(
             );
             return Err(ErrorCode::InsufficientCollateral);
         }
+
+        update_perp_position_liquidation_prices(
+            &mut maker,
+            perp_market_map,
+            oracle_map,
+            maker_total_collateral.safe_sub(maker_margin_requirement.cast()?)?,
+        )?;
     }
 
     let position_base_asset_amount_after =
@@ -1466,31 +2430,127 @@ fn fulfill_perp_order(
 
     let updated_user_state = base_asset_amount != 0;
 
-    Ok((base_asset_amount, risk_increasing, updated_user_state))
+    Ok((
+        base_asset_amount,
+        quote_asset_amount,
+        risk_increasing,
+        updated_user_state,
+    ))
+}
+
+fn get_builder_user_key(
+    user_stats: &UserStats,
+    builder_fee_share: u8,
+    makers_and_referrer: &UserMap,
+) -> DriftResult<Option<Pubkey>> {
+    if builder_fee_share == 0 || user_stats.approved_builder.eq(&Pubkey::default()) {
+        return Ok(None);
+    }
+
+    for (builder_key, builder) in makers_and_referrer.0.iter() {
+        let builder = load!(builder)?;
+        if builder.authority == user_stats.approved_builder && builder.sub_account_id == 0 {
+            return Ok(Some(*builder_key));
+        }
+    }
+
+    Err(ErrorCode::BuilderNotFound)
 }
 
 #[allow(clippy::type_complexity)]
 fn get_referrer<'a>(
-    referrer_info: &'a Option<(Pubkey, Pubkey)>,
+    referrer_info: &'a Option<ReferrerInfo>,
     makers_and_referrer: &'a UserMap,
     makers_and_referrer_stats: &'a UserStatsMap,
     maker: Option<&User>,
-) -> DriftResult<(Option<RefMut<'a, User>>, Option<RefMut<'a, UserStats>>)> {
-    let (referrer_authority_key, referrer_user_key) = match referrer_info {
-        Some(referrer_keys) => referrer_keys,
-        None => return Ok((None, None)),
+) -> DriftResult<(
+    Option<RefMut<'a, User>>,
+    Option<RefMut<'a, UserStats>>,
+    Option<RefMut<'a, User>>,
+    Option<RefMut<'a, UserStats>>,
+)> {
+    let referrer_info = match referrer_info {
+        Some(referrer_info) => referrer_info,
+        None => return Ok((None, None, None, None)),
     };
 
     if let Some(maker) = maker {
-        if &maker.authority == referrer_authority_key {
-            return Ok((None, None));
+        if maker.authority == referrer_info.authority {
+            return Ok((None, None, None, None));
+        }
+    }
+
+    let referrer = makers_and_referrer.get_ref_mut(&referrer_info.user)?;
+    let referrer_stats = makers_and_referrer_stats.get_ref_mut(&referrer_info.authority)?;
+
+    // if the second level referrer is the maker, the referrer keeps the full reward
+    let (second_level_referrer, second_level_referrer_stats) = match referrer_info.second_level {
+        Some((second_level_authority_key, second_level_user_key))
+            if maker.map_or(true, |maker| maker.authority != second_level_authority_key) =>
+        {
+            (
+                Some(makers_and_referrer.get_ref_mut(&second_level_user_key)?),
+                Some(makers_and_referrer_stats.get_ref_mut(&second_level_authority_key)?),
+            )
         }
+        _ => (None, None),
+    };
+
+    Ok((
+        Some(referrer),
+        Some(referrer_stats),
+        second_level_referrer,
+        second_level_referrer_stats,
+    ))
+}
+
+fn pay_referrer_reward(
+    market: &mut PerpMarket,
+    referrer: &mut Option<&mut User>,
+    referrer_stats: &mut Option<&mut UserStats>,
+    second_level_referrer: &mut Option<&mut User>,
+    second_level_referrer_stats: &mut Option<&mut UserStats>,
+    referrer_reward: u64,
+    now: i64,
+) -> DriftResult {
+    if referrer_reward == 0 {
+        return Ok(());
     }
 
-    let referrer = makers_and_referrer.get_ref_mut(referrer_user_key)?;
-    let referrer_stats = makers_and_referrer_stats.get_ref_mut(referrer_authority_key)?;
+    if let (Some(referrer), Some(referrer_stats)) = (referrer.as_mut(), referrer_stats.as_mut()) {
+        if let Ok(referrer_position) = referrer.force_get_perp_position_mut(market.market_index) {
+            let mut second_level_referrer_reward = 0_u64;
+            if let (Some(second_level_referrer), Some(second_level_referrer_stats)) = (
+                second_level_referrer.as_mut(),
+                second_level_referrer_stats.as_mut(),
+            ) {
+                if let Ok(second_level_referrer_position) =
+                    second_level_referrer.force_get_perp_position_mut(market.market_index)
+                {
+                    second_level_referrer_reward = fees::calculate_second_level_referrer_reward(
+                        referrer_reward,
+                        referrer_stats.second_level_referrer_reward_share,
+                    )?;
+
+                    if second_level_referrer_reward > 0 {
+                        update_quote_asset_amount(
+                            second_level_referrer_position,
+                            market,
+                            second_level_referrer_reward.cast()?,
+                        )?;
+                        second_level_referrer_stats
+                            .increment_total_referrer_reward(second_level_referrer_reward, now)?;
+                    }
+                }
+            }
+
+            let referrer_reward = referrer_reward.safe_sub(second_level_referrer_reward)?;
+            update_quote_asset_amount(referrer_position, market, referrer_reward.cast()?)?;
+            referrer_stats.increment_total_referrer_reward(referrer_reward, now)?;
+        }
+    }
 
-    Ok((Some(referrer), Some(referrer_stats)))
+    Ok(())
 }
 
 fn determine_if_user_order_is_risk_decreasing(
@@ -1539,10 +2599,13 @@ pub fn fulfill_perp_order_with_amm(
     filler_stats: &mut Option<&mut UserStats>,
     referrer: &mut Option<&mut User>,
     referrer_stats: &mut Option<&mut UserStats>,
+    second_level_referrer: &mut Option<&mut User>,
+    second_level_referrer_stats: &mut Option<&mut UserStats>,
     fee_structure: &FeeStructure,
     override_base_asset_amount: Option<u64>,
     override_fill_price: Option<u64>,
     split_with_lps: bool,
+    fill_record_buffer: &mut FillRecordBuffer,
 ) -> DriftResult<(u64, u64)> {
     let position_index = get_position_index(&user.perp_positions, market.market_index)?;
 
@@ -1554,6 +2617,7 @@ pub fn fulfill_perp_order_with_amm(
                 None,
                 slot,
                 market.amm.order_tick_size,
+                market.is_prediction_market(),
             )?;
 
             (override_base_asset_amount, limit_price, override_fill_price)
@@ -1641,6 +2705,7 @@ pub fn fulfill_perp_order_with_amm(
         referee_discount,
         referrer_reward,
         fee_to_market_for_lp,
+        token_discount,
         ..
     } = fees::calculate_fee_for_fulfillment_with_amm(
         user_stats,
@@ -1653,6 +2718,7 @@ pub fn fulfill_perp_order_with_amm(
         referrer_stats,
         quote_asset_amount_surplus,
         order_post_only,
+        now,
     )?;
 
     let user_position_delta =
@@ -1695,15 +2761,17 @@ pub fn fulfill_perp_order_with_amm(
     // Increment the user's total fee variables
     user_stats.increment_total_fees(user_fee)?;
     user_stats.increment_total_referee_discount(referee_discount)?;
+    user_stats.increment_total_token_discount(token_discount)?;
 
-    if let (Some(referrer), Some(referrer_stats)) = (referrer.as_mut(), referrer_stats.as_mut()) {
-        if let Ok(referrer_position) = referrer.force_get_perp_position_mut(market.market_index) {
-            if referrer_reward > 0 {
-                update_quote_asset_amount(referrer_position, market, referrer_reward.cast()?)?;
-                referrer_stats.increment_total_referrer_reward(referrer_reward, now)?;
-            }
-        }
-    }
+    pay_referrer_reward(
+        market,
+        referrer,
+        referrer_stats,
+        second_level_referrer,
+        second_level_referrer_stats,
+        referrer_reward,
+        now,
+    )?;
 
     let position_index = get_position_index(&user.perp_positions, market.market_index)?;
 
@@ -1781,7 +2849,7 @@ pub fn fulfill_perp_order_with_amm(
         maker_order,
         oracle_map.get_price_data(&market.amm.oracle)?.price,
     )?;
-    emit_stack::<_, { OrderActionRecord::SIZE }>(order_action_record)?;
+    fill_record_buffer.push(order_action_record)?;
 
     // Cant reset order until after its logged
     if user.orders[order_index].get_base_asset_amount_unfilled(None)? == 0 {
@@ -1808,12 +2876,15 @@ pub fn fulfill_perp_order_with_match(
     filler_key: &Pubkey,
     referrer: &mut Option<&mut User>,
     referrer_stats: &mut Option<&mut UserStats>,
+    second_level_referrer: &mut Option<&mut User>,
+    second_level_referrer_stats: &mut Option<&mut UserStats>,
     reserve_price_before: u64,
     valid_oracle_price: Option<i64>,
     now: i64,
     slot: u64,
     fee_structure: &FeeStructure,
     oracle_map: &mut OracleMap,
+    fill_record_buffer: &mut FillRecordBuffer,
 ) -> DriftResult<(u64, u64)> {
     if !are_orders_same_market_but_different_sides(
         &maker.orders[maker_order_index],
@@ -1839,6 +2910,7 @@ pub fn fulfill_perp_order_with_match(
         Some(taker_fallback_price),
         slot,
         market.amm.order_tick_size,
+        market.is_prediction_market(),
     )?;
 
     let taker_existing_position = taker
@@ -1847,18 +2919,27 @@ pub fn fulfill_perp_order_with_match(
     let taker_base_asset_amount = taker.orders[taker_order_index]
         .get_base_asset_amount_unfilled(Some(taker_existing_position))?;
 
-    let maker_price = maker.orders[maker_order_index].force_get_limit_price(
+    let mut maker_price = maker.orders[maker_order_index].force_get_limit_price(
         Some(oracle_price),
         None,
         slot,
         market.amm.order_tick_size,
+        market.is_prediction_market(),
     )?;
     let maker_direction = maker.orders[maker_order_index].direction;
+    if maker.is_protected_maker {
+        maker_price = apply_protected_maker_price_cushion(
+            maker_price,
+            maker_direction,
+            oracle_price,
+            market.amm.order_tick_size,
+        )?;
+    }
     let maker_existing_position = maker
         .get_perp_position(market.market_index)?
         .base_asset_amount;
     let maker_base_asset_amount = maker.orders[maker_order_index]
-        .get_base_asset_amount_unfilled(Some(maker_existing_position))?;
+        .get_base_asset_amount_displayed(Some(maker_existing_position))?;
 
     let orders_cross = do_orders_cross(maker_direction, maker_price, taker_price);
 
@@ -1929,10 +3010,13 @@ pub fn fulfill_perp_order_with_match(
                 filler_stats,
                 &mut None,
                 &mut None,
+                &mut None,
+                &mut None,
                 fee_structure,
                 Some(jit_base_asset_amount),
                 Some(maker_price), // match the makers price
                 false,             // dont split with the lps
+                fill_record_buffer,
             )?;
             total_quote_asset_amount = quote_asset_amount_filled_by_amm;
         };
@@ -1984,12 +3068,19 @@ pub fn fulfill_perp_order_with_match(
         maker.orders[maker_order_index].direction,
     )?;
 
+    let maker_update_type = get_position_update_type(
+        &maker.perp_positions[maker_position_index],
+        &maker_position_delta,
+    );
+
     update_position_and_market(
         &mut maker.perp_positions[maker_position_index],
         market,
         &maker_position_delta,
     )?;
 
+    validate_open_interest_for_fill(market, maker_update_type)?;
+
     // if maker is none, makes maker and taker authority was the same
     if let Some(maker_stats) = maker_stats {
         maker_stats.update_maker_volume_30d(quote_asset_amount, now)?;
@@ -2008,12 +3099,19 @@ pub fn fulfill_perp_order_with_match(
         taker.orders[taker_order_index].direction,
     )?;
 
+    let taker_update_type = get_position_update_type(
+        &taker.perp_positions[taker_position_index],
+        &taker_position_delta,
+    );
+
     update_position_and_market(
         &mut taker.perp_positions[taker_position_index],
         market,
         &taker_position_delta,
     )?;
 
+    validate_open_interest_for_fill(market, taker_update_type)?;
+
     taker_stats.update_taker_volume_30d(quote_asset_amount, now)?;
 
     let reward_referrer = can_reward_user_with_perp_pnl(referrer, market.market_index);
@@ -2032,6 +3130,7 @@ pub fn fulfill_perp_order_with_match(
         filler_reward,
         referrer_reward,
         referee_discount,
+        token_discount,
         ..
     } = fees::calculate_fee_for_fulfillment_with_match(
         taker_stats,
@@ -2044,6 +3143,7 @@ pub fn fulfill_perp_order_with_match(
         reward_referrer,
         referrer_stats,
         &MarketType::Perp,
+        now,
     )?;
 
     // Increment the markets house's total fee variables
@@ -2069,6 +3169,7 @@ pub fn fulfill_perp_order_with_match(
 
     taker_stats.increment_total_fees(taker_fee)?;
     taker_stats.increment_total_referee_discount(referee_discount)?;
+    taker_stats.increment_total_token_discount(token_discount)?;
 
     controller::position::update_quote_asset_and_break_even_amount(
         &mut maker.perp_positions[maker_position_index],
@@ -2103,14 +3204,15 @@ pub fn fulfill_perp_order_with_match(
         filler.update_last_active_slot(slot);
     }
 
-    if let (Some(referrer), Some(referrer_stats)) = (referrer.as_mut(), referrer_stats.as_mut()) {
-        if let Ok(referrer_position) = referrer.force_get_perp_position_mut(market.market_index) {
-            if referrer_reward > 0 {
-                update_quote_asset_amount(referrer_position, market, referrer_reward.cast()?)?;
-                referrer_stats.increment_total_referrer_reward(referrer_reward, now)?;
-            }
-        }
-    }
+    pay_referrer_reward(
+        market,
+        referrer,
+        referrer_stats,
+        second_level_referrer,
+        second_level_referrer_stats,
+        referrer_reward,
+        now,
+    )?;
 
     update_order_after_fill(
         &mut taker.orders[taker_order_index],
@@ -2163,7 +3265,7 @@ pub fn fulfill_perp_order_with_match(
         Some(maker.orders[maker_order_index]),
         oracle_map.get_price_data(&market.amm.oracle)?.price,
     )?;
-    emit_stack::<_, { OrderActionRecord::SIZE }>(order_action_record)?;
+    fill_record_buffer.push(order_action_record)?;
 
     if taker.orders[taker_order_index].get_base_asset_amount_unfilled(None)? == 0 {
         taker.orders[taker_order_index] = Order::default();
@@ -2175,120 +3277,62 @@ pub fn fulfill_perp_order_with_match(
         maker.orders[maker_order_index] = Order::default();
         let market_position = &mut maker.perp_positions[maker_position_index];
         market_position.open_orders -= 1;
+    } else {
+        replenish_iceberg_order(maker_key, &mut maker.orders[maker_order_index], now, slot)?;
     }
 
     Ok((base_asset_amount, total_quote_asset_amount))
 }
 
-pub fn update_order_after_fill(
-    order: &mut Order,
-    base_asset_amount: u64,
-    quote_asset_amount: u64,
-) -> DriftResult {
-    order.base_asset_amount_filled = order.base_asset_amount_filled.safe_add(base_asset_amount)?;
-
-    order.quote_asset_amount_filled = order
-        .quote_asset_amount_filled
-        .safe_add(quote_asset_amount)?;
-
-    if order.get_base_asset_amount_unfilled(None)? == 0 {
-        order.status = OrderStatus::Filled;
-    }
-
-    Ok(())
-}
-
-fn get_valid_oracle_price(
-    oracle_price_data: &OraclePriceData,
-    market: &PerpMarket,
-    order: &Order,
-    validity_guardrails: &ValidityGuardRails,
-) -> DriftResult<Option<i64>> {
-    let price = {
-        let oracle_validity = oracle::oracle_validity(
-            market.amm.historical_oracle_data.last_oracle_price_twap,
-            oracle_price_data,
-            validity_guardrails,
-        )?;
-
-        let is_oracle_valid =
-            is_oracle_valid_for_action(oracle_validity, Some(DriftAction::FillOrderAmm))?;
-
-        if is_oracle_valid {
-            Some(oracle_price_data.price)
-        } else if order.has_oracle_price_offset() {
-            msg!("Invalid oracle for order with oracle price offset");
-            return Err(print_error!(ErrorCode::InvalidOracle)());
-        } else {
-            msg!("Oracle is invalid");
-            None
-        }
-    };
-
-    Ok(price)
-}
-
-#[allow(clippy::type_complexity)]
-fn get_taker_and_maker_for_order_record(
-    user_key: &Pubkey,
-    user_order: &Order,
-) -> (Option<Pubkey>, Option<Order>, Option<Pubkey>, Option<Order>) {
-    if user_order.post_only {
-        (None, None, Some(*user_key), Some(*user_order))
-    } else {
-        (Some(*user_key), Some(*user_order), None, None)
-    }
-}
-
-pub fn trigger_order(
+pub fn fill_twap_order(
     order_id: u32,
     state: &State,
     user: &AccountLoader<User>,
+    user_stats: &AccountLoader<UserStats>,
     spot_market_map: &SpotMarketMap,
     perp_market_map: &PerpMarketMap,
     oracle_map: &mut OracleMap,
     filler: &AccountLoader<User>,
+    filler_stats: &AccountLoader<UserStats>,
     clock: &Clock,
-) -> DriftResult {
+    market_fee_override: Option<&MarketFeeOverride>,
+) -> DriftResult<u64> {
     let now = clock.unix_timestamp;
     let slot = clock.slot;
 
     let filler_key = filler.key();
     let user_key = user.key();
     let user = &mut load_mut!(user)?;
+    let user_stats = &mut load_mut!(user_stats)?;
 
-    let order_index = user
-        .orders
-        .iter()
-        .position(|order| order.order_id == order_id)
-        .ok_or_else(print_error!(ErrorCode::OrderDoesNotExist))?;
+    user.validate_no_fill_callback()?;
 
-    let (order_status, market_index, market_type) =
-        get_struct_values!(user.orders[order_index], status, market_index, market_type);
+    let order_index = user.get_order_index(order_id)?;
 
-    validate!(
-        order_status == OrderStatus::Open,
-        ErrorCode::OrderNotOpen,
-        "Order not open"
-    )?;
+    let (order_type, market_index, market_type, order_direction) = get_struct_values!(
+        user.orders[order_index],
+        order_type,
+        market_index,
+        market_type,
+        direction
+    );
 
     validate!(
-        user.orders[order_index].must_be_triggered(),
-        ErrorCode::OrderNotTriggerable,
-        "Order is not triggerable"
+        order_type == OrderType::Twap && market_type == MarketType::Perp,
+        ErrorCode::InvalidTwapOrder,
+        "Order is not a perp twap order"
     )?;
 
     validate!(
-        !user.orders[order_index].triggered(),
-        ErrorCode::OrderNotTriggerable,
-        "Order is already triggered"
+        user.orders[order_index].is_twap_slice_ready(slot)?,
+        ErrorCode::TwapSliceNotReady,
+        "Twap slice not ready until slot {}",
+        user.orders[order_index]
+            .slot
+            .safe_add(user.orders[order_index].twap_interval.cast()?)?
     )?;
 
-    validate!(
-        market_type == MarketType::Perp,
-        ErrorCode::InvalidOrderMarketType,
-        "Order must be a perp order"
-    )?;
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
 
     validate_user_not_being_liquidated(
         user,
@@ -2298,115 +3342,30 @@ pub fn trigger_order(
         state.liquidation_margin_buffer_ratio,
     )?;
 
-    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
-
-    let mut perp_market = perp_market_map.get_ref_mut(&market_index)?;
-    let oracle_price_data = &oracle_map.get_price_data(&perp_market.amm.oracle)?;
-
-    let oracle_validity = oracle::oracle_validity(
-        perp_market
-            .amm
-            .historical_oracle_data
-            .last_oracle_price_twap,
-        oracle_price_data,
-        &state.oracle_guard_rails.validity,
-    )?;
-    let is_oracle_valid =
-        is_oracle_valid_for_action(oracle_validity, Some(DriftAction::TriggerOrder))?;
-
-    validate!(is_oracle_valid, ErrorCode::InvalidOracle)?;
-
-    let oracle_price = oracle_price_data.price;
-
-    let can_trigger = order_satisfies_trigger_condition(
-        &user.orders[order_index],
-        oracle_price.unsigned_abs().cast()?,
-    )?;
-    validate!(can_trigger, ErrorCode::OrderDidNotSatisfyTriggerCondition)?;
-
-    {
-        let direction = user.orders[order_index].direction;
-        let base_asset_amount = user.orders[order_index].base_asset_amount;
-
-        user.orders[order_index].trigger_condition =
-            match user.orders[order_index].trigger_condition {
-                OrderTriggerCondition::Above => OrderTriggerCondition::TriggeredAbove,
-                OrderTriggerCondition::Below => OrderTriggerCondition::TriggeredBelow,
-                _ => {
-                    return Err(print_error!(ErrorCode::InvalidTriggerOrderCondition)());
-                }
-            };
-
-        user.orders[order_index].slot = slot;
-        let order_type = user.orders[order_index].order_type;
-        if let OrderType::TriggerMarket = order_type {
-            user.orders[order_index].auction_duration = state.min_perp_auction_duration;
-            let (auction_start_price, auction_end_price) =
-                calculate_auction_prices(oracle_price_data, direction, 0)?;
-            user.orders[order_index].auction_start_price = auction_start_price;
-            user.orders[order_index].auction_end_price = auction_end_price;
-        }
-
-        let user_position = user.get_perp_position_mut(market_index)?;
-        increase_open_bids_and_asks(user_position, &direction, base_asset_amount)?;
-    }
-
     let is_filler_taker = user_key == filler_key;
-    let mut filler = if !is_filler_taker {
-        Some(load_mut!(filler)?)
+    let (mut filler, mut filler_stats) = if !is_filler_taker {
+        let filler = load_mut!(filler)?;
+        if filler.authority != user.authority {
+            (Some(filler), Some(load_mut!(filler_stats)?))
+        } else {
+            (None, None)
+        }
     } else {
-        None
+        (None, None)
     };
 
-    let filler_reward = pay_keeper_flat_reward_for_perps(
-        user,
-        filler.as_deref_mut(),
-        &mut perp_market,
-        state.perp_fee_structure.flat_filler_fee,
-        slot,
-    )?;
-
-    let order_action_record = get_order_action_record(
-        now,
-        OrderAction::Trigger,
-        OrderActionExplanation::None,
-        market_index,
-        Some(filler_key),
-        None,
-        Some(filler_reward),
-        None,
-        None,
-        Some(filler_reward),
-        None,
-        None,
-        None,
-        None,
-        Some(user_key),
-        Some(user.orders[order_index]),
-        None,
-        None,
-        oracle_price,
-    )?;
-    emit!(order_action_record);
-
-    drop(perp_market);
-
-    // If order is risk increasing and user is below initial margin, cancel it
-    let order_direction = user.orders[order_index].direction;
-    let order_base_asset_amount = user.orders[order_index].base_asset_amount;
-    let position_base_asset_amount = user
-        .force_get_perp_position_mut(market_index)?
-        .base_asset_amount;
-    let is_risk_increasing = is_order_risk_increasing(
-        &order_direction,
-        order_base_asset_amount,
-        position_base_asset_amount,
-    )?;
-
-    let meets_initial_margin_requirement =
-        meets_initial_margin_requirement(user, perp_market_map, spot_market_map, oracle_map)?;
+    if should_expire_order(user, order_index, now)? {
+        let filler_reward = {
+            let mut market = perp_market_map.get_ref_mut(&market_index)?;
+            pay_keeper_flat_reward_for_perps(
+                user,
+                filler.as_deref_mut(),
+                market.deref_mut(),
+                state.perp_fee_structure.flat_filler_fee,
+                slot,
+            )?
+        };
 
-    if is_risk_increasing && !meets_initial_margin_requirement {
         cancel_order(
             order_index,
             user,
@@ -2416,486 +3375,747 @@ pub fn trigger_order(
             oracle_map,
             now,
             slot,
-            OrderActionExplanation::InsufficientFreeCollateral,
+            OrderActionExplanation::OrderExpired,
             Some(&filler_key),
-            0,
+            filler_reward,
             false,
         )?;
+        return Ok(0);
     }
 
-    user.update_last_active_slot(slot);
+    let position_index = get_position_index(&user.perp_positions, market_index)?;
 
-    Ok(())
-}
+    let user_order_risk_decreasing;
+    let oracle_reserve_price_spread_pct_before;
+    let base_asset_amount = {
+        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+        controller::lp::settle_funding_payment_then_lp(user, &user_key, &mut market, now)?;
 
-pub fn force_cancel_orders(
-    state: &State,
-    user: &AccountLoader<User>,
-    spot_market_map: &SpotMarketMap,
-    perp_market_map: &PerpMarketMap,
-    oracle_map: &mut OracleMap,
-    filler: &AccountLoader<User>,
-    clock: &Clock,
-) -> DriftResult {
-    let now = clock.unix_timestamp;
-    let slot = clock.slot;
+        validate!(
+            matches!(
+                market.status,
+                MarketStatus::Active
+                    | MarketStatus::FundingPaused
+                    | MarketStatus::ReduceOnly
+                    | MarketStatus::WithdrawPaused
+            ),
+            ErrorCode::MarketFillOrderPaused,
+            "Market unavailable for fills"
+        )?;
 
-    let filler_key = filler.key();
-    let user_key = user.key();
-    let user = &mut load_mut!(user)?;
-    let filler = &mut load_mut!(filler)?;
+        validate!(
+            !market.is_operation_paused(PerpOperation::Fill)?,
+            ErrorCode::MarketFillOrderPaused,
+            "Fills paused for market {}",
+            market_index
+        )?;
 
-    validate!(
-        !user.is_being_liquidated(),
-        ErrorCode::UserIsBeingLiquidated
-    )?;
+        validate!(
+            !state.amm_paused()?,
+            ErrorCode::MarketFillOrderPaused,
+            "Amm unavailable for twap fills"
+        )?;
 
-    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
+        // the slice waits out a tripped breaker, the order stays open and the filler's transaction
+        // still succeeds. every update_amm re-evaluates the breaker
+        if market.is_operation_paused(PerpOperation::OracleCircuitBreaker)? {
+            msg!(
+                "oracle circuit breaker tripped for perp market {}",
+                market_index
+            );
+            return Ok(0);
+        }
 
-    let meets_initial_margin_requirement =
-        meets_initial_margin_requirement(user, perp_market_map, spot_market_map, oracle_map)?;
+        validation::perp_market::validate_perp_market(&market)?;
+        validate!(
+            market.is_active(now)?,
+            ErrorCode::MarketFillOrderPaused,
+            "Market is in settlement mode",
+        )?;
 
-    validate!(
-        !meets_initial_margin_requirement,
-        ErrorCode::SufficientCollateral
-    )?;
+        let fee_structure = get_market_fee_structure(
+            &state.perp_fee_structure,
+            MarketType::Perp,
+            market_index,
+            market.amm.fee_override_enabled,
+            market_fee_override,
+            now,
+        )?;
 
-    let mut total_fee = 0_u64;
+        let (oracle_price, oracle_validity) = {
+            let oracle_price_data = oracle_map.get_price_data(&market.amm.oracle)?;
+            let oracle_validity = oracle::oracle_validity(
+                market.amm.historical_oracle_data.last_oracle_price_twap,
+                oracle_price_data,
+                &state
+                    .oracle_guard_rails
+                    .validity
+                    .with_override(&market.amm.validity_guard_rails_override),
+            )?;
+            (oracle_price_data.price, oracle_validity)
+        };
 
-    for order_index in 0..user.orders.len() {
-        if user.orders[order_index].status != OrderStatus::Open {
-            continue;
-        }
+        validate!(
+            is_oracle_valid_for_action(oracle_validity, Some(DriftAction::FillOrderAmm))?,
+            ErrorCode::InvalidOracle,
+            "Oracle invalid for twap fill"
+        )?;
+        let valid_oracle_price = Some(oracle_price);
 
-        let market_index = user.orders[order_index].market_index;
-        let market_type = user.orders[order_index].market_type;
+        user_order_risk_decreasing =
+            determine_if_user_order_is_risk_decreasing(user, market_index, order_index)?;
 
-        let fee = match market_type {
-            MarketType::Spot => {
-                let spot_market = spot_market_map.get_ref(&market_index)?;
-                let is_risk_decreasing = determine_if_user_spot_order_is_risk_decreasing(
-                    user,
-                    &spot_market,
-                    order_index,
-                )?;
-                if is_risk_decreasing {
-                    continue;
-                }
+        let reserve_price_before = market.amm.reserve_price()?;
+        oracle_reserve_price_spread_pct_before = amm::calculate_oracle_twap_5min_mark_spread_pct(
+            &market.amm,
+            Some(reserve_price_before),
+        )?;
 
-                state.spot_fee_structure.flat_filler_fee
-            }
-            MarketType::Perp => {
-                let is_risk_decreasing =
-                    determine_if_user_order_is_risk_decreasing(user, market_index, order_index)?;
-                if is_risk_decreasing {
-                    continue;
-                }
+        let existing_base_asset_amount = user.perp_positions[position_index].base_asset_amount;
+        let (max_base_asset_amount, _) = calculate_base_asset_amount_for_amm_to_fulfill(
+            &user.orders[order_index],
+            &market,
+            valid_oracle_price,
+            slot,
+            None,
+            existing_base_asset_amount,
+        )?;
 
-                state.perp_fee_structure.flat_filler_fee
-            }
-        };
+        let slice_base_asset_amount = calculate_twap_slice_base_asset_amount(
+            &user.orders[order_index],
+            existing_base_asset_amount,
+            market.amm.order_step_size,
+        )?
+        .min(max_base_asset_amount);
 
-        total_fee = total_fee.safe_add(fee)?;
+        validate!(
+            slice_base_asset_amount >= market.amm.min_order_size,
+            ErrorCode::TwapSliceNotReady,
+            "Amm cant fill twap slice. base asset amount {} market.amm.min_order_size {}",
+            slice_base_asset_amount,
+            market.amm.min_order_size
+        )?;
 
-        cancel_order(
-            order_index,
+        let mut fill_record_buffer = FillRecordBuffer::default();
+        let (base_asset_amount, quote_asset_amount) = fulfill_perp_order_with_amm(
             user,
-            &user_key,
-            perp_market_map,
-            spot_market_map,
+            user_stats,
+            order_index,
+            market.deref_mut(),
             oracle_map,
+            reserve_price_before,
             now,
             slot,
-            OrderActionExplanation::InsufficientFreeCollateral,
-            Some(&filler_key),
-            fee,
-            false,
+            valid_oracle_price,
+            &user_key,
+            &filler_key,
+            &mut filler.as_deref_mut(),
+            &mut filler_stats.as_deref_mut(),
+            &mut None,
+            &mut None,
+            &mut None,
+            &mut None,
+            &fee_structure,
+            Some(slice_base_asset_amount),
+            None,
+            true,
+            &mut fill_record_buffer,
         )?;
-    }
+        fill_record_buffer.flush()?;
 
-    pay_keeper_flat_reward_for_spot(
-        user,
-        Some(filler),
-        spot_market_map.get_quote_spot_market_mut()?.deref_mut(),
-        total_fee,
-    )?;
+        market.update_fill_stats(base_asset_amount, quote_asset_amount, order_direction, now)?;
 
-    user.update_last_active_slot(slot);
+        base_asset_amount
+    };
 
-    Ok(())
-}
+    // a fully filled order is reset, otherwise schedule the next slice
+    if user.orders[order_index].order_id == order_id
+        && user.orders[order_index].status == OrderStatus::Open
+    {
+        user.orders[order_index].slot = slot;
+        user.orders[order_index].twap_slices_remaining = user.orders[order_index]
+            .twap_slices_remaining
+            .saturating_sub(1);
 
-pub fn can_reward_user_with_perp_pnl(user: &mut Option<&mut User>, market_index: u16) -> bool {
-    match user.as_mut() {
-        Some(user) => user.force_get_perp_position_mut(market_index).is_ok(),
-        None => false,
+        if user.orders[order_index].twap_slices_remaining == 0 {
+            cancel_order(
+                order_index,
+                user,
+                &user_key,
+                perp_market_map,
+                spot_market_map,
+                oracle_map,
+                now,
+                slot,
+                OrderActionExplanation::None,
+                Some(&filler_key),
+                0,
+                false,
+            )?;
+        }
     }
-}
-
-pub fn pay_keeper_flat_reward_for_perps(
-    user: &mut User,
-    filler: Option<&mut User>,
-    market: &mut PerpMarket,
-    filler_reward: u64,
-    slot: u64,
-) -> DriftResult<u64> {
-    let filler_reward = if let Some(filler) = filler {
-        let user_position = user.get_perp_position_mut(market.market_index)?;
-        controller::position::update_quote_asset_and_break_even_amount(
-            user_position,
-            market,
-            -filler_reward.cast()?,
-        )?;
 
-        filler.update_last_active_slot(slot);
-        // Dont throw error if filler doesnt have position available
-        let filler_position = match filler.force_get_perp_position_mut(market.market_index) {
-            Ok(position) => position,
-            Err(_) => return Ok(0),
-        };
-        controller::position::update_quote_asset_amount(
-            filler_position,
-            market,
-            filler_reward.cast()?,
+    {
+        let market = perp_market_map.get_ref(&market_index)?;
+        let maintenance_margin_buffer = calculate_maintenance_buffer_ratio(
+            market.margin_ratio_initial,
+            market.margin_ratio_maintenance,
+            user_order_risk_decreasing,
         )?;
+        drop(market);
 
-        filler_reward
-    } else {
-        0
-    };
-
-    Ok(filler_reward)
-}
+        let (_, total_collateral, margin_requirement_plus_buffer, _) =
+            calculate_margin_requirement_and_total_collateral(
+                user,
+                perp_market_map,
+                MarginRequirementType::Maintenance,
+                spot_market_map,
+                oracle_map,
+                Some(maintenance_margin_buffer.cast()?),
+            )?;
 
-pub fn pay_keeper_flat_reward_for_spot(
-    user: &mut User,
-    filler: Option<&mut User>,
-    quote_market: &mut SpotMarket,
-    filler_reward: u64,
-) -> DriftResult<u64> {
-    let filler_reward = if let Some(filler) = filler {
-        update_spot_balances(
-            filler_reward as u128,
-            &SpotBalanceType::Deposit,
-            quote_market,
-            filler.get_quote_spot_position_mut(),
-            false,
+        validate!(
+            total_collateral >= margin_requirement_plus_buffer.cast()?,
+            ErrorCode::InsufficientCollateral,
+            "taker breached maintenance requirements (margin requirement plus buffer {}) (total_collateral {})",
+            margin_requirement_plus_buffer,
+            total_collateral
         )?;
 
-        filler.update_cumulative_spot_fees(filler_reward.cast()?)?;
+        validate!(
+            meets_isolated_perp_position_margin_requirement(
+                user,
+                market_index,
+                perp_market_map,
+                MarginRequirementType::Maintenance,
+                oracle_map,
+                Some(maintenance_margin_buffer.cast()?),
+            )?,
+            ErrorCode::InsufficientCollateral,
+            "taker breached isolated maintenance requirements for perp market {}",
+            market_index
+        )?;
+    }
 
-        update_spot_balances(
-            filler_reward as u128,
-            &SpotBalanceType::Borrow,
-            quote_market,
-            user.get_quote_spot_position_mut(),
-            false,
+    {
+        let market = perp_market_map.get_ref(&market_index)?;
+        validate_market_within_price_band(
+            &market,
+            state,
+            !user_order_risk_decreasing,
+            Some(oracle_reserve_price_spread_pct_before),
         )?;
+    }
 
-        user.update_cumulative_spot_fees(-filler_reward.cast()?)?;
+    cancel_linked_orders(
+        user,
+        &user_key,
+        Some(&filler_key),
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+    )?;
 
-        filler_reward
-    } else {
-        0
-    };
+    user.update_last_active_slot(slot);
 
-    Ok(filler_reward)
+    Ok(base_asset_amount)
 }
 
-pub fn place_spot_order(
+pub fn fill_rfq(
     state: &State,
-    user: &AccountLoader<User>,
-    perp_market_map: &PerpMarketMap,
+    taker: &AccountLoader<User>,
+    taker_stats: &AccountLoader<UserStats>,
+    taker_request: &RfqTakerRequest,
+    maker_quotes: &[RfqMakerQuote],
+    makers: &UserMap,
+    makers_stats: &UserStatsMap,
+    filler: &AccountLoader<User>,
+    filler_stats: &AccountLoader<UserStats>,
     spot_market_map: &SpotMarketMap,
+    perp_market_map: &PerpMarketMap,
     oracle_map: &mut OracleMap,
     clock: &Clock,
-    params: OrderParams,
-) -> DriftResult {
+    market_fee_override: Option<&MarketFeeOverride>,
+) -> DriftResult<u64> {
     let now = clock.unix_timestamp;
     let slot = clock.slot;
-    let user_key = user.key();
-    let user = &mut load_mut!(user)?;
+    let market_index = taker_request.market_index;
+    let taker_direction = taker_request.direction;
 
-    validate_user_not_being_liquidated(
-        user,
-        perp_market_map,
-        spot_market_map,
-        oracle_map,
-        state.liquidation_margin_buffer_ratio,
-    )?;
-
-    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
-
-    expire_orders(
-        user,
-        &user_key,
-        perp_market_map,
-        spot_market_map,
-        oracle_map,
-        now,
-        slot,
-    )?;
-
-    let max_ts = match params.max_ts {
-        Some(max_ts) => max_ts,
-        None => match params.order_type {
-            OrderType::Market | OrderType::Oracle => now.safe_add(30)?,
-            _ => 0_i64,
-        },
-    };
-
-    if max_ts != 0 && max_ts < now {
-        msg!("max_ts ({}) < now ({}), skipping order", max_ts, now);
-        return Ok(());
-    }
-
-    let new_order_index = user
-        .orders
-        .iter()
-        .position(|order| order.status.eq(&OrderStatus::Init))
-        .ok_or(ErrorCode::MaxNumberOfOrders)?;
-
-    if params.user_order_id > 0 {
-        let user_order_id_already_used = user
-            .orders
-            .iter()
-            .position(|order| order.user_order_id == params.user_order_id);
-
-        if user_order_id_already_used.is_some() {
-            msg!("user_order_id is already in use {}", params.user_order_id);
-            return Err(ErrorCode::UserOrderIdAlreadyInUse);
-        }
-    }
-
-    let market_index = params.market_index;
-    let spot_market = &spot_market_map.get_ref(&market_index)?;
-    let force_reduce_only = spot_market.is_reduce_only()?;
-    let step_size = spot_market.order_step_size;
+    let filler_key = filler.key();
+    let taker_key = taker.key();
 
     validate!(
-        !matches!(spot_market.status, MarketStatus::Initialized),
-        ErrorCode::MarketBeingInitialized,
-        "Market is being initialized"
+        !maker_quotes.is_empty(),
+        ErrorCode::InvalidRfqQuote,
+        "rfq must have at least one maker quote"
     )?;
 
-    let spot_position_index = user
-        .get_spot_position_index(market_index)
-        .or_else(|_| user.add_spot_position(market_index, SpotBalanceType::Deposit))?;
+    load!(taker)?.validate_no_fill_callback()?;
 
-    let oracle_price_data = *oracle_map.get_price_data(&spot_market.oracle)?;
-    let (worst_case_token_amount_before, _) = user.spot_positions[spot_position_index]
-        .get_worst_case_token_amounts(spot_market, &oracle_price_data, None, None)?;
+    for quote in maker_quotes.iter() {
+        makers.get_ref(&quote.maker)?.validate_no_fill_callback()?;
 
-    let balance_type = user.spot_positions[spot_position_index].balance_type;
-    let token_amount = user.spot_positions[spot_position_index].get_token_amount(spot_market)?;
-    let signed_token_amount = get_signed_token_amount(token_amount, &balance_type)?;
+        validate!(
+            quote.taker == taker_key && quote.maker != taker_key,
+            ErrorCode::InvalidRfqQuote,
+            "quote from maker {} is for taker {}, not {}",
+            quote.maker,
+            quote.taker,
+            taker_key
+        )?;
 
-    // Increment open orders for existing position
-    let (existing_position_direction, order_base_asset_amount) = {
         validate!(
-            params.base_asset_amount >= step_size,
-            ErrorCode::InvalidOrderSizeTooSmall,
-            "params.base_asset_amount={} cannot be below spot_market.order_step_size={}",
-            params.base_asset_amount,
-            step_size
+            quote.market_index == market_index && quote.direction == taker_direction.opposite(),
+            ErrorCode::InvalidRfqQuote,
+            "quote from maker {} is not the other side of the request for perp market {}",
+            quote.maker,
+            market_index
         )?;
+    }
 
-        let base_asset_amount = if params.base_asset_amount == u64::MAX {
-            calculate_max_spot_order_size(
-                user,
-                params.market_index,
-                params.direction,
-                perp_market_map,
-                spot_market_map,
-                oracle_map,
-            )?
-        } else {
-            standardize_base_asset_amount(params.base_asset_amount, step_size)?
-        };
+    let oracle_price;
+    let taker_limit_price;
+    let reserve_price_before;
+    let oracle_reserve_price_spread_pct_before;
+    let fee_structure;
+    {
+        let market = perp_market_map.get_ref(&market_index)?;
 
         validate!(
-            is_multiple_of_step_size(base_asset_amount, step_size)?,
-            ErrorCode::InvalidOrderNotStepSizeMultiple,
-            "Order base asset amount ({}), is not a multiple of step size ({})",
-            base_asset_amount,
-            step_size
+            matches!(
+                market.status,
+                MarketStatus::Active
+                    | MarketStatus::FundingPaused
+                    | MarketStatus::ReduceOnly
+                    | MarketStatus::WithdrawPaused
+            ),
+            ErrorCode::MarketFillOrderPaused,
+            "Market unavailable for fills"
         )?;
 
-        let existing_position_direction = if signed_token_amount >= 0 {
-            PositionDirection::Long
-        } else {
-            PositionDirection::Short
-        };
-        (
-            existing_position_direction,
-            base_asset_amount.cast::<u64>()?,
-        )
-    };
+        validate!(
+            !market.is_operation_paused(PerpOperation::Fill)?,
+            ErrorCode::MarketFillOrderPaused,
+            "Fills paused for market {}",
+            market_index
+        )?;
 
-    let (auction_start_price, auction_end_price, auction_duration) = get_auction_params(
-        &params,
-        &oracle_price_data,
-        spot_market.order_tick_size,
-        state.default_spot_auction_duration,
-    )?;
+        // error rather than skip the fill, succeeding would consume the request and quote nonces
+        validate!(
+            !market.is_operation_paused(PerpOperation::OracleCircuitBreaker)?,
+            ErrorCode::OracleCircuitBreakerTripped,
+            "oracle circuit breaker tripped for perp market {}",
+            market_index
+        )?;
 
-    validate!(spot_market.orders_enabled, ErrorCode::SpotOrdersDisabled)?;
+        validation::perp_market::validate_perp_market(&market)?;
+        validate!(
+            market.is_active(now)?,
+            ErrorCode::MarketFillOrderPaused,
+            "Market is in settlement mode",
+        )?;
 
-    validate!(
-        params.market_index != QUOTE_SPOT_MARKET_INDEX,
-        ErrorCode::InvalidOrderBaseQuoteAsset,
-        "can not place order for quote asset"
-    )?;
+        fee_structure = get_market_fee_structure(
+            &state.perp_fee_structure,
+            MarketType::Perp,
+            market_index,
+            market.amm.fee_override_enabled,
+            market_fee_override,
+            now,
+        )?;
 
-    validate!(
-        params.market_type == MarketType::Spot,
-        ErrorCode::InvalidOrderMarketType,
-        "must be spot order"
-    )?;
+        let oracle_price_data = oracle_map.get_price_data(&market.amm.oracle)?;
+        let oracle_validity = oracle::oracle_validity(
+            market.amm.historical_oracle_data.last_oracle_price_twap,
+            oracle_price_data,
+            &state
+                .oracle_guard_rails
+                .validity
+                .with_override(&market.amm.validity_guard_rails_override),
+        )?;
 
-    let new_order = Order {
-        status: OrderStatus::Open,
-        order_type: params.order_type,
-        market_type: params.market_type,
-        slot,
-        order_id: get_then_update_id!(user, next_order_id),
-        user_order_id: params.user_order_id,
-        market_index: params.market_index,
-        price: standardize_price(params.price, spot_market.order_tick_size, params.direction)?,
-        existing_position_direction,
-        base_asset_amount: order_base_asset_amount,
-        base_asset_amount_filled: 0,
-        quote_asset_amount_filled: 0,
-        direction: params.direction,
-        reduce_only: params.reduce_only || force_reduce_only,
-        trigger_price: standardize_price(
-            params.trigger_price.unwrap_or(0),
-            spot_market.order_tick_size,
-            params.direction,
-        )?,
-        trigger_condition: params.trigger_condition,
-        post_only: params.post_only != PostOnlyParam::None,
-        oracle_price_offset: params.oracle_price_offset.unwrap_or(0),
-        immediate_or_cancel: params.immediate_or_cancel,
-        auction_start_price,
-        auction_end_price,
-        auction_duration,
-        max_ts,
-        padding: [0; 3],
-    };
+        // quotes are priced against the oracle, so it must be good enough to fill against the amm
+        validate!(
+            is_oracle_valid_for_action(oracle_validity, Some(DriftAction::FillOrderAmm))?,
+            ErrorCode::InvalidOracle,
+            "Oracle invalid for rfq fill"
+        )?;
+        oracle_price = oracle_price_data.price;
 
-    let valid_oracle_price = Some(oracle_price_data.price);
-    validate_spot_order(
-        &new_order,
-        valid_oracle_price,
-        slot,
-        spot_market.order_step_size,
-        spot_market.order_tick_size,
-        spot_market.get_margin_ratio(&MarginRequirementType::Initial)?,
-        spot_market.get_margin_ratio(&MarginRequirementType::Maintenance)?,
-        spot_market.min_order_size,
-    )?;
+        let limit_price = oracle_price.safe_add(taker_request.oracle_price_offset.cast()?)?;
+        validate!(
+            limit_price > 0,
+            ErrorCode::InvalidOrderLimitPrice,
+            "rfq limit price {} must be positive (oracle price {})",
+            limit_price,
+            oracle_price
+        )?;
+        taker_limit_price = standardize_price(
+            limit_price.cast()?,
+            market.amm.order_tick_size,
+            taker_direction,
+        )?;
 
-    user.orders[new_order_index] = new_order;
-    user.spot_positions[spot_position_index].open_orders += 1;
-    if !new_order.must_be_triggered() {
-        increase_spot_open_bids_and_asks(
-            &mut user.spot_positions[spot_position_index],
-            &params.direction,
-            order_base_asset_amount,
+        reserve_price_before = market.amm.reserve_price()?;
+        oracle_reserve_price_spread_pct_before = amm::calculate_oracle_twap_5min_mark_spread_pct(
+            &market.amm,
+            Some(reserve_price_before),
         )?;
     }
 
-    let (worst_case_token_amount_after, _) = user.spot_positions[spot_position_index]
-        .get_worst_case_token_amounts(spot_market, &oracle_price_data, None, None)?;
-
-    let order_risk_decreasing =
-        is_spot_order_risk_decreasing(&user.orders[new_order_index], &balance_type, token_amount)?;
+    for quote in maker_quotes.iter() {
+        let improves_on_limit_price = match taker_direction {
+            PositionDirection::Long => quote.price <= taker_limit_price,
+            PositionDirection::Short => quote.price >= taker_limit_price,
+        };
 
-    // Order fails if it's risk increasing and it brings the user collateral below the margin requirement
-    let risk_decreasing = worst_case_token_amount_after.unsigned_abs()
-        <= worst_case_token_amount_before.unsigned_abs()
-        && order_risk_decreasing;
+        validate!(
+            improves_on_limit_price,
+            ErrorCode::InvalidRfqQuote,
+            "quote price {} from maker {} is worse than taker limit price {} (oracle price {})",
+            quote.price,
+            quote.maker,
+            taker_limit_price,
+            oracle_price
+        )?;
+    }
 
-    let meets_initial_margin_requirement = meets_place_order_margin_requirement(
-        user,
+    place_perp_order(
+        state,
+        taker,
         perp_market_map,
         spot_market_map,
         oracle_map,
-        risk_decreasing,
+        clock,
+        OrderParams {
+            order_type: OrderType::Limit,
+            market_type: MarketType::Perp,
+            direction: taker_direction,
+            base_asset_amount: taker_request.base_asset_amount,
+            price: taker_limit_price,
+            market_index,
+            reduce_only: taker_request.reduce_only,
+            immediate_or_cancel: true,
+            ..OrderParams::default()
+        },
+        &mut PlaceOrderOptions::default(),
     )?;
+    let taker_order_id = load!(taker)?.get_last_order_id();
 
-    if !meets_initial_margin_requirement {
-        return Err(ErrorCode::InvalidOrderForInitialMarginReq);
+    let mut maker_order_ids = Vec::with_capacity(maker_quotes.len());
+    for quote in maker_quotes.iter() {
+        let maker = makers.0.get(&quote.maker).ok_or(ErrorCode::UserNotFound)?;
+        place_perp_order(
+            state,
+            maker,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            clock,
+            OrderParams {
+                order_type: OrderType::Limit,
+                market_type: MarketType::Perp,
+                direction: quote.direction,
+                base_asset_amount: quote.base_asset_amount,
+                price: quote.price,
+                market_index,
+                immediate_or_cancel: true,
+                ..OrderParams::default()
+            },
+            &mut PlaceOrderOptions::default(),
+        )?;
+        maker_order_ids.push(load!(maker)?.get_last_order_id());
     }
 
-    if force_reduce_only && !risk_decreasing {
-        return Err(ErrorCode::InvalidOrderNotRiskReducing);
+    let taker = &mut load_mut!(taker)?;
+    let taker_stats = &mut load_mut!(taker_stats)?;
+    let taker_order_index = taker.get_order_index(taker_order_id)?;
+    let taker_order_risk_decreasing =
+        determine_if_user_order_is_risk_decreasing(taker, market_index, taker_order_index)?;
+
+    {
+        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+        controller::lp::settle_funding_payment_then_lp(taker, &taker_key, &mut market, now)?;
     }
 
-    validate_spot_margin_trading(user, spot_market_map, oracle_map)?;
+    let is_filler_taker = taker_key == filler_key;
+    let is_filler_maker = makers.0.contains_key(&filler_key);
+    let (mut filler, mut filler_stats) = if !is_filler_maker && !is_filler_taker {
+        let filler = load_mut!(filler)?;
+        if filler.authority != taker.authority {
+            (Some(filler), Some(load_mut!(filler_stats)?))
+        } else {
+            (None, None)
+        }
+    } else {
+        (None, None)
+    };
 
-    let (taker, taker_order, maker, maker_order) =
-        get_taker_and_maker_for_order_record(&user_key, &new_order);
+    // quotes are matched in the order the taker submitted them
+    let mut base_asset_amount = 0_u64;
+    let mut fill_record_buffer = FillRecordBuffer::default();
+    for (quote, maker_order_id) in maker_quotes.iter().zip(maker_order_ids.iter()) {
+        if taker.orders[taker_order_index].status != OrderStatus::Open {
+            break;
+        }
 
-    let order_action_record = get_order_action_record(
-        now,
-        OrderAction::Place,
-        OrderActionExplanation::None,
-        params.market_index,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        taker,
-        taker_order,
-        maker,
-        maker_order,
-        oracle_price_data.price,
+        let mut maker = makers.get_ref_mut(&quote.maker)?;
+        let mut maker_stats = if maker.authority == taker.authority {
+            None
+        } else {
+            Some(makers_stats.get_ref_mut(&maker.authority)?)
+        };
+        let maker_order_index = maker.get_order_index(*maker_order_id)?;
+
+        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+        settle_funding_payment(&mut maker, &quote.maker, &mut market, now)?;
+
+        let (fill_base_asset_amount, fill_quote_asset_amount) = fulfill_perp_order_with_match(
+            market.deref_mut(),
+            taker,
+            taker_stats,
+            taker_order_index,
+            &taker_key,
+            &mut maker,
+            &mut maker_stats.as_deref_mut(),
+            maker_order_index,
+            &quote.maker,
+            &mut filler.as_deref_mut(),
+            &mut filler_stats.as_deref_mut(),
+            &filler_key,
+            &mut None,
+            &mut None,
+            &mut None,
+            &mut None,
+            reserve_price_before,
+            Some(oracle_price),
+            now,
+            slot,
+            &fee_structure,
+            oracle_map,
+            &mut fill_record_buffer,
+        )?;
+
+        market.update_fill_stats(
+            fill_base_asset_amount,
+            fill_quote_asset_amount,
+            taker_direction,
+            now,
+        )?;
+        maker.update_last_active_slot(slot);
+
+        base_asset_amount = base_asset_amount.safe_add(fill_base_asset_amount)?;
+    }
+
+    fill_record_buffer.flush()?;
+
+    validate!(
+        base_asset_amount == taker_request.base_asset_amount,
+        ErrorCode::RfqNotFullyFilled,
+        "rfq filled {} of requested {}",
+        base_asset_amount,
+        taker_request.base_asset_amount
     )?;
-    emit!(order_action_record);
 
-    let order_record = OrderRecord {
-        ts: now,
-        user: user_key,
-        order: user.orders[new_order_index],
-    };
-    emit!(order_record);
+    // quotes bigger than the request leave the rest of the maker order open
+    for (quote, maker_order_id) in maker_quotes.iter().zip(maker_order_ids.iter()) {
+        let mut maker = makers.get_ref_mut(&quote.maker)?;
+        if let Ok(maker_order_index) = maker.get_order_index(*maker_order_id) {
+            cancel_order(
+                maker_order_index,
+                &mut maker,
+                &quote.maker,
+                perp_market_map,
+                spot_market_map,
+                oracle_map,
+                now,
+                slot,
+                OrderActionExplanation::None,
+                Some(&filler_key),
+                0,
+                false,
+            )?;
+        }
+    }
 
-    user.update_last_active_slot(slot);
+    {
+        let market = perp_market_map.get_ref(&market_index)?;
+        let taker_maintenance_margin_buffer = calculate_maintenance_buffer_ratio(
+            market.margin_ratio_initial,
+            market.margin_ratio_maintenance,
+            taker_order_risk_decreasing,
+        )?;
+        let maker_maintenance_margin_buffer = calculate_maintenance_buffer_ratio(
+            market.margin_ratio_initial,
+            market.margin_ratio_maintenance,
+            false,
+        )?;
+        drop(market);
+
+        let (_, total_collateral, margin_requirement_plus_buffer, _) =
+            calculate_margin_requirement_and_total_collateral(
+                taker,
+                perp_market_map,
+                MarginRequirementType::Maintenance,
+                spot_market_map,
+                oracle_map,
+                Some(taker_maintenance_margin_buffer.cast()?),
+            )?;
+
+        validate!(
+            total_collateral >= margin_requirement_plus_buffer.cast()?,
+            ErrorCode::InsufficientCollateral,
+            "taker breached maintenance requirements (margin requirement plus buffer {}) (total_collateral {})",
+            margin_requirement_plus_buffer,
+            total_collateral
+        )?;
+
+        validate!(
+            meets_isolated_perp_position_margin_requirement(
+                taker,
+                market_index,
+                perp_market_map,
+                MarginRequirementType::Maintenance,
+                oracle_map,
+                Some(taker_maintenance_margin_buffer.cast()?),
+            )?,
+            ErrorCode::InsufficientCollateral,
+            "taker breached isolated maintenance requirements for perp market {}",
+            market_index
+        )?;
+
+        for quote in maker_quotes.iter() {
+            let maker = makers.get_ref(&quote.maker)?;
+
+            validate!(
+                meets_isolated_perp_position_margin_requirement(
+                    &maker,
+                    market_index,
+                    perp_market_map,
+                    MarginRequirementType::Maintenance,
+                    oracle_map,
+                    Some(maker_maintenance_margin_buffer.cast()?),
+                )?,
+                ErrorCode::InsufficientCollateral,
+                "maker ({}) breached isolated maintenance requirements for perp market {}",
+                quote.maker,
+                market_index
+            )?;
+            let (_, total_collateral, margin_requirement_plus_buffer, _) =
+                calculate_margin_requirement_and_total_collateral(
+                    &maker,
+                    perp_market_map,
+                    MarginRequirementType::Maintenance,
+                    spot_market_map,
+                    oracle_map,
+                    Some(maker_maintenance_margin_buffer.cast()?),
+                )?;
+
+            validate!(
+                total_collateral >= margin_requirement_plus_buffer.cast()?,
+                ErrorCode::InsufficientCollateral,
+                "maker ({}) breached maintenance requirements (margin requirement plus buffer {}) (total_collateral {})",
+                quote.maker,
+                margin_requirement_plus_buffer,
+                total_collateral
+            )?;
+        }
+    }
+
+    {
+        let market = perp_market_map.get_ref(&market_index)?;
+        validate_market_within_price_band(
+            &market,
+            state,
+            !taker_order_risk_decreasing,
+            Some(oracle_reserve_price_spread_pct_before),
+        )?;
+    }
+
+    taker.update_last_active_slot(slot);
+
+    Ok(base_asset_amount)
+}
+
+pub fn update_order_after_fill(
+    order: &mut Order,
+    base_asset_amount: u64,
+    quote_asset_amount: u64,
+) -> DriftResult {
+    order.base_asset_amount_filled = order.base_asset_amount_filled.safe_add(base_asset_amount)?;
+
+    order.quote_asset_amount_filled = order
+        .quote_asset_amount_filled
+        .safe_add(quote_asset_amount)?;
+
+    if order.get_base_asset_amount_unfilled(None)? == 0 {
+        order.status = OrderStatus::Filled;
+    }
 
     Ok(())
 }
 
-pub fn fill_spot_order(
+fn get_valid_oracle_price(
+    oracle_price_data: &OraclePriceData,
+    market: &PerpMarket,
+    order: &Order,
+    validity_guardrails: &ValidityGuardRails,
+) -> DriftResult<Option<i64>> {
+    let price = {
+        let oracle_validity = oracle::oracle_validity(
+            market.amm.historical_oracle_data.last_oracle_price_twap,
+            oracle_price_data,
+            validity_guardrails,
+        )?;
+
+        let is_oracle_valid =
+            is_oracle_valid_for_action(oracle_validity, Some(DriftAction::FillOrderAmm))?;
+
+        if is_oracle_valid {
+            Some(oracle_price_data.price)
+        } else if order.has_oracle_price_offset() {
+            msg!("Invalid oracle for order with oracle price offset");
+            return Err(print_error!(ErrorCode::InvalidOracle)());
+        } else {
+            msg!("Oracle is invalid");
+            None
+        }
+    };
+
+    Ok(price)
+}
+
+#[allow(clippy::type_complexity)]
+fn get_taker_and_maker_for_order_record(
+    user_key: &Pubkey,
+    user_order: &Order,
+) -> (Option<Pubkey>, Option<Order>, Option<Pubkey>, Option<Order>) {
+    if user_order.post_only {
+        (None, None, Some(*user_key), Some(*user_order))
+    } else {
+        (Some(*user_key), Some(*user_order), None, None)
+    }
+}
+
+pub fn trigger_order(
     order_id: u32,
     state: &State,
     user: &AccountLoader<User>,
-    user_stats: &AccountLoader<UserStats>,
     spot_market_map: &SpotMarketMap,
     perp_market_map: &PerpMarketMap,
     oracle_map: &mut OracleMap,
     filler: &AccountLoader<User>,
-    filler_stats: &AccountLoader<UserStats>,
-    maker: Option<&AccountLoader<User>>,
-    maker_stats: Option<&AccountLoader<UserStats>>,
-    maker_order_id: Option<u32>,
     clock: &Clock,
-    serum_fulfillment_params: &mut Option<SerumFulfillmentParams>,
-) -> DriftResult<u64> {
+) -> DriftResult {
     let now = clock.unix_timestamp;
     let slot = clock.slot;
 
     let filler_key = filler.key();
     let user_key = user.key();
     let user = &mut load_mut!(user)?;
-    let user_stats = &mut load_mut!(user_stats)?;
 
     let order_index = user
         .orders
@@ -2903,30 +4123,9 @@ pub fn fill_spot_order(
         .position(|order| order.order_id == order_id)
         .ok_or_else(print_error!(ErrorCode::OrderDoesNotExist))?;
 
-    let (order_status, order_market_index, order_market_type) =
+    let (order_status, market_index, market_type) =
         get_struct_values!(user.orders[order_index], status, market_index, market_type);
 
-    {
-        let spot_market = spot_market_map.get_ref(&order_market_index)?;
-        validate!(
-            matches!(
-                spot_market.status,
-                MarketStatus::Active
-                    | MarketStatus::FundingPaused
-                    | MarketStatus::ReduceOnly
-                    | MarketStatus::WithdrawPaused
-            ),
-            ErrorCode::MarketFillOrderPaused,
-            "Market unavailable for fills"
-        )?;
-    }
-
-    validate!(
-        order_market_type == MarketType::Spot,
-        ErrorCode::InvalidOrderMarketType,
-        "must be spot order"
-    )?;
-
     validate!(
         order_status == OrderStatus::Open,
         ErrorCode::OrderNotOpen,
@@ -2934,169 +4133,195 @@ pub fn fill_spot_order(
     )?;
 
     validate!(
-        !user.orders[order_index].must_be_triggered() || user.orders[order_index].triggered(),
-        ErrorCode::OrderMustBeTriggeredFirst,
-        "Order must be triggered first"
+        user.orders[order_index].must_be_triggered(),
+        ErrorCode::OrderNotTriggerable,
+        "Order is not triggerable"
     )?;
 
-    if user.is_bankrupt() {
-        msg!("User is bankrupt");
-        return Ok(0);
-    }
+    validate!(
+        !user.orders[order_index].triggered(),
+        ErrorCode::OrderNotTriggerable,
+        "Order is already triggered"
+    )?;
 
-    match validate_user_not_being_liquidated(
+    validate!(
+        market_type == MarketType::Perp,
+        ErrorCode::InvalidOrderMarketType,
+        "Order must be a perp order"
+    )?;
+
+    validate_user_not_being_liquidated(
         user,
         perp_market_map,
         spot_market_map,
         oracle_map,
         state.liquidation_margin_buffer_ratio,
-    ) {
-        Ok(_) => {}
-        Err(_) => {
-            msg!("User is being liquidated");
-            return Ok(0);
-        }
-    }
+    )?;
 
-    let is_filler_taker = user_key == filler_key;
-    let is_filler_maker = maker.map_or(false, |maker| maker.key() == filler_key);
-    let (mut filler, mut filler_stats) = if !is_filler_maker && !is_filler_taker {
-        let filler = load_mut!(filler)?;
-        if filler.authority != user.authority {
-            (Some(filler), Some(load_mut!(filler_stats)?))
-        } else {
-            (None, None)
-        }
-    } else {
-        (None, None)
-    };
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
 
-    let (mut maker, mut maker_stats, maker_key, maker_order_index) = get_spot_maker_order(
-        perp_market_map,
-        spot_market_map,
-        oracle_map,
-        maker,
-        maker_stats,
-        maker_order_id,
-        &user_key,
-        &user.authority,
-        &user.orders[order_index],
-        &mut filler.as_deref_mut(),
-        &filler_key,
-        state.spot_fee_structure.flat_filler_fee,
-        now,
-        slot,
+    let mut perp_market = perp_market_map.get_ref_mut(&market_index)?;
+    let oracle_price_data = &oracle_map.get_price_data(&perp_market.amm.oracle)?;
+
+    let oracle_validity = oracle::oracle_validity(
+        perp_market
+            .amm
+            .historical_oracle_data
+            .last_oracle_price_twap,
+        oracle_price_data,
+        &state
+            .oracle_guard_rails
+            .validity
+            .with_override(&perp_market.amm.validity_guard_rails_override),
     )?;
+    let is_oracle_valid =
+        is_oracle_valid_for_action(oracle_validity, Some(DriftAction::TriggerOrder))?;
 
-    let should_expire_order = should_expire_order(user, order_index, now)?;
+    validate!(is_oracle_valid, ErrorCode::InvalidOracle)?;
 
-    let should_cancel_reduce_only = if user.orders[order_index].reduce_only {
-        let market_index = user.orders[order_index].market_index;
-        let position_index = user.get_spot_position_index(market_index)?;
-        let spot_market = spot_market_map.get_ref(&market_index)?;
-        let signed_token_amount =
-            user.spot_positions[position_index].get_signed_token_amount(&spot_market)?;
-        should_cancel_reduce_only_order(&user.orders[order_index], signed_token_amount.cast()?)?
+    let oracle_price = oracle_price_data.price;
+
+    let can_trigger = if user.orders[order_index].is_funding_triggered() {
+        let predicted_funding_rate = calculate_predicted_funding_rate(&perp_market)?;
+        order_satisfies_funding_trigger_condition(
+            &user.orders[order_index],
+            predicted_funding_rate,
+        )?
     } else {
-        false
+        order_satisfies_trigger_condition(
+            &user.orders[order_index],
+            oracle_price.unsigned_abs().cast()?,
+        )?
     };
 
-    if should_expire_order || should_cancel_reduce_only {
-        let filler_reward = {
-            let mut quote_market = spot_market_map.get_quote_spot_market_mut()?;
-            pay_keeper_flat_reward_for_spot(
-                user,
-                filler.as_deref_mut(),
-                &mut quote_market,
-                state.spot_fee_structure.flat_filler_fee,
-            )?
-        };
-
-        let explanation = if should_expire_order {
-            OrderActionExplanation::OrderExpired
-        } else {
-            OrderActionExplanation::ReduceOnlyOrderIncreasedPosition
-        };
+    if !can_trigger && user.orders[order_index].is_trailing() {
+        let trigger_price = user.orders[order_index].trigger_price;
+        let new_trigger_price = calculate_trailing_trigger_price(
+            &user.orders[order_index],
+            oracle_price.unsigned_abs().cast()?,
+            perp_market.amm.order_tick_size,
+        )?;
 
-        cancel_order(
-            order_index,
-            user,
-            &user_key,
-            perp_market_map,
-            spot_market_map,
-            oracle_map,
-            now,
-            slot,
-            explanation,
-            Some(&filler_key),
-            filler_reward,
-            false,
+        validate!(
+            new_trigger_price != trigger_price,
+            ErrorCode::OrderDidNotSatisfyTriggerCondition,
+            "Trailing trigger price unchanged"
         )?;
-        return Ok(0);
+
+        msg!(
+            "Trailing trigger price updated from {} to {}",
+            trigger_price,
+            new_trigger_price
+        );
+        user.orders[order_index].trigger_price = new_trigger_price;
+
+        return Ok(());
     }
 
-    let (base_asset_amount, _updated_user_state) = fulfill_spot_order(
+    validate!(can_trigger, ErrorCode::OrderDidNotSatisfyTriggerCondition)?;
+
+    // a stop placed before a partial close only gets to close what is left
+    let position_index = get_position_index(&user.perp_positions, market_index)?;
+    resize_reduce_only_order(user, order_index, position_index)?;
+
+    {
+        let direction = user.orders[order_index].direction;
+        let base_asset_amount = user.orders[order_index].base_asset_amount;
+
+        user.orders[order_index].trigger_condition =
+            match user.orders[order_index].trigger_condition {
+                OrderTriggerCondition::Above
+                | OrderTriggerCondition::TrailingAbove
+                | OrderTriggerCondition::FundingAbove => OrderTriggerCondition::TriggeredAbove,
+                OrderTriggerCondition::Below
+                | OrderTriggerCondition::TrailingBelow
+                | OrderTriggerCondition::FundingBelow => OrderTriggerCondition::TriggeredBelow,
+                _ => {
+                    return Err(print_error!(ErrorCode::InvalidTriggerOrderCondition)());
+                }
+            };
+
+        user.orders[order_index].slot = slot;
+        let order_type = user.orders[order_index].order_type;
+        if let OrderType::TriggerMarket = order_type {
+            user.orders[order_index].auction_duration = state.min_perp_auction_duration;
+            let (auction_start_price, auction_end_price) =
+                calculate_auction_prices(oracle_price_data, direction, 0)?;
+            user.orders[order_index].auction_start_price = auction_start_price;
+            user.orders[order_index].auction_end_price = auction_end_price;
+        }
+
+        let user_position = user.get_perp_position_mut(market_index)?;
+        increase_open_bids_and_asks(user_position, &direction, base_asset_amount)?;
+    }
+
+    let is_filler_taker = user_key == filler_key;
+    let mut filler = if !is_filler_taker {
+        Some(load_mut!(filler)?)
+    } else {
+        None
+    };
+
+    let filler_reward = pay_keeper_flat_reward_for_perps(
+        user,
+        filler.as_deref_mut(),
+        &mut perp_market,
+        state.perp_fee_structure.flat_filler_fee,
+        slot,
+    )?;
+
+    let order_action_record = get_order_action_record(
+        now,
+        OrderAction::Trigger,
+        OrderActionExplanation::None,
+        market_index,
+        Some(filler_key),
+        None,
+        Some(filler_reward),
+        None,
+        None,
+        Some(filler_reward),
+        None,
+        None,
+        None,
+        None,
+        Some(user_key),
+        Some(user.orders[order_index]),
+        None,
+        None,
+        oracle_price,
+    )?;
+    emit!(order_action_record);
+
+    drop(perp_market);
+
+    cancel_linked_orders(
         user,
-        order_index,
         &user_key,
-        user_stats,
-        &mut maker.as_deref_mut(),
-        &mut maker_stats.as_deref_mut(),
-        maker_order_index,
-        maker_key.as_ref(),
-        &mut filler.as_deref_mut(),
-        &filler_key,
-        &mut filler_stats.as_deref_mut(),
-        spot_market_map,
+        Some(&filler_key),
         perp_market_map,
+        spot_market_map,
         oracle_map,
         now,
         slot,
-        &state.spot_fee_structure,
-        serum_fulfillment_params,
     )?;
 
-    let is_open = user.orders[order_index].status == OrderStatus::Open;
-    let is_reduce_only = user.orders[order_index].reduce_only;
-    let should_cancel_reduce_only = if is_open && is_reduce_only {
-        let market_index = user.orders[order_index].market_index;
-        let position_index = user.get_spot_position_index(market_index)?;
-        let spot_market = spot_market_map.get_ref(&market_index)?;
-        let signed_token_amount =
-            user.spot_positions[position_index].get_signed_token_amount(&spot_market)?;
-        should_cancel_reduce_only_order(&user.orders[order_index], signed_token_amount.cast()?)?
-    } else {
-        false
-    };
-
-    let should_cancel_for_no_borrow_liquidity = if is_open {
-        let market_index = user.orders[order_index].market_index;
-        let base_market = spot_market_map.get_ref(&market_index)?;
-        let quote_market = spot_market_map.get_quote_spot_market()?;
-        let (max_base_asset_amount, max_quote_asset_amount) =
-            get_max_fill_amounts(user, order_index, &base_market, &quote_market)?;
-        max_base_asset_amount == Some(0) || max_quote_asset_amount == Some(0)
-    } else {
-        false
-    };
-
-    if should_cancel_reduce_only || should_cancel_for_no_borrow_liquidity {
-        let filler_reward = {
-            let mut quote_market = spot_market_map.get_quote_spot_market_mut()?;
-            pay_keeper_flat_reward_for_spot(
-                user,
-                filler.as_deref_mut(),
-                &mut quote_market,
-                state.spot_fee_structure.flat_filler_fee,
-            )?
-        };
+    // If order is risk increasing and user is below initial margin, cancel it
+    let order_direction = user.orders[order_index].direction;
+    let order_base_asset_amount = user.orders[order_index].base_asset_amount;
+    let position_base_asset_amount = user
+        .force_get_perp_position_mut(market_index)?
+        .base_asset_amount;
+    let is_risk_increasing = is_order_risk_increasing(
+        &order_direction,
+        order_base_asset_amount,
+        position_base_asset_amount,
+    )?;
 
-        let explanation = if should_cancel_reduce_only {
-            OrderActionExplanation::ReduceOnlyOrderIncreasedPosition
-        } else {
-            OrderActionExplanation::NoBorrowLiquidity
-        };
+    let meets_initial_margin_requirement =
+        meets_initial_margin_requirement(user, perp_market_map, spot_market_map, oracle_map)?;
 
+    if is_risk_increasing && !meets_initial_margin_requirement {
         cancel_order(
             order_index,
             user,
@@ -3106,333 +4331,2122 @@ pub fn fill_spot_order(
             oracle_map,
             now,
             slot,
-            explanation,
+            OrderActionExplanation::InsufficientFreeCollateral,
             Some(&filler_key),
-            filler_reward,
+            0,
             false,
-        )?
+        )?;
     }
 
     user.update_last_active_slot(slot);
 
-    Ok(base_asset_amount)
+    Ok(())
 }
 
-#[allow(clippy::type_complexity)]
-fn get_spot_maker_order<'a>(
-    perp_market_map: &PerpMarketMap,
+pub fn force_cancel_orders(
+    state: &State,
+    user: &AccountLoader<User>,
     spot_market_map: &SpotMarketMap,
+    perp_market_map: &PerpMarketMap,
     oracle_map: &mut OracleMap,
-    maker: Option<&'a AccountLoader<User>>,
-    maker_stats: Option<&'a AccountLoader<UserStats>>,
-    maker_order_id: Option<u32>,
-    taker_key: &Pubkey,
-    taker_authority: &Pubkey,
-    taker_order: &Order,
-    filler: &mut Option<&mut User>,
-    filler_key: &Pubkey,
-    filler_reward: u64,
-    now: i64,
-    slot: u64,
-) -> DriftResult<(
-    Option<RefMut<'a, User>>,
-    Option<RefMut<'a, UserStats>>,
-    Option<Pubkey>,
-    Option<usize>,
-)> {
-    if maker.is_none() || maker_stats.is_none() {
-        return Ok((None, None, None, None));
-    }
-
-    let maker = maker.safe_unwrap()?;
-    if &maker.key() == taker_key {
-        return Ok((None, None, None, None));
-    }
-
-    let maker_key = maker.key();
-    let mut maker = load_mut!(maker)?;
+    filler: &AccountLoader<User>,
+    clock: &Clock,
+) -> DriftResult {
+    let now = clock.unix_timestamp;
+    let slot = clock.slot;
 
-    maker.update_last_active_slot(slot);
+    let filler_key = filler.key();
+    let user_key = user.key();
+    let user = &mut load_mut!(user)?;
+    let filler = &mut load_mut!(filler)?;
 
-    let maker_stats = if &maker.authority == taker_authority {
-        None
-    } else {
-        let maker_stats = load_mut!(maker_stats.safe_unwrap()?)?;
+    validate!(
+        !user.is_being_liquidated(),
+        ErrorCode::UserIsBeingLiquidated
+    )?;
 
-        validate!(
-            maker.authority.eq(&maker_stats.authority),
-            ErrorCode::InvalidMaker,
-            "maker authority != maker stats authority"
-        )?;
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
 
-        Some(maker_stats)
-    };
+    let meets_initial_margin_requirement =
+        meets_initial_margin_requirement(user, perp_market_map, spot_market_map, oracle_map)?;
 
-    let maker_order_id = maker_order_id.ok_or(ErrorCode::MakerOrderNotFound)?;
-    let maker_order_index = match maker.get_order_index(maker_order_id) {
-        Ok(order_index) => order_index,
-        Err(_) => {
-            msg!("Maker has no order id {}", maker_order_id);
-            return Ok((None, None, None, None));
-        }
-    };
+    validate!(
+        !meets_initial_margin_requirement,
+        ErrorCode::SufficientCollateral
+    )?;
 
-    {
-        let maker_order = &maker.orders[maker_order_index];
-        if !is_maker_for_taker(maker_order, taker_order, slot)? {
-            return Ok((None, None, None, None));
-        }
+    let mut total_fee = 0_u64;
 
-        if maker.is_being_liquidated() || maker.is_bankrupt() {
-            return Ok((None, None, None, None));
+    for order_index in 0..user.orders.len() {
+        if user.orders[order_index].status != OrderStatus::Open {
+            continue;
         }
 
-        validate!(
-            !maker_order.must_be_triggered() || maker_order.triggered(),
-            ErrorCode::OrderMustBeTriggeredFirst,
-            "Maker order not triggered"
-        )?;
+        let market_index = user.orders[order_index].market_index;
+        let market_type = user.orders[order_index].market_type;
 
-        validate!(
-            maker_order.market_type == MarketType::Spot,
-            ErrorCode::InvalidOrderMarketType,
-            "Maker order not a spot order"
-        )?
-    }
+        let fee = match market_type {
+            MarketType::Spot => {
+                let spot_market = spot_market_map.get_ref(&market_index)?;
+                let is_risk_decreasing = determine_if_user_spot_order_is_risk_decreasing(
+                    user,
+                    &spot_market,
+                    order_index,
+                )?;
+                if is_risk_decreasing {
+                    continue;
+                }
 
-    let spot_market = spot_market_map.get_ref(&maker.orders[maker_order_index].market_index)?;
-    let breaches_oracle_price_limits = {
-        let oracle_price = oracle_map.get_price_data(&spot_market.oracle)?;
-        let initial_margin_ratio = spot_market.get_margin_ratio(&MarginRequirementType::Initial)?;
-        let maintenance_margin_ratio =
-            spot_market.get_margin_ratio(&MarginRequirementType::Maintenance)?;
-        order_breaches_oracle_price_bands(
-            &maker.orders[maker_order_index],
-            oracle_price.price,
+                state.spot_fee_structure.flat_filler_fee
+            }
+            MarketType::Perp => {
+                let is_risk_decreasing =
+                    determine_if_user_order_is_risk_decreasing(user, market_index, order_index)?;
+                if is_risk_decreasing {
+                    continue;
+                }
+
+                state.perp_fee_structure.flat_filler_fee
+            }
+        };
+
+        total_fee = total_fee.safe_add(fee)?;
+
+        cancel_order(
+            order_index,
+            user,
+            &user_key,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            now,
             slot,
-            spot_market.order_tick_size,
-            initial_margin_ratio,
-            maintenance_margin_ratio,
-        )?
-    };
+            OrderActionExplanation::InsufficientFreeCollateral,
+            Some(&filler_key),
+            fee,
+            false,
+        )?;
+    }
 
-    let should_expire_order = should_expire_order(&maker, maker_order_index, now)?;
+    pay_keeper_flat_reward_for_spot(
+        user,
+        Some(filler),
+        spot_market_map.get_quote_spot_market_mut()?.deref_mut(),
+        total_fee,
+    )?;
 
-    let should_cancel_reduce_only_order = if maker.orders[maker_order_index].reduce_only {
-        let spot_position_index =
-            maker.get_spot_position_index(maker.orders[maker_order_index].market_index)?;
-        let signed_token_amount =
-            maker.spot_positions[spot_position_index].get_signed_token_amount(&spot_market)?;
-        should_cancel_reduce_only_order(
-            &maker.orders[maker_order_index],
-            signed_token_amount.cast()?,
-        )?
-    } else {
-        false
-    };
+    user.update_last_active_slot(slot);
 
-    if breaches_oracle_price_limits || should_expire_order || should_cancel_reduce_only_order {
-        let filler_reward = {
-            let mut quote_market = spot_market_map.get_quote_spot_market_mut()?;
-            pay_keeper_flat_reward_for_spot(
-                &mut maker,
-                filler.as_deref_mut(),
-                &mut quote_market,
-                filler_reward,
-            )?
-        };
+    Ok(())
+}
 
-        let explanation = if breaches_oracle_price_limits {
-            OrderActionExplanation::OraclePriceBreachedLimitPrice
-        } else if should_expire_order {
+/// cancels orders past their max_ts and reduce only trigger orders left with no position to
+/// reduce, paying the filler the flat filler fee for each
+pub fn cancel_stale_orders(
+    state: &State,
+    user: &AccountLoader<User>,
+    spot_market_map: &SpotMarketMap,
+    perp_market_map: &PerpMarketMap,
+    oracle_map: &mut OracleMap,
+    filler: &AccountLoader<User>,
+    clock: &Clock,
+) -> DriftResult {
+    let now = clock.unix_timestamp;
+    let slot = clock.slot;
+
+    let filler_key = filler.key();
+    let user_key = user.key();
+    let user = &mut load_mut!(user)?;
+    let filler = &mut load_mut!(filler)?;
+
+    validate!(
+        !user.is_being_liquidated(),
+        ErrorCode::UserIsBeingLiquidated
+    )?;
+
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
+
+    let mut total_fee = 0_u64;
+
+    for order_index in 0..user.orders.len() {
+        let explanation = if should_expire_order(user, order_index, now)? {
             OrderActionExplanation::OrderExpired
+        } else if is_orphaned_trigger_order(user, order_index)? {
+            OrderActionExplanation::OrphanedTriggerOrder
         } else {
-            OrderActionExplanation::ReduceOnlyOrderIncreasedPosition
+            continue;
+        };
+
+        let fee = match user.orders[order_index].market_type {
+            MarketType::Spot => state.spot_fee_structure.flat_filler_fee,
+            MarketType::Perp => state.perp_fee_structure.flat_filler_fee,
         };
 
+        total_fee = total_fee.safe_add(fee)?;
+
         cancel_order(
-            maker_order_index,
-            maker.deref_mut(),
-            &maker_key,
+            order_index,
+            user,
+            &user_key,
             perp_market_map,
             spot_market_map,
             oracle_map,
             now,
             slot,
             explanation,
-            Some(filler_key),
-            filler_reward,
+            Some(&filler_key),
+            fee,
             false,
         )?;
-
-        return Ok((None, None, None, None));
     }
 
-    Ok((
-        Some(maker),
-        maker_stats,
-        Some(maker_key),
-        Some(maker_order_index),
-    ))
+    validate!(
+        total_fee > 0,
+        ErrorCode::NoStaleOrders,
+        "user has no expired or orphaned trigger orders"
+    )?;
+
+    pay_keeper_flat_reward_for_spot(
+        user,
+        Some(filler),
+        spot_market_map.get_quote_spot_market_mut()?.deref_mut(),
+        total_fee,
+    )?;
+
+    Ok(())
 }
 
-fn fulfill_spot_order(
-    user: &mut User,
-    user_order_index: usize,
-    user_key: &Pubkey,
-    user_stats: &mut UserStats,
-    maker: &mut Option<&mut User>,
-    maker_stats: &mut Option<&mut UserStats>,
-    maker_order_index: Option<usize>,
-    maker_key: Option<&Pubkey>,
-    filler: &mut Option<&mut User>,
-    filler_key: &Pubkey,
-    filler_stats: &mut Option<&mut UserStats>,
-    spot_market_map: &SpotMarketMap,
-    perp_market_map: &PerpMarketMap,
-    oracle_map: &mut OracleMap,
-    now: i64,
-    slot: u64,
-    fee_structure: &FeeStructure,
-    serum_fulfillment_params: &mut Option<SerumFulfillmentParams>,
-) -> DriftResult<(u64, bool)> {
-    let base_market_index = user.orders[user_order_index].market_index;
+fn is_orphaned_trigger_order(user: &User, order_index: usize) -> DriftResult<bool> {
+    let order = &user.orders[order_index];
+    if order.market_type != MarketType::Perp || !order.must_be_triggered() || order.triggered() {
+        return Ok(false);
+    }
 
-    let fulfillment_methods = determine_spot_fulfillment_methods(
-        &user.orders[user_order_index],
-        maker.is_some(),
-        serum_fulfillment_params.is_some(),
-    )?;
+    let existing_base_asset_amount = user
+        .get_perp_position(order.market_index)
+        .map_or(0, |position| position.base_asset_amount);
 
-    let mut quote_market = spot_market_map.get_quote_spot_market_mut()?;
-    let mut base_market = spot_market_map.get_ref_mut(&base_market_index)?;
+    should_cancel_reduce_only_order(order, existing_base_asset_amount)
+}
 
-    let mut base_asset_amount = 0_u64;
-    for fulfillment_method in fulfillment_methods.iter() {
-        if user.orders[user_order_index].status != OrderStatus::Open {
-            break;
-        }
+pub fn can_reward_user_with_perp_pnl(user: &mut Option<&mut User>, market_index: u16) -> bool {
+    match user.as_mut() {
+        Some(user) => user.force_get_perp_position_mut(market_index).is_ok(),
+        None => false,
+    }
+}
 
-        let _base_asset_amount = match fulfillment_method {
-            SpotFulfillmentMethod::Match => fulfill_spot_order_with_match(
-                &mut base_market,
-                &mut quote_market,
-                user,
-                user_stats,
-                user_order_index,
-                user_key,
-                maker.as_deref_mut().safe_unwrap()?,
-                maker_stats,
-                maker_order_index.safe_unwrap()?,
-                maker_key.safe_unwrap()?,
-                filler.as_deref_mut(),
-                filler_stats.as_deref_mut(),
-                filler_key,
-                now,
-                slot,
-                oracle_map,
-                fee_structure,
-            )?,
-            SpotFulfillmentMethod::SerumV3 => fulfill_spot_order_with_serum(
-                &mut base_market,
-                &mut quote_market,
+pub fn pay_keeper_flat_reward_for_perps(
+    user: &mut User,
+    filler: Option<&mut User>,
+    market: &mut PerpMarket,
+    filler_reward: u64,
+    slot: u64,
+) -> DriftResult<u64> {
+    let filler_reward = if let Some(filler) = filler {
+        let user_position = user.get_perp_position_mut(market.market_index)?;
+        controller::position::update_quote_asset_and_break_even_amount(
+            user_position,
+            market,
+            -filler_reward.cast()?,
+        )?;
+
+        filler.update_last_active_slot(slot);
+        // Dont throw error if filler doesnt have position available
+        let filler_position = match filler.force_get_perp_position_mut(market.market_index) {
+            Ok(position) => position,
+            Err(_) => return Ok(0),
+        };
+        controller::position::update_quote_asset_amount(
+            filler_position,
+            market,
+            filler_reward.cast()?,
+        )?;
+
+        filler_reward
+    } else {
+        0
+    };
+
+    Ok(filler_reward)
+}
+
+pub fn pay_builder_reward_for_perps(
+    builder: &mut User,
+    market: &mut PerpMarket,
+    taker_fee: u64,
+    builder_fee_share: u8,
+) -> DriftResult<u64> {
+    let builder_reward = fees::calculate_builder_reward(taker_fee, builder_fee_share)?;
+    if builder_reward == 0 {
+        return Ok(0);
+    }
+
+    // Dont throw error if builder doesnt have position available
+    let builder_position = match builder.force_get_perp_position_mut(market.market_index) {
+        Ok(position) => position,
+        Err(_) => return Ok(0),
+    };
+
+    // paid out of the protocol's share of the taker fee, like the referrer reward
+    controller::position::update_quote_asset_amount(
+        builder_position,
+        market,
+        builder_reward.cast()?,
+    )?;
+
+    market.amm.total_fee = market.amm.total_fee.safe_sub(builder_reward.cast()?)?;
+    market.amm.total_fee_minus_distributions = market
+        .amm
+        .total_fee_minus_distributions
+        .safe_sub(builder_reward.cast()?)?;
+    market.amm.net_revenue_since_last_funding = market
+        .amm
+        .net_revenue_since_last_funding
+        .safe_sub(builder_reward.cast()?)?;
+
+    Ok(builder_reward)
+}
+
+pub fn pay_keeper_flat_reward_for_spot(
+    user: &mut User,
+    filler: Option<&mut User>,
+    quote_market: &mut SpotMarket,
+    filler_reward: u64,
+) -> DriftResult<u64> {
+    let filler_reward = if let Some(filler) = filler {
+        update_spot_balances(
+            filler_reward as u128,
+            &SpotBalanceType::Deposit,
+            quote_market,
+            filler.get_quote_spot_position_mut(),
+            false,
+        )?;
+
+        filler.update_cumulative_spot_fees(filler_reward.cast()?)?;
+
+        update_spot_balances(
+            filler_reward as u128,
+            &SpotBalanceType::Borrow,
+            quote_market,
+            user.get_quote_spot_position_mut(),
+            false,
+        )?;
+
+        user.update_cumulative_spot_fees(-filler_reward.cast()?)?;
+
+        filler_reward
+    } else {
+        0
+    };
+
+    Ok(filler_reward)
+}
+
+pub fn place_spot_order(
+    state: &State,
+    user: &AccountLoader<User>,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    clock: &Clock,
+    params: OrderParams,
+    options: &mut PlaceOrderOptions,
+) -> DriftResult {
+    let now = clock.unix_timestamp;
+    let slot = clock.slot;
+    let user_key = user.key();
+    let user = &mut load_mut!(user)?;
+
+    validate_user_not_being_liquidated(
+        user,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        state.liquidation_margin_buffer_ratio,
+    )?;
+
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
+
+    expire_orders(
+        user,
+        &user_key,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+    )?;
+
+    let max_ts = match params.max_ts {
+        Some(max_ts) => max_ts,
+        None => match params.order_type {
+            OrderType::Market | OrderType::Oracle => now.safe_add(30)?,
+            _ => 0_i64,
+        },
+    };
+
+    if max_ts != 0 && max_ts < now {
+        msg!("max_ts ({}) < now ({}), skipping order", max_ts, now);
+        return Ok(());
+    }
+
+    handle_user_order_id_conflict(
+        user,
+        &user_key,
+        &params,
+        options.can_cancel_orders,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+    )?;
+
+    let new_order_index = user
+        .orders
+        .iter()
+        .position(|order| order.status.eq(&OrderStatus::Init))
+        .ok_or(ErrorCode::MaxNumberOfOrders)?;
+
+    let market_index = params.market_index;
+    let spot_market = &spot_market_map.get_ref(&market_index)?;
+    let force_reduce_only = spot_market.is_reduce_only()?;
+    let step_size = spot_market.order_step_size;
+
+    validate!(
+        !matches!(spot_market.status, MarketStatus::Initialized),
+        ErrorCode::MarketBeingInitialized,
+        "Market is being initialized"
+    )?;
+
+    let spot_position_index = user
+        .get_spot_position_index(market_index)
+        .or_else(|_| user.add_spot_position(market_index, SpotBalanceType::Deposit))?;
+
+    let oracle_price_data = *oracle_map.get_price_data(&spot_market.oracle)?;
+    let (worst_case_token_amount_before, _) = user.spot_positions[spot_position_index]
+        .get_worst_case_token_amounts(spot_market, &oracle_price_data, None, None)?;
+
+    let balance_type = user.spot_positions[spot_position_index].balance_type;
+    let token_amount = user.spot_positions[spot_position_index].get_token_amount(spot_market)?;
+    let signed_token_amount = get_signed_token_amount(token_amount, &balance_type)?;
+
+    // Increment open orders for existing position
+    let (existing_position_direction, order_base_asset_amount) = {
+        validate!(
+            params.base_asset_amount >= step_size,
+            ErrorCode::InvalidOrderSizeTooSmall,
+            "params.base_asset_amount={} cannot be below spot_market.order_step_size={}",
+            params.base_asset_amount,
+            step_size
+        )?;
+
+        let base_asset_amount = if params.base_asset_amount == u64::MAX {
+            calculate_max_spot_order_size(
                 user,
-                user_stats,
-                user_order_index,
-                user_key,
-                filler.as_deref_mut(),
-                filler_stats.as_deref_mut(),
-                filler_key,
-                now,
-                slot,
+                params.market_index,
+                params.direction,
+                perp_market_map,
+                spot_market_map,
                 oracle_map,
-                fee_structure,
-                serum_fulfillment_params,
-            )?,
+            )?
+        } else {
+            standardize_base_asset_amount(params.base_asset_amount, step_size)?
+        };
+
+        validate!(
+            is_multiple_of_step_size(base_asset_amount, step_size)?,
+            ErrorCode::InvalidOrderNotStepSizeMultiple,
+            "Order base asset amount ({}), is not a multiple of step size ({})",
+            base_asset_amount,
+            step_size
+        )?;
+
+        let existing_position_direction = if signed_token_amount >= 0 {
+            PositionDirection::Long
+        } else {
+            PositionDirection::Short
         };
+        (
+            existing_position_direction,
+            base_asset_amount.cast::<u64>()?,
+        )
+    };
+
+    let (auction_start_price, auction_end_price, auction_duration) = get_auction_params(
+        &params,
+        &oracle_price_data,
+        spot_market.order_tick_size,
+        state.default_spot_auction_duration,
+    )?;
+
+    validate!(spot_market.orders_enabled, ErrorCode::SpotOrdersDisabled)?;
+
+    validate!(
+        params.market_index != QUOTE_SPOT_MARKET_INDEX,
+        ErrorCode::InvalidOrderBaseQuoteAsset,
+        "can not place order for quote asset"
+    )?;
+
+    validate!(
+        params.market_type == MarketType::Spot,
+        ErrorCode::InvalidOrderMarketType,
+        "must be spot order"
+    )?;
+
+    validate!(
+        !params.max_margin_sizing,
+        ErrorCode::InvalidOrder,
+        "max margin sizing only supported for perp orders"
+    )?;
+
+    let new_order = Order {
+        status: OrderStatus::Open,
+        order_type: params.order_type,
+        market_type: params.market_type,
+        slot,
+        order_id: match options.existing_order_id {
+            Some(order_id) => order_id,
+            None => get_then_update_id!(user, next_order_id),
+        },
+        linked_order_id: 0,
+        twap_interval: params.twap_interval.unwrap_or(0),
+        user_order_id: params.user_order_id,
+        market_index: params.market_index,
+        price: standardize_price(params.price, spot_market.order_tick_size, params.direction)?,
+        existing_position_direction,
+        base_asset_amount: order_base_asset_amount,
+        base_asset_amount_filled: 0,
+        quote_asset_amount_filled: 0,
+        direction: params.direction,
+        reduce_only: params.reduce_only || force_reduce_only,
+        trigger_price: standardize_price(
+            params.trigger_price.unwrap_or(0),
+            spot_market.order_tick_size,
+            params.direction,
+        )?,
+        trigger_condition: params.trigger_condition,
+        post_only: params.post_only != PostOnlyParam::None,
+        oracle_price_offset: params.oracle_price_offset.unwrap_or(0),
+        immediate_or_cancel: params.immediate_or_cancel,
+        auction_start_price,
+        auction_end_price,
+        auction_duration,
+        max_ts,
+        trail_amount: params.trail_amount.unwrap_or(0),
+        display_quantity: params.display_quantity.unwrap_or(0),
+        trigger_funding_rate: params.trigger_funding_rate.unwrap_or(0),
+        trail_type: params.trail_type,
+        twap_slice_count: params.twap_slice_count.unwrap_or(0),
+        twap_slices_remaining: params.twap_slice_count.unwrap_or(0),
+        auction_curve: params.auction_curve,
+        auction_step_count: params.auction_step_count.unwrap_or(0),
+        auction_extended: false,
+        builder_fee_share: 0,
+        max_margin_sizing: false,
+        padding: [0; 3],
+    };
+
+    let valid_oracle_price = Some(oracle_price_data.price);
+    validate_spot_order(
+        &new_order,
+        valid_oracle_price,
+        slot,
+        spot_market.order_step_size,
+        spot_market.order_tick_size,
+        spot_market.get_margin_ratio(&MarginRequirementType::Initial)?,
+        spot_market.get_margin_ratio(&MarginRequirementType::Maintenance)?,
+        spot_market.min_order_size,
+    )?;
+
+    user.orders[new_order_index] = new_order;
+    user.spot_positions[spot_position_index].open_orders += 1;
+    if !new_order.must_be_triggered() {
+        increase_spot_open_bids_and_asks(
+            &mut user.spot_positions[spot_position_index],
+            &params.direction,
+            order_base_asset_amount,
+        )?;
+    }
+
+    let (worst_case_token_amount_after, _) = user.spot_positions[spot_position_index]
+        .get_worst_case_token_amounts(spot_market, &oracle_price_data, None, None)?;
+
+    let order_risk_decreasing =
+        is_spot_order_risk_decreasing(&user.orders[new_order_index], &balance_type, token_amount)?;
+
+    // Order fails if it's risk increasing and it brings the user collateral below the margin requirement
+    let risk_decreasing = worst_case_token_amount_after.unsigned_abs()
+        <= worst_case_token_amount_before.unsigned_abs()
+        && order_risk_decreasing;
+
+    options.update_risk_increasing(!risk_decreasing);
+
+    if options.enforce_margin_check {
+        let meets_initial_margin_requirement = meets_place_order_margin_requirement(
+            user,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            !options.risk_increasing,
+        )?;
+
+        if !meets_initial_margin_requirement {
+            return Err(ErrorCode::InvalidOrderForInitialMarginReq);
+        }
+    }
+
+    if force_reduce_only && !risk_decreasing {
+        return Err(ErrorCode::InvalidOrderNotRiskReducing);
+    }
+
+    validate_spot_margin_trading(user, spot_market_map, oracle_map)?;
+
+    let (taker, taker_order, maker, maker_order) =
+        get_taker_and_maker_for_order_record(&user_key, &new_order);
+
+    let order_action_record = get_order_action_record(
+        now,
+        OrderAction::Place,
+        OrderActionExplanation::None,
+        params.market_index,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        taker,
+        taker_order,
+        maker,
+        maker_order,
+        oracle_price_data.price,
+    )?;
+    emit!(order_action_record);
+
+    let order_record = OrderRecord {
+        ts: now,
+        user: user_key,
+        order: user.orders[new_order_index],
+    };
+    emit!(order_record);
+
+    user.update_last_active_slot(slot);
+
+    Ok(())
+}
+
+pub fn fill_spot_order(
+    order_id: u32,
+    state: &State,
+    user: &AccountLoader<User>,
+    user_stats: &AccountLoader<UserStats>,
+    spot_market_map: &SpotMarketMap,
+    perp_market_map: &PerpMarketMap,
+    oracle_map: &mut OracleMap,
+    filler: &AccountLoader<User>,
+    filler_stats: &AccountLoader<UserStats>,
+    maker: Option<&AccountLoader<User>>,
+    maker_stats: Option<&AccountLoader<UserStats>>,
+    maker_order_id: Option<u32>,
+    clock: &Clock,
+    serum_fulfillment_params: &mut Option<SerumFulfillmentParams>,
+    openbook_v2_fulfillment_params: &mut Option<OpenbookV2FulfillmentParams>,
+    phoenix_fulfillment_params: &mut Option<PhoenixFulfillmentParams>,
+    market_fee_override: Option<&MarketFeeOverride>,
+) -> DriftResult<u64> {
+    let now = clock.unix_timestamp;
+    let slot = clock.slot;
+
+    let filler_key = filler.key();
+    let user_key = user.key();
+    let user = &mut load_mut!(user)?;
+    let user_stats = &mut load_mut!(user_stats)?;
+
+    user.validate_no_fill_callback()?;
+
+    let order_index = user
+        .orders
+        .iter()
+        .position(|order| order.order_id == order_id)
+        .ok_or_else(print_error!(ErrorCode::OrderDoesNotExist))?;
+
+    let (order_status, order_market_index, order_market_type) =
+        get_struct_values!(user.orders[order_index], status, market_index, market_type);
+
+    let fee_structure = {
+        let spot_market = spot_market_map.get_ref(&order_market_index)?;
+        validate!(
+            matches!(
+                spot_market.status,
+                MarketStatus::Active
+                    | MarketStatus::FundingPaused
+                    | MarketStatus::ReduceOnly
+                    | MarketStatus::WithdrawPaused
+            ),
+            ErrorCode::MarketFillOrderPaused,
+            "Market unavailable for fills"
+        )?;
+
+        validate!(
+            !spot_market.is_operation_paused(SpotOperation::Fill)?,
+            ErrorCode::MarketFillOrderPaused,
+            "fills paused for spot market {}",
+            order_market_index
+        )?;
+
+        get_market_fee_structure(
+            &state.spot_fee_structure,
+            MarketType::Spot,
+            order_market_index,
+            spot_market.fee_override_enabled,
+            market_fee_override,
+            now,
+        )?
+    };
+
+    validate!(
+        order_market_type == MarketType::Spot,
+        ErrorCode::InvalidOrderMarketType,
+        "must be spot order"
+    )?;
+
+    validate!(
+        order_status == OrderStatus::Open,
+        ErrorCode::OrderNotOpen,
+        "Order not open"
+    )?;
+
+    validate!(
+        !user.orders[order_index].must_be_triggered() || user.orders[order_index].triggered(),
+        ErrorCode::OrderMustBeTriggeredFirst,
+        "Order must be triggered first"
+    )?;
+
+    if user.is_bankrupt() {
+        msg!("User is bankrupt");
+        return Ok(0);
+    }
+
+    match validate_user_not_being_liquidated(
+        user,
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        state.liquidation_margin_buffer_ratio,
+    ) {
+        Ok(_) => {}
+        Err(_) => {
+            msg!("User is being liquidated");
+            return Ok(0);
+        }
+    }
+
+    let is_filler_taker = user_key == filler_key;
+    let is_filler_maker = maker.map_or(false, |maker| maker.key() == filler_key);
+    let (mut filler, mut filler_stats) = if !is_filler_maker && !is_filler_taker {
+        let filler = load_mut!(filler)?;
+        if filler.authority != user.authority {
+            (Some(filler), Some(load_mut!(filler_stats)?))
+        } else {
+            (None, None)
+        }
+    } else {
+        (None, None)
+    };
+
+    let (mut maker, mut maker_stats, maker_key, maker_order_index) = get_spot_maker_order(
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        maker,
+        maker_stats,
+        maker_order_id,
+        &user_key,
+        &user.authority,
+        &user.orders[order_index],
+        &mut filler.as_deref_mut(),
+        &filler_key,
+        state.spot_fee_structure.flat_filler_fee,
+        now,
+        slot,
+    )?;
+
+    let should_expire_order = should_expire_order(user, order_index, now)?;
+
+    let should_cancel_reduce_only = if user.orders[order_index].reduce_only {
+        let market_index = user.orders[order_index].market_index;
+        let position_index = user.get_spot_position_index(market_index)?;
+        let spot_market = spot_market_map.get_ref(&market_index)?;
+        let signed_token_amount =
+            user.spot_positions[position_index].get_signed_token_amount(&spot_market)?;
+        should_cancel_reduce_only_order(&user.orders[order_index], signed_token_amount.cast()?)?
+    } else {
+        false
+    };
+
+    if should_expire_order || should_cancel_reduce_only {
+        let filler_reward = {
+            let mut quote_market = spot_market_map.get_quote_spot_market_mut()?;
+            pay_keeper_flat_reward_for_spot(
+                user,
+                filler.as_deref_mut(),
+                &mut quote_market,
+                state.spot_fee_structure.flat_filler_fee,
+            )?
+        };
+
+        let explanation = if should_expire_order {
+            OrderActionExplanation::OrderExpired
+        } else {
+            OrderActionExplanation::ReduceOnlyOrderIncreasedPosition
+        };
+
+        cancel_order(
+            order_index,
+            user,
+            &user_key,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            now,
+            slot,
+            explanation,
+            Some(&filler_key),
+            filler_reward,
+            false,
+        )?;
+        return Ok(0);
+    }
+
+    let (base_asset_amount, _updated_user_state) = fulfill_spot_order(
+        user,
+        order_index,
+        &user_key,
+        user_stats,
+        &mut maker.as_deref_mut(),
+        &mut maker_stats.as_deref_mut(),
+        maker_order_index,
+        maker_key.as_ref(),
+        &mut filler.as_deref_mut(),
+        &filler_key,
+        &mut filler_stats.as_deref_mut(),
+        spot_market_map,
+        perp_market_map,
+        oracle_map,
+        now,
+        slot,
+        &fee_structure,
+        serum_fulfillment_params,
+        openbook_v2_fulfillment_params,
+        phoenix_fulfillment_params,
+    )?;
+
+    let is_open = user.orders[order_index].status == OrderStatus::Open;
+    let is_reduce_only = user.orders[order_index].reduce_only;
+    let should_cancel_reduce_only = if is_open && is_reduce_only {
+        let market_index = user.orders[order_index].market_index;
+        let position_index = user.get_spot_position_index(market_index)?;
+        let spot_market = spot_market_map.get_ref(&market_index)?;
+        let signed_token_amount =
+            user.spot_positions[position_index].get_signed_token_amount(&spot_market)?;
+        should_cancel_reduce_only_order(&user.orders[order_index], signed_token_amount.cast()?)?
+    } else {
+        false
+    };
+
+    let should_cancel_for_no_borrow_liquidity = if is_open {
+        let market_index = user.orders[order_index].market_index;
+        let base_market = spot_market_map.get_ref(&market_index)?;
+        let quote_market = spot_market_map.get_quote_spot_market()?;
+        let (max_base_asset_amount, max_quote_asset_amount) =
+            get_max_fill_amounts(user, order_index, &base_market, &quote_market)?;
+        max_base_asset_amount == Some(0) || max_quote_asset_amount == Some(0)
+    } else {
+        false
+    };
+
+    if should_cancel_reduce_only || should_cancel_for_no_borrow_liquidity {
+        let filler_reward = {
+            let mut quote_market = spot_market_map.get_quote_spot_market_mut()?;
+            pay_keeper_flat_reward_for_spot(
+                user,
+                filler.as_deref_mut(),
+                &mut quote_market,
+                state.spot_fee_structure.flat_filler_fee,
+            )?
+        };
+
+        let explanation = if should_cancel_reduce_only {
+            OrderActionExplanation::ReduceOnlyOrderIncreasedPosition
+        } else {
+            OrderActionExplanation::NoBorrowLiquidity
+        };
+
+        cancel_order(
+            order_index,
+            user,
+            &user_key,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            now,
+            slot,
+            explanation,
+            Some(&filler_key),
+            filler_reward,
+            false,
+        )?
+    }
+
+    cancel_linked_orders(
+        user,
+        &user_key,
+        Some(&filler_key),
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+    )?;
+
+    if let (Some(maker), Some(maker_key)) = (maker.as_deref_mut(), maker_key.as_ref()) {
+        cancel_linked_orders(
+            maker,
+            maker_key,
+            Some(&filler_key),
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            now,
+            slot,
+        )?;
+    }
+
+    user.update_last_active_slot(slot);
+
+    Ok(base_asset_amount)
+}
+
+#[allow(clippy::type_complexity)]
+fn get_spot_maker_order<'a>(
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    maker: Option<&'a AccountLoader<User>>,
+    maker_stats: Option<&'a AccountLoader<UserStats>>,
+    maker_order_id: Option<u32>,
+    taker_key: &Pubkey,
+    taker_authority: &Pubkey,
+    taker_order: &Order,
+    filler: &mut Option<&mut User>,
+    filler_key: &Pubkey,
+    filler_reward: u64,
+    now: i64,
+    slot: u64,
+) -> DriftResult<(
+    Option<RefMut<'a, User>>,
+    Option<RefMut<'a, UserStats>>,
+    Option<Pubkey>,
+    Option<usize>,
+)> {
+    if maker.is_none() || maker_stats.is_none() {
+        return Ok((None, None, None, None));
+    }
+
+    let maker = maker.safe_unwrap()?;
+    if &maker.key() == taker_key {
+        return Ok((None, None, None, None));
+    }
+
+    let maker_key = maker.key();
+    let mut maker = load_mut!(maker)?;
+
+    if maker.has_fill_callback {
+        msg!(
+            "Maker {} has a fill callback, which isnt invoked for spot fills",
+            maker_key
+        );
+        return Ok((None, None, None, None));
+    }
+
+    maker.update_last_active_slot(slot);
+
+    let maker_stats = if &maker.authority == taker_authority {
+        None
+    } else {
+        let maker_stats = load_mut!(maker_stats.safe_unwrap()?)?;
+
+        validate!(
+            maker.authority.eq(&maker_stats.authority),
+            ErrorCode::InvalidMaker,
+            "maker authority != maker stats authority"
+        )?;
+
+        Some(maker_stats)
+    };
+
+    let maker_order_id = maker_order_id.ok_or(ErrorCode::MakerOrderNotFound)?;
+    let maker_order_index = match maker.get_order_index(maker_order_id) {
+        Ok(order_index) => order_index,
+        Err(_) => {
+            msg!("Maker has no order id {}", maker_order_id);
+            return Ok((None, None, None, None));
+        }
+    };
+
+    {
+        let maker_order = &maker.orders[maker_order_index];
+        if !is_maker_for_taker(maker_order, taker_order, slot)? {
+            return Ok((None, None, None, None));
+        }
+
+        if maker.is_being_liquidated() || maker.is_bankrupt() {
+            return Ok((None, None, None, None));
+        }
+
+        validate!(
+            !maker_order.must_be_triggered() || maker_order.triggered(),
+            ErrorCode::OrderMustBeTriggeredFirst,
+            "Maker order not triggered"
+        )?;
+
+        validate!(
+            maker_order.market_type == MarketType::Spot,
+            ErrorCode::InvalidOrderMarketType,
+            "Maker order not a spot order"
+        )?
+    }
+
+    let spot_market = spot_market_map.get_ref(&maker.orders[maker_order_index].market_index)?;
+    let breaches_oracle_price_limits = {
+        let oracle_price = oracle_map.get_price_data(&spot_market.oracle)?;
+        let initial_margin_ratio = spot_market.get_margin_ratio(&MarginRequirementType::Initial)?;
+        let maintenance_margin_ratio =
+            spot_market.get_margin_ratio(&MarginRequirementType::Maintenance)?;
+        order_breaches_oracle_price_bands(
+            &maker.orders[maker_order_index],
+            oracle_price.price,
+            slot,
+            spot_market.order_tick_size,
+            initial_margin_ratio,
+            maintenance_margin_ratio,
+            false,
+        )?
+    };
+
+    let should_expire_order = should_expire_order(&maker, maker_order_index, now)?;
+
+    let should_cancel_reduce_only_order = if maker.orders[maker_order_index].reduce_only {
+        let spot_position_index =
+            maker.get_spot_position_index(maker.orders[maker_order_index].market_index)?;
+        let signed_token_amount =
+            maker.spot_positions[spot_position_index].get_signed_token_amount(&spot_market)?;
+        should_cancel_reduce_only_order(
+            &maker.orders[maker_order_index],
+            signed_token_amount.cast()?,
+        )?
+    } else {
+        false
+    };
+
+    if breaches_oracle_price_limits || should_expire_order || should_cancel_reduce_only_order {
+        let filler_reward = {
+            let mut quote_market = spot_market_map.get_quote_spot_market_mut()?;
+            pay_keeper_flat_reward_for_spot(
+                &mut maker,
+                filler.as_deref_mut(),
+                &mut quote_market,
+                filler_reward,
+            )?
+        };
+
+        let explanation = if breaches_oracle_price_limits {
+            OrderActionExplanation::OraclePriceBreachedLimitPrice
+        } else if should_expire_order {
+            OrderActionExplanation::OrderExpired
+        } else {
+            OrderActionExplanation::ReduceOnlyOrderIncreasedPosition
+        };
+
+        cancel_order(
+            maker_order_index,
+            maker.deref_mut(),
+            &maker_key,
+            perp_market_map,
+            spot_market_map,
+            oracle_map,
+            now,
+            slot,
+            explanation,
+            Some(filler_key),
+            filler_reward,
+            false,
+        )?;
+
+        return Ok((None, None, None, None));
+    }
+
+    Ok((
+        Some(maker),
+        maker_stats,
+        Some(maker_key),
+        Some(maker_order_index),
+    ))
+}
+
+fn fulfill_spot_order(
+    user: &mut User,
+    user_order_index: usize,
+    user_key: &Pubkey,
+    user_stats: &mut UserStats,
+    maker: &mut Option<&mut User>,
+    maker_stats: &mut Option<&mut UserStats>,
+    maker_order_index: Option<usize>,
+    maker_key: Option<&Pubkey>,
+    filler: &mut Option<&mut User>,
+    filler_key: &Pubkey,
+    filler_stats: &mut Option<&mut UserStats>,
+    spot_market_map: &SpotMarketMap,
+    perp_market_map: &PerpMarketMap,
+    oracle_map: &mut OracleMap,
+    now: i64,
+    slot: u64,
+    fee_structure: &FeeStructure,
+    serum_fulfillment_params: &mut Option<SerumFulfillmentParams>,
+    openbook_v2_fulfillment_params: &mut Option<OpenbookV2FulfillmentParams>,
+    phoenix_fulfillment_params: &mut Option<PhoenixFulfillmentParams>,
+) -> DriftResult<(u64, bool)> {
+    let base_market_index = user.orders[user_order_index].market_index;
+
+    let fulfillment_methods = determine_spot_fulfillment_methods(
+        &user.orders[user_order_index],
+        maker.is_some(),
+        serum_fulfillment_params.is_some(),
+        openbook_v2_fulfillment_params.is_some(),
+        phoenix_fulfillment_params.is_some(),
+    )?;
+
+    let mut quote_market = spot_market_map.get_quote_spot_market_mut()?;
+    let mut base_market = spot_market_map.get_ref_mut(&base_market_index)?;
+
+    let balances_before = [
+        (base_market.deposit_balance, base_market.borrow_balance),
+        (quote_market.deposit_balance, quote_market.borrow_balance),
+    ];
+
+    let mut base_asset_amount = 0_u64;
+    for fulfillment_method in fulfillment_methods.iter() {
+        if user.orders[user_order_index].status != OrderStatus::Open {
+            break;
+        }
+
+        let _base_asset_amount = match fulfillment_method {
+            SpotFulfillmentMethod::Match => fulfill_spot_order_with_match(
+                &mut base_market,
+                &mut quote_market,
+                user,
+                user_stats,
+                user_order_index,
+                user_key,
+                maker.as_deref_mut().safe_unwrap()?,
+                maker_stats,
+                maker_order_index.safe_unwrap()?,
+                maker_key.safe_unwrap()?,
+                filler.as_deref_mut(),
+                filler_stats.as_deref_mut(),
+                filler_key,
+                now,
+                slot,
+                oracle_map,
+                fee_structure,
+            )?,
+            SpotFulfillmentMethod::SerumV3 => fulfill_spot_order_with_serum(
+                &mut base_market,
+                &mut quote_market,
+                user,
+                user_stats,
+                user_order_index,
+                user_key,
+                filler.as_deref_mut(),
+                filler_stats.as_deref_mut(),
+                filler_key,
+                now,
+                slot,
+                oracle_map,
+                fee_structure,
+                serum_fulfillment_params,
+            )?,
+            SpotFulfillmentMethod::OpenbookV2 => fulfill_spot_order_with_openbook_v2(
+                &mut base_market,
+                &mut quote_market,
+                user,
+                user_stats,
+                user_order_index,
+                user_key,
+                filler.as_deref_mut(),
+                filler_stats.as_deref_mut(),
+                filler_key,
+                now,
+                slot,
+                oracle_map,
+                fee_structure,
+                openbook_v2_fulfillment_params,
+            )?,
+            SpotFulfillmentMethod::PhoenixV1 => fulfill_spot_order_with_phoenix(
+                &mut base_market,
+                &mut quote_market,
+                user,
+                user_stats,
+                user_order_index,
+                user_key,
+                filler.as_deref_mut(),
+                filler_stats.as_deref_mut(),
+                filler_key,
+                now,
+                slot,
+                oracle_map,
+                fee_structure,
+                phoenix_fulfillment_params,
+            )?,
+        };
+
+        base_asset_amount = base_asset_amount.safe_add(_base_asset_amount)?;
+    }
+
+    // fills that pull tokens out of a market or add borrows are held to the same guards as withdraws
+    for (spot_market, &(deposit_balance_before, borrow_balance_before)) in
+        [&*base_market, &*quote_market]
+            .iter()
+            .zip(balances_before.iter())
+    {
+        validate!(
+            spot_market.deposit_balance >= deposit_balance_before
+                || check_net_withdraw_limit(spot_market)?,
+            ErrorCode::NetWithdrawLimit,
+            "Spot Market {} has hit net withdraw limit",
+            spot_market.market_index
+        )?;
+
+        validate!(
+            spot_market.borrow_balance <= borrow_balance_before
+                || check_max_token_borrows(spot_market)?,
+            ErrorCode::MaxBorrow,
+            "Spot Market {} max borrows: {}",
+            spot_market.market_index,
+            spot_market.max_token_borrows
+        )?;
+    }
+
+    let initial_margin_ratio = base_market.get_margin_ratio(&MarginRequirementType::Initial)?;
+    let maintenance_margin_ratio =
+        base_market.get_margin_ratio(&MarginRequirementType::Maintenance)?;
+    let maintenance_margin_buffer = initial_margin_ratio
+        .safe_sub(maintenance_margin_ratio)?
+        .safe_div(2)?;
+
+    drop(base_market);
+    drop(quote_market);
+
+    let (_, taker_total_collateral, taker_margin_requirement_plus_buffer, _) =
+        calculate_margin_requirement_and_total_collateral(
+            user,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            spot_market_map,
+            oracle_map,
+            Some(maintenance_margin_buffer.cast()?),
+        )?;
+
+    if taker_total_collateral < taker_margin_requirement_plus_buffer.cast()? {
+        msg!(
+            "taker breached maintenance requirements (margin requirement plus buffer {}) (total_collateral {})",
+            taker_margin_requirement_plus_buffer,
+            taker_total_collateral
+        );
+        return Err(ErrorCode::InsufficientCollateral);
+    }
+
+    if let Some(maker) = maker {
+        let (_, maker_total_collateral, maker_margin_requirement_plus_buffer, _) =
+            calculate_margin_requirement_and_total_collateral(
+                maker,
+                perp_market_map,
+                MarginRequirementType::Maintenance,
+                spot_market_map,
+                oracle_map,
+                Some(maintenance_margin_buffer.cast()?),
+            )?;
+
+        if maker_total_collateral < maker_margin_requirement_plus_buffer.cast()? {
+            msg!(
+                "maker ({}) breached maintenance requirements (margin requirement plus buffer {}) (total_collateral {})",
+                maker_key.safe_unwrap()?,
+                maker_margin_requirement_plus_buffer,
+                maker_total_collateral
+            );
+            return Err(ErrorCode::InsufficientCollateral);
+        }
+    }
+
+    Ok((base_asset_amount, base_asset_amount != 0))
+}
+
+fn determine_if_user_spot_order_is_risk_decreasing(
+    user: &User,
+    spot_market: &SpotMarket,
+    order_index: usize,
+) -> DriftResult<bool> {
+    let position_index = user.get_spot_position_index(spot_market.market_index)?;
+    let token_amount = user.spot_positions[position_index].get_token_amount(spot_market)?;
+    is_spot_order_risk_decreasing(
+        &user.orders[order_index],
+        &user.spot_positions[position_index].balance_type,
+        token_amount,
+    )
+}
+
+pub fn fulfill_spot_order_with_match(
+    base_market: &mut SpotMarket,
+    quote_market: &mut SpotMarket,
+    taker: &mut User,
+    taker_stats: &mut UserStats,
+    taker_order_index: usize,
+    taker_key: &Pubkey,
+    maker: &mut User,
+    maker_stats: &mut Option<&mut UserStats>,
+    maker_order_index: usize,
+    maker_key: &Pubkey,
+    filler: Option<&mut User>,
+    filler_stats: Option<&mut UserStats>,
+    filler_key: &Pubkey,
+    now: i64,
+    slot: u64,
+    oracle_map: &mut OracleMap,
+    fee_structure: &FeeStructure,
+) -> DriftResult<u64> {
+    if !are_orders_same_market_but_different_sides(
+        &maker.orders[maker_order_index],
+        &taker.orders[taker_order_index],
+    ) {
+        return Ok(0_u64);
+    }
+
+    let market_index = taker.orders[taker_order_index].market_index;
+    let oracle_price = oracle_map.get_price_data(&base_market.oracle)?.price;
+    let taker_price = match taker.orders[taker_order_index].get_limit_price(
+        Some(oracle_price),
+        None,
+        slot,
+        base_market.order_tick_size,
+        false,
+    )? {
+        Some(price) => price,
+        None => {
+            return Ok(0_u64);
+        }
+    };
+
+    let taker_spot_position_index = taker.get_spot_position_index(market_index)?;
+    let taker_token_amount =
+        taker.spot_positions[taker_spot_position_index].get_signed_token_amount(base_market)?;
+    let taker_base_asset_amount = taker.orders[taker_order_index]
+        .get_base_asset_amount_unfilled(Some(taker_token_amount.cast()?))?;
+    let taker_order_slot = taker.orders[taker_order_index].slot;
+    let taker_direction = taker.orders[taker_order_index].direction;
+
+    let maker_price = maker.orders[maker_order_index].force_get_limit_price(
+        Some(oracle_price),
+        None,
+        slot,
+        base_market.order_tick_size,
+        false,
+    )?;
+    let maker_direction = maker.orders[maker_order_index].direction;
+    let maker_spot_position_index = maker.get_spot_position_index(market_index)?;
+    let maker_token_amount =
+        maker.spot_positions[maker_spot_position_index].get_signed_token_amount(base_market)?;
+    let maker_base_asset_amount = maker.orders[maker_order_index]
+        .get_base_asset_amount_displayed(Some(maker_token_amount.cast()?))?;
+
+    let orders_cross = do_orders_cross(maker_direction, maker_price, taker_price);
+
+    if !orders_cross {
+        msg!(
+            "orders dont cross. maker price {} taker price {}",
+            maker_price,
+            taker_price
+        );
+        return Ok(0_u64);
+    }
+
+    let (taker_max_base_asset_amount, taker_max_quote_asset_amount) =
+        get_max_fill_amounts(taker, taker_order_index, base_market, quote_market)?;
+
+    let taker_base_asset_amount =
+        if let Some(taker_max_quote_asset_amount) = taker_max_quote_asset_amount {
+            let taker_implied_max_base_asset_amount = standardize_base_asset_amount(
+                taker_max_quote_asset_amount
+                    .cast::<u128>()?
+                    .safe_mul(base_market.get_precision().cast()?)?
+                    .safe_div(maker_price.cast()?)?
+                    .cast::<u64>()?,
+                base_market.order_step_size,
+            )?;
+            taker_base_asset_amount.min(taker_implied_max_base_asset_amount)
+        } else if let Some(taker_max_base_asset_amount) = taker_max_base_asset_amount {
+            taker_base_asset_amount.min(taker_max_base_asset_amount)
+        } else {
+            taker_base_asset_amount
+        };
+
+    let (maker_max_base_asset_amount, maker_max_quote_asset_amount) =
+        get_max_fill_amounts(maker, maker_order_index, base_market, quote_market)?;
+
+    let maker_base_asset_amount =
+        if let Some(maker_max_quote_asset_amount) = maker_max_quote_asset_amount {
+            let maker_implied_max_base_asset_amount = standardize_base_asset_amount(
+                maker_max_quote_asset_amount
+                    .cast::<u128>()?
+                    .safe_mul(base_market.get_precision().cast()?)?
+                    .safe_div(maker_price.cast()?)?
+                    .cast::<u64>()?,
+                base_market.order_step_size,
+            )?;
+            maker_base_asset_amount.min(maker_implied_max_base_asset_amount)
+        } else if let Some(maker_max_base_asset_amount) = maker_max_base_asset_amount {
+            maker_base_asset_amount.min(maker_max_base_asset_amount)
+        } else {
+            maker_base_asset_amount
+        };
+
+    let (base_asset_amount, quote_asset_amount) = calculate_fill_for_matched_orders(
+        maker_base_asset_amount,
+        maker_price,
+        taker_base_asset_amount,
+        base_market.decimals,
+        maker_direction,
+    )?;
+
+    if base_asset_amount == 0 {
+        return Ok(0_u64);
+    }
+
+    let base_precision = base_market.get_precision();
+    validate_fill_price(
+        quote_asset_amount,
+        base_asset_amount,
+        base_precision,
+        taker_direction,
+        taker_price,
+        true,
+    )?;
+    validate_fill_price(
+        quote_asset_amount,
+        base_asset_amount,
+        base_precision,
+        maker_direction,
+        maker_price,
+        false,
+    )?;
+
+    let filler_multiplier = if filler.is_some() {
+        calculate_filler_multiplier_for_matched_orders(maker_price, maker_direction, oracle_price)?
+    } else {
+        0
+    };
+
+    let FillFees {
+        user_fee: taker_fee,
+        maker_rebate,
+        filler_reward,
+        fee_to_market,
+        token_discount,
+        ..
+    } = fees::calculate_fee_for_fulfillment_with_match(
+        taker_stats,
+        maker_stats,
+        quote_asset_amount,
+        fee_structure,
+        taker_order_slot,
+        slot,
+        filler_multiplier,
+        false,
+        &None,
+        &MarketType::Spot,
+        now,
+    )?;
+
+    // Update taker state
+    update_spot_balances_and_cumulative_deposits(
+        base_asset_amount.cast()?,
+        &taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Base),
+        base_market,
+        &mut taker.spot_positions[taker_spot_position_index],
+        false,
+        None,
+    )?;
+
+    let taker_quote_asset_amount_delta = match &taker.orders[taker_order_index].direction {
+        PositionDirection::Long => quote_asset_amount.safe_add(taker_fee)?,
+        PositionDirection::Short => quote_asset_amount.safe_sub(taker_fee)?,
+    };
+
+    update_spot_balances_and_cumulative_deposits(
+        taker_quote_asset_amount_delta.cast()?,
+        &taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Quote),
+        quote_market,
+        taker.get_quote_spot_position_mut(),
+        false,
+        Some(quote_asset_amount.cast()?),
+    )?;
+
+    taker.update_cumulative_spot_fees(-taker_fee.cast()?)?;
+
+    update_order_after_fill(
+        &mut taker.orders[taker_order_index],
+        base_asset_amount,
+        quote_asset_amount,
+    )?;
+
+    let taker_order_direction = taker.orders[taker_order_index].direction;
+    decrease_spot_open_bids_and_asks(
+        &mut taker.spot_positions[taker_spot_position_index],
+        &taker_order_direction,
+        base_asset_amount,
+    )?;
+
+    taker_stats.update_taker_volume_30d(quote_asset_amount, now)?;
+
+    taker_stats.increment_total_fees(taker_fee)?;
+    taker_stats.increment_total_token_discount(token_discount)?;
+
+    // Update maker state
+    update_spot_balances_and_cumulative_deposits(
+        base_asset_amount.cast()?,
+        &maker.orders[maker_order_index].get_spot_position_update_direction(AssetType::Base),
+        base_market,
+        &mut maker.spot_positions[maker_spot_position_index],
+        false,
+        None,
+    )?;
+
+    let maker_quote_asset_amount_delta = match &maker.orders[maker_order_index].direction {
+        PositionDirection::Long => quote_asset_amount.safe_sub(maker_rebate)?,
+        PositionDirection::Short => quote_asset_amount.safe_add(maker_rebate)?,
+    };
+
+    update_spot_balances_and_cumulative_deposits(
+        maker_quote_asset_amount_delta.cast()?,
+        &maker.orders[maker_order_index].get_spot_position_update_direction(AssetType::Quote),
+        quote_market,
+        maker.get_quote_spot_position_mut(),
+        false,
+        Some(quote_asset_amount.cast()?),
+    )?;
+
+    maker.update_cumulative_spot_fees(maker_rebate.cast()?)?;
+
+    update_order_after_fill(
+        &mut maker.orders[maker_order_index],
+        base_asset_amount,
+        quote_asset_amount,
+    )?;
+
+    let maker_order_direction = maker.orders[maker_order_index].direction;
+    decrease_spot_open_bids_and_asks(
+        &mut maker.spot_positions[maker_spot_position_index],
+        &maker_order_direction,
+        base_asset_amount,
+    )?;
+
+    if let Some(maker_stats) = maker_stats {
+        maker_stats.update_maker_volume_30d(quote_asset_amount, now)?;
+        maker_stats.increment_total_rebate(maker_rebate)?;
+    } else {
+        taker_stats.update_maker_volume_30d(quote_asset_amount, now)?;
+        taker_stats.increment_total_rebate(maker_rebate)?;
+    }
+
+    // Update filler state
+    if let (Some(filler), Some(filler_stats)) = (filler, filler_stats) {
+        if filler_reward > 0 {
+            update_spot_balances(
+                filler_reward.cast()?,
+                &SpotBalanceType::Deposit,
+                quote_market,
+                filler.get_quote_spot_position_mut(),
+                false,
+            )?;
+
+            filler.update_cumulative_spot_fees(filler_reward.cast()?)?;
+        }
+
+        filler_stats.update_filler_volume(quote_asset_amount, now)?;
+    }
+
+    // Update base market
+    base_market.total_spot_fee = base_market.total_spot_fee.safe_add(fee_to_market.cast()?)?;
+
+    update_spot_balances(
+        fee_to_market.cast()?,
+        &SpotBalanceType::Deposit,
+        quote_market,
+        &mut base_market.spot_fee_pool,
+        false,
+    )?;
+
+    let fee_pool_amount = get_token_amount(
+        base_market.spot_fee_pool.scaled_balance,
+        quote_market,
+        &SpotBalanceType::Deposit,
+    )?;
+
+    if fee_pool_amount > FEE_POOL_TO_REVENUE_POOL_THRESHOLD * 2 {
+        transfer_spot_balance_to_revenue_pool(
+            fee_pool_amount - FEE_POOL_TO_REVENUE_POOL_THRESHOLD,
+            quote_market,
+            &mut base_market.spot_fee_pool,
+        )?;
+    }
+
+    let fill_record_id = get_then_update_id!(base_market, next_fill_record_id);
+    let order_action_explanation = if maker.orders[maker_order_index].is_jit_maker() {
+        OrderActionExplanation::OrderFilledWithMatchJit
+    } else {
+        OrderActionExplanation::OrderFilledWithMatch
+    };
+    let order_action_record = get_order_action_record(
+        now,
+        OrderAction::Fill,
+        order_action_explanation,
+        maker.orders[maker_order_index].market_index,
+        Some(*filler_key),
+        Some(fill_record_id),
+        Some(filler_reward),
+        Some(base_asset_amount),
+        Some(quote_asset_amount.cast()?),
+        Some(taker_fee),
+        Some(maker_rebate),
+        None,
+        Some(0),
+        Some(0),
+        Some(*taker_key),
+        Some(taker.orders[taker_order_index]),
+        Some(*maker_key),
+        Some(maker.orders[maker_order_index]),
+        oracle_map.get_price_data(&base_market.oracle)?.price,
+    )?;
+    emit_stack::<_, { OrderActionRecord::SIZE }>(order_action_record)?;
+
+    // Clear taker/maker order if completely filled
+    if taker.orders[taker_order_index].get_base_asset_amount_unfilled(None)? == 0 {
+        taker.orders[taker_order_index] = Order::default();
+        taker.spot_positions[taker_spot_position_index].open_orders -= 1;
+    }
+
+    if maker.orders[maker_order_index].get_base_asset_amount_unfilled(None)? == 0 {
+        maker.orders[maker_order_index] = Order::default();
+        maker.spot_positions[maker_spot_position_index].open_orders -= 1;
+    } else {
+        replenish_iceberg_order(maker_key, &mut maker.orders[maker_order_index], now, slot)?;
+    }
+
+    Ok(base_asset_amount)
+}
+
+fn replenish_iceberg_order(
+    user_key: &Pubkey,
+    order: &mut Order,
+    now: i64,
+    slot: u64,
+) -> DriftResult {
+    if order.replenish_iceberg_slice(slot)? {
+        emit!(IcebergReplenishRecord {
+            ts: now,
+            user: *user_key,
+            order_id: order.order_id,
+            market_index: order.market_index,
+            market_type: order.market_type,
+            display_quantity: order.get_base_asset_amount_displayed(None)?,
+            base_asset_amount_filled: order.base_asset_amount_filled,
+            slot,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn fulfill_spot_order_with_serum(
+    base_market: &mut SpotMarket,
+    quote_market: &mut SpotMarket,
+    taker: &mut User,
+    taker_stats: &mut UserStats,
+    taker_order_index: usize,
+    taker_key: &Pubkey,
+    filler: Option<&mut User>,
+    filler_stats: Option<&mut UserStats>,
+    filler_key: &Pubkey,
+    now: i64,
+    slot: u64,
+    oracle_map: &mut OracleMap,
+    fee_structure: &FeeStructure,
+    serum_fulfillment_params: &mut Option<SerumFulfillmentParams>,
+) -> DriftResult<u64> {
+    let serum_new_order_accounts = match serum_fulfillment_params {
+        Some(serum_new_order_accounts) => serum_new_order_accounts,
+        None => return Ok(0),
+    };
+
+    let oracle_price = oracle_map.get_price_data(&base_market.oracle)?.price;
+    let taker_price = taker.orders[taker_order_index].get_limit_price(
+        Some(oracle_price),
+        None,
+        slot,
+        base_market.order_tick_size,
+        false,
+    )?;
+    let taker_token_amount = taker
+        .force_get_spot_position_mut(base_market.market_index)?
+        .get_signed_token_amount(base_market)?;
+    let taker_base_asset_amount = taker.orders[taker_order_index]
+        .get_base_asset_amount_unfilled(Some(taker_token_amount.cast()?))?;
+    let order_direction = taker.orders[taker_order_index].direction;
+    let taker_order_slot = taker.orders[taker_order_index].slot;
+
+    let (max_base_asset_amount, max_quote_asset_amount) =
+        get_max_fill_amounts(taker, taker_order_index, base_market, quote_market)?;
+
+    let taker_base_asset_amount =
+        taker_base_asset_amount.min(max_base_asset_amount.unwrap_or(u64::MAX));
+
+    let (best_bid, best_ask) = get_best_bid_and_ask(
+        serum_new_order_accounts.serum_market,
+        serum_new_order_accounts.serum_bids,
+        serum_new_order_accounts.serum_asks,
+        serum_new_order_accounts.serum_program_id.key,
+        base_market.decimals,
+    )?;
+
+    let mut mid_price = 0;
+    if let Some(best_bid) = best_bid {
+        base_market.historical_index_data.last_index_bid_price = best_bid;
+        mid_price += best_bid;
+    }
+
+    if let Some(best_ask) = best_ask {
+        base_market.historical_index_data.last_index_ask_price = best_ask;
+        mid_price = if mid_price == 0 {
+            best_ask
+        } else {
+            mid_price.safe_add(best_ask)?.safe_div(2)?
+        };
+    }
+
+    base_market.historical_index_data.last_index_price_twap = calculate_new_twap(
+        mid_price.cast()?,
+        now,
+        base_market
+            .historical_index_data
+            .last_index_price_twap
+            .cast()?,
+        base_market.historical_index_data.last_index_price_twap_ts,
+        ONE_HOUR as i64,
+    )?
+    .cast()?;
+
+    base_market.historical_index_data.last_index_price_twap_5min = calculate_new_twap(
+        mid_price.cast()?,
+        now,
+        base_market
+            .historical_index_data
+            .last_index_price_twap_5min
+            .cast()?,
+        base_market.historical_index_data.last_index_price_twap_ts,
+        FIVE_MINUTE as i64,
+    )?
+    .cast()?;
+
+    let taker_price = if let Some(price) = taker_price {
+        price
+    } else {
+        match order_direction {
+            PositionDirection::Long => {
+                if let Some(ask) = best_ask {
+                    ask.safe_add(ask / 100)?
+                } else {
+                    msg!("Serum has no ask");
+                    return Ok(0);
+                }
+            }
+            PositionDirection::Short => {
+                if let Some(bid) = best_bid {
+                    bid.safe_sub(bid / 100)?
+                } else {
+                    msg!("Serum has no bid");
+                    return Ok(0);
+                }
+            }
+        }
+    };
+
+    let market_state_before = load_serum_market(
+        serum_new_order_accounts.serum_market,
+        serum_new_order_accounts.serum_program_id.key,
+    )?;
+
+    let serum_order_side = match order_direction {
+        PositionDirection::Long => Side::Bid,
+        PositionDirection::Short => Side::Ask,
+    };
+
+    let serum_max_coin_qty =
+        calculate_serum_max_coin_qty(taker_base_asset_amount, market_state_before.coin_lot_size)?;
+
+    let serum_limit_price = calculate_serum_limit_price(
+        taker_price,
+        market_state_before.pc_lot_size,
+        base_market.decimals,
+        market_state_before.coin_lot_size,
+        order_direction,
+    )?;
+
+    let serum_max_native_pc_qty = calculate_serum_max_native_pc_quantity(
+        serum_limit_price,
+        serum_max_coin_qty,
+        market_state_before.pc_lot_size,
+    )?
+    .min(max_quote_asset_amount.unwrap_or(u64::MAX));
+
+    if serum_max_coin_qty == 0 || serum_max_native_pc_qty == 0 {
+        return Ok(0);
+    }
+
+    let serum_order = NewOrderInstructionV3 {
+        side: serum_order_side,
+        limit_price: NonZeroU64::new(serum_limit_price).safe_unwrap()?,
+        max_coin_qty: NonZeroU64::new(serum_max_coin_qty).safe_unwrap()?, // max base to deposit into serum
+        max_native_pc_qty_including_fees: NonZeroU64::new(serum_max_native_pc_qty).safe_unwrap()?, // max quote to deposit into serum
+        self_trade_behavior: SelfTradeBehavior::AbortTransaction,
+        order_type: serum_dex::matching::OrderType::ImmediateOrCancel,
+        client_order_id: 0,
+        limit: 10,
+        max_ts: now,
+    };
+
+    let _market_fees_accrued_before = market_state_before.pc_fees_accrued;
+    let base_before = serum_new_order_accounts.base_market_vault.amount;
+    let quote_before = serum_new_order_accounts.quote_market_vault.amount;
+    let market_rebates_accrued_before = market_state_before.referrer_rebates_accrued;
+
+    drop(market_state_before);
+
+    invoke_new_order(
+        serum_new_order_accounts.serum_program_id,
+        serum_new_order_accounts.serum_market,
+        serum_new_order_accounts.serum_open_orders,
+        serum_new_order_accounts.serum_request_queue,
+        serum_new_order_accounts.serum_event_queue,
+        serum_new_order_accounts.serum_bids,
+        serum_new_order_accounts.serum_asks,
+        &match order_direction {
+            PositionDirection::Long => serum_new_order_accounts
+                .quote_market_vault
+                .to_account_info(),
+            PositionDirection::Short => {
+                serum_new_order_accounts.base_market_vault.to_account_info()
+            }
+        },
+        serum_new_order_accounts.drift_signer,
+        serum_new_order_accounts.serum_base_vault,
+        serum_new_order_accounts.serum_quote_vault,
+        serum_new_order_accounts.srm_vault,
+        &serum_new_order_accounts.token_program.to_account_info(),
+        serum_order,
+        serum_new_order_accounts.signer_nonce,
+    )?;
+
+    let market_state_after = load_serum_market(
+        serum_new_order_accounts.serum_market,
+        serum_new_order_accounts.serum_program_id.key,
+    )?;
+
+    let _market_fees_accrued_after = market_state_after.pc_fees_accrued;
+    let market_rebates_accrued_after = market_state_after.referrer_rebates_accrued;
+
+    drop(market_state_after);
+
+    let open_orders_before = load_open_orders(serum_new_order_accounts.serum_open_orders)?;
+    let unsettled_referrer_rebate_before = open_orders_before.referrer_rebates_accrued;
+
+    drop(open_orders_before);
+
+    invoke_settle_funds(
+        serum_new_order_accounts.serum_program_id,
+        serum_new_order_accounts.serum_market,
+        serum_new_order_accounts.serum_open_orders,
+        serum_new_order_accounts.drift_signer,
+        serum_new_order_accounts.serum_base_vault,
+        serum_new_order_accounts.serum_quote_vault,
+        &serum_new_order_accounts.base_market_vault.to_account_info(),
+        &serum_new_order_accounts
+            .quote_market_vault
+            .to_account_info(),
+        serum_new_order_accounts.serum_signer,
+        &serum_new_order_accounts.token_program.to_account_info(),
+        serum_new_order_accounts.signer_nonce,
+    )?;
+
+    serum_new_order_accounts
+        .base_market_vault
+        .reload()
+        .map_err(|_e| {
+            msg!("Failed to reload base_market_vault");
+            ErrorCode::FailedSerumCPI
+        })?;
+    serum_new_order_accounts
+        .quote_market_vault
+        .reload()
+        .map_err(|_e| {
+            msg!("Failed to reload quote_market_vault");
+            ErrorCode::FailedSerumCPI
+        })?;
+
+    let base_after = serum_new_order_accounts.base_market_vault.amount;
+    let quote_after = serum_new_order_accounts.quote_market_vault.amount;
+
+    let open_orders_after = load_open_orders(serum_new_order_accounts.serum_open_orders)?;
+    let unsettled_referrer_rebate_after = open_orders_after.referrer_rebates_accrued;
+
+    drop(open_orders_after);
+
+    let settled_referred_rebate =
+        unsettled_referrer_rebate_before.safe_sub(unsettled_referrer_rebate_after)?;
+
+    update_spot_balances(
+        settled_referred_rebate as u128,
+        &SpotBalanceType::Deposit,
+        quote_market,
+        &mut base_market.spot_fee_pool,
+        false,
+    )?;
+
+    let (base_update_direction, base_asset_amount_filled) = if base_after > base_before {
+        (SpotBalanceType::Deposit, base_after.safe_sub(base_before)?)
+    } else {
+        (SpotBalanceType::Borrow, base_before.safe_sub(base_after)?)
+    };
+
+    if base_asset_amount_filled == 0 {
+        msg!("No base filled on serum");
+        return Ok(0);
+    }
+
+    let serum_referrer_rebate =
+        market_rebates_accrued_after.safe_sub(market_rebates_accrued_before)?;
+
+    // rebate is half of taker fee
+    let serum_fee = serum_referrer_rebate;
+
+    let (quote_update_direction, quote_asset_amount_filled) =
+        if base_update_direction == SpotBalanceType::Borrow {
+            let quote_asset_amount_delta = quote_after
+                .safe_sub(quote_before)?
+                .safe_sub(settled_referred_rebate)?;
+
+            (
+                SpotBalanceType::Deposit,
+                quote_asset_amount_delta
+                    .safe_add(serum_fee)?
+                    .safe_add(serum_referrer_rebate)?,
+            )
+        } else {
+            let quote_asset_amount_delta = quote_before
+                .safe_add(settled_referred_rebate)?
+                .safe_sub(quote_after)?;
+
+            (
+                SpotBalanceType::Borrow,
+                quote_asset_amount_delta
+                    .safe_sub(serum_fee)?
+                    .safe_sub(serum_referrer_rebate)?,
+            )
+        };
+
+    validate_fill_price(
+        quote_asset_amount_filled,
+        base_asset_amount_filled,
+        base_market.get_precision(),
+        order_direction,
+        taker_price,
+        true,
+    )?;
+
+    let fee_pool_amount = get_token_amount(
+        base_market.spot_fee_pool.scaled_balance,
+        quote_market,
+        &SpotBalanceType::Deposit,
+    )?;
+
+    if fee_pool_amount > FEE_POOL_TO_REVENUE_POOL_THRESHOLD * 2 {
+        transfer_spot_balance_to_revenue_pool(
+            fee_pool_amount - FEE_POOL_TO_REVENUE_POOL_THRESHOLD,
+            quote_market,
+            &mut base_market.spot_fee_pool,
+        )?;
+    }
+
+    let SerumFillFees {
+        user_fee: taker_fee,
+        fee_to_market,
+        fee_pool_delta,
+        filler_reward,
+    } = fees::calculate_fee_for_fulfillment_with_serum(
+        taker_stats,
+        quote_asset_amount_filled,
+        fee_structure,
+        taker_order_slot,
+        slot,
+        filler.is_some(),
+        serum_fee,
+        serum_referrer_rebate,
+        fee_pool_amount.cast()?,
+    )?;
+
+    let quote_spot_position_delta = match quote_update_direction {
+        SpotBalanceType::Deposit => quote_asset_amount_filled.safe_sub(taker_fee)?,
+        SpotBalanceType::Borrow => quote_asset_amount_filled.safe_add(taker_fee)?,
+    };
+
+    validate!(
+        base_update_direction
+            == taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Base),
+        ErrorCode::FailedToFillOnSerum,
+        "Fill on serum lead to unexpected to update direction"
+    )?;
+
+    update_spot_balances_and_cumulative_deposits(
+        base_asset_amount_filled.cast()?,
+        &taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Base),
+        base_market,
+        taker.force_get_spot_position_mut(base_market.market_index)?,
+        false,
+        None,
+    )?;
 
-        base_asset_amount = base_asset_amount.safe_add(_base_asset_amount)?;
-    }
+    validate!(
+        quote_update_direction
+            == taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Quote),
+        ErrorCode::FailedToFillOnSerum,
+        "Fill on serum lead to unexpected to update direction"
+    )?;
 
-    let initial_margin_ratio = base_market.get_margin_ratio(&MarginRequirementType::Initial)?;
-    let maintenance_margin_ratio =
-        base_market.get_margin_ratio(&MarginRequirementType::Maintenance)?;
-    let maintenance_margin_buffer = initial_margin_ratio
-        .safe_sub(maintenance_margin_ratio)?
-        .safe_div(2)?;
+    update_spot_balances_and_cumulative_deposits(
+        quote_spot_position_delta.cast()?,
+        &taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Quote),
+        quote_market,
+        taker.get_quote_spot_position_mut(),
+        false,
+        Some(quote_asset_amount_filled.cast()?),
+    )?;
 
-    drop(base_market);
-    drop(quote_market);
+    taker.update_cumulative_spot_fees(-taker_fee.cast()?)?;
 
-    let (_, taker_total_collateral, taker_margin_requirement_plus_buffer, _) =
-        calculate_margin_requirement_and_total_collateral(
-            user,
-            perp_market_map,
-            MarginRequirementType::Maintenance,
-            spot_market_map,
-            oracle_map,
-            Some(maintenance_margin_buffer.cast()?),
-        )?;
+    taker_stats.update_taker_volume_30d(quote_asset_amount_filled.cast()?, now)?;
 
-    if taker_total_collateral < taker_margin_requirement_plus_buffer.cast()? {
-        msg!(
-            "taker breached maintenance requirements (margin requirement plus buffer {}) (total_collateral {})",
-            taker_margin_requirement_plus_buffer,
-            taker_total_collateral
-        );
-        return Err(ErrorCode::InsufficientCollateral);
-    }
+    taker_stats.increment_total_fees(taker_fee.cast()?)?;
 
-    if let Some(maker) = maker {
-        let (_, maker_total_collateral, maker_margin_requirement_plus_buffer, _) =
-            calculate_margin_requirement_and_total_collateral(
-                maker,
-                perp_market_map,
-                MarginRequirementType::Maintenance,
-                spot_market_map,
-                oracle_map,
-                Some(maintenance_margin_buffer.cast()?),
+    update_order_after_fill(
+        &mut taker.orders[taker_order_index],
+        base_asset_amount_filled,
+        quote_asset_amount_filled,
+    )?;
+
+    let taker_order_direction = taker.orders[taker_order_index].direction;
+    decrease_spot_open_bids_and_asks(
+        taker.force_get_spot_position_mut(base_market.market_index)?,
+        &taker_order_direction,
+        base_asset_amount_filled,
+    )?;
+
+    if let (Some(filler), Some(filler_stats)) = (filler, filler_stats) {
+        if filler_reward > 0 {
+            update_spot_balances(
+                filler_reward.cast()?,
+                &SpotBalanceType::Deposit,
+                quote_market,
+                filler.get_quote_spot_position_mut(),
+                false,
             )?;
 
-        if maker_total_collateral < maker_margin_requirement_plus_buffer.cast()? {
-            msg!(
-                "maker ({}) breached maintenance requirements (margin requirement plus buffer {}) (total_collateral {})",
-                maker_key.safe_unwrap()?,
-                maker_margin_requirement_plus_buffer,
-                maker_total_collateral
-            );
-            return Err(ErrorCode::InsufficientCollateral);
+            filler.update_cumulative_spot_fees(filler_reward.cast()?)?;
         }
+
+        filler_stats.update_filler_volume(quote_asset_amount_filled.cast()?, now)?;
     }
 
-    Ok((base_asset_amount, base_asset_amount != 0))
-}
+    if fee_pool_delta != 0 {
+        update_spot_balances(
+            fee_pool_delta.unsigned_abs().cast()?,
+            if fee_pool_delta > 0 {
+                &SpotBalanceType::Deposit
+            } else {
+                &SpotBalanceType::Borrow
+            },
+            quote_market,
+            &mut base_market.spot_fee_pool,
+            false,
+        )?;
+    }
 
-fn determine_if_user_spot_order_is_risk_decreasing(
-    user: &User,
-    spot_market: &SpotMarket,
-    order_index: usize,
-) -> DriftResult<bool> {
-    let position_index = user.get_spot_position_index(spot_market.market_index)?;
-    let token_amount = user.spot_positions[position_index].get_token_amount(spot_market)?;
-    is_spot_order_risk_decreasing(
-        &user.orders[order_index],
-        &user.spot_positions[position_index].balance_type,
-        token_amount,
-    )
+    base_market.total_spot_fee = base_market.total_spot_fee.safe_add(fee_to_market.cast()?)?;
+
+    let fill_record_id = get_then_update_id!(base_market, next_fill_record_id);
+    let order_action_record = get_order_action_record(
+        now,
+        OrderAction::Fill,
+        OrderActionExplanation::OrderFillWithSerum,
+        taker.orders[taker_order_index].market_index,
+        Some(*filler_key),
+        Some(fill_record_id),
+        Some(filler_reward),
+        Some(base_asset_amount_filled),
+        Some(quote_asset_amount_filled.cast()?),
+        Some(taker_fee),
+        Some(0),
+        None,
+        Some(0),
+        Some(serum_fee),
+        Some(*taker_key),
+        Some(taker.orders[taker_order_index]),
+        None,
+        None,
+        oracle_price,
+    )?;
+    emit_stack::<_, { OrderActionRecord::SIZE }>(order_action_record)?;
+
+    if taker.orders[taker_order_index].get_base_asset_amount_unfilled(None)? == 0 {
+        taker.orders[taker_order_index] = Order::default();
+        taker
+            .force_get_spot_position_mut(base_market.market_index)?
+            .open_orders -= 1;
+    }
+
+    Ok(base_asset_amount_filled)
 }
 
-pub fn fulfill_spot_order_with_match(
+pub fn fulfill_spot_order_with_openbook_v2(
     base_market: &mut SpotMarket,
     quote_market: &mut SpotMarket,
     taker: &mut User,
     taker_stats: &mut UserStats,
     taker_order_index: usize,
     taker_key: &Pubkey,
-    maker: &mut User,
-    maker_stats: &mut Option<&mut UserStats>,
-    maker_order_index: usize,
-    maker_key: &Pubkey,
     filler: Option<&mut User>,
     filler_stats: Option<&mut UserStats>,
     filler_key: &Pubkey,
@@ -3440,246 +6454,271 @@ pub fn fulfill_spot_order_with_match(
     slot: u64,
     oracle_map: &mut OracleMap,
     fee_structure: &FeeStructure,
+    openbook_v2_fulfillment_params: &mut Option<OpenbookV2FulfillmentParams>,
 ) -> DriftResult<u64> {
-    if !are_orders_same_market_but_different_sides(
-        &maker.orders[maker_order_index],
-        &taker.orders[taker_order_index],
-    ) {
-        return Ok(0_u64);
-    }
+    let openbook_v2_accounts = match openbook_v2_fulfillment_params {
+        Some(openbook_v2_accounts) => openbook_v2_accounts,
+        None => return Ok(0),
+    };
 
-    let market_index = taker.orders[taker_order_index].market_index;
     let oracle_price = oracle_map.get_price_data(&base_market.oracle)?.price;
-    let taker_price = match taker.orders[taker_order_index].get_limit_price(
+    let taker_price = taker.orders[taker_order_index].get_limit_price(
         Some(oracle_price),
         None,
         slot,
         base_market.order_tick_size,
-    )? {
-        Some(price) => price,
-        None => {
-            return Ok(0_u64);
-        }
-    };
-
-    let taker_spot_position_index = taker.get_spot_position_index(market_index)?;
-    let taker_token_amount =
-        taker.spot_positions[taker_spot_position_index].get_signed_token_amount(base_market)?;
+        false,
+    )?;
+    let taker_token_amount = taker
+        .force_get_spot_position_mut(base_market.market_index)?
+        .get_signed_token_amount(base_market)?;
     let taker_base_asset_amount = taker.orders[taker_order_index]
         .get_base_asset_amount_unfilled(Some(taker_token_amount.cast()?))?;
+    let order_direction = taker.orders[taker_order_index].direction;
     let taker_order_slot = taker.orders[taker_order_index].slot;
-    let taker_direction = taker.orders[taker_order_index].direction;
-
-    let maker_price = maker.orders[maker_order_index].force_get_limit_price(
-        Some(oracle_price),
-        None,
-        slot,
-        base_market.order_tick_size,
-    )?;
-    let maker_direction = maker.orders[maker_order_index].direction;
-    let maker_spot_position_index = maker.get_spot_position_index(market_index)?;
-    let maker_token_amount =
-        maker.spot_positions[maker_spot_position_index].get_signed_token_amount(base_market)?;
-    let maker_base_asset_amount = maker.orders[maker_order_index]
-        .get_base_asset_amount_unfilled(Some(maker_token_amount.cast()?))?;
-
-    let orders_cross = do_orders_cross(maker_direction, maker_price, taker_price);
-
-    if !orders_cross {
-        msg!(
-            "orders dont cross. maker price {} taker price {}",
-            maker_price,
-            taker_price
-        );
-        return Ok(0_u64);
-    }
 
-    let (taker_max_base_asset_amount, taker_max_quote_asset_amount) =
+    let (max_base_asset_amount, max_quote_asset_amount) =
         get_max_fill_amounts(taker, taker_order_index, base_market, quote_market)?;
 
     let taker_base_asset_amount =
-        if let Some(taker_max_quote_asset_amount) = taker_max_quote_asset_amount {
-            let taker_implied_max_base_asset_amount = standardize_base_asset_amount(
-                taker_max_quote_asset_amount
-                    .cast::<u128>()?
-                    .safe_mul(base_market.get_precision().cast()?)?
-                    .safe_div(maker_price.cast()?)?
-                    .cast::<u64>()?,
-                base_market.order_step_size,
-            )?;
-            taker_base_asset_amount.min(taker_implied_max_base_asset_amount)
-        } else if let Some(taker_max_base_asset_amount) = taker_max_base_asset_amount {
-            taker_base_asset_amount.min(taker_max_base_asset_amount)
-        } else {
-            taker_base_asset_amount
-        };
+        taker_base_asset_amount.min(max_base_asset_amount.unwrap_or(u64::MAX));
 
-    let (maker_max_base_asset_amount, maker_max_quote_asset_amount) =
-        get_max_fill_amounts(maker, maker_order_index, base_market, quote_market)?;
+    // the openbook v2 book sides aren't read, so orders without a limit price are bounded 1% through the oracle
+    let taker_price = match taker_price {
+        Some(price) => price,
+        None => {
+            let oracle_price = oracle_price.unsigned_abs();
+            match order_direction {
+                PositionDirection::Long => oracle_price.safe_add(oracle_price / 100)?,
+                PositionDirection::Short => oracle_price.safe_sub(oracle_price / 100)?,
+            }
+        }
+    };
 
-    let maker_base_asset_amount =
-        if let Some(maker_max_quote_asset_amount) = maker_max_quote_asset_amount {
-            let maker_implied_max_base_asset_amount = standardize_base_asset_amount(
-                maker_max_quote_asset_amount
-                    .cast::<u128>()?
-                    .safe_mul(base_market.get_precision().cast()?)?
-                    .safe_div(maker_price.cast()?)?
-                    .cast::<u64>()?,
-                base_market.order_step_size,
-            )?;
-            maker_base_asset_amount.min(maker_implied_max_base_asset_amount)
-        } else if let Some(maker_max_base_asset_amount) = maker_max_base_asset_amount {
-            maker_base_asset_amount.min(maker_max_base_asset_amount)
-        } else {
-            maker_base_asset_amount
-        };
+    let market_state_before = load_openbook_v2_market(
+        openbook_v2_accounts.openbook_v2_market,
+        openbook_v2_accounts.openbook_v2_program_id.key,
+    )?;
 
-    let (base_asset_amount, quote_asset_amount) = calculate_fill_for_matched_orders(
-        maker_base_asset_amount,
-        maker_price,
-        taker_base_asset_amount,
+    let openbook_v2_order_side = match order_direction {
+        PositionDirection::Long => OpenbookV2Side::Bid,
+        PositionDirection::Short => OpenbookV2Side::Ask,
+    };
+
+    // openbook v2 lots are defined the same as serum lots, so the serum lot math carries over
+    let base_lot_size = market_state_before.base_lot_size.cast::<u64>()?;
+    let quote_lot_size = market_state_before.quote_lot_size.cast::<u64>()?;
+
+    let openbook_v2_max_base_lots =
+        calculate_serum_max_coin_qty(taker_base_asset_amount, base_lot_size)?;
+
+    let openbook_v2_price_lots = calculate_serum_limit_price(
+        taker_price,
+        quote_lot_size,
         base_market.decimals,
-        maker_direction,
+        base_lot_size,
+        order_direction,
     )?;
 
-    if base_asset_amount == 0 {
-        return Ok(0_u64);
+    let openbook_v2_max_quote_lots = calculate_serum_max_native_pc_quantity(
+        openbook_v2_price_lots,
+        openbook_v2_max_base_lots,
+        quote_lot_size,
+    )?
+    .min(max_quote_asset_amount.unwrap_or(u64::MAX))
+    .safe_div(quote_lot_size)?;
+
+    if openbook_v2_max_base_lots == 0
+        || openbook_v2_price_lots == 0
+        || openbook_v2_max_quote_lots == 0
+    {
+        return Ok(0);
     }
 
-    let base_precision = base_market.get_precision();
+    let openbook_v2_order = OpenbookV2PlaceTakeOrderArgs {
+        side: openbook_v2_order_side,
+        price_lots: openbook_v2_price_lots.cast()?,
+        max_base_lots: openbook_v2_max_base_lots.cast()?,
+        max_quote_lots_including_fees: openbook_v2_max_quote_lots.cast()?,
+        order_type: OpenbookV2PlaceOrderType::ImmediateOrCancel,
+        limit: 10,
+    };
+
+    let market_fees_accrued_before = market_state_before.fees_accrued();
+    let base_before = openbook_v2_accounts.base_market_vault.amount;
+    let quote_before = openbook_v2_accounts.quote_market_vault.amount;
+
+    invoke_place_take_order(
+        openbook_v2_accounts.openbook_v2_program_id,
+        openbook_v2_accounts.drift_signer,
+        openbook_v2_accounts.openbook_v2_market,
+        openbook_v2_accounts.openbook_v2_market_authority,
+        openbook_v2_accounts.openbook_v2_bids,
+        openbook_v2_accounts.openbook_v2_asks,
+        openbook_v2_accounts.openbook_v2_base_vault,
+        openbook_v2_accounts.openbook_v2_quote_vault,
+        openbook_v2_accounts.openbook_v2_event_heap,
+        &openbook_v2_accounts.base_market_vault.to_account_info(),
+        &openbook_v2_accounts.quote_market_vault.to_account_info(),
+        &openbook_v2_accounts.token_program.to_account_info(),
+        openbook_v2_accounts.system_program,
+        openbook_v2_order,
+        openbook_v2_accounts.signer_nonce,
+    )?;
+
+    let market_state_after = load_openbook_v2_market(
+        openbook_v2_accounts.openbook_v2_market,
+        openbook_v2_accounts.openbook_v2_program_id.key,
+    )?;
+
+    let openbook_v2_fee = market_state_after
+        .fees_accrued()
+        .safe_sub(market_fees_accrued_before)?
+        .cast::<u64>()?;
+
+    openbook_v2_accounts
+        .base_market_vault
+        .reload()
+        .map_err(|_e| {
+            msg!("Failed to reload base_market_vault");
+            ErrorCode::FailedOpenbookV2CPI
+        })?;
+    openbook_v2_accounts
+        .quote_market_vault
+        .reload()
+        .map_err(|_e| {
+            msg!("Failed to reload quote_market_vault");
+            ErrorCode::FailedOpenbookV2CPI
+        })?;
+
+    let base_after = openbook_v2_accounts.base_market_vault.amount;
+    let quote_after = openbook_v2_accounts.quote_market_vault.amount;
+
+    let (base_update_direction, base_asset_amount_filled) = if base_after > base_before {
+        (SpotBalanceType::Deposit, base_after.safe_sub(base_before)?)
+    } else {
+        (SpotBalanceType::Borrow, base_before.safe_sub(base_after)?)
+    };
+
+    if base_asset_amount_filled == 0 {
+        msg!("No base filled on openbook v2");
+        return Ok(0);
+    }
+
+    // take orders settle straight to the vaults, so the quote delta is net of the openbook v2 fee
+    let (quote_update_direction, quote_asset_amount_filled) =
+        if base_update_direction == SpotBalanceType::Borrow {
+            (
+                SpotBalanceType::Deposit,
+                quote_after
+                    .safe_sub(quote_before)?
+                    .safe_add(openbook_v2_fee)?,
+            )
+        } else {
+            (
+                SpotBalanceType::Borrow,
+                quote_before
+                    .safe_sub(quote_after)?
+                    .safe_sub(openbook_v2_fee)?,
+            )
+        };
+
     validate_fill_price(
-        quote_asset_amount,
-        base_asset_amount,
-        base_precision,
-        taker_direction,
+        quote_asset_amount_filled,
+        base_asset_amount_filled,
+        base_market.get_precision(),
+        order_direction,
         taker_price,
         true,
     )?;
-    validate_fill_price(
-        quote_asset_amount,
-        base_asset_amount,
-        base_precision,
-        maker_direction,
-        maker_price,
-        false,
+
+    let fee_pool_amount = get_token_amount(
+        base_market.spot_fee_pool.scaled_balance,
+        quote_market,
+        &SpotBalanceType::Deposit,
     )?;
 
-    let filler_multiplier = if filler.is_some() {
-        calculate_filler_multiplier_for_matched_orders(maker_price, maker_direction, oracle_price)?
-    } else {
-        0
-    };
+    if fee_pool_amount > FEE_POOL_TO_REVENUE_POOL_THRESHOLD * 2 {
+        transfer_spot_balance_to_revenue_pool(
+            fee_pool_amount - FEE_POOL_TO_REVENUE_POOL_THRESHOLD,
+            quote_market,
+            &mut base_market.spot_fee_pool,
+        )?;
+    }
 
-    let FillFees {
+    let SerumFillFees {
         user_fee: taker_fee,
-        maker_rebate,
-        filler_reward,
         fee_to_market,
-        ..
-    } = fees::calculate_fee_for_fulfillment_with_match(
+        fee_pool_delta,
+        filler_reward,
+    } = fees::calculate_fee_for_fulfillment_with_serum(
         taker_stats,
-        maker_stats,
-        quote_asset_amount,
+        quote_asset_amount_filled,
         fee_structure,
         taker_order_slot,
         slot,
-        filler_multiplier,
-        false,
-        &None,
-        &MarketType::Spot,
+        filler.is_some(),
+        openbook_v2_fee,
+        0,
+        fee_pool_amount.cast()?,
+    )?;
+
+    let quote_spot_position_delta = match quote_update_direction {
+        SpotBalanceType::Deposit => quote_asset_amount_filled.safe_sub(taker_fee)?,
+        SpotBalanceType::Borrow => quote_asset_amount_filled.safe_add(taker_fee)?,
+    };
+
+    validate!(
+        base_update_direction
+            == taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Base),
+        ErrorCode::FailedToFillOnOpenbookV2,
+        "Fill on openbook v2 lead to unexpected to update direction"
     )?;
 
-    // Update taker state
     update_spot_balances_and_cumulative_deposits(
-        base_asset_amount.cast()?,
+        base_asset_amount_filled.cast()?,
         &taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Base),
         base_market,
-        &mut taker.spot_positions[taker_spot_position_index],
+        taker.force_get_spot_position_mut(base_market.market_index)?,
         false,
         None,
     )?;
 
-    let taker_quote_asset_amount_delta = match &taker.orders[taker_order_index].direction {
-        PositionDirection::Long => quote_asset_amount.safe_add(taker_fee)?,
-        PositionDirection::Short => quote_asset_amount.safe_sub(taker_fee)?,
-    };
+    validate!(
+        quote_update_direction
+            == taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Quote),
+        ErrorCode::FailedToFillOnOpenbookV2,
+        "Fill on openbook v2 lead to unexpected to update direction"
+    )?;
 
     update_spot_balances_and_cumulative_deposits(
-        taker_quote_asset_amount_delta.cast()?,
+        quote_spot_position_delta.cast()?,
         &taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Quote),
         quote_market,
         taker.get_quote_spot_position_mut(),
         false,
-        Some(quote_asset_amount.cast()?),
+        Some(quote_asset_amount_filled.cast()?),
     )?;
 
     taker.update_cumulative_spot_fees(-taker_fee.cast()?)?;
 
+    taker_stats.update_taker_volume_30d(quote_asset_amount_filled.cast()?, now)?;
+
+    taker_stats.increment_total_fees(taker_fee.cast()?)?;
+
     update_order_after_fill(
         &mut taker.orders[taker_order_index],
-        base_asset_amount,
-        quote_asset_amount,
+        base_asset_amount_filled,
+        quote_asset_amount_filled,
     )?;
 
     let taker_order_direction = taker.orders[taker_order_index].direction;
     decrease_spot_open_bids_and_asks(
-        &mut taker.spot_positions[taker_spot_position_index],
+        taker.force_get_spot_position_mut(base_market.market_index)?,
         &taker_order_direction,
-        base_asset_amount,
-    )?;
-
-    taker_stats.update_taker_volume_30d(quote_asset_amount, now)?;
-
-    taker_stats.increment_total_fees(taker_fee)?;
-
-    // Update maker state
-    update_spot_balances_and_cumulative_deposits(
-        base_asset_amount.cast()?,
-        &maker.orders[maker_order_index].get_spot_position_update_direction(AssetType::Base),
-        base_market,
-        &mut maker.spot_positions[maker_spot_position_index],
-        false,
-        None,
-    )?;
-
-    let maker_quote_asset_amount_delta = match &maker.orders[maker_order_index].direction {
-        PositionDirection::Long => quote_asset_amount.safe_sub(maker_rebate)?,
-        PositionDirection::Short => quote_asset_amount.safe_add(maker_rebate)?,
-    };
-
-    update_spot_balances_and_cumulative_deposits(
-        maker_quote_asset_amount_delta.cast()?,
-        &maker.orders[maker_order_index].get_spot_position_update_direction(AssetType::Quote),
-        quote_market,
-        maker.get_quote_spot_position_mut(),
-        false,
-        Some(quote_asset_amount.cast()?),
-    )?;
-
-    maker.update_cumulative_spot_fees(maker_rebate.cast()?)?;
-
-    update_order_after_fill(
-        &mut maker.orders[maker_order_index],
-        base_asset_amount,
-        quote_asset_amount,
-    )?;
-
-    let maker_order_direction = maker.orders[maker_order_index].direction;
-    decrease_spot_open_bids_and_asks(
-        &mut maker.spot_positions[maker_spot_position_index],
-        &maker_order_direction,
-        base_asset_amount,
+        base_asset_amount_filled,
     )?;
 
-    if let Some(maker_stats) = maker_stats {
-        maker_stats.update_maker_volume_30d(quote_asset_amount, now)?;
-        maker_stats.increment_total_rebate(maker_rebate)?;
-    } else {
-        taker_stats.update_maker_volume_30d(quote_asset_amount, now)?;
-        taker_stats.increment_total_rebate(maker_rebate)?;
-    }
-
-    // Update filler state
     if let (Some(filler), Some(filler_stats)) = (filler, filler_stats) {
         if filler_reward > 0 {
             update_spot_balances(
@@ -3693,78 +6732,60 @@ pub fn fulfill_spot_order_with_match(
             filler.update_cumulative_spot_fees(filler_reward.cast()?)?;
         }
 
-        filler_stats.update_filler_volume(quote_asset_amount, now)?;
+        filler_stats.update_filler_volume(quote_asset_amount_filled.cast()?, now)?;
     }
 
-    // Update base market
-    base_market.total_spot_fee = base_market.total_spot_fee.safe_add(fee_to_market.cast()?)?;
-
-    update_spot_balances(
-        fee_to_market.cast()?,
-        &SpotBalanceType::Deposit,
-        quote_market,
-        &mut base_market.spot_fee_pool,
-        false,
-    )?;
-
-    let fee_pool_amount = get_token_amount(
-        base_market.spot_fee_pool.scaled_balance,
-        quote_market,
-        &SpotBalanceType::Deposit,
-    )?;
-
-    if fee_pool_amount > FEE_POOL_TO_REVENUE_POOL_THRESHOLD * 2 {
-        transfer_spot_balance_to_revenue_pool(
-            fee_pool_amount - FEE_POOL_TO_REVENUE_POOL_THRESHOLD,
+    if fee_pool_delta != 0 {
+        update_spot_balances(
+            fee_pool_delta.unsigned_abs().cast()?,
+            if fee_pool_delta > 0 {
+                &SpotBalanceType::Deposit
+            } else {
+                &SpotBalanceType::Borrow
+            },
             quote_market,
             &mut base_market.spot_fee_pool,
+            false,
         )?;
     }
 
+    base_market.total_spot_fee = base_market.total_spot_fee.safe_add(fee_to_market.cast()?)?;
+
     let fill_record_id = get_then_update_id!(base_market, next_fill_record_id);
-    let order_action_explanation = if maker.orders[maker_order_index].is_jit_maker() {
-        OrderActionExplanation::OrderFilledWithMatchJit
-    } else {
-        OrderActionExplanation::OrderFilledWithMatch
-    };
     let order_action_record = get_order_action_record(
         now,
         OrderAction::Fill,
-        order_action_explanation,
-        maker.orders[maker_order_index].market_index,
+        OrderActionExplanation::OrderFillWithOpenbookV2,
+        taker.orders[taker_order_index].market_index,
         Some(*filler_key),
         Some(fill_record_id),
         Some(filler_reward),
-        Some(base_asset_amount),
-        Some(quote_asset_amount.cast()?),
+        Some(base_asset_amount_filled),
+        Some(quote_asset_amount_filled.cast()?),
         Some(taker_fee),
-        Some(maker_rebate),
-        None,
         Some(0),
+        None,
         Some(0),
+        Some(openbook_v2_fee),
         Some(*taker_key),
         Some(taker.orders[taker_order_index]),
-        Some(*maker_key),
-        Some(maker.orders[maker_order_index]),
-        oracle_map.get_price_data(&base_market.oracle)?.price,
+        None,
+        None,
+        oracle_price,
     )?;
     emit_stack::<_, { OrderActionRecord::SIZE }>(order_action_record)?;
 
-    // Clear taker/maker order if completely filled
-    if taker.orders[taker_order_index].get_base_asset_amount_unfilled(None)? == 0 {
-        taker.orders[taker_order_index] = Order::default();
-        taker.spot_positions[taker_spot_position_index].open_orders -= 1;
-    }
-
-    if maker.orders[maker_order_index].get_base_asset_amount_unfilled(None)? == 0 {
-        maker.orders[maker_order_index] = Order::default();
-        maker.spot_positions[maker_spot_position_index].open_orders -= 1;
+    if taker.orders[taker_order_index].get_base_asset_amount_unfilled(None)? == 0 {
+        taker.orders[taker_order_index] = Order::default();
+        taker
+            .force_get_spot_position_mut(base_market.market_index)?
+            .open_orders -= 1;
     }
 
-    Ok(base_asset_amount)
+    Ok(base_asset_amount_filled)
 }
 
-pub fn fulfill_spot_order_with_serum(
+pub fn fulfill_spot_order_with_phoenix(
     base_market: &mut SpotMarket,
     quote_market: &mut SpotMarket,
     taker: &mut User,
@@ -3778,10 +6799,10 @@ pub fn fulfill_spot_order_with_serum(
     slot: u64,
     oracle_map: &mut OracleMap,
     fee_structure: &FeeStructure,
-    serum_fulfillment_params: &mut Option<SerumFulfillmentParams>,
+    phoenix_fulfillment_params: &mut Option<PhoenixFulfillmentParams>,
 ) -> DriftResult<u64> {
-    let serum_new_order_accounts = match serum_fulfillment_params {
-        Some(serum_new_order_accounts) => serum_new_order_accounts,
+    let phoenix_accounts = match phoenix_fulfillment_params {
+        Some(phoenix_accounts) => phoenix_accounts,
         None => return Ok(0),
     };
 
@@ -3791,6 +6812,7 @@ pub fn fulfill_spot_order_with_serum(
         None,
         slot,
         base_market.order_tick_size,
+        false,
     )?;
     let taker_token_amount = taker
         .force_get_spot_position_mut(base_market.market_index)?
@@ -3806,216 +6828,109 @@ pub fn fulfill_spot_order_with_serum(
     let taker_base_asset_amount =
         taker_base_asset_amount.min(max_base_asset_amount.unwrap_or(u64::MAX));
 
-    let (best_bid, best_ask) = get_best_bid_and_ask(
-        serum_new_order_accounts.serum_market,
-        serum_new_order_accounts.serum_bids,
-        serum_new_order_accounts.serum_asks,
-        serum_new_order_accounts.serum_program_id.key,
-        base_market.decimals,
-    )?;
-
-    let mut mid_price = 0;
-    if let Some(best_bid) = best_bid {
-        base_market.historical_index_data.last_index_bid_price = best_bid;
-        mid_price += best_bid;
-    }
-
-    if let Some(best_ask) = best_ask {
-        base_market.historical_index_data.last_index_ask_price = best_ask;
-        mid_price = if mid_price == 0 {
-            best_ask
-        } else {
-            mid_price.safe_add(best_ask)?.safe_div(2)?
-        };
-    }
-
-    base_market.historical_index_data.last_index_price_twap = calculate_new_twap(
-        mid_price.cast()?,
-        now,
-        base_market
-            .historical_index_data
-            .last_index_price_twap
-            .cast()?,
-        base_market.historical_index_data.last_index_price_twap_ts,
-        ONE_HOUR as i64,
-    )?
-    .cast()?;
-
-    base_market.historical_index_data.last_index_price_twap_5min = calculate_new_twap(
-        mid_price.cast()?,
-        now,
-        base_market
-            .historical_index_data
-            .last_index_price_twap_5min
-            .cast()?,
-        base_market.historical_index_data.last_index_price_twap_ts,
-        FIVE_MINUTE as i64,
-    )?
-    .cast()?;
-
-    let taker_price = if let Some(price) = taker_price {
-        price
-    } else {
-        match order_direction {
-            PositionDirection::Long => {
-                if let Some(ask) = best_ask {
-                    ask.safe_add(ask / 100)?
-                } else {
-                    msg!("Serum has no ask");
-                    return Ok(0);
-                }
-            }
-            PositionDirection::Short => {
-                if let Some(bid) = best_bid {
-                    bid.safe_sub(bid / 100)?
-                } else {
-                    msg!("Serum has no bid");
-                    return Ok(0);
-                }
+    // the phoenix book sides aren't read, so orders without a limit price are bounded 1% through the oracle
+    let taker_price = match taker_price {
+        Some(price) => price,
+        None => {
+            let oracle_price = oracle_price.unsigned_abs();
+            match order_direction {
+                PositionDirection::Long => oracle_price.safe_add(oracle_price / 100)?,
+                PositionDirection::Short => oracle_price.safe_sub(oracle_price / 100)?,
             }
         }
     };
 
-    let market_state_before = load_serum_market(
-        serum_new_order_accounts.serum_market,
-        serum_new_order_accounts.serum_program_id.key,
+    let market_state_before = load_phoenix_market(
+        phoenix_accounts.phoenix_market,
+        phoenix_accounts.phoenix_program_id.key,
     )?;
 
-    let serum_order_side = match order_direction {
-        PositionDirection::Long => Side::Bid,
-        PositionDirection::Short => Side::Ask,
+    let phoenix_order_side = match order_direction {
+        PositionDirection::Long => PhoenixSide::Bid,
+        PositionDirection::Short => PhoenixSide::Ask,
     };
 
-    let serum_max_coin_qty =
-        calculate_serum_max_coin_qty(taker_base_asset_amount, market_state_before.coin_lot_size)?;
+    let phoenix_num_base_lots =
+        taker_base_asset_amount.safe_div(market_state_before.base_lot_size)?;
 
-    let serum_limit_price = calculate_serum_limit_price(
+    let phoenix_price_in_ticks = calculate_phoenix_price_in_ticks(
         taker_price,
-        market_state_before.pc_lot_size,
-        base_market.decimals,
-        market_state_before.coin_lot_size,
+        market_state_before.tick_size_in_quote_atoms_per_base_unit,
         order_direction,
     )?;
 
-    let serum_max_native_pc_qty = calculate_serum_max_native_pc_quantity(
-        serum_limit_price,
-        serum_max_coin_qty,
-        market_state_before.pc_lot_size,
-    )?
-    .min(max_quote_asset_amount.unwrap_or(u64::MAX));
+    // a quote budget only limits bids, zero leaves it unbounded
+    let phoenix_num_quote_lots = match (order_direction, max_quote_asset_amount) {
+        (PositionDirection::Long, Some(max_quote_asset_amount)) => {
+            max_quote_asset_amount.safe_div(market_state_before.quote_lot_size)?
+        }
+        _ => 0,
+    };
 
-    if serum_max_coin_qty == 0 || serum_max_native_pc_qty == 0 {
+    if phoenix_num_base_lots == 0
+        || phoenix_price_in_ticks == 0
+        || (order_direction == PositionDirection::Long
+            && max_quote_asset_amount.is_some()
+            && phoenix_num_quote_lots == 0)
+    {
         return Ok(0);
     }
 
-    let serum_order = NewOrderInstructionV3 {
-        side: serum_order_side,
-        limit_price: NonZeroU64::new(serum_limit_price).safe_unwrap()?,
-        max_coin_qty: NonZeroU64::new(serum_max_coin_qty).safe_unwrap()?, // max base to deposit into serum
-        max_native_pc_qty_including_fees: NonZeroU64::new(serum_max_native_pc_qty).safe_unwrap()?, // max quote to deposit into serum
-        self_trade_behavior: SelfTradeBehavior::AbortTransaction,
-        order_type: serum_dex::matching::OrderType::ImmediateOrCancel,
+    let phoenix_order = PhoenixImmediateOrCancelOrderPacket {
+        side: phoenix_order_side,
+        price_in_ticks: Some(phoenix_price_in_ticks),
+        num_base_lots: phoenix_num_base_lots,
+        num_quote_lots: phoenix_num_quote_lots,
+        min_base_lots_to_fill: 0,
+        min_quote_lots_to_fill: 0,
+        self_trade_behavior: PhoenixSelfTradeBehavior::Abort,
+        match_limit: Some(10),
         client_order_id: 0,
-        limit: 10,
-        max_ts: now,
+        use_only_deposited_funds: false,
+        last_valid_slot: None,
+        last_valid_unix_timestamp_in_seconds: None,
     };
 
-    let _market_fees_accrued_before = market_state_before.pc_fees_accrued;
-    let base_before = serum_new_order_accounts.base_market_vault.amount;
-    let quote_before = serum_new_order_accounts.quote_market_vault.amount;
-    let market_rebates_accrued_before = market_state_before.referrer_rebates_accrued;
-
-    drop(market_state_before);
-
-    invoke_new_order(
-        serum_new_order_accounts.serum_program_id,
-        serum_new_order_accounts.serum_market,
-        serum_new_order_accounts.serum_open_orders,
-        serum_new_order_accounts.serum_request_queue,
-        serum_new_order_accounts.serum_event_queue,
-        serum_new_order_accounts.serum_bids,
-        serum_new_order_accounts.serum_asks,
-        &match order_direction {
-            PositionDirection::Long => serum_new_order_accounts
-                .quote_market_vault
-                .to_account_info(),
-            PositionDirection::Short => {
-                serum_new_order_accounts.base_market_vault.to_account_info()
-            }
-        },
-        serum_new_order_accounts.drift_signer,
-        serum_new_order_accounts.serum_base_vault,
-        serum_new_order_accounts.serum_quote_vault,
-        serum_new_order_accounts.srm_vault,
-        &serum_new_order_accounts.token_program.to_account_info(),
-        serum_order,
-        serum_new_order_accounts.signer_nonce,
-    )?;
-
-    let market_state_after = load_serum_market(
-        serum_new_order_accounts.serum_market,
-        serum_new_order_accounts.serum_program_id.key,
+    let quote_lot_size = market_state_before.quote_lot_size;
+    let market_fees_before = market_state_before.collected_quote_lot_fees;
+    let base_before = phoenix_accounts.base_market_vault.amount;
+    let quote_before = phoenix_accounts.quote_market_vault.amount;
+
+    invoke_swap(
+        phoenix_accounts.phoenix_program_id,
+        phoenix_accounts.phoenix_log_authority,
+        phoenix_accounts.phoenix_market,
+        phoenix_accounts.drift_signer,
+        &phoenix_accounts.base_market_vault.to_account_info(),
+        &phoenix_accounts.quote_market_vault.to_account_info(),
+        phoenix_accounts.phoenix_base_vault,
+        phoenix_accounts.phoenix_quote_vault,
+        &phoenix_accounts.token_program.to_account_info(),
+        phoenix_order,
+        phoenix_accounts.signer_nonce,
     )?;
 
-    let _market_fees_accrued_after = market_state_after.pc_fees_accrued;
-    let market_rebates_accrued_after = market_state_after.referrer_rebates_accrued;
-
-    drop(market_state_after);
-
-    let open_orders_before = load_open_orders(serum_new_order_accounts.serum_open_orders)?;
-    let unsettled_referrer_rebate_before = open_orders_before.referrer_rebates_accrued;
-
-    drop(open_orders_before);
-
-    invoke_settle_funds(
-        serum_new_order_accounts.serum_program_id,
-        serum_new_order_accounts.serum_market,
-        serum_new_order_accounts.serum_open_orders,
-        serum_new_order_accounts.drift_signer,
-        serum_new_order_accounts.serum_base_vault,
-        serum_new_order_accounts.serum_quote_vault,
-        &serum_new_order_accounts.base_market_vault.to_account_info(),
-        &serum_new_order_accounts
-            .quote_market_vault
-            .to_account_info(),
-        serum_new_order_accounts.serum_signer,
-        &serum_new_order_accounts.token_program.to_account_info(),
-        serum_new_order_accounts.signer_nonce,
+    let market_state_after = load_phoenix_market(
+        phoenix_accounts.phoenix_market,
+        phoenix_accounts.phoenix_program_id.key,
     )?;
 
-    serum_new_order_accounts
-        .base_market_vault
-        .reload()
-        .map_err(|_e| {
-            msg!("Failed to reload base_market_vault");
-            ErrorCode::FailedSerumCPI
-        })?;
-    serum_new_order_accounts
-        .quote_market_vault
-        .reload()
-        .map_err(|_e| {
-            msg!("Failed to reload quote_market_vault");
-            ErrorCode::FailedSerumCPI
-        })?;
-
-    let base_after = serum_new_order_accounts.base_market_vault.amount;
-    let quote_after = serum_new_order_accounts.quote_market_vault.amount;
-
-    let open_orders_after = load_open_orders(serum_new_order_accounts.serum_open_orders)?;
-    let unsettled_referrer_rebate_after = open_orders_after.referrer_rebates_accrued;
-
-    drop(open_orders_after);
+    let phoenix_fee = market_state_after
+        .collected_quote_lot_fees
+        .safe_sub(market_fees_before)?
+        .safe_mul(quote_lot_size)?;
 
-    let settled_referred_rebate =
-        unsettled_referrer_rebate_before.safe_sub(unsettled_referrer_rebate_after)?;
+    phoenix_accounts.base_market_vault.reload().map_err(|_e| {
+        msg!("Failed to reload base_market_vault");
+        ErrorCode::FailedPhoenixCPI
+    })?;
+    phoenix_accounts.quote_market_vault.reload().map_err(|_e| {
+        msg!("Failed to reload quote_market_vault");
+        ErrorCode::FailedPhoenixCPI
+    })?;
 
-    update_spot_balances(
-        settled_referred_rebate as u128,
-        &SpotBalanceType::Deposit,
-        quote_market,
-        &mut base_market.spot_fee_pool,
-        false,
-    )?;
+    let base_after = phoenix_accounts.base_market_vault.amount;
+    let quote_after = phoenix_accounts.quote_market_vault.amount;
 
     let (base_update_direction, base_asset_amount_filled) = if base_after > base_before {
         (SpotBalanceType::Deposit, base_after.safe_sub(base_before)?)
@@ -4024,38 +6939,21 @@ pub fn fulfill_spot_order_with_serum(
     };
 
     if base_asset_amount_filled == 0 {
-        msg!("No base filled on serum");
+        msg!("No base filled on phoenix");
         return Ok(0);
     }
 
-    let serum_referrer_rebate =
-        market_rebates_accrued_after.safe_sub(market_rebates_accrued_before)?;
-
-    // rebate is half of taker fee
-    let serum_fee = serum_referrer_rebate;
-
+    // swaps settle straight to the vaults, so the quote delta is net of the phoenix fee
     let (quote_update_direction, quote_asset_amount_filled) =
         if base_update_direction == SpotBalanceType::Borrow {
-            let quote_asset_amount_delta = quote_after
-                .safe_sub(quote_before)?
-                .safe_sub(settled_referred_rebate)?;
-
             (
                 SpotBalanceType::Deposit,
-                quote_asset_amount_delta
-                    .safe_add(serum_fee)?
-                    .safe_add(serum_referrer_rebate)?,
+                quote_after.safe_sub(quote_before)?.safe_add(phoenix_fee)?,
             )
         } else {
-            let quote_asset_amount_delta = quote_before
-                .safe_add(settled_referred_rebate)?
-                .safe_sub(quote_after)?;
-
             (
                 SpotBalanceType::Borrow,
-                quote_asset_amount_delta
-                    .safe_sub(serum_fee)?
-                    .safe_sub(serum_referrer_rebate)?,
+                quote_before.safe_sub(quote_after)?.safe_sub(phoenix_fee)?,
             )
         };
 
@@ -4094,8 +6992,8 @@ pub fn fulfill_spot_order_with_serum(
         taker_order_slot,
         slot,
         filler.is_some(),
-        serum_fee,
-        serum_referrer_rebate,
+        phoenix_fee,
+        0,
         fee_pool_amount.cast()?,
     )?;
 
@@ -4107,8 +7005,8 @@ pub fn fulfill_spot_order_with_serum(
     validate!(
         base_update_direction
             == taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Base),
-        ErrorCode::FailedToFillOnSerum,
-        "Fill on serum lead to unexpected to update direction"
+        ErrorCode::FailedToFillOnPhoenix,
+        "Fill on phoenix lead to unexpected to update direction"
     )?;
 
     update_spot_balances_and_cumulative_deposits(
@@ -4123,8 +7021,8 @@ pub fn fulfill_spot_order_with_serum(
     validate!(
         quote_update_direction
             == taker.orders[taker_order_index].get_spot_position_update_direction(AssetType::Quote),
-        ErrorCode::FailedToFillOnSerum,
-        "Fill on serum lead to unexpected to update direction"
+        ErrorCode::FailedToFillOnPhoenix,
+        "Fill on phoenix lead to unexpected to update direction"
     )?;
 
     update_spot_balances_and_cumulative_deposits(
@@ -4191,7 +7089,7 @@ pub fn fulfill_spot_order_with_serum(
     let order_action_record = get_order_action_record(
         now,
         OrderAction::Fill,
-        OrderActionExplanation::OrderFillWithSerum,
+        OrderActionExplanation::OrderFillWithPhoenix,
         taker.orders[taker_order_index].market_index,
         Some(*filler_key),
         Some(fill_record_id),
@@ -4202,7 +7100,7 @@ pub fn fulfill_spot_order_with_serum(
         Some(0),
         None,
         Some(0),
-        Some(serum_fee),
+        Some(phoenix_fee),
         Some(*taker_key),
         Some(taker.orders[taker_order_index]),
         None,
@@ -4285,6 +7183,7 @@ pub fn trigger_spot_order(
     let (oracle_price_data, oracle_validity) = oracle_map.get_price_data_and_validity(
         &spot_market.oracle,
         spot_market.historical_oracle_data.last_oracle_price_twap,
+        &spot_market.validity_guard_rails_override,
     )?;
 
     validate!(
@@ -4300,6 +7199,31 @@ pub fn trigger_spot_order(
         &user.orders[order_index],
         oracle_price.unsigned_abs().cast()?,
     )?;
+
+    if !can_trigger && user.orders[order_index].is_trailing() {
+        let trigger_price = user.orders[order_index].trigger_price;
+        let new_trigger_price = calculate_trailing_trigger_price(
+            &user.orders[order_index],
+            oracle_price.unsigned_abs().cast()?,
+            spot_market.order_tick_size,
+        )?;
+
+        validate!(
+            new_trigger_price != trigger_price,
+            ErrorCode::OrderDidNotSatisfyTriggerCondition,
+            "Trailing trigger price unchanged"
+        )?;
+
+        msg!(
+            "Trailing trigger price updated from {} to {}",
+            trigger_price,
+            new_trigger_price
+        );
+        user.orders[order_index].trigger_price = new_trigger_price;
+
+        return Ok(());
+    }
+
     validate!(can_trigger, ErrorCode::OrderDidNotSatisfyTriggerCondition)?;
 
     {
@@ -4308,8 +7232,12 @@ pub fn trigger_spot_order(
 
         user.orders[order_index].trigger_condition =
             match user.orders[order_index].trigger_condition {
-                OrderTriggerCondition::Above => OrderTriggerCondition::TriggeredAbove,
-                OrderTriggerCondition::Below => OrderTriggerCondition::TriggeredBelow,
+                OrderTriggerCondition::Above
+                | OrderTriggerCondition::TrailingAbove
+                | OrderTriggerCondition::FundingAbove => OrderTriggerCondition::TriggeredAbove,
+                OrderTriggerCondition::Below
+                | OrderTriggerCondition::TrailingBelow
+                | OrderTriggerCondition::FundingBelow => OrderTriggerCondition::TriggeredBelow,
                 _ => {
                     return Err(print_error!(ErrorCode::InvalidTriggerOrderCondition)());
                 }
@@ -4373,6 +7301,17 @@ pub fn trigger_spot_order(
     drop(spot_market);
     drop(quote_market);
 
+    cancel_linked_orders(
+        user,
+        &user_key,
+        Some(&filler_key),
+        perp_market_map,
+        spot_market_map,
+        oracle_map,
+        now,
+        slot,
+    )?;
+
     // If order is risk increasing and user is below initial margin, cancel it
     let balance_type = user.spot_positions[position_index].balance_type;
     let is_risk_increasing =
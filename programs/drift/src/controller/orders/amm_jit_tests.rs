@@ -41,6 +41,7 @@ pub mod amm_jit {
         SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::math::constants::{CONCENTRATION_PRECISION, PRICE_PRECISION_U64};
+    use crate::state::fuel_map::FuelMap;
     use crate::state::oracle::{HistoricalOracleData, OracleSource};
     use crate::state::perp_market::{MarketStatus, PerpMarket, AMM};
     use crate::state::perp_market_map::PerpMarketMap;
@@ -308,6 +309,7 @@ pub mod amm_jit {
             slot,
             0,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -495,6 +497,7 @@ pub mod amm_jit {
             slot,
             10,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -690,6 +693,7 @@ pub mod amm_jit {
             slot,
             10,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -884,6 +888,7 @@ pub mod amm_jit {
             slot,
             10,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -1064,7 +1069,7 @@ pub mod amm_jit {
         let maker_and_referrer_stats = UserStatsMap::load_one(&maker_stats_account_info).unwrap();
         let mut filler_stats = UserStats::default();
 
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -1086,6 +1091,7 @@ pub mod amm_jit {
             slot,
             0,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -1275,7 +1281,7 @@ pub mod amm_jit {
         assert_eq!(market.amm.total_mm_fee, 0);
         assert_eq!(market.amm.total_fee_withdrawn, 0);
 
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -1297,6 +1303,7 @@ pub mod amm_jit {
             slot,
             0,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -1500,6 +1507,7 @@ pub mod amm_jit {
             slot,
             0,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -1663,7 +1671,7 @@ pub mod amm_jit {
         assert_eq!(market.amm.total_fee_withdrawn, 0);
 
         // fulfill with match
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -1685,6 +1693,7 @@ pub mod amm_jit {
             slot,
             10,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -1860,7 +1869,7 @@ pub mod amm_jit {
         assert_eq!(market.amm.total_fee_withdrawn, 0);
 
         // fulfill with match
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -1882,6 +1891,7 @@ pub mod amm_jit {
             slot,
             10,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -2066,9 +2076,14 @@ pub mod amm_jit {
                 break;
             }
 
-            let auction_price =
-                crate::math::auction::calculate_auction_price(&taker.orders[0], slot, 1, None)
-                    .unwrap();
+            let auction_price = crate::math::auction::calculate_auction_price(
+                &taker.orders[0],
+                slot,
+                1,
+                None,
+                false,
+            )
+            .unwrap();
             let baa = market.amm.order_step_size * 4;
 
             let (mark, ask, bid) = {
@@ -2131,6 +2146,7 @@ pub mod amm_jit {
                 slot,
                 auction_duration,
                 true,
+                &FuelMap::empty(),
             )
             .unwrap();
 
@@ -2348,9 +2364,14 @@ pub mod amm_jit {
                 break;
             }
 
-            let auction_price =
-                crate::math::auction::calculate_auction_price(&taker.orders[0], slot, 1, None)
-                    .unwrap();
+            let auction_price = crate::math::auction::calculate_auction_price(
+                &taker.orders[0],
+                slot,
+                1,
+                None,
+                false,
+            )
+            .unwrap();
             let baa = 1000 * 4;
 
             let (mark, ask, bid) = {
@@ -2413,6 +2434,7 @@ pub mod amm_jit {
                 slot,
                 10,
                 true,
+                &FuelMap::empty(),
             )
             .unwrap();
 
@@ -2617,7 +2639,7 @@ pub mod amm_jit {
         assert_eq!(taker.perp_positions[0].open_orders, 1);
 
         // fulfill with match
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -2639,6 +2661,7 @@ pub mod amm_jit {
             slot,
             0,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -37,6 +37,7 @@ pub mod fulfill_order_with_maker_order {
         BID_ASK_SPREAD_PRECISION, PEG_PRECISION, PRICE_PRECISION, PRICE_PRECISION_I64,
         PRICE_PRECISION_U64, QUOTE_PRECISION_I64, QUOTE_PRECISION_U64,
     };
+    use crate::state::events::FillRecordBuffer;
     use crate::state::perp_market::{PerpMarket, AMM};
     use crate::state::user::{Order, OrderType, PerpPosition, User, UserStats};
 
@@ -118,12 +119,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -232,12 +236,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -346,12 +353,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -460,12 +470,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -574,12 +587,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -654,12 +670,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -735,12 +754,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -816,12 +838,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -897,12 +922,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -998,12 +1026,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -1102,12 +1133,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -1213,12 +1247,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -1325,12 +1362,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -1461,12 +1501,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -1572,12 +1615,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -1667,12 +1713,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             Some(oracle_price),
             now,
             slot,
             &fee_structure,
             &mut oracle_map,
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -1776,6 +1825,7 @@ pub mod fulfill_order_with_maker_order {
                 None,
                 slot,
                 1,
+                false,
             )
             .unwrap();
         assert_eq!(taker_price, Some(199000000)); // $51
@@ -1806,12 +1856,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             Some(oracle_price),
             now,
             slot,
             &fee_structure,
             &mut oracle_map,
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -1933,12 +1986,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut oracle_map,
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -2045,6 +2101,7 @@ pub mod fulfill_order_with_maker_order {
                 None,
                 slot,
                 1,
+                false,
             )
             .unwrap();
         assert_eq!(taker_price, Some(51000000)); // $51
@@ -2071,12 +2128,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut oracle_map,
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -2166,7 +2226,7 @@ pub mod fulfill_order_with_maker_order {
 
         assert_eq!(
             taker.orders[0]
-                .get_limit_price(None, None, slot, market.amm.order_tick_size)
+                .get_limit_price(None, None, slot, market.amm.order_tick_size, false)
                 .unwrap(),
             Some(55000000)
         );
@@ -2193,12 +2253,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -2288,7 +2351,7 @@ pub mod fulfill_order_with_maker_order {
 
         assert_eq!(
             taker.orders[0]
-                .get_limit_price(None, None, slot, market.amm.order_tick_size)
+                .get_limit_price(None, None, slot, market.amm.order_tick_size, false)
                 .unwrap(),
             Some(100000000)
         );
@@ -2314,12 +2377,15 @@ pub mod fulfill_order_with_maker_order {
             &filler_key,
             &mut None,
             &mut None,
+            &mut None,
+            &mut None,
             0,
             None,
             now,
             slot,
             &fee_structure,
             &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
         )
         .unwrap();
 
@@ -2358,6 +2424,111 @@ pub mod fulfill_order_with_maker_order {
         assert_eq!(market.amm.total_fee_minus_distributions, 20000);
         assert_eq!(market.amm.net_revenue_since_last_funding, 20000);
     }
+
+    #[test]
+    fn referrer_reward_shared_with_second_level_referrer() {
+        let mut taker = User {
+            orders: get_orders(Order {
+                market_index: 0,
+                order_type: OrderType::Market,
+                direction: PositionDirection::Long,
+                base_asset_amount: BASE_PRECISION_U64,
+                slot: 0,
+                auction_start_price: 100 * PRICE_PRECISION_I64,
+                auction_end_price: 200 * PRICE_PRECISION_I64,
+                auction_duration: 5,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 1,
+                open_bids: BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let mut maker = User {
+            orders: get_orders(Order {
+                market_index: 0,
+                post_only: true,
+                order_type: OrderType::Limit,
+                direction: PositionDirection::Short,
+                base_asset_amount: BASE_PRECISION_U64,
+                price: 100 * PRICE_PRECISION_U64,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 1,
+                open_asks: -BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let mut referrer = User::default();
+        let mut second_level_referrer = User::default();
+
+        let mut market = PerpMarket::default_test();
+
+        let now = 1_i64;
+        let slot = 1_u64;
+
+        let fee_structure = get_fee_structure();
+
+        let (taker_key, maker_key, filler_key) = get_user_keys();
+
+        let mut taker_stats = UserStats::default();
+        let mut maker_stats = UserStats::default();
+        let mut referrer_stats = UserStats {
+            referrer_reward_share: 20,
+            second_level_referrer_reward_share: 50,
+            ..UserStats::default()
+        };
+        let mut second_level_referrer_stats = UserStats::default();
+
+        fulfill_perp_order_with_match(
+            &mut market,
+            &mut taker,
+            &mut taker_stats,
+            0,
+            &taker_key,
+            &mut maker,
+            &mut Some(&mut maker_stats),
+            0,
+            &maker_key,
+            &mut None,
+            &mut None,
+            &filler_key,
+            &mut Some(&mut referrer),
+            &mut Some(&mut referrer_stats),
+            &mut Some(&mut second_level_referrer),
+            &mut Some(&mut second_level_referrer_stats),
+            0,
+            None,
+            now,
+            slot,
+            &fee_structure,
+            &mut get_oracle_map(),
+            &mut FillRecordBuffer::default(),
+        )
+        .unwrap();
+
+        assert_eq!(taker_stats.fees.total_fee_paid, 50000);
+
+        // 20% of the taker fee, half of it passed up to the second level referrer
+        assert_eq!(referrer.perp_positions[0].quote_asset_amount, 5000);
+        assert_eq!(referrer_stats.fees.total_referrer_reward, 5000);
+        assert_eq!(
+            second_level_referrer.perp_positions[0].quote_asset_amount,
+            5000
+        );
+        assert_eq!(second_level_referrer_stats.fees.total_referrer_reward, 5000);
+
+        assert_eq!(market.amm.total_fee, 10000);
+        assert_eq!(market.amm.total_fee_minus_distributions, 10000);
+    }
 }
 
 pub mod fulfill_order {
@@ -2370,10 +2541,11 @@ pub mod fulfill_order {
     use crate::get_orders;
     use crate::math::constants::{
         AMM_RESERVE_PRECISION, BASE_PRECISION_I64, BASE_PRECISION_U64,
-        BID_ASK_SPREAD_PRECISION_I64, PEG_PRECISION, PRICE_PRECISION, PRICE_PRECISION_I64,
-        PRICE_PRECISION_U64, QUOTE_PRECISION_I64, QUOTE_PRECISION_U64, SPOT_BALANCE_PRECISION_U64,
-        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+        BID_ASK_SPREAD_PRECISION_I64, MAX_USER_ORDERS, PEG_PRECISION, PRICE_PRECISION,
+        PRICE_PRECISION_I64, PRICE_PRECISION_U64, QUOTE_PRECISION_I64, QUOTE_PRECISION_U64,
+        SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
+    use crate::state::fuel_map::FuelMap;
     use crate::state::oracle::{HistoricalOracleData, OracleSource};
     use crate::state::perp_market::{PerpMarket, AMM};
     use crate::state::perp_market_map::PerpMarketMap;
@@ -2635,7 +2807,7 @@ pub mod fulfill_order {
 
         let mut filler_stats = UserStats::default();
 
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -2661,6 +2833,7 @@ pub mod fulfill_order {
             slot,
             0,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -2877,7 +3050,7 @@ pub mod fulfill_order {
 
         let mut filler_stats = UserStats::default();
 
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -2902,6 +3075,7 @@ pub mod fulfill_order {
             slot,
             10,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -3067,7 +3241,7 @@ pub mod fulfill_order {
 
         let mut filler_stats = UserStats::default();
 
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -3089,6 +3263,7 @@ pub mod fulfill_order {
             slot,
             0,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -3270,7 +3445,7 @@ pub mod fulfill_order {
         create_anchor_account_info!(maker_stats, UserStats, maker_stats_account_info);
         let maker_and_referrer_stats = UserStatsMap::load_one(&maker_stats_account_info).unwrap();
 
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -3292,6 +3467,7 @@ pub mod fulfill_order {
             slot,
             10,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -3433,7 +3609,7 @@ pub mod fulfill_order {
 
         let mut taker_stats = UserStats::default();
 
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -3455,6 +3631,7 @@ pub mod fulfill_order {
             slot,
             0,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -3601,7 +3778,7 @@ pub mod fulfill_order {
     //
     //     let mut taker_stats = UserStats::default();
     //
-    //     let (base_asset_amount, _, _) = fulfill_perp_order(
+    //     let (base_asset_amount, _, _, _) = fulfill_perp_order(
     //         &mut taker,
     //         0,
     //         &taker_key,
@@ -3709,7 +3886,7 @@ pub mod fulfill_order {
 
         let mut oracle_map = get_oracle_map();
 
-        let mut taker_orders = [Order::default(); 32];
+        let mut taker_orders = [Order::default(); MAX_USER_ORDERS];
         taker_orders[0] = Order {
             market_index: 0,
             status: OrderStatus::Open,
@@ -3766,7 +3943,7 @@ pub mod fulfill_order {
         let maker_key = Pubkey::default();
         let maker_authority =
             Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
-        let mut maker_orders = [Order::default(); 32];
+        let mut maker_orders = [Order::default(); MAX_USER_ORDERS];
         maker_orders[0] = Order {
             market_index: 1,
             post_only: true,
@@ -3833,7 +4010,7 @@ pub mod fulfill_order {
 
         let taker_before = taker;
         let maker_before = maker;
-        let (base_asset_amount, _, _) = fulfill_perp_order(
+        let (base_asset_amount, _, _, _) = fulfill_perp_order(
             &mut taker,
             0,
             &taker_key,
@@ -3855,6 +4032,7 @@ pub mod fulfill_order {
             slot,
             10,
             true,
+            &FuelMap::empty(),
         )
         .unwrap();
 
@@ -3952,6 +4130,7 @@ pub mod fill_order {
         PRICE_PRECISION_I64, PRICE_PRECISION_U64, SPOT_BALANCE_PRECISION_U64,
         SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
+    use crate::state::fuel_map::FuelMap;
     use crate::state::oracle::HistoricalOracleData;
     use crate::state::oracle::OracleSource;
     use crate::state::perp_market::{PerpMarket, AMM};
@@ -4148,7 +4327,7 @@ pub mod fill_order {
             ..State::default()
         };
 
-        let (base_asset_amount, _) = fill_perp_order(
+        let (base_asset_amount, _, _) = fill_perp_order(
             1,
             &state,
             &user_account_loader,
@@ -4162,6 +4341,9 @@ pub mod fill_order {
             &maker_and_referrer_stats,
             None,
             &clock,
+            &FuelMap::empty(),
+            None,
+            &[],
         )
         .unwrap();
 
@@ -4354,7 +4536,7 @@ pub mod fill_order {
             ..State::default()
         };
 
-        let (base_asset_amount, _) = fill_perp_order(
+        let (base_asset_amount, _, _) = fill_perp_order(
             1,
             &state,
             &user_account_loader,
@@ -4368,6 +4550,9 @@ pub mod fill_order {
             &maker_and_referrer_stats,
             None,
             &clock,
+            &FuelMap::empty(),
+            None,
+            &[],
         )
         .unwrap();
 
@@ -4481,7 +4666,7 @@ pub mod fill_order {
             unix_timestamp: 11,
         };
 
-        let (base_asset_amount, _) = fill_perp_order(
+        let (base_asset_amount, _, _) = fill_perp_order(
             1,
             &state,
             &user_account_loader,
@@ -4495,6 +4680,9 @@ pub mod fill_order {
             &UserStatsMap::empty(),
             None,
             &clock,
+            &FuelMap::empty(),
+            None,
+            &[],
         )
         .unwrap();
 
@@ -4661,6 +4849,9 @@ pub mod fill_order {
             &UserStatsMap::empty(),
             None,
             &clock,
+            &FuelMap::empty(),
+            None,
+            &[],
         );
 
         assert_eq!(err, Err(ErrorCode::MaxOpenInterest));
@@ -7109,8 +7300,8 @@ pub mod fulfill_spot_order {
     use crate::create_account_info;
     use crate::create_anchor_account_info;
     use crate::math::constants::{
-        LAMPORTS_PER_SOL_I64, LAMPORTS_PER_SOL_U64, PRICE_PRECISION_I64, PRICE_PRECISION_U64,
-        SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+        LAMPORTS_PER_SOL_I64, LAMPORTS_PER_SOL_U64, MAX_USER_ORDERS, PRICE_PRECISION_I64,
+        PRICE_PRECISION_U64, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
     };
     use crate::state::perp_market_map::PerpMarketMap;
     use crate::state::spot_market::{SpotBalanceType, SpotMarket};
@@ -7371,7 +7562,7 @@ pub mod fulfill_spot_order {
             open_bids: LAMPORTS_PER_SOL_I64,
             ..SpotPosition::default()
         };
-        let mut taker_orders = [Order::default(); 32];
+        let mut taker_orders = [Order::default(); MAX_USER_ORDERS];
         taker_orders[0] = Order {
             order_id: 1,
             market_index: 1,
@@ -7433,7 +7624,7 @@ pub mod fulfill_spot_order {
             open_asks: -LAMPORTS_PER_SOL_I64,
             ..SpotPosition::default()
         };
-        let mut maker_orders = [Order::default(); 32];
+        let mut maker_orders = [Order::default(); MAX_USER_ORDERS];
         maker_orders[0] = Order {
             order_id: 2,
             market_index: 2,
@@ -7545,6 +7736,9 @@ pub mod fulfill_spot_order {
             Some(1),
             &clock,
             &mut None,
+            &mut None,
+            &mut None,
+            None,
         )
         .unwrap();
 
@@ -7732,6 +7926,9 @@ pub mod fill_spot_order {
             Some(1),
             &clock,
             &mut None,
+            &mut None,
+            &mut None,
+            None,
         )
         .unwrap();
 
@@ -7752,8 +7949,9 @@ pub mod force_cancel_orders {
     use crate::create_anchor_account_info;
     use crate::math::constants::{
         AMM_RESERVE_PRECISION, BASE_PRECISION_I64, BASE_PRECISION_U64, LAMPORTS_PER_SOL_I64,
-        LAMPORTS_PER_SOL_U64, PEG_PRECISION, PRICE_PRECISION_U64, SPOT_BALANCE_PRECISION,
-        SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+        LAMPORTS_PER_SOL_U64, MAX_USER_ORDERS, PEG_PRECISION, PRICE_PRECISION_U64,
+        SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
+        SPOT_WEIGHT_PRECISION,
     };
     use crate::state::oracle::HistoricalOracleData;
     use crate::state::oracle::OracleSource;
@@ -7879,7 +8077,7 @@ pub mod force_cancel_orders {
         )
         .unwrap();
 
-        let mut orders = [Order::default(); 32];
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
         orders[0] = Order {
             market_index: 0,
             order_id: 1,
@@ -8049,10 +8247,11 @@ pub mod get_maker_orders_info {
     use crate::controller::position::PositionDirection;
     use crate::create_anchor_account_info;
     use crate::math::constants::{
-        AMM_RESERVE_PRECISION, BASE_PRECISION_I64, BASE_PRECISION_U64, PEG_PRECISION,
-        PRICE_PRECISION_I64, PRICE_PRECISION_U64, SPOT_BALANCE_PRECISION_U64,
+        AMM_RESERVE_PRECISION, BASE_PRECISION_I64, BASE_PRECISION_U64, MAX_USER_ORDERS,
+        PEG_PRECISION, PRICE_PRECISION_I64, PRICE_PRECISION_U64, SPOT_BALANCE_PRECISION_U64,
         SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
+    use crate::state::market_whitelist::MarketWhitelistEntry;
     use crate::state::oracle::HistoricalOracleData;
     use crate::state::oracle::OracleSource;
     use crate::state::perp_market::{PerpMarket, AMM};
@@ -8189,7 +8388,7 @@ pub mod get_maker_orders_info {
             ..User::default()
         };
 
-        let mut maker_orders = [Order::default(); 32];
+        let mut maker_orders = [Order::default(); MAX_USER_ORDERS];
         maker_orders[0] = Order {
             market_index: 0,
             order_id: 1,
@@ -8250,7 +8449,9 @@ pub mod get_maker_orders_info {
             &filler_key,
             0,
             oracle_price,
+            true,
             None,
+            &[],
             clock.unix_timestamp,
             clock.slot,
         )
@@ -8263,13 +8464,13 @@ pub mod get_maker_orders_info {
     }
 
     #[test]
-    fn one_maker_order_canceled_for_being_expired() {
+    fn protected_maker_skipped_for_stale_oracle() {
         let clock = Clock {
             slot: 56,
             epoch_start_timestamp: 0,
             epoch: 0,
             leader_schedule_epoch: 0,
-            unix_timestamp: 6,
+            unix_timestamp: 0,
         };
 
         let mut pyth_price = get_pyth_price(100, 6);
@@ -8364,7 +8565,7 @@ pub mod get_maker_orders_info {
                 auction_start_price: 0,
                 auction_end_price: 50 * PRICE_PRECISION_I64,
                 auction_duration: 5,
-                price: 100 * PRICE_PRECISION_U64,
+                price: 50 * PRICE_PRECISION_U64,
                 ..Order::default()
             }),
             perp_positions: get_positions(PerpPosition {
@@ -8382,7 +8583,7 @@ pub mod get_maker_orders_info {
             ..User::default()
         };
 
-        let mut maker_orders = [Order::default(); 32];
+        let mut maker_orders = [Order::default(); MAX_USER_ORDERS];
         maker_orders[0] = Order {
             market_index: 0,
             order_id: 1,
@@ -8391,8 +8592,7 @@ pub mod get_maker_orders_info {
             direction: PositionDirection::Short,
             base_asset_amount: BASE_PRECISION_U64,
             slot: 0,
-            price: 100 * PRICE_PRECISION_U64,
-            max_ts: 1,
+            price: 50 * PRICE_PRECISION_U64,
             post_only: true,
             ..Order::default()
         };
@@ -8423,6 +8623,7 @@ pub mod get_maker_orders_info {
                 scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
                 ..SpotPosition::default()
             }),
+            is_protected_maker: true,
             ..User::default()
         };
         let maker_key = Pubkey::from_str("My11111111111111111111111111111111111111113").unwrap();
@@ -8444,7 +8645,31 @@ pub mod get_maker_orders_info {
             &filler_key,
             0,
             oracle_price,
+            false,
+            None,
+            &[],
+            clock.unix_timestamp,
+            clock.slot,
+        )
+        .unwrap();
+
+        assert_eq!(maker_order_price_and_indexes, vec![]);
+
+        // once the oracle is valid the maker is matched at its cushioned price
+        let maker_order_price_and_indexes = get_maker_orders_info(
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &makers_and_referrers,
+            &taker_key,
+            &user.orders[0],
+            &mut Some(&mut filler),
+            &filler_key,
+            0,
+            oracle_price,
+            true,
             None,
+            &[],
             clock.unix_timestamp,
             clock.slot,
         )
@@ -8452,14 +8677,14 @@ pub mod get_maker_orders_info {
 
         assert_eq!(
             maker_order_price_and_indexes,
-            vec![(maker_key, 1, 100 * PRICE_PRECISION_U64)]
+            vec![(maker_key, 1, 100_100_000)]
         );
     }
 
     #[test]
-    fn one_maker_order_canceled_for_being_reduce_only() {
+    fn maker_skipped_without_market_whitelist_entry() {
         let clock = Clock {
-            slot: 6,
+            slot: 56,
             epoch_start_timestamp: 0,
             epoch: 0,
             leader_schedule_epoch: 0,
@@ -8509,6 +8734,7 @@ pub mod get_maker_orders_info {
             ..PerpMarket::default()
         };
         market.status = MarketStatus::Active;
+        market.amm.whitelist_enabled = true;
         market.amm.max_base_asset_reserve = u128::MAX;
         market.amm.min_base_asset_reserve = 0;
         let (new_ask_base_asset_reserve, new_ask_quote_asset_reserve) =
@@ -8558,8 +8784,7 @@ pub mod get_maker_orders_info {
                 auction_start_price: 0,
                 auction_end_price: 50 * PRICE_PRECISION_I64,
                 auction_duration: 5,
-                price: 100 * PRICE_PRECISION_U64,
-                max_ts: 1,
+                price: 50 * PRICE_PRECISION_U64,
                 ..Order::default()
             }),
             perp_positions: get_positions(PerpPosition {
@@ -8577,7 +8802,7 @@ pub mod get_maker_orders_info {
             ..User::default()
         };
 
-        let mut maker_orders = [Order::default(); 32];
+        let mut maker_orders = [Order::default(); MAX_USER_ORDERS];
         maker_orders[0] = Order {
             market_index: 0,
             order_id: 1,
@@ -8586,18 +8811,30 @@ pub mod get_maker_orders_info {
             direction: PositionDirection::Short,
             base_asset_amount: BASE_PRECISION_U64,
             slot: 0,
+            price: 50 * PRICE_PRECISION_U64,
+            post_only: true,
+            ..Order::default()
+        };
+        maker_orders[1] = Order {
+            market_index: 0,
+            order_id: 2,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            slot: 0,
             price: 100 * PRICE_PRECISION_U64,
-            reduce_only: true,
+            post_only: true,
             ..Order::default()
         };
 
+        let maker_authority = Pubkey::new_unique();
         let mut maker = User {
             orders: maker_orders,
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
-                base_asset_amount: -BASE_PRECISION_I64,
-                open_orders: 1,
-                open_asks: -BASE_PRECISION_I64,
+                open_orders: 2,
+                open_asks: -2 * BASE_PRECISION_I64,
                 ..PerpPosition::default()
             }),
             spot_positions: get_spot_positions(SpotPosition {
@@ -8606,6 +8843,7 @@ pub mod get_maker_orders_info {
                 scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
                 ..SpotPosition::default()
             }),
+            authority: maker_authority,
             ..User::default()
         };
         let maker_key = Pubkey::from_str("My11111111111111111111111111111111111111113").unwrap();
@@ -8627,23 +8865,55 @@ pub mod get_maker_orders_info {
             &filler_key,
             0,
             oracle_price,
+            true,
             None,
+            &[],
             clock.unix_timestamp,
             clock.slot,
         )
         .unwrap();
 
-        assert_eq!(maker_order_price_and_indexes, vec![],);
+        assert_eq!(maker_order_price_and_indexes, vec![]);
+
+        // the maker is matched once its entry is passed
+        let market_whitelist_entry = MarketWhitelistEntry {
+            authority: maker_authority,
+            market_index: 0,
+            ..MarketWhitelistEntry::default()
+        };
+        let maker_order_price_and_indexes = get_maker_orders_info(
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &makers_and_referrers,
+            &taker_key,
+            &user.orders[0],
+            &mut Some(&mut filler),
+            &filler_key,
+            0,
+            oracle_price,
+            true,
+            None,
+            &[market_whitelist_entry],
+            clock.unix_timestamp,
+            clock.slot,
+        )
+        .unwrap();
+
+        assert_eq!(
+            maker_order_price_and_indexes,
+            vec![(maker_key, 1, 100 * PRICE_PRECISION_U64)]
+        );
     }
 
     #[test]
-    fn two_makers() {
+    fn one_maker_order_canceled_for_being_expired() {
         let clock = Clock {
-            slot: 6,
+            slot: 56,
             epoch_start_timestamp: 0,
             epoch: 0,
             leader_schedule_epoch: 0,
-            unix_timestamp: 0,
+            unix_timestamp: 6,
         };
 
         let mut pyth_price = get_pyth_price(100, 6);
@@ -8739,7 +9009,6 @@ pub mod get_maker_orders_info {
                 auction_end_price: 50 * PRICE_PRECISION_I64,
                 auction_duration: 5,
                 price: 100 * PRICE_PRECISION_U64,
-                max_ts: 1,
                 ..Order::default()
             }),
             perp_positions: get_positions(PerpPosition {
@@ -8757,81 +9026,39 @@ pub mod get_maker_orders_info {
             ..User::default()
         };
 
-        let mut first_maker = User {
-            orders: get_orders!(
-                Order {
-                    market_index: 0,
-                    order_id: 1,
-                    status: OrderStatus::Open,
-                    order_type: OrderType::Limit,
-                    direction: PositionDirection::Short,
-                    base_asset_amount: BASE_PRECISION_U64,
-                    slot: 0,
-                    price: 100 * PRICE_PRECISION_U64,
-                    ..Order::default()
-                },
-                Order {
-                    market_index: 0,
-                    order_id: 1,
-                    status: OrderStatus::Open,
-                    order_type: OrderType::Limit,
-                    direction: PositionDirection::Short,
-                    base_asset_amount: BASE_PRECISION_U64,
-                    slot: 0,
-                    price: 102 * PRICE_PRECISION_U64,
-                    ..Order::default()
-                }
-            ),
-            perp_positions: get_positions(PerpPosition {
-                market_index: 0,
-                open_orders: 2,
-                ..PerpPosition::default()
-            }),
-            spot_positions: get_spot_positions(SpotPosition {
-                market_index: 0,
-                balance_type: SpotBalanceType::Deposit,
-                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
-                ..SpotPosition::default()
-            }),
-            ..User::default()
+        let mut maker_orders = [Order::default(); MAX_USER_ORDERS];
+        maker_orders[0] = Order {
+            market_index: 0,
+            order_id: 1,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            slot: 0,
+            price: 100 * PRICE_PRECISION_U64,
+            max_ts: 1,
+            post_only: true,
+            ..Order::default()
+        };
+        maker_orders[1] = Order {
+            market_index: 0,
+            order_id: 2,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            slot: 0,
+            price: 100 * PRICE_PRECISION_U64,
+            post_only: true,
+            ..Order::default()
         };
-        let first_maker_key =
-            Pubkey::from_str("My11111111111111111111111111111111111111113").unwrap();
-        create_anchor_account_info!(
-            first_maker,
-            &first_maker_key,
-            User,
-            first_maker_account_info
-        );
 
-        let mut second_maker = User {
-            orders: get_orders!(
-                Order {
-                    market_index: 0,
-                    order_id: 1,
-                    status: OrderStatus::Open,
-                    order_type: OrderType::Limit,
-                    direction: PositionDirection::Short,
-                    base_asset_amount: BASE_PRECISION_U64,
-                    slot: 0,
-                    price: 101 * PRICE_PRECISION_U64,
-                    ..Order::default()
-                },
-                Order {
-                    market_index: 0,
-                    order_id: 1,
-                    status: OrderStatus::Open,
-                    order_type: OrderType::Limit,
-                    direction: PositionDirection::Short,
-                    base_asset_amount: BASE_PRECISION_U64,
-                    slot: 0,
-                    price: 103 * PRICE_PRECISION_U64,
-                    ..Order::default()
-                }
-            ),
+        let mut maker = User {
+            orders: maker_orders,
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 open_orders: 2,
+                open_asks: -2 * BASE_PRECISION_I64,
                 ..PerpPosition::default()
             }),
             spot_positions: get_spot_positions(SpotPosition {
@@ -8842,22 +9069,10 @@ pub mod get_maker_orders_info {
             }),
             ..User::default()
         };
-        let second_maker_key =
-            Pubkey::from_str("My11111111111111111111111111111111111111112").unwrap();
-        create_anchor_account_info!(
-            second_maker,
-            &second_maker_key,
-            User,
-            second_maker_account_info
-        );
+        let maker_key = Pubkey::from_str("My11111111111111111111111111111111111111113").unwrap();
+        create_anchor_account_info!(maker, &maker_key, User, maker_account_info);
 
-        let mut makers_and_referrers = UserMap::load_one(&first_maker_account_info).unwrap();
-        makers_and_referrers
-            .insert(
-                second_maker_key,
-                AccountLoader::try_from(&second_maker_account_info).unwrap(),
-            )
-            .unwrap();
+        let makers_and_referrers = UserMap::load_one(&maker_account_info).unwrap();
 
         let filler_key = Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
         let mut filler = User::default();
@@ -8873,7 +9088,9 @@ pub mod get_maker_orders_info {
             &filler_key,
             0,
             oracle_price,
+            true,
             None,
+            &[],
             clock.unix_timestamp,
             clock.slot,
         )
@@ -8881,17 +9098,12 @@ pub mod get_maker_orders_info {
 
         assert_eq!(
             maker_order_price_and_indexes,
-            vec![
-                (first_maker_key, 0, 100000000),
-                (second_maker_key, 0, 101000000),
-                (first_maker_key, 1, 102000000),
-                (second_maker_key, 1, 103000000),
-            ],
+            vec![(maker_key, 1, 100 * PRICE_PRECISION_U64)]
         );
     }
 
     #[test]
-    fn jit_maker_order_id() {
+    fn one_maker_order_canceled_for_being_reduce_only() {
         let clock = Clock {
             slot: 6,
             epoch_start_timestamp: 0,
@@ -9011,34 +9223,27 @@ pub mod get_maker_orders_info {
             ..User::default()
         };
 
-        let mut first_maker = User {
-            orders: get_orders!(
-                Order {
-                    market_index: 0,
-                    order_id: 1,
-                    status: OrderStatus::Open,
-                    order_type: OrderType::Limit,
-                    direction: PositionDirection::Short,
-                    base_asset_amount: BASE_PRECISION_U64,
-                    slot: 0,
-                    price: 100 * PRICE_PRECISION_U64,
-                    ..Order::default()
-                },
-                Order {
-                    market_index: 0,
-                    order_id: 2,
-                    status: OrderStatus::Open,
-                    order_type: OrderType::Limit,
-                    direction: PositionDirection::Short,
-                    base_asset_amount: BASE_PRECISION_U64,
-                    slot: 0,
-                    price: 102 * PRICE_PRECISION_U64,
-                    ..Order::default()
-                }
-            ),
+        let mut maker_orders = [Order::default(); MAX_USER_ORDERS];
+        maker_orders[0] = Order {
+            market_index: 0,
+            order_id: 1,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            slot: 0,
+            price: 100 * PRICE_PRECISION_U64,
+            reduce_only: true,
+            ..Order::default()
+        };
+
+        let mut maker = User {
+            orders: maker_orders,
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
-                open_orders: 2,
+                base_asset_amount: -BASE_PRECISION_I64,
+                open_orders: 1,
+                open_asks: -BASE_PRECISION_I64,
                 ..PerpPosition::default()
             }),
             spot_positions: get_spot_positions(SpotPosition {
@@ -9049,16 +9254,10 @@ pub mod get_maker_orders_info {
             }),
             ..User::default()
         };
-        let first_maker_key =
-            Pubkey::from_str("My11111111111111111111111111111111111111113").unwrap();
-        create_anchor_account_info!(
-            first_maker,
-            &first_maker_key,
-            User,
-            first_maker_account_info
-        );
+        let maker_key = Pubkey::from_str("My11111111111111111111111111111111111111113").unwrap();
+        create_anchor_account_info!(maker, &maker_key, User, maker_account_info);
 
-        let makers_and_referrers = UserMap::load_one(&first_maker_account_info).unwrap();
+        let makers_and_referrers = UserMap::load_one(&maker_account_info).unwrap();
 
         let filler_key = Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
         let mut filler = User::default();
@@ -9074,20 +9273,19 @@ pub mod get_maker_orders_info {
             &filler_key,
             0,
             oracle_price,
-            Some(2),
+            true,
+            None,
+            &[],
             clock.unix_timestamp,
             clock.slot,
         )
         .unwrap();
 
-        assert_eq!(
-            maker_order_price_and_indexes,
-            vec![(first_maker_key, 1, 102000000),],
-        );
+        assert_eq!(maker_order_price_and_indexes, vec![],);
     }
 
     #[test]
-    fn two_makers_with_max_orders() {
+    fn two_makers() {
         let clock = Clock {
             slot: 6,
             epoch_start_timestamp: 0,
@@ -9208,17 +9406,30 @@ pub mod get_maker_orders_info {
         };
 
         let mut first_maker = User {
-            orders: [Order {
-                market_index: 0,
-                order_id: 1,
-                status: OrderStatus::Open,
-                order_type: OrderType::Limit,
-                direction: PositionDirection::Short,
-                base_asset_amount: BASE_PRECISION_U64,
-                slot: 0,
-                price: 100 * PRICE_PRECISION_U64,
-                ..Order::default()
-            }; 32],
+            orders: get_orders!(
+                Order {
+                    market_index: 0,
+                    order_id: 1,
+                    status: OrderStatus::Open,
+                    order_type: OrderType::Limit,
+                    direction: PositionDirection::Short,
+                    base_asset_amount: BASE_PRECISION_U64,
+                    slot: 0,
+                    price: 100 * PRICE_PRECISION_U64,
+                    ..Order::default()
+                },
+                Order {
+                    market_index: 0,
+                    order_id: 1,
+                    status: OrderStatus::Open,
+                    order_type: OrderType::Limit,
+                    direction: PositionDirection::Short,
+                    base_asset_amount: BASE_PRECISION_U64,
+                    slot: 0,
+                    price: 102 * PRICE_PRECISION_U64,
+                    ..Order::default()
+                }
+            ),
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 open_orders: 2,
@@ -9242,17 +9453,30 @@ pub mod get_maker_orders_info {
         );
 
         let mut second_maker = User {
-            orders: [Order {
-                market_index: 0,
-                order_id: 1,
-                status: OrderStatus::Open,
-                order_type: OrderType::Limit,
-                direction: PositionDirection::Short,
-                base_asset_amount: BASE_PRECISION_U64,
-                slot: 0,
-                price: 101 * PRICE_PRECISION_U64,
-                ..Order::default()
-            }; 32],
+            orders: get_orders!(
+                Order {
+                    market_index: 0,
+                    order_id: 1,
+                    status: OrderStatus::Open,
+                    order_type: OrderType::Limit,
+                    direction: PositionDirection::Short,
+                    base_asset_amount: BASE_PRECISION_U64,
+                    slot: 0,
+                    price: 101 * PRICE_PRECISION_U64,
+                    ..Order::default()
+                },
+                Order {
+                    market_index: 0,
+                    order_id: 1,
+                    status: OrderStatus::Open,
+                    order_type: OrderType::Limit,
+                    direction: PositionDirection::Short,
+                    base_asset_amount: BASE_PRECISION_U64,
+                    slot: 0,
+                    price: 103 * PRICE_PRECISION_U64,
+                    ..Order::default()
+                }
+            ),
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 open_orders: 2,
@@ -9297,12 +9521,2010 @@ pub mod get_maker_orders_info {
             &filler_key,
             0,
             oracle_price,
+            true,
             None,
+            &[],
             clock.unix_timestamp,
             clock.slot,
         )
         .unwrap();
 
-        assert_eq!(maker_order_price_and_indexes.len(), 64);
+        assert_eq!(
+            maker_order_price_and_indexes,
+            vec![
+                (first_maker_key, 0, 100000000),
+                (second_maker_key, 0, 101000000),
+                (first_maker_key, 1, 102000000),
+                (second_maker_key, 1, 103000000),
+            ],
+        );
+    }
+
+    #[test]
+    fn jit_maker_order_id() {
+        let clock = Clock {
+            slot: 6,
+            epoch_start_timestamp: 0,
+            epoch: 0,
+            leader_schedule_epoch: 0,
+            unix_timestamp: 0,
+        };
+
+        let mut pyth_price = get_pyth_price(100, 6);
+        let oracle_price = 100 * PRICE_PRECISION_I64;
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            pyth_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, clock.slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                terminal_quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 100,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 1000,
+                order_tick_size: 1,
+                oracle: oracle_price_key,
+                max_spread: 1000,
+                base_spread: 0,
+                long_spread: 0,
+                short_spread: 0,
+                historical_oracle_data: HistoricalOracleData {
+                    last_oracle_price_twap: pyth_price.twap as i64,
+                    last_oracle_price_twap_5min: pyth_price.twap as i64,
+                    last_oracle_price: pyth_price.agg.price as i64,
+                    ..HistoricalOracleData::default()
+                },
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            ..PerpMarket::default()
+        };
+        market.status = MarketStatus::Active;
+        market.amm.max_base_asset_reserve = u128::MAX;
+        market.amm.min_base_asset_reserve = 0;
+        let (new_ask_base_asset_reserve, new_ask_quote_asset_reserve) =
+            crate::math::amm_spread::calculate_spread_reserves(
+                &market.amm,
+                PositionDirection::Long,
+            )
+            .unwrap();
+        let (new_bid_base_asset_reserve, new_bid_quote_asset_reserve) =
+            crate::math::amm_spread::calculate_spread_reserves(
+                &market.amm,
+                PositionDirection::Short,
+            )
+            .unwrap();
+        market.amm.ask_base_asset_reserve = new_ask_base_asset_reserve;
+        market.amm.bid_base_asset_reserve = new_bid_base_asset_reserve;
+        market.amm.ask_quote_asset_reserve = new_ask_quote_asset_reserve;
+        market.amm.bid_quote_asset_reserve = new_bid_quote_asset_reserve;
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        let taker_key = Pubkey::default();
+        let taker_authority =
+            Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
+        let user = User {
+            authority: taker_authority,
+            orders: get_orders(Order {
+                market_index: 0,
+                order_id: 1,
+                status: OrderStatus::Open,
+                order_type: OrderType::Market,
+                direction: PositionDirection::Long,
+                base_asset_amount: BASE_PRECISION_U64,
+                slot: 0,
+                auction_start_price: 0,
+                auction_end_price: 50 * PRICE_PRECISION_I64,
+                auction_duration: 5,
+                price: 100 * PRICE_PRECISION_U64,
+                max_ts: 1,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 1,
+                open_bids: BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let mut first_maker = User {
+            orders: get_orders!(
+                Order {
+                    market_index: 0,
+                    order_id: 1,
+                    status: OrderStatus::Open,
+                    order_type: OrderType::Limit,
+                    direction: PositionDirection::Short,
+                    base_asset_amount: BASE_PRECISION_U64,
+                    slot: 0,
+                    price: 100 * PRICE_PRECISION_U64,
+                    ..Order::default()
+                },
+                Order {
+                    market_index: 0,
+                    order_id: 2,
+                    status: OrderStatus::Open,
+                    order_type: OrderType::Limit,
+                    direction: PositionDirection::Short,
+                    base_asset_amount: BASE_PRECISION_U64,
+                    slot: 0,
+                    price: 102 * PRICE_PRECISION_U64,
+                    ..Order::default()
+                }
+            ),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 2,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+        let first_maker_key =
+            Pubkey::from_str("My11111111111111111111111111111111111111113").unwrap();
+        create_anchor_account_info!(
+            first_maker,
+            &first_maker_key,
+            User,
+            first_maker_account_info
+        );
+
+        let makers_and_referrers = UserMap::load_one(&first_maker_account_info).unwrap();
+
+        let filler_key = Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
+        let mut filler = User::default();
+
+        let maker_order_price_and_indexes = get_maker_orders_info(
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &makers_and_referrers,
+            &taker_key,
+            &user.orders[0],
+            &mut Some(&mut filler),
+            &filler_key,
+            0,
+            oracle_price,
+            true,
+            Some(2),
+            &[],
+            clock.unix_timestamp,
+            clock.slot,
+        )
+        .unwrap();
+
+        assert_eq!(
+            maker_order_price_and_indexes,
+            vec![(first_maker_key, 1, 102000000),],
+        );
+    }
+
+    #[test]
+    fn two_makers_with_max_orders() {
+        let clock = Clock {
+            slot: 6,
+            epoch_start_timestamp: 0,
+            epoch: 0,
+            leader_schedule_epoch: 0,
+            unix_timestamp: 0,
+        };
+
+        let mut pyth_price = get_pyth_price(100, 6);
+        let oracle_price = 100 * PRICE_PRECISION_I64;
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            pyth_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, clock.slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                terminal_quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 100,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 1000,
+                order_tick_size: 1,
+                oracle: oracle_price_key,
+                max_spread: 1000,
+                base_spread: 0,
+                long_spread: 0,
+                short_spread: 0,
+                historical_oracle_data: HistoricalOracleData {
+                    last_oracle_price_twap: pyth_price.twap as i64,
+                    last_oracle_price_twap_5min: pyth_price.twap as i64,
+                    last_oracle_price: pyth_price.agg.price as i64,
+                    ..HistoricalOracleData::default()
+                },
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            ..PerpMarket::default()
+        };
+        market.status = MarketStatus::Active;
+        market.amm.max_base_asset_reserve = u128::MAX;
+        market.amm.min_base_asset_reserve = 0;
+        let (new_ask_base_asset_reserve, new_ask_quote_asset_reserve) =
+            crate::math::amm_spread::calculate_spread_reserves(
+                &market.amm,
+                PositionDirection::Long,
+            )
+            .unwrap();
+        let (new_bid_base_asset_reserve, new_bid_quote_asset_reserve) =
+            crate::math::amm_spread::calculate_spread_reserves(
+                &market.amm,
+                PositionDirection::Short,
+            )
+            .unwrap();
+        market.amm.ask_base_asset_reserve = new_ask_base_asset_reserve;
+        market.amm.bid_base_asset_reserve = new_bid_base_asset_reserve;
+        market.amm.ask_quote_asset_reserve = new_ask_quote_asset_reserve;
+        market.amm.bid_quote_asset_reserve = new_bid_quote_asset_reserve;
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        let taker_key = Pubkey::default();
+        let taker_authority =
+            Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
+        let user = User {
+            authority: taker_authority,
+            orders: get_orders(Order {
+                market_index: 0,
+                order_id: 1,
+                status: OrderStatus::Open,
+                order_type: OrderType::Market,
+                direction: PositionDirection::Long,
+                base_asset_amount: BASE_PRECISION_U64,
+                slot: 0,
+                auction_start_price: 0,
+                auction_end_price: 50 * PRICE_PRECISION_I64,
+                auction_duration: 5,
+                price: 100 * PRICE_PRECISION_U64,
+                max_ts: 1,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 1,
+                open_bids: BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let mut first_maker = User {
+            orders: [Order {
+                market_index: 0,
+                order_id: 1,
+                status: OrderStatus::Open,
+                order_type: OrderType::Limit,
+                direction: PositionDirection::Short,
+                base_asset_amount: BASE_PRECISION_U64,
+                slot: 0,
+                price: 100 * PRICE_PRECISION_U64,
+                ..Order::default()
+            }; 32],
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 2,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+        let first_maker_key =
+            Pubkey::from_str("My11111111111111111111111111111111111111113").unwrap();
+        create_anchor_account_info!(
+            first_maker,
+            &first_maker_key,
+            User,
+            first_maker_account_info
+        );
+
+        let mut second_maker = User {
+            orders: [Order {
+                market_index: 0,
+                order_id: 1,
+                status: OrderStatus::Open,
+                order_type: OrderType::Limit,
+                direction: PositionDirection::Short,
+                base_asset_amount: BASE_PRECISION_U64,
+                slot: 0,
+                price: 101 * PRICE_PRECISION_U64,
+                ..Order::default()
+            }; 32],
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 2,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+        let second_maker_key =
+            Pubkey::from_str("My11111111111111111111111111111111111111112").unwrap();
+        create_anchor_account_info!(
+            second_maker,
+            &second_maker_key,
+            User,
+            second_maker_account_info
+        );
+
+        let mut makers_and_referrers = UserMap::load_one(&first_maker_account_info).unwrap();
+        makers_and_referrers
+            .insert(
+                second_maker_key,
+                AccountLoader::try_from(&second_maker_account_info).unwrap(),
+            )
+            .unwrap();
+
+        let filler_key = Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
+        let mut filler = User::default();
+
+        let maker_order_price_and_indexes = get_maker_orders_info(
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &makers_and_referrers,
+            &taker_key,
+            &user.orders[0],
+            &mut Some(&mut filler),
+            &filler_key,
+            0,
+            oracle_price,
+            true,
+            None,
+            &[],
+            clock.unix_timestamp,
+            clock.slot,
+        )
+        .unwrap();
+
+        assert_eq!(maker_order_price_and_indexes.len(), 64);
+    }
+}
+
+pub mod cancel_linked_orders {
+    use std::str::FromStr;
+
+    use crate::controller::orders::{cancel_linked_orders, cancel_order, link_orders};
+    use crate::controller::position::PositionDirection;
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::math::constants::{
+        BASE_PRECISION_I64, BASE_PRECISION_U64, MAX_USER_ORDERS, PRICE_PRECISION_U64,
+    };
+    use crate::state::events::OrderActionExplanation;
+    use crate::state::perp_market::{PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::user::{MarketType, OrderStatus, OrderTriggerCondition, OrderType, User};
+    use crate::test_utils::*;
+    use crate::test_utils::{create_account_info, get_positions, get_pyth_price};
+
+    use super::*;
+
+    fn get_bracket_orders() -> [Order; MAX_USER_ORDERS] {
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
+        orders[0] = Order {
+            market_index: 0,
+            order_id: 1,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            price: 110 * PRICE_PRECISION_U64,
+            reduce_only: true,
+            ..Order::default()
+        };
+        orders[1] = Order {
+            market_index: 0,
+            order_id: 2,
+            status: OrderStatus::Open,
+            order_type: OrderType::TriggerMarket,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            trigger_price: 90 * PRICE_PRECISION_U64,
+            trigger_condition: OrderTriggerCondition::Below,
+            reduce_only: true,
+            ..Order::default()
+        };
+        orders
+    }
+
+    fn get_user() -> User {
+        User {
+            orders: get_bracket_orders(),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: BASE_PRECISION_I64,
+                open_orders: 2,
+                open_asks: -BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            ..User::default()
+        }
+    }
+
+    #[test]
+    fn link_orders_validation() {
+        let mut user = get_user();
+
+        assert!(link_orders(&mut user, 1, 1).is_err());
+        assert!(link_orders(&mut user, 1, 3).is_err());
+
+        link_orders(&mut user, 1, 2).unwrap();
+        assert_eq!(user.orders[0].linked_order_id, 2);
+        assert_eq!(user.orders[1].linked_order_id, 1);
+
+        // already linked
+        assert!(link_orders(&mut user, 2, 1).is_err());
+
+        let mut user = get_user();
+        user.orders[0].base_asset_amount_filled = BASE_PRECISION_U64 / 2;
+        assert!(link_orders(&mut user, 1, 2).is_err());
+
+        let mut user = get_user();
+        user.orders[1].market_index = 1;
+        assert!(link_orders(&mut user, 1, 2).is_err());
+    }
+
+    #[test]
+    fn cancel_when_linked_order_executes() {
+        let slot = 0;
+        let now = 0;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                oracle: oracle_price_key,
+                ..AMM::default()
+            },
+            status: MarketStatus::Active,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+        let spot_market_map = SpotMarketMap::load_multiple(vec![], true).unwrap();
+
+        let user_key = Pubkey::default();
+
+        // nothing executed, nothing canceled
+        let mut user = get_user();
+        link_orders(&mut user, 1, 2).unwrap();
+        cancel_linked_orders(
+            &mut user,
+            &user_key,
+            None,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+        )
+        .unwrap();
+        assert_eq!(user.orders[0].status, OrderStatus::Open);
+        assert_eq!(user.orders[1].status, OrderStatus::Open);
+
+        // take profit partially fills, stop loss canceled
+        user.orders[0].base_asset_amount_filled = BASE_PRECISION_U64 / 2;
+        cancel_linked_orders(
+            &mut user,
+            &user_key,
+            None,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+        )
+        .unwrap();
+        assert_eq!(user.orders[0].status, OrderStatus::Open);
+        assert_eq!(user.orders[0].linked_order_id, 0);
+        assert_eq!(user.orders[1], Order::default());
+        assert_eq!(user.perp_positions[0].open_orders, 1);
+
+        // stop loss triggers, take profit canceled
+        let mut user = get_user();
+        link_orders(&mut user, 1, 2).unwrap();
+        user.orders[1].trigger_condition = OrderTriggerCondition::TriggeredBelow;
+        cancel_linked_orders(
+            &mut user,
+            &user_key,
+            None,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+        )
+        .unwrap();
+        assert_eq!(user.orders[0], Order::default());
+        assert_eq!(user.orders[1].status, OrderStatus::Open);
+
+        // take profit fully filled and reset, stop loss canceled
+        let mut user = get_user();
+        link_orders(&mut user, 1, 2).unwrap();
+        user.orders[0] = Order::default();
+        cancel_linked_orders(
+            &mut user,
+            &user_key,
+            None,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+        )
+        .unwrap();
+        assert_eq!(user.orders[1], Order::default());
+
+        // canceling an unexecuted leg unlinks the other
+        let mut user = get_user();
+        link_orders(&mut user, 1, 2).unwrap();
+        cancel_order(
+            0,
+            &mut user,
+            &user_key,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+            OrderActionExplanation::None,
+            None,
+            0,
+            false,
+        )
+        .unwrap();
+        assert_eq!(user.orders[1].status, OrderStatus::Open);
+        assert_eq!(user.orders[1].linked_order_id, 0);
+        cancel_linked_orders(
+            &mut user,
+            &user_key,
+            None,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+        )
+        .unwrap();
+        assert_eq!(user.orders[1].status, OrderStatus::Open);
+    }
+}
+
+pub mod cancel_orders_with_filter {
+    use std::str::FromStr;
+
+    use crate::controller::orders::cancel_orders_with_filter;
+    use crate::controller::position::PositionDirection;
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::instructions::CancelOrdersFilter;
+    use crate::math::constants::{
+        BASE_PRECISION_I64, BASE_PRECISION_U64, MAX_USER_ORDERS, PRICE_PRECISION_U64,
+    };
+    use crate::state::events::OrderActionExplanation;
+    use crate::state::perp_market::{PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::user::{MarketType, OrderStatus, OrderTriggerCondition, OrderType, User};
+    use crate::test_utils::*;
+    use crate::test_utils::{create_account_info, get_positions, get_pyth_price};
+
+    use super::*;
+
+    fn get_user() -> User {
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
+        orders[0] = Order {
+            order_id: 1,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            price: 110 * PRICE_PRECISION_U64,
+            ..Order::default()
+        };
+        orders[1] = Order {
+            order_id: 2,
+            status: OrderStatus::Open,
+            order_type: OrderType::TriggerMarket,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            trigger_price: 90 * PRICE_PRECISION_U64,
+            trigger_condition: OrderTriggerCondition::Below,
+            ..Order::default()
+        };
+        orders[2] = Order {
+            order_id: 3,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            price: 120 * PRICE_PRECISION_U64,
+            ..Order::default()
+        };
+
+        User {
+            orders,
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 3,
+                open_asks: -3 * BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            ..User::default()
+        }
+    }
+
+    #[test]
+    fn filters() {
+        let slot = 0;
+        let now = 0;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                oracle: oracle_price_key,
+                ..AMM::default()
+            },
+            status: MarketStatus::Active,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+        let spot_market_map = SpotMarketMap::load_multiple(vec![], true).unwrap();
+
+        let user_key = Pubkey::default();
+
+        let mut user = get_user();
+        let canceled_order_ids = cancel_orders_with_filter(
+            &mut user,
+            &user_key,
+            None,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+            OrderActionExplanation::None,
+            Some(MarketType::Perp),
+            None,
+            None,
+            CancelOrdersFilter {
+                only_trigger_orders: true,
+                ..CancelOrdersFilter::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(canceled_order_ids, vec![2]);
+        assert_eq!(user.perp_positions[0].open_orders, 2);
+
+        let mut user = get_user();
+        let canceled_order_ids = cancel_orders_with_filter(
+            &mut user,
+            &user_key,
+            None,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+            OrderActionExplanation::None,
+            None,
+            None,
+            Some(PositionDirection::Short),
+            CancelOrdersFilter {
+                min_order_id: Some(2),
+                max_order_id: Some(3),
+                only_trigger_orders: false,
+            },
+        )
+        .unwrap();
+        assert_eq!(canceled_order_ids, vec![2, 3]);
+        assert_eq!(user.orders[0].status, OrderStatus::Open);
+
+        // spot filter leaves the perp orders alone
+        let mut user = get_user();
+        let canceled_order_ids = cancel_orders_with_filter(
+            &mut user,
+            &user_key,
+            None,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+            OrderActionExplanation::None,
+            Some(MarketType::Spot),
+            Some(0),
+            None,
+            CancelOrdersFilter::default(),
+        )
+        .unwrap();
+        assert!(canceled_order_ids.is_empty());
+    }
+}
+
+pub mod modify_order {
+    use std::str::FromStr;
+
+    use anchor_lang::prelude::{AccountLoader, Clock};
+
+    use crate::controller::orders::modify_order;
+    use crate::controller::position::PositionDirection;
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::error::ErrorCode;
+    use crate::instructions::{ModifyOrderParams, PostOnlyParam};
+    use crate::math::constants::{
+        AMM_RESERVE_PRECISION, BASE_PRECISION_I64, BASE_PRECISION_U64, PEG_PRECISION,
+        PRICE_PRECISION_U64, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
+        SPOT_WEIGHT_PRECISION,
+    };
+    use crate::state::oracle::{HistoricalOracleData, OracleSource};
+    use crate::state::perp_market::{PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::state::State;
+    use crate::state::user::{OrderStatus, OrderType, SpotPosition, User};
+    use crate::test_utils::*;
+    use crate::test_utils::{
+        create_account_info, get_orders, get_positions, get_pyth_price, get_spot_positions,
+    };
+
+    use super::*;
+
+    #[test]
+    fn modify_size_and_price() {
+        let clock = Clock {
+            slot: 56,
+            epoch_start_timestamp: 0,
+            epoch: 0,
+            leader_schedule_epoch: 0,
+            unix_timestamp: 0,
+        };
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, clock.slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                terminal_quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 100,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 1000,
+                order_tick_size: 1,
+                oracle: oracle_price_key,
+                historical_oracle_data: HistoricalOracleData {
+                    last_oracle_price_twap: oracle_price.twap as i64,
+                    last_oracle_price_twap_5min: oracle_price.twap as i64,
+                    last_oracle_price: oracle_price.agg.price as i64,
+                    ..HistoricalOracleData::default()
+                },
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Active,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        let mut user = User {
+            orders: get_orders(Order {
+                market_index: 0,
+                order_id: 1,
+                status: OrderStatus::Open,
+                order_type: OrderType::Limit,
+                direction: PositionDirection::Long,
+                base_asset_amount: 2 * BASE_PRECISION_U64,
+                slot: 0,
+                price: 50 * PRICE_PRECISION_U64,
+                post_only: true,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 1,
+                open_bids: 2 * BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            next_order_id: 2,
+            ..User::default()
+        };
+        create_anchor_account_info!(user, User, user_account_info);
+        let user_account_loader: AccountLoader<User> =
+            AccountLoader::try_from(&user_account_info).unwrap();
+
+        let state = State::default();
+
+        // can't shrink below what's already filled
+        user_account_loader.load_mut().unwrap().orders[0].base_asset_amount_filled =
+            BASE_PRECISION_U64 / 2;
+        let result = modify_order(
+            1,
+            &state,
+            &user_account_loader,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &clock,
+            ModifyOrderParams {
+                base_asset_amount: Some(BASE_PRECISION_U64 / 2),
+                ..ModifyOrderParams::default()
+            },
+        );
+        assert!(result.is_err());
+        user_account_loader.load_mut().unwrap().orders[0].base_asset_amount_filled = 0;
+
+        // shrinking in place still has to respect the market's min order size
+        market_map.get_ref_mut(&0).unwrap().amm.min_order_size = BASE_PRECISION_U64;
+        let result = modify_order(
+            1,
+            &state,
+            &user_account_loader,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &clock,
+            ModifyOrderParams {
+                base_asset_amount: Some(BASE_PRECISION_U64 / 2),
+                ..ModifyOrderParams::default()
+            },
+        );
+        assert_eq!(result, Err(ErrorCode::InvalidOrderMinOrderSize));
+
+        // shrinking at the same price updates the order in place
+        modify_order(
+            1,
+            &state,
+            &user_account_loader,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &clock,
+            ModifyOrderParams {
+                base_asset_amount: Some(BASE_PRECISION_U64),
+                ..ModifyOrderParams::default()
+            },
+        )
+        .unwrap();
+
+        {
+            let user = user_account_loader.load().unwrap();
+            assert_eq!(user.orders[0].order_id, 1);
+            assert_eq!(user.orders[0].slot, 0);
+            assert_eq!(user.orders[0].base_asset_amount, BASE_PRECISION_U64);
+            assert_eq!(user.perp_positions[0].open_bids, BASE_PRECISION_I64);
+            assert_eq!(user.perp_positions[0].open_orders, 1);
+            assert_eq!(user.next_order_id, 2);
+        }
+
+        // new price re-places the order with the same id
+        modify_order(
+            1,
+            &state,
+            &user_account_loader,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &clock,
+            ModifyOrderParams {
+                price: Some(45 * PRICE_PRECISION_U64),
+                post_only: Some(PostOnlyParam::None),
+                ..ModifyOrderParams::default()
+            },
+        )
+        .unwrap();
+
+        let user = user_account_loader.load().unwrap();
+        let order = user
+            .orders
+            .iter()
+            .find(|order| order.order_id == 1)
+            .unwrap();
+        assert_eq!(order.status, OrderStatus::Open);
+        assert_eq!(order.price, 45 * PRICE_PRECISION_U64);
+        assert_eq!(order.base_asset_amount, BASE_PRECISION_U64);
+        assert_eq!(order.slot, clock.slot);
+        assert!(!order.post_only);
+        assert_eq!(user.perp_positions[0].open_bids, BASE_PRECISION_I64);
+        assert_eq!(user.perp_positions[0].open_orders, 1);
+        assert_eq!(user.next_order_id, 2);
+    }
+}
+
+pub mod fill_rfq {
+    use std::str::FromStr;
+
+    use anchor_lang::prelude::{AccountLoader, Clock};
+
+    use crate::controller::orders::fill_rfq;
+    use crate::controller::position::PositionDirection;
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::error::ErrorCode;
+    use crate::instructions::{RfqMakerQuote, RfqTakerRequest};
+    use crate::math::constants::{
+        AMM_RESERVE_PRECISION, BASE_PRECISION_I64, BASE_PRECISION_U64, PEG_PRECISION,
+        PRICE_PRECISION_I64, PRICE_PRECISION_U64, SPOT_BALANCE_PRECISION_U64,
+        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+    };
+    use crate::state::oracle::{HistoricalOracleData, OracleSource};
+    use crate::state::perp_market::{PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::state::State;
+    use crate::state::user::{OrderStatus, SpotPosition, User, UserStats};
+    use crate::state::user_map::{UserMap, UserStatsMap};
+    use crate::test_utils::*;
+    use crate::test_utils::{
+        create_account_info, get_positions, get_pyth_price, get_spot_positions,
+    };
+
+    use super::*;
+
+    #[test]
+    fn quote_fills_taker_request() {
+        let clock = Clock {
+            slot: 6,
+            epoch_start_timestamp: 0,
+            epoch: 0,
+            leader_schedule_epoch: 0,
+            unix_timestamp: 0,
+        };
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, clock.slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                terminal_quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 100,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 1000,
+                order_tick_size: 1,
+                oracle: oracle_price_key,
+                max_spread: 1000,
+                base_spread: 0,
+                long_spread: 0,
+                short_spread: 0,
+                historical_oracle_data: HistoricalOracleData {
+                    last_oracle_price_twap: oracle_price.twap as i64,
+                    last_oracle_price_twap_5min: oracle_price.twap as i64,
+                    last_oracle_price: oracle_price.agg.price as i64,
+                    ..HistoricalOracleData::default()
+                },
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            ..PerpMarket::default()
+        };
+        market.status = MarketStatus::Active;
+        market.amm.max_base_asset_reserve = u128::MAX;
+        market.amm.min_base_asset_reserve = 0;
+        let (new_ask_base_asset_reserve, new_ask_quote_asset_reserve) =
+            crate::math::amm_spread::calculate_spread_reserves(
+                &market.amm,
+                PositionDirection::Long,
+            )
+            .unwrap();
+        let (new_bid_base_asset_reserve, new_bid_quote_asset_reserve) =
+            crate::math::amm_spread::calculate_spread_reserves(
+                &market.amm,
+                PositionDirection::Short,
+            )
+            .unwrap();
+        market.amm.ask_base_asset_reserve = new_ask_base_asset_reserve;
+        market.amm.bid_base_asset_reserve = new_bid_base_asset_reserve;
+        market.amm.ask_quote_asset_reserve = new_ask_quote_asset_reserve;
+        market.amm.bid_quote_asset_reserve = new_bid_quote_asset_reserve;
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        let mut taker = User {
+            authority: Pubkey::from_str("My11111111111111111111111111111111111111112").unwrap(),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+        create_anchor_account_info!(taker, User, taker_account_info);
+        let taker_account_loader: AccountLoader<User> =
+            AccountLoader::try_from(&taker_account_info).unwrap();
+        let taker_key = taker_account_loader.key();
+
+        create_anchor_account_info!(UserStats::default(), UserStats, taker_stats_account_info);
+        let taker_stats_account_loader: AccountLoader<UserStats> =
+            AccountLoader::try_from(&taker_stats_account_info).unwrap();
+
+        let maker_key = Pubkey::from_str("My11111111111111111111111111111111111111113").unwrap();
+        let maker_authority =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let mut maker = User {
+            authority: maker_authority,
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                ..PerpPosition::default()
+            }),
+            spot_positions: get_spot_positions(SpotPosition {
+                market_index: 0,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+                ..SpotPosition::default()
+            }),
+            ..User::default()
+        };
+        create_anchor_account_info!(maker, &maker_key, User, maker_account_info);
+        let makers = UserMap::load_one(&maker_account_info).unwrap();
+
+        let mut maker_stats = UserStats {
+            authority: maker_authority,
+            ..UserStats::default()
+        };
+        create_anchor_account_info!(maker_stats, UserStats, maker_stats_account_info);
+        let makers_stats = UserStatsMap::load_one(&maker_stats_account_info).unwrap();
+
+        let filler_key = Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
+        create_anchor_account_info!(User::default(), &filler_key, User, filler_account_info);
+        let filler_account_loader: AccountLoader<User> =
+            AccountLoader::try_from(&filler_account_info).unwrap();
+
+        create_anchor_account_info!(UserStats::default(), UserStats, filler_stats_account_info);
+        let filler_stats_account_loader: AccountLoader<UserStats> =
+            AccountLoader::try_from(&filler_stats_account_info).unwrap();
+
+        let state = State {
+            perp_fee_structure: get_fee_structure(),
+            ..State::default()
+        };
+
+        // taker will pay at most a dollar over the oracle
+        let taker_request = RfqTakerRequest {
+            market_index: 0,
+            direction: PositionDirection::Long,
+            base_asset_amount: BASE_PRECISION_U64,
+            oracle_price_offset: PRICE_PRECISION_I64 as i32,
+            ..RfqTakerRequest::default()
+        };
+
+        let quote = RfqMakerQuote {
+            maker: maker_key,
+            taker: taker_key,
+            market_index: 0,
+            direction: PositionDirection::Short,
+            base_asset_amount: 2 * BASE_PRECISION_U64,
+            price: 100_500_000,
+            ..RfqMakerQuote::default()
+        };
+
+        // quote worse than the taker's limit price
+        let result = fill_rfq(
+            &state,
+            &taker_account_loader,
+            &taker_stats_account_loader,
+            &taker_request,
+            &[RfqMakerQuote {
+                price: 102 * PRICE_PRECISION_U64,
+                ..quote
+            }],
+            &makers,
+            &makers_stats,
+            &filler_account_loader,
+            &filler_stats_account_loader,
+            &spot_market_map,
+            &market_map,
+            &mut oracle_map,
+            &clock,
+            None,
+        );
+        assert_eq!(result, Err(ErrorCode::InvalidRfqQuote));
+
+        // quote signed for a different taker
+        let result = fill_rfq(
+            &state,
+            &taker_account_loader,
+            &taker_stats_account_loader,
+            &taker_request,
+            &[RfqMakerQuote {
+                taker: filler_key,
+                ..quote
+            }],
+            &makers,
+            &makers_stats,
+            &filler_account_loader,
+            &filler_stats_account_loader,
+            &spot_market_map,
+            &market_map,
+            &mut oracle_map,
+            &clock,
+            None,
+        );
+        assert_eq!(result, Err(ErrorCode::InvalidRfqQuote));
+
+        let base_asset_amount = fill_rfq(
+            &state,
+            &taker_account_loader,
+            &taker_stats_account_loader,
+            &taker_request,
+            &[quote],
+            &makers,
+            &makers_stats,
+            &filler_account_loader,
+            &filler_stats_account_loader,
+            &spot_market_map,
+            &market_map,
+            &mut oracle_map,
+            &clock,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(base_asset_amount, BASE_PRECISION_U64);
+
+        let taker = taker_account_loader.load().unwrap();
+        assert_eq!(
+            taker.perp_positions[0].base_asset_amount,
+            BASE_PRECISION_I64
+        );
+        assert_eq!(taker.perp_positions[0].quote_entry_amount, -100_500_000);
+        assert_eq!(taker.perp_positions[0].open_orders, 0);
+        assert_eq!(taker.perp_positions[0].open_bids, 0);
+        assert_eq!(taker.orders[0].status, OrderStatus::Init);
+
+        // rest of the quote isn't left resting on the book
+        let maker = makers.get_ref(&maker_key).unwrap();
+        assert_eq!(
+            maker.perp_positions[0].base_asset_amount,
+            -BASE_PRECISION_I64
+        );
+        assert_eq!(maker.perp_positions[0].quote_entry_amount, 100_500_000);
+        assert_eq!(maker.perp_positions[0].open_orders, 0);
+        assert_eq!(maker.perp_positions[0].open_asks, 0);
+        assert_eq!(maker.orders[0].status, OrderStatus::Init);
+    }
+}
+
+pub mod extend_order_auction {
+    use crate::controller::orders::extend_order_auction;
+    use crate::controller::position::PositionDirection;
+    use crate::math::constants::{PERCENTAGE_PRECISION, PRICE_PRECISION_I64, PRICE_PRECISION_U64};
+    use crate::state::user::{Order, OrderStatus, OrderType};
+
+    #[test]
+    fn unfilled_market_order() {
+        let price_buffer = (PERCENTAGE_PRECISION / 100) as u32; // 1%
+
+        let mut order = Order {
+            status: OrderStatus::Open,
+            order_type: OrderType::Market,
+            direction: PositionDirection::Long,
+            slot: 0,
+            auction_duration: 10,
+            auction_start_price: 99 * PRICE_PRECISION_I64,
+            auction_end_price: 100 * PRICE_PRECISION_I64,
+            ..Order::default()
+        };
+
+        assert!(!order.is_eligible_for_auction_extension(10).unwrap());
+        assert!(order.is_eligible_for_auction_extension(11).unwrap());
+
+        let extended = extend_order_auction(&mut order, 5, price_buffer, 11).unwrap();
+        assert!(extended);
+        assert_eq!(order.slot, 11);
+        assert_eq!(order.auction_duration, 5);
+        assert_eq!(order.auction_start_price, 100 * PRICE_PRECISION_I64);
+        assert_eq!(order.auction_end_price, 101 * PRICE_PRECISION_I64);
+        assert!(order.auction_extended);
+
+        // extension is one-time
+        assert!(!order.is_eligible_for_auction_extension(17).unwrap());
+    }
+
+    #[test]
+    fn partially_filled_market_order() {
+        let order = Order {
+            status: OrderStatus::Open,
+            order_type: OrderType::Market,
+            direction: PositionDirection::Long,
+            slot: 0,
+            auction_duration: 10,
+            auction_start_price: 99 * PRICE_PRECISION_I64,
+            auction_end_price: 100 * PRICE_PRECISION_I64,
+            base_asset_amount_filled: 1,
+            ..Order::default()
+        };
+
+        assert!(!order.is_eligible_for_auction_extension(11).unwrap());
+    }
+
+    #[test]
+    fn end_price_at_limit_price() {
+        let price_buffer = (PERCENTAGE_PRECISION / 100) as u32; // 1%
+
+        let mut order = Order {
+            status: OrderStatus::Open,
+            order_type: OrderType::Market,
+            direction: PositionDirection::Long,
+            slot: 0,
+            price: 100 * PRICE_PRECISION_U64,
+            auction_duration: 10,
+            auction_start_price: 99 * PRICE_PRECISION_I64,
+            auction_end_price: 100 * PRICE_PRECISION_I64,
+            ..Order::default()
+        };
+
+        let extended = extend_order_auction(&mut order, 5, price_buffer, 11).unwrap();
+        assert!(!extended);
+        assert_eq!(order.slot, 0);
+        assert!(!order.auction_extended);
+    }
+}
+
+pub mod update_max_margin_order_size {
+    use std::str::FromStr;
+
+    use anchor_lang::Owner;
+    use solana_program::pubkey::Pubkey;
+
+    use crate::controller::orders::update_max_margin_order_size;
+    use crate::controller::position::PositionDirection;
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::math::constants::{
+        AMM_RESERVE_PRECISION, BASE_PRECISION_I64, BASE_PRECISION_U64, PEG_PRECISION,
+        PRICE_PRECISION, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+    };
+    use crate::math::margin::{
+        calculate_margin_requirement_and_total_collateral_and_liability_info, MarginRequirementType,
+    };
+    use crate::state::oracle::{HistoricalOracleData, OracleSource};
+    use crate::state::oracle_map::OracleMap;
+    use crate::state::perp_market::{MarketStatus, PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::user::{Order, OrderStatus, OrderType, PerpPosition, SpotPosition, User};
+    use crate::test_utils::*;
+
+    #[test]
+    fn resizes_to_free_collateral() {
+        let slot = 0_u64;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 50,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 1000,
+                order_tick_size: 1,
+                oracle: oracle_price_key,
+                historical_oracle_data: HistoricalOracleData {
+                    last_oracle_price: (100 * PRICE_PRECISION) as i64,
+                    last_oracle_price_twap: (100 * PRICE_PRECISION) as i64,
+                    last_oracle_price_twap_5min: (100 * PRICE_PRECISION) as i64,
+                    ..HistoricalOracleData::default()
+                },
+                ..AMM::default()
+            },
+            margin_ratio_initial: 2000,
+            margin_ratio_maintenance: 1000,
+            status: MarketStatus::Initialized,
+            ..PerpMarket::default_test()
+        };
+        market.amm.max_base_asset_reserve = u128::MAX;
+        market.amm.min_base_asset_reserve = 0;
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut usdc_spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            deposit_balance: 10000 * SPOT_BALANCE_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(usdc_spot_market, SpotMarket, usdc_spot_market_account_info);
+        let spot_market_map =
+            SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+        let mut spot_positions = [SpotPosition::default(); 8];
+        spot_positions[0] = SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 10000 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        };
+        let mut user = User {
+            orders: get_orders(Order {
+                status: OrderStatus::Open,
+                order_type: OrderType::Market,
+                market_index: 0,
+                order_id: 1,
+                direction: PositionDirection::Long,
+                base_asset_amount: BASE_PRECISION_U64,
+                max_margin_sizing: true,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 1,
+                open_bids: BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions,
+            ..User::default()
+        };
+
+        update_max_margin_order_size(&mut user, 0, &market_map, &spot_market_map, &mut oracle_map)
+            .unwrap();
+
+        let base_asset_amount = user.orders[0].base_asset_amount;
+        assert!(base_asset_amount > 400 * BASE_PRECISION_U64);
+        assert_eq!(user.perp_positions[0].open_bids, base_asset_amount as i64);
+
+        let (margin_requirement, total_collateral, _, _, _, _) =
+            calculate_margin_requirement_and_total_collateral_and_liability_info(
+                &user,
+                &market_map,
+                MarginRequirementType::Initial,
+                &spot_market_map,
+                &mut oracle_map,
+                None,
+                true,
+            )
+            .unwrap();
+
+        assert!(margin_requirement <= total_collateral.unsigned_abs());
+    }
+}
+
+pub mod resize_reduce_only_order {
+    use crate::controller::orders::resize_reduce_only_order;
+    use crate::controller::position::PositionDirection;
+    use crate::math::constants::{BASE_PRECISION_I64, BASE_PRECISION_U64};
+    use crate::state::user::{
+        Order, OrderStatus, OrderTriggerCondition, OrderType, PerpPosition, User,
+    };
+    use crate::test_utils::{get_orders, get_positions};
+
+    #[test]
+    fn shrinks_to_remaining_position() {
+        let mut user = User {
+            orders: get_orders(Order {
+                status: OrderStatus::Open,
+                order_type: OrderType::Limit,
+                direction: PositionDirection::Short,
+                base_asset_amount: 3 * BASE_PRECISION_U64,
+                base_asset_amount_filled: BASE_PRECISION_U64,
+                reduce_only: true,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                base_asset_amount: BASE_PRECISION_I64,
+                open_orders: 1,
+                open_asks: -2 * BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let resized = resize_reduce_only_order(&mut user, 0, 0).unwrap();
+
+        assert!(resized);
+        assert_eq!(user.orders[0].base_asset_amount, 2 * BASE_PRECISION_U64);
+        assert_eq!(user.perp_positions[0].open_asks, -BASE_PRECISION_I64);
+    }
+
+    #[test]
+    fn untriggered_order_keeps_open_asks() {
+        let mut user = User {
+            orders: get_orders(Order {
+                status: OrderStatus::Open,
+                order_type: OrderType::TriggerMarket,
+                trigger_condition: OrderTriggerCondition::Below,
+                direction: PositionDirection::Short,
+                base_asset_amount: 2 * BASE_PRECISION_U64,
+                reduce_only: true,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                base_asset_amount: BASE_PRECISION_I64,
+                open_orders: 1,
+                ..PerpPosition::default()
+            }),
+            ..User::default()
+        };
+
+        let resized = resize_reduce_only_order(&mut user, 0, 0).unwrap();
+
+        assert!(resized);
+        assert_eq!(user.orders[0].base_asset_amount, BASE_PRECISION_U64);
+        assert_eq!(user.perp_positions[0].open_asks, 0);
+    }
+
+    #[test]
+    fn nothing_to_reduce() {
+        let order = Order {
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            reduce_only: true,
+            ..Order::default()
+        };
+
+        // position already closed, left for cancel
+        let mut user = User {
+            orders: get_orders(order),
+            perp_positions: get_positions(PerpPosition {
+                open_orders: 1,
+                open_asks: -BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            ..User::default()
+        };
+
+        assert!(!resize_reduce_only_order(&mut user, 0, 0).unwrap());
+        assert_eq!(user.orders[0], order);
+
+        // position larger than the order
+        user.perp_positions[0].base_asset_amount = 2 * BASE_PRECISION_I64;
+
+        assert!(!resize_reduce_only_order(&mut user, 0, 0).unwrap());
+        assert_eq!(user.orders[0], order);
+        assert_eq!(user.perp_positions[0].open_asks, -BASE_PRECISION_I64);
+    }
+}
+
+pub mod handle_user_order_id_conflict {
+    use std::str::FromStr;
+
+    use anchor_lang::Owner;
+    use solana_program::pubkey::Pubkey;
+
+    use crate::controller::orders::handle_user_order_id_conflict;
+    use crate::controller::position::PositionDirection;
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::error::ErrorCode;
+    use crate::instructions::OrderParams;
+    use crate::math::constants::{BASE_PRECISION_I64, BASE_PRECISION_U64};
+    use crate::state::oracle_map::OracleMap;
+    use crate::state::perp_market::{MarketStatus, PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::user::{MarketType, Order, OrderStatus, OrderType, PerpPosition, User};
+    use crate::test_utils::*;
+
+    #[test]
+    fn reject_or_replace() {
+        let slot = 0;
+        let now = 0;
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                oracle: oracle_price_key,
+                ..AMM::default()
+            },
+            status: MarketStatus::Active,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+        let spot_market_map = SpotMarketMap::load_multiple(vec![], true).unwrap();
+
+        let user_key = Pubkey::default();
+        let mut user = User {
+            orders: get_orders(Order {
+                market_index: 0,
+                order_id: 1,
+                user_order_id: 7,
+                status: OrderStatus::Open,
+                order_type: OrderType::Limit,
+                market_type: MarketType::Perp,
+                direction: PositionDirection::Long,
+                base_asset_amount: BASE_PRECISION_U64,
+                ..Order::default()
+            }),
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 1,
+                open_bids: BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            ..User::default()
+        };
+
+        // no user_order_id or an unused one
+        for user_order_id in [0, 8] {
+            let params = OrderParams {
+                user_order_id,
+                ..OrderParams::default()
+            };
+            handle_user_order_id_conflict(
+                &mut user,
+                &user_key,
+                &params,
+                true,
+                &market_map,
+                &spot_market_map,
+                &mut oracle_map,
+                now,
+                slot,
+            )
+            .unwrap();
+            assert_eq!(user.orders[0].status, OrderStatus::Open);
+        }
+
+        let mut params = OrderParams {
+            user_order_id: 7,
+            ..OrderParams::default()
+        };
+        let result = handle_user_order_id_conflict(
+            &mut user,
+            &user_key,
+            &params,
+            true,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+        );
+        assert_eq!(result, Err(ErrorCode::UserOrderIdAlreadyInUse));
+        assert_eq!(user.orders[0].status, OrderStatus::Open);
+
+        params.replace_existing_user_order_id = true;
+
+        // signer without cancel rights, e.g. a delegate that can only place orders
+        let result = handle_user_order_id_conflict(
+            &mut user,
+            &user_key,
+            &params,
+            false,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+        );
+        assert_eq!(result, Err(ErrorCode::UserOrderIdAlreadyInUse));
+        assert_eq!(user.orders[0].status, OrderStatus::Open);
+
+        // order in another market
+        for (market_type, market_index) in [(MarketType::Perp, 1), (MarketType::Spot, 0)] {
+            let other_market_params = OrderParams {
+                market_type,
+                market_index,
+                ..params.clone()
+            };
+            let result = handle_user_order_id_conflict(
+                &mut user,
+                &user_key,
+                &other_market_params,
+                true,
+                &market_map,
+                &spot_market_map,
+                &mut oracle_map,
+                now,
+                slot,
+            );
+            assert_eq!(result, Err(ErrorCode::UserOrderIdAlreadyInUse));
+            assert_eq!(user.orders[0].status, OrderStatus::Open);
+        }
+
+        handle_user_order_id_conflict(
+            &mut user,
+            &user_key,
+            &params,
+            true,
+            &market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            now,
+            slot,
+        )
+        .unwrap();
+        assert_eq!(user.orders[0], Order::default());
+        assert_eq!(user.perp_positions[0].open_orders, 0);
+        assert_eq!(user.perp_positions[0].open_bids, 0);
+    }
+}
+
+pub mod cancel_stale_orders {
+    use std::str::FromStr;
+
+    use anchor_lang::prelude::{AccountLoader, Clock};
+
+    use crate::controller::orders::cancel_stale_orders;
+    use crate::controller::position::PositionDirection;
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::error::ErrorCode;
+    use crate::math::constants::{
+        BASE_PRECISION_I64, BASE_PRECISION_U64, MAX_USER_ORDERS, PRICE_PRECISION_U64,
+        QUOTE_PRECISION_U64, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+        SPOT_CUMULATIVE_INTEREST_PRECISION,
+    };
+    use crate::state::oracle::OracleSource;
+    use crate::state::perp_market::{PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::state::{FeeStructure, State};
+    use crate::state::user::{
+        MarketType, OrderStatus, OrderTriggerCondition, OrderType, SpotPosition, User,
+    };
+    use crate::test_utils::*;
+    use crate::test_utils::{create_account_info, get_positions, get_pyth_price};
+
+    use super::*;
+
+    #[test]
+    fn expired_and_orphaned_trigger_orders() {
+        let clock = Clock {
+            slot: 6,
+            epoch_start_timestamp: 0,
+            epoch: 0,
+            leader_schedule_epoch: 0,
+            unix_timestamp: 100,
+        };
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, clock.slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                oracle: oracle_price_key,
+                ..AMM::default()
+            },
+            status: MarketStatus::Active,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut usdc_spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            deposit_balance: 100 * SPOT_BALANCE_PRECISION,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(usdc_spot_market, SpotMarket, usdc_spot_market_account_info);
+        let spot_market_map =
+            SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
+        orders[0] = Order {
+            market_index: 0,
+            order_id: 1,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Long,
+            base_asset_amount: BASE_PRECISION_U64,
+            price: 90 * PRICE_PRECISION_U64,
+            max_ts: 50,
+            ..Order::default()
+        };
+        orders[1] = Order {
+            market_index: 0,
+            order_id: 2,
+            status: OrderStatus::Open,
+            order_type: OrderType::TriggerMarket,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Short,
+            base_asset_amount: BASE_PRECISION_U64,
+            trigger_price: 80 * PRICE_PRECISION_U64,
+            trigger_condition: OrderTriggerCondition::Below,
+            reduce_only: true,
+            ..Order::default()
+        };
+        orders[2] = Order {
+            market_index: 0,
+            order_id: 3,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Long,
+            base_asset_amount: BASE_PRECISION_U64,
+            price: 90 * PRICE_PRECISION_U64,
+            ..Order::default()
+        };
+
+        let mut spot_positions = [SpotPosition::default(); 8];
+        spot_positions[0] = SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        };
+        let mut user = User {
+            authority: Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap(), // different authority than filler
+            orders,
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                open_orders: 3,
+                open_bids: 2 * BASE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions,
+            ..User::default()
+        };
+        create_anchor_account_info!(user, User, user_account_info);
+        let user_account_loader: AccountLoader<User> =
+            AccountLoader::try_from(&user_account_info).unwrap();
+
+        let filler_key = Pubkey::from_str("My11111111111111111111111111111111111111111").unwrap();
+        create_anchor_account_info!(User::default(), &filler_key, User, filler_account_info);
+        let filler_account_loader: AccountLoader<User> =
+            AccountLoader::try_from(&filler_account_info).unwrap();
+
+        let state = State {
+            perp_fee_structure: FeeStructure {
+                flat_filler_fee: QUOTE_PRECISION_U64 / 100,
+                ..FeeStructure::test_default()
+            },
+            ..State::default()
+        };
+
+        cancel_stale_orders(
+            &state,
+            &user_account_loader,
+            &spot_market_map,
+            &market_map,
+            &mut oracle_map,
+            &filler_account_loader,
+            &clock,
+        )
+        .unwrap();
+
+        {
+            let user = user_account_loader.load().unwrap();
+            assert_eq!(user.orders[0], Order::default());
+            assert_eq!(user.orders[1], Order::default());
+            assert_eq!(user.orders[2].order_id, 3);
+            assert_eq!(user.perp_positions[0].open_orders, 1);
+            assert_eq!(user.perp_positions[0].open_bids, BASE_PRECISION_I64);
+            assert_eq!(
+                user.spot_positions[0].scaled_balance,
+                100 * SPOT_BALANCE_PRECISION_U64 - 2 * SPOT_BALANCE_PRECISION_U64 / 100
+            );
+
+            let filler = filler_account_loader.load().unwrap();
+            assert_eq!(
+                filler.spot_positions[0].scaled_balance,
+                2 * SPOT_BALANCE_PRECISION_U64 / 100
+            );
+        }
+
+        let result = cancel_stale_orders(
+            &state,
+            &user_account_loader,
+            &spot_market_map,
+            &market_map,
+            &mut oracle_map,
+            &filler_account_loader,
+            &clock,
+        );
+        assert_eq!(result, Err(ErrorCode::NoStaleOrders));
     }
 }
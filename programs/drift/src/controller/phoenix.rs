@@ -0,0 +1,111 @@
+use crate::error::{DriftResult, ErrorCode};
+use crate::signer::get_signer_seeds;
+use crate::state::token::SpotMarketVault;
+use anchor_lang::prelude::{AccountInfo, Program};
+use anchor_spl::token::Token;
+use borsh::BorshSerialize;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::msg;
+
+pub const SWAP_INSTRUCTION_TAG: u8 = 0;
+pub const IMMEDIATE_OR_CANCEL_ORDER_PACKET_TAG: u8 = 2;
+
+#[derive(Clone, Copy, BorshSerialize, PartialEq, Debug, Eq)]
+pub enum PhoenixSide {
+    Bid,
+    Ask,
+}
+
+#[derive(Clone, Copy, BorshSerialize, PartialEq, Debug, Eq)]
+pub enum PhoenixSelfTradeBehavior {
+    Abort,
+    CancelProvide,
+    DecrementTake,
+}
+
+// Fields of the ImmediateOrCancel variant of the phoenix OrderPacket
+#[derive(Clone, Copy, BorshSerialize, PartialEq, Debug, Eq)]
+pub struct PhoenixImmediateOrCancelOrderPacket {
+    pub side: PhoenixSide,
+    pub price_in_ticks: Option<u64>,
+    pub num_base_lots: u64,
+    pub num_quote_lots: u64,
+    pub min_base_lots_to_fill: u64,
+    pub min_quote_lots_to_fill: u64,
+    pub self_trade_behavior: PhoenixSelfTradeBehavior,
+    pub match_limit: Option<u64>,
+    pub client_order_id: u128,
+    pub use_only_deposited_funds: bool,
+    pub last_valid_slot: Option<u64>,
+    pub last_valid_unix_timestamp_in_seconds: Option<u64>,
+}
+
+pub struct PhoenixFulfillmentParams<'a, 'b> {
+    pub drift_signer: &'a AccountInfo<'b>,
+    pub phoenix_program_id: &'a AccountInfo<'b>,
+    pub phoenix_log_authority: &'a AccountInfo<'b>,
+    pub phoenix_market: &'a AccountInfo<'b>,
+    pub phoenix_base_vault: &'a AccountInfo<'b>,
+    pub phoenix_quote_vault: &'a AccountInfo<'b>,
+    pub token_program: Program<'b, Token>,
+    pub base_market_vault: SpotMarketVault<'b>,
+    pub quote_market_vault: SpotMarketVault<'b>,
+    pub signer_nonce: u8,
+}
+
+pub fn invoke_swap<'a>(
+    phoenix_program: &AccountInfo<'a>,
+    phoenix_log_authority: &AccountInfo<'a>,
+    phoenix_market: &AccountInfo<'a>,
+    drift_signer: &AccountInfo<'a>,
+    drift_base_vault: &AccountInfo<'a>,
+    drift_quote_vault: &AccountInfo<'a>,
+    phoenix_base_vault: &AccountInfo<'a>,
+    phoenix_quote_vault: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    order_packet: PhoenixImmediateOrCancelOrderPacket,
+    nonce: u8,
+) -> DriftResult {
+    let mut data = vec![SWAP_INSTRUCTION_TAG, IMMEDIATE_OR_CANCEL_ORDER_PACKET_TAG];
+    order_packet.serialize(&mut data).map_err(|e| {
+        msg!("{:?}", e);
+        ErrorCode::FailedPhoenixCPI
+    })?;
+
+    let instruction = Instruction {
+        program_id: *phoenix_program.key,
+        data,
+        accounts: vec![
+            AccountMeta::new_readonly(*phoenix_program.key, false),
+            AccountMeta::new_readonly(*phoenix_log_authority.key, false),
+            AccountMeta::new(*phoenix_market.key, false),
+            AccountMeta::new_readonly(*drift_signer.key, true),
+            AccountMeta::new(*drift_base_vault.key, false),
+            AccountMeta::new(*drift_quote_vault.key, false),
+            AccountMeta::new(*phoenix_base_vault.key, false),
+            AccountMeta::new(*phoenix_quote_vault.key, false),
+            AccountMeta::new_readonly(*token_program.key, false),
+        ],
+    };
+
+    let account_infos = [
+        phoenix_program.clone(),
+        phoenix_log_authority.clone(),
+        phoenix_market.clone(),
+        drift_signer.clone(),
+        drift_base_vault.clone(),
+        drift_quote_vault.clone(),
+        phoenix_base_vault.clone(),
+        phoenix_quote_vault.clone(),
+        token_program.clone(),
+    ];
+
+    let signer_seeds = get_signer_seeds(&nonce);
+    let signers_seeds = &[&signer_seeds[..]];
+
+    solana_program::program::invoke_signed_unchecked(&instruction, &account_infos, signers_seeds)
+        .map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::FailedPhoenixCPI
+        })
+}
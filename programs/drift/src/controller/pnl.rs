@@ -1,5 +1,6 @@
 use crate::controller::amm::{update_pnl_pool_and_user_balance, update_pool_balances};
 use crate::controller::funding::settle_funding_payment;
+use crate::controller::lp::settle_lp;
 use crate::controller::orders::{cancel_orders, validate_market_within_price_band};
 use crate::controller::position::{
     get_position_index, update_position_and_market, update_quote_asset_amount,
@@ -12,14 +13,20 @@ use crate::error::{DriftResult, ErrorCode};
 use crate::math::amm::calculate_net_user_pnl;
 
 use crate::math::casting::Cast;
-use crate::math::margin::meets_maintenance_margin_requirement;
+use crate::math::funding::calculate_funding_payment;
+use crate::math::margin::{
+    meets_isolated_perp_position_margin_requirement, meets_maintenance_margin_requirement,
+    MarginRequirementType,
+};
 use crate::math::position::calculate_base_asset_value_with_expiry_price;
 use crate::math::safe_math::SafeMath;
 use crate::math::spot_balance::get_token_amount;
 
-use crate::state::events::{OrderActionExplanation, SettlePnlExplanation, SettlePnlRecord};
+use crate::state::events::{
+    FundingBorrowSettleRecord, OrderActionExplanation, SettlePnlExplanation, SettlePnlRecord,
+};
 use crate::state::oracle_map::OracleMap;
-use crate::state::perp_market::MarketStatus;
+use crate::state::perp_market::{MarketStatus, PerpOperation};
 use crate::state::perp_market_map::PerpMarketMap;
 use crate::state::spot_market::{SpotBalance, SpotBalanceType};
 use crate::state::spot_market_map::SpotMarketMap;
@@ -67,16 +74,23 @@ pub fn settle_pnl(
 
     let position_index = get_position_index(&user.perp_positions, market_index)?;
     let unrealized_pnl = user.perp_positions[position_index].get_unrealized_pnl(oracle_price)?;
+    let is_isolated = user.perp_positions[position_index].is_isolated;
 
     // cannot settle negative pnl this way on a user who is in liquidation territory
-    if unrealized_pnl < 0
-        && !meets_maintenance_margin_requirement(
+    let meets_margin_requirement = if is_isolated {
+        meets_isolated_perp_position_margin_requirement(
             user,
+            market_index,
             perp_market_map,
-            spot_market_map,
+            MarginRequirementType::Maintenance,
             oracle_map,
+            None,
         )?
-    {
+    } else {
+        meets_maintenance_margin_requirement(user, perp_market_map, spot_market_map, oracle_map)?
+    };
+
+    if unrealized_pnl < 0 && !meets_margin_requirement {
         return Err(ErrorCode::InsufficientCollateralForSettlingPNL);
     }
 
@@ -106,6 +120,13 @@ pub fn settle_pnl(
         "Cannot settle pnl under current market status"
     )?;
 
+    validate!(
+        !perp_market.is_operation_paused(PerpOperation::SettlePnl)?,
+        ErrorCode::InvalidMarketStatusToSettlePnl,
+        "Settle pnl paused for market {}",
+        market_index
+    )?;
+
     let pnl_pool_token_amount = get_token_amount(
         perp_market.pnl_pool.scaled_balance,
         spot_market,
@@ -119,9 +140,26 @@ pub fn settle_pnl(
         0
     };
 
-    let user_unsettled_pnl: i128 =
+    let mut user_unsettled_pnl: i128 =
         user.perp_positions[position_index].get_claimable_pnl(oracle_price, max_pnl_pool_excess)?;
 
+    // isolated losses can only be paid out of the position's own collateral
+    if is_isolated {
+        user_unsettled_pnl = user_unsettled_pnl.max(
+            -user.perp_positions[position_index]
+                .isolated_collateral
+                .cast::<i128>()?,
+        );
+    }
+
+    // losses can still be settled while profits are paused
+    if user_unsettled_pnl > 0
+        && perp_market.is_operation_paused(PerpOperation::SettlePositivePnl)?
+    {
+        msg!("Settling positive pnl paused for market {}", market_index);
+        user_unsettled_pnl = 0;
+    }
+
     let pnl_to_settle_with_user = update_pool_balances(
         perp_market,
         spot_market,
@@ -142,24 +180,31 @@ pub fn settle_pnl(
     }
 
     validate!(
-        pnl_to_settle_with_user < 0
-            || max_pnl_pool_excess > 0
-            || (user.authority.eq(authority) || user.delegate.eq(authority)),
+        pnl_to_settle_with_user < 0 || max_pnl_pool_excess > 0 || user.authority.eq(authority),
         ErrorCode::UserMustSettleTheirOwnPositiveUnsettledPNL,
         "User must settle their own unsettled pnl when its positive and pnl pool not in excess"
     )?;
 
-    update_spot_balances(
-        pnl_to_settle_with_user.unsigned_abs(),
-        if pnl_to_settle_with_user > 0 {
-            &SpotBalanceType::Deposit
+    if is_isolated {
+        let isolated_collateral = &mut user.perp_positions[position_index].isolated_collateral;
+        *isolated_collateral = if pnl_to_settle_with_user > 0 {
+            isolated_collateral.safe_add(pnl_to_settle_with_user.unsigned_abs().cast()?)?
         } else {
-            &SpotBalanceType::Borrow
-        },
-        spot_market,
-        user.get_quote_spot_position_mut(),
-        false,
-    )?;
+            isolated_collateral.safe_sub(pnl_to_settle_with_user.unsigned_abs().cast()?)?
+        };
+    } else {
+        update_spot_balances(
+            pnl_to_settle_with_user.unsigned_abs(),
+            if pnl_to_settle_with_user > 0 {
+                &SpotBalanceType::Deposit
+            } else {
+                &SpotBalanceType::Borrow
+            },
+            spot_market,
+            user.get_quote_spot_position_mut(),
+            false,
+        )?;
+    }
 
     update_quote_asset_amount(
         &mut user.perp_positions[position_index],
@@ -188,6 +233,153 @@ pub fn settle_pnl(
     Ok(())
 }
 
+/// settles the funding accrued on a perp position straight into the user's quote spot balance,
+/// netting it against their borrow: funding received repays up to the outstanding quote borrow and
+/// funding paid is borrowed. emits a single FundingBorrowSettleRecord in place of the
+/// FundingPaymentRecord and SettlePnlRecord of settle_funding_payment + settle_pnl
+///
+/// funding is paid out of the perp market's quote pnl pool, so only the quote borrow can be netted.
+/// repaying a borrow in the perp's underlying spot market at the oracle price would need base
+/// tokens moved into that market's vault, which nothing on this path provides. a cash and carry
+/// basis position (spot long bought with borrowed quote, perp short) borrows in quote anyway
+pub fn settle_funding_against_borrow(
+    market_index: u16,
+    user: &mut User,
+    user_key: &Pubkey,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    now: i64,
+) -> DriftResult {
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
+
+    {
+        let spot_market = &mut spot_market_map.get_quote_spot_market_mut()?;
+        update_spot_market_cumulative_interest(spot_market, None, now)?;
+    }
+
+    let position_index = get_position_index(&user.perp_positions, market_index)?;
+
+    validate!(
+        !user.perp_positions[position_index].is_isolated,
+        ErrorCode::CannotSettleFundingAgainstBorrow,
+        "isolated position in market {} settles into its own collateral",
+        market_index
+    )?;
+
+    let meets_margin_requirement =
+        meets_maintenance_margin_requirement(user, perp_market_map, spot_market_map, oracle_map)?;
+
+    let spot_market = &mut spot_market_map.get_quote_spot_market_mut()?;
+    let perp_market = &mut perp_market_map.get_ref_mut(&market_index)?;
+
+    validate!(
+        perp_market.status == MarketStatus::Active,
+        ErrorCode::InvalidMarketStatusToSettlePnl,
+        "Cannot settle pnl under current market status"
+    )?;
+
+    validate!(
+        !perp_market.is_operation_paused(PerpOperation::SettlePnl)?,
+        ErrorCode::InvalidMarketStatusToSettlePnl,
+        "Settle pnl paused for market {}",
+        market_index
+    )?;
+
+    let position = &user.perp_positions[position_index];
+    let amm_cumulative_funding_rate = if position.base_asset_amount > 0 {
+        perp_market.amm.cumulative_funding_rate_long
+    } else {
+        perp_market.amm.cumulative_funding_rate_short
+    };
+
+    if position.base_asset_amount == 0
+        || amm_cumulative_funding_rate == position.last_cumulative_funding_rate.cast()?
+    {
+        msg!("User has no funding to settle for market {}", market_index);
+        return Ok(());
+    }
+
+    let funding_payment = calculate_funding_payment(amm_cumulative_funding_rate, position)?;
+    let user_last_cumulative_funding = position.last_cumulative_funding_rate;
+
+    user.update_cumulative_perp_funding(funding_payment)?;
+
+    let position = &mut user.perp_positions[position_index];
+    position.last_cumulative_funding_rate = amm_cumulative_funding_rate.cast()?;
+    update_quote_asset_and_break_even_amount(position, perp_market, funding_payment)?;
+
+    settle_lp(user, user_key, perp_market, now)?;
+
+    let funding_to_settle = if funding_payment > 0 {
+        let quote_spot_position = user.get_quote_spot_position();
+        let quote_borrow_amount = match quote_spot_position.balance_type {
+            SpotBalanceType::Borrow => get_token_amount(
+                quote_spot_position.scaled_balance.cast()?,
+                spot_market,
+                &SpotBalanceType::Borrow,
+            )?,
+            SpotBalanceType::Deposit => 0,
+        };
+        funding_payment
+            .cast::<i128>()?
+            .min(quote_borrow_amount.cast()?)
+    } else if meets_margin_requirement {
+        funding_payment.cast::<i128>()?
+    } else {
+        0
+    };
+
+    let pnl_to_settle_with_user = if funding_to_settle != 0 {
+        update_pool_balances(
+            perp_market,
+            spot_market,
+            user.get_quote_spot_position(),
+            funding_to_settle,
+            now,
+        )?
+    } else {
+        0
+    };
+
+    if pnl_to_settle_with_user != 0 {
+        update_spot_balances(
+            pnl_to_settle_with_user.unsigned_abs(),
+            if pnl_to_settle_with_user > 0 {
+                &SpotBalanceType::Deposit
+            } else {
+                &SpotBalanceType::Borrow
+            },
+            spot_market,
+            user.get_quote_spot_position_mut(),
+            false,
+        )?;
+
+        update_quote_asset_amount(
+            &mut user.perp_positions[position_index],
+            perp_market,
+            -pnl_to_settle_with_user.cast()?,
+        )?;
+
+        update_settled_pnl(user, position_index, pnl_to_settle_with_user.cast()?)?;
+    }
+
+    emit!(FundingBorrowSettleRecord {
+        ts: now,
+        user_authority: user.authority,
+        user: *user_key,
+        market_index,
+        funding_payment,
+        pnl_settled: pnl_to_settle_with_user.cast()?,
+        base_asset_amount: user.perp_positions[position_index].base_asset_amount,
+        user_last_cumulative_funding,
+        amm_cumulative_funding_long: perp_market.amm.cumulative_funding_rate_long,
+        amm_cumulative_funding_short: perp_market.amm.cumulative_funding_rate_short,
+    });
+
+    Ok(())
+}
+
 pub fn settle_expired_position(
     perp_market_index: u16,
     user: &mut User,
@@ -23,6 +23,7 @@ pub mod delisting_test {
     use crate::controller::repeg::settle_expired_market;
     use crate::create_account_info;
     use crate::create_anchor_account_info;
+    use crate::error::ErrorCode;
     use crate::math::amm::calculate_net_user_pnl;
     use crate::math::constants::{
         AMM_RESERVE_PRECISION, BASE_PRECISION_I64, BASE_PRECISION_U64, LIQUIDATION_PCT_PRECISION,
@@ -42,6 +43,7 @@ pub mod delisting_test {
     use crate::state::oracle::HistoricalOracleData;
     use crate::state::oracle::OracleSource;
     use crate::state::perp_market::{MarketStatus, PerpMarket, PoolBalance, AMM};
+    use crate::state::perp_market_delisting::PerpMarketDelisting;
     use crate::state::perp_market_map::PerpMarketMap;
     use crate::state::spot_market::{SpotBalanceType, SpotMarket};
     use crate::state::spot_market_map::SpotMarketMap;
@@ -145,6 +147,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .is_err());
         assert_eq!(market.is_reduce_only().unwrap(), false);
@@ -167,6 +170,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .is_err());
     }
@@ -274,6 +278,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
 
@@ -284,6 +289,146 @@ pub mod delisting_test {
         drop(market);
     }
 
+    #[test]
+    fn delist_market_with_0_balance_long_at_settlement_price_twap() {
+        let _now = 0_i64;
+        let slot = 0_u64;
+        let clock = Clock {
+            slot: 6893025720,
+            epoch_start_timestamp: 1662065595 - 1000,
+            epoch: 2424,
+            leader_schedule_epoch: 1662065595 - 1,
+            unix_timestamp: 1662065595,
+        };
+
+        let mut oracle_price = get_pyth_price(100, 6);
+        let oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            oracle_price,
+            &oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        // net users are short
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                base_asset_amount_with_amm: (AMM_RESERVE_PRECISION / 2) as i128,
+                base_asset_amount_long: (AMM_RESERVE_PRECISION / 2) as i128,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                max_slippage_ratio: 50,
+                max_fill_reserve_fraction: 100,
+                order_step_size: 10000000,
+                oracle: oracle_price_key,
+                amm_jit_intensity: 100,
+                historical_oracle_data: HistoricalOracleData {
+                    last_oracle_price_twap: (99 * PRICE_PRECISION) as i64,
+                    ..HistoricalOracleData::default()
+                },
+                quote_asset_amount: -(QUOTE_PRECISION_I128 * 50), //longs have $100 cost basis
+                delisting_enabled: true,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            expiry_ts: clock.unix_timestamp - 10, // past expiry time
+
+            ..PerpMarket::default_test()
+        };
+        market.amm.max_base_asset_reserve = u128::MAX;
+        market.amm.min_base_asset_reserve = 0;
+
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+        let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+        let state = State {
+            oracle_guard_rails: OracleGuardRails {
+                price_divergence: PriceDivergenceGuardRails {
+                    mark_oracle_divergence_numerator: 1,
+                    mark_oracle_divergence_denominator: 10,
+                },
+                validity: ValidityGuardRails {
+                    slots_before_stale_for_amm: 10,     // 5s
+                    slots_before_stale_for_margin: 120, // 60s
+                    confidence_interval_max_size: 1000,
+                    too_volatile_ratio: 5,
+                },
+            },
+            ..State::default()
+        };
+
+        // expiry time
+        assert_eq!(market.expiry_ts < clock.unix_timestamp, true);
+        assert_eq!(market.status, MarketStatus::Initialized);
+        assert_eq!(market.expiry_price, 0);
+        assert_eq!(market.is_active(clock.unix_timestamp).unwrap(), false);
+
+        // delisted markets need their delisting to settle
+        assert_eq!(
+            settle_expired_market(
+                0,
+                &market_map,
+                &mut oracle_map,
+                &spot_market_map,
+                &state,
+                &clock,
+                None,
+            ),
+            Err(ErrorCode::InvalidPerpMarketDelisting)
+        );
+
+        // 97 sampled over the 100s window
+        let perp_market_delisting = PerpMarketDelisting {
+            settlement_price_cumulative: (97 * PRICE_PRECISION) as i64 * 50,
+            last_oracle_price: (97 * PRICE_PRECISION) as i64,
+            first_sample_ts: market.expiry_ts - 100,
+            last_sample_ts: market.expiry_ts - 50,
+            settlement_twap_window: 100,
+            market_index: 0,
+            padding: [0; 2],
+        };
+
+        // put in settlement mode at the delisting's sampled twap instead of the oracle twap
+        settle_expired_market(
+            0,
+            &market_map,
+            &mut oracle_map,
+            &spot_market_map,
+            &state,
+            &clock,
+            Some(&perp_market_delisting),
+        )
+        .unwrap();
+
+        let market = market_map.get_ref_mut(&0).unwrap();
+        assert_eq!(market.expiry_price > 0, true);
+        assert_eq!(market.expiry_price, 96999999);
+        assert_eq!(market.status, MarketStatus::Settlement);
+        drop(market);
+    }
+
     #[test]
     fn delist_market_with_0_balance_long_at_best_effort() {
         let slot = 0_u64;
@@ -385,6 +530,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
 
@@ -501,6 +647,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
 
@@ -617,6 +764,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
 
@@ -788,6 +936,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
 
@@ -1005,6 +1154,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
 
@@ -1211,6 +1361,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
 
@@ -1506,6 +1657,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
 
@@ -1931,6 +2083,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
         assert_eq!(market.is_reduce_only().unwrap(), false);
@@ -2302,6 +2455,7 @@ pub mod delisting_test {
             &spot_market_map,
             &state,
             &clock,
+            None,
         )
         .unwrap();
 
@@ -2351,6 +2505,7 @@ pub mod delisting_test {
                 MarginRequirementType::Initial,
                 0,
                 false,
+                false,
             )
             .unwrap();
 
@@ -2411,6 +2566,7 @@ pub mod delisting_test {
                 clock.slot,
                 clock.unix_timestamp,
                 &state,
+                0,
             )
             .unwrap();
 
@@ -2429,6 +2585,7 @@ pub mod delisting_test {
                         MarginRequirementType::Initial,
                         0,
                         false,
+                        false,
                     )
                     .unwrap();
 
@@ -2491,6 +2648,7 @@ pub mod delisting_test {
                 10,
                 PERCENTAGE_PRECISION,
                 150,
+                0,
             )
             .unwrap();
 
@@ -2514,6 +2672,7 @@ pub mod delisting_test {
                         MarginRequirementType::Initial,
                         0,
                         false,
+                        false,
                     )
                     .unwrap();
 
@@ -2580,6 +2739,7 @@ pub mod delisting_test {
                 10,
                 PERCENTAGE_PRECISION,
                 150,
+                0,
             )
             .unwrap();
 
@@ -2603,6 +2763,7 @@ pub mod delisting_test {
                         MarginRequirementType::Initial,
                         0,
                         false,
+                        false,
                     )
                     .unwrap();
 
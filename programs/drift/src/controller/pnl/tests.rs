@@ -3,20 +3,20 @@ use std::str::FromStr;
 use anchor_lang::Owner;
 use solana_program::pubkey::Pubkey;
 
-use crate::controller::pnl::settle_pnl;
+use crate::controller::pnl::{settle_funding_against_borrow, settle_pnl};
 use crate::create_account_info;
 use crate::create_anchor_account_info;
 use crate::error::ErrorCode;
 use crate::math::casting::Cast;
 use crate::math::constants::{
-    AMM_RESERVE_PRECISION, BASE_PRECISION_I128, BASE_PRECISION_I64, LIQUIDATION_FEE_PRECISION,
-    PEG_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64, QUOTE_SPOT_MARKET_INDEX,
-    SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
-    SPOT_WEIGHT_PRECISION,
+    AMM_RESERVE_PRECISION, BASE_PRECISION_I128, BASE_PRECISION_I64, FUNDING_RATE_PRECISION_I128,
+    FUNDING_RATE_PRECISION_I64, LIQUIDATION_FEE_PRECISION, PEG_PRECISION, QUOTE_PRECISION_I128,
+    QUOTE_PRECISION_I64, QUOTE_SPOT_MARKET_INDEX, SPOT_BALANCE_PRECISION,
+    SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
 };
 use crate::state::oracle::{HistoricalOracleData, OracleSource};
 use crate::state::oracle_map::OracleMap;
-use crate::state::perp_market::{MarketStatus, PerpMarket, PoolBalance, AMM};
+use crate::state::perp_market::{MarketStatus, PerpMarket, PerpOperation, PoolBalance, AMM};
 use crate::state::perp_market_map::PerpMarketMap;
 use crate::state::spot_market::{SpotBalanceType, SpotMarket};
 use crate::state::spot_market_map::SpotMarketMap;
@@ -648,6 +648,149 @@ pub fn user_unsettled_positive_pnl_less_than_pool() {
     assert_eq!(expected_market, *market_map.get_ref(&0).unwrap());
 }
 
+#[test]
+pub fn user_unsettled_positive_pnl_paused() {
+    let now = 0_i64;
+    let slot = 0_u64;
+    let state = State {
+        oracle_guard_rails: OracleGuardRails {
+            price_divergence: PriceDivergenceGuardRails {
+                mark_oracle_divergence_numerator: 1,
+                mark_oracle_divergence_denominator: 10,
+            },
+            validity: ValidityGuardRails {
+                slots_before_stale_for_amm: 10,     // 5s
+                slots_before_stale_for_margin: 120, // 60s
+                confidence_interval_max_size: 1000,
+                too_volatile_ratio: 5,
+            },
+        },
+        ..State::default()
+    };
+    let mut oracle_price = get_pyth_price(100, 6);
+    let oracle_price_key =
+        Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+    let pyth_program = crate::ids::pyth_program::id();
+    create_account_info!(
+        oracle_price,
+        &oracle_price_key,
+        &pyth_program,
+        oracle_account_info
+    );
+    let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+    let mut market = PerpMarket {
+        amm: AMM {
+            base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+            bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+            ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+            ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+            sqrt_k: 100 * AMM_RESERVE_PRECISION,
+            peg_multiplier: 100 * PEG_PRECISION,
+            max_slippage_ratio: 50,
+            max_fill_reserve_fraction: 100,
+            order_step_size: 10000000,
+            quote_asset_amount: -150 * QUOTE_PRECISION_I128,
+            base_asset_amount_with_amm: BASE_PRECISION_I128,
+            oracle: oracle_price_key,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price: oracle_price.agg.price,
+                last_oracle_price_twap_5min: oracle_price.agg.price,
+                last_oracle_price_twap: oracle_price.agg.price,
+                ..HistoricalOracleData::default()
+            },
+            ..AMM::default()
+        },
+        margin_ratio_initial: 1000,
+        margin_ratio_maintenance: 500,
+        number_of_users_with_base: 1,
+        number_of_users: 1,
+        status: MarketStatus::Active,
+        paused_operations: PerpOperation::SettlePositivePnl as u8,
+        liquidator_fee: LIQUIDATION_FEE_PRECISION / 100,
+        pnl_pool: PoolBalance {
+            scaled_balance: (50 * SPOT_BALANCE_PRECISION) as u128,
+            market_index: QUOTE_SPOT_MARKET_INDEX,
+            ..PoolBalance::default()
+        },
+        unrealized_pnl_maintenance_asset_weight: SPOT_WEIGHT_PRECISION.cast().unwrap(),
+        ..PerpMarket::default()
+    };
+    create_anchor_account_info!(market, PerpMarket, market_account_info);
+    let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+    let mut spot_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        initial_asset_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+        deposit_balance: 100 * SPOT_BALANCE_PRECISION,
+        ..SpotMarket::default()
+    };
+    create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+    let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+    let mut user = User {
+        perp_positions: get_positions(PerpPosition {
+            market_index: 0,
+            quote_asset_amount: 25 * QUOTE_PRECISION_I64,
+            ..PerpPosition::default()
+        }),
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+
+    let user_key = Pubkey::default();
+    let authority = Pubkey::default();
+
+    let expected_user = user;
+    let expected_market = market;
+
+    settle_pnl(
+        0,
+        &mut user,
+        &authority,
+        &user_key,
+        &market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        now,
+        &state,
+    )
+    .unwrap();
+
+    assert_eq!(expected_user, user);
+    assert_eq!(expected_market, *market_map.get_ref(&0).unwrap());
+
+    market_map
+        .get_ref_mut(&0)
+        .unwrap()
+        .set_operation_paused(PerpOperation::SettlePnl, true)
+        .unwrap();
+
+    let result = settle_pnl(
+        0,
+        &mut user,
+        &authority,
+        &user_key,
+        &market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        now,
+        &state,
+    );
+    assert_eq!(result, Err(ErrorCode::InvalidMarketStatusToSettlePnl));
+}
+
 #[test]
 pub fn market_fee_pool_receives_portion() {
     let now = 0_i64;
@@ -1575,3 +1718,140 @@ pub fn user_short_negative_unrealized_pnl() {
     assert_eq!(expected_user, user);
     assert_eq!(expected_market, *market_map.get_ref(&0).unwrap());
 }
+
+#[test]
+pub fn settle_funding_against_borrow_repays_borrow() {
+    let now = 0_i64;
+    let slot = 0_u64;
+
+    let mut oracle_price = get_pyth_price(100, 6);
+    let oracle_price_key =
+        Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+    let pyth_program = crate::ids::pyth_program::id();
+    create_account_info!(
+        oracle_price,
+        &oracle_price_key,
+        &pyth_program,
+        oracle_account_info
+    );
+    let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+    let mut market = PerpMarket {
+        amm: AMM {
+            base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+            bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+            ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+            ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+            sqrt_k: 100 * AMM_RESERVE_PRECISION,
+            peg_multiplier: 100 * PEG_PRECISION,
+            max_slippage_ratio: 50,
+            max_fill_reserve_fraction: 100,
+            order_step_size: 10000000,
+            quote_asset_amount: -100 * QUOTE_PRECISION_I128,
+            base_asset_amount_with_amm: -BASE_PRECISION_I128,
+            cumulative_funding_rate_short: 10 * FUNDING_RATE_PRECISION_I128,
+            oracle: oracle_price_key,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price: oracle_price.agg.price,
+                last_oracle_price_twap_5min: oracle_price.agg.price,
+                last_oracle_price_twap: oracle_price.agg.price,
+                ..HistoricalOracleData::default()
+            },
+            ..AMM::default()
+        },
+        margin_ratio_initial: 1000,
+        margin_ratio_maintenance: 500,
+        number_of_users_with_base: 1,
+        number_of_users: 1,
+        status: MarketStatus::Active,
+        liquidator_fee: LIQUIDATION_FEE_PRECISION / 100,
+        pnl_pool: PoolBalance {
+            scaled_balance: (50 * SPOT_BALANCE_PRECISION) as u128,
+            market_index: QUOTE_SPOT_MARKET_INDEX,
+            ..PoolBalance::default()
+        },
+        unrealized_pnl_maintenance_asset_weight: SPOT_WEIGHT_PRECISION.cast().unwrap(),
+        ..PerpMarket::default()
+    };
+    create_anchor_account_info!(market, PerpMarket, market_account_info);
+    let market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+    let mut spot_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        initial_asset_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+        initial_liability_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_liability_weight: SPOT_WEIGHT_PRECISION,
+        deposit_balance: 100 * SPOT_BALANCE_PRECISION,
+        borrow_balance: 4 * SPOT_BALANCE_PRECISION,
+        ..SpotMarket::default()
+    };
+    create_anchor_account_info!(spot_market, SpotMarket, spot_market_account_info);
+    let spot_market_map = SpotMarketMap::load_one(&spot_market_account_info, true).unwrap();
+
+    let mut user = User {
+        perp_positions: get_positions(PerpPosition {
+            market_index: 0,
+            base_asset_amount: -BASE_PRECISION_I64,
+            quote_asset_amount: 100 * QUOTE_PRECISION_I64,
+            ..PerpPosition::default()
+        }),
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Borrow,
+            scaled_balance: 4 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+
+    let user_key = Pubkey::default();
+
+    settle_funding_against_borrow(
+        0,
+        &mut user,
+        &user_key,
+        &market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        now,
+    )
+    .unwrap();
+
+    // 10 of funding received, 4 of it repays the borrow and the rest stays as unsettled pnl
+    assert_eq!(user.cumulative_perp_funding, 10 * QUOTE_PRECISION_I64);
+    assert_eq!(
+        user.perp_positions[0].last_cumulative_funding_rate,
+        10 * FUNDING_RATE_PRECISION_I64
+    );
+    assert_eq!(
+        user.perp_positions[0].quote_asset_amount,
+        106 * QUOTE_PRECISION_I64
+    );
+    assert_eq!(user.perp_positions[0].settled_pnl, 4 * QUOTE_PRECISION_I64);
+    assert_eq!(user.settled_perp_pnl, 4 * QUOTE_PRECISION_I64);
+    assert_eq!(user.spot_positions[0].scaled_balance, 0);
+
+    let market = market_map.get_ref(&0).unwrap();
+    assert_eq!(market.pnl_pool.scaled_balance, 46 * SPOT_BALANCE_PRECISION);
+    drop(market);
+
+    // already settled up to the market's cumulative funding
+    settle_funding_against_borrow(
+        0,
+        &mut user,
+        &user_key,
+        &market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        now,
+    )
+    .unwrap();
+    assert_eq!(user.settled_perp_pnl, 4 * QUOTE_PRECISION_I64);
+}
@@ -455,6 +455,36 @@ pub fn update_lp_market_position(
     Ok((lp_delta_base, lp_delta_quote, lp_fee))
 }
 
+pub fn validate_open_interest_for_fill(
+    market: &PerpMarket,
+    update_type: PositionUpdateType,
+) -> DriftResult {
+    // reducing fills must always be allowed, even if the cap was lowered below current open interest
+    if matches!(
+        update_type,
+        PositionUpdateType::Reduce | PositionUpdateType::Close
+    ) {
+        return Ok(());
+    }
+
+    let max_open_interest = market.amm.max_open_interest;
+    if max_open_interest == 0 {
+        return Ok(());
+    }
+
+    let open_interest = market.get_open_interest();
+    validate!(
+        open_interest <= max_open_interest,
+        ErrorCode::MaxOpenInterest,
+        "open interest ({}) > max open interest ({}) for perp market {}",
+        open_interest,
+        max_open_interest,
+        market.market_index
+    )?;
+
+    Ok(())
+}
+
 pub fn update_position_with_base_asset_amount(
     base_asset_amount: u64,
     direction: PositionDirection,
@@ -484,12 +514,17 @@ pub fn update_position_with_base_asset_amount(
     let position_delta =
         get_position_delta_for_fill(base_asset_amount, quote_asset_amount, direction)?;
 
+    let update_type =
+        get_position_update_type(&user.perp_positions[position_index], &position_delta);
+
     let pnl = update_position_and_market(
         &mut user.perp_positions[position_index],
         market,
         &position_delta,
     )?;
 
+    validate_open_interest_for_fill(market, update_type)?;
+
     market.amm.base_asset_amount_with_amm = market
         .amm
         .base_asset_amount_with_amm
@@ -1,9 +1,12 @@
 use crate::controller::position::{
-    update_lp_market_position, update_position_and_market, PositionDelta,
+    update_lp_market_position, update_position_and_market, validate_open_interest_for_fill,
+    PositionDelta,
 };
+use crate::error::ErrorCode;
 use crate::math::constants::{
     AMM_RESERVE_PRECISION, AMM_RESERVE_PRECISION_I128, BASE_PRECISION_I64,
 };
+use crate::math::position::PositionUpdateType;
 use crate::state::perp_market::{PerpMarket, AMM};
 use crate::state::user::PerpPosition;
 
@@ -964,3 +967,37 @@ fn close_short_with_quote_break_even_amount_more_than_quote_asset_amount() {
     assert_eq!(market.amm.quote_break_even_amount_long, 0);
     assert_eq!(market.amm.quote_break_even_amount_short, 0);
 }
+
+#[test]
+fn open_interest_cap_only_blocks_risk_increasing_fills() {
+    let mut market = PerpMarket {
+        amm: AMM {
+            base_asset_amount_long: 20 * BASE_PRECISION_I64 as i128,
+            base_asset_amount_short: -10 * BASE_PRECISION_I64 as i128,
+            max_open_interest: 15 * BASE_PRECISION_I64 as u128,
+            ..AMM::default()
+        },
+        ..PerpMarket::default()
+    };
+
+    assert_eq!(
+        validate_open_interest_for_fill(&market, PositionUpdateType::Open),
+        Err(ErrorCode::MaxOpenInterest)
+    );
+    assert_eq!(
+        validate_open_interest_for_fill(&market, PositionUpdateType::Increase),
+        Err(ErrorCode::MaxOpenInterest)
+    );
+    assert_eq!(
+        validate_open_interest_for_fill(&market, PositionUpdateType::Flip),
+        Err(ErrorCode::MaxOpenInterest)
+    );
+    assert!(validate_open_interest_for_fill(&market, PositionUpdateType::Reduce).is_ok());
+    assert!(validate_open_interest_for_fill(&market, PositionUpdateType::Close).is_ok());
+
+    market.amm.max_open_interest = 20 * BASE_PRECISION_I64 as u128;
+    assert!(validate_open_interest_for_fill(&market, PositionUpdateType::Increase).is_ok());
+
+    market.amm.max_open_interest = 0;
+    assert!(validate_open_interest_for_fill(&market, PositionUpdateType::Increase).is_ok());
+}
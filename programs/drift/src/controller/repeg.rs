@@ -5,7 +5,7 @@ use anchor_lang::prelude::*;
 use solana_program::msg;
 
 use crate::controller::amm::update_spreads;
-use crate::controller::spot_balance::update_spot_balances;
+use crate::controller::spot_balance::{update_revenue_pool_balances, update_spot_balances};
 use crate::error::ErrorCode;
 use crate::error::*;
 use crate::load_mut;
@@ -13,7 +13,7 @@ use crate::math::amm;
 use crate::math::bn;
 use crate::math::casting::Cast;
 use crate::math::constants::{
-    K_BPS_UPDATE_SCALE, MAX_SQRT_K, QUOTE_PRECISION, QUOTE_SPOT_MARKET_INDEX,
+    K_BPS_UPDATE_SCALE, MAX_SQRT_K, PRICE_PRECISION_I64, QUOTE_PRECISION, QUOTE_SPOT_MARKET_INDEX,
 };
 use crate::math::cp_curve;
 use crate::math::cp_curve::get_update_k_result;
@@ -23,12 +23,14 @@ use crate::math::oracle::{is_oracle_valid_for_action, oracle_validity, DriftActi
 use crate::math::repeg;
 use crate::math::safe_math::SafeMath;
 use crate::math::spot_balance::get_token_amount;
+use crate::math::spot_withdraw::validate_spot_balances;
 
 use crate::state::oracle::OraclePriceData;
 use crate::state::oracle_map::OracleMap;
-use crate::state::perp_market::{MarketStatus, PerpMarket};
+use crate::state::perp_market::{ContractType, MarketStatus, PerpMarket, PerpOperation};
+use crate::state::perp_market_delisting::PerpMarketDelisting;
 use crate::state::perp_market_map::PerpMarketMap;
-use crate::state::spot_market::SpotBalanceType;
+use crate::state::spot_market::{SpotBalanceType, SpotMarket};
 use crate::state::spot_market_map::SpotMarketMap;
 use crate::state::state::{OracleGuardRails, State};
 use crate::validate;
@@ -153,12 +155,20 @@ pub fn _update_amm(
     let oracle_validity = oracle::oracle_validity(
         market.amm.historical_oracle_data.last_oracle_price_twap,
         oracle_price_data,
-        &state.oracle_guard_rails.validity,
+        &state
+            .oracle_guard_rails
+            .validity
+            .with_override(&market.amm.validity_guard_rails_override),
     )?;
 
+    let circuit_breaker_tripped = update_oracle_circuit_breaker(market, oracle_price_data)?;
+
     let mut amm_update_cost = 0;
     let mut amm_not_successfully_updated = false;
-    if is_oracle_valid_for_action(oracle_validity, Some(DriftAction::UpdateAMMCurve))? {
+    // dont repeg towards an oracle that tripped the circuit breaker
+    if !circuit_breaker_tripped
+        && is_oracle_valid_for_action(oracle_validity, Some(DriftAction::UpdateAMMCurve))?
+    {
         let curve_update_intensity =
             min(market.amm.curve_update_intensity, 100_u8).cast::<i128>()?;
 
@@ -216,6 +226,111 @@ pub fn _update_amm(
     Ok(amm_update_cost)
 }
 
+/// Permissionless formulaic repeg. Unlike the repeg in `_update_amm`, each call moves the peg a
+/// bounded amount and only spends a fraction of the fee pool above its lower bound
+pub fn update_amm_peg(
+    market: &mut PerpMarket,
+    oracle_price_data: &OraclePriceData,
+    state: &State,
+) -> DriftResult<i128> {
+    validate!(
+        !matches!(
+            market.status,
+            MarketStatus::Settlement | MarketStatus::Delisted
+        ),
+        ErrorCode::MarketActionPaused,
+        "market {} cant be repegged in settlement",
+        market.market_index
+    )?;
+
+    validate!(
+        market.amm.curve_update_intensity > 0,
+        ErrorCode::MarketActionPaused,
+        "market {} has formulaic repegs disabled (curve_update_intensity = 0)",
+        market.market_index
+    )?;
+
+    let oracle_validity = oracle::oracle_validity(
+        market.amm.historical_oracle_data.last_oracle_price_twap,
+        oracle_price_data,
+        &state
+            .oracle_guard_rails
+            .validity
+            .with_override(&market.amm.validity_guard_rails_override),
+    )?;
+
+    let circuit_breaker_tripped = update_oracle_circuit_breaker(market, oracle_price_data)?;
+
+    validate!(
+        !circuit_breaker_tripped
+            && is_oracle_valid_for_action(oracle_validity, Some(DriftAction::UpdateAMMCurve))?,
+        ErrorCode::InvalidOracle,
+        "oracle invalid for repeg: validity = {:?} circuit_breaker_tripped = {}",
+        oracle_validity,
+        circuit_breaker_tripped
+    )?;
+
+    let (bounded_peg, fee_budget) =
+        repeg::calculate_bounded_peg_and_budget(market, oracle_price_data)?;
+
+    if bounded_peg == market.amm.peg_multiplier {
+        return Err(ErrorCode::InvalidRepegRedundant);
+    }
+
+    let (repegged_market, repegged_cost) =
+        repeg::adjust_amm(market, bounded_peg, fee_budget, true)?;
+
+    if repegged_market.amm.peg_multiplier == market.amm.peg_multiplier {
+        return Err(ErrorCode::InvalidRepegRedundant);
+    }
+
+    let cost_applied = apply_cost_to_market(market, repegged_cost, true)?;
+    if !cost_applied {
+        return Err(ErrorCode::InvalidRepegProfitability);
+    }
+
+    cp_curve::update_k(
+        market,
+        &UpdateKResult {
+            sqrt_k: repegged_market.amm.sqrt_k,
+            base_asset_reserve: repegged_market.amm.base_asset_reserve,
+            quote_asset_reserve: repegged_market.amm.quote_asset_reserve,
+        },
+    )?;
+    market.amm.peg_multiplier = repegged_market.amm.peg_multiplier;
+
+    let reserve_price_after = market.amm.reserve_price()?;
+    update_spreads(&mut market.amm, reserve_price_after)?;
+
+    Ok(repegged_cost)
+}
+
+/// Sets or clears the market's oracle circuit breaker based on the oracle's divergence from the
+/// 5min mark twap. Returns whether the breaker is tripped
+pub fn update_oracle_circuit_breaker(
+    market: &mut PerpMarket,
+    oracle_price_data: &OraclePriceData,
+) -> DriftResult<bool> {
+    let tripped = oracle::is_oracle_outside_circuit_breaker_band(
+        oracle_price_data.price,
+        market.amm.last_mark_price_twap_5min,
+        market.oracle_circuit_breaker_band,
+    )?;
+
+    if tripped != market.is_operation_paused(PerpOperation::OracleCircuitBreaker)? {
+        msg!(
+            "perp market {} oracle circuit breaker tripped={} (oracle_price={} last_mark_price_twap_5min={})",
+            market.market_index,
+            tripped,
+            oracle_price_data.price,
+            market.amm.last_mark_price_twap_5min
+        );
+        market.set_operation_paused(PerpOperation::OracleCircuitBreaker, tripped)?;
+    }
+
+    Ok(tripped)
+}
+
 pub fn update_amm_and_check_validity(
     market: &mut PerpMarket,
     oracle_price_data: &OraclePriceData,
@@ -232,7 +347,10 @@ pub fn update_amm_and_check_validity(
     let oracle_validity = oracle_validity(
         risk_ema_price,
         oracle_price_data,
-        &state.oracle_guard_rails.validity,
+        &state
+            .oracle_guard_rails
+            .validity
+            .with_override(&market.amm.validity_guard_rails_override),
     )?;
 
     validate!(
@@ -297,6 +415,7 @@ pub fn settle_expired_market(
     spot_market_map: &SpotMarketMap,
     _state: &State,
     clock: &Clock,
+    perp_market_delisting: Option<&PerpMarketDelisting>,
 ) -> DriftResult {
     let now = clock.unix_timestamp;
     let market = &mut market_map.get_ref_mut(&market_index)?;
@@ -321,7 +440,100 @@ pub fn settle_expired_market(
         "Outstanding LP in market"
     )?;
 
+    validate!(
+        market.contract_type != ContractType::Prediction,
+        ErrorCode::InvalidPredictionMarket,
+        "prediction markets settle through resolve_prediction_market"
+    )?;
+
+    // delisted markets settle at the twap sampled over their settlement window, falling back to
+    // the oracle twap if no keeper sampled it
+    let settlement_price_twap = if market.amm.delisting_enabled {
+        let perp_market_delisting = match perp_market_delisting {
+            Some(perp_market_delisting) => perp_market_delisting,
+            None => {
+                msg!(
+                    "perp market delisting not passed for market {}",
+                    market_index
+                );
+                return Err(ErrorCode::InvalidPerpMarketDelisting);
+            }
+        };
+
+        validate!(
+            perp_market_delisting.market_index == market_index,
+            ErrorCode::InvalidPerpMarketDelisting,
+            "perp market delisting is for market {}",
+            perp_market_delisting.market_index
+        )?;
+
+        perp_market_delisting.settlement_price_twap(market.expiry_ts)?
+    } else {
+        None
+    };
+
+    let target_expiry_price =
+        settlement_price_twap.unwrap_or(market.amm.historical_oracle_data.last_oracle_price_twap);
+    validate!(
+        target_expiry_price > 0,
+        ErrorCode::MarketSettlementTargetPriceInvalid,
+        "target_expiry_price <= 0 {}",
+        target_expiry_price
+    )?;
+
     let spot_market = &mut spot_market_map.get_ref_mut(&QUOTE_SPOT_MARKET_INDEX)?;
+    settle_market_at_target_price(market, spot_market, target_expiry_price)
+}
+
+/// settles a prediction market at 0 or 1 depending on the event's outcome
+pub fn resolve_prediction_market(
+    market: &mut PerpMarket,
+    spot_market: &mut SpotMarket,
+    now: i64,
+    outcome: bool,
+) -> DriftResult {
+    let market_index = market.market_index;
+
+    validate!(
+        market.contract_type == ContractType::Prediction,
+        ErrorCode::InvalidPredictionMarket,
+        "market {} is not a prediction market",
+        market_index
+    )?;
+
+    validate!(
+        !matches!(
+            market.status,
+            MarketStatus::Settlement | MarketStatus::Delisted
+        ),
+        ErrorCode::InvalidPredictionMarket,
+        "market {} already resolved",
+        market_index
+    )?;
+
+    validate!(
+        market.amm.base_asset_amount_with_unsettled_lp == 0 && market.amm.user_lp_shares == 0,
+        ErrorCode::MarketSettlementRequiresSettledLP,
+        "Outstanding LP in market"
+    )?;
+
+    // resolving expires the market so it can't be traded at prices the outcome made stale
+    if market.expiry_ts == 0 || market.expiry_ts > now {
+        market.expiry_ts = now;
+    }
+
+    let target_expiry_price = if outcome { PRICE_PRECISION_I64 } else { 0 };
+
+    settle_market_at_target_price(market, spot_market, target_expiry_price)
+}
+
+/// moves the fee pool's surplus to the pnl pool and sets the expiry price as close to the
+/// target as the pnl pool can pay out
+fn settle_market_at_target_price(
+    market: &mut PerpMarket,
+    spot_market: &mut SpotMarket,
+    target_expiry_price: i64,
+) -> DriftResult {
     let fee_reserved_for_protocol = repeg::get_total_fee_lower_bound(market)?
         .safe_add(market.amm.total_liquidation_fee)?
         .safe_sub(market.amm.total_fee_withdrawn)?
@@ -401,19 +613,79 @@ pub fn settle_expired_market(
         "Only support bank.decimals == QUOTE_PRECISION"
     )?;
 
-    let target_expiry_price = market.amm.historical_oracle_data.last_oracle_price_twap;
-    validate!(
-        target_expiry_price > 0,
-        ErrorCode::MarketSettlementTargetPriceInvalid,
-        "target_expiry_price <= 0 {}",
-        target_expiry_price
-    )?;
-
-    let expiry_price =
+    let mut expiry_price =
         amm::calculate_expiry_price(&market.amm, target_expiry_price, pnl_pool_amount)?;
 
+    if market.contract_type == ContractType::Prediction {
+        expiry_price = expiry_price.max(0).min(PRICE_PRECISION_I64);
+    }
+
     market.expiry_price = expiry_price;
     market.status = MarketStatus::Settlement;
 
     Ok(())
 }
+
+pub fn settle_expired_market_pools_to_revenue_pool(
+    perp_market: &mut PerpMarket,
+    spot_market: &mut SpotMarket,
+) -> DriftResult {
+    validate!(
+        perp_market.status == MarketStatus::Settlement,
+        ErrorCode::DefaultError,
+        "Market must in Settlement"
+    )?;
+
+    validate!(
+        perp_market.amm.base_asset_amount_long == 0
+            && perp_market.amm.base_asset_amount_short == 0
+            && perp_market.number_of_users_with_base == 0,
+        ErrorCode::DefaultError,
+        "outstanding base_asset_amounts must be balanced"
+    )?;
+
+    validate!(
+        amm::calculate_net_user_cost_basis(&perp_market.amm)? == 0,
+        ErrorCode::DefaultError,
+        "outstanding quote_asset_amounts must be balanced"
+    )?;
+
+    let fee_pool_token_amount = get_token_amount(
+        perp_market.amm.fee_pool.scaled_balance,
+        spot_market,
+        &SpotBalanceType::Deposit,
+    )?;
+    let pnl_pool_token_amount = get_token_amount(
+        perp_market.pnl_pool.scaled_balance,
+        spot_market,
+        &SpotBalanceType::Deposit,
+    )?;
+
+    update_spot_balances(
+        fee_pool_token_amount,
+        &SpotBalanceType::Borrow,
+        spot_market,
+        &mut perp_market.amm.fee_pool,
+        false,
+    )?;
+
+    update_spot_balances(
+        pnl_pool_token_amount,
+        &SpotBalanceType::Borrow,
+        spot_market,
+        &mut perp_market.pnl_pool,
+        false,
+    )?;
+
+    update_revenue_pool_balances(
+        pnl_pool_token_amount.safe_add(fee_pool_token_amount)?,
+        &SpotBalanceType::Deposit,
+        spot_market,
+    )?;
+
+    validate_spot_balances(spot_market)?;
+
+    perp_market.status = MarketStatus::Delisted;
+
+    Ok(())
+}
@@ -1,14 +1,15 @@
 use crate::controller::repeg::*;
 use crate::math::constants::{
     AMM_RESERVE_PRECISION, PEG_PRECISION, PRICE_PRECISION, PRICE_PRECISION_I64,
-    PRICE_PRECISION_U64, QUOTE_PRECISION,
+    PRICE_PRECISION_U64, QUOTE_PRECISION, QUOTE_PRECISION_I128, QUOTE_SPOT_MARKET_INDEX,
+    SPOT_BALANCE_PRECISION, SPOT_CUMULATIVE_INTEREST_PRECISION,
 };
 use crate::math::oracle::OracleValidity;
 use crate::math::repeg::{
     calculate_fee_pool, calculate_peg_from_target_price, calculate_repeg_cost,
 };
 use crate::state::oracle::HistoricalOracleData;
-use crate::state::perp_market::{ContractTier, AMM};
+use crate::state::perp_market::{ContractTier, ContractType, PerpOperation, PoolBalance, AMM};
 use crate::state::state::{PriceDivergenceGuardRails, ValidityGuardRails};
 
 #[test]
@@ -531,3 +532,201 @@ pub fn update_amm_larg_conf_w_neg_tfmd_test() {
     assert_eq!((oracle_price_data.price as u64) > bid, true);
     assert_eq!((oracle_price_data.price as u64) < ask, true);
 }
+
+#[test]
+pub fn update_oracle_circuit_breaker_test() {
+    let mut market = PerpMarket {
+        amm: AMM {
+            last_mark_price_twap_5min: 20 * PRICE_PRECISION_U64,
+            ..AMM::default()
+        },
+        oracle_circuit_breaker_band: 100_000, // 10%
+        ..PerpMarket::default()
+    };
+
+    let mut oracle_price_data = OraclePriceData {
+        price: 21 * PRICE_PRECISION_I64,
+        confidence: 0,
+        delay: 1,
+        has_sufficient_number_of_data_points: true,
+    };
+
+    // 5% away from mark twap
+    assert!(!update_oracle_circuit_breaker(&mut market, &oracle_price_data).unwrap());
+    assert_eq!(market.paused_operations, 0);
+
+    // 25% away from mark twap
+    oracle_price_data.price = 15 * PRICE_PRECISION_I64;
+    assert!(update_oracle_circuit_breaker(&mut market, &oracle_price_data).unwrap());
+    assert!(market
+        .is_operation_paused(PerpOperation::OracleCircuitBreaker)
+        .unwrap());
+
+    // resets once the oracle is back within the band
+    oracle_price_data.price = 19 * PRICE_PRECISION_I64;
+    assert!(!update_oracle_circuit_breaker(&mut market, &oracle_price_data).unwrap());
+    assert!(!market
+        .is_operation_paused(PerpOperation::OracleCircuitBreaker)
+        .unwrap());
+
+    // disabled band
+    market.oracle_circuit_breaker_band = 0;
+    oracle_price_data.price = 10 * PRICE_PRECISION_I64;
+    assert!(!update_oracle_circuit_breaker(&mut market, &oracle_price_data).unwrap());
+}
+
+#[test]
+pub fn update_amm_peg_test() {
+    let mut market = PerpMarket {
+        amm: AMM {
+            base_asset_reserve: 65 * AMM_RESERVE_PRECISION,
+            quote_asset_reserve: 63015384615,
+            terminal_quote_asset_reserve: 64 * AMM_RESERVE_PRECISION,
+            sqrt_k: 64 * AMM_RESERVE_PRECISION,
+            peg_multiplier: 19_400 * PEG_PRECISION,
+            base_asset_amount_with_amm: -(AMM_RESERVE_PRECISION as i128),
+            mark_std: PRICE_PRECISION as u64,
+            last_mark_price_twap_ts: 0,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price_twap: 19_400 * PRICE_PRECISION_I64,
+                ..HistoricalOracleData::default()
+            },
+            base_spread: 250,
+            curve_update_intensity: 100,
+            max_spread: 55500,
+            concentration_coef: 31020710, //unrealistic but for poc
+            total_exchange_fee: QUOTE_PRECISION,
+            total_fee_minus_distributions: (40 * QUOTE_PRECISION) as i128,
+            ..AMM::default()
+        },
+        status: MarketStatus::Active,
+        contract_tier: ContractTier::B,
+        margin_ratio_initial: 555,
+        ..PerpMarket::default()
+    };
+    let (_, new_terminal_base_reserve) = amm::calculate_terminal_reserves(&market.amm).unwrap();
+    let (min_base_asset_reserve, max_base_asset_reserve) =
+        amm::calculate_bid_ask_bounds(market.amm.concentration_coef, new_terminal_base_reserve)
+            .unwrap();
+    market.amm.min_base_asset_reserve = min_base_asset_reserve;
+    market.amm.max_base_asset_reserve = max_base_asset_reserve;
+
+    let state = State {
+        oracle_guard_rails: OracleGuardRails {
+            price_divergence: PriceDivergenceGuardRails {
+                mark_oracle_divergence_numerator: 1,
+                mark_oracle_divergence_denominator: 10,
+            },
+            validity: ValidityGuardRails {
+                slots_before_stale_for_amm: 10,     // 5s
+                slots_before_stale_for_margin: 120, // 60s
+                confidence_interval_max_size: 1000,
+                too_volatile_ratio: 5,
+            },
+        },
+        ..State::default()
+    };
+
+    let oracle_price_data = OraclePriceData {
+        price: 18_900 * PRICE_PRECISION_I64,
+        confidence: 0,
+        delay: 2,
+        has_sufficient_number_of_data_points: true,
+    };
+
+    // formulaic repegs disabled
+    let mut disabled_market = market;
+    disabled_market.amm.curve_update_intensity = 0;
+    assert!(update_amm_peg(&mut disabled_market, &oracle_price_data, &state).is_err());
+
+    // invalid oracle
+    let invalid_oracle_price_data = OraclePriceData {
+        price: 0,
+        ..oracle_price_data
+    };
+    let mut invalid_oracle_market = market;
+    assert!(update_amm_peg(
+        &mut invalid_oracle_market,
+        &invalid_oracle_price_data,
+        &state
+    )
+    .is_err());
+
+    let total_fee_minus_distributions_before = market.amm.total_fee_minus_distributions;
+    let cost = update_amm_peg(&mut market, &oracle_price_data, &state).unwrap();
+
+    // moving the peg up while users are net short earns the amm revenue
+    assert!(cost < 0);
+    assert_eq!(
+        market.amm.total_fee_minus_distributions,
+        total_fee_minus_distributions_before - cost
+    );
+    assert_eq!(
+        market.amm.peg_multiplier,
+        calculate_peg_from_target_price(
+            market.amm.quote_asset_reserve,
+            market.amm.base_asset_reserve,
+            oracle_price_data.price as u64,
+        )
+        .unwrap()
+    );
+
+    // already at the oracle
+    assert!(update_amm_peg(&mut market, &oracle_price_data, &state).is_err());
+}
+
+#[test]
+pub fn resolve_prediction_market_test() {
+    let now = 1662065595_i64;
+
+    // net users are long .5 at .60 and the pnl pool holds .50
+    let mut market = PerpMarket {
+        amm: AMM {
+            base_asset_amount_with_amm: (AMM_RESERVE_PRECISION / 2) as i128,
+            base_asset_amount_long: (AMM_RESERVE_PRECISION / 2) as i128,
+            quote_asset_amount: -(QUOTE_PRECISION_I128 * 3 / 10),
+            ..AMM::default()
+        },
+        contract_type: ContractType::Prediction,
+        status: MarketStatus::Active,
+        pnl_pool: PoolBalance {
+            scaled_balance: SPOT_BALANCE_PRECISION / 2,
+            market_index: QUOTE_SPOT_MARKET_INDEX,
+            ..PoolBalance::default()
+        },
+        ..PerpMarket::default()
+    };
+
+    let mut spot_market = SpotMarket {
+        market_index: QUOTE_SPOT_MARKET_INDEX,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        ..SpotMarket::default()
+    };
+
+    let mut no_market = market;
+    resolve_prediction_market(&mut no_market, &mut spot_market, now, false).unwrap();
+    assert_eq!(no_market.expiry_price, 0);
+    assert_eq!(no_market.status, MarketStatus::Settlement);
+    assert_eq!(no_market.expiry_ts, now);
+
+    // the pnl pool covers longs being paid out at 1
+    resolve_prediction_market(&mut market, &mut spot_market, now, true).unwrap();
+    assert_eq!(market.expiry_price, PRICE_PRECISION_I64 - 1);
+    assert_eq!(market.status, MarketStatus::Settlement);
+
+    // can only resolve once
+    assert_eq!(
+        resolve_prediction_market(&mut market, &mut spot_market, now, false),
+        Err(ErrorCode::InvalidPredictionMarket)
+    );
+
+    let mut perpetual_market = PerpMarket {
+        status: MarketStatus::Active,
+        ..PerpMarket::default()
+    };
+    assert_eq!(
+        resolve_prediction_market(&mut perpetual_market, &mut spot_market, now, true),
+        Err(ErrorCode::InvalidPredictionMarket)
+    );
+}
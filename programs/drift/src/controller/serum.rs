@@ -1,9 +1,9 @@
 use crate::error::{DriftResult, ErrorCode};
 use crate::signer::get_signer_seeds;
-use anchor_lang::accounts::account::Account;
+use crate::state::token::SpotMarketVault;
 use anchor_lang::prelude::{AccountInfo, Program, Pubkey, Rent, Sysvar};
 use anchor_lang::ToAccountInfo;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token::Token;
 use serum_dex::instruction::NewOrderInstructionV3;
 use solana_program::instruction::{AccountMeta, Instruction};
 use solana_program::msg;
@@ -58,8 +58,8 @@ pub struct SerumFulfillmentParams<'a, 'b> {
     pub serum_quote_vault: &'a AccountInfo<'b>,
     pub serum_open_orders: &'a AccountInfo<'b>,
     pub token_program: Program<'b, Token>,
-    pub base_market_vault: Box<Account<'b, TokenAccount>>,
-    pub quote_market_vault: Box<Account<'b, TokenAccount>>,
+    pub base_market_vault: SpotMarketVault<'b>,
+    pub quote_market_vault: SpotMarketVault<'b>,
     pub srm_vault: &'a AccountInfo<'b>,
     pub serum_signer: &'a AccountInfo<'b>,
     pub signer_nonce: u8,
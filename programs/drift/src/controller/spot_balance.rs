@@ -21,7 +21,7 @@ use crate::math::stats::{calculate_new_twap, calculate_weighted_average};
 use crate::state::events::SpotInterestRecord;
 use crate::state::oracle::OraclePriceData;
 use crate::state::perp_market::{MarketStatus, PerpMarket};
-use crate::state::spot_market::{SpotBalance, SpotBalanceType, SpotMarket};
+use crate::state::spot_market::{SpotBalance, SpotBalanceType, SpotMarket, SpotOperation};
 use crate::validate;
 
 use crate::math::oracle::{is_oracle_valid_for_action, DriftAction};
@@ -124,7 +124,9 @@ pub fn update_spot_market_cumulative_interest(
     oracle_price_data: Option<&OraclePriceData>,
     now: i64,
 ) -> DriftResult {
-    if spot_market.status == MarketStatus::FundingPaused {
+    if spot_market.status == MarketStatus::FundingPaused
+        || spot_market.is_operation_paused(SpotOperation::UpdateCumulativeInterest)?
+    {
         update_spot_market_twap_stats(spot_market, oracle_price_data, now)?;
         return Ok(());
     }
@@ -402,7 +404,11 @@ pub fn update_spot_market_and_check_validity(
     // 1 hour EMA
     let risk_ema_price = spot_market.historical_oracle_data.last_oracle_price_twap;
 
-    let oracle_validity = oracle_validity(risk_ema_price, oracle_price_data, validity_guard_rails)?;
+    let oracle_validity = oracle_validity(
+        risk_ema_price,
+        oracle_price_data,
+        &validity_guard_rails.with_override(&spot_market.validity_guard_rails_override),
+    )?;
 
     validate!(
         is_oracle_valid_for_action(oracle_validity, action)?,
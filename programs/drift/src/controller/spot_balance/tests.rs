@@ -8,24 +8,27 @@ use crate::controller::spot_balance::*;
 use crate::controller::spot_position::update_spot_balances_and_cumulative_deposits_with_limits;
 use crate::create_account_info;
 use crate::create_anchor_account_info;
+use crate::error::ErrorCode;
 use crate::math::constants::{
     AMM_RESERVE_PRECISION, BASE_PRECISION_I128, BASE_PRECISION_I64, LIQUIDATION_FEE_PRECISION,
-    PEG_PRECISION, QUOTE_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64, QUOTE_PRECISION_U64,
-    SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
-    SPOT_RATE_PRECISION_U32, SPOT_UTILIZATION_PRECISION, SPOT_UTILIZATION_PRECISION_U32,
-    SPOT_WEIGHT_PRECISION,
+    MAX_USER_ORDERS, PEG_PRECISION, QUOTE_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64,
+    QUOTE_PRECISION_U64, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+    SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_RATE_PRECISION_U32, SPOT_UTILIZATION_PRECISION,
+    SPOT_UTILIZATION_PRECISION_U32, SPOT_WEIGHT_PRECISION,
 };
 use crate::math::margin::{
     calculate_margin_requirement_and_total_collateral, MarginRequirementType,
 };
 use crate::math::spot_withdraw::{
-    calculate_max_borrow_token_amount, calculate_min_deposit_token, check_withdraw_limits,
+    calculate_max_borrow_token_amount, calculate_min_deposit_token,
+    calculate_min_deposit_token_for_net_withdraws, check_max_token_borrows,
+    check_net_withdraw_limit, check_withdraw_limits,
 };
 use crate::state::oracle::{HistoricalOracleData, OracleSource};
 use crate::state::oracle_map::OracleMap;
 use crate::state::perp_market::{MarketStatus, PerpMarket, AMM};
 use crate::state::perp_market_map::PerpMarketMap;
-use crate::state::spot_market::{InsuranceFund, SpotBalanceType, SpotMarket};
+use crate::state::spot_market::{InsuranceFund, SpotBalanceType, SpotMarket, SpotOperation};
 use crate::state::spot_market_map::SpotMarketMap;
 use crate::state::user::{Order, PerpPosition, SpotPosition, User};
 use crate::test_utils::*;
@@ -125,7 +128,7 @@ fn test_daily_withdraw_limits() {
         ..SpotPosition::default()
     };
     let mut user = User {
-        orders: [Order::default(); 32],
+        orders: [Order::default(); MAX_USER_ORDERS],
         perp_positions: [PerpPosition::default(); 8],
         spot_positions,
         ..User::default()
@@ -443,7 +446,7 @@ fn test_check_withdraw_limits() {
         ..SpotPosition::default()
     };
     let user = User {
-        orders: [Order::default(); 32],
+        orders: [Order::default(); MAX_USER_ORDERS],
         perp_positions: [PerpPosition::default(); 8],
         spot_positions,
         ..User::default()
@@ -466,6 +469,131 @@ fn test_check_withdraw_limits() {
     assert!(!valid_withdraw);
 }
 
+#[test]
+fn test_check_max_token_borrows() {
+    let mut spot_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        deposit_balance: 10 * SPOT_BALANCE_PRECISION,
+        borrow_balance: 5 * SPOT_BALANCE_PRECISION,
+        status: MarketStatus::Active,
+        ..SpotMarket::default()
+    };
+
+    // no cap
+    assert!(check_max_token_borrows(&spot_market).unwrap());
+
+    spot_market.max_token_borrows = 5 * QUOTE_PRECISION_U64;
+    assert!(check_max_token_borrows(&spot_market).unwrap());
+
+    spot_market.max_token_borrows = 5 * QUOTE_PRECISION_U64 - 1;
+    assert!(!check_max_token_borrows(&spot_market).unwrap());
+}
+
+#[test]
+fn test_check_net_withdraw_limit() {
+    let mdt = calculate_min_deposit_token_for_net_withdraws(QUOTE_PRECISION, 1000).unwrap();
+    assert_eq!(mdt, QUOTE_PRECISION - QUOTE_PRECISION / 10);
+
+    // capped at 100%
+    let mdt = calculate_min_deposit_token_for_net_withdraws(QUOTE_PRECISION, 20000).unwrap();
+    assert_eq!(mdt, 0);
+
+    let mut spot_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        deposit_balance: 8 * SPOT_BALANCE_PRECISION,
+        deposit_token_twap: 10 * QUOTE_PRECISION_U64,
+        status: MarketStatus::Active,
+        ..SpotMarket::default()
+    };
+
+    // guard disabled
+    assert!(check_net_withdraw_limit(&spot_market).unwrap());
+
+    // 20% of twap has left, within a 25% guard
+    spot_market.max_net_withdraw_bps = 2500;
+    assert!(check_net_withdraw_limit(&spot_market).unwrap());
+
+    // 20% of twap has left, breaching a 10% guard
+    spot_market.max_net_withdraw_bps = 1000;
+    assert!(!check_net_withdraw_limit(&spot_market).unwrap());
+}
+
+#[test]
+fn test_paused_spot_operations() {
+    let now = 1_000_000;
+    let mut spot_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        deposit_balance: 100 * SPOT_BALANCE_PRECISION,
+        borrow_balance: 50 * SPOT_BALANCE_PRECISION,
+        optimal_utilization: SPOT_UTILIZATION_PRECISION_U32 / 2,
+        optimal_borrow_rate: SPOT_RATE_PRECISION_U32 / 10,
+        max_borrow_rate: SPOT_RATE_PRECISION_U32,
+        last_interest_ts: now as u64,
+        last_twap_ts: now as u64,
+        status: MarketStatus::Active,
+        paused_operations: SpotOperation::UpdateCumulativeInterest as u8
+            | SpotOperation::Withdraw as u8,
+        ..SpotMarket::default()
+    };
+
+    // interest doesnt accrue while paused
+    update_spot_market_cumulative_interest(&mut spot_market, None, now + 3600 * 24).unwrap();
+    assert_eq!(
+        spot_market.cumulative_deposit_interest,
+        SPOT_CUMULATIVE_INTEREST_PRECISION
+    );
+    assert_eq!(spot_market.last_interest_ts, now as u64);
+
+    let mut user = User {
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 10 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+
+    let spot_market_backup = spot_market;
+    let user_backup = user;
+    assert_eq!(
+        update_spot_balances_and_cumulative_deposits_with_limits(
+            QUOTE_PRECISION,
+            &SpotBalanceType::Borrow,
+            &mut spot_market,
+            &mut user,
+        ),
+        Err(ErrorCode::MarketWithdrawPaused)
+    );
+    spot_market = spot_market_backup;
+    user = user_backup;
+
+    spot_market.paused_operations = 0;
+
+    update_spot_market_cumulative_interest(&mut spot_market, None, now + 3600 * 24).unwrap();
+    assert!(spot_market.cumulative_deposit_interest > SPOT_CUMULATIVE_INTEREST_PRECISION);
+
+    update_spot_balances_and_cumulative_deposits_with_limits(
+        QUOTE_PRECISION,
+        &SpotBalanceType::Borrow,
+        &mut spot_market,
+        &mut user,
+    )
+    .unwrap();
+}
+
 #[test]
 fn check_fee_collection() {
     let mut now = 0_i64;
@@ -567,7 +695,7 @@ fn check_fee_collection() {
         ..SpotPosition::default()
     };
     let mut user = User {
-        orders: [Order::default(); 32],
+        orders: [Order::default(); MAX_USER_ORDERS],
         perp_positions: [PerpPosition::default(); 8],
         spot_positions,
         ..User::default()
@@ -932,7 +1060,7 @@ fn check_fee_collection_larger_nums() {
         ..SpotPosition::default()
     };
     let mut user = User {
-        orders: [Order::default(); 32],
+        orders: [Order::default(); MAX_USER_ORDERS],
         perp_positions: [PerpPosition::default(); 8],
         spot_positions,
         ..User::default()
@@ -1222,7 +1350,7 @@ fn attempt_borrow_with_massive_upnl() {
     };
 
     let user = User {
-        orders: [Order::default(); 32],
+        orders: [Order::default(); MAX_USER_ORDERS],
         perp_positions: get_positions(PerpPosition {
             market_index: 0,
             base_asset_amount: 1000 * BASE_PRECISION_I64,
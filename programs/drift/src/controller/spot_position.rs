@@ -6,12 +6,16 @@ use crate::error::DriftResult;
 use crate::error::ErrorCode;
 use crate::math::casting::Cast;
 use crate::math::safe_math::SafeMath;
-use crate::math::spot_withdraw::check_withdraw_limits;
+use crate::math::spot_withdraw::{
+    check_max_token_borrows, check_net_withdraw_limit, check_withdraw_limits,
+};
 use crate::math_error;
 use crate::safe_decrement;
 use crate::safe_increment;
 use crate::state::perp_market::MarketStatus;
-use crate::state::spot_market::{AssetTier, SpotBalance, SpotBalanceType, SpotMarket};
+use crate::state::spot_market::{
+    AssetTier, SpotBalance, SpotBalanceType, SpotMarket, SpotOperation,
+};
 use crate::state::user::{SpotPosition, User};
 
 use crate::validate;
@@ -124,6 +128,27 @@ pub fn update_spot_balances_and_cumulative_deposits_with_limits(
         user.authority
     )?;
 
+    validate!(
+        check_net_withdraw_limit(spot_market)?,
+        ErrorCode::NetWithdrawLimit,
+        "Spot Market {} has hit net withdraw limit. Attempted withdraw amount of {} by {}",
+        spot_market.market_index,
+        token_amount,
+        user.authority
+    )?;
+
+    if user.spot_positions[spot_position_index].balance_type() == &SpotBalanceType::Borrow {
+        validate!(
+            check_max_token_borrows(spot_market)?,
+            ErrorCode::MaxBorrow,
+            "Spot Market {} max borrows: {}. Attempted borrow amount of {} by {}",
+            spot_market.market_index,
+            spot_market.max_token_borrows,
+            token_amount,
+            user.authority
+        )?;
+    }
+
     validate!(
         matches!(
             spot_market.status,
@@ -139,6 +164,13 @@ pub fn update_spot_balances_and_cumulative_deposits_with_limits(
         spot_market.market_index
     )?;
 
+    validate!(
+        !spot_market.is_operation_paused(SpotOperation::Withdraw)?,
+        ErrorCode::MarketWithdrawPaused,
+        "Spot Market {} withdraws are currently paused",
+        spot_market.market_index
+    )?;
+
     validate!(
         !(spot_market.asset_tier == AssetTier::Protected
             && user.spot_positions[spot_position_index].balance_type() == &SpotBalanceType::Borrow),
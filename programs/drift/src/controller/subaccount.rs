@@ -0,0 +1,199 @@
+use anchor_lang::prelude::*;
+
+use crate::controller;
+use crate::error::{DriftResult, ErrorCode};
+use crate::get_then_update_id;
+use crate::math::casting::Cast;
+use crate::math::margin::{meets_withdraw_margin_requirement, validate_spot_margin_trading};
+use crate::math::safe_math::SafeMath;
+use crate::state::events::{DepositDirection, DepositExplanation, DepositRecord};
+use crate::state::oracle_map::OracleMap;
+use crate::state::perp_market_map::PerpMarketMap;
+use crate::state::spot_market::SpotBalanceType;
+use crate::state::spot_market_map::SpotMarketMap;
+use crate::state::user::User;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+/// only idle deposits move, borrows and balances backing open orders stay put
+pub fn get_transferable_spot_market_indexes(user: &User) -> Vec<u16> {
+    user.spot_positions
+        .iter()
+        .filter(|spot_position| {
+            spot_position.balance_type == SpotBalanceType::Deposit
+                && spot_position.scaled_balance != 0
+                && spot_position.open_orders == 0
+        })
+        .map(|spot_position| spot_position.market_index)
+        .collect::<Vec<u16>>()
+}
+
+/// Moves idle deposits and the collateral on flat isolated perp positions from from_user to
+/// to_user. Only from_user is margin checked afterwards, to_user only ever gains collateral
+pub fn transfer_positions_between_subaccounts(
+    authority: &Pubkey,
+    from_user_key: &Pubkey,
+    from_user: &mut User,
+    to_user_key: &Pubkey,
+    to_user: &mut User,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    now: i64,
+    slot: u64,
+) -> DriftResult {
+    validate!(
+        from_user.authority == *authority && to_user.authority == *authority,
+        ErrorCode::CantTransferBetweenDifferentAuthorities,
+        "from_user and to_user must both belong to the signing authority"
+    )?;
+    validate!(
+        !to_user.is_bankrupt(),
+        ErrorCode::UserBankrupt,
+        "to_user bankrupt"
+    )?;
+    validate!(
+        !from_user.is_bankrupt(),
+        ErrorCode::UserBankrupt,
+        "from_user bankrupt"
+    )?;
+    validate!(
+        !from_user.is_being_liquidated(),
+        ErrorCode::UserIsBeingLiquidated,
+        "from_user being liquidated"
+    )?;
+
+    validate!(
+        from_user_key != to_user_key,
+        ErrorCode::CantTransferBetweenSameUserAccount,
+        "cant transfer between the same user account"
+    )?;
+
+    for market_index in get_transferable_spot_market_indexes(from_user) {
+        let spot_market = &mut spot_market_map.get_ref_mut(&market_index)?;
+        let oracle_price_data = oracle_map.get_price_data(&spot_market.oracle)?;
+        let oracle_price = oracle_price_data.price;
+        controller::spot_balance::update_spot_market_cumulative_interest(
+            spot_market,
+            Some(oracle_price_data),
+            now,
+        )?;
+
+        // scaled balances move as is so the market totals are untouched
+        let from_spot_position = from_user.get_spot_position_mut(market_index)?;
+        let amount = from_spot_position
+            .get_token_amount(spot_market)?
+            .cast::<u64>()?;
+        let scaled_balance = from_spot_position.scaled_balance;
+        let cumulative_deposits = from_spot_position.cumulative_deposits;
+        from_spot_position.scaled_balance = 0;
+        from_spot_position.cumulative_deposits = 0;
+
+        let to_spot_position = to_user.force_get_spot_position_mut(market_index)?;
+        validate!(
+            to_spot_position.balance_type == SpotBalanceType::Deposit
+                || to_spot_position.scaled_balance == 0,
+            ErrorCode::InvalidSpotPosition,
+            "to_user has a borrow in spot market {}",
+            market_index
+        )?;
+        to_spot_position.balance_type = SpotBalanceType::Deposit;
+        to_spot_position.scaled_balance =
+            to_spot_position.scaled_balance.safe_add(scaled_balance)?;
+        to_spot_position.cumulative_deposits = to_spot_position
+            .cumulative_deposits
+            .safe_add(cumulative_deposits)?;
+
+        let precision = spot_market.get_precision().cast()?;
+        from_user.increment_total_withdraws(amount, oracle_price, precision)?;
+        to_user.increment_total_deposits(amount, oracle_price, precision)?;
+
+        let deposit_record_id = get_then_update_id!(spot_market, next_deposit_record_id);
+        emit!(DepositRecord {
+            ts: now,
+            deposit_record_id,
+            user_authority: *authority,
+            user: *from_user_key,
+            direction: DepositDirection::Withdraw,
+            amount,
+            oracle_price,
+            market_index,
+            market_deposit_balance: spot_market.deposit_balance,
+            market_withdraw_balance: spot_market.borrow_balance,
+            market_cumulative_deposit_interest: spot_market.cumulative_deposit_interest,
+            market_cumulative_borrow_interest: spot_market.cumulative_borrow_interest,
+            total_deposits_after: from_user.total_deposits,
+            total_withdraws_after: from_user.total_withdraws,
+            explanation: DepositExplanation::Transfer,
+            transfer_user: Some(*to_user_key),
+        });
+
+        let deposit_record_id = get_then_update_id!(spot_market, next_deposit_record_id);
+        emit!(DepositRecord {
+            ts: now,
+            deposit_record_id,
+            user_authority: *authority,
+            user: *to_user_key,
+            direction: DepositDirection::Deposit,
+            amount,
+            oracle_price,
+            market_index,
+            market_deposit_balance: spot_market.deposit_balance,
+            market_withdraw_balance: spot_market.borrow_balance,
+            market_cumulative_deposit_interest: spot_market.cumulative_deposit_interest,
+            market_cumulative_borrow_interest: spot_market.cumulative_borrow_interest,
+            total_deposits_after: to_user.total_deposits,
+            total_withdraws_after: to_user.total_withdraws,
+            explanation: DepositExplanation::Transfer,
+            transfer_user: Some(*from_user_key),
+        });
+    }
+
+    // collateral left on flat isolated positions follows into the same market on to_user
+    for perp_position in from_user.perp_positions.iter_mut() {
+        if !perp_position.is_isolated
+            || perp_position.isolated_collateral == 0
+            || perp_position.is_open_position()
+            || perp_position.quote_asset_amount != 0
+            || perp_position.has_open_order()
+            || perp_position.is_lp()
+        {
+            continue;
+        }
+
+        let market_index = perp_position.market_index;
+        let isolated_collateral = perp_position.isolated_collateral;
+        perp_position.isolated_collateral = 0;
+
+        let to_perp_position = to_user.force_get_perp_position_mut(market_index)?;
+        if to_perp_position.is_available() {
+            to_perp_position.is_isolated = true;
+        }
+
+        validate!(
+            to_perp_position.is_isolated,
+            ErrorCode::InvalidIsolatedPerpPosition,
+            "to_user perp position for market {} is not isolated",
+            market_index
+        )?;
+
+        to_perp_position.isolated_collateral = to_perp_position
+            .isolated_collateral
+            .safe_add(isolated_collateral)?;
+    }
+
+    validate!(
+        meets_withdraw_margin_requirement(from_user, perp_market_map, spot_market_map, oracle_map)?,
+        ErrorCode::InsufficientCollateral,
+        "From user does not meet initial margin requirement"
+    )?;
+
+    validate_spot_margin_trading(from_user, spot_market_map, oracle_map)?;
+
+    from_user.update_last_active_slot(slot);
+    to_user.update_last_active_slot(slot);
+
+    Ok(())
+}
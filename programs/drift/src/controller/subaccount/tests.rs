@@ -0,0 +1,655 @@
+use std::str::FromStr;
+
+use anchor_lang::Owner;
+use solana_program::pubkey::Pubkey;
+
+use crate::controller::subaccount::transfer_positions_between_subaccounts;
+use crate::create_account_info;
+use crate::create_anchor_account_info;
+use crate::error::ErrorCode;
+use crate::math::constants::{
+    AMM_RESERVE_PRECISION, BASE_PRECISION_I64, PEG_PRECISION, QUOTE_PRECISION_I128,
+    QUOTE_PRECISION_I64, QUOTE_PRECISION_U64, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+    SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+};
+use crate::math::margin::calculate_free_collateral;
+use crate::state::oracle::{HistoricalOracleData, OracleSource};
+use crate::state::oracle_map::OracleMap;
+use crate::state::perp_market::{MarketStatus, PerpMarket, AMM};
+use crate::state::perp_market_map::PerpMarketMap;
+use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+use crate::state::spot_market_map::SpotMarketMap;
+use crate::state::user::{PerpPosition, SpotPosition, User, UserStatus};
+use crate::test_utils::*;
+use crate::test_utils::{get_positions, get_pyth_price, get_spot_positions};
+
+#[test]
+pub fn spot_deposit_moves() {
+    let authority = Pubkey::new_unique();
+    let from_user_key = Pubkey::new_unique();
+    let to_user_key = Pubkey::new_unique();
+
+    let mut oracle_map = OracleMap::empty();
+    let perp_market_map = PerpMarketMap::empty();
+
+    let mut usdc_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        initial_asset_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+        deposit_balance: 100 * SPOT_BALANCE_PRECISION,
+        ..SpotMarket::default()
+    };
+    create_anchor_account_info!(usdc_market, SpotMarket, usdc_spot_market_account_info);
+    let spot_market_map = SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+    let mut from_user = User {
+        authority,
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+            cumulative_deposits: 100 * QUOTE_PRECISION_I64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+    let mut to_user = User {
+        authority,
+        ..User::default()
+    };
+
+    transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        1,
+    )
+    .unwrap();
+
+    assert_eq!(from_user.spot_positions[0].scaled_balance, 0);
+    assert_eq!(from_user.spot_positions[0].cumulative_deposits, 0);
+    assert_eq!(from_user.total_withdraws, 100 * QUOTE_PRECISION_U64);
+
+    assert_eq!(to_user.spot_positions[0].market_index, 0);
+    assert_eq!(
+        to_user.spot_positions[0].balance_type,
+        SpotBalanceType::Deposit
+    );
+    assert_eq!(
+        to_user.spot_positions[0].scaled_balance,
+        100 * SPOT_BALANCE_PRECISION_U64
+    );
+    assert_eq!(
+        to_user.spot_positions[0].cumulative_deposits,
+        100 * QUOTE_PRECISION_I64
+    );
+    assert_eq!(to_user.total_deposits, 100 * QUOTE_PRECISION_U64);
+    assert_eq!(to_user.last_active_slot, 1);
+
+    // scaled balances move as is, the market totals are untouched
+    let usdc_market = spot_market_map.get_ref(&0).unwrap();
+    assert_eq!(usdc_market.deposit_balance, 100 * SPOT_BALANCE_PRECISION);
+    assert_eq!(usdc_market.next_deposit_record_id, 2);
+}
+
+#[test]
+pub fn open_order_balances_stay() {
+    let authority = Pubkey::new_unique();
+    let from_user_key = Pubkey::new_unique();
+    let to_user_key = Pubkey::new_unique();
+
+    let mut oracle_map = OracleMap::empty();
+    let perp_market_map = PerpMarketMap::empty();
+
+    let mut usdc_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        initial_asset_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+        deposit_balance: 100 * SPOT_BALANCE_PRECISION,
+        ..SpotMarket::default()
+    };
+    create_anchor_account_info!(usdc_market, SpotMarket, usdc_spot_market_account_info);
+    let spot_market_map = SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+    let mut from_user = User {
+        authority,
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+            open_orders: 1,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+    let mut to_user = User {
+        authority,
+        ..User::default()
+    };
+
+    transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        1,
+    )
+    .unwrap();
+
+    assert_eq!(
+        from_user.spot_positions[0].scaled_balance,
+        100 * SPOT_BALANCE_PRECISION_U64
+    );
+    assert_eq!(to_user.spot_positions[0].scaled_balance, 0);
+}
+
+#[test]
+pub fn to_user_borrow_blocks_spot_move() {
+    let authority = Pubkey::new_unique();
+    let from_user_key = Pubkey::new_unique();
+    let to_user_key = Pubkey::new_unique();
+
+    let mut oracle_map = OracleMap::empty();
+    let perp_market_map = PerpMarketMap::empty();
+
+    let mut usdc_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        initial_asset_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+        deposit_balance: 100 * SPOT_BALANCE_PRECISION,
+        borrow_balance: 10 * SPOT_BALANCE_PRECISION,
+        ..SpotMarket::default()
+    };
+    create_anchor_account_info!(usdc_market, SpotMarket, usdc_spot_market_account_info);
+    let spot_market_map = SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+    let mut from_user = User {
+        authority,
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+    let mut to_user = User {
+        authority,
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Borrow,
+            scaled_balance: 10 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+
+    let result = transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        1,
+    );
+
+    assert_eq!(result, Err(ErrorCode::InvalidSpotPosition));
+}
+
+#[test]
+pub fn isolated_collateral_moves() {
+    let slot = 0_u64;
+    let authority = Pubkey::new_unique();
+    let from_user_key = Pubkey::new_unique();
+    let to_user_key = Pubkey::new_unique();
+
+    let mut oracle_price = get_pyth_price(100, 6);
+    let oracle_price_key =
+        Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+    let pyth_program = crate::ids::pyth_program::id();
+    create_account_info!(
+        oracle_price,
+        &oracle_price_key,
+        &pyth_program,
+        oracle_account_info
+    );
+    let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+    let mut market = PerpMarket {
+        amm: AMM {
+            base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            sqrt_k: 100 * AMM_RESERVE_PRECISION,
+            peg_multiplier: 100 * PEG_PRECISION,
+            order_step_size: 10000000,
+            oracle: oracle_price_key,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price: oracle_price.agg.price,
+                last_oracle_price_twap_5min: oracle_price.agg.price,
+                last_oracle_price_twap: oracle_price.agg.price,
+                ..HistoricalOracleData::default()
+            },
+            ..AMM::default()
+        },
+        margin_ratio_initial: 1000,
+        margin_ratio_maintenance: 500,
+        status: MarketStatus::Active,
+        ..PerpMarket::default()
+    };
+    create_anchor_account_info!(market, PerpMarket, market_account_info);
+    let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+    let mut usdc_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        initial_asset_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+        ..SpotMarket::default()
+    };
+    create_anchor_account_info!(usdc_market, SpotMarket, usdc_spot_market_account_info);
+    let spot_market_map = SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+    let mut from_user = User {
+        authority,
+        perp_positions: get_positions(PerpPosition {
+            market_index: 0,
+            is_isolated: true,
+            isolated_collateral: 50 * QUOTE_PRECISION_U64,
+            ..PerpPosition::default()
+        }),
+        ..User::default()
+    };
+    let mut to_user = User {
+        authority,
+        ..User::default()
+    };
+
+    transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        slot,
+    )
+    .unwrap();
+
+    assert_eq!(from_user.perp_positions[0].isolated_collateral, 0);
+    assert_eq!(to_user.perp_positions[0].market_index, 0);
+    assert!(to_user.perp_positions[0].is_isolated);
+    assert_eq!(
+        to_user.perp_positions[0].isolated_collateral,
+        50 * QUOTE_PRECISION_U64
+    );
+
+    // collateral can't land on a cross position in the same market
+    let mut from_user = User {
+        authority,
+        perp_positions: get_positions(PerpPosition {
+            market_index: 0,
+            is_isolated: true,
+            isolated_collateral: 50 * QUOTE_PRECISION_U64,
+            ..PerpPosition::default()
+        }),
+        ..User::default()
+    };
+    let mut to_user = User {
+        authority,
+        perp_positions: get_positions(PerpPosition {
+            market_index: 0,
+            base_asset_amount: BASE_PRECISION_I64,
+            quote_asset_amount: -100 * QUOTE_PRECISION_I64,
+            ..PerpPosition::default()
+        }),
+        ..User::default()
+    };
+
+    let result = transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        slot,
+    );
+
+    assert_eq!(result, Err(ErrorCode::InvalidIsolatedPerpPosition));
+}
+
+#[test]
+pub fn margin_checked_after_transfer() {
+    let slot = 0_u64;
+    let authority = Pubkey::new_unique();
+    let from_user_key = Pubkey::new_unique();
+    let to_user_key = Pubkey::new_unique();
+
+    let mut oracle_price = get_pyth_price(100, 6);
+    let oracle_price_key =
+        Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+    let pyth_program = crate::ids::pyth_program::id();
+    create_account_info!(
+        oracle_price,
+        &oracle_price_key,
+        &pyth_program,
+        oracle_account_info
+    );
+    let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+    let mut market = PerpMarket {
+        amm: AMM {
+            base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+            sqrt_k: 100 * AMM_RESERVE_PRECISION,
+            peg_multiplier: 100 * PEG_PRECISION,
+            order_step_size: 10000000,
+            oracle: oracle_price_key,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price: oracle_price.agg.price,
+                last_oracle_price_twap_5min: oracle_price.agg.price,
+                last_oracle_price_twap: oracle_price.agg.price,
+                ..HistoricalOracleData::default()
+            },
+            ..AMM::default()
+        },
+        margin_ratio_initial: 1000,
+        margin_ratio_maintenance: 500,
+        number_of_users_with_base: 1,
+        status: MarketStatus::Active,
+        ..PerpMarket::default()
+    };
+    create_anchor_account_info!(market, PerpMarket, market_account_info);
+    let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+    let mut usdc_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        initial_asset_weight: SPOT_WEIGHT_PRECISION,
+        maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+        deposit_balance: 200 * SPOT_BALANCE_PRECISION,
+        ..SpotMarket::default()
+    };
+    create_anchor_account_info!(usdc_market, SpotMarket, usdc_spot_market_account_info);
+    let spot_market_map = SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+    // from_user's deposit backs its perp position, moving it out leaves from_user short of margin
+    let mut from_user = User {
+        authority,
+        perp_positions: get_positions(PerpPosition {
+            market_index: 0,
+            base_asset_amount: BASE_PRECISION_I64,
+            quote_asset_amount: -100 * QUOTE_PRECISION_I64,
+            ..PerpPosition::default()
+        }),
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+    let mut to_user = User {
+        authority,
+        ..User::default()
+    };
+
+    let result = transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        slot,
+    );
+
+    assert_eq!(result, Err(ErrorCode::InsufficientCollateral));
+
+    // to_user only gains collateral, an under collateralized to_user can be topped up
+    let mut from_user = User {
+        authority,
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+    let mut to_user = User {
+        authority,
+        perp_positions: get_positions(PerpPosition {
+            market_index: 0,
+            base_asset_amount: BASE_PRECISION_I64,
+            quote_asset_amount: -100 * QUOTE_PRECISION_I64,
+            ..PerpPosition::default()
+        }),
+        ..User::default()
+    };
+
+    let free_collateral_before = calculate_free_collateral(
+        &to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+    )
+    .unwrap();
+    assert!(free_collateral_before < 0);
+
+    transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        slot,
+    )
+    .unwrap();
+
+    let free_collateral_after = calculate_free_collateral(
+        &to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+    )
+    .unwrap();
+    assert_eq!(
+        free_collateral_after,
+        free_collateral_before + 100 * QUOTE_PRECISION_I128
+    );
+    assert!(free_collateral_after > 0);
+}
+
+#[test]
+pub fn same_authority_required() {
+    let authority = Pubkey::new_unique();
+    let from_user_key = Pubkey::new_unique();
+    let to_user_key = Pubkey::new_unique();
+
+    let mut oracle_map = OracleMap::empty();
+    let perp_market_map = PerpMarketMap::empty();
+
+    let mut usdc_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        deposit_balance: 100 * SPOT_BALANCE_PRECISION,
+        ..SpotMarket::default()
+    };
+    create_anchor_account_info!(usdc_market, SpotMarket, usdc_spot_market_account_info);
+    let spot_market_map = SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+    let mut from_user = User {
+        authority,
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+    let mut to_user = User {
+        authority: Pubkey::new_unique(),
+        ..User::default()
+    };
+
+    let result = transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        1,
+    );
+
+    assert_eq!(
+        result,
+        Err(ErrorCode::CantTransferBetweenDifferentAuthorities)
+    );
+    assert_eq!(
+        from_user.spot_positions[0].scaled_balance,
+        100 * SPOT_BALANCE_PRECISION_U64
+    );
+
+    // nor can the signer move a user it doesn't own
+    to_user.authority = authority;
+    let result = transfer_positions_between_subaccounts(
+        &Pubkey::new_unique(),
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        1,
+    );
+
+    assert_eq!(
+        result,
+        Err(ErrorCode::CantTransferBetweenDifferentAuthorities)
+    );
+
+    let result = transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &from_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        1,
+    );
+
+    assert_eq!(result, Err(ErrorCode::CantTransferBetweenSameUserAccount));
+}
+
+#[test]
+pub fn from_user_being_liquidated() {
+    let authority = Pubkey::new_unique();
+    let from_user_key = Pubkey::new_unique();
+    let to_user_key = Pubkey::new_unique();
+
+    let mut oracle_map = OracleMap::empty();
+    let perp_market_map = PerpMarketMap::empty();
+
+    let mut usdc_market = SpotMarket {
+        market_index: 0,
+        oracle_source: OracleSource::QuoteAsset,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        decimals: 6,
+        deposit_balance: 100 * SPOT_BALANCE_PRECISION,
+        ..SpotMarket::default()
+    };
+    create_anchor_account_info!(usdc_market, SpotMarket, usdc_spot_market_account_info);
+    let spot_market_map = SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+    let mut from_user = User {
+        authority,
+        status: UserStatus::BeingLiquidated,
+        spot_positions: get_spot_positions(SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 100 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        }),
+        ..User::default()
+    };
+    let mut to_user = User {
+        authority,
+        ..User::default()
+    };
+
+    let result = transfer_positions_between_subaccounts(
+        &authority,
+        &from_user_key,
+        &mut from_user,
+        &to_user_key,
+        &mut to_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        0,
+        1,
+    );
+
+    assert_eq!(result, Err(ErrorCode::UserIsBeingLiquidated));
+    assert_eq!(
+        from_user.spot_positions[0].scaled_balance,
+        100 * SPOT_BALANCE_PRECISION_U64
+    );
+    assert_eq!(to_user.spot_positions[0].scaled_balance, 0);
+}
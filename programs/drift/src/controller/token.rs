@@ -1,6 +1,12 @@
 use crate::signer::get_signer_seeds;
+use crate::state::token::get_mint_decimals;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+use anchor_spl::token::{self, Burn, Mint, MintTo, Token, TokenAccount, Transfer};
+use solana_program::instruction::{AccountMeta, Instruction};
+
+const TRANSFER_INSTRUCTION_TAG: u8 = 3;
+const TRANSFER_CHECKED_INSTRUCTION_TAG: u8 = 12;
+const INITIALIZE_ACCOUNT_3_INSTRUCTION_TAG: u8 = 18;
 
 pub fn send_from_program_vault<'info>(
     token_program: &Program<'info, Token>,
@@ -38,3 +44,161 @@ pub fn receive<'info>(
     let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
     token::transfer(cpi_context, amount)
 }
+
+pub fn mint_from_program<'info>(
+    token_program: &Program<'info, Token>,
+    mint: &Account<'info, Mint>,
+    to: &Account<'info, TokenAccount>,
+    authority: &AccountInfo<'info>,
+    nonce: u8,
+    amount: u64,
+) -> Result<()> {
+    let signature_seeds = get_signer_seeds(&nonce);
+    let signers = &[&signature_seeds[..]];
+    let cpi_accounts = MintTo {
+        mint: mint.to_account_info().clone(),
+        to: to.to_account_info().clone(),
+        authority: authority.to_account_info().clone(),
+    };
+    let cpi_program = token_program.to_account_info();
+    let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, signers);
+    token::mint_to(cpi_context, amount)
+}
+
+pub fn burn<'info>(
+    token_program: &Program<'info, Token>,
+    mint: &Account<'info, Mint>,
+    from: &Account<'info, TokenAccount>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = Burn {
+        mint: mint.to_account_info().clone(),
+        from: from.to_account_info().clone(),
+        authority: authority.to_account_info().clone(),
+    };
+    let cpi_program = token_program.to_account_info();
+    let cpi_context = CpiContext::new(cpi_program, cpi_accounts);
+    token::burn(cpi_context, amount)
+}
+
+// anchor_spl only builds instructions for the spl token program, so transfers that may go
+// through token-2022 are built here. Instruction layouts are shared by both programs
+fn build_transfer_instruction(
+    token_program: &AccountInfo,
+    from: &AccountInfo,
+    to: &AccountInfo,
+    authority: &AccountInfo,
+    amount: u64,
+    mint: Option<&AccountInfo>,
+) -> Result<Instruction> {
+    let instruction = match mint {
+        Some(mint) => {
+            let decimals = get_mint_decimals(&mint.try_borrow_data()?)?;
+            let mut data = vec![TRANSFER_CHECKED_INSTRUCTION_TAG];
+            data.extend_from_slice(&amount.to_le_bytes());
+            data.push(decimals);
+
+            Instruction {
+                program_id: *token_program.key,
+                data,
+                accounts: vec![
+                    AccountMeta::new(*from.key, false),
+                    AccountMeta::new_readonly(*mint.key, false),
+                    AccountMeta::new(*to.key, false),
+                    AccountMeta::new_readonly(*authority.key, true),
+                ],
+            }
+        }
+        None => {
+            let mut data = vec![TRANSFER_INSTRUCTION_TAG];
+            data.extend_from_slice(&amount.to_le_bytes());
+
+            Instruction {
+                program_id: *token_program.key,
+                data,
+                accounts: vec![
+                    AccountMeta::new(*from.key, false),
+                    AccountMeta::new(*to.key, false),
+                    AccountMeta::new_readonly(*authority.key, true),
+                ],
+            }
+        }
+    };
+
+    Ok(instruction)
+}
+
+fn transfer_account_infos<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    mint: Option<&AccountInfo<'info>>,
+) -> Vec<AccountInfo<'info>> {
+    let mut account_infos = vec![from.clone()];
+    if let Some(mint) = mint {
+        account_infos.push(mint.clone());
+    }
+    account_infos.push(to.clone());
+    account_infos.push(authority.clone());
+    account_infos.push(token_program.clone());
+    account_infos
+}
+
+pub fn send_from_program_vault_with_mint<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    nonce: u8,
+    amount: u64,
+    mint: Option<&AccountInfo<'info>>,
+) -> Result<()> {
+    let instruction = build_transfer_instruction(token_program, from, to, authority, amount, mint)?;
+    let account_infos = transfer_account_infos(token_program, from, to, authority, mint);
+
+    let signature_seeds = get_signer_seeds(&nonce);
+    let signers = &[&signature_seeds[..]];
+    solana_program::program::invoke_signed(&instruction, &account_infos, signers)
+        .map_err(Into::into)
+}
+
+pub fn receive_with_mint<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    mint: Option<&AccountInfo<'info>>,
+) -> Result<()> {
+    let instruction = build_transfer_instruction(token_program, from, to, authority, amount, mint)?;
+    let account_infos = transfer_account_infos(token_program, from, to, authority, mint);
+
+    solana_program::program::invoke(&instruction, &account_infos).map_err(Into::into)
+}
+
+pub fn initialize_token_account<'info>(
+    token_program: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    owner: &Pubkey,
+) -> Result<()> {
+    let mut data = vec![INITIALIZE_ACCOUNT_3_INSTRUCTION_TAG];
+    data.extend_from_slice(owner.as_ref());
+
+    let instruction = Instruction {
+        program_id: *token_program.key,
+        data,
+        accounts: vec![
+            AccountMeta::new(*account.key, false),
+            AccountMeta::new_readonly(*mint.key, false),
+        ],
+    };
+
+    solana_program::program::invoke(
+        &instruction,
+        &[account.clone(), mint.clone(), token_program.clone()],
+    )
+    .map_err(Into::into)
+}
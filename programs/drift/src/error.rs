@@ -485,6 +485,180 @@ pub enum ErrorCode {
     UserNotInactive,
     #[msg("RevertFill")]
     RevertFill,
+    #[msg("InvalidPrelaunchOraclePrice")]
+    InvalidPrelaunchOraclePrice,
+    #[msg("OracleCircuitBreakerTripped")]
+    OracleCircuitBreakerTripped,
+    #[msg("InvalidLinkedOrder")]
+    InvalidLinkedOrder,
+    #[msg("InvalidTwapOrder")]
+    InvalidTwapOrder,
+    #[msg("TwapSliceNotReady")]
+    TwapSliceNotReady,
+    #[msg("InvalidScaleOrderParams")]
+    InvalidScaleOrderParams,
+    #[msg("InvalidIcebergOrder")]
+    InvalidIcebergOrder,
+    #[msg("SigVerificationFailed")]
+    SigVerificationFailed,
+    #[msg("InvalidSignedOrderNonce")]
+    InvalidSignedOrderNonce,
+    #[msg("SignedOrderExpired")]
+    SignedOrderExpired,
+    #[msg("InvalidRfqQuote")]
+    InvalidRfqQuote,
+    #[msg("RfqNotFullyFilled")]
+    RfqNotFullyFilled,
+    #[msg("InvalidIsolatedPerpPosition")]
+    InvalidIsolatedPerpPosition,
+    #[msg("InvalidHighLeverageModeConfig")]
+    InvalidHighLeverageModeConfig,
+    #[msg("HighLeverageModeFull")]
+    HighLeverageModeFull,
+    #[msg("AutoDeleverageNotNeeded")]
+    AutoDeleverageNotNeeded,
+    #[msg("InvalidPerpPositionToAutoDeleverage")]
+    InvalidPerpPositionToAutoDeleverage,
+    #[msg("InvalidBackstopProvider")]
+    InvalidBackstopProvider,
+    #[msg("BackstopProviderUndercollateralized")]
+    BackstopProviderUndercollateralized,
+    #[msg("InvalidIFStakeLockupTier")]
+    InvalidIFStakeLockupTier,
+    #[msg("InvalidFundingPeriod")]
+    InvalidFundingPeriod,
+    #[msg("InvalidOpenbookV2Program")]
+    InvalidOpenbookV2Program,
+    #[msg("InvalidOpenbookV2Market")]
+    InvalidOpenbookV2Market,
+    #[msg("FailedOpenbookV2CPI")]
+    FailedOpenbookV2CPI,
+    #[msg("FailedToFillOnOpenbookV2")]
+    FailedToFillOnOpenbookV2,
+    #[msg("InvalidOpenbookV2FulfillmentConfig")]
+    InvalidOpenbookV2FulfillmentConfig,
+    #[msg("InvalidPhoenixProgram")]
+    InvalidPhoenixProgram,
+    #[msg("InvalidPhoenixMarket")]
+    InvalidPhoenixMarket,
+    #[msg("FailedPhoenixCPI")]
+    FailedPhoenixCPI,
+    #[msg("FailedToFillOnPhoenix")]
+    FailedToFillOnPhoenix,
+    #[msg("InvalidPhoenixFulfillmentConfig")]
+    InvalidPhoenixFulfillmentConfig,
+    #[msg("InvalidTokenProgram")]
+    InvalidTokenProgram,
+    #[msg("InvalidTokenAccount")]
+    InvalidTokenAccount,
+    #[msg("InvalidTokenMint")]
+    InvalidTokenMint,
+    #[msg("UnsupportedTokenMintExtension")]
+    UnsupportedTokenMintExtension,
+    #[msg("InvalidLstOracle")]
+    InvalidLstOracle,
+    #[msg("InvalidStakePool")]
+    InvalidStakePool,
+    #[msg("Can not borrow more than max borrow")]
+    MaxBorrow,
+    #[msg("NetWithdrawLimit")]
+    NetWithdrawLimit,
+    #[msg("InvalidFlashBorrow")]
+    InvalidFlashBorrow,
+    #[msg("FlashBorrowNotRepaid")]
+    FlashBorrowNotRepaid,
+    #[msg("InvalidInstructionsSysvar")]
+    InvalidInstructionsSysvar,
+    #[msg("InvalidSwap")]
+    InvalidSwap,
+    #[msg("InvalidLpRange")]
+    InvalidLpRange,
+    #[msg("LpRangeStatusUnchanged")]
+    LpRangeStatusUnchanged,
+    #[msg("InvalidSessionKey")]
+    InvalidSessionKey,
+    #[msg("SessionKeyExpired")]
+    SessionKeyExpired,
+    #[msg("SessionKeyLimitExceeded")]
+    SessionKeyLimitExceeded,
+    #[msg("UserTradingDisabled")]
+    UserTradingDisabled,
+    #[msg("InvalidDeadManSwitch")]
+    InvalidDeadManSwitch,
+    #[msg("DeadManSwitchNotTriggered")]
+    DeadManSwitchNotTriggered,
+    #[msg("InvalidBuilder")]
+    InvalidBuilder,
+    #[msg("BuilderNotFound")]
+    BuilderNotFound,
+    #[msg("InvalidGovernanceStake")]
+    InvalidGovernanceStake,
+    #[msg("GovernanceStakeUnstakingPeriodNotElapsed")]
+    GovernanceStakeUnstakingPeriodNotElapsed,
+    #[msg("InvalidUserFuel")]
+    InvalidUserFuel,
+    #[msg("InvalidFuelBoost")]
+    InvalidFuelBoost,
+    #[msg("MarketFeeOverrideNotFound")]
+    MarketFeeOverrideNotFound,
+    #[msg("InvalidMarketFeeOverride")]
+    InvalidMarketFeeOverride,
+    #[msg("InvalidReferrerRewardConfig")]
+    InvalidReferrerRewardConfig,
+    #[msg("AdminTimelockActive")]
+    AdminTimelockActive,
+    #[msg("AdminChangeNotExecutable")]
+    AdminChangeNotExecutable,
+    #[msg("InvalidAdminChange")]
+    InvalidAdminChange,
+    #[msg("InvalidPerpMarketDelisting")]
+    InvalidPerpMarketDelisting,
+    #[msg("SettlementPriceTwapWindowInactive")]
+    SettlementPriceTwapWindowInactive,
+    #[msg("PerpMarketSettlementUsersRemaining")]
+    PerpMarketSettlementUsersRemaining,
+    #[msg("InvalidPerpMarketListing")]
+    InvalidPerpMarketListing,
+    #[msg("PerpMarketListingVetoWindowActive")]
+    PerpMarketListingVetoWindowActive,
+    #[msg("PerpMarketListingVetoWindowEnded")]
+    PerpMarketListingVetoWindowEnded,
+    #[msg("InvalidMarketWhitelistEntry")]
+    InvalidMarketWhitelistEntry,
+    #[msg("AuthorityNotWhitelistedForMarket")]
+    AuthorityNotWhitelistedForMarket,
+    #[msg("InvalidPredictionMarket")]
+    InvalidPredictionMarket,
+    #[msg("PnlPoolRebalanceNotAllowed")]
+    PnlPoolRebalanceNotAllowed,
+    #[msg("InvalidPnlPoolRebalanceConfig")]
+    InvalidPnlPoolRebalanceConfig,
+    #[msg("InvalidFillCallback")]
+    InvalidFillCallback,
+    #[msg("FillCallbackProgramNotWhitelisted")]
+    FillCallbackProgramNotWhitelisted,
+    #[msg("VaultInsolvent")]
+    VaultInsolvent,
+    #[msg("InsufficientVaultShares")]
+    InsufficientVaultShares,
+    #[msg("InvalidVaultProfitShare")]
+    InvalidVaultProfitShare,
+    #[msg("InvalidOrderBookSnapshotParams")]
+    InvalidOrderBookSnapshotParams,
+    #[msg("InvalidMarketLookupTable")]
+    InvalidMarketLookupTable,
+    #[msg("InvalidBasisOrder")]
+    InvalidBasisOrder,
+    #[msg("CannotSettleFundingAgainstBorrow")]
+    CannotSettleFundingAgainstBorrow,
+    #[msg("ThirdPartyDepositsDisabled")]
+    ThirdPartyDepositsDisabled,
+    #[msg("NoStaleOrders")]
+    NoStaleOrders,
+    #[msg("Cant transfer between users with different authorities")]
+    CantTransferBetweenDifferentAuthorities,
+    #[msg("InvalidLpTier")]
+    InvalidLpTier,
 }
 
 #[macro_export]
@@ -6,6 +6,11 @@ pub mod pyth_program {
     declare_id!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s");
 }
 
+pub mod pyth_receiver_program {
+    use solana_program::declare_id;
+    declare_id!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+}
+
 pub mod bonk_oracle {
     use solana_program::declare_id;
     #[cfg(feature = "mainnet-beta")]
@@ -22,6 +27,31 @@ pub mod serum_program {
     declare_id!("DESVgJVGajEgKGXhb6XmqDHGz3VjdgP7rEVESBgxmroY");
 }
 
+pub mod openbook_v2_program {
+    use solana_program::declare_id;
+    declare_id!("opnb2LAfJYbRMAHHvqjCwQxanZn7ReEHp1k81EohpZb");
+}
+
+pub mod phoenix_program {
+    use solana_program::declare_id;
+    declare_id!("PhoeNiXZ8ByJGLkxNfZRnkUfjvmuYqLR89jjFHGqdXY");
+}
+
+pub mod token_2022_program {
+    use solana_program::declare_id;
+    declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+pub mod spl_stake_pool_program {
+    use solana_program::declare_id;
+    declare_id!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+}
+
+pub mod marinade_program {
+    use solana_program::declare_id;
+    declare_id!("MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD");
+}
+
 pub mod srm_mint {
     use solana_program::declare_id;
     declare_id!("SRMuApVNdxXokk5GT7XD5cUUgXMBCoAz2LHeuAoKWRt");
@@ -31,3 +61,13 @@ pub mod msrm_mint {
     use solana_program::declare_id;
     declare_id!("MSRMcoVyrFxnSgo5uXwone5SKcGhT1KEJMFEkMEWf9L");
 }
+
+pub mod switchboard_on_demand {
+    use solana_program::declare_id;
+    declare_id!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+}
+
+pub mod address_lookup_table_program {
+    use solana_program::declare_id;
+    declare_id!("AddressLookupTab1e1111111111111111111111111");
+}
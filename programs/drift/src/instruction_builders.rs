@@ -0,0 +1,256 @@
+//! Instruction builders for integrators, compiled with the `cpi` feature alongside the typed CPI
+//! wrappers anchor generates in `drift::cpi`. User accounts are derived from the authority that
+//! owns them. Remaining accounts are appended as given, see [`market_remaining_accounts`].
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::InstructionData;
+
+use crate::controller::position::PositionDirection;
+use crate::instructions::{CancelOrdersFilter, OrderParams};
+use crate::state::user::MarketType;
+
+#[cfg(test)]
+mod tests;
+
+pub fn get_state_public_key() -> Pubkey {
+    Pubkey::find_program_address(&[b"drift_state".as_ref()], &crate::ID).0
+}
+
+pub fn get_signer_public_key() -> Pubkey {
+    Pubkey::find_program_address(&[b"drift_signer".as_ref()], &crate::ID).0
+}
+
+pub fn get_user_public_key(authority: &Pubkey, sub_account_id: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"user".as_ref(),
+            authority.as_ref(),
+            sub_account_id.to_le_bytes().as_ref(),
+        ],
+        &crate::ID,
+    )
+    .0
+}
+
+pub fn get_user_stats_public_key(authority: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"user_stats".as_ref(), authority.as_ref()], &crate::ID).0
+}
+
+pub fn get_perp_market_public_key(market_index: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"perp_market".as_ref(), market_index.to_le_bytes().as_ref()],
+        &crate::ID,
+    )
+    .0
+}
+
+pub fn get_spot_market_public_key(market_index: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"spot_market".as_ref(), market_index.to_le_bytes().as_ref()],
+        &crate::ID,
+    )
+    .0
+}
+
+pub fn get_spot_market_vault_public_key(market_index: u16) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"spot_market_vault".as_ref(),
+            market_index.to_le_bytes().as_ref(),
+        ],
+        &crate::ID,
+    )
+    .0
+}
+
+/// oracles, then spot markets, then perp markets, as (market_index, writable)
+pub fn market_remaining_accounts(
+    oracles: &[Pubkey],
+    spot_markets: &[(u16, bool)],
+    perp_markets: &[(u16, bool)],
+) -> Vec<AccountMeta> {
+    let oracles = oracles
+        .iter()
+        .map(|oracle| AccountMeta::new_readonly(*oracle, false));
+
+    let spot_markets = spot_markets.iter().map(|(market_index, writable)| {
+        market_account_meta(get_spot_market_public_key(*market_index), *writable)
+    });
+
+    let perp_markets = perp_markets.iter().map(|(market_index, writable)| {
+        market_account_meta(get_perp_market_public_key(*market_index), *writable)
+    });
+
+    oracles.chain(spot_markets).chain(perp_markets).collect()
+}
+
+fn market_account_meta(pubkey: Pubkey, writable: bool) -> AccountMeta {
+    if writable {
+        AccountMeta::new(pubkey, false)
+    } else {
+        AccountMeta::new_readonly(pubkey, false)
+    }
+}
+
+fn build_instruction(
+    accounts: impl ToAccountMetas,
+    data: impl InstructionData,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    let mut account_metas = accounts.to_account_metas(None);
+    account_metas.extend_from_slice(remaining_accounts);
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: account_metas,
+        data: data.data(),
+    }
+}
+
+pub fn deposit(
+    authority: &Pubkey,
+    sub_account_id: u16,
+    market_index: u16,
+    user_token_account: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    reduce_only: bool,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    build_instruction(
+        crate::accounts::Deposit {
+            state: get_state_public_key(),
+            user: get_user_public_key(authority, sub_account_id),
+            user_stats: get_user_stats_public_key(authority),
+            authority: *authority,
+            spot_market_vault: get_spot_market_vault_public_key(market_index),
+            user_token_account: *user_token_account,
+            token_program: *token_program,
+        },
+        crate::instruction::Deposit {
+            market_index,
+            amount,
+            reduce_only,
+        },
+        remaining_accounts,
+    )
+}
+
+pub fn withdraw(
+    authority: &Pubkey,
+    sub_account_id: u16,
+    market_index: u16,
+    user_token_account: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    reduce_only: bool,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    build_instruction(
+        crate::accounts::Withdraw {
+            state: get_state_public_key(),
+            user: get_user_public_key(authority, sub_account_id),
+            user_stats: get_user_stats_public_key(authority),
+            authority: *authority,
+            spot_market_vault: get_spot_market_vault_public_key(market_index),
+            drift_signer: get_signer_public_key(),
+            user_token_account: *user_token_account,
+            token_program: *token_program,
+        },
+        crate::instruction::Withdraw {
+            market_index,
+            amount,
+            reduce_only,
+        },
+        remaining_accounts,
+    )
+}
+
+pub fn place_perp_order(
+    authority: &Pubkey,
+    sub_account_id: u16,
+    params: OrderParams,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    build_instruction(
+        crate::accounts::PlaceOrder {
+            state: get_state_public_key(),
+            user: get_user_public_key(authority, sub_account_id),
+            user_stats: get_user_stats_public_key(authority),
+            authority: *authority,
+        },
+        crate::instruction::PlacePerpOrder { params },
+        remaining_accounts,
+    )
+}
+
+pub fn place_and_take_perp_order(
+    authority: &Pubkey,
+    sub_account_id: u16,
+    params: OrderParams,
+    maker_order_id: Option<u32>,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    build_instruction(
+        crate::accounts::PlaceAndTake {
+            state: get_state_public_key(),
+            user: get_user_public_key(authority, sub_account_id),
+            user_stats: get_user_stats_public_key(authority),
+            authority: *authority,
+        },
+        crate::instruction::PlaceAndTakePerpOrder {
+            params,
+            maker_order_id,
+        },
+        remaining_accounts,
+    )
+}
+
+pub fn cancel_orders(
+    authority: &Pubkey,
+    sub_account_id: u16,
+    market_type: Option<MarketType>,
+    market_index: Option<u16>,
+    direction: Option<PositionDirection>,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    build_instruction(
+        crate::accounts::CancelOrder {
+            state: get_state_public_key(),
+            user: get_user_public_key(authority, sub_account_id),
+            authority: *authority,
+        },
+        crate::instruction::CancelOrders {
+            market_type,
+            market_index,
+            direction,
+        },
+        remaining_accounts,
+    )
+}
+
+pub fn cancel_orders_with_filter(
+    authority: &Pubkey,
+    sub_account_id: u16,
+    market_type: Option<MarketType>,
+    market_index: Option<u16>,
+    direction: Option<PositionDirection>,
+    filter: CancelOrdersFilter,
+    remaining_accounts: &[AccountMeta],
+) -> Instruction {
+    build_instruction(
+        crate::accounts::CancelOrder {
+            state: get_state_public_key(),
+            user: get_user_public_key(authority, sub_account_id),
+            authority: *authority,
+        },
+        crate::instruction::CancelOrdersWithFilter {
+            market_type,
+            market_index,
+            direction,
+            filter,
+        },
+        remaining_accounts,
+    )
+}
@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+use anchor_lang::{Discriminator, InstructionData};
+
+use crate::instruction_builders::{
+    deposit, get_perp_market_public_key, get_spot_market_public_key, get_user_public_key,
+    market_remaining_accounts,
+};
+
+#[test]
+fn deposit_accounts_and_data() {
+    let authority = Pubkey::new_unique();
+    let user_token_account = Pubkey::new_unique();
+    let oracle = Pubkey::new_unique();
+
+    let remaining_accounts = market_remaining_accounts(&[oracle], &[(0, true)], &[(1, false)]);
+    assert_eq!(
+        remaining_accounts,
+        vec![
+            AccountMeta::new_readonly(oracle, false),
+            AccountMeta::new(get_spot_market_public_key(0), false),
+            AccountMeta::new_readonly(get_perp_market_public_key(1), false),
+        ]
+    );
+
+    let ix = deposit(
+        &authority,
+        0,
+        0,
+        &user_token_account,
+        &anchor_spl::token::ID,
+        100,
+        false,
+        &remaining_accounts,
+    );
+
+    assert_eq!(ix.program_id, crate::ID);
+    assert_eq!(ix.accounts.len(), 7 + remaining_accounts.len());
+    assert_eq!(ix.accounts[1].pubkey, get_user_public_key(&authority, 0));
+    assert!(ix.accounts[3].is_signer);
+    assert_eq!(ix.accounts[7..], remaining_accounts[..]);
+    assert_eq!(ix.data[..8], crate::instruction::Deposit::discriminator());
+    assert_eq!(
+        ix.data,
+        crate::instruction::Deposit {
+            market_index: 0,
+            amount: 100,
+            reduce_only: false,
+        }
+        .data()
+    );
+}
@@ -2,13 +2,14 @@ use std::convert::identity;
 use std::mem::size_of;
 
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 use bytemuck::cast_slice;
 use serum_dex::state::ToAlignedBytes;
 use solana_program::msg;
 
 use crate::controller;
-use crate::error::ErrorCode;
+use crate::error::{DriftResult, ErrorCode};
 use crate::get_then_update_id;
 use crate::instructions::constraints::*;
 use crate::instructions::keeper::SpotFulfillmentType;
@@ -16,12 +17,18 @@ use crate::load;
 use crate::load_mut;
 use crate::math::casting::Cast;
 use crate::math::constants::{
-    DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE, DEFAULT_LIQUIDATION_MARGIN_BUFFER_RATIO,
-    DEFAULT_QUOTE_ASSET_AMOUNT_TICK_SIZE, FEE_POOL_TO_REVENUE_POOL_THRESHOLD, IF_FACTOR_PRECISION,
-    INSURANCE_A_MAX, INSURANCE_B_MAX, INSURANCE_C_MAX, INSURANCE_SPECULATIVE_MAX,
-    LIQUIDATION_FEE_PRECISION, MAX_CONCENTRATION_COEFFICIENT, MAX_SQRT_K,
-    MAX_UPDATE_K_PRICE_CHANGE, QUOTE_SPOT_MARKET_INDEX, SPOT_CUMULATIVE_INTEREST_PRECISION,
-    SPOT_IMF_PRECISION, SPOT_WEIGHT_PRECISION, THIRTEEN_DAY, TWENTY_FOUR_HOUR,
+    DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE, DEFAULT_IF_LIQUIDATION_FEE,
+    DEFAULT_LIQUIDATION_MARGIN_BUFFER_RATIO, DEFAULT_QUOTE_ASSET_AMOUNT_TICK_SIZE, EIGHT_HOUR,
+    FEE_POOL_TO_REVENUE_POOL_THRESHOLD, FOUR_HOUR, IF_FACTOR_PRECISION, INSURANCE_A_MAX,
+    INSURANCE_B_MAX, INSURANCE_C_MAX, INSURANCE_SPECULATIVE_MAX, LIQUIDATION_FEE_PRECISION,
+    MARGIN_PRECISION, MAX_CONCENTRATION_COEFFICIENT, MAX_FEE_ADJUSTMENT, MAX_REFERRER_REWARD_SHARE,
+    MAX_SECOND_LEVEL_REFERRER_REWARD_SHARE, MAX_SQRT_K, MAX_UPDATE_K_PRICE_CHANGE,
+    MIN_PERMISSIONLESS_LISTING_VETO_WINDOW, ONE_BPS_DENOMINATOR, ONE_HOUR, PERCENTAGE_PRECISION,
+    PERMISSIONLESS_LISTING_FUNDING_PERIOD, PERMISSIONLESS_LISTING_LIQUIDATOR_FEE,
+    PERMISSIONLESS_LISTING_MARGIN_RATIO_INITIAL, PERMISSIONLESS_LISTING_MARGIN_RATIO_MAINTENANCE,
+    PYTH_PULL_MAX_PUBLISH_TIME_STALENESS, QUOTE_SPOT_MARKET_INDEX,
+    SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_IMF_PRECISION, SPOT_WEIGHT_PRECISION, THIRTEEN_DAY,
+    THIRTY_DAY, TWENTY_FOUR_HOUR,
 };
 use crate::math::cp_curve::get_update_k_result;
 use crate::math::oracle::{is_oracle_valid_for_action, DriftAction};
@@ -31,24 +38,58 @@ use crate::math::safe_math::SafeMath;
 use crate::math::spot_balance::get_token_amount;
 use crate::math::{amm, bn, oracle};
 use crate::math_error;
-use crate::state::events::CurveRecord;
+use crate::state::admin_timelock::{AdminChange, PendingAdminChange};
+use crate::state::backstop_provider::BackstopProvider;
+use crate::state::events::{AdminChangeAction, AdminChangeRecord, CurveRecord};
+use crate::state::fill_callback::FillCallbackProgram;
+use crate::state::fuel::FuelBoost;
+use crate::state::high_leverage_mode_config::HighLeverageModeConfig;
+use crate::state::insurance_fund_stake::InsuranceFundStake;
+use crate::state::lst_oracle::{
+    get_lst_price, get_redemption_rate, get_stake_pool_type, LstOracle,
+};
+use crate::state::market_fee_override::MarketFeeOverride;
+use crate::state::market_lookup_table::MarketLookupTable;
+use crate::state::market_whitelist::MarketWhitelistEntry;
+use crate::state::openbook_v2::load_openbook_v2_market;
 use crate::state::oracle::{
-    get_oracle_price, get_pyth_price, HistoricalIndexData, HistoricalOracleData, OraclePriceData,
-    OracleSource,
+    get_oracle_price, get_prelaunch_price, get_pyth_price, get_pyth_pull_price, get_pyth_pull_twap,
+    get_sb_on_demand_price, validate_pyth_pull_publish_time, HistoricalIndexData,
+    HistoricalOracleData, OraclePriceData, OracleSource,
 };
 use crate::state::perp_market::{
-    ContractTier, ContractType, InsuranceClaim, MarketStatus, PerpMarket, PoolBalance, AMM,
+    ContractTier, ContractType, InsuranceClaim, MarginTier, MarketStatus, PerpMarket,
+    PerpOperation, PoolBalance, AMM,
+};
+use crate::state::perp_market_delisting::PerpMarketDelisting;
+use crate::state::perp_market_listing::{
+    calculate_permissionless_listing_max_open_interest, validate_permissionless_listing_oracle,
+    validate_permissionless_listing_reserves, PerpMarketListing, PerpMarketListingConfig,
 };
+use crate::state::phoenix::{load_phoenix_market, PHOENIX_MARKET_STATUS_ACTIVE};
+use crate::state::pnl_pool_rebalance_config::PnlPoolRebalanceConfig;
+use crate::state::prelaunch_oracle::{PrelaunchOracle, PrelaunchOracleParams};
 use crate::state::serum::{load_open_orders, load_serum_market};
 use crate::state::spot_market::{
-    AssetTier, InsuranceFund, SerumV3FulfillmentConfig, SpotBalanceType,
+    AssetTier, BorrowRateKink, InsuranceFund, OpenbookV2FulfillmentConfig,
+    PhoenixV1FulfillmentConfig, SerumV3FulfillmentConfig, SpotBalanceType,
     SpotFulfillmentConfigStatus, SpotMarket,
 };
-use crate::state::state::{ExchangeStatus, FeeStructure, OracleGuardRails, State};
+use crate::state::state::{
+    AdminRole, AdminRoles, ExchangeStatus, FeeStructure, OracleGuardRails, State,
+    ValidityGuardRailsOverride,
+};
+use crate::state::token::{
+    get_mint_decimals, get_token_account_space, is_token_program, validate_mint_extensions,
+};
 use crate::state::traits::Size;
+use crate::state::user::{MarketType, User, UserStats};
 use crate::validate;
 use crate::validation::fee_structure::validate_fee_structure;
-use crate::validation::margin::{validate_margin, validate_margin_weights};
+use crate::validation::margin::{
+    validate_high_leverage_margin_ratio, validate_margin, validate_margin_tiers,
+    validate_margin_weights,
+};
 use crate::validation::perp_market::validate_perp_market;
 use crate::validation::spot_market::validate_borrow_rate;
 use crate::{math, safe_increment};
@@ -80,7 +121,12 @@ pub fn handle_initialize(ctx: Context<Initialize>) -> Result<()> {
         lp_cooldown_time: 0,
         liquidation_duration: 0,
         initial_pct_to_liquidate: 0,
-        padding: [0; 14],
+        liquidation_fee_ramp_duration: 0,
+        liquidation_target_margin_buffer_ratio: 0,
+        liquidation_stale_fee_escalation_duration: 0,
+        admin_timelock_duration: 0,
+        margin_warning_health: 0,
+        padding: [0; 2],
     };
 
     Ok(())
@@ -104,20 +150,73 @@ pub fn handle_initialize_spot_market(
     let state = &mut ctx.accounts.state;
     let spot_market_pubkey = ctx.accounts.spot_market.key();
 
-    // protocol must be authority of collateral vault
-    if ctx.accounts.spot_market_vault.owner != state.signer {
-        return Err(ErrorCode::InvalidSpotMarketAuthority.into());
-    }
+    let token_program_id = ctx.accounts.token_program.key();
+    validate!(
+        is_token_program(&token_program_id),
+        ErrorCode::InvalidTokenProgram,
+        "token program must be spl token or token-2022"
+    )?;
 
-    // protocol must be authority of collateral vault
-    if ctx.accounts.insurance_fund_vault.owner != state.signer {
-        return Err(ErrorCode::InvalidInsuranceFundAuthority.into());
-    }
+    validate!(
+        ctx.accounts.spot_market_mint.owner == &token_program_id,
+        ErrorCode::InvalidTokenMint,
+        "mint not owned by token program"
+    )?;
+
+    let is_token_2022 = token_program_id == crate::ids::token_2022_program::id();
+    let (mint_decimals, token_account_space) = {
+        let mint_data = ctx.accounts.spot_market_mint.try_borrow_data()?;
+        if is_token_2022 {
+            validate_mint_extensions(&mint_data)?;
+        }
+
+        (
+            get_mint_decimals(&mint_data)?,
+            get_token_account_space(&mint_data)?,
+        )
+    };
 
-    validate_borrow_rate(optimal_utilization, optimal_borrow_rate, max_borrow_rate)?;
+    validate_borrow_rate(
+        optimal_utilization,
+        optimal_borrow_rate,
+        max_borrow_rate,
+        &[BorrowRateKink::default(); 2],
+    )?;
 
     let spot_market_index = get_then_update_id!(state, number_of_spot_markets);
 
+    // vaults are created here rather than with anchor init so token-2022 mints are supported
+    let spot_market_index_bytes = spot_market_index.to_le_bytes();
+    let vaults: [(&AccountInfo, &[u8]); 2] = [
+        (
+            &ctx.accounts.spot_market_vault,
+            b"spot_market_vault".as_ref(),
+        ),
+        (
+            &ctx.accounts.insurance_fund_vault,
+            b"insurance_fund_vault".as_ref(),
+        ),
+    ];
+    for (vault, seed) in vaults {
+        controller::pda::seed_and_create_pda(
+            ctx.program_id,
+            &ctx.accounts.admin.to_account_info(),
+            &ctx.accounts.rent,
+            token_account_space,
+            &token_program_id,
+            &ctx.accounts.system_program.to_account_info(),
+            vault,
+            &[seed, spot_market_index_bytes.as_ref()],
+        )?;
+
+        controller::token::initialize_token_account(
+            &ctx.accounts.token_program,
+            vault,
+            &ctx.accounts.spot_market_mint,
+            &state.signer,
+        )?;
+    }
+
     if oracle_source == OracleSource::QuoteAsset {
         // catches inconsistent parameters
         validate!(
@@ -154,7 +253,7 @@ pub fn handle_initialize_spot_market(
             )?;
 
             validate!(
-                ctx.accounts.spot_market_mint.decimals == 6,
+                mint_decimals == 6,
                 ErrorCode::InvalidSpotMarketInitialization,
                 "For quote asset spot market, mint decimals must be 6"
             )?;
@@ -165,7 +264,7 @@ pub fn handle_initialize_spot_market(
             )
         } else {
             validate!(
-                ctx.accounts.spot_market_mint.decimals >= 6,
+                mint_decimals >= 6,
                 ErrorCode::InvalidSpotMarketInitialization,
                 "Mint decimals must be greater than or equal to 6"
             )?;
@@ -199,7 +298,7 @@ pub fn handle_initialize_spot_market(
         .cast()
         .or(Err(ErrorCode::UnableToCastUnixTime))?;
 
-    let decimals = ctx.accounts.spot_market_mint.decimals.cast::<u32>()?;
+    let decimals = mint_decimals.cast::<u32>()?;
     let order_step_size = 10_u64.pow(2 + decimals - 6); // 10 for usdc/btc, 10000 for sol
 
     **spot_market = SpotMarket {
@@ -250,7 +349,7 @@ pub fn handle_initialize_spot_market(
         maintenance_liability_weight,
         imf_factor,
         liquidator_fee,
-        if_liquidation_fee: LIQUIDATION_FEE_PRECISION / 100, // 1%
+        if_liquidation_fee: DEFAULT_IF_LIQUIDATION_FEE,
         withdraw_guard_threshold: 0,
         order_step_size,
         order_tick_size: DEFAULT_QUOTE_ASSET_AMOUNT_TICK_SIZE,
@@ -261,7 +360,23 @@ pub fn handle_initialize_spot_market(
         spot_fee_pool: PoolBalance::default(), // in quote asset
         total_spot_fee: 0,
         orders_enabled: spot_market_index != 0,
-        padding: [0; 86],
+        max_net_withdraw_bps: 0,
+        validity_guard_rails_override: ValidityGuardRailsOverride::default(),
+        max_collateral_concentration: 0,
+        correlation_margin_credit: 0,
+        correlation_group: 0,
+        token_program: u8::from(is_token_2022),
+        flash_borrow_fee_bps: 0,
+        flash_borrows_enabled: false,
+        fee_override_enabled: false,
+        max_token_borrows: 0,
+        if_boost_weight: 0,
+        cumulative_if_boost_shares_per_weight: 0,
+        revenue_settle_max_amount: 0,
+        borrow_rate_kinks: [BorrowRateKink::default(); 2],
+        insurance_fund_lent_to_amms: 0,
+        paused_operations: 0,
+        padding: [0; 7],
         insurance_fund: InsuranceFund {
             vault: *ctx.accounts.insurance_fund_vault.to_account_info().key,
             unstaking_period: THIRTEEN_DAY,
@@ -421,445 +536,494 @@ pub fn handle_update_serum_fulfillment_config_status(
     Ok(())
 }
 
-pub fn handle_update_serum_vault(ctx: Context<UpdateSerumVault>) -> Result<()> {
-    let vault = &ctx.accounts.srm_vault;
+pub fn handle_initialize_openbook_v2_fulfillment_config(
+    ctx: Context<InitializeOpenbookV2FulfillmentConfig>,
+    market_index: u16,
+) -> Result<()> {
     validate!(
-        vault.mint == crate::ids::srm_mint::id() || vault.mint == crate::ids::msrm_mint::id(),
-        ErrorCode::InvalidSrmVault,
-        "vault did not hav srm or msrm mint"
+        market_index != QUOTE_SPOT_MARKET_INDEX,
+        ErrorCode::InvalidSpotMarketAccount,
+        "Cant add openbook v2 market to quote asset"
     )?;
 
+    let base_spot_market = load!(&ctx.accounts.base_spot_market)?;
+    let quote_spot_market = load!(&ctx.accounts.quote_spot_market)?;
+
+    let openbook_v2_program_id = crate::ids::openbook_v2_program::id();
     validate!(
-        vault.owner == ctx.accounts.state.signer,
-        ErrorCode::InvalidVaultOwner,
-        "vault owner was not program signer"
+        ctx.accounts.openbook_v2_program.key() == openbook_v2_program_id,
+        ErrorCode::InvalidOpenbookV2Program
     )?;
 
-    let state = &mut ctx.accounts.state;
-    state.srm_vault = vault.key();
+    let openbook_v2_market_key = ctx.accounts.openbook_v2_market.key();
+    let market_state =
+        load_openbook_v2_market(&ctx.accounts.openbook_v2_market, &openbook_v2_program_id)?;
+
+    validate!(
+        market_state.base_mint == base_spot_market.mint,
+        ErrorCode::InvalidOpenbookV2Market,
+        "Invalid base mint"
+    )?;
+
+    validate!(
+        market_state.quote_mint == quote_spot_market.mint,
+        ErrorCode::InvalidOpenbookV2Market,
+        "Invalid quote mint"
+    )?;
+
+    validate!(
+        market_state.open_orders_admin == Pubkey::default(),
+        ErrorCode::InvalidOpenbookV2Market,
+        "openbook v2 market has an open orders admin"
+    )?;
+
+    validate!(
+        market_state.oracle_a == Pubkey::default() && market_state.oracle_b == Pubkey::default(),
+        ErrorCode::InvalidOpenbookV2Market,
+        "openbook v2 market has an oracle"
+    )?;
+
+    let market_step_size = market_state.base_lot_size.cast::<u64>()?;
+    validate!(
+        market_step_size > 0,
+        ErrorCode::InvalidOpenbookV2Market,
+        "openbook v2 base lot size must be positive"
+    )?;
+    let valid_step_size = base_spot_market.order_step_size >= market_step_size
+        && base_spot_market
+            .order_step_size
+            .rem_euclid(market_step_size)
+            == 0;
+
+    validate!(
+        valid_step_size,
+        ErrorCode::InvalidOpenbookV2Market,
+        "base market step size ({}) not a multiple of openbook v2 step size ({})",
+        base_spot_market.order_step_size,
+        market_step_size
+    )?;
+
+    let market_tick_size = market_state.quote_lot_size.cast::<u64>()?;
+    validate!(
+        market_tick_size > 0,
+        ErrorCode::InvalidOpenbookV2Market,
+        "openbook v2 quote lot size must be positive"
+    )?;
+    let valid_tick_size = base_spot_market.order_tick_size >= market_tick_size
+        && base_spot_market
+            .order_tick_size
+            .rem_euclid(market_tick_size)
+            == 0;
+
+    validate!(
+        valid_tick_size,
+        ErrorCode::InvalidOpenbookV2Market,
+        "base market tick size ({}) not a multiple of openbook v2 tick size ({})",
+        base_spot_market.order_tick_size,
+        market_tick_size
+    )?;
+
+    let openbook_v2_fulfillment_config_key = ctx.accounts.openbook_v2_fulfillment_config.key();
+    let mut openbook_v2_fulfillment_config =
+        ctx.accounts.openbook_v2_fulfillment_config.load_init()?;
+    *openbook_v2_fulfillment_config = OpenbookV2FulfillmentConfig {
+        pubkey: openbook_v2_fulfillment_config_key,
+        openbook_v2_program_id,
+        openbook_v2_market: openbook_v2_market_key,
+        openbook_v2_market_authority: market_state.market_authority,
+        openbook_v2_event_heap: market_state.event_heap,
+        openbook_v2_bids: market_state.bids,
+        openbook_v2_asks: market_state.asks,
+        openbook_v2_base_vault: market_state.market_base_vault,
+        openbook_v2_quote_vault: market_state.market_quote_vault,
+        market_index,
+        fulfillment_type: SpotFulfillmentType::OpenbookV2,
+        status: SpotFulfillmentConfigStatus::Enabled,
+        padding: [0; 4],
+    };
 
     Ok(())
 }
 
-pub fn handle_initialize_perp_market(
-    ctx: Context<InitializePerpMarket>,
-    amm_base_asset_reserve: u128,
-    amm_quote_asset_reserve: u128,
-    amm_periodicity: i64,
-    amm_peg_multiplier: u128,
-    oracle_source: OracleSource,
-    margin_ratio_initial: u32,
-    margin_ratio_maintenance: u32,
-    liquidator_fee: u32,
-    active_status: bool,
-    name: [u8; 32],
+pub fn handle_update_openbook_v2_fulfillment_config_status(
+    ctx: Context<UpdateOpenbookV2FulfillmentConfig>,
+    status: SpotFulfillmentConfigStatus,
 ) -> Result<()> {
-    let perp_market_pubkey = ctx.accounts.perp_market.to_account_info().key;
-    let perp_market = &mut ctx.accounts.perp_market.load_init()?;
-    let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
-    let clock_slot = clock.slot;
+    let mut config = load_mut!(ctx.accounts.openbook_v2_fulfillment_config)?;
+    config.status = status;
+    Ok(())
+}
 
-    if amm_base_asset_reserve != amm_quote_asset_reserve {
-        return Err(ErrorCode::InvalidInitialPeg.into());
-    }
+pub fn handle_initialize_phoenix_fulfillment_config(
+    ctx: Context<InitializePhoenixFulfillmentConfig>,
+    market_index: u16,
+) -> Result<()> {
+    validate!(
+        market_index != QUOTE_SPOT_MARKET_INDEX,
+        ErrorCode::InvalidSpotMarketAccount,
+        "Cant add phoenix market to quote asset"
+    )?;
 
-    let init_reserve_price = amm::calculate_price(
-        amm_quote_asset_reserve,
-        amm_base_asset_reserve,
-        amm_peg_multiplier,
+    let base_spot_market = load!(&ctx.accounts.base_spot_market)?;
+    let quote_spot_market = load!(&ctx.accounts.quote_spot_market)?;
+
+    let phoenix_program_id = crate::ids::phoenix_program::id();
+    validate!(
+        ctx.accounts.phoenix_program.key() == phoenix_program_id,
+        ErrorCode::InvalidPhoenixProgram
     )?;
 
-    assert_eq!(amm_peg_multiplier, init_reserve_price.cast::<u128>()?);
+    let phoenix_market_key = ctx.accounts.phoenix_market.key();
+    let market_state = load_phoenix_market(&ctx.accounts.phoenix_market, &phoenix_program_id)?;
 
-    let concentration_coef = MAX_CONCENTRATION_COEFFICIENT;
+    validate!(
+        market_state.status == PHOENIX_MARKET_STATUS_ACTIVE,
+        ErrorCode::InvalidPhoenixMarket,
+        "phoenix market not active"
+    )?;
 
-    // Verify there's no overflow
-    let _k =
-        bn::U192::from(amm_base_asset_reserve).safe_mul(bn::U192::from(amm_quote_asset_reserve))?;
+    validate!(
+        market_state.base_params.mint_key == base_spot_market.mint,
+        ErrorCode::InvalidPhoenixMarket,
+        "Invalid base mint"
+    )?;
 
-    let (min_base_asset_reserve, max_base_asset_reserve) =
-        amm::calculate_bid_ask_bounds(concentration_coef, amm_base_asset_reserve)?;
+    validate!(
+        market_state.quote_params.mint_key == quote_spot_market.mint,
+        ErrorCode::InvalidPhoenixMarket,
+        "Invalid quote mint"
+    )?;
 
-    // Verify oracle is readable
-    let (oracle_price, oracle_delay, last_oracle_price_twap) = match oracle_source {
-        OracleSource::Pyth => {
-            let OraclePriceData {
-                price: oracle_price,
-                delay: oracle_delay,
-                ..
-            } = get_pyth_price(&ctx.accounts.oracle, clock_slot, 1)?;
-            let last_oracle_price_twap = perp_market.amm.get_pyth_twap(&ctx.accounts.oracle, 1)?;
-            (oracle_price, oracle_delay, last_oracle_price_twap)
-        }
-        OracleSource::Pyth1K => {
-            let OraclePriceData {
-                price: oracle_price,
-                delay: oracle_delay,
-                ..
-            } = get_pyth_price(&ctx.accounts.oracle, clock_slot, 1000)?;
-            let last_oracle_price_twap =
-                perp_market.amm.get_pyth_twap(&ctx.accounts.oracle, 1000)?;
-            (oracle_price, oracle_delay, last_oracle_price_twap)
-        }
-        OracleSource::Pyth1M => {
-            let OraclePriceData {
-                price: oracle_price,
-                delay: oracle_delay,
-                ..
-            } = get_pyth_price(&ctx.accounts.oracle, clock_slot, 1000000)?;
-            let last_oracle_price_twap = perp_market
-                .amm
-                .get_pyth_twap(&ctx.accounts.oracle, 1000000)?;
-            (oracle_price, oracle_delay, last_oracle_price_twap)
-        }
-        OracleSource::Switchboard => {
-            msg!("Switchboard oracle cant be used for perp market");
-            return Err(ErrorCode::InvalidOracle.into());
-        }
-        OracleSource::QuoteAsset => {
-            msg!("Quote asset oracle cant be used for perp market");
-            return Err(ErrorCode::InvalidOracle.into());
-        }
-    };
+    validate!(
+        market_state.raw_base_units_per_base_unit <= 1,
+        ErrorCode::InvalidPhoenixMarket,
+        "phoenix raw base units per base unit ({}) must be 1",
+        market_state.raw_base_units_per_base_unit
+    )?;
 
-    let max_spread = ((margin_ratio_initial - margin_ratio_maintenance) * (100 - 5)) as u32;
+    let market_step_size = market_state.base_lot_size;
+    validate!(
+        market_step_size > 0,
+        ErrorCode::InvalidPhoenixMarket,
+        "phoenix base lot size must be positive"
+    )?;
+    let valid_step_size = base_spot_market.order_step_size >= market_step_size
+        && base_spot_market
+            .order_step_size
+            .rem_euclid(market_step_size)
+            == 0;
 
-    // todo? should ensure peg within 1 cent of current oracle?
-    // validate!(
-    //     amm_peg_multiplier.cast::<i128>()?
-    //         .safe_sub(oracle_price)
-    //         ?
-    //         .unsigned_abs()
-    //         < PRICE_PRECISION / 100,
-    //     ErrorCode::InvalidInitialPeg
-    // )?;
+    validate!(
+        valid_step_size,
+        ErrorCode::InvalidPhoenixMarket,
+        "base market step size ({}) not a multiple of phoenix step size ({})",
+        base_spot_market.order_step_size,
+        market_step_size
+    )?;
 
-    validate_margin(
-        margin_ratio_initial,
-        margin_ratio_maintenance,
-        liquidator_fee,
-        max_spread,
+    let market_tick_size = market_state.tick_size_in_quote_atoms_per_base_unit;
+    validate!(
+        market_tick_size > 0 && market_state.quote_lot_size > 0,
+        ErrorCode::InvalidPhoenixMarket,
+        "phoenix tick size and quote lot size must be positive"
     )?;
+    let valid_tick_size = base_spot_market.order_tick_size >= market_tick_size
+        && base_spot_market
+            .order_tick_size
+            .rem_euclid(market_tick_size)
+            == 0;
 
-    let state = &mut ctx.accounts.state;
-    let market_index = state.number_of_markets;
-    **perp_market = PerpMarket {
-        contract_type: ContractType::Perpetual,
-        contract_tier: ContractTier::Speculative, // default
-        status: if active_status {
-            MarketStatus::Active
-        } else {
-            MarketStatus::Initialized
-        },
-        name,
-        expiry_price: 0,
-        expiry_ts: 0,
-        pubkey: *perp_market_pubkey,
+    validate!(
+        valid_tick_size,
+        ErrorCode::InvalidPhoenixMarket,
+        "base market tick size ({}) not a multiple of phoenix tick size ({})",
+        base_spot_market.order_tick_size,
+        market_tick_size
+    )?;
+
+    let (phoenix_log_authority, _) =
+        Pubkey::find_program_address(&[b"log".as_ref()], &phoenix_program_id);
+
+    let phoenix_fulfillment_config_key = ctx.accounts.phoenix_fulfillment_config.key();
+    let mut phoenix_fulfillment_config = ctx.accounts.phoenix_fulfillment_config.load_init()?;
+    *phoenix_fulfillment_config = PhoenixV1FulfillmentConfig {
+        pubkey: phoenix_fulfillment_config_key,
+        phoenix_program_id,
+        phoenix_log_authority,
+        phoenix_market: phoenix_market_key,
+        phoenix_base_vault: market_state.base_params.vault_key,
+        phoenix_quote_vault: market_state.quote_params.vault_key,
         market_index,
-        number_of_users_with_base: 0,
-        number_of_users: 0,
-        margin_ratio_initial, // unit is 20% (+2 decimal places)
-        margin_ratio_maintenance,
-        imf_factor: 0,
-        next_fill_record_id: 1,
-        next_funding_rate_record_id: 1,
-        next_curve_record_id: 1,
-        pnl_pool: PoolBalance::default(),
-        insurance_claim: InsuranceClaim::default(),
-        unrealized_pnl_initial_asset_weight: SPOT_WEIGHT_PRECISION.cast()?, // 100%
-        unrealized_pnl_maintenance_asset_weight: SPOT_WEIGHT_PRECISION.cast()?, // 100%
-        unrealized_pnl_imf_factor: 0,
-        unrealized_pnl_max_imbalance: 0,
-        liquidator_fee,
-        if_liquidation_fee: LIQUIDATION_FEE_PRECISION / 100, // 1%
-        padding: [0; 51],
-        amm: AMM {
-            oracle: *ctx.accounts.oracle.key,
-            oracle_source,
-            base_asset_reserve: amm_base_asset_reserve,
-            quote_asset_reserve: amm_quote_asset_reserve,
-            terminal_quote_asset_reserve: amm_quote_asset_reserve,
-            ask_base_asset_reserve: amm_base_asset_reserve,
-            ask_quote_asset_reserve: amm_quote_asset_reserve,
-            bid_base_asset_reserve: amm_base_asset_reserve,
-            bid_quote_asset_reserve: amm_quote_asset_reserve,
-            cumulative_funding_rate_long: 0,
-            cumulative_funding_rate_short: 0,
-            total_social_loss: 0,
-            last_funding_rate: 0,
-            last_funding_rate_long: 0,
-            last_funding_rate_short: 0,
-            last_24h_avg_funding_rate: 0,
-            last_funding_rate_ts: now,
-            funding_period: amm_periodicity,
-            last_mark_price_twap: init_reserve_price,
-            last_mark_price_twap_5min: init_reserve_price,
-            last_mark_price_twap_ts: now,
-            sqrt_k: amm_base_asset_reserve,
-            concentration_coef,
-            min_base_asset_reserve,
-            max_base_asset_reserve,
-            peg_multiplier: amm_peg_multiplier,
-            total_fee: 0,
-            total_fee_withdrawn: 0,
-            total_fee_minus_distributions: 0,
-            total_mm_fee: 0,
-            total_exchange_fee: 0,
-            total_liquidation_fee: 0,
-            net_revenue_since_last_funding: 0,
-            historical_oracle_data: HistoricalOracleData {
-                last_oracle_price: oracle_price,
-                last_oracle_delay: oracle_delay,
-                last_oracle_price_twap,
-                last_oracle_price_twap_5min: oracle_price,
-                last_oracle_price_twap_ts: now,
-                ..HistoricalOracleData::default()
-            },
-            last_oracle_normalised_price: oracle_price,
-            last_oracle_conf_pct: 0,
-            last_oracle_reserve_price_spread_pct: 0, // todo
-            order_step_size: DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE,
-            order_tick_size: DEFAULT_QUOTE_ASSET_AMOUNT_TICK_SIZE,
-            min_order_size: DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE,
-            max_position_size: 0,
-            max_slippage_ratio: 50,         // ~2%
-            max_fill_reserve_fraction: 100, // moves price ~2%
-            base_spread: 0,
-            long_spread: 0,
-            short_spread: 0,
-            max_spread,
-            last_bid_price_twap: init_reserve_price,
-            last_ask_price_twap: init_reserve_price,
-            base_asset_amount_with_amm: 0,
-            base_asset_amount_long: 0,
-            base_asset_amount_short: 0,
-            quote_asset_amount: 0,
-            quote_entry_amount_long: 0,
-            quote_entry_amount_short: 0,
-            quote_break_even_amount_long: 0,
-            quote_break_even_amount_short: 0,
-            max_open_interest: 0,
-            mark_std: 0,
-            oracle_std: 0,
-            volume_24h: 0,
-            long_intensity_count: 0,
-            long_intensity_volume: 0,
-            short_intensity_count: 0,
-            short_intensity_volume: 0,
-            last_trade_ts: now,
-            curve_update_intensity: 0,
-            fee_pool: PoolBalance::default(),
-            base_asset_amount_per_lp: 0,
-            quote_asset_amount_per_lp: 0,
-            last_update_slot: clock_slot,
+        fulfillment_type: SpotFulfillmentType::PhoenixV1,
+        status: SpotFulfillmentConfigStatus::Enabled,
+        padding: [0; 4],
+    };
 
-            // lp stuff
-            base_asset_amount_with_unsettled_lp: 0,
-            user_lp_shares: 0,
-            amm_jit_intensity: 0, // turn it off at the start
+    Ok(())
+}
 
-            last_oracle_valid: false,
-            padding: [0; 48],
-        },
-    };
+pub fn handle_update_phoenix_fulfillment_config_status(
+    ctx: Context<UpdatePhoenixFulfillmentConfig>,
+    status: SpotFulfillmentConfigStatus,
+) -> Result<()> {
+    let mut config = load_mut!(ctx.accounts.phoenix_fulfillment_config)?;
+    config.status = status;
+    Ok(())
+}
 
-    safe_increment!(state.number_of_markets, 1);
+pub fn handle_initialize_high_leverage_mode_config(
+    ctx: Context<InitializeHighLeverageModeConfig>,
+    market_index: u16,
+    max_users: u32,
+) -> Result<()> {
+    let state = &ctx.accounts.state;
+
+    validate!(
+        market_index < state.number_of_markets,
+        ErrorCode::InvalidHighLeverageModeConfig,
+        "perp market index {} doesnt exist",
+        market_index
+    )?;
+
+    let mut config = ctx.accounts.high_leverage_mode_config.load_init()?;
+
+    config.market_index = market_index;
+    config.max_users = max_users;
 
     Ok(())
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_oracle(
-    ctx: Context<AdminUpdateSpotMarketOracle>,
-    oracle: Pubkey,
-    oracle_source: OracleSource,
+pub fn handle_update_high_leverage_mode_config(
+    ctx: Context<UpdateHighLeverageModeConfig>,
+    max_users: u32,
 ) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    let clock = Clock::get()?;
+    let mut config = load_mut!(ctx.accounts.high_leverage_mode_config)?;
 
-    // Verify oracle is readable
-    let OraclePriceData {
-        price: _oracle_price,
-        delay: _oracle_delay,
-        ..
-    } = get_oracle_price(&oracle_source, &ctx.accounts.oracle, clock.slot)?;
+    config.max_users = max_users;
+
+    config.validate()?;
 
-    spot_market.oracle = oracle;
-    spot_market.oracle_source = oracle_source;
     Ok(())
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_expiry(
-    ctx: Context<AdminUpdateSpotMarket>,
-    expiry_ts: i64,
+pub fn handle_initialize_fuel_boost(
+    ctx: Context<InitializeFuelBoost>,
+    market_type: MarketType,
+    market_index: u16,
 ) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    let now = Clock::get()?.unix_timestamp;
+    let state = &ctx.accounts.state;
+    let number_of_markets = match market_type {
+        MarketType::Perp => state.number_of_markets,
+        MarketType::Spot => state.number_of_spot_markets,
+    };
 
     validate!(
-        now < expiry_ts,
-        ErrorCode::DefaultError,
-        "Market expiry ts must later than current clock timestamp"
+        market_index < number_of_markets,
+        ErrorCode::InvalidFuelBoost,
+        "market index {} doesnt exist",
+        market_index
     )?;
 
-    spot_market.status = MarketStatus::ReduceOnly;
-    spot_market.expiry_ts = expiry_ts;
+    let mut fuel_boost = ctx.accounts.fuel_boost.load_init()?;
+
+    fuel_boost.market_type = market_type;
+    fuel_boost.market_index = market_index;
 
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_expiry(
-    ctx: Context<AdminUpdatePerpMarket>,
-    expiry_ts: i64,
+pub fn handle_update_fuel_boost(
+    ctx: Context<UpdateFuelBoost>,
+    taker: u8,
+    maker: u8,
+    borrows: u8,
+    insurance: u8,
+    start_ts: i64,
+    end_ts: i64,
 ) -> Result<()> {
-    let clock = Clock::get()?;
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let mut fuel_boost = load_mut!(ctx.accounts.fuel_boost)?;
+
     validate!(
-        clock.unix_timestamp < expiry_ts,
-        ErrorCode::DefaultError,
-        "Market expiry ts must later than current clock timestamp"
+        start_ts <= end_ts,
+        ErrorCode::InvalidFuelBoost,
+        "start_ts {} after end_ts {}",
+        start_ts,
+        end_ts
     )?;
 
-    // automatically enter reduce only
-    perp_market.status = MarketStatus::ReduceOnly;
-    perp_market.expiry_ts = expiry_ts;
+    msg!(
+        "fuel boost taker {} -> {}, maker {} -> {}, borrows {} -> {}, insurance {} -> {}",
+        fuel_boost.taker,
+        taker,
+        fuel_boost.maker,
+        maker,
+        fuel_boost.borrows,
+        borrows,
+        fuel_boost.insurance,
+        insurance
+    );
+
+    msg!(
+        "fuel boost epoch [{}, {}) -> [{}, {})",
+        fuel_boost.start_ts,
+        fuel_boost.end_ts,
+        start_ts,
+        end_ts
+    );
+
+    fuel_boost.taker = taker;
+    fuel_boost.maker = maker;
+    fuel_boost.borrows = borrows;
+    fuel_boost.insurance = insurance;
+    fuel_boost.start_ts = start_ts;
+    fuel_boost.end_ts = end_ts;
 
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_move_amm_price(
-    ctx: Context<AdminUpdatePerpMarket>,
-    base_asset_reserve: u128,
-    quote_asset_reserve: u128,
-    sqrt_k: u128,
+pub fn handle_initialize_perp_market_fee_override(
+    ctx: Context<InitializePerpMarketFeeOverride>,
+    market_index: u16,
 ) -> Result<()> {
     let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    controller::amm::move_price(
-        &mut perp_market.amm,
-        base_asset_reserve,
-        quote_asset_reserve,
-        sqrt_k,
+
+    validate!(
+        perp_market.market_index == market_index,
+        ErrorCode::InvalidMarketFeeOverride,
+        "perp market index {} != {}",
+        perp_market.market_index,
+        market_index
     )?;
-    validate_perp_market(perp_market)?;
+
+    let mut market_fee_override = ctx.accounts.market_fee_override.load_init()?;
+
+    market_fee_override.market_type = MarketType::Perp;
+    market_fee_override.market_index = market_index;
+
+    perp_market.amm.fee_override_enabled = true;
 
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_settle_expired_market_pools_to_revenue_pool(
-    ctx: Context<SettleExpiredMarketPoolsToRevenuePool>,
+pub fn handle_initialize_spot_market_fee_override(
+    ctx: Context<InitializeSpotMarketFeeOverride>,
+    market_index: u16,
 ) -> Result<()> {
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
     let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    let state = &ctx.accounts.state;
-
-    let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
-
-    controller::spot_balance::update_spot_market_cumulative_interest(spot_market, None, now)?;
 
     validate!(
-        spot_market.market_index == QUOTE_SPOT_MARKET_INDEX,
-        ErrorCode::DefaultError,
-        "spot_market must be perp market's quote asset"
+        spot_market.market_index == market_index,
+        ErrorCode::InvalidMarketFeeOverride,
+        "spot market index {} != {}",
+        spot_market.market_index,
+        market_index
     )?;
 
-    validate!(
-        perp_market.status == MarketStatus::Settlement,
-        ErrorCode::DefaultError,
-        "Market must in Settlement"
-    )?;
+    let mut market_fee_override = ctx.accounts.market_fee_override.load_init()?;
 
-    validate!(
-        perp_market.amm.base_asset_amount_long == 0
-            && perp_market.amm.base_asset_amount_short == 0
-            && perp_market.number_of_users_with_base == 0,
-        ErrorCode::DefaultError,
-        "outstanding base_asset_amounts must be balanced"
-    )?;
+    market_fee_override.market_type = MarketType::Spot;
+    market_fee_override.market_index = market_index;
+
+    spot_market.fee_override_enabled = true;
+
+    Ok(())
+}
+
+pub fn handle_update_market_fee_override(
+    ctx: Context<UpdateMarketFeeOverride>,
+    taker_fee_adjustment: i16,
+    maker_rebate_adjustment: i16,
+    promo_taker_fee_adjustment: i16,
+    promo_maker_rebate_adjustment: i16,
+    promo_start_ts: i64,
+    promo_end_ts: i64,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let mut market_fee_override = load_mut!(ctx.accounts.market_fee_override)?;
 
     validate!(
-        math::amm::calculate_net_user_cost_basis(&perp_market.amm)? == 0,
-        ErrorCode::DefaultError,
-        "outstanding quote_asset_amounts must be balanced"
+        [
+            taker_fee_adjustment,
+            maker_rebate_adjustment,
+            promo_taker_fee_adjustment,
+            promo_maker_rebate_adjustment,
+        ]
+        .iter()
+        .all(|fee_adjustment| (-MAX_FEE_ADJUSTMENT..=MAX_FEE_ADJUSTMENT).contains(fee_adjustment)),
+        ErrorCode::InvalidMarketFeeOverride,
+        "fee adjustments must be within [-{}, {}]",
+        MAX_FEE_ADJUSTMENT,
+        MAX_FEE_ADJUSTMENT
     )?;
 
-    // block when settlement_duration is default/unconfigured
     validate!(
-        state.settlement_duration != 0,
-        ErrorCode::DefaultError,
-        "invalid state.settlement_duration (is 0)"
+        promo_start_ts <= promo_end_ts,
+        ErrorCode::InvalidMarketFeeOverride,
+        "promo_start_ts {} after promo_end_ts {}",
+        promo_start_ts,
+        promo_end_ts
     )?;
 
-    let escrow_period_before_transfer = if state.settlement_duration > 1 {
-        // minimum of TWENTY_FOUR_HOUR to examine settlement process
-        TWENTY_FOUR_HOUR
-            .safe_add(state.settlement_duration.cast()?)?
-            .safe_sub(1)?
-    } else {
-        // for testing / expediting if settlement_duration not default but 1
-        state.settlement_duration.cast::<i64>()?
-    };
+    msg!(
+        "market fee override taker_fee_adjustment {} -> {}, maker_rebate_adjustment {} -> {}",
+        market_fee_override.taker_fee_adjustment,
+        taker_fee_adjustment,
+        market_fee_override.maker_rebate_adjustment,
+        maker_rebate_adjustment
+    );
 
-    validate!(
-        now > perp_market
-            .expiry_ts
-            .safe_add(escrow_period_before_transfer)?,
-        ErrorCode::DefaultError,
-        "must be escrow_period_before_transfer={} after market.expiry_ts",
-        escrow_period_before_transfer
-    )?;
+    msg!(
+        "market fee override promo_taker_fee_adjustment {} -> {}, promo_maker_rebate_adjustment {} -> {}",
+        market_fee_override.promo_taker_fee_adjustment,
+        promo_taker_fee_adjustment,
+        market_fee_override.promo_maker_rebate_adjustment,
+        promo_maker_rebate_adjustment
+    );
 
-    let fee_pool_token_amount = get_token_amount(
-        perp_market.amm.fee_pool.scaled_balance,
-        spot_market,
-        &SpotBalanceType::Deposit,
-    )?;
-    let pnl_pool_token_amount = get_token_amount(
-        perp_market.pnl_pool.scaled_balance,
-        spot_market,
-        &SpotBalanceType::Deposit,
-    )?;
+    msg!(
+        "market fee override promo [{}, {}) -> [{}, {})",
+        market_fee_override.promo_start_ts,
+        market_fee_override.promo_end_ts,
+        promo_start_ts,
+        promo_end_ts
+    );
 
-    controller::spot_balance::update_spot_balances(
-        fee_pool_token_amount,
-        &SpotBalanceType::Borrow,
-        spot_market,
-        &mut perp_market.amm.fee_pool,
-        false,
-    )?;
+    market_fee_override.taker_fee_adjustment = taker_fee_adjustment;
+    market_fee_override.maker_rebate_adjustment = maker_rebate_adjustment;
+    market_fee_override.promo_taker_fee_adjustment = promo_taker_fee_adjustment;
+    market_fee_override.promo_maker_rebate_adjustment = promo_maker_rebate_adjustment;
+    market_fee_override.promo_start_ts = promo_start_ts;
+    market_fee_override.promo_end_ts = promo_end_ts;
 
-    controller::spot_balance::update_spot_balances(
-        pnl_pool_token_amount,
-        &SpotBalanceType::Borrow,
-        spot_market,
-        &mut perp_market.pnl_pool,
-        false,
-    )?;
+    Ok(())
+}
 
-    controller::spot_balance::update_revenue_pool_balances(
-        pnl_pool_token_amount.safe_add(fee_pool_token_amount)?,
-        &SpotBalanceType::Deposit,
-        spot_market,
+pub fn handle_initialize_market_lookup_table(
+    ctx: Context<InitializeMarketLookupTable>,
+    table_index: u16,
+    recent_slot: u64,
+) -> Result<()> {
+    controller::lookup_table::create_lookup_table(
+        &ctx.accounts.address_lookup_table_program,
+        &ctx.accounts.lookup_table,
+        &ctx.accounts.drift_signer,
+        &ctx.accounts.admin.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        recent_slot,
+        ctx.accounts.state.signer_nonce,
     )?;
 
-    math::spot_withdraw::validate_spot_balances(spot_market)?;
+    let mut market_lookup_table = ctx.accounts.market_lookup_table.load_init()?;
 
-    perp_market.status = MarketStatus::Delisted;
+    market_lookup_table.lookup_table = ctx.accounts.lookup_table.key();
+    market_lookup_table.table_index = table_index;
+
+    msg!(
+        "market lookup table {} at {}",
+        table_index,
+        market_lookup_table.lookup_table
+    );
 
     Ok(())
 }
@@ -867,95 +1031,232 @@ pub fn handle_settle_expired_market_pools_to_revenue_pool(
 #[access_control(
     perp_market_valid(&ctx.accounts.perp_market)
 )]
-pub fn handle_deposit_into_perp_market_fee_pool(
-    ctx: Context<DepositIntoMarketFeePool>,
-    amount: u64,
+pub fn handle_update_perp_market_whitelist_enabled(
+    ctx: Context<AdminUpdatePerpMarket>,
+    whitelist_enabled: bool,
 ) -> Result<()> {
     let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
 
-    perp_market.amm.total_fee_minus_distributions = perp_market
-        .amm
-        .total_fee_minus_distributions
-        .safe_add(amount.cast()?)?;
+    msg!(
+        "perp market {} whitelist_enabled {} -> {}",
+        perp_market.market_index,
+        perp_market.amm.whitelist_enabled,
+        whitelist_enabled
+    );
 
-    let quote_spot_market = &mut load_mut!(ctx.accounts.quote_spot_market)?;
+    perp_market.amm.whitelist_enabled = whitelist_enabled;
 
-    controller::spot_balance::update_spot_balances(
-        amount.cast::<u128>()?,
-        &SpotBalanceType::Deposit,
-        quote_spot_market,
-        &mut perp_market.amm.fee_pool,
-        false,
+    Ok(())
+}
+
+pub fn handle_initialize_market_whitelist_entry(
+    ctx: Context<InitializeMarketWhitelistEntry>,
+    market_index: u16,
+    authority: Pubkey,
+) -> Result<()> {
+    let mut market_whitelist_entry = ctx.accounts.market_whitelist_entry.load_init()?;
+
+    market_whitelist_entry.authority = authority;
+    market_whitelist_entry.market_index = market_index;
+
+    msg!(
+        "whitelisted authority {} for perp market {}",
+        authority,
+        market_index
+    );
+
+    Ok(())
+}
+
+pub fn handle_delete_market_whitelist_entry(
+    ctx: Context<DeleteMarketWhitelistEntry>,
+) -> Result<()> {
+    let market_whitelist_entry = load!(ctx.accounts.market_whitelist_entry)?;
+
+    msg!(
+        "removed authority {} from perp market {} whitelist",
+        market_whitelist_entry.authority,
+        market_whitelist_entry.market_index
+    );
+
+    Ok(())
+}
+
+pub fn handle_initialize_fill_callback_program(
+    ctx: Context<InitializeFillCallbackProgram>,
+    program_id: Pubkey,
+) -> Result<()> {
+    let mut fill_callback_program = ctx.accounts.fill_callback_program.load_init()?;
+
+    fill_callback_program.program_id = program_id;
+
+    msg!("whitelisted fill callback program {}", program_id);
+
+    Ok(())
+}
+
+pub fn handle_delete_fill_callback_program(ctx: Context<DeleteFillCallbackProgram>) -> Result<()> {
+    let fill_callback_program = load!(ctx.accounts.fill_callback_program)?;
+
+    msg!(
+        "removed fill callback program {} from whitelist",
+        fill_callback_program.program_id
+    );
+
+    Ok(())
+}
+
+pub fn handle_update_referrer_reward_config(
+    ctx: Context<UpdateReferrerRewardConfig>,
+    referrer_reward_share: u8,
+    second_level_referrer_reward_share: u8,
+    referrer_reward_decay_months: u8,
+) -> Result<()> {
+    let mut referrer_stats = load_mut!(ctx.accounts.referrer_stats)?;
+
+    validate!(
+        referrer_stats.is_referrer,
+        ErrorCode::InvalidReferrerRewardConfig,
+        "user stats {} is not a referrer",
+        referrer_stats.authority
     )?;
 
-    controller::token::receive(
-        &ctx.accounts.token_program,
-        &ctx.accounts.source_vault,
-        &ctx.accounts.spot_market_vault,
-        &ctx.accounts.admin.to_account_info(),
-        amount,
+    validate!(
+        referrer_reward_share <= MAX_REFERRER_REWARD_SHARE,
+        ErrorCode::InvalidReferrerRewardConfig,
+        "referrer_reward_share {} above max {}",
+        referrer_reward_share,
+        MAX_REFERRER_REWARD_SHARE
+    )?;
+
+    validate!(
+        second_level_referrer_reward_share <= MAX_SECOND_LEVEL_REFERRER_REWARD_SHARE,
+        ErrorCode::InvalidReferrerRewardConfig,
+        "second_level_referrer_reward_share {} above max {}",
+        second_level_referrer_reward_share,
+        MAX_SECOND_LEVEL_REFERRER_REWARD_SHARE
     )?;
 
+    msg!(
+        "referrer_reward_share {} -> {}",
+        referrer_stats.referrer_reward_share,
+        referrer_reward_share
+    );
+
+    msg!(
+        "second_level_referrer_reward_share {} -> {}",
+        referrer_stats.second_level_referrer_reward_share,
+        second_level_referrer_reward_share
+    );
+
+    msg!(
+        "referrer_reward_decay_months {} -> {}",
+        referrer_stats.referrer_reward_decay_months,
+        referrer_reward_decay_months
+    );
+
+    referrer_stats.referrer_reward_share = referrer_reward_share;
+    referrer_stats.second_level_referrer_reward_share = second_level_referrer_reward_share;
+    referrer_stats.referrer_reward_decay_months = referrer_reward_decay_months;
+
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
-)]
-pub fn handle_repeg_amm_curve(ctx: Context<RepegCurve>, new_peg_candidate: u128) -> Result<()> {
-    let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
-    let clock_slot = clock.slot;
+pub fn handle_update_admin_timelock_duration(
+    ctx: Context<AdminUpdateState>,
+    admin_timelock_duration: u32,
+) -> Result<()> {
+    let state = &mut ctx.accounts.state;
 
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    let price_oracle = &ctx.accounts.oracle;
-    let OraclePriceData {
-        price: oracle_price,
-        ..
-    } = get_oracle_price(&perp_market.amm.oracle_source, price_oracle, clock.slot)?;
+    // shortening an active timelock has to wait out the current one
+    validate!(
+        state.admin_timelock_duration == 0
+            || admin_timelock_duration >= state.admin_timelock_duration,
+        ErrorCode::AdminTimelockActive,
+        "admin timelock of {}s can only be shortened through a queued change",
+        state.admin_timelock_duration
+    )?;
 
-    let peg_multiplier_before = perp_market.amm.peg_multiplier;
-    let base_asset_reserve_before = perp_market.amm.base_asset_reserve;
-    let quote_asset_reserve_before = perp_market.amm.quote_asset_reserve;
-    let sqrt_k_before = perp_market.amm.sqrt_k;
+    validate!(
+        admin_timelock_duration.cast::<i64>()? <= THIRTY_DAY,
+        ErrorCode::InvalidAdminChange,
+        "admin timelock duration {} above 30 days",
+        admin_timelock_duration
+    )?;
 
-    let oracle_validity_rails = &ctx.accounts.state.oracle_guard_rails;
+    msg!(
+        "admin_timelock_duration {} -> {}",
+        state.admin_timelock_duration,
+        admin_timelock_duration
+    );
 
-    let adjustment_cost = controller::repeg::repeg(
-        perp_market,
-        price_oracle,
-        new_peg_candidate,
-        clock_slot,
-        oracle_validity_rails,
-    )?;
+    state.admin_timelock_duration = admin_timelock_duration;
+    Ok(())
+}
 
-    let peg_multiplier_after = perp_market.amm.peg_multiplier;
-    let base_asset_reserve_after = perp_market.amm.base_asset_reserve;
-    let quote_asset_reserve_after = perp_market.amm.quote_asset_reserve;
-    let sqrt_k_after = perp_market.amm.sqrt_k;
+pub fn handle_queue_admin_change(
+    ctx: Context<QueueAdminChange>,
+    change: AdminChange,
+) -> Result<()> {
+    let state = &ctx.accounts.state;
+    let now = Clock::get()?.unix_timestamp;
 
-    emit!(CurveRecord {
+    match &change {
+        AdminChange::PerpMarketMarginRatio { market_index, .. }
+        | AdminChange::PerpMarketOracle { market_index, .. }
+        | AdminChange::PerpMarketImfFactor { market_index, .. }
+        | AdminChange::PerpMarketLiquidationFee { market_index, .. }
+        | AdminChange::PerpMarketContractTier { market_index, .. }
+        | AdminChange::PerpMarketFundingRateCaps { market_index, .. }
+        | AdminChange::PerpMarketMaxOpenInterest { market_index, .. } => {
+            validate!(
+                *market_index < state.number_of_markets,
+                ErrorCode::InvalidAdminChange,
+                "perp market {} not initialized",
+                market_index
+            )?;
+        }
+        AdminChange::SpotMarketMarginWeights { market_index, .. }
+        | AdminChange::SpotMarketOracle { market_index, .. }
+        | AdminChange::SpotMarketLiquidationFee { market_index, .. }
+        | AdminChange::SpotMarketAssetTier { market_index, .. }
+        | AdminChange::SpotMarketBorrowRate { market_index, .. }
+        | AdminChange::SpotMarketBorrowRateKinks { market_index, .. } => {
+            validate!(
+                *market_index < state.number_of_spot_markets,
+                ErrorCode::InvalidAdminChange,
+                "spot market {} not initialized",
+                market_index
+            )?;
+        }
+        AdminChange::PerpFeeStructure { fee_structure }
+        | AdminChange::SpotFeeStructure { fee_structure } => {
+            validate_fee_structure(fee_structure)?;
+        }
+        AdminChange::AdminTimelockDuration { duration } => {
+            validate!(
+                *duration < state.admin_timelock_duration,
+                ErrorCode::InvalidAdminChange,
+                "queued admin timelock duration {} must be shorter than the current {}",
+                duration,
+                state.admin_timelock_duration
+            )?;
+        }
+        AdminChange::OracleGuardRails { .. } => {}
+    }
+
+    let executable_ts = now.safe_add(state.admin_timelock_duration.cast()?)?;
+
+    let pending_admin_change = &mut ctx.accounts.pending_admin_change;
+    pending_admin_change.change = change.clone();
+    pending_admin_change.queued_ts = now;
+    pending_admin_change.executable_ts = executable_ts;
+
+    emit!(AdminChangeRecord {
         ts: now,
-        record_id: get_then_update_id!(perp_market, next_curve_record_id),
-        market_index: perp_market.market_index,
-        peg_multiplier_before,
-        base_asset_reserve_before,
-        quote_asset_reserve_before,
-        sqrt_k_before,
-        peg_multiplier_after,
-        base_asset_reserve_after,
-        quote_asset_reserve_after,
-        sqrt_k_after,
-        base_asset_amount_long: perp_market.amm.base_asset_amount_long.unsigned_abs(),
-        base_asset_amount_short: perp_market.amm.base_asset_amount_short.unsigned_abs(),
-        base_asset_amount_with_amm: perp_market.amm.base_asset_amount_with_amm,
-        number_of_users: perp_market.number_of_users,
-        total_fee: perp_market.amm.total_fee,
-        total_fee_minus_distributions: perp_market.amm.total_fee_minus_distributions,
-        adjustment_cost,
-        oracle_price,
-        fill_record: 0,
+        action: AdminChangeAction::Queue,
+        change,
+        executable_ts,
     });
 
     Ok(())
@@ -963,885 +1264,1085 @@ pub fn handle_repeg_amm_curve(ctx: Context<RepegCurve>, new_peg_candidate: u128)
 
 #[access_control(
     perp_market_valid(&ctx.accounts.perp_market)
-    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
 )]
-pub fn handle_update_amm_oracle_twap(ctx: Context<RepegCurve>) -> Result<()> {
-    // allow update to amm's oracle twap iff price gap is reduced and thus more tame funding
-    // otherwise if oracle error or funding flip: set oracle twap to mark twap (0 gap)
-
+pub fn handle_execute_perp_market_admin_change(
+    ctx: Context<ExecutePerpMarketAdminChange>,
+) -> Result<()> {
+    let pending_admin_change = &ctx.accounts.pending_admin_change;
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
     let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
 
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    let price_oracle = &ctx.accounts.oracle;
-    let oracle_twap = perp_market.amm.get_oracle_twap(price_oracle)?;
+    pending_admin_change.validate_executable(clock.unix_timestamp)?;
 
-    if let Some(oracle_twap) = oracle_twap {
-        let oracle_mark_gap_before = perp_market
-            .amm
-            .last_mark_price_twap
-            .cast::<i64>()?
-            .safe_sub(
-                perp_market
-                    .amm
-                    .historical_oracle_data
-                    .last_oracle_price_twap,
-            )?;
+    validate!(
+        pending_admin_change.change.market_index() == perp_market.market_index,
+        ErrorCode::InvalidAdminChange,
+        "admin change is for perp market {}",
+        pending_admin_change.change.market_index()
+    )?;
 
-        let oracle_mark_gap_after = perp_market
-            .amm
-            .last_mark_price_twap
-            .cast::<i64>()?
-            .safe_sub(oracle_twap)?;
+    match pending_admin_change.change {
+        AdminChange::PerpMarketMarginRatio {
+            margin_ratio_initial,
+            margin_ratio_maintenance,
+            ..
+        } => {
+            update_perp_market_margin_ratio(
+                perp_market,
+                margin_ratio_initial,
+                margin_ratio_maintenance,
+            )?;
+        }
+        AdminChange::PerpMarketOracle {
+            oracle,
+            oracle_source,
+            ..
+        } => {
+            validate!(
+                ctx.accounts.oracle.key == &oracle,
+                ErrorCode::InvalidAdminChange,
+                "oracle account must be the queued oracle {}",
+                oracle
+            )?;
+            validate_oracle_readable(&ctx.accounts.oracle, oracle_source, &clock)?;
 
-        if (oracle_mark_gap_after > 0 && oracle_mark_gap_before < 0)
-            || (oracle_mark_gap_after < 0 && oracle_mark_gap_before > 0)
-        {
-            perp_market
-                .amm
-                .historical_oracle_data
-                .last_oracle_price_twap = perp_market.amm.last_mark_price_twap.cast::<i64>()?;
-            perp_market
-                .amm
-                .historical_oracle_data
-                .last_oracle_price_twap_ts = now;
-        } else if oracle_mark_gap_after.unsigned_abs() <= oracle_mark_gap_before.unsigned_abs() {
-            perp_market
-                .amm
-                .historical_oracle_data
-                .last_oracle_price_twap = oracle_twap;
-            perp_market
-                .amm
-                .historical_oracle_data
-                .last_oracle_price_twap_ts = now;
-        } else {
-            return Err(ErrorCode::PriceBandsBreached.into());
+            perp_market.amm.oracle = oracle;
+            perp_market.amm.oracle_source = oracle_source;
         }
-    } else {
-        return Err(ErrorCode::InvalidOracle.into());
+        AdminChange::PerpMarketImfFactor {
+            imf_factor,
+            unrealized_pnl_imf_factor,
+            ..
+        } => {
+            update_perp_market_imf_factor(perp_market, imf_factor, unrealized_pnl_imf_factor)?;
+        }
+        AdminChange::PerpMarketLiquidationFee {
+            liquidator_fee,
+            if_liquidation_fee,
+            ..
+        } => {
+            update_perp_market_liquidation_fee(perp_market, liquidator_fee, if_liquidation_fee)?;
+        }
+        AdminChange::PerpMarketContractTier { contract_tier, .. } => {
+            perp_market.contract_tier = contract_tier;
+        }
+        AdminChange::PerpMarketFundingRateCaps {
+            max_funding_rate_spread,
+            escalated_max_funding_rate_spread,
+            funding_divergence_escalation_periods,
+            ..
+        } => {
+            update_perp_market_funding_rate_caps(
+                perp_market,
+                max_funding_rate_spread,
+                escalated_max_funding_rate_spread,
+                funding_divergence_escalation_periods,
+            )?;
+        }
+        AdminChange::PerpMarketMaxOpenInterest {
+            max_open_interest, ..
+        } => {
+            update_perp_market_max_open_interest(perp_market, max_open_interest)?;
+        }
+        _ => return Err(ErrorCode::InvalidAdminChange.into()),
     }
 
+    emit!(AdminChangeRecord {
+        ts: clock.unix_timestamp,
+        action: AdminChangeAction::Execute,
+        change: pending_admin_change.change.clone(),
+        executable_ts: pending_admin_change.executable_ts,
+    });
+
     Ok(())
 }
 
 #[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
+    spot_market_valid(&ctx.accounts.spot_market)
 )]
-pub fn handle_update_k(ctx: Context<AdminUpdateK>, sqrt_k: u128) -> Result<()> {
+pub fn handle_execute_spot_market_admin_change(
+    ctx: Context<ExecuteSpotMarketAdminChange>,
+) -> Result<()> {
+    let pending_admin_change = &ctx.accounts.pending_admin_change;
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
     let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
-
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
 
-    let base_asset_amount_long = perp_market.amm.base_asset_amount_long.unsigned_abs();
-    let base_asset_amount_short = perp_market.amm.base_asset_amount_short.unsigned_abs();
-    let base_asset_amount_with_amm = perp_market.amm.base_asset_amount_with_amm;
-    let number_of_users = perp_market.number_of_users_with_base;
+    pending_admin_change.validate_executable(clock.unix_timestamp)?;
 
-    let price_before = math::amm::calculate_price(
-        perp_market.amm.quote_asset_reserve,
-        perp_market.amm.base_asset_reserve,
-        perp_market.amm.peg_multiplier,
+    validate!(
+        pending_admin_change.change.market_index() == spot_market.market_index,
+        ErrorCode::InvalidAdminChange,
+        "admin change is for spot market {}",
+        pending_admin_change.change.market_index()
     )?;
 
-    let peg_multiplier_before = perp_market.amm.peg_multiplier;
-    let base_asset_reserve_before = perp_market.amm.base_asset_reserve;
-    let quote_asset_reserve_before = perp_market.amm.quote_asset_reserve;
-    let sqrt_k_before = perp_market.amm.sqrt_k;
+    match pending_admin_change.change {
+        AdminChange::SpotMarketMarginWeights {
+            initial_asset_weight,
+            maintenance_asset_weight,
+            initial_liability_weight,
+            maintenance_liability_weight,
+            imf_factor,
+            ..
+        } => {
+            update_spot_market_margin_weights(
+                spot_market,
+                initial_asset_weight,
+                maintenance_asset_weight,
+                initial_liability_weight,
+                maintenance_liability_weight,
+                imf_factor,
+            )?;
+        }
+        AdminChange::SpotMarketOracle {
+            oracle,
+            oracle_source,
+            ..
+        } => {
+            validate!(
+                ctx.accounts.oracle.key == &oracle,
+                ErrorCode::InvalidAdminChange,
+                "oracle account must be the queued oracle {}",
+                oracle
+            )?;
+            validate_oracle_readable(&ctx.accounts.oracle, oracle_source, &clock)?;
 
-    let k_increasing = sqrt_k > perp_market.amm.sqrt_k;
+            spot_market.oracle = oracle;
+            spot_market.oracle_source = oracle_source;
+        }
+        AdminChange::SpotMarketLiquidationFee {
+            liquidator_fee,
+            if_liquidation_fee,
+            ..
+        } => {
+            update_spot_market_liquidation_fee(spot_market, liquidator_fee, if_liquidation_fee)?;
+        }
+        AdminChange::SpotMarketAssetTier { asset_tier, .. } => {
+            update_spot_market_asset_tier(spot_market, asset_tier)?;
+        }
+        AdminChange::SpotMarketBorrowRate {
+            optimal_utilization,
+            optimal_borrow_rate,
+            max_borrow_rate,
+            ..
+        } => {
+            update_spot_market_borrow_rate(
+                spot_market,
+                optimal_utilization,
+                optimal_borrow_rate,
+                max_borrow_rate,
+            )?;
+        }
+        AdminChange::SpotMarketBorrowRateKinks {
+            borrow_rate_kinks, ..
+        } => {
+            update_spot_market_borrow_rate_kinks(spot_market, borrow_rate_kinks)?;
+        }
+        _ => return Err(ErrorCode::InvalidAdminChange.into()),
+    }
 
-    let new_sqrt_k_u192 = bn::U192::from(sqrt_k);
+    emit!(AdminChangeRecord {
+        ts: clock.unix_timestamp,
+        action: AdminChangeAction::Execute,
+        change: pending_admin_change.change.clone(),
+        executable_ts: pending_admin_change.executable_ts,
+    });
 
-    let update_k_result = get_update_k_result(perp_market, new_sqrt_k_u192, true)?;
+    Ok(())
+}
 
-    let adjustment_cost = math::cp_curve::adjust_k_cost(perp_market, &update_k_result)?;
+pub fn handle_execute_state_admin_change(ctx: Context<ExecuteStateAdminChange>) -> Result<()> {
+    let pending_admin_change = &ctx.accounts.pending_admin_change;
+    let state = &mut ctx.accounts.state;
+    let now = Clock::get()?.unix_timestamp;
 
-    math::cp_curve::update_k(perp_market, &update_k_result)?;
+    pending_admin_change.validate_executable(now)?;
 
-    if k_increasing {
-        validate!(
-            adjustment_cost >= 0,
-            ErrorCode::InvalidUpdateK,
-            "adjustment_cost negative when k increased",
-        )?;
-    } else {
-        validate!(
-            adjustment_cost <= 0,
-            ErrorCode::InvalidUpdateK,
-            "adjustment_cost positive when k decreased",
-        )?;
+    match &pending_admin_change.change {
+        AdminChange::PerpFeeStructure { fee_structure } => {
+            validate_fee_structure(fee_structure)?;
+            state.perp_fee_structure = fee_structure.clone();
+        }
+        AdminChange::SpotFeeStructure { fee_structure } => {
+            validate_fee_structure(fee_structure)?;
+            state.spot_fee_structure = fee_structure.clone();
+        }
+        AdminChange::AdminTimelockDuration { duration } => {
+            state.admin_timelock_duration = *duration;
+        }
+        AdminChange::OracleGuardRails { oracle_guard_rails } => {
+            state.oracle_guard_rails = *oracle_guard_rails;
+        }
+        _ => return Err(ErrorCode::InvalidAdminChange.into()),
     }
 
-    if adjustment_cost > 0 {
-        let max_cost = perp_market
-            .amm
-            .total_fee_minus_distributions
-            .safe_sub(get_total_fee_lower_bound(perp_market)?.cast()?)?
-            .safe_sub(perp_market.amm.total_fee_withdrawn.cast()?)?;
-
-        validate!(
-            adjustment_cost <= max_cost,
-            ErrorCode::InvalidUpdateK,
-            "adjustment_cost={} > max_cost={} for k change",
-            adjustment_cost,
-            max_cost
-        )?;
-    }
+    emit!(AdminChangeRecord {
+        ts: now,
+        action: AdminChangeAction::Execute,
+        change: pending_admin_change.change.clone(),
+        executable_ts: pending_admin_change.executable_ts,
+    });
 
-    validate!(
-        !k_increasing || perp_market.amm.sqrt_k < MAX_SQRT_K,
-        ErrorCode::InvalidUpdateK,
-        "cannot increase sqrt_k={} past MAX_SQRT_K",
-        perp_market.amm.sqrt_k
-    )?;
+    Ok(())
+}
 
-    validate!(
-        perp_market.amm.sqrt_k > perp_market.amm.user_lp_shares,
-        ErrorCode::InvalidUpdateK,
-        "cannot decrease sqrt_k={} below user_lp_shares={}",
-        perp_market.amm.sqrt_k,
-        perp_market.amm.user_lp_shares
-    )?;
+pub fn handle_initialize_admin_roles(ctx: Context<InitializeAdminRoles>) -> Result<()> {
+    ctx.accounts.admin_roles.load_init()?;
+    Ok(())
+}
 
-    perp_market.amm.total_fee_minus_distributions = perp_market
-        .amm
-        .total_fee_minus_distributions
-        .safe_sub(adjustment_cost)?;
+pub fn handle_update_admin_role(
+    ctx: Context<UpdateAdminRole>,
+    role: AdminRole,
+    key: Pubkey,
+) -> Result<()> {
+    let mut admin_roles = load_mut!(ctx.accounts.admin_roles)?;
 
-    perp_market.amm.net_revenue_since_last_funding = perp_market
-        .amm
-        .net_revenue_since_last_funding
-        .safe_sub(adjustment_cost as i64)?;
+    msg!("{:?} {} -> {}", role, admin_roles.get_role(role), key);
 
-    let amm = &perp_market.amm;
+    admin_roles.set_role(role, key);
+    Ok(())
+}
 
-    let price_after = math::amm::calculate_price(
-        amm.quote_asset_reserve,
-        amm.base_asset_reserve,
-        amm.peg_multiplier,
-    )?;
+pub fn handle_cancel_admin_change(ctx: Context<CancelAdminChange>) -> Result<()> {
+    let pending_admin_change = &ctx.accounts.pending_admin_change;
 
-    let price_change_too_large = price_before
-        .cast::<i128>()?
-        .safe_sub(price_after.cast::<i128>()?)?
-        .unsigned_abs()
-        .gt(&MAX_UPDATE_K_PRICE_CHANGE);
+    emit!(AdminChangeRecord {
+        ts: Clock::get()?.unix_timestamp,
+        action: AdminChangeAction::Cancel,
+        change: pending_admin_change.change.clone(),
+        executable_ts: pending_admin_change.executable_ts,
+    });
 
-    if price_change_too_large {
-        msg!(
-            "{:?} -> {:?} (> {:?})",
-            price_before,
-            price_after,
-            MAX_UPDATE_K_PRICE_CHANGE
-        );
-        return Err(ErrorCode::InvalidUpdateK.into());
-    }
+    Ok(())
+}
 
-    let k_sqrt_check = bn::U192::from(amm.base_asset_reserve)
-        .safe_mul(bn::U192::from(amm.quote_asset_reserve))?
-        .integer_sqrt()
-        .try_to_u128()?;
+pub fn handle_initialize_backstop_provider(
+    ctx: Context<InitializeBackstopProvider>,
+    committed_collateral: u64,
+    liquidation_fee_bonus: u32,
+) -> Result<()> {
+    let mut backstop_provider = ctx.accounts.backstop_provider.load_init()?;
 
-    let k_err = k_sqrt_check
-        .cast::<i128>()?
-        .safe_sub(amm.sqrt_k.cast::<i128>()?)?;
+    backstop_provider.user = ctx.accounts.user.key();
+    backstop_provider.committed_collateral = committed_collateral;
+    backstop_provider.liquidation_fee_bonus = liquidation_fee_bonus;
+    backstop_provider.approved = true;
 
-    if k_err.unsigned_abs() > 100 {
-        msg!("k_err={:?}, {:?} != {:?}", k_err, k_sqrt_check, amm.sqrt_k);
-        return Err(ErrorCode::InvalidUpdateK.into());
-    }
+    backstop_provider.validate()?;
 
-    let peg_multiplier_after = amm.peg_multiplier;
-    let base_asset_reserve_after = amm.base_asset_reserve;
-    let quote_asset_reserve_after = amm.quote_asset_reserve;
-    let sqrt_k_after = amm.sqrt_k;
+    Ok(())
+}
 
-    let total_fee = amm.total_fee;
-    let total_fee_minus_distributions = amm.total_fee_minus_distributions;
+pub fn handle_update_backstop_provider(
+    ctx: Context<UpdateBackstopProvider>,
+    committed_collateral: u64,
+    liquidation_fee_bonus: u32,
+    approved: bool,
+) -> Result<()> {
+    let mut backstop_provider = load_mut!(ctx.accounts.backstop_provider)?;
 
-    let OraclePriceData {
-        price: oracle_price,
-        ..
-    } = get_oracle_price(
-        &perp_market.amm.oracle_source,
-        &ctx.accounts.oracle,
-        clock.slot,
-    )?;
+    backstop_provider.committed_collateral = committed_collateral;
+    backstop_provider.liquidation_fee_bonus = liquidation_fee_bonus;
+    backstop_provider.approved = approved;
 
-    emit!(CurveRecord {
-        ts: now,
-        record_id: get_then_update_id!(perp_market, next_curve_record_id),
-        market_index: perp_market.market_index,
-        peg_multiplier_before,
-        base_asset_reserve_before,
-        quote_asset_reserve_before,
-        sqrt_k_before,
-        peg_multiplier_after,
-        base_asset_reserve_after,
-        quote_asset_reserve_after,
-        sqrt_k_after,
-        base_asset_amount_long,
-        base_asset_amount_short,
-        base_asset_amount_with_amm,
-        number_of_users,
-        adjustment_cost,
-        total_fee,
-        total_fee_minus_distributions,
-        oracle_price,
-        fill_record: 0,
-    });
+    backstop_provider.validate()?;
 
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
-)]
-pub fn handle_reset_amm_oracle_twap(ctx: Context<RepegCurve>) -> Result<()> {
-    // if oracle is invalid, failsafe to reset amm oracle_twap to the mark_twap
-
-    let state = &ctx.accounts.state;
-
+pub fn handle_initialize_prelaunch_oracle(
+    ctx: Context<InitializePrelaunchOracle>,
+    params: PrelaunchOracleParams,
+) -> Result<()> {
+    let mut oracle = ctx.accounts.prelaunch_oracle.load_init()?;
     let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
-    let clock_slot = clock.slot;
-
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    let price_oracle = &ctx.accounts.oracle;
-    let oracle_price_data =
-        &get_oracle_price(&perp_market.amm.oracle_source, price_oracle, clock_slot)?;
 
-    let oracle_validity = oracle::oracle_validity(
-        perp_market
-            .amm
-            .historical_oracle_data
-            .last_oracle_price_twap,
-        oracle_price_data,
-        &state.oracle_guard_rails.validity,
+    let price = params.price.ok_or(ErrorCode::DefaultError)?;
+    validate!(
+        price > 0,
+        ErrorCode::InvalidPrelaunchOraclePrice,
+        "prelaunch oracle price must be positive"
     )?;
 
-    let is_oracle_valid =
-        is_oracle_valid_for_action(oracle_validity, Some(DriftAction::UpdateFunding))?;
+    let max_price_drift_per_slot = params
+        .max_price_drift_per_slot
+        .ok_or(ErrorCode::DefaultError)?;
+    validate!(
+        max_price_drift_per_slot > 0,
+        ErrorCode::DefaultError,
+        "max_price_drift_per_slot must be positive"
+    )?;
 
-    if !is_oracle_valid {
-        perp_market
-            .amm
-            .historical_oracle_data
-            .last_oracle_price_twap = perp_market.amm.last_mark_price_twap.cast::<i64>()?;
-        perp_market
-            .amm
-            .historical_oracle_data
-            .last_oracle_price_twap_ts = now;
-    }
+    oracle.perp_market_index = params.perp_market_index;
+    oracle.price = price;
+    oracle.max_price_drift_per_slot = max_price_drift_per_slot;
+    oracle.confidence = params.confidence.unwrap_or(0);
+    oracle.last_update_slot = clock.slot;
 
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_margin_ratio(
-    ctx: Context<AdminUpdatePerpMarket>,
-    margin_ratio_initial: u32,
-    margin_ratio_maintenance: u32,
+pub fn handle_update_prelaunch_oracle_params(
+    ctx: Context<UpdatePrelaunchOracleParams>,
+    params: PrelaunchOracleParams,
 ) -> Result<()> {
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    validate_margin(
-        margin_ratio_initial,
-        margin_ratio_maintenance,
-        perp_market.liquidator_fee,
-        perp_market.amm.max_spread,
-    )?;
+    let mut oracle = load_mut!(ctx.accounts.prelaunch_oracle)?;
+    let clock = Clock::get()?;
+
+    // price moves are bounded by the drift configured before this update
+    if let Some(price) = params.price {
+        oracle.update_price(price, clock.slot)?;
+    }
+
+    if let Some(max_price_drift_per_slot) = params.max_price_drift_per_slot {
+        validate!(
+            max_price_drift_per_slot > 0,
+            ErrorCode::DefaultError,
+            "max_price_drift_per_slot must be positive"
+        )?;
+        oracle.max_price_drift_per_slot = max_price_drift_per_slot;
+    }
+
+    if let Some(confidence) = params.confidence {
+        oracle.confidence = confidence;
+    }
 
-    perp_market.margin_ratio_initial = margin_ratio_initial;
-    perp_market.margin_ratio_maintenance = margin_ratio_maintenance;
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_max_imbalances(
-    ctx: Context<AdminUpdatePerpMarket>,
-    unrealized_max_imbalance: u64,
-    max_revenue_withdraw_per_period: u64,
-    quote_max_insurance: u64,
+pub fn handle_initialize_lst_oracle(
+    ctx: Context<InitializeLstOracle>,
+    sol_oracle_source: OracleSource,
 ) -> Result<()> {
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-
-    let max_insurance_for_tier = match perp_market.contract_tier {
-        ContractTier::A => INSURANCE_A_MAX,
-        ContractTier::B => INSURANCE_B_MAX,
-        ContractTier::C => INSURANCE_C_MAX,
-        ContractTier::Speculative => INSURANCE_SPECULATIVE_MAX,
-        ContractTier::Isolated => INSURANCE_SPECULATIVE_MAX,
-    };
-
-    validate!(
-        max_revenue_withdraw_per_period
-            <= max_insurance_for_tier.max(FEE_POOL_TO_REVENUE_POOL_THRESHOLD.cast()?)
-            && unrealized_max_imbalance <= max_insurance_for_tier + 1
-            && quote_max_insurance <= max_insurance_for_tier,
-        ErrorCode::DefaultError,
-        "all maxs must be less than max_insurance for ContractTier ={}",
-        max_insurance_for_tier
-    )?;
+    let mut oracle = ctx.accounts.lst_oracle.load_init()?;
+    let clock_slot = Clock::get()?.slot;
 
     validate!(
-        perp_market.insurance_claim.quote_settled_insurance <= quote_max_insurance,
-        ErrorCode::DefaultError,
-        "quote_max_insurance must be above market.insurance_claim.quote_settled_insurance={}",
-        perp_market.insurance_claim.quote_settled_insurance
+        matches!(
+            sol_oracle_source,
+            OracleSource::Pyth | OracleSource::PythPull | OracleSource::SwitchboardOnDemand
+        ),
+        ErrorCode::InvalidLstOracle,
+        "sol oracle source must be an external price feed"
     )?;
 
-    msg!(
-        "market.max_revenue_withdraw_per_period: {:?} -> {:?}",
-        perp_market.insurance_claim.max_revenue_withdraw_per_period,
-        max_revenue_withdraw_per_period
-    );
-
-    msg!(
-        "market.unrealized_max_imbalance: {:?} -> {:?}",
-        perp_market.unrealized_pnl_max_imbalance,
-        unrealized_max_imbalance
-    );
-
-    msg!(
-        "market.quote_max_insurance: {:?} -> {:?}",
-        perp_market.insurance_claim.quote_max_insurance,
-        quote_max_insurance
-    );
+    let stake_pool_type = get_stake_pool_type(&ctx.accounts.stake_pool)?;
+    let redemption_rate = get_redemption_rate(&ctx.accounts.stake_pool, stake_pool_type)?;
+    let sol_price_data =
+        get_oracle_price(&sol_oracle_source, &ctx.accounts.sol_oracle, clock_slot)?;
 
-    perp_market.insurance_claim.max_revenue_withdraw_per_period = max_revenue_withdraw_per_period;
-    perp_market.unrealized_pnl_max_imbalance = unrealized_max_imbalance;
-    perp_market.insurance_claim.quote_max_insurance = quote_max_insurance;
+    oracle.sol_oracle = ctx.accounts.sol_oracle.key();
+    oracle.sol_oracle_source = sol_oracle_source;
+    oracle.stake_pool = ctx.accounts.stake_pool.key();
+    oracle.stake_pool_type = stake_pool_type;
+    oracle.update(&sol_price_data, redemption_rate, clock_slot)?;
 
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_name(
-    ctx: Context<AdminUpdatePerpMarket>,
-    name: [u8; 32],
-) -> Result<()> {
-    let mut perp_market = load_mut!(ctx.accounts.perp_market)?;
-    perp_market.name = name;
-    Ok(())
-}
-
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_name(
-    ctx: Context<AdminUpdateSpotMarket>,
-    name: [u8; 32],
-) -> Result<()> {
-    let mut spot_market = load_mut!(ctx.accounts.spot_market)?;
-    spot_market.name = name;
-    Ok(())
-}
-
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_liquidation_fee(
-    ctx: Context<AdminUpdatePerpMarket>,
-    liquidator_fee: u32,
-    if_liquidation_fee: u32,
-) -> Result<()> {
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+pub fn handle_update_serum_vault(ctx: Context<UpdateSerumVault>) -> Result<()> {
+    let vault = &ctx.accounts.srm_vault;
     validate!(
-        liquidator_fee < LIQUIDATION_FEE_PRECISION,
-        ErrorCode::DefaultError,
-        "Liquidation fee must be less than 100%"
+        vault.mint == crate::ids::srm_mint::id() || vault.mint == crate::ids::msrm_mint::id(),
+        ErrorCode::InvalidSrmVault,
+        "vault did not hav srm or msrm mint"
     )?;
 
     validate!(
-        if_liquidation_fee < LIQUIDATION_FEE_PRECISION,
-        ErrorCode::DefaultError,
-        "If liquidation fee must be less than 100%"
-    )?;
-
-    validate_margin(
-        perp_market.margin_ratio_initial,
-        perp_market.margin_ratio_maintenance,
-        liquidator_fee,
-        perp_market.amm.max_spread,
+        vault.owner == ctx.accounts.state.signer,
+        ErrorCode::InvalidVaultOwner,
+        "vault owner was not program signer"
     )?;
 
-    perp_market.liquidator_fee = liquidator_fee;
-    perp_market.if_liquidation_fee = if_liquidation_fee;
-    Ok(())
-}
+    let state = &mut ctx.accounts.state;
+    state.srm_vault = vault.key();
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_insurance_fund_unstaking_period(
-    ctx: Context<AdminUpdateSpotMarket>,
-    insurance_fund_unstaking_period: i64,
-) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    spot_market.insurance_fund.unstaking_period = insurance_fund_unstaking_period;
     Ok(())
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_liquidation_fee(
-    ctx: Context<AdminUpdateSpotMarket>,
+pub fn handle_initialize_perp_market(
+    ctx: Context<InitializePerpMarket>,
+    amm_base_asset_reserve: u128,
+    amm_quote_asset_reserve: u128,
+    amm_periodicity: i64,
+    amm_peg_multiplier: u128,
+    oracle_source: OracleSource,
+    margin_ratio_initial: u32,
+    margin_ratio_maintenance: u32,
     liquidator_fee: u32,
-    if_liquidation_fee: u32,
+    active_status: bool,
+    name: [u8; 32],
 ) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    validate!(
-        liquidator_fee < LIQUIDATION_FEE_PRECISION,
-        ErrorCode::DefaultError,
-        "Liquidation fee must be less than 100%"
-    )?;
-
-    validate!(
-        if_liquidation_fee <= LIQUIDATION_FEE_PRECISION / 20,
-        ErrorCode::DefaultError,
-        "if_liquidation_fee must be <= 5%"
-    )?;
+    let perp_market_pubkey = ctx.accounts.perp_market.to_account_info().key;
+    let perp_market = &mut ctx.accounts.perp_market.load_init()?;
 
-    spot_market.liquidator_fee = liquidator_fee;
-    spot_market.if_liquidation_fee = if_liquidation_fee;
-    Ok(())
+    initialize_perp_market(
+        perp_market,
+        perp_market_pubkey,
+        &ctx.accounts.oracle,
+        &mut ctx.accounts.state,
+        amm_base_asset_reserve,
+        amm_quote_asset_reserve,
+        amm_periodicity,
+        amm_peg_multiplier,
+        oracle_source,
+        margin_ratio_initial,
+        margin_ratio_maintenance,
+        liquidator_fee,
+        active_status,
+        name,
+    )
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_withdraw_guard_threshold(
-    ctx: Context<AdminUpdateSpotMarket>,
-    withdraw_guard_threshold: u64,
+#[allow(clippy::too_many_arguments)]
+fn initialize_perp_market(
+    perp_market: &mut PerpMarket,
+    perp_market_pubkey: &Pubkey,
+    oracle: &AccountInfo,
+    state: &mut State,
+    amm_base_asset_reserve: u128,
+    amm_quote_asset_reserve: u128,
+    amm_periodicity: i64,
+    amm_peg_multiplier: u128,
+    oracle_source: OracleSource,
+    margin_ratio_initial: u32,
+    margin_ratio_maintenance: u32,
+    liquidator_fee: u32,
+    active_status: bool,
+    name: [u8; 32],
 ) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    msg!(
-        "spot_market.withdraw_guard_threshold: {:?} -> {:?}",
-        spot_market.withdraw_guard_threshold,
-        withdraw_guard_threshold
-    );
-    spot_market.withdraw_guard_threshold = withdraw_guard_threshold;
-    Ok(())
-}
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let clock_slot = clock.slot;
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_if_factor(
-    ctx: Context<AdminUpdateSpotMarket>,
-    spot_market_index: u16,
-    user_if_factor: u32,
-    total_if_factor: u32,
-) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    if amm_base_asset_reserve != amm_quote_asset_reserve {
+        return Err(ErrorCode::InvalidInitialPeg.into());
+    }
 
-    validate!(
-        spot_market.market_index == spot_market_index,
-        ErrorCode::DefaultError,
-        "spot_market_index dne spot_market.index"
+    let init_reserve_price = amm::calculate_price(
+        amm_quote_asset_reserve,
+        amm_base_asset_reserve,
+        amm_peg_multiplier,
     )?;
 
-    validate!(
-        user_if_factor <= total_if_factor,
-        ErrorCode::DefaultError,
-        "user_if_factor must be <= total_if_factor"
-    )?;
+    assert_eq!(amm_peg_multiplier, init_reserve_price.cast::<u128>()?);
 
-    validate!(
-        total_if_factor <= IF_FACTOR_PRECISION.cast()?,
-        ErrorCode::DefaultError,
-        "total_if_factor must be <= 100%"
-    )?;
+    let concentration_coef = MAX_CONCENTRATION_COEFFICIENT;
 
-    msg!(
-        "spot_market.user_if_factor: {:?} -> {:?}",
-        spot_market.insurance_fund.user_factor,
-        user_if_factor
-    );
-    msg!(
-        "spot_market.total_if_factor: {:?} -> {:?}",
-        spot_market.insurance_fund.total_factor,
-        total_if_factor
-    );
+    // Verify there's no overflow
+    let _k =
+        bn::U192::from(amm_base_asset_reserve).safe_mul(bn::U192::from(amm_quote_asset_reserve))?;
 
-    spot_market.insurance_fund.user_factor = user_if_factor;
-    spot_market.insurance_fund.total_factor = total_if_factor;
+    let (min_base_asset_reserve, max_base_asset_reserve) =
+        amm::calculate_bid_ask_bounds(concentration_coef, amm_base_asset_reserve)?;
 
-    Ok(())
-}
+    // Verify oracle is readable
+    let (oracle_price, oracle_delay, last_oracle_price_twap) = match oracle_source {
+        OracleSource::Pyth => {
+            let OraclePriceData {
+                price: oracle_price,
+                delay: oracle_delay,
+                ..
+            } = get_pyth_price(oracle, clock_slot, 1)?;
+            let last_oracle_price_twap = perp_market.amm.get_pyth_twap(oracle, 1)?;
+            (oracle_price, oracle_delay, last_oracle_price_twap)
+        }
+        OracleSource::Pyth1K => {
+            let OraclePriceData {
+                price: oracle_price,
+                delay: oracle_delay,
+                ..
+            } = get_pyth_price(oracle, clock_slot, 1000)?;
+            let last_oracle_price_twap = perp_market.amm.get_pyth_twap(oracle, 1000)?;
+            (oracle_price, oracle_delay, last_oracle_price_twap)
+        }
+        OracleSource::Pyth1M => {
+            let OraclePriceData {
+                price: oracle_price,
+                delay: oracle_delay,
+                ..
+            } = get_pyth_price(oracle, clock_slot, 1000000)?;
+            let last_oracle_price_twap = perp_market.amm.get_pyth_twap(oracle, 1000000)?;
+            (oracle_price, oracle_delay, last_oracle_price_twap)
+        }
+        OracleSource::Switchboard => {
+            msg!("Switchboard oracle cant be used for perp market");
+            return Err(ErrorCode::InvalidOracle.into());
+        }
+        OracleSource::PythPull => {
+            validate_pyth_pull_publish_time(oracle, now, PYTH_PULL_MAX_PUBLISH_TIME_STALENESS)?;
+            let OraclePriceData {
+                price: oracle_price,
+                delay: oracle_delay,
+                ..
+            } = get_pyth_pull_price(oracle, clock_slot)?;
+            let last_oracle_price_twap = get_pyth_pull_twap(oracle)?;
+            (oracle_price, oracle_delay, last_oracle_price_twap)
+        }
+        OracleSource::SwitchboardOnDemand => {
+            let OraclePriceData {
+                price: oracle_price,
+                delay: oracle_delay,
+                ..
+            } = get_sb_on_demand_price(oracle, clock_slot)?;
+            (oracle_price, oracle_delay, oracle_price)
+        }
+        OracleSource::Prelaunch => {
+            let OraclePriceData {
+                price: oracle_price,
+                delay: oracle_delay,
+                ..
+            } = get_prelaunch_price(oracle)?;
+            (oracle_price, oracle_delay, oracle_price)
+        }
+        OracleSource::LstRedemptionRate => {
+            let OraclePriceData {
+                price: oracle_price,
+                delay: oracle_delay,
+                ..
+            } = get_lst_price(oracle, clock_slot)?;
+            (oracle_price, oracle_delay, oracle_price)
+        }
+        OracleSource::QuoteAsset => {
+            msg!("Quote asset oracle cant be used for perp market");
+            return Err(ErrorCode::InvalidOracle.into());
+        }
+    };
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_revenue_settle_period(
-    ctx: Context<AdminUpdateSpotMarket>,
-    revenue_settle_period: i64,
-) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    validate!(revenue_settle_period > 0, ErrorCode::DefaultError)?;
-    msg!(
-        "spot_market.revenue_settle_period: {:?} -> {:?}",
-        spot_market.insurance_fund.revenue_settle_period,
-        revenue_settle_period
-    );
-    spot_market.insurance_fund.revenue_settle_period = revenue_settle_period;
-    Ok(())
-}
+    let max_spread = ((margin_ratio_initial - margin_ratio_maintenance) * (100 - 5)) as u32;
+
+    // todo? should ensure peg within 1 cent of current oracle?
+    // validate!(
+    //     amm_peg_multiplier.cast::<i128>()?
+    //         .safe_sub(oracle_price)
+    //         ?
+    //         .unsigned_abs()
+    //         < PRICE_PRECISION / 100,
+    //     ErrorCode::InvalidInitialPeg
+    // )?;
+
+    validate_margin(
+        margin_ratio_initial,
+        margin_ratio_maintenance,
+        liquidator_fee.safe_add(DEFAULT_IF_LIQUIDATION_FEE)?,
+        max_spread,
+    )?;
+
+    let market_index = state.number_of_markets;
+    *perp_market = PerpMarket {
+        contract_type: ContractType::Perpetual,
+        contract_tier: ContractTier::Speculative, // default
+        status: if active_status {
+            MarketStatus::Active
+        } else {
+            MarketStatus::Initialized
+        },
+        name,
+        expiry_price: 0,
+        expiry_ts: 0,
+        pubkey: *perp_market_pubkey,
+        market_index,
+        number_of_users_with_base: 0,
+        number_of_users: 0,
+        margin_ratio_initial, // unit is 20% (+2 decimal places)
+        margin_ratio_maintenance,
+        imf_factor: 0,
+        next_fill_record_id: 1,
+        next_funding_rate_record_id: 1,
+        next_curve_record_id: 1,
+        pnl_pool: PoolBalance::default(),
+        insurance_claim: InsuranceClaim::default(),
+        unrealized_pnl_initial_asset_weight: SPOT_WEIGHT_PRECISION.cast()?, // 100%
+        unrealized_pnl_maintenance_asset_weight: SPOT_WEIGHT_PRECISION.cast()?, // 100%
+        unrealized_pnl_imf_factor: 0,
+        unrealized_pnl_max_imbalance: 0,
+        liquidator_fee,
+        if_liquidation_fee: DEFAULT_IF_LIQUIDATION_FEE,
+        additional_oracles: [Pubkey::default(); 2],
+        additional_oracle_sources: [OracleSource::default(); 2],
+        paused_operations: 0,
+        oracle_circuit_breaker_band: 0,
+        auction_extension_price_buffer: 0,
+        margin_tiers: [MarginTier::default(); 3],
+        high_leverage_margin_ratio_initial: 0,
+        high_leverage_margin_ratio_maintenance: 0,
+        auction_extension_duration: 0,
+        correlation_group: 0,
+        correlation_margin_credit: 0,
+        auto_deleverage_enabled: false,
+        insurance_fund_max_draw_pct: 0,
+        lp_early_removal_penalty: 0,
+        lp_min_duration: 0,
+        high_24h: 0,
+        low_24h: 0,
+        funding_paid_24h: 0,
+        insurance_fund_lent: 0,
+        insurance_fund_lend_last_ts: 0,
+        insurance_fund_lend_rate: 0,
+        order_tick_size_bps: 0,
+        insurance_fund_lend_max_pct: 0,
+        padding: [0; 1],
+        amm: AMM {
+            oracle: *oracle.key,
+            oracle_source,
+            base_asset_reserve: amm_base_asset_reserve,
+            quote_asset_reserve: amm_quote_asset_reserve,
+            terminal_quote_asset_reserve: amm_quote_asset_reserve,
+            ask_base_asset_reserve: amm_base_asset_reserve,
+            ask_quote_asset_reserve: amm_quote_asset_reserve,
+            bid_base_asset_reserve: amm_base_asset_reserve,
+            bid_quote_asset_reserve: amm_quote_asset_reserve,
+            cumulative_funding_rate_long: 0,
+            cumulative_funding_rate_short: 0,
+            total_social_loss: 0,
+            last_funding_rate: 0,
+            last_funding_rate_long: 0,
+            last_funding_rate_short: 0,
+            last_24h_avg_funding_rate: 0,
+            last_funding_rate_ts: now,
+            funding_period: amm_periodicity,
+            last_mark_price_twap: init_reserve_price,
+            last_mark_price_twap_5min: init_reserve_price,
+            last_mark_price_twap_ts: now,
+            sqrt_k: amm_base_asset_reserve,
+            concentration_coef,
+            min_base_asset_reserve,
+            max_base_asset_reserve,
+            peg_multiplier: amm_peg_multiplier,
+            total_fee: 0,
+            total_fee_withdrawn: 0,
+            total_fee_minus_distributions: 0,
+            total_mm_fee: 0,
+            total_exchange_fee: 0,
+            total_liquidation_fee: 0,
+            net_revenue_since_last_funding: 0,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price: oracle_price,
+                last_oracle_delay: oracle_delay,
+                last_oracle_price_twap,
+                last_oracle_price_twap_5min: oracle_price,
+                last_oracle_price_twap_ts: now,
+                ..HistoricalOracleData::default()
+            },
+            last_oracle_normalised_price: oracle_price,
+            last_oracle_conf_pct: 0,
+            last_oracle_reserve_price_spread_pct: 0, // todo
+            order_step_size: DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE,
+            order_tick_size: DEFAULT_QUOTE_ASSET_AMOUNT_TICK_SIZE,
+            min_order_size: DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE,
+            max_position_size: 0,
+            max_slippage_ratio: 50,         // ~2%
+            max_fill_reserve_fraction: 100, // moves price ~2%
+            base_spread: 0,
+            long_spread: 0,
+            short_spread: 0,
+            max_spread,
+            last_bid_price_twap: init_reserve_price,
+            last_ask_price_twap: init_reserve_price,
+            base_asset_amount_with_amm: 0,
+            base_asset_amount_long: 0,
+            base_asset_amount_short: 0,
+            quote_asset_amount: 0,
+            quote_entry_amount_long: 0,
+            quote_entry_amount_short: 0,
+            quote_break_even_amount_long: 0,
+            quote_break_even_amount_short: 0,
+            max_open_interest: 0,
+            mark_std: 0,
+            oracle_std: 0,
+            volume_24h: 0,
+            long_intensity_count: 0,
+            long_intensity_volume: 0,
+            short_intensity_count: 0,
+            short_intensity_volume: 0,
+            last_trade_ts: now,
+            curve_update_intensity: 0,
+            fee_pool: PoolBalance::default(),
+            base_asset_amount_per_lp: 0,
+            quote_asset_amount_per_lp: 0,
+            last_update_slot: clock_slot,
+
+            // lp stuff
+            base_asset_amount_with_unsettled_lp: 0,
+            user_lp_shares: 0,
+            amm_jit_intensity: 0, // turn it off at the start
+
+            last_oracle_valid: false,
+            validity_guard_rails_override: ValidityGuardRailsOverride::default(),
+            max_funding_rate_spread: 0,
+            escalated_max_funding_rate_spread: 0,
+            funding_divergence_escalation_periods: 0,
+            funding_divergence_period_count: 0,
+            inventory_skew_spread: 0,
+            volatility_spread_scale: 0,
+            max_reference_price_offset: 0,
+            reference_price_offset: 0,
+            fee_override_enabled: false,
+            delisting_enabled: false,
+            whitelist_enabled: false,
+            padding: [0; 9],
+        },
+    };
+
+    safe_increment!(state.number_of_markets, 1);
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_status(
-    ctx: Context<AdminUpdateSpotMarket>,
-    status: MarketStatus,
-) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    spot_market.status = status;
     Ok(())
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_asset_tier(
-    ctx: Context<AdminUpdateSpotMarket>,
-    asset_tier: AssetTier,
+pub fn handle_initialize_perp_market_listing_config(
+    ctx: Context<InitializePerpMarketListingConfig>,
+    bond_amount: u64,
+    veto_window: u32,
 ) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    validate_perp_market_listing_config(bond_amount, veto_window)?;
 
-    if spot_market.initial_asset_weight > 0 {
-        validate!(
-            matches!(asset_tier, AssetTier::Collateral | AssetTier::Protected),
-            ErrorCode::DefaultError,
-            "initial_asset_weight > 0 so AssetTier must be collateral or protected"
-        )?;
-    }
+    let mut perp_market_listing_config = ctx.accounts.perp_market_listing_config.load_init()?;
+    perp_market_listing_config.bond_amount = bond_amount;
+    perp_market_listing_config.veto_window = veto_window;
+    perp_market_listing_config.enabled = true;
 
-    spot_market.asset_tier = asset_tier;
     Ok(())
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_margin_weights(
-    ctx: Context<AdminUpdateSpotMarket>,
-    initial_asset_weight: u32,
-    maintenance_asset_weight: u32,
-    initial_liability_weight: u32,
-    maintenance_liability_weight: u32,
-    imf_factor: u32,
+pub fn handle_update_perp_market_listing_config(
+    ctx: Context<UpdatePerpMarketListingConfig>,
+    bond_amount: u64,
+    veto_window: u32,
+    enabled: bool,
 ) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    validate_perp_market_listing_config(bond_amount, veto_window)?;
 
-    validate_margin_weights(
-        spot_market.market_index,
-        initial_asset_weight,
-        maintenance_asset_weight,
-        initial_liability_weight,
-        maintenance_liability_weight,
-        imf_factor,
-    )?;
+    let mut perp_market_listing_config = load_mut!(ctx.accounts.perp_market_listing_config)?;
 
-    spot_market.initial_asset_weight = initial_asset_weight;
-    spot_market.maintenance_asset_weight = maintenance_asset_weight;
-    spot_market.initial_liability_weight = initial_liability_weight;
-    spot_market.maintenance_liability_weight = maintenance_liability_weight;
-    spot_market.imf_factor = imf_factor;
+    msg!(
+        "perp market listing bond_amount {} -> {}",
+        perp_market_listing_config.bond_amount,
+        bond_amount
+    );
+    msg!(
+        "perp market listing veto_window {} -> {}",
+        perp_market_listing_config.veto_window,
+        veto_window
+    );
+    msg!(
+        "perp market listing enabled {} -> {}",
+        perp_market_listing_config.enabled,
+        enabled
+    );
+
+    perp_market_listing_config.bond_amount = bond_amount;
+    perp_market_listing_config.veto_window = veto_window;
+    perp_market_listing_config.enabled = enabled;
 
     Ok(())
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_borrow_rate(
-    ctx: Context<AdminUpdateSpotMarket>,
-    optimal_utilization: u32,
-    optimal_borrow_rate: u32,
-    max_borrow_rate: u32,
-) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    validate_borrow_rate(optimal_utilization, optimal_borrow_rate, max_borrow_rate)?;
-    spot_market.optimal_utilization = optimal_utilization;
-    spot_market.optimal_borrow_rate = optimal_borrow_rate;
-    spot_market.max_borrow_rate = max_borrow_rate;
-    Ok(())
+fn validate_perp_market_listing_config(bond_amount: u64, veto_window: u32) -> DriftResult {
+    validate!(
+        bond_amount > 0,
+        ErrorCode::InvalidPerpMarketListing,
+        "bond_amount must be positive"
+    )?;
+
+    validate!(
+        veto_window >= MIN_PERMISSIONLESS_LISTING_VETO_WINDOW,
+        ErrorCode::InvalidPerpMarketListing,
+        "veto_window {} below min {}",
+        veto_window,
+        MIN_PERMISSIONLESS_LISTING_VETO_WINDOW
+    )
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_max_token_deposits(
-    ctx: Context<AdminUpdateSpotMarket>,
-    max_token_deposits: u64,
+pub fn handle_initialize_pnl_pool_rebalance_config(
+    ctx: Context<InitializePnlPoolRebalanceConfig>,
+    surplus_buffer: u64,
+    max_top_up: u64,
+    min_rebalance_interval: u32,
 ) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    spot_market.max_token_deposits = max_token_deposits;
+    validate_pnl_pool_rebalance_config(min_rebalance_interval)?;
+
+    let mut pnl_pool_rebalance_config = ctx.accounts.pnl_pool_rebalance_config.load_init()?;
+    pnl_pool_rebalance_config.surplus_buffer = surplus_buffer;
+    pnl_pool_rebalance_config.max_top_up = max_top_up;
+    pnl_pool_rebalance_config.min_rebalance_interval = min_rebalance_interval;
+    pnl_pool_rebalance_config.enabled = true;
+
     Ok(())
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_orders_enabled(
-    ctx: Context<AdminUpdateSpotMarket>,
-    orders_enabled: bool,
+pub fn handle_update_pnl_pool_rebalance_config(
+    ctx: Context<UpdatePnlPoolRebalanceConfig>,
+    surplus_buffer: u64,
+    max_top_up: u64,
+    min_rebalance_interval: u32,
+    enabled: bool,
 ) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
-    spot_market.orders_enabled = orders_enabled;
+    validate_pnl_pool_rebalance_config(min_rebalance_interval)?;
+
+    let mut pnl_pool_rebalance_config = load_mut!(ctx.accounts.pnl_pool_rebalance_config)?;
+
+    msg!(
+        "pnl pool rebalance surplus_buffer {} -> {}",
+        pnl_pool_rebalance_config.surplus_buffer,
+        surplus_buffer
+    );
+    msg!(
+        "pnl pool rebalance max_top_up {} -> {}",
+        pnl_pool_rebalance_config.max_top_up,
+        max_top_up
+    );
+    msg!(
+        "pnl pool rebalance min_rebalance_interval {} -> {}",
+        pnl_pool_rebalance_config.min_rebalance_interval,
+        min_rebalance_interval
+    );
+    msg!(
+        "pnl pool rebalance enabled {} -> {}",
+        pnl_pool_rebalance_config.enabled,
+        enabled
+    );
+
+    pnl_pool_rebalance_config.surplus_buffer = surplus_buffer;
+    pnl_pool_rebalance_config.max_top_up = max_top_up;
+    pnl_pool_rebalance_config.min_rebalance_interval = min_rebalance_interval;
+    pnl_pool_rebalance_config.enabled = enabled;
+
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_status(
-    ctx: Context<AdminUpdatePerpMarket>,
-    status: MarketStatus,
-) -> Result<()> {
+fn validate_pnl_pool_rebalance_config(min_rebalance_interval: u32) -> DriftResult {
     validate!(
-        !matches!(status, MarketStatus::Delisted | MarketStatus::Settlement),
-        ErrorCode::DefaultError,
-        "must set settlement/delist through another instruction",
-    )?;
+        min_rebalance_interval > 0,
+        ErrorCode::InvalidPnlPoolRebalanceConfig,
+        "min_rebalance_interval must be positive"
+    )
+}
 
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    perp_market.status = status;
-    Ok(())
-}
-
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_contract_tier(
-    ctx: Context<AdminUpdatePerpMarket>,
-    contract_tier: ContractTier,
+/// lists a perp market with the default permissionless risk parameters. the market stays
+/// initialized (fills paused) until the veto window passes without the admin vetoing it
+pub fn handle_propose_perp_market(
+    ctx: Context<ProposePerpMarket>,
+    amm_base_asset_reserve: u128,
+    oracle_source: OracleSource,
+    name: [u8; 32],
 ) -> Result<()> {
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    perp_market.contract_tier = contract_tier;
-    Ok(())
-}
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_imf_factor(
-    ctx: Context<AdminUpdatePerpMarket>,
-    imf_factor: u32,
-    unrealized_pnl_imf_factor: u32,
-) -> Result<()> {
-    validate!(
-        imf_factor <= SPOT_IMF_PRECISION,
-        ErrorCode::DefaultError,
-        "invalid imf factor",
-    )?;
+    let perp_market_listing_config = load!(ctx.accounts.perp_market_listing_config)?;
     validate!(
-        unrealized_pnl_imf_factor <= SPOT_IMF_PRECISION,
-        ErrorCode::DefaultError,
-        "invalid unrealized pnl imf factor",
+        perp_market_listing_config.enabled,
+        ErrorCode::InvalidPerpMarketListing,
+        "permissionless perp market listing is disabled"
     )?;
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    perp_market.imf_factor = imf_factor;
-    perp_market.unrealized_pnl_imf_factor = unrealized_pnl_imf_factor;
-    Ok(())
-}
+    let bond = perp_market_listing_config.bond_amount;
+    let veto_end_ts = now.safe_add(perp_market_listing_config.veto_window.cast()?)?;
+    drop(perp_market_listing_config);
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_unrealized_asset_weight(
-    ctx: Context<AdminUpdatePerpMarket>,
-    unrealized_initial_asset_weight: u32,
-    unrealized_maintenance_asset_weight: u32,
-) -> Result<()> {
+    validate_permissionless_listing_oracle(oracle_source, ctx.accounts.oracle.owner)?;
+
+    // the amm starts at the oracle price
+    let OraclePriceData {
+        price: oracle_price,
+        ..
+    } = get_oracle_price(&oracle_source, &ctx.accounts.oracle, clock.slot)?;
     validate!(
-        unrealized_initial_asset_weight <= SPOT_WEIGHT_PRECISION.cast()?,
-        ErrorCode::DefaultError,
-        "invalid unrealized_initial_asset_weight",
+        oracle_price > 0,
+        ErrorCode::InvalidOracle,
+        "oracle price {} <= 0",
+        oracle_price
     )?;
-    validate!(
-        unrealized_maintenance_asset_weight <= SPOT_WEIGHT_PRECISION.cast()?,
-        ErrorCode::DefaultError,
-        "invalid unrealized_maintenance_asset_weight",
+    let amm_peg_multiplier = oracle_price.cast::<u128>()?;
+
+    validate_permissionless_listing_reserves(amm_base_asset_reserve, oracle_price)?;
+
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.proposer.to_account_info(),
+                to: ctx.accounts.perp_market_listing.to_account_info(),
+            },
+        ),
+        bond,
     )?;
-    validate!(
-        unrealized_initial_asset_weight <= unrealized_maintenance_asset_weight,
-        ErrorCode::DefaultError,
-        "must enforce unrealized_initial_asset_weight <= unrealized_maintenance_asset_weight",
+
+    let perp_market_pubkey = ctx.accounts.perp_market.to_account_info().key;
+    let perp_market = &mut ctx.accounts.perp_market.load_init()?;
+
+    initialize_perp_market(
+        perp_market,
+        perp_market_pubkey,
+        &ctx.accounts.oracle,
+        &mut ctx.accounts.state,
+        amm_base_asset_reserve,
+        amm_base_asset_reserve,
+        PERMISSIONLESS_LISTING_FUNDING_PERIOD,
+        amm_peg_multiplier,
+        oracle_source,
+        PERMISSIONLESS_LISTING_MARGIN_RATIO_INITIAL,
+        PERMISSIONLESS_LISTING_MARGIN_RATIO_MAINTENANCE,
+        PERMISSIONLESS_LISTING_LIQUIDATOR_FEE,
+        false,
+        name,
     )?;
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    perp_market.unrealized_pnl_initial_asset_weight = unrealized_initial_asset_weight;
-    perp_market.unrealized_pnl_maintenance_asset_weight = unrealized_maintenance_asset_weight;
-    Ok(())
-}
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_concentration_coef(
-    ctx: Context<AdminUpdatePerpMarket>,
-    concentration_scale: u128,
-) -> Result<()> {
-    validate!(
-        concentration_scale > 0,
-        ErrorCode::DefaultError,
-        "invalid concentration_scale",
+    // single position only until the admin reviews the market
+    perp_market.contract_tier = ContractTier::Isolated;
+    // unrealized pnl can't back new positions and open interest is capped
+    perp_market.unrealized_pnl_initial_asset_weight = 0;
+    perp_market.amm.max_open_interest = calculate_permissionless_listing_max_open_interest(
+        oracle_price,
+        perp_market.amm.order_step_size,
     )?;
 
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    let prev_concentration_coef = perp_market.amm.concentration_coef;
-    controller::amm::update_concentration_coef(&mut perp_market.amm, concentration_scale)?;
-    let new_concentration_coef = perp_market.amm.concentration_coef;
+    let mut perp_market_listing = ctx.accounts.perp_market_listing.load_init()?;
+    perp_market_listing.proposer = *ctx.accounts.proposer.key;
+    perp_market_listing.bond = bond;
+    perp_market_listing.veto_end_ts = veto_end_ts;
+    perp_market_listing.market_index = perp_market.market_index;
 
     msg!(
-        "perp_market.amm.concentration_coef: {} -> {}",
-        prev_concentration_coef,
-        new_concentration_coef
+        "proposed perp market {} with bond {}, vetoable until {}",
+        perp_market.market_index,
+        bond,
+        veto_end_ts
     );
 
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_curve_update_intensity(
-    ctx: Context<AdminUpdatePerpMarket>,
-    curve_update_intensity: u8,
+pub fn handle_activate_proposed_perp_market(
+    ctx: Context<ActivateProposedPerpMarket>,
+    _market_index: u16,
 ) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let perp_market_listing = load!(ctx.accounts.perp_market_listing)?;
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+
+    perp_market_listing.validate_activatable(now)?;
+
     validate!(
-        curve_update_intensity <= 100,
-        ErrorCode::DefaultError,
-        "invalid curve_update_intensity",
+        perp_market.status != MarketStatus::Delisted,
+        ErrorCode::InvalidPerpMarketListing,
+        "perp market {} is delisted",
+        perp_market.market_index
     )?;
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    perp_market.amm.curve_update_intensity = curve_update_intensity;
-    Ok(())
-}
 
-pub fn handle_update_lp_cooldown_time(
-    ctx: Context<AdminUpdateState>,
-    lp_cooldown_time: u64,
-) -> Result<()> {
-    ctx.accounts.state.lp_cooldown_time = lp_cooldown_time;
-    Ok(())
-}
+    // the admin may have already activated or paused the market during the veto window
+    if perp_market.status == MarketStatus::Initialized {
+        perp_market.status = MarketStatus::Active;
+    }
 
-pub fn handle_update_perp_fee_structure(
-    ctx: Context<AdminUpdateState>,
-    fee_structure: FeeStructure,
-) -> Result<()> {
-    validate_fee_structure(&fee_structure)?;
+    msg!(
+        "activated proposed perp market {}, refunding bond {} to {}",
+        perp_market.market_index,
+        perp_market_listing.bond,
+        perp_market_listing.proposer
+    );
 
-    ctx.accounts.state.perp_fee_structure = fee_structure;
     Ok(())
 }
 
-pub fn handle_update_spot_fee_structure(
-    ctx: Context<AdminUpdateState>,
-    fee_structure: FeeStructure,
+pub fn handle_veto_proposed_perp_market(
+    ctx: Context<VetoProposedPerpMarket>,
+    _market_index: u16,
 ) -> Result<()> {
-    validate_fee_structure(&fee_structure)?;
-    ctx.accounts.state.spot_fee_structure = fee_structure;
-    Ok(())
-}
+    let now = Clock::get()?.unix_timestamp;
+    let perp_market_listing = load!(ctx.accounts.perp_market_listing)?;
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
 
-pub fn handle_update_initial_pct_to_liquidate(
-    ctx: Context<AdminUpdateState>,
-    initial_pct_to_liquidate: u16,
-) -> Result<()> {
-    ctx.accounts.state.initial_pct_to_liquidate = initial_pct_to_liquidate;
-    Ok(())
-}
+    perp_market_listing.validate_vetoable(now)?;
 
-pub fn handle_update_liquidation_duration(
-    ctx: Context<AdminUpdateState>,
-    liquidation_duration: u8,
-) -> Result<()> {
-    ctx.accounts.state.liquidation_duration = liquidation_duration;
-    Ok(())
-}
+    validate!(
+        perp_market.number_of_users == 0,
+        ErrorCode::InvalidPerpMarketListing,
+        "perp market {} has {} users",
+        perp_market.market_index,
+        perp_market.number_of_users
+    )?;
 
-pub fn handle_update_oracle_guard_rails(
-    ctx: Context<AdminUpdateState>,
-    oracle_guard_rails: OracleGuardRails,
-) -> Result<()> {
-    ctx.accounts.state.oracle_guard_rails = oracle_guard_rails;
-    Ok(())
-}
+    msg!(
+        "vetoed proposed perp market {}, bond {} forfeited",
+        perp_market.market_index,
+        perp_market_listing.bond
+    );
+
+    perp_market.status = MarketStatus::Delisted;
 
-pub fn handle_update_state_settlement_duration(
-    ctx: Context<AdminUpdateState>,
-    settlement_duration: u16,
-) -> Result<()> {
-    ctx.accounts.state.settlement_duration = settlement_duration;
     Ok(())
 }
 
 #[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
+    spot_market_valid(&ctx.accounts.spot_market)
 )]
-pub fn handle_update_perp_market_oracle(
-    ctx: Context<RepegCurve>,
+pub fn handle_update_spot_market_oracle(
+    ctx: Context<AdminUpdateSpotMarketOracle>,
     oracle: Pubkey,
     oracle_source: OracleSource,
 ) -> Result<()> {
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
     let clock = Clock::get()?;
 
-    // Verify oracle is readable
+    validate_oracle_readable(&ctx.accounts.oracle, oracle_source, &clock)?;
+
+    spot_market.oracle = oracle;
+    spot_market.oracle_source = oracle_source;
+    Ok(())
+}
+
+fn validate_oracle_readable(
+    oracle: &AccountInfo,
+    oracle_source: OracleSource,
+    clock: &Clock,
+) -> DriftResult {
     let OraclePriceData {
         price: _oracle_price,
         delay: _oracle_delay,
         ..
-    } = get_oracle_price(&oracle_source, &ctx.accounts.oracle, clock.slot)?;
+    } = get_oracle_price(&oracle_source, oracle, clock.slot)?;
 
-    perp_market.amm.oracle = oracle;
-    perp_market.amm.oracle_source = oracle_source;
+    if oracle_source == OracleSource::PythPull {
+        validate_pyth_pull_publish_time(
+            oracle,
+            clock.unix_timestamp,
+            PYTH_PULL_MAX_PUBLISH_TIME_STALENESS,
+        )?;
+    }
 
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_base_spread(
-    ctx: Context<AdminUpdatePerpMarket>,
-    base_spread: u32,
-) -> Result<()> {
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    perp_market.amm.base_spread = base_spread;
-    perp_market.amm.long_spread = base_spread / 2;
-    perp_market.amm.short_spread = base_spread / 2;
-    Ok(())
+fn validate_admin_timelock_inactive(state: &State) -> DriftResult {
+    validate!(
+        state.admin_timelock_duration == 0,
+        ErrorCode::AdminTimelockActive,
+        "admin timelock of {}s is active, the change must be queued",
+        state.admin_timelock_duration
+    )
 }
 
 #[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
+    spot_market_valid(&ctx.accounts.spot_market)
 )]
-pub fn handle_update_amm_jit_intensity(
-    ctx: Context<AdminUpdatePerpMarket>,
-    amm_jit_intensity: u8,
+pub fn handle_update_spot_market_expiry(
+    ctx: Context<AdminUpdateSpotMarket>,
+    expiry_ts: i64,
 ) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    let now = Clock::get()?.unix_timestamp;
+
     validate!(
-        (0..=100).contains(&amm_jit_intensity),
+        now < expiry_ts,
         ErrorCode::DefaultError,
-        "invalid amm_jit_intensity",
+        "Market expiry ts must later than current clock timestamp"
     )?;
 
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    perp_market.amm.amm_jit_intensity = amm_jit_intensity;
+    spot_market.status = MarketStatus::ReduceOnly;
+    spot_market.expiry_ts = expiry_ts;
 
     Ok(())
 }
@@ -1849,407 +2350,3555 @@ pub fn handle_update_amm_jit_intensity(
 #[access_control(
     perp_market_valid(&ctx.accounts.perp_market)
 )]
-pub fn handle_update_perp_market_max_spread(
+pub fn handle_update_perp_market_expiry(
     ctx: Context<AdminUpdatePerpMarket>,
-    max_spread: u32,
+    expiry_ts: i64,
 ) -> Result<()> {
+    let clock = Clock::get()?;
     let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
     validate!(
-        max_spread >= perp_market.amm.base_spread,
-        ErrorCode::DefaultError,
-        "invalid max_spread < base_spread",
-    )?;
-
-    validate!(
-        max_spread <= perp_market.margin_ratio_initial * 100,
+        clock.unix_timestamp < expiry_ts,
         ErrorCode::DefaultError,
-        "invalid max_spread > market.margin_ratio_initial * 100",
+        "Market expiry ts must later than current clock timestamp"
     )?;
 
-    perp_market.amm.max_spread = max_spread;
+    // automatically enter reduce only
+    perp_market.status = MarketStatus::ReduceOnly;
+    perp_market.expiry_ts = expiry_ts;
 
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_step_size_and_tick_size(
-    ctx: Context<AdminUpdatePerpMarket>,
-    step_size: u64,
-    tick_size: u64,
+pub fn handle_delist_perp_market(
+    ctx: Context<DelistPerpMarket>,
+    market_index: u16,
+    expiry_ts: i64,
+    settlement_twap_window: u32,
 ) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
     let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    validate!(step_size > 0 && tick_size > 0, ErrorCode::DefaultError)?;
-    perp_market.amm.order_step_size = step_size;
-    perp_market.amm.order_tick_size = tick_size;
+    let mut perp_market_delisting = ctx.accounts.perp_market_delisting.load_init()?;
+
+    initialize_settlement_price_twap(
+        perp_market,
+        &mut perp_market_delisting,
+        market_index,
+        expiry_ts,
+        settlement_twap_window,
+        now,
+    )?;
+
+    msg!(
+        "delisting perp market {} at expiry_ts {} with settlement_twap_window {}",
+        market_index,
+        expiry_ts,
+        settlement_twap_window
+    );
+
+    perp_market.status = MarketStatus::ReduceOnly;
+
     Ok(())
 }
 
-#[access_control(
-    perp_market_valid(&ctx.accounts.perp_market)
-)]
-pub fn handle_update_perp_market_min_order_size(
-    ctx: Context<AdminUpdatePerpMarket>,
-    order_size: u64,
+pub fn handle_initialize_dated_future(
+    ctx: Context<InitializeDatedFuture>,
+    market_index: u16,
+    expiry_ts: i64,
+    settlement_twap_window: u32,
 ) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
     let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    validate!(order_size > 0, ErrorCode::DefaultError)?;
-    perp_market.amm.min_order_size = order_size;
-    Ok(())
-}
+    let mut perp_market_delisting = ctx.accounts.perp_market_delisting.load_init()?;
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_step_size_and_tick_size(
-    ctx: Context<AdminUpdateSpotMarket>,
-    step_size: u64,
-    tick_size: u64,
-) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
     validate!(
-        spot_market.market_index == 0 || step_size > 0 && tick_size > 0,
-        ErrorCode::DefaultError
+        perp_market.status == MarketStatus::Initialized,
+        ErrorCode::InvalidPerpMarketDelisting,
+        "perp market {} must be a new market to become a dated future",
+        market_index
     )?;
-    spot_market.order_step_size = step_size;
-    spot_market.order_tick_size = tick_size;
+
+    initialize_settlement_price_twap(
+        perp_market,
+        &mut perp_market_delisting,
+        market_index,
+        expiry_ts,
+        settlement_twap_window,
+        now,
+    )?;
+
+    msg!(
+        "perp market {} is a dated future expiring at {} with settlement_twap_window {}",
+        market_index,
+        expiry_ts,
+        settlement_twap_window
+    );
+
+    // trades normally until expiry, then settles at the sampled twap
+    perp_market.contract_type = ContractType::Future;
+
     Ok(())
 }
 
-#[access_control(
-    spot_market_valid(&ctx.accounts.spot_market)
-)]
-pub fn handle_update_spot_market_min_order_size(
-    ctx: Context<AdminUpdateSpotMarket>,
-    order_size: u64,
-) -> Result<()> {
-    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+fn initialize_settlement_price_twap(
+    perp_market: &mut PerpMarket,
+    perp_market_delisting: &mut PerpMarketDelisting,
+    market_index: u16,
+    expiry_ts: i64,
+    settlement_twap_window: u32,
+    now: i64,
+) -> DriftResult {
     validate!(
-        spot_market.market_index == 0 || order_size > 0,
-        ErrorCode::DefaultError
+        perp_market.market_index == market_index,
+        ErrorCode::InvalidPerpMarketDelisting,
+        "perp market index {} != {}",
+        perp_market.market_index,
+        market_index
     )?;
-    spot_market.min_order_size = order_size;
+
+    validate!(
+        !matches!(
+            perp_market.status,
+            MarketStatus::Settlement | MarketStatus::Delisted
+        ),
+        ErrorCode::InvalidPerpMarketDelisting,
+        "perp market already in {:?}",
+        perp_market.status
+    )?;
+
+    // the whole window has to be sampled before expiry
+    validate!(
+        settlement_twap_window > 0
+            && settlement_twap_window.cast::<i64>()? <= THIRTY_DAY
+            && now.safe_add(settlement_twap_window.cast()?)? <= expiry_ts,
+        ErrorCode::InvalidPerpMarketDelisting,
+        "settlement_twap_window {} must be in (0, {}] and start after now {} (expiry_ts {})",
+        settlement_twap_window,
+        THIRTY_DAY,
+        now,
+        expiry_ts
+    )?;
+
+    perp_market_delisting.market_index = market_index;
+    perp_market_delisting.settlement_twap_window = settlement_twap_window;
+
+    perp_market.expiry_ts = expiry_ts;
+    perp_market.amm.delisting_enabled = true;
+
     Ok(())
 }
 
 #[access_control(
     perp_market_valid(&ctx.accounts.perp_market)
 )]
-pub fn handle_update_perp_market_max_slippage_ratio(
+pub fn handle_move_amm_price(
     ctx: Context<AdminUpdatePerpMarket>,
-    max_slippage_ratio: u16,
+    base_asset_reserve: u128,
+    quote_asset_reserve: u128,
+    sqrt_k: u128,
 ) -> Result<()> {
-    validate!(max_slippage_ratio > 0, ErrorCode::DefaultError)?;
     let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    perp_market.amm.max_slippage_ratio = max_slippage_ratio;
+    controller::amm::move_price(
+        &mut perp_market.amm,
+        base_asset_reserve,
+        quote_asset_reserve,
+        sqrt_k,
+    )?;
+    validate_perp_market(perp_market)?;
+
     Ok(())
 }
 
 #[access_control(
     perp_market_valid(&ctx.accounts.perp_market)
 )]
-pub fn handle_update_perp_market_max_fill_reserve_fraction(
-    ctx: Context<AdminUpdatePerpMarket>,
-    max_fill_reserve_fraction: u16,
-) -> Result<()> {
-    validate!(max_fill_reserve_fraction > 0, ErrorCode::DefaultError)?;
-    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
-    perp_market.amm.max_fill_reserve_fraction = max_fill_reserve_fraction;
-    Ok(())
-}
-
 #[access_control(
     perp_market_valid(&ctx.accounts.perp_market)
 )]
-pub fn handle_update_perp_market_max_open_interest(
+pub fn handle_update_perp_market_contract_type(
     ctx: Context<AdminUpdatePerpMarket>,
-    max_open_interest: u128,
+    contract_type: ContractType,
 ) -> Result<()> {
     let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
 
+    // margin and settlement math depend on the contract type, so it's fixed once trading starts
     validate!(
-        is_multiple_of_step_size(
-            max_open_interest.cast::<u64>()?,
-            perp_market.amm.order_step_size
-        )?,
+        perp_market.status == MarketStatus::Initialized,
         ErrorCode::DefaultError,
-        "max oi not a multiple of the step size"
+        "contract type can only be changed before the market is active"
     )?;
 
-    perp_market.amm.max_open_interest = max_open_interest;
-    Ok(())
-}
+    validate!(
+        contract_type != ContractType::Future,
+        ErrorCode::DefaultError,
+        "dated futures need an expiry, use initialize_dated_future"
+    )?;
 
-pub fn handle_update_admin(ctx: Context<AdminUpdateState>, admin: Pubkey) -> Result<()> {
-    ctx.accounts.state.admin = admin;
-    Ok(())
-}
+    msg!(
+        "perp market {} contract type {:?} -> {:?}",
+        perp_market.market_index,
+        perp_market.contract_type,
+        contract_type
+    );
 
-pub fn handle_update_whitelist_mint(
-    ctx: Context<AdminUpdateState>,
-    whitelist_mint: Pubkey,
-) -> Result<()> {
-    ctx.accounts.state.whitelist_mint = whitelist_mint;
-    Ok(())
-}
+    perp_market.contract_type = contract_type;
 
-pub fn handle_update_discount_mint(
-    ctx: Context<AdminUpdateState>,
-    discount_mint: Pubkey,
-) -> Result<()> {
-    ctx.accounts.state.discount_mint = discount_mint;
     Ok(())
 }
 
-pub fn handle_update_exchange_status(
-    ctx: Context<AdminUpdateState>,
-    exchange_status: u8,
+pub fn handle_resolve_prediction_market(
+    ctx: Context<ResolvePredictionMarket>,
+    outcome: bool,
 ) -> Result<()> {
-    ctx.accounts.state.exchange_status = exchange_status;
-    Ok(())
-}
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
 
-pub fn handle_update_perp_auction_duration(
-    ctx: Context<AdminUpdateState>,
-    min_perp_auction_duration: u8,
-) -> Result<()> {
-    ctx.accounts.state.min_perp_auction_duration = min_perp_auction_duration;
-    Ok(())
-}
+    let now = Clock::get()?.unix_timestamp;
+
+    controller::spot_balance::update_spot_market_cumulative_interest(spot_market, None, now)?;
+
+    controller::repeg::resolve_prediction_market(perp_market, spot_market, now, outcome)?;
+
+    msg!(
+        "resolved prediction market {} at expiry price {}",
+        perp_market.market_index,
+        perp_market.expiry_price
+    );
 
-pub fn handle_update_spot_auction_duration(
-    ctx: Context<AdminUpdateState>,
-    default_spot_auction_duration: u8,
-) -> Result<()> {
-    ctx.accounts.state.default_spot_auction_duration = default_spot_auction_duration;
     Ok(())
 }
 
-pub fn handle_admin_remove_insurance_fund_stake(
-    ctx: Context<AdminRemoveInsuranceFundStake>,
-    market_index: u16,
-    amount: u64,
+pub fn handle_settle_expired_market_pools_to_revenue_pool(
+    ctx: Context<SettleExpiredMarketPoolsToRevenuePool>,
 ) -> Result<()> {
-    let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
     let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
     let state = &ctx.accounts.state;
 
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    controller::spot_balance::update_spot_market_cumulative_interest(spot_market, None, now)?;
+
     validate!(
-        market_index == spot_market.market_index,
+        spot_market.market_index == QUOTE_SPOT_MARKET_INDEX,
         ErrorCode::DefaultError,
-        "market_index doesnt match spot_market"
-    )?;
-
-    let n_shares = math::insurance::vault_amount_to_if_shares(
-        amount,
-        spot_market.insurance_fund.total_shares,
-        ctx.accounts.insurance_fund_vault.amount,
+        "spot_market must be perp market's quote asset"
     )?;
 
-    let withdrawn_amount = controller::insurance::admin_remove_insurance_fund_stake(
-        ctx.accounts.insurance_fund_vault.amount,
-        n_shares,
-        spot_market,
-        now,
-        *ctx.accounts.admin.key,
+    // block when settlement_duration is default/unconfigured
+    validate!(
+        state.settlement_duration != 0,
+        ErrorCode::DefaultError,
+        "invalid state.settlement_duration (is 0)"
     )?;
 
-    controller::token::send_from_program_vault(
-        &ctx.accounts.token_program,
-        &ctx.accounts.insurance_fund_vault,
-        &ctx.accounts.admin_token_account,
-        &ctx.accounts.drift_signer,
-        state.signer_nonce,
-        withdrawn_amount,
-    )?;
+    let escrow_period_before_transfer = if state.settlement_duration > 1 {
+        // minimum of TWENTY_FOUR_HOUR to examine settlement process
+        TWENTY_FOUR_HOUR
+            .safe_add(state.settlement_duration.cast()?)?
+            .safe_sub(1)?
+    } else {
+        // for testing / expediting if settlement_duration not default but 1
+        state.settlement_duration.cast::<i64>()?
+    };
 
     validate!(
-        ctx.accounts.insurance_fund_vault.amount > 0,
+        now > perp_market
+            .expiry_ts
+            .safe_add(escrow_period_before_transfer)?,
         ErrorCode::DefaultError,
-        "insurance_fund_vault.amount must remain > 0"
+        "must be escrow_period_before_transfer={} after market.expiry_ts",
+        escrow_period_before_transfer
     )?;
 
+    controller::repeg::settle_expired_market_pools_to_revenue_pool(perp_market, spot_market)?;
+
     Ok(())
 }
 
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_deposit_into_perp_market_fee_pool(
+    ctx: Context<DepositIntoMarketFeePool>,
+    amount: u64,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+
+    perp_market.amm.total_fee_minus_distributions = perp_market
+        .amm
+        .total_fee_minus_distributions
+        .safe_add(amount.cast()?)?;
+
+    let quote_spot_market = &mut load_mut!(ctx.accounts.quote_spot_market)?;
+
+    controller::spot_balance::update_spot_balances(
+        amount.cast::<u128>()?,
+        &SpotBalanceType::Deposit,
+        quote_spot_market,
+        &mut perp_market.amm.fee_pool,
+        false,
+    )?;
+
+    controller::token::receive(
+        &ctx.accounts.token_program,
+        &ctx.accounts.source_vault,
+        &ctx.accounts.spot_market_vault,
+        &ctx.accounts.admin.to_account_info(),
+        amount,
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
+)]
+pub fn handle_repeg_amm_curve(ctx: Context<RepegCurve>, new_peg_candidate: u128) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let clock_slot = clock.slot;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let price_oracle = &ctx.accounts.oracle;
+    let OraclePriceData {
+        price: oracle_price,
+        ..
+    } = get_oracle_price(&perp_market.amm.oracle_source, price_oracle, clock.slot)?;
+
+    let peg_multiplier_before = perp_market.amm.peg_multiplier;
+    let base_asset_reserve_before = perp_market.amm.base_asset_reserve;
+    let quote_asset_reserve_before = perp_market.amm.quote_asset_reserve;
+    let sqrt_k_before = perp_market.amm.sqrt_k;
+
+    let oracle_validity_rails = &ctx.accounts.state.oracle_guard_rails;
+
+    let adjustment_cost = controller::repeg::repeg(
+        perp_market,
+        price_oracle,
+        new_peg_candidate,
+        clock_slot,
+        oracle_validity_rails,
+    )?;
+
+    let peg_multiplier_after = perp_market.amm.peg_multiplier;
+    let base_asset_reserve_after = perp_market.amm.base_asset_reserve;
+    let quote_asset_reserve_after = perp_market.amm.quote_asset_reserve;
+    let sqrt_k_after = perp_market.amm.sqrt_k;
+
+    emit!(CurveRecord {
+        ts: now,
+        record_id: get_then_update_id!(perp_market, next_curve_record_id),
+        market_index: perp_market.market_index,
+        peg_multiplier_before,
+        base_asset_reserve_before,
+        quote_asset_reserve_before,
+        sqrt_k_before,
+        peg_multiplier_after,
+        base_asset_reserve_after,
+        quote_asset_reserve_after,
+        sqrt_k_after,
+        base_asset_amount_long: perp_market.amm.base_asset_amount_long.unsigned_abs(),
+        base_asset_amount_short: perp_market.amm.base_asset_amount_short.unsigned_abs(),
+        base_asset_amount_with_amm: perp_market.amm.base_asset_amount_with_amm,
+        number_of_users: perp_market.number_of_users,
+        total_fee: perp_market.amm.total_fee,
+        total_fee_minus_distributions: perp_market.amm.total_fee_minus_distributions,
+        adjustment_cost,
+        oracle_price,
+        fill_record: 0,
+    });
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
+)]
+pub fn handle_update_amm_oracle_twap(ctx: Context<RepegCurve>) -> Result<()> {
+    // allow update to amm's oracle twap iff price gap is reduced and thus more tame funding
+    // otherwise if oracle error or funding flip: set oracle twap to mark twap (0 gap)
+
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let price_oracle = &ctx.accounts.oracle;
+    let oracle_twap = perp_market.amm.get_oracle_twap(price_oracle)?;
+
+    if let Some(oracle_twap) = oracle_twap {
+        let oracle_mark_gap_before = perp_market
+            .amm
+            .last_mark_price_twap
+            .cast::<i64>()?
+            .safe_sub(
+                perp_market
+                    .amm
+                    .historical_oracle_data
+                    .last_oracle_price_twap,
+            )?;
+
+        let oracle_mark_gap_after = perp_market
+            .amm
+            .last_mark_price_twap
+            .cast::<i64>()?
+            .safe_sub(oracle_twap)?;
+
+        if (oracle_mark_gap_after > 0 && oracle_mark_gap_before < 0)
+            || (oracle_mark_gap_after < 0 && oracle_mark_gap_before > 0)
+        {
+            perp_market
+                .amm
+                .historical_oracle_data
+                .last_oracle_price_twap = perp_market.amm.last_mark_price_twap.cast::<i64>()?;
+            perp_market
+                .amm
+                .historical_oracle_data
+                .last_oracle_price_twap_ts = now;
+        } else if oracle_mark_gap_after.unsigned_abs() <= oracle_mark_gap_before.unsigned_abs() {
+            perp_market
+                .amm
+                .historical_oracle_data
+                .last_oracle_price_twap = oracle_twap;
+            perp_market
+                .amm
+                .historical_oracle_data
+                .last_oracle_price_twap_ts = now;
+        } else {
+            return Err(ErrorCode::PriceBandsBreached.into());
+        }
+    } else {
+        return Err(ErrorCode::InvalidOracle.into());
+    }
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
+)]
+pub fn handle_update_k(ctx: Context<AdminUpdateK>, sqrt_k: u128) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+
+    let base_asset_amount_long = perp_market.amm.base_asset_amount_long.unsigned_abs();
+    let base_asset_amount_short = perp_market.amm.base_asset_amount_short.unsigned_abs();
+    let base_asset_amount_with_amm = perp_market.amm.base_asset_amount_with_amm;
+    let number_of_users = perp_market.number_of_users_with_base;
+
+    let price_before = math::amm::calculate_price(
+        perp_market.amm.quote_asset_reserve,
+        perp_market.amm.base_asset_reserve,
+        perp_market.amm.peg_multiplier,
+    )?;
+
+    let peg_multiplier_before = perp_market.amm.peg_multiplier;
+    let base_asset_reserve_before = perp_market.amm.base_asset_reserve;
+    let quote_asset_reserve_before = perp_market.amm.quote_asset_reserve;
+    let sqrt_k_before = perp_market.amm.sqrt_k;
+
+    let k_increasing = sqrt_k > perp_market.amm.sqrt_k;
+
+    let new_sqrt_k_u192 = bn::U192::from(sqrt_k);
+
+    let update_k_result = get_update_k_result(perp_market, new_sqrt_k_u192, true)?;
+
+    let adjustment_cost = math::cp_curve::adjust_k_cost(perp_market, &update_k_result)?;
+
+    math::cp_curve::update_k(perp_market, &update_k_result)?;
+
+    if k_increasing {
+        validate!(
+            adjustment_cost >= 0,
+            ErrorCode::InvalidUpdateK,
+            "adjustment_cost negative when k increased",
+        )?;
+    } else {
+        validate!(
+            adjustment_cost <= 0,
+            ErrorCode::InvalidUpdateK,
+            "adjustment_cost positive when k decreased",
+        )?;
+    }
+
+    if adjustment_cost > 0 {
+        let max_cost = perp_market
+            .amm
+            .total_fee_minus_distributions
+            .safe_sub(get_total_fee_lower_bound(perp_market)?.cast()?)?
+            .safe_sub(perp_market.amm.total_fee_withdrawn.cast()?)?;
+
+        validate!(
+            adjustment_cost <= max_cost,
+            ErrorCode::InvalidUpdateK,
+            "adjustment_cost={} > max_cost={} for k change",
+            adjustment_cost,
+            max_cost
+        )?;
+    }
+
+    validate!(
+        !k_increasing || perp_market.amm.sqrt_k < MAX_SQRT_K,
+        ErrorCode::InvalidUpdateK,
+        "cannot increase sqrt_k={} past MAX_SQRT_K",
+        perp_market.amm.sqrt_k
+    )?;
+
+    validate!(
+        perp_market.amm.sqrt_k > perp_market.amm.user_lp_shares,
+        ErrorCode::InvalidUpdateK,
+        "cannot decrease sqrt_k={} below user_lp_shares={}",
+        perp_market.amm.sqrt_k,
+        perp_market.amm.user_lp_shares
+    )?;
+
+    perp_market.amm.total_fee_minus_distributions = perp_market
+        .amm
+        .total_fee_minus_distributions
+        .safe_sub(adjustment_cost)?;
+
+    perp_market.amm.net_revenue_since_last_funding = perp_market
+        .amm
+        .net_revenue_since_last_funding
+        .safe_sub(adjustment_cost as i64)?;
+
+    let amm = &perp_market.amm;
+
+    let price_after = math::amm::calculate_price(
+        amm.quote_asset_reserve,
+        amm.base_asset_reserve,
+        amm.peg_multiplier,
+    )?;
+
+    let price_change_too_large = price_before
+        .cast::<i128>()?
+        .safe_sub(price_after.cast::<i128>()?)?
+        .unsigned_abs()
+        .gt(&MAX_UPDATE_K_PRICE_CHANGE);
+
+    if price_change_too_large {
+        msg!(
+            "{:?} -> {:?} (> {:?})",
+            price_before,
+            price_after,
+            MAX_UPDATE_K_PRICE_CHANGE
+        );
+        return Err(ErrorCode::InvalidUpdateK.into());
+    }
+
+    let k_sqrt_check = bn::U192::from(amm.base_asset_reserve)
+        .safe_mul(bn::U192::from(amm.quote_asset_reserve))?
+        .integer_sqrt()
+        .try_to_u128()?;
+
+    let k_err = k_sqrt_check
+        .cast::<i128>()?
+        .safe_sub(amm.sqrt_k.cast::<i128>()?)?;
+
+    if k_err.unsigned_abs() > 100 {
+        msg!("k_err={:?}, {:?} != {:?}", k_err, k_sqrt_check, amm.sqrt_k);
+        return Err(ErrorCode::InvalidUpdateK.into());
+    }
+
+    let peg_multiplier_after = amm.peg_multiplier;
+    let base_asset_reserve_after = amm.base_asset_reserve;
+    let quote_asset_reserve_after = amm.quote_asset_reserve;
+    let sqrt_k_after = amm.sqrt_k;
+
+    let total_fee = amm.total_fee;
+    let total_fee_minus_distributions = amm.total_fee_minus_distributions;
+
+    let OraclePriceData {
+        price: oracle_price,
+        ..
+    } = get_oracle_price(
+        &perp_market.amm.oracle_source,
+        &ctx.accounts.oracle,
+        clock.slot,
+    )?;
+
+    emit!(CurveRecord {
+        ts: now,
+        record_id: get_then_update_id!(perp_market, next_curve_record_id),
+        market_index: perp_market.market_index,
+        peg_multiplier_before,
+        base_asset_reserve_before,
+        quote_asset_reserve_before,
+        sqrt_k_before,
+        peg_multiplier_after,
+        base_asset_reserve_after,
+        quote_asset_reserve_after,
+        sqrt_k_after,
+        base_asset_amount_long,
+        base_asset_amount_short,
+        base_asset_amount_with_amm,
+        number_of_users,
+        adjustment_cost,
+        total_fee,
+        total_fee_minus_distributions,
+        oracle_price,
+        fill_record: 0,
+    });
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
+)]
+pub fn handle_reset_amm_oracle_twap(ctx: Context<RepegCurve>) -> Result<()> {
+    // if oracle is invalid, failsafe to reset amm oracle_twap to the mark_twap
+
+    let state = &ctx.accounts.state;
+
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let clock_slot = clock.slot;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let price_oracle = &ctx.accounts.oracle;
+    let oracle_price_data =
+        &get_oracle_price(&perp_market.amm.oracle_source, price_oracle, clock_slot)?;
+
+    let oracle_validity = oracle::oracle_validity(
+        perp_market
+            .amm
+            .historical_oracle_data
+            .last_oracle_price_twap,
+        oracle_price_data,
+        &state
+            .oracle_guard_rails
+            .validity
+            .with_override(&perp_market.amm.validity_guard_rails_override),
+    )?;
+
+    let is_oracle_valid =
+        is_oracle_valid_for_action(oracle_validity, Some(DriftAction::UpdateFunding))?;
+
+    if !is_oracle_valid {
+        perp_market
+            .amm
+            .historical_oracle_data
+            .last_oracle_price_twap = perp_market.amm.last_mark_price_twap.cast::<i64>()?;
+        perp_market
+            .amm
+            .historical_oracle_data
+            .last_oracle_price_twap_ts = now;
+    }
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_margin_ratio(
+    ctx: Context<RiskParamUpdatePerpMarket>,
+    margin_ratio_initial: u32,
+    margin_ratio_maintenance: u32,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    update_perp_market_margin_ratio(perp_market, margin_ratio_initial, margin_ratio_maintenance)?;
+    Ok(())
+}
+
+fn update_perp_market_margin_ratio(
+    perp_market: &mut PerpMarket,
+    margin_ratio_initial: u32,
+    margin_ratio_maintenance: u32,
+) -> DriftResult {
+    let liquidation_penalty = perp_market.get_liquidation_penalty()?;
+    validate_margin(
+        margin_ratio_initial,
+        margin_ratio_maintenance,
+        liquidation_penalty,
+        perp_market.amm.max_spread,
+    )?;
+    validate_margin_tiers(&perp_market.margin_tiers, margin_ratio_initial)?;
+    validate_high_leverage_margin_ratio(
+        perp_market.high_leverage_margin_ratio_initial.cast()?,
+        perp_market.high_leverage_margin_ratio_maintenance.cast()?,
+        margin_ratio_initial,
+        margin_ratio_maintenance,
+        liquidation_penalty,
+    )?;
+
+    perp_market.margin_ratio_initial = margin_ratio_initial;
+    perp_market.margin_ratio_maintenance = margin_ratio_maintenance;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_margin_tiers(
+    ctx: Context<AdminUpdatePerpMarket>,
+    margin_tiers: [MarginTier; 3],
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate_margin_tiers(&margin_tiers, perp_market.margin_ratio_initial)?;
+
+    perp_market.margin_tiers = margin_tiers;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_correlation_group(
+    ctx: Context<AdminUpdatePerpMarket>,
+    correlation_group: u8,
+    correlation_margin_credit: u16,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+
+    // credit cant exceed what the market charges on a position
+    validate!(
+        correlation_margin_credit.cast::<u32>()? <= perp_market.margin_ratio_maintenance,
+        ErrorCode::DefaultError,
+        "correlation_margin_credit {} > margin_ratio_maintenance {}",
+        correlation_margin_credit,
+        perp_market.margin_ratio_maintenance
+    )?;
+
+    perp_market.correlation_group = correlation_group;
+    perp_market.correlation_margin_credit = correlation_margin_credit;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_auto_deleverage_enabled(
+    ctx: Context<AdminUpdatePerpMarket>,
+    auto_deleverage_enabled: bool,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.auto_deleverage_enabled = auto_deleverage_enabled;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_insurance_fund_max_draw_pct(
+    ctx: Context<AdminUpdatePerpMarket>,
+    insurance_fund_max_draw_pct: u8,
+) -> Result<()> {
+    validate!(
+        insurance_fund_max_draw_pct <= 100,
+        ErrorCode::DefaultError,
+        "insurance_fund_max_draw_pct must be <= 100"
+    )?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.insurance_fund_max_draw_pct = insurance_fund_max_draw_pct;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_insurance_fund_lend(
+    ctx: Context<AdminUpdatePerpMarket>,
+    insurance_fund_lend_max_pct: u8,
+    insurance_fund_lend_rate: u32,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    validate!(
+        insurance_fund_lend_max_pct <= 100,
+        ErrorCode::DefaultError,
+        "insurance_fund_lend_max_pct must be <= 100"
+    )?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+
+    // interest accrues at the rate in place, so it can only change once the loan is recalled
+    validate!(
+        insurance_fund_lend_rate == perp_market.insurance_fund_lend_rate
+            || perp_market.insurance_fund_lent == 0,
+        ErrorCode::DefaultError,
+        "insurance_fund_lent must be 0 to change insurance_fund_lend_rate, is {}",
+        perp_market.insurance_fund_lent
+    )?;
+
+    msg!(
+        "perp_market.insurance_fund_lend_max_pct: {:?} -> {:?}",
+        perp_market.insurance_fund_lend_max_pct,
+        insurance_fund_lend_max_pct
+    );
+
+    msg!(
+        "perp_market.insurance_fund_lend_rate: {:?} -> {:?}",
+        perp_market.insurance_fund_lend_rate,
+        insurance_fund_lend_rate
+    );
+
+    perp_market.insurance_fund_lend_max_pct = insurance_fund_lend_max_pct;
+    perp_market.insurance_fund_lend_rate = insurance_fund_lend_rate;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_high_leverage_margin_ratio(
+    ctx: Context<AdminUpdatePerpMarket>,
+    high_leverage_margin_ratio_initial: u16,
+    high_leverage_margin_ratio_maintenance: u16,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate_high_leverage_margin_ratio(
+        high_leverage_margin_ratio_initial.cast()?,
+        high_leverage_margin_ratio_maintenance.cast()?,
+        perp_market.margin_ratio_initial,
+        perp_market.margin_ratio_maintenance,
+        perp_market.get_liquidation_penalty()?,
+    )?;
+
+    perp_market.high_leverage_margin_ratio_initial = high_leverage_margin_ratio_initial;
+    perp_market.high_leverage_margin_ratio_maintenance = high_leverage_margin_ratio_maintenance;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_max_imbalances(
+    ctx: Context<AdminUpdatePerpMarket>,
+    unrealized_max_imbalance: u64,
+    max_revenue_withdraw_per_period: u64,
+    quote_max_insurance: u64,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+
+    let max_insurance_for_tier = match perp_market.contract_tier {
+        ContractTier::A => INSURANCE_A_MAX,
+        ContractTier::B => INSURANCE_B_MAX,
+        ContractTier::C => INSURANCE_C_MAX,
+        ContractTier::Speculative => INSURANCE_SPECULATIVE_MAX,
+        ContractTier::Isolated => INSURANCE_SPECULATIVE_MAX,
+    };
+
+    validate!(
+        max_revenue_withdraw_per_period
+            <= max_insurance_for_tier.max(FEE_POOL_TO_REVENUE_POOL_THRESHOLD.cast()?)
+            && unrealized_max_imbalance <= max_insurance_for_tier + 1
+            && quote_max_insurance <= max_insurance_for_tier,
+        ErrorCode::DefaultError,
+        "all maxs must be less than max_insurance for ContractTier ={}",
+        max_insurance_for_tier
+    )?;
+
+    validate!(
+        perp_market.insurance_claim.quote_settled_insurance <= quote_max_insurance,
+        ErrorCode::DefaultError,
+        "quote_max_insurance must be above market.insurance_claim.quote_settled_insurance={}",
+        perp_market.insurance_claim.quote_settled_insurance
+    )?;
+
+    msg!(
+        "market.max_revenue_withdraw_per_period: {:?} -> {:?}",
+        perp_market.insurance_claim.max_revenue_withdraw_per_period,
+        max_revenue_withdraw_per_period
+    );
+
+    msg!(
+        "market.unrealized_max_imbalance: {:?} -> {:?}",
+        perp_market.unrealized_pnl_max_imbalance,
+        unrealized_max_imbalance
+    );
+
+    msg!(
+        "market.quote_max_insurance: {:?} -> {:?}",
+        perp_market.insurance_claim.quote_max_insurance,
+        quote_max_insurance
+    );
+
+    perp_market.insurance_claim.max_revenue_withdraw_per_period = max_revenue_withdraw_per_period;
+    perp_market.unrealized_pnl_max_imbalance = unrealized_max_imbalance;
+    perp_market.insurance_claim.quote_max_insurance = quote_max_insurance;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_name(
+    ctx: Context<AdminUpdatePerpMarket>,
+    name: [u8; 32],
+) -> Result<()> {
+    let mut perp_market = load_mut!(ctx.accounts.perp_market)?;
+    perp_market.name = name;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_name(
+    ctx: Context<AdminUpdateSpotMarket>,
+    name: [u8; 32],
+) -> Result<()> {
+    let mut spot_market = load_mut!(ctx.accounts.spot_market)?;
+    spot_market.name = name;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_liquidation_fee(
+    ctx: Context<AdminUpdatePerpMarket>,
+    liquidator_fee: u32,
+    if_liquidation_fee: u32,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    update_perp_market_liquidation_fee(perp_market, liquidator_fee, if_liquidation_fee)?;
+    Ok(())
+}
+
+fn update_perp_market_liquidation_fee(
+    perp_market: &mut PerpMarket,
+    liquidator_fee: u32,
+    if_liquidation_fee: u32,
+) -> DriftResult {
+    validate!(
+        liquidator_fee < LIQUIDATION_FEE_PRECISION,
+        ErrorCode::DefaultError,
+        "Liquidation fee must be less than 100%"
+    )?;
+
+    validate!(
+        if_liquidation_fee < LIQUIDATION_FEE_PRECISION,
+        ErrorCode::DefaultError,
+        "If liquidation fee must be less than 100%"
+    )?;
+
+    // the liquidator and insurance fund shares are charged together, so both have to fit in maintenance
+    validate_margin(
+        perp_market.margin_ratio_initial,
+        perp_market.margin_ratio_maintenance,
+        liquidator_fee.safe_add(if_liquidation_fee)?,
+        perp_market.amm.max_spread,
+    )?;
+
+    perp_market.liquidator_fee = liquidator_fee;
+    perp_market.if_liquidation_fee = if_liquidation_fee;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_insurance_fund_unstaking_period(
+    ctx: Context<AdminUpdateSpotMarket>,
+    insurance_fund_unstaking_period: i64,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    spot_market.insurance_fund.unstaking_period = insurance_fund_unstaking_period;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_liquidation_fee(
+    ctx: Context<AdminUpdateSpotMarket>,
+    liquidator_fee: u32,
+    if_liquidation_fee: u32,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    update_spot_market_liquidation_fee(spot_market, liquidator_fee, if_liquidation_fee)?;
+    Ok(())
+}
+
+fn update_spot_market_liquidation_fee(
+    spot_market: &mut SpotMarket,
+    liquidator_fee: u32,
+    if_liquidation_fee: u32,
+) -> DriftResult {
+    validate!(
+        liquidator_fee < LIQUIDATION_FEE_PRECISION,
+        ErrorCode::DefaultError,
+        "Liquidation fee must be less than 100%"
+    )?;
+
+    validate!(
+        if_liquidation_fee <= LIQUIDATION_FEE_PRECISION / 20,
+        ErrorCode::DefaultError,
+        "if_liquidation_fee must be <= 5%"
+    )?;
+
+    spot_market.liquidator_fee = liquidator_fee;
+    spot_market.if_liquidation_fee = if_liquidation_fee;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_withdraw_guard_threshold(
+    ctx: Context<AdminUpdateSpotMarket>,
+    withdraw_guard_threshold: u64,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    msg!(
+        "spot_market.withdraw_guard_threshold: {:?} -> {:?}",
+        spot_market.withdraw_guard_threshold,
+        withdraw_guard_threshold
+    );
+    spot_market.withdraw_guard_threshold = withdraw_guard_threshold;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_if_factor(
+    ctx: Context<AdminUpdateSpotMarket>,
+    spot_market_index: u16,
+    user_if_factor: u32,
+    total_if_factor: u32,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+
+    validate!(
+        spot_market.market_index == spot_market_index,
+        ErrorCode::DefaultError,
+        "spot_market_index dne spot_market.index"
+    )?;
+
+    validate!(
+        user_if_factor <= total_if_factor,
+        ErrorCode::DefaultError,
+        "user_if_factor must be <= total_if_factor"
+    )?;
+
+    validate!(
+        total_if_factor <= IF_FACTOR_PRECISION.cast()?,
+        ErrorCode::DefaultError,
+        "total_if_factor must be <= 100%"
+    )?;
+
+    msg!(
+        "spot_market.user_if_factor: {:?} -> {:?}",
+        spot_market.insurance_fund.user_factor,
+        user_if_factor
+    );
+    msg!(
+        "spot_market.total_if_factor: {:?} -> {:?}",
+        spot_market.insurance_fund.total_factor,
+        total_if_factor
+    );
+
+    spot_market.insurance_fund.user_factor = user_if_factor;
+    spot_market.insurance_fund.total_factor = total_if_factor;
+
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_revenue_settle_period(
+    ctx: Context<AdminUpdateSpotMarket>,
+    revenue_settle_period: i64,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    validate!(revenue_settle_period > 0, ErrorCode::DefaultError)?;
+    msg!(
+        "spot_market.revenue_settle_period: {:?} -> {:?}",
+        spot_market.insurance_fund.revenue_settle_period,
+        revenue_settle_period
+    );
+    spot_market.insurance_fund.revenue_settle_period = revenue_settle_period;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_revenue_settle_max_amount(
+    ctx: Context<AdminUpdateSpotMarket>,
+    revenue_settle_max_amount: u64,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    msg!(
+        "spot_market.revenue_settle_max_amount: {:?} -> {:?}",
+        spot_market.revenue_settle_max_amount,
+        revenue_settle_max_amount
+    );
+    spot_market.revenue_settle_max_amount = revenue_settle_max_amount;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_status(
+    ctx: Context<PauserUpdateSpotMarket>,
+    status: MarketStatus,
+) -> Result<()> {
+    validate!(
+        !matches!(
+            status,
+            MarketStatus::FundingPaused
+                | MarketStatus::AmmPaused
+                | MarketStatus::FillPaused
+                | MarketStatus::WithdrawPaused
+        ),
+        ErrorCode::DefaultError,
+        "must pause spot market operations through update_spot_market_paused_operations",
+    )?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    spot_market.status = status;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_paused_operations(
+    ctx: Context<PauserUpdateSpotMarket>,
+    paused_operations: u8,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    spot_market.paused_operations = paused_operations;
+
+    msg!(
+        "spot market {} paused operations: {:?}",
+        spot_market.market_index,
+        spot_market.get_paused_operations()?
+    );
+
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_asset_tier(
+    ctx: Context<AdminUpdateSpotMarket>,
+    asset_tier: AssetTier,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    update_spot_market_asset_tier(spot_market, asset_tier)?;
+    Ok(())
+}
+
+fn update_spot_market_asset_tier(
+    spot_market: &mut SpotMarket,
+    asset_tier: AssetTier,
+) -> DriftResult {
+    if spot_market.initial_asset_weight > 0 {
+        validate!(
+            matches!(asset_tier, AssetTier::Collateral | AssetTier::Protected),
+            ErrorCode::DefaultError,
+            "initial_asset_weight > 0 so AssetTier must be collateral or protected"
+        )?;
+    }
+
+    spot_market.asset_tier = asset_tier;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_margin_weights(
+    ctx: Context<RiskParamUpdateSpotMarket>,
+    initial_asset_weight: u32,
+    maintenance_asset_weight: u32,
+    initial_liability_weight: u32,
+    maintenance_liability_weight: u32,
+    imf_factor: u32,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    update_spot_market_margin_weights(
+        spot_market,
+        initial_asset_weight,
+        maintenance_asset_weight,
+        initial_liability_weight,
+        maintenance_liability_weight,
+        imf_factor,
+    )?;
+
+    Ok(())
+}
+
+fn update_spot_market_margin_weights(
+    spot_market: &mut SpotMarket,
+    initial_asset_weight: u32,
+    maintenance_asset_weight: u32,
+    initial_liability_weight: u32,
+    maintenance_liability_weight: u32,
+    imf_factor: u32,
+) -> DriftResult {
+    validate_margin_weights(
+        spot_market.market_index,
+        initial_asset_weight,
+        maintenance_asset_weight,
+        initial_liability_weight,
+        maintenance_liability_weight,
+        imf_factor,
+    )?;
+
+    spot_market.initial_asset_weight = initial_asset_weight;
+    spot_market.maintenance_asset_weight = maintenance_asset_weight;
+    spot_market.initial_liability_weight = initial_liability_weight;
+    spot_market.maintenance_liability_weight = maintenance_liability_weight;
+    spot_market.imf_factor = imf_factor;
+
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_borrow_rate(
+    ctx: Context<AdminUpdateSpotMarket>,
+    optimal_utilization: u32,
+    optimal_borrow_rate: u32,
+    max_borrow_rate: u32,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    update_spot_market_borrow_rate(
+        spot_market,
+        optimal_utilization,
+        optimal_borrow_rate,
+        max_borrow_rate,
+    )?;
+    Ok(())
+}
+
+fn update_spot_market_borrow_rate(
+    spot_market: &mut SpotMarket,
+    optimal_utilization: u32,
+    optimal_borrow_rate: u32,
+    max_borrow_rate: u32,
+) -> DriftResult {
+    validate_borrow_rate(
+        optimal_utilization,
+        optimal_borrow_rate,
+        max_borrow_rate,
+        &spot_market.borrow_rate_kinks,
+    )?;
+    spot_market.optimal_utilization = optimal_utilization;
+    spot_market.optimal_borrow_rate = optimal_borrow_rate;
+    spot_market.max_borrow_rate = max_borrow_rate;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_borrow_rate_kinks(
+    ctx: Context<AdminUpdateSpotMarket>,
+    borrow_rate_kinks: [BorrowRateKink; 2],
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    update_spot_market_borrow_rate_kinks(spot_market, borrow_rate_kinks)?;
+    Ok(())
+}
+
+fn update_spot_market_borrow_rate_kinks(
+    spot_market: &mut SpotMarket,
+    borrow_rate_kinks: [BorrowRateKink; 2],
+) -> DriftResult {
+    validate_borrow_rate(
+        spot_market.optimal_utilization,
+        spot_market.optimal_borrow_rate,
+        spot_market.max_borrow_rate,
+        &borrow_rate_kinks,
+    )?;
+
+    msg!(
+        "spot_market.borrow_rate_kinks: {:?} -> {:?}",
+        spot_market.borrow_rate_kinks,
+        borrow_rate_kinks
+    );
+    spot_market.borrow_rate_kinks = borrow_rate_kinks;
+
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_max_token_deposits(
+    ctx: Context<AdminUpdateSpotMarket>,
+    max_token_deposits: u64,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    spot_market.max_token_deposits = max_token_deposits;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_max_token_borrows(
+    ctx: Context<AdminUpdateSpotMarket>,
+    max_token_borrows: u64,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    msg!(
+        "spot_market.max_token_borrows: {:?} -> {:?}",
+        spot_market.max_token_borrows,
+        max_token_borrows
+    );
+    spot_market.max_token_borrows = max_token_borrows;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_flash_borrow_params(
+    ctx: Context<AdminUpdateSpotMarket>,
+    flash_borrows_enabled: bool,
+    flash_borrow_fee_bps: u16,
+) -> Result<()> {
+    validate!(
+        flash_borrow_fee_bps.cast::<u32>()? <= ONE_BPS_DENOMINATOR,
+        ErrorCode::DefaultError,
+        "flash_borrow_fee_bps must be <= {}",
+        ONE_BPS_DENOMINATOR
+    )?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    msg!(
+        "spot_market.flash_borrows_enabled: {:?} -> {:?}",
+        spot_market.flash_borrows_enabled,
+        flash_borrows_enabled
+    );
+    msg!(
+        "spot_market.flash_borrow_fee_bps: {:?} -> {:?}",
+        spot_market.flash_borrow_fee_bps,
+        flash_borrow_fee_bps
+    );
+    spot_market.flash_borrows_enabled = flash_borrows_enabled;
+    spot_market.flash_borrow_fee_bps = flash_borrow_fee_bps;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_max_net_withdraw_bps(
+    ctx: Context<AdminUpdateSpotMarket>,
+    max_net_withdraw_bps: u16,
+) -> Result<()> {
+    validate!(
+        max_net_withdraw_bps.cast::<u32>()? <= ONE_BPS_DENOMINATOR,
+        ErrorCode::DefaultError,
+        "max_net_withdraw_bps must be <= {}",
+        ONE_BPS_DENOMINATOR
+    )?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    msg!(
+        "spot_market.max_net_withdraw_bps: {:?} -> {:?}",
+        spot_market.max_net_withdraw_bps,
+        max_net_withdraw_bps
+    );
+    spot_market.max_net_withdraw_bps = max_net_withdraw_bps;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_validity_guard_rails_override(
+    ctx: Context<AdminUpdateSpotMarket>,
+    validity_guard_rails_override: ValidityGuardRailsOverride,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    validate_validity_guard_rails_override(
+        &ctx.accounts.state.oracle_guard_rails,
+        &validity_guard_rails_override,
+    )?;
+    spot_market.validity_guard_rails_override = validity_guard_rails_override;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_max_collateral_concentration(
+    ctx: Context<AdminUpdateSpotMarket>,
+    max_collateral_concentration: u32,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+
+    validate!(
+        max_collateral_concentration <= SPOT_WEIGHT_PRECISION,
+        ErrorCode::DefaultError,
+        "max_collateral_concentration must be <= SPOT_WEIGHT_PRECISION"
+    )?;
+
+    spot_market.max_collateral_concentration = max_collateral_concentration;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_correlation_group(
+    ctx: Context<AdminUpdateSpotMarket>,
+    correlation_group: u8,
+    correlation_margin_credit: u16,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+
+    validate!(
+        spot_market.market_index != QUOTE_SPOT_MARKET_INDEX,
+        ErrorCode::DefaultError,
+        "quote spot market cant join a correlation group"
+    )?;
+
+    // credit cant exceed what the market charges on a borrow of the asset
+    let max_correlation_margin_credit = spot_market
+        .maintenance_liability_weight
+        .saturating_sub(SPOT_WEIGHT_PRECISION);
+    validate!(
+        correlation_margin_credit.cast::<u32>()? <= max_correlation_margin_credit,
+        ErrorCode::DefaultError,
+        "correlation_margin_credit {} > max {}",
+        correlation_margin_credit,
+        max_correlation_margin_credit
+    )?;
+
+    spot_market.correlation_group = correlation_group;
+    spot_market.correlation_margin_credit = correlation_margin_credit;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_orders_enabled(
+    ctx: Context<AdminUpdateSpotMarket>,
+    orders_enabled: bool,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    spot_market.orders_enabled = orders_enabled;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_oracle_circuit_breaker_band(
+    ctx: Context<AdminUpdatePerpMarket>,
+    oracle_circuit_breaker_band: u32,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+
+    validate!(
+        oracle_circuit_breaker_band.cast::<u128>()? <= PERCENTAGE_PRECISION,
+        ErrorCode::DefaultError,
+        "oracle_circuit_breaker_band must be <= 100%"
+    )?;
+
+    perp_market.oracle_circuit_breaker_band = oracle_circuit_breaker_band;
+
+    // disabling the breaker lets fills resume immediately
+    if oracle_circuit_breaker_band == 0 {
+        perp_market.set_operation_paused(PerpOperation::OracleCircuitBreaker, false)?;
+    }
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_auction_extension(
+    ctx: Context<AdminUpdatePerpMarket>,
+    auction_extension_duration: u8,
+    auction_extension_price_buffer: u32,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+
+    validate!(
+        auction_extension_price_buffer.cast::<u128>()? <= PERCENTAGE_PRECISION,
+        ErrorCode::DefaultError,
+        "auction_extension_price_buffer must be <= 100%"
+    )?;
+
+    validate!(
+        auction_extension_duration == 0 || auction_extension_price_buffer > 0,
+        ErrorCode::DefaultError,
+        "auction_extension_price_buffer must be > 0 to enable auction extensions"
+    )?;
+
+    perp_market.auction_extension_duration = auction_extension_duration;
+    perp_market.auction_extension_price_buffer = auction_extension_price_buffer;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_validity_guard_rails_override(
+    ctx: Context<AdminUpdatePerpMarket>,
+    validity_guard_rails_override: ValidityGuardRailsOverride,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate_validity_guard_rails_override(
+        &ctx.accounts.state.oracle_guard_rails,
+        &validity_guard_rails_override,
+    )?;
+    perp_market.amm.validity_guard_rails_override = validity_guard_rails_override;
+    Ok(())
+}
+
+fn validate_validity_guard_rails_override(
+    oracle_guard_rails: &OracleGuardRails,
+    validity_guard_rails_override: &ValidityGuardRailsOverride,
+) -> DriftResult {
+    let validity_guard_rails = oracle_guard_rails
+        .validity
+        .with_override(validity_guard_rails_override);
+
+    validate!(
+        validity_guard_rails.slots_before_stale_for_amm
+            <= validity_guard_rails.slots_before_stale_for_margin,
+        ErrorCode::DefaultError,
+        "slots_before_stale_for_amm {} must be <= slots_before_stale_for_margin {}",
+        validity_guard_rails.slots_before_stale_for_amm,
+        validity_guard_rails.slots_before_stale_for_margin
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_status(
+    ctx: Context<PauserUpdatePerpMarket>,
+    status: MarketStatus,
+) -> Result<()> {
+    validate!(
+        !matches!(status, MarketStatus::Delisted | MarketStatus::Settlement),
+        ErrorCode::DefaultError,
+        "must set settlement/delist through another instruction",
+    )?;
+
+    validate!(
+        !matches!(
+            status,
+            MarketStatus::FundingPaused
+                | MarketStatus::AmmPaused
+                | MarketStatus::FillPaused
+                | MarketStatus::WithdrawPaused
+        ),
+        ErrorCode::DefaultError,
+        "must pause perp market operations through update_perp_market_paused_operations",
+    )?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.status = status;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_paused_operations(
+    ctx: Context<PauserUpdatePerpMarket>,
+    paused_operations: u8,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+
+    // the oracle circuit breaker is tripped and reset by update_amm
+    let oracle_circuit_breaker_tripped =
+        perp_market.is_operation_paused(PerpOperation::OracleCircuitBreaker)?;
+    perp_market.paused_operations = paused_operations;
+    perp_market.set_operation_paused(
+        PerpOperation::OracleCircuitBreaker,
+        oracle_circuit_breaker_tripped,
+    )?;
+
+    msg!(
+        "perp market {} paused operations: {:?}",
+        perp_market.market_index,
+        perp_market.get_paused_operations()?
+    );
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_contract_tier(
+    ctx: Context<AdminUpdatePerpMarket>,
+    contract_tier: ContractTier,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.contract_tier = contract_tier;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_imf_factor(
+    ctx: Context<AdminUpdatePerpMarket>,
+    imf_factor: u32,
+    unrealized_pnl_imf_factor: u32,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    update_perp_market_imf_factor(perp_market, imf_factor, unrealized_pnl_imf_factor)?;
+    Ok(())
+}
+
+fn update_perp_market_imf_factor(
+    perp_market: &mut PerpMarket,
+    imf_factor: u32,
+    unrealized_pnl_imf_factor: u32,
+) -> DriftResult {
+    validate!(
+        imf_factor <= SPOT_IMF_PRECISION,
+        ErrorCode::DefaultError,
+        "invalid imf factor",
+    )?;
+    validate!(
+        unrealized_pnl_imf_factor <= SPOT_IMF_PRECISION,
+        ErrorCode::DefaultError,
+        "invalid unrealized pnl imf factor",
+    )?;
+    perp_market.imf_factor = imf_factor;
+    perp_market.unrealized_pnl_imf_factor = unrealized_pnl_imf_factor;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_unrealized_asset_weight(
+    ctx: Context<AdminUpdatePerpMarket>,
+    unrealized_initial_asset_weight: u32,
+    unrealized_maintenance_asset_weight: u32,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    validate!(
+        unrealized_initial_asset_weight <= SPOT_WEIGHT_PRECISION.cast()?,
+        ErrorCode::DefaultError,
+        "invalid unrealized_initial_asset_weight",
+    )?;
+    validate!(
+        unrealized_maintenance_asset_weight <= SPOT_WEIGHT_PRECISION.cast()?,
+        ErrorCode::DefaultError,
+        "invalid unrealized_maintenance_asset_weight",
+    )?;
+    validate!(
+        unrealized_initial_asset_weight <= unrealized_maintenance_asset_weight,
+        ErrorCode::DefaultError,
+        "must enforce unrealized_initial_asset_weight <= unrealized_maintenance_asset_weight",
+    )?;
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.unrealized_pnl_initial_asset_weight = unrealized_initial_asset_weight;
+    perp_market.unrealized_pnl_maintenance_asset_weight = unrealized_maintenance_asset_weight;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_concentration_coef(
+    ctx: Context<AdminUpdatePerpMarket>,
+    concentration_scale: u128,
+) -> Result<()> {
+    validate!(
+        concentration_scale > 0,
+        ErrorCode::DefaultError,
+        "invalid concentration_scale",
+    )?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let prev_concentration_coef = perp_market.amm.concentration_coef;
+    controller::amm::update_concentration_coef(&mut perp_market.amm, concentration_scale)?;
+    let new_concentration_coef = perp_market.amm.concentration_coef;
+
+    msg!(
+        "perp_market.amm.concentration_coef: {} -> {}",
+        prev_concentration_coef,
+        new_concentration_coef
+    );
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_curve_update_intensity(
+    ctx: Context<AdminUpdatePerpMarket>,
+    curve_update_intensity: u8,
+) -> Result<()> {
+    validate!(
+        curve_update_intensity <= 100,
+        ErrorCode::DefaultError,
+        "invalid curve_update_intensity",
+    )?;
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.amm.curve_update_intensity = curve_update_intensity;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_funding_period(
+    ctx: Context<AdminUpdatePerpMarket>,
+    funding_period: i64,
+) -> Result<()> {
+    validate!(
+        [ONE_HOUR, FOUR_HOUR, EIGHT_HOUR].contains(&funding_period),
+        ErrorCode::InvalidFundingPeriod,
+        "funding_period must be one, four or eight hours",
+    )?;
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    msg!(
+        "perp_market.amm.funding_period: {:?} -> {:?}",
+        perp_market.amm.funding_period,
+        funding_period
+    );
+    perp_market.amm.funding_period = funding_period;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_funding_rate_caps(
+    ctx: Context<AdminUpdatePerpMarket>,
+    max_funding_rate_spread: u32,
+    escalated_max_funding_rate_spread: u32,
+    funding_divergence_escalation_periods: u8,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    update_perp_market_funding_rate_caps(
+        perp_market,
+        max_funding_rate_spread,
+        escalated_max_funding_rate_spread,
+        funding_divergence_escalation_periods,
+    )?;
+    Ok(())
+}
+
+fn update_perp_market_funding_rate_caps(
+    perp_market: &mut PerpMarket,
+    max_funding_rate_spread: u32,
+    escalated_max_funding_rate_spread: u32,
+    funding_divergence_escalation_periods: u8,
+) -> DriftResult {
+    validate!(
+        max_funding_rate_spread.cast::<u128>()? <= PERCENTAGE_PRECISION
+            && escalated_max_funding_rate_spread.cast::<u128>()? <= PERCENTAGE_PRECISION,
+        ErrorCode::DefaultError,
+        "funding rate spreads must be <= PERCENTAGE_PRECISION",
+    )?;
+
+    validate!(
+        escalated_max_funding_rate_spread == 0
+            || escalated_max_funding_rate_spread > max_funding_rate_spread,
+        ErrorCode::DefaultError,
+        "escalated_max_funding_rate_spread must be greater than max_funding_rate_spread",
+    )?;
+
+    msg!(
+        "perp_market.amm.max_funding_rate_spread: {:?} -> {:?}",
+        perp_market.amm.max_funding_rate_spread,
+        max_funding_rate_spread
+    );
+    msg!(
+        "perp_market.amm.escalated_max_funding_rate_spread: {:?} -> {:?}",
+        perp_market.amm.escalated_max_funding_rate_spread,
+        escalated_max_funding_rate_spread
+    );
+    msg!(
+        "perp_market.amm.funding_divergence_escalation_periods: {:?} -> {:?}",
+        perp_market.amm.funding_divergence_escalation_periods,
+        funding_divergence_escalation_periods
+    );
+
+    perp_market.amm.max_funding_rate_spread = max_funding_rate_spread;
+    perp_market.amm.escalated_max_funding_rate_spread = escalated_max_funding_rate_spread;
+    perp_market.amm.funding_divergence_escalation_periods = funding_divergence_escalation_periods;
+    Ok(())
+}
+
+pub fn handle_update_lp_cooldown_time(
+    ctx: Context<AdminUpdateState>,
+    lp_cooldown_time: u64,
+) -> Result<()> {
+    ctx.accounts.state.lp_cooldown_time = lp_cooldown_time;
+    Ok(())
+}
+
+pub fn handle_update_perp_fee_structure(
+    ctx: Context<AdminUpdateState>,
+    fee_structure: FeeStructure,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+    validate_fee_structure(&fee_structure)?;
+
+    ctx.accounts.state.perp_fee_structure = fee_structure;
+    Ok(())
+}
+
+pub fn handle_update_spot_fee_structure(
+    ctx: Context<AdminUpdateState>,
+    fee_structure: FeeStructure,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+    validate_fee_structure(&fee_structure)?;
+    ctx.accounts.state.spot_fee_structure = fee_structure;
+    Ok(())
+}
+
+pub fn handle_update_initial_pct_to_liquidate(
+    ctx: Context<AdminUpdateState>,
+    initial_pct_to_liquidate: u16,
+) -> Result<()> {
+    ctx.accounts.state.initial_pct_to_liquidate = initial_pct_to_liquidate;
+    Ok(())
+}
+
+pub fn handle_update_liquidation_duration(
+    ctx: Context<AdminUpdateState>,
+    liquidation_duration: u8,
+) -> Result<()> {
+    ctx.accounts.state.liquidation_duration = liquidation_duration;
+    Ok(())
+}
+
+pub fn handle_update_liquidation_fee_ramp_duration(
+    ctx: Context<AdminUpdateState>,
+    liquidation_fee_ramp_duration: u8,
+) -> Result<()> {
+    ctx.accounts.state.liquidation_fee_ramp_duration = liquidation_fee_ramp_duration;
+    Ok(())
+}
+
+pub fn handle_update_margin_warning_health(
+    ctx: Context<AdminUpdateState>,
+    margin_warning_health: u8,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    validate!(
+        margin_warning_health <= 100,
+        ErrorCode::DefaultError,
+        "margin_warning_health must be <= 100"
+    )?;
+
+    msg!(
+        "state.margin_warning_health: {:?} -> {:?}",
+        ctx.accounts.state.margin_warning_health,
+        margin_warning_health
+    );
+
+    ctx.accounts.state.margin_warning_health = margin_warning_health;
+    Ok(())
+}
+
+pub fn handle_update_liquidation_stale_fee_escalation_duration(
+    ctx: Context<AdminUpdateState>,
+    liquidation_stale_fee_escalation_duration: u16,
+) -> Result<()> {
+    ctx.accounts.state.liquidation_stale_fee_escalation_duration =
+        liquidation_stale_fee_escalation_duration;
+    Ok(())
+}
+
+pub fn handle_update_liquidation_target_margin_buffer_ratio(
+    ctx: Context<AdminUpdateState>,
+    liquidation_target_margin_buffer_ratio: u32,
+) -> Result<()> {
+    validate!(
+        liquidation_target_margin_buffer_ratio <= MARGIN_PRECISION,
+        ErrorCode::DefaultError,
+        "liquidation_target_margin_buffer_ratio must be <= MARGIN_PRECISION"
+    )?;
+
+    ctx.accounts.state.liquidation_target_margin_buffer_ratio =
+        liquidation_target_margin_buffer_ratio;
+    Ok(())
+}
+
+pub fn handle_update_oracle_guard_rails(
+    ctx: Context<AdminUpdateState>,
+    oracle_guard_rails: OracleGuardRails,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    ctx.accounts.state.oracle_guard_rails = oracle_guard_rails;
+    Ok(())
+}
+
+pub fn handle_update_state_settlement_duration(
+    ctx: Context<AdminUpdateState>,
+    settlement_duration: u16,
+) -> Result<()> {
+    ctx.accounts.state.settlement_duration = settlement_duration;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_oracle(
+    ctx: Context<RepegCurve>,
+    oracle: Pubkey,
+    oracle_source: OracleSource,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let clock = Clock::get()?;
+
+    validate_oracle_readable(&ctx.accounts.oracle, oracle_source, &clock)?;
+
+    perp_market.amm.oracle = oracle;
+    perp_market.amm.oracle_source = oracle_source;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_additional_oracles(
+    ctx: Context<AdminUpdatePerpMarket>,
+    additional_oracles: [Pubkey; 2],
+    additional_oracle_sources: [OracleSource; 2],
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let clock = Clock::get()?;
+
+    validate!(
+        additional_oracles[0] == Pubkey::default()
+            || additional_oracles[0] != additional_oracles[1],
+        ErrorCode::InvalidOracle,
+        "additional oracles must be unique"
+    )?;
+
+    for (oracle, oracle_source) in additional_oracles
+        .iter()
+        .zip(additional_oracle_sources.iter())
+    {
+        if *oracle == Pubkey::default() {
+            continue;
+        }
+
+        validate!(
+            *oracle != perp_market.amm.oracle,
+            ErrorCode::InvalidOracle,
+            "additional oracle {} is already the primary oracle",
+            oracle
+        )?;
+
+        let oracle_account_info = ctx
+            .remaining_accounts
+            .iter()
+            .find(|account_info| account_info.key == oracle)
+            .ok_or(ErrorCode::OracleNotFound)?;
+
+        // Verify oracle is readable
+        get_oracle_price(oracle_source, oracle_account_info, clock.slot)?;
+
+        if *oracle_source == OracleSource::PythPull {
+            validate_pyth_pull_publish_time(
+                oracle_account_info,
+                clock.unix_timestamp,
+                PYTH_PULL_MAX_PUBLISH_TIME_STALENESS,
+            )?;
+        }
+    }
+
+    perp_market.additional_oracles = additional_oracles;
+    perp_market.additional_oracle_sources = additional_oracle_sources;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_base_spread(
+    ctx: Context<AdminUpdatePerpMarket>,
+    base_spread: u32,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.amm.base_spread = base_spread;
+    perp_market.amm.long_spread = base_spread / 2;
+    perp_market.amm.short_spread = base_spread / 2;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_amm_jit_intensity(
+    ctx: Context<AdminUpdatePerpMarket>,
+    amm_jit_intensity: u8,
+) -> Result<()> {
+    validate!(
+        (0..=100).contains(&amm_jit_intensity),
+        ErrorCode::DefaultError,
+        "invalid amm_jit_intensity",
+    )?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.amm.amm_jit_intensity = amm_jit_intensity;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_max_spread(
+    ctx: Context<AdminUpdatePerpMarket>,
+    max_spread: u32,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate!(
+        max_spread >= perp_market.amm.base_spread,
+        ErrorCode::DefaultError,
+        "invalid max_spread < base_spread",
+    )?;
+
+    validate!(
+        max_spread <= perp_market.margin_ratio_initial * 100,
+        ErrorCode::DefaultError,
+        "invalid max_spread > market.margin_ratio_initial * 100",
+    )?;
+
+    perp_market.amm.max_spread = max_spread;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_dynamic_spread_params(
+    ctx: Context<AdminUpdatePerpMarket>,
+    inventory_skew_spread: u16,
+    volatility_spread_scale: u16,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate!(
+        inventory_skew_spread.cast::<u32>()? <= perp_market.margin_ratio_initial,
+        ErrorCode::DefaultError,
+        "invalid inventory_skew_spread > market.margin_ratio_initial",
+    )?;
+
+    msg!(
+        "perp_market.amm.inventory_skew_spread: {:?} -> {:?}",
+        perp_market.amm.inventory_skew_spread,
+        inventory_skew_spread
+    );
+    msg!(
+        "perp_market.amm.volatility_spread_scale: {:?} -> {:?}",
+        perp_market.amm.volatility_spread_scale,
+        volatility_spread_scale
+    );
+
+    perp_market.amm.inventory_skew_spread = inventory_skew_spread;
+    perp_market.amm.volatility_spread_scale = volatility_spread_scale;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_max_reference_price_offset(
+    ctx: Context<AdminUpdatePerpMarket>,
+    max_reference_price_offset: u16,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate!(
+        max_reference_price_offset.cast::<u32>()?.safe_mul(100)? <= perp_market.amm.max_spread,
+        ErrorCode::DefaultError,
+        "invalid max_reference_price_offset > amm.max_spread",
+    )?;
+
+    msg!(
+        "perp_market.amm.max_reference_price_offset: {:?} -> {:?}",
+        perp_market.amm.max_reference_price_offset,
+        max_reference_price_offset
+    );
+
+    perp_market.amm.max_reference_price_offset = max_reference_price_offset;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_lp_lockup(
+    ctx: Context<AdminUpdatePerpMarket>,
+    lp_min_duration: u32,
+    lp_early_removal_penalty: u16,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate!(
+        lp_early_removal_penalty.cast::<u32>()? <= perp_market.margin_ratio_initial,
+        ErrorCode::DefaultError,
+        "invalid lp_early_removal_penalty > margin_ratio_initial",
+    )?;
+
+    msg!(
+        "perp_market.lp_min_duration: {:?} -> {:?}",
+        perp_market.lp_min_duration,
+        lp_min_duration
+    );
+
+    msg!(
+        "perp_market.lp_early_removal_penalty: {:?} -> {:?}",
+        perp_market.lp_early_removal_penalty,
+        lp_early_removal_penalty
+    );
+
+    perp_market.lp_min_duration = lp_min_duration;
+    perp_market.lp_early_removal_penalty = lp_early_removal_penalty;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_step_size_and_tick_size(
+    ctx: Context<AdminUpdatePerpMarket>,
+    step_size: u64,
+    tick_size: u64,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate!(step_size > 0 && tick_size > 0, ErrorCode::DefaultError)?;
+    perp_market.amm.order_step_size = step_size;
+    perp_market.amm.order_tick_size = tick_size;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_tick_size_bps(
+    ctx: Context<AdminUpdatePerpMarket>,
+    order_tick_size_bps: u16,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate!(
+        order_tick_size_bps <= 100,
+        ErrorCode::DefaultError,
+        "order_tick_size_bps must be <= 100"
+    )?;
+
+    msg!(
+        "perp_market.order_tick_size_bps: {:?} -> {:?}",
+        perp_market.order_tick_size_bps,
+        order_tick_size_bps
+    );
+
+    perp_market.order_tick_size_bps = order_tick_size_bps;
+    Ok(())
+}
+
+/// Grows a market created before fields were appended to PerpMarket. The appended bytes are
+/// zeroed, which every appended field treats as its default
+pub fn handle_resize_perp_market(ctx: Context<ResizePerpMarket>) -> Result<()> {
+    let perp_market = load!(ctx.accounts.perp_market)?;
+    msg!(
+        "resized perp market {} to {} bytes",
+        perp_market.market_index,
+        PerpMarket::SIZE
+    );
+    Ok(())
+}
+
+/// Grows a market created before fields were appended to SpotMarket. The appended bytes are
+/// zeroed, which every appended field treats as its default
+pub fn handle_resize_spot_market(ctx: Context<ResizeSpotMarket>) -> Result<()> {
+    let spot_market = load!(ctx.accounts.spot_market)?;
+    msg!(
+        "resized spot market {} to {} bytes",
+        spot_market.market_index,
+        SpotMarket::SIZE
+    );
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_min_order_size(
+    ctx: Context<AdminUpdatePerpMarket>,
+    order_size: u64,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    validate!(order_size > 0, ErrorCode::DefaultError)?;
+    perp_market.amm.min_order_size = order_size;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_step_size_and_tick_size(
+    ctx: Context<AdminUpdateSpotMarket>,
+    step_size: u64,
+    tick_size: u64,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    validate!(
+        spot_market.market_index == 0 || step_size > 0 && tick_size > 0,
+        ErrorCode::DefaultError
+    )?;
+    spot_market.order_step_size = step_size;
+    spot_market.order_tick_size = tick_size;
+    Ok(())
+}
+
+#[access_control(
+    spot_market_valid(&ctx.accounts.spot_market)
+)]
+pub fn handle_update_spot_market_min_order_size(
+    ctx: Context<AdminUpdateSpotMarket>,
+    order_size: u64,
+) -> Result<()> {
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    validate!(
+        spot_market.market_index == 0 || order_size > 0,
+        ErrorCode::DefaultError
+    )?;
+    spot_market.min_order_size = order_size;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_max_slippage_ratio(
+    ctx: Context<AdminUpdatePerpMarket>,
+    max_slippage_ratio: u16,
+) -> Result<()> {
+    validate!(max_slippage_ratio > 0, ErrorCode::DefaultError)?;
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.amm.max_slippage_ratio = max_slippage_ratio;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_max_fill_reserve_fraction(
+    ctx: Context<AdminUpdatePerpMarket>,
+    max_fill_reserve_fraction: u16,
+) -> Result<()> {
+    validate!(max_fill_reserve_fraction > 0, ErrorCode::DefaultError)?;
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    perp_market.amm.max_fill_reserve_fraction = max_fill_reserve_fraction;
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_max_open_interest(
+    ctx: Context<AdminUpdatePerpMarket>,
+    max_open_interest: u128,
+) -> Result<()> {
+    validate_admin_timelock_inactive(&ctx.accounts.state)?;
+
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    update_perp_market_max_open_interest(perp_market, max_open_interest)?;
+    Ok(())
+}
+
+fn update_perp_market_max_open_interest(
+    perp_market: &mut PerpMarket,
+    max_open_interest: u128,
+) -> DriftResult {
+    validate!(
+        is_multiple_of_step_size(
+            max_open_interest.cast::<u64>()?,
+            perp_market.amm.order_step_size
+        )?,
+        ErrorCode::DefaultError,
+        "max oi not a multiple of the step size"
+    )?;
+
+    perp_market.amm.max_open_interest = max_open_interest;
+    Ok(())
+}
+
+pub fn handle_update_admin(ctx: Context<AdminUpdateState>, admin: Pubkey) -> Result<()> {
+    ctx.accounts.state.admin = admin;
+    Ok(())
+}
+
+pub fn handle_update_whitelist_mint(
+    ctx: Context<AdminUpdateState>,
+    whitelist_mint: Pubkey,
+) -> Result<()> {
+    ctx.accounts.state.whitelist_mint = whitelist_mint;
+    Ok(())
+}
+
+pub fn handle_update_discount_mint(
+    ctx: Context<AdminUpdateState>,
+    discount_mint: Pubkey,
+) -> Result<()> {
+    ctx.accounts.state.discount_mint = discount_mint;
+    Ok(())
+}
+
+pub fn handle_update_exchange_status(
+    ctx: Context<PauserUpdateState>,
+    exchange_status: u8,
+) -> Result<()> {
+    ctx.accounts.state.exchange_status = exchange_status;
+    Ok(())
+}
+
+pub fn handle_update_perp_auction_duration(
+    ctx: Context<AdminUpdateState>,
+    min_perp_auction_duration: u8,
+) -> Result<()> {
+    ctx.accounts.state.min_perp_auction_duration = min_perp_auction_duration;
+    Ok(())
+}
+
+pub fn handle_update_spot_auction_duration(
+    ctx: Context<AdminUpdateState>,
+    default_spot_auction_duration: u8,
+) -> Result<()> {
+    ctx.accounts.state.default_spot_auction_duration = default_spot_auction_duration;
+    Ok(())
+}
+
+pub fn handle_initialize_insurance_fund_share_mint(
+    ctx: Context<InitializeInsuranceFundShareMint>,
+    market_index: u16,
+) -> Result<()> {
+    let spot_market = load!(ctx.accounts.spot_market)?;
+
+    validate!(
+        spot_market.mint == ctx.accounts.spot_market_mint.key(),
+        ErrorCode::DefaultError,
+        "spot_market_mint doesnt match spot_market"
+    )?;
+
+    let mut wrapped_insurance_fund_stake = ctx
+        .accounts
+        .wrapped_insurance_fund_stake
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    let now = Clock::get()?.unix_timestamp;
+
+    // shares behind the share mint tokens, owned by the program
+    *wrapped_insurance_fund_stake =
+        InsuranceFundStake::new(ctx.accounts.drift_signer.key(), market_index, now);
+
+    Ok(())
+}
+
+pub fn handle_initialize_governance_stake_vault(
+    _ctx: Context<InitializeGovernanceStakeVault>,
+) -> Result<()> {
+    Ok(())
+}
+
+pub fn handle_admin_remove_insurance_fund_stake(
+    ctx: Context<AdminRemoveInsuranceFundStake>,
+    market_index: u16,
+    amount: u64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    let state = &ctx.accounts.state;
+
+    validate!(
+        market_index == spot_market.market_index,
+        ErrorCode::DefaultError,
+        "market_index doesnt match spot_market"
+    )?;
+
+    let n_shares = math::insurance::vault_amount_to_if_shares(
+        amount,
+        spot_market.insurance_fund.total_shares,
+        ctx.accounts.insurance_fund_vault.amount,
+    )?;
+
+    let withdrawn_amount = controller::insurance::admin_remove_insurance_fund_stake(
+        ctx.accounts.insurance_fund_vault.amount,
+        n_shares,
+        spot_market,
+        now,
+        *ctx.accounts.admin.key,
+    )?;
+
+    controller::token::send_from_program_vault(
+        &ctx.accounts.token_program,
+        &ctx.accounts.insurance_fund_vault,
+        &ctx.accounts.admin_token_account,
+        &ctx.accounts.drift_signer,
+        state.signer_nonce,
+        withdrawn_amount,
+    )?;
+
+    validate!(
+        ctx.accounts.insurance_fund_vault.amount > 0,
+        ErrorCode::DefaultError,
+        "insurance_fund_vault.amount must remain > 0"
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"drift_state".as_ref()],
+        space = State::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub quote_asset_mint: Box<Account<'info, Mint>>,
+    /// CHECK: checked in `initialize`
+    pub drift_signer: AccountInfo<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeSpotMarket<'info> {
+    #[account(
+        init,
+        seeds = [b"spot_market", state.number_of_spot_markets.to_le_bytes().as_ref()],
+        space = SpotMarket::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+    /// CHECK: checked in `initialize_spot_market`
+    pub spot_market_mint: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"spot_market_vault".as_ref(), state.number_of_spot_markets.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: created in `initialize_spot_market`
+    pub spot_market_vault: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"insurance_fund_vault".as_ref(), state.number_of_spot_markets.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: created in `initialize_spot_market`
+    pub insurance_fund_vault: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: program signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::MarketLister)?
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
+    /// CHECK: checked in `initialize_spot_market`
+    pub oracle: AccountInfo<'info>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: spl token or token-2022, checked in `initialize_spot_market`
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializeSerumFulfillmentConfig<'info> {
+    #[account(
+        seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub base_spot_market: AccountLoader<'info, SpotMarket>,
+    #[account(
+        seeds = [b"spot_market", 0_u16.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub quote_spot_market: AccountLoader<'info, SpotMarket>,
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: checked in ix
+    pub serum_program: AccountInfo<'info>,
+    /// CHECK: checked in ix
+    pub serum_market: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"serum_open_orders".as_ref(), serum_market.key.as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in ix
+    pub serum_open_orders: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: program signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(
+        init,
+        seeds = [b"serum_fulfillment_config".as_ref(), serum_market.key.as_ref()],
+        space = SerumV3FulfillmentConfig::SIZE,
+        bump,
+        payer = admin,
+    )]
+    pub serum_fulfillment_config: AccountLoader<'info, SerumV3FulfillmentConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializeOpenbookV2FulfillmentConfig<'info> {
+    #[account(
+        seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub base_spot_market: AccountLoader<'info, SpotMarket>,
+    #[account(
+        seeds = [b"spot_market", 0_u16.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub quote_spot_market: AccountLoader<'info, SpotMarket>,
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: checked in ix
+    pub openbook_v2_program: AccountInfo<'info>,
+    /// CHECK: checked in ix
+    pub openbook_v2_market: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: program signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(
+        init,
+        seeds = [b"openbook_v2_fulfillment_config".as_ref(), openbook_v2_market.key.as_ref()],
+        space = OpenbookV2FulfillmentConfig::SIZE,
+        bump,
+        payer = admin,
+    )]
+    pub openbook_v2_fulfillment_config: AccountLoader<'info, OpenbookV2FulfillmentConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializePhoenixFulfillmentConfig<'info> {
+    #[account(
+        seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub base_spot_market: AccountLoader<'info, SpotMarket>,
+    #[account(
+        seeds = [b"spot_market", 0_u16.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub quote_spot_market: AccountLoader<'info, SpotMarket>,
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: checked in ix
+    pub phoenix_program: AccountInfo<'info>,
+    /// CHECK: checked in ix
+    pub phoenix_market: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: program signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(
+        init,
+        seeds = [b"phoenix_fulfillment_config".as_ref(), phoenix_market.key.as_ref()],
+        space = PhoenixV1FulfillmentConfig::SIZE,
+        bump,
+        payer = admin,
+    )]
+    pub phoenix_fulfillment_config: AccountLoader<'info, PhoenixV1FulfillmentConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializeHighLeverageModeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"high_leverage_mode_config".as_ref(), market_index.to_le_bytes().as_ref()],
+        space = HighLeverageModeConfig::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub high_leverage_mode_config: AccountLoader<'info, HighLeverageModeConfig>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_type: MarketType, market_index: u16)]
+pub struct InitializeFuelBoost<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"fuel_boost".as_ref(), &[market_type as u8], market_index.to_le_bytes().as_ref()],
+        space = FuelBoost::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub fuel_boost: AccountLoader<'info, FuelBoost>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFuelBoost<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub fuel_boost: AccountLoader<'info, FuelBoost>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializePerpMarketFeeOverride<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"market_fee_override".as_ref(), &[MarketType::Perp as u8], market_index.to_le_bytes().as_ref()],
+        space = MarketFeeOverride::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub market_fee_override: AccountLoader<'info, MarketFeeOverride>,
+    #[account(mut)]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct DelistPerpMarket<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    #[account(
+        init,
+        seeds = [b"perp_market_delisting".as_ref(), market_index.to_le_bytes().as_ref()],
+        space = PerpMarketDelisting::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub perp_market_delisting: AccountLoader<'info, PerpMarketDelisting>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializeDatedFuture<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    #[account(
+        init,
+        seeds = [b"perp_market_delisting".as_ref(), market_index.to_le_bytes().as_ref()],
+        space = PerpMarketDelisting::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub perp_market_delisting: AccountLoader<'info, PerpMarketDelisting>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializeSpotMarketFeeOverride<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"market_fee_override".as_ref(), &[MarketType::Spot as u8], market_index.to_le_bytes().as_ref()],
+        space = MarketFeeOverride::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub market_fee_override: AccountLoader<'info, MarketFeeOverride>,
+    #[account(mut)]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketFeeOverride<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub market_fee_override: AccountLoader<'info, MarketFeeOverride>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+#[instruction(table_index: u16)]
+pub struct InitializeMarketLookupTable<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        init,
+        seeds = [b"market_lookup_table".as_ref(), table_index.to_le_bytes().as_ref()],
+        space = MarketLookupTable::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub market_lookup_table: AccountLoader<'info, MarketLookupTable>,
+    #[account(mut)]
+    /// CHECK: derived from the drift signer and recent slot in `create_lookup_table`
+    pub lookup_table: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: program signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(
+        address = crate::ids::address_lookup_table_program::id()
+    )]
+    /// CHECK: address lookup table program
+    pub address_lookup_table_program: AccountInfo<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16, authority: Pubkey)]
+pub struct InitializeMarketWhitelistEntry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"market_whitelist_entry".as_ref(), market_index.to_le_bytes().as_ref(), authority.as_ref()],
+        space = MarketWhitelistEntry::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub market_whitelist_entry: AccountLoader<'info, MarketWhitelistEntry>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(program_id: Pubkey)]
+pub struct InitializeFillCallbackProgram<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"fill_callback_program".as_ref(), program_id.as_ref()],
+        space = FillCallbackProgram::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub fill_callback_program: AccountLoader<'info, FillCallbackProgram>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteFillCallbackProgram<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        close = admin
+    )]
+    pub fill_callback_program: AccountLoader<'info, FillCallbackProgram>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+pub struct DeleteMarketWhitelistEntry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        close = admin
+    )]
+    pub market_whitelist_entry: AccountLoader<'info, MarketWhitelistEntry>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateReferrerRewardConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub referrer_stats: AccountLoader<'info, UserStats>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+#[instruction(change: AdminChange)]
+pub struct QueueAdminChange<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"pending_admin_change".as_ref(), change.seed().as_ref()],
+        space = PendingAdminChange::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub pending_admin_change: Box<Account<'info, PendingAdminChange>>,
+    #[account(
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::RiskParamSetter)?
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecutePerpMarketAdminChange<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        close = admin
+    )]
+    pub pending_admin_change: Box<Account<'info, PendingAdminChange>>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    /// CHECK: checked against the queued oracle when the change is an oracle change
+    pub oracle: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteSpotMarketAdminChange<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        close = admin
+    )]
+    pub pending_admin_change: Box<Account<'info, PendingAdminChange>>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+    /// CHECK: checked against the queued oracle when the change is an oracle change
+    pub oracle: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteStateAdminChange<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        close = admin
+    )]
+    pub pending_admin_change: Box<Account<'info, PendingAdminChange>>,
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelAdminChange<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        close = admin
+    )]
+    pub pending_admin_change: Box<Account<'info, PendingAdminChange>>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+pub struct PauserUpdateState<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::Pauser)?
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PauserUpdatePerpMarket<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::Pauser)?
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+}
+
+#[derive(Accounts)]
+pub struct PauserUpdateSpotMarket<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::Pauser)?
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+}
+
+#[derive(Accounts)]
+pub struct RiskParamUpdatePerpMarket<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::RiskParamSetter)?
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+}
+
+#[derive(Accounts)]
+pub struct RiskParamUpdateSpotMarket<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::RiskParamSetter)?
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeAdminRoles<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"admin_roles".as_ref()],
+        space = AdminRoles::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub admin_roles: AccountLoader<'info, AdminRoles>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAdminRole<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: AccountLoader<'info, AdminRoles>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateHighLeverageModeConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub high_leverage_mode_config: AccountLoader<'info, HighLeverageModeConfig>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeBackstopProvider<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"backstop_provider".as_ref(), user.key().as_ref()],
+        space = BackstopProvider::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub backstop_provider: AccountLoader<'info, BackstopProvider>,
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateBackstopProvider<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut)]
+    pub backstop_provider: AccountLoader<'info, BackstopProvider>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: PrelaunchOracleParams)]
+pub struct InitializePrelaunchOracle<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"prelaunch_oracle".as_ref(), params.perp_market_index.to_le_bytes().as_ref()],
+        space = PrelaunchOracle::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub prelaunch_oracle: AccountLoader<'info, PrelaunchOracle>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(params: PrelaunchOracleParams)]
+pub struct UpdatePrelaunchOracleParams<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"prelaunch_oracle".as_ref(), params.perp_market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub prelaunch_oracle: AccountLoader<'info, PrelaunchOracle>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeLstOracle<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"lst_oracle".as_ref(), stake_pool.key.as_ref()],
+        space = LstOracle::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub lst_oracle: AccountLoader<'info, LstOracle>,
+    /// CHECK: checked in `initialize_lst_oracle`
+    pub sol_oracle: AccountInfo<'info>,
+    /// CHECK: checked in `initialize_lst_oracle`
+    pub stake_pool: AccountInfo<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateSerumFulfillmentConfig<'info> {
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub serum_fulfillment_config: AccountLoader<'info, SerumV3FulfillmentConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOpenbookV2FulfillmentConfig<'info> {
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub openbook_v2_fulfillment_config: AccountLoader<'info, OpenbookV2FulfillmentConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePhoenixFulfillmentConfig<'info> {
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub phoenix_fulfillment_config: AccountLoader<'info, PhoenixV1FulfillmentConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateSerumVault<'info> {
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub srm_vault: Box<Account<'info, TokenAccount>>,
+}
+
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+pub struct InitializePerpMarket<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
+    #[account(
+        mut,
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::MarketLister)?
+    )]
+    pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
     #[account(
         init,
-        seeds = [b"drift_state".as_ref()],
-        space = State::SIZE,
+        seeds = [b"perp_market", state.number_of_markets.to_le_bytes().as_ref()],
+        space = PerpMarket::SIZE,
         bump,
         payer = admin
     )]
-    pub state: Box<Account<'info, State>>,
-    pub quote_asset_mint: Box<Account<'info, Mint>>,
-    /// CHECK: checked in `initialize`
-    pub drift_signer: AccountInfo<'info>,
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    /// CHECK: checked in `initialize_perp_market`
+    pub oracle: AccountInfo<'info>,
     pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct InitializeSpotMarket<'info> {
+pub struct InitializePerpMarketListingConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
     #[account(
         init,
-        seeds = [b"spot_market", state.number_of_spot_markets.to_le_bytes().as_ref()],
-        space = SpotMarket::SIZE,
+        seeds = [b"perp_market_listing_config".as_ref()],
+        space = PerpMarketListingConfig::SIZE,
         bump,
         payer = admin
     )]
-    pub spot_market: AccountLoader<'info, SpotMarket>,
-    pub spot_market_mint: Box<Account<'info, Mint>>,
+    pub perp_market_listing_config: AccountLoader<'info, PerpMarketListingConfig>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePerpMarketListingConfig<'info> {
+    pub admin: Signer<'info>,
     #[account(
-        init,
-        seeds = [b"spot_market_vault".as_ref(), state.number_of_spot_markets.to_le_bytes().as_ref()],
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [b"perp_market_listing_config".as_ref()],
         bump,
-        payer = admin,
-        token::mint = spot_market_mint,
-        token::authority = drift_signer
     )]
-    pub spot_market_vault: Box<Account<'info, TokenAccount>>,
+    pub perp_market_listing_config: AccountLoader<'info, PerpMarketListingConfig>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePnlPoolRebalanceConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
     #[account(
         init,
-        seeds = [b"insurance_fund_vault".as_ref(), state.number_of_spot_markets.to_le_bytes().as_ref()],
+        seeds = [b"pnl_pool_rebalance_config".as_ref()],
+        space = PnlPoolRebalanceConfig::SIZE,
         bump,
-        payer = admin,
-        token::mint = spot_market_mint,
-        token::authority = drift_signer
+        payer = admin
     )]
-    pub insurance_fund_vault: Box<Account<'info, TokenAccount>>,
+    pub pnl_pool_rebalance_config: AccountLoader<'info, PnlPoolRebalanceConfig>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePnlPoolRebalanceConfig<'info> {
+    pub admin: Signer<'info>,
     #[account(
-        constraint = state.signer.eq(&drift_signer.key())
+        has_one = admin
     )]
-    /// CHECK: program signer
-    pub drift_signer: AccountInfo<'info>,
+    pub state: Box<Account<'info, State>>,
     #[account(
         mut,
-        has_one = admin
+        seeds = [b"pnl_pool_rebalance_config".as_ref()],
+        bump,
     )]
+    pub pnl_pool_rebalance_config: AccountLoader<'info, PnlPoolRebalanceConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ProposePerpMarket<'info> {
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(mut)]
     pub state: Box<Account<'info, State>>,
-    /// CHECK: checked in `initialize_spot_market`
+    #[account(
+        seeds = [b"perp_market_listing_config".as_ref()],
+        bump,
+    )]
+    pub perp_market_listing_config: AccountLoader<'info, PerpMarketListingConfig>,
+    #[account(
+        init,
+        seeds = [b"perp_market", state.number_of_markets.to_le_bytes().as_ref()],
+        space = PerpMarket::SIZE,
+        bump,
+        payer = proposer
+    )]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    #[account(
+        init,
+        seeds = [b"perp_market_listing".as_ref(), state.number_of_markets.to_le_bytes().as_ref()],
+        space = PerpMarketListing::SIZE,
+        bump,
+        payer = proposer
+    )]
+    pub perp_market_listing: AccountLoader<'info, PerpMarketListing>,
+    /// CHECK: checked in `propose_perp_market`
     pub oracle: AccountInfo<'info>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
     pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
 #[instruction(market_index: u16)]
-pub struct InitializeSerumFulfillmentConfig<'info> {
+pub struct ActivateProposedPerpMarket<'info> {
+    /// CHECK: receives the bond, checked against the listing
+    #[account(mut)]
+    pub proposer: AccountInfo<'info>,
     #[account(
-        seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
-        bump,
+        mut,
+        seeds = [b"perp_market", market_index.to_le_bytes().as_ref()],
+        bump
     )]
-    pub base_spot_market: AccountLoader<'info, SpotMarket>,
+    pub perp_market: AccountLoader<'info, PerpMarket>,
     #[account(
-        seeds = [b"spot_market", 0_u16.to_le_bytes().as_ref()],
+        mut,
+        seeds = [b"perp_market_listing".as_ref(), market_index.to_le_bytes().as_ref()],
         bump,
+        has_one = proposer,
+        close = proposer
     )]
-    pub quote_spot_market: AccountLoader<'info, SpotMarket>,
+    pub perp_market_listing: AccountLoader<'info, PerpMarketListing>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct VetoProposedPerpMarket<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
     #[account(
-        mut,
         has_one = admin
     )]
     pub state: Box<Account<'info, State>>,
-    /// CHECK: checked in ix
-    pub serum_program: AccountInfo<'info>,
-    /// CHECK: checked in ix
-    pub serum_market: AccountInfo<'info>,
     #[account(
         mut,
-        seeds = [b"serum_open_orders".as_ref(), serum_market.key.as_ref()],
-        bump,
-    )]
-    /// CHECK: checked in ix
-    pub serum_open_orders: AccountInfo<'info>,
-    #[account(
-        constraint = state.signer.eq(&drift_signer.key())
+        seeds = [b"perp_market", market_index.to_le_bytes().as_ref()],
+        bump
     )]
-    /// CHECK: program signer
-    pub drift_signer: AccountInfo<'info>,
+    pub perp_market: AccountLoader<'info, PerpMarket>,
     #[account(
-        init,
-        seeds = [b"serum_fulfillment_config".as_ref(), serum_market.key.as_ref()],
-        space = SerumV3FulfillmentConfig::SIZE,
+        mut,
+        seeds = [b"perp_market_listing".as_ref(), market_index.to_le_bytes().as_ref()],
         bump,
-        payer = admin,
+        close = admin
     )]
-    pub serum_fulfillment_config: AccountLoader<'info, SerumV3FulfillmentConfig>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub rent: Sysvar<'info, Rent>,
-    pub system_program: Program<'info, System>,
+    pub perp_market_listing: AccountLoader<'info, PerpMarketListing>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateSerumFulfillmentConfig<'info> {
+pub struct AdminUpdatePerpMarket<'info> {
+    pub admin: Signer<'info>,
     #[account(
-        has_one = admin
+    has_one = admin
     )]
     pub state: Box<Account<'info, State>>,
     #[account(mut)]
-    pub serum_fulfillment_config: AccountLoader<'info, SerumV3FulfillmentConfig>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
+    pub perp_market: AccountLoader<'info, PerpMarket>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateSerumVault<'info> {
+pub struct ResizePerpMarket<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
     #[account(
-        mut,
         has_one = admin
     )]
     pub state: Box<Account<'info, State>>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub srm_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        realloc = PerpMarket::SIZE,
+        realloc::payer = admin,
+        realloc::zero = true,
+    )]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct InitializePerpMarket<'info> {
+pub struct ResizeSpotMarket<'info> {
     #[account(mut)]
     pub admin: Signer<'info>,
     #[account(
-        mut,
         has_one = admin
     )]
     pub state: Box<Account<'info, State>>,
     #[account(
-        init,
-        seeds = [b"perp_market", state.number_of_markets.to_le_bytes().as_ref()],
-        space = PerpMarket::SIZE,
-        bump,
-        payer = admin
+        mut,
+        realloc = SpotMarket::SIZE,
+        realloc::payer = admin,
+        realloc::zero = true,
     )]
-    pub perp_market: AccountLoader<'info, PerpMarket>,
-    /// CHECK: checked in `initialize_perp_market`
-    pub oracle: AccountInfo<'info>,
-    pub rent: Sysvar<'info, Rent>,
+    pub spot_market: AccountLoader<'info, SpotMarket>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct AdminUpdatePerpMarket<'info> {
-    pub admin: Signer<'info>,
+pub struct SettleExpiredMarketPoolsToRevenuePool<'info> {
     #[account(
-    has_one = admin
+        has_one = admin
     )]
     pub state: Box<Account<'info, State>>,
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [b"spot_market", 0_u16.to_le_bytes().as_ref()],
+        bump,
+        mut
+    )]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
     #[account(mut)]
     pub perp_market: AccountLoader<'info, PerpMarket>,
 }
 
 #[derive(Accounts)]
-pub struct SettleExpiredMarketPoolsToRevenuePool<'info> {
+pub struct ResolvePredictionMarket<'info> {
     #[account(
         has_one = admin
     )]
@@ -2269,9 +5918,15 @@ pub struct SettleExpiredMarketPoolsToRevenuePool<'info> {
 pub struct DepositIntoMarketFeePool<'info> {
     #[account(
         mut,
-        has_one = admin
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::Treasurer)?
     )]
     pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
     #[account(mut)]
     pub perp_market: AccountLoader<'info, PerpMarket>,
     pub admin: Signer<'info>,
@@ -2360,14 +6015,93 @@ pub struct AdminUpdateSpotMarketOracle<'info> {
     pub oracle: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializeInsuranceFundShareMint<'info> {
+    #[account(
+        seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+    pub spot_market_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        seeds = [b"insurance_fund_share_mint".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+        payer = admin,
+        mint::decimals = spot_market_mint.decimals,
+        mint::authority = drift_signer
+    )]
+    pub insurance_fund_share_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        seeds = [b"wrapped_insurance_fund_stake", market_index.to_le_bytes().as_ref()],
+        space = InsuranceFundStake::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub wrapped_insurance_fund_stake: AccountLoader<'info, InsuranceFundStake>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: program signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeGovernanceStakeVault<'info> {
+    #[account(
+        address = state.discount_mint
+    )]
+    pub governance_token_mint: Box<Account<'info, Mint>>,
+    #[account(
+        init,
+        seeds = [b"governance_stake_vault".as_ref()],
+        bump,
+        payer = admin,
+        token::mint = governance_token_mint,
+        token::authority = drift_signer
+    )]
+    pub governance_stake_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: program signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
 #[derive(Accounts)]
 #[instruction(market_index: u16,)]
 pub struct AdminRemoveInsuranceFundStake<'info> {
     pub admin: Signer<'info>,
     #[account(
-        has_one = admin
+        constraint = can_sign_for_admin_role(&state, &admin_roles, &admin, AdminRole::Treasurer)?
     )]
     pub state: Box<Account<'info, State>>,
+    /// CHECK: only loaded when the signer isn't the admin, the admin can sign before roles are initialized
+    #[account(
+        seeds = [b"admin_roles".as_ref()],
+        bump,
+    )]
+    pub admin_roles: UncheckedAccount<'info>,
     #[account(
         seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
         bump
@@ -1,21 +1,49 @@
 use anchor_lang::accounts::account::Account;
 use anchor_lang::accounts::account_loader::AccountLoader;
 use anchor_lang::accounts::signer::Signer;
-use anchor_lang::prelude::{AccountInfo, Pubkey};
+use anchor_lang::prelude::{AccountInfo, Clock, Pubkey, SolanaSysvar};
 
 use crate::error::ErrorCode;
+use crate::state::fuel::UserFuel;
 use crate::state::perp_market::{MarketStatus, PerpMarket};
 use crate::state::spot_market::SpotMarket;
-use crate::state::state::{ExchangeStatus, State};
-use crate::state::user::{User, UserStats};
+use crate::state::state::{AdminRole, AdminRoles, ExchangeStatus, State};
+use crate::state::user::{DelegatePermission, User, UserStats};
 use crate::validate;
 use solana_program::msg;
 
 pub fn can_sign_for_user(user: &AccountLoader<User>, signer: &Signer) -> anchor_lang::Result<bool> {
-    user.load().map(|user| {
-        user.authority.eq(signer.key)
-            || (user.delegate.eq(signer.key) && !user.delegate.eq(&Pubkey::default()))
-    })
+    let slot = Clock::get()?.slot;
+    user.load()
+        .map(|user| user.authority.eq(signer.key) || user.is_active_delegate(signer.key, slot))
+}
+
+pub fn can_sign_for_user_with_permission(
+    user: &AccountLoader<User>,
+    signer: &Signer,
+    permission: DelegatePermission,
+) -> anchor_lang::Result<bool> {
+    let slot = Clock::get()?.slot;
+    let user = user.load()?;
+    Ok(user.authority.eq(signer.key)
+        || user.delegate_has_permission(signer.key, permission, slot)?)
+}
+
+/// the admin can sign for every role, a role key only for its own role. admin_roles is only
+/// loaded for role keys, so the admin can sign before it is initialized
+pub fn can_sign_for_admin_role<'info>(
+    state: &State,
+    admin_roles: &AccountInfo<'info>,
+    signer: &Signer,
+    role: AdminRole,
+) -> anchor_lang::Result<bool> {
+    if state.admin.eq(signer.key) {
+        return Ok(true);
+    }
+
+    let admin_roles: AccountLoader<AdminRoles> = AccountLoader::try_from(admin_roles)?;
+    let role_key = admin_roles.load()?.get_role(role);
+    Ok(role_key != Pubkey::default() && role_key.eq(signer.key))
 }
 
 pub fn is_stats_for_user(
@@ -27,6 +55,15 @@ pub fn is_stats_for_user(
     Ok(user_stats.authority.eq(&user.authority))
 }
 
+pub fn is_stats_for_user_fuel(
+    user_fuel: &AccountLoader<UserFuel>,
+    user_stats: &AccountLoader<UserStats>,
+) -> anchor_lang::Result<bool> {
+    let user_fuel = user_fuel.load()?;
+    let user_stats = user_stats.load()?;
+    Ok(user_stats.authority.eq(&user_fuel.authority))
+}
+
 pub fn perp_market_valid(market: &AccountLoader<PerpMarket>) -> anchor_lang::Result<()> {
     if market.load()?.status == MarketStatus::Delisted {
         return Err(ErrorCode::MarketDelisted.into());
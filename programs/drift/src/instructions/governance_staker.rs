@@ -0,0 +1,199 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::controller;
+use crate::error::ErrorCode;
+use crate::math::safe_math::SafeMath;
+use crate::state::governance_stake::GovernanceStake;
+use crate::state::state::State;
+use crate::state::traits::Size;
+use crate::state::user::UserStats;
+use crate::{load_mut, validate};
+
+pub fn handle_initialize_governance_stake(ctx: Context<InitializeGovernanceStake>) -> Result<()> {
+    let mut governance_stake = ctx
+        .accounts
+        .governance_stake
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    governance_stake.authority = *ctx.accounts.authority.key;
+
+    Ok(())
+}
+
+pub fn handle_add_governance_stake(ctx: Context<AddGovernanceStake>, amount: u64) -> Result<()> {
+    if amount == 0 {
+        return Err(ErrorCode::InsufficientDeposit.into());
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    let governance_stake = &mut load_mut!(ctx.accounts.governance_stake)?;
+    let user_stats = &mut load_mut!(ctx.accounts.user_stats)?;
+
+    governance_stake.staked_amount = governance_stake.staked_amount.safe_add(amount)?;
+    governance_stake.last_stake_ts = now;
+
+    update_governance_stake_tier(
+        governance_stake,
+        user_stats,
+        ctx.accounts.governance_token_mint.decimals,
+    )?;
+
+    controller::token::receive(
+        &ctx.accounts.token_program,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.governance_stake_vault,
+        &ctx.accounts.authority,
+        amount,
+    )?;
+
+    Ok(())
+}
+
+pub fn handle_remove_governance_stake(
+    ctx: Context<RemoveGovernanceStake>,
+    amount: u64,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let governance_stake = &mut load_mut!(ctx.accounts.governance_stake)?;
+    let user_stats = &mut load_mut!(ctx.accounts.user_stats)?;
+    let state = &ctx.accounts.state;
+
+    governance_stake.validate_can_unstake(now)?;
+
+    validate!(
+        amount != 0 && amount <= governance_stake.staked_amount,
+        ErrorCode::InvalidGovernanceStake,
+        "amount {} must be between 1 and staked amount {}",
+        amount,
+        governance_stake.staked_amount
+    )?;
+
+    governance_stake.staked_amount = governance_stake.staked_amount.safe_sub(amount)?;
+
+    update_governance_stake_tier(
+        governance_stake,
+        user_stats,
+        ctx.accounts.governance_token_mint.decimals,
+    )?;
+
+    controller::token::send_from_program_vault(
+        &ctx.accounts.token_program,
+        &ctx.accounts.governance_stake_vault,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.drift_signer,
+        state.signer_nonce,
+        amount,
+    )?;
+
+    Ok(())
+}
+
+fn update_governance_stake_tier(
+    governance_stake: &GovernanceStake,
+    user_stats: &mut UserStats,
+    decimals: u8,
+) -> Result<()> {
+    let governance_stake_tier = governance_stake.stake_tier(decimals as u32)?;
+
+    msg!(
+        "user_stats.governance_stake_tier: {:?} -> {:?}",
+        user_stats.governance_stake_tier,
+        governance_stake_tier
+    );
+
+    user_stats.governance_stake_tier = governance_stake_tier;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeGovernanceStake<'info> {
+    #[account(
+        init,
+        seeds = [b"governance_stake", authority.key.as_ref()],
+        space = GovernanceStake::SIZE,
+        bump,
+        payer = payer
+    )]
+    pub governance_stake: AccountLoader<'info, GovernanceStake>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddGovernanceStake<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub governance_stake: AccountLoader<'info, GovernanceStake>,
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub authority: Signer<'info>,
+    #[account(
+        address = state.discount_mint
+    )]
+    pub governance_token_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"governance_stake_vault".as_ref()],
+        bump,
+        token::mint = governance_token_mint
+    )]
+    pub governance_stake_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = governance_stake_vault.mint,
+        token::authority = authority
+    )]
+    pub user_token_account: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveGovernanceStake<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub governance_stake: AccountLoader<'info, GovernanceStake>,
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub authority: Signer<'info>,
+    #[account(
+        address = state.discount_mint
+    )]
+    pub governance_token_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [b"governance_stake_vault".as_ref()],
+        bump,
+        token::mint = governance_token_mint
+    )]
+    pub governance_stake_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: forced drift_signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(
+        mut,
+        token::mint = governance_stake_vault.mint,
+        token::authority = authority
+    )]
+    pub user_token_account: Box<Account<'info, TokenAccount>>,
+    pub token_program: Program<'info, Token>,
+}
@@ -1,10 +1,10 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
 use crate::error::ErrorCode;
 use crate::instructions::constraints::*;
 use crate::load_mut;
-use crate::state::insurance_fund_stake::InsuranceFundStake;
+use crate::state::insurance_fund_stake::{InsuranceFundStake, InsuranceFundStakeLockupTier};
 use crate::state::spot_market::SpotMarket;
 use crate::state::state::State;
 use crate::state::traits::Size;
@@ -180,6 +180,136 @@ pub fn handle_cancel_request_remove_insurance_fund_stake(
     Ok(())
 }
 
+pub fn handle_update_insurance_fund_stake_lockup_tier(
+    ctx: Context<RequestRemoveInsuranceFundStake>,
+    market_index: u16,
+    lockup_tier: InsuranceFundStakeLockupTier,
+) -> Result<()> {
+    let insurance_fund_stake = &mut load_mut!(ctx.accounts.insurance_fund_stake)?;
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+
+    validate!(
+        insurance_fund_stake.market_index == market_index,
+        ErrorCode::IncorrectSpotMarketAccountPassed,
+        "insurance_fund_stake does not match market_index"
+    )?;
+
+    validate!(
+        insurance_fund_stake.last_withdraw_request_shares == 0,
+        ErrorCode::IFWithdrawRequestInProgress,
+        "Withdraw request is in progress"
+    )?;
+
+    controller::insurance::update_insurance_fund_stake_lockup_tier(
+        lockup_tier,
+        ctx.accounts.insurance_fund_vault.amount,
+        insurance_fund_stake,
+        spot_market,
+    )?;
+
+    Ok(())
+}
+
+pub fn handle_wrap_insurance_fund_stake(
+    ctx: Context<WrapInsuranceFundStake>,
+    market_index: u16,
+    amount: u64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let insurance_fund_stake = &mut load_mut!(ctx.accounts.insurance_fund_stake)?;
+    let wrapped_insurance_fund_stake = &mut load_mut!(ctx.accounts.wrapped_insurance_fund_stake)?;
+    let user_stats = &mut load_mut!(ctx.accounts.user_stats)?;
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    let state = &ctx.accounts.state;
+
+    validate!(
+        insurance_fund_stake.market_index == market_index,
+        ErrorCode::IncorrectSpotMarketAccountPassed,
+        "insurance_fund_stake does not match market_index"
+    )?;
+
+    validate!(
+        insurance_fund_stake.last_withdraw_request_shares == 0,
+        ErrorCode::IFWithdrawRequestInProgress,
+        "Withdraw request is in progress"
+    )?;
+
+    let n_shares = math::insurance::vault_amount_to_if_shares(
+        amount,
+        spot_market.insurance_fund.total_shares,
+        ctx.accounts.insurance_fund_vault.amount,
+    )?;
+
+    let token_amount = controller::insurance::wrap_insurance_fund_stake(
+        n_shares,
+        ctx.accounts.insurance_fund_vault.amount,
+        insurance_fund_stake,
+        wrapped_insurance_fund_stake,
+        user_stats,
+        spot_market,
+        ctx.accounts.insurance_fund_share_mint.supply,
+        now,
+    )?;
+
+    controller::token::mint_from_program(
+        &ctx.accounts.token_program,
+        &ctx.accounts.insurance_fund_share_mint,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.drift_signer,
+        state.signer_nonce,
+        token_amount,
+    )?;
+
+    Ok(())
+}
+
+pub fn handle_unwrap_insurance_fund_stake(
+    ctx: Context<WrapInsuranceFundStake>,
+    market_index: u16,
+    token_amount: u64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let insurance_fund_stake = &mut load_mut!(ctx.accounts.insurance_fund_stake)?;
+    let wrapped_insurance_fund_stake = &mut load_mut!(ctx.accounts.wrapped_insurance_fund_stake)?;
+    let user_stats = &mut load_mut!(ctx.accounts.user_stats)?;
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+
+    validate!(
+        insurance_fund_stake.market_index == market_index,
+        ErrorCode::IncorrectSpotMarketAccountPassed,
+        "insurance_fund_stake does not match market_index"
+    )?;
+
+    validate!(
+        insurance_fund_stake.last_withdraw_request_shares == 0,
+        ErrorCode::IFWithdrawRequestInProgress,
+        "Withdraw request is in progress"
+    )?;
+
+    controller::insurance::unwrap_insurance_fund_stake(
+        token_amount,
+        ctx.accounts.insurance_fund_vault.amount,
+        insurance_fund_stake,
+        wrapped_insurance_fund_stake,
+        user_stats,
+        spot_market,
+        ctx.accounts.insurance_fund_share_mint.supply,
+        now,
+    )?;
+
+    controller::token::burn(
+        &ctx.accounts.token_program,
+        &ctx.accounts.insurance_fund_share_mint,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.authority,
+        token_amount,
+    )?;
+
+    Ok(())
+}
+
 #[access_control(
     withdraw_not_paused(&ctx.accounts.state)
 )]
@@ -265,6 +395,7 @@ pub struct InitializeInsuranceFundStake<'info> {
 pub struct AddInsuranceFundStake<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
+        mut,
         seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
         bump
     )]
@@ -311,6 +442,7 @@ pub struct AddInsuranceFundStake<'info> {
 #[instruction(market_index: u16,)]
 pub struct RequestRemoveInsuranceFundStake<'info> {
     #[account(
+        mut,
         seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
         bump
     )]
@@ -339,6 +471,7 @@ pub struct RequestRemoveInsuranceFundStake<'info> {
 pub struct RemoveInsuranceFundStake<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
+        mut,
         seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
         bump
     )]
@@ -373,3 +506,55 @@ pub struct RemoveInsuranceFundStake<'info> {
     pub user_token_account: Box<Account<'info, TokenAccount>>,
     pub token_program: Program<'info, Token>,
 }
+
+#[derive(Accounts)]
+#[instruction(market_index: u16,)]
+pub struct WrapInsuranceFundStake<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub insurance_fund_stake: AccountLoader<'info, InsuranceFundStake>,
+    #[account(
+        mut,
+        seeds = [b"wrapped_insurance_fund_stake", market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub wrapped_insurance_fund_stake: AccountLoader<'info, InsuranceFundStake>,
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"insurance_fund_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub insurance_fund_vault: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"insurance_fund_share_mint".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub insurance_fund_share_mint: Box<Account<'info, Mint>>,
+    #[account(
+        mut,
+        token::mint = insurance_fund_share_mint,
+        token::authority = authority
+    )]
+    pub user_token_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: forced drift_signer
+    pub drift_signer: AccountInfo<'info>,
+    pub token_program: Program<'info, Token>,
+}
@@ -1,32 +1,70 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount};
+use bytemuck::allocation::zeroed_box;
+use solana_program::sysvar;
+use solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+
+use crate::controller::orders::PlaceOrderOptions;
+use crate::controller::position::PositionDirection;
 
 use crate::error::ErrorCode;
+use crate::get_then_update_id;
 use crate::instructions::constraints::*;
 use crate::instructions::optional_accounts::{
-    get_maker_and_maker_stats, get_referrer_and_referrer_stats, get_serum_fulfillment_accounts,
+    copy_account, get_fill_callback_accounts, get_maker_and_maker_stats, get_market_fee_override,
+    get_market_whitelist_entries, get_market_whitelist_entry, get_openbook_v2_fulfillment_accounts,
+    get_perp_market_delisting, get_referrer_and_referrer_stats, get_serum_fulfillment_accounts,
     get_spot_market_vaults, load_maps, AccountMaps,
 };
+use crate::instructions::OrderParams;
 use crate::load_mut;
+use crate::math::casting::Cast;
 use crate::math::constants::QUOTE_SPOT_MARKET_INDEX;
+use crate::math::funding::calculate_predicted_funding;
 use crate::math::insurance::if_shares_to_vault_amount;
+use crate::math::margin::{
+    calculate_and_update_perp_position_liquidation_prices, calculate_health,
+    calculate_margin_contributions, calculate_margin_health,
+    calculate_margin_requirement_and_total_collateral, meets_initial_margin_requirement,
+    MarginRequirementType,
+};
+use crate::math::oracle::{is_oracle_valid_for_action, oracle_validity, DriftAction};
+use crate::math::order_book::{calculate_order_book_snapshot, MAX_ORDER_BOOK_SNAPSHOT_DEPTH};
+use crate::math::orders::calculate_fill_receipt;
+use crate::math::safe_math::SafeMath;
 use crate::math::spot_withdraw::validate_spot_market_vault_amount;
+use crate::print_error;
+use crate::state::backstop_provider::BackstopProvider;
+use crate::state::events::{CurveRecord, LPAction, MarginWarningRecord, UserRiskRecord};
+use crate::state::fuel::UserFuel;
+use crate::state::fuel_map::load_fuel_map;
 use crate::state::insurance_fund_stake::InsuranceFundStake;
+use crate::state::lst_oracle::{get_redemption_rate, LstOracle};
+use crate::state::market_lookup_table::MarketLookupTable;
+use crate::state::market_whitelist::{find_market_whitelist_entry, validate_market_whitelist};
+use crate::state::oracle::get_oracle_price;
 use crate::state::oracle_map::OracleMap;
-use crate::state::perp_market::{MarketStatus, PerpMarket};
+use crate::state::perp_lp_range::PerpLpRange;
+use crate::state::perp_market::{ContractType, MarketStatus, PerpMarket, PerpOperation};
+use crate::state::perp_market_delisting::PerpMarketDelisting;
 use crate::state::perp_market_map::{
     get_market_set_for_user_positions, get_market_set_from_list, get_writable_perp_market_set,
     MarketSet, PerpMarketMap,
 };
+use crate::state::pnl_pool_rebalance_config::PnlPoolRebalanceConfig;
+use crate::state::signed_order::SignedOrderNonce;
 use crate::state::spot_market::SpotMarket;
 use crate::state::spot_market_map::{
     get_writable_spot_market_set, get_writable_spot_market_set_from_many,
 };
 use crate::state::state::State;
-use crate::state::user::{MarketType, User, UserStats};
-use crate::state::user_map::load_user_maps;
+use crate::state::user::{DelegatePermission, MarketType, User, UserStats};
+use crate::state::user_map::{load_user_map, load_user_maps};
 use crate::validate;
-use crate::validation::user::validate_user_is_idle;
+use crate::validation::sig_verification::verify_ed25519_ix;
+use crate::validation::user::{validate_trading_not_disabled, validate_user_is_idle};
 use crate::{controller, load, math};
 
 #[access_control(
@@ -78,7 +116,413 @@ fn fill_order(ctx: Context<FillOrder>, order_id: u32, market_index: u16) -> Resu
         Some(state.oracle_guard_rails),
     )?;
 
+    let market_whitelist_entries = get_market_whitelist_entries(remaining_accounts_iter)?;
+    let market_fee_override = get_market_fee_override(remaining_accounts_iter)?;
+    let fill_callback_accounts =
+        get_fill_callback_accounts(remaining_accounts_iter, &ctx.accounts.user.key())?;
     let (makers_and_referrer, makers_and_referrer_stats) = load_user_maps(remaining_accounts_iter)?;
+    let fuel_map = load_fuel_map(remaining_accounts_iter)?;
+
+    validate!(
+        fill_callback_accounts.is_some() || !load!(ctx.accounts.user)?.has_fill_callback,
+        ErrorCode::InvalidFillCallback,
+        "user {} has a fill callback but its accounts were not passed",
+        ctx.accounts.user.key()
+    )?;
+
+    // the whitelist can be revoked after the order was placed
+    {
+        let market = perp_market_map.get_ref(&market_index)?;
+        let authority = load!(ctx.accounts.user)?.authority;
+        validate_market_whitelist(
+            market.amm.whitelist_enabled,
+            market_index,
+            &authority,
+            find_market_whitelist_entry(&market_whitelist_entries, market_index, &authority),
+        )?;
+    }
+
+    controller::repeg::update_amm(
+        market_index,
+        &perp_market_map,
+        &mut oracle_map,
+        &ctx.accounts.state,
+        clock,
+    )?;
+
+    let base_asset_amount_unfilled_before = match load!(ctx.accounts.user)?.get_order(order_id) {
+        Some(order) => order.get_base_asset_amount_unfilled(None)?,
+        None => 0,
+    };
+    let fees_before = load!(ctx.accounts.user_stats)?.fees;
+
+    let (base_asset_amount_filled, quote_asset_amount_filled, _) =
+        controller::orders::fill_perp_order(
+            order_id,
+            &ctx.accounts.state,
+            &ctx.accounts.user,
+            &ctx.accounts.user_stats,
+            &spot_market_map,
+            &perp_market_map,
+            &mut oracle_map,
+            &ctx.accounts.filler,
+            &ctx.accounts.filler_stats,
+            &makers_and_referrer,
+            &makers_and_referrer_stats,
+            None,
+            clock,
+            &fuel_map,
+            market_fee_override.as_ref(),
+            &market_whitelist_entries,
+        )?;
+
+    let fill_receipt = {
+        let user = load!(ctx.accounts.user)?;
+        let fees = load!(ctx.accounts.user_stats)?.fees;
+        let fee_paid = fees
+            .total_fee_paid
+            .safe_sub(fees_before.total_fee_paid)?
+            .cast::<i64>()?
+            .safe_sub(
+                fees.total_fee_rebate
+                    .safe_sub(fees_before.total_fee_rebate)?
+                    .cast()?,
+            )?;
+
+        calculate_fill_receipt(
+            order_id,
+            base_asset_amount_unfilled_before,
+            user.get_order(order_id),
+            base_asset_amount_filled,
+            quote_asset_amount_filled,
+            fee_paid,
+        )?
+    };
+
+    controller::fill_callback::invoke_fill_callback_and_set_receipt(
+        fill_callback_accounts,
+        &ctx.accounts.user.to_account_info(),
+        market_index,
+        fill_receipt,
+    )?;
+
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct SignedOrderMessage {
+    pub order_params: OrderParams,
+    pub sub_account_id: u16,
+    pub nonce: u64,
+    pub expiry_ts: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct RfqTakerRequest {
+    pub sub_account_id: u16,
+    pub market_index: u16,
+    pub direction: PositionDirection,
+    pub base_asset_amount: u64,
+    /// worst price the taker accepts, as an offset from the oracle price
+    pub oracle_price_offset: i32,
+    pub reduce_only: bool,
+    pub nonce: u64,
+    pub expiry_ts: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct RfqMakerQuote {
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub market_index: u16,
+    pub direction: PositionDirection,
+    pub base_asset_amount: u64,
+    pub price: u64,
+    pub nonce: u64,
+    pub expiry_ts: i64,
+}
+
+#[access_control(
+    exchange_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_place_signed_order(
+    ctx: Context<PlaceSignedOrder>,
+    message: SignedOrderMessage,
+) -> Result<()> {
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    {
+        let user = load!(ctx.accounts.user)?;
+        validate!(
+            message.sub_account_id == user.sub_account_id,
+            ErrorCode::SigVerificationFailed,
+            "message signed for sub account {}, user is sub account {}",
+            message.sub_account_id,
+            user.sub_account_id
+        )?;
+
+        // the taker's signature is checked by the ed25519 instruction right before this one
+        let ix_sysvar = &ctx.accounts.ix_sysvar;
+        let current_index = load_current_index_checked(ix_sysvar)?;
+        validate!(
+            current_index > 0,
+            ErrorCode::SigVerificationFailed,
+            "place_signed_order must follow an ed25519 verification instruction"
+        )?;
+        let ed25519_ix = load_instruction_at_checked((current_index - 1) as usize, ix_sysvar)?;
+
+        let message_data = message
+            .try_to_vec()
+            .map_err(|_| ErrorCode::SigVerificationFailed)?;
+        verify_ed25519_ix(&ed25519_ix, &user.authority, &message_data)?;
+    }
+
+    validate!(
+        clock.unix_timestamp <= message.expiry_ts,
+        ErrorCode::SignedOrderExpired,
+        "signed order expired at {}, now {}",
+        message.expiry_ts,
+        clock.unix_timestamp
+    )?;
+
+    load_mut!(ctx.accounts.signed_order_nonce)?.use_nonce(message.nonce)?;
+
+    let order_params = message.order_params;
+    if order_params.immediate_or_cancel {
+        msg!("immediate_or_cancel order must be in place_and_make or place_and_take");
+        return Err(print_error!(ErrorCode::InvalidOrderIOC)().into());
+    }
+
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        order_params.reduce_only,
+        clock.unix_timestamp,
+    )?;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+    let market_whitelist_entry = get_market_whitelist_entry(remaining_accounts_iter)?;
+
+    match order_params.market_type {
+        MarketType::Perp => controller::orders::place_perp_order(
+            state,
+            &ctx.accounts.user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            clock,
+            order_params,
+            &mut PlaceOrderOptions {
+                market_whitelist_entry,
+                ..PlaceOrderOptions::default()
+            },
+        )?,
+        MarketType::Spot => controller::orders::place_spot_order(
+            state,
+            &ctx.accounts.user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            clock,
+            order_params,
+            &mut PlaceOrderOptions::default(),
+        )?,
+    }
+
+    Ok(())
+}
+
+#[access_control(
+    fill_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_fill_rfq(
+    ctx: Context<FillRfq>,
+    taker_request: RfqTakerRequest,
+    maker_quotes: Vec<RfqMakerQuote>,
+) -> Result<()> {
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
+    let now = clock.unix_timestamp;
+
+    // the ed25519 instructions right before this one verify the taker request then each maker quote
+    let ix_sysvar = &ctx.accounts.ix_sysvar;
+    let current_index = load_current_index_checked(ix_sysvar)? as usize;
+    validate!(
+        current_index > maker_quotes.len(),
+        ErrorCode::SigVerificationFailed,
+        "fill_rfq must follow an ed25519 verification instruction for the request and each quote"
+    )?;
+    let first_sig_index = current_index - maker_quotes.len() - 1;
+
+    {
+        let taker = load!(ctx.accounts.taker)?;
+        validate!(
+            taker_request.sub_account_id == taker.sub_account_id,
+            ErrorCode::SigVerificationFailed,
+            "request signed for sub account {}, taker is sub account {}",
+            taker_request.sub_account_id,
+            taker.sub_account_id
+        )?;
+
+        let ed25519_ix = load_instruction_at_checked(first_sig_index, ix_sysvar)?;
+        let message_data = taker_request
+            .try_to_vec()
+            .map_err(|_| ErrorCode::SigVerificationFailed)?;
+        verify_ed25519_ix(&ed25519_ix, &taker.authority, &message_data)?;
+    }
+
+    validate!(
+        now <= taker_request.expiry_ts,
+        ErrorCode::SignedOrderExpired,
+        "rfq request expired at {}, now {}",
+        taker_request.expiry_ts,
+        now
+    )?;
+
+    load_mut!(ctx.accounts.taker_signed_order_nonce)?.use_nonce(taker_request.nonce)?;
+
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.taker_stats)?,
+        taker_request.reduce_only,
+        now,
+    )?;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(taker_request.market_index),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let market_fee_override = get_market_fee_override(remaining_accounts_iter)?;
+    let (makers, makers_stats) = load_user_maps(remaining_accounts_iter)?;
+
+    // each quote's signed order nonce account follows the makers, in quote order
+    for (i, quote) in maker_quotes.iter().enumerate() {
+        let maker = makers.get_ref(&quote.maker)?;
+        let ed25519_ix = load_instruction_at_checked(first_sig_index + i + 1, ix_sysvar)?;
+        let message_data = quote
+            .try_to_vec()
+            .map_err(|_| ErrorCode::SigVerificationFailed)?;
+        verify_ed25519_ix(&ed25519_ix, &maker.authority, &message_data)?;
+
+        validate_trading_not_disabled(&makers_stats.get_ref(&maker.authority)?, false, now)?;
+
+        validate!(
+            now <= quote.expiry_ts,
+            ErrorCode::SignedOrderExpired,
+            "quote from maker {} expired at {}, now {}",
+            quote.maker,
+            quote.expiry_ts,
+            now
+        )?;
+
+        let nonce_account_info = remaining_accounts_iter
+            .next()
+            .ok_or(ErrorCode::InvalidSignedOrderNonce)?;
+        let nonce_loader: AccountLoader<SignedOrderNonce> =
+            AccountLoader::try_from(nonce_account_info)
+                .or(Err(ErrorCode::InvalidSignedOrderNonce))?;
+        let mut nonce = load_mut!(nonce_loader)?;
+        validate!(
+            nonce.user == quote.maker,
+            ErrorCode::InvalidSignedOrderNonce,
+            "nonce account is for user {}, quote is from maker {}",
+            nonce.user,
+            quote.maker
+        )?;
+        nonce.use_nonce(quote.nonce)?;
+    }
+
+    controller::repeg::update_amm(
+        taker_request.market_index,
+        &perp_market_map,
+        &mut oracle_map,
+        state,
+        clock,
+    )?;
+
+    controller::orders::fill_rfq(
+        state,
+        &ctx.accounts.taker,
+        &ctx.accounts.taker_stats,
+        &taker_request,
+        &maker_quotes,
+        &makers,
+        &makers_stats,
+        &ctx.accounts.filler,
+        &ctx.accounts.filler_stats,
+        &spot_market_map,
+        &perp_market_map,
+        &mut oracle_map,
+        clock,
+        market_fee_override.as_ref(),
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    fill_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_fill_twap_order<'info>(ctx: Context<FillOrder>, order_id: u32) -> Result<()> {
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let market_index = {
+        let user = &load!(ctx.accounts.user)?;
+        match user.get_order(order_id) {
+            Some(order) => order.market_index,
+            None => {
+                msg!("Order does not exist {}", order_id);
+                return Ok(());
+            }
+        }
+    };
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(market_index),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let market_whitelist_entry = get_market_whitelist_entry(remaining_accounts_iter)?;
+    let market_fee_override = get_market_fee_override(remaining_accounts_iter)?;
+
+    // the whitelist can be revoked after the order was placed
+    {
+        let market = perp_market_map.get_ref(&market_index)?;
+        validate_market_whitelist(
+            market.amm.whitelist_enabled,
+            market_index,
+            &load!(ctx.accounts.user)?.authority,
+            market_whitelist_entry.as_ref(),
+        )?;
+    }
 
     controller::repeg::update_amm(
         market_index,
@@ -88,7 +532,7 @@ fn fill_order(ctx: Context<FillOrder>, order_id: u32, market_index: u16) -> Resu
         clock,
     )?;
 
-    controller::orders::fill_perp_order(
+    controller::orders::fill_twap_order(
         order_id,
         &ctx.accounts.state,
         &ctx.accounts.user,
@@ -98,10 +542,8 @@ fn fill_order(ctx: Context<FillOrder>, order_id: u32, market_index: u16) -> Resu
         &mut oracle_map,
         &ctx.accounts.filler,
         &ctx.accounts.filler_stats,
-        &makers_and_referrer,
-        &makers_and_referrer_stats,
-        None,
         clock,
+        market_fee_override.as_ref(),
     )?;
 
     Ok(())
@@ -129,6 +571,8 @@ pub fn handle_revert_fill<'info>(ctx: Context<RevertFill>) -> Result<()> {
 pub enum SpotFulfillmentType {
     SerumV3,
     None,
+    OpenbookV2,
+    PhoenixV1,
 }
 
 impl Default for SpotFulfillmentType {
@@ -194,6 +638,8 @@ fn fill_spot_order(
         None,
     )?;
 
+    let market_fee_override = get_market_fee_override(remaining_accounts_iter)?;
+
     let (maker, maker_stats) = match maker_order_id {
         Some(_) => {
             let (user, user_stats) = get_maker_and_maker_stats(remaining_accounts_iter)?;
@@ -218,6 +664,34 @@ fn fill_spot_order(
         _ => None,
     };
 
+    let mut openbook_v2_fulfillment_params = match fulfillment_type {
+        Some(SpotFulfillmentType::OpenbookV2) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_openbook_v2_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    let mut phoenix_fulfillment_params = match fulfillment_type {
+        Some(SpotFulfillmentType::PhoenixV1) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_phoenix_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
     controller::orders::fill_spot_order(
         order_id,
         &ctx.accounts.state,
@@ -233,10 +707,17 @@ fn fill_spot_order(
         maker_order_id,
         &Clock::get()?,
         &mut serum_fulfillment_params,
+        &mut openbook_v2_fulfillment_params,
+        &mut phoenix_fulfillment_params,
+        market_fee_override.as_ref(),
     )?;
 
-    match serum_fulfillment_params {
-        Some(serum_fulfillment_params) => {
+    match (
+        serum_fulfillment_params,
+        openbook_v2_fulfillment_params,
+        phoenix_fulfillment_params,
+    ) {
+        (Some(serum_fulfillment_params), _, _) => {
             let base_market = spot_market_map.get_ref(&market_index)?;
             validate_spot_market_vault_amount(
                 &base_market,
@@ -248,7 +729,31 @@ fn fill_spot_order(
                 serum_fulfillment_params.quote_market_vault.amount,
             )?;
         }
-        None => {
+        (None, Some(openbook_v2_fulfillment_params), _) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                openbook_v2_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                openbook_v2_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, None, Some(phoenix_fulfillment_params)) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                phoenix_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                phoenix_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, None, None) => {
             let base_market = spot_market_map.get_ref(&market_index)?;
             let quote_market = spot_market_map.get_quote_spot_market()?;
             let (base_market_vault, quote_market_vault) =
@@ -343,18 +848,171 @@ pub fn handle_force_cancel_orders<'info>(ctx: Context<ForceCancelOrder>) -> Resu
 #[access_control(
     exchange_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_update_user_idle<'info>(ctx: Context<UpdateUserIdle>) -> Result<()> {
-    let mut user = load_mut!(ctx.accounts.user)?;
-    let clock = Clock::get()?;
-
-    validate_user_is_idle(&user, clock.slot)?;
-
-    user.idle = true;
-
-    Ok(())
-}
-
-#[access_control(
+pub fn handle_cancel_stale_orders<'info>(ctx: Context<ForceCancelOrder>) -> Result<()> {
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &MarketSet::new(),
+        Clock::get()?.slot,
+        None,
+    )?;
+
+    controller::orders::cancel_stale_orders(
+        &ctx.accounts.state,
+        &ctx.accounts.user,
+        &spot_market_map,
+        &perp_market_map,
+        &mut oracle_map,
+        &ctx.accounts.filler,
+        &Clock::get()?,
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    exchange_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_update_user_idle<'info>(ctx: Context<UpdateUserIdle>) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+    let clock = Clock::get()?;
+
+    validate_user_is_idle(&user, clock.slot)?;
+
+    user.idle = true;
+
+    Ok(())
+}
+
+pub fn handle_log_user_risk(ctx: Context<LogUserRisk>) -> Result<()> {
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let user = load!(ctx.accounts.user)?;
+
+    let margin_health = calculate_margin_health(
+        &user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        None,
+    )?;
+
+    let (spot_contributions, perp_contributions) =
+        calculate_margin_contributions(&user, &perp_market_map, &spot_market_map, &mut oracle_map)?;
+
+    emit!(UserRiskRecord {
+        ts: clock.unix_timestamp,
+        slot: clock.slot,
+        user: ctx.accounts.user.key(),
+        margin_health,
+        spot_contributions,
+        perp_contributions,
+    });
+
+    Ok(())
+}
+
+pub fn handle_update_user_margin_warning(ctx: Context<UpdateUserMarginWarning>) -> Result<()> {
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    validate!(
+        state.margin_warning_health > 0,
+        ErrorCode::DefaultError,
+        "margin warnings are disabled"
+    )?;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let mut user = load_mut!(ctx.accounts.user)?;
+
+    let (margin_requirement, total_collateral, _, _) =
+        calculate_margin_requirement_and_total_collateral(
+            &user,
+            &perp_market_map,
+            MarginRequirementType::Maintenance,
+            &spot_market_map,
+            &mut oracle_map,
+            None,
+        )?;
+
+    let health = calculate_health(total_collateral, margin_requirement)?;
+
+    if user.update_margin_warning(health, state.margin_warning_health, clock.unix_timestamp)? {
+        emit!(MarginWarningRecord {
+            ts: clock.unix_timestamp,
+            user: ctx.accounts.user.key(),
+            margin_warning: user.margin_warning,
+            health,
+            total_collateral,
+            margin_requirement,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn handle_update_user_fuel(ctx: Context<UpdateUserFuel>) -> Result<()> {
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        spot_market_map,
+        mut oracle_map,
+        ..
+    } = load_maps(
+        remaining_accounts_iter,
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let users = load_user_map(remaining_accounts_iter)?;
+    let fuel_map = load_fuel_map(remaining_accounts_iter)?;
+
+    controller::fuel::update_user_fuel(
+        &mut load_mut!(ctx.accounts.user_fuel)?,
+        &load!(ctx.accounts.user_stats)?,
+        &users,
+        &spot_market_map,
+        &mut oracle_map,
+        &fuel_map,
+        clock.unix_timestamp,
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
     settle_pnl_not_paused(&ctx.accounts.state)
 )]
 pub fn handle_settle_pnl(ctx: Context<SettlePNL>, market_index: u16) -> Result<()> {
@@ -405,10 +1063,21 @@ pub fn handle_settle_pnl(ctx: Context<SettlePNL>, market_index: u16) -> Result<(
         )
         .map(|_| ErrorCode::InvalidOracleForSettlePnl)?;
 
+        // delegates with the settle permission settle on the authority's behalf
+        let authority = if user.delegate_has_permission(
+            ctx.accounts.authority.key,
+            DelegatePermission::Settle,
+            clock.slot,
+        )? {
+            user.authority
+        } else {
+            ctx.accounts.authority.key()
+        };
+
         controller::pnl::settle_pnl(
             market_index,
             user,
-            ctx.accounts.authority.key,
+            &authority,
             &user_key,
             &perp_market_map,
             &spot_market_map,
@@ -421,6 +1090,13 @@ pub fn handle_settle_pnl(ctx: Context<SettlePNL>, market_index: u16) -> Result<(
         user.update_last_active_slot(clock.slot);
     }
 
+    calculate_and_update_perp_position_liquidation_prices(
+        user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+    )?;
+
     let spot_market = spot_market_map.get_quote_spot_market()?;
     validate_spot_market_vault_amount(&spot_market, ctx.accounts.spot_market_vault.amount)?;
 
@@ -428,93 +1104,825 @@ pub fn handle_settle_pnl(ctx: Context<SettlePNL>, market_index: u16) -> Result<(
 }
 
 #[access_control(
-    funding_not_paused(&ctx.accounts.state)
+    settle_pnl_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_settle_funding_payment(ctx: Context<SettleFunding>) -> Result<()> {
+pub fn handle_settle_funding_against_borrow(
+    ctx: Context<SettlePNL>,
+    market_index: u16,
+) -> Result<()> {
     let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
+    let state = &ctx.accounts.state;
 
     let user_key = ctx.accounts.user.key();
     let user = &mut load_mut!(ctx.accounts.user)?;
 
     let AccountMaps {
-        perp_market_map, ..
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
     } = load_maps(
         &mut ctx.remaining_accounts.iter().peekable(),
-        &get_market_set_for_user_positions(&user.perp_positions),
-        &MarketSet::new(),
+        &get_writable_perp_market_set(market_index),
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
         clock.slot,
-        None,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    controller::pnl::settle_funding_against_borrow(
+        market_index,
+        user,
+        &user_key,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        clock.unix_timestamp,
     )?;
 
-    controller::funding::settle_funding_payments(user, &user_key, &perp_market_map, now)?;
     user.update_last_active_slot(clock.slot);
+
+    calculate_and_update_perp_position_liquidation_prices(
+        user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+    )?;
+
+    let spot_market = spot_market_map.get_quote_spot_market()?;
+    validate_spot_market_vault_amount(&spot_market, ctx.accounts.spot_market_vault.amount)?;
+
     Ok(())
 }
 
 #[access_control(
-    amm_not_paused(&ctx.accounts.state)
+    settle_pnl_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_settle_lp<'info>(ctx: Context<SettleLP>, market_index: u16) -> Result<()> {
-    let user_key = ctx.accounts.user.key();
-    let user = &mut load_mut!(ctx.accounts.user)?;
-
-    let state = &ctx.accounts.state;
+pub fn handle_settle_pnl_batch(ctx: Context<SettlePnlBatch>, market_index: u16) -> Result<()> {
     let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
+    let state = &ctx.accounts.state;
 
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
-        perp_market_map, ..
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
     } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
+        remaining_accounts_iter,
         &get_writable_perp_market_set(market_index),
-        &MarketSet::new(),
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
 
-    let market = &mut perp_market_map.get_ref_mut(&market_index)?;
-    controller::lp::settle_funding_payment_then_lp(user, &user_key, market, now)?;
-    user.update_last_active_slot(clock.slot);
+    let user_map = load_user_map(remaining_accounts_iter)?;
+
+    validate!(
+        !user_map.0.is_empty(),
+        ErrorCode::UserNotFound,
+        "no users passed to settle pnl for"
+    )?;
+
+    validate!(
+        perp_market_map.get_ref(&market_index)?.status != MarketStatus::Settlement,
+        ErrorCode::InvalidMarketStatusToSettlePnl,
+        "perp market {} is in settlement, use settle_expired_perp_positions",
+        market_index
+    )?;
+
+    controller::repeg::update_amm(
+        market_index,
+        &perp_market_map,
+        &mut oracle_map,
+        state,
+        &clock,
+    )
+    .map(|_| ErrorCode::InvalidOracleForSettlePnl)?;
+
+    // settle_pnl can fail after touching the user, market and pools, so a failed user is
+    // rolled back to where it started instead of aborting the whole batch. the snapshots are too
+    // big for the stack and the heap is never freed, so they're allocated once and reused
+    let mut user_before = zeroed_box::<User>();
+    let mut perp_market_before = zeroed_box::<PerpMarket>();
+    let mut quote_spot_market_before = zeroed_box::<SpotMarket>();
+
+    // a rolled back user mustn't leave records behind. interest, funding and lp are settled
+    // ahead of the snapshots and stick, which leaves settle_pnl nothing to emit until the
+    // SettlePnlRecord after its last check
+    controller::spot_balance::update_spot_market_cumulative_interest(
+        &mut spot_market_map.get_quote_spot_market_mut()?,
+        None,
+        clock.unix_timestamp,
+    )?;
+
+    let mut users_settled: u32 = 0;
+    for user_key in user_map.0.keys() {
+        let user = &mut user_map.get_ref_mut(user_key)?;
+
+        if user.is_bankrupt() {
+            msg!("failed to settle pnl for user {}: UserBankrupt", user_key);
+            continue;
+        }
+
+        controller::lp::settle_funding_payment_then_lp(
+            user,
+            user_key,
+            &mut perp_market_map.get_ref_mut(&market_index)?,
+            clock.unix_timestamp,
+        )?;
+
+        copy_account(&mut *user_before, &**user);
+        copy_account(
+            &mut *perp_market_before,
+            &*perp_market_map.get_ref(&market_index)?,
+        );
+        copy_account(
+            &mut *quote_spot_market_before,
+            &*spot_market_map.get_quote_spot_market()?,
+        );
+
+        let authority = if user.delegate_has_permission(
+            ctx.accounts.authority.key,
+            DelegatePermission::Settle,
+            clock.slot,
+        )? {
+            user.authority
+        } else {
+            ctx.accounts.authority.key()
+        };
+
+        let result = controller::pnl::settle_pnl(
+            market_index,
+            user,
+            &authority,
+            user_key,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            clock.unix_timestamp,
+            state,
+        );
+
+        match result {
+            Ok(()) => {
+                user.update_last_active_slot(clock.slot);
+                calculate_and_update_perp_position_liquidation_prices(
+                    user,
+                    &perp_market_map,
+                    &spot_market_map,
+                    &mut oracle_map,
+                )?;
+                users_settled = users_settled.safe_add(1)?;
+            }
+            Err(error) => {
+                msg!("failed to settle pnl for user {}: {:?}", user_key, error);
+                copy_account(&mut **user, &*user_before);
+                copy_account(
+                    &mut *perp_market_map.get_ref_mut(&market_index)?,
+                    &*perp_market_before,
+                );
+                copy_account(
+                    &mut *spot_market_map.get_quote_spot_market_mut()?,
+                    &*quote_spot_market_before,
+                );
+            }
+        }
+    }
+
+    msg!(
+        "settled pnl for {}/{} users in perp market {}",
+        users_settled,
+        user_map.0.len(),
+        market_index
+    );
+
+    let spot_market = spot_market_map.get_quote_spot_market()?;
+    validate_spot_market_vault_amount(&spot_market, ctx.accounts.spot_market_vault.amount)?;
 
     Ok(())
 }
 
+/// releases pnl pool surplus above the configured buffer from each market into the quote revenue
+/// pool, then tops up markets whose pnl pool can't cover their users out of what was released
 #[access_control(
     settle_pnl_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_settle_expired_market(ctx: Context<UpdateAMM>, market_index: u16) -> Result<()> {
+pub fn handle_rebalance_pnl_pools(
+    ctx: Context<RebalancePnlPools>,
+    perp_market_indexes: Vec<u16>,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let state = &ctx.accounts.state;
+
+    let pnl_pool_rebalance_config = &mut load_mut!(ctx.accounts.pnl_pool_rebalance_config)?;
+    pnl_pool_rebalance_config.validate_rebalance(now)?;
+
+    let writable_perp_markets: MarketSet = perp_market_indexes.iter().copied().collect();
+
+    validate!(
+        !writable_perp_markets.is_empty(),
+        ErrorCode::PerpMarketNotFound,
+        "no perp markets passed to rebalance"
+    )?;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &writable_perp_markets,
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let quote_spot_market = &mut spot_market_map.get_quote_spot_market_mut()?;
+    controller::spot_balance::update_spot_market_cumulative_interest(quote_spot_market, None, now)?;
+
+    let mut oracle_prices = Vec::with_capacity(writable_perp_markets.len());
+    let mut amount_released: u128 = 0;
+    for market_index in writable_perp_markets.iter() {
+        let perp_market = &mut perp_market_map.get_ref_mut(market_index)?;
+
+        validate!(
+            perp_market.status == MarketStatus::Active,
+            ErrorCode::MarketActionPaused,
+            "perp market {} not active",
+            market_index
+        )?;
+
+        let (oracle_price_data, oracle_validity) = oracle_map.get_price_data_and_validity(
+            &perp_market.amm.oracle,
+            perp_market
+                .amm
+                .historical_oracle_data
+                .last_oracle_price_twap,
+            &perp_market.amm.validity_guard_rails_override,
+        )?;
+
+        validate!(
+            is_oracle_valid_for_action(oracle_validity, Some(DriftAction::SettlePnl))?,
+            ErrorCode::InvalidOracle,
+            "OracleValidity for perp marketIndex={} invalid for SettlePnl",
+            market_index
+        )?;
+
+        let oracle_price = oracle_price_data.price;
+
+        let released = controller::amm::release_pnl_pool_surplus(
+            perp_market,
+            quote_spot_market,
+            oracle_price,
+            pnl_pool_rebalance_config.surplus_buffer.cast()?,
+        )?;
+
+        if released > 0 {
+            msg!(
+                "released {} from perp market {} pnl pool",
+                released,
+                market_index
+            );
+        }
+
+        amount_released = amount_released.safe_add(released)?;
+        oracle_prices.push((*market_index, oracle_price));
+    }
+
+    // top ups only come out of what was released above so the revenue pool never pays net
+    let mut amount_topped_up: u128 = 0;
+    for (market_index, oracle_price) in oracle_prices {
+        let max_top_up = amount_released
+            .safe_sub(amount_topped_up)?
+            .min(pnl_pool_rebalance_config.max_top_up.cast()?);
+
+        if max_top_up == 0 {
+            break;
+        }
+
+        let perp_market = &mut perp_market_map.get_ref_mut(&market_index)?;
+        let topped_up = controller::amm::top_up_pnl_pool_deficit(
+            perp_market,
+            quote_spot_market,
+            oracle_price,
+            max_top_up,
+        )?;
+
+        if topped_up > 0 {
+            msg!(
+                "topped up perp market {} pnl pool by {}",
+                market_index,
+                topped_up
+            );
+        }
+
+        amount_topped_up = amount_topped_up.safe_add(topped_up)?;
+    }
+
+    pnl_pool_rebalance_config.last_rebalance_ts = now;
+
+    validate_spot_market_vault_amount(quote_spot_market, ctx.accounts.spot_market_vault.amount)?;
+
+    Ok(())
+}
+
+#[access_control(
+    funding_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_settle_funding_payment(ctx: Context<SettleFunding>) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let user_key = ctx.accounts.user.key();
+    let user = &mut load_mut!(ctx.accounts.user)?;
+
+    let AccountMaps {
+        perp_market_map, ..
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &get_market_set_for_user_positions(&user.perp_positions),
+        &MarketSet::new(),
+        clock.slot,
+        None,
+    )?;
+
+    controller::funding::settle_funding_payments(user, &user_key, &perp_market_map, now)?;
+    user.update_last_active_slot(clock.slot);
+    Ok(())
+}
+
+pub fn handle_get_predicted_funding_rate(ctx: Context<GetPredictedFundingRate>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let perp_market = load!(ctx.accounts.perp_market)?;
+
+    let predicted_funding = calculate_predicted_funding(&perp_market, now)?;
+
+    let predicted_funding_data = predicted_funding
+        .try_to_vec()
+        .map_err(|_| ErrorCode::DefaultError)?;
+    anchor_lang::solana_program::program::set_return_data(&predicted_funding_data);
+
+    Ok(())
+}
+
+pub fn handle_get_order_book_snapshot(
+    ctx: Context<GetOrderBookSnapshot>,
+    market_index: u16,
+    price_step: u64,
+    depth: u8,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        mut oracle_map,
+        ..
+    } = load_maps(
+        remaining_accounts_iter,
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        None,
+    )?;
+
+    let perp_market = perp_market_map.get_ref(&market_index)?;
+
+    validate!(
+        price_step > 0
+            && price_step % perp_market.amm.order_tick_size == 0
+            && depth > 0
+            && depth <= MAX_ORDER_BOOK_SNAPSHOT_DEPTH,
+        ErrorCode::InvalidOrderBookSnapshotParams,
+        "price_step {} must be a multiple of the tick size {} and depth {} at most {}",
+        price_step,
+        perp_market.amm.order_tick_size,
+        depth,
+        MAX_ORDER_BOOK_SNAPSHOT_DEPTH
+    )?;
+
+    let oracle_price = oracle_map.get_price_data(&perp_market.amm.oracle)?.price;
+    let amm_is_available = !state.amm_paused()?
+        && perp_market.status != MarketStatus::AmmPaused
+        && !perp_market.is_operation_paused(PerpOperation::AmmFill)?;
+
+    // users are only read, unlike load_user_map they dont have to be writable
+    let mut orders = vec![];
+    for user_account_info in remaining_accounts_iter {
+        let user_account_loader: AccountLoader<User> =
+            AccountLoader::try_from(user_account_info).or(Err(ErrorCode::InvalidUserAccount))?;
+        let user = load!(user_account_loader)?;
+        orders.extend(
+            user.orders
+                .iter()
+                .filter(|order| order.market_index == market_index),
+        );
+    }
+
+    let order_book_snapshot = calculate_order_book_snapshot(
+        &perp_market,
+        &orders,
+        oracle_price,
+        amm_is_available,
+        clock.slot,
+        price_step,
+        depth,
+    )?;
+
+    let order_book_snapshot_data = order_book_snapshot
+        .try_to_vec()
+        .map_err(|_| ErrorCode::DefaultError)?;
+    anchor_lang::solana_program::program::set_return_data(&order_book_snapshot_data);
+
+    Ok(())
+}
+
+pub fn handle_extend_market_lookup_table(
+    ctx: Context<ExtendMarketLookupTable>,
+    table_index: u16,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        ..
+    } = load_maps(
+        remaining_accounts_iter,
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        None,
+    )?;
+
+    let mut market_addresses = vec![];
+    for spot_market_loader in spot_market_map.0.values() {
+        let spot_market = load!(spot_market_loader)?;
+        market_addresses.push(spot_market_loader.key());
+        if spot_market.oracle != Pubkey::default() {
+            market_addresses.push(spot_market.oracle);
+        }
+        market_addresses.push(spot_market.vault);
+    }
+
+    for perp_market_loader in perp_market_map.0.values() {
+        let perp_market = load!(perp_market_loader)?;
+        market_addresses.push(perp_market_loader.key());
+        market_addresses.push(perp_market.amm.oracle);
+    }
+
+    let table_addresses = controller::lookup_table::get_lookup_table_addresses(
+        &ctx.accounts.lookup_table.try_borrow_data()?,
+    )?;
+    let new_addresses = controller::lookup_table::get_new_lookup_table_addresses(
+        &table_addresses,
+        &market_addresses,
+    )?;
+
+    if new_addresses.is_empty() {
+        msg!(
+            "market lookup table {} already has every address",
+            table_index
+        );
+        return Ok(());
+    }
+
+    msg!(
+        "market lookup table {} adding {} addresses to {}",
+        table_index,
+        new_addresses.len(),
+        table_addresses.len()
+    );
+
+    controller::lookup_table::extend_lookup_table(
+        &ctx.accounts.address_lookup_table_program,
+        &ctx.accounts.lookup_table,
+        &ctx.accounts.drift_signer,
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &new_addresses,
+        state.signer_nonce,
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    funding_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_settle_funding_payments_batch(
+    ctx: Context<SettleFundingBatch>,
+    market_index: u16,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map, ..
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(market_index),
+        &MarketSet::new(),
+        clock.slot,
+        None,
+    )?;
+
+    let user_map = load_user_map(remaining_accounts_iter)?;
+
+    validate!(
+        !user_map.0.is_empty(),
+        ErrorCode::UserNotFound,
+        "no users passed to settle funding for"
+    )?;
+
+    let perp_market = &mut perp_market_map.get_ref_mut(&market_index)?;
+
+    for user_key in user_map.0.keys() {
+        let user = &mut user_map.get_ref_mut(user_key)?;
+        controller::funding::settle_funding_payment(user, user_key, perp_market, now)?;
+        user.update_last_active_slot(clock.slot);
+    }
+
+    Ok(())
+}
+
+#[access_control(
+    amm_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_settle_lp<'info>(ctx: Context<SettleLP>, market_index: u16) -> Result<()> {
+    let user_key = ctx.accounts.user.key();
+    let user = &mut load_mut!(ctx.accounts.user)?;
+
+    let state = &ctx.accounts.state;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let AccountMaps {
+        perp_market_map, ..
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &get_writable_perp_market_set(market_index),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let market = &mut perp_market_map.get_ref_mut(&market_index)?;
+    controller::lp::settle_funding_payment_then_lp(user, &user_key, market, now)?;
+    user.update_last_active_slot(clock.slot);
+
+    Ok(())
+}
+
+#[access_control(
+    settle_pnl_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_settle_expired_market(ctx: Context<UpdateAMM>, market_index: u16) -> Result<()> {
+    let clock = Clock::get()?;
+    let _now = clock.unix_timestamp;
+    let state = &ctx.accounts.state;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(market_index),
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let perp_market_delisting = get_perp_market_delisting(remaining_accounts_iter)?;
+
+    controller::repeg::update_amm(
+        market_index,
+        &perp_market_map,
+        &mut oracle_map,
+        state,
+        &clock,
+    )?;
+
+    controller::repeg::settle_expired_market(
+        market_index,
+        &perp_market_map,
+        &mut oracle_map,
+        &spot_market_map,
+        state,
+        &clock,
+        perp_market_delisting.as_ref(),
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
+)]
+pub fn handle_update_perp_market_delisting_twap(
+    ctx: Context<UpdatePerpMarketDelistingTwap>,
+    _market_index: u16,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let state = &ctx.accounts.state;
+    let perp_market = &load!(ctx.accounts.perp_market)?;
+    let perp_market_delisting = &mut load_mut!(ctx.accounts.perp_market_delisting)?;
+
+    let mut oracle_map = OracleMap::load_one(
+        &ctx.accounts.oracle,
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+    let oracle_price_data = oracle_map.get_price_data(&perp_market.amm.oracle)?;
+
+    let risk_ema_price = perp_market
+        .amm
+        .historical_oracle_data
+        .last_oracle_price_twap;
+    let oracle_validity = oracle_validity(
+        risk_ema_price,
+        oracle_price_data,
+        &state
+            .oracle_guard_rails
+            .validity
+            .with_override(&perp_market.amm.validity_guard_rails_override),
+    )?;
+
+    validate!(
+        is_oracle_valid_for_action(oracle_validity, Some(DriftAction::SettlePnl))?,
+        ErrorCode::InvalidOracle,
+        "Invalid Oracle ({:?} vs ema={:?}) for perp market index={}",
+        oracle_price_data,
+        risk_ema_price,
+        perp_market.market_index
+    )?;
+
+    perp_market_delisting.update_settlement_price_twap(
+        oracle_price_data.price,
+        risk_ema_price,
+        perp_market.expiry_ts,
+        now,
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    settle_pnl_not_paused(&ctx.accounts.state)
+    amm_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_settle_expired_perp_positions(
+    ctx: Context<SettleExpiredPerpPositions>,
+    market_index: u16,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(market_index),
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let user_map = load_user_map(remaining_accounts_iter)?;
+
+    validate!(
+        !user_map.0.is_empty(),
+        ErrorCode::UserNotFound,
+        "no users passed to settle expired positions for"
+    )?;
+
+    for user_key in user_map.0.keys() {
+        let user = &mut user_map.get_ref_mut(user_key)?;
+        controller::pnl::settle_expired_position(
+            market_index,
+            user,
+            user_key,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            clock.unix_timestamp,
+            clock.slot,
+            state,
+        )?;
+        user.update_last_active_slot(clock.slot);
+    }
+
+    let spot_market = spot_market_map.get_quote_spot_market()?;
+    validate_spot_market_vault_amount(&spot_market, ctx.accounts.spot_market_vault.amount)?;
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+)]
+pub fn handle_close_delisted_perp_market(
+    ctx: Context<CloseDelistedPerpMarket>,
+    _market_index: u16,
+) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+    let now = Clock::get()?.unix_timestamp;
+
+    controller::spot_balance::update_spot_market_cumulative_interest(spot_market, None, now)?;
+
+    // every position has to be settled before the pools are swept, no escrow period needed
+    validate!(
+        perp_market.number_of_users == 0,
+        ErrorCode::PerpMarketSettlementUsersRemaining,
+        "{} users still have positions in perp market {}",
+        perp_market.number_of_users,
+        perp_market.market_index
+    )?;
+
+    controller::repeg::settle_expired_market_pools_to_revenue_pool(perp_market, spot_market)?;
+
+    Ok(())
+}
+
+#[access_control(
+    liq_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_liquidate_perp(
+    ctx: Context<LiquidatePerp>,
+    market_index: u16,
+    liquidator_max_base_asset_amount: u64,
+    limit_price: Option<u64>,
+) -> Result<()> {
     let clock = Clock::get()?;
-    let _now = clock.unix_timestamp;
+    let now = clock.unix_timestamp;
+    let slot = clock.slot;
     let state = &ctx.accounts.state;
 
+    let user_key = ctx.accounts.user.key();
+    let liquidator_key = ctx.accounts.liquidator.key();
+
+    validate!(
+        user_key != liquidator_key,
+        ErrorCode::UserCantLiquidateThemself
+    )?;
+
+    let user = &mut load_mut!(ctx.accounts.user)?;
+    let user_stats = &mut load_mut!(ctx.accounts.user_stats)?;
+    let liquidator = &mut load_mut!(ctx.accounts.liquidator)?;
+    let liquidator_stats = &mut load_mut!(ctx.accounts.liquidator_stats)?;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
         perp_market_map,
         spot_market_map,
         mut oracle_map,
     } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
+        remaining_accounts_iter,
         &get_writable_perp_market_set(market_index),
-        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
+        &MarketSet::new(),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
-
-    controller::repeg::update_amm(
+    let market_whitelist_entry = get_market_whitelist_entry(remaining_accounts_iter)?;
+
+    // the liquidator takes on the user's position in the market
+    validate_market_whitelist(
+        perp_market_map
+            .get_ref(&market_index)?
+            .amm
+            .whitelist_enabled,
         market_index,
-        &perp_market_map,
-        &mut oracle_map,
-        state,
-        &clock,
+        &liquidator.authority,
+        market_whitelist_entry.as_ref(),
     )?;
 
-    controller::repeg::settle_expired_market(
+    controller::liquidation::liquidate_perp(
         market_index,
+        liquidator_max_base_asset_amount,
+        limit_price,
+        user,
+        &user_key,
+        user_stats,
+        liquidator,
+        &liquidator_key,
+        liquidator_stats,
         &perp_market_map,
-        &mut oracle_map,
         &spot_market_map,
+        &mut oracle_map,
+        slot,
+        now,
         state,
-        &clock,
+        0,
     )?;
 
     Ok(())
@@ -523,8 +1931,8 @@ pub fn handle_settle_expired_market(ctx: Context<UpdateAMM>, market_index: u16)
 #[access_control(
     liq_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_liquidate_perp(
-    ctx: Context<LiquidatePerp>,
+pub fn handle_liquidate_perp_with_backstop(
+    ctx: Context<LiquidatePerpWithBackstop>,
     market_index: u16,
     liquidator_max_base_asset_amount: u64,
     limit_price: Option<u64>,
@@ -546,18 +1954,44 @@ pub fn handle_liquidate_perp(
     let user_stats = &mut load_mut!(ctx.accounts.user_stats)?;
     let liquidator = &mut load_mut!(ctx.accounts.liquidator)?;
     let liquidator_stats = &mut load_mut!(ctx.accounts.liquidator_stats)?;
+    let backstop_provider = load!(ctx.accounts.backstop_provider)?;
 
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
         perp_market_map,
         spot_market_map,
         mut oracle_map,
     } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
+        remaining_accounts_iter,
         &get_writable_perp_market_set(market_index),
         &MarketSet::new(),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
+    let market_whitelist_entry = get_market_whitelist_entry(remaining_accounts_iter)?;
+
+    // the liquidator takes on the user's position in the market
+    validate_market_whitelist(
+        perp_market_map
+            .get_ref(&market_index)?
+            .amm
+            .whitelist_enabled,
+        market_index,
+        &liquidator.authority,
+        market_whitelist_entry.as_ref(),
+    )?;
+
+    let (_, liquidator_total_collateral, _, _) = calculate_margin_requirement_and_total_collateral(
+        liquidator,
+        &perp_market_map,
+        MarginRequirementType::Initial,
+        &spot_market_map,
+        &mut oracle_map,
+        None,
+    )?;
+
+    let backstop_liquidation_fee_bonus =
+        backstop_provider.get_liquidation_fee_bonus(liquidator_total_collateral)?;
 
     controller::liquidation::liquidate_perp(
         market_index,
@@ -575,6 +2009,7 @@ pub fn handle_liquidate_perp(
         slot,
         now,
         state,
+        backstop_liquidation_fee_bonus,
     )?;
 
     Ok(())
@@ -634,6 +2069,7 @@ pub fn handle_liquidate_spot(
         state.liquidation_margin_buffer_ratio,
         state.initial_pct_to_liquidate as u128,
         state.liquidation_duration as u128,
+        state.liquidation_fee_ramp_duration as u128,
     )?;
 
     Ok(())
@@ -693,6 +2129,7 @@ pub fn handle_liquidate_borrow_for_perp_pnl(
         state.liquidation_margin_buffer_ratio,
         state.initial_pct_to_liquidate as u128,
         state.liquidation_duration as u128,
+        state.liquidation_fee_ramp_duration as u128,
     )?;
 
     Ok(())
@@ -752,6 +2189,7 @@ pub fn handle_liquidate_perp_pnl_for_deposit(
         state.liquidation_margin_buffer_ratio,
         state.initial_pct_to_liquidate as u128,
         state.liquidation_duration as u128,
+        state.liquidation_fee_ramp_duration as u128,
     )?;
 
     Ok(())
@@ -879,6 +2317,93 @@ pub fn handle_resolve_perp_pnl_deficit(
     Ok(())
 }
 
+#[access_control(
+    withdraw_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_update_insurance_fund_lend_to_amm(
+    ctx: Context<ResolvePerpPnlDeficit>,
+    spot_market_index: u16,
+    perp_market_index: u16,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    validate!(spot_market_index == 0, ErrorCode::InvalidSpotMarketAccount)?;
+    let state = &ctx.accounts.state;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &get_writable_perp_market_set(perp_market_index),
+        &get_writable_spot_market_set(spot_market_index),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    controller::repeg::update_amm(
+        perp_market_index,
+        &perp_market_map,
+        &mut oracle_map,
+        state,
+        &clock,
+    )?;
+
+    let insurance_vault_amount = ctx.accounts.insurance_fund_vault.amount;
+    let spot_market_vault_amount = ctx.accounts.spot_market_vault.amount;
+
+    let amount = {
+        let spot_market = &mut spot_market_map.get_ref_mut(&spot_market_index)?;
+        let perp_market = &mut perp_market_map.get_ref_mut(&perp_market_index)?;
+
+        controller::insurance::update_insurance_fund_lend_to_amm(
+            spot_market_vault_amount,
+            insurance_vault_amount,
+            spot_market,
+            perp_market,
+            now,
+        )?
+    };
+
+    if amount > 0 {
+        controller::token::send_from_program_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.insurance_fund_vault,
+            &ctx.accounts.spot_market_vault,
+            &ctx.accounts.drift_signer,
+            state.signer_nonce,
+            amount.unsigned_abs(),
+        )?;
+
+        validate!(
+            ctx.accounts.insurance_fund_vault.amount > 0,
+            ErrorCode::InvalidIFDetected,
+            "insurance_fund_vault.amount must remain > 0"
+        )?;
+    } else if amount < 0 {
+        controller::token::send_from_program_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.spot_market_vault,
+            &ctx.accounts.insurance_fund_vault,
+            &ctx.accounts.drift_signer,
+            state.signer_nonce,
+            amount.unsigned_abs(),
+        )?;
+    }
+
+    // reload the spot market vault balance so it's up-to-date
+    ctx.accounts.spot_market_vault.reload()?;
+    let spot_market = spot_market_map.get_ref(&spot_market_index)?;
+    math::spot_withdraw::validate_spot_market_vault_amount(
+        &spot_market,
+        ctx.accounts.spot_market_vault.amount,
+    )?;
+
+    Ok(())
+}
+
 #[access_control(
     withdraw_not_paused(&ctx.accounts.state)
 )]
@@ -990,6 +2515,61 @@ pub fn handle_resolve_perp_bankruptcy(
     Ok(())
 }
 
+#[access_control(
+    liq_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_auto_deleverage_perp(
+    ctx: Context<AutoDeleveragePerp>,
+    market_index: u16,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let slot = clock.slot;
+    let state = &ctx.accounts.state;
+
+    let user_key = ctx.accounts.user.key();
+    let keeper_key = ctx.accounts.keeper.key();
+
+    validate!(user_key != keeper_key, ErrorCode::UserCantLiquidateThemself)?;
+
+    let user = &mut load_mut!(ctx.accounts.user)?;
+    let keeper = &mut load_mut!(ctx.accounts.keeper)?;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(market_index),
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
+        slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let counterparty_map = load_user_map(remaining_accounts_iter)?;
+
+    controller::liquidation::auto_deleverage_perp(
+        market_index,
+        user,
+        &user_key,
+        &keeper_key,
+        &counterparty_map,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        slot,
+        now,
+        state,
+        ctx.accounts.insurance_fund_vault.amount,
+    )?;
+
+    keeper.update_last_active_slot(slot);
+
+    Ok(())
+}
+
 #[access_control(
     withdraw_not_paused(&ctx.accounts.state)
 )]
@@ -1116,25 +2696,150 @@ pub fn handle_update_funding_rate(
         "Market funding is paused",
     )?;
 
-    validate!(
-        ((clock_slot == perp_market.amm.last_update_slot && perp_market.amm.last_oracle_valid)
-            || perp_market.amm.curve_update_intensity == 0),
-        ErrorCode::AMMNotUpdatedInSameSlot,
-        "AMM must be updated in a prior instruction within same slot"
-    )?;
+    validate!(
+        !perp_market.is_operation_paused(PerpOperation::UpdateFunding)?,
+        ErrorCode::MarketActionPaused,
+        "Market funding is paused",
+    )?;
+
+    validate!(
+        perp_market.contract_type == ContractType::Perpetual,
+        ErrorCode::MarketActionPaused,
+        "Only perpetual markets pay funding",
+    )?;
+
+    validate!(
+        ((clock_slot == perp_market.amm.last_update_slot && perp_market.amm.last_oracle_valid)
+            || perp_market.amm.curve_update_intensity == 0),
+        ErrorCode::AMMNotUpdatedInSameSlot,
+        "AMM must be updated in a prior instruction within same slot"
+    )?;
+
+    let is_updated = controller::funding::update_funding_rate(
+        perp_market_index,
+        perp_market,
+        &mut oracle_map,
+        now,
+        &state.oracle_guard_rails,
+        state.funding_paused()?,
+        None,
+    )?;
+
+    if !is_updated {
+        return Err(ErrorCode::InvalidFundingProfitability.into());
+    }
+
+    Ok(())
+}
+
+#[access_control(
+    perp_market_valid(&ctx.accounts.perp_market)
+    exchange_not_paused(&ctx.accounts.state)
+    valid_oracle_for_perp_market(&ctx.accounts.oracle, &ctx.accounts.perp_market)
+)]
+pub fn handle_update_amm_peg(ctx: Context<UpdateAMMPeg>) -> Result<()> {
+    let perp_market = &mut load_mut!(ctx.accounts.perp_market)?;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let state = &ctx.accounts.state;
+    let mut oracle_map = OracleMap::load_one(
+        &ctx.accounts.oracle,
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+    let oracle_price_data = &oracle_map.get_price_data(&perp_market.amm.oracle)?;
+
+    let peg_multiplier_before = perp_market.amm.peg_multiplier;
+    let base_asset_reserve_before = perp_market.amm.base_asset_reserve;
+    let quote_asset_reserve_before = perp_market.amm.quote_asset_reserve;
+    let sqrt_k_before = perp_market.amm.sqrt_k;
+
+    let adjustment_cost = controller::repeg::update_amm_peg(perp_market, oracle_price_data, state)?;
+
+    emit!(CurveRecord {
+        ts: now,
+        record_id: get_then_update_id!(perp_market, next_curve_record_id),
+        market_index: perp_market.market_index,
+        peg_multiplier_before,
+        base_asset_reserve_before,
+        quote_asset_reserve_before,
+        sqrt_k_before,
+        peg_multiplier_after: perp_market.amm.peg_multiplier,
+        base_asset_reserve_after: perp_market.amm.base_asset_reserve,
+        quote_asset_reserve_after: perp_market.amm.quote_asset_reserve,
+        sqrt_k_after: perp_market.amm.sqrt_k,
+        base_asset_amount_long: perp_market.amm.base_asset_amount_long.unsigned_abs(),
+        base_asset_amount_short: perp_market.amm.base_asset_amount_short.unsigned_abs(),
+        base_asset_amount_with_amm: perp_market.amm.base_asset_amount_with_amm,
+        number_of_users: perp_market.number_of_users,
+        total_fee: perp_market.amm.total_fee,
+        total_fee_minus_distributions: perp_market.amm.total_fee_minus_distributions,
+        adjustment_cost,
+        oracle_price: oracle_price_data.price,
+        fill_record: 0,
+    });
+
+    Ok(())
+}
+
+#[access_control(
+    amm_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_update_perp_lp_range_status<'info>(
+    ctx: Context<'_, '_, '_, 'info, UpdatePerpLpRangeStatus<'info>>,
+    market_index: u16,
+) -> Result<()> {
+    let user_key = ctx.accounts.user.key();
+    let user = &mut load_mut!(ctx.accounts.user)?;
+    let perp_lp_range = &mut load_mut!(ctx.accounts.perp_lp_range)?;
+    let state = &ctx.accounts.state;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &get_writable_perp_market_set(market_index),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
 
-    let is_updated = controller::funding::update_funding_rate(
-        perp_market_index,
-        perp_market,
-        &mut oracle_map,
-        now,
-        &state.oracle_guard_rails,
-        state.funding_paused()?,
-        None,
-    )?;
+    let action = {
+        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+        let oracle_price = oracle_map.get_price_data(&market.amm.oracle)?.price;
 
-    if !is_updated {
-        return Err(ErrorCode::InvalidFundingProfitability.into());
+        controller::lp::update_lp_range_status(
+            user,
+            &user_key,
+            perp_lp_range,
+            &mut market,
+            oracle_price,
+            now,
+        )?
+    };
+
+    match action {
+        Some(LPAction::AddLiquidity) => {
+            // re-minted shares must still be backed by the user's collateral
+            validate!(
+                meets_initial_margin_requirement(
+                    user,
+                    &perp_market_map,
+                    &spot_market_map,
+                    &mut oracle_map
+                )?,
+                ErrorCode::InsufficientCollateral,
+                "User does not meet initial margin requirement"
+            )?;
+        }
+        Some(_) => {}
+        None => return Err(ErrorCode::LpRangeStatusUnchanged.into()),
     }
 
     Ok(())
@@ -1250,6 +2955,23 @@ pub fn handle_update_spot_market_cumulative_interest(
     Ok(())
 }
 
+pub fn handle_update_lst_oracle(ctx: Context<UpdateLstOracle>) -> Result<()> {
+    let lst_oracle = &mut load_mut!(ctx.accounts.lst_oracle)?;
+    let clock_slot = Clock::get()?.slot;
+
+    let sol_price_data = get_oracle_price(
+        &lst_oracle.sol_oracle_source,
+        &ctx.accounts.sol_oracle,
+        clock_slot,
+    )?;
+    let redemption_rate =
+        get_redemption_rate(&ctx.accounts.stake_pool, lst_oracle.stake_pool_type)?;
+
+    lst_oracle.update(&sol_price_data, redemption_rate, clock_slot)?;
+
+    Ok(())
+}
+
 #[access_control(
     exchange_not_paused(&ctx.accounts.state)
 )]
@@ -1266,6 +2988,7 @@ pub fn handle_update_amms(ctx: Context<UpdateAMM>, market_indexes: [u16; 5]) ->
         &get_market_set_from_list(market_indexes),
         remaining_accounts_iter,
     )?;
+    oracle_map.add_perp_market_oracle_aggregations(market_map)?;
 
     controller::repeg::update_amms(market_map, oracle_map, state, &clock)?;
 
@@ -1317,6 +3040,59 @@ pub struct FillOrder<'info> {
     pub user_stats: AccountLoader<'info, UserStats>,
 }
 
+#[derive(Accounts)]
+pub struct PlaceSignedOrder<'info> {
+    pub state: Box<Account<'info, State>>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        constraint = is_stats_for_user(&user, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    #[account(
+        mut,
+        seeds = [b"signed_order_nonce", user.key().as_ref()],
+        bump,
+    )]
+    pub signed_order_nonce: AccountLoader<'info, SignedOrderNonce>,
+    /// CHECK: checked by address constraint
+    #[account(address = sysvar::instructions::ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FillRfq<'info> {
+    pub state: Box<Account<'info, State>>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = can_sign_for_user(&filler, &authority)?
+    )]
+    pub filler: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        constraint = is_stats_for_user(&filler, &filler_stats)?
+    )]
+    pub filler_stats: AccountLoader<'info, UserStats>,
+    #[account(mut)]
+    pub taker: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        constraint = is_stats_for_user(&taker, &taker_stats)?
+    )]
+    pub taker_stats: AccountLoader<'info, UserStats>,
+    #[account(
+        mut,
+        seeds = [b"signed_order_nonce", taker.key().as_ref()],
+        bump,
+    )]
+    pub taker_signed_order_nonce: AccountLoader<'info, SignedOrderNonce>,
+    /// CHECK: checked by address constraint
+    #[account(address = sysvar::instructions::ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct RevertFill<'info> {
     pub state: Box<Account<'info, State>>,
@@ -1372,6 +3148,32 @@ pub struct UpdateUserIdle<'info> {
     pub user: AccountLoader<'info, User>,
 }
 
+#[derive(Accounts)]
+pub struct LogUserRisk<'info> {
+    pub state: Box<Account<'info, State>>,
+    pub authority: Signer<'info>,
+    pub user: AccountLoader<'info, User>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateUserMarginWarning<'info> {
+    pub state: Box<Account<'info, State>>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateUserFuel<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub user_fuel: AccountLoader<'info, UserFuel>,
+    #[account(
+        constraint = is_stats_for_user_fuel(&user_fuel, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+}
+
 #[derive(Accounts)]
 pub struct SettlePNL<'info> {
     pub state: Box<Account<'info, State>>,
@@ -1385,6 +3187,33 @@ pub struct SettlePNL<'info> {
     pub spot_market_vault: Box<Account<'info, TokenAccount>>,
 }
 
+#[derive(Accounts)]
+pub struct SettlePnlBatch<'info> {
+    pub state: Box<Account<'info, State>>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"spot_market_vault".as_ref(), 0_u16.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub spot_market_vault: Box<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct RebalancePnlPools<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [b"pnl_pool_rebalance_config".as_ref()],
+        bump,
+    )]
+    pub pnl_pool_rebalance_config: AccountLoader<'info, PnlPoolRebalanceConfig>,
+    #[account(
+        seeds = [b"spot_market_vault".as_ref(), 0_u16.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub spot_market_vault: Box<Account<'info, TokenAccount>>,
+}
+
 #[derive(Accounts)]
 pub struct SettleFunding<'info> {
     pub state: Box<Account<'info, State>>,
@@ -1392,6 +3221,105 @@ pub struct SettleFunding<'info> {
     pub user: AccountLoader<'info, User>,
 }
 
+#[derive(Accounts)]
+pub struct GetPredictedFundingRate<'info> {
+    pub state: Box<Account<'info, State>>,
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+}
+
+#[derive(Accounts)]
+pub struct GetOrderBookSnapshot<'info> {
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+#[instruction(table_index: u16)]
+pub struct ExtendMarketLookupTable<'info> {
+    /// pays the rent of the added addresses
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        seeds = [b"market_lookup_table".as_ref(), table_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub market_lookup_table: AccountLoader<'info, MarketLookupTable>,
+    #[account(
+        mut,
+        constraint = market_lookup_table.load()?.lookup_table.eq(lookup_table.key)
+    )]
+    /// CHECK: checked against the market lookup table
+    pub lookup_table: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: program signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(
+        address = crate::ids::address_lookup_table_program::id()
+    )]
+    /// CHECK: address lookup table program
+    pub address_lookup_table_program: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleFundingBatch<'info> {
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct UpdatePerpMarketDelistingTwap<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        seeds = [b"perp_market", market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    #[account(
+        mut,
+        seeds = [b"perp_market_delisting".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub perp_market_delisting: AccountLoader<'info, PerpMarketDelisting>,
+    /// CHECK: checked in `update_perp_market_delisting_twap` ix constraint
+    pub oracle: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleExpiredPerpPositions<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        seeds = [b"spot_market_vault".as_ref(), 0_u16.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub spot_market_vault: Box<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct CloseDelistedPerpMarket<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [b"perp_market", market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    #[account(
+        seeds = [b"perp_market_delisting".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub perp_market_delisting: AccountLoader<'info, PerpMarketDelisting>,
+    #[account(
+        mut,
+        seeds = [b"spot_market", 0_u16.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+}
+
 #[derive(Accounts)]
 pub struct SettleLP<'info> {
     pub state: Box<Account<'info, State>>,
@@ -1405,7 +3333,30 @@ pub struct LiquidatePerp<'info> {
     pub authority: Signer<'info>,
     #[account(
         mut,
-        constraint = can_sign_for_user(&liquidator, &authority)?
+        constraint = can_sign_for_user_with_permission(&liquidator, &authority, DelegatePermission::PlaceOrders)?
+    )]
+    pub liquidator: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        constraint = is_stats_for_user(&liquidator, &liquidator_stats)?
+    )]
+    pub liquidator_stats: AccountLoader<'info, UserStats>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        constraint = is_stats_for_user(&user, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+}
+
+#[derive(Accounts)]
+pub struct LiquidatePerpWithBackstop<'info> {
+    pub state: Box<Account<'info, State>>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = can_sign_for_user_with_permission(&liquidator, &authority, DelegatePermission::PlaceOrders)?
     )]
     pub liquidator: AccountLoader<'info, User>,
     #[account(
@@ -1413,6 +3364,11 @@ pub struct LiquidatePerp<'info> {
         constraint = is_stats_for_user(&liquidator, &liquidator_stats)?
     )]
     pub liquidator_stats: AccountLoader<'info, UserStats>,
+    #[account(
+        seeds = [b"backstop_provider".as_ref(), liquidator.key().as_ref()],
+        bump,
+    )]
+    pub backstop_provider: AccountLoader<'info, BackstopProvider>,
     #[account(mut)]
     pub user: AccountLoader<'info, User>,
     #[account(
@@ -1428,7 +3384,7 @@ pub struct LiquidateSpot<'info> {
     pub authority: Signer<'info>,
     #[account(
         mut,
-        constraint = can_sign_for_user(&liquidator, &authority)?
+        constraint = can_sign_for_user_with_permission(&liquidator, &authority, DelegatePermission::PlaceOrders)?
     )]
     pub liquidator: AccountLoader<'info, User>,
     #[account(
@@ -1451,7 +3407,7 @@ pub struct LiquidateBorrowForPerpPnl<'info> {
     pub authority: Signer<'info>,
     #[account(
         mut,
-        constraint = can_sign_for_user(&liquidator, &authority)?
+        constraint = can_sign_for_user_with_permission(&liquidator, &authority, DelegatePermission::PlaceOrders)?
     )]
     pub liquidator: AccountLoader<'info, User>,
     #[account(
@@ -1474,7 +3430,7 @@ pub struct LiquidatePerpPnlForDeposit<'info> {
     pub authority: Signer<'info>,
     #[account(
         mut,
-        constraint = can_sign_for_user(&liquidator, &authority)?
+        constraint = can_sign_for_user_with_permission(&liquidator, &authority, DelegatePermission::PlaceOrders)?
     )]
     pub liquidator: AccountLoader<'info, User>,
     #[account(
@@ -1491,6 +3447,24 @@ pub struct LiquidatePerpPnlForDeposit<'info> {
     pub user_stats: AccountLoader<'info, UserStats>,
 }
 
+#[derive(Accounts)]
+pub struct AutoDeleveragePerp<'info> {
+    pub state: Box<Account<'info, State>>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = can_sign_for_user(&keeper, &authority)?
+    )]
+    pub keeper: AccountLoader<'info, User>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        seeds = [b"insurance_fund_vault".as_ref(), 0_u16.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub insurance_fund_vault: Box<Account<'info, TokenAccount>>,
+}
+
 #[derive(Accounts)]
 #[instruction(spot_market_index: u16,)]
 pub struct ResolveBankruptcy<'info> {
@@ -1597,12 +3571,50 @@ pub struct UpdateSpotMarketCumulativeInterest<'info> {
     pub oracle: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateLstOracle<'info> {
+    #[account(
+        mut,
+        has_one = sol_oracle,
+        has_one = stake_pool,
+    )]
+    pub lst_oracle: AccountLoader<'info, LstOracle>,
+    /// CHECK: checked by lst oracle has_one
+    pub sol_oracle: AccountInfo<'info>,
+    /// CHECK: checked by lst oracle has_one
+    pub stake_pool: AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateAMM<'info> {
     pub state: Box<Account<'info, State>>,
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateAMMPeg<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub perp_market: AccountLoader<'info, PerpMarket>,
+    /// CHECK: checked in `update_amm_peg` ix constraint
+    pub oracle: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct UpdatePerpLpRangeStatus<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        seeds = [b"perp_lp_range", user.key().as_ref(), market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub perp_lp_range: AccountLoader<'info, PerpLpRange>,
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateFundingRate<'info> {
     pub state: Box<Account<'info, State>>,
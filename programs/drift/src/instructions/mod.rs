@@ -1,12 +1,16 @@
 pub use admin::*;
 pub use constraints::*;
+pub use governance_staker::*;
 pub use if_staker::*;
 pub use keeper::*;
 pub use user::*;
+pub use vault::*;
 
 mod admin;
 mod constraints;
+mod governance_staker;
 mod if_staker;
 mod keeper;
 pub mod optional_accounts;
 mod user;
+mod vault;
@@ -1,26 +1,37 @@
+use crate::controller::openbook_v2::OpenbookV2FulfillmentParams;
+use crate::controller::phoenix::PhoenixFulfillmentParams;
 use crate::controller::serum::SerumFulfillmentParams;
 use crate::error::{DriftResult, ErrorCode};
 use crate::load;
 
 use crate::math::safe_unwrap::SafeUnwrap;
+use crate::state::fill_callback::{FillCallbackProgram, UserFillCallback};
+use crate::state::market_fee_override::MarketFeeOverride;
+use crate::state::market_whitelist::MarketWhitelistEntry;
 use crate::state::oracle_map::OracleMap;
+use crate::state::perp_market_delisting::PerpMarketDelisting;
 use crate::state::perp_market_map::{MarketSet, PerpMarketMap};
 use crate::state::spot_market::{
-    SerumV3FulfillmentConfig, SpotFulfillmentConfigStatus, SpotMarket,
+    OpenbookV2FulfillmentConfig, PhoenixV1FulfillmentConfig, SerumV3FulfillmentConfig,
+    SpotFulfillmentConfigStatus, SpotMarket,
 };
 use crate::state::spot_market_map::SpotMarketMap;
 use crate::state::state::{OracleGuardRails, State};
+use crate::state::token::SpotMarketVault;
 use crate::state::traits::Size;
 use crate::state::user::{User, UserStats};
 use crate::validate;
 use anchor_lang::accounts::account::Account;
 use anchor_lang::prelude::AccountLoader;
-use anchor_lang::prelude::{AccountInfo, Program};
+use anchor_lang::prelude::{AccountInfo, Program, Pubkey};
 use anchor_lang::Discriminator;
 use anchor_spl::token::{Token, TokenAccount};
 use arrayref::array_ref;
+use bytemuck::allocation::zeroed_box;
+use bytemuck::{bytes_of, bytes_of_mut, Pod};
 use solana_program::account_info::next_account_info;
 use solana_program::msg;
+use solana_program::system_program;
 use std::iter::Peekable;
 use std::slice::Iter;
 
@@ -37,10 +48,12 @@ pub fn load_maps<'a, 'b, 'c>(
     slot: u64,
     oracle_guard_rails: Option<OracleGuardRails>,
 ) -> DriftResult<AccountMaps<'a>> {
-    let oracle_map = OracleMap::load(account_info_iter, slot, oracle_guard_rails)?;
+    let mut oracle_map = OracleMap::load(account_info_iter, slot, oracle_guard_rails)?;
     let spot_market_map = SpotMarketMap::load(writable_spot_markets, account_info_iter)?;
     let perp_market_map = PerpMarketMap::load(writable_perp_markets, account_info_iter)?;
 
+    oracle_map.add_perp_market_oracle_aggregations(&perp_market_map)?;
+
     Ok(AccountMaps {
         perp_market_map,
         spot_market_map,
@@ -151,6 +164,216 @@ pub fn get_referrer_and_referrer_stats<'a>(
     Ok((Some(referrer), Some(referrer_stats)))
 }
 
+pub fn get_market_fee_override<'a>(
+    account_info_iter: &mut Peekable<Iter<AccountInfo<'a>>>,
+) -> DriftResult<Option<MarketFeeOverride>> {
+    let market_fee_override_account_info = account_info_iter.peek();
+
+    if market_fee_override_account_info.is_none() {
+        return Ok(None);
+    }
+
+    let market_fee_override_account_info = market_fee_override_account_info.safe_unwrap()?;
+    let data = market_fee_override_account_info
+        .try_borrow_data()
+        .map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::InvalidMarketFeeOverride
+        })?;
+
+    if data.len() < MarketFeeOverride::SIZE {
+        return Ok(None);
+    }
+
+    let market_fee_override_discriminator: [u8; 8] = MarketFeeOverride::discriminator();
+    let account_discriminator = array_ref![data, 0, 8];
+    if account_discriminator != &market_fee_override_discriminator {
+        return Ok(None);
+    }
+
+    drop(data);
+
+    let market_fee_override_account_info = next_account_info(account_info_iter).safe_unwrap()?;
+
+    let market_fee_override_loader: AccountLoader<MarketFeeOverride> =
+        AccountLoader::try_from(market_fee_override_account_info)
+            .or(Err(ErrorCode::InvalidMarketFeeOverride))?;
+    let market_fee_override = *market_fee_override_loader
+        .load()
+        .or(Err(ErrorCode::InvalidMarketFeeOverride))?;
+
+    Ok(Some(market_fee_override))
+}
+
+pub fn get_market_whitelist_entry<'a>(
+    account_info_iter: &mut Peekable<Iter<AccountInfo<'a>>>,
+) -> DriftResult<Option<MarketWhitelistEntry>> {
+    let market_whitelist_entry_account_info = account_info_iter.peek();
+
+    if market_whitelist_entry_account_info.is_none() {
+        return Ok(None);
+    }
+
+    let market_whitelist_entry_account_info = market_whitelist_entry_account_info.safe_unwrap()?;
+    let data = market_whitelist_entry_account_info
+        .try_borrow_data()
+        .map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::InvalidMarketWhitelistEntry
+        })?;
+
+    if data.len() < MarketWhitelistEntry::SIZE {
+        return Ok(None);
+    }
+
+    let market_whitelist_entry_discriminator: [u8; 8] = MarketWhitelistEntry::discriminator();
+    let account_discriminator = array_ref![data, 0, 8];
+    if account_discriminator != &market_whitelist_entry_discriminator {
+        return Ok(None);
+    }
+
+    drop(data);
+
+    let market_whitelist_entry_account_info = next_account_info(account_info_iter).safe_unwrap()?;
+
+    let market_whitelist_entry_loader: AccountLoader<MarketWhitelistEntry> =
+        AccountLoader::try_from(market_whitelist_entry_account_info)
+            .or(Err(ErrorCode::InvalidMarketWhitelistEntry))?;
+    let market_whitelist_entry = *market_whitelist_entry_loader
+        .load()
+        .or(Err(ErrorCode::InvalidMarketWhitelistEntry))?;
+
+    Ok(Some(market_whitelist_entry))
+}
+
+pub fn get_market_whitelist_entries<'a>(
+    account_info_iter: &mut Peekable<Iter<AccountInfo<'a>>>,
+) -> DriftResult<Vec<MarketWhitelistEntry>> {
+    let mut market_whitelist_entries = vec![];
+    while let Some(account_info) = account_info_iter.peek() {
+        // a revoked maker's entry is closed, its address can still be passed
+        if account_info.data_is_empty() && account_info.owner == &system_program::ID {
+            account_info_iter.next();
+            continue;
+        }
+
+        match get_market_whitelist_entry(account_info_iter)? {
+            Some(market_whitelist_entry) => market_whitelist_entries.push(market_whitelist_entry),
+            None => break,
+        }
+    }
+
+    Ok(market_whitelist_entries)
+}
+
+pub struct FillCallbackAccounts<'a> {
+    pub user_fill_callback: AccountInfo<'a>,
+    pub callback_program: AccountInfo<'a>,
+    pub bump: u8,
+}
+
+/// the user's UserFillCallback, followed by the FillCallbackProgram whitelisting it and the
+/// callback program itself
+pub fn get_fill_callback_accounts<'a>(
+    account_info_iter: &mut Peekable<Iter<AccountInfo<'a>>>,
+    user_key: &Pubkey,
+) -> DriftResult<Option<FillCallbackAccounts<'a>>> {
+    let user_fill_callback_account_info = account_info_iter.peek();
+
+    if user_fill_callback_account_info.is_none() {
+        return Ok(None);
+    }
+
+    let user_fill_callback_account_info = user_fill_callback_account_info.safe_unwrap()?;
+    let data = user_fill_callback_account_info
+        .try_borrow_data()
+        .map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::InvalidFillCallback
+        })?;
+
+    if data.len() < UserFillCallback::SIZE {
+        return Ok(None);
+    }
+
+    let user_fill_callback_discriminator: [u8; 8] = UserFillCallback::discriminator();
+    let account_discriminator = array_ref![data, 0, 8];
+    if account_discriminator != &user_fill_callback_discriminator {
+        return Ok(None);
+    }
+
+    drop(data);
+
+    let user_fill_callback_account_info = next_account_info(account_info_iter).safe_unwrap()?;
+    let user_fill_callback_loader: AccountLoader<UserFillCallback> =
+        AccountLoader::try_from(user_fill_callback_account_info)
+            .or(Err(ErrorCode::InvalidFillCallback))?;
+    let user_fill_callback = *user_fill_callback_loader
+        .load()
+        .or(Err(ErrorCode::InvalidFillCallback))?;
+
+    let fill_callback_program_account_info =
+        next_account_info(account_info_iter).or(Err(ErrorCode::InvalidFillCallback))?;
+    let fill_callback_program_loader: AccountLoader<FillCallbackProgram> =
+        AccountLoader::try_from(fill_callback_program_account_info)
+            .or(Err(ErrorCode::FillCallbackProgramNotWhitelisted))?;
+    let fill_callback_program = *fill_callback_program_loader
+        .load()
+        .or(Err(ErrorCode::FillCallbackProgramNotWhitelisted))?;
+
+    let callback_program =
+        next_account_info(account_info_iter).or(Err(ErrorCode::InvalidFillCallback))?;
+
+    user_fill_callback.validate(user_key, &fill_callback_program, callback_program)?;
+
+    Ok(Some(FillCallbackAccounts {
+        user_fill_callback: user_fill_callback_account_info.clone(),
+        callback_program: callback_program.clone(),
+        bump: user_fill_callback.bump,
+    }))
+}
+
+pub fn get_perp_market_delisting<'a>(
+    account_info_iter: &mut Peekable<Iter<AccountInfo<'a>>>,
+) -> DriftResult<Option<PerpMarketDelisting>> {
+    let perp_market_delisting_account_info = account_info_iter.peek();
+
+    if perp_market_delisting_account_info.is_none() {
+        return Ok(None);
+    }
+
+    let perp_market_delisting_account_info = perp_market_delisting_account_info.safe_unwrap()?;
+    let data = perp_market_delisting_account_info
+        .try_borrow_data()
+        .map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::InvalidPerpMarketDelisting
+        })?;
+
+    if data.len() < PerpMarketDelisting::SIZE {
+        return Ok(None);
+    }
+
+    let perp_market_delisting_discriminator: [u8; 8] = PerpMarketDelisting::discriminator();
+    let account_discriminator = array_ref![data, 0, 8];
+    if account_discriminator != &perp_market_delisting_discriminator {
+        return Ok(None);
+    }
+
+    drop(data);
+
+    let perp_market_delisting_account_info = next_account_info(account_info_iter).safe_unwrap()?;
+
+    let perp_market_delisting_loader: AccountLoader<PerpMarketDelisting> =
+        AccountLoader::try_from(perp_market_delisting_account_info)
+            .or(Err(ErrorCode::InvalidPerpMarketDelisting))?;
+    let perp_market_delisting = *perp_market_delisting_loader
+        .load()
+        .or(Err(ErrorCode::InvalidPerpMarketDelisting))?;
+
+    Ok(Some(perp_market_delisting))
+}
+
 #[allow(clippy::type_complexity)]
 pub fn get_serum_fulfillment_accounts<'a, 'b, 'c>(
     account_info_iter: &'a mut std::iter::Peekable<std::slice::Iter<'b, AccountInfo<'c>>>,
@@ -213,16 +436,15 @@ pub fn get_serum_fulfillment_accounts<'a, 'b, 'c>(
         ErrorCode::InvalidSerumFulfillmentConfig
     )?;
 
-    let base_market_vault: Box<Account<TokenAccount>> =
-        Box::new(Account::try_from(base_market_vault).map_err(|e| {
-            msg!("{:?}", e);
-            ErrorCode::InvalidSerumFulfillmentConfig
-        })?);
-    let quote_market_vault: Box<Account<TokenAccount>> =
-        Box::new(Account::try_from(quote_market_vault).map_err(|e| {
+    let base_market_vault = SpotMarketVault::load(base_market_vault, base_market).map_err(|e| {
+        msg!("{:?}", e);
+        ErrorCode::InvalidSerumFulfillmentConfig
+    })?;
+    let quote_market_vault =
+        SpotMarketVault::load(quote_market_vault, quote_market).map_err(|e| {
             msg!("{:?}", e);
             ErrorCode::InvalidSerumFulfillmentConfig
-        })?);
+        })?;
 
     let token_program: Program<Token> = Program::try_from(token_program).map_err(|e| {
         msg!("{:?}", e);
@@ -256,15 +478,210 @@ pub fn get_serum_fulfillment_accounts<'a, 'b, 'c>(
     Ok(Some(serum_fulfillment_accounts))
 }
 
+pub fn get_openbook_v2_fulfillment_accounts<'a, 'b, 'c>(
+    account_info_iter: &'a mut std::iter::Peekable<std::slice::Iter<'b, AccountInfo<'c>>>,
+    state: &State,
+    base_market: &SpotMarket,
+    quote_market: &SpotMarket,
+) -> DriftResult<Option<OpenbookV2FulfillmentParams<'a, 'c>>> {
+    let account_info_vec = account_info_iter.collect::<Vec<_>>();
+    let account_infos = array_ref![account_info_vec, 0, 14];
+    let [openbook_v2_fulfillment_config, openbook_v2_program_id, openbook_v2_market, openbook_v2_market_authority, openbook_v2_event_heap, openbook_v2_bids, openbook_v2_asks, openbook_v2_base_vault, openbook_v2_quote_vault, drift_signer, token_program, system_program, base_market_vault, quote_market_vault] =
+        account_infos;
+
+    let openbook_v2_fulfillment_config_loader: AccountLoader<OpenbookV2FulfillmentConfig> =
+        AccountLoader::try_from(openbook_v2_fulfillment_config).map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::InvalidOpenbookV2FulfillmentConfig
+        })?;
+    let openbook_v2_fulfillment_config = load!(openbook_v2_fulfillment_config_loader)?;
+
+    validate!(
+        openbook_v2_fulfillment_config.status == SpotFulfillmentConfigStatus::Enabled,
+        ErrorCode::SpotFulfillmentConfigDisabled
+    )?;
+
+    validate!(
+        &state.signer == drift_signer.key,
+        ErrorCode::InvalidOpenbookV2FulfillmentConfig
+    )?;
+
+    validate!(
+        openbook_v2_fulfillment_config.market_index == base_market.market_index,
+        ErrorCode::InvalidOpenbookV2FulfillmentConfig,
+        "config market index {} does not equal base asset index {}",
+        openbook_v2_fulfillment_config.market_index,
+        base_market.market_index
+    )?;
+
+    validate!(
+        &base_market.vault == base_market_vault.key,
+        ErrorCode::InvalidOpenbookV2FulfillmentConfig
+    )?;
+
+    validate!(
+        &quote_market.vault == quote_market_vault.key,
+        ErrorCode::InvalidOpenbookV2FulfillmentConfig
+    )?;
+
+    validate!(
+        &openbook_v2_fulfillment_config.openbook_v2_program_id == openbook_v2_program_id.key,
+        ErrorCode::InvalidOpenbookV2FulfillmentConfig
+    )?;
+
+    validate!(
+        &openbook_v2_fulfillment_config.openbook_v2_market == openbook_v2_market.key,
+        ErrorCode::InvalidOpenbookV2FulfillmentConfig
+    )?;
+
+    validate!(
+        system_program.key == &solana_program::system_program::id(),
+        ErrorCode::InvalidOpenbookV2FulfillmentConfig
+    )?;
+
+    let base_market_vault = SpotMarketVault::load(base_market_vault, base_market).map_err(|e| {
+        msg!("{:?}", e);
+        ErrorCode::InvalidOpenbookV2FulfillmentConfig
+    })?;
+    let quote_market_vault =
+        SpotMarketVault::load(quote_market_vault, quote_market).map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::InvalidOpenbookV2FulfillmentConfig
+        })?;
+
+    let token_program: Program<Token> = Program::try_from(token_program).map_err(|e| {
+        msg!("{:?}", e);
+        ErrorCode::InvalidOpenbookV2FulfillmentConfig
+    })?;
+
+    let openbook_v2_fulfillment_accounts = OpenbookV2FulfillmentParams {
+        drift_signer,
+        openbook_v2_program_id,
+        openbook_v2_market,
+        openbook_v2_market_authority,
+        openbook_v2_event_heap,
+        openbook_v2_bids,
+        openbook_v2_asks,
+        openbook_v2_base_vault,
+        openbook_v2_quote_vault,
+        token_program,
+        system_program,
+        base_market_vault,
+        quote_market_vault,
+        signer_nonce: state.signer_nonce,
+    };
+
+    Ok(Some(openbook_v2_fulfillment_accounts))
+}
+
+pub fn get_phoenix_fulfillment_accounts<'a, 'b, 'c>(
+    account_info_iter: &'a mut std::iter::Peekable<std::slice::Iter<'b, AccountInfo<'c>>>,
+    state: &State,
+    base_market: &SpotMarket,
+    quote_market: &SpotMarket,
+) -> DriftResult<Option<PhoenixFulfillmentParams<'a, 'c>>> {
+    let account_info_vec = account_info_iter.collect::<Vec<_>>();
+    let account_infos = array_ref![account_info_vec, 0, 10];
+    let [phoenix_fulfillment_config, phoenix_program_id, phoenix_log_authority, phoenix_market, phoenix_base_vault, phoenix_quote_vault, drift_signer, token_program, base_market_vault, quote_market_vault] =
+        account_infos;
+
+    let phoenix_fulfillment_config_loader: AccountLoader<PhoenixV1FulfillmentConfig> =
+        AccountLoader::try_from(phoenix_fulfillment_config).map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::InvalidPhoenixFulfillmentConfig
+        })?;
+    let phoenix_fulfillment_config = load!(phoenix_fulfillment_config_loader)?;
+
+    validate!(
+        phoenix_fulfillment_config.status == SpotFulfillmentConfigStatus::Enabled,
+        ErrorCode::SpotFulfillmentConfigDisabled
+    )?;
+
+    validate!(
+        &state.signer == drift_signer.key,
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    )?;
+
+    validate!(
+        phoenix_fulfillment_config.market_index == base_market.market_index,
+        ErrorCode::InvalidPhoenixFulfillmentConfig,
+        "config market index {} does not equal base asset index {}",
+        phoenix_fulfillment_config.market_index,
+        base_market.market_index
+    )?;
+
+    validate!(
+        &base_market.vault == base_market_vault.key,
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    )?;
+
+    validate!(
+        &quote_market.vault == quote_market_vault.key,
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    )?;
+
+    validate!(
+        &phoenix_fulfillment_config.phoenix_program_id == phoenix_program_id.key,
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    )?;
+
+    validate!(
+        &phoenix_fulfillment_config.phoenix_log_authority == phoenix_log_authority.key,
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    )?;
+
+    validate!(
+        &phoenix_fulfillment_config.phoenix_market == phoenix_market.key,
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    )?;
+
+    validate!(
+        &phoenix_fulfillment_config.phoenix_base_vault == phoenix_base_vault.key,
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    )?;
+
+    validate!(
+        &phoenix_fulfillment_config.phoenix_quote_vault == phoenix_quote_vault.key,
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    )?;
+
+    let base_market_vault = SpotMarketVault::load(base_market_vault, base_market).map_err(|e| {
+        msg!("{:?}", e);
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    })?;
+    let quote_market_vault =
+        SpotMarketVault::load(quote_market_vault, quote_market).map_err(|e| {
+            msg!("{:?}", e);
+            ErrorCode::InvalidPhoenixFulfillmentConfig
+        })?;
+
+    let token_program: Program<Token> = Program::try_from(token_program).map_err(|e| {
+        msg!("{:?}", e);
+        ErrorCode::InvalidPhoenixFulfillmentConfig
+    })?;
+
+    let phoenix_fulfillment_accounts = PhoenixFulfillmentParams {
+        drift_signer,
+        phoenix_program_id,
+        phoenix_log_authority,
+        phoenix_market,
+        phoenix_base_vault,
+        phoenix_quote_vault,
+        token_program,
+        base_market_vault,
+        quote_market_vault,
+        signer_nonce: state.signer_nonce,
+    };
+
+    Ok(Some(phoenix_fulfillment_accounts))
+}
+
 #[allow(clippy::type_complexity)]
 pub fn get_spot_market_vaults<'a, 'b, 'c>(
     account_info_iter: &'a mut std::iter::Peekable<std::slice::Iter<'b, AccountInfo<'c>>>,
     base_market: &SpotMarket,
     quote_market: &SpotMarket,
-) -> DriftResult<(
-    Box<Account<'c, TokenAccount>>,
-    Box<Account<'c, TokenAccount>>,
-)> {
+) -> DriftResult<(SpotMarketVault<'c>, SpotMarketVault<'c>)> {
     let account_info_vec = account_info_iter.collect::<Vec<_>>();
     let account_infos = array_ref![account_info_vec, 0, 2];
     let [base_market_vault, quote_market_vault] = account_infos;
@@ -279,16 +696,15 @@ pub fn get_spot_market_vaults<'a, 'b, 'c>(
         ErrorCode::InvalidSerumFulfillmentConfig
     )?;
 
-    let base_market_vault: Box<Account<TokenAccount>> =
-        Box::new(Account::try_from(base_market_vault).map_err(|e| {
-            msg!("{:?}", e);
-            ErrorCode::InvalidSerumFulfillmentConfig
-        })?);
-    let quote_market_vault: Box<Account<TokenAccount>> =
-        Box::new(Account::try_from(quote_market_vault).map_err(|e| {
+    let base_market_vault = SpotMarketVault::load(base_market_vault, base_market).map_err(|e| {
+        msg!("{:?}", e);
+        ErrorCode::InvalidSerumFulfillmentConfig
+    })?;
+    let quote_market_vault =
+        SpotMarketVault::load(quote_market_vault, quote_market).map_err(|e| {
             msg!("{:?}", e);
             ErrorCode::InvalidSerumFulfillmentConfig
-        })?);
+        })?;
 
     Ok((base_market_vault, quote_market_vault))
 }
@@ -312,3 +728,39 @@ pub fn get_whitelist_token<'a>(
 
     Ok(whitelist_token)
 }
+
+pub fn get_token_mint<'a>(
+    account_info_iter: &mut Peekable<Iter<AccountInfo<'a>>>,
+    spot_market: &SpotMarket,
+) -> DriftResult<Option<AccountInfo<'a>>> {
+    if !spot_market.is_token_2022() {
+        return Ok(None);
+    }
+
+    let mint_account_info = next_account_info(account_info_iter).or_else(|_| {
+        msg!("Could not find mint for token-2022 spot market");
+        Err(ErrorCode::InvalidTokenMint)
+    })?;
+
+    validate!(
+        mint_account_info.key == &spot_market.mint,
+        ErrorCode::InvalidTokenMint,
+        "mint {} does not match spot market mint {}",
+        mint_account_info.key,
+        spot_market.mint
+    )?;
+
+    Ok(Some(mint_account_info.clone()))
+}
+
+/// Copies a zero copy account onto the heap without going through the stack
+pub fn copy_to_heap<T: Pod>(value: &T) -> Box<T> {
+    let mut heap_value = zeroed_box::<T>();
+    copy_account(&mut *heap_value, value);
+    heap_value
+}
+
+/// Copies a zero copy account over another without going through the stack
+pub fn copy_account<T: Pod>(destination: &mut T, source: &T) {
+    bytes_of_mut(destination).copy_from_slice(bytes_of(source));
+}
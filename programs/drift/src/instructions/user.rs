@@ -1,50 +1,89 @@
+use anchor_lang::system_program;
 use anchor_lang::{prelude::*, AnchorDeserialize, AnchorSerialize};
-use anchor_spl::token::{Token, TokenAccount};
+use solana_program::sysvar;
+use solana_program::sysvar::instructions::load_current_index_checked;
 
-use crate::controller::orders::cancel_orders;
+use crate::controller::orders::{cancel_orders, cancel_orders_with_filter, PlaceOrderOptions};
 use crate::controller::position::PositionDirection;
-use crate::error::ErrorCode;
+use crate::error::{DriftResult, ErrorCode};
 use crate::get_then_update_id;
 use crate::instructions::constraints::*;
 use crate::instructions::optional_accounts::{
-    get_maker_and_maker_stats, get_referrer_and_referrer_stats, get_serum_fulfillment_accounts,
-    get_spot_market_vaults, get_whitelist_token, load_maps, AccountMaps,
+    copy_to_heap, get_fill_callback_accounts, get_maker_and_maker_stats, get_market_fee_override,
+    get_market_whitelist_entries, get_market_whitelist_entry, get_openbook_v2_fulfillment_accounts,
+    get_referrer_and_referrer_stats, get_serum_fulfillment_accounts, get_spot_market_vaults,
+    get_token_mint, get_whitelist_token, load_maps, AccountMaps,
 };
 use crate::instructions::SpotFulfillmentType;
 use crate::load;
 use crate::load_mut;
 use crate::math::casting::Cast;
+use crate::math::constants::{ALL_TOKEN_AMOUNT, MAX_BUILDER_FEE_SHARE, QUOTE_SPOT_MARKET_INDEX};
+use crate::math::fees::calculate_simulated_fill_fee;
 use crate::math::liquidation::is_user_being_liquidated;
 use crate::math::margin::{
-    calculate_max_withdrawable_amount, meets_initial_margin_requirement,
-    meets_withdraw_margin_requirement, validate_spot_margin_trading,
+    calculate_margin_health, calculate_max_withdrawable_amount, meets_initial_margin_requirement,
+    meets_isolated_perp_position_margin_requirement, meets_withdraw_margin_requirement,
+    validate_spot_margin_trading, MarginRequirementType,
 };
+use crate::math::orders::{
+    calculate_basis_order_spot_leg, calculate_fill_receipt,
+    calculate_quote_asset_amount_for_maker_order, get_position_delta_for_fill,
+};
+use crate::math::position::calculate_base_asset_value_with_oracle_price;
 use crate::math::safe_math::SafeMath;
-use crate::math::spot_balance::get_token_amount;
+use crate::math::safe_unwrap::SafeUnwrap;
+use crate::math::spot_balance::{
+    calculate_cumulative_deposit_interest_delta_for_fee, calculate_flash_borrow_fee,
+    get_token_amount,
+};
 use crate::math::spot_withdraw::validate_spot_market_vault_amount;
 use crate::math_error;
 use crate::print_error;
 use crate::safe_decrement;
 use crate::safe_increment;
+use crate::state::builder::Builder;
+use crate::state::dead_man_switch::DeadManSwitch;
 use crate::state::events::{
     DepositDirection, DepositExplanation, DepositRecord, LPAction, LPRecord, NewUserRecord,
-    OrderActionExplanation,
+    OrderActionExplanation, SwapRecord,
 };
+use crate::state::fill_callback::{FillCallbackProgram, UserFillCallback};
+use crate::state::fuel::UserFuel;
+use crate::state::fuel_map::load_fuel_map;
+use crate::state::high_leverage_mode_config::HighLeverageModeConfig;
+use crate::state::legacy_user::{LegacyUser, LEGACY_USER_SIZE};
+use crate::state::market_fee_override::get_market_fee_structure;
+use crate::state::market_whitelist::{find_market_whitelist_entry, validate_market_whitelist};
+use crate::state::oracle_map::OracleMap;
+use crate::state::perp_lp_range::PerpLpRange;
 use crate::state::perp_market::MarketStatus;
-use crate::state::perp_market_map::{get_writable_perp_market_set, MarketSet};
-use crate::state::spot_market::SpotBalanceType;
-use crate::state::spot_market_map::get_writable_spot_market_set;
+use crate::state::perp_market_map::{get_writable_perp_market_set, MarketSet, PerpMarketMap};
+use crate::state::session_key::SessionKey;
+use crate::state::signed_order::SignedOrderNonce;
+use crate::state::spot_market::{SpotBalanceType, SpotMarket, SpotOperation};
+use crate::state::spot_market_map::{
+    get_writable_spot_market_set, get_writable_spot_market_set_from_many, SpotMarketMap,
+};
 use crate::state::state::State;
+use crate::state::token::{
+    calculate_pre_transfer_fee_amount, calculate_transfer_fee, load_token_account,
+};
 use crate::state::traits::Size;
 use crate::state::user::{
-    MarketType, OrderTriggerCondition, OrderType, ReferrerName, User, UserStats, UserStatus,
+    AuctionCurve, DelegatePermission, MarketType, Order, OrderTrailType, OrderTriggerCondition,
+    OrderType, PerpLpTier, ReferrerName, SizeDistribution, User, UserStats, UserStatus,
 };
 use crate::state::user_map::load_user_maps;
 use crate::validate;
-use crate::validation::user::validate_user_deletion;
+use crate::validation::flash_borrow::{validate_begin_flash_borrow, validate_end_flash_borrow};
+use crate::validation::ix_sysvar::load_transaction_instructions;
+use crate::validation::swap::{validate_begin_swap, validate_end_swap};
+use crate::validation::user::{validate_trading_not_disabled, validate_user_deletion};
 use crate::validation::whitelist::validate_whitelist_token;
 use crate::{controller, math};
 use borsh::{BorshDeserialize, BorshSerialize};
+use enumflags2::BitFlags;
 
 pub fn handle_initialize_user(
     ctx: Context<InitializeUser>,
@@ -89,6 +128,10 @@ pub fn handle_initialize_user(
             Pubkey::default()
         };
 
+        if !referrer.eq(&Pubkey::default()) {
+            user_stats.referred_ts = Clock::get()?.unix_timestamp.cast()?;
+        }
+
         user_stats.referrer = referrer;
     }
 
@@ -156,6 +199,47 @@ pub fn handle_initialize_user_stats(ctx: Context<InitializeUserStats>) -> Result
     Ok(())
 }
 
+/// Reallocs a user account created before Order, PerpPosition and the order slots grew and
+/// rewrites it in the current layout. Permissionless, the payer covers the extra rent
+pub fn handle_migrate_user(ctx: Context<MigrateUser>) -> Result<()> {
+    let user_account_info = ctx.accounts.user.to_account_info();
+
+    let legacy_user = {
+        let data = user_account_info.try_borrow_data()?;
+        validate!(
+            data.len() == LEGACY_USER_SIZE,
+            ErrorCode::CouldNotLoadUserData,
+            "user account is {} bytes, only {} byte accounts need migrating",
+            data.len(),
+            LEGACY_USER_SIZE
+        )?;
+        LegacyUser::read(&data[8..])?
+    };
+
+    let minimum_balance = Rent::get()?.minimum_balance(User::SIZE);
+    let lamports = user_account_info.lamports();
+    if minimum_balance > lamports {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: user_account_info.clone(),
+                },
+            ),
+            minimum_balance.safe_sub(lamports)?,
+        )?;
+    }
+
+    user_account_info.realloc(User::SIZE, false)?;
+    user_account_info.try_borrow_mut_data()?[8..].fill(0);
+
+    let user = &mut load_mut!(ctx.accounts.user)?;
+    legacy_user.migrate(user);
+
+    Ok(())
+}
+
 pub fn handle_initialize_referrer_name(
     ctx: Context<InitializeReferrerName>,
     name: [u8; 32],
@@ -184,6 +268,20 @@ pub fn handle_initialize_referrer_name(
     Ok(())
 }
 
+pub fn handle_initialize_signed_order_nonce(
+    ctx: Context<InitializeSignedOrderNonce>,
+) -> Result<()> {
+    let mut signed_order_nonce = ctx
+        .accounts
+        .signed_order_nonce
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    signed_order_nonce.user = ctx.accounts.user.key();
+
+    Ok(())
+}
+
 #[access_control(
     deposit_not_paused(&ctx.accounts.state)
 )]
@@ -193,26 +291,112 @@ pub fn handle_deposit(
     amount: u64,
     reduce_only: bool,
 ) -> Result<()> {
-    let user_key = ctx.accounts.user.key();
-    let user = &mut load_mut!(ctx.accounts.user)?;
+    deposit_into_spot_position(
+        &ctx.accounts.state,
+        &ctx.accounts.user,
+        &ctx.accounts.authority,
+        &ctx.accounts.spot_market_vault,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.token_program,
+        ctx.remaining_accounts,
+        market_index,
+        amount,
+        reduce_only,
+        DepositExplanation::None,
+    )
+}
+
+#[access_control(
+    deposit_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_deposit_into_user(
+    ctx: Context<DepositIntoUser>,
+    market_index: u16,
+    amount: u64,
+) -> Result<()> {
+    load!(ctx.accounts.user)?.validate_third_party_deposit(market_index)?;
+
+    deposit_into_spot_position(
+        &ctx.accounts.state,
+        &ctx.accounts.user,
+        &ctx.accounts.authority,
+        &ctx.accounts.spot_market_vault,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.token_program,
+        ctx.remaining_accounts,
+        market_index,
+        amount,
+        false,
+        DepositExplanation::ThirdParty,
+    )
+}
+
+/// moves amount from the authority's token account into the user's spot position, the authority
+/// only has to own the token account
+fn deposit_into_spot_position<'info>(
+    state: &State,
+    user_loader: &AccountLoader<'info, User>,
+    authority: &AccountInfo<'info>,
+    spot_market_vault: &AccountInfo<'info>,
+    user_token_account: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    market_index: u16,
+    amount: u64,
+    reduce_only: bool,
+    explanation: DepositExplanation,
+) -> Result<()> {
+    let user_key = user_loader.key();
+    let user = &mut load_mut!(user_loader)?;
 
-    let state = &ctx.accounts.state;
     let clock = Clock::get()?;
     let now = clock.unix_timestamp;
     let slot = clock.slot;
 
+    let remaining_accounts_iter = &mut remaining_accounts.iter().peekable();
     let AccountMaps {
         perp_market_map,
         spot_market_map,
         mut oracle_map,
     } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
+        remaining_accounts_iter,
         &MarketSet::new(),
         &get_writable_spot_market_set(market_index),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
 
+    let mint = get_token_mint(
+        remaining_accounts_iter,
+        &spot_market_map.get_ref(&market_index)?,
+    )?;
+
+    let vault_amount_before = {
+        let spot_market = spot_market_map.get_ref(&market_index)?;
+        validate!(
+            token_program.key == &spot_market.get_token_program(),
+            ErrorCode::InvalidTokenProgram,
+            "token program does not match spot market"
+        )?;
+
+        let spot_market_vault = load_token_account(spot_market_vault, token_program.key)?;
+        let user_token_account = load_token_account(user_token_account, token_program.key)?;
+
+        validate!(
+            user_token_account.mint == spot_market_vault.mint,
+            ErrorCode::InvalidTokenAccount,
+            "user token account mint does not match spot market vault"
+        )?;
+
+        validate!(
+            user_token_account.owner == authority.key(),
+            ErrorCode::InvalidTokenAccount,
+            "user token account not owned by authority"
+        )?;
+
+        spot_market_vault.amount
+    };
+
     if amount == 0 {
         return Err(ErrorCode::InsufficientDeposit.into());
     }
@@ -228,6 +412,13 @@ pub fn handle_deposit(
         "Market is being initialized"
     )?;
 
+    validate!(
+        !spot_market.is_operation_paused(SpotOperation::Deposit)?,
+        ErrorCode::MarketActionPaused,
+        "Spot Market {} deposits are currently paused",
+        spot_market.market_index
+    )?;
+
     controller::spot_balance::update_spot_market_cumulative_interest(
         &mut spot_market,
         Some(oracle_price_data),
@@ -238,8 +429,20 @@ pub fn handle_deposit(
 
     let force_reduce_only = spot_market.is_reduce_only()?;
 
+    // u64::MAX repays the entire borrow, rounded up so no dust is left behind
+    let repay_all = amount == ALL_TOKEN_AMOUNT;
+    if repay_all {
+        validate!(
+            user.spot_positions[position_index].balance_type == SpotBalanceType::Borrow
+                && user.spot_positions[position_index].scaled_balance > 0,
+            ErrorCode::InvalidSpotPosition,
+            "user has no borrow to repay in spot market {}",
+            market_index
+        )?;
+    }
+
     // if reduce only, have to compare ix amount to current borrow amount
-    let amount = if (force_reduce_only || reduce_only)
+    let amount = if (force_reduce_only || reduce_only || repay_all)
         && user.spot_positions[position_index].balance_type == SpotBalanceType::Borrow
     {
         user.spot_positions[position_index]
@@ -250,6 +453,20 @@ pub fn handle_deposit(
         amount
     };
 
+    // the vault receives the amount net of any token-2022 transfer fee
+    let transfer_amount = match &mint {
+        Some(mint) if repay_all => calculate_pre_transfer_fee_amount(mint, amount)?,
+        _ => amount,
+    };
+    let amount = match &mint {
+        Some(mint) => transfer_amount.safe_sub(calculate_transfer_fee(mint, transfer_amount)?)?,
+        None => transfer_amount,
+    };
+
+    if amount == 0 {
+        return Err(ErrorCode::InsufficientDeposit.into());
+    }
+
     user.increment_total_deposits(
         amount,
         oracle_price_data.price,
@@ -280,6 +497,16 @@ pub fn handle_deposit(
         )?;
     }
 
+    if repay_all {
+        validate!(
+            spot_position.balance_type == SpotBalanceType::Deposit
+                || spot_position.scaled_balance == 0,
+            ErrorCode::InvalidSpotPosition,
+            "repay all left user with borrow. scaled balance = {}",
+            spot_position.scaled_balance
+        )?;
+    }
+
     if spot_position.balance_type == SpotBalanceType::Deposit && spot_position.scaled_balance > 0 {
         validate!(
             matches!(
@@ -296,6 +523,8 @@ pub fn handle_deposit(
     }
 
     drop(spot_market);
+    user.reset_spot_position_if_empty(position_index);
+
     if user.is_being_liquidated() {
         // try to update liquidation status if user is was already being liq'd
         let is_being_liquidated = is_user_being_liquidated(
@@ -317,14 +546,23 @@ pub fn handle_deposit(
 
     let spot_market = &mut spot_market_map.get_ref_mut(&market_index)?;
 
-    controller::token::receive(
-        &ctx.accounts.token_program,
-        &ctx.accounts.user_token_account,
-        &ctx.accounts.spot_market_vault,
-        &ctx.accounts.authority,
-        amount,
+    controller::token::receive_with_mint(
+        token_program,
+        user_token_account,
+        spot_market_vault,
+        authority,
+        transfer_amount,
+        mint.as_ref(),
+    )?;
+
+    let vault_amount_after = load_token_account(spot_market_vault, token_program.key)?.amount;
+    validate!(
+        vault_amount_after.safe_sub(vault_amount_before)? == amount,
+        ErrorCode::InvalidTokenAccount,
+        "spot market vault received {} expected {}",
+        vault_amount_after.safe_sub(vault_amount_before)?,
+        amount
     )?;
-    ctx.accounts.spot_market_vault.reload()?;
 
     let deposit_record_id = get_then_update_id!(spot_market, next_deposit_record_id);
     let oracle_price = oracle_price_data.price;
@@ -343,7 +581,7 @@ pub fn handle_deposit(
         total_deposits_after,
         total_withdraws_after,
         market_index,
-        explanation: DepositExplanation::None,
+        explanation,
         transfer_user: None,
     };
     emit!(deposit_record);
@@ -382,18 +620,56 @@ pub fn handle_withdraw(
     let slot = clock.slot;
     let state = &ctx.accounts.state;
 
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
         perp_market_map,
         spot_market_map,
         mut oracle_map,
     } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
+        remaining_accounts_iter,
         &MarketSet::new(),
         &get_writable_spot_market_set(market_index),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
 
+    let mint = get_token_mint(
+        remaining_accounts_iter,
+        &spot_market_map.get_ref(&market_index)?,
+    )?;
+
+    {
+        let spot_market = spot_market_map.get_ref(&market_index)?;
+        validate!(
+            ctx.accounts.token_program.key == &spot_market.get_token_program(),
+            ErrorCode::InvalidTokenProgram,
+            "token program does not match spot market"
+        )?;
+
+        let spot_market_vault = load_token_account(
+            &ctx.accounts.spot_market_vault,
+            ctx.accounts.token_program.key,
+        )?;
+        let user_token_account = load_token_account(
+            &ctx.accounts.user_token_account,
+            ctx.accounts.token_program.key,
+        )?;
+
+        validate!(
+            user_token_account.mint == spot_market_vault.mint,
+            ErrorCode::InvalidTokenAccount,
+            "user token account mint does not match spot market vault"
+        )?;
+
+        // delegates can only send withdrawals back to the authority
+        validate!(
+            ctx.accounts.authority.key() == user.authority
+                || user_token_account.owner == user.authority,
+            ErrorCode::InvalidTokenAccount,
+            "delegate withdrawals must go to a token account owned by the user authority"
+        )?;
+    }
+
     validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
 
     let spot_market_is_reduce_only = {
@@ -410,7 +686,9 @@ pub fn handle_withdraw(
     };
 
     let amount = {
-        let reduce_only = reduce_only || spot_market_is_reduce_only;
+        // u64::MAX withdraws the entire deposit
+        let withdraw_all = amount == ALL_TOKEN_AMOUNT;
+        let reduce_only = reduce_only || spot_market_is_reduce_only || withdraw_all;
 
         let position_index = user.force_get_spot_position_index(market_index)?;
 
@@ -457,6 +735,8 @@ pub fn handle_withdraw(
             user,
         )?;
 
+        user.reset_spot_position_if_empty(position_index);
+
         amount
     };
 
@@ -492,20 +772,25 @@ pub fn handle_withdraw(
     };
     emit!(deposit_record);
 
-    controller::token::send_from_program_vault(
+    // any token-2022 transfer fee is withheld from what the user receives
+    controller::token::send_from_program_vault_with_mint(
         &ctx.accounts.token_program,
         &ctx.accounts.spot_market_vault,
         &ctx.accounts.user_token_account,
         &ctx.accounts.drift_signer,
         state.signer_nonce,
         amount,
+        mint.as_ref(),
     )?;
 
-    // reload the spot market vault balance so it's up-to-date
-    ctx.accounts.spot_market_vault.reload()?;
+    // read the spot market vault balance after the transfer so it's up-to-date
     math::spot_withdraw::validate_spot_market_vault_amount(
         &spot_market,
-        ctx.accounts.spot_market_vault.amount,
+        load_token_account(
+            &ctx.accounts.spot_market_vault,
+            ctx.accounts.token_program.key,
+        )?
+        .amount,
     )?;
 
     Ok(())
@@ -526,11 +811,67 @@ pub fn handle_transfer_deposit(
 
     let state = &ctx.accounts.state;
     let clock = Clock::get()?;
-    let slot = clock.slot;
 
     let to_user = &mut load_mut!(ctx.accounts.to_user)?;
     let from_user = &mut load_mut!(ctx.accounts.from_user)?;
 
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &get_writable_spot_market_set(market_index),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    transfer_deposit_between_users(
+        from_user,
+        from_user_key,
+        to_user,
+        to_user_key,
+        *authority_key,
+        market_index,
+        amount,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        &clock,
+    )?;
+
+    let spot_market = spot_market_map.get_ref(&market_index)?;
+    math::spot_withdraw::validate_spot_market_vault_amount(
+        &spot_market,
+        load_token_account(
+            &ctx.accounts.spot_market_vault,
+            ctx.accounts.spot_market_vault.owner,
+        )?
+        .amount,
+    )?;
+
+    Ok(())
+}
+
+/// moves a spot deposit from one user to another, the from user must still meet its initial
+/// margin requirement
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn transfer_deposit_between_users(
+    from_user: &mut User,
+    from_user_key: Pubkey,
+    to_user: &mut User,
+    to_user_key: Pubkey,
+    authority_key: Pubkey,
+    market_index: u16,
+    amount: u64,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    clock: &Clock,
+) -> DriftResult {
+    let slot = clock.slot;
+
     validate!(
         !to_user.is_bankrupt(),
         ErrorCode::UserBankrupt,
@@ -548,18 +889,6 @@ pub fn handle_transfer_deposit(
         "cant transfer between the same user account"
     )?;
 
-    let AccountMaps {
-        perp_market_map,
-        spot_market_map,
-        mut oracle_map,
-    } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
-        &MarketSet::new(),
-        &get_writable_spot_market_set(market_index),
-        clock.slot,
-        Some(state.oracle_guard_rails),
-    )?;
-
     {
         let spot_market = &mut spot_market_map.get_ref_mut(&market_index)?;
         let oracle_price_data = oracle_map.get_price_data(&spot_market.oracle)?;
@@ -571,7 +900,7 @@ pub fn handle_transfer_deposit(
     }
 
     let oracle_price = {
-        let spot_market = &spot_market_map.get_ref(&market_index)?;
+        let spot_market = spot_market_map.get_ref(&market_index)?;
         oracle_map.get_price_data(&spot_market.oracle)?.price
     };
 
@@ -609,17 +938,12 @@ pub fn handle_transfer_deposit(
     }
 
     validate!(
-        meets_withdraw_margin_requirement(
-            from_user,
-            &perp_market_map,
-            &spot_market_map,
-            &mut oracle_map,
-        )?,
+        meets_withdraw_margin_requirement(from_user, perp_market_map, spot_market_map, oracle_map)?,
         ErrorCode::InsufficientCollateral,
         "From user does not meet initial margin requirement"
     )?;
 
-    validate_spot_margin_trading(from_user, &spot_market_map, &mut oracle_map)?;
+    validate_spot_margin_trading(from_user, spot_market_map, oracle_map)?;
 
     from_user.status = UserStatus::Active;
 
@@ -632,7 +956,7 @@ pub fn handle_transfer_deposit(
         let deposit_record = DepositRecord {
             ts: clock.unix_timestamp,
             deposit_record_id,
-            user_authority: *authority_key,
+            user_authority: authority_key,
             user: from_user_key,
             direction: DepositDirection::Withdraw,
             amount,
@@ -688,7 +1012,7 @@ pub fn handle_transfer_deposit(
         let deposit_record = DepositRecord {
             ts: clock.unix_timestamp,
             deposit_record_id,
-            user_authority: *authority_key,
+            user_authority: authority_key,
             user: to_user_key,
             direction: DepositDirection::Deposit,
             amount,
@@ -708,55 +1032,28 @@ pub fn handle_transfer_deposit(
 
     to_user.update_last_active_slot(slot);
 
-    let spot_market = spot_market_map.get_ref(&market_index)?;
-    math::spot_withdraw::validate_spot_market_vault_amount(
-        &spot_market,
-        ctx.accounts.spot_market_vault.amount,
-    )?;
-
     Ok(())
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
-pub struct OrderParams {
-    pub order_type: OrderType,
-    pub market_type: MarketType,
-    pub direction: PositionDirection,
-    pub user_order_id: u8,
-    pub base_asset_amount: u64,
-    pub price: u64,
-    pub market_index: u16,
-    pub reduce_only: bool,
-    pub post_only: PostOnlyParam,
-    pub immediate_or_cancel: bool,
-    pub max_ts: Option<i64>,
-    pub trigger_price: Option<u64>,
-    pub trigger_condition: OrderTriggerCondition,
-    pub oracle_price_offset: Option<i32>,
-    pub auction_duration: Option<u8>,
-    pub auction_start_price: Option<i64>,
-    pub auction_end_price: Option<i64>,
-}
-
-#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
-pub enum PostOnlyParam {
-    None,
-    MustPostOnly, // Tx fails if order can't be post only
-    TryPostOnly,  // Tx succeeds and order not placed if can't be post only
-}
-
-impl Default for PostOnlyParam {
-    fn default() -> Self {
-        PostOnlyParam::None
-    }
-}
-
 #[access_control(
-    exchange_not_paused(&ctx.accounts.state)
+    deposit_not_paused(&ctx.accounts.state)
+    withdraw_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_place_perp_order(ctx: Context<PlaceOrder>, params: OrderParams) -> Result<()> {
-    let clock = &Clock::get()?;
+pub fn handle_transfer_positions_between_subaccounts(
+    ctx: Context<TransferPositions>,
+) -> anchor_lang::Result<()> {
+    let authority_key = ctx.accounts.authority.key;
+    let to_user_key = ctx.accounts.to_user.key();
+    let from_user_key = ctx.accounts.from_user.key();
+
     let state = &ctx.accounts.state;
+    let clock = Clock::get()?;
+
+    let to_user = &mut load_mut!(ctx.accounts.to_user)?;
+    let from_user = &mut load_mut!(ctx.accounts.from_user)?;
+
+    let spot_market_indexes =
+        controller::subaccount::get_transferable_spot_market_indexes(from_user);
 
     let AccountMaps {
         perp_market_map,
@@ -765,240 +1062,353 @@ pub fn handle_place_perp_order(ctx: Context<PlaceOrder>, params: OrderParams) ->
     } = load_maps(
         &mut ctx.remaining_accounts.iter().peekable(),
         &MarketSet::new(),
-        &MarketSet::new(),
+        &get_writable_spot_market_set_from_many(spot_market_indexes),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
 
-    if params.immediate_or_cancel {
-        msg!("immediate_or_cancel order must be in place_and_make or place_and_take");
-        return Err(print_error!(ErrorCode::InvalidOrderIOC)().into());
-    }
-
-    controller::orders::place_perp_order(
-        &ctx.accounts.state,
-        &ctx.accounts.user,
+    controller::subaccount::transfer_positions_between_subaccounts(
+        authority_key,
+        &from_user_key,
+        from_user,
+        &to_user_key,
+        to_user,
         &perp_market_map,
         &spot_market_map,
         &mut oracle_map,
-        clock,
-        params,
+        clock.unix_timestamp,
+        clock.slot,
     )?;
 
     Ok(())
 }
 
-#[access_control(
-    exchange_not_paused(&ctx.accounts.state)
-)]
-pub fn handle_cancel_order(ctx: Context<CancelOrder>, order_id: Option<u32>) -> Result<()> {
-    let clock = &Clock::get()?;
-    let state = &ctx.accounts.state;
-
-    let AccountMaps {
-        perp_market_map,
-        spot_market_map,
-        mut oracle_map,
-    } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
-        &MarketSet::new(),
-        &MarketSet::new(),
-        clock.slot,
-        Some(state.oracle_guard_rails),
+fn validate_spot_market_token_accounts(
+    spot_market: &SpotMarket,
+    spot_market_vault: &AccountInfo,
+    user_token_account: &AccountInfo,
+    token_program: &AccountInfo,
+) -> Result<()> {
+    validate!(
+        token_program.key == &spot_market.get_token_program(),
+        ErrorCode::InvalidTokenProgram,
+        "token program does not match spot market"
     )?;
 
-    let order_id = match order_id {
-        Some(order_id) => order_id,
-        None => load!(ctx.accounts.user)?.get_last_order_id(),
-    };
+    validate!(
+        spot_market_vault.key == &spot_market.vault,
+        ErrorCode::InvalidTokenAccount,
+        "spot market vault does not match spot market"
+    )?;
 
-    controller::orders::cancel_order_by_order_id(
-        order_id,
-        &ctx.accounts.user,
-        &perp_market_map,
-        &spot_market_map,
-        &mut oracle_map,
-        clock,
+    let user_token_account = load_token_account(user_token_account, token_program.key)?;
+    validate!(
+        user_token_account.mint == spot_market.mint,
+        ErrorCode::InvalidTokenAccount,
+        "user token account mint does not match spot market"
     )?;
 
     Ok(())
 }
 
 #[access_control(
-    exchange_not_paused(&ctx.accounts.state)
+    withdraw_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_cancel_order_by_user_id(ctx: Context<CancelOrder>, user_order_id: u8) -> Result<()> {
-    let clock = &Clock::get()?;
+pub fn handle_begin_flash_borrow<'info>(
+    ctx: Context<'_, '_, '_, 'info, BeginFlashBorrow<'info>>,
+    market_index: u16,
+    amount: u64,
+) -> Result<()> {
+    let clock = Clock::get()?;
     let state = &ctx.accounts.state;
+    let spot_market = load!(ctx.accounts.spot_market)?;
 
-    let AccountMaps {
-        perp_market_map,
-        spot_market_map,
-        mut oracle_map,
-    } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
-        &MarketSet::new(),
-        &MarketSet::new(),
-        clock.slot,
-        Some(state.oracle_guard_rails),
+    validate!(
+        spot_market.flash_borrows_enabled,
+        ErrorCode::InvalidFlashBorrow,
+        "flash borrows are disabled for spot market {}",
+        market_index
     )?;
 
-    controller::orders::cancel_order_by_user_order_id(
-        user_order_id,
-        &ctx.accounts.user,
-        &perp_market_map,
-        &spot_market_map,
-        &mut oracle_map,
-        clock,
+    validate!(
+        spot_market.is_active(clock.unix_timestamp)?,
+        ErrorCode::InvalidFlashBorrow,
+        "spot market {} is not active",
+        market_index
+    )?;
+
+    validate!(
+        amount > 0,
+        ErrorCode::InvalidFlashBorrow,
+        "flash borrow amount must be greater than 0"
+    )?;
+
+    validate_spot_market_token_accounts(
+        &spot_market,
+        &ctx.accounts.spot_market_vault,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.token_program,
+    )?;
+
+    // repayment is enforced by the matching end_flash_borrow later in the transaction
+    let ix_sysvar = &ctx.accounts.ix_sysvar;
+    let ixs = load_transaction_instructions(ix_sysvar)?;
+    let current_index = load_current_index_checked(ix_sysvar)?;
+    validate_begin_flash_borrow(&ixs, current_index as usize, market_index, amount)?;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let mint = get_token_mint(remaining_accounts_iter, &spot_market)?;
+
+    msg!(
+        "flash borrow {} from spot market {} (fee {} bps)",
+        amount,
+        market_index,
+        spot_market.flash_borrow_fee_bps
+    );
+
+    drop(spot_market);
+
+    controller::token::send_from_program_vault_with_mint(
+        &ctx.accounts.token_program,
+        &ctx.accounts.spot_market_vault,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.drift_signer,
+        state.signer_nonce,
+        amount,
+        mint.as_ref(),
     )?;
 
     Ok(())
 }
 
-#[access_control(
-    exchange_not_paused(&ctx.accounts.state)
-)]
-pub fn handle_cancel_orders(
-    ctx: Context<CancelOrder>,
-    market_type: Option<MarketType>,
-    market_index: Option<u16>,
-    direction: Option<PositionDirection>,
+pub fn handle_end_flash_borrow<'info>(
+    ctx: Context<'_, '_, '_, 'info, EndFlashBorrow<'info>>,
+    market_index: u16,
+    amount: u64,
 ) -> Result<()> {
-    let clock = &Clock::get()?;
-    let state = &ctx.accounts.state;
+    let ix_sysvar = &ctx.accounts.ix_sysvar;
+    let ixs = load_transaction_instructions(ix_sysvar)?;
+    let current_index = load_current_index_checked(ix_sysvar)?;
+    validate_end_flash_borrow(&ixs, current_index as usize, market_index, amount)?;
 
-    let AccountMaps {
-        perp_market_map,
-        spot_market_map,
-        mut oracle_map,
-    } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
-        &MarketSet::new(),
-        &MarketSet::new(),
-        clock.slot,
-        Some(state.oracle_guard_rails),
+    let spot_market = &mut load_mut!(ctx.accounts.spot_market)?;
+
+    validate_spot_market_token_accounts(
+        spot_market,
+        &ctx.accounts.spot_market_vault,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.token_program,
     )?;
 
-    let user_key = ctx.accounts.user.key();
-    let mut user = load_mut!(ctx.accounts.user)?;
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let mint = get_token_mint(remaining_accounts_iter, spot_market)?;
 
-    cancel_orders(
-        &mut user,
-        &user_key,
-        None,
-        &perp_market_map,
-        &spot_market_map,
-        &mut oracle_map,
-        clock.unix_timestamp,
-        clock.slot,
-        OrderActionExplanation::None,
-        market_type,
-        market_index,
-        direction,
+    let fee = calculate_flash_borrow_fee(amount, spot_market.flash_borrow_fee_bps)?;
+    let repay_amount = amount.safe_add(fee)?;
+
+    let vault_amount_before = load_token_account(
+        &ctx.accounts.spot_market_vault,
+        ctx.accounts.token_program.key,
+    )?
+    .amount;
+
+    controller::token::receive_with_mint(
+        &ctx.accounts.token_program,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.spot_market_vault,
+        &ctx.accounts.authority,
+        repay_amount,
+        mint.as_ref(),
+    )?;
+
+    let vault_amount_after = load_token_account(
+        &ctx.accounts.spot_market_vault,
+        ctx.accounts.token_program.key,
+    )?
+    .amount;
+
+    // a token-2022 transfer fee withheld from the repayment leaves the vault short
+    let amount_repaid = vault_amount_after.safe_sub(vault_amount_before)?;
+    validate!(
+        amount_repaid >= repay_amount,
+        ErrorCode::FlashBorrowNotRepaid,
+        "flash borrow repaid {} to vault, expected {} (amount {} + fee {})",
+        amount_repaid,
+        repay_amount,
+        amount,
+        fee
     )?;
 
+    let cumulative_deposit_interest_delta =
+        calculate_cumulative_deposit_interest_delta_for_fee(fee.cast()?, spot_market)?;
+    spot_market.cumulative_deposit_interest = spot_market
+        .cumulative_deposit_interest
+        .safe_add(cumulative_deposit_interest_delta)?;
+
+    math::spot_withdraw::validate_spot_market_vault_amount(spot_market, vault_amount_after)?;
+
     Ok(())
 }
 
 #[access_control(
-    fill_not_paused(&ctx.accounts.state)
+    deposit_not_paused(&ctx.accounts.state)
+    withdraw_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_place_and_take_perp_order<'info>(
-    ctx: Context<PlaceAndTake>,
-    params: OrderParams,
-    _maker_order_id: Option<u32>,
+pub fn handle_begin_swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+    in_market_index: u16,
+    out_market_index: u16,
+    amount_in: u64,
 ) -> Result<()> {
-    let clock = Clock::get()?;
     let state = &ctx.accounts.state;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let user = &mut load_mut!(ctx.accounts.user)?;
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
+    validate!(
+        !user.is_being_liquidated(),
+        ErrorCode::UserIsBeingLiquidated
+    )?;
+
+    validate!(
+        in_market_index != out_market_index,
+        ErrorCode::InvalidSwap,
+        "in and out spot markets must be different"
+    )?;
+
+    validate!(
+        amount_in > 0,
+        ErrorCode::InvalidSwap,
+        "amount_in must be greater than 0"
+    )?;
+
+    // the margin check is deferred to end_swap, so no other drift instruction can run in between
+    let ix_sysvar = &ctx.accounts.ix_sysvar;
+    let ixs = load_transaction_instructions(ix_sysvar)?;
+    let current_index = load_current_index_checked(ix_sysvar)?;
+    validate_begin_swap(
+        &ixs,
+        current_index as usize,
+        in_market_index,
+        out_market_index,
+    )?;
 
     let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
-        perp_market_map,
         spot_market_map,
         mut oracle_map,
+        ..
     } = load_maps(
         remaining_accounts_iter,
-        &get_writable_perp_market_set(params.market_index),
         &MarketSet::new(),
+        &get_writable_spot_market_set_from_many(vec![in_market_index, out_market_index]),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
 
-    if params.post_only != PostOnlyParam::None {
-        msg!("post_only cant be used in place_and_take");
-        return Err(print_error!(ErrorCode::InvalidOrderPostOnly)().into());
-    }
+    let in_mint = get_token_mint(
+        remaining_accounts_iter,
+        &spot_market_map.get_ref(&in_market_index)?,
+    )?;
 
-    let (makers_and_referrer, makers_and_referrer_stats) = load_user_maps(remaining_accounts_iter)?;
+    for market_index in [in_market_index, out_market_index].iter() {
+        let spot_market = &mut spot_market_map.get_ref_mut(market_index)?;
+        let oracle_price_data = oracle_map.get_price_data(&spot_market.oracle)?;
 
-    let is_immediate_or_cancel = params.immediate_or_cancel;
+        controller::spot_balance::update_spot_market_cumulative_interest(
+            spot_market,
+            Some(oracle_price_data),
+            now,
+        )?;
+    }
 
-    controller::repeg::update_amm(
-        params.market_index,
-        &perp_market_map,
-        &mut oracle_map,
-        &ctx.accounts.state,
-        &Clock::get()?,
+    validate_spot_market_token_accounts(
+        &spot_market_map.get_ref(&in_market_index)?,
+        &ctx.accounts.in_spot_market_vault,
+        &ctx.accounts.in_token_account,
+        &ctx.accounts.in_token_program,
     )?;
 
-    controller::orders::place_perp_order(
-        &ctx.accounts.state,
-        &ctx.accounts.user,
-        &perp_market_map,
-        &spot_market_map,
-        &mut oracle_map,
-        &Clock::get()?,
-        params,
+    validate_spot_market_token_accounts(
+        &spot_market_map.get_ref(&out_market_index)?,
+        &ctx.accounts.out_spot_market_vault,
+        &ctx.accounts.out_token_account,
+        &ctx.accounts.out_token_program,
     )?;
 
-    let user = &mut ctx.accounts.user;
-    let order_id = load!(user)?.get_last_order_id();
+    user.swap_in_initial_token_amount = load_token_account(
+        &ctx.accounts.in_token_account,
+        ctx.accounts.in_token_program.key,
+    )?
+    .amount;
 
-    controller::orders::fill_perp_order(
-        order_id,
-        &ctx.accounts.state,
+    user.swap_out_initial_token_amount = load_token_account(
+        &ctx.accounts.out_token_account,
+        ctx.accounts.out_token_program.key,
+    )?
+    .amount;
+
+    let in_spot_market = &mut spot_market_map.get_ref_mut(&in_market_index)?;
+
+    user.force_get_spot_position_index(in_market_index)?;
+
+    // prevents withdraw when limits hit
+    controller::spot_position::update_spot_balances_and_cumulative_deposits_with_limits(
+        amount_in as u128,
+        &SpotBalanceType::Borrow,
+        in_spot_market,
         user,
-        &ctx.accounts.user_stats,
-        &spot_market_map,
-        &perp_market_map,
-        &mut oracle_map,
-        &user.clone(),
-        &ctx.accounts.user_stats.clone(),
-        &makers_and_referrer,
-        &makers_and_referrer_stats,
-        None,
-        &Clock::get()?,
     )?;
 
-    let order_exists = load!(ctx.accounts.user)?
-        .orders
-        .iter()
-        .any(|order| order.order_id == order_id);
+    controller::token::send_from_program_vault_with_mint(
+        &ctx.accounts.in_token_program,
+        &ctx.accounts.in_spot_market_vault,
+        &ctx.accounts.in_token_account,
+        &ctx.accounts.drift_signer,
+        state.signer_nonce,
+        amount_in,
+        in_mint.as_ref(),
+    )?;
 
-    if is_immediate_or_cancel && order_exists {
-        controller::orders::cancel_order_by_order_id(
-            order_id,
-            &ctx.accounts.user,
-            &perp_market_map,
-            &spot_market_map,
-            &mut oracle_map,
-            &Clock::get()?,
-        )?;
-    }
+    math::spot_withdraw::validate_spot_market_vault_amount(
+        in_spot_market,
+        load_token_account(
+            &ctx.accounts.in_spot_market_vault,
+            ctx.accounts.in_token_program.key,
+        )?
+        .amount,
+    )?;
 
     Ok(())
 }
 
 #[access_control(
-    fill_not_paused(&ctx.accounts.state)
+    deposit_not_paused(&ctx.accounts.state)
+    withdraw_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_place_and_make_perp_order<'a, 'b, 'c, 'info>(
-    ctx: Context<'a, 'b, 'c, 'info, PlaceAndMake<'info>>,
-    params: OrderParams,
-    taker_order_id: u32,
+pub fn handle_end_swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+    in_market_index: u16,
+    out_market_index: u16,
 ) -> Result<()> {
-    let clock = &Clock::get()?;
     let state = &ctx.accounts.state;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let slot = clock.slot;
+
+    let user_key = ctx.accounts.user.key();
+    let user = &mut load_mut!(ctx.accounts.user)?;
+
+    let ix_sysvar = &ctx.accounts.ix_sysvar;
+    let ixs = load_transaction_instructions(ix_sysvar)?;
+    let current_index = load_current_index_checked(ix_sysvar)?;
+    let amount_in = validate_end_swap(
+        &ixs,
+        current_index as usize,
+        in_market_index,
+        out_market_index,
+    )?;
 
     let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
@@ -1007,126 +1417,394 @@ pub fn handle_place_and_make_perp_order<'a, 'b, 'c, 'info>(
         mut oracle_map,
     } = load_maps(
         remaining_accounts_iter,
-        &get_writable_perp_market_set(params.market_index),
         &MarketSet::new(),
-        Clock::get()?.slot,
+        &get_writable_spot_market_set_from_many(vec![in_market_index, out_market_index]),
+        slot,
         Some(state.oracle_guard_rails),
     )?;
 
-    if !params.immediate_or_cancel
-        || params.post_only == PostOnlyParam::None
-        || params.order_type != OrderType::Limit
-    {
-        msg!("place_and_make must use IOC post only limit order");
-        return Err(print_error!(ErrorCode::InvalidOrderIOCPostOnly)().into());
-    }
+    let in_mint = get_token_mint(
+        remaining_accounts_iter,
+        &spot_market_map.get_ref(&in_market_index)?,
+    )?;
+    let out_mint = get_token_mint(
+        remaining_accounts_iter,
+        &spot_market_map.get_ref(&out_market_index)?,
+    )?;
 
-    controller::repeg::update_amm(
-        params.market_index,
-        &perp_market_map,
-        &mut oracle_map,
-        state,
-        clock,
+    validate_spot_market_token_accounts(
+        &spot_market_map.get_ref(&in_market_index)?,
+        &ctx.accounts.in_spot_market_vault,
+        &ctx.accounts.in_token_account,
+        &ctx.accounts.in_token_program,
     )?;
 
-    controller::orders::place_perp_order(
-        state,
-        &ctx.accounts.user,
-        &perp_market_map,
-        &spot_market_map,
-        &mut oracle_map,
-        clock,
-        params,
+    validate_spot_market_token_accounts(
+        &spot_market_map.get_ref(&out_market_index)?,
+        &ctx.accounts.out_spot_market_vault,
+        &ctx.accounts.out_token_account,
+        &ctx.accounts.out_token_program,
     )?;
 
-    let (order_id, authority) = {
-        let user = load!(ctx.accounts.user)?;
-        let order_id = user.get_last_order_id();
-        (order_id, user.authority)
+    let in_token_amount = load_token_account(
+        &ctx.accounts.in_token_account,
+        ctx.accounts.in_token_program.key,
+    )?
+    .amount;
+    let out_token_amount = load_token_account(
+        &ctx.accounts.out_token_account,
+        ctx.accounts.out_token_program.key,
+    )?
+    .amount;
+
+    validate!(
+        out_token_amount > user.swap_out_initial_token_amount,
+        ErrorCode::InvalidSwap,
+        "out token account balance {} did not increase from {}",
+        out_token_amount,
+        user.swap_out_initial_token_amount
+    )?;
+
+    let amount_out = out_token_amount.safe_sub(user.swap_out_initial_token_amount)?;
+    // whatever the route didn't spend goes back to the in market
+    let amount_in_unused = in_token_amount
+        .saturating_sub(user.swap_in_initial_token_amount)
+        .min(amount_in);
+
+    user.swap_in_initial_token_amount = 0;
+    user.swap_out_initial_token_amount = 0;
+
+    let out_oracle_price = {
+        let out_spot_market = &mut spot_market_map.get_ref_mut(&out_market_index)?;
+        let deposit_amount = receive_into_spot_market_vault(
+            &ctx.accounts.out_token_program,
+            &ctx.accounts.out_token_account,
+            &ctx.accounts.out_spot_market_vault,
+            &ctx.accounts.authority,
+            amount_out,
+            out_mint.as_ref(),
+        )?;
+        deposit_swap_amount(deposit_amount, out_spot_market, user)?;
+
+        oracle_map.get_price_data(&out_spot_market.oracle)?.price
     };
 
-    let (mut makers_and_referrer, mut makers_and_referrer_stats) =
-        load_user_maps(remaining_accounts_iter)?;
-    makers_and_referrer.insert(ctx.accounts.user.key(), ctx.accounts.user.clone())?;
-    makers_and_referrer_stats.insert(authority, ctx.accounts.user_stats.clone())?;
+    let in_oracle_price = {
+        let in_spot_market = &mut spot_market_map.get_ref_mut(&in_market_index)?;
+        if amount_in_unused > 0 {
+            let deposit_amount = receive_into_spot_market_vault(
+                &ctx.accounts.in_token_program,
+                &ctx.accounts.in_token_account,
+                &ctx.accounts.in_spot_market_vault,
+                &ctx.accounts.authority,
+                amount_in_unused,
+                in_mint.as_ref(),
+            )?;
+            deposit_swap_amount(deposit_amount, in_spot_market, user)?;
+        }
 
-    controller::orders::fill_perp_order(
-        taker_order_id,
-        state,
-        &ctx.accounts.taker,
-        &ctx.accounts.taker_stats,
-        &spot_market_map,
-        &perp_market_map,
-        &mut oracle_map,
-        &ctx.accounts.user.clone(),
-        &ctx.accounts.user_stats.clone(),
-        &makers_and_referrer,
-        &makers_and_referrer_stats,
-        Some(order_id),
-        clock,
-    )?;
+        oracle_map.get_price_data(&in_spot_market.oracle)?.price
+    };
 
-    let order_exists = load!(ctx.accounts.user)?
-        .orders
-        .iter()
-        .any(|order| order.order_id == order_id);
+    emit!(SwapRecord {
+        ts: now,
+        user: user_key,
+        amount_out,
+        amount_in: amount_in.safe_sub(amount_in_unused)?,
+        out_market_index,
+        in_market_index,
+        out_oracle_price,
+        in_oracle_price,
+    });
 
-    if order_exists {
-        controller::orders::cancel_order_by_order_id(
-            order_id,
-            &ctx.accounts.user,
+    validate!(
+        meets_withdraw_margin_requirement(
+            user,
             &perp_market_map,
             &spot_market_map,
-            &mut oracle_map,
-            clock,
-        )?;
-    }
+            &mut oracle_map
+        )?,
+        ErrorCode::InsufficientCollateral,
+        "User does not meet initial margin requirement after swap"
+    )?;
+
+    validate_spot_margin_trading(user, &spot_market_map, &mut oracle_map)?;
+
+    user.update_last_active_slot(slot);
+
+    math::spot_withdraw::validate_spot_market_vault_amount(
+        &spot_market_map.get_ref(&in_market_index)?,
+        load_token_account(
+            &ctx.accounts.in_spot_market_vault,
+            ctx.accounts.in_token_program.key,
+        )?
+        .amount,
+    )?;
+
+    math::spot_withdraw::validate_spot_market_vault_amount(
+        &spot_market_map.get_ref(&out_market_index)?,
+        load_token_account(
+            &ctx.accounts.out_spot_market_vault,
+            ctx.accounts.out_token_program.key,
+        )?
+        .amount,
+    )?;
 
     Ok(())
 }
 
-pub fn handle_place_spot_order(ctx: Context<PlaceOrder>, params: OrderParams) -> Result<()> {
+// returns the amount that landed in the vault, net of any token-2022 transfer fee
+fn receive_into_spot_market_vault<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    spot_market_vault: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    mint: Option<&AccountInfo<'info>>,
+) -> Result<u64> {
+    let vault_amount_before = load_token_account(spot_market_vault, token_program.key)?.amount;
+
+    controller::token::receive_with_mint(
+        token_program,
+        from,
+        spot_market_vault,
+        authority,
+        amount,
+        mint,
+    )?;
+
+    let vault_amount_after = load_token_account(spot_market_vault, token_program.key)?.amount;
+
+    Ok(vault_amount_after.safe_sub(vault_amount_before)?)
+}
+
+fn deposit_swap_amount(amount: u64, spot_market: &mut SpotMarket, user: &mut User) -> Result<()> {
+    let position_index = user.force_get_spot_position_index(spot_market.market_index)?;
+
+    controller::spot_position::update_spot_balances_and_cumulative_deposits(
+        amount as u128,
+        &SpotBalanceType::Deposit,
+        spot_market,
+        &mut user.spot_positions[position_index],
+        false,
+        None,
+    )?;
+
+    let deposits_token_amount = get_token_amount(
+        spot_market.deposit_balance,
+        spot_market,
+        &SpotBalanceType::Deposit,
+    )?;
+
+    validate!(
+        spot_market.max_token_deposits == 0
+            || deposits_token_amount <= spot_market.max_token_deposits.cast()?,
+        ErrorCode::MaxDeposit,
+        "max deposits: {} new deposits {}",
+        spot_market.max_token_deposits,
+        deposits_token_amount
+    )?;
+
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct OrderParams {
+    pub order_type: OrderType,
+    pub market_type: MarketType,
+    pub direction: PositionDirection,
+    pub user_order_id: u8,
+    pub base_asset_amount: u64,
+    pub price: u64,
+    pub market_index: u16,
+    pub reduce_only: bool,
+    pub post_only: PostOnlyParam,
+    pub immediate_or_cancel: bool,
+    pub max_ts: Option<i64>,
+    pub trigger_price: Option<u64>,
+    pub trigger_condition: OrderTriggerCondition,
+    pub oracle_price_offset: Option<i32>,
+    pub auction_duration: Option<u8>,
+    pub auction_start_price: Option<i64>,
+    pub auction_end_price: Option<i64>,
+    pub trail_type: OrderTrailType,
+    pub trail_amount: Option<u64>,
+    pub twap_interval: Option<u32>,
+    pub twap_slice_count: Option<u8>,
+    pub display_quantity: Option<u64>,
+    pub trigger_funding_rate: Option<i64>,
+    pub auction_curve: AuctionCurve,
+    pub auction_step_count: Option<u8>,
+    pub builder_fee_share: Option<u8>,
+    /// resize the order to the max base asset amount the user's margin allows each time it fills
+    pub max_margin_sizing: bool,
+    /// cancel the open order using user_order_id instead of rejecting this one
+    pub replace_existing_user_order_id: bool,
+}
+
+/// narrows cancel_orders_with_filter beyond market and direction
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct CancelOrdersFilter {
+    /// inclusive order id range
+    pub min_order_id: Option<u32>,
+    pub max_order_id: Option<u32>,
+    /// only cancel trigger market and trigger limit orders, e.g. stop losses and take profits
+    pub only_trigger_orders: bool,
+}
+
+impl CancelOrdersFilter {
+    pub fn matches(&self, order: &Order) -> bool {
+        if let Some(min_order_id) = self.min_order_id {
+            if order.order_id < min_order_id {
+                return false;
+            }
+        }
+
+        if let Some(max_order_id) = self.max_order_id {
+            if order.order_id > max_order_id {
+                return false;
+            }
+        }
+
+        !self.only_trigger_orders || order.must_be_triggered()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ModifyOrderParams {
+    pub base_asset_amount: Option<u64>,
+    pub price: Option<u64>,
+    pub post_only: Option<PostOnlyParam>,
+}
+
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub enum PostOnlyParam {
+    None,
+    MustPostOnly, // Tx fails if order can't be post only
+    TryPostOnly,  // Tx succeeds and order not placed if can't be post only
+}
+
+impl Default for PostOnlyParam {
+    fn default() -> Self {
+        PostOnlyParam::None
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ScaleOrderParams {
+    pub market_type: MarketType,
+    pub direction: PositionDirection,
+    pub market_index: u16,
+    pub total_base_asset_amount: u64,
+    pub start_price: u64,
+    pub end_price: u64,
+    pub order_count: u8,
+    pub size_distribution: SizeDistribution,
+    pub reduce_only: bool,
+    pub post_only: PostOnlyParam,
+    pub max_ts: Option<i64>,
+}
+
+#[access_control(
+    exchange_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_place_perp_order(ctx: Context<PlaceOrder>, params: OrderParams) -> Result<()> {
+    let can_cancel_orders = can_sign_for_user_with_permission(
+        &ctx.accounts.user,
+        &ctx.accounts.authority,
+        DelegatePermission::CancelOrders,
+    )?;
+
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
         perp_market_map,
         spot_market_map,
         mut oracle_map,
     } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
+        remaining_accounts_iter,
         &MarketSet::new(),
         &MarketSet::new(),
-        Clock::get()?.slot,
-        None,
+        clock.slot,
+        Some(state.oracle_guard_rails),
     )?;
+    let market_whitelist_entry = get_market_whitelist_entry(remaining_accounts_iter)?;
 
     if params.immediate_or_cancel {
         msg!("immediate_or_cancel order must be in place_and_make or place_and_take");
         return Err(print_error!(ErrorCode::InvalidOrderIOC)().into());
     }
 
-    controller::orders::place_spot_order(
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        params.reduce_only,
+        clock.unix_timestamp,
+    )?;
+
+    controller::orders::place_perp_order(
         &ctx.accounts.state,
         &ctx.accounts.user,
         &perp_market_map,
         &spot_market_map,
         &mut oracle_map,
-        &Clock::get()?,
+        clock,
         params,
+        &mut PlaceOrderOptions {
+            market_whitelist_entry,
+            can_cancel_orders,
+            ..PlaceOrderOptions::default()
+        },
+    )?;
+
+    Ok(())
+}
+
+pub fn handle_initialize_session_key(
+    ctx: Context<InitializeSessionKey>,
+    session_signer: Pubkey,
+    expiry_ts: i64,
+    max_notional: u64,
+    perp_market_indexes: Vec<u16>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+
+    validate!(
+        expiry_ts > now,
+        ErrorCode::InvalidSessionKey,
+        "session key expiry {} must be in the future",
+        expiry_ts
     )?;
 
+    let mut session_key = ctx
+        .accounts
+        .session_key
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    session_key.user = ctx.accounts.user.key();
+    session_key.session_signer = session_signer;
+    session_key.expiry_ts = expiry_ts;
+    session_key.max_notional = max_notional;
+    session_key.set_perp_market_indexes(&perp_market_indexes)?;
+
+    Ok(())
+}
+
+pub fn handle_revoke_session_key(_ctx: Context<RevokeSessionKey>) -> Result<()> {
     Ok(())
 }
 
 #[access_control(
-    fill_not_paused(&ctx.accounts.state)
+    exchange_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_place_and_take_spot_order<'info>(
-    ctx: Context<PlaceAndTake>,
+pub fn handle_place_perp_order_with_session_key(
+    ctx: Context<PlaceOrderWithSessionKey>,
     params: OrderParams,
-    fulfillment_type: Option<SpotFulfillmentType>,
-    maker_order_id: Option<u32>,
 ) -> Result<()> {
-    let clock = Clock::get()?;
-    let market_index = params.market_index;
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
 
     let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
@@ -1135,127 +1813,217 @@ pub fn handle_place_and_take_spot_order<'info>(
         mut oracle_map,
     } = load_maps(
         remaining_accounts_iter,
-        &get_writable_perp_market_set(params.market_index),
+        &MarketSet::new(),
         &MarketSet::new(),
         clock.slot,
-        None,
+        Some(state.oracle_guard_rails),
     )?;
+    let market_whitelist_entry = get_market_whitelist_entry(remaining_accounts_iter)?;
 
-    if params.post_only != PostOnlyParam::None {
-        msg!("post_only cant be used in place_and_take");
-        return Err(print_error!(ErrorCode::InvalidOrderPostOnly)().into());
+    if params.immediate_or_cancel {
+        msg!("immediate_or_cancel order must be in place_and_make or place_and_take");
+        return Err(print_error!(ErrorCode::InvalidOrderIOC)().into());
     }
 
-    let (maker, maker_stats) = match maker_order_id {
-        Some(_) => {
-            let (user, user_stats) = get_maker_and_maker_stats(remaining_accounts_iter)?;
-            (Some(user), Some(user_stats))
-        }
-        None => (None, None),
-    };
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        params.reduce_only,
+        clock.unix_timestamp,
+    )?;
 
-    let (_referrer, _referrer_stats) = get_referrer_and_referrer_stats(remaining_accounts_iter)?;
+    validate!(
+        params.market_type == MarketType::Perp,
+        ErrorCode::InvalidSessionKey,
+        "session keys can only place perp orders"
+    )?;
 
-    let is_immediate_or_cancel = params.immediate_or_cancel;
+    SessionKey::validate_order_size(params.base_asset_amount, params.max_margin_sizing)?;
 
-    let mut serum_fulfillment_params = match fulfillment_type {
-        Some(SpotFulfillmentType::SerumV3) => {
-            let base_market = spot_market_map.get_ref(&market_index)?;
-            let quote_market = spot_market_map.get_quote_spot_market()?;
-            get_serum_fulfillment_accounts(
-                remaining_accounts_iter,
-                &ctx.accounts.state,
-                &base_market,
-                &quote_market,
-            )?
-        }
-        _ => None,
-    };
+    {
+        let market = perp_market_map.get_ref(&params.market_index)?;
+        let oracle_price = oracle_map.get_price_data(&market.amm.oracle)?.price;
 
-    controller::orders::place_spot_order(
+        // value the order at the worse of its limit and the oracle so market orders count fully
+        let notional = calculate_base_asset_value_with_oracle_price(
+            params.base_asset_amount.cast()?,
+            oracle_price.max(params.price.cast()?),
+        )?
+        .cast::<u64>()?;
+
+        let mut session_key = load_mut!(ctx.accounts.session_key)?;
+        session_key.use_for_order(params.market_index, notional, clock.unix_timestamp)?;
+    }
+
+    controller::orders::place_perp_order(
         &ctx.accounts.state,
         &ctx.accounts.user,
         &perp_market_map,
         &spot_market_map,
         &mut oracle_map,
-        &Clock::get()?,
+        clock,
         params,
+        &mut PlaceOrderOptions {
+            market_whitelist_entry,
+            ..PlaceOrderOptions::default()
+        },
     )?;
 
-    let user = &mut ctx.accounts.user;
-    let order_id = load!(user)?.get_last_order_id();
+    Ok(())
+}
 
-    controller::orders::fill_spot_order(
-        order_id,
-        &ctx.accounts.state,
-        user,
-        &ctx.accounts.user_stats,
-        &spot_market_map,
-        &perp_market_map,
-        &mut oracle_map,
-        &user.clone(),
-        &ctx.accounts.user_stats.clone(),
-        maker.as_ref(),
-        maker_stats.as_ref(),
-        maker_order_id,
-        &Clock::get()?,
-        &mut serum_fulfillment_params,
+pub fn handle_initialize_builder(ctx: Context<InitializeBuilder>, name: [u8; 32]) -> Result<()> {
+    let mut builder = ctx
+        .accounts
+        .builder
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    builder.authority = ctx.accounts.authority.key();
+    builder.name = name;
+
+    Ok(())
+}
+
+pub fn handle_approve_builder(ctx: Context<ApproveBuilder>, max_fee_share: u8) -> Result<()> {
+    let builder_authority = load!(ctx.accounts.builder)?.authority;
+    let mut user_stats = load_mut!(ctx.accounts.user_stats)?;
+
+    validate!(
+        max_fee_share <= MAX_BUILDER_FEE_SHARE,
+        ErrorCode::InvalidBuilder,
+        "max fee share {} above {}",
+        max_fee_share,
+        MAX_BUILDER_FEE_SHARE
     )?;
 
-    let order_exists = load!(ctx.accounts.user)?
-        .orders
-        .iter()
-        .any(|order| order.order_id == order_id);
+    validate!(
+        builder_authority != user_stats.authority,
+        ErrorCode::InvalidBuilder,
+        "cant approve yourself as builder"
+    )?;
 
-    if is_immediate_or_cancel && order_exists {
-        controller::orders::cancel_order_by_order_id(
-            order_id,
-            &ctx.accounts.user,
-            &perp_market_map,
-            &spot_market_map,
-            &mut oracle_map,
-            &Clock::get()?,
-        )?;
-    }
+    msg!(
+        "user_stats.approved_builder: {:?} -> {:?}",
+        user_stats.approved_builder,
+        builder_authority
+    );
 
-    match serum_fulfillment_params {
-        Some(serum_fulfillment_params) => {
-            let base_market = spot_market_map.get_ref(&market_index)?;
-            validate_spot_market_vault_amount(
-                &base_market,
-                serum_fulfillment_params.base_market_vault.amount,
-            )?;
-            let quote_market = spot_market_map.get_quote_spot_market()?;
-            validate_spot_market_vault_amount(
-                &quote_market,
-                serum_fulfillment_params.quote_market_vault.amount,
-            )?;
-        }
-        None => {
-            let base_market = spot_market_map.get_ref(&market_index)?;
-            let quote_market = spot_market_map.get_quote_spot_market()?;
-            let (base_market_vault, quote_market_vault) =
-                get_spot_market_vaults(remaining_accounts_iter, &base_market, &quote_market)?;
-            validate_spot_market_vault_amount(&base_market, base_market_vault.amount)?;
-            validate_spot_market_vault_amount(&quote_market, quote_market_vault.amount)?;
-        }
-    }
+    msg!(
+        "user_stats.builder_max_fee_share: {:?} -> {:?}",
+        user_stats.builder_max_fee_share,
+        max_fee_share
+    );
+
+    // 0 keeps the builder approved but stops it taking any fee, effectively revoking it
+    user_stats.approved_builder = builder_authority;
+    user_stats.builder_max_fee_share = max_fee_share;
+
+    Ok(())
+}
+
+pub fn handle_initialize_user_fuel(ctx: Context<InitializeUserFuel>) -> Result<()> {
+    let mut user_fuel = ctx
+        .accounts
+        .user_fuel
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    user_fuel.authority = ctx.accounts.authority.key();
+    user_fuel.last_update_ts = Clock::get()?.unix_timestamp;
+
+    Ok(())
+}
+
+pub fn handle_initialize_dead_man_switch(
+    ctx: Context<InitializeDeadManSwitch>,
+    keeper: Pubkey,
+    withdraw_destination: Pubkey,
+    timeout_days: u16,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+
+    validate!(
+        timeout_days > 0,
+        ErrorCode::InvalidDeadManSwitch,
+        "dead man switch timeout must be at least one day"
+    )?;
+
+    validate!(
+        keeper != Pubkey::default() && withdraw_destination != Pubkey::default(),
+        ErrorCode::InvalidDeadManSwitch,
+        "dead man switch needs a keeper and withdraw destination"
+    )?;
+
+    let mut dead_man_switch = ctx
+        .accounts
+        .dead_man_switch
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    dead_man_switch.user = ctx.accounts.user.key();
+    dead_man_switch.keeper = keeper;
+    dead_man_switch.withdraw_destination = withdraw_destination;
+    dead_man_switch.timeout_days = timeout_days;
+    dead_man_switch.last_ping_ts = now;
+
+    Ok(())
+}
+
+pub fn handle_ping_dead_man_switch(ctx: Context<PingDeadManSwitch>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+
+    let mut dead_man_switch = load_mut!(ctx.accounts.dead_man_switch)?;
+    dead_man_switch.last_ping_ts = now;
+
+    Ok(())
+}
+
+pub fn handle_delete_dead_man_switch(_ctx: Context<DeleteDeadManSwitch>) -> Result<()> {
+    Ok(())
+}
+
+pub fn handle_register_fill_callback(ctx: Context<RegisterFillCallback>) -> Result<()> {
+    let program_id = load!(ctx.accounts.fill_callback_program)?.program_id;
+
+    let mut user_fill_callback = ctx
+        .accounts
+        .user_fill_callback
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    user_fill_callback.user = ctx.accounts.user.key();
+    user_fill_callback.program_id = program_id;
+    user_fill_callback.bump = *ctx.bumps.get("user_fill_callback").safe_unwrap()?;
+
+    load_mut!(ctx.accounts.user)?.has_fill_callback = true;
+
+    msg!(
+        "registered fill callback program {} for user {}",
+        program_id,
+        ctx.accounts.user.key()
+    );
+
+    Ok(())
+}
+
+pub fn handle_unregister_fill_callback(ctx: Context<UnregisterFillCallback>) -> Result<()> {
+    load_mut!(ctx.accounts.user)?.has_fill_callback = false;
 
     Ok(())
 }
 
 #[access_control(
-    fill_not_paused(&ctx.accounts.state)
+    exchange_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_place_and_make_spot_order<'info>(
-    ctx: Context<PlaceAndMake>,
+pub fn handle_place_order_with_dead_man_switch(
+    ctx: Context<PlaceOrderWithDeadManSwitch>,
     params: OrderParams,
-    taker_order_id: u32,
-    fulfillment_type: Option<SpotFulfillmentType>,
 ) -> Result<()> {
     let clock = &Clock::get()?;
     let state = &ctx.accounts.state;
 
+    load!(ctx.accounts.dead_man_switch)?.validate_triggered(clock.unix_timestamp)?;
+
     let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
         perp_market_map,
@@ -1263,394 +2031,2912 @@ pub fn handle_place_and_make_spot_order<'info>(
         mut oracle_map,
     } = load_maps(
         remaining_accounts_iter,
-        &get_writable_perp_market_set(params.market_index),
         &MarketSet::new(),
-        Clock::get()?.slot,
-        None,
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
     )?;
+    let market_whitelist_entry = get_market_whitelist_entry(remaining_accounts_iter)?;
 
-    let (_referrer, _referrer_stats) = get_referrer_and_referrer_stats(remaining_accounts_iter)?;
-
-    if !params.immediate_or_cancel
-        || params.post_only == PostOnlyParam::None
-        || params.order_type != OrderType::Limit
-    {
-        msg!("place_and_make must use IOC post only limit order");
-        return Err(print_error!(ErrorCode::InvalidOrderIOCPostOnly)().into());
+    if params.immediate_or_cancel {
+        msg!("immediate_or_cancel order must be in place_and_make or place_and_take");
+        return Err(print_error!(ErrorCode::InvalidOrderIOC)().into());
     }
 
-    let market_index = params.market_index;
-    let mut serum_fulfillment_params = match fulfillment_type {
-        Some(SpotFulfillmentType::SerumV3) => {
-            let base_market = spot_market_map.get_ref(&market_index)?;
-            let quote_market = spot_market_map.get_quote_spot_market()?;
-            get_serum_fulfillment_accounts(
-                remaining_accounts_iter,
-                &ctx.accounts.state,
-                &base_market,
-                &quote_market,
-            )?
-        }
-        _ => None,
-    };
-
-    controller::orders::place_spot_order(
-        state,
-        &ctx.accounts.user,
-        &perp_market_map,
-        &spot_market_map,
-        &mut oracle_map,
-        clock,
-        params,
-    )?;
-
-    let order_id = load!(ctx.accounts.user)?.get_last_order_id();
-
-    controller::orders::fill_spot_order(
-        taker_order_id,
-        state,
-        &ctx.accounts.taker,
-        &ctx.accounts.taker_stats,
-        &spot_market_map,
-        &perp_market_map,
-        &mut oracle_map,
-        &ctx.accounts.user.clone(),
-        &ctx.accounts.user_stats.clone(),
-        Some(&ctx.accounts.user),
-        Some(&ctx.accounts.user_stats),
-        Some(order_id),
-        clock,
-        &mut serum_fulfillment_params,
+    validate!(
+        params.reduce_only,
+        ErrorCode::InvalidDeadManSwitch,
+        "dead man switch orders must be reduce only"
     )?;
 
-    let order_exists = load!(ctx.accounts.user)?
-        .orders
-        .iter()
-        .any(|order| order.order_id == order_id);
+    let oracle = match params.market_type {
+        MarketType::Perp => perp_market_map.get_ref(&params.market_index)?.amm.oracle,
+        MarketType::Spot => spot_market_map.get_ref(&params.market_index)?.oracle,
+    };
+    DeadManSwitch::validate_order_price(&params, oracle_map.get_price_data(&oracle)?.price)?;
 
-    if order_exists {
-        controller::orders::cancel_order_by_order_id(
-            order_id,
+    match params.market_type {
+        MarketType::Perp => controller::orders::place_perp_order(
+            state,
             &ctx.accounts.user,
             &perp_market_map,
             &spot_market_map,
             &mut oracle_map,
             clock,
-        )?;
-    }
-
-    match serum_fulfillment_params {
-        Some(serum_fulfillment_params) => {
-            let base_market = spot_market_map.get_ref(&market_index)?;
-            validate_spot_market_vault_amount(
-                &base_market,
-                serum_fulfillment_params.base_market_vault.amount,
-            )?;
-            let quote_market = spot_market_map.get_quote_spot_market()?;
-            validate_spot_market_vault_amount(
-                &quote_market,
-                serum_fulfillment_params.quote_market_vault.amount,
-            )?;
-        }
-        None => {
-            let base_market = spot_market_map.get_ref(&market_index)?;
-            let quote_market = spot_market_map.get_quote_spot_market()?;
-            let (base_market_vault, quote_market_vault) =
-                get_spot_market_vaults(remaining_accounts_iter, &base_market, &quote_market)?;
-            validate_spot_market_vault_amount(&base_market, base_market_vault.amount)?;
-            validate_spot_market_vault_amount(&quote_market, quote_market_vault.amount)?;
-        }
+            params,
+            &mut PlaceOrderOptions {
+                market_whitelist_entry,
+                ..PlaceOrderOptions::default()
+            },
+        )?,
+        MarketType::Spot => controller::orders::place_spot_order(
+            state,
+            &ctx.accounts.user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            clock,
+            params,
+            &mut PlaceOrderOptions::default(),
+        )?,
     }
 
     Ok(())
 }
 
 #[access_control(
-    amm_not_paused(&ctx.accounts.state)
+    withdraw_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_add_perp_lp_shares<'info>(
-    ctx: Context<AddRemoveLiquidity>,
-    n_shares: u64,
+pub fn handle_withdraw_with_dead_man_switch(
+    ctx: Context<WithdrawWithDeadManSwitch>,
     market_index: u16,
 ) -> Result<()> {
     let user_key = ctx.accounts.user.key();
     let user = &mut load_mut!(ctx.accounts.user)?;
-    let state = &ctx.accounts.state;
     let clock = Clock::get()?;
     let now = clock.unix_timestamp;
+    let state = &ctx.accounts.state;
+
+    let dead_man_switch = load!(ctx.accounts.dead_man_switch)?;
+    dead_man_switch.validate_triggered(now)?;
 
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
         perp_market_map,
         spot_market_map,
         mut oracle_map,
     } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
-        &get_writable_perp_market_set(market_index),
+        remaining_accounts_iter,
         &MarketSet::new(),
+        &get_writable_spot_market_set(market_index),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
 
-    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
-    math::liquidation::validate_user_not_being_liquidated(
-        user,
-        &perp_market_map,
-        &spot_market_map,
-        &mut oracle_map,
-        state.liquidation_margin_buffer_ratio,
+    let mint = get_token_mint(
+        remaining_accounts_iter,
+        &spot_market_map.get_ref(&market_index)?,
     )?;
 
     {
-        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+        let spot_market = spot_market_map.get_ref(&market_index)?;
+        validate!(
+            ctx.accounts.token_program.key == &spot_market.get_token_program(),
+            ErrorCode::InvalidTokenProgram,
+            "token program does not match spot market"
+        )?;
+
+        let spot_market_vault = load_token_account(
+            &ctx.accounts.spot_market_vault,
+            ctx.accounts.token_program.key,
+        )?;
+        let user_token_account = load_token_account(
+            &ctx.accounts.user_token_account,
+            ctx.accounts.token_program.key,
+        )?;
 
         validate!(
-            matches!(
-                market.status,
-                MarketStatus::Active
-                    | MarketStatus::FundingPaused
-                    | MarketStatus::FillPaused
-                    | MarketStatus::WithdrawPaused
-            ),
-            ErrorCode::MarketStatusInvalidForNewLP,
-            "Market Status doesn't allow for new LP liquidity"
+            user_token_account.mint == spot_market_vault.mint,
+            ErrorCode::InvalidTokenAccount,
+            "user token account mint does not match spot market vault"
         )?;
 
         validate!(
-            n_shares >= market.amm.order_step_size,
-            ErrorCode::NewLPSizeTooSmall,
-            "minting {} shares is less than step size {}",
-            n_shares,
-            market.amm.order_step_size,
+            user_token_account.owner == dead_man_switch.withdraw_destination,
+            ErrorCode::InvalidTokenAccount,
+            "token account must be owned by the dead man switch withdraw destination"
         )?;
+    }
 
-        controller::funding::settle_funding_payment(user, &user_key, &mut market, now)?;
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
 
-        // standardize n shares to mint
-        let n_shares = crate::math::orders::standardize_base_asset_amount(
-            n_shares.cast()?,
-            market.amm.order_step_size,
-        )?
-        .cast::<u64>()?;
+    {
+        let spot_market = &mut spot_market_map.get_ref_mut(&market_index)?;
+        let oracle_price_data = oracle_map.get_price_data(&spot_market.oracle)?;
 
-        controller::lp::mint_lp_shares(
-            user.force_get_perp_position_mut(market_index)?,
-            &mut market,
-            n_shares,
+        controller::spot_balance::update_spot_market_cumulative_interest(
+            spot_market,
+            Some(oracle_price_data),
+            now,
         )?;
-
-        user.last_add_perp_lp_shares_ts = now;
     }
 
-    // check margin requirements
-    validate!(
-        meets_initial_margin_requirement(
+    // always reduce only, the keeper can never open a borrow
+    let amount = {
+        let position_index = user.force_get_spot_position_index(market_index)?;
+
+        validate!(
+            user.spot_positions[position_index].balance_type == SpotBalanceType::Deposit,
+            ErrorCode::ReduceOnlyWithdrawIncreasedRisk
+        )?;
+
+        let max_withdrawable_amount = calculate_max_withdrawable_amount(
+            market_index,
             user,
             &perp_market_map,
             &spot_market_map,
-            &mut oracle_map
-        )?,
-        ErrorCode::InsufficientCollateral,
-        "User does not meet initial margin requirement"
-    )?;
+            &mut oracle_map,
+        )?;
+
+        let spot_market = &mut spot_market_map.get_ref_mut(&market_index)?;
+        let amount = user.spot_positions[position_index]
+            .get_token_amount(spot_market)?
+            .cast::<u64>()?
+            .min(max_withdrawable_amount);
+
+        let oracle_price_data = oracle_map.get_price_data(&spot_market.oracle)?;
+        user.increment_total_withdraws(
+            amount,
+            oracle_price_data.price,
+            spot_market.get_precision().cast()?,
+        )?;
+
+        controller::spot_position::update_spot_balances_and_cumulative_deposits_with_limits(
+            amount as u128,
+            &SpotBalanceType::Borrow,
+            spot_market,
+            user,
+        )?;
+
+        user.reset_spot_position_if_empty(position_index);
+
+        amount
+    };
+
+    meets_withdraw_margin_requirement(user, &perp_market_map, &spot_market_map, &mut oracle_map)?;
 
     user.update_last_active_slot(clock.slot);
 
-    emit!(LPRecord {
+    let mut spot_market = spot_market_map.get_ref_mut(&market_index)?;
+    let oracle_price = oracle_map.get_price_data(&spot_market.oracle)?.price;
+
+    let deposit_record_id = get_then_update_id!(spot_market, next_deposit_record_id);
+    let deposit_record = DepositRecord {
         ts: now,
-        action: LPAction::AddLiquidity,
+        deposit_record_id,
+        user_authority: user.authority,
         user: user_key,
-        n_shares,
+        direction: DepositDirection::Withdraw,
+        oracle_price,
+        amount,
         market_index,
-        ..LPRecord::default()
-    });
+        market_deposit_balance: spot_market.deposit_balance,
+        market_withdraw_balance: spot_market.borrow_balance,
+        market_cumulative_deposit_interest: spot_market.cumulative_deposit_interest,
+        market_cumulative_borrow_interest: spot_market.cumulative_borrow_interest,
+        total_deposits_after: user.total_deposits,
+        total_withdraws_after: user.total_withdraws,
+        explanation: DepositExplanation::None,
+        transfer_user: None,
+    };
+    emit!(deposit_record);
+
+    controller::token::send_from_program_vault_with_mint(
+        &ctx.accounts.token_program,
+        &ctx.accounts.spot_market_vault,
+        &ctx.accounts.user_token_account,
+        &ctx.accounts.drift_signer,
+        state.signer_nonce,
+        amount,
+        mint.as_ref(),
+    )?;
+
+    math::spot_withdraw::validate_spot_market_vault_amount(
+        &spot_market,
+        load_token_account(
+            &ctx.accounts.spot_market_vault,
+            ctx.accounts.token_program.key,
+        )?
+        .amount,
+    )?;
 
     Ok(())
 }
 
-pub fn handle_remove_perp_lp_shares_in_expiring_market(
-    ctx: Context<RemoveLiquidityInExpiredMarket>,
-    shares_to_burn: u64,
-    market_index: u16,
+#[access_control(
+    exchange_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_place_orders_scaled(
+    ctx: Context<PlaceOrder>,
+    params: ScaleOrderParams,
 ) -> Result<()> {
-    let user_key = ctx.accounts.user.key();
-    let user = &mut load_mut!(ctx.accounts.user)?;
-
+    let clock = &Clock::get()?;
     let state = &ctx.accounts.state;
-    let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
 
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
         perp_market_map,
+        spot_market_map,
         mut oracle_map,
-        ..
     } = load_maps(
-        &mut ctx.remaining_accounts.iter().peekable(),
-        &get_writable_perp_market_set(market_index),
+        remaining_accounts_iter,
+        &MarketSet::new(),
         &MarketSet::new(),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
+    let market_whitelist_entry = get_market_whitelist_entry(remaining_accounts_iter)?;
 
-    // additional validate
-    {
-        let market = perp_market_map.get_ref(&market_index)?;
-        validate!(
-            market.is_reduce_only()?,
-            ErrorCode::PerpMarketNotInReduceOnly,
-            "Can only permissionless burn when market is in reduce only"
-        )?;
-    }
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        params.reduce_only,
+        clock.unix_timestamp,
+    )?;
 
-    controller::lp::remove_perp_lp_shares(
-        perp_market_map,
-        &mut oracle_map,
-        state,
-        user,
-        user_key,
-        shares_to_burn,
-        market_index,
-        now,
+    let (step_size, tick_size) = match params.market_type {
+        MarketType::Perp => {
+            let market = perp_market_map.get_ref(&params.market_index)?;
+            let oracle_price = oracle_map.get_price_data(&market.amm.oracle)?.price;
+            (
+                market.amm.order_step_size,
+                market.get_order_tick_size(oracle_price)?,
+            )
+        }
+        MarketType::Spot => {
+            let market = spot_market_map.get_ref(&params.market_index)?;
+            (market.order_step_size, market.order_tick_size)
+        }
+    };
+
+    let prices = math::orders::calculate_scaled_order_prices(
+        params.start_price,
+        params.end_price,
+        params.order_count,
+        tick_size,
+        params.direction,
     )?;
 
-    user.update_last_active_slot(clock.slot);
+    let sizes = math::orders::calculate_scaled_order_sizes(
+        params.total_base_asset_amount,
+        params.order_count,
+        params.size_distribution,
+        step_size,
+    )?;
+
+    // margin is only checked once all the orders are placed
+    let mut options = PlaceOrderOptions {
+        market_whitelist_entry,
+        ..PlaceOrderOptions::default()
+    };
+    let last_index = prices.len() - 1;
+    for (i, (price, base_asset_amount)) in prices.into_iter().zip(sizes).enumerate() {
+        options.enforce_margin_check = i == last_index;
+
+        let order_params = OrderParams {
+            order_type: OrderType::Limit,
+            market_type: params.market_type,
+            direction: params.direction,
+            base_asset_amount,
+            price,
+            market_index: params.market_index,
+            reduce_only: params.reduce_only,
+            post_only: params.post_only,
+            max_ts: params.max_ts,
+            ..OrderParams::default()
+        };
+
+        match params.market_type {
+            MarketType::Perp => controller::orders::place_perp_order(
+                state,
+                &ctx.accounts.user,
+                &perp_market_map,
+                &spot_market_map,
+                &mut oracle_map,
+                clock,
+                order_params,
+                &mut options,
+            )?,
+            MarketType::Spot => controller::orders::place_spot_order(
+                state,
+                &ctx.accounts.user,
+                &perp_market_map,
+                &spot_market_map,
+                &mut oracle_map,
+                clock,
+                order_params,
+                &mut options,
+            )?,
+        }
+    }
 
     Ok(())
 }
 
 #[access_control(
-    amm_not_paused(&ctx.accounts.state)
+    exchange_not_paused(&ctx.accounts.state)
 )]
-pub fn handle_remove_perp_lp_shares(
-    ctx: Context<AddRemoveLiquidity>,
-    shares_to_burn: u64,
-    market_index: u16,
+pub fn handle_modify_order(
+    ctx: Context<PlaceOrder>,
+    order_id: u32,
+    params: ModifyOrderParams,
 ) -> Result<()> {
-    let user_key = ctx.accounts.user.key();
-    let user = &mut load_mut!(ctx.accounts.user)?;
-
+    let clock = &Clock::get()?;
     let state = &ctx.accounts.state;
-    let clock = Clock::get()?;
-    let now = clock.unix_timestamp;
 
     let AccountMaps {
         perp_market_map,
+        spot_market_map,
         mut oracle_map,
-        ..
     } = load_maps(
         &mut ctx.remaining_accounts.iter().peekable(),
-        &get_writable_perp_market_set(market_index),
+        &MarketSet::new(),
         &MarketSet::new(),
         clock.slot,
         Some(state.oracle_guard_rails),
     )?;
 
-    controller::lp::remove_perp_lp_shares(
-        perp_market_map,
-        &mut oracle_map,
+    {
+        let user = load!(ctx.accounts.user)?;
+        let reduce_only = user
+            .get_order(order_id)
+            .map_or(false, |order| order.reduce_only);
+        validate_trading_not_disabled(
+            &load!(ctx.accounts.user_stats)?,
+            reduce_only,
+            clock.unix_timestamp,
+        )?;
+    }
+
+    controller::orders::modify_order(
+        order_id,
         state,
-        user,
-        user_key,
-        shares_to_burn,
-        market_index,
-        now,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        clock,
+        params,
     )?;
 
-    user.update_last_active_slot(clock.slot);
-
-    Ok(())
-}
-
-pub fn handle_update_user_name(
-    ctx: Context<UpdateUser>,
-    _sub_account_id: u16,
-    name: [u8; 32],
-) -> Result<()> {
-    let mut user = load_mut!(ctx.accounts.user)?;
-    user.name = name;
     Ok(())
 }
 
-pub fn handle_update_user_custom_margin_ratio(
-    ctx: Context<UpdateUser>,
-    _sub_account_id: u16,
-    margin_ratio: u32,
-) -> Result<()> {
-    let mut user = load_mut!(ctx.accounts.user)?;
-    user.max_margin_ratio = margin_ratio;
-    Ok(())
-}
+#[access_control(
+    exchange_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_cancel_order(ctx: Context<CancelOrder>, order_id: Option<u32>) -> Result<()> {
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
 
-pub fn handle_update_user_margin_trading_enabled(
-    ctx: Context<UpdateUser>,
-    _sub_account_id: u16,
-    margin_trading_enabled: bool,
-) -> Result<()> {
-    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
     let AccountMaps {
+        perp_market_map,
         spot_market_map,
         mut oracle_map,
-        ..
     } = load_maps(
-        remaining_accounts_iter,
+        &mut ctx.remaining_accounts.iter().peekable(),
         &MarketSet::new(),
         &MarketSet::new(),
-        Clock::get()?.slot,
-        None,
+        clock.slot,
+        Some(state.oracle_guard_rails),
     )?;
 
-    let mut user = load_mut!(ctx.accounts.user)?;
-    user.is_margin_trading_enabled = margin_trading_enabled;
+    let order_id = match order_id {
+        Some(order_id) => order_id,
+        None => load!(ctx.accounts.user)?.get_last_order_id(),
+    };
 
-    validate_spot_margin_trading(&user, &spot_market_map, &mut oracle_map)
-        .map_err(|_| ErrorCode::MarginOrdersOpen)?;
+    controller::orders::cancel_order_by_order_id(
+        order_id,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        clock,
+    )?;
 
     Ok(())
 }
 
-pub fn handle_update_user_delegate(
-    ctx: Context<UpdateUser>,
-    _sub_account_id: u16,
-    delegate: Pubkey,
+#[access_control(
+    exchange_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_cancel_order_by_user_id(ctx: Context<CancelOrder>, user_order_id: u8) -> Result<()> {
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    controller::orders::cancel_order_by_user_order_id(
+        user_order_id,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        clock,
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    exchange_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_cancel_orders(
+    ctx: Context<CancelOrder>,
+    market_type: Option<MarketType>,
+    market_index: Option<u16>,
+    direction: Option<PositionDirection>,
+) -> Result<()> {
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let user_key = ctx.accounts.user.key();
+    let mut user = load_mut!(ctx.accounts.user)?;
+
+    cancel_orders(
+        &mut user,
+        &user_key,
+        None,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        clock.unix_timestamp,
+        clock.slot,
+        OrderActionExplanation::None,
+        market_type,
+        market_index,
+        direction,
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    exchange_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_cancel_orders_with_filter(
+    ctx: Context<CancelOrder>,
+    market_type: Option<MarketType>,
+    market_index: Option<u16>,
+    direction: Option<PositionDirection>,
+    filter: CancelOrdersFilter,
 ) -> Result<()> {
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let user_key = ctx.accounts.user.key();
     let mut user = load_mut!(ctx.accounts.user)?;
-    user.delegate = delegate;
+
+    cancel_orders_with_filter(
+        &mut user,
+        &user_key,
+        None,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        clock.unix_timestamp,
+        clock.slot,
+        OrderActionExplanation::None,
+        market_type,
+        market_index,
+        direction,
+        filter,
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    exchange_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_link_orders(
+    ctx: Context<PlaceOrder>,
+    order_id: u32,
+    linked_order_id: u32,
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+
+    controller::orders::link_orders(&mut user, order_id, linked_order_id)?;
+
+    Ok(())
+}
+
+#[access_control(
+    fill_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_place_and_take_perp_order<'info>(
+    ctx: Context<PlaceAndTake>,
+    params: OrderParams,
+    _maker_order_id: Option<u32>,
+) -> Result<()> {
+    let can_cancel_orders = can_sign_for_user_with_permission(
+        &ctx.accounts.user,
+        &ctx.accounts.authority,
+        DelegatePermission::CancelOrders,
+    )?;
+
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+    let market_index = params.market_index;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(params.market_index),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    if params.post_only != PostOnlyParam::None {
+        msg!("post_only cant be used in place_and_take");
+        return Err(print_error!(ErrorCode::InvalidOrderPostOnly)().into());
+    }
+
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        params.reduce_only,
+        clock.unix_timestamp,
+    )?;
+
+    let market_whitelist_entries = get_market_whitelist_entries(remaining_accounts_iter)?;
+    let market_whitelist_entry = find_market_whitelist_entry(
+        &market_whitelist_entries,
+        params.market_index,
+        &load!(ctx.accounts.user)?.authority,
+    )
+    .copied();
+    let market_fee_override = get_market_fee_override(remaining_accounts_iter)?;
+    let fill_callback_accounts =
+        get_fill_callback_accounts(remaining_accounts_iter, &ctx.accounts.user.key())?;
+    let (makers_and_referrer, makers_and_referrer_stats) = load_user_maps(remaining_accounts_iter)?;
+    let fuel_map = load_fuel_map(remaining_accounts_iter)?;
+
+    validate!(
+        fill_callback_accounts.is_some() || !load!(ctx.accounts.user)?.has_fill_callback,
+        ErrorCode::InvalidFillCallback,
+        "user {} has a fill callback but its accounts were not passed",
+        ctx.accounts.user.key()
+    )?;
+
+    let is_immediate_or_cancel = params.immediate_or_cancel;
+
+    controller::repeg::update_amm(
+        params.market_index,
+        &perp_market_map,
+        &mut oracle_map,
+        &ctx.accounts.state,
+        &Clock::get()?,
+    )?;
+
+    controller::orders::place_perp_order(
+        &ctx.accounts.state,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        &Clock::get()?,
+        params,
+        &mut PlaceOrderOptions {
+            market_whitelist_entry,
+            can_cancel_orders,
+            ..PlaceOrderOptions::default()
+        },
+    )?;
+
+    let user = &mut ctx.accounts.user;
+    let order_id = load!(user)?.get_last_order_id();
+
+    let base_asset_amount_unfilled_before = match load!(user)?.get_order(order_id) {
+        Some(order) => order.get_base_asset_amount_unfilled(None)?,
+        None => 0,
+    };
+    let fees_before = load!(ctx.accounts.user_stats)?.fees;
+
+    let (base_asset_amount_filled, quote_asset_amount_filled, _) =
+        controller::orders::fill_perp_order(
+            order_id,
+            &ctx.accounts.state,
+            user,
+            &ctx.accounts.user_stats,
+            &spot_market_map,
+            &perp_market_map,
+            &mut oracle_map,
+            &user.clone(),
+            &ctx.accounts.user_stats.clone(),
+            &makers_and_referrer,
+            &makers_and_referrer_stats,
+            None,
+            &Clock::get()?,
+            &fuel_map,
+            market_fee_override.as_ref(),
+            &market_whitelist_entries,
+        )?;
+
+    let order_exists = load!(ctx.accounts.user)?
+        .orders
+        .iter()
+        .any(|order| order.order_id == order_id);
+
+    if is_immediate_or_cancel && order_exists {
+        controller::orders::cancel_order_by_order_id(
+            order_id,
+            &ctx.accounts.user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &Clock::get()?,
+        )?;
+    }
+
+    let fill_receipt = {
+        let user = load!(ctx.accounts.user)?;
+        let fees = load!(ctx.accounts.user_stats)?.fees;
+        let fee_paid = fees
+            .total_fee_paid
+            .safe_sub(fees_before.total_fee_paid)?
+            .cast::<i64>()?
+            .safe_sub(
+                fees.total_fee_rebate
+                    .safe_sub(fees_before.total_fee_rebate)?
+                    .cast()?,
+            )?;
+
+        calculate_fill_receipt(
+            order_id,
+            base_asset_amount_unfilled_before,
+            user.get_order(order_id),
+            base_asset_amount_filled,
+            quote_asset_amount_filled,
+            fee_paid,
+        )?
+    };
+
+    controller::fill_callback::invoke_fill_callback_and_set_receipt(
+        fill_callback_accounts,
+        &ctx.accounts.user.to_account_info(),
+        market_index,
+        fill_receipt,
+    )?;
+
     Ok(())
 }
 
-pub fn handle_delete_user(ctx: Context<DeleteUser>) -> Result<()> {
-    let user = &load!(ctx.accounts.user)?;
-    let user_stats = &mut load_mut!(ctx.accounts.user_stats)?;
-
-    validate_user_deletion(user, user_stats)?;
-
-    safe_decrement!(user_stats.number_of_sub_accounts, 1);
-
-    let state = &mut ctx.accounts.state;
-    safe_decrement!(state.number_of_sub_accounts, 1);
-
-    Ok(())
+#[access_control(
+    fill_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_place_and_make_perp_order<'a, 'b, 'c, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, PlaceAndMake<'info>>,
+    params: OrderParams,
+    taker_order_id: u32,
+) -> Result<()> {
+    let can_cancel_orders = can_sign_for_user_with_permission(
+        &ctx.accounts.user,
+        &ctx.accounts.authority,
+        DelegatePermission::CancelOrders,
+    )?;
+
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(params.market_index),
+        &MarketSet::new(),
+        Clock::get()?.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+    let market_whitelist_entries = get_market_whitelist_entries(remaining_accounts_iter)?;
+    let market_whitelist_entry = find_market_whitelist_entry(
+        &market_whitelist_entries,
+        params.market_index,
+        &load!(ctx.accounts.user)?.authority,
+    )
+    .copied();
+
+    // neither side's fill callback is invoked when filling against a maker's ioc order
+    load!(ctx.accounts.user)?.validate_no_fill_callback()?;
+    load!(ctx.accounts.taker)?.validate_no_fill_callback()?;
+
+    // the whitelist can be revoked after the taker's order was placed
+    {
+        let market = perp_market_map.get_ref(&params.market_index)?;
+        let taker_authority = load!(ctx.accounts.taker)?.authority;
+        validate_market_whitelist(
+            market.amm.whitelist_enabled,
+            params.market_index,
+            &taker_authority,
+            find_market_whitelist_entry(
+                &market_whitelist_entries,
+                params.market_index,
+                &taker_authority,
+            ),
+        )?;
+    }
+
+    if !params.immediate_or_cancel
+        || params.post_only == PostOnlyParam::None
+        || params.order_type != OrderType::Limit
+    {
+        msg!("place_and_make must use IOC post only limit order");
+        return Err(print_error!(ErrorCode::InvalidOrderIOCPostOnly)().into());
+    }
+
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        params.reduce_only,
+        clock.unix_timestamp,
+    )?;
+
+    controller::repeg::update_amm(
+        params.market_index,
+        &perp_market_map,
+        &mut oracle_map,
+        state,
+        clock,
+    )?;
+
+    controller::orders::place_perp_order(
+        state,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        clock,
+        params,
+        &mut PlaceOrderOptions {
+            market_whitelist_entry,
+            can_cancel_orders,
+            ..PlaceOrderOptions::default()
+        },
+    )?;
+
+    let (order_id, authority) = {
+        let user = load!(ctx.accounts.user)?;
+        let order_id = user.get_last_order_id();
+        (order_id, user.authority)
+    };
+
+    let market_fee_override = get_market_fee_override(remaining_accounts_iter)?;
+    let (mut makers_and_referrer, mut makers_and_referrer_stats) =
+        load_user_maps(remaining_accounts_iter)?;
+    makers_and_referrer.insert(ctx.accounts.user.key(), ctx.accounts.user.clone())?;
+    makers_and_referrer_stats.insert(authority, ctx.accounts.user_stats.clone())?;
+    let fuel_map = load_fuel_map(remaining_accounts_iter)?;
+
+    controller::orders::fill_perp_order(
+        taker_order_id,
+        state,
+        &ctx.accounts.taker,
+        &ctx.accounts.taker_stats,
+        &spot_market_map,
+        &perp_market_map,
+        &mut oracle_map,
+        &ctx.accounts.user.clone(),
+        &ctx.accounts.user_stats.clone(),
+        &makers_and_referrer,
+        &makers_and_referrer_stats,
+        Some(order_id),
+        clock,
+        &fuel_map,
+        market_fee_override.as_ref(),
+        &market_whitelist_entries,
+    )?;
+
+    let order_exists = load!(ctx.accounts.user)?
+        .orders
+        .iter()
+        .any(|order| order.order_id == order_id);
+
+    if order_exists {
+        controller::orders::cancel_order_by_order_id(
+            order_id,
+            &ctx.accounts.user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            clock,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn handle_place_spot_order(ctx: Context<PlaceOrder>, params: OrderParams) -> Result<()> {
+    let can_cancel_orders = can_sign_for_user_with_permission(
+        &ctx.accounts.user,
+        &ctx.accounts.authority,
+        DelegatePermission::CancelOrders,
+    )?;
+
+    let clock = &Clock::get()?;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        None,
+    )?;
+
+    if params.immediate_or_cancel {
+        msg!("immediate_or_cancel order must be in place_and_make or place_and_take");
+        return Err(print_error!(ErrorCode::InvalidOrderIOC)().into());
+    }
+
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        params.reduce_only,
+        clock.unix_timestamp,
+    )?;
+
+    controller::orders::place_spot_order(
+        &ctx.accounts.state,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        clock,
+        params,
+        &mut PlaceOrderOptions {
+            can_cancel_orders,
+            ..PlaceOrderOptions::default()
+        },
+    )?;
+
+    Ok(())
+}
+
+#[access_control(
+    fill_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_place_and_take_spot_order<'info>(
+    ctx: Context<PlaceAndTake>,
+    params: OrderParams,
+    fulfillment_type: Option<SpotFulfillmentType>,
+    maker_order_id: Option<u32>,
+) -> Result<()> {
+    let can_cancel_orders = can_sign_for_user_with_permission(
+        &ctx.accounts.user,
+        &ctx.accounts.authority,
+        DelegatePermission::CancelOrders,
+    )?;
+
+    let clock = Clock::get()?;
+    let market_index = params.market_index;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(params.market_index),
+        &MarketSet::new(),
+        clock.slot,
+        None,
+    )?;
+
+    let market_fee_override = get_market_fee_override(remaining_accounts_iter)?;
+
+    if params.post_only != PostOnlyParam::None {
+        msg!("post_only cant be used in place_and_take");
+        return Err(print_error!(ErrorCode::InvalidOrderPostOnly)().into());
+    }
+
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        params.reduce_only,
+        clock.unix_timestamp,
+    )?;
+
+    let (maker, maker_stats) = match maker_order_id {
+        Some(_) => {
+            let (user, user_stats) = get_maker_and_maker_stats(remaining_accounts_iter)?;
+            (Some(user), Some(user_stats))
+        }
+        None => (None, None),
+    };
+
+    let (_referrer, _referrer_stats) = get_referrer_and_referrer_stats(remaining_accounts_iter)?;
+
+    let is_immediate_or_cancel = params.immediate_or_cancel;
+
+    let mut serum_fulfillment_params = match fulfillment_type {
+        Some(SpotFulfillmentType::SerumV3) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_serum_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    let mut openbook_v2_fulfillment_params = match fulfillment_type {
+        Some(SpotFulfillmentType::OpenbookV2) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_openbook_v2_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    let mut phoenix_fulfillment_params = match fulfillment_type {
+        Some(SpotFulfillmentType::PhoenixV1) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_phoenix_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    controller::orders::place_spot_order(
+        &ctx.accounts.state,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        &Clock::get()?,
+        params,
+        &mut PlaceOrderOptions {
+            can_cancel_orders,
+            ..PlaceOrderOptions::default()
+        },
+    )?;
+
+    let user = &mut ctx.accounts.user;
+    let order_id = load!(user)?.get_last_order_id();
+
+    controller::orders::fill_spot_order(
+        order_id,
+        &ctx.accounts.state,
+        user,
+        &ctx.accounts.user_stats,
+        &spot_market_map,
+        &perp_market_map,
+        &mut oracle_map,
+        &user.clone(),
+        &ctx.accounts.user_stats.clone(),
+        maker.as_ref(),
+        maker_stats.as_ref(),
+        maker_order_id,
+        &Clock::get()?,
+        &mut serum_fulfillment_params,
+        &mut openbook_v2_fulfillment_params,
+        &mut phoenix_fulfillment_params,
+        market_fee_override.as_ref(),
+    )?;
+
+    let order_exists = load!(ctx.accounts.user)?
+        .orders
+        .iter()
+        .any(|order| order.order_id == order_id);
+
+    if is_immediate_or_cancel && order_exists {
+        controller::orders::cancel_order_by_order_id(
+            order_id,
+            &ctx.accounts.user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &Clock::get()?,
+        )?;
+    }
+
+    match (
+        serum_fulfillment_params,
+        openbook_v2_fulfillment_params,
+        phoenix_fulfillment_params,
+    ) {
+        (Some(serum_fulfillment_params), _, _) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                serum_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                serum_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, Some(openbook_v2_fulfillment_params), _) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                openbook_v2_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                openbook_v2_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, None, Some(phoenix_fulfillment_params)) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                phoenix_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                phoenix_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, None, None) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            let (base_market_vault, quote_market_vault) =
+                get_spot_market_vaults(remaining_accounts_iter, &base_market, &quote_market)?;
+            validate_spot_market_vault_amount(&base_market, base_market_vault.amount)?;
+            validate_spot_market_vault_amount(&quote_market, quote_market_vault.amount)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[access_control(
+    fill_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_place_and_take_basis_order<'info>(
+    ctx: Context<PlaceAndTake>,
+    perp_params: OrderParams,
+    spot_market_index: u16,
+    basis_limit: i64,
+    spot_fulfillment_type: SpotFulfillmentType,
+) -> Result<()> {
+    let can_cancel_orders = can_sign_for_user_with_permission(
+        &ctx.accounts.user,
+        &ctx.accounts.authority,
+        DelegatePermission::CancelOrders,
+    )?;
+
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+    let perp_market_index = perp_params.market_index;
+    let perp_direction = perp_params.direction;
+
+    validate!(
+        perp_params.market_type == MarketType::Perp,
+        ErrorCode::InvalidBasisOrder,
+        "basis order must be placed with perp params"
+    )?;
+
+    validate!(
+        spot_market_index != QUOTE_SPOT_MARKET_INDEX,
+        ErrorCode::InvalidBasisOrder,
+        "basis order spot leg cant be the quote market"
+    )?;
+
+    validate!(
+        spot_fulfillment_type != SpotFulfillmentType::None,
+        ErrorCode::InvalidBasisOrder,
+        "basis order spot leg must be filled externally"
+    )?;
+
+    if perp_params.post_only != PostOnlyParam::None {
+        msg!("post_only cant be used in place_and_take");
+        return Err(print_error!(ErrorCode::InvalidOrderPostOnly)().into());
+    }
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(perp_market_index),
+        &get_writable_spot_market_set_from_many(vec![spot_market_index, QUOTE_SPOT_MARKET_INDEX]),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        perp_params.reduce_only,
+        clock.unix_timestamp,
+    )?;
+
+    validate!(
+        !load!(ctx.accounts.user)?.has_fill_callback,
+        ErrorCode::InvalidBasisOrder,
+        "basis orders cant be placed by users with a fill callback"
+    )?;
+
+    let market_whitelist_entries = get_market_whitelist_entries(remaining_accounts_iter)?;
+    let market_whitelist_entry = find_market_whitelist_entry(
+        &market_whitelist_entries,
+        perp_market_index,
+        &load!(ctx.accounts.user)?.authority,
+    )
+    .copied();
+
+    // a fee override for each leg, in either order
+    let mut perp_market_fee_override = None;
+    let mut spot_market_fee_override = None;
+    for _ in 0..2 {
+        match get_market_fee_override(remaining_accounts_iter)? {
+            Some(market_fee_override) if market_fee_override.market_type == MarketType::Perp => {
+                perp_market_fee_override = Some(market_fee_override)
+            }
+            Some(market_fee_override) => spot_market_fee_override = Some(market_fee_override),
+            None => break,
+        }
+    }
+
+    let (makers_and_referrer, makers_and_referrer_stats) = load_user_maps(remaining_accounts_iter)?;
+    let fuel_map = load_fuel_map(remaining_accounts_iter)?;
+
+    let mut serum_fulfillment_params = match spot_fulfillment_type {
+        SpotFulfillmentType::SerumV3 => {
+            let base_market = spot_market_map.get_ref(&spot_market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_serum_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    let mut openbook_v2_fulfillment_params = match spot_fulfillment_type {
+        SpotFulfillmentType::OpenbookV2 => {
+            let base_market = spot_market_map.get_ref(&spot_market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_openbook_v2_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    let mut phoenix_fulfillment_params = match spot_fulfillment_type {
+        SpotFulfillmentType::PhoenixV1 => {
+            let base_market = spot_market_map.get_ref(&spot_market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_phoenix_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    controller::repeg::update_amm(
+        perp_market_index,
+        &perp_market_map,
+        &mut oracle_map,
+        &ctx.accounts.state,
+        &clock,
+    )?;
+
+    controller::orders::place_perp_order(
+        &ctx.accounts.state,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        &clock,
+        OrderParams {
+            immediate_or_cancel: true,
+            ..perp_params
+        },
+        &mut PlaceOrderOptions {
+            market_whitelist_entry,
+            can_cancel_orders,
+            ..PlaceOrderOptions::default()
+        },
+    )?;
+
+    let user = &mut ctx.accounts.user;
+    let perp_order_id = load!(user)?.get_last_order_id();
+
+    let (perp_base_asset_amount_filled, perp_quote_asset_amount_filled, _) =
+        controller::orders::fill_perp_order(
+            perp_order_id,
+            &ctx.accounts.state,
+            user,
+            &ctx.accounts.user_stats,
+            &spot_market_map,
+            &perp_market_map,
+            &mut oracle_map,
+            &user.clone(),
+            &ctx.accounts.user_stats.clone(),
+            &makers_and_referrer,
+            &makers_and_referrer_stats,
+            None,
+            &clock,
+            &fuel_map,
+            perp_market_fee_override.as_ref(),
+            &market_whitelist_entries,
+        )?;
+
+    let perp_order_exists = load!(ctx.accounts.user)?
+        .orders
+        .iter()
+        .any(|order| order.order_id == perp_order_id);
+
+    if perp_order_exists {
+        controller::orders::cancel_order_by_order_id(
+            perp_order_id,
+            &ctx.accounts.user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            &clock,
+        )?;
+    }
+
+    // the spot leg is sized and priced off the perp fill so the realized basis stays within the limit
+    let (spot_base_asset_amount, spot_price) = {
+        let spot_market = spot_market_map.get_ref(&spot_market_index)?;
+        calculate_basis_order_spot_leg(
+            perp_base_asset_amount_filled,
+            perp_quote_asset_amount_filled,
+            perp_direction,
+            basis_limit,
+            spot_market.decimals,
+            spot_market.order_step_size,
+            spot_market.order_tick_size,
+        )?
+    };
+
+    controller::orders::place_spot_order(
+        &ctx.accounts.state,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        &clock,
+        OrderParams {
+            order_type: OrderType::Limit,
+            market_type: MarketType::Spot,
+            direction: perp_direction.opposite(),
+            base_asset_amount: spot_base_asset_amount,
+            price: spot_price,
+            market_index: spot_market_index,
+            immediate_or_cancel: true,
+            ..OrderParams::default()
+        },
+        &mut PlaceOrderOptions {
+            can_cancel_orders,
+            ..PlaceOrderOptions::default()
+        },
+    )?;
+
+    let user = &mut ctx.accounts.user;
+    let spot_order_id = load!(user)?.get_last_order_id();
+
+    let spot_base_asset_amount_filled = controller::orders::fill_spot_order(
+        spot_order_id,
+        &ctx.accounts.state,
+        user,
+        &ctx.accounts.user_stats,
+        &spot_market_map,
+        &perp_market_map,
+        &mut oracle_map,
+        &user.clone(),
+        &ctx.accounts.user_stats.clone(),
+        None,
+        None,
+        None,
+        &clock,
+        &mut serum_fulfillment_params,
+        &mut openbook_v2_fulfillment_params,
+        &mut phoenix_fulfillment_params,
+        spot_market_fee_override.as_ref(),
+    )?;
+
+    validate!(
+        spot_base_asset_amount_filled == spot_base_asset_amount,
+        ErrorCode::InvalidBasisOrder,
+        "spot leg filled {} of {} within basis limit {}",
+        spot_base_asset_amount_filled,
+        spot_base_asset_amount,
+        basis_limit
+    )?;
+
+    match (
+        serum_fulfillment_params,
+        openbook_v2_fulfillment_params,
+        phoenix_fulfillment_params,
+    ) {
+        (Some(serum_fulfillment_params), _, _) => {
+            let base_market = spot_market_map.get_ref(&spot_market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                serum_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                serum_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, Some(openbook_v2_fulfillment_params), _) => {
+            let base_market = spot_market_map.get_ref(&spot_market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                openbook_v2_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                openbook_v2_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, None, Some(phoenix_fulfillment_params)) => {
+            let base_market = spot_market_map.get_ref(&spot_market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                phoenix_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                phoenix_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, None, None) => {
+            msg!("basis order spot fulfillment accounts not passed");
+            return Err(print_error!(ErrorCode::InvalidBasisOrder)().into());
+        }
+    }
+
+    Ok(())
+}
+
+#[access_control(
+    fill_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_place_and_make_spot_order<'info>(
+    ctx: Context<PlaceAndMake>,
+    params: OrderParams,
+    taker_order_id: u32,
+    fulfillment_type: Option<SpotFulfillmentType>,
+) -> Result<()> {
+    let can_cancel_orders = can_sign_for_user_with_permission(
+        &ctx.accounts.user,
+        &ctx.accounts.authority,
+        DelegatePermission::CancelOrders,
+    )?;
+
+    let clock = &Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(params.market_index),
+        &MarketSet::new(),
+        Clock::get()?.slot,
+        None,
+    )?;
+
+    let market_fee_override = get_market_fee_override(remaining_accounts_iter)?;
+    let (_referrer, _referrer_stats) = get_referrer_and_referrer_stats(remaining_accounts_iter)?;
+
+    load!(ctx.accounts.user)?.validate_no_fill_callback()?;
+
+    if !params.immediate_or_cancel
+        || params.post_only == PostOnlyParam::None
+        || params.order_type != OrderType::Limit
+    {
+        msg!("place_and_make must use IOC post only limit order");
+        return Err(print_error!(ErrorCode::InvalidOrderIOCPostOnly)().into());
+    }
+
+    validate_trading_not_disabled(
+        &load!(ctx.accounts.user_stats)?,
+        params.reduce_only,
+        clock.unix_timestamp,
+    )?;
+
+    let market_index = params.market_index;
+    let mut serum_fulfillment_params = match fulfillment_type {
+        Some(SpotFulfillmentType::SerumV3) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_serum_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    let mut openbook_v2_fulfillment_params = match fulfillment_type {
+        Some(SpotFulfillmentType::OpenbookV2) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_openbook_v2_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    let mut phoenix_fulfillment_params = match fulfillment_type {
+        Some(SpotFulfillmentType::PhoenixV1) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            get_phoenix_fulfillment_accounts(
+                remaining_accounts_iter,
+                &ctx.accounts.state,
+                &base_market,
+                &quote_market,
+            )?
+        }
+        _ => None,
+    };
+
+    controller::orders::place_spot_order(
+        state,
+        &ctx.accounts.user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        clock,
+        params,
+        &mut PlaceOrderOptions {
+            can_cancel_orders,
+            ..PlaceOrderOptions::default()
+        },
+    )?;
+
+    let order_id = load!(ctx.accounts.user)?.get_last_order_id();
+
+    controller::orders::fill_spot_order(
+        taker_order_id,
+        state,
+        &ctx.accounts.taker,
+        &ctx.accounts.taker_stats,
+        &spot_market_map,
+        &perp_market_map,
+        &mut oracle_map,
+        &ctx.accounts.user.clone(),
+        &ctx.accounts.user_stats.clone(),
+        Some(&ctx.accounts.user),
+        Some(&ctx.accounts.user_stats),
+        Some(order_id),
+        clock,
+        &mut serum_fulfillment_params,
+        &mut openbook_v2_fulfillment_params,
+        &mut phoenix_fulfillment_params,
+        market_fee_override.as_ref(),
+    )?;
+
+    let order_exists = load!(ctx.accounts.user)?
+        .orders
+        .iter()
+        .any(|order| order.order_id == order_id);
+
+    if order_exists {
+        controller::orders::cancel_order_by_order_id(
+            order_id,
+            &ctx.accounts.user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            clock,
+        )?;
+    }
+
+    match (
+        serum_fulfillment_params,
+        openbook_v2_fulfillment_params,
+        phoenix_fulfillment_params,
+    ) {
+        (Some(serum_fulfillment_params), _, _) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                serum_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                serum_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, Some(openbook_v2_fulfillment_params), _) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                openbook_v2_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                openbook_v2_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, None, Some(phoenix_fulfillment_params)) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            validate_spot_market_vault_amount(
+                &base_market,
+                phoenix_fulfillment_params.base_market_vault.amount,
+            )?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            validate_spot_market_vault_amount(
+                &quote_market,
+                phoenix_fulfillment_params.quote_market_vault.amount,
+            )?;
+        }
+        (None, None, None) => {
+            let base_market = spot_market_map.get_ref(&market_index)?;
+            let quote_market = spot_market_map.get_quote_spot_market()?;
+            let (base_market_vault, quote_market_vault) =
+                get_spot_market_vaults(remaining_accounts_iter, &base_market, &quote_market)?;
+            validate_spot_market_vault_amount(&base_market, base_market_vault.amount)?;
+            validate_spot_market_vault_amount(&quote_market, quote_market_vault.amount)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[access_control(
+    amm_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_add_perp_lp_shares<'info>(
+    ctx: Context<AddRemoveLiquidity>,
+    n_shares: u64,
+    market_index: u16,
+) -> Result<()> {
+    let user_key = ctx.accounts.user.key();
+    let user = &mut load_mut!(ctx.accounts.user)?;
+    let state = &ctx.accounts.state;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &get_writable_perp_market_set(market_index),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+    let market_whitelist_entry = get_market_whitelist_entry(remaining_accounts_iter)?;
+
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
+    math::liquidation::validate_user_not_being_liquidated(
+        user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        state.liquidation_margin_buffer_ratio,
+    )?;
+
+    {
+        let mut market = perp_market_map.get_ref_mut(&market_index)?;
+
+        validate!(
+            matches!(
+                market.status,
+                MarketStatus::Active
+                    | MarketStatus::FundingPaused
+                    | MarketStatus::FillPaused
+                    | MarketStatus::WithdrawPaused
+            ),
+            ErrorCode::MarketStatusInvalidForNewLP,
+            "Market Status doesn't allow for new LP liquidity"
+        )?;
+
+        validate_market_whitelist(
+            market.amm.whitelist_enabled,
+            market_index,
+            &user.authority,
+            market_whitelist_entry.as_ref(),
+        )?;
+
+        validate!(
+            !user
+                .get_perp_position(market_index)
+                .map_or(false, |position| position.is_isolated),
+            ErrorCode::InvalidIsolatedPerpPosition,
+            "cant provide lp liquidity from an isolated perp position"
+        )?;
+
+        validate!(
+            n_shares >= market.amm.order_step_size,
+            ErrorCode::NewLPSizeTooSmall,
+            "minting {} shares is less than step size {}",
+            n_shares,
+            market.amm.order_step_size,
+        )?;
+
+        controller::funding::settle_funding_payment(user, &user_key, &mut market, now)?;
+
+        // standardize n shares to mint
+        let n_shares = crate::math::orders::standardize_base_asset_amount(
+            n_shares.cast()?,
+            market.amm.order_step_size,
+        )?
+        .cast::<u64>()?;
+
+        let share_multiplier = user.perp_lp_tier.share_multiplier();
+        controller::lp::mint_lp_shares(
+            user.force_get_perp_position_mut(market_index)?,
+            &mut market,
+            n_shares,
+            share_multiplier,
+        )?;
+
+        user.last_add_perp_lp_shares_ts = now;
+    }
+
+    // check margin requirements
+    validate!(
+        meets_initial_margin_requirement(
+            user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map
+        )?,
+        ErrorCode::InsufficientCollateral,
+        "User does not meet initial margin requirement"
+    )?;
+
+    user.update_last_active_slot(clock.slot);
+
+    emit!(LPRecord {
+        ts: now,
+        action: LPAction::AddLiquidity,
+        user: user_key,
+        n_shares,
+        market_index,
+        ..LPRecord::default()
+    });
+
+    Ok(())
+}
+
+pub fn handle_remove_perp_lp_shares_in_expiring_market(
+    ctx: Context<RemoveLiquidityInExpiredMarket>,
+    shares_to_burn: u64,
+    market_index: u16,
+) -> Result<()> {
+    let user_key = ctx.accounts.user.key();
+    let user = &mut load_mut!(ctx.accounts.user)?;
+
+    let state = &ctx.accounts.state;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let AccountMaps {
+        perp_market_map,
+        mut oracle_map,
+        ..
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &get_writable_perp_market_set(market_index),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    // additional validate
+    {
+        let market = perp_market_map.get_ref(&market_index)?;
+        validate!(
+            market.is_reduce_only()?,
+            ErrorCode::PerpMarketNotInReduceOnly,
+            "Can only permissionless burn when market is in reduce only"
+        )?;
+    }
+
+    controller::lp::remove_perp_lp_shares(
+        perp_market_map,
+        &mut oracle_map,
+        state,
+        user,
+        user_key,
+        shares_to_burn,
+        market_index,
+        now,
+    )?;
+
+    user.update_last_active_slot(clock.slot);
+
+    Ok(())
+}
+
+pub fn handle_initialize_perp_lp_range(
+    ctx: Context<InitializePerpLpRange>,
+    market_index: u16,
+    lower_price: u64,
+    upper_price: u64,
+) -> Result<()> {
+    let mut perp_lp_range = ctx
+        .accounts
+        .perp_lp_range
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    perp_lp_range.user = ctx.accounts.user.key();
+    perp_lp_range.market_index = market_index;
+    perp_lp_range.update_range(lower_price, upper_price)?;
+
+    Ok(())
+}
+
+pub fn handle_update_perp_lp_range(
+    ctx: Context<UpdatePerpLpRange>,
+    _market_index: u16,
+    lower_price: u64,
+    upper_price: u64,
+) -> Result<()> {
+    let mut perp_lp_range = load_mut!(ctx.accounts.perp_lp_range)?;
+
+    msg!(
+        "perp_lp_range: [{:?}, {:?}] -> [{:?}, {:?}]",
+        perp_lp_range.lower_price,
+        perp_lp_range.upper_price,
+        lower_price,
+        upper_price
+    );
+
+    perp_lp_range.update_range(lower_price, upper_price)?;
+
+    Ok(())
+}
+
+#[access_control(
+    amm_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_remove_perp_lp_shares(
+    ctx: Context<AddRemoveLiquidity>,
+    shares_to_burn: u64,
+    market_index: u16,
+) -> Result<()> {
+    let user_key = ctx.accounts.user.key();
+    let user = &mut load_mut!(ctx.accounts.user)?;
+
+    let state = &ctx.accounts.state;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+
+    let AccountMaps {
+        perp_market_map,
+        mut oracle_map,
+        ..
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &get_writable_perp_market_set(market_index),
+        &MarketSet::new(),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    controller::lp::remove_perp_lp_shares(
+        perp_market_map,
+        &mut oracle_map,
+        state,
+        user,
+        user_key,
+        shares_to_burn,
+        market_index,
+        now,
+    )?;
+
+    user.update_last_active_slot(clock.slot);
+
+    Ok(())
+}
+
+pub fn handle_update_user_name(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    name: [u8; 32],
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+    user.name = name;
+    Ok(())
+}
+
+pub fn handle_update_user_custom_margin_ratio(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    margin_ratio: u32,
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+    user.max_margin_ratio = margin_ratio;
+    Ok(())
+}
+
+pub fn handle_update_user_margin_trading_enabled(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    margin_trading_enabled: bool,
+) -> Result<()> {
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        spot_market_map,
+        mut oracle_map,
+        ..
+    } = load_maps(
+        remaining_accounts_iter,
+        &MarketSet::new(),
+        &MarketSet::new(),
+        Clock::get()?.slot,
+        None,
+    )?;
+
+    let mut user = load_mut!(ctx.accounts.user)?;
+    user.is_margin_trading_enabled = margin_trading_enabled;
+
+    validate_spot_margin_trading(&user, &spot_market_map, &mut oracle_map)
+        .map_err(|_| ErrorCode::MarginOrdersOpen)?;
+
+    Ok(())
+}
+
+pub fn handle_update_user_protected_maker_mode(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    protected_maker: bool,
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+    user.is_protected_maker = protected_maker;
+    Ok(())
+}
+
+pub fn handle_update_user_third_party_deposits_disabled(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    third_party_deposits_disabled: bool,
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+    user.third_party_deposits_disabled = third_party_deposits_disabled;
+    Ok(())
+}
+
+pub fn handle_update_user_perp_lp_tier(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    perp_lp_tier: PerpLpTier,
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+
+    validate!(
+        !user.perp_positions.iter().any(|position| position.is_lp()),
+        ErrorCode::InvalidLpTier,
+        "cant change lp tier with open lp positions"
+    )?;
+
+    msg!(
+        "user.perp_lp_tier: {:?} -> {:?}",
+        user.perp_lp_tier,
+        perp_lp_tier
+    );
+
+    user.perp_lp_tier = perp_lp_tier;
+
+    Ok(())
+}
+
+pub fn handle_enable_user_high_leverage_mode(
+    ctx: Context<UpdateUserHighLeverageMode>,
+    _sub_account_id: u16,
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+
+    validate!(
+        !user.is_high_leverage_mode,
+        ErrorCode::DefaultError,
+        "user already in high leverage mode"
+    )?;
+
+    let mut config = load_mut!(ctx.accounts.high_leverage_mode_config)?;
+    config.add_user()?;
+
+    user.is_high_leverage_mode = true;
+    user.high_leverage_mode_market_index = config.market_index;
+
+    Ok(())
+}
+
+pub fn handle_disable_user_high_leverage_mode(
+    ctx: Context<UpdateUserHighLeverageMode>,
+    _sub_account_id: u16,
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        None,
+    )?;
+
+    let user = &mut load_mut!(ctx.accounts.user)?;
+
+    validate!(
+        user.is_high_leverage_mode,
+        ErrorCode::DefaultError,
+        "user not in high leverage mode"
+    )?;
+
+    let mut config = load_mut!(ctx.accounts.high_leverage_mode_config)?;
+
+    validate!(
+        config.market_index == user.high_leverage_mode_market_index,
+        ErrorCode::InvalidHighLeverageModeConfig,
+        "user is in high leverage mode for perp market {}, not {}",
+        user.high_leverage_mode_market_index,
+        config.market_index
+    )?;
+
+    config.remove_user()?;
+
+    user.is_high_leverage_mode = false;
+    user.high_leverage_mode_market_index = 0;
+
+    // leaving high leverage mode must not put the user under the standard initial margin
+    validate!(
+        meets_initial_margin_requirement(
+            user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map
+        )?,
+        ErrorCode::InsufficientCollateral,
+        "User does not meet initial margin requirement"
+    )?;
+
+    Ok(())
+}
+
+pub fn handle_update_perp_position_isolated(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    perp_market_index: u16,
+    isolated: bool,
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
+
+    let position = if isolated {
+        user.force_get_perp_position_mut(perp_market_index)?
+    } else {
+        user.get_perp_position_mut(perp_market_index)?
+    };
+
+    validate!(
+        position.base_asset_amount == 0
+            && position.quote_asset_amount == 0
+            && !position.has_open_order()
+            && !position.is_lp(),
+        ErrorCode::InvalidIsolatedPerpPosition,
+        "perp position for market {} must be empty to change isolated mode",
+        perp_market_index
+    )?;
+
+    validate!(
+        position.isolated_collateral == 0,
+        ErrorCode::InvalidIsolatedPerpPosition,
+        "isolated collateral must be transferred out before changing isolated mode"
+    )?;
+
+    position.is_isolated = isolated;
+
+    Ok(())
+}
+
+pub fn handle_transfer_isolated_perp_position_deposit(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    perp_market_index: u16,
+    amount: i64,
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
+        clock.slot,
+        None,
+    )?;
+
+    let user = &mut load_mut!(ctx.accounts.user)?;
+
+    validate!(!user.is_bankrupt(), ErrorCode::UserBankrupt)?;
+
+    validate!(
+        amount != 0,
+        ErrorCode::InsufficientDeposit,
+        "transfer amount cant be 0"
+    )?;
+
+    validate!(
+        user.get_perp_position(perp_market_index)?.is_isolated,
+        ErrorCode::InvalidIsolatedPerpPosition,
+        "perp position for market {} is not isolated",
+        perp_market_index
+    )?;
+
+    let token_amount = amount.unsigned_abs();
+
+    {
+        let spot_market = &mut spot_market_map.get_ref_mut(&QUOTE_SPOT_MARKET_INDEX)?;
+        let oracle_price_data = oracle_map.get_price_data(&spot_market.oracle)?;
+        controller::spot_balance::update_spot_market_cumulative_interest(
+            spot_market,
+            Some(oracle_price_data),
+            clock.unix_timestamp,
+        )?;
+
+        let quote_spot_position = user.get_quote_spot_position_mut();
+
+        if amount > 0 {
+            // collateral can only come from existing deposits, never from a borrow
+            validate!(
+                quote_spot_position.balance_type == SpotBalanceType::Deposit
+                    && quote_spot_position.get_token_amount(spot_market)?
+                        >= token_amount.cast::<u128>()?,
+                ErrorCode::InsufficientDeposit,
+                "quote deposit too small to transfer {} to isolated position",
+                token_amount
+            )?;
+
+            controller::spot_balance::update_spot_balances(
+                token_amount.cast()?,
+                &SpotBalanceType::Borrow,
+                spot_market,
+                quote_spot_position,
+                false,
+            )?;
+        } else {
+            controller::spot_balance::update_spot_balances(
+                token_amount.cast()?,
+                &SpotBalanceType::Deposit,
+                spot_market,
+                quote_spot_position,
+                false,
+            )?;
+        }
+    }
+
+    let perp_position = user.get_perp_position_mut(perp_market_index)?;
+    if amount > 0 {
+        perp_position.isolated_collateral =
+            perp_position.isolated_collateral.safe_add(token_amount)?;
+    } else {
+        validate!(
+            perp_position.isolated_collateral >= token_amount,
+            ErrorCode::InsufficientCollateral,
+            "isolated collateral {} less than transfer amount {}",
+            perp_position.isolated_collateral,
+            token_amount
+        )?;
+
+        perp_position.isolated_collateral =
+            perp_position.isolated_collateral.safe_sub(token_amount)?;
+    }
+
+    validate!(
+        meets_withdraw_margin_requirement(
+            user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map
+        )?,
+        ErrorCode::InsufficientCollateral,
+        "User does not meet cross initial margin requirement"
+    )?;
+
+    validate!(
+        meets_isolated_perp_position_margin_requirement(
+            user,
+            perp_market_index,
+            &perp_market_map,
+            MarginRequirementType::Initial,
+            &mut oracle_map,
+            None,
+        )?,
+        ErrorCode::InsufficientCollateral,
+        "Isolated perp position does not meet initial margin requirement"
+    )?;
+
+    user.update_last_active_slot(clock.slot);
+
+    Ok(())
+}
+
+pub fn handle_simulate_margin_health(
+    ctx: Context<SimulateMarginHealth>,
+    params: Option<OrderParams>,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let state = &ctx.accounts.state;
+
+    let remaining_accounts_iter = &mut ctx.remaining_accounts.iter().peekable();
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        remaining_accounts_iter,
+        &MarketSet::new(),
+        &MarketSet::new(),
+        clock.slot,
+        None,
+    )?;
+
+    let market_fee_override = get_market_fee_override(remaining_accounts_iter)?;
+
+    // simulated fills only touch this copy, the user account is never written
+    let mut user = copy_to_heap(&*load!(ctx.accounts.user)?);
+    let user_stats = load!(ctx.accounts.user_stats)?;
+
+    let perp_market_index = match params {
+        Some(params) if params.market_type == MarketType::Perp => {
+            let (oracle_price, cumulative_funding_rate, fee_structure) = {
+                let perp_market = perp_market_map.get_ref(&params.market_index)?;
+                let oracle_price = oracle_map.get_price_data(&perp_market.amm.oracle)?.price;
+                let cumulative_funding_rate = match params.direction {
+                    PositionDirection::Long => perp_market.amm.cumulative_funding_rate_long,
+                    PositionDirection::Short => perp_market.amm.cumulative_funding_rate_short,
+                };
+                let fee_structure = get_market_fee_structure(
+                    &state.perp_fee_structure,
+                    MarketType::Perp,
+                    params.market_index,
+                    perp_market.amm.fee_override_enabled,
+                    market_fee_override.as_ref(),
+                    now,
+                )?;
+                (oracle_price, cumulative_funding_rate, fee_structure)
+            };
+
+            let fill_price = if params.price == 0 {
+                oracle_price
+            } else {
+                params.price.cast()?
+            };
+
+            let quote_asset_amount = calculate_base_asset_value_with_oracle_price(
+                params.base_asset_amount.cast()?,
+                fill_price,
+            )?;
+
+            let position_delta = get_position_delta_for_fill(
+                params.base_asset_amount,
+                quote_asset_amount.cast()?,
+                params.direction,
+            )?;
+
+            let fee = calculate_simulated_fill_fee(
+                &user_stats,
+                quote_asset_amount.cast()?,
+                &fee_structure,
+                &MarketType::Perp,
+                params.post_only != PostOnlyParam::None,
+            )?;
+
+            let perp_position = user.force_get_perp_position_mut(params.market_index)?;
+            if perp_position.base_asset_amount == 0 {
+                perp_position.last_cumulative_funding_rate = cumulative_funding_rate.cast()?;
+            }
+
+            perp_position.base_asset_amount = perp_position
+                .base_asset_amount
+                .safe_add(position_delta.base_asset_amount)?;
+            perp_position.quote_asset_amount = perp_position
+                .quote_asset_amount
+                .safe_add(position_delta.quote_asset_amount)?
+                .safe_sub(fee)?;
+
+            Some(params.market_index)
+        }
+        Some(params) => {
+            validate!(
+                params.market_index != QUOTE_SPOT_MARKET_INDEX,
+                ErrorCode::InvalidOrderMarketType,
+                "cant simulate orders in the quote spot market"
+            )?;
+
+            // balance updates also move the market's totals, so the fill is applied to copies
+            let mut base_market = copy_to_heap(&*spot_market_map.get_ref(&params.market_index)?);
+            let mut quote_market = copy_to_heap(&*spot_market_map.get_quote_spot_market()?);
+
+            let fill_price = if params.price == 0 {
+                oracle_map
+                    .get_price_data(&base_market.oracle)?
+                    .price
+                    .cast()?
+            } else {
+                params.price
+            };
+
+            let quote_asset_amount = calculate_quote_asset_amount_for_maker_order(
+                params.base_asset_amount,
+                fill_price,
+                base_market.decimals,
+                params.direction,
+            )?;
+
+            let fee_structure = get_market_fee_structure(
+                &state.spot_fee_structure,
+                MarketType::Spot,
+                params.market_index,
+                base_market.fee_override_enabled,
+                market_fee_override.as_ref(),
+                now,
+            )?;
+
+            let fee = calculate_simulated_fill_fee(
+                &user_stats,
+                quote_asset_amount,
+                &fee_structure,
+                &MarketType::Spot,
+                params.post_only != PostOnlyParam::None,
+            )?;
+
+            let (base_update_direction, quote_update_direction, quote_token_amount) =
+                match params.direction {
+                    PositionDirection::Long => (
+                        SpotBalanceType::Deposit,
+                        SpotBalanceType::Borrow,
+                        quote_asset_amount.cast::<i64>()?.safe_add(fee)?,
+                    ),
+                    PositionDirection::Short => (
+                        SpotBalanceType::Borrow,
+                        SpotBalanceType::Deposit,
+                        quote_asset_amount.cast::<i64>()?.safe_sub(fee)?,
+                    ),
+                };
+
+            controller::spot_balance::update_spot_balances(
+                params.base_asset_amount.cast()?,
+                &base_update_direction,
+                &mut base_market,
+                user.force_get_spot_position_mut(params.market_index)?,
+                false,
+            )?;
+
+            controller::spot_balance::update_spot_balances(
+                quote_token_amount.max(0).cast()?,
+                &quote_update_direction,
+                &mut quote_market,
+                user.get_quote_spot_position_mut(),
+                false,
+            )?;
+
+            None
+        }
+        None => None,
+    };
+
+    let margin_health = calculate_margin_health(
+        &user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        perp_market_index,
+    )?;
+
+    let margin_health_data = margin_health
+        .try_to_vec()
+        .map_err(|_| ErrorCode::DefaultError)?;
+    anchor_lang::solana_program::program::set_return_data(&margin_health_data);
+
+    Ok(())
+}
+
+pub fn handle_update_user_delegate(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    delegate: Pubkey,
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+    user.delegate = delegate;
+    Ok(())
+}
+
+pub fn handle_update_user_delegate_permissions(
+    ctx: Context<UpdateUser>,
+    _sub_account_id: u16,
+    delegate_permissions: u8,
+    delegate_expiry_slot: u32,
+) -> Result<()> {
+    let mut user = load_mut!(ctx.accounts.user)?;
+
+    validate!(
+        BitFlags::<DelegatePermission>::from_bits(usize::from(delegate_permissions)).is_ok(),
+        ErrorCode::DefaultError,
+        "invalid delegate permissions {}",
+        delegate_permissions
+    )?;
+
+    msg!(
+        "user.delegate_permissions: {:?} -> {:?}",
+        user.delegate_permissions,
+        delegate_permissions
+    );
+
+    msg!(
+        "user.delegate_expiry_slot: {:?} -> {:?}",
+        user.delegate_expiry_slot,
+        delegate_expiry_slot
+    );
+
+    user.delegate_permissions = delegate_permissions;
+    user.delegate_expiry_slot = delegate_expiry_slot;
+
+    Ok(())
+}
+
+pub fn handle_update_user_disable_trading_until(
+    ctx: Context<UpdateUserDisableTradingUntil>,
+    disable_trading_until: i64,
+) -> Result<()> {
+    let user = load!(ctx.accounts.user)?;
+    let mut user_stats = load_mut!(ctx.accounts.user_stats)?;
+
+    // a delegate can only push the kill switch further out, never lift it
+    if !user.authority.eq(ctx.accounts.authority.key) {
+        validate!(
+            disable_trading_until > user_stats.disable_trading_until,
+            ErrorCode::UserTradingDisabled,
+            "delegate can only extend disable_trading_until {}",
+            user_stats.disable_trading_until
+        )?;
+    }
+
+    msg!(
+        "user_stats.disable_trading_until: {:?} -> {:?}",
+        user_stats.disable_trading_until,
+        disable_trading_until
+    );
+
+    user_stats.disable_trading_until = disable_trading_until;
+
+    Ok(())
+}
+
+pub fn handle_delete_user(ctx: Context<DeleteUser>) -> Result<()> {
+    let user = &load!(ctx.accounts.user)?;
+    let user_stats = &mut load_mut!(ctx.accounts.user_stats)?;
+
+    validate_user_deletion(user, user_stats)?;
+
+    safe_decrement!(user_stats.number_of_sub_accounts, 1);
+
+    let state = &mut ctx.accounts.state;
+    safe_decrement!(state.number_of_sub_accounts, 1);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(
+    sub_account_id: u16,
+)]
+pub struct InitializeUser<'info> {
+    #[account(
+        init,
+        seeds = [b"user", authority.key.as_ref(), sub_account_id.to_le_bytes().as_ref()],
+        space = User::SIZE,
+        bump,
+        payer = payer
+    )]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    #[account(mut)]
+    pub state: Box<Account<'info, State>>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUserStats<'info> {
+    #[account(
+        init,
+        seeds = [b"user_stats", authority.key.as_ref()],
+        space = UserStats::SIZE,
+        bump,
+        payer = payer
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    #[account(mut)]
+    pub state: Box<Account<'info, State>>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUser<'info> {
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    name: [u8; 32],
+)]
+pub struct InitializeReferrerName<'info> {
+    #[account(
+        init,
+        seeds = [b"referrer_name", name.as_ref()],
+        space = ReferrerName::SIZE,
+        bump,
+        payer = payer
+    )]
+    pub referrer_name: AccountLoader<'info, ReferrerName>,
+    #[account(
+        mut,
+        constraint = can_sign_for_user(&user, &authority)?
+    )]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        constraint = is_stats_for_user(&user, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeSignedOrderNonce<'info> {
+    #[account(
+        init,
+        seeds = [b"signed_order_nonce", user.key().as_ref()],
+        space = SignedOrderNonce::SIZE,
+        bump,
+        payer = payer
+    )]
+    pub signed_order_nonce: AccountLoader<'info, SignedOrderNonce>,
+    #[account(
+        constraint = can_sign_for_user(&user, &authority)?
+    )]
+    pub user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16,)]
+pub struct Deposit<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        constraint = can_sign_for_user(&user, &authority)?
+    )]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        constraint = is_stats_for_user(&user, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in `handle_deposit`
+    pub spot_market_vault: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked in `handle_deposit`
+    pub user_token_account: AccountInfo<'info>,
+    /// CHECK: spl token or token-2022, checked in `handle_deposit`
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16,)]
+pub struct DepositIntoUser<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    /// depositor, owns user_token_account but gets no rights over user
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in `handle_deposit_into_user`
+    pub spot_market_vault: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked in `handle_deposit_into_user`
+    pub user_token_account: AccountInfo<'info>,
+    /// CHECK: spl token or token-2022, checked in `handle_deposit_into_user`
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16,)]
+pub struct Withdraw<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        constraint = can_sign_for_user_with_permission(&user, &authority, DelegatePermission::WithdrawToAuthority)?,
+    )]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        constraint = is_stats_for_user(&user, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in `handle_withdraw`
+    pub spot_market_vault: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: forced drift_signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked in `handle_withdraw`
+    pub user_token_account: AccountInfo<'info>,
+    /// CHECK: spl token or token-2022, checked in `handle_withdraw`
+    pub token_program: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16,)]
+pub struct TransferDeposit<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub from_user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub to_user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub authority: Signer<'info>,
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in `handle_transfer_deposit`
+    pub spot_market_vault: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferPositions<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub from_user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub to_user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+    pub state: Box<Account<'info, State>>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16,)]
+pub struct BeginFlashBorrow<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in `handle_begin_flash_borrow`
+    pub spot_market_vault: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: forced drift_signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked in `handle_begin_flash_borrow`
+    pub user_token_account: AccountInfo<'info>,
+    /// CHECK: spl token or token-2022, checked in `handle_begin_flash_borrow`
+    pub token_program: AccountInfo<'info>,
+    /// CHECK: checked by address constraint
+    #[account(address = sysvar::instructions::ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16,)]
+pub struct EndFlashBorrow<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        seeds = [b"spot_market", market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub spot_market: AccountLoader<'info, SpotMarket>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in `handle_end_flash_borrow`
+    pub spot_market_vault: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked in `handle_end_flash_borrow`
+    pub user_token_account: AccountInfo<'info>,
+    /// CHECK: spl token or token-2022, checked in `handle_end_flash_borrow`
+    pub token_program: AccountInfo<'info>,
+    /// CHECK: checked by address constraint
+    #[account(address = sysvar::instructions::ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(in_market_index: u16, out_market_index: u16,)]
+pub struct Swap<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"spot_market_vault".as_ref(), out_market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in `handle_begin_swap` and `handle_end_swap`
+    pub out_spot_market_vault: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"spot_market_vault".as_ref(), in_market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in `handle_begin_swap` and `handle_end_swap`
+    pub in_spot_market_vault: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked in `handle_begin_swap` and `handle_end_swap`
+    pub out_token_account: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked in `handle_begin_swap` and `handle_end_swap`
+    pub in_token_account: AccountInfo<'info>,
+    /// CHECK: spl token or token-2022, checked in `handle_begin_swap` and `handle_end_swap`
+    pub out_token_program: AccountInfo<'info>,
+    /// CHECK: spl token or token-2022, checked in `handle_begin_swap` and `handle_end_swap`
+    pub in_token_program: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: forced drift_signer
+    pub drift_signer: AccountInfo<'info>,
+    /// CHECK: checked by address constraint
+    #[account(address = sysvar::instructions::ID)]
+    pub ix_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrder<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        constraint = can_sign_for_user_with_permission(&user, &authority, DelegatePermission::PlaceOrders)?
+    )]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        constraint = is_stats_for_user(&user, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-#[instruction(
-    sub_account_id: u16,
-)]
-pub struct InitializeUser<'info> {
+#[instruction(session_signer: Pubkey)]
+pub struct InitializeSessionKey<'info> {
     #[account(
         init,
-        seeds = [b"user", authority.key.as_ref(), sub_account_id.to_le_bytes().as_ref()],
-        space = User::SIZE,
+        seeds = [b"session_key", user.key().as_ref(), session_signer.as_ref()],
+        space = SessionKey::SIZE,
         bump,
         payer = payer
     )]
+    pub session_key: AccountLoader<'info, SessionKey>,
+    #[account(
+        has_one = authority
+    )]
     pub user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSessionKey<'info> {
     #[account(
         mut,
+        has_one = user,
+        close = authority
+    )]
+    pub session_key: AccountLoader<'info, SessionKey>,
+    #[account(
         has_one = authority
     )]
-    pub user_stats: AccountLoader<'info, UserStats>,
-    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrderWithSessionKey<'info> {
     pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        constraint = is_stats_for_user(&user, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    #[account(
+        mut,
+        seeds = [b"session_key", user.key().as_ref(), session_signer.key().as_ref()],
+        bump
+    )]
+    pub session_key: AccountLoader<'info, SessionKey>,
+    pub session_signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeBuilder<'info> {
+    #[account(
+        init,
+        seeds = [b"builder", authority.key().as_ref()],
+        space = Builder::SIZE,
+        bump,
+        payer = payer
+    )]
+    pub builder: AccountLoader<'info, Builder>,
     pub authority: Signer<'info>,
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -1659,17 +4945,26 @@ pub struct InitializeUser<'info> {
 }
 
 #[derive(Accounts)]
-pub struct InitializeUserStats<'info> {
+pub struct ApproveBuilder<'info> {
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub builder: AccountLoader<'info, Builder>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeUserFuel<'info> {
     #[account(
         init,
-        seeds = [b"user_stats", authority.key.as_ref()],
-        space = UserStats::SIZE,
+        seeds = [b"user_fuel", authority.key().as_ref()],
+        space = UserFuel::SIZE,
         bump,
         payer = payer
     )]
-    pub user_stats: AccountLoader<'info, UserStats>,
-    #[account(mut)]
-    pub state: Box<Account<'info, State>>,
+    pub user_fuel: AccountLoader<'info, UserFuel>,
     pub authority: Signer<'info>,
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -1678,28 +4973,19 @@ pub struct InitializeUserStats<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(
-    name: [u8; 32],
-)]
-pub struct InitializeReferrerName<'info> {
+pub struct InitializeDeadManSwitch<'info> {
     #[account(
         init,
-        seeds = [b"referrer_name", name.as_ref()],
-        space = ReferrerName::SIZE,
+        seeds = [b"dead_man_switch", user.key().as_ref()],
+        space = DeadManSwitch::SIZE,
         bump,
         payer = payer
     )]
-    pub referrer_name: AccountLoader<'info, ReferrerName>,
+    pub dead_man_switch: AccountLoader<'info, DeadManSwitch>,
     #[account(
-        mut,
-        constraint = can_sign_for_user(&user, &authority)?
+        has_one = authority
     )]
     pub user: AccountLoader<'info, User>,
-    #[account(
-        mut,
-        constraint = is_stats_for_user(&user, &user_stats)?
-    )]
-    pub user_stats: AccountLoader<'info, UserStats>,
     pub authority: Signer<'info>,
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -1708,105 +4994,117 @@ pub struct InitializeReferrerName<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(market_index: u16,)]
-pub struct Deposit<'info> {
-    pub state: Box<Account<'info, State>>,
+pub struct PingDeadManSwitch<'info> {
     #[account(
         mut,
-        constraint = can_sign_for_user(&user, &authority)?
+        has_one = user
     )]
-    pub user: AccountLoader<'info, User>,
+    pub dead_man_switch: AccountLoader<'info, DeadManSwitch>,
     #[account(
-        mut,
-        constraint = is_stats_for_user(&user, &user_stats)?
+        has_one = authority
     )]
-    pub user_stats: AccountLoader<'info, UserStats>,
+    pub user: AccountLoader<'info, User>,
     pub authority: Signer<'info>,
-    #[account(
-        mut,
-        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
-        bump,
-    )]
-    pub spot_market_vault: Box<Account<'info, TokenAccount>>,
-    #[account(
-        mut,
-        constraint = &spot_market_vault.mint.eq(&user_token_account.mint),
-        token::authority = authority
-    )]
-    pub user_token_account: Box<Account<'info, TokenAccount>>,
-    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-#[instruction(market_index: u16,)]
-pub struct Withdraw<'info> {
-    pub state: Box<Account<'info, State>>,
+pub struct DeleteDeadManSwitch<'info> {
     #[account(
         mut,
-        has_one = authority,
+        has_one = user,
+        close = authority
     )]
-    pub user: AccountLoader<'info, User>,
+    pub dead_man_switch: AccountLoader<'info, DeadManSwitch>,
     #[account(
-        mut,
         has_one = authority
     )]
-    pub user_stats: AccountLoader<'info, UserStats>,
+    pub user: AccountLoader<'info, User>,
     pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterFillCallback<'info> {
     #[account(
-        mut,
-        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        init,
+        seeds = [b"user_fill_callback", user.key().as_ref()],
+        space = UserFillCallback::SIZE,
         bump,
+        payer = payer
     )]
-    pub spot_market_vault: Box<Account<'info, TokenAccount>>,
-    #[account(
-        constraint = state.signer.eq(&drift_signer.key())
-    )]
-    /// CHECK: forced drift_signer
-    pub drift_signer: AccountInfo<'info>,
+    pub user_fill_callback: AccountLoader<'info, UserFillCallback>,
+    pub fill_callback_program: AccountLoader<'info, FillCallbackProgram>,
     #[account(
         mut,
-        constraint = &spot_market_vault.mint.eq(&user_token_account.mint)
+        has_one = authority
     )]
-    pub user_token_account: Box<Account<'info, TokenAccount>>,
-    pub token_program: Program<'info, Token>,
+    pub user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(market_index: u16,)]
-pub struct TransferDeposit<'info> {
-    #[account(
-        mut,
-        has_one = authority,
-    )]
-    pub from_user: AccountLoader<'info, User>,
+pub struct UnregisterFillCallback<'info> {
     #[account(
         mut,
-        has_one = authority,
+        has_one = user,
+        close = authority
     )]
-    pub to_user: AccountLoader<'info, User>,
+    pub user_fill_callback: AccountLoader<'info, UserFillCallback>,
     #[account(
         mut,
         has_one = authority
     )]
-    pub user_stats: AccountLoader<'info, UserStats>,
+    pub user: AccountLoader<'info, User>,
     pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrderWithDeadManSwitch<'info> {
     pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
     #[account(
-        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        seeds = [b"dead_man_switch", user.key().as_ref()],
         bump,
+        has_one = keeper
     )]
-    pub spot_market_vault: Box<Account<'info, TokenAccount>>,
+    pub dead_man_switch: AccountLoader<'info, DeadManSwitch>,
+    pub keeper: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct PlaceOrder<'info> {
+#[instruction(market_index: u16,)]
+pub struct WithdrawWithDeadManSwitch<'info> {
     pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        seeds = [b"dead_man_switch", user.key().as_ref()],
+        bump,
+        has_one = keeper
+    )]
+    pub dead_man_switch: AccountLoader<'info, DeadManSwitch>,
+    pub keeper: Signer<'info>,
     #[account(
         mut,
-        constraint = can_sign_for_user(&user, &authority)?
+        seeds = [b"spot_market_vault".as_ref(), market_index.to_le_bytes().as_ref()],
+        bump,
     )]
-    pub user: AccountLoader<'info, User>,
-    pub authority: Signer<'info>,
+    /// CHECK: checked in `handle_withdraw_with_dead_man_switch`
+    pub spot_market_vault: AccountInfo<'info>,
+    #[account(
+        constraint = state.signer.eq(&drift_signer.key())
+    )]
+    /// CHECK: forced drift_signer
+    pub drift_signer: AccountInfo<'info>,
+    #[account(mut)]
+    /// CHECK: checked in `handle_withdraw_with_dead_man_switch`
+    pub user_token_account: AccountInfo<'info>,
+    /// CHECK: spl token or token-2022, checked in `handle_withdraw_with_dead_man_switch`
+    pub token_program: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
@@ -1814,7 +5112,7 @@ pub struct CancelOrder<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
         mut,
-        constraint = can_sign_for_user(&user, &authority)?
+        constraint = can_sign_for_user_with_permission(&user, &authority, DelegatePermission::CancelOrders)?
     )]
     pub user: AccountLoader<'info, User>,
     pub authority: Signer<'info>,
@@ -1825,7 +5123,7 @@ pub struct PlaceAndTake<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
         mut,
-        constraint = can_sign_for_user(&user, &authority)?
+        constraint = can_sign_for_user_with_permission(&user, &authority, DelegatePermission::PlaceOrders)?
     )]
     pub user: AccountLoader<'info, User>,
     #[account(
@@ -1841,7 +5139,7 @@ pub struct PlaceAndMake<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
         mut,
-        constraint = can_sign_for_user(&user, &authority)?
+        constraint = can_sign_for_user_with_permission(&user, &authority, DelegatePermission::PlaceOrders)?
     )]
     pub user: AccountLoader<'info, User>,
     #[account(
@@ -1864,7 +5162,7 @@ pub struct AddRemoveLiquidity<'info> {
     pub state: Box<Account<'info, State>>,
     #[account(
         mut,
-        constraint = can_sign_for_user(&user, &authority)?,
+        constraint = can_sign_for_user_with_permission(&user, &authority, DelegatePermission::ManageLp)?,
     )]
     pub user: AccountLoader<'info, User>,
     pub authority: Signer<'info>,
@@ -1877,6 +5175,44 @@ pub struct RemoveLiquidityInExpiredMarket<'info> {
     pub user: AccountLoader<'info, User>,
 }
 
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct InitializePerpLpRange<'info> {
+    #[account(
+        init,
+        seeds = [b"perp_lp_range", user.key().as_ref(), market_index.to_le_bytes().as_ref()],
+        space = PerpLpRange::SIZE,
+        bump,
+        payer = payer
+    )]
+    pub perp_lp_range: AccountLoader<'info, PerpLpRange>,
+    #[account(
+        constraint = can_sign_for_user_with_permission(&user, &authority, DelegatePermission::ManageLp)?
+    )]
+    pub user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct UpdatePerpLpRange<'info> {
+    #[account(
+        mut,
+        seeds = [b"perp_lp_range", user.key().as_ref(), market_index.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub perp_lp_range: AccountLoader<'info, PerpLpRange>,
+    #[account(
+        constraint = can_sign_for_user_with_permission(&user, &authority, DelegatePermission::ManageLp)?
+    )]
+    pub user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 #[instruction(
     sub_account_id: u16,
@@ -1891,6 +5227,46 @@ pub struct UpdateUser<'info> {
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(
+    sub_account_id: u16,
+)]
+pub struct UpdateUserHighLeverageMode<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", authority.key.as_ref(), sub_account_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub high_leverage_mode_config: AccountLoader<'info, HighLeverageModeConfig>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateUserDisableTradingUntil<'info> {
+    #[account(
+        constraint = can_sign_for_user(&user, &authority)?
+    )]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        constraint = is_stats_for_user(&user, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SimulateMarginHealth<'info> {
+    pub state: Box<Account<'info, State>>,
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        constraint = is_stats_for_user(&user, &user_stats)?
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+}
+
 #[derive(Accounts)]
 pub struct DeleteUser<'info> {
     #[account(
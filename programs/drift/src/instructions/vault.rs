@@ -0,0 +1,437 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::instructions::constraints::*;
+use crate::instructions::optional_accounts::{load_maps, AccountMaps};
+use crate::instructions::transfer_deposit_between_users;
+use crate::math::casting::Cast;
+use crate::math::constants::{PERCENTAGE_PRECISION, QUOTE_SPOT_MARKET_INDEX};
+use crate::math::margin::calculate_user_equity;
+use crate::math::safe_unwrap::SafeUnwrap;
+use crate::math::spot_withdraw::validate_spot_market_vault_amount;
+use crate::state::events::NewUserRecord;
+use crate::state::oracle_map::OracleMap;
+use crate::state::perp_market_map::{MarketSet, PerpMarketMap};
+use crate::state::spot_market_map::{get_writable_spot_market_set, SpotMarketMap};
+use crate::state::state::State;
+use crate::state::token::load_token_account;
+use crate::state::traits::Size;
+use crate::state::user::{DelegatePermission, User, UserStats};
+use crate::state::vault::{Vault, VaultDepositor};
+use crate::{load_mut, safe_increment, validate};
+
+/// creates the vault and the user it owns. the manager trades the user as its delegate but can
+/// only move funds out through manager_withdraw_from_vault
+pub fn handle_initialize_vault(
+    ctx: Context<InitializeVault>,
+    name: [u8; 32],
+    profit_share: u32,
+) -> Result<()> {
+    validate!(
+        profit_share.cast::<u128>()? <= PERCENTAGE_PRECISION,
+        ErrorCode::InvalidVaultProfitShare,
+        "profit_share {} above 100%",
+        profit_share
+    )?;
+
+    let clock = Clock::get()?;
+    let vault_key = ctx.accounts.vault.key();
+    let user_key = ctx.accounts.user.key();
+    let manager_key = ctx.accounts.manager.key();
+
+    let mut vault = ctx
+        .accounts
+        .vault
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    vault.name = name;
+    vault.manager = manager_key;
+    vault.user = user_key;
+    vault.profit_share = profit_share;
+    vault.bump = *ctx.bumps.get("vault").safe_unwrap()?;
+
+    let mut user = ctx
+        .accounts
+        .user
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    user.authority = vault_key;
+    user.delegate = manager_key;
+    user.delegate_permissions = DelegatePermission::PlaceOrders as u8
+        | DelegatePermission::CancelOrders as u8
+        | DelegatePermission::Settle as u8;
+    user.name = name;
+    user.next_order_id = 1;
+    user.next_liquidation_id = 1;
+
+    let mut user_stats = ctx
+        .accounts
+        .user_stats
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    *user_stats = UserStats {
+        authority: vault_key,
+        number_of_sub_accounts: 1,
+        number_of_sub_accounts_created: 1,
+        last_taker_volume_30d_ts: clock.unix_timestamp,
+        last_maker_volume_30d_ts: clock.unix_timestamp,
+        last_filler_volume_30d_ts: clock.unix_timestamp,
+        ..UserStats::default()
+    };
+
+    let state = &mut ctx.accounts.state;
+    safe_increment!(state.number_of_authorities, 1);
+    safe_increment!(state.number_of_sub_accounts, 1);
+
+    emit!(NewUserRecord {
+        ts: clock.unix_timestamp,
+        user_authority: vault_key,
+        user: user_key,
+        sub_account_id: 0,
+        name,
+        referrer: Pubkey::default(),
+    });
+
+    Ok(())
+}
+
+pub fn handle_initialize_vault_depositor(ctx: Context<InitializeVaultDepositor>) -> Result<()> {
+    let mut vault_depositor = ctx
+        .accounts
+        .vault_depositor
+        .load_init()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    vault_depositor.vault = ctx.accounts.vault.key();
+    vault_depositor.authority = ctx.accounts.authority.key();
+
+    Ok(())
+}
+
+/// moves amount of the depositor's quote deposit into the vault user for vault shares
+#[access_control(
+    deposit_not_paused(&ctx.accounts.state)
+    withdraw_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_vault_deposit(ctx: Context<VaultDepositorTransfer>, amount: u64) -> Result<()> {
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let vault_user = &mut load_mut!(ctx.accounts.user)?;
+    let depositor_user = &mut load_mut!(ctx.accounts.depositor_user)?;
+
+    let equity = calculate_vault_equity(
+        vault_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+    )?;
+
+    let vault = &mut load_mut!(ctx.accounts.vault)?;
+    let vault_depositor = &mut load_mut!(ctx.accounts.vault_depositor)?;
+    let shares = vault_depositor.deposit(vault, amount, equity)?;
+
+    transfer_deposit_between_users(
+        depositor_user,
+        ctx.accounts.depositor_user.key(),
+        vault_user,
+        ctx.accounts.user.key(),
+        ctx.accounts.authority.key(),
+        QUOTE_SPOT_MARKET_INDEX,
+        amount,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        &clock,
+    )?;
+
+    validate_spot_market_vault_amount(
+        &spot_market_map.get_ref(&QUOTE_SPOT_MARKET_INDEX)?,
+        load_token_account(
+            &ctx.accounts.spot_market_vault,
+            ctx.accounts.spot_market_vault.owner,
+        )?
+        .amount,
+    )?;
+
+    msg!(
+        "vault depositor {} deposited {} for {} shares",
+        ctx.accounts.vault_depositor.key(),
+        amount,
+        shares
+    );
+
+    Ok(())
+}
+
+/// redeems shares for a quote deposit moved from the vault user back to the depositor, after
+/// paying profit share on the depositor's gains above its high water mark
+#[access_control(
+    deposit_not_paused(&ctx.accounts.state)
+    withdraw_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_vault_withdraw(ctx: Context<VaultDepositorTransfer>, shares: u128) -> Result<()> {
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let vault_user = &mut load_mut!(ctx.accounts.user)?;
+    let depositor_user = &mut load_mut!(ctx.accounts.depositor_user)?;
+
+    let equity = calculate_vault_equity(
+        vault_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+    )?;
+
+    let vault = &mut load_mut!(ctx.accounts.vault)?;
+    let vault_depositor = &mut load_mut!(ctx.accounts.vault_depositor)?;
+    let amount = vault_depositor.withdraw(vault, shares, equity)?;
+
+    transfer_deposit_between_users(
+        vault_user,
+        ctx.accounts.user.key(),
+        depositor_user,
+        ctx.accounts.depositor_user.key(),
+        ctx.accounts.vault.key(),
+        QUOTE_SPOT_MARKET_INDEX,
+        amount,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        &clock,
+    )?;
+
+    validate_spot_market_vault_amount(
+        &spot_market_map.get_ref(&QUOTE_SPOT_MARKET_INDEX)?,
+        load_token_account(
+            &ctx.accounts.spot_market_vault,
+            ctx.accounts.spot_market_vault.owner,
+        )?
+        .amount,
+    )?;
+
+    msg!(
+        "vault depositor {} withdrew {} for {} shares, profit share paid {}",
+        ctx.accounts.vault_depositor.key(),
+        amount,
+        shares,
+        vault_depositor.cumulative_profit_share_paid
+    );
+
+    Ok(())
+}
+
+#[access_control(
+    deposit_not_paused(&ctx.accounts.state)
+    withdraw_not_paused(&ctx.accounts.state)
+)]
+pub fn handle_manager_withdraw_from_vault(
+    ctx: Context<ManagerWithdrawFromVault>,
+    shares: u128,
+) -> Result<()> {
+    let clock = Clock::get()?;
+    let state = &ctx.accounts.state;
+
+    let AccountMaps {
+        perp_market_map,
+        spot_market_map,
+        mut oracle_map,
+    } = load_maps(
+        &mut ctx.remaining_accounts.iter().peekable(),
+        &MarketSet::new(),
+        &get_writable_spot_market_set(QUOTE_SPOT_MARKET_INDEX),
+        clock.slot,
+        Some(state.oracle_guard_rails),
+    )?;
+
+    let vault_user = &mut load_mut!(ctx.accounts.user)?;
+    let manager_user = &mut load_mut!(ctx.accounts.manager_user)?;
+
+    let equity = calculate_vault_equity(
+        vault_user,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+    )?;
+
+    let amount = load_mut!(ctx.accounts.vault)?.manager_withdraw(shares, equity)?;
+
+    transfer_deposit_between_users(
+        vault_user,
+        ctx.accounts.user.key(),
+        manager_user,
+        ctx.accounts.manager_user.key(),
+        ctx.accounts.vault.key(),
+        QUOTE_SPOT_MARKET_INDEX,
+        amount,
+        &perp_market_map,
+        &spot_market_map,
+        &mut oracle_map,
+        &clock,
+    )?;
+
+    validate_spot_market_vault_amount(
+        &spot_market_map.get_ref(&QUOTE_SPOT_MARKET_INDEX)?,
+        load_token_account(
+            &ctx.accounts.spot_market_vault,
+            ctx.accounts.spot_market_vault.owner,
+        )?
+        .amount,
+    )?;
+
+    msg!("vault manager withdrew {} for {} shares", amount, shares);
+
+    Ok(())
+}
+
+fn calculate_vault_equity(
+    vault_user: &User,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+) -> DriftResult<u64> {
+    let (equity, all_oracles_valid) =
+        calculate_user_equity(vault_user, perp_market_map, spot_market_map, oracle_map)?;
+
+    validate!(
+        all_oracles_valid,
+        ErrorCode::InvalidOracle,
+        "vault equity needs valid oracles"
+    )?;
+
+    equity.max(0).cast()
+}
+
+#[derive(Accounts)]
+#[instruction(name: [u8; 32])]
+pub struct InitializeVault<'info> {
+    #[account(
+        init,
+        seeds = [b"vault", name.as_ref()],
+        space = Vault::SIZE,
+        bump,
+        payer = manager
+    )]
+    pub vault: AccountLoader<'info, Vault>,
+    #[account(
+        init,
+        seeds = [b"user", vault.key().as_ref(), 0_u16.to_le_bytes().as_ref()],
+        space = User::SIZE,
+        bump,
+        payer = manager
+    )]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        init,
+        seeds = [b"user_stats", vault.key().as_ref()],
+        space = UserStats::SIZE,
+        bump,
+        payer = manager
+    )]
+    pub user_stats: AccountLoader<'info, UserStats>,
+    #[account(mut)]
+    pub state: Box<Account<'info, State>>,
+    #[account(mut)]
+    pub manager: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeVaultDepositor<'info> {
+    pub vault: AccountLoader<'info, Vault>,
+    #[account(
+        init,
+        seeds = [b"vault_depositor", vault.key().as_ref(), authority.key().as_ref()],
+        space = VaultDepositor::SIZE,
+        bump,
+        payer = payer
+    )]
+    pub vault_depositor: AccountLoader<'info, VaultDepositor>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VaultDepositorTransfer<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        has_one = user
+    )]
+    pub vault: AccountLoader<'info, Vault>,
+    #[account(
+        mut,
+        has_one = vault,
+        has_one = authority
+    )]
+    pub vault_depositor: AccountLoader<'info, VaultDepositor>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    /// the depositor's own user the deposit moves from and back to
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub depositor_user: AccountLoader<'info, User>,
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"spot_market_vault".as_ref(), QUOTE_SPOT_MARKET_INDEX.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in handlers
+    pub spot_market_vault: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManagerWithdrawFromVault<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        has_one = user,
+        has_one = manager
+    )]
+    pub vault: AccountLoader<'info, Vault>,
+    #[account(mut)]
+    pub user: AccountLoader<'info, User>,
+    #[account(
+        mut,
+        constraint = manager_user.load()?.authority.eq(manager.key)
+    )]
+    pub manager_user: AccountLoader<'info, User>,
+    pub manager: Signer<'info>,
+    #[account(
+        seeds = [b"spot_market_vault".as_ref(), QUOTE_SPOT_MARKET_INDEX.to_le_bytes().as_ref()],
+        bump,
+    )]
+    /// CHECK: checked in handlers
+    pub spot_market_vault: AccountInfo<'info>,
+}
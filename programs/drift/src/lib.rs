@@ -12,16 +12,22 @@ use math::{bn, constants::*};
 use state::oracle::OracleSource;
 
 use crate::controller::position::PositionDirection;
-use crate::state::perp_market::{ContractTier, MarketStatus};
+use crate::state::admin_timelock::AdminChange;
+use crate::state::insurance_fund_stake::InsuranceFundStakeLockupTier;
+use crate::state::perp_market::{ContractTier, ContractType, MarginTier, MarketStatus};
+use crate::state::prelaunch_oracle::PrelaunchOracleParams;
 use crate::state::spot_market::AssetTier;
+use crate::state::spot_market::BorrowRateKink;
 use crate::state::spot_market::SpotFulfillmentConfigStatus;
 use crate::state::state::FeeStructure;
 use crate::state::state::*;
-use crate::state::user::MarketType;
+use crate::state::user::{MarketType, PerpLpTier};
 
 pub mod controller;
 pub mod error;
 pub mod ids;
+#[cfg(feature = "cpi")]
+pub mod instruction_builders;
 pub mod instructions;
 pub mod macros;
 pub mod math;
@@ -55,6 +61,10 @@ pub mod drift {
         handle_initialize_user_stats(ctx)
     }
 
+    pub fn migrate_user(ctx: Context<MigrateUser>) -> Result<()> {
+        handle_migrate_user(ctx)
+    }
+
     pub fn initialize_referrer_name(
         ctx: Context<InitializeReferrerName>,
         name: [u8; 32],
@@ -62,6 +72,10 @@ pub mod drift {
         handle_initialize_referrer_name(ctx, name)
     }
 
+    pub fn initialize_signed_order_nonce(ctx: Context<InitializeSignedOrderNonce>) -> Result<()> {
+        handle_initialize_signed_order_nonce(ctx)
+    }
+
     pub fn deposit(
         ctx: Context<Deposit>,
         market_index: u16,
@@ -71,6 +85,14 @@ pub mod drift {
         handle_deposit(ctx, market_index, amount, reduce_only)
     }
 
+    pub fn deposit_into_user(
+        ctx: Context<DepositIntoUser>,
+        market_index: u16,
+        amount: u64,
+    ) -> Result<()> {
+        handle_deposit_into_user(ctx, market_index, amount)
+    }
+
     pub fn withdraw(
         ctx: Context<Withdraw>,
         market_index: u16,
@@ -88,10 +110,166 @@ pub mod drift {
         handle_transfer_deposit(ctx, market_index, amount)
     }
 
+    pub fn transfer_positions_between_subaccounts(
+        ctx: Context<TransferPositions>,
+    ) -> anchor_lang::Result<()> {
+        handle_transfer_positions_between_subaccounts(ctx)
+    }
+
+    pub fn begin_flash_borrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, BeginFlashBorrow<'info>>,
+        market_index: u16,
+        amount: u64,
+    ) -> Result<()> {
+        handle_begin_flash_borrow(ctx, market_index, amount)
+    }
+
+    pub fn end_flash_borrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, EndFlashBorrow<'info>>,
+        market_index: u16,
+        amount: u64,
+    ) -> Result<()> {
+        handle_end_flash_borrow(ctx, market_index, amount)
+    }
+
+    pub fn begin_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        in_market_index: u16,
+        out_market_index: u16,
+        amount_in: u64,
+    ) -> Result<()> {
+        handle_begin_swap(ctx, in_market_index, out_market_index, amount_in)
+    }
+
+    pub fn end_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        in_market_index: u16,
+        out_market_index: u16,
+    ) -> Result<()> {
+        handle_end_swap(ctx, in_market_index, out_market_index)
+    }
+
     pub fn place_perp_order(ctx: Context<PlaceOrder>, params: OrderParams) -> Result<()> {
         handle_place_perp_order(ctx, params)
     }
 
+    pub fn initialize_session_key(
+        ctx: Context<InitializeSessionKey>,
+        session_signer: Pubkey,
+        expiry_ts: i64,
+        max_notional: u64,
+        perp_market_indexes: Vec<u16>,
+    ) -> Result<()> {
+        handle_initialize_session_key(
+            ctx,
+            session_signer,
+            expiry_ts,
+            max_notional,
+            perp_market_indexes,
+        )
+    }
+
+    pub fn revoke_session_key(ctx: Context<RevokeSessionKey>) -> Result<()> {
+        handle_revoke_session_key(ctx)
+    }
+
+    pub fn place_perp_order_with_session_key(
+        ctx: Context<PlaceOrderWithSessionKey>,
+        params: OrderParams,
+    ) -> Result<()> {
+        handle_place_perp_order_with_session_key(ctx, params)
+    }
+
+    pub fn initialize_user_fuel(ctx: Context<InitializeUserFuel>) -> Result<()> {
+        handle_initialize_user_fuel(ctx)
+    }
+
+    pub fn initialize_builder(ctx: Context<InitializeBuilder>, name: [u8; 32]) -> Result<()> {
+        handle_initialize_builder(ctx, name)
+    }
+
+    pub fn approve_builder(ctx: Context<ApproveBuilder>, max_fee_share: u8) -> Result<()> {
+        handle_approve_builder(ctx, max_fee_share)
+    }
+
+    pub fn initialize_dead_man_switch(
+        ctx: Context<InitializeDeadManSwitch>,
+        keeper: Pubkey,
+        withdraw_destination: Pubkey,
+        timeout_days: u16,
+    ) -> Result<()> {
+        handle_initialize_dead_man_switch(ctx, keeper, withdraw_destination, timeout_days)
+    }
+
+    pub fn ping_dead_man_switch(ctx: Context<PingDeadManSwitch>) -> Result<()> {
+        handle_ping_dead_man_switch(ctx)
+    }
+
+    pub fn delete_dead_man_switch(ctx: Context<DeleteDeadManSwitch>) -> Result<()> {
+        handle_delete_dead_man_switch(ctx)
+    }
+
+    pub fn register_fill_callback(ctx: Context<RegisterFillCallback>) -> Result<()> {
+        handle_register_fill_callback(ctx)
+    }
+
+    pub fn unregister_fill_callback(ctx: Context<UnregisterFillCallback>) -> Result<()> {
+        handle_unregister_fill_callback(ctx)
+    }
+
+    pub fn initialize_vault(
+        ctx: Context<InitializeVault>,
+        name: [u8; 32],
+        profit_share: u32,
+    ) -> Result<()> {
+        handle_initialize_vault(ctx, name, profit_share)
+    }
+
+    pub fn initialize_vault_depositor(ctx: Context<InitializeVaultDepositor>) -> Result<()> {
+        handle_initialize_vault_depositor(ctx)
+    }
+
+    pub fn vault_deposit(ctx: Context<VaultDepositorTransfer>, amount: u64) -> Result<()> {
+        handle_vault_deposit(ctx, amount)
+    }
+
+    pub fn vault_withdraw(ctx: Context<VaultDepositorTransfer>, shares: u128) -> Result<()> {
+        handle_vault_withdraw(ctx, shares)
+    }
+
+    pub fn manager_withdraw_from_vault(
+        ctx: Context<ManagerWithdrawFromVault>,
+        shares: u128,
+    ) -> Result<()> {
+        handle_manager_withdraw_from_vault(ctx, shares)
+    }
+
+    pub fn place_order_with_dead_man_switch(
+        ctx: Context<PlaceOrderWithDeadManSwitch>,
+        params: OrderParams,
+    ) -> Result<()> {
+        handle_place_order_with_dead_man_switch(ctx, params)
+    }
+
+    pub fn withdraw_with_dead_man_switch(
+        ctx: Context<WithdrawWithDeadManSwitch>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_withdraw_with_dead_man_switch(ctx, market_index)
+    }
+
+    pub fn place_orders_scaled(ctx: Context<PlaceOrder>, params: ScaleOrderParams) -> Result<()> {
+        handle_place_orders_scaled(ctx, params)
+    }
+
+    pub fn modify_order(
+        ctx: Context<PlaceOrder>,
+        order_id: u32,
+        params: ModifyOrderParams,
+    ) -> Result<()> {
+        handle_modify_order(ctx, order_id, params)
+    }
+
     pub fn cancel_order(ctx: Context<CancelOrder>, order_id: Option<u32>) -> Result<()> {
         handle_cancel_order(ctx, order_id)
     }
@@ -109,6 +287,24 @@ pub mod drift {
         handle_cancel_orders(ctx, market_type, market_index, direction)
     }
 
+    pub fn cancel_orders_with_filter(
+        ctx: Context<CancelOrder>,
+        market_type: Option<MarketType>,
+        market_index: Option<u16>,
+        direction: Option<PositionDirection>,
+        filter: CancelOrdersFilter,
+    ) -> Result<()> {
+        handle_cancel_orders_with_filter(ctx, market_type, market_index, direction, filter)
+    }
+
+    pub fn link_orders(
+        ctx: Context<PlaceOrder>,
+        order_id: u32,
+        linked_order_id: u32,
+    ) -> Result<()> {
+        handle_link_orders(ctx, order_id, linked_order_id)
+    }
+
     pub fn place_and_take_perp_order(
         ctx: Context<PlaceAndTake>,
         params: OrderParams,
@@ -138,6 +334,22 @@ pub mod drift {
         handle_place_and_take_spot_order(ctx, params, fulfillment_type, maker_order_id)
     }
 
+    pub fn place_and_take_basis_order(
+        ctx: Context<PlaceAndTake>,
+        perp_params: OrderParams,
+        spot_market_index: u16,
+        basis_limit: i64,
+        spot_fulfillment_type: SpotFulfillmentType,
+    ) -> Result<()> {
+        handle_place_and_take_basis_order(
+            ctx,
+            perp_params,
+            spot_market_index,
+            basis_limit,
+            spot_fulfillment_type,
+        )
+    }
+
     pub fn place_and_make_spot_order(
         ctx: Context<PlaceAndMake>,
         params: OrderParams,
@@ -171,6 +383,24 @@ pub mod drift {
         handle_remove_perp_lp_shares_in_expiring_market(ctx, shares_to_burn, market_index)
     }
 
+    pub fn initialize_perp_lp_range(
+        ctx: Context<InitializePerpLpRange>,
+        market_index: u16,
+        lower_price: u64,
+        upper_price: u64,
+    ) -> Result<()> {
+        handle_initialize_perp_lp_range(ctx, market_index, lower_price, upper_price)
+    }
+
+    pub fn update_perp_lp_range(
+        ctx: Context<UpdatePerpLpRange>,
+        market_index: u16,
+        lower_price: u64,
+        upper_price: u64,
+    ) -> Result<()> {
+        handle_update_perp_lp_range(ctx, market_index, lower_price, upper_price)
+    }
+
     pub fn update_user_name(
         ctx: Context<UpdateUser>,
         _sub_account_id: u16,
@@ -195,6 +425,78 @@ pub mod drift {
         handle_update_user_margin_trading_enabled(ctx, _sub_account_id, margin_trading_enabled)
     }
 
+    pub fn update_user_protected_maker_mode(
+        ctx: Context<UpdateUser>,
+        _sub_account_id: u16,
+        protected_maker: bool,
+    ) -> Result<()> {
+        handle_update_user_protected_maker_mode(ctx, _sub_account_id, protected_maker)
+    }
+
+    pub fn update_user_third_party_deposits_disabled(
+        ctx: Context<UpdateUser>,
+        _sub_account_id: u16,
+        third_party_deposits_disabled: bool,
+    ) -> Result<()> {
+        handle_update_user_third_party_deposits_disabled(
+            ctx,
+            _sub_account_id,
+            third_party_deposits_disabled,
+        )
+    }
+
+    pub fn update_user_perp_lp_tier(
+        ctx: Context<UpdateUser>,
+        _sub_account_id: u16,
+        perp_lp_tier: PerpLpTier,
+    ) -> Result<()> {
+        handle_update_user_perp_lp_tier(ctx, _sub_account_id, perp_lp_tier)
+    }
+
+    pub fn enable_user_high_leverage_mode(
+        ctx: Context<UpdateUserHighLeverageMode>,
+        _sub_account_id: u16,
+    ) -> Result<()> {
+        handle_enable_user_high_leverage_mode(ctx, _sub_account_id)
+    }
+
+    pub fn disable_user_high_leverage_mode(
+        ctx: Context<UpdateUserHighLeverageMode>,
+        _sub_account_id: u16,
+    ) -> Result<()> {
+        handle_disable_user_high_leverage_mode(ctx, _sub_account_id)
+    }
+
+    pub fn update_perp_position_isolated(
+        ctx: Context<UpdateUser>,
+        _sub_account_id: u16,
+        perp_market_index: u16,
+        isolated: bool,
+    ) -> Result<()> {
+        handle_update_perp_position_isolated(ctx, _sub_account_id, perp_market_index, isolated)
+    }
+
+    pub fn transfer_isolated_perp_position_deposit(
+        ctx: Context<UpdateUser>,
+        _sub_account_id: u16,
+        perp_market_index: u16,
+        amount: i64,
+    ) -> Result<()> {
+        handle_transfer_isolated_perp_position_deposit(
+            ctx,
+            _sub_account_id,
+            perp_market_index,
+            amount,
+        )
+    }
+
+    pub fn simulate_margin_health(
+        ctx: Context<SimulateMarginHealth>,
+        params: Option<OrderParams>,
+    ) -> Result<()> {
+        handle_simulate_margin_health(ctx, params)
+    }
+
     pub fn update_user_delegate(
         ctx: Context<UpdateUser>,
         _sub_account_id: u16,
@@ -203,6 +505,27 @@ pub mod drift {
         handle_update_user_delegate(ctx, _sub_account_id, delegate)
     }
 
+    pub fn update_user_delegate_permissions(
+        ctx: Context<UpdateUser>,
+        _sub_account_id: u16,
+        delegate_permissions: u8,
+        delegate_expiry_slot: u32,
+    ) -> Result<()> {
+        handle_update_user_delegate_permissions(
+            ctx,
+            _sub_account_id,
+            delegate_permissions,
+            delegate_expiry_slot,
+        )
+    }
+
+    pub fn update_user_disable_trading_until(
+        ctx: Context<UpdateUserDisableTradingUntil>,
+        disable_trading_until: i64,
+    ) -> Result<()> {
+        handle_update_user_disable_trading_until(ctx, disable_trading_until)
+    }
+
     pub fn delete_user(ctx: Context<DeleteUser>) -> Result<()> {
         handle_delete_user(ctx)
     }
@@ -217,6 +540,25 @@ pub mod drift {
         handle_fill_perp_order(ctx, order_id)
     }
 
+    pub fn fill_twap_order(ctx: Context<FillOrder>, order_id: u32) -> Result<()> {
+        handle_fill_twap_order(ctx, order_id)
+    }
+
+    pub fn place_signed_order(
+        ctx: Context<PlaceSignedOrder>,
+        message: SignedOrderMessage,
+    ) -> Result<()> {
+        handle_place_signed_order(ctx, message)
+    }
+
+    pub fn fill_rfq(
+        ctx: Context<FillRfq>,
+        taker_request: RfqTakerRequest,
+        maker_quotes: Vec<RfqMakerQuote>,
+    ) -> Result<()> {
+        handle_fill_rfq(ctx, taker_request, maker_quotes)
+    }
+
     pub fn revert_fill(ctx: Context<RevertFill>) -> Result<()> {
         handle_revert_fill(ctx)
     }
@@ -238,18 +580,76 @@ pub mod drift {
         handle_force_cancel_orders(ctx)
     }
 
+    pub fn cancel_stale_orders(ctx: Context<ForceCancelOrder>) -> Result<()> {
+        handle_cancel_stale_orders(ctx)
+    }
+
     pub fn update_user_idle(ctx: Context<UpdateUserIdle>) -> Result<()> {
         handle_update_user_idle(ctx)
     }
 
+    pub fn log_user_risk(ctx: Context<LogUserRisk>) -> Result<()> {
+        handle_log_user_risk(ctx)
+    }
+
+    pub fn update_user_margin_warning(ctx: Context<UpdateUserMarginWarning>) -> Result<()> {
+        handle_update_user_margin_warning(ctx)
+    }
+
+    pub fn update_user_fuel(ctx: Context<UpdateUserFuel>) -> Result<()> {
+        handle_update_user_fuel(ctx)
+    }
+
     pub fn settle_pnl(ctx: Context<SettlePNL>, market_index: u16) -> Result<()> {
         handle_settle_pnl(ctx, market_index)
     }
 
+    pub fn settle_funding_against_borrow(ctx: Context<SettlePNL>, market_index: u16) -> Result<()> {
+        handle_settle_funding_against_borrow(ctx, market_index)
+    }
+
+    pub fn settle_pnl_batch(ctx: Context<SettlePnlBatch>, market_index: u16) -> Result<()> {
+        handle_settle_pnl_batch(ctx, market_index)
+    }
+
+    pub fn rebalance_pnl_pools(
+        ctx: Context<RebalancePnlPools>,
+        perp_market_indexes: Vec<u16>,
+    ) -> Result<()> {
+        handle_rebalance_pnl_pools(ctx, perp_market_indexes)
+    }
+
     pub fn settle_funding_payment(ctx: Context<SettleFunding>) -> Result<()> {
         handle_settle_funding_payment(ctx)
     }
 
+    pub fn settle_funding_payments_batch(
+        ctx: Context<SettleFundingBatch>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_settle_funding_payments_batch(ctx, market_index)
+    }
+
+    pub fn get_predicted_funding_rate(ctx: Context<GetPredictedFundingRate>) -> Result<()> {
+        handle_get_predicted_funding_rate(ctx)
+    }
+
+    pub fn get_order_book_snapshot(
+        ctx: Context<GetOrderBookSnapshot>,
+        market_index: u16,
+        price_step: u64,
+        depth: u8,
+    ) -> Result<()> {
+        handle_get_order_book_snapshot(ctx, market_index, price_step, depth)
+    }
+
+    pub fn extend_market_lookup_table(
+        ctx: Context<ExtendMarketLookupTable>,
+        table_index: u16,
+    ) -> Result<()> {
+        handle_extend_market_lookup_table(ctx, table_index)
+    }
+
     pub fn settle_lp(ctx: Context<SettleLP>, market_index: u16) -> Result<()> {
         handle_settle_lp(ctx, market_index)
     }
@@ -258,6 +658,27 @@ pub mod drift {
         handle_settle_expired_market(ctx, market_index)
     }
 
+    pub fn update_perp_market_delisting_twap(
+        ctx: Context<UpdatePerpMarketDelistingTwap>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_update_perp_market_delisting_twap(ctx, market_index)
+    }
+
+    pub fn settle_expired_perp_positions(
+        ctx: Context<SettleExpiredPerpPositions>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_settle_expired_perp_positions(ctx, market_index)
+    }
+
+    pub fn close_delisted_perp_market(
+        ctx: Context<CloseDelistedPerpMarket>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_close_delisted_perp_market(ctx, market_index)
+    }
+
     pub fn liquidate_perp(
         ctx: Context<LiquidatePerp>,
         market_index: u16,
@@ -272,6 +693,20 @@ pub mod drift {
         )
     }
 
+    pub fn liquidate_perp_with_backstop(
+        ctx: Context<LiquidatePerpWithBackstop>,
+        market_index: u16,
+        liquidator_max_base_asset_amount: u64,
+        limit_price: Option<u64>,
+    ) -> Result<()> {
+        handle_liquidate_perp_with_backstop(
+            ctx,
+            market_index,
+            liquidator_max_base_asset_amount,
+            limit_price,
+        )
+    }
+
     pub fn liquidate_spot(
         ctx: Context<LiquidateSpot>,
         asset_market_index: u16,
@@ -328,6 +763,14 @@ pub mod drift {
         handle_resolve_perp_pnl_deficit(ctx, spot_market_index, perp_market_index)
     }
 
+    pub fn update_insurance_fund_lend_to_amm(
+        ctx: Context<ResolvePerpPnlDeficit>,
+        spot_market_index: u16,
+        perp_market_index: u16,
+    ) -> Result<()> {
+        handle_update_insurance_fund_lend_to_amm(ctx, spot_market_index, perp_market_index)
+    }
+
     pub fn resolve_perp_bankruptcy(
         ctx: Context<ResolveBankruptcy>,
         quote_spot_market_index: u16,
@@ -336,6 +779,10 @@ pub mod drift {
         handle_resolve_perp_bankruptcy(ctx, quote_spot_market_index, market_index)
     }
 
+    pub fn auto_deleverage_perp(ctx: Context<AutoDeleveragePerp>, market_index: u16) -> Result<()> {
+        handle_auto_deleverage_perp(ctx, market_index)
+    }
+
     pub fn resolve_spot_bankruptcy(
         ctx: Context<ResolveBankruptcy>,
         market_index: u16,
@@ -354,12 +801,27 @@ pub mod drift {
         handle_update_funding_rate(ctx, market_index)
     }
 
+    pub fn update_amm_peg(ctx: Context<UpdateAMMPeg>) -> Result<()> {
+        handle_update_amm_peg(ctx)
+    }
+
+    pub fn update_perp_lp_range_status<'info>(
+        ctx: Context<'_, '_, '_, 'info, UpdatePerpLpRangeStatus<'info>>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_update_perp_lp_range_status(ctx, market_index)
+    }
+
     pub fn update_spot_market_cumulative_interest(
         ctx: Context<UpdateSpotMarketCumulativeInterest>,
     ) -> Result<()> {
         handle_update_spot_market_cumulative_interest(ctx)
     }
 
+    pub fn update_lst_oracle(ctx: Context<UpdateLstOracle>) -> Result<()> {
+        handle_update_lst_oracle(ctx)
+    }
+
     pub fn update_amms(ctx: Context<UpdateAMM>, market_indexes: [u16; 5]) -> Result<()> {
         handle_update_amms(ctx, market_indexes)
     }
@@ -409,6 +871,30 @@ pub mod drift {
         handle_cancel_request_remove_insurance_fund_stake(ctx, market_index)
     }
 
+    pub fn update_insurance_fund_stake_lockup_tier(
+        ctx: Context<RequestRemoveInsuranceFundStake>,
+        market_index: u16,
+        lockup_tier: InsuranceFundStakeLockupTier,
+    ) -> Result<()> {
+        handle_update_insurance_fund_stake_lockup_tier(ctx, market_index, lockup_tier)
+    }
+
+    pub fn wrap_insurance_fund_stake(
+        ctx: Context<WrapInsuranceFundStake>,
+        market_index: u16,
+        amount: u64,
+    ) -> Result<()> {
+        handle_wrap_insurance_fund_stake(ctx, market_index, amount)
+    }
+
+    pub fn unwrap_insurance_fund_stake(
+        ctx: Context<WrapInsuranceFundStake>,
+        market_index: u16,
+        token_amount: u64,
+    ) -> Result<()> {
+        handle_unwrap_insurance_fund_stake(ctx, market_index, token_amount)
+    }
+
     pub fn remove_insurance_fund_stake(
         ctx: Context<RemoveInsuranceFundStake>,
         market_index: u16,
@@ -416,6 +902,18 @@ pub mod drift {
         handle_remove_insurance_fund_stake(ctx, market_index)
     }
 
+    pub fn initialize_governance_stake(ctx: Context<InitializeGovernanceStake>) -> Result<()> {
+        handle_initialize_governance_stake(ctx)
+    }
+
+    pub fn add_governance_stake(ctx: Context<AddGovernanceStake>, amount: u64) -> Result<()> {
+        handle_add_governance_stake(ctx, amount)
+    }
+
+    pub fn remove_governance_stake(ctx: Context<RemoveGovernanceStake>, amount: u64) -> Result<()> {
+        handle_remove_governance_stake(ctx, amount)
+    }
+
     // Admin Instructions
 
     pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
@@ -468,6 +966,87 @@ pub mod drift {
         handle_update_serum_fulfillment_config_status(ctx, status)
     }
 
+    pub fn initialize_openbook_v2_fulfillment_config(
+        ctx: Context<InitializeOpenbookV2FulfillmentConfig>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_initialize_openbook_v2_fulfillment_config(ctx, market_index)
+    }
+
+    pub fn update_openbook_v2_fulfillment_config_status(
+        ctx: Context<UpdateOpenbookV2FulfillmentConfig>,
+        status: SpotFulfillmentConfigStatus,
+    ) -> Result<()> {
+        handle_update_openbook_v2_fulfillment_config_status(ctx, status)
+    }
+
+    pub fn initialize_phoenix_fulfillment_config(
+        ctx: Context<InitializePhoenixFulfillmentConfig>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_initialize_phoenix_fulfillment_config(ctx, market_index)
+    }
+
+    pub fn update_phoenix_fulfillment_config_status(
+        ctx: Context<UpdatePhoenixFulfillmentConfig>,
+        status: SpotFulfillmentConfigStatus,
+    ) -> Result<()> {
+        handle_update_phoenix_fulfillment_config_status(ctx, status)
+    }
+
+    pub fn initialize_prelaunch_oracle(
+        ctx: Context<InitializePrelaunchOracle>,
+        params: PrelaunchOracleParams,
+    ) -> Result<()> {
+        handle_initialize_prelaunch_oracle(ctx, params)
+    }
+
+    pub fn update_prelaunch_oracle_params(
+        ctx: Context<UpdatePrelaunchOracleParams>,
+        params: PrelaunchOracleParams,
+    ) -> Result<()> {
+        handle_update_prelaunch_oracle_params(ctx, params)
+    }
+
+    pub fn initialize_lst_oracle(
+        ctx: Context<InitializeLstOracle>,
+        sol_oracle_source: OracleSource,
+    ) -> Result<()> {
+        handle_initialize_lst_oracle(ctx, sol_oracle_source)
+    }
+
+    pub fn initialize_high_leverage_mode_config(
+        ctx: Context<InitializeHighLeverageModeConfig>,
+        market_index: u16,
+        max_users: u32,
+    ) -> Result<()> {
+        handle_initialize_high_leverage_mode_config(ctx, market_index, max_users)
+    }
+
+    pub fn update_high_leverage_mode_config(
+        ctx: Context<UpdateHighLeverageModeConfig>,
+        max_users: u32,
+    ) -> Result<()> {
+        handle_update_high_leverage_mode_config(ctx, max_users)
+    }
+
+    pub fn initialize_backstop_provider(
+        ctx: Context<InitializeBackstopProvider>,
+        committed_collateral: u64,
+        liquidation_fee_bonus: u32,
+    ) -> Result<()> {
+        handle_initialize_backstop_provider(ctx, committed_collateral, liquidation_fee_bonus)
+    }
+
+    pub fn update_backstop_provider(
+        ctx: Context<UpdateBackstopProvider>,
+        committed_collateral: u64,
+        liquidation_fee_bonus: u32,
+        approved: bool,
+    ) -> Result<()> {
+        handle_update_backstop_provider(ctx, committed_collateral, liquidation_fee_bonus, approved)
+    }
+
     pub fn update_serum_vault(ctx: Context<UpdateSerumVault>) -> Result<()> {
         handle_update_serum_vault(ctx)
     }
@@ -485,18 +1064,88 @@ pub mod drift {
         active_status: bool,
         name: [u8; 32],
     ) -> Result<()> {
-        handle_initialize_perp_market(
+        handle_initialize_perp_market(
+            ctx,
+            amm_base_asset_reserve,
+            amm_quote_asset_reserve,
+            amm_periodicity,
+            amm_peg_multiplier,
+            oracle_source,
+            margin_ratio_initial,
+            margin_ratio_maintenance,
+            liquidator_fee,
+            active_status,
+            name,
+        )
+    }
+
+    pub fn initialize_perp_market_listing_config(
+        ctx: Context<InitializePerpMarketListingConfig>,
+        bond_amount: u64,
+        veto_window: u32,
+    ) -> Result<()> {
+        handle_initialize_perp_market_listing_config(ctx, bond_amount, veto_window)
+    }
+
+    pub fn update_perp_market_listing_config(
+        ctx: Context<UpdatePerpMarketListingConfig>,
+        bond_amount: u64,
+        veto_window: u32,
+        enabled: bool,
+    ) -> Result<()> {
+        handle_update_perp_market_listing_config(ctx, bond_amount, veto_window, enabled)
+    }
+
+    pub fn propose_perp_market(
+        ctx: Context<ProposePerpMarket>,
+        amm_base_asset_reserve: u128,
+        oracle_source: OracleSource,
+        name: [u8; 32],
+    ) -> Result<()> {
+        handle_propose_perp_market(ctx, amm_base_asset_reserve, oracle_source, name)
+    }
+
+    pub fn activate_proposed_perp_market(
+        ctx: Context<ActivateProposedPerpMarket>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_activate_proposed_perp_market(ctx, market_index)
+    }
+
+    pub fn veto_proposed_perp_market(
+        ctx: Context<VetoProposedPerpMarket>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_veto_proposed_perp_market(ctx, market_index)
+    }
+
+    pub fn initialize_pnl_pool_rebalance_config(
+        ctx: Context<InitializePnlPoolRebalanceConfig>,
+        surplus_buffer: u64,
+        max_top_up: u64,
+        min_rebalance_interval: u32,
+    ) -> Result<()> {
+        handle_initialize_pnl_pool_rebalance_config(
             ctx,
-            amm_base_asset_reserve,
-            amm_quote_asset_reserve,
-            amm_periodicity,
-            amm_peg_multiplier,
-            oracle_source,
-            margin_ratio_initial,
-            margin_ratio_maintenance,
-            liquidator_fee,
-            active_status,
-            name,
+            surplus_buffer,
+            max_top_up,
+            min_rebalance_interval,
+        )
+    }
+
+    pub fn update_pnl_pool_rebalance_config(
+        ctx: Context<UpdatePnlPoolRebalanceConfig>,
+        surplus_buffer: u64,
+        max_top_up: u64,
+        min_rebalance_interval: u32,
+        enabled: bool,
+    ) -> Result<()> {
+        handle_update_pnl_pool_rebalance_config(
+            ctx,
+            surplus_buffer,
+            max_top_up,
+            min_rebalance_interval,
+            enabled,
         )
     }
 
@@ -516,12 +1165,37 @@ pub mod drift {
         handle_update_perp_market_expiry(ctx, expiry_ts)
     }
 
+    pub fn delist_perp_market(
+        ctx: Context<DelistPerpMarket>,
+        market_index: u16,
+        expiry_ts: i64,
+        settlement_twap_window: u32,
+    ) -> Result<()> {
+        handle_delist_perp_market(ctx, market_index, expiry_ts, settlement_twap_window)
+    }
+
+    pub fn initialize_dated_future(
+        ctx: Context<InitializeDatedFuture>,
+        market_index: u16,
+        expiry_ts: i64,
+        settlement_twap_window: u32,
+    ) -> Result<()> {
+        handle_initialize_dated_future(ctx, market_index, expiry_ts, settlement_twap_window)
+    }
+
     pub fn settle_expired_market_pools_to_revenue_pool(
         ctx: Context<SettleExpiredMarketPoolsToRevenuePool>,
     ) -> Result<()> {
         handle_settle_expired_market_pools_to_revenue_pool(ctx)
     }
 
+    pub fn resolve_prediction_market(
+        ctx: Context<ResolvePredictionMarket>,
+        outcome: bool,
+    ) -> Result<()> {
+        handle_resolve_prediction_market(ctx, outcome)
+    }
+
     pub fn deposit_into_perp_market_fee_pool(
         ctx: Context<DepositIntoMarketFeePool>,
         amount: u64,
@@ -546,13 +1220,70 @@ pub mod drift {
     }
 
     pub fn update_perp_market_margin_ratio(
-        ctx: Context<AdminUpdatePerpMarket>,
+        ctx: Context<RiskParamUpdatePerpMarket>,
         margin_ratio_initial: u32,
         margin_ratio_maintenance: u32,
     ) -> Result<()> {
         handle_update_perp_market_margin_ratio(ctx, margin_ratio_initial, margin_ratio_maintenance)
     }
 
+    pub fn update_perp_market_margin_tiers(
+        ctx: Context<AdminUpdatePerpMarket>,
+        margin_tiers: [MarginTier; 3],
+    ) -> Result<()> {
+        handle_update_perp_market_margin_tiers(ctx, margin_tiers)
+    }
+
+    pub fn update_perp_market_correlation_group(
+        ctx: Context<AdminUpdatePerpMarket>,
+        correlation_group: u8,
+        correlation_margin_credit: u16,
+    ) -> Result<()> {
+        handle_update_perp_market_correlation_group(
+            ctx,
+            correlation_group,
+            correlation_margin_credit,
+        )
+    }
+
+    pub fn update_perp_market_auto_deleverage_enabled(
+        ctx: Context<AdminUpdatePerpMarket>,
+        auto_deleverage_enabled: bool,
+    ) -> Result<()> {
+        handle_update_perp_market_auto_deleverage_enabled(ctx, auto_deleverage_enabled)
+    }
+
+    pub fn update_perp_market_insurance_fund_max_draw_pct(
+        ctx: Context<AdminUpdatePerpMarket>,
+        insurance_fund_max_draw_pct: u8,
+    ) -> Result<()> {
+        handle_update_perp_market_insurance_fund_max_draw_pct(ctx, insurance_fund_max_draw_pct)
+    }
+
+    pub fn update_perp_market_insurance_fund_lend(
+        ctx: Context<AdminUpdatePerpMarket>,
+        insurance_fund_lend_max_pct: u8,
+        insurance_fund_lend_rate: u32,
+    ) -> Result<()> {
+        handle_update_perp_market_insurance_fund_lend(
+            ctx,
+            insurance_fund_lend_max_pct,
+            insurance_fund_lend_rate,
+        )
+    }
+
+    pub fn update_perp_market_high_leverage_margin_ratio(
+        ctx: Context<AdminUpdatePerpMarket>,
+        high_leverage_margin_ratio_initial: u16,
+        high_leverage_margin_ratio_maintenance: u16,
+    ) -> Result<()> {
+        handle_update_perp_market_high_leverage_margin_ratio(
+            ctx,
+            high_leverage_margin_ratio_initial,
+            high_leverage_margin_ratio_maintenance,
+        )
+    }
+
     pub fn update_perp_market_max_imbalances(
         ctx: Context<AdminUpdatePerpMarket>,
         unrealized_max_imbalance: u64,
@@ -613,13 +1344,27 @@ pub mod drift {
         handle_update_spot_market_revenue_settle_period(ctx, revenue_settle_period)
     }
 
-    pub fn update_spot_market_status(
+    pub fn update_spot_market_revenue_settle_max_amount(
         ctx: Context<AdminUpdateSpotMarket>,
+        revenue_settle_max_amount: u64,
+    ) -> Result<()> {
+        handle_update_spot_market_revenue_settle_max_amount(ctx, revenue_settle_max_amount)
+    }
+
+    pub fn update_spot_market_status(
+        ctx: Context<PauserUpdateSpotMarket>,
         status: MarketStatus,
     ) -> Result<()> {
         handle_update_spot_market_status(ctx, status)
     }
 
+    pub fn update_spot_market_paused_operations(
+        ctx: Context<PauserUpdateSpotMarket>,
+        paused_operations: u8,
+    ) -> Result<()> {
+        handle_update_spot_market_paused_operations(ctx, paused_operations)
+    }
+
     pub fn update_spot_market_asset_tier(
         ctx: Context<AdminUpdateSpotMarket>,
         asset_tier: AssetTier,
@@ -628,7 +1373,7 @@ pub mod drift {
     }
 
     pub fn update_spot_market_margin_weights(
-        ctx: Context<AdminUpdateSpotMarket>,
+        ctx: Context<RiskParamUpdateSpotMarket>,
         initial_asset_weight: u32,
         maintenance_asset_weight: u32,
         initial_liability_weight: u32,
@@ -659,6 +1404,13 @@ pub mod drift {
         )
     }
 
+    pub fn update_spot_market_borrow_rate_kinks(
+        ctx: Context<AdminUpdateSpotMarket>,
+        borrow_rate_kinks: [BorrowRateKink; 2],
+    ) -> Result<()> {
+        handle_update_spot_market_borrow_rate_kinks(ctx, borrow_rate_kinks)
+    }
+
     pub fn update_spot_market_max_token_deposits(
         ctx: Context<AdminUpdateSpotMarket>,
         max_token_deposits: u64,
@@ -666,6 +1418,32 @@ pub mod drift {
         handle_update_spot_market_max_token_deposits(ctx, max_token_deposits)
     }
 
+    pub fn update_spot_market_max_token_borrows(
+        ctx: Context<AdminUpdateSpotMarket>,
+        max_token_borrows: u64,
+    ) -> Result<()> {
+        handle_update_spot_market_max_token_borrows(ctx, max_token_borrows)
+    }
+
+    pub fn update_spot_market_max_net_withdraw_bps(
+        ctx: Context<AdminUpdateSpotMarket>,
+        max_net_withdraw_bps: u16,
+    ) -> Result<()> {
+        handle_update_spot_market_max_net_withdraw_bps(ctx, max_net_withdraw_bps)
+    }
+
+    pub fn update_spot_market_flash_borrow_params(
+        ctx: Context<AdminUpdateSpotMarket>,
+        flash_borrows_enabled: bool,
+        flash_borrow_fee_bps: u16,
+    ) -> Result<()> {
+        handle_update_spot_market_flash_borrow_params(
+            ctx,
+            flash_borrows_enabled,
+            flash_borrow_fee_bps,
+        )
+    }
+
     pub fn update_spot_market_oracle(
         ctx: Context<AdminUpdateSpotMarketOracle>,
         oracle: Pubkey,
@@ -689,6 +1467,25 @@ pub mod drift {
         handle_update_spot_market_min_order_size(ctx, order_size)
     }
 
+    pub fn update_spot_market_max_collateral_concentration(
+        ctx: Context<AdminUpdateSpotMarket>,
+        max_collateral_concentration: u32,
+    ) -> Result<()> {
+        handle_update_spot_market_max_collateral_concentration(ctx, max_collateral_concentration)
+    }
+
+    pub fn update_spot_market_correlation_group(
+        ctx: Context<AdminUpdateSpotMarket>,
+        correlation_group: u8,
+        correlation_margin_credit: u16,
+    ) -> Result<()> {
+        handle_update_spot_market_correlation_group(
+            ctx,
+            correlation_group,
+            correlation_margin_credit,
+        )
+    }
+
     pub fn update_spot_market_orders_enabled(
         ctx: Context<AdminUpdateSpotMarket>,
         orders_enabled: bool,
@@ -704,12 +1501,19 @@ pub mod drift {
     }
 
     pub fn update_perp_market_status(
-        ctx: Context<AdminUpdatePerpMarket>,
+        ctx: Context<PauserUpdatePerpMarket>,
         status: MarketStatus,
     ) -> Result<()> {
         handle_update_perp_market_status(ctx, status)
     }
 
+    pub fn update_perp_market_paused_operations(
+        ctx: Context<PauserUpdatePerpMarket>,
+        paused_operations: u8,
+    ) -> Result<()> {
+        handle_update_perp_market_paused_operations(ctx, paused_operations)
+    }
+
     pub fn update_perp_market_contract_tier(
         ctx: Context<AdminUpdatePerpMarket>,
         contract_tier: ContractTier,
@@ -717,6 +1521,13 @@ pub mod drift {
         handle_update_perp_market_contract_tier(ctx, contract_tier)
     }
 
+    pub fn update_perp_market_contract_type(
+        ctx: Context<AdminUpdatePerpMarket>,
+        contract_type: ContractType,
+    ) -> Result<()> {
+        handle_update_perp_market_contract_type(ctx, contract_type)
+    }
+
     pub fn update_perp_market_imf_factor(
         ctx: Context<AdminUpdatePerpMarket>,
         imf_factor: u32,
@@ -751,6 +1562,27 @@ pub mod drift {
         handle_update_perp_market_curve_update_intensity(ctx, curve_update_intensity)
     }
 
+    pub fn update_perp_market_funding_period(
+        ctx: Context<AdminUpdatePerpMarket>,
+        funding_period: i64,
+    ) -> Result<()> {
+        handle_update_perp_market_funding_period(ctx, funding_period)
+    }
+
+    pub fn update_perp_market_funding_rate_caps(
+        ctx: Context<AdminUpdatePerpMarket>,
+        max_funding_rate_spread: u32,
+        escalated_max_funding_rate_spread: u32,
+        funding_divergence_escalation_periods: u8,
+    ) -> Result<()> {
+        handle_update_perp_market_funding_rate_caps(
+            ctx,
+            max_funding_rate_spread,
+            escalated_max_funding_rate_spread,
+            funding_divergence_escalation_periods,
+        )
+    }
+
     pub fn update_lp_cooldown_time(
         ctx: Context<AdminUpdateState>,
         lp_cooldown_time: u64,
@@ -786,6 +1618,73 @@ pub mod drift {
         handle_update_liquidation_duration(ctx, liquidation_duration)
     }
 
+    pub fn update_liquidation_fee_ramp_duration(
+        ctx: Context<AdminUpdateState>,
+        liquidation_fee_ramp_duration: u8,
+    ) -> Result<()> {
+        handle_update_liquidation_fee_ramp_duration(ctx, liquidation_fee_ramp_duration)
+    }
+
+    pub fn update_margin_warning_health(
+        ctx: Context<AdminUpdateState>,
+        margin_warning_health: u8,
+    ) -> Result<()> {
+        handle_update_margin_warning_health(ctx, margin_warning_health)
+    }
+
+    pub fn update_liquidation_stale_fee_escalation_duration(
+        ctx: Context<AdminUpdateState>,
+        liquidation_stale_fee_escalation_duration: u16,
+    ) -> Result<()> {
+        handle_update_liquidation_stale_fee_escalation_duration(
+            ctx,
+            liquidation_stale_fee_escalation_duration,
+        )
+    }
+
+    pub fn update_liquidation_target_margin_buffer_ratio(
+        ctx: Context<AdminUpdateState>,
+        liquidation_target_margin_buffer_ratio: u32,
+    ) -> Result<()> {
+        handle_update_liquidation_target_margin_buffer_ratio(
+            ctx,
+            liquidation_target_margin_buffer_ratio,
+        )
+    }
+
+    pub fn update_perp_market_oracle_circuit_breaker_band(
+        ctx: Context<AdminUpdatePerpMarket>,
+        oracle_circuit_breaker_band: u32,
+    ) -> Result<()> {
+        handle_update_perp_market_oracle_circuit_breaker_band(ctx, oracle_circuit_breaker_band)
+    }
+
+    pub fn update_perp_market_auction_extension(
+        ctx: Context<AdminUpdatePerpMarket>,
+        auction_extension_duration: u8,
+        auction_extension_price_buffer: u32,
+    ) -> Result<()> {
+        handle_update_perp_market_auction_extension(
+            ctx,
+            auction_extension_duration,
+            auction_extension_price_buffer,
+        )
+    }
+
+    pub fn update_perp_market_validity_guard_rails_override(
+        ctx: Context<AdminUpdatePerpMarket>,
+        validity_guard_rails_override: ValidityGuardRailsOverride,
+    ) -> Result<()> {
+        handle_update_perp_market_validity_guard_rails_override(ctx, validity_guard_rails_override)
+    }
+
+    pub fn update_spot_market_validity_guard_rails_override(
+        ctx: Context<AdminUpdateSpotMarket>,
+        validity_guard_rails_override: ValidityGuardRailsOverride,
+    ) -> Result<()> {
+        handle_update_spot_market_validity_guard_rails_override(ctx, validity_guard_rails_override)
+    }
+
     pub fn update_oracle_guard_rails(
         ctx: Context<AdminUpdateState>,
         oracle_guard_rails: OracleGuardRails,
@@ -808,6 +1707,18 @@ pub mod drift {
         handle_update_perp_market_oracle(ctx, oracle, oracle_source)
     }
 
+    pub fn update_perp_market_additional_oracles(
+        ctx: Context<AdminUpdatePerpMarket>,
+        additional_oracles: [Pubkey; 2],
+        additional_oracle_sources: [OracleSource; 2],
+    ) -> Result<()> {
+        handle_update_perp_market_additional_oracles(
+            ctx,
+            additional_oracles,
+            additional_oracle_sources,
+        )
+    }
+
     pub fn update_perp_market_base_spread(
         ctx: Context<AdminUpdatePerpMarket>,
         base_spread: u32,
@@ -829,6 +1740,33 @@ pub mod drift {
         handle_update_perp_market_max_spread(ctx, max_spread)
     }
 
+    pub fn update_perp_market_dynamic_spread_params(
+        ctx: Context<AdminUpdatePerpMarket>,
+        inventory_skew_spread: u16,
+        volatility_spread_scale: u16,
+    ) -> Result<()> {
+        handle_update_perp_market_dynamic_spread_params(
+            ctx,
+            inventory_skew_spread,
+            volatility_spread_scale,
+        )
+    }
+
+    pub fn update_perp_market_max_reference_price_offset(
+        ctx: Context<AdminUpdatePerpMarket>,
+        max_reference_price_offset: u16,
+    ) -> Result<()> {
+        handle_update_perp_market_max_reference_price_offset(ctx, max_reference_price_offset)
+    }
+
+    pub fn update_perp_market_lp_lockup(
+        ctx: Context<AdminUpdatePerpMarket>,
+        lp_min_duration: u32,
+        lp_early_removal_penalty: u16,
+    ) -> Result<()> {
+        handle_update_perp_market_lp_lockup(ctx, lp_min_duration, lp_early_removal_penalty)
+    }
+
     pub fn update_perp_market_step_size_and_tick_size(
         ctx: Context<AdminUpdatePerpMarket>,
         step_size: u64,
@@ -837,6 +1775,21 @@ pub mod drift {
         handle_update_perp_market_step_size_and_tick_size(ctx, step_size, tick_size)
     }
 
+    pub fn update_perp_market_tick_size_bps(
+        ctx: Context<AdminUpdatePerpMarket>,
+        order_tick_size_bps: u16,
+    ) -> Result<()> {
+        handle_update_perp_market_tick_size_bps(ctx, order_tick_size_bps)
+    }
+
+    pub fn resize_perp_market(ctx: Context<ResizePerpMarket>) -> Result<()> {
+        handle_resize_perp_market(ctx)
+    }
+
+    pub fn resize_spot_market(ctx: Context<ResizeSpotMarket>) -> Result<()> {
+        handle_resize_spot_market(ctx)
+    }
+
     pub fn update_perp_market_name(
         ctx: Context<AdminUpdatePerpMarket>,
         name: [u8; 32],
@@ -891,7 +1844,7 @@ pub mod drift {
     }
 
     pub fn update_exchange_status(
-        ctx: Context<AdminUpdateState>,
+        ctx: Context<PauserUpdateState>,
         exchange_status: u8,
     ) -> Result<()> {
         handle_update_exchange_status(ctx, exchange_status)
@@ -911,6 +1864,13 @@ pub mod drift {
         handle_update_spot_auction_duration(ctx, default_spot_auction_duration)
     }
 
+    pub fn initialize_insurance_fund_share_mint(
+        ctx: Context<InitializeInsuranceFundShareMint>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_initialize_insurance_fund_share_mint(ctx, market_index)
+    }
+
     pub fn admin_remove_insurance_fund_stake(
         ctx: Context<AdminRemoveInsuranceFundStake>,
         market_index: u16,
@@ -918,4 +1878,159 @@ pub mod drift {
     ) -> Result<()> {
         handle_admin_remove_insurance_fund_stake(ctx, market_index, amount)
     }
+
+    pub fn initialize_fuel_boost(
+        ctx: Context<InitializeFuelBoost>,
+        market_type: MarketType,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_initialize_fuel_boost(ctx, market_type, market_index)
+    }
+
+    pub fn update_fuel_boost(
+        ctx: Context<UpdateFuelBoost>,
+        taker: u8,
+        maker: u8,
+        borrows: u8,
+        insurance: u8,
+        start_ts: i64,
+        end_ts: i64,
+    ) -> Result<()> {
+        handle_update_fuel_boost(ctx, taker, maker, borrows, insurance, start_ts, end_ts)
+    }
+
+    pub fn initialize_perp_market_fee_override(
+        ctx: Context<InitializePerpMarketFeeOverride>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_initialize_perp_market_fee_override(ctx, market_index)
+    }
+
+    pub fn initialize_spot_market_fee_override(
+        ctx: Context<InitializeSpotMarketFeeOverride>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_initialize_spot_market_fee_override(ctx, market_index)
+    }
+
+    pub fn update_market_fee_override(
+        ctx: Context<UpdateMarketFeeOverride>,
+        taker_fee_adjustment: i16,
+        maker_rebate_adjustment: i16,
+        promo_taker_fee_adjustment: i16,
+        promo_maker_rebate_adjustment: i16,
+        promo_start_ts: i64,
+        promo_end_ts: i64,
+    ) -> Result<()> {
+        handle_update_market_fee_override(
+            ctx,
+            taker_fee_adjustment,
+            maker_rebate_adjustment,
+            promo_taker_fee_adjustment,
+            promo_maker_rebate_adjustment,
+            promo_start_ts,
+            promo_end_ts,
+        )
+    }
+
+    pub fn initialize_market_lookup_table(
+        ctx: Context<InitializeMarketLookupTable>,
+        table_index: u16,
+        recent_slot: u64,
+    ) -> Result<()> {
+        handle_initialize_market_lookup_table(ctx, table_index, recent_slot)
+    }
+
+    pub fn update_perp_market_whitelist_enabled(
+        ctx: Context<AdminUpdatePerpMarket>,
+        whitelist_enabled: bool,
+    ) -> Result<()> {
+        handle_update_perp_market_whitelist_enabled(ctx, whitelist_enabled)
+    }
+
+    pub fn initialize_market_whitelist_entry(
+        ctx: Context<InitializeMarketWhitelistEntry>,
+        market_index: u16,
+        authority: Pubkey,
+    ) -> Result<()> {
+        handle_initialize_market_whitelist_entry(ctx, market_index, authority)
+    }
+
+    pub fn delete_market_whitelist_entry(ctx: Context<DeleteMarketWhitelistEntry>) -> Result<()> {
+        handle_delete_market_whitelist_entry(ctx)
+    }
+
+    pub fn initialize_fill_callback_program(
+        ctx: Context<InitializeFillCallbackProgram>,
+        program_id: Pubkey,
+    ) -> Result<()> {
+        handle_initialize_fill_callback_program(ctx, program_id)
+    }
+
+    pub fn delete_fill_callback_program(ctx: Context<DeleteFillCallbackProgram>) -> Result<()> {
+        handle_delete_fill_callback_program(ctx)
+    }
+
+    pub fn update_referrer_reward_config(
+        ctx: Context<UpdateReferrerRewardConfig>,
+        referrer_reward_share: u8,
+        second_level_referrer_reward_share: u8,
+        referrer_reward_decay_months: u8,
+    ) -> Result<()> {
+        handle_update_referrer_reward_config(
+            ctx,
+            referrer_reward_share,
+            second_level_referrer_reward_share,
+            referrer_reward_decay_months,
+        )
+    }
+
+    pub fn update_admin_timelock_duration(
+        ctx: Context<AdminUpdateState>,
+        admin_timelock_duration: u32,
+    ) -> Result<()> {
+        handle_update_admin_timelock_duration(ctx, admin_timelock_duration)
+    }
+
+    pub fn queue_admin_change(ctx: Context<QueueAdminChange>, change: AdminChange) -> Result<()> {
+        handle_queue_admin_change(ctx, change)
+    }
+
+    pub fn execute_perp_market_admin_change(
+        ctx: Context<ExecutePerpMarketAdminChange>,
+    ) -> Result<()> {
+        handle_execute_perp_market_admin_change(ctx)
+    }
+
+    pub fn execute_spot_market_admin_change(
+        ctx: Context<ExecuteSpotMarketAdminChange>,
+    ) -> Result<()> {
+        handle_execute_spot_market_admin_change(ctx)
+    }
+
+    pub fn execute_state_admin_change(ctx: Context<ExecuteStateAdminChange>) -> Result<()> {
+        handle_execute_state_admin_change(ctx)
+    }
+
+    pub fn cancel_admin_change(ctx: Context<CancelAdminChange>) -> Result<()> {
+        handle_cancel_admin_change(ctx)
+    }
+
+    pub fn initialize_admin_roles(ctx: Context<InitializeAdminRoles>) -> Result<()> {
+        handle_initialize_admin_roles(ctx)
+    }
+
+    pub fn update_admin_role(
+        ctx: Context<UpdateAdminRole>,
+        role: AdminRole,
+        key: Pubkey,
+    ) -> Result<()> {
+        handle_update_admin_role(ctx, role, key)
+    }
+
+    pub fn initialize_governance_stake_vault(
+        ctx: Context<InitializeGovernanceStakeVault>,
+    ) -> Result<()> {
+        handle_initialize_governance_stake_vault(ctx)
+    }
 }
@@ -5,14 +5,15 @@ use solana_program::msg;
 use crate::controller::amm::SwapDirection;
 use crate::controller::position::PositionDirection;
 use crate::error::{DriftResult, ErrorCode};
+use crate::math::amm_spread::calculate_inventory_liquidity_ratio;
 use crate::math::bn::U192;
 use crate::math::casting::Cast;
 use crate::math::constants::{
     BID_ASK_SPREAD_PRECISION, BID_ASK_SPREAD_PRECISION_I128, BID_ASK_SPREAD_PRECISION_U128,
-    CONCENTRATION_PRECISION, DEFAULT_MAX_TWAP_UPDATE_PRICE_BAND_DENOMINATOR, FIVE_MINUTE, ONE_HOUR,
-    ONE_MINUTE, PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO,
-    PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO_I128, PRICE_TO_PEG_PRECISION_RATIO,
-    QUOTE_PRECISION_I64,
+    CONCENTRATION_PRECISION, DEFAULT_MAX_TWAP_UPDATE_PRICE_BAND_DENOMINATOR, FIVE_MINUTE,
+    ONE_BPS_DENOMINATOR, ONE_HOUR, ONE_MINUTE, PERCENTAGE_PRECISION_I128,
+    PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO, PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO_I128,
+    PRICE_TO_PEG_PRECISION_RATIO, QUOTE_PRECISION_I64,
 };
 use crate::math::orders::standardize_base_asset_amount;
 use crate::math::quote_asset::reserve_to_asset_amount;
@@ -123,6 +124,79 @@ pub fn _calculate_market_open_bids_asks(
     Ok((max_bids, max_asks))
 }
 
+pub fn calculate_reference_price_offset(
+    base_asset_amount_with_amm: i128,
+    base_asset_amount_long: i128,
+    base_asset_amount_short: i128,
+    base_asset_reserve: u128,
+    min_base_asset_reserve: u128,
+    max_base_asset_reserve: u128,
+    max_reference_price_offset: u16,
+) -> DriftResult<i32> {
+    if max_reference_price_offset == 0 {
+        return Ok(0);
+    }
+
+    // amm inventory relative to the liquidity on its thinner side
+    let inventory_pct = calculate_inventory_liquidity_ratio(
+        base_asset_amount_with_amm,
+        base_asset_reserve,
+        min_base_asset_reserve,
+        max_base_asset_reserve,
+    )?
+    .safe_mul(base_asset_amount_with_amm.signum())?;
+
+    // long/short open interest imbalance
+    let open_interest = base_asset_amount_long.safe_add(base_asset_amount_short.abs())?;
+    let imbalance_pct = if open_interest > 0 {
+        base_asset_amount_long
+            .safe_sub(base_asset_amount_short.abs())?
+            .safe_mul(PERCENTAGE_PRECISION_I128)?
+            .safe_div(open_interest)?
+    } else {
+        0
+    };
+
+    let offset_pct = inventory_pct
+        .safe_add(imbalance_pct)?
+        .safe_div(2)?
+        .clamp(-PERCENTAGE_PRECISION_I128, PERCENTAGE_PRECISION_I128);
+
+    // users net long => amm is short, raise the reference price so it buys back more easily
+    max_reference_price_offset
+        .cast::<i128>()?
+        .safe_mul(BID_ASK_SPREAD_PRECISION_I128 / ONE_BPS_DENOMINATOR as i128)?
+        .safe_mul(offset_pct)?
+        .safe_div(PERCENTAGE_PRECISION_I128)?
+        .cast::<i32>()
+}
+
+/// shifts both spreads by the reference price offset while keeping each side at least half the
+/// base spread, so the total spread is unchanged
+pub fn apply_reference_price_offset(
+    long_spread: u32,
+    short_spread: u32,
+    base_spread: u32,
+    reference_price_offset: i32,
+) -> DriftResult<(u32, u32, i32)> {
+    let half_base_spread = base_spread.safe_div(2)?.cast::<i64>()?;
+    let min_offset = half_base_spread.safe_sub(long_spread.cast()?)?.min(0);
+    let max_offset = short_spread
+        .cast::<i64>()?
+        .safe_sub(half_base_spread)?
+        .max(0);
+
+    let offset = reference_price_offset
+        .cast::<i64>()?
+        .clamp(min_offset, max_offset);
+
+    Ok((
+        long_spread.cast::<i64>()?.safe_add(offset)?.cast()?,
+        short_spread.cast::<i64>()?.safe_sub(offset)?.cast()?,
+        offset.cast()?,
+    ))
+}
+
 pub fn update_mark_twap(
     amm: &mut AMM,
     now: i64,
@@ -793,6 +867,20 @@ pub fn calculate_net_user_pnl(amm: &AMM, oracle_price: i64) -> DriftResult<i128>
     net_user_base_asset_value.safe_add(calculate_net_user_cost_basis(amm)?)
 }
 
+/// pnl pool tokens above what the market's users are owed in aggregate, negative if the pool
+/// cannot cover them
+pub fn calculate_pnl_pool_surplus(
+    amm: &AMM,
+    oracle_price: i64,
+    pnl_pool_amount: u128,
+) -> DriftResult<i128> {
+    let net_user_pnl = calculate_net_user_pnl(amm, oracle_price)?;
+
+    pnl_pool_amount
+        .cast::<i128>()?
+        .safe_sub(net_user_pnl.max(0))
+}
+
 pub fn calculate_expiry_price(
     amm: &AMM,
     target_price: i64,
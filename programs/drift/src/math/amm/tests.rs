@@ -801,3 +801,99 @@ fn calc_oracle_twap_clamp_update_tests() {
     );
     assert_eq!(amm.last_oracle_normalised_price, 129_900_873);
 }
+
+#[test]
+fn calculate_reference_price_offset_test() {
+    let base_asset_reserve = 100 * AMM_RESERVE_PRECISION;
+    let min_base_asset_reserve = 50 * AMM_RESERVE_PRECISION;
+    let max_base_asset_reserve = 200 * AMM_RESERVE_PRECISION;
+    let amount = AMM_RESERVE_PRECISION as i128;
+
+    // disabled
+    let offset = calculate_reference_price_offset(
+        10 * amount,
+        30 * amount,
+        -20 * amount,
+        base_asset_reserve,
+        min_base_asset_reserve,
+        max_base_asset_reserve,
+        0,
+    )
+    .unwrap();
+    assert_eq!(offset, 0);
+
+    // users net long, 20% inventory and 20% imbalance => 20% of the 1% max offset
+    let offset = calculate_reference_price_offset(
+        10 * amount,
+        30 * amount,
+        -20 * amount,
+        base_asset_reserve,
+        min_base_asset_reserve,
+        max_base_asset_reserve,
+        100,
+    )
+    .unwrap();
+    assert_eq!(offset, 2000);
+
+    // users net short
+    let offset = calculate_reference_price_offset(
+        -10 * amount,
+        20 * amount,
+        -30 * amount,
+        base_asset_reserve,
+        min_base_asset_reserve,
+        max_base_asset_reserve,
+        100,
+    )
+    .unwrap();
+    assert_eq!(offset, -2000);
+
+    // inventory and imbalance cancel out
+    let offset = calculate_reference_price_offset(
+        10 * amount,
+        20 * amount,
+        -30 * amount,
+        base_asset_reserve,
+        min_base_asset_reserve,
+        max_base_asset_reserve,
+        100,
+    )
+    .unwrap();
+    assert_eq!(offset, 0);
+
+    // bounded by the max offset
+    let offset = calculate_reference_price_offset(
+        100 * amount,
+        100 * amount,
+        0,
+        base_asset_reserve,
+        min_base_asset_reserve,
+        max_base_asset_reserve,
+        100,
+    )
+    .unwrap();
+    assert_eq!(offset, 10000);
+}
+
+#[test]
+fn apply_reference_price_offset_test() {
+    assert_eq!(
+        apply_reference_price_offset(500, 500, 400, -100).unwrap(),
+        (400, 600, -100)
+    );
+
+    // each side keeps half the base spread
+    assert_eq!(
+        apply_reference_price_offset(500, 500, 400, 2000).unwrap(),
+        (800, 200, 300)
+    );
+    assert_eq!(
+        apply_reference_price_offset(500, 500, 400, -1000).unwrap(),
+        (200, 800, -300)
+    );
+
+    assert_eq!(
+        apply_reference_price_offset(500, 500, 400, 0).unwrap(),
+        (500, 500, 0)
+    );
+}
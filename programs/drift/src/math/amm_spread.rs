@@ -11,8 +11,8 @@ use crate::math::constants::{
     AMM_TIMES_PEG_TO_QUOTE_PRECISION_RATIO_I128, AMM_TO_QUOTE_PRECISION_RATIO_I128,
     BID_ASK_SPREAD_PRECISION, BID_ASK_SPREAD_PRECISION_I128, BID_ASK_SPREAD_PRECISION_U128,
     DEFAULT_LARGE_BID_ASK_FACTOR, DEFAULT_REVENUE_SINCE_LAST_FUNDING_SPREAD_RETREAT,
-    MAX_BID_ASK_INVENTORY_SKEW_FACTOR, PEG_PRECISION, PERCENTAGE_PRECISION, PRICE_PRECISION,
-    PRICE_PRECISION_I128,
+    MAX_BID_ASK_INVENTORY_SKEW_FACTOR, ONE_BPS_DENOMINATOR, PEG_PRECISION, PERCENTAGE_PRECISION,
+    PRICE_PRECISION, PRICE_PRECISION_I128,
 };
 use crate::math::safe_math::SafeMath;
 
@@ -289,6 +289,63 @@ pub fn calculate_spread_revenue_retreat_amount(
     Ok(revenue_retreat_amount)
 }
 
+pub fn calculate_dynamic_spread(
+    base_asset_amount_with_amm: i128,
+    base_asset_reserve: u128,
+    min_base_asset_reserve: u128,
+    max_base_asset_reserve: u128,
+    reserve_price: u64,
+    mark_std: u64,
+    oracle_std: u64,
+    inventory_skew_spread: u16,
+    volatility_spread_scale: u16,
+) -> DriftResult<(u64, u64)> {
+    // realized volatility widens both sides
+    let volatility_spread = if volatility_spread_scale > 0 {
+        mark_std
+            .max(oracle_std)
+            .cast::<u128>()?
+            .safe_mul(PERCENTAGE_PRECISION)?
+            .safe_div(reserve_price.max(1).cast::<u128>()?)?
+            .safe_mul(volatility_spread_scale.cast()?)?
+            .safe_div(100)?
+            .cast::<u64>()?
+    } else {
+        0
+    };
+
+    // inventory skew only widens the side that adds to the amm's inventory
+    let inventory_spread = if inventory_skew_spread > 0 && base_asset_amount_with_amm != 0 {
+        let amm_inventory_pct = calculate_inventory_liquidity_ratio(
+            base_asset_amount_with_amm,
+            base_asset_reserve,
+            min_base_asset_reserve,
+            max_base_asset_reserve,
+        )?;
+
+        inventory_skew_spread
+            .cast::<u128>()?
+            .safe_mul(BID_ASK_SPREAD_PRECISION_U128 / ONE_BPS_DENOMINATOR as u128)?
+            .safe_mul(amm_inventory_pct.unsigned_abs())?
+            .safe_div(PERCENTAGE_PRECISION)?
+            .cast::<u64>()?
+    } else {
+        0
+    };
+
+    if base_asset_amount_with_amm > 0 {
+        Ok((
+            volatility_spread.safe_add(inventory_spread)?,
+            volatility_spread,
+        ))
+    } else {
+        Ok((
+            volatility_spread,
+            volatility_spread.safe_add(inventory_spread)?,
+        ))
+    }
+}
+
 #[allow(clippy::comparison_chain)]
 pub fn calculate_spread(
     base_spread: u32,
@@ -310,6 +367,8 @@ pub fn calculate_spread(
     long_intensity_volume: u64,
     short_intensity_volume: u64,
     volume_24h: u64,
+    inventory_skew_spread: u16,
+    volatility_spread_scale: u16,
 ) -> DriftResult<(u32, u32)> {
     let (long_vol_spread, short_vol_spread) = calculate_long_short_vol_spread(
         last_oracle_conf_pct,
@@ -417,6 +476,20 @@ pub fn calculate_spread(
         }
     }
 
+    let (long_dynamic_spread, short_dynamic_spread) = calculate_dynamic_spread(
+        base_asset_amount_with_amm,
+        base_asset_reserve,
+        min_base_asset_reserve,
+        max_base_asset_reserve,
+        reserve_price,
+        mark_std,
+        oracle_std,
+        inventory_skew_spread,
+        volatility_spread_scale,
+    )?;
+    long_spread = long_spread.safe_add(long_dynamic_spread)?;
+    short_spread = short_spread.safe_add(short_dynamic_spread)?;
+
     let (long_spread, short_spread) =
         cap_to_max_spread(long_spread, short_spread, max_target_spread)?;
 
@@ -2,8 +2,9 @@
 mod test {
     use crate::math::amm_spread::*;
     use crate::math::constants::{
-        AMM_RESERVE_PRECISION, BASE_PRECISION_I128, BID_ASK_SPREAD_PRECISION,
-        BID_ASK_SPREAD_PRECISION_I64, QUOTE_PRECISION, QUOTE_PRECISION_I128,
+        AMM_RESERVE_PRECISION, AMM_RESERVE_PRECISION_I128, BASE_PRECISION_I128,
+        BID_ASK_SPREAD_PRECISION, BID_ASK_SPREAD_PRECISION_I64, PRICE_PRECISION_U64,
+        QUOTE_PRECISION, QUOTE_PRECISION_I128,
     };
 
     #[test]
@@ -84,6 +85,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread1, (base_spread * 10 / 2));
@@ -113,6 +116,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread2, 16667);
@@ -142,6 +147,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
 
@@ -177,6 +184,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert!(short_spread4 < long_spread4);
@@ -206,6 +215,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
 
@@ -254,6 +265,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
 
@@ -280,6 +293,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
 
@@ -333,6 +348,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
 
@@ -381,6 +398,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread1, 500);
@@ -408,6 +427,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread1, 345);
@@ -434,6 +455,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread1, 110);
@@ -483,6 +506,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread1, 199926);
@@ -508,6 +533,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread1, 199951);
@@ -533,6 +560,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread1, 199815);
@@ -889,6 +918,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
 
@@ -929,6 +960,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
 
@@ -955,6 +988,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread, 195556);
@@ -980,6 +1015,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread, 1639);
@@ -1079,6 +1116,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
 
@@ -1119,6 +1158,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
 
@@ -1145,6 +1186,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread, 197138); // big cause of oracel pct
@@ -1170,6 +1213,8 @@ mod test {
             long_intensity_volume,
             short_intensity_volume,
             volume_24h,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread, 1639);
@@ -1198,6 +1243,8 @@ mod test {
             12358265776,
             72230366233,
             432067603632,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread, 4262);
@@ -1223,9 +1270,85 @@ mod test {
             9520659647,
             53979922148,
             427588331503,
+            0,
+            0,
         )
         .unwrap();
         assert_eq!(long_spread, 4390);
         assert_eq!(short_spread, 43110);
     }
+
+    #[test]
+    fn calculate_dynamic_spread_tests() {
+        let base_asset_reserve = 100 * AMM_RESERVE_PRECISION;
+        let min_base_asset_reserve = 50 * AMM_RESERVE_PRECISION;
+        let max_base_asset_reserve = 200 * AMM_RESERVE_PRECISION;
+        let reserve_price = 100 * PRICE_PRECISION_U64;
+        let mark_std = PRICE_PRECISION_U64;
+        let oracle_std = 2 * PRICE_PRECISION_U64;
+
+        // disabled
+        let (long_spread, short_spread) = calculate_dynamic_spread(
+            10 * AMM_RESERVE_PRECISION_I128,
+            base_asset_reserve,
+            min_base_asset_reserve,
+            max_base_asset_reserve,
+            reserve_price,
+            mark_std,
+            oracle_std,
+            0,
+            0,
+        )
+        .unwrap();
+        assert_eq!(long_spread, 0);
+        assert_eq!(short_spread, 0);
+
+        // users net long, 20% of the min side liquidity
+        let (long_spread, short_spread) = calculate_dynamic_spread(
+            10 * AMM_RESERVE_PRECISION_I128,
+            base_asset_reserve,
+            min_base_asset_reserve,
+            max_base_asset_reserve,
+            reserve_price,
+            mark_std,
+            oracle_std,
+            50,
+            0,
+        )
+        .unwrap();
+        assert_eq!(long_spread, 1000);
+        assert_eq!(short_spread, 0);
+
+        // half of the 2% realized vol on both sides, inventory only on the short side
+        let (long_spread, short_spread) = calculate_dynamic_spread(
+            -10 * AMM_RESERVE_PRECISION_I128,
+            base_asset_reserve,
+            min_base_asset_reserve,
+            max_base_asset_reserve,
+            reserve_price,
+            mark_std,
+            oracle_std,
+            50,
+            50,
+        )
+        .unwrap();
+        assert_eq!(long_spread, 10000);
+        assert_eq!(short_spread, 11000);
+
+        // no inventory
+        let (long_spread, short_spread) = calculate_dynamic_spread(
+            0,
+            base_asset_reserve,
+            min_base_asset_reserve,
+            max_base_asset_reserve,
+            reserve_price,
+            mark_std,
+            oracle_std,
+            50,
+            50,
+        )
+        .unwrap();
+        assert_eq!(long_spread, 10000);
+        assert_eq!(short_spread, 10000);
+    }
 }
@@ -1,11 +1,14 @@
 use crate::controller::position::PositionDirection;
 use crate::error::{DriftResult, ErrorCode};
 use crate::math::casting::Cast;
-use crate::math::constants::AUCTION_DERIVE_PRICE_FRACTION;
+use crate::math::constants::{
+    AUCTION_DERIVE_PRICE_FRACTION, EXPONENTIAL_AUCTION_CURVE_EXPONENT, MAX_PREDICTION_MARKET_PRICE,
+    PERCENTAGE_PRECISION, PERCENTAGE_PRECISION_I128,
+};
 use crate::math::orders::standardize_price;
 use crate::math::safe_math::SafeMath;
 use crate::state::oracle::OraclePriceData;
-use crate::state::user::{Order, OrderType};
+use crate::state::user::{AuctionCurve, Order, OrderType};
 use solana_program::msg;
 
 use std::cmp::min;
@@ -83,19 +86,26 @@ pub fn calculate_auction_price(
     slot: u64,
     tick_size: u64,
     valid_oracle_price: Option<i64>,
+    is_prediction_market: bool,
 ) -> DriftResult<u64> {
-    match order.order_type {
+    let price = match order.order_type {
         OrderType::Market | OrderType::TriggerMarket | OrderType::Limit => {
-            calculate_auction_price_for_fixed_auction(order, slot, tick_size)
+            calculate_auction_price_for_fixed_auction(order, slot, tick_size)?
         }
         OrderType::Oracle => calculate_auction_price_for_oracle_offset_auction(
             order,
             slot,
             tick_size,
             valid_oracle_price,
-        ),
+        )?,
         _ => unreachable!(),
+    };
+
+    if is_prediction_market {
+        return Ok(price.min(MAX_PREDICTION_MARKET_PRICE));
     }
+
+    Ok(price)
 }
 
 fn calculate_auction_price_for_fixed_auction(
@@ -103,29 +113,21 @@ fn calculate_auction_price_for_fixed_auction(
     slot: u64,
     tick_size: u64,
 ) -> DriftResult<u64> {
-    let slots_elapsed = slot.safe_sub(order.slot)?;
-
-    let delta_numerator = min(slots_elapsed, order.auction_duration.cast()?);
-    let delta_denominator = order.auction_duration;
-
     let auction_start_price = order.auction_start_price.cast::<u64>()?;
     let auction_end_price = order.auction_end_price.cast::<u64>()?;
 
-    if delta_denominator == 0 {
+    if order.auction_duration == 0 {
         return standardize_price(auction_end_price, tick_size, order.direction);
     }
 
-    let price_delta = match order.direction {
-        PositionDirection::Long => auction_end_price
-            .safe_sub(auction_start_price)?
-            .safe_mul(delta_numerator.cast()?)?
-            .safe_div(delta_denominator.cast()?)?,
-        PositionDirection::Short => auction_start_price
-            .safe_sub(auction_end_price)?
-            .safe_mul(delta_numerator.cast()?)?
-            .safe_div(delta_denominator.cast()?)?,
+    let price_range = match order.direction {
+        PositionDirection::Long => auction_end_price.safe_sub(auction_start_price)?,
+        PositionDirection::Short => auction_start_price.safe_sub(auction_end_price)?,
     };
 
+    let price_delta =
+        calculate_auction_price_delta(order, slot, price_range.cast()?)?.cast::<u64>()?;
+
     let price = match order.direction {
         PositionDirection::Long => auction_start_price.safe_add(price_delta)?,
         PositionDirection::Short => auction_start_price.safe_sub(price_delta)?,
@@ -145,15 +147,10 @@ fn calculate_auction_price_for_oracle_offset_auction(
         ErrorCode::OracleNotFound
     })?;
 
-    let slots_elapsed = slot.safe_sub(order.slot)?;
-
-    let delta_numerator = min(slots_elapsed, order.auction_duration.cast()?);
-    let delta_denominator = order.auction_duration;
-
     let auction_start_price_offset = order.auction_start_price;
     let auction_end_price_offset = order.auction_end_price;
 
-    if delta_denominator == 0 {
+    if order.auction_duration == 0 {
         let price = oracle_price.safe_add(auction_end_price_offset)?;
 
         if price <= 0 {
@@ -164,17 +161,15 @@ fn calculate_auction_price_for_oracle_offset_auction(
         return standardize_price(price.cast()?, tick_size, order.direction);
     }
 
-    let price_offset_delta = match order.direction {
-        PositionDirection::Long => auction_end_price_offset
-            .safe_sub(auction_start_price_offset)?
-            .safe_mul(delta_numerator.cast()?)?
-            .safe_div(delta_denominator.cast()?)?,
-        PositionDirection::Short => auction_start_price_offset
-            .safe_sub(auction_end_price_offset)?
-            .safe_mul(delta_numerator.cast()?)?
-            .safe_div(delta_denominator.cast()?)?,
+    let price_offset_range = match order.direction {
+        PositionDirection::Long => auction_end_price_offset.safe_sub(auction_start_price_offset)?,
+        PositionDirection::Short => {
+            auction_start_price_offset.safe_sub(auction_end_price_offset)?
+        }
     };
 
+    let price_offset_delta = calculate_auction_price_delta(order, slot, price_offset_range)?;
+
     let price_offset = match order.direction {
         PositionDirection::Long => auction_start_price_offset.safe_add(price_offset_delta)?,
         PositionDirection::Short => auction_start_price_offset.safe_sub(price_offset_delta)?,
@@ -194,6 +189,87 @@ fn calculate_auction_price_for_oracle_offset_auction(
     Ok(price)
 }
 
+/// How far through price_range the auction has moved, following the order's auction curve
+fn calculate_auction_price_delta(order: &Order, slot: u64, price_range: i64) -> DriftResult<i64> {
+    let auction_duration = order.auction_duration.cast::<u128>()?;
+    let slots_elapsed =
+        min(slot.safe_sub(order.slot)?, order.auction_duration.cast()?).cast::<u128>()?;
+
+    let (delta_numerator, delta_denominator) = match order.auction_curve {
+        AuctionCurve::Linear => (slots_elapsed, auction_duration),
+        AuctionCurve::Exponential => {
+            // (2^(k * t) - 1) / (2^k - 1) for t the fraction of the auction elapsed,
+            // interpolated linearly between whole powers of two
+            let exponent = slots_elapsed
+                .safe_mul(EXPONENTIAL_AUCTION_CURVE_EXPONENT)?
+                .safe_mul(PERCENTAGE_PRECISION)?
+                .safe_div(auction_duration)?;
+            let whole_exponent = exponent.safe_div(PERCENTAGE_PRECISION)?;
+            let exponent_remainder =
+                exponent.safe_sub(whole_exponent.safe_mul(PERCENTAGE_PRECISION)?)?;
+            let power_of_two = 2_u128.pow(whole_exponent.cast()?);
+
+            (
+                power_of_two
+                    .safe_mul(PERCENTAGE_PRECISION.safe_add(exponent_remainder)?)?
+                    .safe_sub(PERCENTAGE_PRECISION)?,
+                2_u128
+                    .pow(EXPONENTIAL_AUCTION_CURVE_EXPONENT.cast()?)
+                    .safe_sub(1)?
+                    .safe_mul(PERCENTAGE_PRECISION)?,
+            )
+        }
+        AuctionCurve::Step => {
+            let step_count = order.auction_step_count.cast::<u128>()?;
+            (
+                slots_elapsed
+                    .safe_mul(step_count)?
+                    .safe_div(auction_duration)?,
+                step_count,
+            )
+        }
+    };
+
+    price_range
+        .cast::<i128>()?
+        .safe_mul(delta_numerator.cast()?)?
+        .safe_div(delta_denominator.cast()?)?
+        .cast()
+}
+
+/// End price for a one-time auction extension, widened from the original end price by
+/// price_buffer but never past the order's own limit price
+pub fn calculate_auction_extension_end_price(order: &Order, price_buffer: u32) -> DriftResult<i64> {
+    let auction_end_price = order.auction_end_price;
+    let end_price_buffer = auction_end_price
+        .cast::<i128>()?
+        .safe_mul(price_buffer.cast()?)?
+        .safe_div(PERCENTAGE_PRECISION_I128)?
+        .cast::<i64>()?;
+
+    let limit_price = order.price.cast::<i64>()?;
+    let extended_end_price = match order.direction {
+        PositionDirection::Long => {
+            let extended_end_price = auction_end_price.safe_add(end_price_buffer)?;
+            if limit_price > 0 {
+                extended_end_price.min(limit_price)
+            } else {
+                extended_end_price
+            }
+        }
+        PositionDirection::Short => {
+            let extended_end_price = auction_end_price.safe_sub(end_price_buffer)?;
+            if limit_price > 0 {
+                extended_end_price.max(limit_price)
+            } else {
+                extended_end_price
+            }
+        }
+    };
+
+    Ok(extended_end_price)
+}
+
 pub fn does_auction_satisfy_maker_order(
     maker_order: &Order,
     taker_order: &Order,
@@ -166,9 +166,10 @@ mod calculate_auction_prices {
 }
 
 mod calculate_auction_price {
+    use crate::controller::position::PositionDirection;
     use crate::math::auction::calculate_auction_price;
     use crate::math::constants::{PRICE_PRECISION_I64, PRICE_PRECISION_U64};
-    use crate::state::user::{Order, OrderType};
+    use crate::state::user::{AuctionCurve, Order, OrderType};
 
     #[test]
     fn long_oracle_order() {
@@ -186,17 +187,17 @@ mod calculate_auction_price {
         let oracle_price = Some(PRICE_PRECISION_I64);
 
         let slot = 0;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 9 * PRICE_PRECISION_U64 / 10);
 
         let slot = 5;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, PRICE_PRECISION_U64);
 
         let slot = 10;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 11 * PRICE_PRECISION_U64 / 10);
 
@@ -211,17 +212,17 @@ mod calculate_auction_price {
         };
 
         let slot = 0;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 8 * PRICE_PRECISION_U64 / 10);
 
         let slot = 5;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 85 * PRICE_PRECISION_U64 / 100);
 
         let slot = 10;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 9 * PRICE_PRECISION_U64 / 10);
 
@@ -236,17 +237,17 @@ mod calculate_auction_price {
         };
 
         let slot = 0;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 11 * PRICE_PRECISION_U64 / 10);
 
         let slot = 5;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 115 * PRICE_PRECISION_U64 / 100);
 
         let slot = 10;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 12 * PRICE_PRECISION_U64 / 10);
     }
@@ -266,17 +267,17 @@ mod calculate_auction_price {
         let oracle_price = Some(PRICE_PRECISION_I64);
 
         let slot = 0;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 11 * PRICE_PRECISION_U64 / 10);
 
         let slot = 5;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, PRICE_PRECISION_U64);
 
         let slot = 10;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 9 * PRICE_PRECISION_U64 / 10);
 
@@ -291,17 +292,17 @@ mod calculate_auction_price {
         };
 
         let slot = 0;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 12 * PRICE_PRECISION_U64 / 10);
 
         let slot = 5;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 115 * PRICE_PRECISION_U64 / 100);
 
         let slot = 10;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 11 * PRICE_PRECISION_U64 / 10);
 
@@ -316,18 +317,193 @@ mod calculate_auction_price {
         };
 
         let slot = 0;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 9 * PRICE_PRECISION_U64 / 10);
 
         let slot = 5;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 85 * PRICE_PRECISION_U64 / 100);
 
         let slot = 10;
-        let price = calculate_auction_price(&order, slot, tick_size, oracle_price).unwrap();
+        let price = calculate_auction_price(&order, slot, tick_size, oracle_price, false).unwrap();
 
         assert_eq!(price, 8 * PRICE_PRECISION_U64 / 10);
     }
+
+    #[test]
+    fn long_exponential_market_order() {
+        let tick_size = 1;
+
+        let order = Order {
+            order_type: OrderType::Market,
+            direction: PositionDirection::Long,
+            auction_duration: 10,
+            slot: 0,
+            auction_start_price: 100 * PRICE_PRECISION_I64,
+            auction_end_price: 110 * PRICE_PRECISION_I64,
+            auction_curve: AuctionCurve::Exponential,
+            ..Order::default()
+        };
+
+        let price = calculate_auction_price(&order, 0, tick_size, None, false).unwrap();
+        assert_eq!(price, 100 * PRICE_PRECISION_U64);
+
+        // 2^2.4 interpolated as 2^2 * 1.4
+        let price = calculate_auction_price(&order, 3, tick_size, None, false).unwrap();
+        assert_eq!(price, 100_180_392);
+
+        // 15/255 of the way halfway through
+        let price = calculate_auction_price(&order, 5, tick_size, None, false).unwrap();
+        assert_eq!(price, 100_588_235);
+
+        let price = calculate_auction_price(&order, 10, tick_size, None, false).unwrap();
+        assert_eq!(price, 110 * PRICE_PRECISION_U64);
+
+        let price = calculate_auction_price(&order, 20, tick_size, None, false).unwrap();
+        assert_eq!(price, 110 * PRICE_PRECISION_U64);
+    }
+
+    #[test]
+    fn long_exponential_oracle_order() {
+        let tick_size = 1;
+
+        // auction starts $.10 below oracle and ends $.1 above oracle
+        let order = Order {
+            order_type: OrderType::Oracle,
+            auction_duration: 10,
+            slot: 0,
+            auction_start_price: -PRICE_PRECISION_I64 / 10,
+            auction_end_price: PRICE_PRECISION_I64 / 10,
+            auction_curve: AuctionCurve::Exponential,
+            ..Order::default()
+        };
+        let oracle_price = Some(PRICE_PRECISION_I64);
+
+        let price = calculate_auction_price(&order, 5, tick_size, oracle_price, false).unwrap();
+        assert_eq!(price, 911_764);
+
+        let price = calculate_auction_price(&order, 10, tick_size, oracle_price, false).unwrap();
+        assert_eq!(price, 11 * PRICE_PRECISION_U64 / 10);
+    }
+
+    #[test]
+    fn short_step_market_order() {
+        let tick_size = 1;
+
+        let order = Order {
+            order_type: OrderType::Market,
+            direction: PositionDirection::Short,
+            auction_duration: 10,
+            slot: 0,
+            auction_start_price: 110 * PRICE_PRECISION_I64,
+            auction_end_price: 100 * PRICE_PRECISION_I64,
+            auction_curve: AuctionCurve::Step,
+            auction_step_count: 4,
+            ..Order::default()
+        };
+
+        let price = calculate_auction_price(&order, 2, tick_size, None, false).unwrap();
+        assert_eq!(price, 110 * PRICE_PRECISION_U64);
+
+        let price = calculate_auction_price(&order, 3, tick_size, None, false).unwrap();
+        assert_eq!(price, 107_500_000);
+
+        let price = calculate_auction_price(&order, 5, tick_size, None, false).unwrap();
+        assert_eq!(price, 105 * PRICE_PRECISION_U64);
+
+        let price = calculate_auction_price(&order, 9, tick_size, None, false).unwrap();
+        assert_eq!(price, 102_500_000);
+
+        let price = calculate_auction_price(&order, 10, tick_size, None, false).unwrap();
+        assert_eq!(price, 100 * PRICE_PRECISION_U64);
+    }
+
+    #[test]
+    fn prediction_market_auction_capped_at_one() {
+        let tick_size = 1;
+
+        // auction starts $.10 below oracle and ends $.1 above oracle
+        let order = Order {
+            order_type: OrderType::Oracle,
+            auction_duration: 10,
+            slot: 0,
+            auction_start_price: -PRICE_PRECISION_I64 / 10,
+            auction_end_price: PRICE_PRECISION_I64 / 10,
+            ..Order::default()
+        };
+        let oracle_price = Some(PRICE_PRECISION_I64);
+
+        let price = calculate_auction_price(&order, 0, tick_size, oracle_price, true).unwrap();
+        assert_eq!(price, 9 * PRICE_PRECISION_U64 / 10);
+
+        let price = calculate_auction_price(&order, 10, tick_size, oracle_price, true).unwrap();
+        assert_eq!(price, PRICE_PRECISION_U64);
+
+        let order = Order {
+            order_type: OrderType::Market,
+            auction_duration: 10,
+            slot: 0,
+            auction_start_price: PRICE_PRECISION_I64,
+            auction_end_price: 2 * PRICE_PRECISION_I64,
+            direction: PositionDirection::Long,
+            ..Order::default()
+        };
+
+        let price = calculate_auction_price(&order, 5, tick_size, None, false).unwrap();
+        assert_eq!(price, 3 * PRICE_PRECISION_U64 / 2);
+
+        let price = calculate_auction_price(&order, 5, tick_size, None, true).unwrap();
+        assert_eq!(price, PRICE_PRECISION_U64);
+    }
+}
+
+mod calculate_auction_extension_end_price {
+    use crate::controller::position::PositionDirection;
+    use crate::math::auction::calculate_auction_extension_end_price;
+    use crate::math::constants::{PERCENTAGE_PRECISION, PRICE_PRECISION_I64, PRICE_PRECISION_U64};
+    use crate::state::user::{Order, OrderType};
+
+    #[test]
+    fn long_market_order() {
+        let price_buffer = (PERCENTAGE_PRECISION / 100) as u32; // 1%
+
+        let mut order = Order {
+            order_type: OrderType::Market,
+            direction: PositionDirection::Long,
+            auction_start_price: 99 * PRICE_PRECISION_I64,
+            auction_end_price: 100 * PRICE_PRECISION_I64,
+            ..Order::default()
+        };
+
+        let end_price = calculate_auction_extension_end_price(&order, price_buffer).unwrap();
+        assert_eq!(end_price, 101 * PRICE_PRECISION_I64);
+
+        // capped at the order's limit price
+        order.price = 201 * PRICE_PRECISION_U64 / 2;
+        let end_price = calculate_auction_extension_end_price(&order, price_buffer).unwrap();
+        assert_eq!(end_price, 201 * PRICE_PRECISION_I64 / 2);
+    }
+
+    #[test]
+    fn short_market_order() {
+        let price_buffer = (PERCENTAGE_PRECISION / 100) as u32; // 1%
+
+        let mut order = Order {
+            order_type: OrderType::Market,
+            direction: PositionDirection::Short,
+            auction_start_price: 101 * PRICE_PRECISION_I64,
+            auction_end_price: 100 * PRICE_PRECISION_I64,
+            ..Order::default()
+        };
+
+        let end_price = calculate_auction_extension_end_price(&order, price_buffer).unwrap();
+        assert_eq!(end_price, 99 * PRICE_PRECISION_I64);
+
+        // capped at the order's limit price
+        order.price = 199 * PRICE_PRECISION_U64 / 2;
+        let end_price = calculate_auction_extension_end_price(&order, price_buffer).unwrap();
+        assert_eq!(end_price, 199 * PRICE_PRECISION_I64 / 2);
+    }
 }
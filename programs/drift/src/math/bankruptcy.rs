@@ -19,6 +19,11 @@ pub fn is_user_bankrupt(user: &User) -> bool {
     }
 
     for perp_position in user.perp_positions.iter() {
+        // isolated positions go bankrupt on their own
+        if perp_position.is_isolated {
+            continue;
+        }
+
         if perp_position.base_asset_amount != 0
             || perp_position.quote_asset_amount > 0
             || perp_position.has_open_order()
@@ -8,7 +8,10 @@ pub const QUOTE_SPOT_MARKET_INDEX: u16 = 0;
 // USER ACCOUNT CONSTANTS
 pub const MAX_SPOT_POSITIONS: u8 = 8;
 pub const MAX_PERP_POSITIONS: u8 = 8;
-pub const MAX_OPEN_ORDERS: u8 = 32;
+// order slots every user account has. the zero-copy User layout is fixed, so the capacity can't
+// be chosen per account, migrate_user is the one resize from the legacy 32 slot layout
+pub const MAX_USER_ORDERS: usize = 64;
+pub const MAX_OPEN_ORDERS: u8 = MAX_USER_ORDERS as u8;
 
 // PRECISIONS
 pub const AMM_RESERVE_PRECISION: u128 = 1_000_000_000; //expo = -9;
@@ -23,6 +26,8 @@ pub const PRICE_PRECISION: u128 = 1_000_000; //expo = -6;
 pub const PRICE_PRECISION_I128: i128 = PRICE_PRECISION as i128;
 pub const PRICE_PRECISION_U64: u64 = 1_000_000; //expo = -6;
 pub const PRICE_PRECISION_I64: i64 = 1_000_000; //expo = -6;
+pub const MAX_PREDICTION_MARKET_PRICE: u64 = PRICE_PRECISION_U64;
+pub const MAX_PREDICTION_MARKET_PRICE_I64: i64 = PRICE_PRECISION_I64;
 
 pub const PEG_PRECISION: u128 = 1_000_000; //expo = -6
 pub const PEG_PRECISION_I128: i128 = PEG_PRECISION as i128; //expo = -6
@@ -73,6 +78,8 @@ pub const SPOT_IMF_PRECISION_U128: u128 = SPOT_IMF_PRECISION as u128; // expo =
 // FORMULAIC REPEG / K
 pub const K_BPS_UPDATE_SCALE: i128 = PERCENTAGE_PRECISION_I128;
 pub const PEG_BPS_UPDATE_SCALE: u128 = PERCENTAGE_PRECISION; // expo = -6 (represents 100%)
+pub const MAX_PEG_BPS_UPDATE: u128 = PEG_BPS_UPDATE_SCALE / 100; // 1% peg move per repeg crank at full curve_update_intensity
+pub const PEG_UPDATE_FEE_POOL_DIVISOR: u128 = 10; // repeg crank can spend 10% of the fee pool
 
 // PRECISION CONVERSIONS
 pub const PRICE_TO_PEG_PRECISION_RATIO: u128 = PRICE_PRECISION / PEG_PRECISION; // expo: 1 (Delete if we keep peg/price as 1e6)
@@ -111,11 +118,15 @@ pub const SHARE_OF_IF_ESCROW_ALLOCATED_TO_PROTOCOL_DENOMINATOR: u128 = 2;
 pub const SHARE_OF_REVENUE_ALLOCATED_TO_INSURANCE_FUND_VAULT_NUMERATOR: u128 = 1;
 pub const SHARE_OF_REVENUE_ALLOCATED_TO_INSURANCE_FUND_VAULT_DENOMINATOR: u128 = 1;
 
+pub const IF_BOOST_SHARES_PER_WEIGHT_PRECISION: u128 = 1_000_000_000_000; // expo = -12
+
 // TIME PERIODS
 pub const ONE_MINUTE: i128 = 60_i128;
 pub const FIVE_MINUTE: i128 = (60 * 5) as i128;
 pub const ONE_HOUR: i64 = 3600;
 pub const ONE_HOUR_I128: i128 = ONE_HOUR as i128;
+pub const FOUR_HOUR: i64 = ONE_HOUR * 4;
+pub const EIGHT_HOUR: i64 = ONE_HOUR * 8;
 pub const TWENTY_FOUR_HOUR: i64 = 3600 * 24;
 pub const THIRTEEN_DAY: i64 = TWENTY_FOUR_HOUR * 13; // IF unstake default
 pub const EPOCH_DURATION: i64 = TWENTY_FOUR_HOUR * 28;
@@ -150,10 +161,19 @@ pub const ONE_BPS_DENOMINATOR: u32 = 10000;
 pub const MAX_REFERRER_REWARD_EPOCH_UPPER_BOUND: u64 = (4000 * QUOTE_PRECISION) as u64;
 pub const LP_FEE_SLICE_NUMERATOR: u128 = 8;
 pub const LP_FEE_SLICE_DENOMINATOR: u128 = 10;
+pub const PRO_LP_TIER_SHARE_MULTIPLIER: u64 = 2;
 pub const FEE_DENOMINATOR: u32 = 10 * ONE_BPS_DENOMINATOR;
 pub const FEE_PERCENTAGE_DENOMINATOR: u32 = 100;
+pub const MAX_BUILDER_FEE_SHARE: u8 = 25; // 25% of the taker fee
+pub const MAX_REFERRER_REWARD_SHARE: u8 = 25; // 25% of the taker fee
+pub const MAX_SECOND_LEVEL_REFERRER_REWARD_SHARE: u8 = 50; // 50% of the referrer reward
+pub const REFERRER_REWARD_DECAY_MONTH: i64 = THIRTY_DAY;
+pub const MAX_FEE_ADJUSTMENT: i16 = 100; // market fees range from free to double the global tiers
 pub const OPEN_ORDER_MARGIN_REQUIREMENT: u128 = QUOTE_PRECISION / 100;
 
+// FUEL
+pub const FUEL_BOOST_PRECISION: u64 = 10; // 10 = 1x
+
 // PRICE AMOUNTS
 pub const HUNDRENTH_OF_CENT: u128 = PRICE_PRECISION / 10_000; //.0001
 
@@ -176,6 +196,7 @@ pub const MAX_MARK_TWAP_DIVERGENCE: u128 = 500_000; // expo = -3
 
 pub const MAX_MARGIN_RATIO: u32 = MARGIN_PRECISION as u32; // 1x leverage
 pub const MIN_MARGIN_RATIO: u32 = MARGIN_PRECISION as u32 / 50; // 50x leverage
+pub const MIN_HIGH_LEVERAGE_MARGIN_RATIO: u32 = MARGIN_PRECISION as u32 / 100; // 100x leverage
 
 pub const MAX_BID_ASK_INVENTORY_SKEW_FACTOR: u64 = 10 * BID_ASK_SPREAD_PRECISION;
 
@@ -186,12 +207,29 @@ pub const DEFAULT_MAX_TWAP_UPDATE_PRICE_BAND_DENOMINATOR: i64 = 3; // '3' here m
 pub const DEFAULT_REVENUE_SINCE_LAST_FUNDING_SPREAD_RETREAT: i64 = -25 * QUOTE_PRECISION_I64; //$25 loss
 pub const DEFAULT_LARGE_BID_ASK_FACTOR: u64 = 10 * BID_ASK_SPREAD_PRECISION;
 pub const DEFAULT_LIQUIDATION_MARGIN_BUFFER_RATIO: u32 = (MARGIN_PRECISION as u32) / 50; // 2%
+pub const DEFAULT_IF_LIQUIDATION_FEE: u32 = LIQUIDATION_FEE_PRECISION / 100; // 1%
 pub const DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE: u64 = BASE_PRECISION_U64 / 10000; // 1e-4;
 pub const DEFAULT_QUOTE_ASSET_AMOUNT_TICK_SIZE: u64 =
     PRICE_PRECISION_U64 / DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE; // 1e-2
 
+// PERMISSIONLESS LISTING
+pub const PERMISSIONLESS_LISTING_MARGIN_RATIO_INITIAL: u32 = MARGIN_PRECISION / 5; // 5x leverage
+pub const PERMISSIONLESS_LISTING_MARGIN_RATIO_MAINTENANCE: u32 = MARGIN_PRECISION / 10; // 10x leverage
+pub const PERMISSIONLESS_LISTING_LIQUIDATOR_FEE: u32 = LIQUIDATION_FEE_PRECISION / 100; // 1%
+pub const PERMISSIONLESS_LISTING_FUNDING_PERIOD: i64 = ONE_HOUR;
+pub const MIN_PERMISSIONLESS_LISTING_VETO_WINDOW: u32 = TWENTY_FOUR_HOUR as u32;
+pub const PERMISSIONLESS_LISTING_MAX_OPEN_INTEREST_NOTIONAL: u128 = 100_000 * QUOTE_PRECISION; // $100k
+pub const PERMISSIONLESS_LISTING_MAX_RESERVE_NOTIONAL: u128 = 1_000_000 * QUOTE_PRECISION; // $1m
+
+// ORACLES
+pub const PYTH_PULL_MAX_PUBLISH_TIME_STALENESS: i64 = 60; // seconds, for admin oracle updates
+
 // ORDERS
 pub const AUCTION_DERIVE_PRICE_FRACTION: i64 = 200;
+pub const EXPONENTIAL_AUCTION_CURVE_EXPONENT: u128 = 8; // halfway through, price has moved 15/255 of the range
+pub const PROTECTED_MAKER_PRICE_CUSHION_DIVISOR: u64 = 1000; // 10 bps of oracle price
+pub const DEAD_MAN_SWITCH_MAX_SLIPPAGE_DIVISOR: i64 = 20; // 5% of oracle price
 
 // WITHDRAWS
+pub const ALL_TOKEN_AMOUNT: u64 = u64::MAX; // deposit/withdraw amount that closes the whole spot position
 pub const SPOT_MARKET_TOKEN_TWAP_WINDOW: i64 = TWENTY_FOUR_HOUR;
@@ -336,7 +336,7 @@ fn calculate_k_with_lps_tests() {
         ..PerpPosition::default()
     };
 
-    mint_lp_shares(&mut position, &mut market, BASE_PRECISION_U64).unwrap();
+    mint_lp_shares(&mut position, &mut market, BASE_PRECISION_U64, 1).unwrap();
 
     market.amm.base_asset_amount_per_lp = 1;
     market.amm.quote_asset_amount_per_lp = -QUOTE_PRECISION_I64 as i128;
@@ -375,7 +375,7 @@ fn calculate_k_with_lps_tests() {
 
     // lp whale adds
     let lp_whale_amount = 1000 * BASE_PRECISION_U64;
-    mint_lp_shares(&mut position, &mut market, lp_whale_amount).unwrap();
+    mint_lp_shares(&mut position, &mut market, lp_whale_amount, 1).unwrap();
 
     // ensure same cost
     let update_k_up =
@@ -393,7 +393,7 @@ fn calculate_k_with_lps_tests() {
     assert_eq!(cost, -4995004950); //amm rug
 
     // lp whale removes
-    burn_lp_shares(&mut position, &mut market, lp_whale_amount, 0).unwrap();
+    burn_lp_shares(&mut position, &mut market, lp_whale_amount, 0, 1).unwrap();
 
     // ensure same cost
     let update_k_up =
@@ -6,8 +6,10 @@ use crate::error::DriftResult;
 use crate::math::casting::Cast;
 
 use crate::math::constants::{
-    FIFTY_MILLION_QUOTE, FIVE_MILLION_QUOTE, ONE_HUNDRED_MILLION_QUOTE, ONE_MILLION_QUOTE,
-    ONE_THOUSAND_QUOTE, TEN_BPS, TEN_MILLION_QUOTE, TEN_THOUSAND_QUOTE,
+    FEE_PERCENTAGE_DENOMINATOR, FIFTY_MILLION_QUOTE, FIVE_MILLION_QUOTE, MAX_BUILDER_FEE_SHARE,
+    MAX_REFERRER_REWARD_SHARE, MAX_SECOND_LEVEL_REFERRER_REWARD_SHARE, ONE_HUNDRED_MILLION_QUOTE,
+    ONE_MILLION_QUOTE, ONE_THOUSAND_QUOTE, REFERRER_REWARD_DECAY_MONTH, TEN_BPS, TEN_MILLION_QUOTE,
+    TEN_THOUSAND_QUOTE,
 };
 use crate::math::helpers::get_proportion_u128;
 use crate::math::safe_math::SafeMath;
@@ -28,6 +30,7 @@ pub struct FillFees {
     pub filler_reward: u64,
     pub referrer_reward: u64,
     pub referee_discount: u64,
+    pub token_discount: u64,
 }
 
 pub fn calculate_fee_for_fulfillment_with_amm(
@@ -41,6 +44,7 @@ pub fn calculate_fee_for_fulfillment_with_amm(
     referrer_stats: &Option<&mut UserStats>,
     quote_asset_amount_surplus: i64,
     is_post_only: bool,
+    now: i64,
 ) -> DriftResult<FillFees> {
     let fee_tier = determine_user_fee_tier(user_stats, fee_structure, &MarketType::Perp)?;
 
@@ -73,16 +77,22 @@ pub fn calculate_fee_for_fulfillment_with_amm(
             filler_reward,
             referrer_reward: 0,
             referee_discount: 0,
+            token_discount: 0,
         })
     } else {
         let fee = calculate_taker_fee(quote_asset_amount, fee_tier)?;
 
+        let token_discount = calculate_token_discount(fee, user_stats)?;
+        let fee = fee.safe_sub(token_discount)?;
+
         let (fee, referee_discount, referrer_reward) = if reward_referrer {
             calculate_referee_fee_and_referrer_reward(
                 fee,
                 fee_tier,
                 fee_structure.referrer_reward_epoch_upper_bound,
+                user_stats,
                 referrer_stats,
+                now,
             )?
         } else {
             (fee, 0, 0)
@@ -117,6 +127,7 @@ pub fn calculate_fee_for_fulfillment_with_amm(
             filler_reward,
             referrer_reward,
             referee_discount,
+            token_discount,
         })
     }
 }
@@ -141,7 +152,9 @@ fn calculate_referee_fee_and_referrer_reward(
     fee: u64,
     fee_tier: &FeeTier,
     referrer_reward_epoch_upper_bound: u64,
+    referee_stats: &UserStats,
     referrer_stats: &Option<&mut UserStats>,
+    now: i64,
 ) -> DriftResult<(u64, u64, u64)> {
     let referee_discount = get_proportion_u128(
         fee as u128,
@@ -161,6 +174,26 @@ fn calculate_referee_fee_and_referrer_reward(
 
     let referrer_reward = match referrer_stats {
         Some(referrer_stats) => {
+            let max_referrer_reward_from_fee = if referrer_stats.referrer_reward_share > 0 {
+                fee.safe_mul(
+                    referrer_stats
+                        .referrer_reward_share
+                        .min(MAX_REFERRER_REWARD_SHARE)
+                        .cast()?,
+                )?
+                .safe_div(FEE_PERCENTAGE_DENOMINATOR.cast()?)?
+            } else {
+                max_referrer_reward_from_fee
+            };
+            let max_referrer_reward_from_fee =
+                calculate_boosted_referrer_reward(max_referrer_reward_from_fee, referrer_stats)?
+                    .min(referee_fee);
+            let max_referrer_reward_from_fee = calculate_decayed_referrer_reward(
+                max_referrer_reward_from_fee,
+                referee_stats.referred_ts,
+                referrer_stats.referrer_reward_decay_months,
+                now,
+            )?;
             let max_referrer_reward_in_epoch = referrer_reward_epoch_upper_bound
                 .saturating_sub(referrer_stats.fees.current_epoch_referrer_reward);
             max_referrer_reward_from_fee.min(max_referrer_reward_in_epoch)
@@ -221,6 +254,7 @@ pub fn calculate_fee_for_fulfillment_with_match(
     reward_referrer: bool,
     referrer_stats: &Option<&mut UserStats>,
     market_type: &MarketType,
+    now: i64,
 ) -> DriftResult<FillFees> {
     let taker_fee_tier = determine_user_fee_tier(taker_stats, fee_structure, market_type)?;
     let maker_fee_tier = if let Some(maker_stats) = maker_stats {
@@ -231,12 +265,17 @@ pub fn calculate_fee_for_fulfillment_with_match(
 
     let taker_fee = calculate_taker_fee(quote_asset_amount, taker_fee_tier)?;
 
+    let token_discount = calculate_token_discount(taker_fee, taker_stats)?;
+    let taker_fee = taker_fee.safe_sub(token_discount)?;
+
     let (taker_fee, referee_discount, referrer_reward) = if reward_referrer {
         calculate_referee_fee_and_referrer_reward(
             taker_fee,
             taker_fee_tier,
             fee_structure.referrer_reward_epoch_upper_bound,
+            taker_stats,
             referrer_stats,
+            now,
         )?
     } else {
         (taker_fee, 0, 0)
@@ -271,6 +310,7 @@ pub fn calculate_fee_for_fulfillment_with_match(
         referrer_reward,
         fee_to_market_for_lp: 0,
         referee_discount,
+        token_discount,
     })
 }
 
@@ -337,6 +377,147 @@ pub fn calculate_fee_for_fulfillment_with_serum(
     })
 }
 
+/// Fee paid for a hypothetical fill, negative if the user earns a maker rebate. Ignores filler
+/// and referrer rewards
+pub fn calculate_simulated_fill_fee(
+    user_stats: &UserStats,
+    quote_asset_amount: u64,
+    fee_structure: &FeeStructure,
+    market_type: &MarketType,
+    is_maker: bool,
+) -> DriftResult<i64> {
+    let fee_tier = determine_user_fee_tier(user_stats, fee_structure, market_type)?;
+
+    if is_maker {
+        return calculate_maker_rebate(quote_asset_amount, fee_tier)?
+            .cast::<i64>()
+            .map(|maker_rebate| -maker_rebate);
+    }
+
+    let fee = calculate_taker_fee(quote_asset_amount, fee_tier)?;
+    fee.safe_sub(calculate_token_discount(fee, user_stats)?)?
+        .cast()
+}
+
+/// scales the taker fee of every tier by taker_fee_adjustment percent and the maker rebate by
+/// maker_rebate_adjustment percent
+pub fn calculate_fee_structure_with_adjustment(
+    fee_structure: &FeeStructure,
+    taker_fee_adjustment: i16,
+    maker_rebate_adjustment: i16,
+) -> DriftResult<FeeStructure> {
+    let mut adjusted_fee_structure = fee_structure.clone();
+
+    if taker_fee_adjustment == 0 && maker_rebate_adjustment == 0 {
+        return Ok(adjusted_fee_structure);
+    }
+
+    for fee_tier in adjusted_fee_structure.fee_tiers.iter_mut() {
+        fee_tier.fee_numerator =
+            apply_fee_adjustment(fee_tier.fee_numerator, taker_fee_adjustment)?;
+        fee_tier.maker_rebate_numerator =
+            apply_fee_adjustment(fee_tier.maker_rebate_numerator, maker_rebate_adjustment)?;
+    }
+
+    Ok(adjusted_fee_structure)
+}
+
+fn apply_fee_adjustment(numerator: u32, fee_adjustment: i16) -> DriftResult<u32> {
+    numerator
+        .cast::<i64>()?
+        .safe_mul(
+            FEE_PERCENTAGE_DENOMINATOR
+                .cast::<i64>()?
+                .safe_add(fee_adjustment.cast()?)?,
+        )?
+        .safe_div(FEE_PERCENTAGE_DENOMINATOR.cast()?)?
+        .max(0)
+        .cast()
+}
+
+pub fn calculate_builder_reward(taker_fee: u64, builder_fee_share: u8) -> DriftResult<u64> {
+    taker_fee
+        .safe_mul(builder_fee_share.min(MAX_BUILDER_FEE_SHARE).cast()?)?
+        .safe_div(FEE_PERCENTAGE_DENOMINATOR.cast()?)
+}
+
+/// full reward for the first decay period after the referral, then halved every period after
+pub fn calculate_decayed_referrer_reward(
+    referrer_reward: u64,
+    referred_ts: u32,
+    decay_months: u8,
+    now: i64,
+) -> DriftResult<u64> {
+    if decay_months == 0 || referred_ts == 0 {
+        return Ok(referrer_reward);
+    }
+
+    let decay_period = REFERRER_REWARD_DECAY_MONTH.safe_mul(decay_months.cast()?)?;
+    let periods_elapsed = now
+        .safe_sub(referred_ts.cast()?)?
+        .max(0)
+        .safe_div(decay_period)?;
+
+    Ok(referrer_reward >> periods_elapsed.min(63))
+}
+
+/// share of the referrer reward passed up to the referrer's own referrer
+pub fn calculate_second_level_referrer_reward(
+    referrer_reward: u64,
+    second_level_referrer_reward_share: u8,
+) -> DriftResult<u64> {
+    referrer_reward
+        .safe_mul(
+            second_level_referrer_reward_share
+                .min(MAX_SECOND_LEVEL_REFERRER_REWARD_SHARE)
+                .cast()?,
+        )?
+        .safe_div(FEE_PERCENTAGE_DENOMINATOR.cast()?)
+}
+
+/// percent of the taker fee waived for each governance stake tier
+fn governance_stake_fee_discount(governance_stake_tier: u8) -> u64 {
+    match governance_stake_tier {
+        0 => 0,
+        1 => 5,
+        2 => 10,
+        3 => 15,
+        _ => 20,
+    }
+}
+
+/// percent added to the referrer reward for each governance stake tier of the referrer
+fn governance_stake_referrer_reward_boost(governance_stake_tier: u8) -> u64 {
+    match governance_stake_tier {
+        0 => 0,
+        1 => 10,
+        2 => 20,
+        3 => 30,
+        _ => 40,
+    }
+}
+
+pub fn calculate_token_discount(taker_fee: u64, user_stats: &UserStats) -> DriftResult<u64> {
+    taker_fee
+        .safe_mul(governance_stake_fee_discount(
+            user_stats.governance_stake_tier,
+        ))?
+        .safe_div(FEE_PERCENTAGE_DENOMINATOR.cast()?)
+}
+
+pub fn calculate_boosted_referrer_reward(
+    referrer_reward: u64,
+    referrer_stats: &UserStats,
+) -> DriftResult<u64> {
+    let boost = referrer_reward
+        .safe_mul(governance_stake_referrer_reward_boost(
+            referrer_stats.governance_stake_tier,
+        ))?
+        .safe_div(FEE_PERCENTAGE_DENOMINATOR.cast()?)?;
+
+    referrer_reward.safe_add(boost)
+}
+
 pub fn determine_user_fee_tier<'a>(
     user_stats: &UserStats,
     fee_structure: &'a FeeStructure,
@@ -348,45 +529,58 @@ pub fn determine_user_fee_tier<'a>(
     }
 }
 
-fn determine_perp_fee_tier<'a>(
-    user_stats: &UserStats,
-    fee_structure: &'a FeeStructure,
-) -> DriftResult<&'a FeeTier> {
+fn determine_fee_tier_index(user_stats: &UserStats) -> DriftResult<usize> {
     let total_30d_volume = user_stats.get_total_30d_volume()?;
     let staked_quote_asset_amount = user_stats.if_staked_quote_asset_amount;
 
     if total_30d_volume >= ONE_HUNDRED_MILLION_QUOTE
         || staked_quote_asset_amount >= TEN_THOUSAND_QUOTE
     {
-        return Ok(&fee_structure.fee_tiers[5]);
+        return Ok(5);
     }
 
     if total_30d_volume >= FIFTY_MILLION_QUOTE
         || staked_quote_asset_amount >= ONE_THOUSAND_QUOTE * 5
     {
-        return Ok(&fee_structure.fee_tiers[4]);
+        return Ok(4);
     }
 
     if total_30d_volume >= TEN_MILLION_QUOTE || staked_quote_asset_amount >= ONE_THOUSAND_QUOTE * 2
     {
-        return Ok(&fee_structure.fee_tiers[3]);
+        return Ok(3);
     }
 
     if total_30d_volume >= FIVE_MILLION_QUOTE || staked_quote_asset_amount >= ONE_THOUSAND_QUOTE {
-        return Ok(&fee_structure.fee_tiers[2]);
+        return Ok(2);
     }
 
     if total_30d_volume >= ONE_MILLION_QUOTE || staked_quote_asset_amount >= ONE_THOUSAND_QUOTE / 2
     {
-        return Ok(&fee_structure.fee_tiers[1]);
+        return Ok(1);
     }
 
-    Ok(&fee_structure.fee_tiers[0])
+    Ok(0)
+}
+
+fn determine_perp_fee_tier<'a>(
+    user_stats: &UserStats,
+    fee_structure: &'a FeeStructure,
+) -> DriftResult<&'a FeeTier> {
+    Ok(&fee_structure.fee_tiers[determine_fee_tier_index(user_stats)?])
 }
 
 fn determine_spot_fee_tier<'a>(
-    _user_stats: &UserStats,
+    user_stats: &UserStats,
     fee_structure: &'a FeeStructure,
 ) -> DriftResult<&'a FeeTier> {
-    Ok(&fee_structure.fee_tiers[0])
+    let fee_tier_index = determine_fee_tier_index(user_stats)?;
+
+    // spot fee structures often only configure the first tier, fall back past tiers left unset
+    let fee_tier = fee_structure.fee_tiers[1..=fee_tier_index]
+        .iter()
+        .rev()
+        .find(|fee_tier| fee_tier.fee_numerator != 0)
+        .unwrap_or(&fee_structure.fee_tiers[0]);
+
+    Ok(fee_tier)
 }
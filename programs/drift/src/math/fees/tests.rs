@@ -29,6 +29,7 @@ mod calculate_fee_for_taker_and_maker {
             false,
             &None,
             &MarketType::Perp,
+            0,
         )
         .unwrap();
 
@@ -71,6 +72,7 @@ mod calculate_fee_for_taker_and_maker {
             false,
             &None,
             &MarketType::Perp,
+            0,
         )
         .unwrap();
 
@@ -112,6 +114,7 @@ mod calculate_fee_for_taker_and_maker {
             false,
             &None,
             &MarketType::Perp,
+            0,
         )
         .unwrap();
 
@@ -153,6 +156,7 @@ mod calculate_fee_for_taker_and_maker {
             false,
             &None,
             &MarketType::Perp,
+            0,
         )
         .unwrap();
 
@@ -192,6 +196,7 @@ mod calculate_fee_for_taker_and_maker {
             true,
             &None,
             &MarketType::Perp,
+            0,
         )
         .unwrap();
 
@@ -205,7 +210,7 @@ mod calculate_fee_for_taker_and_maker {
 }
 
 mod calculate_fee_for_order_fulfill_against_amm {
-    use crate::math::constants::QUOTE_PRECISION_U64;
+    use crate::math::constants::{QUOTE_PRECISION_U64, THIRTY_DAY};
     use crate::math::fees::{calculate_fee_for_fulfillment_with_amm, FillFees};
     use crate::state::state::FeeStructure;
     use crate::state::user::UserStats;
@@ -235,6 +240,7 @@ mod calculate_fee_for_order_fulfill_against_amm {
             &None,
             0,
             false,
+            0,
         )
         .unwrap();
 
@@ -244,6 +250,96 @@ mod calculate_fee_for_order_fulfill_against_amm {
         assert_eq!(referrer_reward, 10000);
         assert_eq!(referee_discount, 10000);
     }
+
+    #[test]
+    fn referrer_reward_share_with_decay() {
+        let quote_asset_amount = 100 * QUOTE_PRECISION_U64;
+
+        let taker_stats = UserStats {
+            referred_ts: 1,
+            ..UserStats::default()
+        };
+        let mut referrer_stats = UserStats {
+            referrer_reward_share: 20,
+            referrer_reward_decay_months: 1,
+            ..UserStats::default()
+        };
+        let fee_structure = FeeStructure::test_default();
+
+        let FillFees {
+            user_fee,
+            fee_to_market,
+            referee_discount,
+            referrer_reward,
+            ..
+        } = calculate_fee_for_fulfillment_with_amm(
+            &taker_stats,
+            quote_asset_amount,
+            &fee_structure,
+            0,
+            60,
+            false,
+            true,
+            &Some(&mut referrer_stats),
+            0,
+            false,
+            1 + 2 * THIRTY_DAY,
+        )
+        .unwrap();
+
+        // 20% of the taker fee, halved twice after two decay periods
+        assert_eq!(referee_discount, 10000);
+        assert_eq!(user_fee, 90000);
+        assert_eq!(referrer_reward, 5000);
+        assert_eq!(fee_to_market, 85000);
+    }
+
+    #[test]
+    fn governance_stake() {
+        let quote_asset_amount = 100 * QUOTE_PRECISION_U64;
+
+        let taker_stats = UserStats {
+            governance_stake_tier: 2,
+            ..UserStats::default()
+        };
+        let mut referrer_stats = UserStats {
+            governance_stake_tier: 4,
+            ..UserStats::default()
+        };
+        let fee_structure = FeeStructure::test_default();
+
+        let FillFees {
+            user_fee,
+            fee_to_market,
+            filler_reward,
+            referee_discount,
+            referrer_reward,
+            token_discount,
+            ..
+        } = calculate_fee_for_fulfillment_with_amm(
+            &taker_stats,
+            quote_asset_amount,
+            &fee_structure,
+            0,
+            60,
+            false,
+            true,
+            &Some(&mut referrer_stats),
+            0,
+            false,
+            0,
+        )
+        .unwrap();
+
+        // 10% of the taker fee waived for tier 2
+        assert_eq!(token_discount, 10000);
+        assert_eq!(referee_discount, 9000);
+        assert_eq!(user_fee, 81000);
+        // referrer reward boosted 40% for tier 4
+        assert_eq!(referrer_reward, 12600);
+        assert_eq!(fee_to_market, 68400);
+        assert_eq!(filler_reward, 0);
+    }
 }
 
 mod calculate_fee_for_fulfillment_with_serum {
@@ -402,3 +498,280 @@ mod calculate_fee_for_fulfillment_with_serum {
         assert_eq!(filler_reward, 2000);
     }
 }
+
+mod determine_user_fee_tier {
+    use crate::math::constants::{FEE_DENOMINATOR, ONE_MILLION_QUOTE, TEN_MILLION_QUOTE};
+    use crate::math::fees::determine_user_fee_tier;
+    use crate::state::state::{FeeStructure, FeeTier};
+    use crate::state::user::{MarketType, UserStats};
+
+    #[test]
+    fn perp_volume_tiers() {
+        let fee_structure = FeeStructure::perps_default();
+
+        let user_stats = UserStats::default();
+        let fee_tier =
+            determine_user_fee_tier(&user_stats, &fee_structure, &MarketType::Perp).unwrap();
+        assert_eq!(fee_tier.fee_numerator, 100);
+
+        let user_stats = UserStats {
+            taker_volume_30d: TEN_MILLION_QUOTE,
+            ..UserStats::default()
+        };
+        let fee_tier =
+            determine_user_fee_tier(&user_stats, &fee_structure, &MarketType::Perp).unwrap();
+        assert_eq!(fee_tier.fee_numerator, 70);
+
+        // maker and taker volume count towards the same tier
+        let user_stats = UserStats {
+            maker_volume_30d: ONE_MILLION_QUOTE / 2,
+            taker_volume_30d: ONE_MILLION_QUOTE / 2,
+            ..UserStats::default()
+        };
+        let fee_tier =
+            determine_user_fee_tier(&user_stats, &fee_structure, &MarketType::Perp).unwrap();
+        assert_eq!(fee_tier.fee_numerator, 90);
+    }
+
+    #[test]
+    fn spot_volume_tiers() {
+        let user_stats = UserStats {
+            taker_volume_30d: TEN_MILLION_QUOTE,
+            ..UserStats::default()
+        };
+
+        // only the first tier configured
+        let mut fee_structure = FeeStructure::spot_default();
+        let fee_tier =
+            determine_user_fee_tier(&user_stats, &fee_structure, &MarketType::Spot).unwrap();
+        assert_eq!(fee_tier.fee_numerator, 100);
+
+        // tier 3 unset, falls back to tier 2
+        fee_structure.fee_tiers[2] = FeeTier {
+            fee_numerator: 80,
+            fee_denominator: FEE_DENOMINATOR,
+            ..fee_structure.fee_tiers[0]
+        };
+        let fee_tier =
+            determine_user_fee_tier(&user_stats, &fee_structure, &MarketType::Spot).unwrap();
+        assert_eq!(fee_tier.fee_numerator, 80);
+
+        fee_structure.fee_tiers[3] = FeeTier {
+            fee_numerator: 70,
+            fee_denominator: FEE_DENOMINATOR,
+            ..fee_structure.fee_tiers[0]
+        };
+        let fee_tier =
+            determine_user_fee_tier(&user_stats, &fee_structure, &MarketType::Spot).unwrap();
+        assert_eq!(fee_tier.fee_numerator, 70);
+    }
+}
+
+mod calculate_builder_reward {
+    use crate::math::constants::QUOTE_PRECISION_U64;
+    use crate::math::fees::calculate_builder_reward;
+
+    #[test]
+    fn share_of_taker_fee() {
+        let taker_fee = QUOTE_PRECISION_U64;
+
+        assert_eq!(calculate_builder_reward(taker_fee, 0).unwrap(), 0);
+        assert_eq!(
+            calculate_builder_reward(taker_fee, 10).unwrap(),
+            QUOTE_PRECISION_U64 / 10
+        );
+
+        // capped at 25% of the taker fee
+        assert_eq!(
+            calculate_builder_reward(taker_fee, 100).unwrap(),
+            QUOTE_PRECISION_U64 / 4
+        );
+    }
+}
+
+mod calculate_decayed_referrer_reward {
+    use crate::math::constants::{QUOTE_PRECISION_U64, THIRTY_DAY};
+    use crate::math::fees::{
+        calculate_decayed_referrer_reward, calculate_second_level_referrer_reward,
+    };
+
+    #[test]
+    fn decay() {
+        let reward = QUOTE_PRECISION_U64;
+        let referred_ts = 1_000_000;
+        let referred_ts_i64 = referred_ts as i64;
+
+        // no decay configured or a referral that predates decay
+        assert_eq!(
+            calculate_decayed_referrer_reward(reward, referred_ts, 0, i64::MAX).unwrap(),
+            reward
+        );
+        assert_eq!(
+            calculate_decayed_referrer_reward(reward, 0, 3, i64::MAX).unwrap(),
+            reward
+        );
+
+        // full reward within the first period
+        let now = referred_ts_i64 + 3 * THIRTY_DAY - 1;
+        assert_eq!(
+            calculate_decayed_referrer_reward(reward, referred_ts, 3, now).unwrap(),
+            reward
+        );
+
+        // halved every period after
+        let now = referred_ts_i64 + 3 * THIRTY_DAY;
+        assert_eq!(
+            calculate_decayed_referrer_reward(reward, referred_ts, 3, now).unwrap(),
+            reward / 2
+        );
+        let now = referred_ts_i64 + 6 * THIRTY_DAY;
+        assert_eq!(
+            calculate_decayed_referrer_reward(reward, referred_ts, 3, now).unwrap(),
+            reward / 4
+        );
+
+        assert_eq!(
+            calculate_decayed_referrer_reward(reward, referred_ts, 1, i64::MAX).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn second_level_share() {
+        let reward = QUOTE_PRECISION_U64;
+
+        assert_eq!(
+            calculate_second_level_referrer_reward(reward, 0).unwrap(),
+            0
+        );
+        assert_eq!(
+            calculate_second_level_referrer_reward(reward, 20).unwrap(),
+            reward / 5
+        );
+
+        // capped at 50% of the referrer reward
+        assert_eq!(
+            calculate_second_level_referrer_reward(reward, 100).unwrap(),
+            reward / 2
+        );
+    }
+}
+
+mod calculate_token_discount {
+    use crate::math::constants::QUOTE_PRECISION_U64;
+    use crate::math::fees::{calculate_boosted_referrer_reward, calculate_token_discount};
+    use crate::state::user::UserStats;
+
+    #[test]
+    fn governance_stake_tiers() {
+        let fee = QUOTE_PRECISION_U64;
+        let mut user_stats = UserStats::default();
+
+        assert_eq!(calculate_token_discount(fee, &user_stats).unwrap(), 0);
+        assert_eq!(
+            calculate_boosted_referrer_reward(fee, &user_stats).unwrap(),
+            fee
+        );
+
+        user_stats.governance_stake_tier = 1;
+        assert_eq!(
+            calculate_token_discount(fee, &user_stats).unwrap(),
+            QUOTE_PRECISION_U64 / 20
+        );
+        assert_eq!(
+            calculate_boosted_referrer_reward(fee, &user_stats).unwrap(),
+            QUOTE_PRECISION_U64 * 11 / 10
+        );
+
+        // tiers past the last are treated as the last
+        user_stats.governance_stake_tier = 10;
+        assert_eq!(
+            calculate_token_discount(fee, &user_stats).unwrap(),
+            QUOTE_PRECISION_U64 / 5
+        );
+        assert_eq!(
+            calculate_boosted_referrer_reward(fee, &user_stats).unwrap(),
+            QUOTE_PRECISION_U64 * 7 / 5
+        );
+    }
+}
+
+mod calculate_fee_structure_with_adjustment {
+    use crate::math::fees::calculate_fee_structure_with_adjustment;
+    use crate::state::state::FeeStructure;
+
+    #[test]
+    fn scales_taker_fee_and_maker_rebate() {
+        let fee_structure = FeeStructure::perps_default();
+
+        let adjusted = calculate_fee_structure_with_adjustment(&fee_structure, 0, 0).unwrap();
+        assert_eq!(adjusted.fee_tiers[0].fee_numerator, 100);
+        assert_eq!(adjusted.fee_tiers[0].maker_rebate_numerator, 20);
+
+        let adjusted = calculate_fee_structure_with_adjustment(&fee_structure, -50, -50).unwrap();
+        assert_eq!(adjusted.fee_tiers[0].fee_numerator, 50);
+        assert_eq!(adjusted.fee_tiers[0].maker_rebate_numerator, 10);
+        assert_eq!(adjusted.fee_tiers[1].fee_numerator, 45);
+        assert_eq!(
+            adjusted.fee_tiers[0].referrer_reward_numerator,
+            fee_structure.fee_tiers[0].referrer_reward_numerator
+        );
+
+        let adjusted = calculate_fee_structure_with_adjustment(&fee_structure, 100, 100).unwrap();
+        assert_eq!(adjusted.fee_tiers[0].fee_numerator, 200);
+        assert_eq!(adjusted.fee_tiers[0].maker_rebate_numerator, 40);
+
+        let adjusted = calculate_fee_structure_with_adjustment(&fee_structure, -100, -100).unwrap();
+        assert!(adjusted
+            .fee_tiers
+            .iter()
+            .all(|fee_tier| fee_tier.fee_numerator == 0 && fee_tier.maker_rebate_numerator == 0));
+    }
+
+    #[test]
+    fn scales_taker_fee_and_maker_rebate_separately() {
+        let fee_structure = FeeStructure::perps_default();
+
+        let adjusted = calculate_fee_structure_with_adjustment(&fee_structure, 0, -100).unwrap();
+        assert_eq!(adjusted.fee_tiers[0].fee_numerator, 100);
+        assert_eq!(adjusted.fee_tiers[0].maker_rebate_numerator, 0);
+
+        let adjusted = calculate_fee_structure_with_adjustment(&fee_structure, -50, 100).unwrap();
+        assert_eq!(adjusted.fee_tiers[0].fee_numerator, 50);
+        assert_eq!(adjusted.fee_tiers[0].maker_rebate_numerator, 40);
+    }
+}
+
+mod calculate_simulated_fill_fee {
+    use crate::math::constants::QUOTE_PRECISION_U64;
+    use crate::math::fees::calculate_simulated_fill_fee;
+    use crate::state::state::FeeStructure;
+    use crate::state::user::{MarketType, UserStats};
+
+    #[test]
+    fn taker_fee_and_maker_rebate() {
+        let fee_structure = FeeStructure::perps_default();
+        let user_stats = UserStats::default();
+        let quote_asset_amount = 100 * QUOTE_PRECISION_U64;
+
+        let fee = calculate_simulated_fill_fee(
+            &user_stats,
+            quote_asset_amount,
+            &fee_structure,
+            &MarketType::Perp,
+            false,
+        )
+        .unwrap();
+        assert_eq!(fee, 100_000);
+
+        let fee = calculate_simulated_fill_fee(
+            &user_stats,
+            quote_asset_amount,
+            &fee_structure,
+            &MarketType::Perp,
+            true,
+        )
+        .unwrap();
+        assert_eq!(fee, -20_000);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::error::DriftResult;
+use crate::math::casting::Cast;
+use crate::math::constants::{FUEL_BOOST_PRECISION, TWENTY_FOUR_HOUR};
+use crate::math::safe_math::SafeMath;
+
+#[cfg(test)]
+mod tests;
+
+/// fuel for filled volume, 1 per dollar at a 1x boost
+pub fn calculate_volume_fuel(quote_asset_amount: u64, boost: u8) -> DriftResult<u64> {
+    quote_asset_amount
+        .cast::<u128>()?
+        .safe_mul(boost.cast()?)?
+        .safe_div(FUEL_BOOST_PRECISION.cast()?)?
+        .cast()
+}
+
+/// fuel for a balance held over a duration, 1 per dollar per day at a 1x boost
+pub fn calculate_time_weighted_fuel(value: u64, duration: i64, boost: u8) -> DriftResult<u64> {
+    value
+        .cast::<u128>()?
+        .safe_mul(duration.max(0).cast()?)?
+        .safe_mul(boost.cast()?)?
+        .safe_div(
+            TWENTY_FOUR_HOUR
+                .cast::<u128>()?
+                .safe_mul(FUEL_BOOST_PRECISION.cast()?)?,
+        )?
+        .cast()
+}
+
+/// fuel for a rate of fuel per day held over a duration
+pub fn calculate_fuel_for_rate(fuel_per_day: u64, duration: i64) -> DriftResult<u64> {
+    calculate_time_weighted_fuel(fuel_per_day, duration, FUEL_BOOST_PRECISION.cast()?)
+}
@@ -0,0 +1,49 @@
+use crate::math::constants::{QUOTE_PRECISION_U64, TWENTY_FOUR_HOUR};
+use crate::math::fuel::{
+    calculate_fuel_for_rate, calculate_time_weighted_fuel, calculate_volume_fuel,
+};
+
+#[test]
+fn volume_fuel() {
+    let quote_asset_amount = 100 * QUOTE_PRECISION_U64;
+
+    assert_eq!(calculate_volume_fuel(quote_asset_amount, 0).unwrap(), 0);
+    assert_eq!(
+        calculate_volume_fuel(quote_asset_amount, 10).unwrap(),
+        100 * QUOTE_PRECISION_U64
+    );
+    assert_eq!(
+        calculate_volume_fuel(quote_asset_amount, 25).unwrap(),
+        250 * QUOTE_PRECISION_U64
+    );
+}
+
+#[test]
+fn time_weighted_fuel() {
+    let value = 1_000 * QUOTE_PRECISION_U64;
+
+    assert_eq!(
+        calculate_time_weighted_fuel(value, TWENTY_FOUR_HOUR, 10).unwrap(),
+        1_000 * QUOTE_PRECISION_U64
+    );
+    assert_eq!(
+        calculate_time_weighted_fuel(value, TWENTY_FOUR_HOUR / 2, 20).unwrap(),
+        1_000 * QUOTE_PRECISION_U64
+    );
+    assert_eq!(calculate_time_weighted_fuel(value, 0, 10).unwrap(), 0);
+    assert_eq!(calculate_time_weighted_fuel(value, -1, 10).unwrap(), 0);
+}
+
+#[test]
+fn fuel_for_rate() {
+    let fuel_per_day = 1_000 * QUOTE_PRECISION_U64;
+
+    assert_eq!(
+        calculate_fuel_for_rate(fuel_per_day, TWENTY_FOUR_HOUR).unwrap(),
+        1_000 * QUOTE_PRECISION_U64
+    );
+    assert_eq!(
+        calculate_fuel_for_rate(fuel_per_day, TWENTY_FOUR_HOUR / 4).unwrap(),
+        250 * QUOTE_PRECISION_U64
+    );
+}
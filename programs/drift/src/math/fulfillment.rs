@@ -19,6 +19,7 @@ pub fn determine_perp_fulfillment_methods(
     amm_is_available: bool,
     slot: u64,
     min_auction_duration: u8,
+    is_prediction_market: bool,
 ) -> DriftResult<Vec<PerpFulfillmentMethod>> {
     let mut fulfillment_methods = Vec::with_capacity(8);
 
@@ -26,8 +27,13 @@ pub fn determine_perp_fulfillment_methods(
         && valid_oracle_price.is_some()
         && is_amm_available_liquidity_source(taker_order, min_auction_duration, slot)?;
 
-    let taker_price =
-        taker_order.get_limit_price(valid_oracle_price, None, slot, amm.order_tick_size)?;
+    let taker_price = taker_order.get_limit_price(
+        valid_oracle_price,
+        None,
+        slot,
+        amm.order_tick_size,
+        is_prediction_market,
+    )?;
 
     let maker_direction = taker_order.direction.opposite();
 
@@ -92,6 +98,8 @@ pub fn determine_spot_fulfillment_methods(
     taker_order: &Order,
     maker_available: bool,
     serum_fulfillment_params_available: bool,
+    openbook_v2_fulfillment_params_available: bool,
+    phoenix_fulfillment_params_available: bool,
 ) -> DriftResult<Vec<SpotFulfillmentMethod>> {
     let mut fulfillment_methods = vec![];
 
@@ -103,5 +111,13 @@ pub fn determine_spot_fulfillment_methods(
         fulfillment_methods.push(SpotFulfillmentMethod::SerumV3)
     }
 
+    if !taker_order.post_only && openbook_v2_fulfillment_params_available {
+        fulfillment_methods.push(SpotFulfillmentMethod::OpenbookV2)
+    }
+
+    if !taker_order.post_only && phoenix_fulfillment_params_available {
+        fulfillment_methods.push(SpotFulfillmentMethod::PhoenixV1)
+    }
+
     Ok(fulfillment_methods)
 }
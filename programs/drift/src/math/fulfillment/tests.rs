@@ -62,6 +62,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -119,6 +120,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -188,6 +190,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -255,6 +258,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -327,6 +331,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -394,6 +399,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -463,6 +469,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -531,6 +538,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -598,6 +606,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -667,6 +676,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -727,6 +737,7 @@ mod determine_perp_fulfillment_methods {
             true,
             0,
             0,
+            false,
         )
         .unwrap();
 
@@ -1,5 +1,6 @@
 use std::cmp::{max, min};
 
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::msg;
 
 use crate::error::{DriftResult, ErrorCode};
@@ -7,12 +8,13 @@ use crate::math::bn;
 use crate::math::casting::Cast;
 use crate::math::constants::{
     AMM_TO_QUOTE_PRECISION_RATIO, AMM_TO_QUOTE_PRECISION_RATIO_I128, FUNDING_RATE_BUFFER,
-    ONE_HOUR_I128, PRICE_PRECISION, QUOTE_TO_BASE_AMT_FUNDING_PRECISION,
+    ONE_HOUR_I128, PERCENTAGE_PRECISION_I128, PRICE_PRECISION, QUOTE_TO_BASE_AMT_FUNDING_PRECISION,
 };
+use crate::math::helpers::on_the_hour_update;
 use crate::math::repeg::{calculate_fee_pool, get_total_fee_lower_bound};
 use crate::math::safe_math::SafeMath;
 
-use crate::state::perp_market::PerpMarket;
+use crate::state::perp_market::{PerpMarket, AMM};
 use crate::state::user::PerpPosition;
 
 #[cfg(test)]
@@ -22,6 +24,23 @@ pub fn calculate_funding_rate(
     mid_price_twap: u128,
     oracle_price_twap: i128,
     funding_period: i64,
+) -> DriftResult<i128> {
+    // clamp price divergence to 3% for funding rate calculation
+    let max_price_spread = oracle_price_twap.safe_div(33)?; // 3%
+
+    calculate_funding_rate_with_max_price_spread(
+        mid_price_twap,
+        oracle_price_twap,
+        funding_period,
+        max_price_spread,
+    )
+}
+
+pub fn calculate_funding_rate_with_max_price_spread(
+    mid_price_twap: u128,
+    oracle_price_twap: i128,
+    funding_period: i64,
+    max_price_spread: i128,
 ) -> DriftResult<i128> {
     // funding period = 1 hour, window = 1 day
     // low periodicity => quickly updating/settled funding rates
@@ -32,8 +51,6 @@ pub fn calculate_funding_rate(
 
     let price_spread = mid_price_twap.cast::<i128>()?.safe_sub(oracle_price_twap)?;
 
-    // clamp price divergence to 3% for funding rate calculation
-    let max_price_spread = oracle_price_twap.safe_div(33)?; // 3%
     let clamped_price_spread = max(-max_price_spread, min(price_spread, max_price_spread));
 
     let funding_rate = clamped_price_spread
@@ -43,6 +60,87 @@ pub fn calculate_funding_rate(
     Ok(funding_rate)
 }
 
+/// Max mark/oracle twap spread funding is paid on along with the updated count of consecutive
+/// funding periods the spread has exceeded the base cap. The escalated cap only applies once
+/// that count reaches the market's escalation periods
+pub fn calculate_max_funding_price_spread(
+    amm: &AMM,
+    oracle_price_twap: i64,
+    price_spread: i64,
+) -> DriftResult<(i64, u8)> {
+    let base_max_price_spread = if amm.max_funding_rate_spread == 0 {
+        oracle_price_twap.safe_div(33)? // 3%
+    } else {
+        oracle_price_twap
+            .cast::<i128>()?
+            .safe_mul(amm.max_funding_rate_spread.cast()?)?
+            .safe_div(PERCENTAGE_PRECISION_I128)?
+            .cast()?
+    };
+
+    let divergence_period_count =
+        if price_spread.unsigned_abs() > base_max_price_spread.unsigned_abs() {
+            amm.funding_divergence_period_count.saturating_add(1)
+        } else {
+            0
+        };
+
+    let escalated = amm.funding_divergence_escalation_periods > 0
+        && amm.escalated_max_funding_rate_spread > 0
+        && divergence_period_count >= amm.funding_divergence_escalation_periods;
+
+    let max_price_spread = if escalated {
+        oracle_price_twap
+            .cast::<i128>()?
+            .safe_mul(amm.escalated_max_funding_rate_spread.cast()?)?
+            .safe_div(PERCENTAGE_PRECISION_I128)?
+            .cast::<i64>()?
+            .max(base_max_price_spread)
+    } else {
+        base_max_price_spread
+    };
+
+    Ok((max_price_spread, divergence_period_count))
+}
+
+/// Funding rate the next update would settle at given the current mark and oracle twaps
+pub fn calculate_predicted_funding_rate(market: &PerpMarket) -> DriftResult<i64> {
+    let mid_price_twap = market.amm.last_mark_price_twap;
+    let oracle_price_twap = market.amm.historical_oracle_data.last_oracle_price_twap;
+    let price_spread = mid_price_twap.cast::<i64>()?.safe_sub(oracle_price_twap)?;
+
+    let (max_price_spread, _) =
+        calculate_max_funding_price_spread(&market.amm, oracle_price_twap, price_spread)?;
+
+    calculate_funding_rate_with_max_price_spread(
+        mid_price_twap.cast()?,
+        oracle_price_twap.cast()?,
+        market.amm.funding_period,
+        max_price_spread.cast()?,
+    )?
+    .cast()
+}
+
+#[derive(Default, Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub struct PredictedFunding {
+    pub funding_rate: i64, // precision: FUNDING_RATE_PRECISION
+    pub next_funding_rate_ts: i64,
+}
+
+/// Predicted funding rate along with the earliest time the next funding update can land
+pub fn calculate_predicted_funding(market: &PerpMarket, now: i64) -> DriftResult<PredictedFunding> {
+    let time_until_next_update = on_the_hour_update(
+        now,
+        market.amm.last_funding_rate_ts,
+        market.amm.funding_period,
+    )?;
+
+    Ok(PredictedFunding {
+        funding_rate: calculate_predicted_funding_rate(market)?,
+        next_funding_rate_ts: now.safe_add(time_until_next_update)?,
+    })
+}
+
 /// With a virtual AMM, there can be an imbalance between longs and shorts and thus funding can be asymmetric.
 /// To account for this, amm keeps track of the cumulative funding rate for both longs and shorts.
 /// When there is a period with asymmetric funding, the protocol will pay/receive funding from/to it's collected fees.
@@ -1,5 +1,6 @@
 use crate::math::constants::{
-    AMM_RESERVE_PRECISION, PRICE_PRECISION, PRICE_PRECISION_U64, QUOTE_PRECISION,
+    AMM_RESERVE_PRECISION, PRICE_PRECISION, PRICE_PRECISION_I64, PRICE_PRECISION_U64,
+    QUOTE_PRECISION,
 };
 use crate::math::funding::*;
 use crate::state::oracle::HistoricalOracleData;
@@ -446,3 +447,122 @@ fn funding_unsettled_lps_amm_lose_test() {
     let new_fees = market.amm.total_fee_minus_distributions;
     assert_eq!(new_fees, 416667); // lost
 }
+
+#[test]
+fn predicted_funding_rate_test() {
+    let mut market = PerpMarket {
+        amm: AMM {
+            last_mark_price_twap: 101 * PRICE_PRECISION_U64,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price_twap: 100 * PRICE_PRECISION_I64,
+                ..HistoricalOracleData::default()
+            },
+            funding_period: 3600,
+            ..AMM::default()
+        },
+        ..PerpMarket::default()
+    };
+
+    // mark above oracle, longs pay
+    let predicted_funding_rate = calculate_predicted_funding_rate(&market).unwrap();
+    assert_eq!(predicted_funding_rate, 41_666_666);
+
+    // mark below oracle, shorts pay
+    market.amm.last_mark_price_twap = 99 * PRICE_PRECISION_U64;
+    let predicted_funding_rate = calculate_predicted_funding_rate(&market).unwrap();
+    assert_eq!(predicted_funding_rate, -41_666_666);
+
+    // spread clamped to 3% of oracle twap
+    market.amm.last_mark_price_twap = 110 * PRICE_PRECISION_U64;
+    let predicted_funding_rate = calculate_predicted_funding_rate(&market).unwrap();
+    assert_eq!(predicted_funding_rate, 126_262_625);
+}
+
+#[test]
+fn escalated_funding_rate_cap_test() {
+    let mut market = PerpMarket {
+        amm: AMM {
+            max_funding_rate_spread: 10_000,           // 1%
+            escalated_max_funding_rate_spread: 30_000, // 3%
+            funding_divergence_escalation_periods: 3,
+            ..AMM::default()
+        },
+        ..PerpMarket::default()
+    };
+
+    let oracle_price_twap = 100 * PRICE_PRECISION_I64;
+
+    // spread within the base cap resets the divergence count
+    market.amm.funding_divergence_period_count = 2;
+    let (max_price_spread, count) =
+        calculate_max_funding_price_spread(&market.amm, oracle_price_twap, PRICE_PRECISION_I64 / 2)
+            .unwrap();
+    assert_eq!(max_price_spread, PRICE_PRECISION_I64);
+    assert_eq!(count, 0);
+
+    // divergence below the escalation periods stays at the base cap
+    market.amm.funding_divergence_period_count = 1;
+    let (max_price_spread, count) = calculate_max_funding_price_spread(
+        &market.amm,
+        oracle_price_twap,
+        -5 * PRICE_PRECISION_I64,
+    )
+    .unwrap();
+    assert_eq!(max_price_spread, PRICE_PRECISION_I64);
+    assert_eq!(count, 2);
+
+    // persistent divergence escalates the cap
+    market.amm.funding_divergence_period_count = count;
+    let (max_price_spread, count) = calculate_max_funding_price_spread(
+        &market.amm,
+        oracle_price_twap,
+        -5 * PRICE_PRECISION_I64,
+    )
+    .unwrap();
+    assert_eq!(max_price_spread, 3 * PRICE_PRECISION_I64);
+    assert_eq!(count, 3);
+
+    // predicted funding uses the escalated cap
+    market.amm.funding_divergence_period_count = 2;
+    market.amm.funding_period = 3600;
+    market.amm.last_mark_price_twap = 110 * PRICE_PRECISION_U64;
+    market.amm.historical_oracle_data.last_oracle_price_twap = oracle_price_twap;
+    let predicted_funding_rate = calculate_predicted_funding_rate(&market).unwrap();
+    assert_eq!(predicted_funding_rate, 125_000_000);
+
+    // without escalation periods the base cap always applies
+    market.amm.funding_divergence_escalation_periods = 0;
+    let predicted_funding_rate = calculate_predicted_funding_rate(&market).unwrap();
+    assert_eq!(predicted_funding_rate, 41_666_666);
+}
+
+#[test]
+fn predicted_funding_test() {
+    let market = PerpMarket {
+        amm: AMM {
+            last_mark_price_twap: 101 * PRICE_PRECISION_U64,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price_twap: 100 * PRICE_PRECISION_I64,
+                ..HistoricalOracleData::default()
+            },
+            funding_period: 3600,
+            last_funding_rate_ts: 3600,
+            ..AMM::default()
+        },
+        ..PerpMarket::default()
+    };
+
+    // mid period, next update on the next hour
+    let predicted_funding = calculate_predicted_funding(&market, 3600 + 1200).unwrap();
+    assert_eq!(
+        predicted_funding,
+        PredictedFunding {
+            funding_rate: 41_666_666,
+            next_funding_rate_ts: 7200,
+        }
+    );
+
+    // update already due
+    let predicted_funding = calculate_predicted_funding(&market, 7200).unwrap();
+    assert_eq!(predicted_funding.next_funding_rate_ts, 7200);
+}
@@ -2,6 +2,7 @@ use solana_program::msg;
 
 use crate::error::{DriftResult, ErrorCode};
 use crate::math::casting::Cast;
+use crate::math::constants::{ONE_YEAR, PERCENTAGE_PRECISION};
 use crate::math::helpers::{get_proportion_u128, log10_iter};
 use crate::math::safe_math::SafeMath;
 
@@ -116,3 +117,20 @@ pub fn calculate_if_shares_lost(
 
     Ok(if_shares_lost)
 }
+
+/// simple interest on the insurance fund's loan to a fee pool since it last accrued
+pub fn calculate_insurance_fund_lend_interest(
+    insurance_fund_lent: u64,
+    insurance_fund_lend_rate: u32,
+    last_ts: i64,
+    now: i64,
+) -> DriftResult<u64> {
+    let time_since_last_accrual = now.safe_sub(last_ts)?.max(0);
+
+    insurance_fund_lent
+        .cast::<u128>()?
+        .safe_mul(insurance_fund_lend_rate.cast()?)?
+        .safe_mul(time_since_last_accrual.cast()?)?
+        .safe_div(PERCENTAGE_PRECISION.safe_mul(ONE_YEAR)?)?
+        .cast()
+}
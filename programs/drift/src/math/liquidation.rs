@@ -3,8 +3,9 @@ use crate::math::casting::Cast;
 use crate::math::constants::{
     AMM_RESERVE_PRECISION_I128, FUNDING_RATE_TO_QUOTE_PRECISION_PRECISION_RATIO,
     LIQUIDATION_FEE_PRECISION, LIQUIDATION_FEE_PRECISION_U128,
-    LIQUIDATION_FEE_TO_MARGIN_PRECISION_RATIO, LIQUIDATION_PCT_PRECISION, PRICE_PRECISION,
-    PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO, QUOTE_PRECISION, SPOT_WEIGHT_PRECISION_U128,
+    LIQUIDATION_FEE_TO_MARGIN_PRECISION_RATIO, LIQUIDATION_PCT_PRECISION, PERCENTAGE_PRECISION,
+    PRICE_PRECISION, PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO, QUOTE_PRECISION,
+    SPOT_WEIGHT_PRECISION_U128,
 };
 use crate::math::margin::{
     calculate_margin_requirement_and_total_collateral, MarginRequirementType,
@@ -257,6 +258,61 @@ pub fn calculate_liquidation_multiplier(
     }
 }
 
+/// Liquidator fee grows linearly from 0 to the market's max fee over liquidation_fee_ramp_duration
+/// slots after the user enters liquidation, so liquidators are only paid what it takes to clear the account
+pub fn calculate_dutch_auction_liquidation_fee(
+    max_liquidation_fee: u32,
+    user: &User,
+    slot: u64,
+    liquidation_fee_ramp_duration: u128,
+) -> DriftResult<u32> {
+    if liquidation_fee_ramp_duration == 0 {
+        return Ok(max_liquidation_fee);
+    }
+
+    // a user not yet being liquidated enters liquidation this slot
+    let slots_elapsed = if user.is_being_liquidated() {
+        slot.saturating_sub(user.last_active_slot)
+    } else {
+        0
+    };
+
+    max_liquidation_fee
+        .cast::<u128>()?
+        .safe_mul(
+            slots_elapsed
+                .cast::<u128>()?
+                .min(liquidation_fee_ramp_duration),
+        )?
+        .safe_div(liquidation_fee_ramp_duration)?
+        .cast()
+}
+
+/// Once the liquidator fee has fully ramped, keep shifting the insurance fund's cut to the liquidator
+/// over liquidation_stale_fee_escalation_duration slots so stale accounts stay worth clearing
+pub fn calculate_stale_liquidation_fee_bonus(
+    if_liquidation_fee: u32,
+    user: &User,
+    slot: u64,
+    liquidation_fee_ramp_duration: u128,
+    liquidation_stale_fee_escalation_duration: u128,
+) -> DriftResult<u32> {
+    if liquidation_stale_fee_escalation_duration == 0 || !user.is_being_liquidated() {
+        return Ok(0);
+    }
+
+    let slots_stale = slot
+        .saturating_sub(user.last_active_slot)
+        .cast::<u128>()?
+        .saturating_sub(liquidation_fee_ramp_duration);
+
+    if_liquidation_fee
+        .cast::<u128>()?
+        .safe_mul(slots_stale.min(liquidation_stale_fee_escalation_duration))?
+        .safe_div(liquidation_stale_fee_escalation_duration)?
+        .cast()
+}
+
 pub fn calculate_funding_rate_deltas_to_resolve_bankruptcy(
     loss: i128,
     market: &PerpMarket,
@@ -279,6 +335,68 @@ pub fn calculate_funding_rate_deltas_to_resolve_bankruptcy(
         .safe_mul(FUNDING_RATE_TO_QUOTE_PRECISION_PRECISION_RATIO.cast()?)
 }
 
+/// Ranks positions for auto-deleveraging: pnl as a pct of entry times leverage.
+/// Keepers should target the highest score first. precision: PERCENTAGE_PRECISION
+pub fn calculate_auto_deleverage_score(
+    unrealized_pnl: i128,
+    quote_entry_amount: i64,
+    base_asset_value: u128,
+    total_collateral: i128,
+) -> DriftResult<u128> {
+    if unrealized_pnl <= 0 {
+        return Ok(0);
+    }
+
+    if quote_entry_amount == 0 || total_collateral <= 0 {
+        return Ok(u128::MAX);
+    }
+
+    let pnl_pct = unrealized_pnl
+        .unsigned_abs()
+        .safe_mul(PERCENTAGE_PRECISION)?
+        .safe_div(quote_entry_amount.unsigned_abs().cast()?)?;
+
+    let leverage = base_asset_value
+        .safe_mul(PERCENTAGE_PRECISION)?
+        .safe_div(total_collateral.unsigned_abs())?;
+
+    pnl_pct.safe_mul(leverage)?.safe_div(PERCENTAGE_PRECISION)
+}
+
+/// The price at which closing the position brings the user's total collateral back to zero.
+/// Longs close above the oracle and shorts below it, so the counterparty absorbs the deficit.
+pub fn calculate_auto_deleverage_price(
+    oracle_price: i64,
+    base_asset_amount: i64,
+    deficit: u128,
+) -> DriftResult<i64> {
+    validate!(
+        base_asset_amount != 0,
+        ErrorCode::InvalidPerpPositionToAutoDeleverage,
+        "base_asset_amount must be non-zero"
+    )?;
+
+    let price_delta = deficit
+        .safe_mul(AMM_RESERVE_PRECISION_I128.cast()?)?
+        .safe_div_ceil(base_asset_amount.unsigned_abs().cast()?)?
+        .cast::<i64>()?;
+
+    let bankruptcy_price = if base_asset_amount > 0 {
+        oracle_price.safe_add(price_delta)?
+    } else {
+        oracle_price.safe_sub(price_delta)?
+    };
+
+    validate!(
+        bankruptcy_price > 0,
+        ErrorCode::InvalidPerpPositionToAutoDeleverage,
+        "bankruptcy price {} must be positive",
+        bankruptcy_price
+    )?;
+
+    Ok(bankruptcy_price)
+}
+
 pub fn calculate_cumulative_deposit_interest_delta_to_resolve_bankruptcy(
     borrow: u128,
     spot_market: &SpotMarket,
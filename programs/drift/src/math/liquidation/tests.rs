@@ -495,3 +495,175 @@ mod validate_transfer_satisfies_limit_price {
         .is_err());
     }
 }
+
+mod calculate_dutch_auction_liquidation_fee {
+    use crate::math::constants::LIQUIDATION_FEE_PRECISION;
+    use crate::math::liquidation::calculate_dutch_auction_liquidation_fee;
+    use crate::state::user::{User, UserStatus};
+
+    #[test]
+    fn fee_ramps_with_slots_in_liquidation() {
+        let max_liquidation_fee = LIQUIDATION_FEE_PRECISION / 100; // 1%
+
+        let mut user = User {
+            status: UserStatus::BeingLiquidated,
+            last_active_slot: 100,
+            ..User::default()
+        };
+
+        let fee =
+            calculate_dutch_auction_liquidation_fee(max_liquidation_fee, &user, 100, 150).unwrap();
+        assert_eq!(fee, 0);
+
+        let fee =
+            calculate_dutch_auction_liquidation_fee(max_liquidation_fee, &user, 175, 150).unwrap();
+        assert_eq!(fee, max_liquidation_fee / 2);
+
+        let fee =
+            calculate_dutch_auction_liquidation_fee(max_liquidation_fee, &user, 1000, 150).unwrap();
+        assert_eq!(fee, max_liquidation_fee);
+
+        // no ramp configured
+        let fee =
+            calculate_dutch_auction_liquidation_fee(max_liquidation_fee, &user, 100, 0).unwrap();
+        assert_eq!(fee, max_liquidation_fee);
+
+        // user entering liquidation this slot starts the ramp
+        user.status = UserStatus::Active;
+        let fee =
+            calculate_dutch_auction_liquidation_fee(max_liquidation_fee, &user, 1000, 150).unwrap();
+        assert_eq!(fee, 0);
+    }
+}
+
+mod calculate_stale_liquidation_fee_bonus {
+    use crate::math::constants::LIQUIDATION_FEE_PRECISION;
+    use crate::math::liquidation::calculate_stale_liquidation_fee_bonus;
+    use crate::state::user::{User, UserStatus};
+
+    #[test]
+    fn bonus_escalates_after_ramp() {
+        let if_liquidation_fee = LIQUIDATION_FEE_PRECISION / 100; // 1%
+
+        let mut user = User {
+            status: UserStatus::BeingLiquidated,
+            last_active_slot: 100,
+            ..User::default()
+        };
+
+        // still ramping the liquidator fee
+        let bonus =
+            calculate_stale_liquidation_fee_bonus(if_liquidation_fee, &user, 150, 50, 200).unwrap();
+        assert_eq!(bonus, 0);
+
+        let bonus =
+            calculate_stale_liquidation_fee_bonus(if_liquidation_fee, &user, 250, 50, 200).unwrap();
+        assert_eq!(bonus, if_liquidation_fee / 2);
+
+        let bonus =
+            calculate_stale_liquidation_fee_bonus(if_liquidation_fee, &user, 10000, 50, 200)
+                .unwrap();
+        assert_eq!(bonus, if_liquidation_fee);
+
+        // no escalation configured
+        let bonus =
+            calculate_stale_liquidation_fee_bonus(if_liquidation_fee, &user, 10000, 50, 0).unwrap();
+        assert_eq!(bonus, 0);
+
+        // user entering liquidation this slot isn't stale yet
+        user.status = UserStatus::Active;
+        let bonus =
+            calculate_stale_liquidation_fee_bonus(if_liquidation_fee, &user, 10000, 50, 200)
+                .unwrap();
+        assert_eq!(bonus, 0);
+    }
+}
+
+mod calculate_auto_deleverage_score {
+    use crate::math::constants::{PERCENTAGE_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64};
+    use crate::math::liquidation::calculate_auto_deleverage_score;
+
+    #[test]
+    fn score() {
+        // 100% pnl at 2x leverage
+        let score = calculate_auto_deleverage_score(
+            50 * QUOTE_PRECISION_I128,
+            -50 * QUOTE_PRECISION_I64,
+            100 * QUOTE_PRECISION_I128 as u128,
+            50 * QUOTE_PRECISION_I128,
+        )
+        .unwrap();
+        assert_eq!(score, 2 * PERCENTAGE_PRECISION);
+
+        // same pnl pct at 4x leverage ranks higher
+        let levered_score = calculate_auto_deleverage_score(
+            50 * QUOTE_PRECISION_I128,
+            -50 * QUOTE_PRECISION_I64,
+            100 * QUOTE_PRECISION_I128 as u128,
+            25 * QUOTE_PRECISION_I128,
+        )
+        .unwrap();
+        assert_eq!(levered_score, 4 * PERCENTAGE_PRECISION);
+
+        // losing positions are never targeted
+        let score = calculate_auto_deleverage_score(
+            -50 * QUOTE_PRECISION_I128,
+            -150 * QUOTE_PRECISION_I64,
+            100 * QUOTE_PRECISION_I128 as u128,
+            50 * QUOTE_PRECISION_I128,
+        )
+        .unwrap();
+        assert_eq!(score, 0);
+
+        let score = calculate_auto_deleverage_score(
+            50 * QUOTE_PRECISION_I128,
+            -50 * QUOTE_PRECISION_I64,
+            100 * QUOTE_PRECISION_I128 as u128,
+            0,
+        )
+        .unwrap();
+        assert_eq!(score, u128::MAX);
+    }
+}
+
+mod calculate_auto_deleverage_price {
+    use crate::error::ErrorCode;
+    use crate::math::constants::{BASE_PRECISION_I64, PRICE_PRECISION_I64, QUOTE_PRECISION};
+    use crate::math::liquidation::calculate_auto_deleverage_price;
+
+    #[test]
+    fn price() {
+        // long 10 with a $50 deficit closes $5 above the oracle
+        let price = calculate_auto_deleverage_price(
+            100 * PRICE_PRECISION_I64,
+            10 * BASE_PRECISION_I64,
+            50 * QUOTE_PRECISION,
+        )
+        .unwrap();
+        assert_eq!(price, 105 * PRICE_PRECISION_I64);
+
+        let price = calculate_auto_deleverage_price(
+            100 * PRICE_PRECISION_I64,
+            -10 * BASE_PRECISION_I64,
+            50 * QUOTE_PRECISION,
+        )
+        .unwrap();
+        assert_eq!(price, 95 * PRICE_PRECISION_I64);
+
+        // rounds so the user is always made whole
+        let price = calculate_auto_deleverage_price(
+            100 * PRICE_PRECISION_I64,
+            3 * BASE_PRECISION_I64,
+            QUOTE_PRECISION,
+        )
+        .unwrap();
+        assert_eq!(price, 100 * PRICE_PRECISION_I64 + 333_334);
+
+        let result = calculate_auto_deleverage_price(
+            PRICE_PRECISION_I64,
+            -BASE_PRECISION_I64,
+            2 * QUOTE_PRECISION,
+        );
+        assert_eq!(result, Err(ErrorCode::InvalidPerpPositionToAutoDeleverage));
+    }
+}
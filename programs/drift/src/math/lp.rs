@@ -1,9 +1,10 @@
 use crate::error::DriftResult;
 use crate::math::amm::calculate_market_open_bids_asks;
 use crate::math::casting::Cast;
-use crate::math::constants::AMM_RESERVE_PRECISION_I128;
+use crate::math::constants::{AMM_RESERVE_PRECISION_I128, ONE_BPS_DENOMINATOR};
 use crate::math::helpers;
 use crate::math::orders::standardize_base_asset_amount_with_remainder_i128;
+use crate::math::position::calculate_base_asset_value_with_oracle_price;
 use crate::math::safe_math::SafeMath;
 
 use crate::state::perp_market::PerpMarket;
@@ -81,3 +82,22 @@ pub fn calculate_lp_open_bids_asks(
 
     Ok((open_bids.cast()?, open_asks.cast()?))
 }
+
+/// Quote penalty owed for removing lp shares before the market's lp_min_duration has passed
+pub fn calculate_lp_early_removal_penalty(
+    market: &PerpMarket,
+    shares_to_burn: u64,
+    oracle_price: i64,
+    time_since_last_add_liquidity: i64,
+) -> DriftResult<u64> {
+    if market.lp_early_removal_penalty == 0
+        || time_since_last_add_liquidity >= market.lp_min_duration.cast()?
+    {
+        return Ok(0);
+    }
+
+    calculate_base_asset_value_with_oracle_price(shares_to_burn.cast()?, oracle_price)?
+        .safe_mul(market.lp_early_removal_penalty.cast()?)?
+        .safe_div(ONE_BPS_DENOMINATOR.cast()?)?
+        .cast()
+}
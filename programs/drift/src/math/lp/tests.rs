@@ -248,3 +248,51 @@ mod calculate_settle_lp_metrics {
         assert_eq!(lp_metrics.remainder_base_asset_amount, 1);
     }
 }
+
+mod calculate_lp_early_removal_penalty {
+    use crate::math::constants::{BASE_PRECISION_U64, PRICE_PRECISION_I64, QUOTE_PRECISION_U64};
+    use crate::math::lp::*;
+    use crate::state::perp_market::PerpMarket;
+
+    #[test]
+    fn test_penalty_only_within_min_duration() {
+        let market = PerpMarket {
+            lp_min_duration: 3600,
+            lp_early_removal_penalty: 50, // 50 bps
+            ..PerpMarket::default_test()
+        };
+
+        // 10 shares at $100 = $1000 notional
+        let penalty = calculate_lp_early_removal_penalty(
+            &market,
+            10 * BASE_PRECISION_U64,
+            100 * PRICE_PRECISION_I64,
+            60,
+        )
+        .unwrap();
+        assert_eq!(penalty, 5 * QUOTE_PRECISION_U64);
+
+        let penalty = calculate_lp_early_removal_penalty(
+            &market,
+            10 * BASE_PRECISION_U64,
+            100 * PRICE_PRECISION_I64,
+            3600,
+        )
+        .unwrap();
+        assert_eq!(penalty, 0);
+
+        // disabled
+        let market = PerpMarket {
+            lp_min_duration: 3600,
+            ..PerpMarket::default_test()
+        };
+        let penalty = calculate_lp_early_removal_penalty(
+            &market,
+            10 * BASE_PRECISION_U64,
+            100 * PRICE_PRECISION_I64,
+            60,
+        )
+        .unwrap();
+        assert_eq!(penalty, 0);
+    }
+}
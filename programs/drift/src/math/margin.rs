@@ -1,8 +1,9 @@
 use crate::error::DriftResult;
 use crate::error::ErrorCode;
 use crate::math::constants::{
-    MARGIN_PRECISION_U128, MAX_POSITIVE_UPNL_FOR_INITIAL_MARGIN, PRICE_PRECISION,
-    SPOT_IMF_PRECISION_U128, SPOT_WEIGHT_PRECISION, SPOT_WEIGHT_PRECISION_U128,
+    AMM_RESERVE_PRECISION_I128, MARGIN_PRECISION_U128, MAX_POSITIVE_UPNL_FOR_INITIAL_MARGIN,
+    PRICE_PRECISION, PRICE_PRECISION_I64, QUOTE_PRECISION, SPOT_IMF_PRECISION_U128,
+    SPOT_WEIGHT_PRECISION, SPOT_WEIGHT_PRECISION_U128,
 };
 use crate::math::position::{
     calculate_base_asset_value_and_pnl_with_oracle_price,
@@ -24,11 +25,12 @@ use crate::math::spot_balance::{
 use crate::math::safe_math::SafeMath;
 use crate::state::oracle::OraclePriceData;
 use crate::state::oracle_map::OracleMap;
-use crate::state::perp_market::{ContractTier, MarketStatus, PerpMarket};
+use crate::state::perp_market::{ContractTier, ContractType, MarginTier, MarketStatus, PerpMarket};
 use crate::state::perp_market_map::PerpMarketMap;
 use crate::state::spot_market::{AssetTier, SpotBalanceType, SpotMarket};
 use crate::state::spot_market_map::SpotMarketMap;
 use crate::state::user::{PerpPosition, SpotPosition, User};
+use borsh::{BorshDeserialize, BorshSerialize};
 use num_integer::Roots;
 use solana_program::msg;
 use std::cmp::{max, min, Ordering};
@@ -127,12 +129,145 @@ pub fn calculate_spot_position_value(
     Ok(balance_equity_value)
 }
 
+/// Highest initial margin ratio among the tiers whose notional threshold the position has reached,
+/// 0 if it hasn't reached any
+pub fn calculate_margin_tier_ratio(
+    margin_tiers: &[MarginTier],
+    base_asset_value: u128,
+) -> DriftResult<u32> {
+    let mut margin_tier_ratio = 0_u32;
+    for margin_tier in margin_tiers.iter() {
+        if margin_tier.notional_threshold == 0 {
+            break;
+        }
+
+        let notional_threshold = margin_tier
+            .notional_threshold
+            .cast::<u128>()?
+            .safe_mul(QUOTE_PRECISION)?;
+
+        if base_asset_value < notional_threshold {
+            break;
+        }
+
+        margin_tier_ratio = margin_tier_ratio.max(margin_tier.margin_ratio_initial);
+    }
+
+    Ok(margin_tier_ratio)
+}
+
+/// Weighted collateral to exclude because a single asset provides more than its market's
+/// max_collateral_concentration share of the user's total spot collateral
+pub fn calculate_collateral_concentration_haircut(
+    concentrated_collateral: &[(u128, u32)],
+    total_spot_collateral: u128,
+) -> DriftResult<u128> {
+    let mut haircut = 0_u128;
+    for (weighted_token_value, max_collateral_concentration) in concentrated_collateral.iter() {
+        let max_weighted_token_value = total_spot_collateral
+            .safe_mul((*max_collateral_concentration).cast()?)?
+            .safe_div(SPOT_WEIGHT_PRECISION_U128)?;
+
+        haircut =
+            haircut.safe_add(weighted_token_value.saturating_sub(max_weighted_token_value))?;
+    }
+
+    Ok(haircut)
+}
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CorrelationGroupExposure {
+    pub correlation_group: u8,
+    pub long_notional: u128,
+    pub short_notional: u128,
+    pub margin_credit: u32,
+}
+
+/// Adds a position's signed notional to its correlation group. The group's credit is the smallest
+/// credit of the markets in it so one generous market can't raise the offset for the others
+pub fn update_correlation_group_exposures(
+    exposures: &mut Vec<CorrelationGroupExposure>,
+    correlation_group: u8,
+    correlation_margin_credit: u16,
+    signed_notional: i128,
+) -> DriftResult {
+    if correlation_group == 0 || correlation_margin_credit == 0 || signed_notional == 0 {
+        return Ok(());
+    }
+
+    let margin_credit = correlation_margin_credit.cast::<u32>()?;
+    let exposure_index = match exposures
+        .iter()
+        .position(|exposure| exposure.correlation_group == correlation_group)
+    {
+        Some(exposure_index) => exposure_index,
+        None => {
+            exposures.push(CorrelationGroupExposure {
+                correlation_group,
+                margin_credit,
+                ..CorrelationGroupExposure::default()
+            });
+            exposures.len() - 1
+        }
+    };
+
+    let exposure = &mut exposures[exposure_index];
+    exposure.margin_credit = exposure.margin_credit.min(margin_credit);
+
+    if signed_notional > 0 {
+        exposure.long_notional = exposure
+            .long_notional
+            .safe_add(signed_notional.unsigned_abs())?;
+    } else {
+        exposure.short_notional = exposure
+            .short_notional
+            .safe_add(signed_notional.unsigned_abs())?;
+    }
+
+    Ok(())
+}
+
+/// Margin credit for the notional that offsets between the long and short legs of each group
+pub fn calculate_correlation_margin_credit(
+    exposures: &[CorrelationGroupExposure],
+) -> DriftResult<u128> {
+    let mut margin_credit = 0_u128;
+    for exposure in exposures.iter() {
+        let offset_notional = exposure.long_notional.min(exposure.short_notional);
+
+        margin_credit = margin_credit.safe_add(
+            offset_notional
+                .safe_mul(exposure.margin_credit.cast()?)?
+                .safe_div(MARGIN_PRECISION_U128)?,
+        )?;
+    }
+
+    Ok(margin_credit)
+}
+
+/// prediction market positions are margined by their max loss at resolution:
+/// longs lose their value if it resolves at 0, shorts lose the rest of 1 if it resolves at 1
+pub fn calculate_prediction_market_margin_requirement(
+    base_asset_amount: i128,
+    price: i64,
+) -> DriftResult<u128> {
+    let price = price.max(0).min(PRICE_PRECISION_I64);
+    let max_loss_price = if base_asset_amount >= 0 {
+        price
+    } else {
+        PRICE_PRECISION_I64.safe_sub(price)?
+    };
+
+    calculate_base_asset_value_with_oracle_price(base_asset_amount, max_loss_price)
+}
+
 pub fn calculate_perp_position_value_and_pnl(
     market_position: &PerpPosition,
     market: &PerpMarket,
     oracle_price_data: &OraclePriceData,
     margin_requirement_type: MarginRequirementType,
     user_custom_margin_ratio: u32,
+    user_high_leverage_mode: bool,
     with_bounds: bool,
 ) -> DriftResult<(u128, i128, u128)> {
     let unrealized_funding = calculate_funding_payment(
@@ -204,13 +339,26 @@ pub fn calculate_perp_position_value_and_pnl(
         valuation_price,
     )?;
 
-    let margin_ratio = user_custom_margin_ratio.max(market.get_margin_ratio(
+    let mut margin_ratio = user_custom_margin_ratio.max(market.get_margin_ratio(
         worst_case_base_asset_amount.unsigned_abs(),
         margin_requirement_type,
+        user_high_leverage_mode,
     )?);
 
+    if margin_requirement_type == MarginRequirementType::Initial {
+        margin_ratio = margin_ratio.max(calculate_margin_tier_ratio(
+            &market.margin_tiers,
+            worse_case_base_asset_value,
+        )?);
+    }
+
     let mut margin_requirement = if market.status == MarketStatus::Settlement {
         0
+    } else if market.contract_type == ContractType::Prediction {
+        calculate_prediction_market_margin_requirement(
+            worst_case_base_asset_amount,
+            valuation_price,
+        )?
     } else {
         worse_case_base_asset_value
             .safe_mul(margin_ratio.cast()?)?
@@ -283,6 +431,9 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
     let mut num_spot_liabilities: u8 = 0;
     let mut num_perp_liabilities: u8 = 0;
     let mut with_isolated_liability: bool = false;
+    let mut total_spot_collateral: u128 = 0;
+    let mut concentrated_collateral: Vec<(u128, u32)> = vec![];
+    let mut correlation_group_exposures: Vec<CorrelationGroupExposure> = vec![];
 
     let user_custom_margin_ratio = if margin_requirement_type == MarginRequirementType::Initial {
         user.max_margin_ratio
@@ -301,6 +452,7 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
         let (oracle_price_data, oracle_validity) = oracle_map.get_price_data_and_validity(
             &spot_market.oracle,
             spot_market.historical_oracle_data.last_oracle_price_twap,
+            &spot_market.validity_guard_rails_override,
         )?;
         all_oracles_valid &=
             is_oracle_valid_for_action(oracle_validity, Some(DriftAction::MarginCalc))?;
@@ -318,7 +470,8 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
             }
             match spot_position.balance_type {
                 SpotBalanceType::Deposit => {
-                    total_collateral = total_collateral.safe_add(token_amount.cast::<i128>()?)?
+                    total_collateral = total_collateral.safe_add(token_amount.cast::<i128>()?)?;
+                    total_spot_collateral = total_spot_collateral.safe_add(token_amount)?;
                 }
                 SpotBalanceType::Borrow => {
                     let liability_weight = user_custom_margin_ratio.max(SPOT_WEIGHT_PRECISION);
@@ -406,6 +559,13 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
             let worst_case_token_value =
                 signed_token_value.safe_add(worst_cast_quote_token_amount.neg())?;
 
+            update_correlation_group_exposures(
+                &mut correlation_group_exposures,
+                spot_market.correlation_group,
+                spot_market.correlation_margin_credit,
+                signed_token_value,
+            )?;
+
             margin_requirement =
                 margin_requirement.safe_add(spot_position.margin_requirement_for_open_orders()?)?;
 
@@ -425,6 +585,14 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
 
                     total_collateral =
                         total_collateral.safe_add(weighted_token_value.cast::<i128>()?)?;
+                    total_spot_collateral = total_spot_collateral.safe_add(weighted_token_value)?;
+
+                    if spot_market.max_collateral_concentration > 0 {
+                        concentrated_collateral.push((
+                            weighted_token_value,
+                            spot_market.max_collateral_concentration,
+                        ));
+                    }
                 }
                 Ordering::Less => {
                     let liability_weight =
@@ -478,7 +646,9 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
             match worst_cast_quote_token_amount.cmp(&0) {
                 Ordering::Greater => {
                     total_collateral =
-                        total_collateral.safe_add(worst_cast_quote_token_amount.cast::<i128>()?)?
+                        total_collateral.safe_add(worst_cast_quote_token_amount.cast::<i128>()?)?;
+                    total_spot_collateral = total_spot_collateral
+                        .safe_add(worst_cast_quote_token_amount.unsigned_abs())?;
                 }
                 Ordering::Less => {
                     let liability_weight = user_custom_margin_ratio.max(SPOT_WEIGHT_PRECISION);
@@ -504,8 +674,17 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
         }
     }
 
+    if !concentrated_collateral.is_empty() {
+        let collateral_concentration_haircut = calculate_collateral_concentration_haircut(
+            &concentrated_collateral,
+            total_spot_collateral,
+        )?;
+        total_collateral = total_collateral.safe_sub(collateral_concentration_haircut.cast()?)?;
+    }
+
     for market_position in user.perp_positions.iter() {
-        if market_position.is_available() {
+        // isolated positions are margined against their own collateral
+        if market_position.is_available() || market_position.is_isolated {
             continue;
         }
 
@@ -514,6 +693,7 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
         let (oracle_price_data, oracle_validity) = oracle_map.get_price_data_and_validity(
             &market.amm.oracle,
             market.amm.historical_oracle_data.last_oracle_price_twap,
+            &market.amm.validity_guard_rails_override,
         )?;
         all_oracles_valid &=
             is_oracle_valid_for_action(oracle_validity, Some(DriftAction::MarginCalc))?;
@@ -525,6 +705,7 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
                 oracle_price_data,
                 margin_requirement_type,
                 user_custom_margin_ratio,
+                user.is_high_leverage_mode_for(market_position.market_index),
                 true,
             )?;
 
@@ -542,6 +723,25 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
 
         total_collateral = total_collateral.safe_add(weighted_pnl)?;
 
+        if market.correlation_group != 0 {
+            let base_asset_value = calculate_base_asset_value_with_oracle_price(
+                market_position.base_asset_amount.cast()?,
+                oracle_price_data.price,
+            )?
+            .cast::<i128>()?;
+
+            update_correlation_group_exposures(
+                &mut correlation_group_exposures,
+                market.correlation_group,
+                market.correlation_margin_credit,
+                if market_position.base_asset_amount > 0 {
+                    base_asset_value
+                } else {
+                    base_asset_value.neg()
+                },
+            )?;
+        }
+
         if market_position.base_asset_amount != 0
             || market_position.quote_asset_amount < 0
             || market_position.has_open_order()
@@ -553,6 +753,19 @@ pub fn calculate_margin_requirement_and_total_collateral_and_liability_info(
             margin_requirement > 0 && market.contract_tier == ContractTier::Isolated;
     }
 
+    if !correlation_group_exposures.is_empty() {
+        let correlation_margin_credit =
+            calculate_correlation_margin_credit(&correlation_group_exposures)?;
+
+        margin_requirement =
+            margin_requirement.safe_sub(correlation_margin_credit.min(margin_requirement))?;
+
+        if margin_buffer_ratio.is_some() {
+            margin_requirement_plus_buffer = margin_requirement_plus_buffer
+                .safe_sub(correlation_margin_credit.min(margin_requirement_plus_buffer))?;
+        }
+    }
+
     if num_spot_liabilities > 0 {
         validate!(
             margin_requirement > 0,
@@ -606,6 +819,105 @@ pub fn calculate_margin_requirement_and_total_collateral(
     ))
 }
 
+pub fn calculate_isolated_perp_position_margin_requirement_and_total_collateral(
+    user: &User,
+    market_index: u16,
+    perp_market_map: &PerpMarketMap,
+    margin_requirement_type: MarginRequirementType,
+    oracle_map: &mut OracleMap,
+    margin_buffer_ratio: Option<u128>,
+) -> DriftResult<(u128, i128, u128, bool)> {
+    let market_position = user.get_perp_position(market_index)?;
+
+    validate!(
+        market_position.is_isolated,
+        ErrorCode::InvalidIsolatedPerpPosition,
+        "perp position for market {} is not isolated",
+        market_index
+    )?;
+
+    let user_custom_margin_ratio = if margin_requirement_type == MarginRequirementType::Initial {
+        user.max_margin_ratio
+    } else {
+        0_u32
+    };
+
+    let market = &perp_market_map.get_ref(&market_index)?;
+
+    let (oracle_price_data, oracle_validity) = oracle_map.get_price_data_and_validity(
+        &market.amm.oracle,
+        market.amm.historical_oracle_data.last_oracle_price_twap,
+        &market.amm.validity_guard_rails_override,
+    )?;
+    let oracle_valid = is_oracle_valid_for_action(oracle_validity, Some(DriftAction::MarginCalc))?;
+
+    let (margin_requirement, weighted_pnl, worst_case_base_asset_value) =
+        calculate_perp_position_value_and_pnl(
+            market_position,
+            market,
+            oracle_price_data,
+            margin_requirement_type,
+            user_custom_margin_ratio,
+            user.is_high_leverage_mode_for(market_position.market_index),
+            true,
+        )?;
+
+    let margin_requirement_plus_buffer = match margin_buffer_ratio {
+        Some(margin_buffer_ratio) => calculate_margin_requirement_with_buffer(
+            margin_requirement,
+            worst_case_base_asset_value,
+            margin_buffer_ratio,
+        )?,
+        None => 0,
+    };
+
+    let total_collateral = market_position
+        .isolated_collateral
+        .cast::<i128>()?
+        .safe_add(weighted_pnl)?;
+
+    Ok((
+        margin_requirement,
+        total_collateral,
+        margin_requirement_plus_buffer,
+        oracle_valid,
+    ))
+}
+
+/// Always true for a cross position, otherwise whether the isolated collateral covers the
+/// position's requirement (plus buffer when one is given)
+pub fn meets_isolated_perp_position_margin_requirement(
+    user: &User,
+    market_index: u16,
+    perp_market_map: &PerpMarketMap,
+    margin_requirement_type: MarginRequirementType,
+    oracle_map: &mut OracleMap,
+    margin_buffer_ratio: Option<u128>,
+) -> DriftResult<bool> {
+    match user.get_perp_position(market_index) {
+        Ok(market_position) if market_position.is_isolated => {}
+        _ => return Ok(true),
+    }
+
+    let (margin_requirement, total_collateral, margin_requirement_plus_buffer, _) =
+        calculate_isolated_perp_position_margin_requirement_and_total_collateral(
+            user,
+            market_index,
+            perp_market_map,
+            margin_requirement_type,
+            oracle_map,
+            margin_buffer_ratio,
+        )?;
+
+    let margin_requirement = if margin_buffer_ratio.is_some() {
+        margin_requirement_plus_buffer
+    } else {
+        margin_requirement
+    };
+
+    Ok(total_collateral >= margin_requirement.cast::<i128>()?)
+}
+
 pub fn meets_withdraw_margin_requirement(
     user: &User,
     perp_market_map: &PerpMarketMap,
@@ -707,6 +1019,335 @@ pub fn meets_place_order_margin_requirement(
     Ok(true)
 }
 
+#[derive(Default, Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub struct MarginHealth {
+    pub initial_total_collateral: i128,
+    pub initial_margin_requirement: u128,
+    pub maintenance_total_collateral: i128,
+    pub maintenance_margin_requirement: u128,
+    pub liquidation_price: i64, // -1 if no liquidation price for the market. precision: PRICE_PRECISION
+}
+
+/// 100 with no margin requirement, 0 once collateral no longer covers it. Linear, unlike the
+/// log scaled health the sdk displays
+pub fn calculate_health(total_collateral: i128, margin_requirement: u128) -> DriftResult<u8> {
+    if margin_requirement == 0 && total_collateral >= 0 {
+        return Ok(100);
+    }
+
+    if total_collateral <= 0 {
+        return Ok(0);
+    }
+
+    let total_collateral = total_collateral.unsigned_abs();
+    if margin_requirement >= total_collateral {
+        return Ok(0);
+    }
+
+    total_collateral
+        .safe_sub(margin_requirement)?
+        .safe_mul(100)?
+        .safe_div(total_collateral)?
+        .cast()
+}
+
+/// Cross margin totals for both margin types plus the liquidation price of the given perp market
+pub fn calculate_margin_health(
+    user: &User,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+    perp_market_index: Option<u16>,
+) -> DriftResult<MarginHealth> {
+    let (initial_margin_requirement, initial_total_collateral, _, _) =
+        calculate_margin_requirement_and_total_collateral(
+            user,
+            perp_market_map,
+            MarginRequirementType::Initial,
+            spot_market_map,
+            oracle_map,
+            None,
+        )?;
+
+    let (maintenance_margin_requirement, maintenance_total_collateral, _, _) =
+        calculate_margin_requirement_and_total_collateral(
+            user,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            spot_market_map,
+            oracle_map,
+            None,
+        )?;
+
+    let liquidation_price = match perp_market_index {
+        Some(perp_market_index) => {
+            let free_collateral = calculate_perp_position_maintenance_free_collateral(
+                user,
+                perp_market_index,
+                perp_market_map,
+                oracle_map,
+                maintenance_total_collateral
+                    .safe_sub(maintenance_margin_requirement.cast::<i128>()?)?,
+            )?;
+
+            let perp_market = perp_market_map.get_ref(&perp_market_index)?;
+            let oracle_price = oracle_map.get_price_data(&perp_market.amm.oracle)?.price;
+
+            calculate_perp_liquidation_price(user, &perp_market, oracle_price, free_collateral)?
+        }
+        None => -1,
+    };
+
+    Ok(MarginHealth {
+        initial_total_collateral,
+        initial_margin_requirement,
+        maintenance_total_collateral,
+        maintenance_margin_requirement,
+        liquidation_price,
+    })
+}
+
+#[derive(Default, Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub struct SpotMarginContribution {
+    pub market_index: u16,
+    pub token_amount: i128,
+    pub oracle_price: i64,
+    /// asset weighted deposit value, negative for the liability weighted borrow value
+    pub weighted_value: i128,
+    pub open_orders_margin_requirement: u128,
+}
+
+#[derive(Default, Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub struct PerpMarginContribution {
+    pub market_index: u16,
+    pub base_asset_amount: i64,
+    pub quote_asset_amount: i64,
+    pub oracle_price: i64,
+    pub margin_requirement: u128,
+    pub weighted_pnl: i128,
+    pub is_isolated: bool,
+}
+
+/// Maintenance margin contribution of each open position, before collateral concentration
+/// haircuts and correlation credits are applied to the totals
+pub fn calculate_margin_contributions(
+    user: &User,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+) -> DriftResult<(Vec<SpotMarginContribution>, Vec<PerpMarginContribution>)> {
+    let mut spot_contributions = vec![];
+    for spot_position in user.spot_positions.iter() {
+        if spot_position.is_available() {
+            continue;
+        }
+
+        let spot_market = spot_market_map.get_ref(&spot_position.market_index)?;
+        let oracle_price_data = oracle_map.get_price_data(&spot_market.oracle)?;
+        let token_amount = spot_position.get_signed_token_amount(&spot_market)?;
+
+        let weighted_value = if spot_market.market_index == 0 {
+            token_amount
+        } else {
+            let value = calculate_spot_position_value(
+                spot_position,
+                &spot_market,
+                oracle_price_data,
+                MarginRequirementType::Maintenance,
+            )?
+            .cast::<i128>()?;
+
+            match spot_position.balance_type {
+                SpotBalanceType::Deposit => value,
+                SpotBalanceType::Borrow => value.neg(),
+            }
+        };
+
+        spot_contributions.push(SpotMarginContribution {
+            market_index: spot_position.market_index,
+            token_amount,
+            oracle_price: oracle_price_data.price,
+            weighted_value,
+            open_orders_margin_requirement: spot_position.margin_requirement_for_open_orders()?,
+        });
+    }
+
+    let mut perp_contributions = vec![];
+    for market_position in user.perp_positions.iter() {
+        if market_position.is_available() {
+            continue;
+        }
+
+        let market = perp_market_map.get_ref(&market_position.market_index)?;
+        let oracle_price_data = oracle_map.get_price_data(&market.amm.oracle)?;
+
+        let (margin_requirement, weighted_pnl, _) = calculate_perp_position_value_and_pnl(
+            market_position,
+            &market,
+            oracle_price_data,
+            MarginRequirementType::Maintenance,
+            0,
+            user.is_high_leverage_mode_for(market_position.market_index),
+            true,
+        )?;
+
+        perp_contributions.push(PerpMarginContribution {
+            market_index: market_position.market_index,
+            base_asset_amount: market_position.base_asset_amount,
+            quote_asset_amount: market_position.quote_asset_amount,
+            oracle_price: oracle_price_data.price,
+            margin_requirement,
+            weighted_pnl,
+            is_isolated: market_position.is_isolated,
+        });
+    }
+
+    Ok((spot_contributions, perp_contributions))
+}
+
+/// Maintenance free collateral that backs the user's position in the market, its own collateral for
+/// an isolated position and the cross margin free collateral otherwise
+pub fn calculate_perp_position_maintenance_free_collateral(
+    user: &User,
+    market_index: u16,
+    perp_market_map: &PerpMarketMap,
+    oracle_map: &mut OracleMap,
+    cross_maintenance_free_collateral: i128,
+) -> DriftResult<i128> {
+    match user.get_perp_position(market_index) {
+        Ok(perp_position) if perp_position.is_isolated => {}
+        _ => return Ok(cross_maintenance_free_collateral),
+    }
+
+    let (margin_requirement, total_collateral, _, _) =
+        calculate_isolated_perp_position_margin_requirement_and_total_collateral(
+            user,
+            market_index,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            oracle_map,
+            None,
+        )?;
+
+    total_collateral.safe_sub(margin_requirement.cast()?)
+}
+
+/// Oracle price at which maintenance free collateral reaches zero if only this market's price moves.
+/// For an isolated position the free collateral must be the position's own. Ignores pnl asset
+/// weights, -1 if a price move can't liquidate the position
+pub fn calculate_perp_liquidation_price(
+    user: &User,
+    perp_market: &PerpMarket,
+    oracle_price: i64,
+    maintenance_free_collateral: i128,
+) -> DriftResult<i64> {
+    let perp_position = match user.get_perp_position(perp_market.market_index) {
+        Ok(perp_position) => perp_position,
+        _ => return Ok(-1),
+    };
+
+    let base_asset_amount = perp_position.base_asset_amount.cast::<i128>()?;
+    if base_asset_amount == 0 {
+        return Ok(-1);
+    }
+
+    let margin_ratio = perp_market
+        .get_margin_ratio(
+            base_asset_amount.unsigned_abs(),
+            MarginRequirementType::Maintenance,
+            user.is_high_leverage_mode_for(perp_market.market_index),
+        )?
+        .cast::<i128>()?;
+
+    // free collateral change per unit of price move, relative to the base amount
+    let margin_ratio_delta = if base_asset_amount > 0 {
+        MARGIN_PRECISION_U128
+            .cast::<i128>()?
+            .safe_sub(margin_ratio)?
+    } else {
+        MARGIN_PRECISION_U128
+            .cast::<i128>()?
+            .safe_add(margin_ratio)?
+    };
+
+    if margin_ratio_delta <= 0 {
+        return Ok(-1);
+    }
+
+    let price_delta = maintenance_free_collateral
+        .safe_mul(AMM_RESERVE_PRECISION_I128)?
+        .safe_mul(MARGIN_PRECISION_U128.cast()?)?
+        .safe_div(base_asset_amount.safe_mul(margin_ratio_delta)?)?;
+
+    let liquidation_price = oracle_price.cast::<i128>()?.safe_sub(price_delta)?;
+
+    if liquidation_price <= 0 {
+        return Ok(-1);
+    }
+
+    liquidation_price.cast()
+}
+
+/// Stores the liquidation price of every open perp position given the user's maintenance free
+/// collateral, 0 for positions a price move can't liquidate. Goes stale as other prices move
+pub fn update_perp_position_liquidation_prices(
+    user: &mut User,
+    perp_market_map: &PerpMarketMap,
+    oracle_map: &mut OracleMap,
+    maintenance_free_collateral: i128,
+) -> DriftResult {
+    for position_index in 0..user.perp_positions.len() {
+        let perp_position = &user.perp_positions[position_index];
+        if !perp_position.is_open_position() {
+            user.perp_positions[position_index].liquidation_price = 0;
+            continue;
+        }
+
+        let market_index = perp_position.market_index;
+        let free_collateral = calculate_perp_position_maintenance_free_collateral(
+            user,
+            market_index,
+            perp_market_map,
+            oracle_map,
+            maintenance_free_collateral,
+        )?;
+
+        let perp_market = perp_market_map.get_ref(&market_index)?;
+        let oracle_price = oracle_map.get_price_data(&perp_market.amm.oracle)?.price;
+
+        let liquidation_price =
+            calculate_perp_liquidation_price(user, &perp_market, oracle_price, free_collateral)?;
+
+        user.perp_positions[position_index].liquidation_price = liquidation_price.max(0).cast()?;
+    }
+
+    Ok(())
+}
+
+pub fn calculate_and_update_perp_position_liquidation_prices(
+    user: &mut User,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+) -> DriftResult {
+    let (margin_requirement, total_collateral, _, _) =
+        calculate_margin_requirement_and_total_collateral(
+            user,
+            perp_market_map,
+            MarginRequirementType::Maintenance,
+            spot_market_map,
+            oracle_map,
+            None,
+        )?;
+
+    update_perp_position_liquidation_prices(
+        user,
+        perp_market_map,
+        oracle_map,
+        total_collateral.safe_sub(margin_requirement.cast()?)?,
+    )
+}
+
 pub fn meets_initial_margin_requirement(
     user: &User,
     perp_market_map: &PerpMarketMap,
@@ -763,6 +1404,97 @@ pub fn calculate_free_collateral(
     total_collateral.safe_sub(margin_requirement.cast::<i128>()?)
 }
 
+/// unweighted value of the user's spot balances plus perp pnl and unsettled funding at oracle
+/// prices, in quote. also returns whether all oracles were valid for a margin calc
+pub fn calculate_user_equity(
+    user: &User,
+    perp_market_map: &PerpMarketMap,
+    spot_market_map: &SpotMarketMap,
+    oracle_map: &mut OracleMap,
+) -> DriftResult<(i128, bool)> {
+    let mut equity: i128 = 0;
+    let mut all_oracles_valid = true;
+
+    for spot_position in user.spot_positions.iter() {
+        if spot_position.is_available() {
+            continue;
+        }
+
+        let spot_market = spot_market_map.get_ref(&spot_position.market_index)?;
+        let (oracle_price_data, oracle_validity) = oracle_map.get_price_data_and_validity(
+            &spot_market.oracle,
+            spot_market.historical_oracle_data.last_oracle_price_twap,
+            &spot_market.validity_guard_rails_override,
+        )?;
+        all_oracles_valid &=
+            is_oracle_valid_for_action(oracle_validity, Some(DriftAction::MarginCalc))?;
+
+        let token_value = get_token_value(
+            spot_position.get_signed_token_amount(&spot_market)?,
+            spot_market.decimals,
+            oracle_price_data.price,
+        )?;
+        equity = equity.safe_add(token_value)?;
+    }
+
+    for market_position in user.perp_positions.iter() {
+        if market_position.is_available() {
+            continue;
+        }
+
+        let market = perp_market_map.get_ref(&market_position.market_index)?;
+        let (oracle_price_data, oracle_validity) = oracle_map.get_price_data_and_validity(
+            &market.amm.oracle,
+            market.amm.historical_oracle_data.last_oracle_price_twap,
+            &market.amm.validity_guard_rails_override,
+        )?;
+        all_oracles_valid &=
+            is_oracle_valid_for_action(oracle_validity, Some(DriftAction::MarginCalc))?;
+
+        let unrealized_funding = calculate_funding_payment(
+            if market_position.base_asset_amount > 0 {
+                market.amm.cumulative_funding_rate_long
+            } else {
+                market.amm.cumulative_funding_rate_short
+            },
+            market_position,
+        )?;
+
+        let market_position = if market_position.is_lp() {
+            let lp_metrics = calculate_settle_lp_metrics(&market.amm, market_position)?;
+
+            PerpPosition {
+                base_asset_amount: market_position
+                    .base_asset_amount
+                    .safe_add(lp_metrics.base_asset_amount.cast()?)?,
+                quote_asset_amount: market_position
+                    .quote_asset_amount
+                    .safe_add(lp_metrics.quote_asset_amount.cast()?)?,
+                ..PerpPosition::default()
+            }
+        } else {
+            *market_position
+        };
+
+        let valuation_price = if market.status == MarketStatus::Settlement {
+            market.expiry_price
+        } else {
+            oracle_price_data.price
+        };
+
+        let (_, unrealized_pnl) = calculate_base_asset_value_and_pnl_with_oracle_price(
+            &market_position,
+            valuation_price,
+        )?;
+
+        equity = equity
+            .safe_add(unrealized_pnl)?
+            .safe_add(unrealized_funding.cast()?)?;
+    }
+
+    Ok((equity, all_oracles_valid))
+}
+
 pub fn calculate_max_withdrawable_amount(
     market_index: u16,
     user: &User,
@@ -5,12 +5,13 @@ mod test {
     use crate::amm::calculate_swap_output;
     use crate::controller::amm::SwapDirection;
     use crate::math::constants::{
-        AMM_RESERVE_PRECISION, PRICE_PRECISION, PRICE_PRECISION_U64, QUOTE_PRECISION,
-        QUOTE_PRECISION_I64, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
-        SPOT_IMF_PRECISION,
+        AMM_RESERVE_PRECISION, AMM_RESERVE_PRECISION_I128, PRICE_PRECISION, PRICE_PRECISION_I64,
+        PRICE_PRECISION_U64, QUOTE_PRECISION, QUOTE_PRECISION_I64, SPOT_BALANCE_PRECISION_U64,
+        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_IMF_PRECISION,
     };
     use crate::math::margin::{
-        calculate_perp_position_value_and_pnl, calculate_spot_position_value, MarginRequirementType,
+        calculate_perp_position_value_and_pnl, calculate_prediction_market_margin_requirement,
+        calculate_spot_position_value, MarginRequirementType,
     };
     use crate::math::position::{
         calculate_base_asset_value_and_pnl_with_oracle_price, calculate_position_pnl,
@@ -20,6 +21,31 @@ mod test {
     use crate::state::spot_market::{AssetTier, SpotBalanceType, SpotMarket};
     use crate::state::user::{PerpPosition, SpotPosition, User};
 
+    #[test]
+    fn prediction_market_margin_requirement() {
+        let price = PRICE_PRECISION_I64 * 3 / 10;
+
+        // long 10 at .30 can lose the .30 premium
+        let long_requirement =
+            calculate_prediction_market_margin_requirement(10 * AMM_RESERVE_PRECISION_I128, price)
+                .unwrap();
+        assert_eq!(long_requirement, 3 * QUOTE_PRECISION);
+
+        // short 10 at .30 can lose the other .70
+        let short_requirement =
+            calculate_prediction_market_margin_requirement(-10 * AMM_RESERVE_PRECISION_I128, price)
+                .unwrap();
+        assert_eq!(short_requirement, 7 * QUOTE_PRECISION);
+
+        // prices outside [0, 1] are bounded
+        let short_requirement = calculate_prediction_market_margin_requirement(
+            -10 * AMM_RESERVE_PRECISION_I128,
+            2 * PRICE_PRECISION_I64,
+        )
+        .unwrap();
+        assert_eq!(short_requirement, 0);
+    }
+
     #[test]
     fn asset_tier_checks() {
         // first is as safe or safer
@@ -197,6 +223,7 @@ mod test {
             MarginRequirementType::Initial,
             0,
             false,
+            false,
         )
         .unwrap();
 
@@ -311,6 +338,7 @@ mod test {
             MarginRequirementType::Initial,
             0,
             false,
+            false,
         )
         .unwrap();
 
@@ -387,6 +415,7 @@ mod test {
             MarginRequirementType::Initial,
             0,
             false,
+            false,
         )
         .unwrap();
 
@@ -449,6 +478,7 @@ mod test {
             MarginRequirementType::Initial,
             0,
             false,
+            false,
         )
         .unwrap();
 
@@ -472,6 +502,7 @@ mod test {
             MarginRequirementType::Initial,
             0,
             false,
+            false,
         )
         .unwrap();
 
@@ -518,6 +549,7 @@ mod test {
             MarginRequirementType::Initial,
             0,
             false,
+            false,
         )
         .unwrap();
 
@@ -540,6 +572,7 @@ mod test {
             MarginRequirementType::Initial,
             0,
             false,
+            false,
         )
         .unwrap();
 
@@ -559,7 +592,7 @@ mod calculate_margin_requirement_and_total_collateral {
     use crate::create_anchor_account_info;
     use crate::math::constants::{
         AMM_RESERVE_PRECISION, BASE_PRECISION_I64, LIQUIDATION_FEE_PRECISION, MARGIN_PRECISION,
-        PEG_PRECISION, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+        MAX_USER_ORDERS, PEG_PRECISION, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
         SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::math::margin::{
@@ -642,7 +675,7 @@ mod calculate_margin_requirement_and_total_collateral {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -730,7 +763,7 @@ mod calculate_margin_requirement_and_total_collateral {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -819,7 +852,7 @@ mod calculate_margin_requirement_and_total_collateral {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -927,7 +960,7 @@ mod calculate_margin_requirement_and_total_collateral {
         };
 
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 base_asset_amount: 100 * BASE_PRECISION_I64,
@@ -1085,7 +1118,7 @@ mod calculate_margin_requirement_and_total_collateral {
         };
 
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             spot_positions,
             max_margin_ratio: 2 * MARGIN_PRECISION as u32, // .5x leverage
             ..User::default()
@@ -1202,7 +1235,7 @@ mod calculate_margin_requirement_and_total_collateral {
         };
 
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             spot_positions,
             max_margin_ratio: 2 * MARGIN_PRECISION as u32, // .5x leverage
             ..User::default()
@@ -1542,8 +1575,8 @@ mod calculate_max_withdrawable_amount {
     use crate::create_account_info;
     use crate::create_anchor_account_info;
     use crate::math::constants::{
-        LIQUIDATION_FEE_PRECISION, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
-        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+        LIQUIDATION_FEE_PRECISION, MAX_USER_ORDERS, SPOT_BALANCE_PRECISION,
+        SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::math::margin::calculate_max_withdrawable_amount;
     use crate::state::oracle::OracleSource;
@@ -1622,7 +1655,7 @@ mod calculate_max_withdrawable_amount {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -1709,7 +1742,7 @@ mod calculate_max_withdrawable_amount {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -1796,7 +1829,7 @@ mod calculate_max_withdrawable_amount {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -1826,8 +1859,8 @@ mod validate_spot_margin_trading {
     use crate::create_anchor_account_info;
     use crate::error::ErrorCode;
     use crate::math::constants::{
-        LIQUIDATION_FEE_PRECISION, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
-        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+        LIQUIDATION_FEE_PRECISION, MAX_USER_ORDERS, SPOT_BALANCE_PRECISION,
+        SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::math::margin::validate_spot_margin_trading;
     use crate::state::oracle::OracleSource;
@@ -1906,7 +1939,7 @@ mod validate_spot_margin_trading {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -1984,7 +2017,7 @@ mod validate_spot_margin_trading {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2062,7 +2095,7 @@ mod validate_spot_margin_trading {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2140,7 +2173,7 @@ mod validate_spot_margin_trading {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2218,7 +2251,7 @@ mod validate_spot_margin_trading {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2296,7 +2329,7 @@ mod validate_spot_margin_trading {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -2307,3 +2340,936 @@ mod validate_spot_margin_trading {
         assert_eq!(result, Err(ErrorCode::MarginTradingDisabled));
     }
 }
+
+mod calculate_isolated_perp_position_margin_requirement_and_total_collateral {
+    use std::str::FromStr;
+
+    use anchor_lang::Owner;
+    use solana_program::pubkey::Pubkey;
+
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::math::constants::{
+        AMM_RESERVE_PRECISION, BASE_PRECISION_I64, MAX_USER_ORDERS, PEG_PRECISION, QUOTE_PRECISION,
+        QUOTE_PRECISION_I128, QUOTE_PRECISION_I64, QUOTE_PRECISION_U64, SPOT_BALANCE_PRECISION,
+        SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+    };
+    use crate::math::margin::{
+        calculate_isolated_perp_position_margin_requirement_and_total_collateral,
+        calculate_margin_requirement_and_total_collateral,
+        meets_isolated_perp_position_margin_requirement, MarginRequirementType,
+    };
+    use crate::state::oracle::OracleSource;
+    use crate::state::oracle_map::OracleMap;
+    use crate::state::perp_market::{MarketStatus, PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::user::{Order, PerpPosition, SpotPosition, User};
+    use crate::test_utils::*;
+    use crate::test_utils::{get_positions, get_pyth_price};
+
+    #[test]
+    fn isolated_position_excluded_from_cross_margin() {
+        let slot = 0_u64;
+
+        let mut sol_oracle_price = get_pyth_price(100, 6);
+        let sol_oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            sol_oracle_price,
+            &sol_oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                order_step_size: 10000000,
+                oracle: sol_oracle_price_key,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut usdc_spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            deposit_balance: 10000 * SPOT_BALANCE_PRECISION,
+            liquidator_fee: 0,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(usdc_spot_market, SpotMarket, usdc_spot_market_account_info);
+        let spot_market_map =
+            SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+        let mut spot_positions = [SpotPosition::default(); 8];
+        spot_positions[0] = SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 1000 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        };
+
+        let user = User {
+            orders: [Order::default(); MAX_USER_ORDERS],
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: 10 * BASE_PRECISION_I64,
+                quote_asset_amount: -1000 * QUOTE_PRECISION_I64,
+                isolated_collateral: 75 * QUOTE_PRECISION_U64,
+                is_isolated: true,
+                ..PerpPosition::default()
+            }),
+            spot_positions,
+            ..User::default()
+        };
+
+        let (margin_requirement, total_collateral, _, _) =
+            calculate_margin_requirement_and_total_collateral(
+                &user,
+                &perp_market_map,
+                MarginRequirementType::Initial,
+                &spot_market_map,
+                &mut oracle_map,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(margin_requirement, 0);
+        assert_eq!(total_collateral, 1000 * QUOTE_PRECISION_I128);
+
+        let (margin_requirement, total_collateral, _, _) =
+            calculate_isolated_perp_position_margin_requirement_and_total_collateral(
+                &user,
+                0,
+                &perp_market_map,
+                MarginRequirementType::Initial,
+                &mut oracle_map,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(margin_requirement, 100 * QUOTE_PRECISION);
+        assert_eq!(total_collateral, 75 * QUOTE_PRECISION_I128);
+
+        let meets_initial = meets_isolated_perp_position_margin_requirement(
+            &user,
+            0,
+            &perp_market_map,
+            MarginRequirementType::Initial,
+            &mut oracle_map,
+            None,
+        )
+        .unwrap();
+        assert!(!meets_initial);
+
+        let meets_maintenance = meets_isolated_perp_position_margin_requirement(
+            &user,
+            0,
+            &perp_market_map,
+            MarginRequirementType::Maintenance,
+            &mut oracle_map,
+            None,
+        )
+        .unwrap();
+        assert!(meets_maintenance);
+    }
+}
+
+mod calculate_margin_tier_ratio {
+    use crate::math::constants::{BASE_PRECISION_I64, PRICE_PRECISION_I64, QUOTE_PRECISION};
+    use crate::math::margin::{
+        calculate_margin_tier_ratio, calculate_perp_position_value_and_pnl, MarginRequirementType,
+    };
+    use crate::state::oracle::OraclePriceData;
+    use crate::state::perp_market::{MarginTier, PerpMarket};
+    use crate::state::user::PerpPosition;
+
+    fn margin_tiers() -> [MarginTier; 3] {
+        [
+            MarginTier {
+                notional_threshold: 100_000,
+                margin_ratio_initial: 2000,
+            },
+            MarginTier {
+                notional_threshold: 1_000_000,
+                margin_ratio_initial: 5000,
+            },
+            MarginTier::default(),
+        ]
+    }
+
+    #[test]
+    fn tiers_by_notional() {
+        let margin_tiers = margin_tiers();
+
+        let margin_tier_ratio =
+            calculate_margin_tier_ratio(&margin_tiers, 99_999 * QUOTE_PRECISION).unwrap();
+        assert_eq!(margin_tier_ratio, 0);
+
+        let margin_tier_ratio =
+            calculate_margin_tier_ratio(&margin_tiers, 100_000 * QUOTE_PRECISION).unwrap();
+        assert_eq!(margin_tier_ratio, 2000);
+
+        let margin_tier_ratio =
+            calculate_margin_tier_ratio(&margin_tiers, 5_000_000 * QUOTE_PRECISION).unwrap();
+        assert_eq!(margin_tier_ratio, 5000);
+
+        let margin_tier_ratio =
+            calculate_margin_tier_ratio(&[MarginTier::default(); 3], 5_000_000 * QUOTE_PRECISION)
+                .unwrap();
+        assert_eq!(margin_tier_ratio, 0);
+    }
+
+    #[test]
+    fn tiers_only_apply_to_initial_margin() {
+        let market = PerpMarket {
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            margin_tiers: margin_tiers(),
+            ..PerpMarket::default()
+        };
+
+        // $200k notional
+        let perp_position = PerpPosition {
+            base_asset_amount: 2000 * BASE_PRECISION_I64,
+            quote_asset_amount: -200_000 * QUOTE_PRECISION as i64,
+            ..PerpPosition::default()
+        };
+
+        let oracle_price_data = OraclePriceData {
+            price: 100 * PRICE_PRECISION_I64,
+            confidence: 1,
+            delay: 0,
+            has_sufficient_number_of_data_points: true,
+        };
+
+        let (initial_margin_requirement, _, _) = calculate_perp_position_value_and_pnl(
+            &perp_position,
+            &market,
+            &oracle_price_data,
+            MarginRequirementType::Initial,
+            0,
+            false,
+            false,
+        )
+        .unwrap();
+        assert_eq!(initial_margin_requirement, 40_000 * QUOTE_PRECISION);
+
+        let (maintenance_margin_requirement, _, _) = calculate_perp_position_value_and_pnl(
+            &perp_position,
+            &market,
+            &oracle_price_data,
+            MarginRequirementType::Maintenance,
+            0,
+            false,
+            false,
+        )
+        .unwrap();
+        assert_eq!(maintenance_margin_requirement, 10_000 * QUOTE_PRECISION);
+    }
+}
+
+mod high_leverage_mode {
+    use crate::math::constants::{BASE_PRECISION_I64, PRICE_PRECISION_I64, QUOTE_PRECISION};
+    use crate::math::margin::{calculate_perp_position_value_and_pnl, MarginRequirementType};
+    use crate::state::oracle::OraclePriceData;
+    use crate::state::perp_market::PerpMarket;
+    use crate::state::user::PerpPosition;
+
+    #[test]
+    fn high_leverage_margin_ratios_only_apply_to_opted_in_users() {
+        let market = PerpMarket {
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            high_leverage_margin_ratio_initial: 200,
+            high_leverage_margin_ratio_maintenance: 100,
+            ..PerpMarket::default()
+        };
+
+        // $200k notional
+        let perp_position = PerpPosition {
+            base_asset_amount: 2000 * BASE_PRECISION_I64,
+            quote_asset_amount: -200_000 * QUOTE_PRECISION as i64,
+            ..PerpPosition::default()
+        };
+
+        let oracle_price_data = OraclePriceData {
+            price: 100 * PRICE_PRECISION_I64,
+            confidence: 1,
+            delay: 0,
+            has_sufficient_number_of_data_points: true,
+        };
+
+        let expected = [
+            (
+                MarginRequirementType::Initial,
+                false,
+                20_000 * QUOTE_PRECISION,
+            ),
+            (
+                MarginRequirementType::Maintenance,
+                false,
+                10_000 * QUOTE_PRECISION,
+            ),
+            (
+                MarginRequirementType::Initial,
+                true,
+                4_000 * QUOTE_PRECISION,
+            ),
+            (
+                MarginRequirementType::Maintenance,
+                true,
+                2_000 * QUOTE_PRECISION,
+            ),
+        ];
+
+        for (margin_requirement_type, high_leverage_mode, expected_margin_requirement) in expected {
+            let (margin_requirement, _, _) = calculate_perp_position_value_and_pnl(
+                &perp_position,
+                &market,
+                &oracle_price_data,
+                margin_requirement_type,
+                0,
+                high_leverage_mode,
+                false,
+            )
+            .unwrap();
+            assert_eq!(margin_requirement, expected_margin_requirement);
+        }
+
+        // market without high leverage ratios falls back to the standard ratios
+        let market = PerpMarket {
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            ..PerpMarket::default()
+        };
+
+        let (margin_requirement, _, _) = calculate_perp_position_value_and_pnl(
+            &perp_position,
+            &market,
+            &oracle_price_data,
+            MarginRequirementType::Initial,
+            0,
+            true,
+            false,
+        )
+        .unwrap();
+        assert_eq!(margin_requirement, 20_000 * QUOTE_PRECISION);
+    }
+}
+
+mod calculate_collateral_concentration_haircut {
+    use crate::math::constants::{QUOTE_PRECISION, SPOT_WEIGHT_PRECISION};
+    use crate::math::margin::calculate_collateral_concentration_haircut;
+
+    #[test]
+    fn excess_above_cap_is_excluded() {
+        // single asset capped at 50% of spot collateral
+        let haircut = calculate_collateral_concentration_haircut(
+            &[(100 * QUOTE_PRECISION, SPOT_WEIGHT_PRECISION / 2)],
+            100 * QUOTE_PRECISION,
+        )
+        .unwrap();
+        assert_eq!(haircut, 50 * QUOTE_PRECISION);
+
+        // asset within its cap
+        let haircut = calculate_collateral_concentration_haircut(
+            &[(40 * QUOTE_PRECISION, SPOT_WEIGHT_PRECISION / 2)],
+            100 * QUOTE_PRECISION,
+        )
+        .unwrap();
+        assert_eq!(haircut, 0);
+
+        // caps apply per asset
+        let haircut = calculate_collateral_concentration_haircut(
+            &[
+                (60 * QUOTE_PRECISION, SPOT_WEIGHT_PRECISION / 2),
+                (30 * QUOTE_PRECISION, SPOT_WEIGHT_PRECISION / 5),
+            ],
+            100 * QUOTE_PRECISION,
+        )
+        .unwrap();
+        assert_eq!(haircut, 20 * QUOTE_PRECISION);
+    }
+}
+
+mod calculate_correlation_margin_credit {
+    use crate::math::constants::QUOTE_PRECISION;
+    use crate::math::margin::{
+        calculate_correlation_margin_credit, update_correlation_group_exposures,
+        CorrelationGroupExposure,
+    };
+
+    #[test]
+    fn offsetting_legs_in_same_group() {
+        let mut exposures: Vec<CorrelationGroupExposure> = vec![];
+
+        // $100k long perp vs $80k spot borrow, 5% and 3% credit
+        update_correlation_group_exposures(
+            &mut exposures,
+            1,
+            500,
+            100_000 * QUOTE_PRECISION as i128,
+        )
+        .unwrap();
+        update_correlation_group_exposures(
+            &mut exposures,
+            1,
+            300,
+            -80_000 * QUOTE_PRECISION as i128,
+        )
+        .unwrap();
+
+        // ungrouped position is ignored
+        update_correlation_group_exposures(
+            &mut exposures,
+            0,
+            500,
+            -50_000 * QUOTE_PRECISION as i128,
+        )
+        .unwrap();
+
+        assert_eq!(
+            exposures,
+            vec![CorrelationGroupExposure {
+                correlation_group: 1,
+                long_notional: 100_000 * QUOTE_PRECISION,
+                short_notional: 80_000 * QUOTE_PRECISION,
+                margin_credit: 300,
+            }]
+        );
+
+        let margin_credit = calculate_correlation_margin_credit(&exposures).unwrap();
+        assert_eq!(margin_credit, 2_400 * QUOTE_PRECISION);
+    }
+
+    #[test]
+    fn same_direction_gets_no_credit() {
+        let mut exposures: Vec<CorrelationGroupExposure> = vec![];
+
+        update_correlation_group_exposures(
+            &mut exposures,
+            2,
+            500,
+            100_000 * QUOTE_PRECISION as i128,
+        )
+        .unwrap();
+        update_correlation_group_exposures(
+            &mut exposures,
+            2,
+            500,
+            50_000 * QUOTE_PRECISION as i128,
+        )
+        .unwrap();
+
+        let margin_credit = calculate_correlation_margin_credit(&exposures).unwrap();
+        assert_eq!(margin_credit, 0);
+    }
+}
+
+mod calculate_margin_health {
+    use std::str::FromStr;
+
+    use anchor_lang::Owner;
+    use solana_program::pubkey::Pubkey;
+
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::math::constants::{
+        AMM_RESERVE_PRECISION, BASE_PRECISION_I64, MAX_USER_ORDERS, PEG_PRECISION,
+        PRICE_PRECISION_U64, QUOTE_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64,
+        QUOTE_PRECISION_U64, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
+    };
+    use crate::math::margin::{
+        calculate_and_update_perp_position_liquidation_prices, calculate_margin_health,
+        MarginHealth,
+    };
+    use crate::state::oracle::OracleSource;
+    use crate::state::oracle_map::OracleMap;
+    use crate::state::perp_market::{MarketStatus, PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::user::{Order, PerpPosition, SpotPosition, User};
+    use crate::test_utils::*;
+    use crate::test_utils::{get_positions, get_pyth_price};
+
+    #[test]
+    fn long_liquidation_price() {
+        let slot = 0_u64;
+
+        let mut sol_oracle_price = get_pyth_price(100, 6);
+        let sol_oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            sol_oracle_price,
+            &sol_oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                order_step_size: 10000000,
+                oracle: sol_oracle_price_key,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut usdc_spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            deposit_balance: 10000 * SPOT_BALANCE_PRECISION,
+            liquidator_fee: 0,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(usdc_spot_market, SpotMarket, usdc_spot_market_account_info);
+        let spot_market_map =
+            SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+        let mut spot_positions = [SpotPosition::default(); 8];
+        spot_positions[0] = SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 1000 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        };
+
+        let user = User {
+            orders: [Order::default(); MAX_USER_ORDERS],
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: 20 * BASE_PRECISION_I64,
+                quote_asset_amount: -2000 * QUOTE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions,
+            ..User::default()
+        };
+
+        let margin_health = calculate_margin_health(
+            &user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            Some(0),
+        )
+        .unwrap();
+
+        // 900 of maintenance free collateral covers a 20 * (1 - 5%) per $ move
+        assert_eq!(
+            margin_health,
+            MarginHealth {
+                initial_total_collateral: 1000 * QUOTE_PRECISION_I128,
+                initial_margin_requirement: 200 * QUOTE_PRECISION,
+                maintenance_total_collateral: 1000 * QUOTE_PRECISION_I128,
+                maintenance_margin_requirement: 100 * QUOTE_PRECISION,
+                liquidation_price: 52_631_579,
+            }
+        );
+
+        let margin_health = calculate_margin_health(
+            &user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            None,
+        )
+        .unwrap();
+        assert_eq!(margin_health.liquidation_price, -1);
+    }
+
+    #[test]
+    fn stored_liquidation_prices() {
+        let slot = 0_u64;
+
+        let mut sol_oracle_price = get_pyth_price(100, 6);
+        let sol_oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            sol_oracle_price,
+            &sol_oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                order_step_size: 10000000,
+                oracle: sol_oracle_price_key,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut usdc_spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            deposit_balance: 10000 * SPOT_BALANCE_PRECISION,
+            liquidator_fee: 0,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(usdc_spot_market, SpotMarket, usdc_spot_market_account_info);
+        let spot_market_map =
+            SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+        let mut spot_positions = [SpotPosition::default(); 8];
+        spot_positions[0] = SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 1000 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        };
+
+        let mut perp_positions = [PerpPosition::default(); 8];
+        perp_positions[0] = PerpPosition {
+            market_index: 0,
+            base_asset_amount: 20 * BASE_PRECISION_I64,
+            quote_asset_amount: -2000 * QUOTE_PRECISION_I64,
+            ..PerpPosition::default()
+        };
+        // a stale price on a closed position is cleared
+        perp_positions[1] = PerpPosition {
+            market_index: 1,
+            liquidation_price: 50 * PRICE_PRECISION_U64,
+            ..PerpPosition::default()
+        };
+
+        let mut user = User {
+            orders: [Order::default(); MAX_USER_ORDERS],
+            perp_positions,
+            spot_positions,
+            ..User::default()
+        };
+
+        calculate_and_update_perp_position_liquidation_prices(
+            &mut user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+        )
+        .unwrap();
+
+        assert_eq!(user.perp_positions[0].liquidation_price, 52_631_579);
+        assert_eq!(user.perp_positions[1].liquidation_price, 0);
+    }
+
+    #[test]
+    fn isolated_liquidation_price() {
+        let slot = 0_u64;
+
+        let mut sol_oracle_price = get_pyth_price(100, 6);
+        let sol_oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            sol_oracle_price,
+            &sol_oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                order_step_size: 10000000,
+                oracle: sol_oracle_price_key,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut usdc_spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            deposit_balance: 10000 * SPOT_BALANCE_PRECISION,
+            liquidator_fee: 0,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(usdc_spot_market, SpotMarket, usdc_spot_market_account_info);
+        let spot_market_map =
+            SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+        let mut spot_positions = [SpotPosition::default(); 8];
+        spot_positions[0] = SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 1000 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        };
+
+        let mut user = User {
+            orders: [Order::default(); MAX_USER_ORDERS],
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: 20 * BASE_PRECISION_I64,
+                quote_asset_amount: -2000 * QUOTE_PRECISION_I64,
+                is_isolated: true,
+                isolated_collateral: 500 * QUOTE_PRECISION_U64,
+                ..PerpPosition::default()
+            }),
+            spot_positions,
+            ..User::default()
+        };
+
+        // 400 of isolated free collateral covers a 20 * (1 - 5%) per $ move, the cross
+        // deposit doesnt back the position
+        let margin_health = calculate_margin_health(
+            &user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+            Some(0),
+        )
+        .unwrap();
+        assert_eq!(margin_health.liquidation_price, 78_947_369);
+
+        calculate_and_update_perp_position_liquidation_prices(
+            &mut user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+        )
+        .unwrap();
+        assert_eq!(user.perp_positions[0].liquidation_price, 78_947_369);
+    }
+}
+
+mod calculate_margin_contributions {
+    use std::str::FromStr;
+
+    use anchor_lang::Owner;
+    use solana_program::pubkey::Pubkey;
+
+    use crate::create_account_info;
+    use crate::create_anchor_account_info;
+    use crate::math::constants::{
+        AMM_RESERVE_PRECISION, BASE_PRECISION_I64, MAX_USER_ORDERS, PEG_PRECISION,
+        PRICE_PRECISION_I64, QUOTE_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_I64,
+        SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
+        SPOT_WEIGHT_PRECISION,
+    };
+    use crate::math::margin::{
+        calculate_margin_contributions, PerpMarginContribution, SpotMarginContribution,
+    };
+    use crate::state::oracle::OracleSource;
+    use crate::state::oracle_map::OracleMap;
+    use crate::state::perp_market::{MarketStatus, PerpMarket, AMM};
+    use crate::state::perp_market_map::PerpMarketMap;
+    use crate::state::spot_market::{SpotBalanceType, SpotMarket};
+    use crate::state::spot_market_map::SpotMarketMap;
+    use crate::state::user::{Order, PerpPosition, SpotPosition, User};
+    use crate::test_utils::*;
+    use crate::test_utils::{get_positions, get_pyth_price};
+
+    #[test]
+    fn spot_and_perp_contributions() {
+        let slot = 0_u64;
+
+        let mut sol_oracle_price = get_pyth_price(100, 6);
+        let sol_oracle_price_key =
+            Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+        let pyth_program = crate::ids::pyth_program::id();
+        create_account_info!(
+            sol_oracle_price,
+            &sol_oracle_price_key,
+            &pyth_program,
+            oracle_account_info
+        );
+        let mut oracle_map = OracleMap::load_one(&oracle_account_info, slot, None).unwrap();
+
+        let mut market = PerpMarket {
+            amm: AMM {
+                base_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                quote_asset_reserve: 100 * AMM_RESERVE_PRECISION,
+                bid_base_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                bid_quote_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_base_asset_reserve: 99 * AMM_RESERVE_PRECISION,
+                ask_quote_asset_reserve: 101 * AMM_RESERVE_PRECISION,
+                sqrt_k: 100 * AMM_RESERVE_PRECISION,
+                peg_multiplier: 100 * PEG_PRECISION,
+                order_step_size: 10000000,
+                oracle: sol_oracle_price_key,
+                ..AMM::default()
+            },
+            margin_ratio_initial: 1000,
+            margin_ratio_maintenance: 500,
+            status: MarketStatus::Initialized,
+            ..PerpMarket::default()
+        };
+        create_anchor_account_info!(market, PerpMarket, market_account_info);
+        let perp_market_map = PerpMarketMap::load_one(&market_account_info, true).unwrap();
+
+        let mut usdc_spot_market = SpotMarket {
+            market_index: 0,
+            oracle_source: OracleSource::QuoteAsset,
+            cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+            decimals: 6,
+            initial_asset_weight: SPOT_WEIGHT_PRECISION,
+            maintenance_asset_weight: SPOT_WEIGHT_PRECISION,
+            deposit_balance: 10000 * SPOT_BALANCE_PRECISION,
+            liquidator_fee: 0,
+            ..SpotMarket::default()
+        };
+        create_anchor_account_info!(usdc_spot_market, SpotMarket, usdc_spot_market_account_info);
+        let spot_market_map =
+            SpotMarketMap::load_one(&usdc_spot_market_account_info, true).unwrap();
+
+        let mut spot_positions = [SpotPosition::default(); 8];
+        spot_positions[0] = SpotPosition {
+            market_index: 0,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 1000 * SPOT_BALANCE_PRECISION_U64,
+            ..SpotPosition::default()
+        };
+
+        let user = User {
+            orders: [Order::default(); MAX_USER_ORDERS],
+            perp_positions: get_positions(PerpPosition {
+                market_index: 0,
+                base_asset_amount: 20 * BASE_PRECISION_I64,
+                quote_asset_amount: -2000 * QUOTE_PRECISION_I64,
+                ..PerpPosition::default()
+            }),
+            spot_positions,
+            ..User::default()
+        };
+
+        let (spot_contributions, perp_contributions) = calculate_margin_contributions(
+            &user,
+            &perp_market_map,
+            &spot_market_map,
+            &mut oracle_map,
+        )
+        .unwrap();
+
+        assert_eq!(
+            spot_contributions,
+            vec![SpotMarginContribution {
+                market_index: 0,
+                token_amount: 1000 * QUOTE_PRECISION_I128,
+                oracle_price: PRICE_PRECISION_I64,
+                weighted_value: 1000 * QUOTE_PRECISION_I128,
+                open_orders_margin_requirement: 0,
+            }]
+        );
+
+        // 5% maintenance margin on $2000 of notional, bought at the oracle price
+        assert_eq!(
+            perp_contributions,
+            vec![PerpMarginContribution {
+                market_index: 0,
+                base_asset_amount: 20 * BASE_PRECISION_I64,
+                quote_asset_amount: -2000 * QUOTE_PRECISION_I64,
+                oracle_price: 100 * PRICE_PRECISION_I64,
+                margin_requirement: 100 * QUOTE_PRECISION,
+                weighted_pnl: 0,
+                is_isolated: false,
+            }]
+        );
+    }
+}
+
+mod calculate_health {
+    use crate::math::constants::{QUOTE_PRECISION, QUOTE_PRECISION_I128};
+    use crate::math::margin::calculate_health;
+
+    #[test]
+    fn linear_in_margin_requirement() {
+        assert_eq!(calculate_health(0, 0).unwrap(), 100);
+        assert_eq!(
+            calculate_health(1000 * QUOTE_PRECISION_I128, 0).unwrap(),
+            100
+        );
+        assert_eq!(
+            calculate_health(1000 * QUOTE_PRECISION_I128, 250 * QUOTE_PRECISION).unwrap(),
+            75
+        );
+        assert_eq!(
+            calculate_health(1000 * QUOTE_PRECISION_I128, 1000 * QUOTE_PRECISION).unwrap(),
+            0
+        );
+        assert_eq!(
+            calculate_health(-QUOTE_PRECISION_I128, 100 * QUOTE_PRECISION).unwrap(),
+            0
+        );
+    }
+}
@@ -10,6 +10,7 @@ pub mod constants;
 pub mod cp_curve;
 pub mod fees;
 mod floor_div;
+pub mod fuel;
 pub mod fulfillment;
 pub mod funding;
 pub mod helpers;
@@ -19,7 +20,9 @@ pub mod lp;
 pub mod margin;
 pub mod matching;
 pub mod oracle;
+pub mod order_book;
 pub mod orders;
+pub mod phoenix;
 pub mod pnl;
 pub mod position;
 pub mod quote_asset;
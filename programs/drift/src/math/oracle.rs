@@ -6,11 +6,12 @@ use solana_program::msg;
 use crate::error::DriftResult;
 use crate::math::amm;
 use crate::math::casting::Cast;
-use crate::math::constants::BID_ASK_SPREAD_PRECISION;
+use crate::math::constants::{BID_ASK_SPREAD_PRECISION, PERCENTAGE_PRECISION};
 use crate::math::safe_math::SafeMath;
+use crate::math::safe_unwrap::SafeUnwrap;
 
 use crate::state::oracle::OraclePriceData;
-use crate::state::perp_market::{MarketStatus, PerpMarket, AMM};
+use crate::state::perp_market::{ContractType, MarketStatus, PerpMarket, PerpOperation, AMM};
 use crate::state::state::{OracleGuardRails, ValidityGuardRails};
 
 #[cfg(test)]
@@ -120,7 +121,11 @@ pub fn block_operation(
     let is_oracle_valid =
         is_oracle_valid_for_action(oracle_validity, Some(DriftAction::UpdateFunding))?;
 
-    let funding_paused_on_market = market.status == MarketStatus::FundingPaused;
+    // dated futures and prediction markets converge to their settlement, not to the oracle,
+    // so they don't pay funding
+    let funding_paused_on_market = market.status == MarketStatus::FundingPaused
+        || market.is_operation_paused(PerpOperation::UpdateFunding)?
+        || market.contract_type != ContractType::Perpetual;
 
     let block = !is_oracle_valid || is_oracle_mark_too_divergent || funding_paused_on_market;
     Ok(block)
@@ -143,7 +148,9 @@ pub fn get_oracle_status<'a>(
     let oracle_validity = oracle_validity(
         amm.historical_oracle_data.last_oracle_price_twap,
         oracle_price_data,
-        &guard_rails.validity,
+        &guard_rails
+            .validity
+            .with_override(&amm.validity_guard_rails_override),
     )?;
     let oracle_reserve_price_spread_pct =
         amm::calculate_oracle_twap_5min_mark_spread_pct(amm, precomputed_reserve_price)?;
@@ -160,6 +167,27 @@ pub fn get_oracle_status<'a>(
     })
 }
 
+/// True if the oracle price is more than `band` (PERCENTAGE_PRECISION) away from the mark twap.
+/// A zero band disables the circuit breaker
+pub fn is_oracle_outside_circuit_breaker_band(
+    oracle_price: i64,
+    mark_twap: u64,
+    band: u32,
+) -> DriftResult<bool> {
+    if band == 0 || mark_twap == 0 {
+        return Ok(false);
+    }
+
+    let divergence = oracle_price
+        .cast::<i128>()?
+        .safe_sub(mark_twap.cast()?)?
+        .unsigned_abs()
+        .safe_mul(PERCENTAGE_PRECISION)?
+        .safe_div(mark_twap.cast()?)?;
+
+    Ok(divergence > band.cast()?)
+}
+
 pub fn oracle_validity(
     last_oracle_twap: i64,
     oracle_price_data: &OraclePriceData,
@@ -228,3 +256,77 @@ pub fn oracle_validity(
 
     Ok(oracle_validity)
 }
+
+/// Aggregates the price data of a market's oracles, primary oracle first. Oracles that aren't valid
+/// for margin are ignored. Three or more usable oracles give the median, two give a
+/// confidence-weighted average. If none are usable the primary oracle's data is returned so the
+/// usual validity checks still block the market.
+pub fn calculate_median_oracle_price_data(
+    oracle_price_datas: &[(OraclePriceData, OracleValidity)],
+) -> DriftResult<OraclePriceData> {
+    let mut usable_oracle_price_datas = Vec::with_capacity(oracle_price_datas.len());
+    for (oracle_price_data, oracle_validity) in oracle_price_datas.iter() {
+        if is_oracle_valid_for_action(*oracle_validity, Some(DriftAction::MarginCalc))? {
+            usable_oracle_price_datas.push(*oracle_price_data);
+        }
+    }
+
+    match usable_oracle_price_datas.len() {
+        0 => oracle_price_datas
+            .first()
+            .map(|(oracle_price_data, _)| *oracle_price_data)
+            .safe_unwrap(),
+        1 => Ok(usable_oracle_price_datas[0]),
+        2 => calculate_confidence_weighted_oracle_price_data(
+            &usable_oracle_price_datas[0],
+            &usable_oracle_price_datas[1],
+        ),
+        len => {
+            usable_oracle_price_datas.sort_by_key(|oracle_price_data| oracle_price_data.price);
+            Ok(usable_oracle_price_datas[len / 2])
+        }
+    }
+}
+
+pub fn calculate_confidence_weighted_oracle_price_data(
+    oracle_price_data_a: &OraclePriceData,
+    oracle_price_data_b: &OraclePriceData,
+) -> DriftResult<OraclePriceData> {
+    let total_confidence = oracle_price_data_a
+        .confidence
+        .cast::<i128>()?
+        .safe_add(oracle_price_data_b.confidence.cast()?)?;
+
+    let price = if total_confidence == 0 {
+        oracle_price_data_a
+            .price
+            .cast::<i128>()?
+            .safe_add(oracle_price_data_b.price.cast()?)?
+            .safe_div(2)?
+    } else {
+        // each price is weighted by the other's confidence so the tighter feed dominates
+        oracle_price_data_a
+            .price
+            .cast::<i128>()?
+            .safe_mul(oracle_price_data_b.confidence.cast()?)?
+            .safe_add(
+                oracle_price_data_b
+                    .price
+                    .cast::<i128>()?
+                    .safe_mul(oracle_price_data_a.confidence.cast()?)?,
+            )?
+            .safe_div(total_confidence)?
+    }
+    .cast::<i64>()?;
+
+    Ok(OraclePriceData {
+        price,
+        confidence: oracle_price_data_a
+            .confidence
+            .max(oracle_price_data_b.confidence),
+        delay: oracle_price_data_a.delay.max(oracle_price_data_b.delay),
+        has_sufficient_number_of_data_points: oracle_price_data_a
+            .has_sufficient_number_of_data_points
+            && oracle_price_data_b.has_sufficient_number_of_data_points,
+    })
+}
@@ -4,7 +4,10 @@ use crate::math::constants::{
 };
 use crate::math::oracle::*;
 use crate::state::oracle::HistoricalOracleData;
-use crate::state::state::{OracleGuardRails, PriceDivergenceGuardRails, State, ValidityGuardRails};
+use crate::state::state::{
+    OracleGuardRails, PriceDivergenceGuardRails, State, ValidityGuardRails,
+    ValidityGuardRailsOverride,
+};
 
 #[test]
 fn calculate_oracle_valid() {
@@ -95,3 +98,149 @@ fn calculate_oracle_valid() {
     assert!(oracle_status.mark_too_divergent);
     assert!(oracle_status.oracle_validity == OracleValidity::TooUncertain);
 }
+
+#[test]
+fn calculate_median_oracle_price() {
+    let oracle_price_data_a = OraclePriceData {
+        price: (34 * PRICE_PRECISION) as i64,
+        confidence: PRICE_PRECISION_U64 / 100,
+        delay: 1,
+        has_sufficient_number_of_data_points: true,
+    };
+    let oracle_price_data_b = OraclePriceData {
+        price: (35 * PRICE_PRECISION) as i64,
+        confidence: PRICE_PRECISION_U64 / 50,
+        delay: 2,
+        has_sufficient_number_of_data_points: true,
+    };
+    let oracle_price_data_c = OraclePriceData {
+        price: (60 * PRICE_PRECISION) as i64,
+        confidence: PRICE_PRECISION_U64 / 100,
+        delay: 3,
+        has_sufficient_number_of_data_points: true,
+    };
+
+    // median ignores the outlier
+    let median = calculate_median_oracle_price_data(&[
+        (oracle_price_data_c, OracleValidity::Valid),
+        (oracle_price_data_a, OracleValidity::Valid),
+        (oracle_price_data_b, OracleValidity::Valid),
+    ])
+    .unwrap();
+    assert_eq!(median.price, oracle_price_data_b.price);
+    assert_eq!(median.delay, 2);
+
+    // two usable oracles fall back to confidence weighting, tighter feed has twice the weight
+    let median = calculate_median_oracle_price_data(&[
+        (oracle_price_data_c, OracleValidity::TooVolatile),
+        (oracle_price_data_a, OracleValidity::Valid),
+        (oracle_price_data_b, OracleValidity::StaleForAMM),
+    ])
+    .unwrap();
+    assert_eq!(median.price, 34_333_333);
+    assert_eq!(median.confidence, PRICE_PRECISION_U64 / 50);
+    assert_eq!(median.delay, 2);
+
+    let median = calculate_median_oracle_price_data(&[
+        (oracle_price_data_c, OracleValidity::Invalid),
+        (oracle_price_data_a, OracleValidity::Valid),
+    ])
+    .unwrap();
+    assert_eq!(median.price, oracle_price_data_a.price);
+
+    // nothing usable returns the primary oracle
+    let median = calculate_median_oracle_price_data(&[
+        (oracle_price_data_c, OracleValidity::Invalid),
+        (oracle_price_data_a, OracleValidity::TooUncertain),
+    ])
+    .unwrap();
+    assert_eq!(median.price, oracle_price_data_c.price);
+}
+
+#[test]
+fn oracle_validity_with_market_override() {
+    let validity_guard_rails = OracleGuardRails::default().validity;
+    let oracle_price_data = OraclePriceData {
+        price: (34 * PRICE_PRECISION) as i64,
+        confidence: (PRICE_PRECISION_U64 * 34) / 20, // 5% of price
+        delay: 30,
+        has_sufficient_number_of_data_points: true,
+    };
+    let last_oracle_twap = (34 * PRICE_PRECISION) as i64;
+
+    let validity =
+        oracle_validity(last_oracle_twap, &oracle_price_data, &validity_guard_rails).unwrap();
+    assert_eq!(validity, OracleValidity::TooUncertain);
+
+    // looser confidence interval, amm staleness still global
+    let guard_rails_override = ValidityGuardRailsOverride {
+        confidence_interval_max_size: 100_000, // 10% of price
+        ..ValidityGuardRailsOverride::default()
+    };
+    let validity = oracle_validity(
+        last_oracle_twap,
+        &oracle_price_data,
+        &validity_guard_rails.with_override(&guard_rails_override),
+    )
+    .unwrap();
+    assert_eq!(validity, OracleValidity::StaleForAMM);
+
+    let guard_rails_override = ValidityGuardRailsOverride {
+        confidence_interval_max_size: 100_000,
+        slots_before_stale_for_amm: 60,
+        ..ValidityGuardRailsOverride::default()
+    };
+    let merged = validity_guard_rails.with_override(&guard_rails_override);
+    assert_eq!(merged.slots_before_stale_for_margin, 120);
+    assert_eq!(merged.too_volatile_ratio, 5);
+    let validity = oracle_validity(last_oracle_twap, &oracle_price_data, &merged).unwrap();
+    assert_eq!(validity, OracleValidity::Valid);
+}
+
+#[test]
+fn block_funding_for_non_perpetual_markets() {
+    let px = 32 * PRICE_PRECISION;
+
+    let mut market = PerpMarket {
+        amm: AMM {
+            base_asset_reserve: 2 * AMM_RESERVE_PRECISION,
+            quote_asset_reserve: 2 * AMM_RESERVE_PRECISION,
+            peg_multiplier: 33 * PEG_PRECISION,
+            historical_oracle_data: HistoricalOracleData {
+                last_oracle_price_twap_5min: px as i64,
+                last_oracle_price_twap: (px as i64) - 1000,
+                ..HistoricalOracleData::default()
+            },
+            mark_std: PRICE_PRECISION as u64,
+            funding_period: 3600_i64,
+            ..AMM::default()
+        },
+        ..PerpMarket::default()
+    };
+    let oracle_price_data = OraclePriceData {
+        price: (34 * PRICE_PRECISION) as i64,
+        confidence: PRICE_PRECISION_U64 / 100,
+        delay: 1,
+        has_sufficient_number_of_data_points: true,
+    };
+    let guard_rails = OracleGuardRails {
+        price_divergence: PriceDivergenceGuardRails {
+            mark_oracle_divergence_numerator: 1,
+            mark_oracle_divergence_denominator: 10,
+        },
+        validity: ValidityGuardRails {
+            slots_before_stale_for_amm: 10,      // 5s
+            slots_before_stale_for_margin: 120,  // 60s
+            confidence_interval_max_size: 20000, // 2%
+            too_volatile_ratio: 5,
+        },
+    };
+
+    assert!(!block_operation(&market, &oracle_price_data, &guard_rails, None).unwrap());
+
+    market.contract_type = ContractType::Future;
+    assert!(block_operation(&market, &oracle_price_data, &guard_rails, None).unwrap());
+
+    market.contract_type = ContractType::Prediction;
+    assert!(block_operation(&market, &oracle_price_data, &guard_rails, None).unwrap());
+}
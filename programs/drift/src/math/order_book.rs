@@ -0,0 +1,196 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::controller::position::PositionDirection;
+use crate::error::DriftResult;
+use crate::math::amm_spread::calculate_base_asset_amount_to_trade_to_price;
+use crate::math::safe_math::SafeMath;
+use crate::state::perp_market::PerpMarket;
+use crate::state::user::{MarketType, Order, OrderStatus};
+
+#[cfg(test)]
+mod tests;
+
+/// levels per side, keeps a snapshot inside the 1024 byte return data limit
+pub const MAX_ORDER_BOOK_SNAPSHOT_DEPTH: u8 = 20;
+
+#[derive(Default, Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub struct OrderBookLevel {
+    pub price: u64,             // best price in the level. precision: PRICE_PRECISION
+    pub base_asset_amount: u64, // resting orders and amm liquidity in the level. precision: BASE_PRECISION
+}
+
+#[derive(Default, Clone, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub struct OrderBookSnapshot {
+    pub slot: u64,
+    pub oracle_price: i64,
+    pub amm_bid_price: u64,        // 0 if the amm isnt available
+    pub amm_ask_price: u64,        // 0 if the amm isnt available
+    pub bids: Vec<OrderBookLevel>, // best first
+    pub asks: Vec<OrderBookLevel>, // best first
+}
+
+/// Depth of a perp market in levels price_step wide, starting from the best of the amm quote and
+/// the resting limit orders on each side. Orders that couldnt be filled against now are skipped
+pub fn calculate_order_book_snapshot(
+    market: &PerpMarket,
+    orders: &[Order],
+    oracle_price: i64,
+    amm_is_available: bool,
+    slot: u64,
+    price_step: u64,
+    depth: u8,
+) -> DriftResult<OrderBookSnapshot> {
+    let mut bids: Vec<(u64, u64)> = vec![];
+    let mut asks: Vec<(u64, u64)> = vec![];
+    for order in orders.iter() {
+        if order.status != OrderStatus::Open
+            || order.market_type != MarketType::Perp
+            || order.market_index != market.market_index
+            || (order.must_be_triggered() && !order.triggered())
+            || !order.is_resting_limit_order(slot)?
+        {
+            continue;
+        }
+
+        let limit_price = match order.get_limit_price(
+            Some(oracle_price),
+            None,
+            slot,
+            market.amm.order_tick_size,
+            market.is_prediction_market(),
+        )? {
+            Some(limit_price) => limit_price,
+            None => continue,
+        };
+
+        let base_asset_amount = order.get_base_asset_amount_displayed(None)?;
+        match order.direction {
+            PositionDirection::Long => bids.push((limit_price, base_asset_amount)),
+            PositionDirection::Short => asks.push((limit_price, base_asset_amount)),
+        }
+    }
+
+    let (amm_bid_price, amm_ask_price) = if amm_is_available {
+        let reserve_price = market.amm.reserve_price()?;
+        (
+            market.amm.bid_price(reserve_price)?,
+            market.amm.ask_price(reserve_price)?,
+        )
+    } else {
+        (0, 0)
+    };
+
+    Ok(OrderBookSnapshot {
+        slot,
+        oracle_price,
+        amm_bid_price,
+        amm_ask_price,
+        bids: calculate_order_book_levels(
+            market,
+            &bids,
+            amm_bid_price,
+            PositionDirection::Long,
+            price_step,
+            depth,
+        )?,
+        asks: calculate_order_book_levels(
+            market,
+            &asks,
+            amm_ask_price,
+            PositionDirection::Short,
+            price_step,
+            depth,
+        )?,
+    })
+}
+
+/// levels for the side resting in direction. amm_price is 0 if the amm isnt available
+fn calculate_order_book_levels(
+    market: &PerpMarket,
+    orders: &[(u64, u64)],
+    amm_price: u64,
+    direction: PositionDirection,
+    price_step: u64,
+    depth: u8,
+) -> DriftResult<Vec<OrderBookLevel>> {
+    let best_order_price = match direction {
+        PositionDirection::Long => orders.iter().map(|(price, _)| *price).max(),
+        PositionDirection::Short => orders.iter().map(|(price, _)| *price).min(),
+    };
+
+    let best_price = match (best_order_price, amm_price) {
+        (None, 0) => return Ok(vec![]),
+        (None, amm_price) => amm_price,
+        (Some(best_order_price), 0) => best_order_price,
+        (Some(best_order_price), amm_price) => match direction {
+            PositionDirection::Long => best_order_price.max(amm_price),
+            PositionDirection::Short => best_order_price.min(amm_price),
+        },
+    };
+
+    let mut levels = Vec::with_capacity(depth as usize);
+    let mut amm_base_asset_amount_before = 0_u64;
+    for level_index in 0..depth as u64 {
+        let offset = level_index.safe_mul(price_step)?;
+
+        // a level holds prices from its price up to, not including, a step worse
+        let (price, next_price) = match direction {
+            PositionDirection::Long => {
+                if offset >= best_price {
+                    break;
+                }
+                let price = best_price.safe_sub(offset)?;
+                (price, price.saturating_sub(price_step))
+            }
+            PositionDirection::Short => {
+                let price = best_price.safe_add(offset)?;
+                (price, price.safe_add(price_step)?)
+            }
+        };
+
+        let mut base_asset_amount = 0_u64;
+        for (order_price, order_base_asset_amount) in orders.iter() {
+            let in_level = match direction {
+                PositionDirection::Long => *order_price <= price && *order_price > next_price,
+                PositionDirection::Short => *order_price >= price && *order_price < next_price,
+            };
+
+            if in_level {
+                base_asset_amount = base_asset_amount.safe_add(*order_base_asset_amount)?;
+            }
+        }
+
+        if amm_price != 0 && next_price != 0 {
+            let amm_base_asset_amount =
+                calculate_amm_base_asset_amount_to_price(market, next_price, direction)?;
+            base_asset_amount = base_asset_amount
+                .safe_add(amm_base_asset_amount.saturating_sub(amm_base_asset_amount_before))?;
+            amm_base_asset_amount_before = amm_base_asset_amount_before.max(amm_base_asset_amount);
+        }
+
+        levels.push(OrderBookLevel {
+            price,
+            base_asset_amount,
+        });
+    }
+
+    Ok(levels)
+}
+
+/// base the amm fills for takers trading against the side resting in direction until its quote
+/// reaches price
+fn calculate_amm_base_asset_amount_to_price(
+    market: &PerpMarket,
+    price: u64,
+    direction: PositionDirection,
+) -> DriftResult<u64> {
+    let taker_direction = direction.opposite();
+    let (base_asset_amount, trade_direction) =
+        calculate_base_asset_amount_to_trade_to_price(&market.amm, price, taker_direction)?;
+
+    if trade_direction != taker_direction {
+        return Ok(0);
+    }
+
+    Ok(base_asset_amount)
+}
@@ -0,0 +1,127 @@
+use crate::controller::position::PositionDirection;
+use crate::math::constants::{BASE_PRECISION_U64, PRICE_PRECISION_I64, PRICE_PRECISION_U64};
+use crate::math::order_book::{calculate_order_book_snapshot, OrderBookLevel};
+use crate::state::perp_market::PerpMarket;
+use crate::state::user::{MarketType, Order, OrderStatus, OrderTriggerCondition, OrderType};
+
+fn get_resting_order(direction: PositionDirection, price: u64, base_asset_amount: u64) -> Order {
+    Order {
+        status: OrderStatus::Open,
+        order_type: OrderType::Limit,
+        market_type: MarketType::Perp,
+        direction,
+        price,
+        base_asset_amount,
+        post_only: true,
+        ..Order::default()
+    }
+}
+
+#[test]
+fn resting_orders_grouped_into_levels() {
+    let perp_market = PerpMarket::default_test();
+
+    let orders = [
+        get_resting_order(PositionDirection::Long, 990_000, BASE_PRECISION_U64),
+        get_resting_order(PositionDirection::Long, 985_000, 2 * BASE_PRECISION_U64),
+        get_resting_order(PositionDirection::Long, 970_000, 3 * BASE_PRECISION_U64),
+        get_resting_order(PositionDirection::Short, 1_010_000, BASE_PRECISION_U64),
+        // iceberg only shows what is left of its slice
+        Order {
+            display_quantity: BASE_PRECISION_U64,
+            base_asset_amount_filled: BASE_PRECISION_U64 / 2,
+            ..get_resting_order(PositionDirection::Short, 1_020_000, 5 * BASE_PRECISION_U64)
+        },
+        // untriggered
+        Order {
+            order_type: OrderType::TriggerLimit,
+            trigger_condition: OrderTriggerCondition::Below,
+            ..get_resting_order(PositionDirection::Long, 990_000, BASE_PRECISION_U64)
+        },
+        // other market
+        Order {
+            market_index: 1,
+            ..get_resting_order(PositionDirection::Short, 1_010_000, BASE_PRECISION_U64)
+        },
+    ];
+
+    let snapshot = calculate_order_book_snapshot(
+        &perp_market,
+        &orders,
+        PRICE_PRECISION_I64,
+        false,
+        0,
+        PRICE_PRECISION_U64 / 100,
+        3,
+    )
+    .unwrap();
+
+    assert_eq!(snapshot.amm_bid_price, 0);
+    assert_eq!(
+        snapshot.bids,
+        vec![
+            OrderBookLevel {
+                price: 990_000,
+                base_asset_amount: 3 * BASE_PRECISION_U64,
+            },
+            OrderBookLevel {
+                price: 980_000,
+                base_asset_amount: 0,
+            },
+            OrderBookLevel {
+                price: 970_000,
+                base_asset_amount: 3 * BASE_PRECISION_U64,
+            },
+        ]
+    );
+    assert_eq!(
+        snapshot.asks,
+        vec![
+            OrderBookLevel {
+                price: 1_010_000,
+                base_asset_amount: BASE_PRECISION_U64,
+            },
+            OrderBookLevel {
+                price: 1_020_000,
+                base_asset_amount: BASE_PRECISION_U64 / 2,
+            },
+            OrderBookLevel {
+                price: 1_030_000,
+                base_asset_amount: 0,
+            },
+        ]
+    );
+}
+
+#[test]
+fn amm_liquidity_in_levels() {
+    let perp_market = PerpMarket::default_test();
+
+    let snapshot = calculate_order_book_snapshot(
+        &perp_market,
+        &[],
+        PRICE_PRECISION_I64,
+        true,
+        0,
+        PRICE_PRECISION_U64 / 100,
+        3,
+    )
+    .unwrap();
+
+    assert_eq!(snapshot.amm_bid_price, PRICE_PRECISION_U64);
+    assert_eq!(snapshot.amm_ask_price, PRICE_PRECISION_U64);
+
+    assert_eq!(snapshot.bids.len(), 3);
+    assert_eq!(snapshot.bids[0].price, PRICE_PRECISION_U64);
+    assert!(snapshot
+        .bids
+        .iter()
+        .all(|level| level.base_asset_amount > 0));
+
+    assert_eq!(snapshot.asks.len(), 3);
+    assert_eq!(snapshot.asks[0].price, PRICE_PRECISION_U64);
+    assert!(snapshot
+        .asks
+        .iter()
+        .all(|level| level.base_asset_amount > 0));
+}
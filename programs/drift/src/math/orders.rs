@@ -1,6 +1,7 @@
 use std::cmp::min;
 use std::ops::{Neg, Sub};
 
+use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::msg;
 
 use crate::controller::position::PositionDelta;
@@ -9,16 +10,21 @@ use crate::error::{DriftResult, ErrorCode};
 use crate::math::amm::calculate_amm_available_liquidity;
 use crate::math::auction::is_auction_complete;
 use crate::math::casting::Cast;
+use crate::state::user::SizeDistribution;
 use crate::{
-    math, BASE_PRECISION_I128, OPEN_ORDER_MARGIN_REQUIREMENT, PRICE_PRECISION_I128,
+    math, BASE_PRECISION, BASE_PRECISION_I128, OPEN_ORDER_MARGIN_REQUIREMENT, PRICE_PRECISION_I128,
     QUOTE_PRECISION_I128, SPOT_WEIGHT_PRECISION,
 };
 
-use crate::math::constants::MARGIN_PRECISION_U128;
+use crate::math::constants::{
+    MARGIN_PRECISION_U128, MAX_USER_ORDERS, ONE_BPS_DENOMINATOR,
+    PROTECTED_MAKER_PRICE_CUSHION_DIVISOR,
+};
 use crate::math::margin::{
-    calculate_margin_requirement_and_total_collateral_and_liability_info, MarginRequirementType,
+    calculate_margin_requirement_and_total_collateral_and_liability_info,
+    calculate_margin_tier_ratio, MarginRequirementType,
 };
-use crate::math::position::calculate_entry_price;
+use crate::math::position::{calculate_base_asset_value_with_oracle_price, calculate_entry_price};
 use crate::math::safe_math::SafeMath;
 use crate::math::spot_balance::{get_strict_token_value, get_token_value};
 use crate::math::spot_withdraw::get_max_withdraw_for_market_with_token_amount;
@@ -30,7 +36,7 @@ use crate::state::perp_market_map::PerpMarketMap;
 use crate::state::spot_market::{SpotBalanceType, SpotMarket};
 use crate::state::spot_market_map::SpotMarketMap;
 use crate::state::user::{
-    MarketType, Order, OrderStatus, OrderTriggerCondition, PerpPosition, User,
+    MarketType, Order, OrderStatus, OrderTrailType, OrderTriggerCondition, PerpPosition, User,
 };
 use crate::validate;
 
@@ -46,9 +52,13 @@ pub fn calculate_base_asset_amount_for_amm_to_fulfill(
     existing_base_asset_amount: i64,
 ) -> DriftResult<(u64, Option<u64>)> {
     let limit_price = if let Some(override_limit_price) = override_limit_price {
-        if let Some(limit_price) =
-            order.get_limit_price(valid_oracle_price, None, slot, market.amm.order_tick_size)?
-        {
+        if let Some(limit_price) = order.get_limit_price(
+            valid_oracle_price,
+            None,
+            slot,
+            market.amm.order_tick_size,
+            market.is_prediction_market(),
+        )? {
             validate!(
                 (limit_price >= override_limit_price && order.direction == PositionDirection::Long)
                     || (limit_price <= override_limit_price
@@ -62,7 +72,13 @@ pub fn calculate_base_asset_amount_for_amm_to_fulfill(
 
         Some(override_limit_price)
     } else {
-        order.get_limit_price(valid_oracle_price, None, slot, market.amm.order_tick_size)?
+        order.get_limit_price(
+            valid_oracle_price,
+            None,
+            slot,
+            market.amm.order_tick_size,
+            market.is_prediction_market(),
+        )?
     };
 
     if order.must_be_triggered() && !order.triggered() {
@@ -255,6 +271,29 @@ pub fn standardize_price_i64(
     }
 }
 
+/// Moves a protected maker's limit price away from the oracle by a cushion, so takers fill
+/// against it at a price slightly better for the maker than the order's resting price
+pub fn apply_protected_maker_price_cushion(
+    limit_price: u64,
+    direction: PositionDirection,
+    oracle_price: i64,
+    tick_size: u64,
+) -> DriftResult<u64> {
+    let cushion = oracle_price
+        .unsigned_abs()
+        .safe_div(PROTECTED_MAKER_PRICE_CUSHION_DIVISOR)?
+        .max(tick_size);
+
+    match direction {
+        PositionDirection::Long => {
+            standardize_price(limit_price.saturating_sub(cushion), tick_size, direction)
+        }
+        PositionDirection::Short => {
+            standardize_price(limit_price.safe_add(cushion)?, tick_size, direction)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test2 {
     use crate::controller::position::PositionDirection;
@@ -328,9 +367,15 @@ pub fn order_breaches_oracle_price_bands(
     tick_size: u64,
     margin_ratio_initial: u32,
     margin_ratio_maintenance: u32,
+    is_prediction_market: bool,
 ) -> DriftResult<bool> {
-    let order_limit_price =
-        order.force_get_limit_price(Some(oracle_price), None, slot, tick_size)?;
+    let order_limit_price = order.force_get_limit_price(
+        Some(oracle_price),
+        None,
+        slot,
+        tick_size,
+        is_prediction_market,
+    )?;
     limit_price_breaches_oracle_price_bands(
         order_limit_price,
         order.direction,
@@ -403,8 +448,292 @@ pub fn limit_price_breaches_oracle_price_bands(
 
 pub fn order_satisfies_trigger_condition(order: &Order, oracle_price: u64) -> DriftResult<bool> {
     match order.trigger_condition {
-        OrderTriggerCondition::Above => Ok(oracle_price > order.trigger_price),
-        OrderTriggerCondition::Below => Ok(oracle_price < order.trigger_price),
+        OrderTriggerCondition::Above | OrderTriggerCondition::TrailingAbove => {
+            Ok(oracle_price > order.trigger_price)
+        }
+        OrderTriggerCondition::Below | OrderTriggerCondition::TrailingBelow => {
+            Ok(oracle_price < order.trigger_price)
+        }
+        _ => Err(print_error!(ErrorCode::InvalidTriggerOrderCondition)()),
+    }
+}
+
+pub fn order_satisfies_funding_trigger_condition(
+    order: &Order,
+    predicted_funding_rate: i64,
+) -> DriftResult<bool> {
+    match order.trigger_condition {
+        OrderTriggerCondition::FundingAbove => {
+            Ok(predicted_funding_rate > order.trigger_funding_rate)
+        }
+        OrderTriggerCondition::FundingBelow => {
+            Ok(predicted_funding_rate < order.trigger_funding_rate)
+        }
+        _ => Err(print_error!(ErrorCode::InvalidTriggerOrderCondition)()),
+    }
+}
+
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub struct FillReceipt {
+    pub order_id: u32,
+    pub status: OrderStatus,
+    pub base_asset_amount_filled: u64,
+    pub quote_asset_amount_filled: u64,
+    pub fill_price: u64, // 0 if nothing filled. precision: PRICE_PRECISION
+    pub fee_paid: i64,   // negative for a net rebate. precision: QUOTE_PRECISION
+}
+
+/// What a single fill instruction did to an order. order_after is None once the order has been
+/// filled or canceled and removed from the user
+pub fn calculate_fill_receipt(
+    order_id: u32,
+    base_asset_amount_unfilled_before: u64,
+    order_after: Option<&Order>,
+    base_asset_amount_filled: u64,
+    quote_asset_amount_filled: u64,
+    fee_paid: i64,
+) -> DriftResult<FillReceipt> {
+    let status = match order_after {
+        Some(order) => order.status,
+        None if base_asset_amount_filled == base_asset_amount_unfilled_before => {
+            OrderStatus::Filled
+        }
+        None => OrderStatus::Canceled,
+    };
+
+    let fill_price = if base_asset_amount_filled > 0 {
+        calculate_entry_price(
+            quote_asset_amount_filled.cast()?,
+            base_asset_amount_filled.cast()?,
+        )?
+        .cast()?
+    } else {
+        0
+    };
+
+    Ok(FillReceipt {
+        order_id,
+        status,
+        base_asset_amount_filled,
+        quote_asset_amount_filled,
+        fill_price,
+        fee_paid,
+    })
+}
+
+/// spot leg offsetting a filled perp leg so that perp avg price - spot price stays within the
+/// basis limit: a long perp sells spot at or above avg - basis_limit, a short perp buys spot at
+/// or below it. returns (spot base asset amount, spot limit price)
+pub fn calculate_basis_order_spot_leg(
+    perp_base_asset_amount_filled: u64,
+    perp_quote_asset_amount_filled: u64,
+    perp_direction: PositionDirection,
+    basis_limit: i64,
+    spot_decimals: u32,
+    spot_step_size: u64,
+    spot_tick_size: u64,
+) -> DriftResult<(u64, u64)> {
+    validate!(
+        perp_base_asset_amount_filled > 0,
+        ErrorCode::InvalidBasisOrder,
+        "perp leg did not fill"
+    )?;
+
+    let perp_quote_asset_amount_filled = perp_quote_asset_amount_filled
+        .cast::<u128>()?
+        .safe_mul(BASE_PRECISION)?;
+    let perp_fill_price = match perp_direction {
+        PositionDirection::Long => {
+            perp_quote_asset_amount_filled.safe_div_ceil(perp_base_asset_amount_filled.cast()?)?
+        }
+        PositionDirection::Short => {
+            perp_quote_asset_amount_filled.safe_div(perp_base_asset_amount_filled.cast()?)?
+        }
+    }
+    .cast::<i64>()?;
+
+    let spot_direction = perp_direction.opposite();
+    let spot_price = perp_fill_price.safe_sub(basis_limit)?;
+
+    validate!(
+        spot_price > 0,
+        ErrorCode::InvalidBasisOrder,
+        "perp fill price {} minus basis limit {} leaves no spot price",
+        perp_fill_price,
+        basis_limit
+    )?;
+
+    let spot_price = standardize_price(spot_price.cast()?, spot_tick_size, spot_direction)?;
+
+    let (numerator, denominator) = if spot_decimals >= 9 {
+        (10_u128.pow(spot_decimals - 9), 1)
+    } else {
+        (1, 10_u128.pow(9 - spot_decimals))
+    };
+
+    let scaled_base_asset_amount = perp_base_asset_amount_filled
+        .cast::<u128>()?
+        .safe_mul(numerator)?;
+
+    validate!(
+        scaled_base_asset_amount % denominator == 0,
+        ErrorCode::InvalidBasisOrder,
+        "perp fill of {} has no exact spot amount",
+        perp_base_asset_amount_filled
+    )?;
+
+    let spot_base_asset_amount = scaled_base_asset_amount
+        .safe_div(denominator)?
+        .cast::<u64>()?;
+
+    validate!(
+        is_multiple_of_step_size(spot_base_asset_amount, spot_step_size)?,
+        ErrorCode::InvalidBasisOrder,
+        "spot amount {} is not a multiple of step size {}",
+        spot_base_asset_amount,
+        spot_step_size
+    )?;
+
+    Ok((spot_base_asset_amount, spot_price))
+}
+
+pub fn calculate_twap_slice_base_asset_amount(
+    order: &Order,
+    existing_base_asset_amount: i64,
+    step_size: u64,
+) -> DriftResult<u64> {
+    let base_asset_amount_unfilled =
+        order.get_base_asset_amount_unfilled(Some(existing_base_asset_amount))?;
+
+    if order.twap_slices_remaining <= 1 {
+        return Ok(base_asset_amount_unfilled);
+    }
+
+    let slice_base_asset_amount = standardize_base_asset_amount(
+        order
+            .get_base_asset_amount_unfilled(None)?
+            .safe_div(order.twap_slices_remaining.cast()?)?,
+        step_size,
+    )?
+    .max(step_size);
+
+    Ok(slice_base_asset_amount.min(base_asset_amount_unfilled))
+}
+
+pub fn calculate_scaled_order_prices(
+    start_price: u64,
+    end_price: u64,
+    order_count: u8,
+    tick_size: u64,
+    direction: PositionDirection,
+) -> DriftResult<Vec<u64>> {
+    validate!(
+        order_count >= 2,
+        ErrorCode::InvalidScaleOrderParams,
+        "scale orders require at least two orders"
+    )?;
+
+    let start_price = start_price.cast::<i128>()?;
+    let price_range = end_price.cast::<i128>()?.safe_sub(start_price)?;
+    let last_index = (order_count - 1).cast::<i128>()?;
+
+    let mut prices = Vec::with_capacity(order_count as usize);
+    for i in 0..order_count {
+        let price = start_price
+            .safe_add(price_range.safe_mul(i.cast()?)?.safe_div(last_index)?)?
+            .cast::<u64>()?;
+
+        prices.push(standardize_price(price, tick_size, direction)?);
+    }
+
+    Ok(prices)
+}
+
+pub fn calculate_scaled_order_sizes(
+    total_base_asset_amount: u64,
+    order_count: u8,
+    size_distribution: SizeDistribution,
+    step_size: u64,
+) -> DriftResult<Vec<u64>> {
+    validate!(
+        order_count >= 2,
+        ErrorCode::InvalidScaleOrderParams,
+        "scale orders require at least two orders"
+    )?;
+
+    let total_base_asset_amount =
+        standardize_base_asset_amount(total_base_asset_amount, step_size)?;
+
+    let weights: Vec<u128> = (0..order_count as u128)
+        .map(|i| match size_distribution {
+            SizeDistribution::Flat => 1,
+            SizeDistribution::Ascending => i + 1,
+            SizeDistribution::Descending => order_count as u128 - i,
+        })
+        .collect();
+    let total_weight: u128 = weights.iter().sum();
+
+    let mut sizes = Vec::with_capacity(order_count as usize);
+    let mut base_asset_amount_allocated = 0_u64;
+    for weight in weights.iter().take(weights.len() - 1) {
+        let size = standardize_base_asset_amount(
+            total_base_asset_amount
+                .cast::<u128>()?
+                .safe_mul(*weight)?
+                .safe_div(total_weight)?
+                .cast()?,
+            step_size,
+        )?;
+        base_asset_amount_allocated = base_asset_amount_allocated.safe_add(size)?;
+        sizes.push(size);
+    }
+
+    // last order takes whatever rounding left over
+    sizes.push(total_base_asset_amount.safe_sub(base_asset_amount_allocated)?);
+
+    validate!(
+        sizes.iter().all(|size| *size > 0),
+        ErrorCode::InvalidScaleOrderParams,
+        "total base asset amount {} too small to split into {} orders",
+        total_base_asset_amount,
+        order_count
+    )?;
+
+    Ok(sizes)
+}
+
+pub fn calculate_trailing_trigger_price(
+    order: &Order,
+    oracle_price: u64,
+    tick_size: u64,
+) -> DriftResult<u64> {
+    let trail_amount = match order.trail_type {
+        OrderTrailType::Absolute => order.trail_amount,
+        OrderTrailType::Bps => oracle_price
+            .cast::<u128>()?
+            .safe_mul(order.trail_amount.cast()?)?
+            .safe_div(ONE_BPS_DENOMINATOR.cast()?)?
+            .cast()?,
+    };
+
+    // trigger price only ever moves in the direction favorable to the order
+    match order.trigger_condition {
+        OrderTriggerCondition::TrailingBelow => {
+            let trailing_price = standardize_price(
+                oracle_price.saturating_sub(trail_amount),
+                tick_size,
+                order.direction,
+            )?;
+            Ok(order.trigger_price.max(trailing_price))
+        }
+        OrderTriggerCondition::TrailingAbove => {
+            let trailing_price = standardize_price(
+                oracle_price.safe_add(trail_amount)?,
+                tick_size,
+                order.direction,
+            )?;
+            Ok(order.trigger_price.min(trailing_price))
+        }
         _ => Err(print_error!(ErrorCode::InvalidTriggerOrderCondition)()),
     }
 }
@@ -591,6 +920,7 @@ pub fn find_fallback_maker_order(
     valid_oracle_price: Option<i64>,
     slot: u64,
     tick_size: u64,
+    is_prediction_market: bool,
 ) -> DriftResult<Option<usize>> {
     let mut best_limit_price = match direction {
         PositionDirection::Long => 0,
@@ -616,7 +946,13 @@ pub fn find_fallback_maker_order(
             continue;
         }
 
-        let limit_price = order.force_get_limit_price(valid_oracle_price, None, slot, tick_size)?;
+        let limit_price = order.force_get_limit_price(
+            valid_oracle_price,
+            None,
+            slot,
+            tick_size,
+            is_prediction_market,
+        )?;
 
         // if fallback maker order is not set, set it else check if this order is better
         if fallback_maker_order_index.is_none()
@@ -639,8 +975,9 @@ pub fn find_maker_orders(
     valid_oracle_price: Option<i64>,
     slot: u64,
     tick_size: u64,
+    is_prediction_market: bool,
 ) -> DriftResult<Vec<(usize, u64)>> {
-    let mut orders: Vec<(usize, u64)> = Vec::with_capacity(32);
+    let mut orders: Vec<(usize, u64)> = Vec::with_capacity(MAX_USER_ORDERS);
 
     for (order_index, order) in user.orders.iter().enumerate() {
         if order.status != OrderStatus::Open {
@@ -660,7 +997,24 @@ pub fn find_maker_orders(
             continue;
         }
 
-        let limit_price = order.force_get_limit_price(valid_oracle_price, None, slot, tick_size)?;
+        let mut limit_price = order.force_get_limit_price(
+            valid_oracle_price,
+            None,
+            slot,
+            tick_size,
+            is_prediction_market,
+        )?;
+
+        if user.is_protected_maker && *market_type == MarketType::Perp {
+            if let Some(oracle_price) = valid_oracle_price {
+                limit_price = apply_protected_maker_price_cushion(
+                    limit_price,
+                    order.direction,
+                    oracle_price,
+                    tick_size,
+                )?;
+            }
+        }
 
         orders.push((order_index, limit_price));
     }
@@ -699,10 +1053,19 @@ pub fn calculate_max_perp_order_size(
     let base_asset_amount = perp_position.base_asset_amount;
     let worst_case_base_asset_amount = perp_position.worst_case_base_asset_amount()?;
 
-    let margin_ratio = perp_market.get_margin_ratio(
-        worst_case_base_asset_amount.unsigned_abs(),
-        MarginRequirementType::Initial,
-    )?;
+    let margin_ratio = perp_market
+        .get_margin_ratio(
+            worst_case_base_asset_amount.unsigned_abs(),
+            MarginRequirementType::Initial,
+            user.is_high_leverage_mode_for(market_index),
+        )?
+        .max(calculate_margin_tier_ratio(
+            &perp_market.margin_tiers,
+            calculate_base_asset_value_with_oracle_price(
+                worst_case_base_asset_amount,
+                oracle_price_data.price,
+            )?,
+        )?);
 
     let mut order_size_to_flip = 0_u64;
     // account for order flipping worst case base asset amount
@@ -740,12 +1103,23 @@ pub fn calculate_max_perp_order_size(
         .safe_div(oracle_price_data.price.cast()?)?
         .cast::<u64>()?;
 
-    let updated_margin_ratio = perp_market.get_margin_ratio(
-        worst_case_base_asset_amount
-            .unsigned_abs()
-            .safe_add(order_size.cast()?)?,
-        MarginRequirementType::Initial,
-    )?;
+    let updated_worst_case_base_asset_amount = worst_case_base_asset_amount
+        .unsigned_abs()
+        .safe_add(order_size.cast()?)?;
+
+    let updated_margin_ratio = perp_market
+        .get_margin_ratio(
+            updated_worst_case_base_asset_amount,
+            MarginRequirementType::Initial,
+            user.is_high_leverage_mode_for(market_index),
+        )?
+        .max(calculate_margin_tier_ratio(
+            &perp_market.margin_tiers,
+            calculate_base_asset_value_with_oracle_price(
+                updated_worst_case_base_asset_amount.cast()?,
+                oracle_price_data.price,
+            )?,
+        )?);
 
     if updated_margin_ratio != margin_ratio {
         order_size = free_collateral
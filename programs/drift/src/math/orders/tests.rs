@@ -227,6 +227,7 @@ mod order_breaches_oracle_price_limits {
             tick_size,
             margin_ratio_initial,
             margin_ratio_maintenance,
+            false,
         )
         .unwrap();
 
@@ -259,6 +260,7 @@ mod order_breaches_oracle_price_limits {
             tick_size,
             margin_ratio_initial,
             margin_ratio_maintenance,
+            false,
         )
         .unwrap();
 
@@ -293,6 +295,7 @@ mod order_breaches_oracle_price_limits {
             tick_size,
             margin_ratio_initial,
             margin_ratio_maintenance,
+            false,
         )
         .unwrap();
 
@@ -327,6 +330,7 @@ mod order_breaches_oracle_price_limits {
             tick_size,
             margin_ratio_initial,
             margin_ratio_maintenance,
+            false,
         )
         .unwrap();
 
@@ -361,6 +365,7 @@ mod order_breaches_oracle_price_limits {
             tick_size,
             margin_ratio_initial,
             margin_ratio_maintenance,
+            false,
         )
         .unwrap();
 
@@ -395,6 +400,7 @@ mod order_breaches_oracle_price_limits {
             tick_size,
             margin_ratio_initial,
             margin_ratio_maintenance,
+            false,
         )
         .unwrap();
 
@@ -778,7 +784,7 @@ mod get_max_fill_amounts {
 
 mod find_fallback_maker_order {
     use crate::controller::position::PositionDirection;
-    use crate::math::constants::{PRICE_PRECISION_I64, PRICE_PRECISION_U64};
+    use crate::math::constants::{MAX_USER_ORDERS, PRICE_PRECISION_I64, PRICE_PRECISION_U64};
     use crate::math::orders::find_fallback_maker_order;
     use crate::state::user::{
         MarketType, Order, OrderStatus, OrderTriggerCondition, OrderType, User,
@@ -802,6 +808,7 @@ mod find_fallback_maker_order {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -836,6 +843,7 @@ mod find_fallback_maker_order {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -871,6 +879,7 @@ mod find_fallback_maker_order {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -906,6 +915,7 @@ mod find_fallback_maker_order {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -941,6 +951,7 @@ mod find_fallback_maker_order {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -976,6 +987,7 @@ mod find_fallback_maker_order {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -984,7 +996,7 @@ mod find_fallback_maker_order {
 
     #[test]
     fn only_one_fallback_bid() {
-        let mut orders = [Order::default(); 32];
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
         orders[0] = Order {
             status: OrderStatus::Open,
             order_type: OrderType::Limit,
@@ -1014,6 +1026,7 @@ mod find_fallback_maker_order {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1022,7 +1035,7 @@ mod find_fallback_maker_order {
 
     #[test]
     fn find_best_bid() {
-        let mut orders = [Order::default(); 32];
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
         for (i, order) in orders.iter_mut().enumerate() {
             *order = Order {
                 status: OrderStatus::Open,
@@ -1054,6 +1067,7 @@ mod find_fallback_maker_order {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1062,7 +1076,7 @@ mod find_fallback_maker_order {
 
     #[test]
     fn find_best_ask() {
-        let mut orders = [Order::default(); 32];
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
         for (i, order) in orders.iter_mut().enumerate() {
             *order = Order {
                 status: OrderStatus::Open,
@@ -1094,6 +1108,7 @@ mod find_fallback_maker_order {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1103,7 +1118,7 @@ mod find_fallback_maker_order {
 
 mod find_maker_orders {
     use crate::controller::position::PositionDirection;
-    use crate::math::constants::{PRICE_PRECISION_I64, PRICE_PRECISION_U64};
+    use crate::math::constants::{MAX_USER_ORDERS, PRICE_PRECISION_I64, PRICE_PRECISION_U64};
     use crate::math::orders::find_maker_orders;
     use crate::state::user::{
         MarketType, Order, OrderStatus, OrderTriggerCondition, OrderType, User,
@@ -1127,6 +1142,7 @@ mod find_maker_orders {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1161,6 +1177,7 @@ mod find_maker_orders {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1196,6 +1213,7 @@ mod find_maker_orders {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1231,6 +1249,7 @@ mod find_maker_orders {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1266,6 +1285,7 @@ mod find_maker_orders {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1301,6 +1321,7 @@ mod find_maker_orders {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1309,7 +1330,7 @@ mod find_maker_orders {
 
     #[test]
     fn only_one_maker_bid() {
-        let mut orders = [Order::default(); 32];
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
         orders[0] = Order {
             status: OrderStatus::Open,
             order_type: OrderType::Limit,
@@ -1339,6 +1360,7 @@ mod find_maker_orders {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
@@ -1347,7 +1369,7 @@ mod find_maker_orders {
 
     #[test]
     fn multiple_maker_bids() {
-        let mut orders = [Order::default(); 32];
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
         for (i, order) in orders.iter_mut().enumerate() {
             *order = Order {
                 status: OrderStatus::Open,
@@ -1379,11 +1401,12 @@ mod find_maker_orders {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
         let mut expected_orders = vec![];
-        for i in 0..32 {
+        for i in 0..MAX_USER_ORDERS {
             expected_orders.push((i, (i as u64 + 1) * PRICE_PRECISION_U64));
         }
 
@@ -1392,7 +1415,7 @@ mod find_maker_orders {
 
     #[test]
     fn multiple_asks() {
-        let mut orders = [Order::default(); 32];
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
         for (i, order) in orders.iter_mut().enumerate() {
             *order = Order {
                 status: OrderStatus::Open,
@@ -1424,16 +1447,80 @@ mod find_maker_orders {
             Some(oracle_price),
             slot,
             tick_size,
+            false,
         )
         .unwrap();
 
         let mut expected_orders = vec![];
-        for i in 0..32 {
+        for i in 0..MAX_USER_ORDERS {
             expected_orders.push((i, (i as u64 + 1) * PRICE_PRECISION_U64));
         }
 
         assert_eq!(orders, expected_orders);
     }
+
+    #[test]
+    fn protected_maker() {
+        let mut orders = [Order::default(); MAX_USER_ORDERS];
+        orders[0] = Order {
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            market_index: 0,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Long,
+            price: PRICE_PRECISION_U64,
+            ..Order::default()
+        };
+        orders[1] = Order {
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            market_index: 0,
+            market_type: MarketType::Perp,
+            direction: PositionDirection::Short,
+            price: PRICE_PRECISION_U64,
+            ..Order::default()
+        };
+
+        let user = User {
+            orders,
+            is_protected_maker: true,
+            ..User::default()
+        };
+        let market_type = MarketType::Perp;
+        let market_index = 0;
+        let oracle_price = PRICE_PRECISION_I64;
+        let slot = 0;
+        let tick_size = 1;
+
+        // bid and ask are each cushioned 10 bps of oracle away from their resting price
+        let orders = find_maker_orders(
+            &user,
+            &PositionDirection::Long,
+            &market_type,
+            market_index,
+            Some(oracle_price),
+            slot,
+            tick_size,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(orders, vec![(0, 999_000)]);
+
+        let orders = find_maker_orders(
+            &user,
+            &PositionDirection::Short,
+            &market_type,
+            market_index,
+            Some(oracle_price),
+            slot,
+            tick_size,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(orders, vec![(1, 1_001_000)]);
+    }
 }
 
 mod calculate_max_spot_order_size {
@@ -1443,7 +1530,7 @@ mod calculate_max_spot_order_size {
     use solana_program::pubkey::Pubkey;
 
     use crate::math::constants::{
-        LIQUIDATION_FEE_PRECISION, PRICE_PRECISION_I64, SPOT_BALANCE_PRECISION,
+        LIQUIDATION_FEE_PRECISION, MAX_USER_ORDERS, PRICE_PRECISION_I64, SPOT_BALANCE_PRECISION,
         SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::math::margin::{
@@ -1531,7 +1618,7 @@ mod calculate_max_spot_order_size {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -1637,7 +1724,7 @@ mod calculate_max_spot_order_size {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -1725,7 +1812,7 @@ mod calculate_max_spot_order_size {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -1831,7 +1918,7 @@ mod calculate_max_spot_order_size {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: [PerpPosition::default(); 8],
             spot_positions,
             ..User::default()
@@ -1858,8 +1945,8 @@ mod calculate_max_perp_order_size {
     use solana_program::pubkey::Pubkey;
 
     use crate::math::constants::{
-        SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64, SPOT_CUMULATIVE_INTEREST_PRECISION,
-        SPOT_WEIGHT_PRECISION,
+        MAX_USER_ORDERS, SPOT_BALANCE_PRECISION, SPOT_BALANCE_PRECISION_U64,
+        SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_WEIGHT_PRECISION,
     };
     use crate::math::margin::{
         calculate_margin_requirement_and_total_collateral_and_liability_info, MarginRequirementType,
@@ -1957,7 +2044,7 @@ mod calculate_max_perp_order_size {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 ..PerpPosition::default()
@@ -2073,7 +2160,7 @@ mod calculate_max_perp_order_size {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 base_asset_amount: -500000000000,
@@ -2173,7 +2260,7 @@ mod calculate_max_perp_order_size {
             ..SpotPosition::default()
         };
         let mut user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 ..PerpPosition::default()
@@ -2289,7 +2376,7 @@ mod calculate_max_perp_order_size {
             ..SpotPosition::default()
         };
         let user = User {
-            orders: [Order::default(); 32],
+            orders: [Order::default(); MAX_USER_ORDERS],
             perp_positions: get_positions(PerpPosition {
                 market_index: 0,
                 base_asset_amount: 500000000000,
@@ -2313,3 +2400,436 @@ mod calculate_max_perp_order_size {
         assert_eq!(max_order_size, 999999999000);
     }
 }
+
+mod calculate_trailing_trigger_price {
+    use crate::controller::position::PositionDirection;
+    use crate::math::constants::PRICE_PRECISION_U64;
+    use crate::math::orders::{
+        calculate_trailing_trigger_price, order_satisfies_trigger_condition,
+    };
+    use crate::state::user::{Order, OrderTrailType, OrderTriggerCondition};
+
+    #[test]
+    fn trailing_below_absolute() {
+        let order = Order {
+            direction: PositionDirection::Short,
+            trigger_condition: OrderTriggerCondition::TrailingBelow,
+            trigger_price: 95 * PRICE_PRECISION_U64,
+            trail_type: OrderTrailType::Absolute,
+            trail_amount: 5 * PRICE_PRECISION_U64,
+            ..Order::default()
+        };
+        let tick_size = PRICE_PRECISION_U64 / 1000;
+
+        // oracle moves up, trigger price ratchets up
+        let trigger_price =
+            calculate_trailing_trigger_price(&order, 110 * PRICE_PRECISION_U64, tick_size).unwrap();
+        assert_eq!(trigger_price, 105 * PRICE_PRECISION_U64);
+
+        // oracle moves down, trigger price stays
+        let trigger_price =
+            calculate_trailing_trigger_price(&order, 98 * PRICE_PRECISION_U64, tick_size).unwrap();
+        assert_eq!(trigger_price, 95 * PRICE_PRECISION_U64);
+
+        assert!(order_satisfies_trigger_condition(&order, 94 * PRICE_PRECISION_U64).unwrap());
+        assert!(!order_satisfies_trigger_condition(&order, 96 * PRICE_PRECISION_U64).unwrap());
+    }
+
+    #[test]
+    fn trailing_above_bps() {
+        let order = Order {
+            direction: PositionDirection::Long,
+            trigger_condition: OrderTriggerCondition::TrailingAbove,
+            trigger_price: 105 * PRICE_PRECISION_U64,
+            trail_type: OrderTrailType::Bps,
+            trail_amount: 500, // 5%
+            ..Order::default()
+        };
+        let tick_size = PRICE_PRECISION_U64 / 1000;
+
+        // oracle moves down, trigger price ratchets down
+        let trigger_price =
+            calculate_trailing_trigger_price(&order, 80 * PRICE_PRECISION_U64, tick_size).unwrap();
+        assert_eq!(trigger_price, 84 * PRICE_PRECISION_U64);
+
+        // oracle moves up, trigger price stays
+        let trigger_price =
+            calculate_trailing_trigger_price(&order, 102 * PRICE_PRECISION_U64, tick_size).unwrap();
+        assert_eq!(trigger_price, 105 * PRICE_PRECISION_U64);
+
+        assert!(order_satisfies_trigger_condition(&order, 106 * PRICE_PRECISION_U64).unwrap());
+        assert!(!order_satisfies_trigger_condition(&order, 104 * PRICE_PRECISION_U64).unwrap());
+    }
+
+    #[test]
+    fn non_trailing_condition() {
+        let order = Order {
+            trigger_condition: OrderTriggerCondition::Below,
+            trigger_price: 95 * PRICE_PRECISION_U64,
+            trail_amount: 5 * PRICE_PRECISION_U64,
+            ..Order::default()
+        };
+
+        assert!(calculate_trailing_trigger_price(&order, 110 * PRICE_PRECISION_U64, 1).is_err());
+    }
+}
+
+mod calculate_twap_slice_base_asset_amount {
+    use crate::controller::position::PositionDirection;
+    use crate::math::constants::{BASE_PRECISION_I64, BASE_PRECISION_U64};
+    use crate::math::orders::calculate_twap_slice_base_asset_amount;
+    use crate::state::user::{Order, OrderType};
+
+    #[test]
+    fn slices() {
+        let step_size = BASE_PRECISION_U64 / 10;
+        let mut order = Order {
+            order_type: OrderType::Twap,
+            direction: PositionDirection::Short,
+            base_asset_amount: 10 * BASE_PRECISION_U64,
+            twap_slice_count: 3,
+            twap_slices_remaining: 3,
+            twap_interval: 10,
+            ..Order::default()
+        };
+
+        let slice = calculate_twap_slice_base_asset_amount(&order, 0, step_size).unwrap();
+        assert_eq!(slice, 33 * BASE_PRECISION_U64 / 10);
+
+        order.base_asset_amount_filled = slice;
+        order.twap_slices_remaining = 2;
+        let slice = calculate_twap_slice_base_asset_amount(&order, 0, step_size).unwrap();
+        assert_eq!(slice, 33 * BASE_PRECISION_U64 / 10);
+
+        // last slice fills the remainder
+        order.base_asset_amount_filled += slice;
+        order.twap_slices_remaining = 1;
+        let slice = calculate_twap_slice_base_asset_amount(&order, 0, step_size).unwrap();
+        assert_eq!(slice, 34 * BASE_PRECISION_U64 / 10);
+    }
+
+    #[test]
+    fn reduce_only_slice_capped_by_position() {
+        let step_size = BASE_PRECISION_U64 / 10;
+        let order = Order {
+            order_type: OrderType::Twap,
+            direction: PositionDirection::Short,
+            base_asset_amount: 10 * BASE_PRECISION_U64,
+            reduce_only: true,
+            twap_slice_count: 2,
+            twap_slices_remaining: 2,
+            twap_interval: 10,
+            ..Order::default()
+        };
+
+        let slice =
+            calculate_twap_slice_base_asset_amount(&order, BASE_PRECISION_I64, step_size).unwrap();
+        assert_eq!(slice, BASE_PRECISION_U64);
+    }
+}
+
+mod calculate_scaled_orders {
+    use crate::controller::position::PositionDirection;
+    use crate::math::constants::{BASE_PRECISION_U64, PRICE_PRECISION_U64};
+    use crate::math::orders::{calculate_scaled_order_prices, calculate_scaled_order_sizes};
+    use crate::state::user::SizeDistribution;
+
+    #[test]
+    fn prices() {
+        let tick_size = PRICE_PRECISION_U64 / 100;
+
+        let prices = calculate_scaled_order_prices(
+            100 * PRICE_PRECISION_U64,
+            110 * PRICE_PRECISION_U64,
+            5,
+            tick_size,
+            PositionDirection::Long,
+        )
+        .unwrap();
+        assert_eq!(
+            prices,
+            vec![
+                100 * PRICE_PRECISION_U64,
+                1025 * PRICE_PRECISION_U64 / 10,
+                105 * PRICE_PRECISION_U64,
+                1075 * PRICE_PRECISION_U64 / 10,
+                110 * PRICE_PRECISION_U64,
+            ]
+        );
+
+        // descending price range
+        let prices = calculate_scaled_order_prices(
+            110 * PRICE_PRECISION_U64,
+            100 * PRICE_PRECISION_U64,
+            3,
+            tick_size,
+            PositionDirection::Short,
+        )
+        .unwrap();
+        assert_eq!(
+            prices,
+            vec![
+                110 * PRICE_PRECISION_U64,
+                105 * PRICE_PRECISION_U64,
+                100 * PRICE_PRECISION_U64,
+            ]
+        );
+
+        let result = calculate_scaled_order_prices(
+            100 * PRICE_PRECISION_U64,
+            110 * PRICE_PRECISION_U64,
+            1,
+            tick_size,
+            PositionDirection::Long,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn sizes() {
+        let step_size = BASE_PRECISION_U64 / 10;
+        let total = 10 * BASE_PRECISION_U64;
+
+        let sizes =
+            calculate_scaled_order_sizes(total, 4, SizeDistribution::Flat, step_size).unwrap();
+        assert_eq!(sizes, vec![25 * BASE_PRECISION_U64 / 10; 4]);
+
+        let sizes =
+            calculate_scaled_order_sizes(total, 4, SizeDistribution::Ascending, step_size).unwrap();
+        assert_eq!(
+            sizes,
+            vec![
+                BASE_PRECISION_U64,
+                2 * BASE_PRECISION_U64,
+                3 * BASE_PRECISION_U64,
+                4 * BASE_PRECISION_U64,
+            ]
+        );
+
+        let sizes = calculate_scaled_order_sizes(total, 4, SizeDistribution::Descending, step_size)
+            .unwrap();
+        assert_eq!(
+            sizes,
+            vec![
+                4 * BASE_PRECISION_U64,
+                3 * BASE_PRECISION_U64,
+                2 * BASE_PRECISION_U64,
+                BASE_PRECISION_U64,
+            ]
+        );
+    }
+
+    #[test]
+    fn last_order_takes_remainder() {
+        let step_size = BASE_PRECISION_U64 / 10;
+
+        let sizes =
+            calculate_scaled_order_sizes(BASE_PRECISION_U64, 3, SizeDistribution::Flat, step_size)
+                .unwrap();
+        assert_eq!(
+            sizes,
+            vec![
+                3 * BASE_PRECISION_U64 / 10,
+                3 * BASE_PRECISION_U64 / 10,
+                4 * BASE_PRECISION_U64 / 10,
+            ]
+        );
+    }
+
+    #[test]
+    fn total_too_small() {
+        let step_size = BASE_PRECISION_U64 / 10;
+
+        let result =
+            calculate_scaled_order_sizes(2 * step_size, 3, SizeDistribution::Flat, step_size);
+        assert!(result.is_err());
+    }
+}
+
+mod order_satisfies_funding_trigger_condition {
+    use crate::math::orders::order_satisfies_funding_trigger_condition;
+    use crate::state::user::{Order, OrderTriggerCondition, OrderType};
+
+    #[test]
+    fn funding_flips_sign() {
+        let mut order = Order {
+            order_type: OrderType::TriggerMarket,
+            trigger_condition: OrderTriggerCondition::FundingBelow,
+            trigger_funding_rate: 0,
+            ..Order::default()
+        };
+
+        assert!(!order_satisfies_funding_trigger_condition(&order, 1000).unwrap());
+        assert!(!order_satisfies_funding_trigger_condition(&order, 0).unwrap());
+        assert!(order_satisfies_funding_trigger_condition(&order, -1000).unwrap());
+
+        order.trigger_condition = OrderTriggerCondition::FundingAbove;
+        assert!(order_satisfies_funding_trigger_condition(&order, 1000).unwrap());
+        assert!(!order_satisfies_funding_trigger_condition(&order, -1000).unwrap());
+
+        order.trigger_condition = OrderTriggerCondition::Above;
+        assert!(order_satisfies_funding_trigger_condition(&order, 1000).is_err());
+    }
+}
+
+mod calculate_fill_receipt {
+    use crate::math::constants::{BASE_PRECISION_U64, PRICE_PRECISION_U64, QUOTE_PRECISION_U64};
+    use crate::math::orders::calculate_fill_receipt;
+    use crate::state::user::{Order, OrderStatus};
+
+    #[test]
+    fn partial_fill_still_open() {
+        let order = Order {
+            order_id: 1,
+            status: OrderStatus::Open,
+            ..Order::default()
+        };
+
+        let receipt = calculate_fill_receipt(
+            1,
+            2 * BASE_PRECISION_U64,
+            Some(&order),
+            BASE_PRECISION_U64,
+            100 * QUOTE_PRECISION_U64,
+            50_000,
+        )
+        .unwrap();
+
+        assert_eq!(receipt.status, OrderStatus::Open);
+        assert_eq!(receipt.fill_price, 100 * PRICE_PRECISION_U64);
+        assert_eq!(receipt.fee_paid, 50_000);
+    }
+
+    #[test]
+    fn removed_order() {
+        let receipt = calculate_fill_receipt(
+            1,
+            BASE_PRECISION_U64,
+            None,
+            BASE_PRECISION_U64,
+            150 * QUOTE_PRECISION_U64,
+            0,
+        )
+        .unwrap();
+        assert_eq!(receipt.status, OrderStatus::Filled);
+        assert_eq!(receipt.fill_price, 150 * PRICE_PRECISION_U64);
+
+        // immediate or cancel order canceled after a partial fill
+        let receipt = calculate_fill_receipt(
+            1,
+            2 * BASE_PRECISION_U64,
+            None,
+            BASE_PRECISION_U64,
+            150 * QUOTE_PRECISION_U64,
+            0,
+        )
+        .unwrap();
+        assert_eq!(receipt.status, OrderStatus::Canceled);
+
+        let receipt = calculate_fill_receipt(1, BASE_PRECISION_U64, None, 0, 0, 0).unwrap();
+        assert_eq!(receipt.status, OrderStatus::Canceled);
+        assert_eq!(receipt.fill_price, 0);
+    }
+}
+
+mod calculate_basis_order_spot_leg {
+    use crate::controller::position::PositionDirection;
+    use crate::error::ErrorCode;
+    use crate::math::constants::{BASE_PRECISION_U64, PRICE_PRECISION_I64, QUOTE_PRECISION_U64};
+    use crate::math::orders::calculate_basis_order_spot_leg;
+
+    #[test]
+    fn long_perp_sells_spot_at_basis() {
+        let (base_asset_amount, price) = calculate_basis_order_spot_leg(
+            BASE_PRECISION_U64,
+            100 * QUOTE_PRECISION_U64,
+            PositionDirection::Long,
+            PRICE_PRECISION_I64,
+            9,
+            BASE_PRECISION_U64 / 1000,
+            100,
+        )
+        .unwrap();
+
+        assert_eq!(base_asset_amount, BASE_PRECISION_U64);
+        assert_eq!(price, 99 * QUOTE_PRECISION_U64);
+    }
+
+    #[test]
+    fn short_perp_buys_spot_rounded_down() {
+        // avg fill price 100.000333, basis -0.5
+        let (base_asset_amount, price) = calculate_basis_order_spot_leg(
+            3 * BASE_PRECISION_U64,
+            300_001_000,
+            PositionDirection::Short,
+            -PRICE_PRECISION_I64 / 2,
+            6,
+            1000,
+            100,
+        )
+        .unwrap();
+
+        assert_eq!(base_asset_amount, 3_000_000);
+        assert_eq!(price, 100_500_300);
+    }
+
+    #[test]
+    fn long_perp_sells_spot_rounded_up() {
+        // avg fill price 100.000334 rounded up, basis 0
+        let (_, price) = calculate_basis_order_spot_leg(
+            3 * BASE_PRECISION_U64,
+            300_001_000,
+            PositionDirection::Long,
+            0,
+            6,
+            1000,
+            100,
+        )
+        .unwrap();
+
+        assert_eq!(price, 100_000_400);
+    }
+
+    #[test]
+    fn invalid_spot_leg() {
+        // basis above perp price
+        let result = calculate_basis_order_spot_leg(
+            BASE_PRECISION_U64,
+            100 * QUOTE_PRECISION_U64,
+            PositionDirection::Long,
+            100 * PRICE_PRECISION_I64,
+            9,
+            1,
+            1,
+        );
+        assert_eq!(result, Err(ErrorCode::InvalidBasisOrder));
+
+        // perp fill finer than spot decimals
+        let result = calculate_basis_order_spot_leg(
+            BASE_PRECISION_U64 + 500,
+            100 * QUOTE_PRECISION_U64,
+            PositionDirection::Long,
+            0,
+            6,
+            1,
+            1,
+        );
+        assert_eq!(result, Err(ErrorCode::InvalidBasisOrder));
+
+        // spot amount not a multiple of step size
+        let result = calculate_basis_order_spot_leg(
+            BASE_PRECISION_U64 + 1000,
+            100 * QUOTE_PRECISION_U64,
+            PositionDirection::Long,
+            0,
+            6,
+            1000,
+            1,
+        );
+        assert_eq!(result, Err(ErrorCode::InvalidBasisOrder));
+
+        // perp leg unfilled
+        let result = calculate_basis_order_spot_leg(0, 0, PositionDirection::Long, 0, 9, 1, 1);
+        assert_eq!(result, Err(ErrorCode::InvalidBasisOrder));
+    }
+}
@@ -0,0 +1,28 @@
+#[cfg(test)]
+mod tests;
+
+use crate::controller::position::PositionDirection;
+use crate::error::DriftResult;
+use crate::math::casting::Cast;
+use crate::math::constants::PRICE_TO_QUOTE_PRECISION_RATIO;
+use crate::math::safe_math::SafeMath;
+
+// calculate limit price in phoenix ticks, rounding against the taker
+pub fn calculate_phoenix_price_in_ticks(
+    limit_price: u64,
+    tick_size_in_quote_atoms_per_base_unit: u64,
+    direction: PositionDirection,
+) -> DriftResult<u64> {
+    let quote_atoms_per_base_unit = limit_price
+        .cast::<u128>()?
+        .safe_div(PRICE_TO_QUOTE_PRECISION_RATIO)?;
+
+    match direction {
+        PositionDirection::Long => quote_atoms_per_base_unit
+            .safe_div(tick_size_in_quote_atoms_per_base_unit.cast()?)?
+            .cast(),
+        PositionDirection::Short => quote_atoms_per_base_unit
+            .safe_div_ceil(tick_size_in_quote_atoms_per_base_unit.cast()?)?
+            .cast(),
+    }
+}
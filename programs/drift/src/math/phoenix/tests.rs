@@ -0,0 +1,32 @@
+use crate::controller::position::PositionDirection;
+use crate::math::phoenix::calculate_phoenix_price_in_ticks;
+
+#[test]
+fn test_calculate_phoenix_price_in_ticks_bid() {
+    let limit_price = 21359900;
+    let tick_size_in_quote_atoms_per_base_unit = 1000;
+
+    let price_in_ticks = calculate_phoenix_price_in_ticks(
+        limit_price,
+        tick_size_in_quote_atoms_per_base_unit,
+        PositionDirection::Long,
+    )
+    .unwrap();
+
+    assert_eq!(price_in_ticks, 21359);
+}
+
+#[test]
+fn test_calculate_phoenix_price_in_ticks_ask() {
+    let limit_price = 21359900;
+    let tick_size_in_quote_atoms_per_base_unit = 1000;
+
+    let price_in_ticks = calculate_phoenix_price_in_ticks(
+        limit_price,
+        tick_size_in_quote_atoms_per_base_unit,
+        PositionDirection::Short,
+    )
+    .unwrap();
+
+    assert_eq!(price_in_ticks, 21360);
+}
@@ -8,7 +8,8 @@ use crate::math::amm;
 use crate::math::bn;
 use crate::math::casting::Cast;
 use crate::math::constants::{
-    AMM_RESERVE_PRECISION_I128, BID_ASK_SPREAD_PRECISION_U128, PEG_PRECISION_I128,
+    AMM_RESERVE_PRECISION_I128, BID_ASK_SPREAD_PRECISION_U128, MAX_PEG_BPS_UPDATE,
+    PEG_BPS_UPDATE_SCALE, PEG_PRECISION_I128, PEG_UPDATE_FEE_POOL_DIVISOR,
     PRICE_TO_PEG_PRECISION_RATIO, SHARE_OF_FEES_ALLOCATED_TO_DRIFT_DENOMINATOR,
     SHARE_OF_FEES_ALLOCATED_TO_DRIFT_NUMERATOR,
 };
@@ -38,7 +39,9 @@ pub fn calculate_repeg_validity_from_oracle_account(
     let oracle_is_valid = oracle::oracle_validity(
         market.amm.historical_oracle_data.last_oracle_price_twap,
         &oracle_price_data,
-        &oracle_guard_rails.validity,
+        &oracle_guard_rails
+            .validity
+            .with_override(&market.amm.validity_guard_rails_override),
     )? == OracleValidity::Valid;
 
     let (oracle_is_valid, direction_valid, profitability_valid, price_impact_valid) =
@@ -436,6 +439,37 @@ pub fn calculate_optimal_peg_and_budget(
     Ok((optimal_peg, fee_budget, check_lower_bound))
 }
 
+pub fn calculate_bounded_peg_and_budget(
+    market: &PerpMarket,
+    oracle_price_data: &OraclePriceData,
+) -> DriftResult<(u128, u128)> {
+    let (optimal_peg, _, _) = calculate_optimal_peg_and_budget(market, oracle_price_data)?;
+
+    // peg can only move a fraction of the current peg per update, scaled by curve_update_intensity
+    let curve_update_intensity = min(market.amm.curve_update_intensity, 100_u8).cast::<u128>()?;
+    let max_peg_delta = market
+        .amm
+        .peg_multiplier
+        .safe_mul(MAX_PEG_BPS_UPDATE)?
+        .safe_mul(curve_update_intensity)?
+        .safe_div(100)?
+        .safe_div(PEG_BPS_UPDATE_SCALE)?
+        .max(1);
+
+    let bounded_peg = optimal_peg.clamp(
+        market
+            .amm
+            .peg_multiplier
+            .saturating_sub(max_peg_delta)
+            .max(1),
+        market.amm.peg_multiplier.safe_add(max_peg_delta)?,
+    );
+
+    let fee_budget = calculate_fee_pool(market)?.safe_div(PEG_UPDATE_FEE_POOL_DIVISOR)?;
+
+    Ok((bounded_peg, fee_budget))
+}
+
 pub fn calculate_fee_pool(market: &PerpMarket) -> DriftResult<u128> {
     let total_fee_minus_distributions_lower_bound = get_total_fee_lower_bound(market)?.cast()?;
 
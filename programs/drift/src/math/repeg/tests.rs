@@ -336,3 +336,72 @@ fn calc_adjust_amm_tests_sufficent_fee_for_repeg() {
     assert_eq!(new_peg, 34657283);
     assert_eq!(_amm_update_cost, 304289);
 }
+
+#[test]
+fn calculate_bounded_peg_and_budget_test() {
+    let mut market = PerpMarket {
+        amm: AMM {
+            base_asset_reserve: 65 * AMM_RESERVE_PRECISION,
+            quote_asset_reserve: 63015384615,
+            terminal_quote_asset_reserve: 64 * AMM_RESERVE_PRECISION,
+            sqrt_k: 64 * AMM_RESERVE_PRECISION,
+            peg_multiplier: 19_400_000_000,
+            base_asset_amount_with_amm: -(AMM_RESERVE_PRECISION as i128),
+            mark_std: PRICE_PRECISION as u64,
+            last_mark_price_twap_ts: 0,
+            base_spread: 250,
+            curve_update_intensity: 100,
+            max_spread: 500 * 100,
+            total_exchange_fee: QUOTE_PRECISION,
+            total_fee_minus_distributions: (40 * QUOTE_PRECISION) as i128,
+            ..AMM::default()
+        },
+        margin_ratio_initial: 500,
+
+        ..PerpMarket::default()
+    };
+
+    // oracle within 1% of the current peg
+    let oracle_price_data = OraclePriceData {
+        price: (18_900 * PRICE_PRECISION) as i64,
+        confidence: 0,
+        delay: 2,
+        has_sufficient_number_of_data_points: true,
+    };
+
+    let (bounded_peg, budget) =
+        calculate_bounded_peg_and_budget(&market, &oracle_price_data).unwrap();
+    assert_eq!(
+        bounded_peg,
+        calculate_peg_from_target_price(
+            market.amm.quote_asset_reserve,
+            market.amm.base_asset_reserve,
+            oracle_price_data.price as u64,
+        )
+        .unwrap()
+    );
+    assert_eq!(bounded_peg, 19495239257);
+    // 10% of the fee pool above the 0.5 lower bound
+    assert_eq!(budget, 3_950_000);
+
+    // oracle far below, peg can only move 1%
+    let oracle_price_data = OraclePriceData {
+        price: (12_400 * PRICE_PRECISION) as i64,
+        confidence: 0,
+        delay: 2,
+        has_sufficient_number_of_data_points: true,
+    };
+
+    let (bounded_peg, _) = calculate_bounded_peg_and_budget(&market, &oracle_price_data).unwrap();
+    assert_eq!(bounded_peg, 19_206_000_000);
+
+    // bound scales with curve_update_intensity
+    market.amm.curve_update_intensity = 50;
+    let (bounded_peg, _) = calculate_bounded_peg_and_budget(&market, &oracle_price_data).unwrap();
+    assert_eq!(bounded_peg, 19_303_000_000);
+
+    // no budget without fees above the lower bound
+    market.amm.total_fee_minus_distributions = 0;
+    let (_, budget) = calculate_bounded_peg_and_budget(&market, &oracle_price_data).unwrap();
+    assert_eq!(budget, 0);
+}
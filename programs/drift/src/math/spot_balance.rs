@@ -2,13 +2,19 @@ use solana_program::msg;
 
 use crate::error::{DriftResult, ErrorCode};
 use crate::math::casting::Cast;
-use crate::math::constants::{ONE_YEAR, SPOT_RATE_PRECISION, SPOT_UTILIZATION_PRECISION};
+use crate::math::constants::{
+    ONE_BPS_DENOMINATOR, ONE_YEAR, SPOT_RATE_PRECISION, SPOT_UTILIZATION_PRECISION,
+};
 use crate::math::safe_math::{SafeDivFloor, SafeMath};
+use crate::math::safe_unwrap::SafeUnwrap;
 use crate::state::oracle::OraclePriceData;
 use crate::state::spot_market::{SpotBalanceType, SpotMarket};
 use crate::state::user::SpotPosition;
 use crate::validate;
 
+#[cfg(test)]
+mod tests;
+
 pub fn get_spot_balance(
     token_amount: u128,
     spot_market: &SpotMarket,
@@ -81,6 +87,35 @@ pub fn get_interest_token_amount(
     Ok(token_amount)
 }
 
+pub fn calculate_flash_borrow_fee(amount: u64, flash_borrow_fee_bps: u16) -> DriftResult<u64> {
+    amount
+        .cast::<u128>()?
+        .safe_mul(flash_borrow_fee_bps.cast()?)?
+        .safe_div_ceil(ONE_BPS_DENOMINATOR.cast()?)?
+        .cast()
+}
+
+// increase in cumulative_deposit_interest that spreads `fee` tokens across current depositors
+pub fn calculate_cumulative_deposit_interest_delta_for_fee(
+    fee: u128,
+    spot_market: &SpotMarket,
+) -> DriftResult<u128> {
+    let total_deposits = get_token_amount(
+        spot_market.deposit_balance,
+        spot_market,
+        &SpotBalanceType::Deposit,
+    )?;
+
+    if total_deposits == 0 {
+        return Ok(0);
+    }
+
+    spot_market
+        .cumulative_deposit_interest
+        .safe_mul(fee)?
+        .safe_div(total_deposits)
+}
+
 pub struct InterestAccumulated {
     pub borrow_interest: u128,
     pub deposit_interest: u128,
@@ -121,6 +156,47 @@ pub fn calculate_spot_market_utilization(spot_market: &SpotMarket) -> DriftResul
     Ok(utilization)
 }
 
+pub fn calculate_borrow_rate(spot_market: &SpotMarket, utilization: u128) -> DriftResult<u128> {
+    // piecewise linear through zero, the optimal kink, any extra kinks and the max rate at 100%
+    let mut curve = vec![
+        (0_u128, 0_u128),
+        (
+            spot_market.optimal_utilization.cast::<u128>()?,
+            spot_market.optimal_borrow_rate.cast::<u128>()?,
+        ),
+    ];
+    for kink in spot_market.borrow_rate_kinks.iter() {
+        if kink.is_active() {
+            curve.push((kink.utilization.cast()?, kink.borrow_rate.cast()?));
+        }
+    }
+    curve.push((
+        SPOT_UTILIZATION_PRECISION,
+        spot_market.max_borrow_rate.cast()?,
+    ));
+
+    // utilization above 100% extends the last segment
+    let segment = curve
+        .windows(2)
+        .find(|points| utilization <= points[1].0)
+        .or_else(|| curve.windows(2).last())
+        .safe_unwrap()?;
+    let (start_utilization, start_borrow_rate) = segment[0];
+    let (end_utilization, end_borrow_rate) = segment[1];
+
+    let borrow_rate_slope = end_borrow_rate
+        .safe_sub(start_borrow_rate)?
+        .safe_mul(SPOT_UTILIZATION_PRECISION)?
+        .safe_div(end_utilization.safe_sub(start_utilization)?)?;
+
+    start_borrow_rate.safe_add(
+        utilization
+            .safe_sub(start_utilization)?
+            .safe_mul(borrow_rate_slope)?
+            .safe_div(SPOT_UTILIZATION_PRECISION)?,
+    )
+}
+
 pub fn calculate_accumulated_interest(
     spot_market: &SpotMarket,
     now: i64,
@@ -134,34 +210,7 @@ pub fn calculate_accumulated_interest(
         });
     }
 
-    let borrow_rate = if utilization > spot_market.optimal_utilization.cast()? {
-        let surplus_utilization = utilization.safe_sub(spot_market.optimal_utilization.cast()?)?;
-
-        let borrow_rate_slope = spot_market
-            .max_borrow_rate
-            .cast::<u128>()?
-            .safe_sub(spot_market.optimal_borrow_rate.cast()?)?
-            .safe_mul(SPOT_UTILIZATION_PRECISION)?
-            .safe_div(
-                SPOT_UTILIZATION_PRECISION.safe_sub(spot_market.optimal_utilization.cast()?)?,
-            )?;
-
-        spot_market.optimal_borrow_rate.cast::<u128>()?.safe_add(
-            surplus_utilization
-                .safe_mul(borrow_rate_slope)?
-                .safe_div(SPOT_UTILIZATION_PRECISION)?,
-        )?
-    } else {
-        let borrow_rate_slope = spot_market
-            .optimal_borrow_rate
-            .cast::<u128>()?
-            .safe_mul(SPOT_UTILIZATION_PRECISION)?
-            .safe_div(spot_market.optimal_utilization.cast()?)?;
-
-        utilization
-            .safe_mul(borrow_rate_slope)?
-            .safe_div(SPOT_UTILIZATION_PRECISION)?
-    };
+    let borrow_rate = calculate_borrow_rate(spot_market, utilization)?;
 
     let time_since_last_update = now
         .cast::<u64>()
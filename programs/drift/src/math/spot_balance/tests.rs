@@ -0,0 +1,152 @@
+use crate::math::constants::{
+    SPOT_BALANCE_PRECISION, SPOT_CUMULATIVE_INTEREST_PRECISION, SPOT_RATE_PRECISION_U32,
+    SPOT_UTILIZATION_PRECISION_U32,
+};
+use crate::math::spot_balance::{
+    calculate_borrow_rate, calculate_cumulative_deposit_interest_delta_for_fee,
+    calculate_flash_borrow_fee, get_token_amount,
+};
+use crate::state::spot_market::{BorrowRateKink, SpotBalanceType, SpotMarket};
+
+#[test]
+fn borrow_rate_single_kink() {
+    let spot_market = SpotMarket {
+        optimal_utilization: SPOT_UTILIZATION_PRECISION_U32 * 8 / 10, // 80%
+        optimal_borrow_rate: SPOT_RATE_PRECISION_U32 / 10,            // 10%
+        max_borrow_rate: SPOT_RATE_PRECISION_U32,                     // 100%
+        ..SpotMarket::default()
+    };
+
+    assert_eq!(calculate_borrow_rate(&spot_market, 0).unwrap(), 0);
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 400_000).unwrap(),
+        50_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 800_000).unwrap(),
+        100_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 900_000).unwrap(),
+        550_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 1_000_000).unwrap(),
+        1_000_000
+    );
+    // above 100% utilization extends the last segment
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 1_100_000).unwrap(),
+        1_450_000
+    );
+}
+
+#[test]
+fn borrow_rate_multiple_kinks() {
+    let spot_market = SpotMarket {
+        optimal_utilization: SPOT_UTILIZATION_PRECISION_U32 * 7 / 10, // 70%
+        optimal_borrow_rate: SPOT_RATE_PRECISION_U32 * 7 / 100,       // 7%
+        max_borrow_rate: SPOT_RATE_PRECISION_U32 * 3,                 // 300%
+        borrow_rate_kinks: [
+            BorrowRateKink {
+                utilization: SPOT_UTILIZATION_PRECISION_U32 * 9 / 10, // 90%
+                borrow_rate: SPOT_RATE_PRECISION_U32 / 5,             // 20%
+            },
+            BorrowRateKink {
+                utilization: SPOT_UTILIZATION_PRECISION_U32 * 95 / 100, // 95%
+                borrow_rate: SPOT_RATE_PRECISION_U32 / 2,               // 50%
+            },
+        ],
+        ..SpotMarket::default()
+    };
+
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 350_000).unwrap(),
+        25_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 700_000).unwrap(),
+        50_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 800_000).unwrap(),
+        125_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 900_000).unwrap(),
+        200_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 925_000).unwrap(),
+        350_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 950_000).unwrap(),
+        500_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 975_000).unwrap(),
+        1_750_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 1_000_000).unwrap(),
+        3_000_000
+    );
+
+    // unused kinks are skipped
+    let spot_market = SpotMarket {
+        borrow_rate_kinks: [spot_market.borrow_rate_kinks[0], BorrowRateKink::default()],
+        ..spot_market
+    };
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 900_000).unwrap(),
+        200_000
+    );
+    assert_eq!(
+        calculate_borrow_rate(&spot_market, 950_000).unwrap(),
+        1_600_000
+    );
+}
+
+#[test]
+fn flash_borrow_fee() {
+    assert_eq!(calculate_flash_borrow_fee(1_000_000, 0).unwrap(), 0);
+    assert_eq!(calculate_flash_borrow_fee(1_000_000, 5).unwrap(), 500); // 5 bps
+    assert_eq!(
+        calculate_flash_borrow_fee(1_000_000, 10000).unwrap(),
+        1_000_000
+    );
+
+    // rounds up so small borrows still pay
+    assert_eq!(calculate_flash_borrow_fee(1, 5).unwrap(), 1);
+}
+
+#[test]
+fn flash_borrow_fee_credited_to_depositors() {
+    let mut spot_market = SpotMarket {
+        decimals: 6,
+        cumulative_deposit_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        cumulative_borrow_interest: SPOT_CUMULATIVE_INTEREST_PRECISION,
+        deposit_balance: 1000 * SPOT_BALANCE_PRECISION,
+        ..SpotMarket::default()
+    };
+
+    // 1000 tokens in deposits earning a 10 token fee
+    let delta =
+        calculate_cumulative_deposit_interest_delta_for_fee(10_000_000, &spot_market).unwrap();
+    assert_eq!(delta, SPOT_CUMULATIVE_INTEREST_PRECISION / 100);
+
+    spot_market.cumulative_deposit_interest += delta;
+    let deposits = get_token_amount(
+        spot_market.deposit_balance,
+        &spot_market,
+        &SpotBalanceType::Deposit,
+    )
+    .unwrap();
+    assert_eq!(deposits, 1_010_000_000);
+
+    spot_market.deposit_balance = 0;
+    let delta =
+        calculate_cumulative_deposit_interest_delta_for_fee(10_000_000, &spot_market).unwrap();
+    assert_eq!(delta, 0);
+}
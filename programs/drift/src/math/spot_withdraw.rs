@@ -2,6 +2,7 @@ use solana_program::msg;
 
 use crate::error::{DriftResult, ErrorCode};
 use crate::math::casting::Cast;
+use crate::math::constants::ONE_BPS_DENOMINATOR;
 use crate::math::safe_math::SafeMath;
 
 use crate::math::spot_balance::get_token_amount;
@@ -136,6 +137,62 @@ pub fn check_withdraw_limits(
     Ok(valid_withdrawal)
 }
 
+pub fn check_max_token_borrows(spot_market: &SpotMarket) -> DriftResult<bool> {
+    if spot_market.max_token_borrows == 0 {
+        return Ok(true);
+    }
+
+    let borrow_token_amount = get_token_amount(
+        spot_market.borrow_balance,
+        spot_market,
+        &SpotBalanceType::Borrow,
+    )?;
+
+    Ok(borrow_token_amount <= spot_market.max_token_borrows.cast()?)
+}
+
+pub fn calculate_min_deposit_token_for_net_withdraws(
+    deposit_token_twap: u128,
+    max_net_withdraw_bps: u128,
+) -> DriftResult<u128> {
+    let max_net_withdraw = deposit_token_twap
+        .safe_mul(max_net_withdraw_bps.min(ONE_BPS_DENOMINATOR.cast()?))?
+        .safe_div(ONE_BPS_DENOMINATOR.cast()?)?;
+
+    deposit_token_twap.safe_sub(max_net_withdraw)
+}
+
+// deposit_token_twap trails deposits over 24 hours, so deposits falling too far below it
+// means too much has left the market within the window
+pub fn check_net_withdraw_limit(spot_market: &SpotMarket) -> DriftResult<bool> {
+    if spot_market.max_net_withdraw_bps == 0 {
+        return Ok(true);
+    }
+
+    let deposit_token_amount = get_token_amount(
+        spot_market.deposit_balance,
+        spot_market,
+        &SpotBalanceType::Deposit,
+    )?;
+
+    let min_deposit_token = calculate_min_deposit_token_for_net_withdraws(
+        spot_market.deposit_token_twap.cast()?,
+        spot_market.max_net_withdraw_bps.cast()?,
+    )?;
+
+    if deposit_token_amount < min_deposit_token {
+        msg!(
+            "max_net_withdraw_bps={:?}",
+            spot_market.max_net_withdraw_bps
+        );
+        msg!("min_deposit_token={:?}", min_deposit_token);
+        msg!("deposit_token_amount={:?}", deposit_token_amount);
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
 pub fn get_max_withdraw_for_market_with_token_amount(
     spot_market: &SpotMarket,
     token_amount: i128,
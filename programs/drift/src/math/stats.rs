@@ -1,7 +1,7 @@
 use crate::error::DriftResult;
 use crate::math::casting::Cast;
 use crate::math::safe_math::SafeMath;
-use std::cmp::max;
+use std::cmp::{max, min};
 
 pub fn calculate_rolling_sum(
     data1: u64,
@@ -64,3 +64,23 @@ pub fn calculate_new_twap(
 
     calculate_weighted_average(current_price, last_twap, since_last, from_start)
 }
+
+/// moves a rolling high or low toward the latest price by the share of the period since it was
+/// last updated, reaching the latest price once a full period has passed
+pub fn calculate_rolling_extreme(
+    last_extreme: u64,
+    price: u64,
+    since_last: i64,
+    period: i64,
+) -> DriftResult<u64> {
+    let elapsed = max(0_i64, min(since_last, period));
+    let last_extreme = last_extreme.cast::<i128>()?;
+
+    price
+        .cast::<i128>()?
+        .safe_sub(last_extreme)?
+        .safe_mul(elapsed.cast()?)?
+        .safe_div(period.cast()?)?
+        .safe_add(last_extreme)?
+        .cast()
+}
@@ -0,0 +1,169 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::state::oracle::OracleSource;
+use crate::state::perp_market::ContractTier;
+use crate::state::spot_market::{AssetTier, BorrowRateKink};
+use crate::state::state::{FeeStructure, OracleGuardRails};
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+/// risk-critical parameter change that has to sit in the queue for the state's admin timelock duration
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum AdminChange {
+    PerpMarketMarginRatio {
+        market_index: u16,
+        margin_ratio_initial: u32,
+        margin_ratio_maintenance: u32,
+    },
+    SpotMarketMarginWeights {
+        market_index: u16,
+        initial_asset_weight: u32,
+        maintenance_asset_weight: u32,
+        initial_liability_weight: u32,
+        maintenance_liability_weight: u32,
+        imf_factor: u32,
+    },
+    PerpMarketOracle {
+        market_index: u16,
+        oracle: Pubkey,
+        oracle_source: OracleSource,
+    },
+    SpotMarketOracle {
+        market_index: u16,
+        oracle: Pubkey,
+        oracle_source: OracleSource,
+    },
+    PerpFeeStructure {
+        fee_structure: FeeStructure,
+    },
+    SpotFeeStructure {
+        fee_structure: FeeStructure,
+    },
+    AdminTimelockDuration {
+        duration: u32,
+    },
+    OracleGuardRails {
+        oracle_guard_rails: OracleGuardRails,
+    },
+    PerpMarketImfFactor {
+        market_index: u16,
+        imf_factor: u32,
+        unrealized_pnl_imf_factor: u32,
+    },
+    PerpMarketLiquidationFee {
+        market_index: u16,
+        liquidator_fee: u32,
+        if_liquidation_fee: u32,
+    },
+    SpotMarketLiquidationFee {
+        market_index: u16,
+        liquidator_fee: u32,
+        if_liquidation_fee: u32,
+    },
+    SpotMarketAssetTier {
+        market_index: u16,
+        asset_tier: AssetTier,
+    },
+    PerpMarketContractTier {
+        market_index: u16,
+        contract_tier: ContractTier,
+    },
+    SpotMarketBorrowRate {
+        market_index: u16,
+        optimal_utilization: u32,
+        optimal_borrow_rate: u32,
+        max_borrow_rate: u32,
+    },
+    SpotMarketBorrowRateKinks {
+        market_index: u16,
+        borrow_rate_kinks: [BorrowRateKink; 2],
+    },
+    PerpMarketFundingRateCaps {
+        market_index: u16,
+        max_funding_rate_spread: u32,
+        escalated_max_funding_rate_spread: u32,
+        funding_divergence_escalation_periods: u8,
+    },
+    PerpMarketMaxOpenInterest {
+        market_index: u16,
+        max_open_interest: u128,
+    },
+}
+
+impl AdminChange {
+    fn kind(&self) -> u8 {
+        match self {
+            AdminChange::PerpMarketMarginRatio { .. } => 0,
+            AdminChange::SpotMarketMarginWeights { .. } => 1,
+            AdminChange::PerpMarketOracle { .. } => 2,
+            AdminChange::SpotMarketOracle { .. } => 3,
+            AdminChange::PerpFeeStructure { .. } => 4,
+            AdminChange::SpotFeeStructure { .. } => 5,
+            AdminChange::AdminTimelockDuration { .. } => 6,
+            AdminChange::OracleGuardRails { .. } => 7,
+            AdminChange::PerpMarketImfFactor { .. } => 8,
+            AdminChange::PerpMarketLiquidationFee { .. } => 9,
+            AdminChange::SpotMarketLiquidationFee { .. } => 10,
+            AdminChange::SpotMarketAssetTier { .. } => 11,
+            AdminChange::PerpMarketContractTier { .. } => 12,
+            AdminChange::SpotMarketBorrowRate { .. } => 13,
+            AdminChange::SpotMarketBorrowRateKinks { .. } => 14,
+            AdminChange::PerpMarketFundingRateCaps { .. } => 15,
+            AdminChange::PerpMarketMaxOpenInterest { .. } => 16,
+        }
+    }
+
+    /// 0 for changes to the state
+    pub fn market_index(&self) -> u16 {
+        match self {
+            AdminChange::PerpMarketMarginRatio { market_index, .. }
+            | AdminChange::SpotMarketMarginWeights { market_index, .. }
+            | AdminChange::PerpMarketOracle { market_index, .. }
+            | AdminChange::SpotMarketOracle { market_index, .. }
+            | AdminChange::PerpMarketImfFactor { market_index, .. }
+            | AdminChange::PerpMarketLiquidationFee { market_index, .. }
+            | AdminChange::SpotMarketLiquidationFee { market_index, .. }
+            | AdminChange::SpotMarketAssetTier { market_index, .. }
+            | AdminChange::PerpMarketContractTier { market_index, .. }
+            | AdminChange::SpotMarketBorrowRate { market_index, .. }
+            | AdminChange::SpotMarketBorrowRateKinks { market_index, .. }
+            | AdminChange::PerpMarketFundingRateCaps { market_index, .. }
+            | AdminChange::PerpMarketMaxOpenInterest { market_index, .. } => *market_index,
+            _ => 0,
+        }
+    }
+
+    /// one pending change per kind and market
+    pub fn seed(&self) -> [u8; 3] {
+        let market_index = self.market_index().to_le_bytes();
+        [self.kind(), market_index[0], market_index[1]]
+    }
+}
+
+#[account]
+pub struct PendingAdminChange {
+    pub change: AdminChange,
+    pub queued_ts: i64,
+    pub executable_ts: i64,
+}
+
+impl Size for PendingAdminChange {
+    // discriminator + largest change (fee structure) + timestamps
+    const SIZE: usize = 385;
+}
+
+impl PendingAdminChange {
+    pub fn validate_executable(&self, now: i64) -> DriftResult {
+        validate!(
+            now >= self.executable_ts,
+            ErrorCode::AdminChangeNotExecutable,
+            "admin change executable at {}, now {}",
+            self.executable_ts,
+            now
+        )
+    }
+}
@@ -0,0 +1,72 @@
+use anchor_lang::prelude::Pubkey;
+use borsh::BorshSerialize;
+
+use crate::error::ErrorCode;
+use crate::state::admin_timelock::{AdminChange, PendingAdminChange};
+use crate::state::oracle::OracleSource;
+use crate::state::state::FeeStructure;
+use crate::state::traits::Size;
+
+#[test]
+fn seed() {
+    let margin_ratio_change = AdminChange::PerpMarketMarginRatio {
+        market_index: 258,
+        margin_ratio_initial: 1000,
+        margin_ratio_maintenance: 500,
+    };
+    assert_eq!(margin_ratio_change.seed(), [0, 2, 1]);
+
+    // perp and spot changes to the same market index don't collide
+    let perp_oracle_change = AdminChange::PerpMarketOracle {
+        market_index: 1,
+        oracle: Pubkey::default(),
+        oracle_source: OracleSource::Pyth,
+    };
+    let spot_oracle_change = AdminChange::SpotMarketOracle {
+        market_index: 1,
+        oracle: Pubkey::default(),
+        oracle_source: OracleSource::Pyth,
+    };
+    assert_eq!(perp_oracle_change.seed(), [2, 1, 0]);
+    assert_eq!(spot_oracle_change.seed(), [3, 1, 0]);
+
+    let fee_structure_change = AdminChange::SpotFeeStructure {
+        fee_structure: FeeStructure::spot_default(),
+    };
+    assert_eq!(fee_structure_change.seed(), [5, 0, 0]);
+
+    let max_open_interest_change = AdminChange::PerpMarketMaxOpenInterest {
+        market_index: 1,
+        max_open_interest: 0,
+    };
+    assert_eq!(max_open_interest_change.seed(), [16, 1, 0]);
+}
+
+#[test]
+fn validate_executable() {
+    let pending_admin_change = PendingAdminChange {
+        change: AdminChange::AdminTimelockDuration { duration: 0 },
+        queued_ts: 100,
+        executable_ts: 200,
+    };
+
+    assert_eq!(
+        pending_admin_change.validate_executable(199),
+        Err(ErrorCode::AdminChangeNotExecutable)
+    );
+    assert_eq!(pending_admin_change.validate_executable(200), Ok(()));
+}
+
+#[test]
+fn size_fits_largest_change() {
+    let pending_admin_change = PendingAdminChange {
+        change: AdminChange::PerpFeeStructure {
+            fee_structure: FeeStructure::perps_default(),
+        },
+        queued_ts: 0,
+        executable_ts: 0,
+    };
+
+    let expected_size = pending_admin_change.try_to_vec().unwrap().len() + 8;
+    assert_eq!(PendingAdminChange::SIZE, expected_size);
+}
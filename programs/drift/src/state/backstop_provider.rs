@@ -0,0 +1,62 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::casting::Cast;
+use crate::math::constants::LIQUIDATION_FEE_PRECISION;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct BackstopProvider {
+    /// the user account that takes over liquidated perp positions
+    pub user: Pubkey,
+    /// collateral the provider must keep in `user` to liquidate as a backstop. precision: QUOTE_PRECISION
+    pub committed_collateral: u64,
+    /// share of the insurance fund liquidation fee paid to the provider on top of the liquidator fee.
+    /// precision: LIQUIDATION_FEE_PRECISION
+    pub liquidation_fee_bonus: u32,
+    pub approved: bool,
+    pub padding: [u8; 35],
+}
+
+impl Size for BackstopProvider {
+    const SIZE: usize = 88;
+}
+
+impl BackstopProvider {
+    pub fn validate(&self) -> DriftResult {
+        validate!(
+            self.liquidation_fee_bonus <= LIQUIDATION_FEE_PRECISION,
+            ErrorCode::InvalidBackstopProvider,
+            "liquidation_fee_bonus {} > LIQUIDATION_FEE_PRECISION",
+            self.liquidation_fee_bonus
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the fee bonus the provider earns on a liquidation, if it is approved and holds its commitment.
+    pub fn get_liquidation_fee_bonus(&self, total_collateral: i128) -> DriftResult<u32> {
+        validate!(
+            self.approved,
+            ErrorCode::InvalidBackstopProvider,
+            "backstop provider for {} is not approved",
+            self.user
+        )?;
+
+        validate!(
+            total_collateral >= self.committed_collateral.cast::<i128>()?,
+            ErrorCode::BackstopProviderUndercollateralized,
+            "total collateral {} < committed collateral {}",
+            total_collateral,
+            self.committed_collateral
+        )?;
+
+        Ok(self.liquidation_fee_bonus)
+    }
+}
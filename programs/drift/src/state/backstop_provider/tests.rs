@@ -0,0 +1,41 @@
+use crate::math::constants::{
+    LIQUIDATION_FEE_PRECISION, QUOTE_PRECISION_I128, QUOTE_PRECISION_U64,
+};
+use crate::state::backstop_provider::BackstopProvider;
+
+#[test]
+fn get_liquidation_fee_bonus() {
+    let mut provider = BackstopProvider {
+        committed_collateral: 1000 * QUOTE_PRECISION_U64,
+        liquidation_fee_bonus: LIQUIDATION_FEE_PRECISION / 200,
+        approved: true,
+        ..BackstopProvider::default()
+    };
+
+    let bonus = provider
+        .get_liquidation_fee_bonus(1000 * QUOTE_PRECISION_I128)
+        .unwrap();
+    assert_eq!(bonus, LIQUIDATION_FEE_PRECISION / 200);
+
+    // must hold what it committed
+    assert!(provider
+        .get_liquidation_fee_bonus(999 * QUOTE_PRECISION_I128)
+        .is_err());
+
+    provider.approved = false;
+    assert!(provider
+        .get_liquidation_fee_bonus(1000 * QUOTE_PRECISION_I128)
+        .is_err());
+}
+
+#[test]
+fn validate() {
+    let mut provider = BackstopProvider {
+        liquidation_fee_bonus: LIQUIDATION_FEE_PRECISION,
+        ..BackstopProvider::default()
+    };
+    assert!(provider.validate().is_ok());
+
+    provider.liquidation_fee_bonus = LIQUIDATION_FEE_PRECISION + 1;
+    assert!(provider.validate().is_err());
+}
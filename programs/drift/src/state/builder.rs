@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+use crate::state::traits::Size;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct Builder {
+    /// authority whose sub account 0 receives the builder's share of taker fees
+    pub authority: Pubkey,
+    pub name: [u8; 32],
+}
+
+impl Size for Builder {
+    const SIZE: usize = 72;
+}
@@ -0,0 +1,110 @@
+use anchor_lang::prelude::*;
+
+use crate::controller::position::PositionDirection;
+use crate::error::{DriftResult, ErrorCode};
+use crate::instructions::OrderParams;
+use crate::math::casting::Cast;
+use crate::math::constants::{DEAD_MAN_SWITCH_MAX_SLIPPAGE_DIVISOR, TWENTY_FOUR_HOUR};
+use crate::math::safe_math::SafeMath;
+use crate::state::traits::Size;
+use crate::state::user::OrderType;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct DeadManSwitch {
+    pub user: Pubkey,
+    /// may reduce only close positions and withdraw once the switch triggers
+    pub keeper: Pubkey,
+    /// owner of the token accounts the keeper is allowed to withdraw to
+    pub withdraw_destination: Pubkey,
+    pub last_ping_ts: i64,
+    /// days without a ping from the authority before the switch triggers
+    pub timeout_days: u16,
+    pub padding: [u8; 6],
+}
+
+impl Size for DeadManSwitch {
+    const SIZE: usize = 120;
+}
+
+impl DeadManSwitch {
+    pub fn trigger_ts(&self) -> DriftResult<i64> {
+        self.last_ping_ts.safe_add(
+            self.timeout_days
+                .cast::<i64>()?
+                .safe_mul(TWENTY_FOUR_HOUR)?,
+        )
+    }
+
+    pub fn is_triggered(&self, now: i64) -> DriftResult<bool> {
+        Ok(now >= self.trigger_ts()?)
+    }
+
+    pub fn validate_triggered(&self, now: i64) -> DriftResult {
+        validate!(
+            self.is_triggered(now)?,
+            ErrorCode::DeadManSwitchNotTriggered,
+            "dead man switch triggers at {}",
+            self.trigger_ts()?
+        )?;
+
+        Ok(())
+    }
+
+    /// the keeper only reduces positions but picks the price, so the limit price and auction
+    /// prices can't be more than 1/DEAD_MAN_SWITCH_MAX_SLIPPAGE_DIVISOR worse than the oracle
+    pub fn validate_order_price(params: &OrderParams, oracle_price: i64) -> DriftResult {
+        let max_slippage = oracle_price
+            .safe_div(DEAD_MAN_SWITCH_MAX_SLIPPAGE_DIVISOR)?
+            .abs();
+
+        let limit_price = match params.oracle_price_offset {
+            Some(offset) if offset != 0 || params.order_type == OrderType::Oracle => {
+                oracle_price.safe_add(offset.cast()?)?
+            }
+            _ => params.price.cast::<i64>()?,
+        };
+
+        validate!(
+            limit_price > 0,
+            ErrorCode::InvalidDeadManSwitch,
+            "dead man switch orders must have a limit price"
+        )?;
+
+        // oracle order auctions are priced as offsets from the oracle
+        let auction_prices = [params.auction_start_price, params.auction_end_price]
+            .iter()
+            .flatten()
+            .map(|price| {
+                if params.order_type == OrderType::Oracle {
+                    oracle_price.safe_add(*price)
+                } else {
+                    Ok(*price)
+                }
+            })
+            .collect::<DriftResult<Vec<i64>>>()?;
+
+        for price in std::iter::once(limit_price).chain(auction_prices) {
+            let within_slippage = match params.direction {
+                PositionDirection::Long => price <= oracle_price.safe_add(max_slippage)?,
+                PositionDirection::Short => price >= oracle_price.safe_sub(max_slippage)?,
+            };
+
+            validate!(
+                within_slippage,
+                ErrorCode::InvalidDeadManSwitch,
+                "dead man switch order price {} more than {} from oracle price {}",
+                price,
+                max_slippage,
+                oracle_price
+            )?;
+        }
+
+        Ok(())
+    }
+}
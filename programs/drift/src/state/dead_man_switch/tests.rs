@@ -0,0 +1,104 @@
+use crate::controller::position::PositionDirection;
+use crate::instructions::OrderParams;
+use crate::math::constants::{PRICE_PRECISION_I64, PRICE_PRECISION_U64, TWENTY_FOUR_HOUR};
+use crate::state::dead_man_switch::DeadManSwitch;
+use crate::state::user::OrderType;
+
+#[test]
+fn is_triggered() {
+    let dead_man_switch = DeadManSwitch {
+        last_ping_ts: 1_000,
+        timeout_days: 30,
+        ..DeadManSwitch::default()
+    };
+
+    let trigger_ts = 1_000 + 30 * TWENTY_FOUR_HOUR;
+    assert_eq!(dead_man_switch.trigger_ts().unwrap(), trigger_ts);
+
+    assert!(!dead_man_switch.is_triggered(trigger_ts - 1).unwrap());
+    assert!(dead_man_switch.validate_triggered(trigger_ts - 1).is_err());
+
+    assert!(dead_man_switch.is_triggered(trigger_ts).unwrap());
+    assert!(dead_man_switch.validate_triggered(trigger_ts).is_ok());
+}
+
+#[test]
+fn validate_order_price() {
+    let oracle_price = 100 * PRICE_PRECISION_I64;
+
+    // closing a short buys, at most 5% above the oracle
+    let params = OrderParams {
+        order_type: OrderType::Limit,
+        direction: PositionDirection::Long,
+        price: 105 * PRICE_PRECISION_U64,
+        reduce_only: true,
+        ..OrderParams::default()
+    };
+    assert!(DeadManSwitch::validate_order_price(&params, oracle_price).is_ok());
+
+    let params = OrderParams {
+        price: 106 * PRICE_PRECISION_U64,
+        ..params
+    };
+    assert!(DeadManSwitch::validate_order_price(&params, oracle_price).is_err());
+
+    // closing a long sells, at most 5% below the oracle
+    let params = OrderParams {
+        order_type: OrderType::Limit,
+        direction: PositionDirection::Short,
+        price: 95 * PRICE_PRECISION_U64,
+        reduce_only: true,
+        ..OrderParams::default()
+    };
+    assert!(DeadManSwitch::validate_order_price(&params, oracle_price).is_ok());
+
+    let params = OrderParams {
+        price: PRICE_PRECISION_U64,
+        ..params
+    };
+    assert!(DeadManSwitch::validate_order_price(&params, oracle_price).is_err());
+
+    // no limit price means no bound on the fill
+    let params = OrderParams {
+        order_type: OrderType::Market,
+        direction: PositionDirection::Short,
+        price: 0,
+        auction_start_price: Some(100 * PRICE_PRECISION_I64),
+        auction_end_price: Some(99 * PRICE_PRECISION_I64),
+        reduce_only: true,
+        ..OrderParams::default()
+    };
+    assert!(DeadManSwitch::validate_order_price(&params, oracle_price).is_err());
+
+    // the auction can't end past the bound either
+    let params = OrderParams {
+        price: 95 * PRICE_PRECISION_U64,
+        auction_end_price: Some(90 * PRICE_PRECISION_I64),
+        ..params
+    };
+    assert!(DeadManSwitch::validate_order_price(&params, oracle_price).is_err());
+
+    let params = OrderParams {
+        auction_end_price: Some(95 * PRICE_PRECISION_I64),
+        ..params
+    };
+    assert!(DeadManSwitch::validate_order_price(&params, oracle_price).is_ok());
+
+    // oracle offsets are checked against the oracle price
+    let params = OrderParams {
+        order_type: OrderType::Oracle,
+        direction: PositionDirection::Long,
+        oracle_price_offset: Some((5 * PRICE_PRECISION_I64) as i32),
+        auction_start_price: Some(0),
+        auction_end_price: Some(5 * PRICE_PRECISION_I64),
+        reduce_only: true,
+        ..OrderParams::default()
+    };
+    assert!(DeadManSwitch::validate_order_price(&params, oracle_price).is_ok());
+
+    let params = OrderParams {
+        oracle_price_offset: Some((6 * PRICE_PRECISION_I64) as i32),
+        ..params
+    };
+    assert!(DeadManSwitch::validate_order_price(&params, oracle_price).is_err());
+}
@@ -4,12 +4,17 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use crate::controller::position::PositionDirection;
 use crate::error::{DriftResult, ErrorCode::InvalidOrder};
 use crate::math::casting::Cast;
+use crate::math::margin::{MarginHealth, PerpMarginContribution, SpotMarginContribution};
 use crate::math::safe_unwrap::SafeUnwrap;
+use crate::state::admin_timelock::AdminChange;
 use crate::state::traits::Size;
 use crate::state::user::{MarketType, Order};
 use anchor_lang::Discriminator;
 use std::io::Write;
 
+#[cfg(all(test, not(feature = "legacy-fill-events")))]
+mod tests;
+
 #[event]
 pub struct NewUserRecord {
     pub ts: i64,
@@ -44,6 +49,7 @@ pub struct DepositRecord {
 pub enum DepositExplanation {
     None,
     Transfer,
+    ThirdParty,
 }
 
 impl Default for DepositExplanation {
@@ -92,6 +98,22 @@ pub struct FundingPaymentRecord {
     pub amm_cumulative_funding_short: i128,
 }
 
+/// funding settled straight into the quote spot balance, see settle_funding_against_borrow
+#[event]
+pub struct FundingBorrowSettleRecord {
+    pub ts: i64,
+    pub user_authority: Pubkey,
+    pub user: Pubkey,
+    pub market_index: u16,
+    pub funding_payment: i64,
+    /// part of funding_payment moved into the quote spot balance, positive repays borrow
+    pub pnl_settled: i64,
+    pub base_asset_amount: i64,
+    pub user_last_cumulative_funding: i64,
+    pub amm_cumulative_funding_long: i128,
+    pub amm_cumulative_funding_short: i128,
+}
+
 #[event]
 pub struct FundingRateRecord {
     pub ts: i64,
@@ -277,6 +299,11 @@ pub enum OrderActionExplanation {
     ReduceOnlyOrderIncreasedPosition,
     OrderFillWithSerum,
     NoBorrowLiquidity,
+    LinkedOrderExecuted,
+    OrderFillWithOpenbookV2,
+    OrderFillWithPhoenix,
+    UserOrderIdReplaced,
+    OrphanedTriggerOrder,
 }
 
 impl Default for OrderAction {
@@ -331,6 +358,7 @@ pub struct LiquidationRecord {
     pub liquidate_perp_pnl_for_deposit: LiquidatePerpPnlForDepositRecord,
     pub perp_bankruptcy: PerpBankruptcyRecord,
     pub spot_bankruptcy: SpotBankruptcyRecord,
+    pub auto_deleverage: AutoDeleverageRecord,
 }
 
 #[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
@@ -341,6 +369,7 @@ pub enum LiquidationType {
     LiquidatePerpPnlForDeposit,
     PerpBankruptcy,
     SpotBankruptcy,
+    AutoDeleverage,
 }
 
 impl Default for LiquidationType {
@@ -405,6 +434,18 @@ pub struct PerpBankruptcyRecord {
     pub cumulative_funding_rate_delta: i128,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
+pub struct AutoDeleverageRecord {
+    pub market_index: u16,
+    pub oracle_price: i64,
+    pub bankruptcy_price: i64,
+    pub counterparty: Pubkey,
+    pub base_asset_amount: i64,
+    pub quote_asset_amount: i64,
+    pub counterparty_pnl: i64,
+    pub score: u128,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default)]
 pub struct SpotBankruptcyRecord {
     pub market_index: u16,
@@ -455,6 +496,19 @@ pub struct InsuranceFundRecord {
     pub amount: i64,
 }
 
+#[event]
+#[derive(Default)]
+pub struct SwapRecord {
+    pub ts: i64,
+    pub user: Pubkey,
+    pub amount_out: u64,
+    pub amount_in: u64,
+    pub out_market_index: u16,
+    pub in_market_index: u16,
+    pub out_oracle_price: i64,
+    pub in_oracle_price: i64,
+}
+
 #[event]
 #[derive(Default)]
 pub struct InsuranceFundStakeRecord {
@@ -479,6 +533,8 @@ pub enum StakeAction {
     UnstakeRequest,
     UnstakeCancelRequest,
     Unstake,
+    Wrap,
+    Unwrap,
 }
 
 impl Default for StakeAction {
@@ -487,6 +543,58 @@ impl Default for StakeAction {
     }
 }
 
+#[event]
+pub struct AdminChangeRecord {
+    pub ts: i64,
+    pub action: AdminChangeAction,
+    pub change: AdminChange,
+    pub executable_ts: i64,
+}
+
+/// margin_warning set or cleared, health and the totals are maintenance margin
+#[event]
+#[derive(Default)]
+pub struct MarginWarningRecord {
+    pub ts: i64,
+    pub user: Pubkey,
+    pub margin_warning: bool,
+    pub health: u8,
+    pub total_collateral: i128,
+    pub margin_requirement: u128,
+}
+
+/// snapshot of a user's margin, per position contributions are maintenance margin
+#[event]
+pub struct UserRiskRecord {
+    pub ts: i64,
+    pub slot: u64,
+    pub user: Pubkey,
+    pub margin_health: MarginHealth,
+    pub spot_contributions: Vec<SpotMarginContribution>,
+    pub perp_contributions: Vec<PerpMarginContribution>,
+}
+
+/// the displayed slice of an iceberg order filled and the next slice was shown
+#[event]
+#[derive(Default)]
+pub struct IcebergReplenishRecord {
+    pub ts: i64,
+    pub user: Pubkey,
+    pub order_id: u32,
+    pub market_index: u16,
+    pub market_type: MarketType,
+    pub display_quantity: u64,
+    pub base_asset_amount_filled: u64,
+    pub slot: u64,
+}
+
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub enum AdminChangeAction {
+    Queue,
+    Execute,
+    Cancel,
+}
+
 pub fn emit_stack<T: AnchorSerialize + Discriminator, const N: usize>(event: T) -> DriftResult {
     let mut data_buf = [0u8; N];
     let mut out_buf = [0u8; N];
@@ -519,3 +627,149 @@ pub fn emit_buffers<T: AnchorSerialize + Discriminator>(
 
     Ok(())
 }
+
+/// fills per FillRecordBatch, keeps a batch well inside the transaction log limit
+pub const MAX_FILL_RECORD_BATCH_SIZE: usize = 16;
+
+/// the fill fields of an OrderActionRecord, the fields shared by every fill of an instruction
+/// are logged once in the FillRecordBatch
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct CompactFillRecord {
+    pub action_explanation: OrderActionExplanation,
+    pub fill_record_id: u64,
+    pub filler_reward: Option<u64>,
+    pub base_asset_amount_filled: u64,
+    pub quote_asset_amount_filled: u64,
+    pub taker_fee: Option<u64>,
+    pub maker_fee: Option<i64>,
+    pub referrer_reward: Option<u32>,
+    pub quote_asset_amount_surplus: Option<i64>,
+
+    pub taker: Option<Pubkey>,
+    pub taker_order_id: Option<u32>,
+    pub taker_order_direction: Option<PositionDirection>,
+    pub taker_order_base_asset_amount: Option<u64>,
+    pub taker_order_cumulative_base_asset_amount_filled: Option<u64>,
+    pub taker_order_cumulative_quote_asset_amount_filled: Option<u64>,
+
+    pub maker: Option<Pubkey>,
+    pub maker_order_id: Option<u32>,
+    pub maker_order_direction: Option<PositionDirection>,
+    pub maker_order_base_asset_amount: Option<u64>,
+    pub maker_order_cumulative_base_asset_amount_filled: Option<u64>,
+    pub maker_order_cumulative_quote_asset_amount_filled: Option<u64>,
+}
+
+impl CompactFillRecord {
+    pub fn new(record: &OrderActionRecord) -> DriftResult<Self> {
+        Ok(CompactFillRecord {
+            action_explanation: record.action_explanation,
+            fill_record_id: record.fill_record_id.safe_unwrap()?,
+            filler_reward: record.filler_reward,
+            base_asset_amount_filled: record.base_asset_amount_filled.safe_unwrap()?,
+            quote_asset_amount_filled: record.quote_asset_amount_filled.safe_unwrap()?,
+            taker_fee: record.taker_fee,
+            maker_fee: record.maker_fee,
+            referrer_reward: record.referrer_reward,
+            quote_asset_amount_surplus: record.quote_asset_amount_surplus,
+            taker: record.taker,
+            taker_order_id: record.taker_order_id,
+            taker_order_direction: record.taker_order_direction,
+            taker_order_base_asset_amount: record.taker_order_base_asset_amount,
+            taker_order_cumulative_base_asset_amount_filled: record
+                .taker_order_cumulative_base_asset_amount_filled,
+            taker_order_cumulative_quote_asset_amount_filled: record
+                .taker_order_cumulative_quote_asset_amount_filled,
+            maker: record.maker,
+            maker_order_id: record.maker_order_id,
+            maker_order_direction: record.maker_order_direction,
+            maker_order_base_asset_amount: record.maker_order_base_asset_amount,
+            maker_order_cumulative_base_asset_amount_filled: record
+                .maker_order_cumulative_base_asset_amount_filled,
+            maker_order_cumulative_quote_asset_amount_filled: record
+                .maker_order_cumulative_quote_asset_amount_filled,
+        })
+    }
+}
+
+/// perp fills logged as one event instead of an OrderActionRecord each. every fill is an
+/// OrderActionRecord with action Fill and these shared fields
+#[event]
+pub struct FillRecordBatch {
+    pub ts: i64,
+    pub market_index: u16,
+    pub market_type: MarketType,
+    pub filler: Option<Pubkey>,
+    pub oracle_price: i64,
+    pub fills: Vec<CompactFillRecord>,
+}
+
+impl FillRecordBatch {
+    fn shares_fields_with(&self, record: &OrderActionRecord) -> bool {
+        self.ts == record.ts
+            && self.market_index == record.market_index
+            && self.market_type == record.market_type
+            && self.filler == record.filler
+            && self.oracle_price == record.oracle_price
+    }
+}
+
+/// collects the fill records of an instruction and logs them as FillRecordBatch events. with
+/// the legacy-fill-events feature every record is logged as its own OrderActionRecord
+#[derive(Default)]
+pub struct FillRecordBuffer {
+    batch: Option<FillRecordBatch>,
+}
+
+impl FillRecordBuffer {
+    pub fn push(&mut self, record: OrderActionRecord) -> DriftResult {
+        if cfg!(feature = "legacy-fill-events") {
+            return emit_stack::<_, { OrderActionRecord::SIZE }>(record);
+        }
+
+        let fill = CompactFillRecord::new(&record)?;
+
+        let shares_fields = self
+            .batch
+            .as_ref()
+            .map_or(false, |batch| batch.shares_fields_with(&record));
+        if !shares_fields {
+            self.flush()?;
+        }
+
+        if let Some(batch) = self.batch.as_mut() {
+            batch.fills.push(fill);
+        } else {
+            self.batch = Some(FillRecordBatch {
+                ts: record.ts,
+                market_index: record.market_index,
+                market_type: record.market_type,
+                filler: record.filler,
+                oracle_price: record.oracle_price,
+                fills: vec![fill],
+            });
+        }
+
+        if self.len() >= MAX_FILL_RECORD_BATCH_SIZE {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.batch.as_ref().map_or(0, |batch| batch.fills.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn flush(&mut self) -> DriftResult {
+        if let Some(batch) = self.batch.take() {
+            emit!(batch);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::state::events::{
+    get_order_action_record, FillRecordBuffer, OrderAction, OrderActionExplanation,
+    OrderActionRecord, MAX_FILL_RECORD_BATCH_SIZE,
+};
+use crate::state::user::{MarketType, Order};
+
+fn get_fill_record(market_index: u16, fill_record_id: u64) -> OrderActionRecord {
+    get_order_action_record(
+        0,
+        OrderAction::Fill,
+        OrderActionExplanation::OrderFilledWithMatch,
+        market_index,
+        None,
+        Some(fill_record_id),
+        None,
+        Some(1),
+        Some(1),
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(Pubkey::default()),
+        Some(Order {
+            market_index,
+            market_type: MarketType::Perp,
+            ..Order::default()
+        }),
+        None,
+        None,
+        100,
+    )
+    .unwrap()
+}
+
+#[test]
+fn fills_with_shared_fields_are_batched() {
+    let mut fill_record_buffer = FillRecordBuffer::default();
+    assert!(fill_record_buffer.is_empty());
+
+    fill_record_buffer.push(get_fill_record(0, 1)).unwrap();
+    fill_record_buffer.push(get_fill_record(0, 2)).unwrap();
+    assert_eq!(fill_record_buffer.len(), 2);
+
+    // a fill in another market starts a new batch
+    fill_record_buffer.push(get_fill_record(1, 3)).unwrap();
+    assert_eq!(fill_record_buffer.len(), 1);
+
+    fill_record_buffer.flush().unwrap();
+    assert!(fill_record_buffer.is_empty());
+}
+
+#[test]
+fn full_batch_is_flushed() {
+    let mut fill_record_buffer = FillRecordBuffer::default();
+
+    for fill_record_id in 0..MAX_FILL_RECORD_BATCH_SIZE as u64 - 1 {
+        fill_record_buffer
+            .push(get_fill_record(0, fill_record_id))
+            .unwrap();
+    }
+    assert_eq!(fill_record_buffer.len(), MAX_FILL_RECORD_BATCH_SIZE - 1);
+
+    fill_record_buffer
+        .push(get_fill_record(0, MAX_FILL_RECORD_BATCH_SIZE as u64))
+        .unwrap();
+    assert!(fill_record_buffer.is_empty());
+}
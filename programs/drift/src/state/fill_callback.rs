@@ -0,0 +1,87 @@
+use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::hash::hash;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::orders::FillReceipt;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+/// a program the admin allows users to register as their fill callback
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct FillCallbackProgram {
+    pub program_id: Pubkey,
+}
+
+impl Size for FillCallbackProgram {
+    const SIZE: usize = 40;
+}
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct UserFillCallback {
+    pub user: Pubkey,
+    /// program invoked with the user's fill receipt after their taker orders fill
+    pub program_id: Pubkey,
+    pub bump: u8,
+    pub padding: [u8; 7],
+}
+
+impl Size for UserFillCallback {
+    const SIZE: usize = 80;
+}
+
+impl UserFillCallback {
+    pub fn validate(
+        &self,
+        user: &Pubkey,
+        fill_callback_program: &FillCallbackProgram,
+        callback_program: &AccountInfo,
+    ) -> DriftResult {
+        validate!(
+            self.user == *user,
+            ErrorCode::InvalidFillCallback,
+            "fill callback is for user {} not {}",
+            self.user,
+            user
+        )?;
+
+        validate!(
+            fill_callback_program.program_id == self.program_id,
+            ErrorCode::FillCallbackProgramNotWhitelisted,
+            "program {} not whitelisted as a fill callback",
+            self.program_id
+        )?;
+
+        validate!(
+            callback_program.key() == self.program_id && callback_program.executable,
+            ErrorCode::InvalidFillCallback,
+            "expected executable fill callback program {}",
+            self.program_id
+        )
+    }
+}
+
+/// what a fill callback program receives after each fill, after the 8 byte
+/// `on_drift_fill` instruction discriminator
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub struct FillCallbackData {
+    pub user: Pubkey,
+    pub market_index: u16,
+    pub receipt: FillReceipt,
+}
+
+impl FillCallbackData {
+    pub fn instruction_data(&self) -> DriftResult<Vec<u8>> {
+        let mut data = hash(b"global:on_drift_fill").to_bytes()[..8].to_vec();
+        self.serialize(&mut data)
+            .map_err(|_| ErrorCode::DefaultError)?;
+        Ok(data)
+    }
+}
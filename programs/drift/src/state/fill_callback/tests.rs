@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::state::fill_callback::{FillCallbackProgram, UserFillCallback};
+use crate::test_utils::create_account_info;
+
+#[test]
+fn validate() {
+    let user = Pubkey::new_unique();
+    let program_id = Pubkey::new_unique();
+    let user_fill_callback = UserFillCallback {
+        user,
+        program_id,
+        ..UserFillCallback::default()
+    };
+    let fill_callback_program = FillCallbackProgram { program_id };
+
+    let mut lamports = 0;
+    let mut data = vec![];
+    let owner = Pubkey::default();
+    let mut callback_program =
+        create_account_info(&program_id, false, &mut lamports, &mut data, &owner);
+    callback_program.executable = true;
+
+    assert!(user_fill_callback
+        .validate(&user, &fill_callback_program, &callback_program)
+        .is_ok());
+
+    assert_eq!(
+        user_fill_callback.validate(
+            &Pubkey::new_unique(),
+            &fill_callback_program,
+            &callback_program
+        ),
+        Err(ErrorCode::InvalidFillCallback)
+    );
+
+    let other_program = FillCallbackProgram {
+        program_id: Pubkey::new_unique(),
+    };
+    assert_eq!(
+        user_fill_callback.validate(&user, &other_program, &callback_program),
+        Err(ErrorCode::FillCallbackProgramNotWhitelisted)
+    );
+
+    callback_program.executable = false;
+    assert_eq!(
+        user_fill_callback.validate(&user, &fill_callback_program, &callback_program),
+        Err(ErrorCode::InvalidFillCallback)
+    );
+}
@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+
+use crate::error::DriftResult;
+use crate::math::safe_math::SafeMath;
+use crate::state::traits::Size;
+use crate::state::user::MarketType;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct UserFuel {
+    pub authority: Pubkey,
+    /// precision: QUOTE_PRECISION
+    pub taker: u64,
+    /// precision: QUOTE_PRECISION
+    pub maker: u64,
+    /// precision: QUOTE_PRECISION
+    pub insurance: u64,
+    /// precision: QUOTE_PRECISION
+    pub borrows: u64,
+    /// last time insurance and borrow fuel was accrued
+    pub last_update_ts: i64,
+    /// insurance fuel per day the stake earned at the last update, caps the next accrual.
+    /// precision: QUOTE_PRECISION
+    pub last_insurance_fuel_rate: u64,
+    /// borrow fuel per day the borrows earned at the last update, caps the next accrual.
+    /// precision: QUOTE_PRECISION
+    pub last_borrows_fuel_rate: u64,
+    pub padding: [u8; 8],
+}
+
+impl Size for UserFuel {
+    const SIZE: usize = 104;
+}
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct FuelBoost {
+    /// start of the epoch the boosts apply in
+    pub start_ts: i64,
+    /// end of the epoch, no fuel accrues for the market outside [start_ts, end_ts)
+    pub end_ts: i64,
+    pub market_index: u16,
+    pub market_type: MarketType,
+    /// precision: FUEL_BOOST_PRECISION
+    pub taker: u8,
+    /// precision: FUEL_BOOST_PRECISION
+    pub maker: u8,
+    /// spot markets only. precision: FUEL_BOOST_PRECISION
+    pub borrows: u8,
+    /// quote spot market only. precision: FUEL_BOOST_PRECISION
+    pub insurance: u8,
+    pub padding: [u8; 9],
+}
+
+impl Size for FuelBoost {
+    const SIZE: usize = 40;
+}
+
+impl FuelBoost {
+    pub fn is_active(&self, now: i64) -> bool {
+        self.start_ts <= now && now < self.end_ts
+    }
+
+    /// time between from_ts and to_ts that falls inside the epoch
+    pub fn active_duration(&self, from_ts: i64, to_ts: i64) -> DriftResult<i64> {
+        let start_ts = from_ts.max(self.start_ts);
+        let end_ts = to_ts.min(self.end_ts);
+
+        if start_ts >= end_ts {
+            return Ok(0);
+        }
+
+        end_ts.safe_sub(start_ts)
+    }
+}
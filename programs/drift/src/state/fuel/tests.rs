@@ -0,0 +1,21 @@
+use crate::state::fuel::FuelBoost;
+
+#[test]
+fn active_duration() {
+    let fuel_boost = FuelBoost {
+        start_ts: 100,
+        end_ts: 200,
+        ..FuelBoost::default()
+    };
+
+    assert!(!fuel_boost.is_active(99));
+    assert!(fuel_boost.is_active(100));
+    assert!(!fuel_boost.is_active(200));
+
+    assert_eq!(fuel_boost.active_duration(0, 50).unwrap(), 0);
+    assert_eq!(fuel_boost.active_duration(50, 150).unwrap(), 50);
+    assert_eq!(fuel_boost.active_duration(120, 180).unwrap(), 60);
+    assert_eq!(fuel_boost.active_duration(150, 300).unwrap(), 50);
+    assert_eq!(fuel_boost.active_duration(0, 300).unwrap(), 100);
+    assert_eq!(fuel_boost.active_duration(250, 300).unwrap(), 0);
+}
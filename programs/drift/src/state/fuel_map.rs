@@ -0,0 +1,110 @@
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::safe_unwrap::SafeUnwrap;
+use crate::state::fuel::{FuelBoost, UserFuel};
+use crate::state::traits::Size;
+use crate::state::user::MarketType;
+use anchor_lang::prelude::AccountLoader;
+use anchor_lang::Discriminator;
+use arrayref::array_ref;
+use solana_program::account_info::AccountInfo;
+use solana_program::msg;
+use solana_program::pubkey::Pubkey;
+use std::cell::RefMut;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::slice::Iter;
+
+/// fuel accounts keyed by authority and the boosts for the markets being touched. users whose
+/// fuel account or markets whose boost isn't passed accrue no fuel
+pub struct FuelMap<'a> {
+    pub user_fuel: BTreeMap<Pubkey, AccountLoader<'a, UserFuel>>,
+    pub fuel_boosts: Vec<FuelBoost>,
+}
+
+impl<'a> FuelMap<'a> {
+    pub fn get_user_fuel_mut(&self, authority: &Pubkey) -> DriftResult<Option<RefMut<UserFuel>>> {
+        match self.user_fuel.get(authority) {
+            Some(loader) => match loader.load_mut() {
+                Ok(user_fuel) => Ok(Some(user_fuel)),
+                Err(e) => {
+                    msg!("{:?}", e);
+                    msg!("Could not load user fuel for {}", authority);
+                    Err(ErrorCode::InvalidUserFuel)
+                }
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_fuel_boost(
+        &self,
+        market_type: &MarketType,
+        market_index: u16,
+    ) -> Option<&FuelBoost> {
+        self.fuel_boosts.iter().find(|fuel_boost| {
+            fuel_boost.market_type == *market_type && fuel_boost.market_index == market_index
+        })
+    }
+
+    pub fn empty() -> FuelMap<'a> {
+        FuelMap {
+            user_fuel: BTreeMap::new(),
+            fuel_boosts: vec![],
+        }
+    }
+}
+
+pub fn load_fuel_map<'a>(
+    account_info_iter: &mut Peekable<Iter<AccountInfo<'a>>>,
+) -> DriftResult<FuelMap<'a>> {
+    let mut fuel_map = FuelMap::empty();
+
+    let user_fuel_discriminator: [u8; 8] = UserFuel::discriminator();
+    let fuel_boost_discriminator: [u8; 8] = FuelBoost::discriminator();
+    while let Some(account_info) = account_info_iter.peek() {
+        let data = account_info
+            .try_borrow_data()
+            .or(Err(ErrorCode::InvalidUserFuel))?;
+
+        if data.len() < 8 {
+            break;
+        }
+
+        let account_discriminator = array_ref![data, 0, 8];
+        if account_discriminator == &user_fuel_discriminator {
+            if data.len() < UserFuel::SIZE {
+                return Err(ErrorCode::InvalidUserFuel);
+            }
+
+            let authority = Pubkey::new(array_ref![data, 8, 32]);
+            drop(data);
+
+            let account_info = account_info_iter.next().safe_unwrap()?;
+
+            if !account_info.is_writable {
+                return Err(ErrorCode::InvalidUserFuel);
+            }
+
+            let user_fuel_loader: AccountLoader<UserFuel> =
+                AccountLoader::try_from(account_info).or(Err(ErrorCode::InvalidUserFuel))?;
+
+            fuel_map.user_fuel.insert(authority, user_fuel_loader);
+        } else if account_discriminator == &fuel_boost_discriminator {
+            drop(data);
+
+            let account_info = account_info_iter.next().safe_unwrap()?;
+
+            let fuel_boost_loader: AccountLoader<FuelBoost> =
+                AccountLoader::try_from(account_info).or(Err(ErrorCode::InvalidFuelBoost))?;
+            let fuel_boost = *fuel_boost_loader
+                .load()
+                .or(Err(ErrorCode::InvalidFuelBoost))?;
+
+            fuel_map.fuel_boosts.push(fuel_boost);
+        } else {
+            break;
+        }
+    }
+
+    Ok(fuel_map)
+}
@@ -9,5 +9,7 @@ pub enum PerpFulfillmentMethod {
 #[derive(Debug)]
 pub enum SpotFulfillmentMethod {
     SerumV3,
+    OpenbookV2,
+    PhoenixV1,
     Match,
 }
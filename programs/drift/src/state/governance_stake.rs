@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::casting::Cast;
+use crate::math::constants::THIRTEEN_DAY;
+use crate::math::safe_math::SafeMath;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+/// whole governance tokens that must be staked to reach each tier
+pub const GOVERNANCE_STAKE_TIER_THRESHOLDS: [u64; 4] = [1_000, 10_000, 100_000, 1_000_000];
+/// time after the last stake before tokens can be unstaked, stops staking into a fill for the discount
+pub const GOVERNANCE_STAKE_UNSTAKING_PERIOD: i64 = THIRTEEN_DAY;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct GovernanceStake {
+    pub authority: Pubkey,
+    /// precision: governance token mint precision
+    pub staked_amount: u64,
+    pub last_stake_ts: i64,
+    pub padding: [u8; 16],
+}
+
+impl Size for GovernanceStake {
+    const SIZE: usize = 72;
+}
+
+impl GovernanceStake {
+    pub fn stake_tier(&self, decimals: u32) -> DriftResult<u8> {
+        let staked_tokens = self.staked_amount.safe_div(10_u64.pow(decimals))?;
+
+        GOVERNANCE_STAKE_TIER_THRESHOLDS
+            .iter()
+            .filter(|threshold| staked_tokens >= **threshold)
+            .count()
+            .cast()
+    }
+
+    pub fn validate_can_unstake(&self, now: i64) -> DriftResult {
+        let unstake_ts = self
+            .last_stake_ts
+            .safe_add(GOVERNANCE_STAKE_UNSTAKING_PERIOD)?;
+
+        validate!(
+            now >= unstake_ts,
+            ErrorCode::GovernanceStakeUnstakingPeriodNotElapsed,
+            "governance stake can be unstaked at {}",
+            unstake_ts
+        )?;
+
+        Ok(())
+    }
+}
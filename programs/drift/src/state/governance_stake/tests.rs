@@ -0,0 +1,36 @@
+use crate::state::governance_stake::{GovernanceStake, GOVERNANCE_STAKE_UNSTAKING_PERIOD};
+
+#[test]
+fn stake_tier() {
+    let decimals = 6;
+    let token = 10_u64.pow(decimals);
+
+    let mut governance_stake = GovernanceStake::default();
+    assert_eq!(governance_stake.stake_tier(decimals).unwrap(), 0);
+
+    governance_stake.staked_amount = 999 * token;
+    assert_eq!(governance_stake.stake_tier(decimals).unwrap(), 0);
+
+    governance_stake.staked_amount = 1_000 * token;
+    assert_eq!(governance_stake.stake_tier(decimals).unwrap(), 1);
+
+    governance_stake.staked_amount = 150_000 * token;
+    assert_eq!(governance_stake.stake_tier(decimals).unwrap(), 3);
+
+    governance_stake.staked_amount = 5_000_000 * token;
+    assert_eq!(governance_stake.stake_tier(decimals).unwrap(), 4);
+}
+
+#[test]
+fn validate_can_unstake() {
+    let governance_stake = GovernanceStake {
+        last_stake_ts: 1_000,
+        ..GovernanceStake::default()
+    };
+
+    let unstake_ts = 1_000 + GOVERNANCE_STAKE_UNSTAKING_PERIOD;
+    assert!(governance_stake
+        .validate_can_unstake(unstake_ts - 1)
+        .is_err());
+    assert!(governance_stake.validate_can_unstake(unstake_ts).is_ok());
+}
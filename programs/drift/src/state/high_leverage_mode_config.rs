@@ -0,0 +1,61 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::safe_math::SafeMath;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct HighLeverageModeConfig {
+    /// max number of users that can be in high leverage mode for the market at once
+    pub max_users: u32,
+    pub current_users: u32,
+    pub market_index: u16,
+    pub padding: [u8; 30],
+}
+
+impl Size for HighLeverageModeConfig {
+    const SIZE: usize = 48;
+}
+
+impl HighLeverageModeConfig {
+    pub fn validate(&self) -> DriftResult {
+        validate!(
+            self.current_users <= self.max_users,
+            ErrorCode::InvalidHighLeverageModeConfig,
+            "current_users {} exceeds max_users {}",
+            self.current_users,
+            self.max_users
+        )?;
+
+        Ok(())
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.current_users >= self.max_users
+    }
+
+    pub fn add_user(&mut self) -> DriftResult {
+        validate!(
+            !self.is_full(),
+            ErrorCode::HighLeverageModeFull,
+            "all {} high leverage mode slots are taken",
+            self.max_users
+        )?;
+
+        self.current_users = self.current_users.safe_add(1)?;
+
+        Ok(())
+    }
+
+    pub fn remove_user(&mut self) -> DriftResult {
+        self.current_users = self.current_users.safe_sub(1)?;
+
+        Ok(())
+    }
+}
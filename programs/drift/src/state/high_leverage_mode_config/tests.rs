@@ -0,0 +1,23 @@
+use crate::state::high_leverage_mode_config::HighLeverageModeConfig;
+
+#[test]
+fn add_user() {
+    let mut config = HighLeverageModeConfig {
+        max_users: 2,
+        ..HighLeverageModeConfig::default()
+    };
+
+    config.add_user().unwrap();
+    config.add_user().unwrap();
+    assert_eq!(config.current_users, 2);
+    assert!(config.is_full());
+
+    // registry is bounded
+    assert!(config.add_user().is_err());
+
+    // freed slots can be reused
+    config.remove_user().unwrap();
+    assert!(!config.is_full());
+    config.add_user().unwrap();
+    assert_eq!(config.current_users, 2);
+}
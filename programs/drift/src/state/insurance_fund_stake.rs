@@ -1,5 +1,7 @@
 use crate::error::DriftResult;
 use crate::error::ErrorCode;
+use crate::math::constants::{PERCENTAGE_PRECISION, THIRTY_DAY};
+use crate::math::safe_math::SafeMath;
 use crate::math_error;
 use crate::safe_decrement;
 use crate::safe_increment;
@@ -7,6 +9,40 @@ use crate::state::spot_market::SpotMarket;
 use crate::state::traits::Size;
 use crate::validate;
 use anchor_lang::prelude::*;
+use borsh::{BorshDeserialize, BorshSerialize};
+
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq, PartialOrd, Ord)]
+pub enum InsuranceFundStakeLockupTier {
+    None,
+    ThirtyDay,
+    NinetyDay,
+}
+
+impl Default for InsuranceFundStakeLockupTier {
+    fn default() -> Self {
+        InsuranceFundStakeLockupTier::None
+    }
+}
+
+impl InsuranceFundStakeLockupTier {
+    /// minimum time between an unstake request and the unstake, on top of the market's unstaking_period
+    pub fn unstaking_period(&self) -> i64 {
+        match self {
+            InsuranceFundStakeLockupTier::None => 0,
+            InsuranceFundStakeLockupTier::ThirtyDay => THIRTY_DAY,
+            InsuranceFundStakeLockupTier::NinetyDay => THIRTY_DAY * 3,
+        }
+    }
+
+    /// extra weight on the stake's if shares in revenue settlements. precision: PERCENTAGE_PRECISION
+    pub fn share_boost(&self) -> u128 {
+        match self {
+            InsuranceFundStakeLockupTier::None => 0,
+            InsuranceFundStakeLockupTier::ThirtyDay => PERCENTAGE_PRECISION / 10, // 10%
+            InsuranceFundStakeLockupTier::NinetyDay => PERCENTAGE_PRECISION / 4,  // 25%
+        }
+    }
+}
 
 #[account(zero_copy)]
 #[derive(Default, Eq, PartialEq, Debug)]
@@ -21,7 +57,9 @@ pub struct InsuranceFundStake {
     pub last_withdraw_request_ts: i64,
     pub cost_basis: i64,
     pub market_index: u16,
-    pub padding: [u8; 14],
+    pub lockup_tier: InsuranceFundStakeLockupTier,
+    pub padding: [u8; 5],
+    pub last_cumulative_if_boost_shares_per_weight: u64, // spot market's cumulative_if_boost_shares_per_weight when boost was last claimed
 }
 
 // implement SIZE const for InsuranceFundStake
@@ -41,7 +79,9 @@ impl InsuranceFundStake {
             if_base: 0,
             last_valid_ts: now,
             if_shares: 0,
-            padding: [0; 14],
+            lockup_tier: InsuranceFundStakeLockupTier::None,
+            padding: [0; 5],
+            last_cumulative_if_boost_shares_per_weight: 0,
         }
     }
 
@@ -78,6 +118,12 @@ impl InsuranceFundStake {
         Ok(())
     }
 
+    pub fn get_if_boost_weight(&self) -> DriftResult<u128> {
+        self.if_shares
+            .safe_mul(self.lockup_tier.share_boost())?
+            .safe_div(PERCENTAGE_PRECISION)
+    }
+
     pub fn update_if_shares(&mut self, new_shares: u128, spot_market: &SpotMarket) -> DriftResult {
         self.validate_base(spot_market)?;
         self.if_shares = new_shares;
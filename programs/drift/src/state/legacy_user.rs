@@ -0,0 +1,235 @@
+use anchor_lang::prelude::*;
+
+use crate::controller::position::PositionDirection;
+use crate::error::{DriftResult, ErrorCode};
+use crate::state::user::{
+    MarketType, Order, OrderStatus, OrderTriggerCondition, OrderType, PerpPosition, SpotPosition,
+    User, UserStatus,
+};
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+/// Size of a user account created before Order and PerpPosition grew and orders went from 32 to
+/// MAX_USER_ORDERS slots, discriminator included
+pub const LEGACY_USER_SIZE: usize = 8 + std::mem::size_of::<LegacyUser>();
+
+/// The user account layout before Order, PerpPosition and the order slots grew. Only read by
+/// migrate_user
+#[zero_copy]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub(crate) struct LegacyUser {
+    pub authority: Pubkey,
+    pub delegate: Pubkey,
+    pub name: [u8; 32],
+    pub spot_positions: [SpotPosition; 8],
+    pub perp_positions: [LegacyPerpPosition; 8],
+    pub orders: [LegacyOrder; 32],
+    pub last_add_perp_lp_shares_ts: i64,
+    pub total_deposits: u64,
+    pub total_withdraws: u64,
+    pub total_social_loss: u64,
+    pub settled_perp_pnl: i64,
+    pub cumulative_spot_fees: i64,
+    pub cumulative_perp_funding: i64,
+    pub liquidation_margin_freed: u64,
+    pub last_active_slot: u64,
+    pub next_order_id: u32,
+    pub max_margin_ratio: u32,
+    pub next_liquidation_id: u16,
+    pub sub_account_id: u16,
+    pub status: UserStatus,
+    pub is_margin_trading_enabled: bool,
+    pub idle: bool,
+    pub padding: [u8; 25],
+}
+
+#[zero_copy]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub(crate) struct LegacyPerpPosition {
+    pub last_cumulative_funding_rate: i64,
+    pub base_asset_amount: i64,
+    pub quote_asset_amount: i64,
+    pub quote_break_even_amount: i64,
+    pub quote_entry_amount: i64,
+    pub open_bids: i64,
+    pub open_asks: i64,
+    pub settled_pnl: i64,
+    pub lp_shares: u64,
+    pub last_base_asset_amount_per_lp: i64,
+    pub last_quote_asset_amount_per_lp: i64,
+    pub remainder_base_asset_amount: i32,
+    pub market_index: u16,
+    pub open_orders: u8,
+    pub padding: [u8; 1],
+}
+
+#[zero_copy]
+#[derive(Eq, PartialEq, Debug)]
+#[repr(C)]
+pub(crate) struct LegacyOrder {
+    pub slot: u64,
+    pub price: u64,
+    pub base_asset_amount: u64,
+    pub base_asset_amount_filled: u64,
+    pub quote_asset_amount_filled: u64,
+    pub trigger_price: u64,
+    pub auction_start_price: i64,
+    pub auction_end_price: i64,
+    pub max_ts: i64,
+    pub oracle_price_offset: i32,
+    pub order_id: u32,
+    pub market_index: u16,
+    pub status: OrderStatus,
+    pub order_type: OrderType,
+    pub market_type: MarketType,
+    pub user_order_id: u8,
+    pub existing_position_direction: PositionDirection,
+    pub direction: PositionDirection,
+    pub reduce_only: bool,
+    pub post_only: bool,
+    pub immediate_or_cancel: bool,
+    pub trigger_condition: OrderTriggerCondition,
+    pub auction_duration: u8,
+    pub padding: [u8; 3],
+}
+
+impl Default for LegacyOrder {
+    fn default() -> Self {
+        Self {
+            slot: 0,
+            price: 0,
+            base_asset_amount: 0,
+            base_asset_amount_filled: 0,
+            quote_asset_amount_filled: 0,
+            trigger_price: 0,
+            auction_start_price: 0,
+            auction_end_price: 0,
+            max_ts: 0,
+            oracle_price_offset: 0,
+            order_id: 0,
+            market_index: 0,
+            status: OrderStatus::Init,
+            order_type: OrderType::Limit,
+            market_type: MarketType::Perp,
+            user_order_id: 0,
+            existing_position_direction: PositionDirection::Long,
+            direction: PositionDirection::Long,
+            reduce_only: false,
+            post_only: false,
+            immediate_or_cancel: false,
+            trigger_condition: OrderTriggerCondition::Above,
+            auction_duration: 0,
+            padding: [0; 3],
+        }
+    }
+}
+
+impl LegacyPerpPosition {
+    fn migrate(&self) -> PerpPosition {
+        PerpPosition {
+            last_cumulative_funding_rate: self.last_cumulative_funding_rate,
+            base_asset_amount: self.base_asset_amount,
+            quote_asset_amount: self.quote_asset_amount,
+            quote_break_even_amount: self.quote_break_even_amount,
+            quote_entry_amount: self.quote_entry_amount,
+            open_bids: self.open_bids,
+            open_asks: self.open_asks,
+            settled_pnl: self.settled_pnl,
+            lp_shares: self.lp_shares,
+            last_base_asset_amount_per_lp: self.last_base_asset_amount_per_lp,
+            last_quote_asset_amount_per_lp: self.last_quote_asset_amount_per_lp,
+            remainder_base_asset_amount: self.remainder_base_asset_amount,
+            market_index: self.market_index,
+            open_orders: self.open_orders,
+            ..PerpPosition::default()
+        }
+    }
+}
+
+impl LegacyOrder {
+    fn migrate(&self) -> Order {
+        Order {
+            slot: self.slot,
+            price: self.price,
+            base_asset_amount: self.base_asset_amount,
+            base_asset_amount_filled: self.base_asset_amount_filled,
+            quote_asset_amount_filled: self.quote_asset_amount_filled,
+            trigger_price: self.trigger_price,
+            auction_start_price: self.auction_start_price,
+            auction_end_price: self.auction_end_price,
+            max_ts: self.max_ts,
+            oracle_price_offset: self.oracle_price_offset,
+            order_id: self.order_id,
+            market_index: self.market_index,
+            status: self.status,
+            order_type: self.order_type,
+            market_type: self.market_type,
+            user_order_id: self.user_order_id,
+            existing_position_direction: self.existing_position_direction,
+            direction: self.direction,
+            reduce_only: self.reduce_only,
+            post_only: self.post_only,
+            immediate_or_cancel: self.immediate_or_cancel,
+            trigger_condition: self.trigger_condition,
+            auction_duration: self.auction_duration,
+            ..Order::default()
+        }
+    }
+}
+
+impl LegacyUser {
+    /// Copies a legacy account's data (without discriminator) onto the heap, it doesnt fit on the
+    /// stack
+    pub fn read(data: &[u8]) -> DriftResult<Box<LegacyUser>> {
+        validate!(
+            data.len() >= std::mem::size_of::<LegacyUser>(),
+            ErrorCode::CouldNotLoadUserData,
+            "legacy user data is {} bytes",
+            data.len()
+        )?;
+
+        let mut legacy_user = bytemuck::allocation::zeroed_box::<LegacyUser>();
+        bytemuck::bytes_of_mut(&mut *legacy_user)
+            .copy_from_slice(&data[..std::mem::size_of::<LegacyUser>()]);
+        Ok(legacy_user)
+    }
+
+    /// Writes the legacy fields into a zeroed user, fields and order slots added since are left at
+    /// their default
+    pub fn migrate(&self, user: &mut User) {
+        user.authority = self.authority;
+        user.delegate = self.delegate;
+        user.name = self.name;
+        user.spot_positions = self.spot_positions;
+        for (perp_position, legacy_perp_position) in user
+            .perp_positions
+            .iter_mut()
+            .zip(self.perp_positions.iter())
+        {
+            *perp_position = legacy_perp_position.migrate();
+        }
+        for (order, legacy_order) in user.orders.iter_mut().zip(self.orders.iter()) {
+            *order = legacy_order.migrate();
+        }
+        user.last_add_perp_lp_shares_ts = self.last_add_perp_lp_shares_ts;
+        user.total_deposits = self.total_deposits;
+        user.total_withdraws = self.total_withdraws;
+        user.total_social_loss = self.total_social_loss;
+        user.settled_perp_pnl = self.settled_perp_pnl;
+        user.cumulative_spot_fees = self.cumulative_spot_fees;
+        user.cumulative_perp_funding = self.cumulative_perp_funding;
+        user.liquidation_margin_freed = self.liquidation_margin_freed;
+        user.last_active_slot = self.last_active_slot;
+        user.next_order_id = self.next_order_id;
+        user.max_margin_ratio = self.max_margin_ratio;
+        user.next_liquidation_id = self.next_liquidation_id;
+        user.sub_account_id = self.sub_account_id;
+        user.status = self.status;
+        user.is_margin_trading_enabled = self.is_margin_trading_enabled;
+        user.idle = self.idle;
+    }
+}
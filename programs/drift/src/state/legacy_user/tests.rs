@@ -0,0 +1,78 @@
+use anchor_lang::prelude::Pubkey;
+use solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+
+use crate::controller::position::PositionDirection;
+use crate::state::legacy_user::{LegacyOrder, LegacyPerpPosition, LegacyUser, LEGACY_USER_SIZE};
+use crate::state::traits::Size;
+use crate::state::user::{Order, OrderStatus, OrderType, PerpPosition, User};
+
+#[test]
+fn legacy_user_size() {
+    assert_eq!(LEGACY_USER_SIZE, 4376);
+    // migrate_user grows the account in a single realloc
+    assert!(User::SIZE - LEGACY_USER_SIZE <= MAX_PERMITTED_DATA_INCREASE);
+}
+
+#[test]
+fn migrate_legacy_user() {
+    let mut legacy_user = LegacyUser {
+        authority: Pubkey::new_unique(),
+        next_order_id: 7,
+        idle: true,
+        ..LegacyUser::default()
+    };
+    legacy_user.perp_positions[1] = LegacyPerpPosition {
+        base_asset_amount: 100,
+        quote_asset_amount: -100,
+        market_index: 3,
+        open_orders: 1,
+        ..LegacyPerpPosition::default()
+    };
+    legacy_user.orders[31] = LegacyOrder {
+        order_id: 6,
+        price: 100,
+        base_asset_amount: 100,
+        market_index: 3,
+        status: OrderStatus::Open,
+        order_type: OrderType::Limit,
+        direction: PositionDirection::Short,
+        auction_duration: 10,
+        ..LegacyOrder::default()
+    };
+
+    let read_legacy_user = LegacyUser::read(bytemuck::bytes_of(&legacy_user)).unwrap();
+    assert_eq!(*read_legacy_user, legacy_user);
+
+    let mut user = User::default();
+    read_legacy_user.migrate(&mut user);
+
+    assert_eq!(user.authority, legacy_user.authority);
+    assert_eq!(user.next_order_id, 7);
+    assert!(user.idle);
+    assert_eq!(
+        user.perp_positions[1],
+        PerpPosition {
+            base_asset_amount: 100,
+            quote_asset_amount: -100,
+            market_index: 3,
+            open_orders: 1,
+            ..PerpPosition::default()
+        }
+    );
+    assert_eq!(
+        user.orders[31],
+        Order {
+            order_id: 6,
+            price: 100,
+            base_asset_amount: 100,
+            market_index: 3,
+            status: OrderStatus::Open,
+            order_type: OrderType::Limit,
+            direction: PositionDirection::Short,
+            auction_duration: 10,
+            ..Order::default()
+        }
+    );
+    assert_eq!(user.orders[0], Order::default());
+    assert_eq!(user.orders[32], Order::default());
+}
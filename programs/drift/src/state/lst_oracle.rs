@@ -0,0 +1,215 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::ids::{marinade_program, spl_stake_pool_program};
+use crate::math::casting::Cast;
+use crate::math::constants::PRICE_PRECISION;
+use crate::math::safe_math::SafeMath;
+use crate::state::oracle::{OraclePriceData, OracleSource};
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+// spl stake pool: account type (1), manager, staker, stake deposit authority (32 each),
+// withdraw bump seed (1), validator list, reserve stake, pool mint, manager fee account,
+// token program (32 each), then total_lamports and pool_token_supply
+const SPL_STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+const SPL_STAKE_POOL_TOTAL_LAMPORTS_OFFSET: usize = 258;
+const SPL_STAKE_POOL_POOL_TOKEN_SUPPLY_OFFSET: usize = 266;
+
+// marinade state stores the msol price directly, scaled by 2^32
+const MARINADE_MSOL_PRICE_OFFSET: usize = 512;
+const MARINADE_MSOL_PRICE_DENOMINATOR: u128 = 0x1_0000_0000;
+
+#[derive(Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq, Eq, Debug)]
+pub enum StakePoolType {
+    SplStakePool,
+    Marinade,
+}
+
+impl Default for StakePoolType {
+    fn default() -> Self {
+        StakePoolType::SplStakePool
+    }
+}
+
+/// Prices a liquid staking token as the sol price scaled by the stake pool redemption rate.
+/// Updated permissionlessly from the sol oracle and stake pool accounts it records
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct LstOracle {
+    /// precision: PRICE_PRECISION
+    pub price: i64,
+    /// precision: PRICE_PRECISION
+    pub confidence: u64,
+    /// sol per lst token
+    /// precision: PRICE_PRECISION
+    pub redemption_rate: u64,
+    /// slot the sol price used in the last update was published
+    pub last_update_slot: u64,
+    pub sol_oracle: Pubkey,
+    pub stake_pool: Pubkey,
+    pub sol_oracle_source: OracleSource,
+    pub stake_pool_type: StakePoolType,
+    pub padding: [u8; 30],
+}
+
+impl Size for LstOracle {
+    const SIZE: usize = 136;
+}
+
+impl LstOracle {
+    pub fn update(
+        &mut self,
+        sol_price_data: &OraclePriceData,
+        redemption_rate: u64,
+        clock_slot: u64,
+    ) -> DriftResult {
+        validate!(
+            sol_price_data.price > 0,
+            ErrorCode::InvalidLstOracle,
+            "sol oracle price must be positive"
+        )?;
+
+        validate!(
+            redemption_rate > 0,
+            ErrorCode::InvalidStakePool,
+            "stake pool redemption rate must be positive"
+        )?;
+
+        self.price = sol_price_data
+            .price
+            .cast::<u128>()?
+            .safe_mul(redemption_rate.cast()?)?
+            .safe_div(PRICE_PRECISION)?
+            .cast()?;
+        self.confidence = sol_price_data
+            .confidence
+            .cast::<u128>()?
+            .safe_mul(redemption_rate.cast()?)?
+            .safe_div(PRICE_PRECISION)?
+            .cast()?;
+        self.redemption_rate = redemption_rate;
+        self.last_update_slot = clock_slot.saturating_sub(sol_price_data.delay.max(0).cast()?);
+
+        Ok(())
+    }
+}
+
+pub fn get_stake_pool_type(stake_pool: &AccountInfo) -> DriftResult<StakePoolType> {
+    if stake_pool.owner == &spl_stake_pool_program::id() {
+        Ok(StakePoolType::SplStakePool)
+    } else if stake_pool.owner == &marinade_program::id() {
+        Ok(StakePoolType::Marinade)
+    } else {
+        msg!("stake pool {} has unsupported owner", stake_pool.key);
+        Err(ErrorCode::InvalidStakePool)
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> DriftResult<u64> {
+    let bytes = data
+        .get(offset..offset + 8)
+        .ok_or(ErrorCode::InvalidStakePool)?;
+    let mut array = [0_u8; 8];
+    array.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(array))
+}
+
+pub fn calculate_spl_stake_pool_redemption_rate(data: &[u8]) -> DriftResult<u64> {
+    validate!(
+        data.first() == Some(&SPL_STAKE_POOL_ACCOUNT_TYPE),
+        ErrorCode::InvalidStakePool,
+        "account is not an spl stake pool"
+    )?;
+
+    let total_lamports = read_u64(data, SPL_STAKE_POOL_TOTAL_LAMPORTS_OFFSET)?;
+    let pool_token_supply = read_u64(data, SPL_STAKE_POOL_POOL_TOKEN_SUPPLY_OFFSET)?;
+
+    validate!(
+        pool_token_supply > 0,
+        ErrorCode::InvalidStakePool,
+        "stake pool has no pool token supply"
+    )?;
+
+    total_lamports
+        .cast::<u128>()?
+        .safe_mul(PRICE_PRECISION)?
+        .safe_div(pool_token_supply.cast()?)?
+        .cast()
+}
+
+pub fn calculate_marinade_redemption_rate(data: &[u8]) -> DriftResult<u64> {
+    let msol_price = read_u64(data, MARINADE_MSOL_PRICE_OFFSET)?;
+
+    msol_price
+        .cast::<u128>()?
+        .safe_mul(PRICE_PRECISION)?
+        .safe_div(MARINADE_MSOL_PRICE_DENOMINATOR)?
+        .cast()
+}
+
+pub fn get_redemption_rate(
+    stake_pool: &AccountInfo,
+    stake_pool_type: StakePoolType,
+) -> DriftResult<u64> {
+    validate!(
+        get_stake_pool_type(stake_pool)? == stake_pool_type,
+        ErrorCode::InvalidStakePool,
+        "stake pool owner does not match stake pool type"
+    )?;
+
+    let data = stake_pool
+        .try_borrow_data()
+        .or(Err(ErrorCode::InvalidStakePool))?;
+
+    match stake_pool_type {
+        StakePoolType::SplStakePool => calculate_spl_stake_pool_redemption_rate(&data),
+        StakePoolType::Marinade => calculate_marinade_redemption_rate(&data),
+    }
+}
+
+pub fn is_lst_oracle_account(account_info: &AccountInfo) -> bool {
+    account_info.owner == &crate::id()
+        && account_info
+            .try_borrow_data()
+            .map(|data| data.len() >= 8 && data[..8] == LstOracle::discriminator())
+            .unwrap_or(false)
+}
+
+pub fn load_lst_oracle(price_oracle: &AccountInfo) -> DriftResult<LstOracle> {
+    let data = price_oracle
+        .try_borrow_data()
+        .or(Err(ErrorCode::UnableToLoadOracle))?;
+
+    let lst_oracle_size = std::mem::size_of::<LstOracle>();
+    validate!(
+        data.len() >= lst_oracle_size + 8
+            && data[..8] == <LstOracle as Discriminator>::discriminator(),
+        ErrorCode::UnableToLoadOracle,
+        "invalid lst oracle account"
+    )?;
+
+    let lst_oracle = bytemuck::try_from_bytes::<LstOracle>(&data[8..8 + lst_oracle_size])
+        .or(Err(ErrorCode::UnableToLoadOracle))?;
+
+    Ok(*lst_oracle)
+}
+
+pub fn get_lst_price(price_oracle: &AccountInfo, clock_slot: u64) -> DriftResult<OraclePriceData> {
+    let lst_oracle = load_lst_oracle(price_oracle)?;
+
+    // staleness is measured from the sol price, so a lagging update shows up as delay
+    Ok(OraclePriceData {
+        price: lst_oracle.price,
+        confidence: lst_oracle.confidence,
+        delay: clock_slot
+            .cast::<i64>()?
+            .safe_sub(lst_oracle.last_update_slot.cast()?)?,
+        has_sufficient_number_of_data_points: true,
+    })
+}
@@ -0,0 +1,67 @@
+use crate::math::constants::{PRICE_PRECISION_I64, PRICE_PRECISION_U64};
+use crate::state::lst_oracle::{
+    calculate_marinade_redemption_rate, calculate_spl_stake_pool_redemption_rate, LstOracle,
+};
+use crate::state::oracle::OraclePriceData;
+
+#[test]
+fn spl_stake_pool_redemption_rate() {
+    let mut data = vec![0_u8; 611];
+    data[0] = 1;
+    // 1.1 sol per pool token
+    data[258..266].copy_from_slice(&1_100_000_000_000_u64.to_le_bytes());
+    data[266..274].copy_from_slice(&1_000_000_000_000_u64.to_le_bytes());
+
+    let redemption_rate = calculate_spl_stake_pool_redemption_rate(&data).unwrap();
+    assert_eq!(redemption_rate, 1_100_000);
+
+    // no supply
+    data[266..274].copy_from_slice(&0_u64.to_le_bytes());
+    assert!(calculate_spl_stake_pool_redemption_rate(&data).is_err());
+
+    // not a stake pool account
+    data[0] = 2;
+    assert!(calculate_spl_stake_pool_redemption_rate(&data).is_err());
+
+    assert!(calculate_spl_stake_pool_redemption_rate(&data[..200]).is_err());
+}
+
+#[test]
+fn marinade_redemption_rate() {
+    let mut data = vec![0_u8; 1024];
+    // 1.25 sol per msol, scaled by 2^32
+    data[512..520].copy_from_slice(&(5_u64 << 30).to_le_bytes());
+
+    let redemption_rate = calculate_marinade_redemption_rate(&data).unwrap();
+    assert_eq!(redemption_rate, 1_250_000);
+
+    assert!(calculate_marinade_redemption_rate(&data[..400]).is_err());
+}
+
+#[test]
+fn update_scales_sol_price_by_redemption_rate() {
+    let mut oracle = LstOracle::default();
+
+    let sol_price_data = OraclePriceData {
+        price: 100 * PRICE_PRECISION_I64,
+        confidence: PRICE_PRECISION_U64 / 10,
+        delay: 2,
+        has_sufficient_number_of_data_points: true,
+    };
+
+    oracle.update(&sol_price_data, 1_150_000, 100).unwrap();
+    assert_eq!(oracle.price, 115 * PRICE_PRECISION_I64);
+    assert_eq!(oracle.confidence, 115_000);
+    assert_eq!(oracle.redemption_rate, 1_150_000);
+    // staleness tracks the sol price
+    assert_eq!(oracle.last_update_slot, 98);
+
+    assert!(oracle.update(&sol_price_data, 0, 101).is_err());
+
+    let bad_sol_price_data = OraclePriceData {
+        price: 0,
+        ..sol_price_data
+    };
+    assert!(oracle.update(&bad_sol_price_data, 1_150_000, 101).is_err());
+    assert_eq!(oracle.price, 115 * PRICE_PRECISION_I64);
+}
@@ -0,0 +1,101 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::fees::calculate_fee_structure_with_adjustment;
+use crate::state::state::FeeStructure;
+use crate::state::traits::Size;
+use crate::state::user::MarketType;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct MarketFeeOverride {
+    /// start of the promotional fee window
+    pub promo_start_ts: i64,
+    /// end of the promotional fee window, promo_fee_adjustment applies in [promo_start_ts, promo_end_ts)
+    pub promo_end_ts: i64,
+    /// percent the taker fee of every global fee tier is scaled by, e.g. -50 halves it
+    /// 0 leaves the global taker fee
+    pub taker_fee_adjustment: i16,
+    /// percent the maker rebate of every global fee tier is scaled by, e.g. 100 doubles it
+    /// 0 leaves the global maker rebate
+    pub maker_rebate_adjustment: i16,
+    /// replaces taker_fee_adjustment while the promo window is active, -100 makes taking free for the window
+    pub promo_taker_fee_adjustment: i16,
+    /// replaces maker_rebate_adjustment while the promo window is active
+    pub promo_maker_rebate_adjustment: i16,
+    pub market_index: u16,
+    pub market_type: MarketType,
+    pub padding: [u8; 5],
+}
+
+impl Size for MarketFeeOverride {
+    const SIZE: usize = 40;
+}
+
+impl MarketFeeOverride {
+    pub fn is_promo_active(&self, now: i64) -> bool {
+        self.promo_start_ts <= now && now < self.promo_end_ts
+    }
+
+    /// taker fee and maker rebate adjustments in effect at now
+    pub fn get_fee_adjustments(&self, now: i64) -> (i16, i16) {
+        if self.is_promo_active(now) {
+            (
+                self.promo_taker_fee_adjustment,
+                self.promo_maker_rebate_adjustment,
+            )
+        } else {
+            (self.taker_fee_adjustment, self.maker_rebate_adjustment)
+        }
+    }
+}
+
+/// fee structure fills in the market are charged with. a market with an override enabled can't
+/// be filled without its override, so promos can't be skipped by whoever builds the fill
+pub fn get_market_fee_structure(
+    fee_structure: &FeeStructure,
+    market_type: MarketType,
+    market_index: u16,
+    fee_override_enabled: bool,
+    market_fee_override: Option<&MarketFeeOverride>,
+    now: i64,
+) -> DriftResult<FeeStructure> {
+    if !fee_override_enabled {
+        return Ok(fee_structure.clone());
+    }
+
+    let market_fee_override = match market_fee_override {
+        Some(market_fee_override) => market_fee_override,
+        None => {
+            msg!(
+                "market fee override not passed for {:?} market {}",
+                market_type,
+                market_index
+            );
+            return Err(ErrorCode::MarketFeeOverrideNotFound);
+        }
+    };
+
+    validate!(
+        market_fee_override.market_type == market_type
+            && market_fee_override.market_index == market_index,
+        ErrorCode::InvalidMarketFeeOverride,
+        "market fee override is for {:?} market {}",
+        market_fee_override.market_type,
+        market_fee_override.market_index
+    )?;
+
+    let (taker_fee_adjustment, maker_rebate_adjustment) =
+        market_fee_override.get_fee_adjustments(now);
+
+    calculate_fee_structure_with_adjustment(
+        fee_structure,
+        taker_fee_adjustment,
+        maker_rebate_adjustment,
+    )
+}
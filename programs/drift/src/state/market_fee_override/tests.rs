@@ -0,0 +1,91 @@
+use crate::error::ErrorCode;
+use crate::state::market_fee_override::{get_market_fee_structure, MarketFeeOverride};
+use crate::state::state::FeeStructure;
+use crate::state::user::MarketType;
+
+#[test]
+fn promo_window() {
+    let market_fee_override = MarketFeeOverride {
+        promo_start_ts: 100,
+        promo_end_ts: 200,
+        taker_fee_adjustment: -25,
+        maker_rebate_adjustment: 10,
+        promo_taker_fee_adjustment: 0,
+        promo_maker_rebate_adjustment: -100,
+        ..MarketFeeOverride::default()
+    };
+
+    assert_eq!(market_fee_override.get_fee_adjustments(99), (-25, 10));
+    assert_eq!(market_fee_override.get_fee_adjustments(100), (0, -100));
+    assert_eq!(market_fee_override.get_fee_adjustments(199), (0, -100));
+    assert_eq!(market_fee_override.get_fee_adjustments(200), (-25, 10));
+}
+
+#[test]
+fn market_fee_structure() {
+    let fee_structure = FeeStructure::perps_default();
+    let market_fee_override = MarketFeeOverride {
+        promo_start_ts: 100,
+        promo_end_ts: 200,
+        taker_fee_adjustment: -50,
+        maker_rebate_adjustment: -50,
+        promo_taker_fee_adjustment: 0,
+        promo_maker_rebate_adjustment: -100,
+        market_index: 1,
+        market_type: MarketType::Perp,
+        ..MarketFeeOverride::default()
+    };
+
+    // override not enabled for the market, global fees apply
+    let market_fee_structure = get_market_fee_structure(
+        &fee_structure,
+        MarketType::Perp,
+        1,
+        false,
+        Some(&market_fee_override),
+        150,
+    )
+    .unwrap();
+    assert_eq!(market_fee_structure.fee_tiers[0].fee_numerator, 100);
+
+    let market_fee_structure = get_market_fee_structure(
+        &fee_structure,
+        MarketType::Perp,
+        1,
+        true,
+        Some(&market_fee_override),
+        50,
+    )
+    .unwrap();
+    assert_eq!(market_fee_structure.fee_tiers[0].fee_numerator, 50);
+    assert_eq!(market_fee_structure.fee_tiers[0].maker_rebate_numerator, 10);
+
+    let market_fee_structure = get_market_fee_structure(
+        &fee_structure,
+        MarketType::Perp,
+        1,
+        true,
+        Some(&market_fee_override),
+        150,
+    )
+    .unwrap();
+    assert_eq!(market_fee_structure.fee_tiers[0].fee_numerator, 100);
+    assert_eq!(market_fee_structure.fee_tiers[0].maker_rebate_numerator, 0);
+
+    // enabled override must be passed
+    let result =
+        get_market_fee_structure(&fee_structure, MarketType::Perp, 1, true, None, 150).map(|_| ());
+    assert_eq!(result, Err(ErrorCode::MarketFeeOverrideNotFound));
+
+    // and be for the market being filled
+    let result = get_market_fee_structure(
+        &fee_structure,
+        MarketType::Spot,
+        1,
+        true,
+        Some(&market_fee_override),
+        150,
+    )
+    .map(|_| ());
+    assert_eq!(result, Err(ErrorCode::InvalidMarketFeeOverride));
+}
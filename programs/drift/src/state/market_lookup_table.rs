@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+use crate::state::traits::Size;
+
+/// canonical address lookup table of market, oracle and spot vault accounts, owned by the drift
+/// signer. tables are sharded by table_index since one table holds at most 256 addresses
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct MarketLookupTable {
+    pub lookup_table: Pubkey,
+    pub table_index: u16,
+    pub padding: [u8; 6],
+}
+
+impl Size for MarketLookupTable {
+    const SIZE: usize = 48;
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct MarketWhitelistEntry {
+    /// authority allowed to place and fill orders in the perp market
+    pub authority: Pubkey,
+    pub market_index: u16,
+    pub padding: [u8; 6],
+}
+
+impl Size for MarketWhitelistEntry {
+    const SIZE: usize = 48;
+}
+
+/// a market with its whitelist enabled only accepts orders from authorities with an entry,
+/// the entry must be passed with the order
+pub fn validate_market_whitelist(
+    whitelist_enabled: bool,
+    market_index: u16,
+    authority: &Pubkey,
+    market_whitelist_entry: Option<&MarketWhitelistEntry>,
+) -> DriftResult {
+    if !whitelist_enabled {
+        return Ok(());
+    }
+
+    validate!(
+        market_whitelist_entry.map_or(false, |entry| entry.market_index == market_index
+            && entry.authority == *authority),
+        ErrorCode::AuthorityNotWhitelistedForMarket,
+        "authority {} not whitelisted for perp market {}",
+        authority,
+        market_index
+    )
+}
+
+/// the entry for an authority among the entries passed with a fill, one per whitelisted taker or maker
+pub fn find_market_whitelist_entry<'a>(
+    market_whitelist_entries: &'a [MarketWhitelistEntry],
+    market_index: u16,
+    authority: &Pubkey,
+) -> Option<&'a MarketWhitelistEntry> {
+    market_whitelist_entries
+        .iter()
+        .find(|entry| entry.market_index == market_index && entry.authority == *authority)
+}
@@ -0,0 +1,66 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::error::ErrorCode;
+use crate::state::market_whitelist::{
+    find_market_whitelist_entry, validate_market_whitelist, MarketWhitelistEntry,
+};
+
+#[test]
+fn market_whitelist() {
+    let authority = Pubkey::new_unique();
+    let entry = MarketWhitelistEntry {
+        authority,
+        market_index: 1,
+        ..MarketWhitelistEntry::default()
+    };
+
+    // whitelist disabled
+    assert!(validate_market_whitelist(false, 1, &authority, None).is_ok());
+
+    assert!(validate_market_whitelist(true, 1, &authority, Some(&entry)).is_ok());
+
+    assert_eq!(
+        validate_market_whitelist(true, 1, &authority, None),
+        Err(ErrorCode::AuthorityNotWhitelistedForMarket)
+    );
+
+    // entry for another market
+    assert_eq!(
+        validate_market_whitelist(true, 2, &authority, Some(&entry)),
+        Err(ErrorCode::AuthorityNotWhitelistedForMarket)
+    );
+
+    // entry for another authority
+    assert_eq!(
+        validate_market_whitelist(true, 1, &Pubkey::new_unique(), Some(&entry)),
+        Err(ErrorCode::AuthorityNotWhitelistedForMarket)
+    );
+}
+
+#[test]
+fn find_market_whitelist_entry_for_authority() {
+    let taker = Pubkey::new_unique();
+    let maker = Pubkey::new_unique();
+    let entries = [
+        MarketWhitelistEntry {
+            authority: taker,
+            market_index: 1,
+            ..MarketWhitelistEntry::default()
+        },
+        MarketWhitelistEntry {
+            authority: maker,
+            market_index: 1,
+            ..MarketWhitelistEntry::default()
+        },
+    ];
+
+    assert_eq!(
+        find_market_whitelist_entry(&entries, 1, &maker),
+        Some(&entries[1])
+    );
+    assert_eq!(find_market_whitelist_entry(&entries, 2, &maker), None);
+    assert_eq!(
+        find_market_whitelist_entry(&entries, 1, &Pubkey::new_unique()),
+        None
+    );
+}
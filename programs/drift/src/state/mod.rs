@@ -1,15 +1,42 @@
+pub mod admin_timelock;
+pub mod backstop_provider;
+pub mod builder;
+pub mod dead_man_switch;
 pub mod events;
+pub mod fill_callback;
+pub mod fuel;
+pub mod fuel_map;
 pub mod fulfillment;
+pub mod governance_stake;
+pub mod high_leverage_mode_config;
 pub mod insurance_fund_stake;
+pub mod legacy_user;
+pub mod lst_oracle;
+pub mod market_fee_override;
+pub mod market_lookup_table;
+pub mod market_whitelist;
+pub mod openbook_v2;
 pub mod oracle;
 pub mod oracle_map;
+pub mod perp_lp_range;
 pub mod perp_market;
+pub mod perp_market_delisting;
+pub mod perp_market_listing;
 pub mod perp_market_map;
+pub mod phoenix;
+pub mod pnl_pool_rebalance_config;
+pub mod prelaunch_oracle;
+pub mod pyth_pull;
 pub mod serum;
+pub mod session_key;
+pub mod signed_order;
 pub mod spot_market;
 pub mod spot_market_map;
 #[allow(clippy::module_inception)]
 pub mod state;
+pub mod switchboard_on_demand;
+pub mod token;
 pub mod traits;
 pub mod user;
 pub mod user_map;
+pub mod vault;
@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::validate;
+
+// Mirrors the account layout of the openbook v2 program's Market so markets can be read
+// without pulling in the openbook-v2 crate. u128 counters are kept as le bytes so the
+// layout only needs 8 byte alignment
+
+pub const OPENBOOK_V2_MARKET_DISCRIMINATOR: [u8; 8] = [219, 190, 213, 55, 0, 227, 198, 154];
+
+#[zero_copy]
+#[repr(C)]
+pub struct OpenbookV2Market {
+    pub bump: u8,
+    pub base_decimals: u8,
+    pub quote_decimals: u8,
+    pub padding1: [u8; 5],
+    pub market_authority: Pubkey,
+    pub time_expiry: i64,
+    pub collect_fee_admin: Pubkey,
+    pub open_orders_admin: Pubkey, // default pubkey if none
+    pub consume_events_admin: Pubkey,
+    pub close_market_admin: Pubkey,
+    pub name: [u8; 16],
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub event_heap: Pubkey,
+    pub oracle_a: Pubkey, // default pubkey if none
+    pub oracle_b: Pubkey, // default pubkey if none
+    pub oracle_config: [u8; 88],
+    pub quote_lot_size: i64,
+    pub base_lot_size: i64,
+    pub seq_num: u64,
+    pub registration_time: i64,
+    pub maker_fee: i64,
+    pub taker_fee: i64,
+    pub fees_accrued: [u8; 16],
+    pub fees_to_referrers: [u8; 16],
+    pub referrer_rebates_accrued: u64,
+    pub fees_available: u64,
+    pub maker_volume: [u8; 16],
+    pub taker_volume_wo_oo: [u8; 16],
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub market_base_vault: Pubkey,
+    pub base_deposit_total: u64,
+    pub market_quote_vault: Pubkey,
+    pub quote_deposit_total: u64,
+    pub reserved: [u8; 128],
+}
+
+impl OpenbookV2Market {
+    pub fn fees_accrued(&self) -> u128 {
+        u128::from_le_bytes(self.fees_accrued)
+    }
+}
+
+pub fn load_openbook_v2_market(
+    account_info: &AccountInfo,
+    program_id: &Pubkey,
+) -> DriftResult<OpenbookV2Market> {
+    validate!(
+        account_info.owner == program_id,
+        ErrorCode::InvalidOpenbookV2Market,
+        "openbook v2 market not owned by openbook v2 program"
+    )?;
+
+    let data = account_info
+        .try_borrow_data()
+        .or(Err(ErrorCode::InvalidOpenbookV2Market))?;
+
+    let market_size = std::mem::size_of::<OpenbookV2Market>();
+    validate!(
+        data.len() >= market_size + 8 && data[..8] == OPENBOOK_V2_MARKET_DISCRIMINATOR,
+        ErrorCode::InvalidOpenbookV2Market,
+        "invalid openbook v2 market account"
+    )?;
+
+    let market = bytemuck::try_from_bytes::<OpenbookV2Market>(&data[8..8 + market_size])
+        .or(Err(ErrorCode::InvalidOpenbookV2Market))?;
+
+    Ok(*market)
+}
@@ -1,11 +1,16 @@
 use anchor_lang::prelude::*;
 
-use crate::error::DriftResult;
+use crate::error::{DriftResult, ErrorCode};
 use crate::math::casting::Cast;
 use crate::math::constants::{PRICE_PRECISION, PRICE_PRECISION_I64, PRICE_PRECISION_U64};
 use crate::math::safe_math::SafeMath;
 
 use crate::math::safe_unwrap::SafeUnwrap;
+use crate::state::lst_oracle::get_lst_price;
+use crate::state::prelaunch_oracle::PrelaunchOracle;
+use crate::state::pyth_pull::load_price_update_v2;
+use crate::state::switchboard_on_demand::{PullFeedAccountData, SB_ON_DEMAND_PRECISION};
+use crate::validate;
 use switchboard_v2::decimal::SwitchboardDecimal;
 
 #[cfg(test)]
@@ -96,6 +101,10 @@ pub enum OracleSource {
     QuoteAsset,
     Pyth1K,
     Pyth1M,
+    SwitchboardOnDemand,
+    PythPull,
+    Prelaunch,
+    LstRedemptionRate,
 }
 
 impl Default for OracleSource {
@@ -143,6 +152,10 @@ pub fn get_oracle_price(
             delay: 0,
             has_sufficient_number_of_data_points: true,
         }),
+        OracleSource::SwitchboardOnDemand => get_sb_on_demand_price(price_oracle, clock_slot),
+        OracleSource::PythPull => get_pyth_pull_price(price_oracle, clock_slot),
+        OracleSource::Prelaunch => get_prelaunch_price(price_oracle),
+        OracleSource::LstRedemptionRate => get_lst_price(price_oracle, clock_slot),
     }
 }
 
@@ -156,10 +169,104 @@ pub fn get_pyth_price(
         .or(Err(crate::error::ErrorCode::UnableToLoadOracle))?;
     let price_data = pyth_client::cast::<pyth_client::Price>(&pyth_price_data);
 
-    let oracle_price = price_data.agg.price;
-    let oracle_conf = price_data.agg.conf;
+    let (oracle_price_scaled, oracle_conf_scaled) = scale_pyth_price(
+        price_data.agg.price,
+        price_data.agg.conf,
+        price_data.expo,
+        multiple,
+    )?;
 
-    let oracle_precision = 10_u128.pow(price_data.expo.unsigned_abs());
+    let oracle_delay: i64 = clock_slot
+        .cast::<i64>()?
+        .safe_sub(price_data.valid_slot.cast()?)?;
+
+    Ok(OraclePriceData {
+        price: oracle_price_scaled,
+        confidence: oracle_conf_scaled,
+        delay: oracle_delay,
+        has_sufficient_number_of_data_points: true,
+    })
+}
+
+pub fn get_pyth_pull_price(
+    price_oracle: &AccountInfo,
+    clock_slot: u64,
+) -> DriftResult<OraclePriceData> {
+    let price_update = load_price_update_v2(price_oracle)?;
+    let price_message = &price_update.price_message;
+
+    validate!(
+        price_message.publish_time >= price_message.prev_publish_time,
+        ErrorCode::InvalidOracle,
+        "pyth pull publish_time {} before prev_publish_time {}",
+        price_message.publish_time,
+        price_message.prev_publish_time
+    )?;
+
+    let (oracle_price_scaled, oracle_conf_scaled) = scale_pyth_price(
+        price_message.price,
+        price_message.conf,
+        price_message.exponent,
+        1,
+    )?;
+
+    // pull updates can be posted long after they are published, so measure delay from when the
+    // update was posted on chain
+    let oracle_delay: i64 = clock_slot
+        .cast::<i64>()?
+        .safe_sub(price_update.posted_slot.cast()?)?;
+
+    Ok(OraclePriceData {
+        price: oracle_price_scaled,
+        confidence: oracle_conf_scaled,
+        delay: oracle_delay,
+        has_sufficient_number_of_data_points: true,
+    })
+}
+
+/// Returns the ema price of a pyth pull price update, scaled to PRICE_PRECISION
+pub fn get_pyth_pull_twap(price_oracle: &AccountInfo) -> DriftResult<i64> {
+    let price_update = load_price_update_v2(price_oracle)?;
+    let price_message = &price_update.price_message;
+
+    let (oracle_twap_scaled, _) = scale_pyth_price(
+        price_message.ema_price,
+        price_message.ema_conf,
+        price_message.exponent,
+        1,
+    )?;
+
+    Ok(oracle_twap_scaled)
+}
+
+/// Checks that a pull price update was published within max_staleness seconds of now
+pub fn validate_pyth_pull_publish_time(
+    price_oracle: &AccountInfo,
+    now: i64,
+    max_staleness: i64,
+) -> DriftResult {
+    let price_update = load_price_update_v2(price_oracle)?;
+    let publish_time = price_update.price_message.publish_time;
+
+    validate!(
+        now.safe_sub(publish_time)? <= max_staleness,
+        ErrorCode::InvalidOracle,
+        "pyth pull publish_time {} older than {}s (now = {})",
+        publish_time,
+        max_staleness,
+        now
+    )?;
+
+    Ok(())
+}
+
+fn scale_pyth_price(
+    oracle_price: i64,
+    oracle_conf: u64,
+    expo: i32,
+    multiple: u128,
+) -> DriftResult<(i64, u64)> {
+    let oracle_precision = 10_u128.pow(expo.unsigned_abs());
 
     if oracle_precision <= multiple {
         msg!("Multiple larger than oracle precision");
@@ -189,18 +296,93 @@ pub fn get_pyth_price(
         .safe_div(oracle_scale_div)?
         .cast::<u64>()?;
 
-    let oracle_delay: i64 = clock_slot
-        .cast::<i64>()?
-        .safe_sub(price_data.valid_slot.cast()?)?;
+    Ok((oracle_price_scaled, oracle_conf_scaled))
+}
+
+pub fn load_prelaunch_oracle(price_oracle: &AccountInfo) -> DriftResult<PrelaunchOracle> {
+    let data = price_oracle
+        .try_borrow_data()
+        .or(Err(ErrorCode::UnableToLoadOracle))?;
+
+    let prelaunch_oracle_size = std::mem::size_of::<PrelaunchOracle>();
+    validate!(
+        data.len() >= prelaunch_oracle_size + 8
+            && data[..8] == <PrelaunchOracle as Discriminator>::discriminator(),
+        ErrorCode::UnableToLoadOracle,
+        "invalid prelaunch oracle account"
+    )?;
+
+    let prelaunch_oracle =
+        bytemuck::try_from_bytes::<PrelaunchOracle>(&data[8..8 + prelaunch_oracle_size])
+            .or(Err(ErrorCode::UnableToLoadOracle))?;
+
+    Ok(*prelaunch_oracle)
+}
 
+pub fn get_prelaunch_price(price_oracle: &AccountInfo) -> DriftResult<OraclePriceData> {
+    let prelaunch_oracle = load_prelaunch_oracle(price_oracle)?;
+
+    // the price is set by the admin rather than a feed, so it never goes stale
     Ok(OraclePriceData {
-        price: oracle_price_scaled,
-        confidence: oracle_conf_scaled,
-        delay: oracle_delay,
+        price: prelaunch_oracle.price,
+        confidence: prelaunch_oracle.confidence,
+        delay: 0,
         has_sufficient_number_of_data_points: true,
     })
 }
 
+pub fn get_sb_on_demand_price(
+    price_oracle: &AccountInfo,
+    clock_slot: u64,
+) -> DriftResult<OraclePriceData> {
+    let data = price_oracle
+        .try_borrow_data()
+        .or(Err(crate::error::ErrorCode::UnableToLoadOracle))?;
+
+    // skip the anchor discriminator
+    let pull_feed_size = std::mem::size_of::<PullFeedAccountData>();
+    if data.len() < pull_feed_size + 8 {
+        msg!("Switchboard on-demand account too small");
+        return Err(crate::error::ErrorCode::UnableToLoadOracle);
+    }
+
+    let pull_feed = bytemuck::try_from_bytes::<PullFeedAccountData>(&data[8..8 + pull_feed_size])
+        .or(Err(crate::error::ErrorCode::UnableToLoadOracle))?;
+
+    let result = &pull_feed.result;
+    if result.slot == 0 {
+        msg!("Switchboard on-demand feed has no result");
+        return Err(crate::error::ErrorCode::InvalidOracle);
+    }
+
+    let price = convert_sb_on_demand_i128(result.value)?.cast::<i64>()?;
+
+    // range of the latest samples is used as the confidence interval
+    let confidence = convert_sb_on_demand_i128(result.range)?
+        .unsigned_abs()
+        .cast::<u64>()?;
+
+    let delay = clock_slot.cast::<i64>()?.safe_sub(result.slot.cast()?)?;
+
+    let has_sufficient_number_of_data_points = result.num_samples >= pull_feed.min_sample_size;
+
+    Ok(OraclePriceData {
+        price,
+        confidence,
+        delay,
+        has_sufficient_number_of_data_points,
+    })
+}
+
+/// switchboard on-demand values have 18 decimals of precision
+fn convert_sb_on_demand_i128(value: i128) -> DriftResult<i128> {
+    let sb_precision_to_price_precision_ratio = 10_i128
+        .pow(SB_ON_DEMAND_PRECISION)
+        .safe_div(PRICE_PRECISION.cast()?)?;
+
+    value.safe_div(sb_precision_to_price_precision_ratio)
+}
+
 // pub fn get_switchboard_price(
 //     _price_oracle: &AccountInfo,
 //     _clock_slot: u64,
@@ -1,10 +1,18 @@
 use std::str::FromStr;
 
+use anchor_lang::{AnchorSerialize, Owner};
 use solana_program::pubkey::Pubkey;
 
 use crate::create_account_info;
-use crate::state::oracle::{get_oracle_price, OracleSource};
+use crate::create_anchor_account_info;
+use crate::state::oracle::{get_oracle_price, validate_pyth_pull_publish_time, OracleSource};
+use crate::state::oracle_map::OracleMap;
 use crate::state::perp_market::AMM;
+use crate::state::prelaunch_oracle::PrelaunchOracle;
+use crate::state::pyth_pull::{
+    PriceFeedMessage, PriceUpdateV2, VerificationLevel, PRICE_UPDATE_V2_DISCRIMINATOR,
+};
+use crate::state::switchboard_on_demand::PullFeedAccountData;
 use crate::test_utils::*;
 
 #[test]
@@ -58,3 +66,143 @@ fn pyth_1m() {
     let twap = amm.get_oracle_twap(&oracle_account_info).unwrap();
     assert_eq!(twap, Some(839400));
 }
+
+#[test]
+fn switchboard_on_demand() {
+    let mut pull_feed: PullFeedAccountData = bytemuck::Zeroable::zeroed();
+    pull_feed.min_sample_size = 1;
+    pull_feed.result.value = 23_350_000_000_000_000_000; // $23.35
+    pull_feed.result.range = 10_000_000_000_000_000; // 1 cent
+    pull_feed.result.num_samples = 2;
+    pull_feed.result.slot = 100;
+
+    let mut data = vec![0_u8; 8];
+    data.extend_from_slice(bytemuck::bytes_of(&pull_feed));
+
+    let oracle_price_key = Pubkey::new_unique();
+    let owner = crate::ids::switchboard_on_demand::id();
+    let mut lamports = 0;
+    let oracle_account_info = create_account_info(
+        &oracle_price_key,
+        true,
+        &mut lamports,
+        &mut data[..],
+        &owner,
+    );
+
+    let oracle_price_data = get_oracle_price(
+        &OracleSource::SwitchboardOnDemand,
+        &oracle_account_info,
+        105,
+    )
+    .unwrap();
+    assert_eq!(oracle_price_data.price, 23_350_000);
+    assert_eq!(oracle_price_data.confidence, 10_000);
+    assert_eq!(oracle_price_data.delay, 5);
+    assert!(oracle_price_data.has_sufficient_number_of_data_points);
+
+    let amm = AMM {
+        oracle_source: OracleSource::SwitchboardOnDemand,
+        ..AMM::default()
+    };
+    let twap = amm.get_oracle_twap(&oracle_account_info).unwrap();
+    assert_eq!(twap, None);
+}
+
+#[test]
+fn pyth_pull() {
+    let mut price_update = PriceUpdateV2 {
+        write_authority: Pubkey::new_unique(),
+        verification_level: VerificationLevel::Full,
+        price_message: PriceFeedMessage {
+            feed_id: [0; 32],
+            price: 2_335_000_000, // $23.35
+            conf: 1_000_000,      // 1 cent
+            exponent: -8,
+            publish_time: 1_000,
+            prev_publish_time: 999,
+            ema_price: 2_330_000_000,
+            ema_conf: 1_000_000,
+        },
+        posted_slot: 100,
+    };
+
+    let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&price_update.try_to_vec().unwrap());
+
+    let oracle_price_key = Pubkey::new_unique();
+    let owner = crate::ids::pyth_receiver_program::id();
+    let mut lamports = 0;
+    let oracle_account_info = create_account_info(
+        &oracle_price_key,
+        true,
+        &mut lamports,
+        &mut data[..],
+        &owner,
+    );
+
+    let oracle_price_data =
+        get_oracle_price(&OracleSource::PythPull, &oracle_account_info, 105).unwrap();
+    assert_eq!(oracle_price_data.price, 23_350_000);
+    assert_eq!(oracle_price_data.confidence, 10_000);
+    assert_eq!(oracle_price_data.delay, 5);
+
+    let amm = AMM {
+        oracle_source: OracleSource::PythPull,
+        ..AMM::default()
+    };
+    let twap = amm.get_oracle_twap(&oracle_account_info).unwrap();
+    assert_eq!(twap, Some(23_300_000));
+
+    assert!(validate_pyth_pull_publish_time(&oracle_account_info, 1_060, 60).is_ok());
+    assert!(validate_pyth_pull_publish_time(&oracle_account_info, 1_061, 60).is_err());
+
+    // partially verified updates are rejected
+    price_update.verification_level = VerificationLevel::Partial { num_signatures: 5 };
+    let mut data = PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&price_update.try_to_vec().unwrap());
+    let mut lamports = 0;
+    let oracle_account_info = create_account_info(
+        &oracle_price_key,
+        true,
+        &mut lamports,
+        &mut data[..],
+        &owner,
+    );
+    assert!(get_oracle_price(&OracleSource::PythPull, &oracle_account_info, 105).is_err());
+}
+
+#[test]
+fn prelaunch() {
+    let mut prelaunch_oracle = PrelaunchOracle {
+        price: 5_000_000,
+        confidence: 50_000,
+        last_update_slot: 10,
+        perp_market_index: 1,
+        ..PrelaunchOracle::default()
+    };
+    let oracle_price_key = Pubkey::new_unique();
+    create_anchor_account_info!(
+        prelaunch_oracle,
+        &oracle_price_key,
+        PrelaunchOracle,
+        oracle_account_info
+    );
+
+    let oracle_price_data =
+        get_oracle_price(&OracleSource::Prelaunch, &oracle_account_info, 1000).unwrap();
+    assert_eq!(oracle_price_data.price, 5_000_000);
+    assert_eq!(oracle_price_data.confidence, 50_000);
+    assert_eq!(oracle_price_data.delay, 0);
+
+    let amm = AMM {
+        oracle_source: OracleSource::Prelaunch,
+        ..AMM::default()
+    };
+    let twap = amm.get_oracle_twap(&oracle_account_info).unwrap();
+    assert_eq!(twap, Some(5_000_000));
+
+    let mut oracle_map = OracleMap::load_one(&oracle_account_info, 1000, None).unwrap();
+    let oracle_price_data = oracle_map.get_price_data(&oracle_price_key).unwrap();
+    assert_eq!(oracle_price_data.price, 5_000_000);
+}
@@ -1,9 +1,12 @@
 use crate::error::{DriftResult, ErrorCode};
-use crate::ids::{bonk_oracle, pyth_program};
+use crate::ids::{bonk_oracle, pyth_program, pyth_receiver_program, switchboard_on_demand};
 use crate::math::constants::PRICE_PRECISION_I64;
-use crate::math::oracle::{oracle_validity, OracleValidity};
+use crate::math::oracle::{calculate_median_oracle_price_data, oracle_validity, OracleValidity};
+use crate::state::lst_oracle::is_lst_oracle_account;
 use crate::state::oracle::{get_oracle_price, OraclePriceData, OracleSource};
-use crate::state::state::OracleGuardRails;
+use crate::state::perp_market_map::PerpMarketMap;
+use crate::state::prelaunch_oracle::is_prelaunch_oracle_account;
+use crate::state::state::{OracleGuardRails, ValidityGuardRailsOverride};
 use anchor_lang::prelude::{AccountInfo, Pubkey};
 use anchor_lang::Key;
 use solana_program::msg;
@@ -14,15 +17,31 @@ use std::slice::Iter;
 use super::state::ValidityGuardRails;
 use crate::math::safe_unwrap::SafeUnwrap;
 
+#[cfg(test)]
+mod tests;
+
 pub struct AccountInfoAndOracleSource<'a> {
     /// CHECK: ownders are validated in OracleMap::load
     pub account_info: AccountInfo<'a>,
     pub oracle_source: OracleSource,
 }
 
+#[derive(Clone, Copy)]
+struct PendingOracleAggregation {
+    additional_oracles: [Pubkey; 2],
+    last_oracle_price_twap: i64,
+    validity_guard_rails_override: ValidityGuardRailsOverride,
+}
+
 pub struct OracleMap<'a> {
     oracles: BTreeMap<Pubkey, AccountInfoAndOracleSource<'a>>,
     price_data: BTreeMap<Pubkey, OraclePriceData>,
+    /// validity by oracle and last twap, so the margin checks of every participant in a fill
+    /// dont each recompute (and log) it for the same markets
+    validity: BTreeMap<(Pubkey, i64), (ValidityGuardRailsOverride, OracleValidity)>,
+    /// additional oracles of loaded perp markets by the market's primary oracle. the primary's
+    /// price is replaced by the median the first time it is read
+    pending_aggregations: BTreeMap<Pubkey, PendingOracleAggregation>,
     pub slot: u64,
     pub oracle_guard_rails: OracleGuardRails,
     pub quote_asset_price_data: OraclePriceData,
@@ -51,20 +70,7 @@ impl<'a> OracleMap<'a> {
             return self.price_data.get(pubkey).safe_unwrap();
         }
 
-        let (account_info, oracle_source) = match self.oracles.get(pubkey) {
-            Some(AccountInfoAndOracleSource {
-                account_info,
-                oracle_source,
-            }) => (account_info, oracle_source),
-            None => {
-                msg!("oracle pubkey not found in oracle_map: {}", pubkey);
-                return Err(ErrorCode::OracleNotFound);
-            }
-        };
-
-        let price_data = get_oracle_price(oracle_source, account_info, self.slot)?;
-
-        self.price_data.insert(*pubkey, price_data);
+        self.load_price_data(pubkey)?;
 
         self.price_data.get(pubkey).safe_unwrap()
     }
@@ -73,42 +79,52 @@ impl<'a> OracleMap<'a> {
         &mut self,
         pubkey: &Pubkey,
         last_oracle_price_twap: i64,
+        validity_guard_rails_override: &ValidityGuardRailsOverride,
     ) -> DriftResult<(&OraclePriceData, OracleValidity)> {
+        let validity_guard_rails = self
+            .oracle_guard_rails
+            .validity
+            .with_override(validity_guard_rails_override);
+
         if pubkey == &Pubkey::default() {
             return Ok((&self.quote_asset_price_data, OracleValidity::Valid));
         }
 
         if self.price_data.contains_key(pubkey) {
             let oracle_price_data = self.price_data.get(pubkey).safe_unwrap()?;
+
+            if let Some((cached_validity_guard_rails_override, oracle_validity)) =
+                self.validity.get(&(*pubkey, last_oracle_price_twap))
+            {
+                if cached_validity_guard_rails_override == validity_guard_rails_override {
+                    return Ok((oracle_price_data, *oracle_validity));
+                }
+            }
+
             let oracle_validity = oracle_validity(
                 last_oracle_price_twap,
                 oracle_price_data,
-                &self.oracle_guard_rails.validity,
+                &validity_guard_rails,
             )?;
+            self.validity.insert(
+                (*pubkey, last_oracle_price_twap),
+                (*validity_guard_rails_override, oracle_validity),
+            );
             return Ok((oracle_price_data, oracle_validity));
         }
 
-        let (account_info, oracle_source) = match self.oracles.get(pubkey) {
-            Some(AccountInfoAndOracleSource {
-                account_info,
-                oracle_source,
-            }) => (account_info, oracle_source),
-            None => {
-                msg!("oracle pubkey not found in oracle_map: {}", pubkey);
-                return Err(ErrorCode::OracleNotFound);
-            }
-        };
-
-        let price_data = get_oracle_price(oracle_source, account_info, self.slot)?;
-
-        self.price_data.insert(*pubkey, price_data);
+        self.load_price_data(pubkey)?;
 
         let oracle_price_data = self.price_data.get(pubkey).safe_unwrap()?;
         let oracle_validity = oracle_validity(
             last_oracle_price_twap,
             oracle_price_data,
-            &self.oracle_guard_rails.validity,
+            &validity_guard_rails,
         )?;
+        self.validity.insert(
+            (*pubkey, last_oracle_price_twap),
+            (*validity_guard_rails_override, oracle_validity),
+        );
 
         Ok((oracle_price_data, oracle_validity))
     }
@@ -129,6 +145,15 @@ impl<'a> OracleMap<'a> {
             return Ok((oracle_price_data, validity_guard_rails));
         }
 
+        self.load_price_data(pubkey)?;
+
+        let oracle_price_data = self.price_data.get(pubkey).safe_unwrap()?;
+        let validity_guard_rails = &self.oracle_guard_rails.validity;
+
+        Ok((oracle_price_data, validity_guard_rails))
+    }
+
+    fn get_raw_price_data(&self, pubkey: &Pubkey) -> DriftResult<OraclePriceData> {
         let (account_info, oracle_source) = match self.oracles.get(pubkey) {
             Some(AccountInfoAndOracleSource {
                 account_info,
@@ -140,14 +165,102 @@ impl<'a> OracleMap<'a> {
             }
         };
 
-        let price_data = get_oracle_price(oracle_source, account_info, self.slot)?;
+        get_oracle_price(oracle_source, account_info, self.slot)
+    }
+
+    fn load_price_data(&mut self, pubkey: &Pubkey) -> DriftResult {
+        let price_data = self.get_raw_price_data(pubkey)?;
+
+        let price_data = match self.pending_aggregations.remove(pubkey) {
+            Some(aggregation) => self.aggregate_price_data(price_data, &aggregation)?,
+            None => price_data,
+        };
 
         self.price_data.insert(*pubkey, price_data);
 
-        let oracle_price_data = self.price_data.get(pubkey).safe_unwrap()?;
-        let validity_guard_rails = &self.oracle_guard_rails.validity;
+        Ok(())
+    }
 
-        Ok((oracle_price_data, validity_guard_rails))
+    fn aggregate_price_data(
+        &self,
+        primary_price_data: OraclePriceData,
+        aggregation: &PendingOracleAggregation,
+    ) -> DriftResult<OraclePriceData> {
+        let validity_guard_rails = self
+            .oracle_guard_rails
+            .validity
+            .with_override(&aggregation.validity_guard_rails_override);
+
+        let mut oracle_price_datas = Vec::with_capacity(3);
+        oracle_price_datas.push((
+            primary_price_data,
+            oracle_validity(
+                aggregation.last_oracle_price_twap,
+                &primary_price_data,
+                &validity_guard_rails,
+            )?,
+        ));
+
+        for oracle in aggregation.additional_oracles.iter() {
+            if *oracle == Pubkey::default() {
+                continue;
+            }
+
+            // additional oracles are optional accounts, the median is taken over the ones passed
+            if !self.oracles.contains_key(oracle) {
+                msg!("additional oracle not passed, skipping: {}", oracle);
+                continue;
+            }
+
+            // a failing additional oracle is skipped rather than failing the whole market
+            match self.get_raw_price_data(oracle) {
+                Ok(oracle_price_data) => oracle_price_datas.push((
+                    oracle_price_data,
+                    oracle_validity(
+                        aggregation.last_oracle_price_twap,
+                        &oracle_price_data,
+                        &validity_guard_rails,
+                    )?,
+                )),
+                Err(e) => msg!("skipping additional oracle {}: {:?}", oracle, e),
+            }
+        }
+
+        calculate_median_oracle_price_data(&oracle_price_datas)
+    }
+
+    /// For perp markets with additional oracles, the price of amm.oracle is replaced with the
+    /// median across the market's oracles when it is first read. Must run before any price is read
+    /// for the market; anything else priced off the same primary oracle will also see the median.
+    /// Markets that are never priced in the instruction don't read their oracles at all
+    pub fn add_perp_market_oracle_aggregations(
+        &mut self,
+        perp_market_map: &PerpMarketMap,
+    ) -> DriftResult {
+        for market_index in perp_market_map.0.keys() {
+            let perp_market = perp_market_map.get_ref(market_index)?;
+            if !perp_market.has_additional_oracles() {
+                continue;
+            }
+
+            self.price_data.remove(&perp_market.amm.oracle);
+            self.validity
+                .retain(|(oracle, _), _| *oracle != perp_market.amm.oracle);
+
+            self.pending_aggregations.insert(
+                perp_market.amm.oracle,
+                PendingOracleAggregation {
+                    additional_oracles: perp_market.additional_oracles,
+                    last_oracle_price_twap: perp_market
+                        .amm
+                        .historical_oracle_data
+                        .last_oracle_price_twap,
+                    validity_guard_rails_override: perp_market.amm.validity_guard_rails_override,
+                },
+            );
+        }
+
+        Ok(())
     }
 
     pub fn load<'c>(
@@ -158,6 +271,12 @@ impl<'a> OracleMap<'a> {
         let mut oracles: BTreeMap<Pubkey, AccountInfoAndOracleSource<'a>> = BTreeMap::new();
 
         while let Some(account_info) = account_info_iter.peek() {
+            // an oracle shared by several markets or makers is only classified once
+            if oracles.contains_key(account_info.key) {
+                account_info_iter.next();
+                continue;
+            }
+
             if account_info.owner == &pyth_program::id() {
                 let account_info = account_info_iter.next().safe_unwrap()?;
                 let pubkey = account_info.key();
@@ -179,6 +298,66 @@ impl<'a> OracleMap<'a> {
                 continue;
             }
 
+            if account_info.owner == &pyth_receiver_program::id() {
+                let account_info = account_info_iter.next().safe_unwrap()?;
+                let pubkey = account_info.key();
+
+                oracles.insert(
+                    pubkey,
+                    AccountInfoAndOracleSource {
+                        account_info: account_info.clone(),
+                        oracle_source: OracleSource::PythPull,
+                    },
+                );
+
+                continue;
+            }
+
+            if account_info.owner == &switchboard_on_demand::id() {
+                let account_info = account_info_iter.next().safe_unwrap()?;
+                let pubkey = account_info.key();
+
+                oracles.insert(
+                    pubkey,
+                    AccountInfoAndOracleSource {
+                        account_info: account_info.clone(),
+                        oracle_source: OracleSource::SwitchboardOnDemand,
+                    },
+                );
+
+                continue;
+            }
+
+            if is_prelaunch_oracle_account(account_info) {
+                let account_info = account_info_iter.next().safe_unwrap()?;
+                let pubkey = account_info.key();
+
+                oracles.insert(
+                    pubkey,
+                    AccountInfoAndOracleSource {
+                        account_info: account_info.clone(),
+                        oracle_source: OracleSource::Prelaunch,
+                    },
+                );
+
+                continue;
+            }
+
+            if is_lst_oracle_account(account_info) {
+                let account_info = account_info_iter.next().safe_unwrap()?;
+                let pubkey = account_info.key();
+
+                oracles.insert(
+                    pubkey,
+                    AccountInfoAndOracleSource {
+                        account_info: account_info.clone(),
+                        oracle_source: OracleSource::LstRedemptionRate,
+                    },
+                );
+
+                continue;
+            }
+
             break;
         }
 
@@ -191,6 +370,8 @@ impl<'a> OracleMap<'a> {
         Ok(OracleMap {
             oracles,
             price_data: BTreeMap::new(),
+            validity: BTreeMap::new(),
+            pending_aggregations: BTreeMap::new(),
             slot,
             oracle_guard_rails: ogr,
             quote_asset_price_data: OraclePriceData {
@@ -223,6 +404,38 @@ impl<'a> OracleMap<'a> {
                     oracle_source,
                 },
             );
+        } else if account_info.owner == &pyth_receiver_program::id() {
+            oracles.insert(
+                account_info.key(),
+                AccountInfoAndOracleSource {
+                    account_info: account_info.clone(),
+                    oracle_source: OracleSource::PythPull,
+                },
+            );
+        } else if account_info.owner == &switchboard_on_demand::id() {
+            oracles.insert(
+                account_info.key(),
+                AccountInfoAndOracleSource {
+                    account_info: account_info.clone(),
+                    oracle_source: OracleSource::SwitchboardOnDemand,
+                },
+            );
+        } else if is_prelaunch_oracle_account(account_info) {
+            oracles.insert(
+                account_info.key(),
+                AccountInfoAndOracleSource {
+                    account_info: account_info.clone(),
+                    oracle_source: OracleSource::Prelaunch,
+                },
+            );
+        } else if is_lst_oracle_account(account_info) {
+            oracles.insert(
+                account_info.key(),
+                AccountInfoAndOracleSource {
+                    account_info: account_info.clone(),
+                    oracle_source: OracleSource::LstRedemptionRate,
+                },
+            );
         } else if account_info.key() != Pubkey::default() {
             return Err(ErrorCode::InvalidOracle);
         }
@@ -236,6 +449,8 @@ impl<'a> OracleMap<'a> {
         Ok(OracleMap {
             oracles,
             price_data: BTreeMap::new(),
+            validity: BTreeMap::new(),
+            pending_aggregations: BTreeMap::new(),
             slot,
             oracle_guard_rails: ogr,
             quote_asset_price_data: OraclePriceData {
@@ -254,6 +469,8 @@ impl<'a> OracleMap<'a> {
         OracleMap {
             oracles: BTreeMap::new(),
             price_data: BTreeMap::new(),
+            validity: BTreeMap::new(),
+            pending_aggregations: BTreeMap::new(),
             slot: 0,
             oracle_guard_rails: OracleGuardRails::default(),
             quote_asset_price_data: OraclePriceData {
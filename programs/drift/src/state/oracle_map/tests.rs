@@ -0,0 +1,126 @@
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Owner;
+
+use crate::math::constants::PRICE_PRECISION_I64;
+use crate::math::oracle::OracleValidity;
+use crate::state::oracle_map::OracleMap;
+use crate::state::perp_market::{PerpMarket, AMM};
+use crate::state::perp_market_map::PerpMarketMap;
+use crate::state::state::ValidityGuardRailsOverride;
+use crate::test_utils::{
+    create_account_info, get_account_bytes, get_anchor_account_bytes, get_pyth_price,
+};
+use crate::{create_account_info, create_anchor_account_info};
+
+#[test]
+fn validity_cached_per_twap_and_override() {
+    let mut oracle_price = get_pyth_price(100, 6);
+    let oracle_price_key =
+        Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+    let pyth_program = crate::ids::pyth_program::id();
+    create_account_info!(
+        oracle_price,
+        &oracle_price_key,
+        &pyth_program,
+        oracle_account_info
+    );
+    let mut oracle_map = OracleMap::load_one(&oracle_account_info, 0, None).unwrap();
+
+    let twap = 100 * PRICE_PRECISION_I64;
+    let no_override = ValidityGuardRailsOverride::default();
+
+    let (_, oracle_validity) = oracle_map
+        .get_price_data_and_validity(&oracle_price_key, twap, &no_override)
+        .unwrap();
+    assert_eq!(oracle_map.validity.len(), 1);
+
+    // a cached validity is returned as is
+    oracle_map.validity.insert(
+        (oracle_price_key, twap),
+        (no_override, OracleValidity::Invalid),
+    );
+    let (_, cached_oracle_validity) = oracle_map
+        .get_price_data_and_validity(&oracle_price_key, twap, &no_override)
+        .unwrap();
+    assert_eq!(cached_oracle_validity, OracleValidity::Invalid);
+
+    // another override is recomputed
+    let stale_override = ValidityGuardRailsOverride {
+        slots_before_stale_for_margin: 1,
+        ..ValidityGuardRailsOverride::default()
+    };
+    oracle_map
+        .get_price_data_and_validity(&oracle_price_key, twap, &stale_override)
+        .unwrap();
+    assert_eq!(oracle_map.validity.len(), 1);
+    let (_, oracle_validity_after) = oracle_map
+        .get_price_data_and_validity(&oracle_price_key, twap, &no_override)
+        .unwrap();
+    assert_eq!(oracle_validity_after, oracle_validity);
+
+    // another twap is its own entry
+    oracle_map
+        .get_price_data_and_validity(&oracle_price_key, twap / 2, &no_override)
+        .unwrap();
+    assert_eq!(oracle_map.validity.len(), 2);
+}
+
+#[test]
+fn duplicate_oracles_loaded_once() {
+    let mut oracle_price = get_pyth_price(100, 6);
+    let oracle_price_key =
+        Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+    let pyth_program = crate::ids::pyth_program::id();
+    create_account_info!(
+        oracle_price,
+        &oracle_price_key,
+        &pyth_program,
+        oracle_account_info
+    );
+
+    let account_infos = vec![oracle_account_info.clone(), oracle_account_info];
+    let mut account_info_iter = account_infos.iter().peekable();
+    let oracle_map = OracleMap::load(&mut account_info_iter, 0, None).unwrap();
+
+    assert_eq!(oracle_map.oracles.len(), 1);
+    assert!(account_info_iter.peek().is_none());
+}
+
+#[test]
+fn additional_oracles_aggregated_only_when_priced() {
+    let mut oracle_price = get_pyth_price(100, 6);
+    let oracle_price_key =
+        Pubkey::from_str("J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix").unwrap();
+    let pyth_program = crate::ids::pyth_program::id();
+    create_account_info!(
+        oracle_price,
+        &oracle_price_key,
+        &pyth_program,
+        oracle_account_info
+    );
+    let mut oracle_map = OracleMap::load_one(&oracle_account_info, 0, None).unwrap();
+
+    // the additional oracle isnt passed
+    let mut perp_market = PerpMarket {
+        amm: AMM {
+            oracle: oracle_price_key,
+            ..AMM::default()
+        },
+        additional_oracles: [Pubkey::new_unique(), Pubkey::default()],
+        ..PerpMarket::default()
+    };
+    create_anchor_account_info!(perp_market, PerpMarket, perp_market_account_info);
+    let perp_market_map = PerpMarketMap::load_one(&perp_market_account_info, true).unwrap();
+
+    oracle_map
+        .add_perp_market_oracle_aggregations(&perp_market_map)
+        .unwrap();
+    assert_eq!(oracle_map.pending_aggregations.len(), 1);
+    assert!(oracle_map.price_data.is_empty());
+
+    let oracle_price_data = oracle_map.get_price_data(&oracle_price_key).unwrap();
+    assert_eq!(oracle_price_data.price, 100 * PRICE_PRECISION_I64);
+    assert!(oracle_map.pending_aggregations.is_empty());
+}
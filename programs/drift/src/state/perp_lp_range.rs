@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct PerpLpRange {
+    pub user: Pubkey,
+    /// the lp's shares only provide liquidity while the amm reserve price is within the range
+    /// precision: PRICE_PRECISION
+    pub lower_price: u64,
+    /// precision: PRICE_PRECISION
+    pub upper_price: u64,
+    /// shares burned from the amm while the reserve price was out of range
+    /// minted back once it re-enters the range
+    /// precision: AMM_RESERVE_PRECISION
+    pub inactive_shares: u64,
+    pub market_index: u16,
+    pub padding: [u8; 6],
+}
+
+impl Size for PerpLpRange {
+    const SIZE: usize = 72;
+}
+
+impl PerpLpRange {
+    pub fn update_range(&mut self, lower_price: u64, upper_price: u64) -> DriftResult {
+        validate!(
+            lower_price > 0 && lower_price < upper_price,
+            ErrorCode::InvalidLpRange,
+            "lower price {} must be positive and less than upper price {}",
+            lower_price,
+            upper_price
+        )?;
+
+        self.lower_price = lower_price;
+        self.upper_price = upper_price;
+
+        Ok(())
+    }
+
+    pub fn is_in_range(&self, price: u64) -> bool {
+        self.lower_price <= price && price <= self.upper_price
+    }
+}
@@ -0,0 +1,43 @@
+use crate::math::constants::PRICE_PRECISION_U64;
+use crate::state::perp_lp_range::PerpLpRange;
+
+#[test]
+fn update_range() {
+    let mut perp_lp_range = PerpLpRange::default();
+
+    perp_lp_range
+        .update_range(90 * PRICE_PRECISION_U64, 110 * PRICE_PRECISION_U64)
+        .unwrap();
+    assert_eq!(perp_lp_range.lower_price, 90 * PRICE_PRECISION_U64);
+    assert_eq!(perp_lp_range.upper_price, 110 * PRICE_PRECISION_U64);
+
+    // empty and inverted ranges are rejected
+    assert!(perp_lp_range
+        .update_range(100 * PRICE_PRECISION_U64, 100 * PRICE_PRECISION_U64)
+        .is_err());
+    assert!(perp_lp_range
+        .update_range(110 * PRICE_PRECISION_U64, 90 * PRICE_PRECISION_U64)
+        .is_err());
+    assert!(perp_lp_range
+        .update_range(0, 90 * PRICE_PRECISION_U64)
+        .is_err());
+
+    // failed updates leave the range untouched
+    assert_eq!(perp_lp_range.lower_price, 90 * PRICE_PRECISION_U64);
+    assert_eq!(perp_lp_range.upper_price, 110 * PRICE_PRECISION_U64);
+}
+
+#[test]
+fn is_in_range() {
+    let perp_lp_range = PerpLpRange {
+        lower_price: 90 * PRICE_PRECISION_U64,
+        upper_price: 110 * PRICE_PRECISION_U64,
+        ..PerpLpRange::default()
+    };
+
+    assert!(perp_lp_range.is_in_range(90 * PRICE_PRECISION_U64));
+    assert!(perp_lp_range.is_in_range(100 * PRICE_PRECISION_U64));
+    assert!(perp_lp_range.is_in_range(110 * PRICE_PRECISION_U64));
+    assert!(!perp_lp_range.is_in_range(90 * PRICE_PRECISION_U64 - 1));
+    assert!(!perp_lp_range.is_in_range(110 * PRICE_PRECISION_U64 + 1));
+}
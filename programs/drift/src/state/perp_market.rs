@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
+use enumflags2::BitFlags;
 
-use std::cmp::max;
+use std::cmp::{max, min};
 
 use crate::controller::position::PositionDirection;
 use crate::error::{DriftResult, ErrorCode};
@@ -11,22 +12,32 @@ use crate::math::constants::{
     AMM_RESERVE_PRECISION, MAX_CONCENTRATION_COEFFICIENT, PRICE_PRECISION_I64,
 };
 use crate::math::constants::{
-    BID_ASK_SPREAD_PRECISION_U128, MARGIN_PRECISION_U128, SPOT_WEIGHT_PRECISION, TWENTY_FOUR_HOUR,
+    BID_ASK_SPREAD_PRECISION_U128, MARGIN_PRECISION_U128, ONE_BPS_DENOMINATOR,
+    SPOT_WEIGHT_PRECISION, TWENTY_FOUR_HOUR,
 };
-
+use crate::math::funding::calculate_funding_payment_in_quote_precision;
+use crate::math::helpers::log10;
 use crate::math::margin::{
     calculate_size_discount_asset_weight, calculate_size_premium_liability_weight,
     MarginRequirementType,
 };
+use crate::math::position::calculate_entry_price;
 use crate::math::safe_math::SafeMath;
+use crate::math::safe_unwrap::SafeUnwrap;
 use crate::math::stats;
 
-use crate::state::oracle::{HistoricalOracleData, OracleSource};
+use crate::state::oracle::{
+    get_pyth_pull_twap, load_prelaunch_oracle, HistoricalOracleData, OracleSource,
+};
 use crate::state::spot_market::{AssetTier, SpotBalance, SpotBalanceType};
+use crate::state::state::ValidityGuardRailsOverride;
 use crate::state::traits::{MarketIndexOffset, Size};
 use crate::{AMM_TO_QUOTE_PRECISION_RATIO, PRICE_PRECISION};
 use borsh::{BorshDeserialize, BorshSerialize};
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
 pub enum MarketStatus {
     Initialized,    // warm up period for initialization, fills are paused
@@ -46,10 +57,25 @@ impl Default for MarketStatus {
     }
 }
 
+#[derive(BitFlags, Clone, Copy, PartialEq, Debug, Eq)]
+pub enum PerpOperation {
+    // Active = 0b00000000
+    OracleCircuitBreaker = 0b00000001, // oracle diverged from mark twap, fills and liquidations are paused
+    UpdateFunding = 0b00000010,
+    AmmFill = 0b00000100,
+    Fill = 0b00001000,
+    SettlePnl = 0b00010000,
+    SettlePositivePnl = 0b00100000, // withdrawing perp profits into the quote spot balance
+    Liquidation = 0b01000000,
+    RiskIncreasingOrder = 0b10000000,
+}
+
 #[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
 pub enum ContractType {
     Perpetual,
     Future,
+    /// binary event market, price is the probability in [0, 1] and resolution settles it at 0 or 1
+    Prediction,
 }
 
 impl Default for ContractType {
@@ -119,7 +145,30 @@ pub struct PerpMarket {
     pub status: MarketStatus,
     pub contract_type: ContractType,
     pub contract_tier: ContractTier,
-    pub padding: [u8; 51],
+    pub additional_oracles: [Pubkey; 2], // optional oracles aggregated with amm.oracle
+    pub additional_oracle_sources: [OracleSource; 2],
+    pub paused_operations: u8,
+    pub oracle_circuit_breaker_band: u32, // max oracle divergence from the 5min mark twap, 0 disables. precision: PERCENTAGE_PRECISION
+    pub auction_extension_price_buffer: u32, // how far an extended auction widens the end price. precision: PERCENTAGE_PRECISION
+    pub margin_tiers: [MarginTier; 3], // initial margin ratio steps by position notional, ascending
+    pub high_leverage_margin_ratio_initial: u16, // for users in high leverage mode, 0 if the market doesnt offer it. precision: MARGIN_PRECISION
+    pub high_leverage_margin_ratio_maintenance: u16, // precision: MARGIN_PRECISION
+    pub auction_extension_duration: u8, // slots an unfilled market order auction is extended by, 0 disables
+    pub correlation_group: u8, // markets sharing a non zero group offset each other's exposure in margin
+    pub correlation_margin_credit: u16, // margin credited per unit of offset notional. precision: MARGIN_PRECISION
+    pub auto_deleverage_enabled: bool, // bankruptcy losses the if and fee pool cant cover go to adl instead of funding
+    pub insurance_fund_max_draw_pct: u8, // max percent of the insurance vault a single draw for this market can take, 0 disables
+    pub lp_early_removal_penalty: u16, // penalty on the notional of lp shares removed before lp_min_duration, paid to remaining lps. precision: bps
+    pub lp_min_duration: u32, // seconds lp shares must be held to be removed without penalty, 0 disables
+    pub high_24h: u64, // highest fill price, decays to the latest fill over 24h. precision: PRICE_PRECISION
+    pub low_24h: u64, // lowest fill price, decays to the latest fill over 24h. precision: PRICE_PRECISION
+    pub funding_paid_24h: u64, // rolling 24h sum of funding paid by the side paying it. precision: QUOTE_PRECISION
+    pub insurance_fund_lent: u64, // quote lent by the insurance fund to the fee pool. precision: QUOTE_PRECISION
+    pub insurance_fund_lend_last_ts: i64, // last time interest accrued on insurance_fund_lent
+    pub insurance_fund_lend_rate: u32, // yearly interest the fee pool pays on insurance_fund_lent. precision: PERCENTAGE_PRECISION
+    pub order_tick_size_bps: u16, // tick size as bps of the oracle price, rounded down to a power of ten, amm.order_tick_size is the floor. 0 disables
+    pub insurance_fund_lend_max_pct: u8, // max percent of the insurance fund lent to the fee pool, 0 disables
+    pub padding: [u8; 1],
 }
 
 impl Default for PerpMarket {
@@ -150,13 +199,36 @@ impl Default for PerpMarket {
             status: MarketStatus::default(),
             contract_type: ContractType::default(),
             contract_tier: ContractTier::default(),
-            padding: [0; 51],
+            additional_oracles: [Pubkey::default(); 2],
+            additional_oracle_sources: [OracleSource::default(); 2],
+            paused_operations: 0,
+            oracle_circuit_breaker_band: 0,
+            auction_extension_price_buffer: 0,
+            margin_tiers: [MarginTier::default(); 3],
+            high_leverage_margin_ratio_initial: 0,
+            high_leverage_margin_ratio_maintenance: 0,
+            auction_extension_duration: 0,
+            correlation_group: 0,
+            correlation_margin_credit: 0,
+            auto_deleverage_enabled: false,
+            insurance_fund_max_draw_pct: 0,
+            lp_early_removal_penalty: 0,
+            lp_min_duration: 0,
+            high_24h: 0,
+            low_24h: 0,
+            funding_paid_24h: 0,
+            insurance_fund_lent: 0,
+            insurance_fund_lend_last_ts: 0,
+            insurance_fund_lend_rate: 0,
+            order_tick_size_bps: 0,
+            insurance_fund_lend_max_pct: 0,
+            padding: [0; 1],
         }
     }
 }
 
 impl Size for PerpMarket {
-    const SIZE: usize = 1216;
+    const SIZE: usize = 1328;
 }
 
 impl MarketIndexOffset for PerpMarket {
@@ -177,6 +249,35 @@ impl PerpMarket {
         Ok(self.status == MarketStatus::ReduceOnly)
     }
 
+    pub fn is_prediction_market(&self) -> bool {
+        self.contract_type == ContractType::Prediction
+    }
+
+    pub fn get_paused_operations(&self) -> DriftResult<BitFlags<PerpOperation>> {
+        BitFlags::<PerpOperation>::from_bits(usize::from(self.paused_operations)).safe_unwrap()
+    }
+
+    pub fn is_operation_paused(&self, operation: PerpOperation) -> DriftResult<bool> {
+        Ok(self.get_paused_operations()?.contains(operation))
+    }
+
+    pub fn set_operation_paused(&mut self, operation: PerpOperation, paused: bool) -> DriftResult {
+        let mut paused_operations = self.get_paused_operations()?;
+        if paused {
+            paused_operations.insert(operation);
+        } else {
+            paused_operations.remove(operation);
+        }
+        self.paused_operations = paused_operations.bits() as u8;
+        Ok(())
+    }
+
+    pub fn has_additional_oracles(&self) -> bool {
+        self.additional_oracles
+            .iter()
+            .any(|oracle| *oracle != Pubkey::default())
+    }
+
     pub fn get_sanitize_clamp_denominator(self) -> DriftResult<Option<i64>> {
         Ok(match self.contract_tier {
             ContractTier::A => Some(10_i64),   // 10%
@@ -187,18 +288,91 @@ impl PerpMarket {
         })
     }
 
+    /// total penalty a liquidated user pays, split between the liquidator and the insurance fund
+    pub fn get_liquidation_penalty(&self) -> DriftResult<u32> {
+        self.liquidator_fee.safe_add(self.if_liquidation_fee)
+    }
+
+    /// insurance this market can still draw, bounded by its lifetime claim and its share of the vault
+    pub fn get_max_insurance_fund_draw(
+        &self,
+        insurance_fund_vault_balance: u64,
+    ) -> DriftResult<u128> {
+        let max_insurance_withdraw = self
+            .insurance_claim
+            .quote_max_insurance
+            .safe_sub(self.insurance_claim.quote_settled_insurance)?
+            .cast::<u128>()?;
+
+        if self.insurance_fund_max_draw_pct == 0 {
+            return Ok(max_insurance_withdraw);
+        }
+
+        let max_vault_draw = insurance_fund_vault_balance
+            .cast::<u128>()?
+            .safe_mul(self.insurance_fund_max_draw_pct.cast()?)?
+            .safe_div(100)?;
+
+        Ok(max_insurance_withdraw.min(max_vault_draw))
+    }
+
+    /// tick new orders are placed at, scales with the oracle price when order_tick_size_bps is set
+    pub fn get_order_tick_size(&self, oracle_price: i64) -> DriftResult<u64> {
+        if self.order_tick_size_bps == 0 {
+            return Ok(self.amm.order_tick_size);
+        }
+
+        let price_tick = oracle_price
+            .unsigned_abs()
+            .cast::<u128>()?
+            .safe_mul(self.order_tick_size_bps.cast()?)?
+            .safe_div(ONE_BPS_DENOMINATOR.cast()?)?;
+
+        if price_tick == 0 {
+            return Ok(self.amm.order_tick_size);
+        }
+
+        let price_tick = 10_u128.pow(log10(price_tick).cast()?).cast::<u64>()?;
+
+        Ok(price_tick.max(self.amm.order_tick_size))
+    }
+
+    /// insurance_fund_equity counts what the insurance fund has lent out, so lending doesn't
+    /// shrink the bound
+    pub fn get_max_insurance_fund_lend(&self, insurance_fund_equity: u64) -> DriftResult<u64> {
+        insurance_fund_equity
+            .cast::<u128>()?
+            .safe_mul(self.insurance_fund_lend_max_pct.cast()?)?
+            .safe_div(100)?
+            .cast()
+    }
+
+    pub fn is_high_leverage_mode_enabled(&self) -> bool {
+        self.high_leverage_margin_ratio_initial > 0
+            && self.high_leverage_margin_ratio_maintenance > 0
+    }
+
     pub fn get_margin_ratio(
         &self,
         size: u128,
         margin_type: MarginRequirementType,
+        user_high_leverage_mode: bool,
     ) -> DriftResult<u32> {
         if self.status == MarketStatus::Settlement {
             return Ok(0); // no liability weight on size
         }
 
-        let default_margin_ratio = match margin_type {
-            MarginRequirementType::Initial => self.margin_ratio_initial,
-            MarginRequirementType::Maintenance => self.margin_ratio_maintenance,
+        let high_leverage_mode = user_high_leverage_mode && self.is_high_leverage_mode_enabled();
+
+        let default_margin_ratio = match (margin_type, high_leverage_mode) {
+            (MarginRequirementType::Initial, false) => self.margin_ratio_initial,
+            (MarginRequirementType::Maintenance, false) => self.margin_ratio_maintenance,
+            (MarginRequirementType::Initial, true) => {
+                self.high_leverage_margin_ratio_initial as u32
+            }
+            (MarginRequirementType::Maintenance, true) => {
+                self.high_leverage_margin_ratio_maintenance as u32
+            }
         };
 
         let size_adj_margin_ratio = calculate_size_premium_liability_weight(
@@ -281,6 +455,83 @@ impl PerpMarket {
             .max(self.amm.base_asset_amount_short.abs())
             .unsigned_abs()
     }
+
+    /// updates the rolling 24h volume, high and low with a fill
+    pub fn update_fill_stats(
+        &mut self,
+        base_asset_amount: u64,
+        quote_asset_amount: u64,
+        direction: PositionDirection,
+        now: i64,
+    ) -> DriftResult {
+        if base_asset_amount > 0 {
+            let fill_price =
+                calculate_entry_price(quote_asset_amount.cast()?, base_asset_amount.cast()?)?
+                    .cast::<u64>()?;
+            let since_last = now.safe_sub(self.amm.last_trade_ts)?;
+
+            self.high_24h = if self.high_24h == 0 {
+                fill_price
+            } else {
+                let high_24h = stats::calculate_rolling_extreme(
+                    self.high_24h,
+                    fill_price,
+                    since_last,
+                    TWENTY_FOUR_HOUR,
+                )?;
+                max(high_24h, fill_price)
+            };
+
+            self.low_24h = if self.low_24h == 0 {
+                fill_price
+            } else {
+                let low_24h = stats::calculate_rolling_extreme(
+                    self.low_24h,
+                    fill_price,
+                    since_last,
+                    TWENTY_FOUR_HOUR,
+                )?;
+                min(low_24h, fill_price)
+            };
+        }
+
+        self.amm
+            .update_volume_24h(quote_asset_amount, direction, now)
+    }
+
+    /// adds the funding the paying side owes for a funding rate update to funding_paid_24h.
+    /// must be called before amm.last_funding_rate_ts is updated
+    pub fn update_funding_paid_24h(
+        &mut self,
+        funding_rate_long: i128,
+        funding_rate_short: i128,
+        now: i64,
+    ) -> DriftResult {
+        // payments are negative for the side paying
+        let long_funding_payment = calculate_funding_payment_in_quote_precision(
+            funding_rate_long,
+            self.amm.base_asset_amount_long,
+        )?;
+        let short_funding_payment = calculate_funding_payment_in_quote_precision(
+            funding_rate_short,
+            self.amm.base_asset_amount_short,
+        )?;
+        let funding_paid = long_funding_payment
+            .min(0)
+            .safe_add(short_funding_payment.min(0))?
+            .unsigned_abs()
+            .cast::<u64>()?;
+
+        let since_last = max(1_i64, now.safe_sub(self.amm.last_funding_rate_ts)?);
+        self.funding_paid_24h = stats::calculate_rolling_sum(
+            self.funding_paid_24h,
+            funding_paid,
+            since_last,
+            TWENTY_FOUR_HOUR,
+        )?;
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -307,6 +558,13 @@ impl PerpMarket {
     }
 }
 
+/// Initial margin ratio a position is held to once its notional reaches the threshold
+#[derive(Copy, AnchorSerialize, AnchorDeserialize, Clone, Default, Eq, PartialEq, Debug)]
+pub struct MarginTier {
+    pub notional_threshold: u32, // in whole quote units (no decimals), 0 disables the tier
+    pub margin_ratio_initial: u32, // precision: MARGIN_PRECISION
+}
+
 #[zero_copy]
 #[derive(Default, Eq, PartialEq, Debug)]
 #[repr(C)]
@@ -434,7 +692,19 @@ pub struct AMM {
     pub amm_jit_intensity: u8,
     pub oracle_source: OracleSource,
     pub last_oracle_valid: bool,
-    pub padding: [u8; 48],
+    pub validity_guard_rails_override: ValidityGuardRailsOverride,
+    pub max_funding_rate_spread: u32, // max mark/oracle twap spread funding is paid on, 0 defaults to 3%. precision: PERCENTAGE_PRECISION
+    pub escalated_max_funding_rate_spread: u32, // cap once divergence persists for the escalation periods, 0 disables. precision: PERCENTAGE_PRECISION
+    pub funding_divergence_escalation_periods: u8, // consecutive capped funding periods before the escalated cap applies
+    pub funding_divergence_period_count: u8, // consecutive funding periods the spread has exceeded max_funding_rate_spread
+    pub inventory_skew_spread: u16, // max extra spread on the side adding to amm inventory, scaled by inventory ratio, 0 disables. precision: bps
+    pub volatility_spread_scale: u16, // extra spread on both sides as a percent of realized volatility (mark/oracle std), 0 disables
+    pub max_reference_price_offset: u16, // max shift of the quoted mid from the reserve price, 0 disables. precision: bps
+    pub reference_price_offset: i32, // current shift of the quoted mid, positive raises both bid and ask. precision: BID_ASK_SPREAD_PRECISION
+    pub fee_override_enabled: bool, // fills must pass the market's MarketFeeOverride and are charged the adjusted fee structure
+    pub delisting_enabled: bool, // settlement must pass the market's PerpMarketDelisting and uses its sampled settlement price twap
+    pub whitelist_enabled: bool, // orders can only be placed and filled by authorities with a MarketWhitelistEntry
+    pub padding: [u8; 9],
 }
 
 impl Default for AMM {
@@ -515,7 +785,19 @@ impl Default for AMM {
             amm_jit_intensity: 0,
             oracle_source: OracleSource::default(),
             last_oracle_valid: false,
-            padding: [0; 48],
+            validity_guard_rails_override: ValidityGuardRailsOverride::default(),
+            max_funding_rate_spread: 0,
+            escalated_max_funding_rate_spread: 0,
+            funding_divergence_escalation_periods: 0,
+            funding_divergence_period_count: 0,
+            inventory_skew_spread: 0,
+            volatility_spread_scale: 0,
+            max_reference_price_offset: 0,
+            reference_price_offset: 0,
+            fee_override_enabled: false,
+            delisting_enabled: false,
+            whitelist_enabled: false,
+            padding: [0; 9],
         }
     }
 }
@@ -570,6 +852,10 @@ impl AMM {
             OracleSource::Pyth1K => Ok(Some(self.get_pyth_twap(price_oracle, 1000)?)),
             OracleSource::Pyth1M => Ok(Some(self.get_pyth_twap(price_oracle, 1000000)?)),
             OracleSource::Switchboard => Ok(None),
+            OracleSource::SwitchboardOnDemand => Ok(None),
+            OracleSource::PythPull => Ok(Some(get_pyth_pull_twap(price_oracle)?)),
+            OracleSource::Prelaunch => Ok(Some(load_prelaunch_oracle(price_oracle)?.price)),
+            OracleSource::LstRedemptionRate => Ok(None),
             OracleSource::QuoteAsset => {
                 msg!("Can't get oracle twap for quote asset");
                 Err(ErrorCode::DefaultError)
@@ -0,0 +1,113 @@
+use crate::controller::position::PositionDirection;
+use crate::math::constants::{
+    BASE_PRECISION_I128, BASE_PRECISION_U64, FUNDING_RATE_PRECISION_I128, PRICE_PRECISION_I64,
+    PRICE_PRECISION_U64, QUOTE_PRECISION_U64,
+};
+use crate::state::perp_market::{PerpMarket, AMM};
+
+#[test]
+fn fill_stats() {
+    let mut perp_market = PerpMarket::default_test();
+
+    let mut fill = |price: u64, now: i64| {
+        perp_market
+            .update_fill_stats(
+                BASE_PRECISION_U64,
+                price * QUOTE_PRECISION_U64,
+                PositionDirection::Long,
+                now,
+            )
+            .unwrap();
+        (perp_market.high_24h, perp_market.low_24h)
+    };
+
+    assert_eq!(
+        fill(100, 0),
+        (100 * PRICE_PRECISION_U64, 100 * PRICE_PRECISION_U64)
+    );
+    assert_eq!(
+        fill(110, 0),
+        (110 * PRICE_PRECISION_U64, 100 * PRICE_PRECISION_U64)
+    );
+
+    // half a day later the high and low are halfway to the latest fill
+    assert_eq!(fill(105, 12 * 60 * 60), (107_500_000, 102_500_000));
+
+    // a full day later only the latest fill counts
+    assert_eq!(
+        fill(120, 36 * 60 * 60),
+        (120 * PRICE_PRECISION_U64, 120 * PRICE_PRECISION_U64)
+    );
+}
+
+#[test]
+fn funding_paid_24h() {
+    let mut perp_market = PerpMarket {
+        amm: AMM {
+            base_asset_amount_long: 10 * BASE_PRECISION_I128,
+            base_asset_amount_short: -10 * BASE_PRECISION_I128,
+            ..AMM::default_test()
+        },
+        ..PerpMarket::default_test()
+    };
+
+    // longs pay shorts $.01 per base
+    let funding_rate = FUNDING_RATE_PRECISION_I128 / 100;
+    perp_market
+        .update_funding_paid_24h(funding_rate, funding_rate, 0)
+        .unwrap();
+    assert_eq!(perp_market.funding_paid_24h, QUOTE_PRECISION_U64 / 10);
+
+    // shorts pay longs, half of the earlier payment has rolled off
+    perp_market.amm.last_funding_rate_ts = 0;
+    perp_market
+        .update_funding_paid_24h(-funding_rate, -funding_rate, 12 * 60 * 60)
+        .unwrap();
+    assert_eq!(
+        perp_market.funding_paid_24h,
+        QUOTE_PRECISION_U64 / 20 + QUOTE_PRECISION_U64 / 10
+    );
+}
+
+#[test]
+fn order_tick_size_scales_with_oracle_price() {
+    let mut perp_market = PerpMarket {
+        amm: AMM {
+            order_tick_size: 100,
+            ..AMM::default()
+        },
+        ..PerpMarket::default()
+    };
+
+    assert_eq!(
+        perp_market
+            .get_order_tick_size(1000 * PRICE_PRECISION_I64)
+            .unwrap(),
+        100
+    );
+
+    // 1 bps of $1000 is $.1
+    perp_market.order_tick_size_bps = 1;
+    assert_eq!(
+        perp_market
+            .get_order_tick_size(1000 * PRICE_PRECISION_I64)
+            .unwrap(),
+        PRICE_PRECISION_U64 / 10
+    );
+
+    // 1 bps of $2500 is $.25, rounded down to $.1
+    assert_eq!(
+        perp_market
+            .get_order_tick_size(2500 * PRICE_PRECISION_I64)
+            .unwrap(),
+        PRICE_PRECISION_U64 / 10
+    );
+
+    // never finer than amm.order_tick_size
+    assert_eq!(
+        perp_market
+            .get_order_tick_size(PRICE_PRECISION_I64)
+            .unwrap(),
+        100
+    );
+}
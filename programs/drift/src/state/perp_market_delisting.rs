@@ -0,0 +1,120 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::casting::Cast;
+use crate::math::safe_math::SafeMath;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct PerpMarketDelisting {
+    /// sum of sampled oracle prices weighted by the seconds each was held for, seeded with the
+    /// oracle twap from the start of the window to the first sample
+    pub settlement_price_cumulative: i64,
+    /// oracle price at the last sample, held until the next sample or expiry
+    pub last_oracle_price: i64,
+    pub first_sample_ts: i64,
+    pub last_sample_ts: i64,
+    /// seconds before the market's expiry_ts the settlement price is averaged over
+    pub settlement_twap_window: u32,
+    pub market_index: u16,
+    pub padding: [u8; 2],
+}
+
+impl Size for PerpMarketDelisting {
+    const SIZE: usize = 48;
+}
+
+impl PerpMarketDelisting {
+    pub fn twap_window_start(&self, expiry_ts: i64) -> DriftResult<i64> {
+        expiry_ts.safe_sub(self.settlement_twap_window.cast()?)
+    }
+
+    /// samples must span at least half the window for the twap to be used
+    pub fn min_sample_duration(&self) -> DriftResult<i64> {
+        self.settlement_twap_window.safe_div(2)?.cast()
+    }
+
+    pub fn update_settlement_price_twap(
+        &mut self,
+        oracle_price: i64,
+        oracle_price_twap: i64,
+        expiry_ts: i64,
+        now: i64,
+    ) -> DriftResult {
+        let twap_window_start = self.twap_window_start(expiry_ts)?;
+        validate!(
+            twap_window_start <= now && now <= expiry_ts,
+            ErrorCode::SettlementPriceTwapWindowInactive,
+            "settlement price twap window is [{}, {}], now {}",
+            twap_window_start,
+            expiry_ts,
+            now
+        )?;
+
+        validate!(
+            oracle_price > 0,
+            ErrorCode::MarketSettlementTargetPriceInvalid,
+            "oracle price <= 0 {}",
+            oracle_price
+        )?;
+
+        if self.first_sample_ts == 0 {
+            validate!(
+                oracle_price_twap > 0,
+                ErrorCode::MarketSettlementTargetPriceInvalid,
+                "oracle price twap <= 0 {}",
+                oracle_price_twap
+            )?;
+
+            // the window before the first sample is held at the oracle twap, so a first sample
+            // late in the window can't set the settlement price on its own
+            self.settlement_price_cumulative =
+                oracle_price_twap.safe_mul(now.safe_sub(twap_window_start)?)?;
+            self.first_sample_ts = now;
+        } else {
+            let since_last = now.safe_sub(self.last_sample_ts)?;
+            self.settlement_price_cumulative = self
+                .settlement_price_cumulative
+                .safe_add(self.last_oracle_price.safe_mul(since_last)?)?;
+        }
+
+        self.last_oracle_price = oracle_price;
+        self.last_sample_ts = now;
+
+        Ok(())
+    }
+
+    /// time weighted average of the oracle prices over the window, None if the samples span less
+    /// than the minimum sample duration
+    pub fn settlement_price_twap(&self, expiry_ts: i64) -> DriftResult<Option<i64>> {
+        if self.first_sample_ts == 0 {
+            return Ok(None);
+        }
+
+        let sample_duration = self.last_sample_ts.safe_sub(self.first_sample_ts)?;
+        let min_sample_duration = self.min_sample_duration()?;
+        if sample_duration < min_sample_duration {
+            msg!(
+                "settlement price twap samples span {}s, need {}s",
+                sample_duration,
+                min_sample_duration
+            );
+            return Ok(None);
+        }
+
+        let duration = expiry_ts.safe_sub(self.twap_window_start(expiry_ts)?)?;
+
+        let since_last = expiry_ts.safe_sub(self.last_sample_ts)?;
+        let settlement_price_cumulative = self
+            .settlement_price_cumulative
+            .safe_add(self.last_oracle_price.safe_mul(since_last)?)?;
+
+        Ok(Some(settlement_price_cumulative.safe_div(duration)?))
+    }
+}
@@ -0,0 +1,119 @@
+use crate::error::ErrorCode;
+use crate::math::constants::PRICE_PRECISION_I64;
+use crate::state::perp_market_delisting::PerpMarketDelisting;
+
+#[test]
+fn update_settlement_price_twap() {
+    let expiry_ts = 10_000;
+    let oracle_price_twap = 11 * PRICE_PRECISION_I64;
+    let mut delisting = PerpMarketDelisting {
+        settlement_twap_window: 1000,
+        ..PerpMarketDelisting::default()
+    };
+
+    assert_eq!(delisting.settlement_price_twap(expiry_ts).unwrap(), None);
+
+    // before window
+    assert_eq!(
+        delisting.update_settlement_price_twap(
+            10 * PRICE_PRECISION_I64,
+            oracle_price_twap,
+            expiry_ts,
+            8999
+        ),
+        Err(ErrorCode::SettlementPriceTwapWindowInactive)
+    );
+
+    delisting
+        .update_settlement_price_twap(10 * PRICE_PRECISION_I64, oracle_price_twap, expiry_ts, 9000)
+        .unwrap();
+    // samples dont span the minimum sample duration yet
+    assert_eq!(delisting.settlement_price_twap(expiry_ts).unwrap(), None);
+
+    // 10 held for 250s
+    delisting
+        .update_settlement_price_twap(20 * PRICE_PRECISION_I64, oracle_price_twap, expiry_ts, 9250)
+        .unwrap();
+    assert_eq!(delisting.settlement_price_twap(expiry_ts).unwrap(), None);
+
+    // 20 held for 500s, 12 held for the remaining 250s
+    delisting
+        .update_settlement_price_twap(12 * PRICE_PRECISION_I64, oracle_price_twap, expiry_ts, 9750)
+        .unwrap();
+    assert_eq!(
+        delisting.settlement_price_twap(expiry_ts).unwrap(),
+        Some(15_500_000)
+    );
+
+    // after expiry
+    assert_eq!(
+        delisting.update_settlement_price_twap(
+            10 * PRICE_PRECISION_I64,
+            oracle_price_twap,
+            expiry_ts,
+            10_001
+        ),
+        Err(ErrorCode::SettlementPriceTwapWindowInactive)
+    );
+
+    assert_eq!(
+        delisting.update_settlement_price_twap(0, oracle_price_twap, expiry_ts, 9800),
+        Err(ErrorCode::MarketSettlementTargetPriceInvalid)
+    );
+}
+
+#[test]
+fn settlement_price_twap_seeded_with_oracle_twap() {
+    let expiry_ts = 10_000;
+    let oracle_price_twap = 10 * PRICE_PRECISION_I64;
+    let mut delisting = PerpMarketDelisting {
+        settlement_twap_window: 1000,
+        ..PerpMarketDelisting::default()
+    };
+
+    assert_eq!(
+        delisting.update_settlement_price_twap(20 * PRICE_PRECISION_I64, 0, expiry_ts, 9400),
+        Err(ErrorCode::MarketSettlementTargetPriceInvalid)
+    );
+
+    // oracle twap of 10 held for the 400s before the first sample, 20 for the remaining 600s
+    delisting
+        .update_settlement_price_twap(20 * PRICE_PRECISION_I64, oracle_price_twap, expiry_ts, 9400)
+        .unwrap();
+    delisting
+        .update_settlement_price_twap(
+            20 * PRICE_PRECISION_I64,
+            oracle_price_twap,
+            expiry_ts,
+            expiry_ts,
+        )
+        .unwrap();
+    assert_eq!(
+        delisting.settlement_price_twap(expiry_ts).unwrap(),
+        Some(16 * PRICE_PRECISION_I64)
+    );
+}
+
+#[test]
+fn settlement_price_twap_sampled_late() {
+    let expiry_ts = 10_000;
+    let oracle_price_twap = 10 * PRICE_PRECISION_I64;
+    let mut delisting = PerpMarketDelisting {
+        settlement_twap_window: 1000,
+        ..PerpMarketDelisting::default()
+    };
+
+    // samples only in the last 100s of the window dont set the settlement price
+    delisting
+        .update_settlement_price_twap(50 * PRICE_PRECISION_I64, oracle_price_twap, expiry_ts, 9900)
+        .unwrap();
+    delisting
+        .update_settlement_price_twap(
+            50 * PRICE_PRECISION_I64,
+            oracle_price_twap,
+            expiry_ts,
+            expiry_ts,
+        )
+        .unwrap();
+    assert_eq!(delisting.settlement_price_twap(expiry_ts).unwrap(), None);
+}
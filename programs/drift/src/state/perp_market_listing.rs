@@ -0,0 +1,136 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::ids::{pyth_receiver_program, switchboard_on_demand};
+use crate::math::casting::Cast;
+use crate::math::constants::{
+    AMM_RESERVE_PRECISION, PERMISSIONLESS_LISTING_MAX_OPEN_INTEREST_NOTIONAL,
+    PERMISSIONLESS_LISTING_MAX_RESERVE_NOTIONAL,
+};
+use crate::math::orders::standardize_base_asset_amount;
+use crate::math::position::calculate_base_asset_value_with_oracle_price;
+use crate::math::safe_math::SafeMath;
+use crate::state::oracle::OracleSource;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct PerpMarketListingConfig {
+    /// lamports a proposer posts to list a perp market, refunded on activation, forfeited to the admin on veto
+    pub bond_amount: u64,
+    /// seconds after a proposal the admin can veto it
+    pub veto_window: u32,
+    pub enabled: bool,
+    pub padding: [u8; 3],
+}
+
+impl Size for PerpMarketListingConfig {
+    const SIZE: usize = 24;
+}
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct PerpMarketListing {
+    pub proposer: Pubkey,
+    /// lamports held by the listing account on top of its rent
+    pub bond: u64,
+    pub veto_end_ts: i64,
+    pub market_index: u16,
+    pub padding: [u8; 6],
+}
+
+impl Size for PerpMarketListing {
+    const SIZE: usize = 64;
+}
+
+impl PerpMarketListing {
+    pub fn validate_vetoable(&self, now: i64) -> DriftResult {
+        validate!(
+            now < self.veto_end_ts,
+            ErrorCode::PerpMarketListingVetoWindowEnded,
+            "veto window for perp market {} ended at {}, now {}",
+            self.market_index,
+            self.veto_end_ts,
+            now
+        )
+    }
+
+    pub fn validate_activatable(&self, now: i64) -> DriftResult {
+        validate!(
+            now >= self.veto_end_ts,
+            ErrorCode::PerpMarketListingVetoWindowActive,
+            "perp market {} can be vetoed until {}, now {}",
+            self.market_index,
+            self.veto_end_ts,
+            now
+        )
+    }
+}
+
+/// proposers can only list on pull oracles owned by their oracle program, sources the admin or
+/// the proposer controls (prelaunch, quote asset) and derived prices (lst) are rejected
+pub fn validate_permissionless_listing_oracle(
+    oracle_source: OracleSource,
+    oracle_owner: &Pubkey,
+) -> DriftResult {
+    let oracle_program = match oracle_source {
+        OracleSource::PythPull => pyth_receiver_program::id(),
+        OracleSource::SwitchboardOnDemand => switchboard_on_demand::id(),
+        _ => {
+            msg!(
+                "oracle source {:?} cant be used for permissionless listing",
+                oracle_source
+            );
+            return Err(ErrorCode::InvalidOracle);
+        }
+    };
+
+    validate!(
+        *oracle_owner == oracle_program,
+        ErrorCode::InvalidOracle,
+        "{:?} oracle owned by {}, expected {}",
+        oracle_source,
+        oracle_owner,
+        oracle_program
+    )
+}
+
+pub fn validate_permissionless_listing_reserves(
+    amm_base_asset_reserve: u128,
+    oracle_price: i64,
+) -> DriftResult {
+    let reserve_notional =
+        calculate_base_asset_value_with_oracle_price(amm_base_asset_reserve.cast()?, oracle_price)?;
+
+    validate!(
+        reserve_notional <= PERMISSIONLESS_LISTING_MAX_RESERVE_NOTIONAL,
+        ErrorCode::InvalidPerpMarketListing,
+        "amm reserves worth {} above the permissionless listing max {}",
+        reserve_notional,
+        PERMISSIONLESS_LISTING_MAX_RESERVE_NOTIONAL
+    )
+}
+
+/// max open interest worth PERMISSIONLESS_LISTING_MAX_OPEN_INTEREST_NOTIONAL at the oracle price,
+/// at least one step so it never becomes 0 (uncapped)
+pub fn calculate_permissionless_listing_max_open_interest(
+    oracle_price: i64,
+    order_step_size: u64,
+) -> DriftResult<u128> {
+    let max_open_interest = PERMISSIONLESS_LISTING_MAX_OPEN_INTEREST_NOTIONAL
+        .safe_mul(AMM_RESERVE_PRECISION)?
+        .safe_div(oracle_price.cast()?)?
+        .cast::<u64>()?;
+
+    Ok(
+        standardize_base_asset_amount(max_open_interest, order_step_size)?
+            .max(order_step_size)
+            .cast()?,
+    )
+}
@@ -0,0 +1,112 @@
+use anchor_lang::prelude::Pubkey;
+
+use crate::error::ErrorCode;
+use crate::ids::{pyth_program, pyth_receiver_program, switchboard_on_demand};
+use crate::math::constants::{
+    AMM_RESERVE_PRECISION, BASE_PRECISION, DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE, PRICE_PRECISION_I64,
+};
+use crate::state::oracle::OracleSource;
+use crate::state::perp_market_listing::{
+    calculate_permissionless_listing_max_open_interest, validate_permissionless_listing_oracle,
+    validate_permissionless_listing_reserves, PerpMarketListing,
+};
+
+#[test]
+fn veto_window() {
+    let perp_market_listing = PerpMarketListing {
+        veto_end_ts: 1000,
+        ..PerpMarketListing::default()
+    };
+
+    assert!(perp_market_listing.validate_vetoable(999).is_ok());
+    assert_eq!(
+        perp_market_listing.validate_activatable(999),
+        Err(ErrorCode::PerpMarketListingVetoWindowActive)
+    );
+
+    assert_eq!(
+        perp_market_listing.validate_vetoable(1000),
+        Err(ErrorCode::PerpMarketListingVetoWindowEnded)
+    );
+    assert!(perp_market_listing.validate_activatable(1000).is_ok());
+}
+
+#[test]
+fn permissionless_listing_oracle() {
+    assert!(validate_permissionless_listing_oracle(
+        OracleSource::PythPull,
+        &pyth_receiver_program::id()
+    )
+    .is_ok());
+    assert!(validate_permissionless_listing_oracle(
+        OracleSource::SwitchboardOnDemand,
+        &switchboard_on_demand::id()
+    )
+    .is_ok());
+
+    // owned by another program
+    assert_eq!(
+        validate_permissionless_listing_oracle(OracleSource::PythPull, &Pubkey::new_unique()),
+        Err(ErrorCode::InvalidOracle)
+    );
+
+    for oracle_source in [
+        OracleSource::Pyth,
+        OracleSource::Prelaunch,
+        OracleSource::QuoteAsset,
+        OracleSource::LstRedemptionRate,
+    ] {
+        assert_eq!(
+            validate_permissionless_listing_oracle(oracle_source, &pyth_program::id()),
+            Err(ErrorCode::InvalidOracle)
+        );
+    }
+}
+
+#[test]
+fn permissionless_listing_reserves() {
+    // $1m of reserves at $10
+    let oracle_price = 10 * PRICE_PRECISION_I64;
+    assert!(validate_permissionless_listing_reserves(
+        100_000 * AMM_RESERVE_PRECISION,
+        oracle_price
+    )
+    .is_ok());
+    assert_eq!(
+        validate_permissionless_listing_reserves(100_001 * AMM_RESERVE_PRECISION, oracle_price),
+        Err(ErrorCode::InvalidPerpMarketListing)
+    );
+}
+
+#[test]
+fn permissionless_listing_max_open_interest() {
+    // $100k at $10
+    assert_eq!(
+        calculate_permissionless_listing_max_open_interest(
+            10 * PRICE_PRECISION_I64,
+            DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE
+        )
+        .unwrap(),
+        10_000 * BASE_PRECISION
+    );
+
+    // rounded down to the step size
+    assert_eq!(
+        calculate_permissionless_listing_max_open_interest(
+            3 * PRICE_PRECISION_I64,
+            DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE
+        )
+        .unwrap(),
+        33_333_333_300_000
+    );
+
+    // never uncapped
+    assert_eq!(
+        calculate_permissionless_listing_max_open_interest(
+            i64::MAX,
+            DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE
+        )
+        .unwrap(),
+        DEFAULT_BASE_ASSET_AMOUNT_STEP_SIZE as u128
+    );
+}
@@ -7,6 +7,7 @@ use std::slice::Iter;
 use anchor_lang::prelude::AccountInfo;
 
 use anchor_lang::Discriminator;
+use anchor_lang::Key;
 use arrayref::array_ref;
 
 use crate::error::{DriftResult, ErrorCode};
@@ -112,7 +113,13 @@ impl<'a> PerpMarketMap<'a> {
             // market index 1160 bytes from front of account
             let market_index = u16::from_le_bytes(*array_ref![data, 1160, 2]);
 
-            if perp_market_map.0.contains_key(&market_index) {
+            if let Some(account_loader) = perp_market_map.0.get(&market_index) {
+                // the same market passed for several makers is only loaded once
+                if account_loader.key() == *account_info.key {
+                    account_info_iter.next();
+                    continue;
+                }
+
                 msg!("Can not include same market index twice {}", market_index);
                 return Err(ErrorCode::InvalidMarketAccount);
             }
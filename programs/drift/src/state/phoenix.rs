@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::validate;
+
+// Mirrors the phoenix v1 MarketHeader followed by the fixed prefix of the FIFOMarket so
+// markets can be read without pulling in the phoenix crate. The order book trees that
+// follow the prefix are not mirrored
+
+pub const PHOENIX_MARKET_STATUS_ACTIVE: u64 = 1;
+
+#[zero_copy]
+#[repr(C)]
+pub struct PhoenixTokenParams {
+    pub decimals: u32,
+    pub vault_bump: u32,
+    pub mint_key: Pubkey,
+    pub vault_key: Pubkey,
+}
+
+#[zero_copy]
+#[repr(C)]
+pub struct PhoenixMarket {
+    pub discriminant: u64,
+    pub status: u64,
+    pub bids_size: u64,
+    pub asks_size: u64,
+    pub num_seats: u64,
+    pub base_params: PhoenixTokenParams,
+    pub base_lot_size: u64,
+    pub quote_params: PhoenixTokenParams,
+    pub quote_lot_size: u64,
+    pub tick_size_in_quote_atoms_per_base_unit: u64,
+    pub authority: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub market_sequence_number: u64,
+    pub successor: Pubkey,
+    pub raw_base_units_per_base_unit: u32,
+    pub padding1: u32,
+    pub padding2: [u64; 32],
+    pub padding3: [u64; 32],
+    pub base_lots_per_base_unit: u64,
+    pub tick_size_in_quote_lots_per_base_unit: u64,
+    pub order_sequence_number: u64,
+    pub taker_fee_bps: u64,
+    pub collected_quote_lot_fees: u64,
+    pub unclaimed_quote_lot_fees: u64,
+}
+
+pub fn load_phoenix_market(
+    account_info: &AccountInfo,
+    program_id: &Pubkey,
+) -> DriftResult<PhoenixMarket> {
+    validate!(
+        account_info.owner == program_id,
+        ErrorCode::InvalidPhoenixMarket,
+        "phoenix market not owned by phoenix program"
+    )?;
+
+    let data = account_info
+        .try_borrow_data()
+        .or(Err(ErrorCode::InvalidPhoenixMarket))?;
+
+    // phoenix seats are the only other accounts the program owns and they are far smaller
+    // than a market, so the length check is enough to tell them apart
+    let market_size = std::mem::size_of::<PhoenixMarket>();
+    validate!(
+        data.len() > market_size,
+        ErrorCode::InvalidPhoenixMarket,
+        "invalid phoenix market account"
+    )?;
+
+    let market = bytemuck::try_from_bytes::<PhoenixMarket>(&data[..market_size])
+        .or(Err(ErrorCode::InvalidPhoenixMarket))?;
+
+    Ok(*market)
+}
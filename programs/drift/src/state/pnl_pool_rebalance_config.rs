@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::safe_math::SafeMath;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct PnlPoolRebalanceConfig {
+    /// quote a market's pnl pool keeps above what its users are owed before the rest is released
+    pub surplus_buffer: u64,
+    /// max quote a single rebalance tops up one deficit market's pnl pool by
+    pub max_top_up: u64,
+    pub last_rebalance_ts: i64,
+    /// min seconds between rebalances
+    pub min_rebalance_interval: u32,
+    pub enabled: bool,
+    pub padding: [u8; 3],
+}
+
+impl Size for PnlPoolRebalanceConfig {
+    const SIZE: usize = 40;
+}
+
+impl PnlPoolRebalanceConfig {
+    pub fn validate_rebalance(&self, now: i64) -> DriftResult {
+        validate!(
+            self.enabled,
+            ErrorCode::PnlPoolRebalanceNotAllowed,
+            "pnl pool rebalancing disabled"
+        )?;
+
+        let next_rebalance_ts = self
+            .last_rebalance_ts
+            .safe_add(self.min_rebalance_interval as i64)?;
+        validate!(
+            now >= next_rebalance_ts,
+            ErrorCode::PnlPoolRebalanceNotAllowed,
+            "last pnl pool rebalance at {}, next allowed at {}, now {}",
+            self.last_rebalance_ts,
+            next_rebalance_ts,
+            now
+        )
+    }
+}
@@ -0,0 +1,23 @@
+use crate::error::ErrorCode;
+use crate::state::pnl_pool_rebalance_config::PnlPoolRebalanceConfig;
+
+#[test]
+fn validate_rebalance() {
+    let mut config = PnlPoolRebalanceConfig {
+        last_rebalance_ts: 1000,
+        min_rebalance_interval: 3600,
+        ..PnlPoolRebalanceConfig::default()
+    };
+
+    assert_eq!(
+        config.validate_rebalance(10_000),
+        Err(ErrorCode::PnlPoolRebalanceNotAllowed)
+    );
+
+    config.enabled = true;
+    assert_eq!(
+        config.validate_rebalance(4599),
+        Err(ErrorCode::PnlPoolRebalanceNotAllowed)
+    );
+    assert!(config.validate_rebalance(4600).is_ok());
+}
@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::casting::Cast;
+use crate::math::constants::PERCENTAGE_PRECISION;
+use crate::math::safe_math::SafeMath;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct PrelaunchOracle {
+    /// precision: PRICE_PRECISION
+    pub price: i64,
+    /// max price change per slot since the last update
+    /// precision: PERCENTAGE_PRECISION
+    pub max_price_drift_per_slot: u64,
+    /// precision: PRICE_PRECISION
+    pub confidence: u64,
+    pub last_update_slot: u64,
+    pub perp_market_index: u16,
+    pub padding: [u8; 30],
+}
+
+impl Size for PrelaunchOracle {
+    const SIZE: usize = 72;
+}
+
+#[derive(Debug, Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq, Eq)]
+pub struct PrelaunchOracleParams {
+    pub perp_market_index: u16,
+    pub price: Option<i64>,
+    pub max_price_drift_per_slot: Option<u64>,
+    pub confidence: Option<u64>,
+}
+
+impl PrelaunchOracle {
+    pub fn update_price(&mut self, price: i64, slot: u64) -> DriftResult {
+        validate!(
+            price > 0,
+            ErrorCode::InvalidPrelaunchOraclePrice,
+            "prelaunch oracle price must be positive"
+        )?;
+
+        let slots_since_last_update = slot.saturating_sub(self.last_update_slot).max(1);
+
+        let max_price_change = self
+            .price
+            .unsigned_abs()
+            .cast::<u128>()?
+            .safe_mul(self.max_price_drift_per_slot.cast()?)?
+            .safe_mul(slots_since_last_update.cast()?)?
+            .safe_div(PERCENTAGE_PRECISION)?;
+
+        let price_change = price.safe_sub(self.price)?.unsigned_abs().cast::<u128>()?;
+
+        validate!(
+            price_change <= max_price_change,
+            ErrorCode::InvalidPrelaunchOraclePrice,
+            "prelaunch oracle price change {} exceeds max {} over {} slots",
+            price_change,
+            max_price_change,
+            slots_since_last_update
+        )?;
+
+        self.price = price;
+        self.last_update_slot = slot;
+
+        Ok(())
+    }
+}
+
+pub fn is_prelaunch_oracle_account(account_info: &AccountInfo) -> bool {
+    account_info.owner == &crate::id()
+        && account_info
+            .try_borrow_data()
+            .map(|data| data.len() >= 8 && data[..8] == PrelaunchOracle::discriminator())
+            .unwrap_or(false)
+}
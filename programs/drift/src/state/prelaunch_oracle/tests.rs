@@ -0,0 +1,25 @@
+use crate::math::constants::{PERCENTAGE_PRECISION_U64, PRICE_PRECISION_I64};
+use crate::state::prelaunch_oracle::PrelaunchOracle;
+
+#[test]
+fn update_price_bounded_by_max_drift() {
+    let mut oracle = PrelaunchOracle {
+        price: 10 * PRICE_PRECISION_I64,
+        max_price_drift_per_slot: PERCENTAGE_PRECISION_U64 / 100, // 1% per slot
+        last_update_slot: 100,
+        ..PrelaunchOracle::default()
+    };
+
+    // 1 slot allows at most 1%
+    assert!(oracle.update_price(10_200_000, 101).is_err());
+    oracle.update_price(10_100_000, 101).unwrap();
+    assert_eq!(oracle.price, 10_100_000);
+    assert_eq!(oracle.last_update_slot, 101);
+
+    // 5 slots allows at most 5%
+    assert!(oracle.update_price(9_500_000, 106).is_err());
+    oracle.update_price(9_600_000, 106).unwrap();
+    assert_eq!(oracle.price, 9_600_000);
+
+    assert!(oracle.update_price(0, 1000).is_err());
+}
@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::validate;
+
+// Mirrors the pyth-solana-receiver PriceUpdateV2 account so pull updates can be read
+// without pulling in the pyth-solana-receiver-sdk crate
+
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+pub fn load_price_update_v2(price_oracle: &AccountInfo) -> DriftResult<PriceUpdateV2> {
+    let data = price_oracle
+        .try_borrow_data()
+        .or(Err(ErrorCode::UnableToLoadOracle))?;
+
+    validate!(
+        data.len() > 8 && data[..8] == PRICE_UPDATE_V2_DISCRIMINATOR,
+        ErrorCode::UnableToLoadOracle,
+        "invalid pyth pull price update discriminator"
+    )?;
+
+    let price_update =
+        PriceUpdateV2::deserialize(&mut &data[8..]).or(Err(ErrorCode::UnableToLoadOracle))?;
+
+    // partially verified updates have only been checked against a subset of the wormhole guardians
+    validate!(
+        price_update.verification_level == VerificationLevel::Full,
+        ErrorCode::InvalidOracle,
+        "pyth pull price update not fully verified"
+    )?;
+
+    Ok(price_update)
+}
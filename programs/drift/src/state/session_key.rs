@@ -0,0 +1,95 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::safe_math::SafeMath;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+pub const MAX_SESSION_KEY_PERP_MARKETS: usize = 8;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct SessionKey {
+    pub user: Pubkey,
+    /// key the frontend holds to place orders for the user without the authority signing
+    pub session_signer: Pubkey,
+    pub expiry_ts: i64,
+    /// total notional of orders the session can place over its lifetime
+    /// precision: QUOTE_PRECISION
+    pub max_notional: u64,
+    /// precision: QUOTE_PRECISION
+    pub notional_used: u64,
+    pub perp_market_indexes: [u16; 8],
+    pub num_perp_markets: u8,
+    pub padding: [u8; 7],
+}
+
+impl Size for SessionKey {
+    const SIZE: usize = 120;
+}
+
+impl SessionKey {
+    pub fn set_perp_market_indexes(&mut self, perp_market_indexes: &[u16]) -> DriftResult {
+        validate!(
+            !perp_market_indexes.is_empty()
+                && perp_market_indexes.len() <= MAX_SESSION_KEY_PERP_MARKETS,
+            ErrorCode::InvalidSessionKey,
+            "session key must allow between 1 and {} perp markets",
+            MAX_SESSION_KEY_PERP_MARKETS
+        )?;
+
+        self.perp_market_indexes = [0; 8];
+        self.perp_market_indexes[..perp_market_indexes.len()].copy_from_slice(perp_market_indexes);
+        self.num_perp_markets = perp_market_indexes.len() as u8;
+
+        Ok(())
+    }
+
+    pub fn is_perp_market_allowed(&self, market_index: u16) -> bool {
+        self.perp_market_indexes[..self.num_perp_markets as usize].contains(&market_index)
+    }
+
+    /// orders are charged their notional at placement, so sizes the program fills in afterwards
+    /// (max margin sizing or u64::MAX for the max order size) can't be placed with a session key
+    pub fn validate_order_size(base_asset_amount: u64, max_margin_sizing: bool) -> DriftResult {
+        validate!(
+            !max_margin_sizing && base_asset_amount != u64::MAX,
+            ErrorCode::InvalidSessionKey,
+            "session keys cant place max margin sized orders"
+        )
+    }
+
+    pub fn use_for_order(&mut self, market_index: u16, notional: u64, now: i64) -> DriftResult {
+        validate!(
+            now <= self.expiry_ts,
+            ErrorCode::SessionKeyExpired,
+            "session key expired at {}",
+            self.expiry_ts
+        )?;
+
+        validate!(
+            self.is_perp_market_allowed(market_index),
+            ErrorCode::SessionKeyLimitExceeded,
+            "session key not allowed to trade perp market {}",
+            market_index
+        )?;
+
+        let notional_used = self.notional_used.safe_add(notional)?;
+
+        validate!(
+            notional_used <= self.max_notional,
+            ErrorCode::SessionKeyLimitExceeded,
+            "session key notional used {} would exceed max notional {}",
+            notional_used,
+            self.max_notional
+        )?;
+
+        self.notional_used = notional_used;
+
+        Ok(())
+    }
+}
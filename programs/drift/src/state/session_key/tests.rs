@@ -0,0 +1,74 @@
+use crate::error::ErrorCode;
+use crate::math::constants::{BASE_PRECISION_U64, QUOTE_PRECISION_U64};
+use crate::state::session_key::SessionKey;
+
+#[test]
+fn set_perp_market_indexes() {
+    let mut session_key = SessionKey::default();
+
+    session_key.set_perp_market_indexes(&[0, 3]).unwrap();
+    assert!(session_key.is_perp_market_allowed(0));
+    assert!(session_key.is_perp_market_allowed(3));
+    assert!(!session_key.is_perp_market_allowed(1));
+
+    // unused slots are not allowed markets
+    session_key.set_perp_market_indexes(&[3]).unwrap();
+    assert!(!session_key.is_perp_market_allowed(0));
+
+    assert!(session_key.set_perp_market_indexes(&[]).is_err());
+    assert!(session_key.set_perp_market_indexes(&[0; 9]).is_err());
+}
+
+#[test]
+fn use_for_order() {
+    let mut session_key = SessionKey {
+        expiry_ts: 100,
+        max_notional: 1000 * QUOTE_PRECISION_U64,
+        ..SessionKey::default()
+    };
+    session_key.set_perp_market_indexes(&[1]).unwrap();
+
+    session_key
+        .use_for_order(1, 600 * QUOTE_PRECISION_U64, 50)
+        .unwrap();
+    assert_eq!(session_key.notional_used, 600 * QUOTE_PRECISION_U64);
+
+    // over the notional limit
+    assert!(session_key
+        .use_for_order(1, 401 * QUOTE_PRECISION_U64, 50)
+        .is_err());
+
+    // market not allowed
+    assert!(session_key
+        .use_for_order(0, 100 * QUOTE_PRECISION_U64, 50)
+        .is_err());
+
+    // expired
+    assert!(session_key
+        .use_for_order(1, 100 * QUOTE_PRECISION_U64, 101)
+        .is_err());
+
+    session_key
+        .use_for_order(1, 400 * QUOTE_PRECISION_U64, 100)
+        .unwrap();
+    assert_eq!(session_key.notional_used, 1000 * QUOTE_PRECISION_U64);
+}
+
+#[test]
+fn validate_order_size_rejects_max_margin_sizing() {
+    assert!(SessionKey::validate_order_size(BASE_PRECISION_U64, false).is_ok());
+
+    // a dust order resized to the full free collateral after the notional is charged
+    assert_eq!(
+        SessionKey::validate_order_size(1, true),
+        Err(ErrorCode::InvalidSessionKey)
+    );
+}
+
+#[test]
+fn validate_order_size_rejects_max_size() {
+    assert_eq!(
+        SessionKey::validate_order_size(u64::MAX, false),
+        Err(ErrorCode::InvalidSessionKey)
+    );
+}
@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct SignedOrderNonce {
+    pub user: Pubkey,
+    /// signed order messages must carry a nonce greater than the last one used
+    pub last_nonce: u64,
+}
+
+impl Size for SignedOrderNonce {
+    const SIZE: usize = 48;
+}
+
+impl SignedOrderNonce {
+    pub fn use_nonce(&mut self, nonce: u64) -> DriftResult {
+        validate!(
+            nonce > self.last_nonce,
+            ErrorCode::InvalidSignedOrderNonce,
+            "signed order nonce {} must be greater than last nonce {}",
+            nonce,
+            self.last_nonce
+        )?;
+
+        self.last_nonce = nonce;
+
+        Ok(())
+    }
+}
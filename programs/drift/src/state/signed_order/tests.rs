@@ -0,0 +1,17 @@
+use crate::state::signed_order::SignedOrderNonce;
+
+#[test]
+fn use_nonce() {
+    let mut signed_order_nonce = SignedOrderNonce::default();
+
+    signed_order_nonce.use_nonce(1).unwrap();
+    assert_eq!(signed_order_nonce.last_nonce, 1);
+
+    // replaying a message fails
+    assert!(signed_order_nonce.use_nonce(1).is_err());
+
+    // nonces can skip ahead but never go back
+    signed_order_nonce.use_nonce(5).unwrap();
+    assert_eq!(signed_order_nonce.last_nonce, 5);
+    assert!(signed_order_nonce.use_nonce(3).is_err());
+}
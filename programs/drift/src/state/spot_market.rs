@@ -3,8 +3,10 @@ use std::fmt::{Display, Formatter};
 
 use anchor_lang::prelude::*;
 use borsh::{BorshDeserialize, BorshSerialize};
+use enumflags2::BitFlags;
 
 use crate::error::DriftResult;
+use crate::ids::token_2022_program;
 use crate::instructions::SpotFulfillmentType;
 #[cfg(test)]
 use crate::math::constants::SPOT_CUMULATIVE_INTEREST_PRECISION;
@@ -14,12 +16,24 @@ use crate::math::margin::{
     MarginRequirementType,
 };
 use crate::math::safe_math::SafeMath;
+use crate::math::safe_unwrap::SafeUnwrap;
 use crate::math::spot_balance::get_token_amount;
 
 use crate::state::oracle::{HistoricalIndexData, HistoricalOracleData, OracleSource};
 use crate::state::perp_market::{MarketStatus, PoolBalance};
+use crate::state::state::ValidityGuardRailsOverride;
 use crate::state::traits::{MarketIndexOffset, Size};
 
+#[derive(BitFlags, Clone, Copy, PartialEq, Debug, Eq)]
+pub enum SpotOperation {
+    // Active = 0b00000000
+    UpdateCumulativeInterest = 0b00000001,
+    Fill = 0b00000010,
+    Deposit = 0b00000100,
+    Withdraw = 0b00001000, // withdraws, borrows and transfers out
+    Liquidation = 0b00010000,
+}
+
 #[account(zero_copy)]
 #[derive(PartialEq, Eq, Debug)]
 #[repr(C)]
@@ -71,7 +85,48 @@ pub struct SpotMarket {
     pub oracle_source: OracleSource,
     pub status: MarketStatus,
     pub asset_tier: AssetTier,
-    pub padding: [u8; 86],
+    /// max drop in deposits below the 24h deposit_token_twap before withdraws and borrows pause
+    /// precision: ONE_BPS_DENOMINATOR (10000 = 100%), 0 disables the guard
+    pub max_net_withdraw_bps: u16,
+    pub validity_guard_rails_override: ValidityGuardRailsOverride,
+    /// max share of a user's spot collateral this asset can provide before the excess is excluded
+    /// precision: SPOT_WEIGHT_PRECISION, 0 disables the limit
+    pub max_collateral_concentration: u32,
+    /// margin credited per unit of notional offset by a perp or spot position in the same correlation group
+    /// precision: MARGIN_PRECISION
+    pub correlation_margin_credit: u16,
+    /// markets sharing a non zero group offset each other's exposure in margin
+    pub correlation_group: u8,
+    /// the token program that owns the mint and vaults
+    /// 0: spl token, 1: token-2022
+    pub token_program: u8,
+    /// fee on flash borrowed tokens, credited to depositors on repayment
+    /// precision: ONE_BPS_DENOMINATOR (10000 = 100%)
+    pub flash_borrow_fee_bps: u16,
+    pub flash_borrows_enabled: bool,
+    /// fills must pass the market's MarketFeeOverride and are charged the adjusted fee structure
+    pub fee_override_enabled: bool,
+    /// hard cap on total token borrows
+    /// precision: token mint precision, 0 disables the cap
+    pub max_token_borrows: u64,
+    /// lockup boost on top of staked if shares, summed across stakers
+    /// these earn an extra cut of the user share of each revenue settlement
+    pub if_boost_weight: u128,
+    /// boost if shares minted per unit of if_boost_weight across all settlements
+    /// precision: IF_BOOST_SHARES_PER_WEIGHT_PRECISION
+    pub cumulative_if_boost_shares_per_weight: u64,
+    /// max revenue pool token amount swept to the insurance fund per settle period
+    /// precision: token mint precision, 0 leaves only the default caps
+    pub revenue_settle_max_amount: u64,
+    /// kinks after the optimal utilization kink, in increasing utilization
+    /// a zero utilization kink is unused
+    pub borrow_rate_kinks: [BorrowRateKink; 2],
+    /// quote the insurance fund has lent to perp market fee pools
+    /// precision: QUOTE_PRECISION
+    pub insurance_fund_lent_to_amms: u64,
+    /// SpotOperation bits the pauser has paused
+    pub paused_operations: u8,
+    pub padding: [u8; 7],
 }
 
 impl Default for SpotMarket {
@@ -124,13 +179,29 @@ impl Default for SpotMarket {
             oracle_source: OracleSource::default(),
             status: MarketStatus::default(),
             asset_tier: AssetTier::default(),
-            padding: [0; 86],
+            max_net_withdraw_bps: 0,
+            validity_guard_rails_override: ValidityGuardRailsOverride::default(),
+            max_collateral_concentration: 0,
+            correlation_margin_credit: 0,
+            correlation_group: 0,
+            token_program: 0,
+            flash_borrow_fee_bps: 0,
+            flash_borrows_enabled: false,
+            fee_override_enabled: false,
+            max_token_borrows: 0,
+            if_boost_weight: 0,
+            cumulative_if_boost_shares_per_weight: 0,
+            revenue_settle_max_amount: 0,
+            borrow_rate_kinks: [BorrowRateKink::default(); 2],
+            insurance_fund_lent_to_amms: 0,
+            paused_operations: 0,
+            padding: [0; 7],
         }
     }
 }
 
 impl Size for SpotMarket {
-    const SIZE: usize = 776;
+    const SIZE: usize = 792;
 }
 
 impl MarketIndexOffset for SpotMarket {
@@ -151,6 +222,26 @@ impl SpotMarket {
         Ok(self.status == MarketStatus::ReduceOnly)
     }
 
+    pub fn get_paused_operations(&self) -> DriftResult<BitFlags<SpotOperation>> {
+        BitFlags::<SpotOperation>::from_bits(usize::from(self.paused_operations)).safe_unwrap()
+    }
+
+    pub fn is_operation_paused(&self, operation: SpotOperation) -> DriftResult<bool> {
+        Ok(self.get_paused_operations()?.contains(operation))
+    }
+
+    pub fn is_token_2022(&self) -> bool {
+        self.token_program == 1
+    }
+
+    pub fn get_token_program(&self) -> Pubkey {
+        if self.is_token_2022() {
+            token_2022_program::id()
+        } else {
+            anchor_spl::token::ID
+        }
+    }
+
     pub fn get_sanitize_clamp_denominator(&self) -> DriftResult<Option<i64>> {
         Ok(match self.asset_tier {
             AssetTier::Collateral => Some(10), // 10%
@@ -342,6 +433,49 @@ impl Size for SerumV3FulfillmentConfig {
     const SIZE: usize = 344;
 }
 
+#[account(zero_copy)]
+#[derive(Default, PartialEq, Eq, Debug)]
+#[repr(C)]
+pub struct OpenbookV2FulfillmentConfig {
+    pub pubkey: Pubkey,
+    pub openbook_v2_program_id: Pubkey,
+    pub openbook_v2_market: Pubkey,
+    pub openbook_v2_market_authority: Pubkey,
+    pub openbook_v2_event_heap: Pubkey,
+    pub openbook_v2_bids: Pubkey,
+    pub openbook_v2_asks: Pubkey,
+    pub openbook_v2_base_vault: Pubkey,
+    pub openbook_v2_quote_vault: Pubkey,
+    pub market_index: u16,
+    pub fulfillment_type: SpotFulfillmentType,
+    pub status: SpotFulfillmentConfigStatus,
+    pub padding: [u8; 4],
+}
+
+impl Size for OpenbookV2FulfillmentConfig {
+    const SIZE: usize = 304;
+}
+
+#[account(zero_copy)]
+#[derive(Default, PartialEq, Eq, Debug)]
+#[repr(C)]
+pub struct PhoenixV1FulfillmentConfig {
+    pub pubkey: Pubkey,
+    pub phoenix_program_id: Pubkey,
+    pub phoenix_log_authority: Pubkey,
+    pub phoenix_market: Pubkey,
+    pub phoenix_base_vault: Pubkey,
+    pub phoenix_quote_vault: Pubkey,
+    pub market_index: u16,
+    pub fulfillment_type: SpotFulfillmentType,
+    pub status: SpotFulfillmentConfigStatus,
+    pub padding: [u8; 4],
+}
+
+impl Size for PhoenixV1FulfillmentConfig {
+    const SIZE: usize = 208;
+}
+
 #[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
 pub enum SpotFulfillmentConfigStatus {
     Enabled,
@@ -369,6 +503,21 @@ impl Default for AssetTier {
     }
 }
 
+/// A point on the borrow rate curve between the optimal utilization and 100% utilization
+#[derive(Copy, AnchorSerialize, AnchorDeserialize, Clone, Default, Eq, PartialEq, Debug)]
+pub struct BorrowRateKink {
+    /// precision: SPOT_UTILIZATION_PRECISION
+    pub utilization: u32,
+    /// precision: SPOT_RATE_PRECISION
+    pub borrow_rate: u32,
+}
+
+impl BorrowRateKink {
+    pub fn is_active(&self) -> bool {
+        self.utilization != 0
+    }
+}
+
 #[zero_copy]
 #[derive(Default, Eq, PartialEq, Debug)]
 #[repr(C)]
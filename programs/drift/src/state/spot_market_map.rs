@@ -9,6 +9,7 @@ use std::slice::Iter;
 
 use crate::math::constants::QUOTE_SPOT_MARKET_INDEX;
 use anchor_lang::Discriminator;
+use anchor_lang::Key;
 use arrayref::array_ref;
 
 use crate::math::safe_unwrap::SafeUnwrap;
@@ -175,7 +176,13 @@ impl<'a> SpotMarketMap<'a> {
 
             let market_index = u16::from_le_bytes(*array_ref![data, 684, 2]);
 
-            if spot_market_map.0.contains_key(&market_index) {
+            if let Some(account_loader) = spot_market_map.0.get(&market_index) {
+                // the same market passed for several makers is only loaded once
+                if account_loader.key() == *account_info.key {
+                    account_info_iter.next();
+                    continue;
+                }
+
                 msg!("Can not include same market index twice {}", market_index);
                 return Err(ErrorCode::InvalidSpotMarketAccount);
             }
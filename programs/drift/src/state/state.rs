@@ -34,7 +34,12 @@ pub struct State {
     pub exchange_status: u8,
     pub liquidation_duration: u8,
     pub initial_pct_to_liquidate: u16,
-    pub padding: [u8; 14],
+    pub liquidation_fee_ramp_duration: u8, // slots for the liquidator fee to ramp from 0 to the market max, 0 pays the max immediately
+    pub liquidation_target_margin_buffer_ratio: u32, // buffer above maintenance liquidate_perp sizes to, 0 uses the pct ramp
+    pub liquidation_stale_fee_escalation_duration: u16, // slots after the fee ramp to shift the if liquidation fee to the liquidator, 0 disables
+    pub admin_timelock_duration: u32, // seconds risk-critical admin changes are queued before they can execute, 0 applies them instantly
+    pub margin_warning_health: u8, // health below which keepers set user.margin_warning, 0 disables
+    pub padding: [u8; 2],
 }
 
 #[derive(BitFlags, Clone, Copy, PartialEq, Debug, Eq)]
@@ -78,6 +83,53 @@ impl Size for State {
     const SIZE: usize = 992;
 }
 
+/// hot keys the admin delegates day to day operations to, the admin keeps every role
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct AdminRoles {
+    /// can update the exchange and market statuses
+    pub pauser: Pubkey,
+    /// can update margin ratios and weights and queue timelocked admin changes
+    pub risk_param_setter: Pubkey,
+    /// can initialize perp and spot markets
+    pub market_lister: Pubkey,
+    /// can deposit into market fee pools and remove the protocol's insurance fund stake
+    pub treasurer: Pubkey,
+}
+
+impl Size for AdminRoles {
+    const SIZE: usize = 136;
+}
+
+impl AdminRoles {
+    pub fn get_role(&self, role: AdminRole) -> Pubkey {
+        match role {
+            AdminRole::Pauser => self.pauser,
+            AdminRole::RiskParamSetter => self.risk_param_setter,
+            AdminRole::MarketLister => self.market_lister,
+            AdminRole::Treasurer => self.treasurer,
+        }
+    }
+
+    pub fn set_role(&mut self, role: AdminRole, key: Pubkey) {
+        match role {
+            AdminRole::Pauser => self.pauser = key,
+            AdminRole::RiskParamSetter => self.risk_param_setter = key,
+            AdminRole::MarketLister => self.market_lister = key,
+            AdminRole::Treasurer => self.treasurer = key,
+        }
+    }
+}
+
+#[derive(Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq, Debug, Eq)]
+pub enum AdminRole {
+    Pauser,
+    RiskParamSetter,
+    MarketLister,
+    Treasurer,
+}
+
 #[derive(Copy, AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct OracleGuardRails {
     pub price_divergence: PriceDivergenceGuardRails,
@@ -115,6 +167,49 @@ pub struct ValidityGuardRails {
     pub too_volatile_ratio: i64,
 }
 
+impl ValidityGuardRails {
+    pub fn with_override(
+        &self,
+        guard_rails_override: &ValidityGuardRailsOverride,
+    ) -> ValidityGuardRails {
+        ValidityGuardRails {
+            slots_before_stale_for_amm: override_or_global(
+                guard_rails_override.slots_before_stale_for_amm,
+                self.slots_before_stale_for_amm,
+            ),
+            slots_before_stale_for_margin: override_or_global(
+                guard_rails_override.slots_before_stale_for_margin,
+                self.slots_before_stale_for_margin,
+            ),
+            confidence_interval_max_size: override_or_global(
+                guard_rails_override.confidence_interval_max_size,
+                self.confidence_interval_max_size,
+            ),
+            too_volatile_ratio: override_or_global(
+                guard_rails_override.too_volatile_ratio,
+                self.too_volatile_ratio,
+            ),
+        }
+    }
+}
+
+fn override_or_global<T: From<u32>>(override_value: u32, global_value: T) -> T {
+    if override_value == 0 {
+        global_value
+    } else {
+        T::from(override_value)
+    }
+}
+
+/// Per market replacements for the global validity guard rails, zero uses the global value
+#[derive(Copy, AnchorSerialize, AnchorDeserialize, Clone, Default, Eq, PartialEq, Debug)]
+pub struct ValidityGuardRailsOverride {
+    pub slots_before_stale_for_amm: u32,
+    pub slots_before_stale_for_margin: u32,
+    pub confidence_interval_max_size: u32,
+    pub too_volatile_ratio: u32,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct FeeStructure {
     pub fee_tiers: [FeeTier; 10],
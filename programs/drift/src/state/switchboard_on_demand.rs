@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+// Mirrors the account layout of the switchboard on-demand program's PullFeedAccountData
+// so feeds can be read without pulling in the switchboard-on-demand crate
+
+pub const SB_ON_DEMAND_PRECISION: u32 = 18;
+
+#[zero_copy]
+#[repr(C)]
+pub struct OracleSubmission {
+    pub oracle: Pubkey,
+    pub slot: u64,
+    pub padding1: [u8; 8],
+    pub value: i128,
+}
+
+#[zero_copy]
+#[repr(C)]
+pub struct CurrentResult {
+    pub value: i128,
+    pub std_dev: i128,
+    pub mean: i128,
+    pub range: i128,
+    pub min_value: i128,
+    pub max_value: i128,
+    pub num_samples: u8,
+    pub padding1: [u8; 7],
+    pub slot: u64,
+    pub min_slot: u64,
+    pub max_slot: u64,
+}
+
+#[zero_copy]
+#[repr(C)]
+pub struct PullFeedAccountData {
+    pub submissions: [OracleSubmission; 32],
+    pub authority: Pubkey,
+    pub queue: Pubkey,
+    pub feed_hash: [u8; 32],
+    pub initialized_at: i64,
+    pub permissions: u64,
+    pub max_variance: u64,
+    pub min_responses: u32,
+    pub name: [u8; 32],
+    pub padding1: [u8; 3],
+    pub min_sample_size: u8,
+    pub last_update_timestamp: i64,
+    pub lut_slot: u64,
+    pub reserved1: [u8; 32],
+    pub result: CurrentResult,
+    pub max_staleness: u32,
+    pub padding2: [u8; 12],
+    pub ebuf: [u8; 1024],
+}
@@ -0,0 +1,329 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::ids::token_2022_program;
+use crate::math::safe_math::SafeMath;
+use crate::state::spot_market::SpotMarket;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+// Token-2022 accounts share the spl token base layouts. When a token-2022 account has
+// extensions, the base layout is padded to BASE_ACCOUNT_LENGTH and followed by an account
+// type byte and TLV entries (u16 type, u16 length, value). Only what the protocol needs
+// is read here
+
+pub const MINT_LENGTH: usize = 82;
+pub const BASE_ACCOUNT_LENGTH: usize = 165;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+const TOKEN_ACCOUNT_STATE_INITIALIZED: u8 = 1;
+
+pub const EXTENSION_TRANSFER_FEE_CONFIG: u16 = 1;
+pub const EXTENSION_TRANSFER_FEE_AMOUNT: u16 = 2;
+pub const EXTENSION_MINT_CLOSE_AUTHORITY: u16 = 3;
+pub const EXTENSION_CONFIDENTIAL_TRANSFER_MINT: u16 = 4;
+pub const EXTENSION_DEFAULT_ACCOUNT_STATE: u16 = 6;
+pub const EXTENSION_NON_TRANSFERABLE: u16 = 9;
+pub const EXTENSION_INTEREST_BEARING_CONFIG: u16 = 10;
+pub const EXTENSION_PERMANENT_DELEGATE: u16 = 12;
+pub const EXTENSION_TRANSFER_HOOK: u16 = 14;
+pub const EXTENSION_TRANSFER_HOOK_ACCOUNT: u16 = 15;
+pub const EXTENSION_CONFIDENTIAL_TRANSFER_FEE_CONFIG: u16 = 16;
+pub const EXTENSION_METADATA_POINTER: u16 = 18;
+pub const EXTENSION_TOKEN_METADATA: u16 = 19;
+pub const EXTENSION_GROUP_POINTER: u16 = 20;
+pub const EXTENSION_TOKEN_GROUP: u16 = 21;
+pub const EXTENSION_GROUP_MEMBER_POINTER: u16 = 22;
+pub const EXTENSION_TOKEN_GROUP_MEMBER: u16 = 23;
+
+const TRANSFER_FEE_AMOUNT_LENGTH: usize = 8;
+const TRANSFER_HOOK_ACCOUNT_LENGTH: usize = 1;
+
+pub fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == anchor_spl::token::ID || *program_id == token_2022_program::id()
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TokenAccountState {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+pub fn load_token_account(
+    account_info: &AccountInfo,
+    token_program_id: &Pubkey,
+) -> DriftResult<TokenAccountState> {
+    validate!(
+        account_info.owner == token_program_id && is_token_program(token_program_id),
+        ErrorCode::InvalidTokenAccount,
+        "token account {} not owned by token program {}",
+        account_info.key,
+        token_program_id
+    )?;
+
+    let data = account_info
+        .try_borrow_data()
+        .or(Err(ErrorCode::InvalidTokenAccount))?;
+
+    validate!(
+        data.len() == BASE_ACCOUNT_LENGTH
+            || (data.len() > BASE_ACCOUNT_LENGTH
+                && data[BASE_ACCOUNT_LENGTH] == ACCOUNT_TYPE_ACCOUNT),
+        ErrorCode::InvalidTokenAccount,
+        "invalid token account {}",
+        account_info.key
+    )?;
+
+    validate!(
+        data[108] == TOKEN_ACCOUNT_STATE_INITIALIZED,
+        ErrorCode::InvalidTokenAccount,
+        "token account {} not initialized or frozen",
+        account_info.key
+    )?;
+
+    Ok(TokenAccountState {
+        mint: Pubkey::new(&data[0..32]),
+        owner: Pubkey::new(&data[32..64]),
+        amount: u64::from_le_bytes(read_array(&data, 64)?),
+    })
+}
+
+/// A spot market vault read with the market's token program, so spl token and token-2022
+/// vaults load the same way
+pub struct SpotMarketVault<'a> {
+    account_info: AccountInfo<'a>,
+    token_program: Pubkey,
+    pub amount: u64,
+}
+
+impl<'a> SpotMarketVault<'a> {
+    pub fn load(account_info: &AccountInfo<'a>, spot_market: &SpotMarket) -> DriftResult<Self> {
+        let token_program = spot_market.get_token_program();
+        let amount = load_token_account(account_info, &token_program)?.amount;
+
+        Ok(SpotMarketVault {
+            account_info: account_info.clone(),
+            token_program,
+            amount,
+        })
+    }
+
+    pub fn reload(&mut self) -> DriftResult {
+        self.amount = load_token_account(&self.account_info, &self.token_program)?.amount;
+        Ok(())
+    }
+
+    pub fn to_account_info(&self) -> AccountInfo<'a> {
+        self.account_info.clone()
+    }
+}
+
+fn read_array<const N: usize>(data: &[u8], offset: usize) -> DriftResult<[u8; N]> {
+    let mut bytes = [0_u8; N];
+    bytes.copy_from_slice(
+        data.get(offset..offset + N)
+            .ok_or(ErrorCode::InvalidTokenMint)?,
+    );
+    Ok(bytes)
+}
+
+pub fn get_mint_decimals(mint_data: &[u8]) -> DriftResult<u8> {
+    validate!(
+        mint_data.len() >= MINT_LENGTH && mint_data[45] == 1,
+        ErrorCode::InvalidTokenMint,
+        "mint not initialized"
+    )?;
+
+    Ok(mint_data[44])
+}
+
+pub fn get_mint_extensions(mint_data: &[u8]) -> DriftResult<Vec<(u16, &[u8])>> {
+    let mut extensions = vec![];
+
+    if mint_data.len() <= BASE_ACCOUNT_LENGTH {
+        return Ok(extensions);
+    }
+
+    validate!(
+        mint_data[BASE_ACCOUNT_LENGTH] == ACCOUNT_TYPE_MINT,
+        ErrorCode::InvalidTokenMint,
+        "account is not a mint"
+    )?;
+
+    let mut offset = BASE_ACCOUNT_LENGTH + 1;
+    while offset + 4 <= mint_data.len() {
+        let extension_type = u16::from_le_bytes(read_array(mint_data, offset)?);
+        let length = u16::from_le_bytes(read_array(mint_data, offset + 2)?) as usize;
+
+        // uninitialized space at the end of the tlv data
+        if extension_type == 0 {
+            break;
+        }
+
+        let value = mint_data
+            .get(offset + 4..offset + 4 + length)
+            .ok_or(ErrorCode::InvalidTokenMint)?;
+        extensions.push((extension_type, value));
+
+        offset = offset + 4 + length;
+    }
+
+    Ok(extensions)
+}
+
+// Extensions that break vault accounting or let a third party move vault funds are rejected
+pub fn validate_mint_extensions(mint_data: &[u8]) -> DriftResult {
+    for (extension_type, value) in get_mint_extensions(mint_data)? {
+        match extension_type {
+            EXTENSION_TRANSFER_FEE_CONFIG
+            | EXTENSION_MINT_CLOSE_AUTHORITY
+            | EXTENSION_CONFIDENTIAL_TRANSFER_MINT
+            | EXTENSION_INTEREST_BEARING_CONFIG
+            | EXTENSION_CONFIDENTIAL_TRANSFER_FEE_CONFIG
+            | EXTENSION_METADATA_POINTER
+            | EXTENSION_TOKEN_METADATA
+            | EXTENSION_GROUP_POINTER
+            | EXTENSION_TOKEN_GROUP
+            | EXTENSION_GROUP_MEMBER_POINTER
+            | EXTENSION_TOKEN_GROUP_MEMBER => {}
+            EXTENSION_TRANSFER_HOOK => {
+                // hooks need extra accounts on every transfer, so only an unset hook program is allowed
+                let program_id = value.get(32..64).ok_or(ErrorCode::InvalidTokenMint)?;
+                validate!(
+                    program_id.iter().all(|byte| *byte == 0),
+                    ErrorCode::UnsupportedTokenMintExtension,
+                    "transfer hook program must be unset"
+                )?;
+            }
+            _ => {
+                msg!("unsupported mint extension {}", extension_type);
+                return Err(ErrorCode::UnsupportedTokenMintExtension);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Size of a token account for the mint, including the extensions token-2022 requires
+// every account of the mint to carry
+pub fn get_token_account_space(mint_data: &[u8]) -> DriftResult<usize> {
+    let mut extensions_length = 0_usize;
+    for (extension_type, _) in get_mint_extensions(mint_data)? {
+        match extension_type {
+            EXTENSION_TRANSFER_FEE_CONFIG => {
+                extensions_length = extensions_length.safe_add(4 + TRANSFER_FEE_AMOUNT_LENGTH)?
+            }
+            EXTENSION_TRANSFER_HOOK => {
+                extensions_length = extensions_length.safe_add(4 + TRANSFER_HOOK_ACCOUNT_LENGTH)?
+            }
+            _ => {}
+        }
+    }
+
+    if extensions_length == 0 {
+        Ok(BASE_ACCOUNT_LENGTH)
+    } else {
+        BASE_ACCOUNT_LENGTH.safe_add(1)?.safe_add(extensions_length)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransferFee {
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub transfer_fee_basis_points: u16,
+}
+
+impl TransferFee {
+    fn from_bytes(data: &[u8], offset: usize) -> DriftResult<Self> {
+        Ok(TransferFee {
+            epoch: u64::from_le_bytes(read_array(data, offset)?),
+            maximum_fee: u64::from_le_bytes(read_array(data, offset + 8)?),
+            transfer_fee_basis_points: u16::from_le_bytes(read_array(data, offset + 16)?),
+        })
+    }
+
+    pub fn calculate_fee(&self, amount: u64) -> DriftResult<u64> {
+        if self.transfer_fee_basis_points == 0 || amount == 0 {
+            return Ok(0);
+        }
+
+        let fee = (amount as u128)
+            .safe_mul(self.transfer_fee_basis_points as u128)?
+            .safe_div_ceil(10_000)?;
+
+        Ok(fee.min(self.maximum_fee as u128) as u64)
+    }
+
+    /// smallest amount to send so that at least `post_fee_amount` arrives
+    pub fn calculate_pre_fee_amount(&self, post_fee_amount: u64) -> DriftResult<u64> {
+        if self.transfer_fee_basis_points == 0 || post_fee_amount == 0 {
+            return Ok(post_fee_amount);
+        }
+
+        let max_pre_fee_amount = post_fee_amount.safe_add(self.maximum_fee)?;
+        if self.transfer_fee_basis_points >= 10_000 {
+            return Ok(max_pre_fee_amount);
+        }
+
+        let mut pre_fee_amount = (post_fee_amount as u128)
+            .safe_mul(10_000)?
+            .safe_div_ceil(10_000 - self.transfer_fee_basis_points as u128)?
+            .min(max_pre_fee_amount as u128) as u64;
+
+        if pre_fee_amount.safe_sub(self.calculate_fee(pre_fee_amount)?)? < post_fee_amount {
+            pre_fee_amount = pre_fee_amount.safe_add(1)?;
+        }
+
+        Ok(pre_fee_amount)
+    }
+}
+
+pub fn get_transfer_fee(mint_data: &[u8], epoch: u64) -> DriftResult<Option<TransferFee>> {
+    for (extension_type, value) in get_mint_extensions(mint_data)? {
+        if extension_type == EXTENSION_TRANSFER_FEE_CONFIG {
+            // config authority (32), withdraw withheld authority (32), withheld amount (8)
+            let older_transfer_fee = TransferFee::from_bytes(value, 72)?;
+            let newer_transfer_fee = TransferFee::from_bytes(value, 90)?;
+
+            return Ok(Some(if epoch >= newer_transfer_fee.epoch {
+                newer_transfer_fee
+            } else {
+                older_transfer_fee
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+fn get_mint_transfer_fee(mint: &AccountInfo) -> DriftResult<Option<TransferFee>> {
+    if mint.owner != &token_2022_program::id() {
+        return Ok(None);
+    }
+
+    let mint_data = mint
+        .try_borrow_data()
+        .or(Err(ErrorCode::InvalidTokenMint))?;
+    let epoch = Clock::get().or(Err(ErrorCode::InvalidTokenMint))?.epoch;
+
+    get_transfer_fee(&mint_data, epoch)
+}
+
+pub fn calculate_transfer_fee(mint: &AccountInfo, amount: u64) -> DriftResult<u64> {
+    match get_mint_transfer_fee(mint)? {
+        Some(transfer_fee) => transfer_fee.calculate_fee(amount),
+        None => Ok(0),
+    }
+}
+
+pub fn calculate_pre_transfer_fee_amount(mint: &AccountInfo, amount: u64) -> DriftResult<u64> {
+    match get_mint_transfer_fee(mint)? {
+        Some(transfer_fee) => transfer_fee.calculate_pre_fee_amount(amount),
+        None => Ok(amount),
+    }
+}
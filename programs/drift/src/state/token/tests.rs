@@ -0,0 +1,140 @@
+use crate::state::token::{
+    get_mint_decimals, get_token_account_space, get_transfer_fee, validate_mint_extensions,
+    TransferFee, BASE_ACCOUNT_LENGTH, EXTENSION_METADATA_POINTER, EXTENSION_PERMANENT_DELEGATE,
+    EXTENSION_TRANSFER_FEE_CONFIG, EXTENSION_TRANSFER_HOOK, MINT_LENGTH,
+};
+
+fn mint_data(decimals: u8, extensions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+    let mut data = vec![0_u8; MINT_LENGTH];
+    data[44] = decimals;
+    data[45] = 1;
+
+    if !extensions.is_empty() {
+        data.resize(BASE_ACCOUNT_LENGTH, 0);
+        data.push(1);
+        for (extension_type, value) in extensions.iter() {
+            data.extend_from_slice(&extension_type.to_le_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            data.extend_from_slice(value);
+        }
+    }
+
+    data
+}
+
+fn transfer_fee_config(older: TransferFee, newer: TransferFee) -> Vec<u8> {
+    let mut value = vec![0_u8; 72];
+    for transfer_fee in [older, newer].iter() {
+        value.extend_from_slice(&transfer_fee.epoch.to_le_bytes());
+        value.extend_from_slice(&transfer_fee.maximum_fee.to_le_bytes());
+        value.extend_from_slice(&transfer_fee.transfer_fee_basis_points.to_le_bytes());
+    }
+    value
+}
+
+#[test]
+fn spl_token_mint() {
+    let data = mint_data(6, &[]);
+
+    assert_eq!(get_mint_decimals(&data).unwrap(), 6);
+    assert!(validate_mint_extensions(&data).is_ok());
+    assert_eq!(get_token_account_space(&data).unwrap(), 165);
+    assert_eq!(get_transfer_fee(&data, 0).unwrap(), None);
+}
+
+#[test]
+fn transfer_fee_mint() {
+    let older = TransferFee {
+        epoch: 0,
+        maximum_fee: u64::MAX,
+        transfer_fee_basis_points: 10,
+    };
+    let newer = TransferFee {
+        epoch: 100,
+        maximum_fee: 5_000,
+        transfer_fee_basis_points: 50,
+    };
+    let data = mint_data(
+        6,
+        &[
+            (
+                EXTENSION_TRANSFER_FEE_CONFIG,
+                transfer_fee_config(older, newer),
+            ),
+            (EXTENSION_METADATA_POINTER, vec![0; 64]),
+        ],
+    );
+
+    assert!(validate_mint_extensions(&data).is_ok());
+    assert_eq!(get_token_account_space(&data).unwrap(), 165 + 1 + 4 + 8);
+
+    let transfer_fee = get_transfer_fee(&data, 99).unwrap().unwrap();
+    assert_eq!(transfer_fee, older);
+    assert_eq!(transfer_fee.calculate_fee(1_000_000).unwrap(), 1_000);
+    assert_eq!(transfer_fee.calculate_fee(1).unwrap(), 1); // rounds up
+
+    let transfer_fee = get_transfer_fee(&data, 100).unwrap().unwrap();
+    assert_eq!(transfer_fee, newer);
+    assert_eq!(transfer_fee.calculate_fee(100_000).unwrap(), 500);
+    assert_eq!(transfer_fee.calculate_fee(10_000_000).unwrap(), 5_000); // capped
+    assert_eq!(transfer_fee.calculate_fee(0).unwrap(), 0);
+}
+
+#[test]
+fn transfer_fee_pre_fee_amount() {
+    let transfer_fee = TransferFee {
+        epoch: 0,
+        maximum_fee: 5_000,
+        transfer_fee_basis_points: 50,
+    };
+
+    for post_fee_amount in [1_u64, 199, 200, 99_500, 1_000_000, 999_999_999].iter() {
+        let pre_fee_amount = transfer_fee
+            .calculate_pre_fee_amount(*post_fee_amount)
+            .unwrap();
+        let received = pre_fee_amount - transfer_fee.calculate_fee(pre_fee_amount).unwrap();
+        assert!(received >= *post_fee_amount);
+
+        let received_with_less =
+            pre_fee_amount - 1 - transfer_fee.calculate_fee(pre_fee_amount - 1).unwrap();
+        assert!(received_with_less < *post_fee_amount);
+    }
+
+    assert_eq!(
+        transfer_fee.calculate_pre_fee_amount(99_500).unwrap(),
+        100_000
+    );
+    // capped at the maximum fee
+    assert_eq!(
+        transfer_fee.calculate_pre_fee_amount(10_000_000).unwrap(),
+        10_005_000
+    );
+
+    let no_fee = TransferFee::default();
+    assert_eq!(no_fee.calculate_pre_fee_amount(1_000).unwrap(), 1_000);
+
+    let full_fee = TransferFee {
+        epoch: 0,
+        maximum_fee: 7,
+        transfer_fee_basis_points: 10_000,
+    };
+    assert_eq!(full_fee.calculate_pre_fee_amount(1_000).unwrap(), 1_007);
+}
+
+#[test]
+fn incompatible_mint_extensions() {
+    let data = mint_data(6, &[(EXTENSION_PERMANENT_DELEGATE, vec![1; 32])]);
+    assert!(validate_mint_extensions(&data).is_err());
+
+    let mut transfer_hook = vec![0_u8; 64];
+    let data = mint_data(6, &[(EXTENSION_TRANSFER_HOOK, transfer_hook.clone())]);
+    assert!(validate_mint_extensions(&data).is_ok());
+    assert_eq!(get_token_account_space(&data).unwrap(), 165 + 1 + 4 + 1);
+
+    transfer_hook[40] = 1;
+    let data = mint_data(6, &[(EXTENSION_TRANSFER_HOOK, transfer_hook)]);
+    assert!(validate_mint_extensions(&data).is_err());
+
+    let data = mint_data(6, &[(u16::MAX, vec![])]);
+    assert!(validate_mint_extensions(&data).is_err());
+}
@@ -1,11 +1,27 @@
 mod size {
     use crate::state::events::OrderActionRecord;
+    use crate::state::fill_callback::{FillCallbackProgram, UserFillCallback};
+    use crate::state::fuel::{FuelBoost, UserFuel};
+    use crate::state::high_leverage_mode_config::HighLeverageModeConfig;
     use crate::state::insurance_fund_stake::InsuranceFundStake;
+    use crate::state::lst_oracle::LstOracle;
+    use crate::state::market_fee_override::MarketFeeOverride;
+    use crate::state::market_lookup_table::MarketLookupTable;
+    use crate::state::market_whitelist::MarketWhitelistEntry;
     use crate::state::perp_market::PerpMarket;
-    use crate::state::spot_market::{SerumV3FulfillmentConfig, SpotMarket};
-    use crate::state::state::State;
+    use crate::state::perp_market_delisting::PerpMarketDelisting;
+    use crate::state::perp_market_listing::{PerpMarketListing, PerpMarketListingConfig};
+    use crate::state::pnl_pool_rebalance_config::PnlPoolRebalanceConfig;
+    use crate::state::prelaunch_oracle::PrelaunchOracle;
+    use crate::state::signed_order::SignedOrderNonce;
+    use crate::state::spot_market::{
+        OpenbookV2FulfillmentConfig, PhoenixV1FulfillmentConfig, SerumV3FulfillmentConfig,
+        SpotMarket,
+    };
+    use crate::state::state::{AdminRoles, State};
     use crate::state::traits::Size;
     use crate::state::user::{User, UserStats};
+    use crate::state::vault::{Vault, VaultDepositor};
 
     #[test]
     fn order_action_records() {
@@ -14,6 +30,66 @@ mod size {
         assert_eq!(actual_size, expected_size);
     }
 
+    #[test]
+    fn admin_roles() {
+        let expected_size = std::mem::size_of::<AdminRoles>() + 8;
+        let actual_size = AdminRoles::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn fill_callback() {
+        let expected_size = std::mem::size_of::<FillCallbackProgram>() + 8;
+        let actual_size = FillCallbackProgram::SIZE;
+        assert_eq!(actual_size, expected_size);
+
+        let expected_size = std::mem::size_of::<UserFillCallback>() + 8;
+        let actual_size = UserFillCallback::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn fuel_boost() {
+        let expected_size = std::mem::size_of::<FuelBoost>() + 8;
+        let actual_size = FuelBoost::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn high_leverage_mode_config() {
+        let expected_size = std::mem::size_of::<HighLeverageModeConfig>() + 8;
+        let actual_size = HighLeverageModeConfig::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn lst_oracle() {
+        let expected_size = std::mem::size_of::<LstOracle>() + 8;
+        let actual_size = LstOracle::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn market_fee_override() {
+        let expected_size = std::mem::size_of::<MarketFeeOverride>() + 8;
+        let actual_size = MarketFeeOverride::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn market_lookup_table() {
+        let expected_size = std::mem::size_of::<MarketLookupTable>() + 8;
+        let actual_size = MarketLookupTable::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn market_whitelist_entry() {
+        let expected_size = std::mem::size_of::<MarketWhitelistEntry>() + 8;
+        let actual_size = MarketWhitelistEntry::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
     #[test]
     fn perp_market() {
         let expected_size = std::mem::size_of::<PerpMarket>() + 8;
@@ -21,6 +97,45 @@ mod size {
         assert_eq!(actual_size, expected_size);
     }
 
+    #[test]
+    fn perp_market_delisting() {
+        let expected_size = std::mem::size_of::<PerpMarketDelisting>() + 8;
+        let actual_size = PerpMarketDelisting::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn perp_market_listing() {
+        let expected_size = std::mem::size_of::<PerpMarketListing>() + 8;
+        let actual_size = PerpMarketListing::SIZE;
+        assert_eq!(actual_size, expected_size);
+
+        let expected_size = std::mem::size_of::<PerpMarketListingConfig>() + 8;
+        let actual_size = PerpMarketListingConfig::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn pnl_pool_rebalance_config() {
+        let expected_size = std::mem::size_of::<PnlPoolRebalanceConfig>() + 8;
+        let actual_size = PnlPoolRebalanceConfig::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn prelaunch_oracle() {
+        let expected_size = std::mem::size_of::<PrelaunchOracle>() + 8;
+        let actual_size = PrelaunchOracle::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn signed_order_nonce() {
+        let expected_size = std::mem::size_of::<SignedOrderNonce>() + 8;
+        let actual_size = SignedOrderNonce::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
     #[test]
     fn spot_market() {
         let expected_size = std::mem::size_of::<SpotMarket>() + 8;
@@ -35,6 +150,20 @@ mod size {
         assert_eq!(actual_size, expected_size);
     }
 
+    #[test]
+    fn openbook_v2_config() {
+        let expected_size = std::mem::size_of::<OpenbookV2FulfillmentConfig>() + 8;
+        let actual_size = OpenbookV2FulfillmentConfig::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
+    #[test]
+    fn phoenix_v1_config() {
+        let expected_size = std::mem::size_of::<PhoenixV1FulfillmentConfig>() + 8;
+        let actual_size = PhoenixV1FulfillmentConfig::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
     #[test]
     fn state() {
         let expected_size = std::mem::size_of::<State>() + 8;
@@ -49,6 +178,13 @@ mod size {
         assert_eq!(actual_size, expected_size);
     }
 
+    #[test]
+    fn user_fuel() {
+        let expected_size = std::mem::size_of::<UserFuel>() + 8;
+        let actual_size = UserFuel::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
     #[test]
     fn user_stats() {
         let expected_size = std::mem::size_of::<UserStats>() + 8;
@@ -56,6 +192,17 @@ mod size {
         assert_eq!(actual_size, expected_size);
     }
 
+    #[test]
+    fn vault() {
+        let expected_size = std::mem::size_of::<Vault>() + 8;
+        let actual_size = Vault::SIZE;
+        assert_eq!(actual_size, expected_size);
+
+        let expected_size = std::mem::size_of::<VaultDepositor>() + 8;
+        let actual_size = VaultDepositor::SIZE;
+        assert_eq!(actual_size, expected_size);
+    }
+
     #[test]
     fn insurance_fund_stake() {
         let expected_size = std::mem::size_of::<InsuranceFundStake>() + 8;
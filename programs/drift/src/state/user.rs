@@ -4,12 +4,14 @@ use crate::get_then_update_id;
 use crate::math::auction::{calculate_auction_price, is_auction_complete};
 use crate::math::casting::Cast;
 use crate::math::constants::{
-    AMM_TO_QUOTE_PRECISION_RATIO_I128, EPOCH_DURATION, OPEN_ORDER_MARGIN_REQUIREMENT,
-    PRICE_PRECISION_I128, QUOTE_SPOT_MARKET_INDEX, THIRTY_DAY,
+    AMM_TO_QUOTE_PRECISION_RATIO_I128, EPOCH_DURATION, MAX_PREDICTION_MARKET_PRICE,
+    MAX_USER_ORDERS, OPEN_ORDER_MARGIN_REQUIREMENT, PRICE_PRECISION_I128,
+    PRO_LP_TIER_SHARE_MULTIPLIER, QUOTE_SPOT_MARKET_INDEX, THIRTY_DAY,
 };
 use crate::math::orders::standardize_price;
 use crate::math::position::calculate_base_asset_value_and_pnl_with_oracle_price;
 use crate::math::safe_math::SafeMath;
+use crate::math::safe_unwrap::SafeUnwrap;
 use crate::math::spot_balance::{get_signed_token_amount, get_token_amount, get_token_value};
 use crate::math::stats::calculate_rolling_sum;
 use crate::math_error;
@@ -20,6 +22,7 @@ use crate::state::traits::Size;
 use crate::validate;
 use anchor_lang::prelude::*;
 use borsh::{BorshDeserialize, BorshSerialize};
+use enumflags2::BitFlags;
 use solana_program::msg;
 use std::cmp::max;
 use std::panic::Location;
@@ -40,13 +43,45 @@ impl Default for UserStatus {
     }
 }
 
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub enum PerpLpTier {
+    Standard,
+    // takes over protocol owned liquidity, earning more fees and taking more inventory per share added
+    Pro,
+}
+
+impl Default for PerpLpTier {
+    fn default() -> Self {
+        PerpLpTier::Standard
+    }
+}
+
+#[derive(BitFlags, Clone, Copy, PartialEq, Debug, Eq)]
+pub enum DelegatePermission {
+    PlaceOrders = 0b00000001, // place, modify and take orders, take over positions as a liquidator
+    CancelOrders = 0b00000010,
+    WithdrawToAuthority = 0b00000100, // withdraw, only into token accounts owned by the user authority
+    Settle = 0b00001000,              // settle positive pnl on the authority's behalf
+    ManageLp = 0b00010000,            // add and remove lp shares, manage lp ranges
+}
+
+impl PerpLpTier {
+    /// Position lp shares received per share of curve liquidity added
+    pub fn share_multiplier(&self) -> u64 {
+        match self {
+            PerpLpTier::Standard => 1,
+            PerpLpTier::Pro => PRO_LP_TIER_SHARE_MULTIPLIER,
+        }
+    }
+}
+
 // implement SIZE const for User
 impl Size for User {
-    const SIZE: usize = 4376;
+    const SIZE: usize = 10144;
 }
 
 #[account(zero_copy)]
-#[derive(Default, Eq, PartialEq, Debug)]
+#[derive(Eq, PartialEq, Debug)]
 #[repr(C)]
 pub struct User {
     pub authority: Pubkey,
@@ -54,7 +89,7 @@ pub struct User {
     pub name: [u8; 32],
     pub spot_positions: [SpotPosition; 8],
     pub perp_positions: [PerpPosition; 8],
-    pub orders: [Order; 32],
+    pub orders: [Order; 64], // MAX_USER_ORDERS, written out for the idl
     pub last_add_perp_lp_shares_ts: i64,
     pub total_deposits: u64,
     pub total_withdraws: u64,
@@ -73,7 +108,60 @@ pub struct User {
     pub status: UserStatus,
     pub is_margin_trading_enabled: bool,
     pub idle: bool,
-    pub padding: [u8; 25],
+    pub is_protected_maker: bool, // resting perp orders get a price cushion and cant be crossed by stale oracle takers
+    pub is_high_leverage_mode: bool, // uses the high leverage margin ratio of high_leverage_mode_market_index
+    pub perp_lp_tier: PerpLpTier,
+    pub delegate_permissions: u8, // bitmask of DelegatePermission, 0 leaves the delegate unrestricted
+    pub has_fill_callback: bool, // perp taker fills must pass its UserFillCallback, other fills reject the user
+    pub delegate_expiry_slot: u32, // slot after which the delegate can no longer sign, 0 never expires
+    /// balances of the swap token accounts when begin_swap ran, zero outside a swap
+    pub swap_in_initial_token_amount: u64,
+    pub swap_out_initial_token_amount: u64,
+    pub third_party_deposits_disabled: bool, // only the authority and delegate can deposit, rejects deposit_into_user
+    pub margin_warning: bool, // health was below state.margin_warning_health when a keeper last checked
+    pub high_leverage_mode_market_index: u16, // perp market whose high leverage mode registry the user is in
+    pub margin_warning_ts: u32,               // last time margin_warning was set or cleared
+}
+
+impl Default for User {
+    fn default() -> Self {
+        Self {
+            authority: Pubkey::default(),
+            delegate: Pubkey::default(),
+            name: [0; 32],
+            spot_positions: [SpotPosition::default(); 8],
+            perp_positions: [PerpPosition::default(); 8],
+            orders: [Order::default(); MAX_USER_ORDERS],
+            last_add_perp_lp_shares_ts: 0,
+            total_deposits: 0,
+            total_withdraws: 0,
+            total_social_loss: 0,
+            settled_perp_pnl: 0,
+            cumulative_spot_fees: 0,
+            cumulative_perp_funding: 0,
+            liquidation_margin_freed: 0,
+            last_active_slot: 0,
+            next_order_id: 0,
+            max_margin_ratio: 0,
+            next_liquidation_id: 0,
+            sub_account_id: 0,
+            status: UserStatus::Active,
+            is_margin_trading_enabled: false,
+            idle: false,
+            is_protected_maker: false,
+            is_high_leverage_mode: false,
+            perp_lp_tier: PerpLpTier::Standard,
+            delegate_permissions: 0,
+            has_fill_callback: false,
+            delegate_expiry_slot: 0,
+            swap_in_initial_token_amount: 0,
+            swap_out_initial_token_amount: 0,
+            third_party_deposits_disabled: false,
+            margin_warning: false,
+            high_leverage_mode_market_index: 0,
+            margin_warning_ts: 0,
+        }
+    }
 }
 
 impl User {
@@ -88,6 +176,62 @@ impl User {
         self.status == UserStatus::Bankrupt
     }
 
+    pub fn is_high_leverage_mode_for(&self, perp_market_index: u16) -> bool {
+        self.is_high_leverage_mode && self.high_leverage_mode_market_index == perp_market_index
+    }
+
+    /// fill callbacks are only invoked for taker fills in fill_perp_order and place_and_take_perp_order,
+    /// every other path that fills the user's orders must reject the user
+    pub fn validate_no_fill_callback(&self) -> DriftResult {
+        validate!(
+            !self.has_fill_callback,
+            ErrorCode::InvalidFillCallback,
+            "user has a fill callback, which isnt invoked for this fill"
+        )
+    }
+
+    /// deposits from other signers can only top up spot positions the user already holds, so they
+    /// cant fill the user's free spot slots with dust positions
+    pub fn validate_third_party_deposit(&self, market_index: u16) -> DriftResult {
+        validate!(
+            !self.third_party_deposits_disabled,
+            ErrorCode::ThirdPartyDepositsDisabled,
+            "user does not accept deposits from other signers"
+        )?;
+
+        validate!(
+            self.get_spot_position_index(market_index).is_ok(),
+            ErrorCode::CouldNotFindSpotPosition,
+            "user has no spot position in market {} for a third party to deposit into",
+            market_index
+        )
+    }
+
+    pub fn get_delegate_permissions(&self) -> DriftResult<BitFlags<DelegatePermission>> {
+        BitFlags::<DelegatePermission>::from_bits(usize::from(self.delegate_permissions))
+            .safe_unwrap()
+    }
+
+    pub fn is_active_delegate(&self, signer: &Pubkey, slot: u64) -> bool {
+        self.delegate.eq(signer)
+            && !self.delegate.eq(&Pubkey::default())
+            && (self.delegate_expiry_slot == 0 || slot <= self.delegate_expiry_slot as u64)
+    }
+
+    pub fn delegate_has_permission(
+        &self,
+        signer: &Pubkey,
+        permission: DelegatePermission,
+        slot: u64,
+    ) -> DriftResult<bool> {
+        if !self.is_active_delegate(signer, slot) {
+            return Ok(false);
+        }
+
+        // delegates set before permissions existed keep full access until restricted
+        Ok(self.delegate_permissions == 0 || self.get_delegate_permissions()?.contains(permission))
+    }
+
     pub fn get_spot_position_index(&self, market_index: u16) -> DriftResult<usize> {
         // first spot position is always quote asset
         if market_index == 0 {
@@ -152,6 +296,14 @@ impl User {
         Ok(new_spot_position_index)
     }
 
+    /// frees the slot of a spot position that has been fully closed
+    pub fn reset_spot_position_if_empty(&mut self, position_index: usize) {
+        let spot_position = &self.spot_positions[position_index];
+        if position_index != 0 && spot_position.is_available() && !spot_position.has_open_order() {
+            self.spot_positions[position_index] = SpotPosition::default();
+        }
+    }
+
     pub fn force_get_spot_position_mut(
         &mut self,
         market_index: u16,
@@ -286,6 +438,23 @@ impl User {
         }
         self.idle = false;
     }
+
+    /// returns whether margin_warning changed
+    pub fn update_margin_warning(
+        &mut self,
+        health: u8,
+        margin_warning_health: u8,
+        now: i64,
+    ) -> DriftResult<bool> {
+        let margin_warning = health < margin_warning_health;
+        if margin_warning == self.margin_warning {
+            return Ok(false);
+        }
+
+        self.margin_warning = margin_warning;
+        self.margin_warning_ts = now.cast()?;
+        Ok(true)
+    }
 }
 
 #[zero_copy]
@@ -420,7 +589,9 @@ pub struct PerpPosition {
     pub remainder_base_asset_amount: i32,
     pub market_index: u16,
     pub open_orders: u8,
-    pub padding: [u8; 1],
+    pub is_isolated: bool,
+    pub isolated_collateral: u64, // quote backing an isolated position, kept out of cross margin. precision: QUOTE_PRECISION
+    pub liquidation_price: u64, // approximate, updated on fills and pnl settles. 0 if none. precision: PRICE_PRECISION
 }
 
 impl PerpPosition {
@@ -433,6 +604,7 @@ impl PerpPosition {
             && !self.has_open_order()
             && !self.has_unsettled_pnl()
             && !self.is_lp()
+            && !self.is_isolated
     }
 
     pub fn is_open_position(&self) -> bool {
@@ -457,6 +629,15 @@ impl PerpPosition {
         self.base_asset_amount == 0 && self.quote_asset_amount != 0
     }
 
+    /// Isolated position whose collateral is exhausted with losses left over
+    pub fn is_isolated_bankrupt(&self) -> bool {
+        self.is_isolated
+            && self.base_asset_amount == 0
+            && !self.has_open_order()
+            && self.isolated_collateral == 0
+            && self.quote_asset_amount < 0
+    }
+
     pub fn worst_case_base_asset_amount(&self) -> DriftResult<i128> {
         let base_asset_amount_all_bids_fill = self.base_asset_amount.safe_add(self.open_bids)?;
         let base_asset_amount_all_asks_fill = self.base_asset_amount.safe_add(self.open_asks)?;
@@ -558,6 +739,19 @@ pub struct Order {
     pub immediate_or_cancel: bool,
     pub trigger_condition: OrderTriggerCondition,
     pub auction_duration: u8,
+    pub trail_type: OrderTrailType,
+    pub twap_slice_count: u8,
+    pub twap_slices_remaining: u8,
+    pub trail_amount: u64, // distance trigger price trails oracle. precision: PRICE_PRECISION or bps (see trail_type)
+    pub display_quantity: u64, // iceberg slice matchable at a time, 0 if the full size is displayed. precision: BASE_PRECISION
+    pub trigger_funding_rate: i64, // predicted funding rate threshold for funding trigger conditions. precision: FUNDING_RATE_PRECISION
+    pub linked_order_id: u32,      // order canceled once this one fills or triggers, 0 if unlinked
+    pub twap_interval: u32,        // slots between twap slices
+    pub auction_curve: AuctionCurve,
+    pub auction_step_count: u8, // price moves in this many equal jumps for step auctions
+    pub auction_extended: bool, // market order auction has used its one-time extension
+    pub builder_fee_share: u8,  // percent of the taker fee sent to the approved builder
+    pub max_margin_sizing: bool, // resized to the user's free collateral at each fill
     pub padding: [u8; 3],
 }
 
@@ -578,6 +772,7 @@ impl Order {
         fallback_price: Option<u64>,
         slot: u64,
         tick_size: u64,
+        is_prediction_market: bool,
     ) -> DriftResult<Option<u64>> {
         let price = if self.has_auction_price(self.slot, self.auction_duration, slot)? {
             Some(calculate_auction_price(
@@ -585,6 +780,7 @@ impl Order {
                 slot,
                 tick_size,
                 valid_oracle_price,
+                is_prediction_market,
             )?)
         } else if self.has_oracle_price_offset() {
             let oracle_price = valid_oracle_price.ok_or_else(|| {
@@ -613,6 +809,15 @@ impl Order {
             Some(self.price)
         };
 
+        // prediction market prices can't go above 1, so a long without a limit is limited at 1
+        if is_prediction_market {
+            return Ok(match (price, self.direction) {
+                (Some(price), _) => Some(price.min(MAX_PREDICTION_MARKET_PRICE)),
+                (None, PositionDirection::Long) => Some(MAX_PREDICTION_MARKET_PRICE),
+                (None, PositionDirection::Short) => None,
+            });
+        }
+
         Ok(price)
     }
 
@@ -624,8 +829,15 @@ impl Order {
         fallback_price: Option<u64>,
         slot: u64,
         tick_size: u64,
+        is_prediction_market: bool,
     ) -> DriftResult<u64> {
-        match self.get_limit_price(valid_oracle_price, fallback_price, slot, tick_size)? {
+        match self.get_limit_price(
+            valid_oracle_price,
+            fallback_price,
+            slot,
+            tick_size,
+            is_prediction_market,
+        )? {
             Some(price) => Ok(price),
             None => {
                 let caller = Location::caller();
@@ -653,6 +865,16 @@ impl Order {
         self.auction_duration != 0
     }
 
+    pub fn is_eligible_for_auction_extension(&self, slot: u64) -> DriftResult<bool> {
+        Ok(matches!(
+            self.order_type,
+            OrderType::Market | OrderType::TriggerMarket
+        ) && self.has_auction()
+            && !self.auction_extended
+            && self.base_asset_amount_filled == 0
+            && self.is_auction_complete(slot)?)
+    }
+
     pub fn has_auction_price(
         &self,
         order_slot: u64,
@@ -707,6 +929,53 @@ impl Order {
         }
     }
 
+    pub fn is_iceberg(&self) -> bool {
+        self.display_quantity != 0
+    }
+
+    /// The part of the unfilled amount makers can match against. Iceberg orders only show the
+    /// remainder of their current slice, which is replenished once the slice fills
+    pub fn get_base_asset_amount_displayed(
+        &self,
+        existing_position: Option<i64>,
+    ) -> DriftResult<u64> {
+        let base_asset_amount_unfilled = self.get_base_asset_amount_unfilled(existing_position)?;
+
+        if !self.is_iceberg() {
+            return Ok(base_asset_amount_unfilled);
+        }
+
+        let slice_filled = self
+            .base_asset_amount_filled
+            .checked_rem_euclid(self.display_quantity)
+            .ok_or_else(math_error!())?;
+
+        Ok(base_asset_amount_unfilled.min(self.display_quantity.safe_sub(slice_filled)?))
+    }
+
+    /// Called after an iceberg order is filled as a maker. Once the displayed slice is fully
+    /// filled the next slice is shown and gets a new slot, so it queues behind orders already
+    /// resting at the price. The auction is cleared so the new slot doesnt restart it
+    pub fn replenish_iceberg_slice(&mut self, slot: u64) -> DriftResult<bool> {
+        if !self.is_iceberg() || self.get_base_asset_amount_unfilled(None)? == 0 {
+            return Ok(false);
+        }
+
+        let slice_filled = self
+            .base_asset_amount_filled
+            .checked_rem_euclid(self.display_quantity)
+            .ok_or_else(math_error!())?;
+
+        if slice_filled != 0 {
+            return Ok(false);
+        }
+
+        self.slot = slot;
+        self.auction_duration = 0;
+
+        Ok(true)
+    }
+
     pub fn must_be_triggered(&self) -> bool {
         matches!(
             self.order_type,
@@ -714,6 +983,33 @@ impl Order {
         )
     }
 
+    pub fn is_trailing(&self) -> bool {
+        matches!(
+            self.trigger_condition,
+            OrderTriggerCondition::TrailingAbove | OrderTriggerCondition::TrailingBelow
+        )
+    }
+
+    pub fn is_funding_triggered(&self) -> bool {
+        matches!(
+            self.trigger_condition,
+            OrderTriggerCondition::FundingAbove | OrderTriggerCondition::FundingBelow
+        )
+    }
+
+    pub fn is_twap_slice_ready(&self, slot: u64) -> DriftResult<bool> {
+        // first slice can fill immediately, the rest once per interval
+        if self.twap_slices_remaining == self.twap_slice_count {
+            return Ok(true);
+        }
+
+        Ok(slot >= self.slot.safe_add(self.twap_interval.cast()?)?)
+    }
+
+    pub fn is_linked(&self) -> bool {
+        self.linked_order_id != 0
+    }
+
     pub fn triggered(&self) -> bool {
         matches!(
             self.trigger_condition,
@@ -764,6 +1060,8 @@ impl Default for Order {
             market_type: MarketType::Perp,
             slot: 0,
             order_id: 0,
+            linked_order_id: 0,
+            twap_interval: 0,
             user_order_id: 0,
             market_index: 0,
             price: 0,
@@ -782,6 +1080,17 @@ impl Default for Order {
             auction_end_price: 0,
             auction_duration: 0,
             max_ts: 0,
+            trail_amount: 0,
+            display_quantity: 0,
+            trigger_funding_rate: 0,
+            trail_type: OrderTrailType::Absolute,
+            twap_slice_count: 0,
+            twap_slices_remaining: 0,
+            auction_curve: AuctionCurve::Linear,
+            auction_step_count: 0,
+            auction_extended: false,
+            builder_fee_share: 0,
+            max_margin_sizing: false,
             padding: [0; 3],
         }
     }
@@ -802,6 +1111,7 @@ pub enum OrderType {
     TriggerMarket,
     TriggerLimit,
     Oracle,
+    Twap,
 }
 
 impl Default for OrderType {
@@ -816,6 +1126,10 @@ pub enum OrderTriggerCondition {
     Below,
     TriggeredAbove, // above condition has been triggered
     TriggeredBelow, // below condition has been triggered
+    TrailingAbove,  // trigger price trails the oracle from above
+    TrailingBelow,  // trigger price trails the oracle from below
+    FundingAbove,   // predicted funding rate above trigger_funding_rate
+    FundingBelow,   // predicted funding rate below trigger_funding_rate
 }
 
 impl Default for OrderTriggerCondition {
@@ -824,6 +1138,44 @@ impl Default for OrderTriggerCondition {
     }
 }
 
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub enum OrderTrailType {
+    Absolute,
+    Bps,
+}
+
+impl Default for OrderTrailType {
+    fn default() -> Self {
+        OrderTrailType::Absolute
+    }
+}
+
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub enum AuctionCurve {
+    Linear,
+    Exponential, // moves slowly at the start of the auction and quickly at the end
+    Step,        // moves in auction_step_count equal jumps
+}
+
+impl Default for AuctionCurve {
+    fn default() -> Self {
+        AuctionCurve::Linear
+    }
+}
+
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
+pub enum SizeDistribution {
+    Flat,
+    Ascending,  // order size grows from start price to end price
+    Descending, // order size shrinks from start price to end price
+}
+
+impl Default for SizeDistribution {
+    fn default() -> Self {
+        SizeDistribution::Flat
+    }
+}
+
 #[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Debug, Eq)]
 pub enum MarketType {
     Spot,
@@ -858,7 +1210,22 @@ pub struct UserStats {
     pub number_of_sub_accounts: u16,
     pub number_of_sub_accounts_created: u16,
     pub is_referrer: bool,
-    pub padding: [u8; 51],
+    /// percent of the referee's taker fee paid to this referrer, 0 to use the fee tier
+    pub referrer_reward_share: u8,
+    /// percent of this referrer's reward passed up to its own referrer
+    pub second_level_referrer_reward_share: u8,
+    /// months of full referrer reward before it starts halving, 0 to never decay
+    pub referrer_reward_decay_months: u8,
+    pub disable_trading_until: i64,
+    /// builder allowed to take a share of this authority's taker fees
+    pub approved_builder: Pubkey,
+    /// max percent of the taker fee the approved builder can take
+    pub builder_max_fee_share: u8,
+    /// tier of the authority's governance token stake, cached on every stake change
+    pub governance_stake_tier: u8,
+    pub padding: [u8; 2],
+    /// unix ts the referrer was linked, 0 for referrals that predate decay
+    pub referred_ts: u32,
 }
 
 impl Default for UserStats {
@@ -878,7 +1245,15 @@ impl Default for UserStats {
             number_of_sub_accounts: 0,
             number_of_sub_accounts_created: 0,
             is_referrer: false,
-            padding: [0; 51],
+            referrer_reward_share: 0,
+            second_level_referrer_reward_share: 0,
+            referrer_reward_decay_months: 0,
+            disable_trading_until: 0,
+            approved_builder: Pubkey::default(),
+            builder_max_fee_share: 0,
+            governance_stake_tier: 0,
+            padding: [0; 2],
+            referred_ts: 0,
         }
     }
 }
@@ -888,6 +1263,10 @@ impl Size for UserStats {
 }
 
 impl UserStats {
+    pub fn is_trading_disabled(&self, now: i64) -> bool {
+        now < self.disable_trading_until
+    }
+
     pub fn update_maker_volume_30d(&mut self, quote_asset_amount: u64, now: i64) -> DriftResult {
         let since_last = max(1_i64, now.safe_sub(self.last_maker_volume_30d_ts)?);
 
@@ -971,6 +1350,12 @@ impl UserStats {
         Ok(())
     }
 
+    pub fn increment_total_token_discount(&mut self, discount: u64) -> DriftResult {
+        self.fees.total_token_discount = self.fees.total_token_discount.safe_add(discount)?;
+
+        Ok(())
+    }
+
     pub fn has_referrer(&self) -> bool {
         !self.referrer.eq(&Pubkey::default())
     }
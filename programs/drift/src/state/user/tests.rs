@@ -1016,3 +1016,419 @@ mod get_base_asset_amount_unfilled {
         assert_eq!(order.get_base_asset_amount_unfilled(Some(6)).unwrap(), 5)
     }
 }
+
+mod get_base_asset_amount_displayed {
+    use crate::state::user::Order;
+
+    #[test]
+    fn not_iceberg() {
+        let order = Order {
+            base_asset_amount: 10,
+            base_asset_amount_filled: 3,
+            ..Order::default()
+        };
+
+        assert_eq!(order.get_base_asset_amount_displayed(None).unwrap(), 7)
+    }
+
+    #[test]
+    fn iceberg_slices() {
+        let mut order = Order {
+            base_asset_amount: 10,
+            display_quantity: 4,
+            ..Order::default()
+        };
+
+        assert_eq!(order.get_base_asset_amount_displayed(None).unwrap(), 4);
+
+        order.base_asset_amount_filled = 3;
+        assert_eq!(order.get_base_asset_amount_displayed(None).unwrap(), 1);
+
+        // slice fully filled, next slice displayed
+        order.base_asset_amount_filled = 4;
+        assert_eq!(order.get_base_asset_amount_displayed(None).unwrap(), 4);
+
+        // last slice capped by what is left unfilled
+        order.base_asset_amount_filled = 8;
+        assert_eq!(order.get_base_asset_amount_displayed(None).unwrap(), 2);
+    }
+
+    #[test]
+    fn iceberg_reduce_only() {
+        let order = Order {
+            base_asset_amount: 10,
+            display_quantity: 4,
+            reduce_only: true,
+            ..Order::default()
+        };
+
+        assert_eq!(order.get_base_asset_amount_displayed(Some(-2)).unwrap(), 2)
+    }
+}
+
+mod get_limit_price {
+    use crate::controller::position::PositionDirection;
+    use crate::math::constants::{PRICE_PRECISION_I64, PRICE_PRECISION_U64};
+    use crate::state::user::{Order, OrderType};
+
+    #[test]
+    fn prediction_market_limit_price_capped_at_one() {
+        let order = Order {
+            order_type: OrderType::Limit,
+            oracle_price_offset: (PRICE_PRECISION_I64 / 10) as i32,
+            direction: PositionDirection::Long,
+            ..Order::default()
+        };
+        let oracle_price = Some(95 * PRICE_PRECISION_I64 / 100);
+
+        assert_eq!(
+            order
+                .get_limit_price(oracle_price, None, 0, 1, false)
+                .unwrap(),
+            Some(105 * PRICE_PRECISION_U64 / 100)
+        );
+        assert_eq!(
+            order
+                .get_limit_price(oracle_price, None, 0, 1, true)
+                .unwrap(),
+            Some(PRICE_PRECISION_U64)
+        );
+
+        // a long without a limit buys up to 1
+        let order = Order {
+            order_type: OrderType::Market,
+            direction: PositionDirection::Long,
+            ..Order::default()
+        };
+        assert_eq!(
+            order.get_limit_price(None, None, 0, 1, false).unwrap(),
+            None
+        );
+        assert_eq!(
+            order.get_limit_price(None, None, 0, 1, true).unwrap(),
+            Some(PRICE_PRECISION_U64)
+        );
+
+        let order = Order {
+            order_type: OrderType::Market,
+            direction: PositionDirection::Short,
+            ..Order::default()
+        };
+        assert_eq!(order.get_limit_price(None, None, 0, 1, true).unwrap(), None);
+    }
+}
+
+mod replenish_iceberg_slice {
+    use crate::state::user::Order;
+
+    #[test]
+    fn not_iceberg() {
+        let mut order = Order {
+            slot: 1,
+            base_asset_amount: 10,
+            base_asset_amount_filled: 4,
+            ..Order::default()
+        };
+
+        assert!(!order.replenish_iceberg_slice(5).unwrap());
+        assert_eq!(order.slot, 1);
+    }
+
+    #[test]
+    fn slice_partially_filled() {
+        let mut order = Order {
+            slot: 1,
+            base_asset_amount: 10,
+            base_asset_amount_filled: 3,
+            display_quantity: 4,
+            ..Order::default()
+        };
+
+        assert!(!order.replenish_iceberg_slice(5).unwrap());
+        assert_eq!(order.slot, 1);
+    }
+
+    #[test]
+    fn slice_filled() {
+        let mut order = Order {
+            slot: 1,
+            base_asset_amount: 10,
+            base_asset_amount_filled: 4,
+            display_quantity: 4,
+            auction_duration: 10,
+            ..Order::default()
+        };
+
+        assert!(order.replenish_iceberg_slice(5).unwrap());
+        assert_eq!(order.slot, 5);
+        assert_eq!(order.auction_duration, 0);
+        assert_eq!(order.get_base_asset_amount_displayed(None).unwrap(), 4);
+    }
+
+    #[test]
+    fn order_filled() {
+        let mut order = Order {
+            slot: 1,
+            base_asset_amount: 8,
+            base_asset_amount_filled: 8,
+            display_quantity: 4,
+            ..Order::default()
+        };
+
+        assert!(!order.replenish_iceberg_slice(5).unwrap());
+        assert_eq!(order.slot, 1);
+    }
+}
+
+mod reset_spot_position_if_empty {
+    use crate::state::spot_market::SpotBalanceType;
+    use crate::state::user::{SpotPosition, User};
+
+    #[test]
+    fn resets_closed_position() {
+        let mut user = User::default();
+        user.spot_positions[1] = SpotPosition {
+            market_index: 1,
+            balance_type: SpotBalanceType::Borrow,
+            cumulative_deposits: -100,
+            ..SpotPosition::default()
+        };
+
+        user.reset_spot_position_if_empty(1);
+
+        assert_eq!(user.spot_positions[1], SpotPosition::default());
+        assert!(user.get_spot_position(1).is_err());
+    }
+
+    #[test]
+    fn keeps_open_position() {
+        let mut user = User::default();
+        let with_balance = SpotPosition {
+            market_index: 1,
+            balance_type: SpotBalanceType::Borrow,
+            scaled_balance: 1,
+            ..SpotPosition::default()
+        };
+        let with_open_order = SpotPosition {
+            market_index: 2,
+            open_bids: 100,
+            ..SpotPosition::default()
+        };
+        user.spot_positions[1] = with_balance;
+        user.spot_positions[2] = with_open_order;
+
+        user.reset_spot_position_if_empty(1);
+        user.reset_spot_position_if_empty(2);
+
+        assert_eq!(user.spot_positions[1], with_balance);
+        assert_eq!(user.spot_positions[2], with_open_order);
+    }
+
+    #[test]
+    fn keeps_quote_position() {
+        let mut user = User::default();
+        user.spot_positions[0].cumulative_deposits = 100;
+
+        user.reset_spot_position_if_empty(0);
+
+        assert_eq!(user.spot_positions[0].cumulative_deposits, 100);
+    }
+}
+
+mod is_high_leverage_mode_for {
+    use crate::state::user::User;
+
+    #[test]
+    fn only_registered_market() {
+        let mut user = User {
+            high_leverage_mode_market_index: 1,
+            ..User::default()
+        };
+
+        assert!(!user.is_high_leverage_mode_for(1));
+
+        user.is_high_leverage_mode = true;
+        assert!(user.is_high_leverage_mode_for(1));
+        assert!(!user.is_high_leverage_mode_for(0));
+    }
+}
+
+mod delegate_has_permission {
+    use crate::state::user::{DelegatePermission, User};
+    use anchor_lang::prelude::Pubkey;
+
+    #[test]
+    fn no_delegate() {
+        let user = User::default();
+
+        // unset delegate never matches the default pubkey signer
+        assert!(!user.is_active_delegate(&Pubkey::default(), 0));
+        assert!(!user
+            .delegate_has_permission(&Pubkey::default(), DelegatePermission::PlaceOrders, 0)
+            .unwrap());
+    }
+
+    #[test]
+    fn unrestricted_delegate() {
+        let delegate = Pubkey::new_unique();
+        let user = User {
+            delegate,
+            ..User::default()
+        };
+
+        assert!(user
+            .delegate_has_permission(&delegate, DelegatePermission::WithdrawToAuthority, 100)
+            .unwrap());
+        assert!(!user
+            .delegate_has_permission(&Pubkey::new_unique(), DelegatePermission::PlaceOrders, 100)
+            .unwrap());
+    }
+
+    #[test]
+    fn restricted_delegate() {
+        let delegate = Pubkey::new_unique();
+        let user = User {
+            delegate,
+            delegate_permissions: (DelegatePermission::PlaceOrders
+                | DelegatePermission::CancelOrders)
+                .bits() as u8,
+            ..User::default()
+        };
+
+        assert!(user
+            .delegate_has_permission(&delegate, DelegatePermission::PlaceOrders, 100)
+            .unwrap());
+        assert!(user
+            .delegate_has_permission(&delegate, DelegatePermission::CancelOrders, 100)
+            .unwrap());
+        assert!(!user
+            .delegate_has_permission(&delegate, DelegatePermission::WithdrawToAuthority, 100)
+            .unwrap());
+        assert!(!user
+            .delegate_has_permission(&delegate, DelegatePermission::ManageLp, 100)
+            .unwrap());
+    }
+
+    #[test]
+    fn expired_delegate() {
+        let delegate = Pubkey::new_unique();
+        let user = User {
+            delegate,
+            delegate_expiry_slot: 100,
+            ..User::default()
+        };
+
+        assert!(user.is_active_delegate(&delegate, 100));
+        assert!(!user.is_active_delegate(&delegate, 101));
+        assert!(!user
+            .delegate_has_permission(&delegate, DelegatePermission::CancelOrders, 101)
+            .unwrap());
+    }
+}
+
+mod is_trading_disabled {
+    use crate::state::user::UserStats;
+    use crate::validation::user::validate_trading_not_disabled;
+
+    #[test]
+    fn kill_switch() {
+        let user_stats = UserStats {
+            disable_trading_until: 100,
+            ..UserStats::default()
+        };
+
+        assert!(user_stats.is_trading_disabled(99));
+        assert!(!user_stats.is_trading_disabled(100));
+
+        assert!(validate_trading_not_disabled(&user_stats, false, 99).is_err());
+        assert!(validate_trading_not_disabled(&user_stats, true, 99).is_ok());
+        assert!(validate_trading_not_disabled(&user_stats, false, 100).is_ok());
+    }
+}
+
+mod update_margin_warning {
+    use crate::state::user::User;
+
+    #[test]
+    fn set_and_clear() {
+        let mut user = User::default();
+
+        assert!(!user.update_margin_warning(30, 20, 1).unwrap());
+        assert!(!user.margin_warning);
+
+        assert!(user.update_margin_warning(19, 20, 2).unwrap());
+        assert!(user.margin_warning);
+        assert_eq!(user.margin_warning_ts, 2);
+
+        // unchanged while health stays below the threshold
+        assert!(!user.update_margin_warning(10, 20, 3).unwrap());
+        assert_eq!(user.margin_warning_ts, 2);
+
+        assert!(user.update_margin_warning(20, 20, 4).unwrap());
+        assert!(!user.margin_warning);
+        assert_eq!(user.margin_warning_ts, 4);
+    }
+}
+
+mod validate_third_party_deposit {
+    use crate::error::ErrorCode;
+    use crate::state::spot_market::SpotBalanceType;
+    use crate::state::user::{SpotPosition, User};
+
+    #[test]
+    fn existing_position() {
+        let mut user = User::default();
+        user.spot_positions[1] = SpotPosition {
+            market_index: 1,
+            balance_type: SpotBalanceType::Deposit,
+            scaled_balance: 1,
+            ..SpotPosition::default()
+        };
+
+        assert_eq!(user.validate_third_party_deposit(0), Ok(()));
+        assert_eq!(user.validate_third_party_deposit(1), Ok(()));
+    }
+
+    #[test]
+    fn opt_out() {
+        let user = User {
+            third_party_deposits_disabled: true,
+            ..User::default()
+        };
+
+        assert_eq!(
+            user.validate_third_party_deposit(0),
+            Err(ErrorCode::ThirdPartyDepositsDisabled)
+        );
+    }
+
+    #[test]
+    fn new_position() {
+        let user = User::default();
+
+        // opening a position would take one of the user's free spot slots
+        assert_eq!(
+            user.validate_third_party_deposit(1),
+            Err(ErrorCode::CouldNotFindSpotPosition)
+        );
+    }
+
+    #[test]
+    fn full_slots() {
+        let mut user = User::default();
+        for (i, spot_position) in user.spot_positions.iter_mut().enumerate() {
+            *spot_position = SpotPosition {
+                market_index: i as u16,
+                balance_type: SpotBalanceType::Deposit,
+                scaled_balance: 1,
+                ..SpotPosition::default()
+            };
+        }
+
+        assert_eq!(user.validate_third_party_deposit(7), Ok(()));
+        assert_eq!(
+            user.validate_third_party_deposit(8),
+            Err(ErrorCode::CouldNotFindSpotPosition)
+        );
+    }
+}
@@ -297,17 +297,31 @@ pub fn load_user_maps<'a>(
             break;
         }
 
+        // user authority 8 bytes from front of account
+        let user_authority = Pubkey::new(array_ref![data, 8, 32]);
+
         let user_account_info = account_info_iter.next().safe_unwrap()?;
 
-        let is_writable = user_account_info.is_writable;
-        if !is_writable {
-            return Err(ErrorCode::UserWrongMutability);
-        }
+        // a maker repeated across the remaining accounts is only loaded and validated once
+        if !user_map.0.contains_key(user_key) {
+            let is_writable = user_account_info.is_writable;
+            if !is_writable {
+                return Err(ErrorCode::UserWrongMutability);
+            }
 
-        let user_account_loader: AccountLoader<User> =
-            AccountLoader::try_from(user_account_info).or(Err(ErrorCode::InvalidUserAccount))?;
+            let user_account_loader: AccountLoader<User> =
+                AccountLoader::try_from(user_account_info)
+                    .or(Err(ErrorCode::InvalidUserAccount))?;
+
+            user_map.0.insert(*user_key, user_account_loader);
+        }
 
-        user_map.0.insert(*user_key, user_account_loader);
+        // sub accounts of an authority whose user stats is already loaded can omit it
+        if user_stats_map.0.contains_key(&user_authority)
+            && !is_next_account_user_stats(account_info_iter, &user_stats_discriminator)?
+        {
+            continue;
+        }
 
         validate!(
             account_info_iter.peek().is_some(),
@@ -353,3 +367,62 @@ pub fn load_user_maps<'a>(
 
     Ok((user_map, user_stats_map))
 }
+
+fn is_next_account_user_stats(
+    account_info_iter: &mut Peekable<Iter<AccountInfo>>,
+    user_stats_discriminator: &[u8; 8],
+) -> DriftResult<bool> {
+    let account_info = match account_info_iter.peek() {
+        Some(account_info) => account_info,
+        None => return Ok(false),
+    };
+
+    let data = account_info
+        .try_borrow_data()
+        .or(Err(ErrorCode::CouldNotLoadUserStatsData))?;
+
+    if data.len() < UserStats::SIZE {
+        return Ok(false);
+    }
+
+    Ok(array_ref![data, 0, 8] == user_stats_discriminator)
+}
+
+pub fn load_user_map<'a>(
+    account_info_iter: &mut Peekable<Iter<AccountInfo<'a>>>,
+) -> DriftResult<UserMap<'a>> {
+    let mut user_map = UserMap::empty();
+
+    let user_discriminator: [u8; 8] = User::discriminator();
+    while let Some(user_account_info) = account_info_iter.peek() {
+        let user_key = user_account_info.key;
+
+        let data = user_account_info
+            .try_borrow_data()
+            .or(Err(ErrorCode::CouldNotLoadUserData))?;
+
+        let expected_data_len = User::SIZE;
+        if data.len() < expected_data_len {
+            break;
+        }
+
+        let account_discriminator = array_ref![data, 0, 8];
+        if account_discriminator != &user_discriminator {
+            break;
+        }
+
+        let user_account_info = account_info_iter.next().safe_unwrap()?;
+
+        let is_writable = user_account_info.is_writable;
+        if !is_writable {
+            return Err(ErrorCode::UserWrongMutability);
+        }
+
+        let user_account_loader: AccountLoader<User> =
+            AccountLoader::try_from(user_account_info).or(Err(ErrorCode::InvalidUserAccount))?;
+
+        user_map.insert(*user_key, user_account_loader)?;
+    }
+
+    Ok(user_map)
+}
@@ -0,0 +1,175 @@
+use anchor_lang::prelude::*;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::math::casting::Cast;
+use crate::math::constants::PERCENTAGE_PRECISION;
+use crate::math::safe_math::SafeMath;
+use crate::state::traits::Size;
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+/// a user owned by the program, traded by a manager with the quote deposits of its depositors
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct Vault {
+    pub name: [u8; 32],
+    pub manager: Pubkey,
+    /// the vault is the authority of this user, the manager is its delegate
+    pub user: Pubkey,
+    /// shares of all depositors and the manager
+    pub total_shares: u128,
+    /// shares the manager earned from profit share
+    pub manager_shares: u128,
+    /// share of a depositor's profit above its high water mark paid to the manager, PERCENTAGE_PRECISION
+    pub profit_share: u32,
+    pub bump: u8,
+    pub padding: [u8; 11],
+}
+
+impl Size for Vault {
+    const SIZE: usize = 152;
+}
+
+impl Vault {
+    pub fn calculate_shares_for_amount(&self, amount: u64, equity: u64) -> DriftResult<u128> {
+        if self.total_shares == 0 {
+            return Ok(amount.cast()?);
+        }
+
+        validate!(
+            equity > 0,
+            ErrorCode::VaultInsolvent,
+            "vault has {} shares and no equity",
+            self.total_shares
+        )?;
+
+        amount
+            .cast::<u128>()?
+            .safe_mul(self.total_shares)?
+            .safe_div(equity.cast()?)
+    }
+
+    pub fn calculate_amount_for_shares(&self, shares: u128, equity: u64) -> DriftResult<u64> {
+        if self.total_shares == 0 {
+            return Ok(0);
+        }
+
+        shares
+            .safe_mul(equity.cast()?)?
+            .safe_div(self.total_shares)?
+            .cast()
+    }
+
+    pub fn manager_withdraw(&mut self, shares: u128, equity: u64) -> DriftResult<u64> {
+        validate!(
+            shares > 0 && shares <= self.manager_shares,
+            ErrorCode::InsufficientVaultShares,
+            "manager has {} shares, tried to withdraw {}",
+            self.manager_shares,
+            shares
+        )?;
+
+        let amount = self.calculate_amount_for_shares(shares, equity)?;
+
+        self.manager_shares = self.manager_shares.safe_sub(shares)?;
+        self.total_shares = self.total_shares.safe_sub(shares)?;
+
+        Ok(amount)
+    }
+}
+
+#[account(zero_copy)]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct VaultDepositor {
+    pub vault: Pubkey,
+    pub authority: Pubkey,
+    pub shares: u128,
+    /// value of the depositor's shares profit share was last paid up to, in quote
+    pub high_water_mark: u64,
+    pub cumulative_profit_share_paid: u64,
+}
+
+impl Size for VaultDepositor {
+    const SIZE: usize = 104;
+}
+
+impl VaultDepositor {
+    /// moves the profit share on the depositor's value above its high water mark to the manager
+    pub fn apply_profit_share(&mut self, vault: &mut Vault, equity: u64) -> DriftResult<u64> {
+        let value = vault.calculate_amount_for_shares(self.shares, equity)?;
+        if value <= self.high_water_mark {
+            return Ok(0);
+        }
+
+        let profit_share_amount = value
+            .safe_sub(self.high_water_mark)?
+            .cast::<u128>()?
+            .safe_mul(vault.profit_share.cast()?)?
+            .safe_div(PERCENTAGE_PRECISION)?
+            .cast::<u64>()?;
+
+        let profit_share_shares = vault
+            .calculate_shares_for_amount(profit_share_amount, equity)?
+            .min(self.shares);
+
+        self.shares = self.shares.safe_sub(profit_share_shares)?;
+        vault.manager_shares = vault.manager_shares.safe_add(profit_share_shares)?;
+
+        self.high_water_mark = value.safe_sub(profit_share_amount)?;
+        self.cumulative_profit_share_paid = self
+            .cumulative_profit_share_paid
+            .safe_add(profit_share_amount)?;
+
+        Ok(profit_share_amount)
+    }
+
+    pub fn deposit(&mut self, vault: &mut Vault, amount: u64, equity: u64) -> DriftResult<u128> {
+        self.apply_profit_share(vault, equity)?;
+
+        let shares = vault.calculate_shares_for_amount(amount, equity)?;
+
+        validate!(
+            shares > 0,
+            ErrorCode::InsufficientDeposit,
+            "deposit of {} too small for a vault share",
+            amount
+        )?;
+
+        self.shares = self.shares.safe_add(shares)?;
+        vault.total_shares = vault.total_shares.safe_add(shares)?;
+        self.high_water_mark = self.high_water_mark.safe_add(amount)?;
+
+        Ok(shares)
+    }
+
+    pub fn withdraw(&mut self, vault: &mut Vault, shares: u128, equity: u64) -> DriftResult<u64> {
+        self.apply_profit_share(vault, equity)?;
+
+        validate!(
+            shares > 0 && shares <= self.shares,
+            ErrorCode::InsufficientVaultShares,
+            "depositor has {} shares, tried to withdraw {}",
+            self.shares,
+            shares
+        )?;
+
+        let amount = vault.calculate_amount_for_shares(shares, equity)?;
+
+        let high_water_mark_removed = self
+            .high_water_mark
+            .cast::<u128>()?
+            .safe_mul(shares)?
+            .safe_div(self.shares)?
+            .cast::<u64>()?;
+        self.high_water_mark = self.high_water_mark.safe_sub(high_water_mark_removed)?;
+
+        self.shares = self.shares.safe_sub(shares)?;
+        vault.total_shares = vault.total_shares.safe_sub(shares)?;
+
+        Ok(amount)
+    }
+}
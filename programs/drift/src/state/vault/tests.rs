@@ -0,0 +1,69 @@
+use crate::error::ErrorCode;
+use crate::math::constants::QUOTE_PRECISION_U64;
+use crate::state::vault::{Vault, VaultDepositor};
+
+#[test]
+fn profit_share_above_high_water_mark() {
+    let mut vault = Vault {
+        profit_share: 200_000, // 20%
+        ..Vault::default()
+    };
+    let mut vault_depositor = VaultDepositor::default();
+
+    let shares = vault_depositor
+        .deposit(&mut vault, 100 * QUOTE_PRECISION_U64, 0)
+        .unwrap();
+    assert_eq!(shares, 100 * QUOTE_PRECISION_U64 as u128);
+    assert_eq!(vault.total_shares, shares);
+    assert_eq!(vault_depositor.high_water_mark, 100 * QUOTE_PRECISION_U64);
+
+    // equity doubles, 20% of the 100 profit goes to the manager
+    let amount = vault_depositor
+        .withdraw(&mut vault, shares / 2, 200 * QUOTE_PRECISION_U64)
+        .unwrap();
+    assert_eq!(amount, 100 * QUOTE_PRECISION_U64);
+    assert_eq!(
+        vault_depositor.cumulative_profit_share_paid,
+        20 * QUOTE_PRECISION_U64
+    );
+    assert_eq!(vault.manager_shares, 10 * QUOTE_PRECISION_U64 as u128);
+    assert_eq!(vault_depositor.shares, 40 * QUOTE_PRECISION_U64 as u128);
+    assert_eq!(vault_depositor.high_water_mark, 80 * QUOTE_PRECISION_U64);
+    assert_eq!(vault.total_shares, 50 * QUOTE_PRECISION_U64 as u128);
+
+    let amount = vault
+        .manager_withdraw(10 * QUOTE_PRECISION_U64 as u128, 100 * QUOTE_PRECISION_U64)
+        .unwrap();
+    assert_eq!(amount, 20 * QUOTE_PRECISION_U64);
+    assert_eq!(vault.manager_shares, 0);
+
+    // no profit share below the high water mark
+    let profit_share_amount = vault_depositor
+        .apply_profit_share(&mut vault, 60 * QUOTE_PRECISION_U64)
+        .unwrap();
+    assert_eq!(profit_share_amount, 0);
+    assert_eq!(vault_depositor.shares, 40 * QUOTE_PRECISION_U64 as u128);
+
+    assert_eq!(
+        vault_depositor.withdraw(
+            &mut vault,
+            41 * QUOTE_PRECISION_U64 as u128,
+            60 * QUOTE_PRECISION_U64
+        ),
+        Err(ErrorCode::InsufficientVaultShares)
+    );
+}
+
+#[test]
+fn insolvent_vault() {
+    let mut vault = Vault {
+        total_shares: 100,
+        ..Vault::default()
+    };
+    let mut vault_depositor = VaultDepositor::default();
+
+    assert_eq!(
+        vault_depositor.deposit(&mut vault, 100, 0),
+        Err(ErrorCode::VaultInsolvent)
+    );
+}
@@ -4,6 +4,7 @@ use bytes::BytesMut;
 
 use pyth::pc::Price;
 
+use crate::math::constants::MAX_USER_ORDERS;
 use crate::state::user::{Order, PerpPosition, SpotPosition};
 
 pub fn get_positions(position: PerpPosition) -> [PerpPosition; 8] {
@@ -12,8 +13,8 @@ pub fn get_positions(position: PerpPosition) -> [PerpPosition; 8] {
     positions
 }
 
-pub fn get_orders(order: Order) -> [Order; 32] {
-    let mut orders = [Order::default(); 32];
+pub fn get_orders(order: Order) -> [Order; MAX_USER_ORDERS] {
+    let mut orders = [Order::default(); MAX_USER_ORDERS];
     orders[0] = order;
     orders
 }
@@ -22,7 +23,7 @@ pub fn get_orders(order: Order) -> [Order; 32] {
 macro_rules! get_orders {
     ($($order: expr),+) => {
         {
-            let mut orders = [Order::default(); 32];
+            let mut orders = [Order::default(); $crate::math::constants::MAX_USER_ORDERS];
             let mut index = 0;
             $(
                 index += 1;
@@ -0,0 +1,144 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use solana_program::instruction::Instruction;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::instruction::{BeginFlashBorrow, EndFlashBorrow};
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+enum FlashBorrowIx {
+    Begin { market_index: u16, amount: u64 },
+    End { market_index: u16, amount: u64 },
+}
+
+fn parse_flash_borrow_ix(ix: &Instruction) -> DriftResult<Option<FlashBorrowIx>> {
+    if ix.program_id != crate::id() || ix.data.len() < 8 {
+        return Ok(None);
+    }
+
+    let (discriminator, mut args) = ix.data.split_at(8);
+    if discriminator == BeginFlashBorrow::discriminator() {
+        let BeginFlashBorrow {
+            market_index,
+            amount,
+        } = BeginFlashBorrow::deserialize(&mut args).map_err(|_| ErrorCode::InvalidFlashBorrow)?;
+        return Ok(Some(FlashBorrowIx::Begin {
+            market_index,
+            amount,
+        }));
+    }
+
+    if discriminator == EndFlashBorrow::discriminator() {
+        let EndFlashBorrow {
+            market_index,
+            amount,
+        } = EndFlashBorrow::deserialize(&mut args).map_err(|_| ErrorCode::InvalidFlashBorrow)?;
+        return Ok(Some(FlashBorrowIx::End {
+            market_index,
+            amount,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Checks that the begin at `current_index` is a top level instruction and that the next flash
+/// borrow instruction in the transaction is an end repaying the same market and amount
+pub fn validate_begin_flash_borrow(
+    ixs: &[Instruction],
+    current_index: usize,
+    market_index: u16,
+    amount: u64,
+) -> DriftResult {
+    validate!(
+        matches!(
+            ixs.get(current_index)
+                .map(parse_flash_borrow_ix)
+                .transpose()?,
+            Some(Some(FlashBorrowIx::Begin { .. }))
+        ),
+        ErrorCode::InvalidFlashBorrow,
+        "begin_flash_borrow can not be called through cpi"
+    )?;
+
+    for ix in ixs.iter().skip(current_index + 1) {
+        match parse_flash_borrow_ix(ix)? {
+            Some(FlashBorrowIx::End {
+                market_index: end_market_index,
+                amount: end_amount,
+            }) => {
+                validate!(
+                    end_market_index == market_index && end_amount == amount,
+                    ErrorCode::InvalidFlashBorrow,
+                    "end_flash_borrow (market {} amount {}) does not match begin_flash_borrow (market {} amount {})",
+                    end_market_index,
+                    end_amount,
+                    market_index,
+                    amount
+                )?;
+
+                return Ok(());
+            }
+            Some(FlashBorrowIx::Begin { .. }) => {
+                msg!("flash borrows can not be nested");
+                return Err(ErrorCode::InvalidFlashBorrow);
+            }
+            None => {}
+        }
+    }
+
+    msg!("begin_flash_borrow must be followed by end_flash_borrow");
+    Err(ErrorCode::InvalidFlashBorrow)
+}
+
+/// Checks that the end at `current_index` is a top level instruction and that the previous flash
+/// borrow instruction in the transaction is a begin for the same market and amount
+pub fn validate_end_flash_borrow(
+    ixs: &[Instruction],
+    current_index: usize,
+    market_index: u16,
+    amount: u64,
+) -> DriftResult {
+    validate!(
+        matches!(
+            ixs.get(current_index)
+                .map(parse_flash_borrow_ix)
+                .transpose()?,
+            Some(Some(FlashBorrowIx::End { .. }))
+        ),
+        ErrorCode::InvalidFlashBorrow,
+        "end_flash_borrow can not be called through cpi"
+    )?;
+
+    for ix in ixs.iter().take(current_index).rev() {
+        match parse_flash_borrow_ix(ix)? {
+            Some(FlashBorrowIx::Begin {
+                market_index: begin_market_index,
+                amount: begin_amount,
+            }) => {
+                validate!(
+                    begin_market_index == market_index && begin_amount == amount,
+                    ErrorCode::InvalidFlashBorrow,
+                    "begin_flash_borrow (market {} amount {}) does not match end_flash_borrow (market {} amount {})",
+                    begin_market_index,
+                    begin_amount,
+                    market_index,
+                    amount
+                )?;
+
+                return Ok(());
+            }
+            Some(FlashBorrowIx::End { .. }) => {
+                msg!("end_flash_borrow already used for the previous begin_flash_borrow");
+                return Err(ErrorCode::InvalidFlashBorrow);
+            }
+            None => {}
+        }
+    }
+
+    msg!("end_flash_borrow must follow begin_flash_borrow");
+    Err(ErrorCode::InvalidFlashBorrow)
+}
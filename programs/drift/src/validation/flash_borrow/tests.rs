@@ -0,0 +1,95 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::InstructionData;
+use solana_program::instruction::Instruction;
+
+use crate::instruction::{BeginFlashBorrow, EndFlashBorrow};
+use crate::validation::flash_borrow::{validate_begin_flash_borrow, validate_end_flash_borrow};
+
+fn begin_ix(market_index: u16, amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![],
+        data: BeginFlashBorrow {
+            market_index,
+            amount,
+        }
+        .data(),
+    }
+}
+
+fn end_ix(market_index: u16, amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![],
+        data: EndFlashBorrow {
+            market_index,
+            amount,
+        }
+        .data(),
+    }
+}
+
+fn other_ix() -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_unique(),
+        accounts: vec![],
+        data: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn valid_flash_borrow() {
+    let ixs = vec![begin_ix(1, 100), other_ix(), other_ix(), end_ix(1, 100)];
+
+    assert!(validate_begin_flash_borrow(&ixs, 0, 1, 100).is_ok());
+    assert!(validate_end_flash_borrow(&ixs, 3, 1, 100).is_ok());
+}
+
+#[test]
+fn missing_end() {
+    let ixs = vec![other_ix(), begin_ix(1, 100), other_ix()];
+
+    assert!(validate_begin_flash_borrow(&ixs, 1, 1, 100).is_err());
+}
+
+#[test]
+fn mismatched_end() {
+    let ixs = vec![begin_ix(1, 100), end_ix(1, 99)];
+    assert!(validate_begin_flash_borrow(&ixs, 0, 1, 100).is_err());
+    assert!(validate_end_flash_borrow(&ixs, 1, 1, 99).is_err());
+
+    let ixs = vec![begin_ix(1, 100), end_ix(2, 100)];
+    assert!(validate_begin_flash_borrow(&ixs, 0, 1, 100).is_err());
+    assert!(validate_end_flash_borrow(&ixs, 1, 2, 100).is_err());
+}
+
+#[test]
+fn nested_begin() {
+    // both begins would otherwise match the single end
+    let ixs = vec![begin_ix(1, 100), begin_ix(1, 100), end_ix(1, 100)];
+
+    assert!(validate_begin_flash_borrow(&ixs, 0, 1, 100).is_err());
+}
+
+#[test]
+fn end_reused() {
+    let ixs = vec![begin_ix(1, 100), end_ix(1, 100), end_ix(1, 100)];
+
+    assert!(validate_end_flash_borrow(&ixs, 1, 1, 100).is_ok());
+    assert!(validate_end_flash_borrow(&ixs, 2, 1, 100).is_err());
+}
+
+#[test]
+fn end_without_begin() {
+    let ixs = vec![other_ix(), end_ix(1, 100)];
+
+    assert!(validate_end_flash_borrow(&ixs, 1, 1, 100).is_err());
+}
+
+#[test]
+fn called_through_cpi() {
+    // the top level instruction at the current index belongs to another program
+    let ixs = vec![other_ix(), end_ix(1, 100)];
+
+    assert!(validate_begin_flash_borrow(&ixs, 0, 1, 100).is_err());
+}
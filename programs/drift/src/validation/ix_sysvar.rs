@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+use solana_program::instruction::Instruction;
+use solana_program::sysvar::instructions::load_instruction_at_checked;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::validate;
+
+/// Loads every top level instruction in the transaction from the instructions sysvar
+pub fn load_transaction_instructions(ix_sysvar: &AccountInfo) -> DriftResult<Vec<Instruction>> {
+    let number_of_instructions = {
+        let data = ix_sysvar
+            .try_borrow_data()
+            .map_err(|_| ErrorCode::InvalidInstructionsSysvar)?;
+        validate!(
+            data.len() >= 2,
+            ErrorCode::InvalidInstructionsSysvar,
+            "invalid instructions sysvar"
+        )?;
+        u16::from_le_bytes([data[0], data[1]]) as usize
+    };
+
+    (0..number_of_instructions)
+        .map(|index| {
+            load_instruction_at_checked(index, ix_sysvar)
+                .map_err(|_| ErrorCode::InvalidInstructionsSysvar)
+        })
+        .collect()
+}
@@ -1,8 +1,9 @@
 use crate::error::{DriftResult, ErrorCode};
 use crate::math::constants::{
-    LIQUIDATION_FEE_TO_MARGIN_PRECISION_RATIO, MAX_MARGIN_RATIO, MIN_MARGIN_RATIO,
-    SPOT_IMF_PRECISION, SPOT_WEIGHT_PRECISION,
+    LIQUIDATION_FEE_TO_MARGIN_PRECISION_RATIO, MAX_MARGIN_RATIO, MIN_HIGH_LEVERAGE_MARGIN_RATIO,
+    MIN_MARGIN_RATIO, SPOT_IMF_PRECISION, SPOT_WEIGHT_PRECISION,
 };
+use crate::state::perp_market::MarginTier;
 use crate::validate;
 use solana_program::msg;
 
@@ -39,6 +40,98 @@ pub fn validate_margin(
     Ok(())
 }
 
+pub fn validate_high_leverage_margin_ratio(
+    high_leverage_margin_ratio_initial: u32,
+    high_leverage_margin_ratio_maintenance: u32,
+    margin_ratio_initial: u32,
+    margin_ratio_maintenance: u32,
+    liquidation_fee: u32,
+) -> DriftResult {
+    // both zero turns high leverage mode off for the market
+    if high_leverage_margin_ratio_initial == 0 && high_leverage_margin_ratio_maintenance == 0 {
+        return Ok(());
+    }
+
+    validate!(
+        (MIN_HIGH_LEVERAGE_MARGIN_RATIO..=margin_ratio_initial)
+            .contains(&high_leverage_margin_ratio_initial),
+        ErrorCode::InvalidMarginRatio,
+        "high_leverage_margin_ratio_initial={} must be between {} and margin_ratio_initial={}",
+        high_leverage_margin_ratio_initial,
+        MIN_HIGH_LEVERAGE_MARGIN_RATIO,
+        margin_ratio_initial
+    )?;
+
+    validate!(
+        high_leverage_margin_ratio_maintenance < high_leverage_margin_ratio_initial
+            && high_leverage_margin_ratio_maintenance <= margin_ratio_maintenance,
+        ErrorCode::InvalidMarginRatio,
+        "high_leverage_margin_ratio_maintenance={} must be less than high_leverage_margin_ratio_initial and <= margin_ratio_maintenance={}",
+        high_leverage_margin_ratio_maintenance,
+        margin_ratio_maintenance
+    )?;
+
+    validate!(
+        high_leverage_margin_ratio_maintenance * LIQUIDATION_FEE_TO_MARGIN_PRECISION_RATIO
+            > liquidation_fee,
+        ErrorCode::InvalidMarginRatio,
+        "high_leverage_margin_ratio_maintenance must be greater than liquidation fee"
+    )?;
+
+    Ok(())
+}
+
+pub fn validate_margin_tiers(
+    margin_tiers: &[MarginTier],
+    margin_ratio_initial: u32,
+) -> DriftResult {
+    let mut last_notional_threshold = 0_u32;
+    let mut last_margin_ratio = margin_ratio_initial;
+    let mut tier_disabled = false;
+
+    for margin_tier in margin_tiers.iter() {
+        if margin_tier.notional_threshold == 0 {
+            validate!(
+                margin_tier.margin_ratio_initial == 0,
+                ErrorCode::InvalidMarginRatio,
+                "disabled margin tier must have margin_ratio_initial of 0"
+            )?;
+
+            tier_disabled = true;
+            continue;
+        }
+
+        validate!(
+            !tier_disabled,
+            ErrorCode::InvalidMarginRatio,
+            "enabled margin tiers must come before disabled ones"
+        )?;
+
+        validate!(
+            margin_tier.notional_threshold > last_notional_threshold,
+            ErrorCode::InvalidMarginRatio,
+            "margin tier notional_threshold={} must be greater than previous threshold={}",
+            margin_tier.notional_threshold,
+            last_notional_threshold
+        )?;
+
+        validate!(
+            margin_tier.margin_ratio_initial > last_margin_ratio
+                && margin_tier.margin_ratio_initial <= MAX_MARGIN_RATIO,
+            ErrorCode::InvalidMarginRatio,
+            "margin tier margin_ratio_initial={} must be greater than {} and <= {}",
+            margin_tier.margin_ratio_initial,
+            last_margin_ratio,
+            MAX_MARGIN_RATIO
+        )?;
+
+        last_notional_threshold = margin_tier.notional_threshold;
+        last_margin_ratio = margin_tier.margin_ratio_initial;
+    }
+
+    Ok(())
+}
+
 pub fn validate_margin_weights(
     spot_market_index: u16,
     initial_asset_weight: u32,
@@ -1,8 +1,12 @@
 pub mod fee_structure;
+pub mod flash_borrow;
+pub mod ix_sysvar;
 pub mod margin;
 pub mod order;
 pub mod perp_market;
 pub mod position;
+pub mod sig_verification;
 pub mod spot_market;
+pub mod swap;
 pub mod user;
 pub mod whitelist;
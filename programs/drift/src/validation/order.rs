@@ -4,12 +4,16 @@ use crate::controller::position::PositionDirection;
 use crate::error::{DriftResult, ErrorCode};
 
 use crate::math::casting::Cast;
+use crate::math::constants::{
+    MAX_PREDICTION_MARKET_PRICE, MAX_PREDICTION_MARKET_PRICE_I64, ONE_BPS_DENOMINATOR,
+};
 use crate::math::orders::{
     calculate_base_asset_amount_to_fill_up_to_limit_price, is_multiple_of_step_size,
     order_breaches_oracle_price_bands,
 };
+use crate::math::safe_math::SafeMath;
 use crate::state::perp_market::PerpMarket;
-use crate::state::user::{Order, OrderTriggerCondition, OrderType};
+use crate::state::user::{AuctionCurve, Order, OrderTrailType, OrderTriggerCondition, OrderType};
 use crate::validate;
 
 pub fn validate_order(
@@ -36,6 +40,32 @@ pub fn validate_order(
         OrderType::Oracle => {
             validate_oracle_order(order, market.amm.order_step_size, market.amm.min_order_size)?
         }
+        OrderType::Twap => {
+            validate_twap_order(order, market.amm.order_step_size, market.amm.min_order_size)?
+        }
+    }
+
+    validate_display_quantity(order, market.amm.order_step_size, market.amm.min_order_size)?;
+    validate_auction_curve(order)?;
+
+    if market.is_prediction_market() {
+        validate!(
+            order.price <= MAX_PREDICTION_MARKET_PRICE,
+            ErrorCode::InvalidOrderLimitPrice,
+            "prediction market order price {} above 1",
+            order.price
+        )?;
+
+        if order.order_type != OrderType::Oracle {
+            validate!(
+                order.auction_start_price <= MAX_PREDICTION_MARKET_PRICE_I64
+                    && order.auction_end_price <= MAX_PREDICTION_MARKET_PRICE_I64,
+                ErrorCode::InvalidOrderAuction,
+                "prediction market auction prices {} {} above 1",
+                order.auction_start_price,
+                order.auction_end_price
+            )?;
+        }
     }
 
     Ok(())
@@ -190,6 +220,7 @@ fn validate_limit_order(
             market.amm.order_tick_size,
             market.margin_ratio_initial,
             market.margin_ratio_maintenance,
+            market.is_prediction_market(),
         )?;
 
         if order_breaches_oracle_price_limits {
@@ -234,8 +265,13 @@ fn validate_post_only_order(
     valid_oracle_price: Option<i64>,
     slot: u64,
 ) -> DriftResult {
-    let limit_price =
-        order.force_get_limit_price(valid_oracle_price, None, slot, market.amm.order_tick_size)?;
+    let limit_price = order.force_get_limit_price(
+        valid_oracle_price,
+        None,
+        slot,
+        market.amm.order_tick_size,
+        market.is_prediction_market(),
+    )?;
 
     let base_asset_amount_market_can_fill = calculate_base_asset_amount_to_fill_up_to_limit_price(
         order,
@@ -282,18 +318,26 @@ fn validate_trigger_limit_order(order: &Order, step_size: u64, min_order_size: u
 
     if !matches!(
         order.trigger_condition,
-        OrderTriggerCondition::Above | OrderTriggerCondition::Below
+        OrderTriggerCondition::Above
+            | OrderTriggerCondition::Below
+            | OrderTriggerCondition::TrailingAbove
+            | OrderTriggerCondition::TrailingBelow
+            | OrderTriggerCondition::FundingAbove
+            | OrderTriggerCondition::FundingBelow
     ) {
-        msg!("Invalid trigger condition, must be Above or Below");
+        msg!("Invalid trigger condition, must be Above, Below, TrailingAbove, TrailingBelow, FundingAbove or FundingBelow");
         return Err(ErrorCode::InvalidTriggerOrderCondition);
     }
 
+    validate_trail_amount(order)?;
+    validate_trigger_funding_rate(order)?;
+
     if order.price == 0 {
         msg!("Trigger limit order price == 0");
         return Err(ErrorCode::InvalidOrderLimitPrice);
     }
 
-    if order.trigger_price == 0 {
+    if order.trigger_price == 0 && !order.is_funding_triggered() {
         msg!("Trigger price == 0");
         return Err(ErrorCode::InvalidOrderTrigger);
     }
@@ -320,18 +364,26 @@ fn validate_trigger_market_order(
 
     if !matches!(
         order.trigger_condition,
-        OrderTriggerCondition::Above | OrderTriggerCondition::Below
+        OrderTriggerCondition::Above
+            | OrderTriggerCondition::Below
+            | OrderTriggerCondition::TrailingAbove
+            | OrderTriggerCondition::TrailingBelow
+            | OrderTriggerCondition::FundingAbove
+            | OrderTriggerCondition::FundingBelow
     ) {
-        msg!("Invalid trigger condition, must be Above or Below");
+        msg!("Invalid trigger condition, must be Above, Below, TrailingAbove, TrailingBelow, FundingAbove or FundingBelow");
         return Err(ErrorCode::InvalidTriggerOrderCondition);
     }
 
+    validate_trail_amount(order)?;
+    validate_trigger_funding_rate(order)?;
+
     if order.price > 0 {
         msg!("Trigger market order should not have price");
         return Err(ErrorCode::InvalidOrderLimitPrice);
     }
 
-    if order.trigger_price == 0 {
+    if order.trigger_price == 0 && !order.is_funding_triggered() {
         msg!("Trigger market order trigger_price == 0");
         return Err(ErrorCode::InvalidOrderTrigger);
     }
@@ -349,6 +401,164 @@ fn validate_trigger_market_order(
     Ok(())
 }
 
+fn validate_twap_order(order: &Order, step_size: u64, min_order_size: u64) -> DriftResult {
+    validate_base_asset_amount(order, step_size, min_order_size, order.reduce_only)?;
+
+    if order.twap_slice_count == 0 {
+        msg!("Twap order slice count == 0");
+        return Err(ErrorCode::InvalidTwapOrder);
+    }
+
+    if order.twap_interval == 0 {
+        msg!("Twap order interval == 0");
+        return Err(ErrorCode::InvalidTwapOrder);
+    }
+
+    let slice_base_asset_amount = order
+        .base_asset_amount
+        .safe_div(order.twap_slice_count.cast()?)?;
+    if slice_base_asset_amount < min_order_size {
+        msg!(
+            "Twap order slice base asset amount ({}) < min order size ({})",
+            slice_base_asset_amount,
+            min_order_size
+        );
+        return Err(ErrorCode::InvalidTwapOrder);
+    }
+
+    if order.trigger_price > 0 {
+        msg!("Twap order should not have trigger price");
+        return Err(ErrorCode::InvalidOrderTrigger);
+    }
+
+    if order.post_only {
+        msg!("Twap order can not be post only");
+        return Err(ErrorCode::InvalidOrderPostOnly);
+    }
+
+    if order.immediate_or_cancel {
+        msg!("Twap order can not be immediate or cancel");
+        return Err(ErrorCode::InvalidOrderIOC);
+    }
+
+    if order.has_oracle_price_offset() {
+        msg!("Twap order can not have oracle offset");
+        return Err(ErrorCode::InvalidOrderOracleOffset);
+    }
+
+    Ok(())
+}
+
+fn validate_trail_amount(order: &Order) -> DriftResult {
+    if !order.is_trailing() {
+        if order.trail_amount != 0 {
+            msg!("Only trailing trigger orders can have trail amount");
+            return Err(ErrorCode::InvalidOrderTrigger);
+        }
+
+        return Ok(());
+    }
+
+    if order.trail_amount == 0 {
+        msg!("Trailing trigger order trail amount == 0");
+        return Err(ErrorCode::InvalidOrderTrigger);
+    }
+
+    if order.trail_type == OrderTrailType::Bps
+        && order.trail_amount >= ONE_BPS_DENOMINATOR.cast::<u64>()?
+    {
+        msg!(
+            "Trailing trigger order trail amount must be less than {} bps",
+            ONE_BPS_DENOMINATOR
+        );
+        return Err(ErrorCode::InvalidOrderTrigger);
+    }
+
+    Ok(())
+}
+
+fn validate_trigger_funding_rate(order: &Order) -> DriftResult {
+    if !order.is_funding_triggered() {
+        if order.trigger_funding_rate != 0 {
+            msg!("Only funding trigger orders can have trigger funding rate");
+            return Err(ErrorCode::InvalidOrderTrigger);
+        }
+
+        return Ok(());
+    }
+
+    if order.trigger_price != 0 {
+        msg!("Funding trigger order can not have trigger price");
+        return Err(ErrorCode::InvalidOrderTrigger);
+    }
+
+    Ok(())
+}
+
+fn validate_display_quantity(order: &Order, step_size: u64, min_order_size: u64) -> DriftResult {
+    if !order.is_iceberg() {
+        return Ok(());
+    }
+
+    if order.order_type != OrderType::Limit {
+        msg!("Only limit orders can be iceberg orders");
+        return Err(ErrorCode::InvalidIcebergOrder);
+    }
+
+    if order.immediate_or_cancel {
+        msg!("Iceberg order can not be immediate or cancel");
+        return Err(ErrorCode::InvalidIcebergOrder);
+    }
+
+    validate!(
+        order.display_quantity < order.base_asset_amount,
+        ErrorCode::InvalidIcebergOrder,
+        "Iceberg display quantity ({}) must be less than base asset amount ({})",
+        order.display_quantity,
+        order.base_asset_amount
+    )?;
+
+    validate!(
+        is_multiple_of_step_size(order.display_quantity, step_size)?,
+        ErrorCode::InvalidIcebergOrder,
+        "Iceberg display quantity ({}) not a multiple of the step size ({})",
+        order.display_quantity,
+        step_size
+    )?;
+
+    validate!(
+        order.display_quantity >= min_order_size,
+        ErrorCode::InvalidIcebergOrder,
+        "Iceberg display quantity ({}) < min_order_size ({})",
+        order.display_quantity,
+        min_order_size
+    )?;
+
+    Ok(())
+}
+
+fn validate_auction_curve(order: &Order) -> DriftResult {
+    if order.auction_curve != AuctionCurve::Step {
+        validate!(
+            order.auction_step_count == 0,
+            ErrorCode::InvalidOrderAuction,
+            "Only step auctions can have an auction step count"
+        )?;
+
+        return Ok(());
+    }
+
+    validate!(
+        order.auction_step_count >= 2 && order.auction_step_count <= order.auction_duration,
+        ErrorCode::InvalidOrderAuction,
+        "Step auction step count ({}) must be between 2 and the auction duration ({})",
+        order.auction_step_count,
+        order.auction_duration
+    )?;
+
+    Ok(())
+}
+
 fn validate_base_asset_amount(
     order: &Order,
     step_size: u64,
@@ -463,8 +673,20 @@ pub fn validate_spot_order(
         }
         OrderType::TriggerLimit => validate_trigger_limit_order(order, step_size, min_order_size)?,
         OrderType::Oracle => validate_oracle_order(order, step_size, min_order_size)?,
+        OrderType::Twap => {
+            msg!("Twap orders are only supported for perp markets");
+            return Err(ErrorCode::InvalidTwapOrder);
+        }
+    }
+
+    if order.is_funding_triggered() {
+        msg!("Funding trigger orders are only supported for perp markets");
+        return Err(ErrorCode::InvalidTriggerOrderCondition);
     }
 
+    validate_display_quantity(order, step_size, min_order_size)?;
+    validate_auction_curve(order)?;
+
     Ok(())
 }
 
@@ -509,6 +731,7 @@ fn validate_spot_limit_order(
             tick_size,
             margin_ratio_initial,
             margin_ratio_maintenance,
+            false,
         )?;
 
         if order_breaches_oracle_price_limits {
@@ -0,0 +1,76 @@
+use anchor_lang::prelude::*;
+use solana_program::ed25519_program;
+use solana_program::instruction::Instruction;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_SERIALIZED_SIZE: usize = 14;
+const PUBKEY_SERIALIZED_SIZE: usize = 32;
+
+/// Checks that `ix` asks the ed25519 program to verify a single signature by `pubkey` over `message`.
+/// The ed25519 program fails the whole transaction if the signature itself is invalid
+pub fn verify_ed25519_ix(ix: &Instruction, pubkey: &Pubkey, message: &[u8]) -> DriftResult {
+    validate!(
+        ix.program_id == ed25519_program::id() && ix.accounts.is_empty(),
+        ErrorCode::SigVerificationFailed,
+        "instruction is not an ed25519 signature verification"
+    )?;
+
+    let data = &ix.data;
+    validate!(
+        data.len() >= SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_SERIALIZED_SIZE && data[0] == 1,
+        ErrorCode::SigVerificationFailed,
+        "ed25519 instruction must verify exactly one signature"
+    )?;
+
+    let read_u16 = |index: usize| {
+        let start = SIGNATURE_OFFSETS_START + index;
+        u16::from_le_bytes([data[start], data[start + 1]])
+    };
+
+    let signature_instruction_index = read_u16(2);
+    let public_key_offset = read_u16(4) as usize;
+    let public_key_instruction_index = read_u16(6);
+    let message_data_offset = read_u16(8) as usize;
+    let message_data_size = read_u16(10) as usize;
+    let message_instruction_index = read_u16(12);
+
+    // signature, pubkey and message must all live in the verification instruction itself
+    validate!(
+        signature_instruction_index == u16::MAX
+            && public_key_instruction_index == u16::MAX
+            && message_instruction_index == u16::MAX,
+        ErrorCode::SigVerificationFailed,
+        "ed25519 instruction must not reference data in other instructions"
+    )?;
+
+    let signer = get_data_slice(data, public_key_offset, PUBKEY_SERIALIZED_SIZE)?;
+    validate!(
+        signer == pubkey.as_ref(),
+        ErrorCode::SigVerificationFailed,
+        "signed by {:?}, expected {}",
+        signer,
+        pubkey
+    )?;
+
+    let signed_message = get_data_slice(data, message_data_offset, message_data_size)?;
+    validate!(
+        signed_message == message,
+        ErrorCode::SigVerificationFailed,
+        "signed message does not match"
+    )?;
+
+    Ok(())
+}
+
+fn get_data_slice(data: &[u8], offset: usize, len: usize) -> DriftResult<&[u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(ErrorCode::SigVerificationFailed)
+}
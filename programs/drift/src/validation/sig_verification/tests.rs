@@ -0,0 +1,84 @@
+use anchor_lang::prelude::Pubkey;
+use solana_program::ed25519_program;
+use solana_program::instruction::{AccountMeta, Instruction};
+
+use crate::validation::sig_verification::verify_ed25519_ix;
+
+// mirrors the layout of solana_sdk::ed25519_instruction::new_ed25519_instruction
+fn get_ed25519_ix(pubkey: &Pubkey, message: &[u8]) -> Instruction {
+    let public_key_offset: u16 = 16;
+    let signature_offset: u16 = public_key_offset + 32;
+    let message_data_offset: u16 = signature_offset + 64;
+
+    let mut data = vec![1_u8, 0];
+    for value in [
+        signature_offset,
+        u16::MAX,
+        public_key_offset,
+        u16::MAX,
+        message_data_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(pubkey.as_ref());
+    data.extend_from_slice(&[0_u8; 64]);
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::id(),
+        accounts: vec![],
+        data,
+    }
+}
+
+#[test]
+fn valid_ix() {
+    let pubkey = Pubkey::new_unique();
+    let message = b"signed order".to_vec();
+
+    let ix = get_ed25519_ix(&pubkey, &message);
+    assert!(verify_ed25519_ix(&ix, &pubkey, &message).is_ok());
+}
+
+#[test]
+fn wrong_signer_or_message() {
+    let pubkey = Pubkey::new_unique();
+    let message = b"signed order".to_vec();
+    let ix = get_ed25519_ix(&pubkey, &message);
+
+    assert!(verify_ed25519_ix(&ix, &Pubkey::new_unique(), &message).is_err());
+    assert!(verify_ed25519_ix(&ix, &pubkey, b"other order").is_err());
+}
+
+#[test]
+fn malformed_ix() {
+    let pubkey = Pubkey::new_unique();
+    let message = b"signed order".to_vec();
+
+    let mut ix = get_ed25519_ix(&pubkey, &message);
+    ix.program_id = Pubkey::new_unique();
+    assert!(verify_ed25519_ix(&ix, &pubkey, &message).is_err());
+
+    let mut ix = get_ed25519_ix(&pubkey, &message);
+    ix.accounts.push(AccountMeta::new_readonly(pubkey, false));
+    assert!(verify_ed25519_ix(&ix, &pubkey, &message).is_err());
+
+    // message read from another instruction
+    let mut ix = get_ed25519_ix(&pubkey, &message);
+    ix.data[14..16].copy_from_slice(&0_u16.to_le_bytes());
+    assert!(verify_ed25519_ix(&ix, &pubkey, &message).is_err());
+
+    // message size out of bounds
+    let mut ix = get_ed25519_ix(&pubkey, &message);
+    ix.data[12..14].copy_from_slice(&u16::MAX.to_le_bytes());
+    assert!(verify_ed25519_ix(&ix, &pubkey, &message).is_err());
+
+    let ix = get_ed25519_ix(&pubkey, &message);
+    let ix = Instruction {
+        data: ix.data[..10].to_vec(),
+        ..ix
+    };
+    assert!(verify_ed25519_ix(&ix, &pubkey, &message).is_err());
+}
@@ -1,5 +1,6 @@
 use crate::error::{DriftResult, ErrorCode};
 use crate::math::constants::SPOT_UTILIZATION_PRECISION_U32;
+use crate::state::spot_market::BorrowRateKink;
 use crate::validate;
 use solana_program::msg;
 
@@ -7,6 +8,7 @@ pub fn validate_borrow_rate(
     optimal_utilization: u32,
     optimal_borrow_rate: u32,
     max_borrow_rate: u32,
+    borrow_rate_kinks: &[BorrowRateKink],
 ) -> DriftResult {
     validate!(
         optimal_utilization <= SPOT_UTILIZATION_PRECISION_U32,
@@ -23,5 +25,48 @@ pub fn validate_borrow_rate(
         max_borrow_rate
     )?;
 
+    let mut last_utilization = optimal_utilization;
+    let mut last_borrow_rate = optimal_borrow_rate;
+    let mut found_inactive_kink = false;
+    for kink in borrow_rate_kinks.iter() {
+        if !kink.is_active() {
+            validate!(
+                kink.borrow_rate == 0,
+                ErrorCode::InvalidSpotMarketInitialization,
+                "For spot market, unused borrow rate kink must have zero borrow rate"
+            )?;
+            found_inactive_kink = true;
+            continue;
+        }
+
+        validate!(
+            !found_inactive_kink,
+            ErrorCode::InvalidSpotMarketInitialization,
+            "For spot market, borrow rate kinks must be set before unused kinks"
+        )?;
+
+        validate!(
+            kink.utilization > last_utilization
+                && kink.utilization < SPOT_UTILIZATION_PRECISION_U32,
+            ErrorCode::InvalidSpotMarketInitialization,
+            "For spot market, borrow rate kink utilization ({}) must be between {} and {}",
+            kink.utilization,
+            last_utilization,
+            SPOT_UTILIZATION_PRECISION_U32
+        )?;
+
+        validate!(
+            kink.borrow_rate >= last_borrow_rate && kink.borrow_rate <= max_borrow_rate,
+            ErrorCode::InvalidSpotMarketInitialization,
+            "For spot market, borrow rate kink rate ({}) must be between {} and {}",
+            kink.borrow_rate,
+            last_borrow_rate,
+            max_borrow_rate
+        )?;
+
+        last_utilization = kink.utilization;
+        last_borrow_rate = kink.borrow_rate;
+    }
+
     Ok(())
 }
@@ -0,0 +1,128 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use solana_program::instruction::Instruction;
+
+use crate::error::{DriftResult, ErrorCode};
+use crate::instruction::{BeginSwap, EndSwap};
+use crate::validate;
+
+#[cfg(test)]
+mod tests;
+
+enum SwapIx {
+    Begin(BeginSwap),
+    End(EndSwap),
+    Other,
+}
+
+fn parse_swap_ix(ix: &Instruction) -> DriftResult<Option<SwapIx>> {
+    if ix.program_id != crate::id() {
+        return Ok(None);
+    }
+
+    if ix.data.len() < 8 {
+        return Ok(Some(SwapIx::Other));
+    }
+
+    let (discriminator, mut args) = ix.data.split_at(8);
+    let swap_ix = if discriminator == BeginSwap::discriminator() {
+        SwapIx::Begin(BeginSwap::deserialize(&mut args).map_err(|_| ErrorCode::InvalidSwap)?)
+    } else if discriminator == EndSwap::discriminator() {
+        SwapIx::End(EndSwap::deserialize(&mut args).map_err(|_| ErrorCode::InvalidSwap)?)
+    } else {
+        SwapIx::Other
+    };
+
+    Ok(Some(swap_ix))
+}
+
+/// Checks that the begin at `current_index` is a top level instruction and that the next drift
+/// instruction in the transaction is an end_swap for the same markets.
+/// No other drift instruction can run while the user is between begin and end
+pub fn validate_begin_swap(
+    ixs: &[Instruction],
+    current_index: usize,
+    in_market_index: u16,
+    out_market_index: u16,
+) -> DriftResult {
+    validate!(
+        matches!(
+            ixs.get(current_index).map(parse_swap_ix).transpose()?,
+            Some(Some(SwapIx::Begin(_)))
+        ),
+        ErrorCode::InvalidSwap,
+        "begin_swap can not be called through cpi"
+    )?;
+
+    for ix in ixs.iter().skip(current_index + 1) {
+        match parse_swap_ix(ix)? {
+            Some(SwapIx::End(end_swap)) => {
+                validate!(
+                    end_swap.in_market_index == in_market_index
+                        && end_swap.out_market_index == out_market_index,
+                    ErrorCode::InvalidSwap,
+                    "end_swap (in {} out {}) does not match begin_swap (in {} out {})",
+                    end_swap.in_market_index,
+                    end_swap.out_market_index,
+                    in_market_index,
+                    out_market_index
+                )?;
+
+                return Ok(());
+            }
+            Some(_) => {
+                msg!("only end_swap can be the next drift instruction after begin_swap");
+                return Err(ErrorCode::InvalidSwap);
+            }
+            None => {}
+        }
+    }
+
+    msg!("begin_swap must be followed by end_swap");
+    Err(ErrorCode::InvalidSwap)
+}
+
+/// Checks that the end at `current_index` is a top level instruction and that the previous drift
+/// instruction in the transaction is a begin_swap for the same markets. Returns the begin amount_in
+pub fn validate_end_swap(
+    ixs: &[Instruction],
+    current_index: usize,
+    in_market_index: u16,
+    out_market_index: u16,
+) -> DriftResult<u64> {
+    validate!(
+        matches!(
+            ixs.get(current_index).map(parse_swap_ix).transpose()?,
+            Some(Some(SwapIx::End(_)))
+        ),
+        ErrorCode::InvalidSwap,
+        "end_swap can not be called through cpi"
+    )?;
+
+    for ix in ixs.iter().take(current_index).rev() {
+        match parse_swap_ix(ix)? {
+            Some(SwapIx::Begin(begin_swap)) => {
+                validate!(
+                    begin_swap.in_market_index == in_market_index
+                        && begin_swap.out_market_index == out_market_index,
+                    ErrorCode::InvalidSwap,
+                    "begin_swap (in {} out {}) does not match end_swap (in {} out {})",
+                    begin_swap.in_market_index,
+                    begin_swap.out_market_index,
+                    in_market_index,
+                    out_market_index
+                )?;
+
+                return Ok(begin_swap.amount_in);
+            }
+            Some(_) => {
+                msg!("only begin_swap can be the previous drift instruction before end_swap");
+                return Err(ErrorCode::InvalidSwap);
+            }
+            None => {}
+        }
+    }
+
+    msg!("end_swap must follow begin_swap");
+    Err(ErrorCode::InvalidSwap)
+}
@@ -0,0 +1,94 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::InstructionData;
+use solana_program::instruction::Instruction;
+
+use crate::instruction::{BeginSwap, CancelOrder, EndSwap};
+use crate::validation::swap::{validate_begin_swap, validate_end_swap};
+
+fn drift_ix(data: Vec<u8>) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![],
+        data,
+    }
+}
+
+fn begin_ix(in_market_index: u16, out_market_index: u16, amount_in: u64) -> Instruction {
+    drift_ix(
+        BeginSwap {
+            in_market_index,
+            out_market_index,
+            amount_in,
+        }
+        .data(),
+    )
+}
+
+fn end_ix(in_market_index: u16, out_market_index: u16) -> Instruction {
+    drift_ix(
+        EndSwap {
+            in_market_index,
+            out_market_index,
+        }
+        .data(),
+    )
+}
+
+fn router_ix() -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_unique(),
+        accounts: vec![],
+        data: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn valid_swap() {
+    let ixs = vec![begin_ix(0, 1, 100), router_ix(), router_ix(), end_ix(0, 1)];
+
+    assert!(validate_begin_swap(&ixs, 0, 0, 1).is_ok());
+    assert_eq!(validate_end_swap(&ixs, 3, 0, 1).unwrap(), 100);
+}
+
+#[test]
+fn missing_end() {
+    let ixs = vec![begin_ix(0, 1, 100), router_ix()];
+
+    assert!(validate_begin_swap(&ixs, 0, 0, 1).is_err());
+}
+
+#[test]
+fn mismatched_markets() {
+    let ixs = vec![begin_ix(0, 1, 100), router_ix(), end_ix(1, 0)];
+
+    assert!(validate_begin_swap(&ixs, 0, 0, 1).is_err());
+    assert!(validate_end_swap(&ixs, 2, 1, 0).is_err());
+}
+
+#[test]
+fn drift_ix_between_begin_and_end() {
+    let ixs = vec![
+        begin_ix(0, 1, 100),
+        drift_ix(CancelOrder { order_id: None }.data()),
+        end_ix(0, 1),
+    ];
+
+    assert!(validate_begin_swap(&ixs, 0, 0, 1).is_err());
+    assert!(validate_end_swap(&ixs, 2, 0, 1).is_err());
+}
+
+#[test]
+fn end_reused() {
+    let ixs = vec![begin_ix(0, 1, 100), end_ix(0, 1), end_ix(0, 1)];
+
+    assert_eq!(validate_end_swap(&ixs, 1, 0, 1).unwrap(), 100);
+    assert!(validate_end_swap(&ixs, 2, 0, 1).is_err());
+}
+
+#[test]
+fn called_through_cpi() {
+    let ixs = vec![router_ix(), end_ix(0, 1)];
+
+    assert!(validate_begin_swap(&ixs, 0, 0, 1).is_err());
+    assert!(validate_end_swap(&ixs, 0, 0, 1).is_err());
+}
@@ -4,6 +4,21 @@ use crate::state::user::{OrderStatus, User, UserStats};
 use crate::validate;
 use solana_program::msg;
 
+pub fn validate_trading_not_disabled(
+    user_stats: &UserStats,
+    reduce_only: bool,
+    now: i64,
+) -> DriftResult {
+    validate!(
+        reduce_only || !user_stats.is_trading_disabled(now),
+        ErrorCode::UserTradingDisabled,
+        "trading disabled until {}, only reduce only orders allowed",
+        user_stats.disable_trading_until
+    )?;
+
+    Ok(())
+}
+
 pub fn validate_user_deletion(user: &User, user_stats: &UserStats) -> DriftResult {
     validate!(
         !user_stats.is_referrer || user.sub_account_id != 0,
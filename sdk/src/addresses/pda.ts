@@ -1,6 +1,7 @@
 import { PublicKey } from '@solana/web3.js';
 import * as anchor from '@project-serum/anchor';
 import { BN } from '@project-serum/anchor';
+import { AdminChange, isVariant, MarketType } from '../types';
 
 export async function getDriftStateAccountPublicKeyAndNonce(
 	programId: PublicKey
@@ -189,6 +190,420 @@ export function getSerumFulfillmentConfigPublicKey(
 	)[0];
 }
 
+export function getOpenbookV2FulfillmentConfigPublicKey(
+	programId: PublicKey,
+	market: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(
+				anchor.utils.bytes.utf8.encode('openbook_v2_fulfillment_config')
+			),
+			market.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getPhoenixFulfillmentConfigPublicKey(
+	programId: PublicKey,
+	market: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('phoenix_fulfillment_config')),
+			market.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getPrelaunchOraclePublicKey(
+	programId: PublicKey,
+	marketIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('prelaunch_oracle')),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getLstOraclePublicKey(
+	programId: PublicKey,
+	stakePool: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('lst_oracle')),
+			stakePool.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getAdminRolesPublicKey(programId: PublicKey): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[Buffer.from(anchor.utils.bytes.utf8.encode('admin_roles'))],
+		programId
+	)[0];
+}
+
+export function getHighLeverageModeConfigPublicKey(
+	programId: PublicKey,
+	marketIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(
+				anchor.utils.bytes.utf8.encode('high_leverage_mode_config')
+			),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getBackstopProviderPublicKey(
+	programId: PublicKey,
+	userAccountPublicKey: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('backstop_provider')),
+			userAccountPublicKey.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getInsuranceFundShareMintPublicKey(
+	programId: PublicKey,
+	marketIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('insurance_fund_share_mint')),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getWrappedInsuranceFundStakePublicKey(
+	programId: PublicKey,
+	marketIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(
+				anchor.utils.bytes.utf8.encode('wrapped_insurance_fund_stake')
+			),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getSignedOrderNoncePublicKeySync(
+	programId: PublicKey,
+	userAccountPublicKey: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('signed_order_nonce')),
+			userAccountPublicKey.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getPerpLpRangePublicKeySync(
+	programId: PublicKey,
+	userAccountPublicKey: PublicKey,
+	marketIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('perp_lp_range')),
+			userAccountPublicKey.toBuffer(),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getBuilderPublicKeySync(
+	programId: PublicKey,
+	authority: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('builder')),
+			authority.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getGovernanceStakePublicKeySync(
+	programId: PublicKey,
+	authority: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('governance_stake')),
+			authority.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getGovernanceStakeVaultPublicKey(
+	programId: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[Buffer.from(anchor.utils.bytes.utf8.encode('governance_stake_vault'))],
+		programId
+	)[0];
+}
+
+export function getUserFuelPublicKeySync(
+	programId: PublicKey,
+	authority: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('user_fuel')),
+			authority.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getFuelBoostPublicKey(
+	programId: PublicKey,
+	marketType: MarketType,
+	marketIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('fuel_boost')),
+			Buffer.from([isVariant(marketType, 'perp') ? 1 : 0]),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getMarketFeeOverridePublicKey(
+	programId: PublicKey,
+	marketType: MarketType,
+	marketIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('market_fee_override')),
+			Buffer.from([isVariant(marketType, 'perp') ? 1 : 0]),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getMarketLookupTablePublicKey(
+	programId: PublicKey,
+	tableIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('market_lookup_table')),
+			new anchor.BN(tableIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getMarketWhitelistEntryPublicKey(
+	programId: PublicKey,
+	marketIndex: number,
+	authority: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('market_whitelist_entry')),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+			authority.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getPerpMarketDelistingPublicKey(
+	programId: PublicKey,
+	marketIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('perp_market_delisting')),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getPerpMarketListingConfigPublicKey(
+	programId: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[Buffer.from(anchor.utils.bytes.utf8.encode('perp_market_listing_config'))],
+		programId
+	)[0];
+}
+
+export function getPnlPoolRebalanceConfigPublicKey(
+	programId: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[Buffer.from(anchor.utils.bytes.utf8.encode('pnl_pool_rebalance_config'))],
+		programId
+	)[0];
+}
+
+export function getFillCallbackProgramPublicKey(
+	programId: PublicKey,
+	callbackProgramId: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('fill_callback_program')),
+			callbackProgramId.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getUserFillCallbackPublicKeySync(
+	programId: PublicKey,
+	userAccountPublicKey: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('user_fill_callback')),
+			userAccountPublicKey.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getVaultPublicKey(
+	programId: PublicKey,
+	name: number[]
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('vault')),
+			Buffer.from(name),
+		],
+		programId
+	)[0];
+}
+
+export function getVaultDepositorPublicKey(
+	programId: PublicKey,
+	vault: PublicKey,
+	authority: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('vault_depositor')),
+			vault.toBuffer(),
+			authority.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getPerpMarketListingPublicKey(
+	programId: PublicKey,
+	marketIndex: number
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('perp_market_listing')),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+// order matches the AdminChange variants on chain
+const ADMIN_CHANGE_KINDS = [
+	'perpMarketMarginRatio',
+	'spotMarketMarginWeights',
+	'perpMarketOracle',
+	'spotMarketOracle',
+	'perpFeeStructure',
+	'spotFeeStructure',
+	'adminTimelockDuration',
+	'oracleGuardRails',
+	'perpMarketImfFactor',
+	'perpMarketLiquidationFee',
+	'spotMarketLiquidationFee',
+	'spotMarketAssetTier',
+	'perpMarketContractTier',
+	'spotMarketBorrowRate',
+	'spotMarketBorrowRateKinks',
+	'perpMarketFundingRateCaps',
+	'perpMarketMaxOpenInterest',
+];
+
+export function getPendingAdminChangePublicKey(
+	programId: PublicKey,
+	change: AdminChange
+): PublicKey {
+	const kind = Object.keys(change)[0];
+	const marketIndex = change[kind].marketIndex ?? 0;
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('pending_admin_change')),
+			Buffer.from([ADMIN_CHANGE_KINDS.indexOf(kind)]),
+			new anchor.BN(marketIndex).toArrayLike(Buffer, 'le', 2),
+		],
+		programId
+	)[0];
+}
+
+export function getDeadManSwitchPublicKeySync(
+	programId: PublicKey,
+	userAccountPublicKey: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('dead_man_switch')),
+			userAccountPublicKey.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
+export function getSessionKeyPublicKeySync(
+	programId: PublicKey,
+	userAccountPublicKey: PublicKey,
+	sessionSigner: PublicKey
+): PublicKey {
+	return PublicKey.findProgramAddressSync(
+		[
+			Buffer.from(anchor.utils.bytes.utf8.encode('session_key')),
+			userAccountPublicKey.toBuffer(),
+			sessionSigner.toBuffer(),
+		],
+		programId
+	)[0];
+}
+
 export function getReferrerNamePublicKeySync(
 	programId: PublicKey,
 	nameBuffer: number[]
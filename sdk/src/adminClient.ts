@@ -1,4 +1,5 @@
 import {
+	AddressLookupTableProgram,
 	PublicKey,
 	SYSVAR_RENT_PUBKEY,
 	TransactionSignature,
@@ -10,8 +11,15 @@ import {
 	ExchangeStatus,
 	MarketStatus,
 	ContractTier,
+	ContractType,
 	AssetTier,
 	SpotFulfillmentConfigStatus,
+	ValidityGuardRailsOverride,
+	MarginTier,
+	BorrowRateKink,
+	MarketType,
+	AdminChange,
+	AdminRole,
 } from './types';
 import { DEFAULT_MARKET_NAME, encodeName } from './userName';
 import { BN } from '@project-serum/anchor';
@@ -24,6 +32,27 @@ import {
 	getInsuranceFundVaultPublicKey,
 	getSerumOpenOrdersPublicKey,
 	getSerumFulfillmentConfigPublicKey,
+	getOpenbookV2FulfillmentConfigPublicKey,
+	getPhoenixFulfillmentConfigPublicKey,
+	getPrelaunchOraclePublicKey,
+	getLstOraclePublicKey,
+	getHighLeverageModeConfigPublicKey,
+	getBackstopProviderPublicKey,
+	getInsuranceFundShareMintPublicKey,
+	getWrappedInsuranceFundStakePublicKey,
+	getGovernanceStakeVaultPublicKey,
+	getFuelBoostPublicKey,
+	getMarketFeeOverridePublicKey,
+	getMarketLookupTablePublicKey,
+	getMarketWhitelistEntryPublicKey,
+	getFillCallbackProgramPublicKey,
+	getPerpMarketDelistingPublicKey,
+	getPerpMarketListingConfigPublicKey,
+	getPerpMarketListingPublicKey,
+	getPnlPoolRebalanceConfigPublicKey,
+	getUserStatsAccountPublicKey,
+	getPendingAdminChangePublicKey,
+	getAdminRolesPublicKey,
 } from './addresses/pda';
 import { squareRootBN } from './math/utils';
 import { TOKEN_PROGRAM_ID } from '@solana/spl-token';
@@ -32,6 +61,25 @@ import { PEG_PRECISION } from './constants/numericConstants';
 import { calculateTargetPriceTrade } from './math/trade';
 import { calculateAmmReservesAfterSwap, getSwapDirection } from './math/amm';
 
+const PERP_MARKET_ADMIN_CHANGE_KINDS = [
+	'perpMarketMarginRatio',
+	'perpMarketOracle',
+	'perpMarketImfFactor',
+	'perpMarketLiquidationFee',
+	'perpMarketContractTier',
+	'perpMarketFundingRateCaps',
+	'perpMarketMaxOpenInterest',
+];
+
+const SPOT_MARKET_ADMIN_CHANGE_KINDS = [
+	'spotMarketMarginWeights',
+	'spotMarketOracle',
+	'spotMarketLiquidationFee',
+	'spotMarketAssetTier',
+	'spotMarketBorrowRate',
+	'spotMarketBorrowRateKinks',
+];
+
 export class AdminClient extends DriftClient {
 	public async initialize(
 		usdcMint: PublicKey,
@@ -83,7 +131,8 @@ export class AdminClient extends DriftClient {
 		imfFactor = 0,
 		liquidatorFee = 0,
 		activeStatus = true,
-		name = DEFAULT_MARKET_NAME
+		name = DEFAULT_MARKET_NAME,
+		tokenProgram = TOKEN_PROGRAM_ID
 	): Promise<TransactionSignature> {
 		const spotMarketIndex = this.getStateAccount().numberOfSpotMarkets;
 		const spotMarket = await getSpotMarketPublicKey(
@@ -119,6 +168,7 @@ export class AdminClient extends DriftClient {
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
+					adminRoles: getAdminRolesPublicKey(this.program.programId),
 					spotMarket,
 					spotMarketVault,
 					insuranceFundVault,
@@ -127,7 +177,7 @@ export class AdminClient extends DriftClient {
 					oracle,
 					rent: SYSVAR_RENT_PUBKEY,
 					systemProgram: anchor.web3.SystemProgram.programId,
-					tokenProgram: TOKEN_PROGRAM_ID,
+					tokenProgram,
 				},
 			}
 		);
@@ -181,92 +231,89 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async initializePerpMarket(
-		priceOracle: PublicKey,
-		baseAssetReserve: BN,
-		quoteAssetReserve: BN,
-		periodicity: BN,
-		pegMultiplier: BN = PEG_PRECISION,
-		oracleSource: OracleSource = OracleSource.PYTH,
-		marginRatioInitial = 2000,
-		marginRatioMaintenance = 500,
-		liquidatorFee = 0,
-		activeStatus = true,
-		name = DEFAULT_MARKET_NAME
+	public async initializeOpenbookV2FulfillmentConfig(
+		marketIndex: number,
+		openbookV2Market: PublicKey,
+		openbookV2Program: PublicKey
 	): Promise<TransactionSignature> {
-		const currentPerpMarketIndex = this.getStateAccount().numberOfMarkets;
-		const perpMarketPublicKey = await getPerpMarketPublicKey(
+		const openbookV2FulfillmentConfig = getOpenbookV2FulfillmentConfigPublicKey(
 			this.program.programId,
-			currentPerpMarketIndex
+			openbookV2Market
 		);
 
-		const nameBuffer = encodeName(name);
-		const initializeMarketTx =
-			await this.program.transaction.initializePerpMarket(
-				baseAssetReserve,
-				quoteAssetReserve,
-				periodicity,
-				pegMultiplier,
-				oracleSource,
-				marginRatioInitial,
-				marginRatioMaintenance,
-				liquidatorFee,
-				activeStatus,
-				nameBuffer,
+		const tx =
+			await this.program.transaction.initializeOpenbookV2FulfillmentConfig(
+				marketIndex,
 				{
 					accounts: {
-						state: await this.getStatePublicKey(),
 						admin: this.wallet.publicKey,
-						oracle: priceOracle,
-						perpMarket: perpMarketPublicKey,
+						state: await this.getStatePublicKey(),
+						baseSpotMarket: this.getSpotMarketAccount(marketIndex).pubkey,
+						quoteSpotMarket: this.getQuoteSpotMarketAccount().pubkey,
+						driftSigner: this.getSignerPublicKey(),
+						openbookV2Program,
+						openbookV2Market,
 						rent: SYSVAR_RENT_PUBKEY,
 						systemProgram: anchor.web3.SystemProgram.programId,
+						openbookV2FulfillmentConfig,
 					},
 				}
 			);
-		const { txSig } = await this.sendTransaction(
-			initializeMarketTx,
-			[],
-			this.opts
-		);
-
-		while (this.getStateAccount().numberOfMarkets <= currentPerpMarketIndex) {
-			await this.fetchAccounts();
-		}
-
-		await this.accountSubscriber.addPerpMarket(currentPerpMarketIndex);
-		await this.accountSubscriber.addOracle({
-			source: oracleSource,
-			publicKey: priceOracle,
-		});
 
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
 		return txSig;
 	}
 
-	public async moveAmmPrice(
-		perpMarketIndex: number,
-		baseAssetReserve: BN,
-		quoteAssetReserve: BN,
-		sqrtK?: BN
+	public async initializePhoenixFulfillmentConfig(
+		marketIndex: number,
+		phoenixMarket: PublicKey,
+		phoenixProgram: PublicKey
 	): Promise<TransactionSignature> {
-		const marketPublicKey = await getPerpMarketPublicKey(
+		const phoenixFulfillmentConfig = getPhoenixFulfillmentConfigPublicKey(
 			this.program.programId,
-			perpMarketIndex
+			phoenixMarket
 		);
 
-		if (sqrtK == undefined) {
-			sqrtK = squareRootBN(baseAssetReserve.mul(quoteAssetReserve));
-		}
+		const tx =
+			await this.program.transaction.initializePhoenixFulfillmentConfig(
+				marketIndex,
+				{
+					accounts: {
+						admin: this.wallet.publicKey,
+						state: await this.getStatePublicKey(),
+						baseSpotMarket: this.getSpotMarketAccount(marketIndex).pubkey,
+						quoteSpotMarket: this.getQuoteSpotMarketAccount().pubkey,
+						driftSigner: this.getSignerPublicKey(),
+						phoenixProgram,
+						phoenixMarket,
+						rent: SYSVAR_RENT_PUBKEY,
+						systemProgram: anchor.web3.SystemProgram.programId,
+						phoenixFulfillmentConfig,
+					},
+				}
+			);
 
-		const tx = await this.program.transaction.moveAmmPrice(
-			baseAssetReserve,
-			quoteAssetReserve,
-			sqrtK,
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async initializeHighLeverageModeConfig(
+		marketIndex: number,
+		maxUsers: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeHighLeverageModeConfig(
+			marketIndex,
+			maxUsers,
 			{
 				accounts: {
-					state: await this.getStatePublicKey(),
 					admin: this.wallet.publicKey,
-					perpMarket: marketPublicKey,
+					highLeverageModeConfig: getHighLeverageModeConfigPublicKey(
+						this.program.programId,
+						marketIndex
+					),
+					state: await this.getStatePublicKey(),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
 				},
 			}
 		);
@@ -275,81 +322,73 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateK(
-		perpMarketIndex: number,
-		sqrtK: BN
+	public async updateHighLeverageModeConfig(
+		marketIndex: number,
+		maxUsers: number
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updateK(sqrtK, {
+		return await this.program.rpc.updateHighLeverageModeConfig(maxUsers, {
 			accounts: {
-				state: await this.getStatePublicKey(),
 				admin: this.wallet.publicKey,
-				perpMarket: await getPerpMarketPublicKey(
+				highLeverageModeConfig: getHighLeverageModeConfigPublicKey(
 					this.program.programId,
-					perpMarketIndex
+					marketIndex
 				),
-				oracle: this.getPerpMarketAccount(perpMarketIndex).amm.oracle,
+				state: await this.getStatePublicKey(),
 			},
 		});
-
-		const { txSig } = await this.sendTransaction(tx, [], this.opts);
-		return txSig;
 	}
 
-	public async updatePerpMarketConcentrationScale(
-		perpMarketIndex: number,
-		concentrationScale: BN
+	public async initializeBackstopProvider(
+		userAccountPublicKey: PublicKey,
+		committedCollateral: BN,
+		liquidationFeeBonus: number
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updatePerpMarketConcentrationCoef(
-			concentrationScale,
+		const tx = await this.program.transaction.initializeBackstopProvider(
+			committedCollateral,
+			liquidationFeeBonus,
 			{
 				accounts: {
-					state: await this.getStatePublicKey(),
 					admin: this.wallet.publicKey,
-					perpMarket: await getPerpMarketPublicKey(
+					backstopProvider: getBackstopProviderPublicKey(
 						this.program.programId,
-						perpMarketIndex
+						userAccountPublicKey
 					),
+					user: userAccountPublicKey,
+					state: await this.getStatePublicKey(),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
 				},
 			}
 		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async moveAmmToPrice(
-		perpMarketIndex: number,
-		targetPrice: BN
+	public async initializeInsuranceFundShareMint(
+		marketIndex: number
 	): Promise<TransactionSignature> {
-		const perpMarket = this.getPerpMarketAccount(perpMarketIndex);
-
-		const [direction, tradeSize, _] = calculateTargetPriceTrade(
-			perpMarket,
-			targetPrice,
-			new BN(1000),
-			'quote',
-			undefined //todo
-		);
-
-		const [newQuoteAssetAmount, newBaseAssetAmount] =
-			calculateAmmReservesAfterSwap(
-				perpMarket.amm,
-				'quote',
-				tradeSize,
-				getSwapDirection('quote', direction)
-			);
-
-		const perpMarketPublicKey = await getPerpMarketPublicKey(
-			this.program.programId,
-			perpMarketIndex
-		);
-
-		const tx = await this.program.transaction.moveAmmPrice(
-			newBaseAssetAmount,
-			newQuoteAssetAmount,
-			perpMarket.amm.sqrtK,
+		const spotMarket = this.getSpotMarketAccount(marketIndex);
+		const tx = await this.program.transaction.initializeInsuranceFundShareMint(
+			marketIndex,
 			{
 				accounts: {
-					state: await this.getStatePublicKey(),
 					admin: this.wallet.publicKey,
-					perpMarket: perpMarketPublicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: spotMarket.pubkey,
+					spotMarketMint: spotMarket.mint,
+					insuranceFundShareMint: getInsuranceFundShareMintPublicKey(
+						this.program.programId,
+						marketIndex
+					),
+					wrappedInsuranceFundStake: getWrappedInsuranceFundStakePublicKey(
+						this.program.programId,
+						marketIndex
+					),
+					driftSigner: this.getSignerPublicKey(),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+					tokenProgram: TOKEN_PROGRAM_ID,
 				},
 			}
 		);
@@ -358,88 +397,106 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async repegAmmCurve(
-		newPeg: BN,
-		perpMarketIndex: number
-	): Promise<TransactionSignature> {
-		const perpMarketPublicKey = await getPerpMarketPublicKey(
-			this.program.programId,
-			perpMarketIndex
-		);
-		const ammData = this.getPerpMarketAccount(perpMarketIndex).amm;
-
-		const tx = await this.program.transaction.repegAmmCurve(newPeg, {
+	public async initializeGovernanceStakeVault(): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeGovernanceStakeVault({
 			accounts: {
-				state: await this.getStatePublicKey(),
 				admin: this.wallet.publicKey,
-				oracle: ammData.oracle,
-				perpMarket: perpMarketPublicKey,
+				state: await this.getStatePublicKey(),
+				governanceTokenMint: this.getStateAccount().discountMint,
+				governanceStakeVault: getGovernanceStakeVaultPublicKey(
+					this.program.programId
+				),
+				driftSigner: this.getSignerPublicKey(),
+				rent: SYSVAR_RENT_PUBKEY,
+				systemProgram: anchor.web3.SystemProgram.programId,
+				tokenProgram: TOKEN_PROGRAM_ID,
 			},
 		});
+
 		const { txSig } = await this.sendTransaction(tx, [], this.opts);
 		return txSig;
 	}
 
-	public async updatePerpMarketAmmOracleTwap(
-		perpMarketIndex: number
+	public async initializeFuelBoost(
+		marketType: MarketType,
+		marketIndex: number
 	): Promise<TransactionSignature> {
-		const ammData = this.getPerpMarketAccount(perpMarketIndex).amm;
-		const perpMarketPublicKey = await getPerpMarketPublicKey(
-			this.program.programId,
-			perpMarketIndex
+		const tx = await this.program.transaction.initializeFuelBoost(
+			marketType,
+			marketIndex,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					fuelBoost: getFuelBoostPublicKey(
+						this.program.programId,
+						marketType,
+						marketIndex
+					),
+					state: await this.getStatePublicKey(),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
 		);
 
-		return await this.program.rpc.updatePerpMarketAmmOracleTwap({
-			accounts: {
-				state: await this.getStatePublicKey(),
-				admin: this.wallet.publicKey,
-				oracle: ammData.oracle,
-				perpMarket: perpMarketPublicKey,
-			},
-		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async resetPerpMarketAmmOracleTwap(
-		perpMarketIndex: number
+	public async updateFuelBoost(
+		marketType: MarketType,
+		marketIndex: number,
+		taker: number,
+		maker: number,
+		borrows: number,
+		insurance: number,
+		startTs: BN,
+		endTs: BN
 	): Promise<TransactionSignature> {
-		const ammData = this.getPerpMarketAccount(perpMarketIndex).amm;
-		const perpMarketPublicKey = await getPerpMarketPublicKey(
-			this.program.programId,
-			perpMarketIndex
+		const tx = await this.program.transaction.updateFuelBoost(
+			taker,
+			maker,
+			borrows,
+			insurance,
+			startTs,
+			endTs,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					fuelBoost: getFuelBoostPublicKey(
+						this.program.programId,
+						marketType,
+						marketIndex
+					),
+					state: await this.getStatePublicKey(),
+				},
+			}
 		);
 
-		return await this.program.rpc.resetPerpMarketAmmOracleTwap({
-			accounts: {
-				state: await this.getStatePublicKey(),
-				admin: this.wallet.publicKey,
-				oracle: ammData.oracle,
-				perpMarket: perpMarketPublicKey,
-			},
-		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async depositIntoPerpMarketFeePool(
-		perpMarketIndex: number,
-		amount: BN,
-		sourceVault: PublicKey
+	public async initializePerpMarketFeeOverride(
+		perpMarketIndex: number
 	): Promise<TransactionSignature> {
-		const spotMarket = this.getQuoteSpotMarketAccount();
-
-		const tx = await this.program.transaction.depositIntoPerpMarketFeePool(
-			amount,
+		const tx = await this.program.transaction.initializePerpMarketFeeOverride(
+			perpMarketIndex,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
-					state: await this.getStatePublicKey(),
+					marketFeeOverride: getMarketFeeOverridePublicKey(
+						this.program.programId,
+						MarketType.PERP,
+						perpMarketIndex
+					),
 					perpMarket: await getPerpMarketPublicKey(
 						this.program.programId,
 						perpMarketIndex
 					),
-					sourceVault,
-					driftSigner: this.getSignerPublicKey(),
-					quoteSpotMarket: spotMarket.pubkey,
-					spotMarketVault: spotMarket.vault,
-					tokenProgram: TOKEN_PROGRAM_ID,
+					state: await this.getStatePublicKey(),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
 				},
 			}
 		);
@@ -448,56 +505,100 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateAdmin(admin: PublicKey): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updateAdmin(admin, {
-			accounts: {
-				admin: this.wallet.publicKey,
-				state: await this.getStatePublicKey(),
-			},
-		});
+	public async initializeSpotMarketFeeOverride(
+		spotMarketIndex: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeSpotMarketFeeOverride(
+			spotMarketIndex,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					marketFeeOverride: getMarketFeeOverridePublicKey(
+						this.program.programId,
+						MarketType.SPOT,
+						spotMarketIndex
+					),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+					state: await this.getStatePublicKey(),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
+		);
 
 		const { txSig } = await this.sendTransaction(tx, [], this.opts);
 		return txSig;
 	}
 
-	public async updatePerpMarketCurveUpdateIntensity(
-		perpMarketIndex: number,
-		curveUpdateIntensity: number
+	public async updateMarketFeeOverride(
+		marketType: MarketType,
+		marketIndex: number,
+		takerFeeAdjustment: number,
+		makerRebateAdjustment: number,
+		promoTakerFeeAdjustment: number,
+		promoMakerRebateAdjustment: number,
+		promoStartTs: BN,
+		promoEndTs: BN
 	): Promise<TransactionSignature> {
-		// assert(curveUpdateIntensity >= 0 && curveUpdateIntensity <= 100);
-		// assert(Number.isInteger(curveUpdateIntensity));
-
-		return await this.program.rpc.updatePerpMarketCurveUpdateIntensity(
-			curveUpdateIntensity,
+		const tx = await this.program.transaction.updateMarketFeeOverride(
+			takerFeeAdjustment,
+			makerRebateAdjustment,
+			promoTakerFeeAdjustment,
+			promoMakerRebateAdjustment,
+			promoStartTs,
+			promoEndTs,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
-					state: await this.getStatePublicKey(),
-					perpMarket: await getPerpMarketPublicKey(
+					marketFeeOverride: getMarketFeeOverridePublicKey(
 						this.program.programId,
-						perpMarketIndex
+						marketType,
+						marketIndex
 					),
+					state: await this.getStatePublicKey(),
 				},
 			}
 		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async updatePerpMarketMarginRatio(
-		perpMarketIndex: number,
-		marginRatioInitial: number,
-		marginRatioMaintenance: number
+	/**
+	 * Creates the canonical lookup table for tableIndex, owned by the drift signer
+	 * @param recentSlot a recent slot the lookup table address is derived from
+	 */
+	public async initializeMarketLookupTable(
+		tableIndex: number,
+		recentSlot: number
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updatePerpMarketMarginRatio(
-			marginRatioInitial,
-			marginRatioMaintenance,
+		const [lookupTable] = PublicKey.findProgramAddressSync(
+			[
+				this.getSignerPublicKey().toBuffer(),
+				new BN(recentSlot).toArrayLike(Buffer, 'le', 8),
+			],
+			AddressLookupTableProgram.programId
+		);
+
+		const tx = await this.program.transaction.initializeMarketLookupTable(
+			tableIndex,
+			new BN(recentSlot),
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
-					perpMarket: await getPerpMarketPublicKey(
+					marketLookupTable: getMarketLookupTablePublicKey(
 						this.program.programId,
-						perpMarketIndex
+						tableIndex
 					),
+					lookupTable,
+					driftSigner: this.getSignerPublicKey(),
+					addressLookupTableProgram: AddressLookupTableProgram.programId,
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
 				},
 			}
 		);
@@ -506,14 +607,12 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updatePerpMarketImfFactor(
+	public async updatePerpMarketWhitelistEnabled(
 		perpMarketIndex: number,
-		imfFactor: number,
-		unrealizedPnlImfFactor: number
+		whitelistEnabled: boolean
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updatePerpMarketImfFactor(
-			imfFactor,
-			unrealizedPnlImfFactor,
+		const tx = await this.program.transaction.updatePerpMarketWhitelistEnabled(
+			whitelistEnabled,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
@@ -525,22 +624,29 @@ export class AdminClient extends DriftClient {
 				},
 			}
 		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async updatePerpMarketBaseSpread(
+	public async initializeMarketWhitelistEntry(
 		perpMarketIndex: number,
-		baseSpread: number
+		authority: PublicKey
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updatePerpMarketBaseSpread(
-			baseSpread,
+		const tx = await this.program.transaction.initializeMarketWhitelistEntry(
+			perpMarketIndex,
+			authority,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
-					state: await this.getStatePublicKey(),
-					perpMarket: await getPerpMarketPublicKey(
+					marketWhitelistEntry: getMarketWhitelistEntryPublicKey(
 						this.program.programId,
-						perpMarketIndex
+						perpMarketIndex,
+						authority
 					),
+					state: await this.getStatePublicKey(),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
 				},
 			}
 		);
@@ -549,20 +655,41 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateAmmJitIntensity(
+	public async deleteMarketWhitelistEntry(
 		perpMarketIndex: number,
-		ammJitIntensity: number
+		authority: PublicKey
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updateAmmJitIntensity(
-			ammJitIntensity,
+		const tx = await this.program.transaction.deleteMarketWhitelistEntry({
+			accounts: {
+				admin: this.wallet.publicKey,
+				marketWhitelistEntry: getMarketWhitelistEntryPublicKey(
+					this.program.programId,
+					perpMarketIndex,
+					authority
+				),
+				state: await this.getStatePublicKey(),
+			},
+		});
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async initializeFillCallbackProgram(
+		callbackProgramId: PublicKey
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeFillCallbackProgram(
+			callbackProgramId,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
-					state: await this.getStatePublicKey(),
-					perpMarket: await getPerpMarketPublicKey(
+					fillCallbackProgram: getFillCallbackProgramPublicKey(
 						this.program.programId,
-						perpMarketIndex
+						callbackProgramId
 					),
+					state: await this.getStatePublicKey(),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
 				},
 			}
 		);
@@ -571,20 +698,17 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updatePerpMarketName(
-		perpMarketIndex: number,
-		name: string
+	public async deleteFillCallbackProgram(
+		callbackProgramId: PublicKey
 	): Promise<TransactionSignature> {
-		const nameBuffer = encodeName(name);
-
-		const tx = await this.program.transaction.updatePerpMarketName(nameBuffer, {
+		const tx = await this.program.transaction.deleteFillCallbackProgram({
 			accounts: {
 				admin: this.wallet.publicKey,
-				state: await this.getStatePublicKey(),
-				perpMarket: await getPerpMarketPublicKey(
+				fillCallbackProgram: getFillCallbackProgramPublicKey(
 					this.program.programId,
-					perpMarketIndex
+					callbackProgramId
 				),
+				state: await this.getStatePublicKey(),
 			},
 		});
 
@@ -592,49 +716,160 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateSpotMarketName(
-		spotMarketIndex: number,
-		name: string
+	public async updateReferrerRewardConfig(
+		referrerAuthority: PublicKey,
+		referrerRewardShare: number,
+		secondLevelReferrerRewardShare: number,
+		referrerRewardDecayMonths: number
 	): Promise<TransactionSignature> {
-		const nameBuffer = encodeName(name);
+		const tx = await this.program.transaction.updateReferrerRewardConfig(
+			referrerRewardShare,
+			secondLevelReferrerRewardShare,
+			referrerRewardDecayMonths,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					referrerStats: getUserStatsAccountPublicKey(
+						this.program.programId,
+						referrerAuthority
+					),
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
 
-		return await this.program.rpc.updateSpotMarketName(nameBuffer, {
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateAdminTimelockDuration(
+		adminTimelockDuration: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateAdminTimelockDuration(
+			adminTimelockDuration,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async queueAdminChange(
+		change: AdminChange
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.queueAdminChange(change, {
 			accounts: {
 				admin: this.wallet.publicKey,
-				state: await this.getStatePublicKey(),
-				spotMarket: await getSpotMarketPublicKey(
+				pendingAdminChange: getPendingAdminChangePublicKey(
 					this.program.programId,
-					spotMarketIndex
+					change
 				),
+				state: await this.getStatePublicKey(),
+				adminRoles: getAdminRolesPublicKey(this.program.programId),
+				rent: SYSVAR_RENT_PUBKEY,
+				systemProgram: anchor.web3.SystemProgram.programId,
 			},
 		});
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async updatePerpMarketMaxSpread(
-		perpMarketIndex: number,
-		maxSpread: number
+	/**
+	 * Executes a queued change once its timelock has passed. The change is needed to derive the pending account.
+	 */
+	public async executeAdminChange(
+		change: AdminChange
 	): Promise<TransactionSignature> {
-		const perpMarketPublicKey = await getPerpMarketPublicKey(
+		const pendingAdminChange = getPendingAdminChangePublicKey(
 			this.program.programId,
-			perpMarketIndex
+			change
 		);
+		const state = await this.getStatePublicKey();
+
+		const kind = Object.keys(change)[0];
+		let tx;
+		if (PERP_MARKET_ADMIN_CHANGE_KINDS.includes(kind)) {
+			const marketIndex: number = change[kind].marketIndex;
+			const oracle =
+				'perpMarketOracle' in change
+					? change.perpMarketOracle.oracle
+					: this.getPerpMarketAccount(marketIndex).amm.oracle;
+			tx = await this.program.transaction.executePerpMarketAdminChange({
+				accounts: {
+					admin: this.wallet.publicKey,
+					pendingAdminChange,
+					state,
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						marketIndex
+					),
+					oracle,
+				},
+			});
+		} else if (SPOT_MARKET_ADMIN_CHANGE_KINDS.includes(kind)) {
+			const marketIndex: number = change[kind].marketIndex;
+			const oracle =
+				'spotMarketOracle' in change
+					? change.spotMarketOracle.oracle
+					: this.getSpotMarketAccount(marketIndex).oracle;
+			tx = await this.program.transaction.executeSpotMarketAdminChange({
+				accounts: {
+					admin: this.wallet.publicKey,
+					pendingAdminChange,
+					state,
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						marketIndex
+					),
+					oracle,
+				},
+			});
+		} else {
+			tx = await this.program.transaction.executeStateAdminChange({
+				accounts: {
+					admin: this.wallet.publicKey,
+					pendingAdminChange,
+					state,
+				},
+			});
+		}
 
-		return await this.program.rpc.updatePerpMarketMaxSpread(maxSpread, {
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async cancelAdminChange(
+		change: AdminChange
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.cancelAdminChange({
 			accounts: {
 				admin: this.wallet.publicKey,
+				pendingAdminChange: getPendingAdminChangePublicKey(
+					this.program.programId,
+					change
+				),
 				state: await this.getStatePublicKey(),
-				perpMarket: perpMarketPublicKey,
 			},
 		});
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async updatePerpFeeStructure(
-		feeStructure: FeeStructure
-	): Promise<TransactionSignature> {
-		const tx = this.program.transaction.updatePerpFeeStructure(feeStructure, {
+	public async initializeAdminRoles(): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeAdminRoles({
 			accounts: {
 				admin: this.wallet.publicKey,
+				adminRoles: getAdminRolesPublicKey(this.program.programId),
 				state: await this.getStatePublicKey(),
+				rent: SYSVAR_RENT_PUBKEY,
+				systemProgram: anchor.web3.SystemProgram.programId,
 			},
 		});
 
@@ -642,60 +877,97 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateSpotFeeStructure(
-		feeStructure: FeeStructure
+	public async updateAdminRole(
+		role: AdminRole,
+		key: PublicKey
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updateSpotFeeStructure(
-			feeStructure,
-			{
-				accounts: {
-					admin: this.wallet.publicKey,
-					state: await this.getStatePublicKey(),
-				},
-			}
-		);
+		const tx = await this.program.transaction.updateAdminRole(role, key, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				adminRoles: getAdminRolesPublicKey(this.program.programId),
+				state: await this.getStatePublicKey(),
+			},
+		});
 
 		const { txSig } = await this.sendTransaction(tx, [], this.opts);
 		return txSig;
 	}
 
-	public async updateInitialPctToLiquidate(
-		initialPctToLiquidate: number
+	public async updateBackstopProvider(
+		userAccountPublicKey: PublicKey,
+		committedCollateral: BN,
+		liquidationFeeBonus: number,
+		approved: boolean
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updateInitialPctToLiquidate(
-			initialPctToLiquidate,
+		return await this.program.rpc.updateBackstopProvider(
+			committedCollateral,
+			liquidationFeeBonus,
+			approved,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
+					backstopProvider: getBackstopProviderPublicKey(
+						this.program.programId,
+						userAccountPublicKey
+					),
 					state: await this.getStatePublicKey(),
 				},
 			}
 		);
 	}
 
-	public async updateLiquidationDuration(
-		liquidationDuration: number
+	public async initializePrelaunchOracle(
+		perpMarketIndex: number,
+		price: BN,
+		maxPriceDriftPerSlot: BN,
+		confidence?: BN
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updateLiquidationDuration(
-			liquidationDuration,
+		const tx = await this.program.transaction.initializePrelaunchOracle(
+			{
+				perpMarketIndex,
+				price,
+				maxPriceDriftPerSlot,
+				confidence: confidence || null,
+			},
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
+					prelaunchOracle: getPrelaunchOraclePublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
 				},
 			}
 		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async updateOracleGuardRails(
-		oracleGuardRails: OracleGuardRails
+	public async updatePrelaunchOracleParams(
+		perpMarketIndex: number,
+		price?: BN,
+		maxPriceDriftPerSlot?: BN,
+		confidence?: BN
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updateOracleGuardRails(
-			oracleGuardRails,
+		const tx = await this.program.transaction.updatePrelaunchOracleParams(
+			{
+				perpMarketIndex,
+				price: price || null,
+				maxPriceDriftPerSlot: maxPriceDriftPerSlot || null,
+				confidence: confidence || null,
+			},
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
+					prelaunchOracle: getPrelaunchOraclePublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
 				},
 			}
 		);
@@ -704,33 +976,133 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateStateSettlementDuration(
-		settlementDuration: number
+	public async initializeLstOracle(
+		stakePool: PublicKey,
+		solOracle: PublicKey,
+		solOracleSource: OracleSource
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updateStateSettlementDuration(
-			settlementDuration,
+		const tx = await this.program.transaction.initializeLstOracle(
+			solOracleSource,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
+					lstOracle: getLstOraclePublicKey(this.program.programId, stakePool),
+					solOracle,
+					stakePool,
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
 				},
 			}
 		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async updateWithdrawGuardThreshold(
-		spotMarketIndex: number,
-		withdrawGuardThreshold: BN
-	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updateWithdrawGuardThreshold(
-			withdrawGuardThreshold,
-			{
-				accounts: {
-					admin: this.wallet.publicKey,
-					state: await this.getStatePublicKey(),
-					spotMarket: await getSpotMarketPublicKey(
-						this.program.programId,
-						spotMarketIndex
+	public async initializePerpMarket(
+		priceOracle: PublicKey,
+		baseAssetReserve: BN,
+		quoteAssetReserve: BN,
+		periodicity: BN,
+		pegMultiplier: BN = PEG_PRECISION,
+		oracleSource: OracleSource = OracleSource.PYTH,
+		marginRatioInitial = 2000,
+		marginRatioMaintenance = 500,
+		liquidatorFee = 0,
+		activeStatus = true,
+		name = DEFAULT_MARKET_NAME
+	): Promise<TransactionSignature> {
+		const currentPerpMarketIndex = this.getStateAccount().numberOfMarkets;
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			currentPerpMarketIndex
+		);
+
+		const nameBuffer = encodeName(name);
+		const initializeMarketTx =
+			await this.program.transaction.initializePerpMarket(
+				baseAssetReserve,
+				quoteAssetReserve,
+				periodicity,
+				pegMultiplier,
+				oracleSource,
+				marginRatioInitial,
+				marginRatioMaintenance,
+				liquidatorFee,
+				activeStatus,
+				nameBuffer,
+				{
+					accounts: {
+						state: await this.getStatePublicKey(),
+						adminRoles: getAdminRolesPublicKey(this.program.programId),
+						admin: this.wallet.publicKey,
+						oracle: priceOracle,
+						perpMarket: perpMarketPublicKey,
+						rent: SYSVAR_RENT_PUBKEY,
+						systemProgram: anchor.web3.SystemProgram.programId,
+					},
+				}
+			);
+		const { txSig } = await this.sendTransaction(
+			initializeMarketTx,
+			[],
+			this.opts
+		);
+
+		while (this.getStateAccount().numberOfMarkets <= currentPerpMarketIndex) {
+			await this.fetchAccounts();
+		}
+
+		await this.accountSubscriber.addPerpMarket(currentPerpMarketIndex);
+		await this.accountSubscriber.addOracle({
+			source: oracleSource,
+			publicKey: priceOracle,
+		});
+
+		return txSig;
+	}
+
+	public async initializePerpMarketListingConfig(
+		bondAmount: BN,
+		vetoWindow: number
+	): Promise<TransactionSignature> {
+		const tx =
+			await this.program.transaction.initializePerpMarketListingConfig(
+				bondAmount,
+				vetoWindow,
+				{
+					accounts: {
+						admin: this.wallet.publicKey,
+						state: await this.getStatePublicKey(),
+						perpMarketListingConfig: getPerpMarketListingConfigPublicKey(
+							this.program.programId
+						),
+						rent: SYSVAR_RENT_PUBKEY,
+						systemProgram: anchor.web3.SystemProgram.programId,
+					},
+				}
+			);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketListingConfig(
+		bondAmount: BN,
+		vetoWindow: number,
+		enabled: boolean
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updatePerpMarketListingConfig(
+			bondAmount,
+			vetoWindow,
+			enabled,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarketListingConfig: getPerpMarketListingConfigPublicKey(
+						this.program.programId
 					),
 				},
 			}
@@ -740,22 +1112,22 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateSpotMarketIfFactor(
-		spotMarketIndex: number,
-		userIfFactor: BN,
-		totalIfFactor: BN
+	public async vetoProposedPerpMarket(
+		perpMarketIndex: number
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updateSpotMarketIfFactor(
-			spotMarketIndex,
-			userIfFactor,
-			totalIfFactor,
+		const tx = await this.program.transaction.vetoProposedPerpMarket(
+			perpMarketIndex,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
-					spotMarket: await getSpotMarketPublicKey(
+					perpMarket: await getPerpMarketPublicKey(
 						this.program.programId,
-						spotMarketIndex
+						perpMarketIndex
+					),
+					perpMarketListing: getPerpMarketListingPublicKey(
+						this.program.programId,
+						perpMarketIndex
 					),
 				},
 			}
@@ -765,21 +1137,25 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateSpotMarketRevenueSettlePeriod(
-		spotMarketIndex: number,
-		revenueSettlePeriod: BN
+	public async initializePnlPoolRebalanceConfig(
+		surplusBuffer: BN,
+		maxTopUp: BN,
+		minRebalanceInterval: number
 	): Promise<TransactionSignature> {
 		const tx =
-			await this.program.transaction.updateSpotMarketRevenueSettlePeriod(
-				revenueSettlePeriod,
+			await this.program.transaction.initializePnlPoolRebalanceConfig(
+				surplusBuffer,
+				maxTopUp,
+				minRebalanceInterval,
 				{
 					accounts: {
 						admin: this.wallet.publicKey,
 						state: await this.getStatePublicKey(),
-						spotMarket: await getSpotMarketPublicKey(
-							this.program.programId,
-							spotMarketIndex
+						pnlPoolRebalanceConfig: getPnlPoolRebalanceConfigPublicKey(
+							this.program.programId
 						),
+						rent: SYSVAR_RENT_PUBKEY,
+						systemProgram: anchor.web3.SystemProgram.programId,
 					},
 				}
 			);
@@ -788,19 +1164,23 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateSpotMarketMaxTokenDeposits(
-		spotMarketIndex: number,
-		maxTokenDeposits: BN
+	public async updatePnlPoolRebalanceConfig(
+		surplusBuffer: BN,
+		maxTopUp: BN,
+		minRebalanceInterval: number,
+		enabled: boolean
 	): Promise<TransactionSignature> {
-		const tx = this.program.transaction.updateSpotMarketMaxTokenDeposits(
-			maxTokenDeposits,
+		const tx = await this.program.transaction.updatePnlPoolRebalanceConfig(
+			surplusBuffer,
+			maxTopUp,
+			minRebalanceInterval,
+			enabled,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
-					spotMarket: await getSpotMarketPublicKey(
-						this.program.programId,
-						spotMarketIndex
+					pnlPoolRebalanceConfig: getPnlPoolRebalanceConfigPublicKey(
+						this.program.programId
 					),
 				},
 			}
@@ -810,54 +1190,1284 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
-	public async updateInsuranceFundUnstakingPeriod(
-		spotMarketIndex: number,
-		insuranceWithdrawEscrowPeriod: BN
+	public async moveAmmPrice(
+		perpMarketIndex: number,
+		baseAssetReserve: BN,
+		quoteAssetReserve: BN,
+		sqrtK?: BN
 	): Promise<TransactionSignature> {
-		const tx =
-			await this.program.transaction.updateInsuranceFundUnstakingPeriod(
-				insuranceWithdrawEscrowPeriod,
-				{
-					accounts: {
-						admin: this.wallet.publicKey,
-						state: await this.getStatePublicKey(),
-						spotMarket: await getSpotMarketPublicKey(
-							this.program.programId,
-							spotMarketIndex
-						),
-					},
-				}
+		const marketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+
+		if (sqrtK == undefined) {
+			sqrtK = squareRootBN(baseAssetReserve.mul(quoteAssetReserve));
+		}
+
+		const tx = await this.program.transaction.moveAmmPrice(
+			baseAssetReserve,
+			quoteAssetReserve,
+			sqrtK,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					admin: this.wallet.publicKey,
+					perpMarket: marketPublicKey,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateK(
+		perpMarketIndex: number,
+		sqrtK: BN
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateK(sqrtK, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				admin: this.wallet.publicKey,
+				perpMarket: await getPerpMarketPublicKey(
+					this.program.programId,
+					perpMarketIndex
+				),
+				oracle: this.getPerpMarketAccount(perpMarketIndex).amm.oracle,
+			},
+		});
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketConcentrationScale(
+		perpMarketIndex: number,
+		concentrationScale: BN
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketConcentrationCoef(
+			concentrationScale,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					admin: this.wallet.publicKey,
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async moveAmmToPrice(
+		perpMarketIndex: number,
+		targetPrice: BN
+	): Promise<TransactionSignature> {
+		const perpMarket = this.getPerpMarketAccount(perpMarketIndex);
+
+		const [direction, tradeSize, _] = calculateTargetPriceTrade(
+			perpMarket,
+			targetPrice,
+			new BN(1000),
+			'quote',
+			undefined //todo
+		);
+
+		const [newQuoteAssetAmount, newBaseAssetAmount] =
+			calculateAmmReservesAfterSwap(
+				perpMarket.amm,
+				'quote',
+				tradeSize,
+				getSwapDirection('quote', direction)
 			);
 
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+
+		const tx = await this.program.transaction.moveAmmPrice(
+			newBaseAssetAmount,
+			newQuoteAssetAmount,
+			perpMarket.amm.sqrtK,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					admin: this.wallet.publicKey,
+					perpMarket: perpMarketPublicKey,
+				},
+			}
+		);
+
 		const { txSig } = await this.sendTransaction(tx, [], this.opts);
 		return txSig;
 	}
 
-	public async updateLpCooldownTime(
-		cooldownTime: BN
+	public async repegAmmCurve(
+		newPeg: BN,
+		perpMarketIndex: number
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updateLpCooldownTime(
-			cooldownTime,
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+		const ammData = this.getPerpMarketAccount(perpMarketIndex).amm;
+
+		const tx = await this.program.transaction.repegAmmCurve(newPeg, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				admin: this.wallet.publicKey,
+				oracle: ammData.oracle,
+				perpMarket: perpMarketPublicKey,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketAmmOracleTwap(
+		perpMarketIndex: number
+	): Promise<TransactionSignature> {
+		const ammData = this.getPerpMarketAccount(perpMarketIndex).amm;
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+
+		return await this.program.rpc.updatePerpMarketAmmOracleTwap({
+			accounts: {
+				state: await this.getStatePublicKey(),
+				admin: this.wallet.publicKey,
+				oracle: ammData.oracle,
+				perpMarket: perpMarketPublicKey,
+			},
+		});
+	}
+
+	public async resetPerpMarketAmmOracleTwap(
+		perpMarketIndex: number
+	): Promise<TransactionSignature> {
+		const ammData = this.getPerpMarketAccount(perpMarketIndex).amm;
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+
+		return await this.program.rpc.resetPerpMarketAmmOracleTwap({
+			accounts: {
+				state: await this.getStatePublicKey(),
+				admin: this.wallet.publicKey,
+				oracle: ammData.oracle,
+				perpMarket: perpMarketPublicKey,
+			},
+		});
+	}
+
+	public async depositIntoPerpMarketFeePool(
+		perpMarketIndex: number,
+		amount: BN,
+		sourceVault: PublicKey
+	): Promise<TransactionSignature> {
+		const spotMarket = this.getQuoteSpotMarketAccount();
+
+		const tx = await this.program.transaction.depositIntoPerpMarketFeePool(
+			amount,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					adminRoles: getAdminRolesPublicKey(this.program.programId),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					sourceVault,
+					driftSigner: this.getSignerPublicKey(),
+					quoteSpotMarket: spotMarket.pubkey,
+					spotMarketVault: spotMarket.vault,
+					tokenProgram: TOKEN_PROGRAM_ID,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateAdmin(admin: PublicKey): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateAdmin(admin, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+			},
+		});
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketCurveUpdateIntensity(
+		perpMarketIndex: number,
+		curveUpdateIntensity: number
+	): Promise<TransactionSignature> {
+		// assert(curveUpdateIntensity >= 0 && curveUpdateIntensity <= 100);
+		// assert(Number.isInteger(curveUpdateIntensity));
+
+		return await this.program.rpc.updatePerpMarketCurveUpdateIntensity(
+			curveUpdateIntensity,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updatePerpMarketFundingPeriod(
+		perpMarketIndex: number,
+		fundingPeriod: BN
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketFundingPeriod(fundingPeriod, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				perpMarket: await getPerpMarketPublicKey(
+					this.program.programId,
+					perpMarketIndex
+				),
+			},
+		});
+	}
+
+	public async updatePerpMarketFundingRateCaps(
+		perpMarketIndex: number,
+		maxFundingRateSpread: number,
+		escalatedMaxFundingRateSpread: number,
+		fundingDivergenceEscalationPeriods: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketFundingRateCaps(
+			maxFundingRateSpread,
+			escalatedMaxFundingRateSpread,
+			fundingDivergenceEscalationPeriods,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updatePerpMarketMarginRatio(
+		perpMarketIndex: number,
+		marginRatioInitial: number,
+		marginRatioMaintenance: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updatePerpMarketMarginRatio(
+			marginRatioInitial,
+			marginRatioMaintenance,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					adminRoles: getAdminRolesPublicKey(this.program.programId),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketImfFactor(
+		perpMarketIndex: number,
+		imfFactor: number,
+		unrealizedPnlImfFactor: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketImfFactor(
+			imfFactor,
+			unrealizedPnlImfFactor,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updatePerpMarketBaseSpread(
+		perpMarketIndex: number,
+		baseSpread: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updatePerpMarketBaseSpread(
+			baseSpread,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateAmmJitIntensity(
+		perpMarketIndex: number,
+		ammJitIntensity: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateAmmJitIntensity(
+			ammJitIntensity,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketName(
+		perpMarketIndex: number,
+		name: string
+	): Promise<TransactionSignature> {
+		const nameBuffer = encodeName(name);
+
+		const tx = await this.program.transaction.updatePerpMarketName(nameBuffer, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				perpMarket: await getPerpMarketPublicKey(
+					this.program.programId,
+					perpMarketIndex
+				),
+			},
+		});
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketName(
+		spotMarketIndex: number,
+		name: string
+	): Promise<TransactionSignature> {
+		const nameBuffer = encodeName(name);
+
+		return await this.program.rpc.updateSpotMarketName(nameBuffer, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				spotMarket: await getSpotMarketPublicKey(
+					this.program.programId,
+					spotMarketIndex
+				),
+			},
+		});
+	}
+
+	public async updatePerpMarketMaxSpread(
+		perpMarketIndex: number,
+		maxSpread: number
+	): Promise<TransactionSignature> {
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+
+		return await this.program.rpc.updatePerpMarketMaxSpread(maxSpread, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				perpMarket: perpMarketPublicKey,
+			},
+		});
+	}
+
+	public async updatePerpMarketDynamicSpreadParams(
+		perpMarketIndex: number,
+		inventorySkewSpread: number,
+		volatilitySpreadScale: number
+	): Promise<TransactionSignature> {
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+
+		const tx = this.program.transaction.updatePerpMarketDynamicSpreadParams(
+			inventorySkewSpread,
+			volatilitySpreadScale,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: perpMarketPublicKey,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketMaxReferencePriceOffset(
+		perpMarketIndex: number,
+		maxReferencePriceOffset: number
+	): Promise<TransactionSignature> {
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+
+		const tx = this.program.transaction.updatePerpMarketMaxReferencePriceOffset(
+			maxReferencePriceOffset,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: perpMarketPublicKey,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketLpLockup(
+		perpMarketIndex: number,
+		lpMinDuration: number,
+		lpEarlyRemovalPenalty: number
+	): Promise<TransactionSignature> {
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+
+		const tx = this.program.transaction.updatePerpMarketLpLockup(
+			lpMinDuration,
+			lpEarlyRemovalPenalty,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: perpMarketPublicKey,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpFeeStructure(
+		feeStructure: FeeStructure
+	): Promise<TransactionSignature> {
+		const tx = this.program.transaction.updatePerpFeeStructure(feeStructure, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+			},
+		});
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotFeeStructure(
+		feeStructure: FeeStructure
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateSpotFeeStructure(
+			feeStructure,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateInitialPctToLiquidate(
+		initialPctToLiquidate: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateInitialPctToLiquidate(
+			initialPctToLiquidate,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+	}
+
+	public async updateLiquidationDuration(
+		liquidationDuration: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateLiquidationDuration(
+			liquidationDuration,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+	}
+
+	public async updateLiquidationFeeRampDuration(
+		liquidationFeeRampDuration: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateLiquidationFeeRampDuration(
+			liquidationFeeRampDuration,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+	}
+
+	public async updateLiquidationTargetMarginBufferRatio(
+		liquidationTargetMarginBufferRatio: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateLiquidationTargetMarginBufferRatio(
+			liquidationTargetMarginBufferRatio,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+	}
+
+	public async updateLiquidationStaleFeeEscalationDuration(
+		liquidationStaleFeeEscalationDuration: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateLiquidationStaleFeeEscalationDuration(
+			liquidationStaleFeeEscalationDuration,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+	}
+
+	public async updateMarginWarningHealth(
+		marginWarningHealth: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateMarginWarningHealth(
+			marginWarningHealth,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+	}
+
+	public async updateOracleGuardRails(
+		oracleGuardRails: OracleGuardRails
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateOracleGuardRails(
+			oracleGuardRails,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateStateSettlementDuration(
+		settlementDuration: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateStateSettlementDuration(
+			settlementDuration,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+	}
+
+	public async updateWithdrawGuardThreshold(
+		spotMarketIndex: number,
+		withdrawGuardThreshold: BN
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateWithdrawGuardThreshold(
+			withdrawGuardThreshold,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketIfFactor(
+		spotMarketIndex: number,
+		userIfFactor: BN,
+		totalIfFactor: BN
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateSpotMarketIfFactor(
+			spotMarketIndex,
+			userIfFactor,
+			totalIfFactor,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketRevenueSettlePeriod(
+		spotMarketIndex: number,
+		revenueSettlePeriod: BN
+	): Promise<TransactionSignature> {
+		const tx =
+			await this.program.transaction.updateSpotMarketRevenueSettlePeriod(
+				revenueSettlePeriod,
+				{
+					accounts: {
+						admin: this.wallet.publicKey,
+						state: await this.getStatePublicKey(),
+						spotMarket: await getSpotMarketPublicKey(
+							this.program.programId,
+							spotMarketIndex
+						),
+					},
+				}
+			);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketRevenueSettleMaxAmount(
+		spotMarketIndex: number,
+		revenueSettleMaxAmount: BN
+	): Promise<TransactionSignature> {
+		const tx =
+			await this.program.transaction.updateSpotMarketRevenueSettleMaxAmount(
+				revenueSettleMaxAmount,
+				{
+					accounts: {
+						admin: this.wallet.publicKey,
+						state: await this.getStatePublicKey(),
+						spotMarket: await getSpotMarketPublicKey(
+							this.program.programId,
+							spotMarketIndex
+						),
+					},
+				}
+			);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketMaxTokenBorrows(
+		spotMarketIndex: number,
+		maxTokenBorrows: BN
+	): Promise<TransactionSignature> {
+		const tx = this.program.transaction.updateSpotMarketMaxTokenBorrows(
+			maxTokenBorrows,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketFlashBorrowParams(
+		spotMarketIndex: number,
+		flashBorrowsEnabled: boolean,
+		flashBorrowFeeBps: number
+	): Promise<TransactionSignature> {
+		const tx = this.program.transaction.updateSpotMarketFlashBorrowParams(
+			flashBorrowsEnabled,
+			flashBorrowFeeBps,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketMaxNetWithdrawBps(
+		spotMarketIndex: number,
+		maxNetWithdrawBps: number
+	): Promise<TransactionSignature> {
+		const tx = this.program.transaction.updateSpotMarketMaxNetWithdrawBps(
+			maxNetWithdrawBps,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketMaxTokenDeposits(
+		spotMarketIndex: number,
+		maxTokenDeposits: BN
+	): Promise<TransactionSignature> {
+		const tx = this.program.transaction.updateSpotMarketMaxTokenDeposits(
+			maxTokenDeposits,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateInsuranceFundUnstakingPeriod(
+		spotMarketIndex: number,
+		insuranceWithdrawEscrowPeriod: BN
+	): Promise<TransactionSignature> {
+		const tx =
+			await this.program.transaction.updateInsuranceFundUnstakingPeriod(
+				insuranceWithdrawEscrowPeriod,
+				{
+					accounts: {
+						admin: this.wallet.publicKey,
+						state: await this.getStatePublicKey(),
+						spotMarket: await getSpotMarketPublicKey(
+							this.program.programId,
+							spotMarketIndex
+						),
+					},
+				}
+			);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateLpCooldownTime(
+		cooldownTime: BN
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateLpCooldownTime(
+			cooldownTime,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketOracle(
+		perpMarketIndex: number,
+		oracle: PublicKey,
+		oracleSource: OracleSource
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updatePerpMarketOracle(
+			oracle,
+			oracleSource,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					oracle: oracle,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketAdditionalOracles(
+		perpMarketIndex: number,
+		additionalOracles: PublicKey[],
+		additionalOracleSources: OracleSource[]
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updatePerpMarketAdditionalOracles(
+			additionalOracles,
+			additionalOracleSources,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+				remainingAccounts: additionalOracles
+					.filter((oracle) => !oracle.equals(PublicKey.default))
+					.map((oracle) => ({
+						pubkey: oracle,
+						isSigner: false,
+						isWritable: false,
+					})),
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketStepSizeAndTickSize(
+		perpMarketIndex: number,
+		stepSize: BN,
+		tickSize: BN
+	): Promise<TransactionSignature> {
+		const tx =
+			await this.program.transaction.updatePerpMarketStepSizeAndTickSize(
+				stepSize,
+				tickSize,
+				{
+					accounts: {
+						admin: this.wallet.publicKey,
+						state: await this.getStatePublicKey(),
+						perpMarket: await getPerpMarketPublicKey(
+							this.program.programId,
+							perpMarketIndex
+						),
+					},
+				}
+			);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketMinOrderSize(
+		perpMarketIndex: number,
+		orderSize: BN
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketMinOrderSize(orderSize, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				perpMarket: await getPerpMarketPublicKey(
+					this.program.programId,
+					perpMarketIndex
+				),
+			},
+		});
+	}
+
+	public async updateSpotMarketStepSizeAndTickSize(
+		spotMarketIndex: number,
+		stepSize: BN,
+		tickSize: BN
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateSpotMarketStepSizeAndTickSize(
+			stepSize,
+			tickSize,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updateSpotMarketMinOrderSize(
+		spotMarketIndex: number,
+		orderSize: BN
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateSpotMarketMinOrderSize(orderSize, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				spotMarket: await getSpotMarketPublicKey(
+					this.program.programId,
+					spotMarketIndex
+				),
+			},
+		});
+	}
+
+	public async updatePerpMarketExpiry(
+		perpMarketIndex: number,
+		expiryTs: BN
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketExpiry(expiryTs, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				perpMarket: await getPerpMarketPublicKey(
+					this.program.programId,
+					perpMarketIndex
+				),
+			},
+		});
+	}
+
+	public async delistPerpMarket(
+		perpMarketIndex: number,
+		expiryTs: BN,
+		settlementTwapWindow: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.delistPerpMarket(
+			perpMarketIndex,
+			expiryTs,
+			settlementTwapWindow,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					perpMarketDelisting: getPerpMarketDelistingPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async initializeDatedFuture(
+		perpMarketIndex: number,
+		expiryTs: BN,
+		settlementTwapWindow: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeDatedFuture(
+			perpMarketIndex,
+			expiryTs,
+			settlementTwapWindow,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					perpMarketDelisting: getPerpMarketDelistingPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					rent: SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketOracle(
+		spotMarketIndex: number,
+		oracle: PublicKey,
+		oracleSource: OracleSource
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateSpotMarketOracle(oracle, oracleSource, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				spotMarket: await getSpotMarketPublicKey(
+					this.program.programId,
+					spotMarketIndex
+				),
+				oracle: oracle,
+			},
+		});
+	}
+
+	public async updateSpotMarketOrdersEnabled(
+		spotMarketIndex: number,
+		ordersEnabled: boolean
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateSpotMarketOrdersEnabled(ordersEnabled, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				spotMarket: await getSpotMarketPublicKey(
+					this.program.programId,
+					spotMarketIndex
+				),
+			},
+		});
+	}
+
+	public async updateSpotMarketCorrelationGroup(
+		spotMarketIndex: number,
+		correlationGroup: number,
+		correlationMarginCredit: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateSpotMarketCorrelationGroup(
+			correlationGroup,
+			correlationMarginCredit,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updateSpotMarketMaxCollateralConcentration(
+		spotMarketIndex: number,
+		maxCollateralConcentration: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateSpotMarketMaxCollateralConcentration(
+			maxCollateralConcentration,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updateSpotMarketValidityGuardRailsOverride(
+		spotMarketIndex: number,
+		validityGuardRailsOverride: ValidityGuardRailsOverride
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateSpotMarketValidityGuardRailsOverride(
+			validityGuardRailsOverride,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updatePerpMarketOracleCircuitBreakerBand(
+		perpMarketIndex: number,
+		oracleCircuitBreakerBand: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketOracleCircuitBreakerBand(
+			oracleCircuitBreakerBand,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updatePerpMarketAuctionExtension(
+		perpMarketIndex: number,
+		auctionExtensionDuration: number,
+		auctionExtensionPriceBuffer: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketAuctionExtension(
+			auctionExtensionDuration,
+			auctionExtensionPriceBuffer,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updatePerpMarketMarginTiers(
+		perpMarketIndex: number,
+		marginTiers: MarginTier[]
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketMarginTiers(marginTiers, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				perpMarket: await getPerpMarketPublicKey(
+					this.program.programId,
+					perpMarketIndex
+				),
+			},
+		});
+	}
+
+	public async updatePerpMarketCorrelationGroup(
+		perpMarketIndex: number,
+		correlationGroup: number,
+		correlationMarginCredit: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketCorrelationGroup(
+			correlationGroup,
+			correlationMarginCredit,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+	}
+
+	public async updatePerpMarketAutoDeleverageEnabled(
+		perpMarketIndex: number,
+		autoDeleverageEnabled: boolean
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketAutoDeleverageEnabled(
+			autoDeleverageEnabled,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
 				},
 			}
 		);
-
-		const { txSig } = await this.sendTransaction(tx, [], this.opts);
-		return txSig;
 	}
 
-	public async updatePerpMarketOracle(
+	public async updatePerpMarketInsuranceFundMaxDrawPct(
 		perpMarketIndex: number,
-		oracle: PublicKey,
-		oracleSource: OracleSource
+		insuranceFundMaxDrawPct: number
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updatePerpMarketOracle(
-			oracle,
-			oracleSource,
+		return await this.program.rpc.updatePerpMarketInsuranceFundMaxDrawPct(
+			insuranceFundMaxDrawPct,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
@@ -866,45 +2476,34 @@ export class AdminClient extends DriftClient {
 						this.program.programId,
 						perpMarketIndex
 					),
-					oracle: oracle,
 				},
 			}
 		);
-
-		const { txSig } = await this.sendTransaction(tx, [], this.opts);
-		return txSig;
 	}
 
-	public async updatePerpMarketStepSizeAndTickSize(
+	public async updatePerpMarketTickSizeBps(
 		perpMarketIndex: number,
-		stepSize: BN,
-		tickSize: BN
+		orderTickSizeBps: number
 	): Promise<TransactionSignature> {
-		const tx =
-			await this.program.transaction.updatePerpMarketStepSizeAndTickSize(
-				stepSize,
-				tickSize,
-				{
-					accounts: {
-						admin: this.wallet.publicKey,
-						state: await this.getStatePublicKey(),
-						perpMarket: await getPerpMarketPublicKey(
-							this.program.programId,
-							perpMarketIndex
-						),
-					},
-				}
-			);
-
-		const { txSig } = await this.sendTransaction(tx, [], this.opts);
-		return txSig;
+		return await this.program.rpc.updatePerpMarketTickSizeBps(
+			orderTickSizeBps,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
 	}
 
-	public async updatePerpMarketMinOrderSize(
-		perpMarketIndex: number,
-		orderSize: BN
+	public async resizePerpMarket(
+		perpMarketIndex: number
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updatePerpMarketMinOrderSize(orderSize, {
+		return await this.program.rpc.resizePerpMarket({
 			accounts: {
 				admin: this.wallet.publicKey,
 				state: await this.getStatePublicKey(),
@@ -912,106 +2511,126 @@ export class AdminClient extends DriftClient {
 					this.program.programId,
 					perpMarketIndex
 				),
+				systemProgram: anchor.web3.SystemProgram.programId,
 			},
 		});
 	}
 
-	public async updateSpotMarketStepSizeAndTickSize(
-		spotMarketIndex: number,
-		stepSize: BN,
-		tickSize: BN
+	public async resizeSpotMarket(
+		spotMarketIndex: number
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updateSpotMarketStepSizeAndTickSize(
-			stepSize,
-			tickSize,
+		return await this.program.rpc.resizeSpotMarket({
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				spotMarket: await getSpotMarketPublicKey(
+					this.program.programId,
+					spotMarketIndex
+				),
+				systemProgram: anchor.web3.SystemProgram.programId,
+			},
+		});
+	}
+
+	public async updatePerpMarketInsuranceFundLend(
+		perpMarketIndex: number,
+		insuranceFundLendMaxPct: number,
+		insuranceFundLendRate: number
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updatePerpMarketInsuranceFundLend(
+			insuranceFundLendMaxPct,
+			insuranceFundLendRate,
 			{
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
-					spotMarket: await getSpotMarketPublicKey(
+					perpMarket: await getPerpMarketPublicKey(
 						this.program.programId,
-						spotMarketIndex
+						perpMarketIndex
 					),
 				},
 			}
 		);
 	}
 
-	public async updateSpotMarketMinOrderSize(
-		spotMarketIndex: number,
-		orderSize: BN
+	public async updatePerpMarketHighLeverageMarginRatio(
+		perpMarketIndex: number,
+		highLeverageMarginRatioInitial: number,
+		highLeverageMarginRatioMaintenance: number
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updateSpotMarketMinOrderSize(orderSize, {
-			accounts: {
-				admin: this.wallet.publicKey,
-				state: await this.getStatePublicKey(),
-				spotMarket: await getSpotMarketPublicKey(
-					this.program.programId,
-					spotMarketIndex
-				),
-			},
-		});
+		return await this.program.rpc.updatePerpMarketHighLeverageMarginRatio(
+			highLeverageMarginRatioInitial,
+			highLeverageMarginRatioMaintenance,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
 	}
 
-	public async updatePerpMarketExpiry(
+	public async updatePerpMarketValidityGuardRailsOverride(
 		perpMarketIndex: number,
-		expiryTs: BN
+		validityGuardRailsOverride: ValidityGuardRailsOverride
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updatePerpMarketExpiry(expiryTs, {
-			accounts: {
-				admin: this.wallet.publicKey,
-				state: await this.getStatePublicKey(),
-				perpMarket: await getPerpMarketPublicKey(
-					this.program.programId,
-					perpMarketIndex
-				),
-			},
-		});
+		return await this.program.rpc.updatePerpMarketValidityGuardRailsOverride(
+			validityGuardRailsOverride,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
 	}
 
-	public async updateSpotMarketOracle(
-		spotMarketIndex: number,
-		oracle: PublicKey,
-		oracleSource: OracleSource
+	public async updateSerumFulfillmentConfigStatus(
+		serumFulfillmentConfig: PublicKey,
+		status: SpotFulfillmentConfigStatus
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updateSpotMarketOracle(oracle, oracleSource, {
+		return await this.program.rpc.updateSerumFulfillmentConfigStatus(status, {
 			accounts: {
 				admin: this.wallet.publicKey,
 				state: await this.getStatePublicKey(),
-				spotMarket: await getSpotMarketPublicKey(
-					this.program.programId,
-					spotMarketIndex
-				),
-				oracle: oracle,
+				serumFulfillmentConfig,
 			},
 		});
 	}
 
-	public async updateSpotMarketOrdersEnabled(
-		spotMarketIndex: number,
-		ordersEnabled: boolean
+	public async updateOpenbookV2FulfillmentConfigStatus(
+		openbookV2FulfillmentConfig: PublicKey,
+		status: SpotFulfillmentConfigStatus
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updateSpotMarketOrdersEnabled(ordersEnabled, {
-			accounts: {
-				admin: this.wallet.publicKey,
-				state: await this.getStatePublicKey(),
-				spotMarket: await getSpotMarketPublicKey(
-					this.program.programId,
-					spotMarketIndex
-				),
-			},
-		});
+		return await this.program.rpc.updateOpenbookV2FulfillmentConfigStatus(
+			status,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					openbookV2FulfillmentConfig,
+				},
+			}
+		);
 	}
 
-	public async updateSerumFulfillmentConfigStatus(
-		serumFulfillmentConfig: PublicKey,
+	public async updatePhoenixFulfillmentConfigStatus(
+		phoenixFulfillmentConfig: PublicKey,
 		status: SpotFulfillmentConfigStatus
 	): Promise<TransactionSignature> {
-		return await this.program.rpc.updateSerumFulfillmentConfigStatus(status, {
+		return await this.program.rpc.updatePhoenixFulfillmentConfigStatus(status, {
 			accounts: {
 				admin: this.wallet.publicKey,
 				state: await this.getStatePublicKey(),
-				serumFulfillmentConfig,
+				phoenixFulfillmentConfig,
 			},
 		});
 	}
@@ -1081,6 +2700,7 @@ export class AdminClient extends DriftClient {
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
+					adminRoles: getAdminRolesPublicKey(this.program.programId),
 					spotMarket: await getSpotMarketPublicKey(
 						this.program.programId,
 						spotMarketIndex
@@ -1113,6 +2733,28 @@ export class AdminClient extends DriftClient {
 		);
 	}
 
+	public async updateSpotMarketBorrowRateKinks(
+		spotMarketIndex: number,
+		borrowRateKinks: BorrowRateKink[]
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateSpotMarketBorrowRateKinks(
+			borrowRateKinks,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
 	public async updateSpotMarketAssetTier(
 		spotMarketIndex: number,
 		assetTier: AssetTier
@@ -1145,6 +2787,30 @@ export class AdminClient extends DriftClient {
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
+					adminRoles: getAdminRolesPublicKey(this.program.programId),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						spotMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateSpotMarketPausedOperations(
+		spotMarketIndex: number,
+		pausedOperations: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateSpotMarketPausedOperations(
+			pausedOperations,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					adminRoles: getAdminRolesPublicKey(this.program.programId),
 					spotMarket: await getSpotMarketPublicKey(
 						this.program.programId,
 						spotMarketIndex
@@ -1167,6 +2833,30 @@ export class AdminClient extends DriftClient {
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
+					adminRoles: getAdminRolesPublicKey(this.program.programId),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpMarketPausedOperations(
+		perpMarketIndex: number,
+		pausedOperations: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updatePerpMarketPausedOperations(
+			pausedOperations,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					adminRoles: getAdminRolesPublicKey(this.program.programId),
 					perpMarket: await getPerpMarketPublicKey(
 						this.program.programId,
 						perpMarketIndex
@@ -1201,6 +2891,48 @@ export class AdminClient extends DriftClient {
 		return txSig;
 	}
 
+	public async updatePerpMarketContractType(
+		perpMarketIndex: number,
+		contractType: ContractType
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updatePerpMarketContractType(
+			contractType,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async resolvePredictionMarket(
+		perpMarketIndex: number,
+		outcome: boolean
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.resolvePredictionMarket(outcome, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				spotMarket: await getSpotMarketPublicKey(this.program.programId, 0),
+				perpMarket: await getPerpMarketPublicKey(
+					this.program.programId,
+					perpMarketIndex
+				),
+			},
+		});
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
 	public async updateExchangeStatus(
 		exchangeStatus: ExchangeStatus
 	): Promise<TransactionSignature> {
@@ -1210,6 +2942,7 @@ export class AdminClient extends DriftClient {
 				accounts: {
 					admin: this.wallet.publicKey,
 					state: await this.getStatePublicKey(),
+					adminRoles: getAdminRolesPublicKey(this.program.programId),
 				},
 			}
 		);
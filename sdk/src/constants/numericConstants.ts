@@ -98,3 +98,6 @@ export const OPEN_ORDER_MARGIN_REQUIREMENT = QUOTE_PRECISION.div(new BN(100));
 export const DEFAULT_REVENUE_SINCE_LAST_FUNDING_SPREAD_RETREAT = new BN(
 	-25
 ).mul(QUOTE_PRECISION);
+
+// deposit/withdraw amount that repays or withdraws the entire spot position
+export const ALL_TOKEN_AMOUNT = new BN('18446744073709551615');
@@ -23,6 +23,10 @@ export const WRAPPED_SOL_MINT = new PublicKey(
 	'So11111111111111111111111111111111111111112'
 );
 
+export const TOKEN_2022_PROGRAM_ID = new PublicKey(
+	'TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb'
+);
+
 export const DevnetSpotMarkets: SpotMarketConfig[] = [
 	{
 		symbol: 'USDC',
@@ -16,6 +16,9 @@ import {
 	IWallet,
 	PositionDirection,
 	UserAccount,
+	UserFillCallback,
+	Vault,
+	VaultDepositor,
 	PerpMarketAccount,
 	OrderParams,
 	Order,
@@ -24,12 +27,23 @@ import {
 	MakerInfo,
 	TakerInfo,
 	OptionalOrderParams,
+	ScaleOrderParams,
+	ModifyOrderParams,
+	CancelOrdersFilter,
+	SignedOrderMessage,
+	RfqTakerRequest,
+	RfqMakerQuote,
+	RfqQuoteInfo,
 	DefaultOrderParams,
 	OrderType,
 	ReferrerInfo,
+	BuilderInfo,
+	FuelInfo,
 	MarketType,
 	TxParams,
 	SerumV3FulfillmentConfigAccount,
+	OpenbookV2FulfillmentConfigAccount,
+	PhoenixV1FulfillmentConfigAccount,
 	isVariant,
 	ReferrerNameAccount,
 	OrderTriggerCondition,
@@ -37,6 +51,15 @@ import {
 	PostOnlyParams,
 	SpotBalanceType,
 	PerpMarketExtendedInfo,
+	MarginHealth,
+	PredictedFunding,
+	OrderBookLevel,
+	OrderBookSnapshot,
+	MarketLookupTableAccount,
+	InsuranceFundStakeLockupTier,
+	PerpLpTier,
+	OracleSource,
+	PerpMarketListing,
 } from './types';
 import * as anchor from '@project-serum/anchor';
 import driftIDL from './idl/drift.json';
@@ -55,6 +78,9 @@ import {
 	SystemProgram,
 	ComputeBudgetProgram,
 	AddressLookupTableAccount,
+	AddressLookupTableProgram,
+	Ed25519Program,
+	SYSVAR_INSTRUCTIONS_PUBKEY,
 } from '@solana/web3.js';
 
 import { TokenFaucet } from './tokenFaucet';
@@ -66,7 +92,33 @@ import {
 	getInsuranceFundStakeAccountPublicKey,
 	getPerpMarketPublicKey,
 	getReferrerNamePublicKeySync,
+	getSignedOrderNoncePublicKeySync,
+	getPerpLpRangePublicKeySync,
+	getSessionKeyPublicKeySync,
+	getDeadManSwitchPublicKeySync,
+	getBuilderPublicKeySync,
+	getUserFuelPublicKeySync,
+	getMarketFeeOverridePublicKey,
+	getMarketLookupTablePublicKey,
+	getMarketWhitelistEntryPublicKey,
+	getFillCallbackProgramPublicKey,
+	getUserFillCallbackPublicKeySync,
+	getVaultDepositorPublicKey,
+	getVaultPublicKey,
+	getPerpMarketDelistingPublicKey,
+	getPerpMarketListingConfigPublicKey,
+	getPerpMarketListingPublicKey,
+	getGovernanceStakePublicKeySync,
+	getGovernanceStakeVaultPublicKey,
+	getHighLeverageModeConfigPublicKey,
+	getBackstopProviderPublicKey,
+	getInsuranceFundShareMintPublicKey,
+	getWrappedInsuranceFundStakePublicKey,
 	getSerumFulfillmentConfigPublicKey,
+	getOpenbookV2FulfillmentConfigPublicKey,
+	getPhoenixFulfillmentConfigPublicKey,
+	getPnlPoolRebalanceConfigPublicKey,
+	getLstOraclePublicKey,
 	getSerumSignerPublicKey,
 	getSpotMarketPublicKey,
 	getUserAccountPublicKey,
@@ -83,7 +135,11 @@ import { wrapInTx } from './tx/utils';
 import { QUOTE_SPOT_MARKET_INDEX, ZERO } from './constants/numericConstants';
 import { findDirectionToClose, positionIsAvailable } from './math/position';
 import { getTokenAmount } from './math/spotBalance';
-import { DEFAULT_USER_NAME, encodeName } from './userName';
+import {
+	DEFAULT_MARKET_NAME,
+	DEFAULT_USER_NAME,
+	encodeName,
+} from './userName';
 import { OraclePriceData } from './oracles/types';
 import { DriftClientConfig } from './driftClientConfig';
 import { PollingDriftClientAccountSubscriber } from './accounts/pollingDriftClientAccountSubscriber';
@@ -92,7 +148,10 @@ import { RetryTxSender } from './tx/retryTxSender';
 import { User } from './user';
 import { UserSubscriptionConfig } from './userConfig';
 import { configs, getMarketsAndOraclesForSubscription } from './config';
-import { WRAPPED_SOL_MINT } from './constants/spotMarkets';
+import {
+	TOKEN_2022_PROGRAM_ID,
+	WRAPPED_SOL_MINT,
+} from './constants/spotMarkets';
 import { UserStats } from './userStats';
 import { isSpotPositionAvailable } from './math/spotPosition';
 import { calculateMarketMaxAvailableInsurance } from './math/market';
@@ -410,6 +469,30 @@ export class DriftClient {
 		)) as SerumV3FulfillmentConfigAccount;
 	}
 
+	public async getOpenbookV2FulfillmentConfig(
+		openbookV2Market: PublicKey
+	): Promise<OpenbookV2FulfillmentConfigAccount> {
+		const address = getOpenbookV2FulfillmentConfigPublicKey(
+			this.program.programId,
+			openbookV2Market
+		);
+		return (await this.program.account.openbookV2FulfillmentConfig.fetch(
+			address
+		)) as OpenbookV2FulfillmentConfigAccount;
+	}
+
+	public async getPhoenixV1FulfillmentConfig(
+		phoenixMarket: PublicKey
+	): Promise<PhoenixV1FulfillmentConfigAccount> {
+		const address = getPhoenixFulfillmentConfigPublicKey(
+			this.program.programId,
+			phoenixMarket
+		);
+		return (await this.program.account.phoenixV1FulfillmentConfig.fetch(
+			address
+		)) as PhoenixV1FulfillmentConfigAccount;
+	}
+
 	public async fetchMarketLookupTableAccount(): Promise<AddressLookupTableAccount> {
 		if (!this.marketLookupTable) {
 			throw Error('Market lookup table address not set');
@@ -626,6 +709,32 @@ export class DriftClient {
 		return txSig;
 	}
 
+	public async initializeSignedOrderNonce(
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.initializeSignedOrderNonce({
+			accounts: {
+				signedOrderNonce: getSignedOrderNoncePublicKeySync(
+					this.program.programId,
+					userAccountPublicKey
+				),
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+				payer: this.wallet.publicKey,
+				rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+				systemProgram: anchor.web3.SystemProgram.programId,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
 	public async updateUserName(
 		name: string,
 		subAccountId = 0
@@ -700,16 +809,20 @@ export class DriftClient {
 		return txSig;
 	}
 
-	public async updateUserDelegate(
-		delegate: PublicKey,
+	public async updateUserProtectedMakerMode(
+		protectedMaker: boolean,
 		subAccountId = 0
 	): Promise<TransactionSignature> {
-		const tx = await this.program.transaction.updateUserDelegate(
+		const tx = await this.program.transaction.updateUserProtectedMakerMode(
 			subAccountId,
-			delegate,
+			protectedMaker,
 			{
 				accounts: {
-					user: await this.getUserAccountPublicKey(),
+					user: getUserAccountPublicKeySync(
+						this.program.programId,
+						this.wallet.publicKey,
+						subAccountId
+					),
 					authority: this.wallet.publicKey,
 				},
 			}
@@ -719,144 +832,468 @@ export class DriftClient {
 		return txSig;
 	}
 
-	public async fetchAllUserAccounts(
-		includeIdle = true
-	): Promise<ProgramAccount<UserAccount>[]> {
-		let filters = undefined;
-		if (!includeIdle) {
-			filters = [
+	public async updateUserThirdPartyDepositsDisabled(
+		thirdPartyDepositsDisabled: boolean,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const tx =
+			await this.program.transaction.updateUserThirdPartyDepositsDisabled(
+				subAccountId,
+				thirdPartyDepositsDisabled,
 				{
-					memcmp: {
-						offset: 4350,
-						bytes: bs58.encode(Uint8Array.from([0])),
+					accounts: {
+						user: getUserAccountPublicKeySync(
+							this.program.programId,
+							this.wallet.publicKey,
+							subAccountId
+						),
+						authority: this.wallet.publicKey,
 					},
-				},
-			];
-		}
-		return (await this.program.account.user.all(
-			filters
-		)) as ProgramAccount<UserAccount>[];
+				}
+			);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async getUserAccountsForDelegate(
-		delegate: PublicKey
-	): Promise<UserAccount[]> {
-		const programAccounts = await this.program.account.user.all([
+	public async updateUserPerpLpTier(
+		perpLpTier: PerpLpTier,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateUserPerpLpTier(
+			subAccountId,
+			perpLpTier,
 			{
-				memcmp: {
-					offset: 40,
-					/** data to match, as base-58 encoded string and limited to less than 129 bytes */
-					bytes: bs58.encode(delegate.toBuffer()),
+				accounts: {
+					user: getUserAccountPublicKeySync(
+						this.program.programId,
+						this.wallet.publicKey,
+						subAccountId
+					),
+					authority: this.wallet.publicKey,
 				},
-			},
-		]);
-
-		return programAccounts.map(
-			(programAccount) => programAccount.account as UserAccount
+			}
 		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async getUserAccountsForAuthority(
-		authority: PublicKey
-	): Promise<UserAccount[]> {
-		const programAccounts = await this.program.account.user.all([
+	public async enableUserHighLeverageMode(
+		marketIndex: number,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.enableUserHighLeverageMode(
+			subAccountId,
 			{
-				memcmp: {
-					offset: 8,
-					/** data to match, as base-58 encoded string and limited to less than 129 bytes */
-					bytes: bs58.encode(authority.toBuffer()),
+				accounts: {
+					user: getUserAccountPublicKeySync(
+						this.program.programId,
+						this.wallet.publicKey,
+						subAccountId
+					),
+					authority: this.wallet.publicKey,
+					highLeverageModeConfig: getHighLeverageModeConfigPublicKey(
+						this.program.programId,
+						marketIndex
+					),
 				},
-			},
-		]);
-
-		return programAccounts.map(
-			(programAccount) => programAccount.account as UserAccount
+			}
 		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async getReferrerNameAccountsForAuthority(
-		authority: PublicKey
-	): Promise<ReferrerNameAccount[]> {
-		const programAccounts = await this.program.account.referrerName.all([
+	public async disableUserHighLeverageMode(
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		await this.addUser(subAccountId);
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount(subAccountId)],
+		});
+
+		const tx = await this.program.transaction.disableUserHighLeverageMode(
+			subAccountId,
 			{
-				memcmp: {
-					offset: 8,
-					/** data to match, as base-58 encoded string and limited to less than 129 bytes */
-					bytes: bs58.encode(authority.toBuffer()),
+				accounts: {
+					user: getUserAccountPublicKeySync(
+						this.program.programId,
+						this.wallet.publicKey,
+						subAccountId
+					),
+					authority: this.wallet.publicKey,
+					highLeverageModeConfig: getHighLeverageModeConfigPublicKey(
+						this.program.programId,
+						this.getUserAccount(subAccountId).highLeverageModeMarketIndex
+					),
 				},
-			},
-		]);
-
-		return programAccounts.map(
-			(programAccount) => programAccount.account as ReferrerNameAccount
+				remainingAccounts,
+			}
 		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async deleteUser(
+	/**
+	 * Runs the program's margin engine against the user, optionally after a hypothetical perp or
+	 * spot fill including fees, without sending a transaction
+	 * @param orderParams order to simulate filling in full at its price, or the oracle price if 0
+	 * @param marketType market type of orderParams
+	 */
+	public async simulateMarginHealth(
+		orderParams?: OptionalOrderParams,
 		subAccountId = 0,
-		txParams?: TxParams
-	): Promise<TransactionSignature> {
-		const userAccountPublicKey = getUserAccountPublicKeySync(
-			this.program.programId,
-			this.wallet.publicKey,
-			subAccountId
+		marketType = MarketType.PERP
+	): Promise<MarginHealth> {
+		const tx = new Transaction().add(
+			await this.getSimulateMarginHealthIx(
+				orderParams,
+				subAccountId,
+				marketType
+			)
 		);
+		tx.feePayer = this.wallet.publicKey;
 
-		const ix = await this.program.instruction.deleteUser({
+		const { value } = await this.connection.simulateTransaction(tx);
+		if (value.err || !value.returnData) {
+			throw new Error(
+				`simulate margin health failed: ${JSON.stringify(value.err)}`
+			);
+		}
+
+		const data = Buffer.from(value.returnData.data[0], 'base64');
+		const read = (offset: number, bytes: number) =>
+			new BN(data.subarray(offset, offset + bytes), 'le');
+		return {
+			initialTotalCollateral: read(0, 16).fromTwos(128),
+			initialMarginRequirement: read(16, 16),
+			maintenanceTotalCollateral: read(32, 16).fromTwos(128),
+			maintenanceMarginRequirement: read(48, 16),
+			liquidationPrice: read(64, 8).fromTwos(64),
+		};
+	}
+
+	public async getSimulateMarginHealthIx(
+		orderParams?: OptionalOrderParams,
+		subAccountId = 0,
+		marketType = MarketType.PERP
+	): Promise<TransactionInstruction> {
+		const params = orderParams
+			? this.getOrderParams(orderParams, marketType)
+			: null;
+		const isPerp = isVariant(marketType, 'perp');
+
+		await this.addUser(subAccountId);
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount(subAccountId)],
+			readablePerpMarketIndex: isPerp ? params?.marketIndex : undefined,
+			readableSpotMarketIndexes:
+				params && !isPerp
+					? [QUOTE_SPOT_MARKET_INDEX, params.marketIndex]
+					: undefined,
+		});
+		if (params) {
+			this.addMarketFeeOverrideToRemainingAccounts(
+				remainingAccounts,
+				marketType,
+				params.marketIndex
+			);
+		}
+
+		return await this.program.instruction.simulateMarginHealth(params, {
 			accounts: {
-				user: userAccountPublicKey,
-				userStats: this.getUserStatsAccountPublicKey(),
-				authority: this.wallet.publicKey,
 				state: await this.getStatePublicKey(),
+				user: getUserAccountPublicKeySync(
+					this.program.programId,
+					this.wallet.publicKey,
+					subAccountId
+				),
+				userStats: this.getUserStatsAccountPublicKey(),
 			},
+			remainingAccounts,
 		});
+	}
 
-		const { txSig } = await this.sendTransaction(
-			wrapInTx(ix, txParams?.computeUnits, txParams?.computeUnitsPrice),
-			[],
-			this.opts
+	public async updatePerpPositionIsolated(
+		perpMarketIndex: number,
+		isolated: boolean,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updatePerpPositionIsolated(
+			subAccountId,
+			perpMarketIndex,
+			isolated,
+			{
+				accounts: {
+					user: getUserAccountPublicKeySync(
+						this.program.programId,
+						this.wallet.publicKey,
+						subAccountId
+					),
+					authority: this.wallet.publicKey,
+				},
+			}
 		);
 
-		await this.users.get(subAccountId)?.unsubscribe();
-		this.users.delete(subAccountId);
-
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
 		return txSig;
 	}
 
-	public getUser(subAccountId?: number): User {
-		subAccountId = subAccountId ?? this.activeSubAccountId;
-		if (!this.users.has(subAccountId)) {
-			throw new Error(`Clearing House has no user for user id ${subAccountId}`);
-		}
-		return this.users.get(subAccountId);
-	}
-
-	public getUsers(): User[] {
-		return [...this.users.values()];
-	}
-
-	public getUserStats(): UserStats {
-		return this.userStats;
-	}
-
-	public async fetchReferrerNameAccount(
-		name: string
-	): Promise<ReferrerNameAccount | undefined> {
-		const nameBuffer = encodeName(name);
-		const referrerNameAccountPublicKey = getReferrerNamePublicKeySync(
+	/**
+	 * Moves quote collateral between the cross account and an isolated perp position
+	 * @param amount positive to fund the isolated position, negative to return collateral to cross
+	 */
+	public async transferIsolatedPerpPositionDeposit(
+		perpMarketIndex: number,
+		amount: BN,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
 			this.program.programId,
-			nameBuffer
+			this.wallet.publicKey,
+			subAccountId
 		);
-		return (await this.program.account.referrerName.fetch(
-			referrerNameAccountPublicKey
-		)) as ReferrerNameAccount;
-	}
-
-	userStatsAccountPublicKey: PublicKey;
-	public getUserStatsAccountPublicKey(): PublicKey {
-		if (this.userStatsAccountPublicKey) {
-			return this.userStatsAccountPublicKey;
-		}
+
+		await this.addUser(subAccountId);
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount(subAccountId)],
+			readablePerpMarketIndex: perpMarketIndex,
+			writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
+
+		const tx = await this.program.transaction.transferIsolatedPerpPositionDeposit(
+			subAccountId,
+			perpMarketIndex,
+			amount,
+			{
+				accounts: {
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+				},
+				remainingAccounts,
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updateUserDelegate(
+		delegate: PublicKey,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateUserDelegate(
+			subAccountId,
+			delegate,
+			{
+				accounts: {
+					user: await this.getUserAccountPublicKey(),
+					authority: this.wallet.publicKey,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	/**
+	 * @param delegatePermissions bitmask of DelegatePermission, 0 leaves the delegate unrestricted
+	 * @param delegateExpirySlot slot after which the delegate can no longer sign, 0 never expires
+	 */
+	public async updateUserDelegatePermissions(
+		delegatePermissions: number,
+		delegateExpirySlot = 0,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateUserDelegatePermissions(
+			subAccountId,
+			delegatePermissions,
+			delegateExpirySlot,
+			{
+				accounts: {
+					user: getUserAccountPublicKeySync(
+						this.program.programId,
+						this.wallet.publicKey,
+						subAccountId
+					),
+					authority: this.wallet.publicKey,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	/**
+	 * Rejects new non reduce only orders across all sub accounts until the timestamp.
+	 * A delegate can only extend it.
+	 * @param disableTradingUntil unix timestamp, 0 clears it
+	 */
+	public async updateUserDisableTradingUntil(
+		disableTradingUntil: BN,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.updateUserDisableTradingUntil(
+			disableTradingUntil,
+			{
+				accounts: {
+					user: getUserAccountPublicKeySync(
+						this.program.programId,
+						this.wallet.publicKey,
+						subAccountId
+					),
+					userStats: this.getUserStatsAccountPublicKey(),
+					authority: this.wallet.publicKey,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async fetchAllUserAccounts(
+		includeIdle = true
+	): Promise<ProgramAccount<UserAccount>[]> {
+		let filters = undefined;
+		if (!includeIdle) {
+			filters = [
+				{
+					memcmp: {
+						offset: 10110,
+						bytes: bs58.encode(Uint8Array.from([0])),
+					},
+				},
+			];
+		}
+		return (await this.program.account.user.all(
+			filters
+		)) as ProgramAccount<UserAccount>[];
+	}
+
+	public async getUserAccountsForDelegate(
+		delegate: PublicKey
+	): Promise<UserAccount[]> {
+		const programAccounts = await this.program.account.user.all([
+			{
+				memcmp: {
+					offset: 40,
+					/** data to match, as base-58 encoded string and limited to less than 129 bytes */
+					bytes: bs58.encode(delegate.toBuffer()),
+				},
+			},
+		]);
+
+		return programAccounts.map(
+			(programAccount) => programAccount.account as UserAccount
+		);
+	}
+
+	public async getUserAccountsForAuthority(
+		authority: PublicKey
+	): Promise<UserAccount[]> {
+		const programAccounts = await this.program.account.user.all([
+			{
+				memcmp: {
+					offset: 8,
+					/** data to match, as base-58 encoded string and limited to less than 129 bytes */
+					bytes: bs58.encode(authority.toBuffer()),
+				},
+			},
+		]);
+
+		return programAccounts.map(
+			(programAccount) => programAccount.account as UserAccount
+		);
+	}
+
+	public async getReferrerNameAccountsForAuthority(
+		authority: PublicKey
+	): Promise<ReferrerNameAccount[]> {
+		const programAccounts = await this.program.account.referrerName.all([
+			{
+				memcmp: {
+					offset: 8,
+					/** data to match, as base-58 encoded string and limited to less than 129 bytes */
+					bytes: bs58.encode(authority.toBuffer()),
+				},
+			},
+		]);
+
+		return programAccounts.map(
+			(programAccount) => programAccount.account as ReferrerNameAccount
+		);
+	}
+
+	public async deleteUser(
+		subAccountId = 0,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const ix = await this.program.instruction.deleteUser({
+			accounts: {
+				user: userAccountPublicKey,
+				userStats: this.getUserStatsAccountPublicKey(),
+				authority: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+			},
+		});
+
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(ix, txParams?.computeUnits, txParams?.computeUnitsPrice),
+			[],
+			this.opts
+		);
+
+		await this.users.get(subAccountId)?.unsubscribe();
+		this.users.delete(subAccountId);
+
+		return txSig;
+	}
+
+	public getUser(subAccountId?: number): User {
+		subAccountId = subAccountId ?? this.activeSubAccountId;
+		if (!this.users.has(subAccountId)) {
+			throw new Error(`Clearing House has no user for user id ${subAccountId}`);
+		}
+		return this.users.get(subAccountId);
+	}
+
+	public getUsers(): User[] {
+		return [...this.users.values()];
+	}
+
+	public getUserStats(): UserStats {
+		return this.userStats;
+	}
+
+	public async fetchReferrerNameAccount(
+		name: string
+	): Promise<ReferrerNameAccount | undefined> {
+		const nameBuffer = encodeName(name);
+		const referrerNameAccountPublicKey = getReferrerNamePublicKeySync(
+			this.program.programId,
+			nameBuffer
+		);
+		return (await this.program.account.referrerName.fetch(
+			referrerNameAccountPublicKey
+		)) as ReferrerNameAccount;
+	}
+
+	userStatsAccountPublicKey: PublicKey;
+	public getUserStatsAccountPublicKey(): PublicKey {
+		if (this.userStatsAccountPublicKey) {
+			return this.userStatsAccountPublicKey;
+		}
 
 		this.userStatsAccountPublicKey = getUserStatsAccountPublicKey(
 			this.program.programId,
@@ -919,41 +1356,205 @@ export class DriftClient {
 		);
 	}
 
-	getRemainingAccounts(params: RemainingAccountParams): AccountMeta[] {
-		const { oracleAccountMap, spotMarketAccountMap, perpMarketAccountMap } =
-			this.getRemainingAccountMapsForUsers(params.userAccounts);
-
-		if (params.useMarketLastSlotCache) {
-			const lastUserSlot = this.getUserAccountAndSlot()?.slot;
-			for (const [
-				marketIndex,
-				slot,
-			] of this.perpMarketLastSlotCache.entries()) {
-				// if cache has more recent slot than user positions account slot, add market to remaining accounts
-				// otherwise remove from slot
-				if (slot > lastUserSlot) {
-					const marketAccount = this.getPerpMarketAccount(marketIndex);
-					perpMarketAccountMap.set(marketIndex, {
-						pubkey: marketAccount.pubkey,
-						isSigner: false,
-						isWritable: false,
-					});
-					oracleAccountMap.set(marketAccount.amm.oracle.toString(), {
-						pubkey: marketAccount.amm.oracle,
-						isSigner: false,
-						isWritable: false,
-					});
-				} else {
-					this.perpMarketLastSlotCache.delete(marketIndex);
-				}
-			}
+	/**
+	 * Fills in a market with a fee override enabled must pass the override right after the market accounts
+	 */
+	addMarketFeeOverrideToRemainingAccounts(
+		remainingAccounts: AccountMeta[],
+		marketType: MarketType,
+		marketIndex: number
+	): void {
+		const feeOverrideEnabled = isVariant(marketType, 'perp')
+			? this.getPerpMarketAccount(marketIndex)?.amm.feeOverrideEnabled
+			: this.getSpotMarketAccount(marketIndex)?.feeOverrideEnabled;
 
-			for (const [
-				marketIndex,
-				slot,
-			] of this.spotMarketLastSlotCache.entries()) {
-				// if cache has more recent slot than user positions account slot, add market to remaining accounts
-				// otherwise remove from slot
+		if (feeOverrideEnabled) {
+			remainingAccounts.push({
+				pubkey: getMarketFeeOverridePublicKey(
+					this.program.programId,
+					marketType,
+					marketIndex
+				),
+				isWritable: false,
+				isSigner: false,
+			});
+		}
+	}
+
+	/**
+	 * Fills of a user with a fill callback must pass its UserFillCallback, the whitelisted
+	 * FillCallbackProgram and the callback program right after the market fee override
+	 */
+	async addFillCallbackToRemainingAccounts(
+		remainingAccounts: AccountMeta[],
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount
+	): Promise<void> {
+		if (!userAccount.hasFillCallback) {
+			return;
+		}
+
+		const userFillCallbackPublicKey = getUserFillCallbackPublicKeySync(
+			this.program.programId,
+			userAccountPublicKey
+		);
+		const userFillCallback =
+			(await this.program.account.userFillCallback.fetch(
+				userFillCallbackPublicKey
+			)) as UserFillCallback;
+
+		remainingAccounts.push(
+			{
+				pubkey: userFillCallbackPublicKey,
+				isWritable: false,
+				isSigner: false,
+			},
+			{
+				pubkey: getFillCallbackProgramPublicKey(
+					this.program.programId,
+					userFillCallback.programId
+				),
+				isWritable: false,
+				isSigner: false,
+			},
+			{
+				pubkey: userFillCallback.programId,
+				isWritable: false,
+				isSigner: false,
+			}
+		);
+	}
+
+	/**
+	 * Orders in a market with its whitelist enabled must pass the authority's whitelist entry right after the market accounts.
+	 * Fills pass an entry for the taker and for each maker, makers without one are skipped
+	 */
+	addMarketWhitelistEntryToRemainingAccounts(
+		remainingAccounts: AccountMeta[],
+		marketIndex: number,
+		...authorities: PublicKey[]
+	): void {
+		if (!this.getPerpMarketAccount(marketIndex)?.amm.whitelistEnabled) {
+			return;
+		}
+
+		const added = new Set<string>();
+		for (const authority of authorities) {
+			if (added.has(authority.toBase58())) {
+				continue;
+			}
+			added.add(authority.toBase58());
+
+			remainingAccounts.push({
+				pubkey: getMarketWhitelistEntryPublicKey(
+					this.program.programId,
+					marketIndex,
+					authority
+				),
+				isWritable: false,
+				isSigner: false,
+			});
+		}
+	}
+
+	/**
+	 * Fills for a taker whose referrer shares its reward must also pass the referrer's own referrer
+	 */
+	addSecondLevelReferrerToRemainingAccounts(
+		remainingAccounts: AccountMeta[],
+		referrerInfo: ReferrerInfo
+	): void {
+		if (
+			referrerInfo.secondLevelReferrer === undefined ||
+			referrerInfo.secondLevelReferrerStats === undefined
+		) {
+			return;
+		}
+
+		const alreadyIncluded = remainingAccounts.some((account) =>
+			account.pubkey.equals(referrerInfo.secondLevelReferrer)
+		);
+		if (alreadyIncluded) {
+			return;
+		}
+
+		remainingAccounts.push({
+			pubkey: referrerInfo.secondLevelReferrer,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: referrerInfo.secondLevelReferrerStats,
+			isWritable: true,
+			isSigner: false,
+		});
+	}
+
+	/**
+	 * Makers are passed once each, and sub accounts of the same authority share one user stats
+	 */
+	addMakersToRemainingAccounts(
+		remainingAccounts: AccountMeta[],
+		makerInfo: MakerInfo[]
+	): void {
+		const isIncluded = (pubkey: PublicKey) =>
+			remainingAccounts.some((account) => account.pubkey.equals(pubkey));
+
+		for (const maker of makerInfo) {
+			if (isIncluded(maker.maker)) {
+				continue;
+			}
+
+			remainingAccounts.push({
+				pubkey: maker.maker,
+				isWritable: true,
+				isSigner: false,
+			});
+			if (!isIncluded(maker.makerStats)) {
+				remainingAccounts.push({
+					pubkey: maker.makerStats,
+					isWritable: true,
+					isSigner: false,
+				});
+			}
+		}
+	}
+
+	getRemainingAccounts(params: RemainingAccountParams): AccountMeta[] {
+		const { oracleAccountMap, spotMarketAccountMap, perpMarketAccountMap } =
+			this.getRemainingAccountMapsForUsers(params.userAccounts);
+
+		if (params.useMarketLastSlotCache) {
+			const lastUserSlot = this.getUserAccountAndSlot()?.slot;
+			for (const [
+				marketIndex,
+				slot,
+			] of this.perpMarketLastSlotCache.entries()) {
+				// if cache has more recent slot than user positions account slot, add market to remaining accounts
+				// otherwise remove from slot
+				if (slot > lastUserSlot) {
+					const marketAccount = this.getPerpMarketAccount(marketIndex);
+					perpMarketAccountMap.set(marketIndex, {
+						pubkey: marketAccount.pubkey,
+						isSigner: false,
+						isWritable: false,
+					});
+					oracleAccountMap.set(marketAccount.amm.oracle.toString(), {
+						pubkey: marketAccount.amm.oracle,
+						isSigner: false,
+						isWritable: false,
+					});
+				} else {
+					this.perpMarketLastSlotCache.delete(marketIndex);
+				}
+			}
+
+			for (const [
+				marketIndex,
+				slot,
+			] of this.spotMarketLastSlotCache.entries()) {
+				// if cache has more recent slot than user positions account slot, add market to remaining accounts
+				// otherwise remove from slot
 				if (slot > lastUserSlot) {
 					const marketAccount = this.getSpotMarketAccount(marketIndex);
 					spotMarketAccountMap.set(marketIndex, {
@@ -1113,6 +1714,20 @@ export class DriftClient {
 			}
 		}
 
+		// perp markets priced off a median need all of their oracles
+		for (const marketIndex of perpMarketAccountMap.keys()) {
+			const marketAccount = this.getPerpMarketAccount(marketIndex);
+			for (const oracle of marketAccount.additionalOracles) {
+				if (!oracle.equals(PublicKey.default)) {
+					oracleAccountMap.set(oracle.toString(), {
+						pubkey: oracle,
+						isSigner: false,
+						isWritable: false,
+					});
+				}
+			}
+		}
+
 		return {
 			oracleAccountMap,
 			spotMarketAccountMap,
@@ -1234,6 +1849,7 @@ export class DriftClient {
 		}
 
 		const spotMarketAccount = this.getSpotMarketAccount(marketIndex);
+		this.addTokenMintToRemainingAccounts(spotMarketAccount, remainingAccounts);
 
 		return await this.program.instruction.deposit(
 			marketIndex,
@@ -1248,13 +1864,98 @@ export class DriftClient {
 					userStats: this.getUserStatsAccountPublicKey(),
 					userTokenAccount: userTokenAccount,
 					authority: this.wallet.publicKey,
-					tokenProgram: TOKEN_PROGRAM_ID,
+					tokenProgram: this.getTokenProgramForSpotMarket(spotMarketAccount),
 				},
 				remainingAccounts,
 			}
 		);
 	}
 
+	/**
+	 * Deposits from the wallet's token account into another authority's user,
+	 * the wallet gets no rights over the user. Only tops up spot positions the
+	 * user already holds
+	 */
+	public async depositIntoUser(
+		amount: BN,
+		marketIndex: number,
+		userAccountPublicKey: PublicKey,
+		userTokenAccount: PublicKey,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig, slot } = await this.sendTransaction(
+			wrapInTx(
+				await this.getDepositIntoUserIx(
+					amount,
+					marketIndex,
+					userAccountPublicKey,
+					userTokenAccount
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		this.spotMarketLastSlotCache.set(marketIndex, slot);
+		return txSig;
+	}
+
+	public async getDepositIntoUserIx(
+		amount: BN,
+		marketIndex: number,
+		userAccountPublicKey: PublicKey,
+		userTokenAccount: PublicKey
+	): Promise<TransactionInstruction> {
+		const userAccount = (await this.program.account.user.fetch(
+			userAccountPublicKey
+		)) as UserAccount;
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [userAccount],
+			writableSpotMarketIndexes: [marketIndex],
+		});
+
+		const spotMarketAccount = this.getSpotMarketAccount(marketIndex);
+		this.addTokenMintToRemainingAccounts(spotMarketAccount, remainingAccounts);
+
+		return await this.program.instruction.depositIntoUser(marketIndex, amount, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+				spotMarketVault: spotMarketAccount.vault,
+				userTokenAccount,
+				tokenProgram: this.getTokenProgramForSpotMarket(spotMarketAccount),
+			},
+			remainingAccounts,
+		});
+	}
+
+	public getTokenProgramForSpotMarket(
+		spotMarketAccount: SpotMarketAccount
+	): PublicKey {
+		return spotMarketAccount.tokenProgram === 1
+			? TOKEN_2022_PROGRAM_ID
+			: TOKEN_PROGRAM_ID;
+	}
+
+	/**
+	 * Token-2022 spot markets need the mint after the market and oracle accounts for transfers
+	 */
+	public addTokenMintToRemainingAccounts(
+		spotMarketAccount: SpotMarketAccount,
+		remainingAccounts: AccountMeta[]
+	): void {
+		if (spotMarketAccount.tokenProgram === 1) {
+			remainingAccounts.push({
+				pubkey: spotMarketAccount.mint,
+				isSigner: false,
+				isWritable: false,
+			});
+		}
+	}
+
 	private async checkIfAccountExists(account: PublicKey): Promise<boolean> {
 		try {
 			const accountInfo = await this.connection.getAccountInfo(account);
@@ -1591,6 +2292,7 @@ export class DriftClient {
 		});
 
 		const spotMarketAccount = this.getSpotMarketAccount(marketIndex);
+		this.addTokenMintToRemainingAccounts(spotMarketAccount, remainingAccounts);
 
 		return await this.program.instruction.withdraw(
 			marketIndex,
@@ -1606,59 +2308,242 @@ export class DriftClient {
 					userStats: this.getUserStatsAccountPublicKey(),
 					userTokenAccount: userTokenAccount,
 					authority: this.wallet.publicKey,
-					tokenProgram: TOKEN_PROGRAM_ID,
+					tokenProgram: this.getTokenProgramForSpotMarket(spotMarketAccount),
 				},
 				remainingAccounts,
 			}
 		);
 	}
 
-	public async transferDeposit(
+	/**
+	 * Borrows tokens from a spot market vault for the length of the transaction.
+	 * The amount plus the market's flash borrow fee must be in userTokenAccount by the end instruction
+	 * @param amount
+	 * @param marketIndex
+	 * @param userTokenAccount
+	 * @param ixs instructions that run between the borrow and the repayment
+	 * @param txParams
+	 */
+	public async flashBorrow(
 		amount: BN,
 		marketIndex: number,
-		fromSubAccountId: number,
-		toSubAccountId: number,
+		userTokenAccount: PublicKey,
+		ixs: TransactionInstruction[],
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
-		const { txSig, slot } = await this.sendTransaction(
-			wrapInTx(
-				await this.getTransferDepositIx(
-					amount,
-					marketIndex,
-					fromSubAccountId,
-					toSubAccountId
-				),
-				txParams?.computeUnits,
-				txParams?.computeUnitsPrice
-			),
-			[],
-			this.opts
+		const tx = wrapInTx(
+			await this.getBeginFlashBorrowIx(amount, marketIndex, userTokenAccount),
+			txParams?.computeUnits,
+			txParams?.computeUnitsPrice
 		);
-		if (
-			fromSubAccountId === this.activeSubAccountId ||
-			toSubAccountId === this.activeSubAccountId
-		) {
-			this.spotMarketLastSlotCache.set(marketIndex, slot);
-		}
+		tx.add(...ixs);
+		tx.add(
+			await this.getEndFlashBorrowIx(amount, marketIndex, userTokenAccount)
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
 		return txSig;
 	}
 
-	public async getTransferDepositIx(
+	public async getBeginFlashBorrowIx(
 		amount: BN,
 		marketIndex: number,
-		fromSubAccountId: number,
-		toSubAccountId: number
+		userTokenAccount: PublicKey
 	): Promise<TransactionInstruction> {
-		const fromUser = await getUserAccountPublicKey(
-			this.program.programId,
-			this.wallet.publicKey,
-			fromSubAccountId
-		);
-		const toUser = await getUserAccountPublicKey(
-			this.program.programId,
-			this.wallet.publicKey,
-			toSubAccountId
-		);
+		const spotMarketAccount = this.getSpotMarketAccount(marketIndex);
+		const remainingAccounts = [];
+		this.addTokenMintToRemainingAccounts(spotMarketAccount, remainingAccounts);
+
+		return await this.program.instruction.beginFlashBorrow(
+			marketIndex,
+			amount,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					spotMarket: spotMarketAccount.pubkey,
+					authority: this.wallet.publicKey,
+					spotMarketVault: spotMarketAccount.vault,
+					driftSigner: this.getSignerPublicKey(),
+					userTokenAccount: userTokenAccount,
+					tokenProgram: this.getTokenProgramForSpotMarket(spotMarketAccount),
+					ixSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
+	public async getEndFlashBorrowIx(
+		amount: BN,
+		marketIndex: number,
+		userTokenAccount: PublicKey
+	): Promise<TransactionInstruction> {
+		const spotMarketAccount = this.getSpotMarketAccount(marketIndex);
+		const remainingAccounts = [];
+		this.addTokenMintToRemainingAccounts(spotMarketAccount, remainingAccounts);
+
+		return await this.program.instruction.endFlashBorrow(marketIndex, amount, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				spotMarket: spotMarketAccount.pubkey,
+				authority: this.wallet.publicKey,
+				spotMarketVault: spotMarketAccount.vault,
+				userTokenAccount: userTokenAccount,
+				tokenProgram: this.getTokenProgramForSpotMarket(spotMarketAccount),
+				ixSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+			},
+			remainingAccounts,
+		});
+	}
+
+	/**
+	 * Swaps deposits between two spot markets through an external router (e.g. a Jupiter route).
+	 * The margin requirement is only checked once the swap has settled
+	 * @param inMarketIndex market the tokens are withdrawn from
+	 * @param outMarketIndex market the swapped tokens are deposited into
+	 * @param amountIn
+	 * @param inTokenAccount
+	 * @param outTokenAccount
+	 * @param ixs router instructions that swap from inTokenAccount into outTokenAccount
+	 * @param txParams
+	 */
+	public async swap(
+		inMarketIndex: number,
+		outMarketIndex: number,
+		amountIn: BN,
+		inTokenAccount: PublicKey,
+		outTokenAccount: PublicKey,
+		ixs: TransactionInstruction[],
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { beginSwapIx, endSwapIx } = await this.getSwapIxs(
+			inMarketIndex,
+			outMarketIndex,
+			amountIn,
+			inTokenAccount,
+			outTokenAccount
+		);
+
+		const tx = wrapInTx(
+			beginSwapIx,
+			txParams?.computeUnits,
+			txParams?.computeUnitsPrice
+		);
+		tx.add(...ixs);
+		tx.add(endSwapIx);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async getSwapIxs(
+		inMarketIndex: number,
+		outMarketIndex: number,
+		amountIn: BN,
+		inTokenAccount: PublicKey,
+		outTokenAccount: PublicKey
+	): Promise<{
+		beginSwapIx: TransactionInstruction;
+		endSwapIx: TransactionInstruction;
+	}> {
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount()],
+			writableSpotMarketIndexes: [inMarketIndex, outMarketIndex],
+			readableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
+
+		const inSpotMarketAccount = this.getSpotMarketAccount(inMarketIndex);
+		const outSpotMarketAccount = this.getSpotMarketAccount(outMarketIndex);
+		this.addTokenMintToRemainingAccounts(inSpotMarketAccount, remainingAccounts);
+		this.addTokenMintToRemainingAccounts(
+			outSpotMarketAccount,
+			remainingAccounts
+		);
+
+		const accounts = {
+			state: await this.getStatePublicKey(),
+			user: userAccountPublicKey,
+			authority: this.wallet.publicKey,
+			outSpotMarketVault: outSpotMarketAccount.vault,
+			inSpotMarketVault: inSpotMarketAccount.vault,
+			outTokenAccount,
+			inTokenAccount,
+			outTokenProgram: this.getTokenProgramForSpotMarket(outSpotMarketAccount),
+			inTokenProgram: this.getTokenProgramForSpotMarket(inSpotMarketAccount),
+			driftSigner: this.getSignerPublicKey(),
+			ixSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+		};
+
+		const beginSwapIx = await this.program.instruction.beginSwap(
+			inMarketIndex,
+			outMarketIndex,
+			amountIn,
+			{
+				accounts,
+				remainingAccounts,
+			}
+		);
+
+		const endSwapIx = await this.program.instruction.endSwap(
+			inMarketIndex,
+			outMarketIndex,
+			{
+				accounts,
+				remainingAccounts,
+			}
+		);
+
+		return { beginSwapIx, endSwapIx };
+	}
+
+	public async transferDeposit(
+		amount: BN,
+		marketIndex: number,
+		fromSubAccountId: number,
+		toSubAccountId: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig, slot } = await this.sendTransaction(
+			wrapInTx(
+				await this.getTransferDepositIx(
+					amount,
+					marketIndex,
+					fromSubAccountId,
+					toSubAccountId
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		if (
+			fromSubAccountId === this.activeSubAccountId ||
+			toSubAccountId === this.activeSubAccountId
+		) {
+			this.spotMarketLastSlotCache.set(marketIndex, slot);
+		}
+		return txSig;
+	}
+
+	public async getTransferDepositIx(
+		amount: BN,
+		marketIndex: number,
+		fromSubAccountId: number,
+		toSubAccountId: number
+	): Promise<TransactionInstruction> {
+		const fromUser = await getUserAccountPublicKey(
+			this.program.programId,
+			this.wallet.publicKey,
+			fromSubAccountId
+		);
+		const toUser = await getUserAccountPublicKey(
+			this.program.programId,
+			this.wallet.publicKey,
+			toSubAccountId
+		);
 
 		let remainingAccounts;
 		if (this.users.has(fromSubAccountId)) {
@@ -1697,6 +2582,75 @@ export class DriftClient {
 		});
 	}
 
+	/**
+	 * Moves every idle spot deposit and the collateral of flat isolated perp positions
+	 * from one sub account to another
+	 */
+	public async transferPositionsBetweenSubaccounts(
+		fromSubAccountId: number,
+		toSubAccountId: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getTransferPositionsBetweenSubaccountsIx(
+					fromSubAccountId,
+					toSubAccountId
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getTransferPositionsBetweenSubaccountsIx(
+		fromSubAccountId: number,
+		toSubAccountId: number
+	): Promise<TransactionInstruction> {
+		const fromUser = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			fromSubAccountId
+		);
+		const toUser = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			toSubAccountId
+		);
+
+		const fromUserAccount = this.users.has(fromSubAccountId)
+			? this.users.get(fromSubAccountId).getUserAccount()
+			: ((await this.program.account.user.fetch(fromUser)) as UserAccount);
+
+		const writableSpotMarketIndexes = fromUserAccount.spotPositions
+			.filter(
+				(spotPosition) =>
+					isVariant(spotPosition.balanceType, 'deposit') &&
+					!spotPosition.scaledBalance.eq(ZERO) &&
+					spotPosition.openOrders === 0
+			)
+			.map((spotPosition) => spotPosition.marketIndex);
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [fromUserAccount],
+			useMarketLastSlotCache: true,
+			writableSpotMarketIndexes,
+		});
+
+		return await this.program.instruction.transferPositionsBetweenSubaccounts({
+			accounts: {
+				fromUser,
+				toUser,
+				authority: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+			},
+			remainingAccounts,
+		});
+	}
+
 	public async updateSpotMarketCumulativeInterest(
 		marketIndex: number,
 		txParams?: TxParams
@@ -1726,6 +2680,36 @@ export class DriftClient {
 		});
 	}
 
+	public async updateLstOracle(
+		stakePool: PublicKey,
+		solOracle: PublicKey,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getUpdateLstOracleIx(stakePool, solOracle),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getUpdateLstOracleIx(
+		stakePool: PublicKey,
+		solOracle: PublicKey
+	): Promise<TransactionInstruction> {
+		return await this.program.instruction.updateLstOracle({
+			accounts: {
+				lstOracle: getLstOraclePublicKey(this.program.programId, stakePool),
+				solOracle,
+				stakePool,
+			},
+		});
+	}
+
 	public async settleLP(
 		settleeUserAccountPublicKey: PublicKey,
 		marketIndex: number,
@@ -1756,23 +2740,771 @@ export class DriftClient {
 			writablePerpMarketIndexes: [marketIndex],
 		});
 
-		return this.program.instruction.settleLp(marketIndex, {
-			accounts: {
-				state: await this.getStatePublicKey(),
-				user: settleeUserAccountPublicKey,
+		return this.program.instruction.settleLp(marketIndex, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				user: settleeUserAccountPublicKey,
+			},
+			remainingAccounts: remainingAccounts,
+		});
+	}
+
+	public async removePerpLpShares(
+		marketIndex: number,
+		sharesToBurn?: BN,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getRemovePerpLpSharesIx(marketIndex, sharesToBurn),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async removePerpLpSharesInExpiringMarket(
+		marketIndex: number,
+		userAccountPublicKey: PublicKey,
+		sharesToBurn?: BN,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getRemovePerpLpSharesInExpiringMarket(
+					marketIndex,
+					userAccountPublicKey,
+					sharesToBurn
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getRemovePerpLpSharesInExpiringMarket(
+		marketIndex: number,
+		userAccountPublicKey: PublicKey,
+		sharesToBurn?: BN
+	): Promise<TransactionInstruction> {
+		const userAccount = (await this.program.account.user.fetch(
+			userAccountPublicKey
+		)) as UserAccount;
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [userAccount],
+			useMarketLastSlotCache: true,
+			writablePerpMarketIndexes: [marketIndex],
+		});
+
+		if (sharesToBurn == undefined) {
+			const perpPosition = userAccount.perpPositions.filter(
+				(position) => position.marketIndex === marketIndex
+			)[0];
+			sharesToBurn = perpPosition.lpShares;
+			console.log('burning lp shares:', sharesToBurn.toString());
+		}
+
+		return this.program.instruction.removePerpLpSharesInExpiringMarket(
+			sharesToBurn,
+			marketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+				},
+				remainingAccounts: remainingAccounts,
+			}
+		);
+	}
+
+	public async getRemovePerpLpSharesIx(
+		marketIndex: number,
+		sharesToBurn?: BN
+	): Promise<TransactionInstruction> {
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount()],
+			useMarketLastSlotCache: true,
+			writablePerpMarketIndexes: [marketIndex],
+		});
+
+		if (sharesToBurn == undefined) {
+			const userAccount = this.getUserAccount();
+			const perpPosition = userAccount.perpPositions.filter(
+				(position) => position.marketIndex === marketIndex
+			)[0];
+			sharesToBurn = perpPosition.lpShares;
+			console.log('burning lp shares:', sharesToBurn.toString());
+		}
+
+		return this.program.instruction.removePerpLpShares(
+			sharesToBurn,
+			marketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+				},
+				remainingAccounts: remainingAccounts,
+			}
+		);
+	}
+
+	public async addPerpLpShares(
+		amount: BN,
+		marketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig, slot } = await this.sendTransaction(
+			wrapInTx(
+				await this.getAddPerpLpSharesIx(amount, marketIndex),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		this.perpMarketLastSlotCache.set(marketIndex, slot);
+		return txSig;
+	}
+
+	public async getAddPerpLpSharesIx(
+		amount: BN,
+		marketIndex: number
+	): Promise<TransactionInstruction> {
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount()],
+			useMarketLastSlotCache: true,
+			writablePerpMarketIndexes: [marketIndex],
+		});
+		this.addMarketWhitelistEntryToRemainingAccounts(
+			remainingAccounts,
+			marketIndex,
+			this.getUserAccount().authority
+		);
+
+		return this.program.instruction.addPerpLpShares(amount, marketIndex, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+			},
+			remainingAccounts: remainingAccounts,
+		});
+	}
+
+	public async initializePerpLpRange(
+		marketIndex: number,
+		lowerPrice: BN,
+		upperPrice: BN,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.initializePerpLpRange(
+			marketIndex,
+			lowerPrice,
+			upperPrice,
+			{
+				accounts: {
+					perpLpRange: getPerpLpRangePublicKeySync(
+						this.program.programId,
+						userAccountPublicKey,
+						marketIndex
+					),
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+					payer: this.wallet.publicKey,
+					rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
+		);
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async updatePerpLpRange(
+		marketIndex: number,
+		lowerPrice: BN,
+		upperPrice: BN,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.updatePerpLpRange(
+			marketIndex,
+			lowerPrice,
+			upperPrice,
+			{
+				accounts: {
+					perpLpRange: getPerpLpRangePublicKeySync(
+						this.program.programId,
+						userAccountPublicKey,
+						marketIndex
+					),
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+				},
+			}
+		);
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async openPosition(
+		direction: PositionDirection,
+		amount: BN,
+		marketIndex: number,
+		limitPrice?: BN
+	): Promise<TransactionSignature> {
+		return await this.placeAndTakePerpOrder({
+			orderType: OrderType.MARKET,
+			marketIndex,
+			direction,
+			baseAssetAmount: amount,
+			price: limitPrice,
+		});
+	}
+
+	public async sendSignedTx(tx: Transaction): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			tx,
+			undefined,
+			this.opts,
+			true
+		);
+
+		return txSig;
+	}
+
+	/**
+	 * Sends a market order and returns a signed tx which can fill the order against the vamm, which the caller can use to fill their own order if required.
+	 * @param orderParams
+	 * @param userAccountPublicKey
+	 * @param userAccount
+	 * @param makerInfo
+	 * @param txParams
+	 * @param bracketOrdersParams
+	 * @returns
+	 */
+	public async sendMarketOrderAndGetSignedFillTx(
+		orderParams: OptionalOrderParams,
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		makerInfo?: MakerInfo | MakerInfo[],
+		txParams?: TxParams,
+		bracketOrdersParams = new Array<OptionalOrderParams>(),
+		referrerInfo?: ReferrerInfo
+	): Promise<{ txSig: TransactionSignature; signedFillTx: Transaction }> {
+		const marketIndex = orderParams.marketIndex;
+		const orderId = userAccount.nextOrderId;
+
+		const marketOrderTx = wrapInTx(
+			await this.getPlacePerpOrderIx(orderParams),
+			txParams?.computeUnits,
+			txParams?.computeUnitsPrice
+		);
+		for (const bracketOrderParams of bracketOrdersParams) {
+			marketOrderTx.add(await this.getPlacePerpOrderIx(bracketOrderParams));
+		}
+
+		const fillTx = wrapInTx(
+			await this.getFillPerpOrderIx(
+				userAccountPublicKey,
+				userAccount,
+				{
+					orderId,
+					marketIndex,
+				},
+				makerInfo,
+				referrerInfo
+			),
+			txParams?.computeUnits,
+			txParams?.computeUnitsPrice
+		);
+
+		// Apply the latest blockhash to the txs so that we can sign before sending them
+		const currentBlockHash = (
+			await this.connection.getLatestBlockhash('finalized')
+		).blockhash;
+		marketOrderTx.recentBlockhash = currentBlockHash;
+		fillTx.recentBlockhash = currentBlockHash;
+
+		marketOrderTx.feePayer = userAccount.authority;
+		fillTx.feePayer = userAccount.authority;
+
+		const [signedMarketOrderTx, signedFillTx] =
+			await this.provider.wallet.signAllTransactions([marketOrderTx, fillTx]);
+
+		const { txSig, slot } = await this.sendTransaction(
+			signedMarketOrderTx,
+			[],
+			this.opts,
+			true
+		);
+
+		this.perpMarketLastSlotCache.set(orderParams.marketIndex, slot);
+
+		return { txSig, signedFillTx };
+	}
+
+	public async placePerpOrder(
+		orderParams: OptionalOrderParams,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig, slot } = await this.sendTransaction(
+			wrapInTx(
+				await this.getPlacePerpOrderIx(orderParams),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		this.perpMarketLastSlotCache.set(orderParams.marketIndex, slot);
+		return txSig;
+	}
+
+	getOrderParams(
+		optionalOrderParams: OptionalOrderParams,
+		marketType: MarketType
+	): OrderParams {
+		return Object.assign({}, DefaultOrderParams, optionalOrderParams, {
+			marketType,
+		});
+	}
+
+	public async getPlacePerpOrderIx(
+		orderParams: OptionalOrderParams
+	): Promise<TransactionInstruction> {
+		orderParams = this.getOrderParams(orderParams, MarketType.PERP);
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount()],
+			useMarketLastSlotCache: true,
+			readablePerpMarketIndex: orderParams.marketIndex,
+		});
+		this.addMarketWhitelistEntryToRemainingAccounts(
+			remainingAccounts,
+			orderParams.marketIndex,
+			this.getUserAccount().authority
+		);
+
+		return await this.program.instruction.placePerpOrder(orderParams, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				user: userAccountPublicKey,
+				userStats: this.getUserStatsAccountPublicKey(),
+				authority: this.wallet.publicKey,
+			},
+			remainingAccounts,
+		});
+	}
+
+	public async initializeSessionKey(
+		sessionSigner: PublicKey,
+		expiryTs: BN,
+		maxNotional: BN,
+		perpMarketIndexes: number[],
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.initializeSessionKey(
+			sessionSigner,
+			expiryTs,
+			maxNotional,
+			perpMarketIndexes,
+			{
+				accounts: {
+					sessionKey: getSessionKeyPublicKeySync(
+						this.program.programId,
+						userAccountPublicKey,
+						sessionSigner
+					),
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+					payer: this.wallet.publicKey,
+					rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
+		);
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async revokeSessionKey(
+		sessionSigner: PublicKey,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.revokeSessionKey({
+			accounts: {
+				sessionKey: getSessionKeyPublicKeySync(
+					this.program.programId,
+					userAccountPublicKey,
+					sessionSigner
+				),
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async placePerpOrderWithSessionKey(
+		orderParams: OptionalOrderParams,
+		sessionSigner: Keypair,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig, slot } = await this.sendTransaction(
+			wrapInTx(
+				await this.getPlacePerpOrderWithSessionKeyIx(
+					orderParams,
+					sessionSigner.publicKey
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[sessionSigner],
+			this.opts
+		);
+		this.perpMarketLastSlotCache.set(orderParams.marketIndex, slot);
+		return txSig;
+	}
+
+	public async getPlacePerpOrderWithSessionKeyIx(
+		orderParams: OptionalOrderParams,
+		sessionSigner: PublicKey
+	): Promise<TransactionInstruction> {
+		orderParams = this.getOrderParams(orderParams, MarketType.PERP);
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount()],
+			useMarketLastSlotCache: true,
+			readablePerpMarketIndex: orderParams.marketIndex,
+		});
+
+		return await this.program.instruction.placePerpOrderWithSessionKey(
+			orderParams,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+					userStats: this.getUserStatsAccountPublicKey(),
+					sessionKey: getSessionKeyPublicKeySync(
+						this.program.programId,
+						userAccountPublicKey,
+						sessionSigner
+					),
+					sessionSigner,
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
+	/**
+	 * Registers the wallet as a builder, fees are paid to its sub account 0
+	 */
+	public async initializeBuilder(name: string): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeBuilder(
+			encodeName(name),
+			{
+				accounts: {
+					builder: getBuilderPublicKeySync(
+						this.program.programId,
+						this.wallet.publicKey
+					),
+					authority: this.wallet.publicKey,
+					payer: this.wallet.publicKey,
+					rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
+		);
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	/**
+	 * @param maxFeeShare max percent of taker fees the builder can take, 0 stops builder fees
+	 */
+	public async approveBuilder(
+		builderAuthority: PublicKey,
+		maxFeeShare: number
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.approveBuilder(maxFeeShare, {
+			accounts: {
+				userStats: this.getUserStatsAccountPublicKey(),
+				builder: getBuilderPublicKeySync(
+					this.program.programId,
+					builderAuthority
+				),
+				authority: this.wallet.publicKey,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	/**
+	 * If the authority doesn't ping within timeoutDays, the keeper can reduce only close
+	 * positions and withdraw to token accounts owned by withdrawDestination
+	 */
+	public async initializeDeadManSwitch(
+		keeper: PublicKey,
+		withdrawDestination: PublicKey,
+		timeoutDays: number,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.initializeDeadManSwitch(
+			keeper,
+			withdrawDestination,
+			timeoutDays,
+			{
+				accounts: {
+					deadManSwitch: getDeadManSwitchPublicKeySync(
+						this.program.programId,
+						userAccountPublicKey
+					),
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+					payer: this.wallet.publicKey,
+					rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
+		);
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async pingDeadManSwitch(
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.pingDeadManSwitch({
+			accounts: {
+				deadManSwitch: getDeadManSwitchPublicKeySync(
+					this.program.programId,
+					userAccountPublicKey
+				),
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async deleteDeadManSwitch(
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.deleteDeadManSwitch({
+			accounts: {
+				deadManSwitch: getDeadManSwitchPublicKeySync(
+					this.program.programId,
+					userAccountPublicKey
+				),
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	/**
+	 * After each fill of the user's taker orders, the whitelisted callbackProgramId is invoked
+	 * with the fill receipt, signed by the user's UserFillCallback
+	 */
+	public async registerFillCallback(
+		callbackProgramId: PublicKey,
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.registerFillCallback({
+			accounts: {
+				userFillCallback: getUserFillCallbackPublicKeySync(
+					this.program.programId,
+					userAccountPublicKey
+				),
+				fillCallbackProgram: getFillCallbackProgramPublicKey(
+					this.program.programId,
+					callbackProgramId
+				),
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+				payer: this.wallet.publicKey,
+				rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+				systemProgram: anchor.web3.SystemProgram.programId,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async unregisterFillCallback(
+		subAccountId = 0
+	): Promise<TransactionSignature> {
+		const userAccountPublicKey = getUserAccountPublicKeySync(
+			this.program.programId,
+			this.wallet.publicKey,
+			subAccountId
+		);
+
+		const tx = await this.program.transaction.unregisterFillCallback({
+			accounts: {
+				userFillCallback: getUserFillCallbackPublicKeySync(
+					this.program.programId,
+					userAccountPublicKey
+				),
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	/**
+	 * Creates a vault and the user it owns, traded by this wallet as its delegate.
+	 * profitShare is in PERCENTAGE_PRECISION
+	 */
+	public async initializeVault(
+		name: string,
+		profitShare: number
+	): Promise<TransactionSignature> {
+		const nameBuffer = encodeName(name);
+		const vault = getVaultPublicKey(this.program.programId, nameBuffer);
+
+		const tx = await this.program.transaction.initializeVault(
+			nameBuffer,
+			profitShare,
+			{
+				accounts: {
+					vault,
+					user: getUserAccountPublicKeySync(
+						this.program.programId,
+						vault,
+						0
+					),
+					userStats: getUserStatsAccountPublicKey(
+						this.program.programId,
+						vault
+					),
+					state: await this.getStatePublicKey(),
+					manager: this.wallet.publicKey,
+					rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
+		);
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async initializeVaultDepositor(
+		vault: PublicKey
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeVaultDepositor({
+			accounts: {
+				vault,
+				vaultDepositor: getVaultDepositorPublicKey(
+					this.program.programId,
+					vault,
+					this.wallet.publicKey
+				),
+				authority: this.wallet.publicKey,
+				payer: this.wallet.publicKey,
+				rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+				systemProgram: anchor.web3.SystemProgram.programId,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async getVaultDepositors(
+		vault: PublicKey
+	): Promise<ProgramAccount<VaultDepositor>[]> {
+		return (await this.program.account.vaultDepositor.all([
+			{
+				memcmp: {
+					offset: 8,
+					bytes: bs58.encode(vault.toBuffer()),
+				},
 			},
-			remainingAccounts: remainingAccounts,
-		});
+		])) as ProgramAccount<VaultDepositor>[];
 	}
 
-	public async removePerpLpShares(
-		marketIndex: number,
-		sharesToBurn?: BN,
+	/**
+	 * Moves amount of the quote deposit of the wallet's sub account into the vault for shares
+	 */
+	public async vaultDeposit(
+		vault: PublicKey,
+		amount: BN,
+		subAccountId?: number,
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
 		const { txSig } = await this.sendTransaction(
 			wrapInTx(
-				await this.getRemovePerpLpSharesIx(marketIndex, sharesToBurn),
+				await this.getVaultDepositIx(vault, amount, subAccountId),
 				txParams?.computeUnits,
 				txParams?.computeUnitsPrice
 			),
@@ -1782,19 +3514,30 @@ export class DriftClient {
 		return txSig;
 	}
 
-	public async removePerpLpSharesInExpiringMarket(
-		marketIndex: number,
-		userAccountPublicKey: PublicKey,
-		sharesToBurn?: BN,
+	public async getVaultDepositIx(
+		vault: PublicKey,
+		amount: BN,
+		subAccountId?: number
+	): Promise<TransactionInstruction> {
+		return await this.program.instruction.vaultDeposit(
+			amount,
+			await this.getVaultDepositorTransferAccounts(vault, subAccountId)
+		);
+	}
+
+	/**
+	 * Redeems vault shares for a quote deposit into the wallet's sub account, after paying
+	 * profit share on gains above the depositor's high water mark
+	 */
+	public async vaultWithdraw(
+		vault: PublicKey,
+		shares: BN,
+		subAccountId?: number,
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
 		const { txSig } = await this.sendTransaction(
 			wrapInTx(
-				await this.getRemovePerpLpSharesInExpiringMarket(
-					marketIndex,
-					userAccountPublicKey,
-					sharesToBurn
-				),
+				await this.getVaultWithdrawIx(vault, shares, subAccountId),
 				txParams?.computeUnits,
 				txParams?.computeUnitsPrice
 			),
@@ -1804,252 +3547,252 @@ export class DriftClient {
 		return txSig;
 	}
 
-	public async getRemovePerpLpSharesInExpiringMarket(
-		marketIndex: number,
-		userAccountPublicKey: PublicKey,
-		sharesToBurn?: BN
+	public async getVaultWithdrawIx(
+		vault: PublicKey,
+		shares: BN,
+		subAccountId?: number
 	): Promise<TransactionInstruction> {
-		const userAccount = (await this.program.account.user.fetch(
-			userAccountPublicKey
-		)) as UserAccount;
-
-		const remainingAccounts = this.getRemainingAccounts({
-			userAccounts: [userAccount],
-			useMarketLastSlotCache: true,
-			writablePerpMarketIndexes: [marketIndex],
-		});
-
-		if (sharesToBurn == undefined) {
-			const perpPosition = userAccount.perpPositions.filter(
-				(position) => position.marketIndex === marketIndex
-			)[0];
-			sharesToBurn = perpPosition.lpShares;
-			console.log('burning lp shares:', sharesToBurn.toString());
-		}
-
-		return this.program.instruction.removePerpLpSharesInExpiringMarket(
-			sharesToBurn,
-			marketIndex,
-			{
-				accounts: {
-					state: await this.getStatePublicKey(),
-					user: userAccountPublicKey,
-				},
-				remainingAccounts: remainingAccounts,
-			}
+		return await this.program.instruction.vaultWithdraw(
+			shares,
+			await this.getVaultDepositorTransferAccounts(vault, subAccountId)
 		);
 	}
 
-	public async getRemovePerpLpSharesIx(
-		marketIndex: number,
-		sharesToBurn?: BN
-	): Promise<TransactionInstruction> {
-		const userAccountPublicKey = await this.getUserAccountPublicKey();
+	async getVaultDepositorTransferAccounts(
+		vault: PublicKey,
+		subAccountId?: number
+	): Promise<{
+		accounts: Record<string, PublicKey>;
+		remainingAccounts: AccountMeta[];
+	}> {
+		const vaultAccount = (await this.program.account.vault.fetch(
+			vault
+		)) as Vault;
+		const vaultUserAccount = (await this.program.account.user.fetch(
+			vaultAccount.user
+		)) as UserAccount;
 
-		const remainingAccounts = this.getRemainingAccounts({
-			userAccounts: [this.getUserAccount()],
-			useMarketLastSlotCache: true,
-			writablePerpMarketIndexes: [marketIndex],
-		});
+		return {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				vault,
+				vaultDepositor: getVaultDepositorPublicKey(
+					this.program.programId,
+					vault,
+					this.wallet.publicKey
+				),
+				user: vaultAccount.user,
+				depositorUser: await this.getUserAccountPublicKey(subAccountId),
+				authority: this.wallet.publicKey,
+				spotMarketVault: this.getQuoteSpotMarketAccount().vault,
+			},
+			remainingAccounts: this.getRemainingAccounts({
+				userAccounts: [vaultUserAccount, this.getUserAccount(subAccountId)],
+				useMarketLastSlotCache: true,
+				writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+			}),
+		};
+	}
 
-		if (sharesToBurn == undefined) {
-			const userAccount = this.getUserAccount();
-			const perpPosition = userAccount.perpPositions.filter(
-				(position) => position.marketIndex === marketIndex
-			)[0];
-			sharesToBurn = perpPosition.lpShares;
-			console.log('burning lp shares:', sharesToBurn.toString());
-		}
+	/**
+	 * Redeems the manager's profit share shares into the wallet's sub account
+	 */
+	public async managerWithdrawFromVault(
+		vault: PublicKey,
+		shares: BN,
+		subAccountId?: number
+	): Promise<TransactionSignature> {
+		const vaultAccount = (await this.program.account.vault.fetch(
+			vault
+		)) as Vault;
+		const vaultUserAccount = (await this.program.account.user.fetch(
+			vaultAccount.user
+		)) as UserAccount;
 
-		return this.program.instruction.removePerpLpShares(
-			sharesToBurn,
-			marketIndex,
+		const tx = await this.program.transaction.managerWithdrawFromVault(
+			shares,
 			{
 				accounts: {
 					state: await this.getStatePublicKey(),
-					user: userAccountPublicKey,
-					authority: this.wallet.publicKey,
+					vault,
+					user: vaultAccount.user,
+					managerUser: await this.getUserAccountPublicKey(subAccountId),
+					manager: this.wallet.publicKey,
+					spotMarketVault: this.getQuoteSpotMarketAccount().vault,
 				},
-				remainingAccounts: remainingAccounts,
+				remainingAccounts: this.getRemainingAccounts({
+					userAccounts: [vaultUserAccount, this.getUserAccount(subAccountId)],
+					useMarketLastSlotCache: true,
+					writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+				}),
 			}
 		);
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
 	}
 
-	public async addPerpLpShares(
-		amount: BN,
-		marketIndex: number,
+	public async placeOrderWithDeadManSwitch(
+		userAccountPublicKey: PublicKey,
+		user: UserAccount,
+		orderParams: OptionalOrderParams,
+		marketType: MarketType,
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
-		const { txSig, slot } = await this.sendTransaction(
+		const { txSig } = await this.sendTransaction(
 			wrapInTx(
-				await this.getAddPerpLpSharesIx(amount, marketIndex),
+				await this.getPlaceOrderWithDeadManSwitchIx(
+					userAccountPublicKey,
+					user,
+					orderParams,
+					marketType
+				),
 				txParams?.computeUnits,
 				txParams?.computeUnitsPrice
 			),
 			[],
 			this.opts
 		);
-		this.perpMarketLastSlotCache.set(marketIndex, slot);
 		return txSig;
 	}
 
-	public async getAddPerpLpSharesIx(
-		amount: BN,
-		marketIndex: number
+	public async getPlaceOrderWithDeadManSwitchIx(
+		userAccountPublicKey: PublicKey,
+		user: UserAccount,
+		orderParams: OptionalOrderParams,
+		marketType: MarketType
 	): Promise<TransactionInstruction> {
-		const userAccountPublicKey = await this.getUserAccountPublicKey();
+		orderParams = this.getOrderParams(
+			{ ...orderParams, reduceOnly: true },
+			marketType
+		);
+
 		const remainingAccounts = this.getRemainingAccounts({
-			userAccounts: [this.getUserAccount()],
-			useMarketLastSlotCache: true,
-			writablePerpMarketIndexes: [marketIndex],
+			userAccounts: [user],
+			readablePerpMarketIndex: isVariant(marketType, 'perp')
+				? orderParams.marketIndex
+				: undefined,
+			readableSpotMarketIndexes: isVariant(marketType, 'spot')
+				? [orderParams.marketIndex, QUOTE_SPOT_MARKET_INDEX]
+				: undefined,
 		});
 
-		return this.program.instruction.addPerpLpShares(amount, marketIndex, {
-			accounts: {
-				state: await this.getStatePublicKey(),
-				user: userAccountPublicKey,
-				authority: this.wallet.publicKey,
-			},
-			remainingAccounts: remainingAccounts,
-		});
+		return await this.program.instruction.placeOrderWithDeadManSwitch(
+			orderParams,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+					deadManSwitch: getDeadManSwitchPublicKeySync(
+						this.program.programId,
+						userAccountPublicKey
+					),
+					keeper: this.wallet.publicKey,
+				},
+				remainingAccounts,
+			}
+		);
 	}
 
-	public async openPosition(
-		direction: PositionDirection,
-		amount: BN,
+	public async withdrawWithDeadManSwitch(
+		userAccountPublicKey: PublicKey,
+		user: UserAccount,
 		marketIndex: number,
-		limitPrice?: BN
+		userTokenAccount: PublicKey,
+		txParams?: TxParams
 	): Promise<TransactionSignature> {
-		return await this.placeAndTakePerpOrder({
-			orderType: OrderType.MARKET,
-			marketIndex,
-			direction,
-			baseAssetAmount: amount,
-			price: limitPrice,
-		});
-	}
-
-	public async sendSignedTx(tx: Transaction): Promise<TransactionSignature> {
 		const { txSig } = await this.sendTransaction(
-			tx,
-			undefined,
-			this.opts,
-			true
-		);
-
-		return txSig;
-	}
-
-	/**
-	 * Sends a market order and returns a signed tx which can fill the order against the vamm, which the caller can use to fill their own order if required.
-	 * @param orderParams
-	 * @param userAccountPublicKey
-	 * @param userAccount
-	 * @param makerInfo
-	 * @param txParams
-	 * @param bracketOrdersParams
-	 * @returns
-	 */
-	public async sendMarketOrderAndGetSignedFillTx(
-		orderParams: OptionalOrderParams,
-		userAccountPublicKey: PublicKey,
-		userAccount: UserAccount,
-		makerInfo?: MakerInfo | MakerInfo[],
-		txParams?: TxParams,
-		bracketOrdersParams = new Array<OptionalOrderParams>(),
-		referrerInfo?: ReferrerInfo
-	): Promise<{ txSig: TransactionSignature; signedFillTx: Transaction }> {
-		const marketIndex = orderParams.marketIndex;
-		const orderId = userAccount.nextOrderId;
-
-		const marketOrderTx = wrapInTx(
-			await this.getPlacePerpOrderIx(orderParams),
-			txParams?.computeUnits,
-			txParams?.computeUnitsPrice
-		);
-		for (const bracketOrderParams of bracketOrdersParams) {
-			marketOrderTx.add(await this.getPlacePerpOrderIx(bracketOrderParams));
-		}
-
-		const fillTx = wrapInTx(
-			await this.getFillPerpOrderIx(
-				userAccountPublicKey,
-				userAccount,
-				{
-					orderId,
-					marketIndex,
-				},
-				makerInfo,
-				referrerInfo
-			),
-			txParams?.computeUnits,
-			txParams?.computeUnitsPrice
-		);
-
-		// Apply the latest blockhash to the txs so that we can sign before sending them
-		const currentBlockHash = (
-			await this.connection.getLatestBlockhash('finalized')
-		).blockhash;
-		marketOrderTx.recentBlockhash = currentBlockHash;
-		fillTx.recentBlockhash = currentBlockHash;
-
-		marketOrderTx.feePayer = userAccount.authority;
-		fillTx.feePayer = userAccount.authority;
-
-		const [signedMarketOrderTx, signedFillTx] =
-			await this.provider.wallet.signAllTransactions([marketOrderTx, fillTx]);
-
-		const { txSig, slot } = await this.sendTransaction(
-			signedMarketOrderTx,
+			wrapInTx(
+				await this.getWithdrawWithDeadManSwitchIx(
+					userAccountPublicKey,
+					user,
+					marketIndex,
+					userTokenAccount
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
 			[],
-			this.opts,
-			true
+			this.opts
 		);
+		return txSig;
+	}
 
-		this.perpMarketLastSlotCache.set(orderParams.marketIndex, slot);
+	public async getWithdrawWithDeadManSwitchIx(
+		userAccountPublicKey: PublicKey,
+		user: UserAccount,
+		marketIndex: number,
+		userTokenAccount: PublicKey
+	): Promise<TransactionInstruction> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [user],
+			writableSpotMarketIndexes: [marketIndex],
+			readableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
 
-		return { txSig, signedFillTx };
+		const spotMarketAccount = this.getSpotMarketAccount(marketIndex);
+		this.addTokenMintToRemainingAccounts(spotMarketAccount, remainingAccounts);
+
+		return await this.program.instruction.withdrawWithDeadManSwitch(
+			marketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+					deadManSwitch: getDeadManSwitchPublicKeySync(
+						this.program.programId,
+						userAccountPublicKey
+					),
+					keeper: this.wallet.publicKey,
+					spotMarketVault: spotMarketAccount.vault,
+					driftSigner: this.getSignerPublicKey(),
+					userTokenAccount,
+					tokenProgram: this.getTokenProgramForSpotMarket(spotMarketAccount),
+				},
+				remainingAccounts,
+			}
+		);
 	}
 
-	public async placePerpOrder(
-		orderParams: OptionalOrderParams,
+	public async placeOrdersScaled(
+		params: ScaleOrderParams,
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
 		const { txSig, slot } = await this.sendTransaction(
 			wrapInTx(
-				await this.getPlacePerpOrderIx(orderParams),
+				await this.getPlaceOrdersScaledIx(params),
 				txParams?.computeUnits,
 				txParams?.computeUnitsPrice
 			),
 			[],
 			this.opts
 		);
-		this.perpMarketLastSlotCache.set(orderParams.marketIndex, slot);
+		if (isVariant(params.marketType, 'perp')) {
+			this.perpMarketLastSlotCache.set(params.marketIndex, slot);
+		}
 		return txSig;
 	}
 
-	getOrderParams(
-		optionalOrderParams: OptionalOrderParams,
-		marketType: MarketType
-	): OrderParams {
-		return Object.assign({}, DefaultOrderParams, optionalOrderParams, {
-			marketType,
-		});
-	}
-
-	public async getPlacePerpOrderIx(
-		orderParams: OptionalOrderParams
+	public async getPlaceOrdersScaledIx(
+		params: ScaleOrderParams
 	): Promise<TransactionInstruction> {
-		orderParams = this.getOrderParams(orderParams, MarketType.PERP);
 		const userAccountPublicKey = await this.getUserAccountPublicKey();
 
-		const remainingAccounts = this.getRemainingAccounts({
-			userAccounts: [this.getUserAccount()],
-			useMarketLastSlotCache: true,
-			readablePerpMarketIndex: orderParams.marketIndex,
-		});
-
-		return await this.program.instruction.placePerpOrder(orderParams, {
+		const remainingAccounts = isVariant(params.marketType, 'perp')
+			? this.getRemainingAccounts({
+					userAccounts: [this.getUserAccount()],
+					useMarketLastSlotCache: true,
+					readablePerpMarketIndex: params.marketIndex,
+			  })
+			: this.getRemainingAccounts({
+					userAccounts: [this.getUserAccount()],
+					useMarketLastSlotCache: true,
+					readableSpotMarketIndexes: [
+						params.marketIndex,
+						QUOTE_SPOT_MARKET_INDEX,
+					],
+			  });
+
+		return await this.program.instruction.placeOrdersScaled(params, {
 			accounts: {
 				state: await this.getStatePublicKey(),
 				user: userAccountPublicKey,
@@ -2115,52 +3858,267 @@ export class DriftClient {
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
 		const { txSig } = await this.sendTransaction(
-			wrapInTx(
-				await this.getSettleExpiredMarketIx(marketIndex),
-				txParams?.computeUnits,
-				txParams?.computeUnitsPrice
-			),
+			wrapInTx(
+				await this.getSettleExpiredMarketIx(marketIndex),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getSettleExpiredMarketIx(
+		marketIndex: number
+	): Promise<TransactionInstruction> {
+		const marketAccountInfos = [];
+		const oracleAccountInfos = [];
+		const spotMarketAccountInfos = [];
+		const market = this.getPerpMarketAccount(marketIndex);
+		marketAccountInfos.push({
+			pubkey: market.pubkey,
+			isWritable: true,
+			isSigner: false,
+		});
+		oracleAccountInfos.push({
+			pubkey: market.amm.oracle,
+			isWritable: false,
+			isSigner: false,
+		});
+
+		spotMarketAccountInfos.push({
+			pubkey: this.getSpotMarketAccount(QUOTE_SPOT_MARKET_INDEX).pubkey,
+			isSigner: false,
+			isWritable: true,
+		});
+
+		const remainingAccounts = oracleAccountInfos
+			.concat(spotMarketAccountInfos)
+			.concat(marketAccountInfos);
+
+		if (market.amm.delistingEnabled) {
+			remainingAccounts.push({
+				pubkey: getPerpMarketDelistingPublicKey(
+					this.program.programId,
+					marketIndex
+				),
+				isWritable: false,
+				isSigner: false,
+			});
+		}
+
+		return await this.program.instruction.settleExpiredMarket(marketIndex, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				authority: this.wallet.publicKey,
+			},
+			remainingAccounts,
+		});
+	}
+
+	/**
+	 * Lists a perp market with the default permissionless risk parameters by posting the
+	 * listing config's bond. The market can be activated once the admin's veto window passes.
+	 */
+	public async proposePerpMarket(
+		priceOracle: PublicKey,
+		baseAssetReserve: BN,
+		oracleSource: OracleSource = OracleSource.PYTH,
+		name = DEFAULT_MARKET_NAME,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const perpMarketIndex = this.getStateAccount().numberOfMarkets;
+		const ix = await this.program.instruction.proposePerpMarket(
+			baseAssetReserve,
+			oracleSource,
+			encodeName(name),
+			{
+				accounts: {
+					proposer: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					perpMarketListingConfig: getPerpMarketListingConfigPublicKey(
+						this.program.programId
+					),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					perpMarketListing: getPerpMarketListingPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					oracle: priceOracle,
+					rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+					systemProgram: anchor.web3.SystemProgram.programId,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(ix, txParams?.computeUnits, txParams?.computeUnitsPrice),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async activateProposedPerpMarket(
+		perpMarketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const perpMarketListingPublicKey = getPerpMarketListingPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+		const perpMarketListing =
+			(await this.program.account.perpMarketListing.fetch(
+				perpMarketListingPublicKey
+			)) as PerpMarketListing;
+
+		const ix = await this.program.instruction.activateProposedPerpMarket(
+			perpMarketIndex,
+			{
+				accounts: {
+					proposer: perpMarketListing.proposer,
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						perpMarketIndex
+					),
+					perpMarketListing: perpMarketListingPublicKey,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(ix, txParams?.computeUnits, txParams?.computeUnitsPrice),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async updatePerpMarketDelistingTwap(
+		marketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getUpdatePerpMarketDelistingTwapIx(marketIndex),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getUpdatePerpMarketDelistingTwapIx(
+		marketIndex: number
+	): Promise<TransactionInstruction> {
+		const market = this.getPerpMarketAccount(marketIndex);
+		return await this.program.instruction.updatePerpMarketDelistingTwap(
+			marketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					perpMarket: market.pubkey,
+					perpMarketDelisting: getPerpMarketDelistingPublicKey(
+						this.program.programId,
+						marketIndex
+					),
+					oracle: market.amm.oracle,
+				},
+			}
+		);
+	}
+
+	public async settleExpiredPerpPositions(
+		settleeUserAccountPublicKeys: PublicKey[],
+		settleeUserAccounts: UserAccount[],
+		marketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getSettleExpiredPerpPositionsIx(
+					settleeUserAccountPublicKeys,
+					settleeUserAccounts,
+					marketIndex
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getSettleExpiredPerpPositionsIx(
+		settleeUserAccountPublicKeys: PublicKey[],
+		settleeUserAccounts: UserAccount[],
+		marketIndex: number
+	): Promise<TransactionInstruction> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: settleeUserAccounts,
+			writablePerpMarketIndexes: [marketIndex],
+			writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
+
+		for (const userAccountPublicKey of settleeUserAccountPublicKeys) {
+			remainingAccounts.push({
+				pubkey: userAccountPublicKey,
+				isSigner: false,
+				isWritable: true,
+			});
+		}
+
+		return await this.program.instruction.settleExpiredPerpPositions(
+			marketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					spotMarketVault: this.getQuoteSpotMarketAccount().vault,
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
+	public async closeDelistedPerpMarket(
+		marketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const ix = await this.program.instruction.closeDelistedPerpMarket(
+			marketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					perpMarket: await getPerpMarketPublicKey(
+						this.program.programId,
+						marketIndex
+					),
+					perpMarketDelisting: getPerpMarketDelistingPublicKey(
+						this.program.programId,
+						marketIndex
+					),
+					spotMarket: await getSpotMarketPublicKey(
+						this.program.programId,
+						QUOTE_SPOT_MARKET_INDEX
+					),
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(ix, txParams?.computeUnits, txParams?.computeUnitsPrice),
 			[],
 			this.opts
 		);
-		return txSig;
-	}
-
-	public async getSettleExpiredMarketIx(
-		marketIndex: number
-	): Promise<TransactionInstruction> {
-		const marketAccountInfos = [];
-		const oracleAccountInfos = [];
-		const spotMarketAccountInfos = [];
-		const market = this.getPerpMarketAccount(marketIndex);
-		marketAccountInfos.push({
-			pubkey: market.pubkey,
-			isWritable: true,
-			isSigner: false,
-		});
-		oracleAccountInfos.push({
-			pubkey: market.amm.oracle,
-			isWritable: false,
-			isSigner: false,
-		});
-
-		spotMarketAccountInfos.push({
-			pubkey: this.getSpotMarketAccount(QUOTE_SPOT_MARKET_INDEX).pubkey,
-			isSigner: false,
-			isWritable: true,
-		});
-
-		const remainingAccounts = oracleAccountInfos
-			.concat(spotMarketAccountInfos)
-			.concat(marketAccountInfos);
 
-		return await this.program.instruction.settleExpiredMarket(marketIndex, {
-			accounts: {
-				state: await this.getStatePublicKey(),
-				authority: this.wallet.publicKey,
-			},
-			remainingAccounts,
-		});
+		return txSig;
 	}
 
 	public async settleExpiredMarketPoolsToRevenuePool(
@@ -2232,6 +4190,49 @@ export class DriftClient {
 		});
 	}
 
+	public async modifyOrder(
+		orderId: number,
+		modifyOrderParams: ModifyOrderParams,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getModifyOrderIx(orderId, modifyOrderParams),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getModifyOrderIx(
+		orderId: number,
+		modifyOrderParams: ModifyOrderParams
+	): Promise<TransactionInstruction> {
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount()],
+			useMarketLastSlotCache: true,
+		});
+
+		return await this.program.instruction.modifyOrder(
+			orderId,
+			modifyOrderParams,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+					userStats: this.getUserStatsAccountPublicKey(),
+					authority: this.wallet.publicKey,
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
 	public async cancelOrderByUserId(
 		userOrderId: number,
 		txParams?: TxParams
@@ -2297,6 +4298,83 @@ export class DriftClient {
 	): Promise<TransactionInstruction> {
 		const userAccountPublicKey = await this.getUserAccountPublicKey();
 
+		const remainingAccounts = this.getCancelOrdersRemainingAccounts(
+			marketType,
+			marketIndex
+		);
+
+		return await this.program.instruction.cancelOrders(
+			marketType ?? null,
+			marketIndex ?? null,
+			direction ?? null,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
+	public async cancelOrdersWithFilter(
+		filter: CancelOrdersFilter,
+		marketType?: MarketType,
+		marketIndex?: number,
+		direction?: PositionDirection,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getCancelOrdersWithFilterIx(
+					filter,
+					marketType,
+					marketIndex,
+					direction
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getCancelOrdersWithFilterIx(
+		filter: CancelOrdersFilter,
+		marketType: MarketType | null,
+		marketIndex: number | null,
+		direction: PositionDirection | null
+	): Promise<TransactionInstruction> {
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+
+		const remainingAccounts = this.getCancelOrdersRemainingAccounts(
+			marketType,
+			marketIndex
+		);
+
+		return await this.program.instruction.cancelOrdersWithFilter(
+			marketType ?? null,
+			marketIndex ?? null,
+			direction ?? null,
+			filter,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
+	getCancelOrdersRemainingAccounts(
+		marketType: MarketType | null,
+		marketIndex: number | null
+	): AccountMeta[] {
 		let readablePerpMarketIndex = undefined;
 		let readableSpotMarketIndexes = undefined;
 		if (marketIndex) {
@@ -2307,26 +4385,268 @@ export class DriftClient {
 			}
 		}
 
-		const remainingAccounts = this.getRemainingAccounts({
-			userAccounts: [this.getUserAccount()],
-			readablePerpMarketIndex,
-			readableSpotMarketIndexes,
-			useMarketLastSlotCache: true,
-		});
+		return this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount()],
+			readablePerpMarketIndex,
+			readableSpotMarketIndexes,
+			useMarketLastSlotCache: true,
+		});
+	}
+
+	public async linkOrders(
+		orderId: number,
+		linkedOrderId: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getLinkOrdersIx(orderId, linkedOrderId),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getLinkOrdersIx(
+		orderId: number,
+		linkedOrderId: number
+	): Promise<TransactionInstruction> {
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+
+		return await this.program.instruction.linkOrders(orderId, linkedOrderId, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				user: userAccountPublicKey,
+				userStats: this.getUserStatsAccountPublicKey(),
+				authority: this.wallet.publicKey,
+			},
+		});
+	}
+
+	/**
+	 * Bytes the taker authority signs (ed25519) off-chain for place_signed_order
+	 */
+	public encodeSignedOrderMessage(message: SignedOrderMessage): Buffer {
+		return this.program.coder.types.encode('SignedOrderMessage', message);
+	}
+
+	/**
+	 * Submits an order signed off-chain by the taker. The program checks the signature via
+	 * the ed25519 instruction placed right before place_signed_order
+	 */
+	public async placeSignedOrder(
+		message: SignedOrderMessage,
+		signature: Buffer,
+		takerAuthority: PublicKey,
+		takerUserAccountPublicKey: PublicKey,
+		takerUserAccount: UserAccount,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const [ed25519Ix, placeSignedOrderIx] = await this.getPlaceSignedOrderIxs(
+			message,
+			signature,
+			takerAuthority,
+			takerUserAccountPublicKey,
+			takerUserAccount
+		);
+
+		const tx = wrapInTx(
+			ed25519Ix,
+			txParams?.computeUnits,
+			txParams?.computeUnitsPrice
+		).add(placeSignedOrderIx);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async getPlaceSignedOrderIxs(
+		message: SignedOrderMessage,
+		signature: Buffer,
+		takerAuthority: PublicKey,
+		takerUserAccountPublicKey: PublicKey,
+		takerUserAccount: UserAccount
+	): Promise<[TransactionInstruction, TransactionInstruction]> {
+		const ed25519Ix = Ed25519Program.createInstructionWithPublicKey({
+			publicKey: takerAuthority.toBytes(),
+			message: this.encodeSignedOrderMessage(message),
+			signature,
+		});
+
+		const marketIndex = message.orderParams.marketIndex;
+		const remainingAccounts = isVariant(message.orderParams.marketType, 'perp')
+			? this.getRemainingAccounts({
+					userAccounts: [takerUserAccount],
+					useMarketLastSlotCache: true,
+					readablePerpMarketIndex: marketIndex,
+			  })
+			: this.getRemainingAccounts({
+					userAccounts: [takerUserAccount],
+					useMarketLastSlotCache: true,
+					readableSpotMarketIndexes: [marketIndex, QUOTE_SPOT_MARKET_INDEX],
+			  });
+
+		const placeSignedOrderIx = await this.program.instruction.placeSignedOrder(
+			message,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					authority: this.wallet.publicKey,
+					user: takerUserAccountPublicKey,
+					userStats: getUserStatsAccountPublicKey(
+						this.program.programId,
+						takerAuthority
+					),
+					signedOrderNonce: getSignedOrderNoncePublicKeySync(
+						this.program.programId,
+						takerUserAccountPublicKey
+					),
+					ixSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
+				},
+				remainingAccounts,
+			}
+		);
+
+		return [ed25519Ix, placeSignedOrderIx];
+	}
+
+	/**
+	 * Bytes the taker authority signs (ed25519) off-chain for fill_rfq
+	 */
+	public encodeRfqTakerRequest(request: RfqTakerRequest): Buffer {
+		return this.program.coder.types.encode('RfqTakerRequest', request);
+	}
+
+	/**
+	 * Bytes the maker authority signs (ed25519) off-chain for fill_rfq
+	 */
+	public encodeRfqMakerQuote(quote: RfqMakerQuote): Buffer {
+		return this.program.coder.types.encode('RfqMakerQuote', quote);
+	}
+
+	/**
+	 * Settles a taker's signed rfq request against signed maker quotes in one transaction.
+	 * Quotes are matched in the order given
+	 */
+	public async fillRfq(
+		takerRequest: RfqTakerRequest,
+		takerSignature: Buffer,
+		takerUserAccountPublicKey: PublicKey,
+		takerUserAccount: UserAccount,
+		quotes: RfqQuoteInfo[],
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const ixs = await this.getFillRfqIxs(
+			takerRequest,
+			takerSignature,
+			takerUserAccountPublicKey,
+			takerUserAccount,
+			quotes
+		);
+
+		const tx = wrapInTx(
+			ixs[0],
+			txParams?.computeUnits,
+			txParams?.computeUnitsPrice
+		);
+		for (const ix of ixs.slice(1)) {
+			tx.add(ix);
+		}
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async getFillRfqIxs(
+		takerRequest: RfqTakerRequest,
+		takerSignature: Buffer,
+		takerUserAccountPublicKey: PublicKey,
+		takerUserAccount: UserAccount,
+		quotes: RfqQuoteInfo[]
+	): Promise<TransactionInstruction[]> {
+		const ed25519Ixs = [
+			Ed25519Program.createInstructionWithPublicKey({
+				publicKey: takerUserAccount.authority.toBytes(),
+				message: this.encodeRfqTakerRequest(takerRequest),
+				signature: takerSignature,
+			}),
+		];
+		for (const { quote, signature, makerInfo } of quotes) {
+			ed25519Ixs.push(
+				Ed25519Program.createInstructionWithPublicKey({
+					publicKey: makerInfo.makerUserAccount.authority.toBytes(),
+					message: this.encodeRfqMakerQuote(quote),
+					signature,
+				})
+			);
+		}
+
+		const userAccounts = [takerUserAccount];
+		for (const { makerInfo } of quotes) {
+			userAccounts.push(makerInfo.makerUserAccount);
+		}
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts,
+			writablePerpMarketIndexes: [takerRequest.marketIndex],
+		});
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.PERP,
+			takerRequest.marketIndex
+		);
+
+		for (const { makerInfo } of quotes) {
+			remainingAccounts.push({
+				pubkey: makerInfo.maker,
+				isWritable: true,
+				isSigner: false,
+			});
+			remainingAccounts.push({
+				pubkey: makerInfo.makerStats,
+				isWritable: true,
+				isSigner: false,
+			});
+		}
+
+		for (const { makerInfo } of quotes) {
+			remainingAccounts.push({
+				pubkey: getSignedOrderNoncePublicKeySync(
+					this.program.programId,
+					makerInfo.maker
+				),
+				isWritable: true,
+				isSigner: false,
+			});
+		}
 
-		return await this.program.instruction.cancelOrders(
-			marketType ?? null,
-			marketIndex ?? null,
-			direction ?? null,
+		const fillRfqIx = await this.program.instruction.fillRfq(
+			takerRequest,
+			quotes.map(({ quote }) => quote),
 			{
 				accounts: {
 					state: await this.getStatePublicKey(),
-					user: userAccountPublicKey,
 					authority: this.wallet.publicKey,
+					filler: await this.getUserAccountPublicKey(),
+					fillerStats: this.getUserStatsAccountPublicKey(),
+					taker: takerUserAccountPublicKey,
+					takerStats: getUserStatsAccountPublicKey(
+						this.program.programId,
+						takerUserAccount.authority
+					),
+					takerSignedOrderNonce: getSignedOrderNoncePublicKeySync(
+						this.program.programId,
+						takerUserAccountPublicKey
+					),
+					ixSysvar: SYSVAR_INSTRUCTIONS_PUBKEY,
 				},
 				remainingAccounts,
 			}
 		);
+
+		return [...ed25519Ixs, fillRfqIx];
 	}
 
 	public async fillPerpOrder(
@@ -2335,7 +4655,9 @@ export class DriftClient {
 		order?: Pick<Order, 'marketIndex' | 'orderId'>,
 		makerInfo?: MakerInfo | MakerInfo[],
 		referrerInfo?: ReferrerInfo,
-		txParams?: TxParams
+		txParams?: TxParams,
+		builderInfo?: BuilderInfo,
+		fuelInfo?: FuelInfo
 	): Promise<TransactionSignature> {
 		const { txSig } = await this.sendTransaction(
 			wrapInTx(
@@ -2344,7 +4666,9 @@ export class DriftClient {
 					user,
 					order,
 					makerInfo,
-					referrerInfo
+					referrerInfo,
+					builderInfo,
+					fuelInfo
 				),
 				txParams?.computeUnits,
 				txParams?.computeUnitsPrice
@@ -2355,12 +4679,73 @@ export class DriftClient {
 		return txSig;
 	}
 
+	public async fillTwapOrder(
+		userAccountPublicKey: PublicKey,
+		user: UserAccount,
+		order: Pick<Order, 'marketIndex' | 'orderId'>,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getFillTwapOrderIx(userAccountPublicKey, user, order),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getFillTwapOrderIx(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		order: Pick<Order, 'marketIndex' | 'orderId'>
+	): Promise<TransactionInstruction> {
+		const userStatsPublicKey = getUserStatsAccountPublicKey(
+			this.program.programId,
+			userAccount.authority
+		);
+
+		const fillerPublicKey = await this.getUserAccountPublicKey();
+		const fillerStatsPublicKey = this.getUserStatsAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [userAccount],
+			writablePerpMarketIndexes: [order.marketIndex],
+		});
+		this.addMarketWhitelistEntryToRemainingAccounts(
+			remainingAccounts,
+			order.marketIndex,
+			userAccount.authority
+		);
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.PERP,
+			order.marketIndex
+		);
+
+		return await this.program.instruction.fillTwapOrder(order.orderId, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				filler: fillerPublicKey,
+				fillerStats: fillerStatsPublicKey,
+				user: userAccountPublicKey,
+				userStats: userStatsPublicKey,
+				authority: this.wallet.publicKey,
+			},
+			remainingAccounts,
+		});
+	}
+
 	public async getFillPerpOrderIx(
 		userAccountPublicKey: PublicKey,
 		userAccount: UserAccount,
 		order: Pick<Order, 'marketIndex' | 'orderId'>,
 		makerInfo?: MakerInfo | MakerInfo[],
-		referrerInfo?: ReferrerInfo
+		referrerInfo?: ReferrerInfo,
+		builderInfo?: BuilderInfo,
+		fuelInfo?: FuelInfo
 	): Promise<TransactionInstruction> {
 		const userStatsPublicKey = getUserStatsAccountPublicKey(
 			this.program.programId,
@@ -2390,19 +4775,24 @@ export class DriftClient {
 			userAccounts,
 			writablePerpMarketIndexes: [marketIndex],
 		});
+		this.addMarketWhitelistEntryToRemainingAccounts(
+			remainingAccounts,
+			marketIndex,
+			userAccount.authority,
+			...makerInfo.map((maker) => maker.makerUserAccount.authority)
+		);
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.PERP,
+			marketIndex
+		);
+		await this.addFillCallbackToRemainingAccounts(
+			remainingAccounts,
+			userAccountPublicKey,
+			userAccount
+		);
 
-		for (const maker of makerInfo) {
-			remainingAccounts.push({
-				pubkey: maker.maker,
-				isWritable: true,
-				isSigner: false,
-			});
-			remainingAccounts.push({
-				pubkey: maker.makerStats,
-				isWritable: true,
-				isSigner: false,
-			});
-		}
+		this.addMakersToRemainingAccounts(remainingAccounts, makerInfo);
 
 		if (referrerInfo) {
 			const referrerIsMaker =
@@ -2420,6 +4810,43 @@ export class DriftClient {
 					isSigner: false,
 				});
 			}
+			this.addSecondLevelReferrerToRemainingAccounts(
+				remainingAccounts,
+				referrerInfo
+			);
+		}
+
+		if (builderInfo) {
+			const builderIsIncluded =
+				makerInfo.find((maker) => maker.maker.equals(builderInfo.builder)) !==
+					undefined || referrerInfo?.referrer.equals(builderInfo.builder);
+			if (!builderIsIncluded) {
+				remainingAccounts.push({
+					pubkey: builderInfo.builder,
+					isWritable: true,
+					isSigner: false,
+				});
+				remainingAccounts.push({
+					pubkey: builderInfo.builderStats,
+					isWritable: true,
+					isSigner: false,
+				});
+			}
+		}
+
+		if (fuelInfo) {
+			remainingAccounts.push({
+				pubkey: fuelInfo.fuelBoost,
+				isWritable: false,
+				isSigner: false,
+			});
+			for (const userFuel of fuelInfo.userFuels) {
+				remainingAccounts.push({
+					pubkey: userFuel,
+					isWritable: true,
+					isSigner: false,
+				});
+			}
 		}
 
 		const orderId = order.orderId;
@@ -2498,7 +4925,10 @@ export class DriftClient {
 		userAccountPublicKey: PublicKey,
 		user: UserAccount,
 		order?: Order,
-		fulfillmentConfig?: SerumV3FulfillmentConfigAccount,
+		fulfillmentConfig?:
+			| SerumV3FulfillmentConfigAccount
+			| OpenbookV2FulfillmentConfigAccount
+			| PhoenixV1FulfillmentConfigAccount,
 		makerInfo?: MakerInfo,
 		referrerInfo?: ReferrerInfo,
 		txParams?: TxParams
@@ -2526,7 +4956,10 @@ export class DriftClient {
 		userAccountPublicKey: PublicKey,
 		userAccount: UserAccount,
 		order?: Order,
-		fulfillmentConfig?: SerumV3FulfillmentConfigAccount,
+		fulfillmentConfig?:
+			| SerumV3FulfillmentConfigAccount
+			| OpenbookV2FulfillmentConfigAccount
+			| PhoenixV1FulfillmentConfigAccount,
 		makerInfo?: MakerInfo,
 		referrerInfo?: ReferrerInfo
 	): Promise<TransactionInstruction> {
@@ -2552,6 +4985,11 @@ export class DriftClient {
 			userAccounts,
 			writableSpotMarketIndexes: [marketIndex, QUOTE_SPOT_MARKET_INDEX],
 		});
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.SPOT,
+			marketIndex
+		);
 
 		if (makerInfo) {
 			remainingAccounts.push({
@@ -2609,14 +5047,31 @@ export class DriftClient {
 	addSpotFulfillmentAccounts(
 		marketIndex: number,
 		remainingAccounts: AccountMeta[],
-		fulfillmentConfig?: SerumV3FulfillmentConfigAccount
+		fulfillmentConfig?:
+			| SerumV3FulfillmentConfigAccount
+			| OpenbookV2FulfillmentConfigAccount
+			| PhoenixV1FulfillmentConfigAccount
 	) {
 		if (fulfillmentConfig) {
-			this.addSerumRemainingAccounts(
-				marketIndex,
-				remainingAccounts,
-				fulfillmentConfig
-			);
+			if (isVariant(fulfillmentConfig.fulfillmentType, 'openbookV2')) {
+				this.addOpenbookV2RemainingAccounts(
+					marketIndex,
+					remainingAccounts,
+					fulfillmentConfig as OpenbookV2FulfillmentConfigAccount
+				);
+			} else if (isVariant(fulfillmentConfig.fulfillmentType, 'phoenixV1')) {
+				this.addPhoenixRemainingAccounts(
+					marketIndex,
+					remainingAccounts,
+					fulfillmentConfig as PhoenixV1FulfillmentConfigAccount
+				);
+			} else {
+				this.addSerumRemainingAccounts(
+					marketIndex,
+					remainingAccounts,
+					fulfillmentConfig as SerumV3FulfillmentConfigAccount
+				);
+			}
 		} else {
 			remainingAccounts.push({
 				pubkey: this.getSpotMarketAccount(marketIndex).vault,
@@ -2715,11 +5170,145 @@ export class DriftClient {
 			isWritable: true,
 			isSigner: false,
 		});
-		remainingAccounts.push({
-			pubkey: this.getStateAccount().srmVault,
-			isWritable: false,
-			isSigner: false,
-		});
+		remainingAccounts.push({
+			pubkey: this.getStateAccount().srmVault,
+			isWritable: false,
+			isSigner: false,
+		});
+	}
+
+	addOpenbookV2RemainingAccounts(
+		marketIndex: number,
+		remainingAccounts: AccountMeta[],
+		fulfillmentConfig: OpenbookV2FulfillmentConfigAccount
+	) {
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.pubkey,
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.openbookV2ProgramId,
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.openbookV2Market,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.openbookV2MarketAuthority,
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.openbookV2EventHeap,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.openbookV2Bids,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.openbookV2Asks,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.openbookV2BaseVault,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.openbookV2QuoteVault,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: this.getSignerPublicKey(),
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: TOKEN_PROGRAM_ID,
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: SystemProgram.programId,
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: this.getSpotMarketAccount(marketIndex).vault,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: this.getQuoteSpotMarketAccount().vault,
+			isWritable: true,
+			isSigner: false,
+		});
+	}
+
+	addPhoenixRemainingAccounts(
+		marketIndex: number,
+		remainingAccounts: AccountMeta[],
+		fulfillmentConfig: PhoenixV1FulfillmentConfigAccount
+	) {
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.pubkey,
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.phoenixProgramId,
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.phoenixLogAuthority,
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.phoenixMarket,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.phoenixBaseVault,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: fulfillmentConfig.phoenixQuoteVault,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: this.getSignerPublicKey(),
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: TOKEN_PROGRAM_ID,
+			isWritable: false,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: this.getSpotMarketAccount(marketIndex).vault,
+			isWritable: true,
+			isSigner: false,
+		});
+		remainingAccounts.push({
+			pubkey: this.getQuoteSpotMarketAccount().vault,
+			isWritable: true,
+			isSigner: false,
+		});
 	}
 
 	public async triggerOrder(
@@ -2815,6 +5404,45 @@ export class DriftClient {
 		});
 	}
 
+	public async cancelStaleOrders(
+		userAccountPublicKey: PublicKey,
+		user: UserAccount,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.txSender.send(
+			wrapInTx(
+				await this.getCancelStaleOrdersIx(userAccountPublicKey, user),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getCancelStaleOrdersIx(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount
+	): Promise<TransactionInstruction> {
+		const fillerPublicKey = await this.getUserAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [userAccount],
+			writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
+
+		return await this.program.instruction.cancelStaleOrders({
+			accounts: {
+				state: await this.getStatePublicKey(),
+				filler: fillerPublicKey,
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+			},
+			remainingAccounts,
+		});
+	}
+
 	public async updateUserIdle(
 		userAccountPublicKey: PublicKey,
 		user: UserAccount,
@@ -2853,6 +5481,104 @@ export class DriftClient {
 		});
 	}
 
+	public async migrateUser(
+		userAccountPublicKey: PublicKey,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.txSender.send(
+			wrapInTx(
+				await this.getMigrateUserIx(userAccountPublicKey),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getMigrateUserIx(
+		userAccountPublicKey: PublicKey
+	): Promise<TransactionInstruction> {
+		return await this.program.instruction.migrateUser({
+			accounts: {
+				user: userAccountPublicKey,
+				payer: this.wallet.publicKey,
+				systemProgram: anchor.web3.SystemProgram.programId,
+			},
+		});
+	}
+
+	public async logUserRisk(
+		userAccountPublicKey: PublicKey,
+		user: UserAccount,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.txSender.send(
+			wrapInTx(
+				await this.getLogUserRiskIx(userAccountPublicKey, user),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getLogUserRiskIx(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount
+	): Promise<TransactionInstruction> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [userAccount],
+		});
+
+		return await this.program.instruction.logUserRisk({
+			accounts: {
+				state: await this.getStatePublicKey(),
+				authority: this.wallet.publicKey,
+				user: userAccountPublicKey,
+			},
+			remainingAccounts,
+		});
+	}
+
+	public async updateUserMarginWarning(
+		userAccountPublicKey: PublicKey,
+		user: UserAccount,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.txSender.send(
+			wrapInTx(
+				await this.getUpdateUserMarginWarningIx(userAccountPublicKey, user),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getUpdateUserMarginWarningIx(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount
+	): Promise<TransactionInstruction> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [userAccount],
+		});
+
+		return await this.program.instruction.updateUserMarginWarning({
+			accounts: {
+				state: await this.getStatePublicKey(),
+				authority: this.wallet.publicKey,
+				user: userAccountPublicKey,
+			},
+			remainingAccounts,
+		});
+	}
+
 	public async placeAndTakePerpOrder(
 		orderParams: OptionalOrderParams,
 		makerInfo?: MakerInfo | MakerInfo[],
@@ -2901,19 +5627,24 @@ export class DriftClient {
 			useMarketLastSlotCache: true,
 			writablePerpMarketIndexes: [orderParams.marketIndex],
 		});
+		this.addMarketWhitelistEntryToRemainingAccounts(
+			remainingAccounts,
+			orderParams.marketIndex,
+			this.getUserAccount().authority,
+			...makerInfo.map((maker) => maker.makerUserAccount.authority)
+		);
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.PERP,
+			orderParams.marketIndex
+		);
+		await this.addFillCallbackToRemainingAccounts(
+			remainingAccounts,
+			userAccountPublicKey,
+			this.getUserAccount()
+		);
 
-		for (const maker of makerInfo) {
-			remainingAccounts.push({
-				pubkey: maker.maker,
-				isWritable: true,
-				isSigner: false,
-			});
-			remainingAccounts.push({
-				pubkey: maker.makerStats,
-				isWritable: true,
-				isSigner: false,
-			});
-		}
+		this.addMakersToRemainingAccounts(remainingAccounts, makerInfo);
 
 		if (referrerInfo) {
 			const referrerIsMaker =
@@ -2931,6 +5662,10 @@ export class DriftClient {
 					isSigner: false,
 				});
 			}
+			this.addSecondLevelReferrerToRemainingAccounts(
+				remainingAccounts,
+				referrerInfo
+			);
 		}
 
 		return await this.program.instruction.placeAndTakePerpOrder(
@@ -2987,6 +5722,17 @@ export class DriftClient {
 			useMarketLastSlotCache: true,
 			writablePerpMarketIndexes: [orderParams.marketIndex],
 		});
+		this.addMarketWhitelistEntryToRemainingAccounts(
+			remainingAccounts,
+			orderParams.marketIndex,
+			this.getUserAccount().authority,
+			takerInfo.takerUserAccount.authority
+		);
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.PERP,
+			orderParams.marketIndex
+		);
 
 		if (referrerInfo) {
 			remainingAccounts.push({
@@ -2999,6 +5745,10 @@ export class DriftClient {
 				isWritable: true,
 				isSigner: false,
 			});
+			this.addSecondLevelReferrerToRemainingAccounts(
+				remainingAccounts,
+				referrerInfo
+			);
 		}
 
 		const takerOrderId = takerInfo.order.orderId;
@@ -3021,7 +5771,10 @@ export class DriftClient {
 
 	public async placeAndTakeSpotOrder(
 		orderParams: OptionalOrderParams,
-		fulfillmentConfig?: SerumV3FulfillmentConfigAccount,
+		fulfillmentConfig?:
+			| SerumV3FulfillmentConfigAccount
+			| OpenbookV2FulfillmentConfigAccount
+			| PhoenixV1FulfillmentConfigAccount,
 		makerInfo?: MakerInfo,
 		referrerInfo?: ReferrerInfo,
 		txParams?: TxParams
@@ -3040,72 +5793,214 @@ export class DriftClient {
 			[],
 			this.opts
 		);
-		this.spotMarketLastSlotCache.set(orderParams.marketIndex, slot);
+		this.spotMarketLastSlotCache.set(orderParams.marketIndex, slot);
+		this.spotMarketLastSlotCache.set(QUOTE_SPOT_MARKET_INDEX, slot);
+		return txSig;
+	}
+
+	public async getPlaceAndTakeSpotOrderIx(
+		orderParams: OptionalOrderParams,
+		fulfillmentConfig?:
+			| SerumV3FulfillmentConfigAccount
+			| OpenbookV2FulfillmentConfigAccount
+			| PhoenixV1FulfillmentConfigAccount,
+		makerInfo?: MakerInfo,
+		referrerInfo?: ReferrerInfo
+	): Promise<TransactionInstruction> {
+		orderParams = this.getOrderParams(orderParams, MarketType.SPOT);
+		const userStatsPublicKey = await this.getUserStatsAccountPublicKey();
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+
+		const userAccounts = [this.getUserAccount()];
+		if (makerInfo !== undefined) {
+			userAccounts.push(makerInfo.makerUserAccount);
+		}
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts,
+			useMarketLastSlotCache: true,
+			writableSpotMarketIndexes: [
+				orderParams.marketIndex,
+				QUOTE_SPOT_MARKET_INDEX,
+			],
+		});
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.SPOT,
+			orderParams.marketIndex
+		);
+
+		let makerOrderId = null;
+		if (makerInfo) {
+			makerOrderId = makerInfo.order.orderId;
+			remainingAccounts.push({
+				pubkey: makerInfo.maker,
+				isSigner: false,
+				isWritable: true,
+			});
+			remainingAccounts.push({
+				pubkey: makerInfo.makerStats,
+				isSigner: false,
+				isWritable: true,
+			});
+		}
+
+		if (referrerInfo) {
+			remainingAccounts.push({
+				pubkey: referrerInfo.referrer,
+				isWritable: true,
+				isSigner: false,
+			});
+			remainingAccounts.push({
+				pubkey: referrerInfo.referrerStats,
+				isWritable: true,
+				isSigner: false,
+			});
+		}
+
+		this.addSpotFulfillmentAccounts(
+			orderParams.marketIndex,
+			remainingAccounts,
+			fulfillmentConfig
+		);
+
+		return await this.program.instruction.placeAndTakeSpotOrder(
+			orderParams,
+			fulfillmentConfig ? fulfillmentConfig.fulfillmentType : null,
+			makerOrderId,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+					userStats: userStatsPublicKey,
+					authority: this.wallet.publicKey,
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
+	public async placeAndTakeBasisOrder(
+		perpOrderParams: OptionalOrderParams,
+		spotMarketIndex: number,
+		basisLimit: BN,
+		fulfillmentConfig:
+			| SerumV3FulfillmentConfigAccount
+			| OpenbookV2FulfillmentConfigAccount
+			| PhoenixV1FulfillmentConfigAccount,
+		makerInfo?: MakerInfo | MakerInfo[],
+		referrerInfo?: ReferrerInfo,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig, slot } = await this.sendTransaction(
+			wrapInTx(
+				await this.getPlaceAndTakeBasisOrderIx(
+					perpOrderParams,
+					spotMarketIndex,
+					basisLimit,
+					fulfillmentConfig,
+					makerInfo,
+					referrerInfo
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		this.perpMarketLastSlotCache.set(perpOrderParams.marketIndex, slot);
+		this.spotMarketLastSlotCache.set(spotMarketIndex, slot);
 		this.spotMarketLastSlotCache.set(QUOTE_SPOT_MARKET_INDEX, slot);
 		return txSig;
 	}
 
-	public async getPlaceAndTakeSpotOrderIx(
-		orderParams: OptionalOrderParams,
-		fulfillmentConfig?: SerumV3FulfillmentConfigAccount,
-		makerInfo?: MakerInfo,
+	/**
+	 * Fills the perp order then an offsetting spot order through the external
+	 * fulfillment, priced at the perp fill price minus basisLimit
+	 * (PRICE_PRECISION). Reverts unless the spot leg fully fills.
+	 */
+	public async getPlaceAndTakeBasisOrderIx(
+		perpOrderParams: OptionalOrderParams,
+		spotMarketIndex: number,
+		basisLimit: BN,
+		fulfillmentConfig:
+			| SerumV3FulfillmentConfigAccount
+			| OpenbookV2FulfillmentConfigAccount
+			| PhoenixV1FulfillmentConfigAccount,
+		makerInfo?: MakerInfo | MakerInfo[],
 		referrerInfo?: ReferrerInfo
 	): Promise<TransactionInstruction> {
-		orderParams = this.getOrderParams(orderParams, MarketType.SPOT);
+		const orderParams = this.getOrderParams(
+			perpOrderParams,
+			MarketType.PERP
+		);
 		const userStatsPublicKey = await this.getUserStatsAccountPublicKey();
 		const userAccountPublicKey = await this.getUserAccountPublicKey();
 
+		makerInfo = Array.isArray(makerInfo)
+			? makerInfo
+			: makerInfo
+			? [makerInfo]
+			: [];
+
 		const userAccounts = [this.getUserAccount()];
-		if (makerInfo !== undefined) {
-			userAccounts.push(makerInfo.makerUserAccount);
+		for (const maker of makerInfo) {
+			userAccounts.push(maker.makerUserAccount);
 		}
+
 		const remainingAccounts = this.getRemainingAccounts({
 			userAccounts,
 			useMarketLastSlotCache: true,
-			writableSpotMarketIndexes: [
-				orderParams.marketIndex,
-				QUOTE_SPOT_MARKET_INDEX,
-			],
+			writablePerpMarketIndexes: [orderParams.marketIndex],
+			writableSpotMarketIndexes: [spotMarketIndex, QUOTE_SPOT_MARKET_INDEX],
 		});
+		this.addMarketWhitelistEntryToRemainingAccounts(
+			remainingAccounts,
+			orderParams.marketIndex,
+			this.getUserAccount().authority,
+			...makerInfo.map((maker) => maker.makerUserAccount.authority)
+		);
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.PERP,
+			orderParams.marketIndex
+		);
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.SPOT,
+			spotMarketIndex
+		);
 
-		let makerOrderId = null;
-		if (makerInfo) {
-			makerOrderId = makerInfo.order.orderId;
-			remainingAccounts.push({
-				pubkey: makerInfo.maker,
-				isSigner: false,
-				isWritable: true,
-			});
-			remainingAccounts.push({
-				pubkey: makerInfo.makerStats,
-				isSigner: false,
-				isWritable: true,
-			});
-		}
+		this.addMakersToRemainingAccounts(remainingAccounts, makerInfo);
 
 		if (referrerInfo) {
-			remainingAccounts.push({
-				pubkey: referrerInfo.referrer,
-				isWritable: true,
-				isSigner: false,
-			});
-			remainingAccounts.push({
-				pubkey: referrerInfo.referrerStats,
-				isWritable: true,
-				isSigner: false,
-			});
+			const referrerIsMaker =
+				makerInfo.find((maker) => maker.maker.equals(referrerInfo.referrer)) !==
+				undefined;
+			if (!referrerIsMaker) {
+				remainingAccounts.push({
+					pubkey: referrerInfo.referrer,
+					isWritable: true,
+					isSigner: false,
+				});
+				remainingAccounts.push({
+					pubkey: referrerInfo.referrerStats,
+					isWritable: true,
+					isSigner: false,
+				});
+			}
 		}
 
 		this.addSpotFulfillmentAccounts(
-			orderParams.marketIndex,
+			spotMarketIndex,
 			remainingAccounts,
 			fulfillmentConfig
 		);
 
-		return await this.program.instruction.placeAndTakeSpotOrder(
+		return await this.program.instruction.placeAndTakeBasisOrder(
 			orderParams,
-			fulfillmentConfig ? fulfillmentConfig.fulfillmentType : null,
-			makerOrderId,
+			spotMarketIndex,
+			basisLimit,
+			fulfillmentConfig.fulfillmentType,
 			{
 				accounts: {
 					state: await this.getStatePublicKey(),
@@ -3121,7 +6016,10 @@ export class DriftClient {
 	public async placeAndMakeSpotOrder(
 		orderParams: OptionalOrderParams,
 		takerInfo: TakerInfo,
-		fulfillmentConfig?: SerumV3FulfillmentConfigAccount,
+		fulfillmentConfig?:
+			| SerumV3FulfillmentConfigAccount
+			| OpenbookV2FulfillmentConfigAccount
+			| PhoenixV1FulfillmentConfigAccount,
 		referrerInfo?: ReferrerInfo,
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
@@ -3147,7 +6045,10 @@ export class DriftClient {
 	public async getPlaceAndMakeSpotOrderIx(
 		orderParams: OptionalOrderParams,
 		takerInfo: TakerInfo,
-		fulfillmentConfig?: SerumV3FulfillmentConfigAccount,
+		fulfillmentConfig?:
+			| SerumV3FulfillmentConfigAccount
+			| OpenbookV2FulfillmentConfigAccount
+			| PhoenixV1FulfillmentConfigAccount,
 		referrerInfo?: ReferrerInfo
 	): Promise<TransactionInstruction> {
 		orderParams = this.getOrderParams(orderParams, MarketType.SPOT);
@@ -3162,6 +6063,11 @@ export class DriftClient {
 				QUOTE_SPOT_MARKET_INDEX,
 			],
 		});
+		this.addMarketFeeOverrideToRemainingAccounts(
+			remainingAccounts,
+			MarketType.SPOT,
+			orderParams.marketIndex
+		);
 
 		if (referrerInfo) {
 			remainingAccounts.push({
@@ -3567,6 +6473,144 @@ export class DriftClient {
 		});
 	}
 
+	public async settleFundingAgainstBorrow(
+		settleeUserAccountPublicKey: PublicKey,
+		settleeUserAccount: UserAccount,
+		marketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getSettleFundingAgainstBorrowIx(
+					settleeUserAccountPublicKey,
+					settleeUserAccount,
+					marketIndex
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getSettleFundingAgainstBorrowIx(
+		settleeUserAccountPublicKey: PublicKey,
+		settleeUserAccount: UserAccount,
+		marketIndex: number
+	): Promise<TransactionInstruction> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [settleeUserAccount],
+			writablePerpMarketIndexes: [marketIndex],
+			writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
+
+		return await this.program.instruction.settleFundingAgainstBorrow(
+			marketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					authority: this.wallet.publicKey,
+					user: settleeUserAccountPublicKey,
+					spotMarketVault: this.getQuoteSpotMarketAccount().vault,
+				},
+				remainingAccounts: remainingAccounts,
+			}
+		);
+	}
+
+	public async settlePNLBatch(
+		settleeUserAccountPublicKeys: PublicKey[],
+		settleeUserAccounts: UserAccount[],
+		marketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.settlePNLBatchIx(
+					settleeUserAccountPublicKeys,
+					settleeUserAccounts,
+					marketIndex
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async settlePNLBatchIx(
+		settleeUserAccountPublicKeys: PublicKey[],
+		settleeUserAccounts: UserAccount[],
+		marketIndex: number
+	): Promise<TransactionInstruction> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: settleeUserAccounts,
+			writablePerpMarketIndexes: [marketIndex],
+			writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
+
+		for (const userAccountPublicKey of settleeUserAccountPublicKeys) {
+			remainingAccounts.push({
+				pubkey: userAccountPublicKey,
+				isSigner: false,
+				isWritable: true,
+			});
+		}
+
+		return await this.program.instruction.settlePnlBatch(marketIndex, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				authority: this.wallet.publicKey,
+				spotMarketVault: this.getQuoteSpotMarketAccount().vault,
+			},
+			remainingAccounts,
+		});
+	}
+
+	public async rebalancePnlPools(
+		perpMarketIndexes: number[],
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getRebalancePnlPoolsIx(perpMarketIndexes),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getRebalancePnlPoolsIx(
+		perpMarketIndexes: number[]
+	): Promise<TransactionInstruction> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [],
+			writablePerpMarketIndexes: perpMarketIndexes,
+			writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
+
+		return await this.program.instruction.rebalancePnlPools(
+			perpMarketIndexes,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					pnlPoolRebalanceConfig: getPnlPoolRebalanceConfigPublicKey(
+						this.program.programId
+					),
+					spotMarketVault: this.getQuoteSpotMarketAccount().vault,
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
 	public async liquidatePerp(
 		userAccountPublicKey: PublicKey,
 		userAccount: UserAccount,
@@ -3614,6 +6658,11 @@ export class DriftClient {
 			useMarketLastSlotCache: true,
 			writablePerpMarketIndexes: [marketIndex],
 		});
+		this.addMarketWhitelistEntryToRemainingAccounts(
+			remainingAccounts,
+			marketIndex,
+			this.getUserAccount().authority
+		);
 
 		return await this.program.instruction.liquidatePerp(
 			marketIndex,
@@ -3633,6 +6682,81 @@ export class DriftClient {
 		);
 	}
 
+	public async liquidatePerpWithBackstop(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		marketIndex: number,
+		maxBaseAssetAmount: BN,
+		limitPrice?: BN,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig, slot } = await this.sendTransaction(
+			wrapInTx(
+				await this.getLiquidatePerpWithBackstopIx(
+					userAccountPublicKey,
+					userAccount,
+					marketIndex,
+					maxBaseAssetAmount,
+					limitPrice
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		this.perpMarketLastSlotCache.set(marketIndex, slot);
+		return txSig;
+	}
+
+	public async getLiquidatePerpWithBackstopIx(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		marketIndex: number,
+		maxBaseAssetAmount: BN,
+		limitPrice?: BN
+	): Promise<TransactionInstruction> {
+		const userStatsPublicKey = getUserStatsAccountPublicKey(
+			this.program.programId,
+			userAccount.authority
+		);
+
+		const liquidatorPublicKey = await this.getUserAccountPublicKey();
+		const liquidatorStatsPublicKey = this.getUserStatsAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount(), userAccount],
+			useMarketLastSlotCache: true,
+			writablePerpMarketIndexes: [marketIndex],
+		});
+		this.addMarketWhitelistEntryToRemainingAccounts(
+			remainingAccounts,
+			marketIndex,
+			this.getUserAccount().authority
+		);
+
+		return await this.program.instruction.liquidatePerpWithBackstop(
+			marketIndex,
+			maxBaseAssetAmount,
+			limitPrice ?? null,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					authority: this.wallet.publicKey,
+					user: userAccountPublicKey,
+					userStats: userStatsPublicKey,
+					liquidator: liquidatorPublicKey,
+					liquidatorStats: liquidatorStatsPublicKey,
+					backstopProvider: getBackstopProviderPublicKey(
+						this.program.programId,
+						liquidatorPublicKey
+					),
+				},
+				remainingAccounts: remainingAccounts,
+			}
+		);
+	}
+
 	public async liquidateSpot(
 		userAccountPublicKey: PublicKey,
 		userAccount: UserAccount,
@@ -3775,88 +6899,342 @@ export class DriftClient {
 		);
 	}
 
-	public async liquidatePerpPnlForDeposit(
-		userAccountPublicKey: PublicKey,
-		userAccount: UserAccount,
+	public async liquidatePerpPnlForDeposit(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		perpMarketIndex: number,
+		assetMarketIndex: number,
+		maxPnlTransfer: BN,
+		limitPrice?: BN,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig, slot } = await this.sendTransaction(
+			wrapInTx(
+				await this.getLiquidatePerpPnlForDepositIx(
+					userAccountPublicKey,
+					userAccount,
+					perpMarketIndex,
+					assetMarketIndex,
+					maxPnlTransfer,
+					limitPrice
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		this.perpMarketLastSlotCache.set(perpMarketIndex, slot);
+		this.spotMarketLastSlotCache.set(assetMarketIndex, slot);
+		return txSig;
+	}
+
+	public async getLiquidatePerpPnlForDepositIx(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		perpMarketIndex: number,
+		assetMarketIndex: number,
+		maxPnlTransfer: BN,
+		limitPrice?: BN
+	): Promise<TransactionInstruction> {
+		const userStatsPublicKey = getUserStatsAccountPublicKey(
+			this.program.programId,
+			userAccount.authority
+		);
+
+		const liquidatorPublicKey = await this.getUserAccountPublicKey();
+		const liquidatorStatsPublicKey = await this.getUserStatsAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount(), userAccount],
+			writablePerpMarketIndexes: [perpMarketIndex],
+			writableSpotMarketIndexes: [assetMarketIndex],
+		});
+
+		return await this.program.instruction.liquidatePerpPnlForDeposit(
+			perpMarketIndex,
+			assetMarketIndex,
+			maxPnlTransfer,
+			limitPrice || null,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					authority: this.wallet.publicKey,
+					user: userAccountPublicKey,
+					userStats: userStatsPublicKey,
+					liquidator: liquidatorPublicKey,
+					liquidatorStats: liquidatorStatsPublicKey,
+				},
+				remainingAccounts: remainingAccounts,
+			}
+		);
+	}
+
+	public async resolvePerpBankruptcy(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		marketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getResolvePerpBankruptcyIx(
+					userAccountPublicKey,
+					userAccount,
+					marketIndex
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getResolvePerpBankruptcyIx(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		marketIndex: number
+	): Promise<TransactionInstruction> {
+		const userStatsPublicKey = getUserStatsAccountPublicKey(
+			this.program.programId,
+			userAccount.authority
+		);
+
+		const liquidatorPublicKey = await this.getUserAccountPublicKey();
+		const liquidatorStatsPublicKey = await this.getUserStatsAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount(), userAccount],
+			writablePerpMarketIndexes: [marketIndex],
+			writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
+
+		const spotMarket = this.getQuoteSpotMarketAccount();
+
+		return await this.program.instruction.resolvePerpBankruptcy(
+			QUOTE_SPOT_MARKET_INDEX,
+			marketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					authority: this.wallet.publicKey,
+					user: userAccountPublicKey,
+					userStats: userStatsPublicKey,
+					liquidator: liquidatorPublicKey,
+					liquidatorStats: liquidatorStatsPublicKey,
+					spotMarketVault: spotMarket.vault,
+					insuranceFundVault: spotMarket.insuranceFund.vault,
+					driftSigner: this.getSignerPublicKey(),
+					tokenProgram: TOKEN_PROGRAM_ID,
+				},
+				remainingAccounts: remainingAccounts,
+			}
+		);
+	}
+
+	public async autoDeleveragePerp(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		marketIndex: number,
+		counterparties: { publicKey: PublicKey; account: UserAccount }[],
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getAutoDeleveragePerpIx(
+					userAccountPublicKey,
+					userAccount,
+					marketIndex,
+					counterparties
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	/**
+	 * @param counterparties opposing positions to deleverage against, the program ranks them by auto-deleverage score
+	 */
+	public async getAutoDeleveragePerpIx(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		marketIndex: number,
+		counterparties: { publicKey: PublicKey; account: UserAccount }[]
+	): Promise<TransactionInstruction> {
+		const keeperPublicKey = await this.getUserAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [
+				userAccount,
+				...counterparties.map((counterparty) => counterparty.account),
+			],
+			writablePerpMarketIndexes: [marketIndex],
+			writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
+		});
+
+		for (const counterparty of counterparties) {
+			remainingAccounts.push({
+				pubkey: counterparty.publicKey,
+				isWritable: true,
+				isSigner: false,
+			});
+		}
+
+		return await this.program.instruction.autoDeleveragePerp(marketIndex, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				authority: this.wallet.publicKey,
+				keeper: keeperPublicKey,
+				user: userAccountPublicKey,
+				insuranceFundVault:
+					this.getQuoteSpotMarketAccount().insuranceFund.vault,
+			},
+			remainingAccounts: remainingAccounts,
+		});
+	}
+
+	public async resolveSpotBankruptcy(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		marketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getResolveSpotBankruptcyIx(
+					userAccountPublicKey,
+					userAccount,
+					marketIndex
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getResolveSpotBankruptcyIx(
+		userAccountPublicKey: PublicKey,
+		userAccount: UserAccount,
+		marketIndex: number
+	): Promise<TransactionInstruction> {
+		const userStatsPublicKey = getUserStatsAccountPublicKey(
+			this.program.programId,
+			userAccount.authority
+		);
+
+		const liquidatorPublicKey = await this.getUserAccountPublicKey();
+		const liquidatorStatsPublicKey = await this.getUserStatsAccountPublicKey();
+
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [this.getUserAccount(), userAccount],
+			writableSpotMarketIndexes: [marketIndex],
+		});
+
+		const spotMarket = this.getSpotMarketAccount(marketIndex);
+
+		return await this.program.instruction.resolveSpotBankruptcy(marketIndex, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				authority: this.wallet.publicKey,
+				user: userAccountPublicKey,
+				userStats: userStatsPublicKey,
+				liquidatorStats: liquidatorStatsPublicKey,
+				liquidator: liquidatorPublicKey,
+				spotMarketVault: spotMarket.vault,
+				insuranceFundVault: spotMarket.insuranceFund.vault,
+				driftSigner: this.getSignerPublicKey(),
+				tokenProgram: TOKEN_PROGRAM_ID,
+			},
+			remainingAccounts: remainingAccounts,
+		});
+	}
+
+	public async updateFundingRate(
+		perpMarketIndex: number,
+		oracle: PublicKey,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getUpdateFundingRateIx(perpMarketIndex, oracle),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getUpdateFundingRateIx(
 		perpMarketIndex: number,
-		assetMarketIndex: number,
-		maxPnlTransfer: BN,
-		limitPrice?: BN,
+		oracle: PublicKey
+	): Promise<TransactionInstruction> {
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
+			this.program.programId,
+			perpMarketIndex
+		);
+		return await this.program.instruction.updateFundingRate(perpMarketIndex, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				perpMarket: perpMarketPublicKey,
+				oracle: oracle,
+			},
+		});
+	}
+
+	public async updateAMMPeg(
+		perpMarketIndex: number,
+		oracle: PublicKey,
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
-		const { txSig, slot } = await this.sendTransaction(
+		const { txSig } = await this.sendTransaction(
 			wrapInTx(
-				await this.getLiquidatePerpPnlForDepositIx(
-					userAccountPublicKey,
-					userAccount,
-					perpMarketIndex,
-					assetMarketIndex,
-					maxPnlTransfer,
-					limitPrice
-				),
+				await this.getUpdateAMMPegIx(perpMarketIndex, oracle),
 				txParams?.computeUnits,
 				txParams?.computeUnitsPrice
 			),
 			[],
 			this.opts
 		);
-		this.perpMarketLastSlotCache.set(perpMarketIndex, slot);
-		this.spotMarketLastSlotCache.set(assetMarketIndex, slot);
 		return txSig;
 	}
 
-	public async getLiquidatePerpPnlForDepositIx(
-		userAccountPublicKey: PublicKey,
-		userAccount: UserAccount,
+	public async getUpdateAMMPegIx(
 		perpMarketIndex: number,
-		assetMarketIndex: number,
-		maxPnlTransfer: BN,
-		limitPrice?: BN
+		oracle: PublicKey
 	): Promise<TransactionInstruction> {
-		const userStatsPublicKey = getUserStatsAccountPublicKey(
+		const perpMarketPublicKey = await getPerpMarketPublicKey(
 			this.program.programId,
-			userAccount.authority
+			perpMarketIndex
 		);
-
-		const liquidatorPublicKey = await this.getUserAccountPublicKey();
-		const liquidatorStatsPublicKey = await this.getUserStatsAccountPublicKey();
-
-		const remainingAccounts = this.getRemainingAccounts({
-			userAccounts: [this.getUserAccount(), userAccount],
-			writablePerpMarketIndexes: [perpMarketIndex],
-			writableSpotMarketIndexes: [assetMarketIndex],
+		return await this.program.instruction.updateAmmPeg({
+			accounts: {
+				state: await this.getStatePublicKey(),
+				perpMarket: perpMarketPublicKey,
+				oracle: oracle,
+			},
 		});
-
-		return await this.program.instruction.liquidatePerpPnlForDeposit(
-			perpMarketIndex,
-			assetMarketIndex,
-			maxPnlTransfer,
-			limitPrice || null,
-			{
-				accounts: {
-					state: await this.getStatePublicKey(),
-					authority: this.wallet.publicKey,
-					user: userAccountPublicKey,
-					userStats: userStatsPublicKey,
-					liquidator: liquidatorPublicKey,
-					liquidatorStats: liquidatorStatsPublicKey,
-				},
-				remainingAccounts: remainingAccounts,
-			}
-		);
 	}
 
-	public async resolvePerpBankruptcy(
+	public async updatePerpLpRangeStatus(
 		userAccountPublicKey: PublicKey,
-		userAccount: UserAccount,
 		marketIndex: number,
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
 		const { txSig } = await this.sendTransaction(
 			wrapInTx(
-				await this.getResolvePerpBankruptcyIx(
+				await this.getUpdatePerpLpRangeStatusIx(
 					userAccountPublicKey,
-					userAccount,
 					marketIndex
 				),
 				txParams?.computeUnits,
@@ -3868,61 +7246,44 @@ export class DriftClient {
 		return txSig;
 	}
 
-	public async getResolvePerpBankruptcyIx(
+	public async getUpdatePerpLpRangeStatusIx(
 		userAccountPublicKey: PublicKey,
-		userAccount: UserAccount,
 		marketIndex: number
 	): Promise<TransactionInstruction> {
-		const userStatsPublicKey = getUserStatsAccountPublicKey(
-			this.program.programId,
-			userAccount.authority
-		);
-
-		const liquidatorPublicKey = await this.getUserAccountPublicKey();
-		const liquidatorStatsPublicKey = await this.getUserStatsAccountPublicKey();
+		const userAccount = (await this.program.account.user.fetch(
+			userAccountPublicKey
+		)) as UserAccount;
 
 		const remainingAccounts = this.getRemainingAccounts({
-			userAccounts: [this.getUserAccount(), userAccount],
+			userAccounts: [userAccount],
 			writablePerpMarketIndexes: [marketIndex],
-			writableSpotMarketIndexes: [QUOTE_SPOT_MARKET_INDEX],
 		});
 
-		const spotMarket = this.getQuoteSpotMarketAccount();
-
-		return await this.program.instruction.resolvePerpBankruptcy(
-			QUOTE_SPOT_MARKET_INDEX,
+		return await this.program.instruction.updatePerpLpRangeStatus(
 			marketIndex,
 			{
 				accounts: {
 					state: await this.getStatePublicKey(),
-					authority: this.wallet.publicKey,
 					user: userAccountPublicKey,
-					userStats: userStatsPublicKey,
-					liquidator: liquidatorPublicKey,
-					liquidatorStats: liquidatorStatsPublicKey,
-					spotMarketVault: spotMarket.vault,
-					insuranceFundVault: spotMarket.insuranceFund.vault,
-					driftSigner: this.getSignerPublicKey(),
-					tokenProgram: TOKEN_PROGRAM_ID,
+					perpLpRange: getPerpLpRangePublicKeySync(
+						this.program.programId,
+						userAccountPublicKey,
+						marketIndex
+					),
+					authority: this.wallet.publicKey,
 				},
-				remainingAccounts: remainingAccounts,
+				remainingAccounts,
 			}
 		);
 	}
 
-	public async resolveSpotBankruptcy(
+	public async settleFundingPayment(
 		userAccountPublicKey: PublicKey,
-		userAccount: UserAccount,
-		marketIndex: number,
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
 		const { txSig } = await this.sendTransaction(
 			wrapInTx(
-				await this.getResolveSpotBankruptcyIx(
-					userAccountPublicKey,
-					userAccount,
-					marketIndex
-				),
+				await this.getSettleFundingPaymentIx(userAccountPublicKey),
 				txParams?.computeUnits,
 				txParams?.computeUnitsPrice
 			),
@@ -3932,51 +7293,173 @@ export class DriftClient {
 		return txSig;
 	}
 
-	public async getResolveSpotBankruptcyIx(
-		userAccountPublicKey: PublicKey,
-		userAccount: UserAccount,
-		marketIndex: number
+	public async getSettleFundingPaymentIx(
+		userAccountPublicKey: PublicKey
 	): Promise<TransactionInstruction> {
-		const userStatsPublicKey = getUserStatsAccountPublicKey(
-			this.program.programId,
-			userAccount.authority
-		);
+		const userAccount = (await this.program.account.user.fetch(
+			userAccountPublicKey
+		)) as UserAccount;
 
-		const liquidatorPublicKey = await this.getUserAccountPublicKey();
-		const liquidatorStatsPublicKey = await this.getUserStatsAccountPublicKey();
+		const writablePerpMarketIndexes = [];
+		for (const position of userAccount.perpPositions) {
+			if (!positionIsAvailable(position)) {
+				writablePerpMarketIndexes.push(position.marketIndex);
+			}
+		}
 
 		const remainingAccounts = this.getRemainingAccounts({
-			userAccounts: [this.getUserAccount(), userAccount],
-			writableSpotMarketIndexes: [marketIndex],
+			userAccounts: [userAccount],
+			writablePerpMarketIndexes,
 		});
 
-		const spotMarket = this.getSpotMarketAccount(marketIndex);
-
-		return await this.program.instruction.resolveSpotBankruptcy(marketIndex, {
+		return await this.program.instruction.settleFundingPayment({
 			accounts: {
 				state: await this.getStatePublicKey(),
-				authority: this.wallet.publicKey,
 				user: userAccountPublicKey,
-				userStats: userStatsPublicKey,
-				liquidatorStats: liquidatorStatsPublicKey,
-				liquidator: liquidatorPublicKey,
-				spotMarketVault: spotMarket.vault,
-				insuranceFundVault: spotMarket.insuranceFund.vault,
-				driftSigner: this.getSignerPublicKey(),
-				tokenProgram: TOKEN_PROGRAM_ID,
 			},
-			remainingAccounts: remainingAccounts,
+			remainingAccounts,
 		});
 	}
 
-	public async updateFundingRate(
-		perpMarketIndex: number,
-		oracle: PublicKey,
+	public async getPredictedFundingRate(
+		marketIndex: number
+	): Promise<PredictedFunding> {
+		const tx = new Transaction().add(
+			await this.getPredictedFundingRateIx(marketIndex)
+		);
+		tx.feePayer = this.wallet.publicKey;
+
+		const { value } = await this.connection.simulateTransaction(tx);
+		if (value.err || !value.returnData) {
+			throw new Error(
+				`get predicted funding rate failed: ${JSON.stringify(value.err)}`
+			);
+		}
+
+		const data = Buffer.from(value.returnData.data[0], 'base64');
+		const read = (offset: number, bytes: number) =>
+			new BN(data.subarray(offset, offset + bytes), 'le');
+		return {
+			fundingRate: read(0, 8).fromTwos(64),
+			nextFundingRateTs: read(8, 8).fromTwos(64),
+		};
+	}
+
+	public async getPredictedFundingRateIx(
+		marketIndex: number
+	): Promise<TransactionInstruction> {
+		return await this.program.instruction.getPredictedFundingRate({
+			accounts: {
+				state: await this.getStatePublicKey(),
+				perpMarket: await getPerpMarketPublicKey(
+					this.program.programId,
+					marketIndex
+				),
+			},
+		});
+	}
+
+	/**
+	 * Depth of a perp market from the amm and the resting orders of the given
+	 * users, in depth levels per side that are priceStep wide
+	 */
+	public async getOrderBookSnapshot(
+		marketIndex: number,
+		priceStep: BN,
+		depth: number,
+		userAccountPublicKeys: PublicKey[]
+	): Promise<OrderBookSnapshot> {
+		const tx = new Transaction().add(
+			await this.getOrderBookSnapshotIx(
+				marketIndex,
+				priceStep,
+				depth,
+				userAccountPublicKeys
+			)
+		);
+		tx.feePayer = this.wallet.publicKey;
+
+		const { value } = await this.connection.simulateTransaction(tx);
+		if (value.err || !value.returnData) {
+			throw new Error(
+				`get order book snapshot failed: ${JSON.stringify(value.err)}`
+			);
+		}
+
+		const data = Buffer.from(value.returnData.data[0], 'base64');
+		let offset = 0;
+		const read = (bytes: number) => {
+			const bn = new BN(data.subarray(offset, offset + bytes), 'le');
+			offset += bytes;
+			return bn;
+		};
+		const readLevels = () => {
+			const levels: OrderBookLevel[] = [];
+			const length = read(4).toNumber();
+			for (let i = 0; i < length; i++) {
+				levels.push({ price: read(8), baseAssetAmount: read(8) });
+			}
+			return levels;
+		};
+
+		return {
+			slot: read(8),
+			oraclePrice: read(8).fromTwos(64),
+			ammBidPrice: read(8),
+			ammAskPrice: read(8),
+			bids: readLevels(),
+			asks: readLevels(),
+		};
+	}
+
+	public async getOrderBookSnapshotIx(
+		marketIndex: number,
+		priceStep: BN,
+		depth: number,
+		userAccountPublicKeys: PublicKey[]
+	): Promise<TransactionInstruction> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [],
+			readablePerpMarketIndex: marketIndex,
+		});
+		for (const userAccountPublicKey of userAccountPublicKeys) {
+			remainingAccounts.push({
+				pubkey: userAccountPublicKey,
+				isSigner: false,
+				isWritable: false,
+			});
+		}
+
+		return await this.program.instruction.getOrderBookSnapshot(
+			marketIndex,
+			priceStep,
+			depth,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
+	/**
+	 * Adds the accounts of the given markets missing from the canonical lookup table for tableIndex.
+	 * The caller pays the rent of the added addresses
+	 */
+	public async extendMarketLookupTable(
+		tableIndex: number,
+		perpMarketIndexes: number[],
+		spotMarketIndexes: number[],
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
 		const { txSig } = await this.sendTransaction(
 			wrapInTx(
-				await this.getUpdateFundingRateIx(perpMarketIndex, oracle),
+				await this.getExtendMarketLookupTableIx(
+					tableIndex,
+					perpMarketIndexes,
+					spotMarketIndexes
+				),
 				txParams?.computeUnits,
 				txParams?.computeUnitsPrice
 			),
@@ -3986,30 +7469,61 @@ export class DriftClient {
 		return txSig;
 	}
 
-	public async getUpdateFundingRateIx(
-		perpMarketIndex: number,
-		oracle: PublicKey
+	public async getExtendMarketLookupTableIx(
+		tableIndex: number,
+		perpMarketIndexes: number[],
+		spotMarketIndexes: number[]
 	): Promise<TransactionInstruction> {
-		const perpMarketPublicKey = await getPerpMarketPublicKey(
+		const marketLookupTable = getMarketLookupTablePublicKey(
 			this.program.programId,
-			perpMarketIndex
+			tableIndex
 		);
-		return await this.program.instruction.updateFundingRate(perpMarketIndex, {
+		const marketLookupTableAccount =
+			(await this.program.account.marketLookupTable.fetch(
+				marketLookupTable
+			)) as MarketLookupTableAccount;
+
+		const remainingAccounts: AccountMeta[] = [];
+		for (const spotMarketIndex of spotMarketIndexes) {
+			remainingAccounts.push({
+				pubkey: this.getSpotMarketAccount(spotMarketIndex).pubkey,
+				isSigner: false,
+				isWritable: false,
+			});
+		}
+		for (const perpMarketIndex of perpMarketIndexes) {
+			remainingAccounts.push({
+				pubkey: this.getPerpMarketAccount(perpMarketIndex).pubkey,
+				isSigner: false,
+				isWritable: false,
+			});
+		}
+
+		return await this.program.instruction.extendMarketLookupTable(tableIndex, {
 			accounts: {
+				authority: this.wallet.publicKey,
 				state: await this.getStatePublicKey(),
-				perpMarket: perpMarketPublicKey,
-				oracle: oracle,
+				marketLookupTable,
+				lookupTable: marketLookupTableAccount.lookupTable,
+				driftSigner: this.getSignerPublicKey(),
+				addressLookupTableProgram: AddressLookupTableProgram.programId,
+				systemProgram: anchor.web3.SystemProgram.programId,
 			},
+			remainingAccounts,
 		});
 	}
 
-	public async settleFundingPayment(
-		userAccountPublicKey: PublicKey,
+	public async settleFundingPaymentsBatch(
+		userAccountPublicKeys: PublicKey[],
+		marketIndex: number,
 		txParams?: TxParams
 	): Promise<TransactionSignature> {
 		const { txSig } = await this.sendTransaction(
 			wrapInTx(
-				await this.getSettleFundingPaymentIx(userAccountPublicKey),
+				await this.getSettleFundingPaymentsBatchIx(
+					userAccountPublicKeys,
+					marketIndex
+				),
 				txParams?.computeUnits,
 				txParams?.computeUnitsPrice
 			),
@@ -4019,32 +7533,32 @@ export class DriftClient {
 		return txSig;
 	}
 
-	public async getSettleFundingPaymentIx(
-		userAccountPublicKey: PublicKey
+	public async getSettleFundingPaymentsBatchIx(
+		userAccountPublicKeys: PublicKey[],
+		marketIndex: number
 	): Promise<TransactionInstruction> {
-		const userAccount = (await this.program.account.user.fetch(
-			userAccountPublicKey
-		)) as UserAccount;
-
-		const writablePerpMarketIndexes = [];
-		for (const position of userAccount.perpPositions) {
-			if (!positionIsAvailable(position)) {
-				writablePerpMarketIndexes.push(position.marketIndex);
-			}
-		}
-
 		const remainingAccounts = this.getRemainingAccounts({
-			userAccounts: [userAccount],
-			writablePerpMarketIndexes,
+			userAccounts: [],
+			writablePerpMarketIndexes: [marketIndex],
 		});
 
-		return await this.program.instruction.settleFundingPayment({
-			accounts: {
-				state: await this.getStatePublicKey(),
-				user: userAccountPublicKey,
-			},
-			remainingAccounts,
-		});
+		for (const userAccountPublicKey of userAccountPublicKeys) {
+			remainingAccounts.push({
+				pubkey: userAccountPublicKey,
+				isSigner: false,
+				isWritable: true,
+			});
+		}
+
+		return await this.program.instruction.settleFundingPaymentsBatch(
+			marketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+				},
+				remainingAccounts,
+			}
+		);
 	}
 
 	public triggerEvent(eventName: keyof DriftClientAccountEvents, data?: any) {
@@ -4107,6 +7621,145 @@ export class DriftClient {
 		);
 	}
 
+	public async initializeUserFuel(): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeUserFuel({
+			accounts: {
+				userFuel: getUserFuelPublicKeySync(
+					this.program.programId,
+					this.wallet.publicKey
+				),
+				authority: this.wallet.publicKey,
+				payer: this.wallet.publicKey,
+				rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+				systemProgram: anchor.web3.SystemProgram.programId,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	/**
+	 * Accrues insurance and borrow fuel for an authority. Every sub account of the authority
+	 * must be passed, along with the fuel boosts of the markets that earn fuel
+	 */
+	public async updateUserFuel(
+		authority: PublicKey,
+		userAccounts: UserAccount[],
+		fuelBoosts: PublicKey[]
+	): Promise<TransactionSignature> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts,
+		});
+
+		for (const userAccount of userAccounts) {
+			remainingAccounts.push({
+				pubkey: getUserAccountPublicKeySync(
+					this.program.programId,
+					authority,
+					userAccount.subAccountId
+				),
+				isWritable: true,
+				isSigner: false,
+			});
+		}
+
+		for (const fuelBoost of fuelBoosts) {
+			remainingAccounts.push({
+				pubkey: fuelBoost,
+				isWritable: false,
+				isSigner: false,
+			});
+		}
+
+		const tx = await this.program.transaction.updateUserFuel({
+			accounts: {
+				state: await this.getStatePublicKey(),
+				userFuel: getUserFuelPublicKeySync(this.program.programId, authority),
+				userStats: getUserStatsAccountPublicKey(
+					this.program.programId,
+					authority
+				),
+			},
+			remainingAccounts,
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async initializeGovernanceStake(): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.initializeGovernanceStake({
+			accounts: {
+				governanceStake: getGovernanceStakePublicKeySync(
+					this.program.programId,
+					this.wallet.publicKey
+				),
+				authority: this.wallet.publicKey,
+				payer: this.wallet.publicKey,
+				rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+				systemProgram: anchor.web3.SystemProgram.programId,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	/**
+	 * Stakes the governance token for taker fee discounts and boosted referrer rewards
+	 */
+	public async addGovernanceStake(
+		amount: BN,
+		tokenAccountPublicKey: PublicKey
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.addGovernanceStake(amount, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				governanceStake: getGovernanceStakePublicKeySync(
+					this.program.programId,
+					this.wallet.publicKey
+				),
+				userStats: this.getUserStatsAccountPublicKey(),
+				authority: this.wallet.publicKey,
+				governanceTokenMint: this.getStateAccount().discountMint,
+				governanceStakeVault: getGovernanceStakeVaultPublicKey(
+					this.program.programId
+				),
+				userTokenAccount: tokenAccountPublicKey,
+				tokenProgram: TOKEN_PROGRAM_ID,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	/**
+	 * Only allowed once the unstaking period has passed since the last stake
+	 */
+	public async removeGovernanceStake(
+		amount: BN,
+		tokenAccountPublicKey: PublicKey
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.removeGovernanceStake(amount, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				governanceStake: getGovernanceStakePublicKeySync(
+					this.program.programId,
+					this.wallet.publicKey
+				),
+				userStats: this.getUserStatsAccountPublicKey(),
+				authority: this.wallet.publicKey,
+				governanceTokenMint: this.getStateAccount().discountMint,
+				governanceStakeVault: getGovernanceStakeVaultPublicKey(
+					this.program.programId
+				),
+				driftSigner: this.getSignerPublicKey(),
+				userTokenAccount: tokenAccountPublicKey,
+				tokenProgram: TOKEN_PROGRAM_ID,
+			},
+		});
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
 	public async addInsuranceFundStake(
 		marketIndex: number,
 		amount: BN,
@@ -4222,6 +7875,105 @@ export class DriftClient {
 		return txSig;
 	}
 
+	public async updateInsuranceFundStakeLockupTier(
+		marketIndex: number,
+		lockupTier: InsuranceFundStakeLockupTier
+	): Promise<TransactionSignature> {
+		const spotMarketAccount = this.getSpotMarketAccount(marketIndex);
+		const ifStakeAccountPublicKey = getInsuranceFundStakeAccountPublicKey(
+			this.program.programId,
+			this.wallet.publicKey,
+			marketIndex
+		);
+
+		const tx = await this.program.transaction.updateInsuranceFundStakeLockupTier(
+			marketIndex,
+			lockupTier,
+			{
+				accounts: {
+					spotMarket: spotMarketAccount.pubkey,
+					insuranceFundStake: ifStakeAccountPublicKey,
+					userStats: this.getUserStatsAccountPublicKey(),
+					authority: this.wallet.publicKey,
+					insuranceFundVault: spotMarketAccount.insuranceFund.vault,
+				},
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async wrapInsuranceFundStake(
+		marketIndex: number,
+		amount: BN,
+		shareTokenAccountPublicKey: PublicKey
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.wrapInsuranceFundStake(
+			marketIndex,
+			amount,
+			{
+				accounts: await this.getWrapInsuranceFundStakeAccounts(
+					marketIndex,
+					shareTokenAccountPublicKey
+				),
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	public async unwrapInsuranceFundStake(
+		marketIndex: number,
+		tokenAmount: BN,
+		shareTokenAccountPublicKey: PublicKey
+	): Promise<TransactionSignature> {
+		const tx = await this.program.transaction.unwrapInsuranceFundStake(
+			marketIndex,
+			tokenAmount,
+			{
+				accounts: await this.getWrapInsuranceFundStakeAccounts(
+					marketIndex,
+					shareTokenAccountPublicKey
+				),
+			}
+		);
+
+		const { txSig } = await this.sendTransaction(tx, [], this.opts);
+		return txSig;
+	}
+
+	async getWrapInsuranceFundStakeAccounts(
+		marketIndex: number,
+		shareTokenAccountPublicKey: PublicKey
+	) {
+		const spotMarketAccount = this.getSpotMarketAccount(marketIndex);
+		return {
+			state: await this.getStatePublicKey(),
+			spotMarket: spotMarketAccount.pubkey,
+			insuranceFundStake: getInsuranceFundStakeAccountPublicKey(
+				this.program.programId,
+				this.wallet.publicKey,
+				marketIndex
+			),
+			wrappedInsuranceFundStake: getWrappedInsuranceFundStakePublicKey(
+				this.program.programId,
+				marketIndex
+			),
+			userStats: this.getUserStatsAccountPublicKey(),
+			authority: this.wallet.publicKey,
+			insuranceFundVault: spotMarketAccount.insuranceFund.vault,
+			insuranceFundShareMint: getInsuranceFundShareMintPublicKey(
+				this.program.programId,
+				marketIndex
+			),
+			userTokenAccount: shareTokenAccountPublicKey,
+			driftSigner: this.getSignerPublicKey(),
+			tokenProgram: TOKEN_PROGRAM_ID,
+		};
+	}
+
 	public async removeInsuranceFundStake(
 		marketIndex: number,
 		collateralAccountPublicKey: PublicKey
@@ -4338,6 +8090,55 @@ export class DriftClient {
 		);
 	}
 
+	public async updateInsuranceFundLendToAmm(
+		spotMarketIndex: number,
+		perpMarketIndex: number,
+		txParams?: TxParams
+	): Promise<TransactionSignature> {
+		const { txSig } = await this.sendTransaction(
+			wrapInTx(
+				await this.getUpdateInsuranceFundLendToAmmIx(
+					spotMarketIndex,
+					perpMarketIndex
+				),
+				txParams?.computeUnits,
+				txParams?.computeUnitsPrice
+			),
+			[],
+			this.opts
+		);
+		return txSig;
+	}
+
+	public async getUpdateInsuranceFundLendToAmmIx(
+		spotMarketIndex: number,
+		perpMarketIndex: number
+	): Promise<TransactionInstruction> {
+		const remainingAccounts = this.getRemainingAccounts({
+			userAccounts: [],
+			writablePerpMarketIndexes: [perpMarketIndex],
+			writableSpotMarketIndexes: [spotMarketIndex],
+		});
+
+		const spotMarket = this.getSpotMarketAccount(spotMarketIndex);
+
+		return await this.program.instruction.updateInsuranceFundLendToAmm(
+			spotMarketIndex,
+			perpMarketIndex,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					authority: this.wallet.publicKey,
+					spotMarketVault: spotMarket.vault,
+					insuranceFundVault: spotMarket.insuranceFund.vault,
+					driftSigner: this.getSignerPublicKey(),
+					tokenProgram: TOKEN_PROGRAM_ID,
+				},
+				remainingAccounts: remainingAccounts,
+			}
+		);
+	}
+
 	public getPerpMarketExtendedInfo(
 		marketIndex: number
 	): PerpMarketExtendedInfo {
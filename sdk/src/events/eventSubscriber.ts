@@ -17,6 +17,7 @@ import { WebSocketLogProvider } from './webSocketLogProvider';
 import { EventEmitter } from 'events';
 import StrictEventEmitter from 'strict-event-emitter-types';
 import { getSortFn } from './sort';
+import { expandFillRecordBatch } from './fillRecordBatch';
 
 export class EventSubscriber {
 	private eventListMap: Map<EventType, EventList<EventType>>;
@@ -165,6 +166,20 @@ export class EventSubscriber {
 		const records = [];
 		// @ts-ignore
 		this.program._events._eventParser.parseLogs(logs, (event) => {
+			if (event.name === 'FillRecordBatch') {
+				if (this.eventListMap.has('OrderActionRecord')) {
+					for (const record of expandFillRecordBatch(event.data)) {
+						records.push({
+							...record,
+							txSig,
+							slot,
+							eventType: 'OrderActionRecord',
+						});
+					}
+				}
+				return;
+			}
+
 			const expectRecordType = this.eventListMap.has(event.name);
 			if (expectRecordType) {
 				event.data.txSig = txSig;
@@ -8,6 +8,7 @@ import {
 	VersionedTransactionResponse,
 } from '@solana/web3.js';
 import { WrappedEvents } from './types';
+import { expandFillRecordBatch } from './fillRecordBatch';
 
 type Log = { txSig: TransactionSignature; slot: number; logs: string[] };
 type FetchLogsResponse = {
@@ -121,6 +122,18 @@ export class LogParser {
 		const records: WrappedEvents = [];
 		// @ts-ignore
 		this.program._events._eventParser.parseLogs(event.logs, (eventLog) => {
+			if (eventLog.name === 'FillRecordBatch') {
+				for (const record of expandFillRecordBatch(eventLog.data)) {
+					records.push({
+						...record,
+						txSig: event.txSig,
+						slot: event.slot,
+						eventType: 'OrderActionRecord',
+					});
+				}
+				return;
+			}
+
 			eventLog.data.txSig = event.txSig;
 			eventLog.data.slot = event.slot;
 			eventLog.data.eventType = eventLog.name;
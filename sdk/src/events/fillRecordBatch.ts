@@ -0,0 +1,23 @@
+import { FillRecordBatch, OrderAction, OrderActionRecord } from '../types';
+
+/**
+ * Perp fills are logged as one FillRecordBatch per instruction unless the
+ * program is built with the legacy-fill-events feature. Expands a batch into
+ * the OrderActionRecords it replaces.
+ */
+export function expandFillRecordBatch(
+	batch: FillRecordBatch
+): OrderActionRecord[] {
+	return batch.fills.map((fill) => {
+		return {
+			...fill,
+			ts: batch.ts,
+			action: OrderAction.FILL,
+			marketIndex: batch.marketIndex,
+			marketType: batch.marketType,
+			filler: batch.filler,
+			spotFulfillmentMethodFee: null,
+			oraclePrice: batch.oraclePrice,
+		};
+	});
+}
@@ -2,6 +2,7 @@ import { Commitment, TransactionSignature } from '@solana/web3.js';
 import {
 	DepositRecord,
 	FundingPaymentRecord,
+	FundingBorrowSettleRecord,
 	FundingRateRecord,
 	LiquidationRecord,
 	NewUserRecord,
@@ -13,6 +14,10 @@ import {
 	SpotInterestRecord,
 	InsuranceFundStakeRecord,
 	CurveRecord,
+	SwapRecord,
+	UserRiskRecord,
+	MarginWarningRecord,
+	IcebergReplenishRecord,
 } from '../index';
 
 export type EventSubscriptionOptions = {
@@ -32,6 +37,7 @@ export const DefaultEventSubscriptionOptions: EventSubscriptionOptions = {
 	eventTypes: [
 		'DepositRecord',
 		'FundingPaymentRecord',
+		'FundingBorrowSettleRecord',
 		'LiquidationRecord',
 		'OrderRecord',
 		'OrderActionRecord',
@@ -43,6 +49,10 @@ export const DefaultEventSubscriptionOptions: EventSubscriptionOptions = {
 		'SpotInterestRecord',
 		'InsuranceFundStakeRecord',
 		'CurveRecord',
+		'SwapRecord',
+		'UserRiskRecord',
+		'MarginWarningRecord',
+		'IcebergReplenishRecord',
 	],
 	maxEventsPerType: 4096,
 	orderBy: 'blockchain',
@@ -72,6 +82,7 @@ export type WrappedEvents = WrappedEvent<EventType>[];
 export type EventMap = {
 	DepositRecord: Event<DepositRecord>;
 	FundingPaymentRecord: Event<FundingPaymentRecord>;
+	FundingBorrowSettleRecord: Event<FundingBorrowSettleRecord>;
 	LiquidationRecord: Event<LiquidationRecord>;
 	FundingRateRecord: Event<FundingRateRecord>;
 	OrderRecord: Event<OrderRecord>;
@@ -83,6 +94,10 @@ export type EventMap = {
 	SpotInterestRecord: Event<SpotInterestRecord>;
 	InsuranceFundStakeRecord: Event<InsuranceFundStakeRecord>;
 	CurveRecord: Event<CurveRecord>;
+	SwapRecord: Event<SwapRecord>;
+	UserRiskRecord: Event<UserRiskRecord>;
+	MarginWarningRecord: Event<MarginWarningRecord>;
+	IcebergReplenishRecord: Event<IcebergReplenishRecord>;
 };
 
 export type EventType = keyof EventMap;
@@ -90,6 +105,7 @@ export type EventType = keyof EventMap;
 export type DriftEvent =
 	| Event<DepositRecord>
 	| Event<FundingPaymentRecord>
+	| Event<FundingBorrowSettleRecord>
 	| Event<LiquidationRecord>
 	| Event<FundingRateRecord>
 	| Event<OrderRecord>
@@ -100,7 +116,11 @@ export type DriftEvent =
 	| Event<InsuranceFundRecord>
 	| Event<SpotInterestRecord>
 	| Event<InsuranceFundStakeRecord>
-	| Event<CurveRecord>;
+	| Event<CurveRecord>
+	| Event<SwapRecord>
+	| Event<UserRiskRecord>
+	| Event<MarginWarningRecord>
+	| Event<IcebergReplenishRecord>;
 
 export interface EventSubscriberEvents {
 	newEvent: (event: WrappedEvent<EventType>) => void;
@@ -483,6 +483,112 @@ export function calculateVolSpreadBN(
 	return [longVolSpread, shortVolSpread];
 }
 
+export function calculateDynamicSpread(
+	baseAssetAmountWithAmm: BN,
+	baseAssetReserve: BN,
+	minBaseAssetReserve: BN,
+	maxBaseAssetReserve: BN,
+	reservePrice: BN,
+	markStd: BN,
+	oracleStd: BN,
+	inventorySkewSpread: number,
+	volatilitySpreadScale: number
+): [number, number] {
+	// realized volatility widens both sides
+	let volatilitySpread = ZERO;
+	if (volatilitySpreadScale > 0) {
+		volatilitySpread = BN.max(markStd, oracleStd)
+			.mul(PERCENTAGE_PRECISION)
+			.div(BN.max(reservePrice, ONE))
+			.mul(new BN(volatilitySpreadScale))
+			.div(new BN(100));
+	}
+
+	// inventory skew only widens the side that adds to the amm's inventory
+	let inventorySpread = ZERO;
+	if (inventorySkewSpread > 0 && !baseAssetAmountWithAmm.eq(ZERO)) {
+		const [openBids, openAsks] = calculateMarketOpenBidAsk(
+			baseAssetReserve,
+			minBaseAssetReserve,
+			maxBaseAssetReserve
+		);
+		const minSideLiquidity = BN.min(openBids.abs(), openAsks.abs());
+		const inventoryPct = BN.min(
+			baseAssetAmountWithAmm
+				.abs()
+				.mul(PERCENTAGE_PRECISION)
+				.div(BN.max(minSideLiquidity, ONE)),
+			PERCENTAGE_PRECISION
+		);
+		inventorySpread = new BN(inventorySkewSpread)
+			.mul(BID_ASK_SPREAD_PRECISION.div(new BN(10000)))
+			.mul(inventoryPct)
+			.div(PERCENTAGE_PRECISION);
+	}
+
+	if (baseAssetAmountWithAmm.gt(ZERO)) {
+		return [
+			volatilitySpread.add(inventorySpread).toNumber(),
+			volatilitySpread.toNumber(),
+		];
+	} else {
+		return [
+			volatilitySpread.toNumber(),
+			volatilitySpread.add(inventorySpread).toNumber(),
+		];
+	}
+}
+
+export function calculateReferencePriceOffset(
+	baseAssetAmountWithAmm: BN,
+	baseAssetAmountLong: BN,
+	baseAssetAmountShort: BN,
+	baseAssetReserve: BN,
+	minBaseAssetReserve: BN,
+	maxBaseAssetReserve: BN,
+	maxReferencePriceOffset: number
+): number {
+	if (maxReferencePriceOffset == 0) {
+		return 0;
+	}
+
+	const [openBids, openAsks] = calculateMarketOpenBidAsk(
+		baseAssetReserve,
+		minBaseAssetReserve,
+		maxBaseAssetReserve
+	);
+	const minSideLiquidity = BN.min(openBids.abs(), openAsks.abs());
+	let inventoryPct = BN.min(
+		baseAssetAmountWithAmm
+			.abs()
+			.mul(PERCENTAGE_PRECISION)
+			.div(BN.max(minSideLiquidity, ONE)),
+		PERCENTAGE_PRECISION
+	);
+	if (baseAssetAmountWithAmm.lt(ZERO)) {
+		inventoryPct = inventoryPct.neg();
+	}
+
+	const openInterest = baseAssetAmountLong.add(baseAssetAmountShort.abs());
+	const imbalancePct = openInterest.gt(ZERO)
+		? baseAssetAmountLong
+				.sub(baseAssetAmountShort.abs())
+				.mul(PERCENTAGE_PRECISION)
+				.div(openInterest)
+		: ZERO;
+
+	const offsetPct = BN.max(
+		BN.min(inventoryPct.add(imbalancePct).div(new BN(2)), PERCENTAGE_PRECISION),
+		PERCENTAGE_PRECISION.neg()
+	);
+
+	return new BN(maxReferencePriceOffset)
+		.mul(BID_ASK_SPREAD_PRECISION.div(new BN(10000)))
+		.mul(offsetPct)
+		.div(PERCENTAGE_PRECISION)
+		.toNumber();
+}
+
 export function calculateSpreadBN(
 	baseSpread: number,
 	lastOracleReservePriceSpreadPct: BN,
@@ -503,7 +609,9 @@ export function calculateSpreadBN(
 	longIntensity: BN,
 	shortIntensity: BN,
 	volume24H: BN,
-	returnTerms = false
+	returnTerms = false,
+	inventorySkewSpread = 0,
+	volatilitySpreadScale = 0
 ) {
 	assert(Number.isInteger(baseSpread));
 	assert(Number.isInteger(maxSpread));
@@ -657,6 +765,20 @@ export function calculateSpreadBN(
 	spreadTerms.longSpreadwRevRetreat = longSpread;
 	spreadTerms.shortSpreadwRevRetreat = shortSpread;
 
+	const [longDynamicSpread, shortDynamicSpread] = calculateDynamicSpread(
+		baseAssetAmountWithAmm,
+		baseAssetReserve,
+		minBaseAssetReserve,
+		maxBaseAssetReserve,
+		reservePrice,
+		markStd,
+		oracleStd,
+		inventorySkewSpread,
+		volatilitySpreadScale
+	);
+	longSpread += longDynamicSpread;
+	shortSpread += shortDynamicSpread;
+
 	const totalSpread = longSpread + shortSpread;
 	if (totalSpread > maxTargetSpread) {
 		if (longSpread > shortSpread) {
@@ -726,10 +848,31 @@ export function calculateSpread(
 		liveOracleStd,
 		amm.longIntensityVolume,
 		amm.shortIntensityVolume,
-		amm.volume24H
+		amm.volume24H,
+		false,
+		amm.inventorySkewSpread,
+		amm.volatilitySpreadScale
+	);
+	let longSpread = spreads[0];
+	let shortSpread = spreads[1];
+
+	// shift the quoted mid by the reference price offset, keeping half the base spread per side
+	const referencePriceOffset = calculateReferencePriceOffset(
+		amm.baseAssetAmountWithAmm,
+		amm.baseAssetAmountLong,
+		amm.baseAssetAmountShort,
+		amm.baseAssetReserve,
+		amm.minBaseAssetReserve,
+		amm.maxBaseAssetReserve,
+		amm.maxReferencePriceOffset
+	);
+	const halfBaseSpread = Math.floor(amm.baseSpread / 2);
+	const offset = Math.max(
+		Math.min(halfBaseSpread - longSpread, 0),
+		Math.min(referencePriceOffset, Math.max(shortSpread - halfBaseSpread, 0))
 	);
-	const longSpread = spreads[0];
-	const shortSpread = spreads[1];
+	longSpread += offset;
+	shortSpread -= offset;
 
 	return [longSpread, shortSpread];
 }
@@ -1,5 +1,7 @@
 import { isOneOfVariant, isVariant, Order } from '../types';
-import { BN, ZERO } from '../.';
+import { BN, ONE, PERCENTAGE_PRECISION } from '../.';
+
+const EXPONENTIAL_AUCTION_CURVE_EXPONENT = new BN(8);
 
 export function isAuctionComplete(order: Order, slot: number): boolean {
 	if (order.auctionDuration === 0) {
@@ -36,28 +38,19 @@ export function getAuctionPrice(
 }
 
 export function getAuctionPriceForFixedAuction(order: Order, slot: number): BN {
-	const slotsElapsed = new BN(slot).sub(order.slot);
-
-	const deltaDenominator = new BN(order.auctionDuration);
-	const deltaNumerator = BN.min(slotsElapsed, deltaDenominator);
-
-	if (deltaDenominator.eq(ZERO)) {
+	if (order.auctionDuration === 0) {
 		return order.auctionEndPrice;
 	}
 
-	let priceDelta;
+	let priceRange;
 	if (isVariant(order.direction, 'long')) {
-		priceDelta = order.auctionEndPrice
-			.sub(order.auctionStartPrice)
-			.mul(deltaNumerator)
-			.div(deltaDenominator);
+		priceRange = order.auctionEndPrice.sub(order.auctionStartPrice);
 	} else {
-		priceDelta = order.auctionStartPrice
-			.sub(order.auctionEndPrice)
-			.mul(deltaNumerator)
-			.div(deltaDenominator);
+		priceRange = order.auctionStartPrice.sub(order.auctionEndPrice);
 	}
 
+	const priceDelta = getAuctionPriceDelta(order, slot, priceRange);
+
 	let price;
 	if (isVariant(order.direction, 'long')) {
 		price = order.auctionStartPrice.add(priceDelta);
@@ -73,28 +66,19 @@ export function getAuctionPriceForOracleOffsetAuction(
 	slot: number,
 	oraclePrice: BN
 ): BN {
-	const slotsElapsed = new BN(slot).sub(order.slot);
-
-	const deltaDenominator = new BN(order.auctionDuration);
-	const deltaNumerator = BN.min(slotsElapsed, deltaDenominator);
-
-	if (deltaDenominator.eq(ZERO)) {
+	if (order.auctionDuration === 0) {
 		return order.auctionEndPrice.add(order.auctionEndPrice);
 	}
 
-	let priceOffsetDelta;
+	let priceOffsetRange;
 	if (isVariant(order.direction, 'long')) {
-		priceOffsetDelta = order.auctionEndPrice
-			.sub(order.auctionStartPrice)
-			.mul(deltaNumerator)
-			.div(deltaDenominator);
+		priceOffsetRange = order.auctionEndPrice.sub(order.auctionStartPrice);
 	} else {
-		priceOffsetDelta = order.auctionStartPrice
-			.sub(order.auctionEndPrice)
-			.mul(deltaNumerator)
-			.div(deltaDenominator);
+		priceOffsetRange = order.auctionStartPrice.sub(order.auctionEndPrice);
 	}
 
+	const priceOffsetDelta = getAuctionPriceDelta(order, slot, priceOffsetRange);
+
 	let priceOffset;
 	if (isVariant(order.direction, 'long')) {
 		priceOffset = order.auctionStartPrice.add(priceOffsetDelta);
@@ -104,3 +88,44 @@ export function getAuctionPriceForOracleOffsetAuction(
 
 	return oraclePrice.add(priceOffset);
 }
+
+export function getAuctionPriceDelta(
+	order: Order,
+	slot: number,
+	priceRange: BN
+): BN {
+	const auctionDuration = new BN(order.auctionDuration);
+	const slotsElapsed = BN.min(new BN(slot).sub(order.slot), auctionDuration);
+
+	let deltaNumerator;
+	let deltaDenominator;
+	if (isVariant(order.auctionCurve, 'exponential')) {
+		// (2^(k * t) - 1) / (2^k - 1), interpolated linearly between whole powers of two
+		const exponent = slotsElapsed
+			.mul(EXPONENTIAL_AUCTION_CURVE_EXPONENT)
+			.mul(PERCENTAGE_PRECISION)
+			.div(auctionDuration);
+		const wholeExponent = exponent.div(PERCENTAGE_PRECISION);
+		const exponentRemainder = exponent.sub(
+			wholeExponent.mul(PERCENTAGE_PRECISION)
+		);
+		const powerOfTwo = new BN(2).pow(wholeExponent);
+
+		deltaNumerator = powerOfTwo
+			.mul(PERCENTAGE_PRECISION.add(exponentRemainder))
+			.sub(PERCENTAGE_PRECISION);
+		deltaDenominator = new BN(2)
+			.pow(EXPONENTIAL_AUCTION_CURVE_EXPONENT)
+			.sub(ONE)
+			.mul(PERCENTAGE_PRECISION);
+	} else if (isVariant(order.auctionCurve, 'step')) {
+		const stepCount = new BN(order.auctionStepCount);
+		deltaNumerator = slotsElapsed.mul(stepCount).div(auctionDuration);
+		deltaDenominator = stepCount;
+	} else {
+		deltaNumerator = slotsElapsed;
+		deltaDenominator = auctionDuration;
+	}
+
+	return priceRange.mul(deltaNumerator).div(deltaDenominator);
+}
@@ -1,5 +1,6 @@
 import { BN } from '@project-serum/anchor';
 import {
+	MarginTier,
 	PerpMarketAccount,
 	PositionDirection,
 	MarginCategory,
@@ -24,6 +25,7 @@ import {
 	BASE_PRECISION,
 	MARGIN_PRECISION,
 	PRICE_TO_QUOTE_PRECISION,
+	QUOTE_PRECISION,
 	ZERO,
 	QUOTE_SPOT_MARKET_INDEX,
 } from '../constants/numericConstants';
@@ -129,15 +131,25 @@ export function calculateOracleSpread(
 export function calculateMarketMarginRatio(
 	market: PerpMarketAccount,
 	size: BN,
-	marginCategory: MarginCategory
+	marginCategory: MarginCategory,
+	userHighLeverageMode = false
 ): number {
+	const highLeverageMode =
+		userHighLeverageMode &&
+		market.highLeverageMarginRatioInitial > 0 &&
+		market.highLeverageMarginRatioMaintenance > 0;
+
 	let marginRatio;
 	switch (marginCategory) {
 		case 'Initial': {
 			marginRatio = calculateSizePremiumLiabilityWeight(
 				size,
 				new BN(market.imfFactor),
-				new BN(market.marginRatioInitial),
+				new BN(
+					highLeverageMode
+						? market.highLeverageMarginRatioInitial
+						: market.marginRatioInitial
+				),
 				MARGIN_PRECISION
 			).toNumber();
 			break;
@@ -146,7 +158,11 @@ export function calculateMarketMarginRatio(
 			marginRatio = calculateSizePremiumLiabilityWeight(
 				size,
 				new BN(market.imfFactor),
-				new BN(market.marginRatioMaintenance),
+				new BN(
+					highLeverageMode
+						? market.highLeverageMarginRatioMaintenance
+						: market.marginRatioMaintenance
+				),
 				MARGIN_PRECISION
 			).toNumber();
 			break;
@@ -156,6 +172,36 @@ export function calculateMarketMarginRatio(
 	return marginRatio;
 }
 
+/**
+ * Highest initial margin ratio among the market's tiers whose notional threshold is reached, 0 if none
+ * @param marginTiers
+ * @param baseAssetValue : QUOTE_PRECISION
+ * @returns margin ratio : MARGIN_PRECISION
+ */
+export function calculateMarginTierRatio(
+	marginTiers: MarginTier[],
+	baseAssetValue: BN
+): number {
+	let marginTierRatio = 0;
+	for (const marginTier of marginTiers) {
+		if (marginTier.notionalThreshold === 0) {
+			break;
+		}
+
+		if (
+			baseAssetValue.lt(
+				new BN(marginTier.notionalThreshold).mul(QUOTE_PRECISION)
+			)
+		) {
+			break;
+		}
+
+		marginTierRatio = Math.max(marginTierRatio, marginTier.marginRatioInitial);
+	}
+
+	return marginTierRatio;
+}
+
 export function calculateUnrealizedAssetWeight(
 	market: PerpMarketAccount,
 	quoteSpotMarket: SpotMarketAccount,
@@ -4,7 +4,9 @@ import {
 	isVariant,
 	PerpMarketAccount,
 	AMM,
+	FillReceipt,
 	Order,
+	OrderStatus,
 	PositionDirection,
 } from '../types';
 import { ZERO, TWO } from '../constants/numericConstants';
@@ -297,3 +299,27 @@ export function isRestingLimitOrder(order: Order, slot: number): boolean {
 export function isTakingOrder(order: Order, slot: number): boolean {
 	return isMarketOrder(order) || !isRestingLimitOrder(order, slot);
 }
+
+const ORDER_STATUSES = [
+	OrderStatus.INIT,
+	OrderStatus.OPEN,
+	OrderStatus.FILLED,
+	OrderStatus.CANCELED,
+];
+
+/**
+ * Decodes the receipt place_and_take_perp_order and fill_perp_order set as return data
+ * @param data the transaction's return data
+ */
+export function decodeFillReceipt(data: Buffer): FillReceipt {
+	const read = (offset: number, bytes: number) =>
+		new BN(data.subarray(offset, offset + bytes), 'le');
+	return {
+		orderId: data.readUInt32LE(0),
+		status: ORDER_STATUSES[data[4]],
+		baseAssetAmountFilled: read(5, 8),
+		quoteAssetAmountFilled: read(13, 8),
+		fillPrice: read(21, 8),
+		feePaid: read(29, 8).fromTwos(64),
+	};
+}
@@ -224,33 +224,43 @@ export function calculateUtilization(bank: SpotMarketAccount): BN {
 export function calculateInterestRate(bank: SpotMarketAccount): BN {
 	const utilization = calculateUtilization(bank);
 
-	let interestRate: BN;
-	if (utilization.gt(new BN(bank.optimalUtilization))) {
-		const surplusUtilization = utilization.sub(new BN(bank.optimalUtilization));
-		const borrowRateSlope = new BN(bank.maxBorrowRate - bank.optimalBorrowRate)
-			.mul(SPOT_MARKET_UTILIZATION_PRECISION)
-			.div(
-				SPOT_MARKET_UTILIZATION_PRECISION.sub(new BN(bank.optimalUtilization))
-			);
+	// piecewise linear through zero, the optimal kink, any extra kinks and the max rate at 100%
+	const curve: [BN, BN][] = [
+		[ZERO, ZERO],
+		[new BN(bank.optimalUtilization), new BN(bank.optimalBorrowRate)],
+	];
+	for (const kink of bank.borrowRateKinks ?? []) {
+		if (kink.utilization !== 0) {
+			curve.push([new BN(kink.utilization), new BN(kink.borrowRate)]);
+		}
+	}
+	curve.push([
+		SPOT_MARKET_UTILIZATION_PRECISION,
+		new BN(bank.maxBorrowRate),
+	]);
+
+	// utilization above 100% extends the last segment
+	let segmentEnd = curve.findIndex(
+		([kinkUtilization], i) => i > 0 && utilization.lte(kinkUtilization)
+	);
+	if (segmentEnd === -1) {
+		segmentEnd = curve.length - 1;
+	}
 
-		interestRate = new BN(bank.optimalBorrowRate).add(
-			surplusUtilization
-				.mul(borrowRateSlope)
-				.div(SPOT_MARKET_UTILIZATION_PRECISION)
-		);
-	} else {
-		const borrowRateSlope = new BN(bank.optimalBorrowRate)
-			.mul(SPOT_MARKET_UTILIZATION_PRECISION)
-			.div(
-				SPOT_MARKET_UTILIZATION_PRECISION.sub(new BN(bank.optimalUtilization))
-			);
+	const [startUtilization, startBorrowRate] = curve[segmentEnd - 1];
+	const [endUtilization, endBorrowRate] = curve[segmentEnd];
 
-		interestRate = utilization
-			.mul(borrowRateSlope)
-			.div(SPOT_MARKET_UTILIZATION_PRECISION);
-	}
+	const borrowRateSlope = endBorrowRate
+		.sub(startBorrowRate)
+		.mul(SPOT_MARKET_UTILIZATION_PRECISION)
+		.div(endUtilization.sub(startUtilization));
 
-	return interestRate;
+	return startBorrowRate.add(
+		utilization
+			.sub(startUtilization)
+			.mul(borrowRateSlope)
+			.div(SPOT_MARKET_UTILIZATION_PRECISION)
+	);
 }
 
 export function calculateDepositRate(bank: SpotMarketAccount): BN {
@@ -15,6 +15,33 @@ export enum ExchangeStatus {
 	PAUSED = 127,
 }
 
+export enum PerpOperation {
+	ORACLE_CIRCUIT_BREAKER = 1,
+	UPDATE_FUNDING = 2,
+	AMM_FILL = 4,
+	FILL = 8,
+	SETTLE_PNL = 16,
+	SETTLE_POSITIVE_PNL = 32,
+	LIQUIDATION = 64,
+	RISK_INCREASING_ORDER = 128,
+}
+
+export enum SpotOperation {
+	UPDATE_CUMULATIVE_INTEREST = 1,
+	FILL = 2,
+	DEPOSIT = 4,
+	WITHDRAW = 8,
+	LIQUIDATION = 16,
+}
+
+export enum DelegatePermission {
+	PLACE_ORDERS = 1,
+	CANCEL_ORDERS = 2,
+	WITHDRAW_TO_AUTHORITY = 4,
+	SETTLE = 8,
+	MANAGE_LP = 16,
+}
+
 export class MarketStatus {
 	static readonly INITIALIZED = { initialized: {} };
 	static readonly ACTIVE = { active: {} };
@@ -33,9 +60,15 @@ export class UserStatus {
 	static readonly BANKRUPT = { bankrupt: {} };
 }
 
+export class PerpLpTier {
+	static readonly STANDARD = { standard: {} };
+	static readonly PRO = { pro: {} };
+}
+
 export class ContractType {
 	static readonly PERPETUAL = { perpetual: {} };
 	static readonly FUTURE = { future: {} };
+	static readonly PREDICTION = { prediction: {} };
 }
 
 export class ContractTier {
@@ -80,6 +113,10 @@ export class OracleSource {
 	static readonly PYTH_1M = { pyth1M: {} };
 	// static readonly SWITCHBOARD = { switchboard: {} };
 	static readonly QUOTE_ASSET = { quoteAsset: {} };
+	static readonly SWITCHBOARD_ON_DEMAND = { switchboardOnDemand: {} };
+	static readonly PYTH_PULL = { pythPull: {} };
+	static readonly PRELAUNCH = { prelaunch: {} };
+	static readonly LST_REDEMPTION_RATE = { lstRedemptionRate: {} };
 }
 
 export class OrderType {
@@ -88,6 +125,7 @@ export class OrderType {
 	static readonly TRIGGER_LIMIT = { triggerLimit: {} };
 	static readonly MARKET = { market: {} };
 	static readonly ORACLE = { oracle: {} };
+	static readonly TWAP = { twap: {} };
 }
 
 export declare type MarketTypeStr = 'perp' | 'spot';
@@ -99,6 +137,8 @@ export class MarketType {
 export class OrderStatus {
 	static readonly INIT = { init: {} };
 	static readonly OPEN = { open: {} };
+	static readonly FILLED = { filled: {} };
+	static readonly CANCELED = { canceled: {} };
 }
 
 export class OrderAction {
@@ -150,6 +190,21 @@ export class OrderActionExplanation {
 	static readonly REDUCE_ONLY_ORDER_INCREASED_POSITION = {
 		reduceOnlyOrderIncreasedPosition: {},
 	};
+	static readonly LINKED_ORDER_EXECUTED = {
+		linkedOrderExecuted: {},
+	};
+	static readonly ORDER_FILLED_WITH_OPENBOOK_V2 = {
+		orderFillWithOpenbookV2: {},
+	};
+	static readonly ORDER_FILLED_WITH_PHOENIX = {
+		orderFillWithPhoenix: {},
+	};
+	static readonly USER_ORDER_ID_REPLACED = {
+		userOrderIdReplaced: {},
+	};
+	static readonly ORPHANED_TRIGGER_ORDER = {
+		orphanedTriggerOrder: {},
+	};
 }
 
 export class OrderTriggerCondition {
@@ -157,10 +212,27 @@ export class OrderTriggerCondition {
 	static readonly BELOW = { below: {} };
 	static readonly TRIGGERED_ABOVE = { triggeredAbove: {} }; // above condition has been triggered
 	static readonly TRIGGERED_BELOW = { triggeredBelow: {} }; // below condition has been triggered
+	static readonly TRAILING_ABOVE = { trailingAbove: {} }; // trigger price trails the oracle from above
+	static readonly TRAILING_BELOW = { trailingBelow: {} }; // trigger price trails the oracle from below
+	static readonly FUNDING_ABOVE = { fundingAbove: {} }; // predicted funding rate above triggerFundingRate
+	static readonly FUNDING_BELOW = { fundingBelow: {} }; // predicted funding rate below triggerFundingRate
+}
+
+export class OrderTrailType {
+	static readonly ABSOLUTE = { absolute: {} };
+	static readonly BPS = { bps: {} };
+}
+
+export class AuctionCurve {
+	static readonly LINEAR = { linear: {} };
+	static readonly EXPONENTIAL = { exponential: {} };
+	static readonly STEP = { step: {} };
 }
 
 export class SpotFulfillmentType {
 	static readonly SERUM_v3 = { serumV3: {} };
+	static readonly OPENBOOK_V2 = { openbookV2: {} };
+	static readonly PHOENIX_V1 = { phoenixV1: {} };
 }
 
 export class SpotFulfillmentStatus {
@@ -183,11 +255,26 @@ export class SpotFulfillmentConfigStatus {
 	static readonly DISABLED = { disabled: {} };
 }
 
+export class AdminRole {
+	static readonly PAUSER = { pauser: {} };
+	static readonly RISK_PARAM_SETTER = { riskParamSetter: {} };
+	static readonly MARKET_LISTER = { marketLister: {} };
+	static readonly TREASURER = { treasurer: {} };
+}
+
 export class StakeAction {
 	static readonly STAKE = { stake: {} };
 	static readonly UNSTAKE_REQUEST = { unstakeRequest: {} };
 	static readonly UNSTAKE_CANCEL_REQUEST = { unstakeCancelRequest: {} };
 	static readonly UNSTAKE = { unstake: {} };
+	static readonly WRAP = { wrap: {} };
+	static readonly UNWRAP = { unwrap: {} };
+}
+
+export class InsuranceFundStakeLockupTier {
+	static readonly NONE = { none: {} };
+	static readonly THIRTY_DAY = { thirtyDay: {} };
+	static readonly NINETY_DAY = { ninetyDay: {} };
 }
 
 export function isVariant(object: unknown, type: string) {
@@ -314,6 +401,64 @@ export declare type InsuranceFundStakeRecord = {
 	totalIfSharesAfter: BN;
 };
 
+export type SwapRecord = {
+	ts: BN;
+	user: PublicKey;
+	amountOut: BN;
+	amountIn: BN;
+	outMarketIndex: number;
+	inMarketIndex: number;
+	outOraclePrice: BN;
+	inOraclePrice: BN;
+};
+
+export type SpotMarginContribution = {
+	marketIndex: number;
+	tokenAmount: BN;
+	oraclePrice: BN;
+	weightedValue: BN;
+	openOrdersMarginRequirement: BN;
+};
+
+export type PerpMarginContribution = {
+	marketIndex: number;
+	baseAssetAmount: BN;
+	quoteAssetAmount: BN;
+	oraclePrice: BN;
+	marginRequirement: BN;
+	weightedPnl: BN;
+	isIsolated: boolean;
+};
+
+export type MarginWarningRecord = {
+	ts: BN;
+	user: PublicKey;
+	marginWarning: boolean;
+	health: number;
+	totalCollateral: BN;
+	marginRequirement: BN;
+};
+
+export type IcebergReplenishRecord = {
+	ts: BN;
+	user: PublicKey;
+	orderId: number;
+	marketIndex: number;
+	marketType: MarketType;
+	displayQuantity: BN;
+	baseAssetAmountFilled: BN;
+	slot: BN;
+};
+
+export type UserRiskRecord = {
+	ts: BN;
+	slot: BN;
+	user: PublicKey;
+	marginHealth: MarginHealth;
+	spotContributions: SpotMarginContribution[];
+	perpContributions: PerpMarginContribution[];
+};
+
 export type LPRecord = {
 	ts: BN;
 	user: PublicKey;
@@ -359,6 +504,19 @@ export type FundingPaymentRecord = {
 	ammCumulativeFundingShort: BN;
 };
 
+export type FundingBorrowSettleRecord = {
+	ts: BN;
+	userAuthority: PublicKey;
+	user: PublicKey;
+	marketIndex: number;
+	fundingPayment: BN;
+	pnlSettled: BN;
+	baseAssetAmount: BN;
+	userLastCumulativeFunding: BN;
+	ammCumulativeFundingLong: BN;
+	ammCumulativeFundingShort: BN;
+};
+
 export type LiquidationRecord = {
 	ts: BN;
 	user: PublicKey;
@@ -376,6 +534,7 @@ export type LiquidationRecord = {
 	liquidatePerpPnlForDeposit: LiquidatePerpPnlForDepositRecord;
 	perpBankruptcy: PerpBankruptcyRecord;
 	spotBankruptcy: SpotBankruptcyRecord;
+	autoDeleverage: AutoDeleverageRecord;
 };
 
 export class LiquidationType {
@@ -395,6 +554,9 @@ export class LiquidationType {
 	static readonly LIQUIDATE_SPOT = {
 		liquidateSpot: {},
 	};
+	static readonly AUTO_DELEVERAGE = {
+		autoDeleverage: {},
+	};
 }
 
 export type LiquidatePerpRecord = {
@@ -447,6 +609,17 @@ export type PerpBankruptcyRecord = {
 	cumulativeFundingRateDelta: BN;
 };
 
+export type AutoDeleverageRecord = {
+	marketIndex: number;
+	oraclePrice: BN;
+	bankruptcyPrice: BN;
+	counterparty: PublicKey;
+	baseAssetAmount: BN;
+	quoteAssetAmount: BN;
+	counterpartyPnl: BN;
+	score: BN;
+};
+
 export type SpotBankruptcyRecord = {
 	marketIndex: number;
 	borrowAmount: BN;
@@ -503,6 +676,39 @@ export type OrderActionRecord = {
 	oraclePrice: BN;
 };
 
+export type CompactFillRecord = {
+	actionExplanation: OrderActionExplanation;
+	fillRecordId: BN;
+	fillerReward: BN | null;
+	baseAssetAmountFilled: BN;
+	quoteAssetAmountFilled: BN;
+	takerFee: BN | null;
+	makerFee: BN | null;
+	referrerReward: number | null;
+	quoteAssetAmountSurplus: BN | null;
+	taker: PublicKey | null;
+	takerOrderId: number | null;
+	takerOrderDirection: PositionDirection | null;
+	takerOrderBaseAssetAmount: BN | null;
+	takerOrderCumulativeBaseAssetAmountFilled: BN | null;
+	takerOrderCumulativeQuoteAssetAmountFilled: BN | null;
+	maker: PublicKey | null;
+	makerOrderId: number | null;
+	makerOrderDirection: PositionDirection | null;
+	makerOrderBaseAssetAmount: BN | null;
+	makerOrderCumulativeBaseAssetAmountFilled: BN | null;
+	makerOrderCumulativeQuoteAssetAmountFilled: BN | null;
+};
+
+export type FillRecordBatch = {
+	ts: BN;
+	marketIndex: number;
+	marketType: MarketType;
+	filler: PublicKey | null;
+	oraclePrice: BN;
+	fills: CompactFillRecord[];
+};
+
 export type StateAccount = {
 	admin: PublicKey;
 	exchangeStatus: number;
@@ -526,12 +732,40 @@ export type StateAccount = {
 	lpCooldownTime: BN;
 	initialPctToLiquidate: number;
 	liquidationDuration: number;
+	liquidationFeeRampDuration: number;
+	liquidationTargetMarginBufferRatio: number;
+	liquidationStaleFeeEscalationDuration: number;
+	adminTimelockDuration: number;
+	marginWarningHealth: number;
 };
 
 export type PerpMarketAccount = {
 	status: MarketStatus;
 	contractType: ContractType;
 	contractTier: ContractTier;
+	additionalOracles: PublicKey[];
+	additionalOracleSources: OracleSource[];
+	pausedOperations: number;
+	oracleCircuitBreakerBand: number;
+	auctionExtensionDuration: number;
+	auctionExtensionPriceBuffer: number;
+	marginTiers: MarginTier[];
+	highLeverageMarginRatioInitial: number;
+	highLeverageMarginRatioMaintenance: number;
+	correlationGroup: number;
+	correlationMarginCredit: number;
+	autoDeleverageEnabled: boolean;
+	insuranceFundMaxDrawPct: number;
+	lpEarlyRemovalPenalty: number;
+	lpMinDuration: number;
+	high24H: BN;
+	low24H: BN;
+	fundingPaid24H: BN;
+	insuranceFundLent: BN;
+	insuranceFundLendLastTs: BN;
+	insuranceFundLendRate: number;
+	orderTickSizeBps: number;
+	insuranceFundLendMaxPct: number;
 	expiryTs: BN;
 	expiryPrice: BN;
 	marketIndex: number;
@@ -579,6 +813,11 @@ export type HistoricalIndexData = {
 	lastIndexPriceTwapTs: BN;
 };
 
+export type BorrowRateKink = {
+	utilization: number;
+	borrowRate: number;
+};
+
 export type SpotMarketAccount = {
 	status: MarketStatus;
 	assetTier: AssetTier;
@@ -592,6 +831,17 @@ export type SpotMarketAccount = {
 	oracleSource: OracleSource;
 	historicalOracleData: HistoricalOracleData;
 	historicalIndexData: HistoricalIndexData;
+	validityGuardRailsOverride: ValidityGuardRailsOverride;
+	maxCollateralConcentration: number;
+	correlationMarginCredit: number;
+	correlationGroup: number;
+	ifBoostWeight: BN;
+	cumulativeIfBoostSharesPerWeight: BN;
+	revenueSettleMaxAmount: BN;
+	tokenProgram: number;
+	borrowRateKinks: BorrowRateKink[];
+	insuranceFundLentToAmms: BN;
+	pausedOperations: number;
 
 	insuranceFund: {
 		vault: PublicKey;
@@ -620,6 +870,11 @@ export type SpotMarketAccount = {
 	depositBalance: BN;
 	borrowBalance: BN;
 	maxTokenDeposits: BN;
+	maxTokenBorrows: BN;
+	maxNetWithdrawBps: number;
+	flashBorrowFeeBps: number;
+	flashBorrowsEnabled: boolean;
+	feeOverrideEnabled: boolean;
 
 	lastInterestTs: BN;
 	lastTwapTs: BN;
@@ -702,6 +957,18 @@ export type AMM = {
 	lastUpdateSlot: BN;
 	lastOracleNormalisedPrice: BN;
 	lastOracleValid: boolean;
+	validityGuardRailsOverride: ValidityGuardRailsOverride;
+	maxFundingRateSpread: number;
+	escalatedMaxFundingRateSpread: number;
+	fundingDivergenceEscalationPeriods: number;
+	fundingDivergencePeriodCount: number;
+	inventorySkewSpread: number;
+	volatilitySpreadScale: number;
+	maxReferencePriceOffset: number;
+	referencePriceOffset: number;
+	feeOverrideEnabled: boolean;
+	delistingEnabled: boolean;
+	whitelistEnabled: boolean;
 	lastBidPriceTwap: BN;
 	lastAskPriceTwap: BN;
 	longSpread: number;
@@ -754,6 +1021,9 @@ export type PerpPosition = {
 	remainderBaseAssetAmount: number;
 	lastBaseAssetAmountPerLp: BN;
 	lastQuoteAssetAmountPerLp: BN;
+	isolatedCollateral: BN;
+	liquidationPrice: BN;
+	isIsolated: boolean;
 };
 
 export type UserStatsAccount = {
@@ -775,6 +1045,14 @@ export type UserStatsAccount = {
 	};
 	referrer: PublicKey;
 	isReferrer: boolean;
+	referrerRewardShare: number;
+	secondLevelReferrerRewardShare: number;
+	referrerRewardDecayMonths: number;
+	disableTradingUntil: BN;
+	approvedBuilder: PublicKey;
+	builderMaxFeeShare: number;
+	governanceStakeTier: number;
+	referredTs: number;
 	authority: PublicKey;
 	ifStakedQuoteAssetAmount: BN;
 };
@@ -801,6 +1079,18 @@ export type UserAccount = {
 	lastActiveSlot: BN;
 	isMarginTradingEnabled: boolean;
 	idle: boolean;
+	isProtectedMaker: boolean;
+	isHighLeverageMode: boolean;
+	perpLpTier: PerpLpTier;
+	delegatePermissions: number;
+	hasFillCallback: boolean;
+	delegateExpirySlot: number;
+	swapInInitialTokenAmount: BN;
+	swapOutInitialTokenAmount: BN;
+	thirdPartyDepositsDisabled: boolean;
+	marginWarning: boolean;
+	highLeverageModeMarketIndex: number;
+	marginWarningTs: number;
 };
 
 export type SpotPosition = {
@@ -819,6 +1109,10 @@ export type Order = {
 	marketType: MarketType;
 	slot: BN;
 	orderId: number;
+	linkedOrderId: number;
+	twapInterval: number;
+	twapSliceCount: number;
+	twapSlicesRemaining: number;
 	userOrderId: number;
 	marketIndex: number;
 	price: BN;
@@ -838,6 +1132,15 @@ export type Order = {
 	auctionStartPrice: BN;
 	auctionEndPrice: BN;
 	maxTs: BN;
+	trailAmount: BN;
+	trailType: OrderTrailType;
+	displayQuantity: BN;
+	triggerFundingRate: BN;
+	auctionCurve: AuctionCurve;
+	auctionStepCount: number;
+	auctionExtended: boolean;
+	builderFeeShare: number;
+	maxMarginSizing: boolean;
 };
 
 export type OrderParams = {
@@ -859,6 +1162,17 @@ export type OrderParams = {
 	maxTs: BN | null;
 	auctionStartPrice: BN | null;
 	auctionEndPrice: BN | null;
+	trailType: OrderTrailType;
+	trailAmount: BN | null;
+	twapInterval: number | null;
+	twapSliceCount: number | null;
+	displayQuantity: BN | null;
+	triggerFundingRate: BN | null;
+	auctionCurve: AuctionCurve;
+	auctionStepCount: number | null;
+	builderFeeShare: number | null;
+	maxMarginSizing: boolean;
+	replaceExistingUserOrderId: boolean;
 };
 
 export class PostOnlyParams {
@@ -867,6 +1181,45 @@ export class PostOnlyParams {
 	static readonly TRY_POST_ONLY = { tryPostOnly: {} }; // Tx succeeds and order not placed if can't be post only
 }
 
+export type SignedOrderMessage = {
+	orderParams: OrderParams;
+	subAccountId: number;
+	nonce: BN;
+	expiryTs: BN;
+};
+
+export type CancelOrdersFilter = {
+	minOrderId: number | null;
+	maxOrderId: number | null;
+	onlyTriggerOrders: boolean;
+};
+
+export type ModifyOrderParams = {
+	baseAssetAmount: BN | null;
+	price: BN | null;
+	postOnly: PostOnlyParams | null;
+};
+
+export class SizeDistribution {
+	static readonly FLAT = { flat: {} };
+	static readonly ASCENDING = { ascending: {} };
+	static readonly DESCENDING = { descending: {} };
+}
+
+export type ScaleOrderParams = {
+	marketType: MarketType;
+	direction: PositionDirection;
+	marketIndex: number;
+	totalBaseAssetAmount: BN;
+	startPrice: BN;
+	endPrice: BN;
+	orderCount: number;
+	sizeDistribution: SizeDistribution;
+	reduceOnly: boolean;
+	postOnly: PostOnlyParams;
+	maxTs: BN | null;
+};
+
 export type NecessaryOrderParams = {
 	orderType: OrderType;
 	marketIndex: number;
@@ -897,6 +1250,17 @@ export const DefaultOrderParams: OrderParams = {
 	maxTs: null,
 	auctionStartPrice: null,
 	auctionEndPrice: null,
+	trailType: OrderTrailType.ABSOLUTE,
+	trailAmount: null,
+	twapInterval: null,
+	twapSliceCount: null,
+	displayQuantity: null,
+	triggerFundingRate: null,
+	auctionCurve: AuctionCurve.LINEAR,
+	auctionStepCount: null,
+	builderFeeShare: null,
+	maxMarginSizing: false,
+	replaceExistingUserOrderId: false,
 };
 
 export type MakerInfo = {
@@ -906,6 +1270,34 @@ export type MakerInfo = {
 	order?: Order;
 };
 
+export type RfqTakerRequest = {
+	subAccountId: number;
+	marketIndex: number;
+	direction: PositionDirection;
+	baseAssetAmount: BN;
+	oraclePriceOffset: number;
+	reduceOnly: boolean;
+	nonce: BN;
+	expiryTs: BN;
+};
+
+export type RfqMakerQuote = {
+	maker: PublicKey;
+	taker: PublicKey;
+	marketIndex: number;
+	direction: PositionDirection;
+	baseAssetAmount: BN;
+	price: BN;
+	nonce: BN;
+	expiryTs: BN;
+};
+
+export type RfqQuoteInfo = {
+	quote: RfqMakerQuote;
+	signature: Buffer;
+	makerInfo: MakerInfo;
+};
+
 export type TakerInfo = {
 	taker: PublicKey;
 	takerStats: PublicKey;
@@ -916,6 +1308,20 @@ export type TakerInfo = {
 export type ReferrerInfo = {
 	referrer: PublicKey;
 	referrerStats: PublicKey;
+	// required when the referrer passes a share of its reward to its own referrer
+	secondLevelReferrer?: PublicKey;
+	secondLevelReferrerStats?: PublicKey;
+};
+
+export type BuilderInfo = {
+	builder: PublicKey;
+	builderStats: PublicKey;
+};
+
+export type FuelInfo = {
+	fuelBoost: PublicKey;
+	// existing user fuel accounts of the taker and makers
+	userFuels: PublicKey[];
 };
 
 export type TxParams = {
@@ -969,6 +1375,312 @@ export type OracleGuardRails = {
 	};
 };
 
+// zero values fall back to the global OracleGuardRails.validity
+export type ValidityGuardRailsOverride = {
+	slotsBeforeStaleForAmm: number;
+	slotsBeforeStaleForMargin: number;
+	confidenceIntervalMaxSize: number;
+	tooVolatileRatio: number;
+};
+
+export type MarginHealth = {
+	initialTotalCollateral: BN;
+	initialMarginRequirement: BN;
+	maintenanceTotalCollateral: BN;
+	maintenanceMarginRequirement: BN;
+	liquidationPrice: BN;
+};
+
+export type FillReceipt = {
+	orderId: number;
+	status: OrderStatus;
+	baseAssetAmountFilled: BN;
+	quoteAssetAmountFilled: BN;
+	fillPrice: BN;
+	feePaid: BN;
+};
+
+export type PredictedFunding = {
+	fundingRate: BN;
+	nextFundingRateTs: BN;
+};
+
+export type OrderBookLevel = {
+	price: BN;
+	baseAssetAmount: BN;
+};
+
+export type OrderBookSnapshot = {
+	slot: BN;
+	oraclePrice: BN;
+	ammBidPrice: BN;
+	ammAskPrice: BN;
+	bids: OrderBookLevel[];
+	asks: OrderBookLevel[];
+};
+
+export type HighLeverageModeConfig = {
+	maxUsers: number;
+	currentUsers: number;
+	marketIndex: number;
+};
+
+export type BackstopProvider = {
+	user: PublicKey;
+	committedCollateral: BN;
+	liquidationFeeBonus: number;
+	approved: boolean;
+};
+
+export type SessionKey = {
+	user: PublicKey;
+	sessionSigner: PublicKey;
+	expiryTs: BN;
+	maxNotional: BN;
+	notionalUsed: BN;
+	perpMarketIndexes: number[];
+	numPerpMarkets: number;
+};
+
+export type UserFuel = {
+	authority: PublicKey;
+	taker: BN;
+	maker: BN;
+	insurance: BN;
+	borrows: BN;
+	lastUpdateTs: BN;
+	lastInsuranceFuelRate: BN;
+	lastBorrowsFuelRate: BN;
+};
+
+export type FuelBoost = {
+	startTs: BN;
+	endTs: BN;
+	marketIndex: number;
+	marketType: MarketType;
+	taker: number;
+	maker: number;
+	borrows: number;
+	insurance: number;
+};
+
+export type MarketFeeOverride = {
+	promoStartTs: BN;
+	promoEndTs: BN;
+	takerFeeAdjustment: number;
+	makerRebateAdjustment: number;
+	promoTakerFeeAdjustment: number;
+	promoMakerRebateAdjustment: number;
+	marketIndex: number;
+	marketType: MarketType;
+};
+
+export type MarketLookupTableAccount = {
+	lookupTable: PublicKey;
+	tableIndex: number;
+};
+
+export type MarketWhitelistEntry = {
+	authority: PublicKey;
+	marketIndex: number;
+};
+
+export type PerpMarketDelisting = {
+	settlementPriceCumulative: BN;
+	lastOraclePrice: BN;
+	firstSampleTs: BN;
+	lastSampleTs: BN;
+	settlementTwapWindow: number;
+	marketIndex: number;
+};
+
+export type PerpMarketListingConfig = {
+	bondAmount: BN;
+	vetoWindow: number;
+	enabled: boolean;
+};
+
+export type PerpMarketListing = {
+	proposer: PublicKey;
+	bond: BN;
+	vetoEndTs: BN;
+	marketIndex: number;
+};
+
+export type PnlPoolRebalanceConfig = {
+	surplusBuffer: BN;
+	maxTopUp: BN;
+	lastRebalanceTs: BN;
+	minRebalanceInterval: number;
+	enabled: boolean;
+};
+
+export type FillCallbackProgram = {
+	programId: PublicKey;
+};
+
+export type UserFillCallback = {
+	user: PublicKey;
+	programId: PublicKey;
+	bump: number;
+};
+
+export type Vault = {
+	name: number[];
+	manager: PublicKey;
+	user: PublicKey;
+	totalShares: BN;
+	managerShares: BN;
+	profitShare: number;
+	bump: number;
+};
+
+export type VaultDepositor = {
+	vault: PublicKey;
+	authority: PublicKey;
+	shares: BN;
+	highWaterMark: BN;
+	cumulativeProfitSharePaid: BN;
+};
+
+export type AdminChange =
+	| {
+			perpMarketMarginRatio: {
+				marketIndex: number;
+				marginRatioInitial: number;
+				marginRatioMaintenance: number;
+			};
+	  }
+	| {
+			spotMarketMarginWeights: {
+				marketIndex: number;
+				initialAssetWeight: number;
+				maintenanceAssetWeight: number;
+				initialLiabilityWeight: number;
+				maintenanceLiabilityWeight: number;
+				imfFactor: number;
+			};
+	  }
+	| {
+			perpMarketOracle: {
+				marketIndex: number;
+				oracle: PublicKey;
+				oracleSource: OracleSource;
+			};
+	  }
+	| {
+			spotMarketOracle: {
+				marketIndex: number;
+				oracle: PublicKey;
+				oracleSource: OracleSource;
+			};
+	  }
+	| { perpFeeStructure: { feeStructure: FeeStructure } }
+	| { spotFeeStructure: { feeStructure: FeeStructure } }
+	| { adminTimelockDuration: { duration: number } }
+	| { oracleGuardRails: { oracleGuardRails: OracleGuardRails } }
+	| {
+			perpMarketImfFactor: {
+				marketIndex: number;
+				imfFactor: number;
+				unrealizedPnlImfFactor: number;
+			};
+	  }
+	| {
+			perpMarketLiquidationFee: {
+				marketIndex: number;
+				liquidatorFee: number;
+				ifLiquidationFee: number;
+			};
+	  }
+	| {
+			spotMarketLiquidationFee: {
+				marketIndex: number;
+				liquidatorFee: number;
+				ifLiquidationFee: number;
+			};
+	  }
+	| { spotMarketAssetTier: { marketIndex: number; assetTier: AssetTier } }
+	| {
+			perpMarketContractTier: {
+				marketIndex: number;
+				contractTier: ContractTier;
+			};
+	  }
+	| {
+			spotMarketBorrowRate: {
+				marketIndex: number;
+				optimalUtilization: number;
+				optimalBorrowRate: number;
+				maxBorrowRate: number;
+			};
+	  }
+	| {
+			spotMarketBorrowRateKinks: {
+				marketIndex: number;
+				borrowRateKinks: BorrowRateKink[];
+			};
+	  }
+	| {
+			perpMarketFundingRateCaps: {
+				marketIndex: number;
+				maxFundingRateSpread: number;
+				escalatedMaxFundingRateSpread: number;
+				fundingDivergenceEscalationPeriods: number;
+			};
+	  }
+	| {
+			perpMarketMaxOpenInterest: {
+				marketIndex: number;
+				maxOpenInterest: BN;
+			};
+	  };
+
+export type AdminRoles = {
+	pauser: PublicKey;
+	riskParamSetter: PublicKey;
+	marketLister: PublicKey;
+	treasurer: PublicKey;
+};
+
+export type PendingAdminChange = {
+	change: AdminChange;
+	queuedTs: BN;
+	executableTs: BN;
+};
+
+export type GovernanceStake = {
+	authority: PublicKey;
+	stakedAmount: BN;
+	lastStakeTs: BN;
+};
+
+export type Builder = {
+	authority: PublicKey;
+	name: number[];
+};
+
+export type DeadManSwitch = {
+	user: PublicKey;
+	keeper: PublicKey;
+	withdrawDestination: PublicKey;
+	lastPingTs: BN;
+	timeoutDays: number;
+};
+
+export type PerpLpRange = {
+	user: PublicKey;
+	lowerPrice: BN;
+	upperPrice: BN;
+	inactiveShares: BN;
+	marketIndex: number;
+};
+
+export type MarginTier = {
+	notionalThreshold: number;
+	marginRatioInitial: number;
+};
+
 export type MarginCategory = 'Initial' | 'Maintenance';
 
 export type InsuranceFundStake = {
@@ -981,6 +1693,9 @@ export type InsuranceFundStake = {
 	lastWithdrawRequestShares: BN;
 	lastWithdrawRequestValue: BN;
 	lastWithdrawRequestTs: BN;
+
+	lockupTier: InsuranceFundStakeLockupTier;
+	lastCumulativeIfBoostSharesPerWeight: BN;
 };
 
 export type SerumV3FulfillmentConfigAccount = {
@@ -1000,6 +1715,33 @@ export type SerumV3FulfillmentConfigAccount = {
 	serumSignerNonce: BN;
 };
 
+export type OpenbookV2FulfillmentConfigAccount = {
+	fulfillmentType: SpotFulfillmentType;
+	status: SpotFulfillmentStatus;
+	pubkey: PublicKey;
+	marketIndex: number;
+	openbookV2ProgramId: PublicKey;
+	openbookV2Market: PublicKey;
+	openbookV2MarketAuthority: PublicKey;
+	openbookV2EventHeap: PublicKey;
+	openbookV2Bids: PublicKey;
+	openbookV2Asks: PublicKey;
+	openbookV2BaseVault: PublicKey;
+	openbookV2QuoteVault: PublicKey;
+};
+
+export type PhoenixV1FulfillmentConfigAccount = {
+	fulfillmentType: SpotFulfillmentType;
+	status: SpotFulfillmentStatus;
+	pubkey: PublicKey;
+	marketIndex: number;
+	phoenixProgramId: PublicKey;
+	phoenixLogAuthority: PublicKey;
+	phoenixMarket: PublicKey;
+	phoenixBaseVault: PublicKey;
+	phoenixQuoteVault: PublicKey;
+};
+
 export type ReferrerNameAccount = {
 	name: number[];
 	user: PublicKey;
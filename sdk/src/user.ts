@@ -19,6 +19,7 @@ import {
 	ZERO,
 	TEN_THOUSAND,
 	BN_MAX,
+	PERCENTAGE_PRECISION,
 	QUOTE_PRECISION,
 	AMM_RESERVE_PRECISION,
 	MARGIN_PRECISION,
@@ -41,6 +42,7 @@ import {
 	calculatePositionPNL,
 	calculateUnrealizedAssetWeight,
 	calculateMarketMarginRatio,
+	calculateMarginTierRatio,
 	PositionDirection,
 	BN,
 	SpotMarketAccount,
@@ -131,6 +133,17 @@ export class User {
 		return this.accountSubscriber.getUserAccountAndSlot().data;
 	}
 
+	/**
+	 * Whether the user is in the high leverage mode registry of the perp market
+	 */
+	public isHighLeverageModeFor(marketIndex: number): boolean {
+		const userAccount = this.getUserAccount();
+		return (
+			userAccount.isHighLeverageMode &&
+			userAccount.highLeverageModeMarketIndex === marketIndex
+		);
+	}
+
 	public async forceGetUserAccount(): Promise<UserAccount> {
 		await this.fetchAccounts();
 		return this.accountSubscriber.getUserAccountAndSlot().data;
@@ -178,6 +191,9 @@ export class User {
 			lpShares: ZERO,
 			lastBaseAssetAmountPerLp: ZERO,
 			lastQuoteAssetAmountPerLp: ZERO,
+			isolatedCollateral: ZERO,
+			liquidationPrice: ZERO,
+			isIsolated: false,
 		};
 	}
 
@@ -396,7 +412,8 @@ export class User {
 		const marginRatio = calculateMarketMarginRatio(
 			this.driftClient.getPerpMarketAccount(marketIndex),
 			baseAssetAmount,
-			'Initial'
+			'Initial',
+			this.isHighLeverageModeFor(marketIndex)
 		);
 
 		return freeCollateral.mul(MARGIN_PRECISION).div(new BN(marginRatio));
@@ -421,7 +438,7 @@ export class User {
 		liquidationBuffer?: BN,
 		strict = false
 	): BN {
-		return this.getTotalPerpPositionValue(
+		const marginRequirement = this.getTotalPerpPositionValue(
 			marginCategory,
 			liquidationBuffer,
 			true
@@ -434,6 +451,117 @@ export class User {
 				strict
 			)
 		);
+
+		return BN.max(
+			marginRequirement.sub(this.getCorrelationMarginCredit()),
+			ZERO
+		);
+	}
+
+	/**
+	 * calculates the margin credit for offsetting exposure between markets in the same correlation group
+	 * @returns : Precision QUOTE_PRECISION
+	 */
+	public getCorrelationMarginCredit(): BN {
+		const exposures = new Map<
+			number,
+			{ longNotional: BN; shortNotional: BN; marginCredit: number }
+		>();
+
+		const addExposure = (
+			correlationGroup: number,
+			correlationMarginCredit: number,
+			signedNotional: BN
+		) => {
+			if (
+				correlationGroup === 0 ||
+				correlationMarginCredit === 0 ||
+				signedNotional.eq(ZERO)
+			) {
+				return;
+			}
+
+			const exposure = exposures.get(correlationGroup) ?? {
+				longNotional: ZERO,
+				shortNotional: ZERO,
+				marginCredit: correlationMarginCredit,
+			};
+			exposure.marginCredit = Math.min(
+				exposure.marginCredit,
+				correlationMarginCredit
+			);
+			if (signedNotional.gt(ZERO)) {
+				exposure.longNotional = exposure.longNotional.add(signedNotional);
+			} else {
+				exposure.shortNotional = exposure.shortNotional.add(
+					signedNotional.abs()
+				);
+			}
+			exposures.set(correlationGroup, exposure);
+		};
+
+		for (const spotPosition of this.getUserAccount().spotPositions) {
+			if (
+				isSpotPositionAvailable(spotPosition) ||
+				spotPosition.marketIndex === QUOTE_SPOT_MARKET_INDEX
+			) {
+				continue;
+			}
+
+			const spotMarketAccount = this.driftClient.getSpotMarketAccount(
+				spotPosition.marketIndex
+			);
+			const signedTokenAmount = getSignedTokenAmount(
+				getTokenAmount(
+					spotPosition.scaledBalance,
+					spotMarketAccount,
+					spotPosition.balanceType
+				),
+				spotPosition.balanceType
+			);
+			addExposure(
+				spotMarketAccount.correlationGroup,
+				spotMarketAccount.correlationMarginCredit,
+				getTokenValue(
+					signedTokenAmount,
+					spotMarketAccount.decimals,
+					this.getOracleDataForSpotMarket(spotPosition.marketIndex)
+				)
+			);
+		}
+
+		for (const perpPosition of this.getActivePerpPositions()) {
+			if (perpPosition.isIsolated) {
+				continue;
+			}
+
+			const market = this.driftClient.getPerpMarketAccount(
+				perpPosition.marketIndex
+			);
+			const baseAssetValue = calculateBaseAssetValueWithOracle(
+				market,
+				perpPosition,
+				this.getOracleDataForPerpMarket(perpPosition.marketIndex)
+			);
+			addExposure(
+				market.correlationGroup,
+				market.correlationMarginCredit,
+				perpPosition.baseAssetAmount.isNeg()
+					? baseAssetValue.neg()
+					: baseAssetValue
+			);
+		}
+
+		let marginCredit = ZERO;
+		for (const exposure of exposures.values()) {
+			marginCredit = marginCredit.add(
+				BN.min(exposure.longNotional, exposure.shortNotional)
+					.mul(new BN(exposure.marginCredit))
+					.div(MARGIN_PRECISION)
+			);
+		}
+
+		return marginCredit;
 	}
 
 	/**
@@ -887,9 +1015,55 @@ export class User {
 	 * @returns : Precision QUOTE_PRECISION
 	 */
 	public getTotalCollateral(marginCategory: MarginCategory = 'Initial'): BN {
-		return this.getSpotMarketAssetValue(undefined, marginCategory, true).add(
-			this.getUnrealizedPNL(true, undefined, marginCategory)
+		return this.getSpotMarketAssetValue(undefined, marginCategory, true)
+			.sub(this.getCollateralConcentrationHaircut(marginCategory))
+			.add(this.getUnrealizedPNL(true, undefined, marginCategory));
+	}
+
+	/**
+	 * calculates the spot collateral excluded because a single asset exceeds its market's max collateral concentration
+	 * @returns : Precision QUOTE_PRECISION
+	 */
+	public getCollateralConcentrationHaircut(
+		marginCategory: MarginCategory = 'Initial'
+	): BN {
+		const totalSpotCollateral = this.getSpotMarketAssetValue(
+			undefined,
+			marginCategory,
+			true
 		);
+
+		let haircut = ZERO;
+		for (const spotPosition of this.getUserAccount().spotPositions) {
+			if (
+				isSpotPositionAvailable(spotPosition) ||
+				spotPosition.marketIndex === QUOTE_SPOT_MARKET_INDEX
+			) {
+				continue;
+			}
+
+			const spotMarketAccount = this.driftClient.getSpotMarketAccount(
+				spotPosition.marketIndex
+			);
+			if (spotMarketAccount.maxCollateralConcentration === 0) {
+				continue;
+			}
+
+			const assetValue = this.getSpotMarketAssetValue(
+				spotPosition.marketIndex,
+				marginCategory,
+				true
+			);
+			const maxAssetValue = totalSpotCollateral
+				.mul(new BN(spotMarketAccount.maxCollateralConcentration))
+				.div(SPOT_MARKET_WEIGHT_PRECISION);
+
+			if (assetValue.gt(maxAssetValue)) {
+				haircut = haircut.add(assetValue.sub(maxAssetValue));
+			}
+		}
+
+		return haircut;
 	}
 
 	/**
@@ -992,7 +1166,8 @@ export class User {
 						calculateMarketMarginRatio(
 							market,
 							baseAssetAmount.abs(),
-							marginCategory
+							marginCategory,
+							this.isHighLeverageModeFor(market.marketIndex)
 						)
 					);
 
@@ -1001,6 +1176,12 @@ export class User {
 							marginRatio,
 							new BN(this.getUserAccount().maxMarginRatio)
 						);
+						marginRatio = BN.max(
+							marginRatio,
+							new BN(
+								calculateMarginTierRatio(market.marginTiers, baseAssetValue)
+							)
+						);
 					}
 
 					if (liquidationBuffer !== undefined) {
@@ -1124,6 +1305,43 @@ export class User {
 		return [exitPrice, pnl];
 	}
 
+	/**
+	 * auto-deleverage rank of a perp position, pnl pct of entry times leverage. opposing positions are deleveraged highest score first
+	 * @returns : Precision PERCENTAGE_PRECISION
+	 */
+	public getAutoDeleverageScore(marketIndex: number): BN {
+		const perpPosition = this.getPerpPosition(marketIndex);
+		if (!perpPosition || perpPosition.baseAssetAmount.eq(ZERO)) {
+			return ZERO;
+		}
+
+		const market = this.driftClient.getPerpMarketAccount(marketIndex);
+		const oraclePriceData = this.getOracleDataForPerpMarket(marketIndex);
+		const unrealizedPnl = calculatePositionPNL(
+			market,
+			perpPosition,
+			true,
+			oraclePriceData
+		);
+		if (unrealizedPnl.lte(ZERO)) {
+			return ZERO;
+		}
+
+		const totalCollateral = this.getTotalCollateral('Maintenance');
+		if (perpPosition.quoteEntryAmount.eq(ZERO) || totalCollateral.lte(ZERO)) {
+			return BN_MAX;
+		}
+
+		const pnlPct = unrealizedPnl
+			.mul(PERCENTAGE_PRECISION)
+			.div(perpPosition.quoteEntryAmount.abs());
+		const leverage = this.getPerpPositionValue(marketIndex, oraclePriceData)
+			.mul(PERCENTAGE_PRECISION)
+			.div(totalCollateral);
+
+		return pnlPct.mul(leverage).div(PERCENTAGE_PRECISION);
+	}
+
 	/**
 	 * calculates current user leverage which is (total liability size) / (net asset value)
 	 * @returns : Precision TEN_THOUSAND
@@ -1219,7 +1437,8 @@ export class User {
 		const marginRatio = calculateMarketMarginRatio(
 			market,
 			ZERO, // todo
-			category
+			category,
+			this.isHighLeverageModeFor(market.marketIndex)
 		);
 		const freeCollateral = this.getFreeCollateral();
 
@@ -1591,7 +1810,8 @@ export class User {
 		const marginRatio = calculateMarketMarginRatio(
 			market,
 			proposedWorstCaseBaseAssetAmount.abs(),
-			'Maintenance'
+			'Maintenance',
+			this.isHighLeverageModeFor(market.marketIndex)
 		);
 		const marginRatioQuotePrecision = new BN(marginRatio)
 			.mul(QUOTE_PRECISION)
@@ -186,7 +186,7 @@ export class UserMap implements UserMapInterface {
 			filters = [
 				{
 					memcmp: {
-						offset: 4350,
+						offset: 10110,
 						bytes: bs58.encode(Uint8Array.from([0])),
 					},
 				},